@@ -85,7 +85,7 @@ echo "  ✓ Built $NATIVE_COUNT native RISC-V binaries"
 echo -e "${YELLOW}[4/5] Creating filesystem image...${NC}"
 cargo run -p mkfs --release -- \
     --output "$OUTPUT_DIR/fs.img" \
-    --dir mkfs/root \
+    --manifest mkfs/image.toml \
     --size 20
 echo "  ✓ Created: fs.img (20MB)"
 
@@ -82,10 +82,12 @@ SECTIONS
     /* Place critical runtime functions close to .init to stay within JAL range */
     /* These are functions referenced by riscv-rt startup code */
     *libriscv_rt*.rlib:*(.text .text.*);
-    *libpanic_halt*.rlib:*(.text .text.*);
     
     /* Now place all other text sections */
     *(.text .text.*);
+
+    /* End of code, used by the panic handler's stack scan */
+    _etext = .;
   } > REGION_TEXT
 
   .rodata : ALIGN(4)
@@ -1,8 +1,19 @@
-use linked_list_allocator::LockedHeap;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::alloc::{GlobalAlloc, Layout};
+use core::fmt::Write;
+use core::ptr::{self, NonNull};
+use core::sync::atomic::{AtomicBool, AtomicU32, AtomicU8, AtomicUsize, Ordering};
+
+use linked_list_allocator::{Heap, LockedHeap};
+
+use crate::cpu::cgroup;
 
 unsafe extern "C" {
     // Linker symbols for section boundaries
     static _stext: u8;      // Start of .text section (kernel code)
+    static _etext: u8;      // End of .text section
     static mut _sheap: u8;  // Start of heap (end of static sections)
     static mut _eheap: u8;  // End of heap
 }
@@ -16,33 +27,151 @@ const RAM_BASE: usize = 0x8000_0000;
 /// Total RAM size (must match link.x: LENGTH = 512M)
 const RAM_SIZE: usize = 512 * 1024 * 1024;
 
+/// Memory set aside at the top of the heap region for device DMA
+/// (`device::dma`)
+const DMA_POOL_SIZE: usize = 256 * 1024;
+
+/// Kernel heap that also records its high-water mark
+struct TrackingHeap {
+    heap: LockedHeap,
+    /// Highest `used()` seen since the last `reset_heap_peak`
+    peak: AtomicUsize,
+}
+
+impl TrackingHeap {
+    /// Run `f` on the locked heap. The heap lock is not one of ours, so
+    /// preemption is held off by hand: a process switched out while holding
+    /// it would stall every allocation on its hart.
+    fn with_heap<R>(&self, f: impl FnOnce(&mut Heap) -> R) -> R {
+        crate::lock::preempt_disable();
+        let result = f(&mut self.heap.lock());
+        crate::lock::preempt_enable();
+        result
+    }
+}
+
+unsafe impl GlobalAlloc for TrackingHeap {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let (outer, prefix) = if HEAP_DEBUG {
+            match guarded_layout(layout) {
+                Some(guarded) => guarded,
+                None => return ptr::null_mut(),
+            }
+        } else {
+            match owned_layout(layout) {
+                Some(owned) => (owned, 0),
+                None => return ptr::null_mut(),
+            }
+        };
+        let pid = current_tag();
+        let subsystem = current_subsystem();
+        let owner = owner_word(pid, subsystem);
+        let account = claim_account(pid);
+        if let Some(account) = account {
+            if !account.charge(layout.size()) {
+                return ptr::null_mut();
+            }
+        }
+        let allocate = || {
+            self.with_heap(|heap| {
+                let block = heap
+                    .allocate_first_fit(outer)
+                    .map_or(ptr::null_mut(), |allocation| allocation.as_ptr());
+                self.peak.fetch_max(heap.used(), Ordering::Relaxed);
+                block
+            })
+        };
+        let mut block = allocate();
+        if block.is_null() {
+            // Out of heap: free what can be freed before failing
+            block = crate::oom::recover(outer.size(), &allocate);
+        }
+        if block.is_null() {
+            if let Some(account) = account {
+                account.credit(layout.size());
+            }
+            return block;
+        }
+        let ptr = block.add(prefix);
+        SUBSYSTEMS[subsystem as usize].charge(layout.size());
+        if HEAP_DEBUG {
+            guard_block(ptr, layout.size(), owner);
+        } else {
+            *owner_slot(ptr, layout.size()) = owner;
+        }
+        if LEAK_TRACKING.load(Ordering::Relaxed) {
+            track_alloc(ptr as usize, layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        // Forget the record before the block can be handed out again
+        if LEAK_TRACKING.load(Ordering::Relaxed) {
+            track_dealloc(ptr as usize, layout.size());
+        }
+        if HEAP_DEBUG {
+            // Layouts that could not be guarded were never handed out
+            let Some((outer, prefix)) = guarded_layout(layout) else {
+                return;
+            };
+            let owner = check_and_poison(ptr, layout.size());
+            credit_owner(owner, layout.size());
+            self.with_heap(|heap| heap.deallocate(NonNull::new_unchecked(ptr.sub(prefix)), outer));
+        } else {
+            let Some(outer) = owned_layout(layout) else {
+                return;
+            };
+            credit_owner(*owner_slot(ptr, layout.size()), layout.size());
+            self.with_heap(|heap| heap.deallocate(NonNull::new_unchecked(ptr), outer));
+        }
+    }
+}
+
 #[global_allocator]
-static ALLOCATOR: LockedHeap = LockedHeap::empty();
+static ALLOCATOR: TrackingHeap = TrackingHeap {
+    heap: LockedHeap::empty(),
+    peak: AtomicUsize::new(0),
+};
 
 /// Initialize the heap allocator.
 /// Must be called before any heap allocations occur.
 pub fn init() {
     unsafe {
         let heap_start = &raw mut _sheap as *mut u8;
-        let heap_end = &raw const _eheap as usize;
-        let heap_size = heap_end - (heap_start as usize);
-        ALLOCATOR.lock().init(heap_start, heap_size);
+        ALLOCATOR.heap.lock().init(heap_start, heap_size());
     }
 }
 
+/// The region reserved for DMA buffers: (start, length). It is page-aligned,
+/// physically contiguous and never handed out by the heap.
+pub fn dma_region() -> (usize, usize) {
+    let heap_end = &raw const _eheap as usize;
+    let start = (heap_end - DMA_POOL_SIZE) & !(4096 - 1);
+    (start, heap_end - start)
+}
+
 /// Returns (used, free) bytes in the heap, if the allocator supports introspection.
 pub fn heap_stats() -> (usize, usize) {
-    let allocator = ALLOCATOR.lock();
-    let used = allocator.used();
-    let free = allocator.free();
-    (used, free)
+    ALLOCATOR.with_heap(|heap| (heap.used(), heap.free()))
 }
 
-/// Returns the total heap size.
+/// Restart peak tracking from the current usage. Returns the bytes in use.
+pub fn reset_heap_peak() -> usize {
+    let used = ALLOCATOR.with_heap(|heap| heap.used());
+    ALLOCATOR.peak.store(used, Ordering::Relaxed);
+    used
+}
+
+/// Highest heap usage (bytes) since the last `reset_heap_peak`
+pub fn heap_peak() -> usize {
+    ALLOCATOR.peak.load(Ordering::Relaxed)
+}
+
+/// Returns the total heap size (the DMA region excluded).
 pub fn heap_size() -> usize {
     let heap_start = &raw const _sheap as usize;
-    let heap_end = &raw const _eheap as usize;
-    heap_end - heap_start
+    dma_region().0 - heap_start
 }
 
 /// Comprehensive memory statistics
@@ -106,3 +235,739 @@ pub fn memory_stats(active_harts: usize, gpu_enabled: bool) -> MemoryStats {
     }
 }
 
+// ═══════════════════════════════════════════════════════════════════════════════
+// Heap Guards (debug builds and the `heap-debug` feature)
+// ═══════════════════════════════════════════════════════════════════════════════
+//
+// Each block gets a header just before it (size, owning tag, live/freed
+// state, and a canary derived from its address) and GUARD_SUFFIX guard bytes
+// after it. Frees check all of them and panic naming the tag that allocated
+// the block, so an overrun or double free is caught at the free instead of
+// surfacing later as a corrupted free list and a hang. Freed memory is
+// filled with POISON_BYTE so use-after-free reads stand out.
+//
+// The first GUARD_RESERVED bytes of a block are left alone: the allocator
+// keeps its free-list node there once the block is freed, and the header must
+// survive that to recognise a second free.
+
+/// Whether allocations are guarded
+const HEAP_DEBUG: bool = cfg!(any(debug_assertions, feature = "heap-debug"));
+
+/// Start of a block the allocator overwrites when it is freed
+const GUARD_RESERVED: usize = 16;
+/// Bytes before each allocation: reserved space plus the header
+const GUARD_PREFIX: usize = GUARD_RESERVED + core::mem::size_of::<GuardHeader>();
+/// Guard bytes after each allocation
+const GUARD_SUFFIX: usize = 16;
+const GUARD_BYTE: u8 = 0xFD;
+const POISON_BYTE: u8 = 0xDD;
+
+const STATE_LIVE: u32 = 0xA110_CA7E;
+const STATE_FREED: u32 = 0xF7EE_D00D;
+const CANARY: u64 = 0x5AFE_C0DE_CA7A_11E5;
+
+#[repr(C)]
+struct GuardHeader {
+    size: usize,
+    /// Process and subsystem that allocated the block (`owner_word`)
+    owner: u32,
+    state: u32,
+    /// CANARY ^ block address
+    canary: u64,
+}
+
+/// Tag as shown in heap panics (no allocation: the heap may be corrupt)
+struct Tag(u32);
+
+impl core::fmt::Display for Tag {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self.0 {
+            0 => write!(f, "kernel"),
+            pid => write!(f, "pid {}", pid),
+        }
+    }
+}
+
+/// Layout of the underlying block for `layout`, and the offset of the
+/// allocation within it
+fn guarded_layout(layout: Layout) -> Option<(Layout, usize)> {
+    let align = layout.align().max(core::mem::align_of::<GuardHeader>());
+    let prefix = GUARD_PREFIX.next_multiple_of(align);
+    let size = prefix.checked_add(layout.size())?.checked_add(GUARD_SUFFIX)?;
+    Some((Layout::from_size_align(size, align).ok()?, prefix))
+}
+
+/// Write the header and trailing guard of a new allocation
+unsafe fn guard_block(ptr: *mut u8, size: usize, owner: u32) {
+    let header = &mut *(ptr.sub(core::mem::size_of::<GuardHeader>()) as *mut GuardHeader);
+    *header = GuardHeader { size, owner, state: STATE_LIVE, canary: CANARY ^ ptr as u64 };
+    ptr::write_bytes(ptr.add(size), GUARD_BYTE, GUARD_SUFFIX);
+}
+
+/// Verify a block being freed, then mark it freed and poison it. Returns
+/// its owner word.
+unsafe fn check_and_poison(ptr: *mut u8, size: usize) -> u32 {
+    let header = &mut *(ptr.sub(core::mem::size_of::<GuardHeader>()) as *mut GuardHeader);
+    let owner = Tag(owner_pid(header.owner));
+    if header.state == STATE_FREED && header.canary == CANARY ^ ptr as u64 {
+        panic!(
+            "heap: double free of {:p} ({} bytes, allocated by {}, freed again by {})",
+            ptr, header.size, owner, Tag(current_tag())
+        );
+    }
+    if header.state != STATE_LIVE || header.canary != CANARY ^ ptr as u64 {
+        panic!(
+            "heap: bad free of {:p} by {}: header corrupted (underrun) or not a heap block",
+            ptr,
+            Tag(current_tag())
+        );
+    }
+    if header.size != size {
+        panic!(
+            "heap: {:p} freed by {} as {} bytes, but {} allocated {}",
+            ptr,
+            Tag(current_tag()),
+            size,
+            owner,
+            header.size
+        );
+    }
+    let suffix = core::slice::from_raw_parts(ptr.add(size), GUARD_SUFFIX);
+    if let Some(offset) = suffix.iter().position(|&b| b != GUARD_BYTE) {
+        panic!(
+            "heap: overrun past the end of {:p} ({} bytes, allocated by {}): guard byte {} overwritten",
+            ptr, size, owner, offset
+        );
+    }
+    ptr::write_bytes(ptr, POISON_BYTE, size);
+    header.state = STATE_FREED;
+    header.owner
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Per-Process Accounting
+// ═══════════════════════════════════════════════════════════════════════════════
+//
+// Every block records the process that allocated it (the one running on the
+// hart, 0 for the kernel) and the subsystem it was allocated for (see
+// "Subsystem Accounting" below) in one owner word: in the guard header in
+// debug builds, otherwise in a word after the block. Its size is charged to that process until the
+// block is freed, whoever frees it. A process can be given a cap; an
+// allocation that would take it past the cap fails (returns null) instead of
+// eating into the heap everyone shares. A process in a control group is
+// charged to the group as well, against the group's cap (`cpu::cgroup`).
+//
+// The counters live in a fixed table updated with atomics only, so the
+// allocator never allocates or locks to keep them. Processes that find the
+// table full go untracked.
+
+/// Processes whose heap use can be tracked at once
+const MAX_ACCOUNTS: usize = 128;
+
+/// Heap use of one process
+struct Account {
+    /// Owning PID (0 = free slot)
+    pid: AtomicU32,
+    /// Bytes currently allocated
+    bytes: AtomicUsize,
+    /// Blocks currently allocated
+    blocks: AtomicUsize,
+    /// Cap on `bytes` (0 = none)
+    limit: AtomicUsize,
+    /// Control group also charged (cgroup::ROOT = none)
+    group: AtomicUsize,
+}
+
+impl Account {
+    const fn new() -> Self {
+        Self {
+            pid: AtomicU32::new(0),
+            bytes: AtomicUsize::new(0),
+            blocks: AtomicUsize::new(0),
+            limit: AtomicUsize::new(0),
+            group: AtomicUsize::new(cgroup::ROOT),
+        }
+    }
+
+    /// Charge an allocation of `size` bytes; false if it would go past the
+    /// process's cap or its group's
+    fn charge(&self, size: usize) -> bool {
+        let group = self.group.load(Ordering::Relaxed);
+        if !cgroup::charge_memory(group, size) {
+            return false;
+        }
+        let limit = self.limit.load(Ordering::Relaxed);
+        let charged = self.bytes.fetch_update(Ordering::AcqRel, Ordering::Acquire, |bytes| {
+            let total = bytes.checked_add(size)?;
+            (limit == 0 || total <= limit).then_some(total)
+        });
+        if charged.is_ok() {
+            self.blocks.fetch_add(1, Ordering::Relaxed);
+        } else {
+            cgroup::credit_memory(group, size);
+        }
+        charged.is_ok()
+    }
+
+    /// Give back a freed block of `size` bytes
+    fn credit(&self, size: usize) {
+        cgroup::credit_memory(self.group.load(Ordering::Relaxed), size);
+        let _ = self.bytes.fetch_update(Ordering::AcqRel, Ordering::Acquire, |bytes| Some(bytes.saturating_sub(size)));
+        let _ = self.blocks.fetch_update(Ordering::AcqRel, Ordering::Acquire, |blocks| Some(blocks.saturating_sub(1)));
+    }
+}
+
+static ACCOUNTS: [Account; MAX_ACCOUNTS] = [const { Account::new() }; MAX_ACCOUNTS];
+
+/// Layout of a block with room for its owner after the data
+fn owned_layout(layout: Layout) -> Option<Layout> {
+    let size = layout.size().next_multiple_of(4).checked_add(4)?;
+    Layout::from_size_align(size, layout.align().max(4)).ok()
+}
+
+/// Owner word of a block: the PID in the low 24 bits, the subsystem in the
+/// top 8
+fn owner_word(pid: u32, subsystem: Subsystem) -> u32 {
+    (pid & 0x00FF_FFFF) | (subsystem as u32) << 24
+}
+
+fn owner_pid(owner: u32) -> u32 {
+    owner & 0x00FF_FFFF
+}
+
+/// Where a block of `size` bytes keeps its owner (release builds)
+fn owner_slot(ptr: *mut u8, size: usize) -> *mut u32 {
+    ptr.wrapping_add(size.next_multiple_of(4)) as *mut u32
+}
+
+/// The account of `pid`, if it has one
+fn find_account(pid: u32) -> Option<&'static Account> {
+    if pid == 0 {
+        return None;
+    }
+    ACCOUNTS.iter().find(|account| account.pid.load(Ordering::Acquire) == pid)
+}
+
+/// The account of `pid`, taking a free slot for it if needed (none for the
+/// kernel or when the table is full)
+fn claim_account(pid: u32) -> Option<&'static Account> {
+    if pid == 0 {
+        return None;
+    }
+    if let Some(account) = find_account(pid) {
+        return Some(account);
+    }
+    ACCOUNTS.iter().find(|account| {
+        match account.pid.compare_exchange(0, pid, Ordering::AcqRel, Ordering::Acquire) {
+            Ok(_) => true,
+            // Claimed by someone else for the same process meanwhile
+            Err(owner) => owner == pid,
+        }
+    })
+}
+
+fn credit_owner(owner: u32, size: usize) {
+    if let Some(usage) = SUBSYSTEMS.get((owner >> 24) as usize) {
+        usage.credit(size);
+    }
+    if let Some(account) = find_account(owner_pid(owner)) {
+        account.credit(size);
+    }
+}
+
+/// Heap bytes and blocks a process has allocated and not freed
+pub fn process_heap(pid: u32) -> (usize, usize) {
+    find_account(pid).map_or((0, 0), |account| {
+        (account.bytes.load(Ordering::Relaxed), account.blocks.load(Ordering::Relaxed))
+    })
+}
+
+/// Processes holding heap: (pid, bytes)
+pub fn heap_holders() -> impl Iterator<Item = (u32, usize)> {
+    ACCOUNTS.iter().filter_map(|account| {
+        let pid = account.pid.load(Ordering::Acquire);
+        let bytes = account.bytes.load(Ordering::Relaxed);
+        (pid != 0 && bytes != 0).then_some((pid, bytes))
+    })
+}
+
+/// Cap a process's heap use at `limit` bytes (None = no cap). It applies to
+/// new allocations only. Fails if the accounting table is full.
+pub fn set_heap_limit(pid: u32, limit: Option<usize>) -> Result<(), &'static str> {
+    let account = claim_account(pid).ok_or("Too many processes tracked")?;
+    let limit = match limit {
+        Some(bytes) => bytes.max(1),
+        None => 0,
+    };
+    account.limit.store(limit, Ordering::Relaxed);
+    Ok(())
+}
+
+/// A process's heap cap, if it has one
+pub fn heap_limit(pid: u32) -> Option<usize> {
+    find_account(pid).map(|account| account.limit.load(Ordering::Relaxed)).filter(|&limit| limit != 0)
+}
+
+/// Charge a process's heap to control group `group` from now on; what it
+/// already holds moves there from its previous group. Fails if the
+/// accounting table is full.
+pub fn set_heap_group(pid: u32, group: cgroup::GroupId) -> Result<(), &'static str> {
+    let account = claim_account(pid).ok_or("Too many processes tracked")?;
+    let old = account.group.swap(group, Ordering::AcqRel);
+    if old != group {
+        cgroup::move_memory(old, group, account.bytes.load(Ordering::Relaxed));
+    }
+    Ok(())
+}
+
+/// Free the accounting slot of a process that has gone; blocks it leaves
+/// behind are no longer counted anywhere
+pub fn forget_process(pid: u32) {
+    if let Some(account) = find_account(pid) {
+        let group = account.group.swap(cgroup::ROOT, Ordering::AcqRel);
+        cgroup::credit_memory(group, account.bytes.load(Ordering::Relaxed));
+        account.limit.store(0, Ordering::Relaxed);
+        account.bytes.store(0, Ordering::Relaxed);
+        account.blocks.store(0, Ordering::Relaxed);
+        account.pid.store(0, Ordering::Release);
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Subsystem Accounting (memstat)
+// ═══════════════════════════════════════════════════════════════════════════════
+//
+// Code that allocates on behalf of a subsystem enters it for the duration
+// (`enter_subsystem`), and the blocks it allocates meanwhile are counted
+// against that subsystem until they are freed, wherever that happens. The
+// current subsystem is kept per hart; the scheduler puts it back to Kernel
+// while a tick is switched out and restores it on resume. Work done on hart
+// 0 for other harts takes the subsystem of its device (`io_router`).
+
+/// What a heap block was allocated for
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Subsystem {
+    /// Everything not tagged otherwise
+    Kernel = 0,
+    Net,
+    Fs,
+    Wasm,
+    Ui,
+    /// Native binaries: images, stacks and `mmap` blocks
+    Elf,
+    Audio,
+}
+
+const SUBSYSTEM_COUNT: usize = 7;
+
+impl Subsystem {
+    const ALL: [Subsystem; SUBSYSTEM_COUNT] = [
+        Subsystem::Kernel,
+        Subsystem::Net,
+        Subsystem::Fs,
+        Subsystem::Wasm,
+        Subsystem::Ui,
+        Subsystem::Elf,
+        Subsystem::Audio,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Subsystem::Kernel => "kernel",
+            Subsystem::Net => "net",
+            Subsystem::Fs => "fs",
+            Subsystem::Wasm => "wasm",
+            Subsystem::Ui => "ui",
+            Subsystem::Elf => "elf",
+            Subsystem::Audio => "audio",
+        }
+    }
+}
+
+/// Heap use of one subsystem
+struct SubsystemUsage {
+    /// Bytes currently allocated
+    bytes: AtomicUsize,
+    /// Blocks currently allocated
+    blocks: AtomicUsize,
+    /// Highest `bytes` seen
+    peak: AtomicUsize,
+    /// Allocations made since boot
+    allocs: AtomicUsize,
+}
+
+impl SubsystemUsage {
+    const fn new() -> Self {
+        Self {
+            bytes: AtomicUsize::new(0),
+            blocks: AtomicUsize::new(0),
+            peak: AtomicUsize::new(0),
+            allocs: AtomicUsize::new(0),
+        }
+    }
+
+    fn charge(&self, size: usize) {
+        let bytes = self.bytes.fetch_add(size, Ordering::Relaxed) + size;
+        self.peak.fetch_max(bytes, Ordering::Relaxed);
+        self.blocks.fetch_add(1, Ordering::Relaxed);
+        self.allocs.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn credit(&self, size: usize) {
+        let _ = self.bytes.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |bytes| Some(bytes.saturating_sub(size)));
+        let _ = self.blocks.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |blocks| Some(blocks.saturating_sub(1)));
+    }
+}
+
+static SUBSYSTEMS: [SubsystemUsage; SUBSYSTEM_COUNT] = [const { SubsystemUsage::new() }; SUBSYSTEM_COUNT];
+
+/// Subsystem each hart is allocating for
+static CURRENT_SUBSYSTEM: [AtomicU8; crate::cpu::MAX_HARTS] = [const { AtomicU8::new(0) }; crate::cpu::MAX_HARTS];
+
+fn current_subsystem() -> Subsystem {
+    let hart = crate::cpu::get_hart_id();
+    CURRENT_SUBSYSTEM
+        .get(hart)
+        .and_then(|current| Subsystem::ALL.get(current.load(Ordering::Relaxed) as usize).copied())
+        .unwrap_or(Subsystem::Kernel)
+}
+
+/// Count this hart's allocations against `subsystem` from now on; returns
+/// the previous one
+pub fn set_subsystem(subsystem: Subsystem) -> Subsystem {
+    let previous = current_subsystem();
+    if let Some(current) = CURRENT_SUBSYSTEM.get(crate::cpu::get_hart_id()) {
+        current.store(subsystem as u8, Ordering::Relaxed);
+    }
+    previous
+}
+
+/// Puts the previous subsystem back when dropped
+pub struct SubsystemScope {
+    previous: Subsystem,
+}
+
+impl Drop for SubsystemScope {
+    fn drop(&mut self) {
+        set_subsystem(self.previous);
+    }
+}
+
+/// Count this hart's allocations against `subsystem` until the returned
+/// scope is dropped
+pub fn enter_subsystem(subsystem: Subsystem) -> SubsystemScope {
+    SubsystemScope { previous: set_subsystem(subsystem) }
+}
+
+/// Heap use per subsystem: current bytes and blocks, peak bytes and
+/// allocations since boot
+pub fn subsystem_report() -> String {
+    let rows: Vec<(Subsystem, usize, usize, usize, usize)> = Subsystem::ALL
+        .iter()
+        .map(|&subsystem| {
+            let usage = &SUBSYSTEMS[subsystem as usize];
+            (
+                subsystem,
+                usage.bytes.load(Ordering::Relaxed),
+                usage.blocks.load(Ordering::Relaxed),
+                usage.peak.load(Ordering::Relaxed),
+                usage.allocs.load(Ordering::Relaxed),
+            )
+        })
+        .collect();
+    let (used, free) = heap_stats();
+    let counted: usize = rows.iter().map(|row| row.1).sum();
+
+    let mut out = String::new();
+    let _ = writeln!(out, "SUBSYSTEM       BYTES  BLOCKS   PEAK BYTES     ALLOCS");
+    for (subsystem, bytes, blocks, peak, allocs) in rows {
+        let _ = writeln!(out, "{:<9} {:>11} {:>7} {:>12} {:>10}", subsystem.name(), bytes, blocks, peak, allocs);
+    }
+    let _ = writeln!(
+        out,
+        "Heap: {} bytes used ({} in blocks, {} allocator overhead), {} free",
+        used,
+        counted,
+        used.saturating_sub(counted),
+        free
+    );
+    let (events, failures) = crate::oom::stats();
+    if events != 0 {
+        let _ = writeln!(out, "Out of memory: {} times, {} not recovered", events, failures);
+    }
+    out
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Leak Detector (heapdiff)
+// ═══════════════════════════════════════════════════════════════════════════════
+//
+// While tracking is on, every allocation records the PID running on its hart
+// (its tag) and the first return addresses found on the stack (its call
+// site) in a fixed-size table keyed by address. Frees remove the record, so
+// what is left when tracking stops is what was allocated since it started and
+// never freed. The table is allocated up front and never grows, so recording
+// never allocates itself.
+
+/// Live allocations the tracker can hold (power of two)
+const LEAK_TABLE_SIZE: usize = 16384;
+/// Records kept before new allocations are only counted (3/4 full)
+const LEAK_TABLE_LIMIT: usize = LEAK_TABLE_SIZE / 4 * 3;
+/// Return addresses kept per call site
+const SITE_FRAMES: usize = 4;
+/// Stack words searched for return addresses
+const SITE_SCAN_WORDS: usize = 48;
+/// Call sites listed in a report
+const REPORT_SITES: usize = 16;
+
+/// Whether allocations are being recorded (checked before taking any lock)
+static LEAK_TRACKING: AtomicBool = AtomicBool::new(false);
+static LEAK_TRACKER: crate::Spinlock<Option<LeakTracker>> = crate::Spinlock::new(None);
+
+/// Call site of an allocation: the innermost return addresses into .text
+type Site = [usize; SITE_FRAMES];
+
+#[derive(Clone, Copy)]
+struct LeakRecord {
+    /// Block address (0 = empty slot)
+    ptr: usize,
+    size: usize,
+    /// Process running on the hart when the block was allocated (0 = kernel)
+    pid: u32,
+    site: Site,
+}
+
+const EMPTY_RECORD: LeakRecord = LeakRecord { ptr: 0, size: 0, pid: 0, site: [0; SITE_FRAMES] };
+
+/// Counters kept alongside the live records
+#[derive(Clone, Copy, Default)]
+struct LeakStats {
+    started_ms: i64,
+    allocs: usize,
+    frees: usize,
+    /// Frees of blocks allocated before tracking started
+    untracked_frees: usize,
+    untracked_bytes: usize,
+    /// Allocations not recorded because the table was full
+    dropped: usize,
+}
+
+struct LeakTracker {
+    /// Open-addressed table of live allocations made since tracking started
+    records: Vec<LeakRecord>,
+    live: usize,
+    stats: LeakStats,
+}
+
+impl LeakTracker {
+    fn slot(ptr: usize) -> usize {
+        (ptr >> 4).wrapping_mul(0x9e37_79b9_7f4a_7c15) >> (usize::BITS - LEAK_TABLE_SIZE.trailing_zeros())
+    }
+
+    fn insert(&mut self, record: LeakRecord) {
+        self.stats.allocs += 1;
+        if self.live >= LEAK_TABLE_LIMIT {
+            self.stats.dropped += 1;
+            return;
+        }
+        let mut i = Self::slot(record.ptr);
+        while self.records[i].ptr != 0 && self.records[i].ptr != record.ptr {
+            i = (i + 1) & (LEAK_TABLE_SIZE - 1);
+        }
+        if self.records[i].ptr == 0 {
+            self.live += 1;
+        }
+        self.records[i] = record;
+    }
+
+    fn remove(&mut self, ptr: usize, size: usize) {
+        self.stats.frees += 1;
+        let mut i = Self::slot(ptr);
+        loop {
+            match self.records[i].ptr {
+                0 => {
+                    self.stats.untracked_frees += 1;
+                    self.stats.untracked_bytes += size;
+                    return;
+                }
+                p if p == ptr => break,
+                _ => i = (i + 1) & (LEAK_TABLE_SIZE - 1),
+            }
+        }
+        self.live -= 1;
+
+        // Backward-shift deletion: pull later entries of the probe run into
+        // the hole so lookups never stop early at it
+        let mut hole = i;
+        let mut j = i;
+        loop {
+            j = (j + 1) & (LEAK_TABLE_SIZE - 1);
+            let next = self.records[j];
+            if next.ptr == 0 {
+                break;
+            }
+            let home = Self::slot(next.ptr);
+            let distance_to_hole = hole.wrapping_sub(home) & (LEAK_TABLE_SIZE - 1);
+            let distance_to_j = j.wrapping_sub(home) & (LEAK_TABLE_SIZE - 1);
+            if distance_to_hole <= distance_to_j {
+                self.records[hole] = next;
+                hole = j;
+            }
+        }
+        self.records[hole] = EMPTY_RECORD;
+    }
+}
+
+/// Innermost likely return addresses on the current stack (same heuristic
+/// as the panic backtrace: words that point into .text)
+#[inline(always)]
+fn call_site() -> Site {
+    let sp: usize;
+    unsafe { core::arch::asm!("mv {}, sp", out(reg) sp, options(nomem, nostack)) };
+    let (text_start, text_end) = unsafe { (&raw const _stext as usize, &raw const _etext as usize) };
+
+    let mut site = [0; SITE_FRAMES];
+    let mut found = 0;
+    for i in 0..SITE_SCAN_WORDS {
+        let word = unsafe { ptr::read_volatile((sp + i * 8) as *const usize) };
+        if word > text_start && word < text_end && word % 2 == 0 {
+            site[found] = word;
+            found += 1;
+            if found == SITE_FRAMES {
+                break;
+            }
+        }
+    }
+    site
+}
+
+/// Process running on this hart (0 = kernel), the tag allocations are charged to
+fn current_tag() -> u32 {
+    let hart = crate::cpu::get_hart_id();
+    crate::CPU_TABLE.get(hart).and_then(|cpu| cpu.running_process()).unwrap_or(0)
+}
+
+fn track_alloc(ptr: usize, size: usize) {
+    let pid = current_tag();
+    let site = call_site();
+    if let Some(tracker) = LEAK_TRACKER.lock().as_mut() {
+        tracker.insert(LeakRecord { ptr, size, pid, site });
+    }
+}
+
+fn track_dealloc(ptr: usize, size: usize) {
+    if let Some(tracker) = LEAK_TRACKER.lock().as_mut() {
+        tracker.remove(ptr, size);
+    }
+}
+
+/// Start (or restart) recording allocations for `leak_report`
+pub fn leak_tracking_start() {
+    let tracker = LeakTracker {
+        records: alloc::vec![EMPTY_RECORD; LEAK_TABLE_SIZE],
+        live: 0,
+        stats: LeakStats { started_ms: crate::get_time_ms(), ..LeakStats::default() },
+    };
+    let previous = LEAK_TRACKER.lock().replace(tracker);
+    LEAK_TRACKING.store(true, Ordering::Relaxed);
+    // Freed after the lock is released: dropping the old table is tracked too
+    drop(previous);
+}
+
+/// Stop recording and report what was allocated since the start and not
+/// freed. None if tracking was not running.
+pub fn leak_tracking_stop() -> Option<String> {
+    LEAK_TRACKING.store(false, Ordering::Relaxed);
+    let tracker = LEAK_TRACKER.lock().take()?;
+    let live: Vec<LeakRecord> = tracker.records.iter().filter(|r| r.ptr != 0).copied().collect();
+    Some(format_leak_report(&live, &tracker.stats))
+}
+
+/// Report the allocation delta so far while tracking keeps running.
+/// None if tracking is not running.
+pub fn leak_report() -> Option<String> {
+    // Reserve room first: nothing may allocate while the tracker is locked
+    let mut live = Vec::with_capacity(LEAK_TABLE_LIMIT);
+    let stats = {
+        let guard = LEAK_TRACKER.lock();
+        let tracker = guard.as_ref()?;
+        live.extend(tracker.records.iter().filter(|r| r.ptr != 0).copied());
+        tracker.stats
+    };
+    Some(format_leak_report(&live, &stats))
+}
+
+fn tag_name(pid: u32) -> String {
+    if pid == 0 {
+        return String::from("kernel");
+    }
+    match crate::PROC_SCHEDULER.get_process(pid) {
+        Some(process) => alloc::format!("{} ({})", process.name, pid),
+        None => alloc::format!("exited ({})", pid),
+    }
+}
+
+fn format_leak_report(live: &[LeakRecord], stats: &LeakStats) -> String {
+    let mut by_tag: BTreeMap<u32, (usize, usize)> = BTreeMap::new();
+    let mut by_site: BTreeMap<(u32, Site), (usize, usize)> = BTreeMap::new();
+    for record in live {
+        let tag = by_tag.entry(record.pid).or_default();
+        tag.0 += 1;
+        tag.1 += record.size;
+        let site = by_site.entry((record.pid, record.site)).or_default();
+        site.0 += 1;
+        site.1 += record.size;
+    }
+    let live_bytes: usize = live.iter().map(|r| r.size).sum();
+    let elapsed_ms = crate::get_time_ms().saturating_sub(stats.started_ms);
+
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "Heap delta over {}.{} s: +{} bytes in {} blocks still allocated",
+        elapsed_ms / 1000,
+        (elapsed_ms % 1000) / 100,
+        live_bytes,
+        live.len()
+    );
+    let _ = writeln!(
+        out,
+        "  {} allocations, {} frees ({} blocks / {} bytes from before the start)",
+        stats.allocs, stats.frees, stats.untracked_frees, stats.untracked_bytes
+    );
+    if stats.dropped > 0 {
+        let _ = writeln!(out, "  {} allocations not recorded (table full)", stats.dropped);
+    }
+    if live.is_empty() {
+        return out;
+    }
+
+    let mut tags: Vec<_> = by_tag.into_iter().collect();
+    tags.sort_by(|a, b| b.1 .1.cmp(&a.1 .1));
+    let _ = writeln!(out, "\nBy tag:\n     BYTES  BLOCKS  TAG");
+    for (pid, (count, bytes)) in tags {
+        let _ = writeln!(out, "{:>10}  {:>6}  {}", bytes, count, tag_name(pid));
+    }
+
+    let mut sites: Vec<_> = by_site.into_iter().collect();
+    sites.sort_by(|a, b| b.1 .1.cmp(&a.1 .1));
+    // Frames every site shares are the allocator itself: leave them out
+    let first = sites[0].0 .1;
+    let shared = (0..SITE_FRAMES - 1)
+        .take_while(|&i| sites.len() > 1 && sites.iter().all(|((_, site), _)| site[i] == first[i]))
+        .count();
+    let _ = writeln!(out, "\nBy call site (innermost first):\n     BYTES  BLOCKS  TAG / SITE");
+    for ((pid, site), (count, bytes)) in sites.iter().take(REPORT_SITES) {
+        let _ = write!(out, "{:>10}  {:>6}  {} ", bytes, count, tag_name(*pid));
+        for addr in site[shared..].iter().filter(|&&a| a != 0) {
+            let _ = write!(out, " {:#x}", addr);
+        }
+        out.push('\n');
+    }
+    if sites.len() > REPORT_SITES {
+        let _ = writeln!(out, "  ... {} more sites", sites.len() - REPORT_SITES);
+    }
+    let _ = writeln!(out, "Resolve sites with addr2line -f -e <kernel elf> <addr>");
+    out
+}
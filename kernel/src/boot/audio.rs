@@ -3,6 +3,7 @@
 //! Initializes the D1 audio codec driver during boot and plays a boot sound.
 
 use crate::platform;
+use crate::tone;
 
 /// Sample rate for boot sound
 const SAMPLE_RATE: u32 = 48000;
@@ -45,14 +46,10 @@ fn play_boot_beep() {
     let samples_to_play = (SAMPLE_RATE * BEEP_DURATION_MS / 1000) as usize;
     let samples_per_cycle = SAMPLE_RATE / BEEP_FREQ;
     
-    // Pre-compute sine table for one cycle (256 entries)
-    // Using fixed-point math since we're in no_std
-    const SINE_TABLE: [i16; 256] = generate_sine_table();
-    
     for i in 0..samples_to_play {
         // Calculate phase position in sine table (0-255)
         let phase = ((i as u32 * 256) / samples_per_cycle as u32) % 256;
-        let sample = SINE_TABLE[phase as usize];
+        let sample = tone::SINE_TABLE[phase as usize];
         
         // Apply volume envelope (fade in/out)
         let envelope = calculate_envelope(i, samples_to_play);
@@ -86,39 +83,3 @@ fn calculate_envelope(sample_idx: usize, total_samples: usize) -> u8 {
         100
     }
 }
-
-/// Generate a sine table at compile time
-const fn generate_sine_table() -> [i16; 256] {
-    let mut table = [0i16; 256];
-    let mut i = 0;
-    while i < 256 {
-        // Approximate sine using Taylor series (good enough for audio)
-        // sin(x) ≈ x - x³/6 + x⁵/120 for x in radians
-        // x = 2π * i / 256
-        // Using scaled integer math (scale = 10000)
-        
-        let x_scaled = (i as i32 * 62832) / 256; // 2π * 10000 * i / 256
-        
-        // Normalize to -π to π range
-        let x = if x_scaled > 31416 {
-            x_scaled - 62832
-        } else {
-            x_scaled
-        };
-        
-        // Simple sine approximation using parabola (faster, good for audio)
-        // sin(x) ≈ 4x(π-x) / π² for 0 ≤ x ≤ π
-        let pi = 31416; // π * 10000
-        let abs_x = if x < 0 { -x } else { x };
-        let sign = if x < 0 { -1 } else { 1 };
-        
-        // y = 4 * x * (π - x) / π²
-        let y = (4 * abs_x * (pi - abs_x)) / ((pi * pi) / 10000);
-        
-        // Scale to i16 range (-32767 to 32767), but use ~50% volume
-        table[i] = ((sign * y * 16000) / 10000) as i16;
-        
-        i += 1;
-    }
-    table
-}
@@ -3,11 +3,11 @@ use core::sync::atomic::{Ordering, fence};
 
 use alloc::format;
 
-use crate::{boot::{console::{print_info, print_section, print_status}}, cpu::{self, HARTS_ONLINE, get_expected_harts, get_hart_id, sched, send_ipi}, fence_memory, init, services::shelld::shell_service, trap, ui::boot::print_line};
+use crate::{boot::{console::{print_info, print_section, print_status}}, locale::msg, cpu::{self, HARTS_ONLINE, get_expected_harts, get_hart_id, sched, send_ipi}, fence_memory, init, services::shelld::shell_service, trap, ui::boot::print_line};
 
 pub fn init_cpu() {
     print_line("\n");
-    print_section("CPU & ARCHITECTURE");
+    print_section(&msg("boot.section.cpu"));
     print_info("Architecture", "RISC-V 64-bit (RV64GC)");
     print_info("Mode", "Supervisor Mode (S-Mode via SBI)");
     print_info("Timer Source", "CLINT @ 0x02000000");
@@ -37,7 +37,7 @@ pub fn init_cpu() {
     }
     
     print_status(&format!("SMP ready for {} harts", expected_harts), true);
-    print_section("PROCESS MANAGER");
+    print_section(&msg("boot.section.process"));
     
     // Initialize CPU table for ALL expected harts (not just currently online)
     // Secondary harts will join when they wake up from IPI
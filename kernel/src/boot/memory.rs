@@ -1,11 +1,11 @@
 
 use alloc::format;
-use crate::{allocator, boot::console::{print_info, print_section, print_status}};
+use crate::{allocator, boot::console::{print_info, print_section, print_status}, locale::msg};
 
 
 
 pub fn init_memory() {
-    print_section("MEMORY SUBSYSTEM");
+    print_section(&msg("boot.section.memory"));
     let total_heap = allocator::heap_size();
     print_info("Heap Base", "0x80800000");
     print_info("Heap Size", &format!("{} KiB", total_heap / 1024));
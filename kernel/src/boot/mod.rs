@@ -1,5 +1,7 @@
+use alloc::format;
 use core::sync::atomic::{AtomicBool, Ordering};
 
+use crate::locale::{msg, msg_fmt};
 use crate::boot::{
     cpu::init_cpu, 
     dtb::init_dtb, 
@@ -25,19 +27,44 @@ pub mod dtb;
 pub mod touch;
 pub mod audio;
 pub mod services;
+pub mod rescue;
+pub mod profile;
 
 pub(crate) static BOOT_READY: AtomicBool = AtomicBool::new(false);
 
 pub fn init_boot() {
-    init_logger();
-    init_dtb();
-    init_gpu();
-    init_cpu();
-    init_memory();
-    init_storage();
-    init_network();
-    init_touch();
-    init_audio();
-    init_services();
+    profile::stage("logger", init_logger);
+    profile::stage("dtb", init_dtb);
+    profile::stage("gpu", init_gpu);
+    profile::stage("cpu", init_cpu);
+    profile::stage("memory", init_memory);
+    profile::stage("random", crate::random::init);
+    profile::stage("paging", crate::paging::init);
+    profile::stage("storage", init_storage);
+    if storage::has_block_device() && !storage::is_root_mounted() {
+        profile::stage("rescue", || rescue::enter("Root filesystem could not be mounted"));
+    }
+    profile::stage("settings", crate::settings::load);
+    profile::stage("locale", crate::locale::load);
+    profile::stage("network", init_network);
+    profile::stage("touch", init_touch);
+    profile::stage("audio", init_audio);
+    profile::stage("services", init_services);
+    profile::stage("resume", crate::hibernate::resume_session);
+    if crate::init::service_count() == 0 {
+        profile::stage("rescue", || rescue::enter("Init failed to start any service"));
+    }
+    finish_boot_profile();
     BOOT_READY.store(true, Ordering::Release);
+}
+
+/// Report the total boot time and save the chart for later inspection
+fn finish_boot_profile() {
+    console::print_info(
+        &msg("boot.time"),
+        &msg_fmt("boot.time_detail", &[&format!("{}", profile::total_ms())]),
+    );
+    if storage::is_root_mounted() {
+        let _ = crate::cpu::fs_proxy::fs_write(profile::BOOTCHART_LOG, profile::report().as_bytes());
+    }
 }
\ No newline at end of file
@@ -3,12 +3,13 @@ use alloc::format;
 use crate::boot::console::{print_info, print_line, print_section, print_status};
 use crate::device::NetworkDevice;
 use crate::lock::utils::NET_STATE;
+use crate::locale::msg;
 use crate::net;
 use crate::platform;
 
 /// Initialize the network stack
 pub fn init_network() {
-    print_section("NETWORK SUBSYSTEM");
+    print_section(&msg("boot.section.network"));
     print_info("Probing", "D1 EMAC...");
     
     if platform::d1_emac::probe() {
@@ -28,9 +29,21 @@ pub fn init_network() {
                 // Create NetState (stored in unified NET_STATE)
                 match net::NetState::new(device) {
                     Ok(state) => {
-                        let mut net_guard = NET_STATE.lock();
-                        *net_guard = Some(state);
+                        *NET_STATE.lock() = Some(state);
                         print_status("D1 EMAC network initialized (smoltcp)", true);
+                        platform::plic::init_hart(0);
+                        platform::plic::enable(platform::plic::EMAC_IRQ, 0);
+                        print_info("RX IRQ", &format!("{} via PLIC to hart 0", platform::plic::EMAC_IRQ));
+                        print_info("Hosts", &format!("{} names from {}", crate::dns::load_hosts(), crate::dns::HOSTS_PATH));
+                        match net::firewall::load() {
+                            Ok(count) => print_info("Firewall", &format!("{} rules from {}", count, net::firewall::RULES_PATH)),
+                            Err(e) => print_status(&e, false),
+                        }
+
+                        if crate::settings::net_profile() == crate::settings::NetProfile::Static {
+                            crate::settings::apply_network();
+                            print_info("Profile", &format!("static {}", crate::settings::get("network.address").unwrap_or_default()));
+                        }
                     }
                     Err(e) => {
                         print_status(&format!("D1 network init failed: {}", e), false);
@@ -0,0 +1,106 @@
+//! Boot Profiling
+//!
+//! Timestamps every `init_boot` stage and every service start so boot time
+//! can be broken down (storage vs network vs GPU init, ...). The profile is
+//! kept in memory for the `bootchart` command and the GUI chart, and written
+//! to `/var/log/bootchart.log` once boot completes.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::clint::get_time_ms;
+use crate::Spinlock;
+
+/// Log file the final report is written to
+pub const BOOTCHART_LOG: &str = "/var/log/bootchart.log";
+
+/// Width of the text chart bars
+const BAR_WIDTH: usize = 32;
+
+/// What a profile entry measures
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum StageKind {
+    /// A top-level `init_boot` stage
+    Stage,
+    /// A service started during the services stage
+    Service,
+}
+
+/// One measured span (ms since reset)
+#[derive(Clone)]
+pub struct BootStage {
+    pub name: String,
+    pub kind: StageKind,
+    pub start_ms: u64,
+    pub end_ms: u64,
+}
+
+impl BootStage {
+    pub fn duration_ms(&self) -> u64 {
+        self.end_ms.saturating_sub(self.start_ms)
+    }
+}
+
+static BOOT_PROFILE: Spinlock<Vec<BootStage>> = Spinlock::new(Vec::new());
+
+/// Run a boot stage and record how long it took
+pub fn stage<R>(name: &str, f: impl FnOnce() -> R) -> R {
+    let start = get_time_ms() as u64;
+    let result = f();
+    record(name, StageKind::Stage, start);
+    result
+}
+
+/// Record a span that started at `start_ms` and ends now
+pub fn record(name: &str, kind: StageKind, start_ms: u64) {
+    BOOT_PROFILE.lock().push(BootStage {
+        name: String::from(name),
+        kind,
+        start_ms,
+        end_ms: get_time_ms() as u64,
+    });
+}
+
+/// All recorded spans, by start time (services follow their stage)
+pub fn stages() -> Vec<BootStage> {
+    let mut stages = BOOT_PROFILE.lock().clone();
+    stages.sort_by_key(|s| s.start_ms);
+    stages
+}
+
+/// Time from reset to the end of the last recorded span
+pub fn total_ms() -> u64 {
+    BOOT_PROFILE.lock().iter().map(|s| s.end_ms).max().unwrap_or(0)
+}
+
+/// Text chart: one line per span with a bar showing when it ran
+pub fn report() -> String {
+    let stages = stages();
+    let total = stages.iter().map(|s| s.end_ms).max().unwrap_or(0).max(1);
+
+    let mut out = format!("Boot chart: {} ms from reset to boot complete\n\n", total);
+    out.push_str("STAGE              START     TIME\n");
+    for stage in &stages {
+        let indent = if stage.kind == StageKind::Service { "  " } else { "" };
+        let name = format!("{}{}", indent, stage.name);
+
+        let offset = (stage.start_ms as usize * BAR_WIDTH) / total as usize;
+        let width = ((stage.duration_ms() as usize * BAR_WIDTH) / total as usize)
+            .max(1)
+            .min(BAR_WIDTH - offset.min(BAR_WIDTH - 1));
+        let mut bar = String::with_capacity(BAR_WIDTH);
+        for i in 0..BAR_WIDTH {
+            bar.push(if i >= offset && i < offset + width { '#' } else { '.' });
+        }
+
+        out.push_str(&format!(
+            "{:<16} {:>6} ms {:>5} ms  |{}|\n",
+            name,
+            stage.start_ms,
+            stage.duration_ms(),
+            bar
+        ));
+    }
+    out
+}
@@ -0,0 +1,218 @@
+//! Rescue Shell - single-user recovery mode
+//!
+//! Entered from `init_boot` when the root filesystem can't be mounted or
+//! init fails to start any service. Runs on hart 0 before the scheduler and
+//! services, polling the UART directly and keeping everything in RAM, so a
+//! broken image can be checked, repaired and remounted in place.
+//!
+//! Commands:
+//! - `fsck [-y]`          check the SFS image (`-y` repairs)
+//! - `mount`              retry mounting the root filesystem
+//! - `netcfg [up | <ip>]` show, bring up or statically configure networking
+//! - `reboot`             cold reboot via SBI
+//! - `continue [-f]`      resume boot (`-f` even without a root filesystem)
+
+use alloc::format;
+
+use crate::boot::console::{print_info, print_line, print_section, print_status};
+use crate::boot::{network, storage};
+use crate::fs::fsck::Repair;
+use crate::locale::msg;
+use crate::lock::utils::{BLK_DEV, FS_STATE, NET_STATE, VFS_STATE};
+use crate::{fs, net, sbi, uart};
+
+/// Input line buffer size
+const LINE_SIZE: usize = 128;
+
+const PROMPT: &str = "\x1b[1;31mrescue#\x1b[0m ";
+
+/// Run the rescue shell until the user resumes boot (or reboots)
+pub fn enter(reason: &str) {
+    print_section(&msg("boot.section.rescue"));
+    print_status(reason, false);
+    print_line(&format!("    {}", msg("rescue.hint")));
+    print_line(&format!("    {}", msg("rescue.hint_continue")));
+
+    let mut line = [0u8; LINE_SIZE];
+    loop {
+        uart::write_str(PROMPT);
+        let len = read_line(&mut line);
+        let input = core::str::from_utf8(&line[..len]).unwrap_or("").trim();
+
+        let mut words = input.split_whitespace();
+        let cmd = match words.next() {
+            Some(cmd) => cmd,
+            None => continue,
+        };
+        let arg = words.next();
+
+        match cmd {
+            "help" => help(),
+            "fsck" => fsck(arg == Some("-y")),
+            "mount" => mount(),
+            "netcfg" => netcfg(arg),
+            "reboot" => {
+                uart::write_line("Rebooting...");
+                uart::flush();
+                sbi::reboot();
+            }
+            "continue" | "exit" => {
+                if storage::is_root_mounted() || arg == Some("-f") {
+                    print_line("Resuming boot");
+                    return;
+                }
+                uart::write_line("Root filesystem is not mounted (use 'continue -f' to boot anyway)");
+            }
+            _ => {
+                uart::write_str("Unknown command: ");
+                uart::write_line(cmd);
+            }
+        }
+    }
+}
+
+/// Read one line from the UART with echo and backspace handling
+fn read_line(buf: &mut [u8]) -> usize {
+    let mut len = 0;
+    loop {
+        if !uart::Console::is_rx_ready_public() {
+            core::hint::spin_loop();
+            continue;
+        }
+
+        match uart::Console::new().read_byte() {
+            b'\r' | b'\n' => {
+                uart::write_line("");
+                return len;
+            }
+            // Backspace / DEL
+            0x08 | 0x7f => {
+                if len > 0 {
+                    len -= 1;
+                    uart::write_str("\x08 \x08");
+                }
+            }
+            byte @ 0x20..=0x7e => {
+                if len < buf.len() {
+                    buf[len] = byte;
+                    len += 1;
+                    uart::write_byte(byte);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn help() {
+    uart::write_line("  fsck [-y]          Check the root filesystem (-y: repair)");
+    uart::write_line("  mount              Mount the root filesystem");
+    uart::write_line("  netcfg             Show network status");
+    uart::write_line("  netcfg up          Initialize the network device");
+    uart::write_line("  netcfg <a.b.c.d>   Set a static IP address");
+    uart::write_line("  reboot             Reboot the machine");
+    uart::write_line("  continue [-f]      Resume boot (-f: without a root filesystem)");
+}
+
+fn fsck(repair: bool) {
+    if repair && storage::is_root_mounted() {
+        // fsck writes the raw device: flush and drop the cached state first
+        let mut fs_guard = FS_STATE.write();
+        let mut blk_guard = BLK_DEV.write();
+        if let (Some(fs), Some(dev)) = (fs_guard.as_mut(), blk_guard.as_mut()) {
+            let _ = fs.sync(dev);
+        }
+        *fs_guard = None;
+        drop(blk_guard);
+        drop(fs_guard);
+        *VFS_STATE.write() = None;
+        uart::write_line("Unmounted root filesystem for repair");
+    }
+
+    let mut blk_guard = BLK_DEV.write();
+    let dev = match blk_guard.as_mut() {
+        Some(dev) => dev,
+        None => {
+            uart::write_line("fsck: no block device");
+            return;
+        }
+    };
+
+    let mode = if repair { Repair::All } else { Repair::None };
+    match fs::fsck::check(dev, mode) {
+        Ok(report) => {
+            for problem in &report.problems {
+                uart::write_str("  ");
+                uart::write_line(problem);
+            }
+            print_info("Entries", &format!("{}", report.files));
+            print_info("Data sectors", &format!("{}", report.sectors));
+            if report.is_clean() {
+                print_status("Filesystem clean", true);
+            } else if repair {
+                print_status(&format!("{} problem(s), {} repaired", report.problems.len(), report.repaired), report.repaired > 0);
+                uart::write_line("Run 'mount' to remount the repaired filesystem");
+            } else {
+                print_status(&format!("{} problem(s) found, run 'fsck -y' to repair", report.problems.len()), false);
+            }
+        }
+        Err(e) => {
+            uart::write_str("fsck: ");
+            uart::write_line(e);
+        }
+    }
+}
+
+fn mount() {
+    if storage::is_root_mounted() {
+        uart::write_line("Root filesystem already mounted");
+        return;
+    }
+    if !storage::has_block_device() {
+        uart::write_line("mount: no block device");
+        return;
+    }
+    storage::mount_root();
+}
+
+fn netcfg(arg: Option<&str>) {
+    match arg {
+        None => {
+            let net_guard = NET_STATE.lock();
+            match net_guard.as_ref() {
+                Some(state) => {
+                    let mac = state.mac_str();
+                    print_info("MAC", core::str::from_utf8(&mac).unwrap_or("?"));
+                    let mut ip_buf = [0u8; 16];
+                    let ip_len = net::format_ipv4(net::get_my_ip(), &mut ip_buf);
+                    print_info("IP", core::str::from_utf8(&ip_buf[..ip_len]).unwrap_or("?"));
+                }
+                None => uart::write_line("Network not initialized (use 'netcfg up')"),
+            }
+        }
+        Some("up") => {
+            if NET_STATE.lock().is_some() {
+                uart::write_line("Network already initialized");
+            } else {
+                network::init_network();
+            }
+        }
+        Some(addr) => {
+            let ip = match net::parse_ipv4(addr.as_bytes()) {
+                Some(ip) => ip,
+                None => {
+                    uart::write_line("netcfg: invalid IPv4 address");
+                    return;
+                }
+            };
+            let mut net_guard = NET_STATE.lock();
+            match net_guard.as_mut() {
+                Some(state) => {
+                    state.set_static_ip(ip);
+                    print_status(&format!("IP set to {}/{}", addr, net::PREFIX_LEN), true);
+                }
+                None => uart::write_line("Network not initialized (use 'netcfg up')"),
+            }
+        }
+    }
+}
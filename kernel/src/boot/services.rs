@@ -4,16 +4,22 @@ use alloc::{format, string::ToString};
 
 use crate::{
     boot::console::{print_info, print_section, print_status},
-    cpu::{self, process::{Pid, Priority, ProcessEntry}, sched},
+    boot::profile::{self, StageKind},
+    cpu::{self, process::{CpuMask, Pid, Priority, ProcessEntry}, sched},
     fence_memory, init,
+    locale::msg,
     services::{
         gpuid::{self, gpuid_service},
+        hostbridged,
         httpd,
+        kioskd,
         klogd::{self, klog_debug, klog_error, klog_info},
         netd,
+        portfwd,
         shelld::{self, shell_tick},
         sysmond,
         tcpd,
+        telnetd,
     }, trap,
 };
 
@@ -48,10 +54,10 @@ fn write_boot_log() {
     );
 
     // Write to kernel.log
-    let mut fs_guard = crate::FS_STATE.write();
-    let mut blk_guard = crate::lock::utils::BLK_DEV.write();
+    let fs_guard = crate::FS_STATE.read();
+    let blk_guard = crate::lock::utils::BLK_DEV.read();
 
-    if let (Some(fs), Some(dev)) = (fs_guard.as_mut(), blk_guard.as_mut()) {
+    if let (Some(fs), Some(dev)) = (fs_guard.as_ref(), blk_guard.as_ref()) {
         if let Err(e) = fs.write_file(dev, "/var/log/kernel.log", boot_msg.as_bytes()) {
             klog_error("init", &format!("Failed to write boot log: {}", e));
         } else {
@@ -68,10 +74,10 @@ fn write_boot_log() {
 /// Run init scripts from /etc/init.d/
 /// Note: Init scripts must be WASM binaries
 fn run_init_scripts() {
-    let mut fs_guard = crate::FS_STATE.write();
-    let mut blk_guard = crate::lock::utils::BLK_DEV.write();
+    let fs_guard = crate::FS_STATE.read();
+    let blk_guard = crate::lock::utils::BLK_DEV.read();
 
-    if let (Some(fs), Some(dev)) = (fs_guard.as_mut(), blk_guard.as_mut()) {
+    if let (Some(fs), Some(dev)) = (fs_guard.as_ref(), blk_guard.as_ref()) {
         // Look for init scripts
         let files = fs.list_dir(dev, "/");
         for file in files {
@@ -117,42 +123,80 @@ fn schedule_service(
     description: &str,
     entry: ProcessEntry, 
     priority: Priority, 
-    cpu_affinity: Option<usize>
+    affinity: CpuMask,
+    group: &'static str,
 ) {    
 
-    let affinity_str = match cpu_affinity {
-        Some(hart) => format!("hart {}", hart),
-        None => format!("any hart"),
+    let start = crate::get_time_ms() as u64;
+    let affinity_str = if affinity.is_all() {
+        format!("any hart")
+    } else {
+        format!("harts {}", affinity.intersect(sched::SCHEDULER.online_mask()).to_list())
     };
     print_status(&format!("Scheduling service: {} ({})", name, affinity_str), true);
     print_info("Registering service definition", &format!("{}", name));
-    let hart = if let Some(hart) = cpu_affinity {
-        hart
-    } else {
-        sched::SCHEDULER.find_least_loaded_cpu()
-    };
+    let hart = sched::SCHEDULER.least_loaded_in(affinity);
     init::register_service_def(
         name,
         description,
         entry,
         priority,
-        Some(hart),
+        affinity,
+        group,
     );
+    // Still registered above, so it can be started by hand later
+    if !crate::settings::autostart(name) {
+        print_info("Not started", &format!("{} (autostart off)", name));
+        return;
+    }
    
-    let pid = sched::SCHEDULER.spawn_daemon_on_cpu(name, entry, priority, Some(hart));
-    print_info("Started service", &format!("{} (PID {}, {})", name, pid, hart));
+    // Daemons start on `hart` and are moved by the scheduler's rebalance
+    // pass, among the harts their affinity allows, when it gets overloaded
+    let pid = sched::SCHEDULER.spawn_daemon_with_affinity(name, entry, priority, affinity);
+    init::join_service_group(name, pid, group);
+    print_info("Started service", &format!("{} (PID {}, {}, group {})", name, pid, hart, group));
     init::register_service(name, pid, Some(hart));
+    profile::record(name, StageKind::Service, start);
+}
+
+/// Start a WASM worker daemon pinned to every secondary hart, then the
+/// services of /etc/wasm-services.conf (run as jobs on those workers)
+fn start_wasm_services() {
+    let num_harts = crate::HARTS_ONLINE.load(Ordering::Relaxed);
+    crate::wasm_service::init(num_harts);
+    for hart in 1..num_harts {
+        schedule_service(
+            &format!("wasmworkerd-{}", hart),
+            "WASM worker - runs wasmrun jobs and WASM services on its hart",
+            init::wasm_worker_service,
+            Priority::Normal,
+            CpuMask::single(hart),
+            "system",
+        );
+    }
+
+    for name in init::load_wasm_services() {
+        match init::start_service(&name) {
+            Ok(()) => print_info("Started WASM service", &name),
+            Err(e) => {
+                print_status(&format!("WASM service {}: {}", name, e), false);
+                klog_error("init", &format!("WASM service {} not started: {}", name, e));
+            }
+        }
+    }
 }
 
 pub fn init_services() {
 
-    print_section("SERVICES");
+    print_section(&msg("boot.section.services"));
+    init::define_service_groups();
     schedule_service(
         "klogd",
         "Kernel logger daemon - logs system memory stats",
         klogd::klogd_service,
         Priority::Normal,
-        None,
+        CpuMask::ALL,
+        "system",
     );
 
     schedule_service(
@@ -160,7 +204,8 @@ pub fn init_services() {
         "System monitor daemon - monitors system health",
         sysmond::sysmond_service,
         Priority::Normal,
-        None,
+        CpuMask::ALL,
+        "system",
     );
    
     let has_gpu = crate::platform::d1_display::is_available();
@@ -173,7 +218,8 @@ pub fn init_services() {
             "Shell daemon - handles interactive command input",
             shelld::shell_service,
             Priority::High,
-            None,  // Testing: keep on hart 0
+            CpuMask::ALL,  // Testing: keep on hart 0
+            "interactive",
         );
 
     if has_net {
@@ -182,7 +228,8 @@ pub fn init_services() {
             "Network daemon - handles IP assignment from relay",
             netd::netd_service,
             Priority::High,
-            None,  // Can run on any hart
+            CpuMask::ALL,  // Can run on any hart
+            "network",
         );
     
         schedule_service(
@@ -190,15 +237,35 @@ pub fn init_services() {
             "TCP daemon - listens on port 30, responds with hello",
             tcpd::tcpd_service,
             Priority::Normal,
-            None,
+            CpuMask::ALL,
+            "network",
         );
     
         schedule_service(
             "httpd",
-            "HTTP server daemon - listens on port 80, serves web content",
+            "HTTP server daemon - listens on port 80 (and 443 with a certificate), serves web content",
             httpd::httpd_service,
             Priority::Normal,
-            None,
+            CpuMask::SECONDARY,  // CGI and TLS work stays off hart 0
+            "web",
+        );
+    
+        schedule_service(
+            "portfwd",
+            "Port forwarding daemon - relays local ports to other hosts (/etc/portfwd.conf)",
+            portfwd::portfwd_service,
+            Priority::Normal,
+            CpuMask::ALL,
+            "network",
+        );
+    
+        schedule_service(
+            "telnetd",
+            "Telnet daemon - remote console on port 23",
+            telnetd::telnetd_service,
+            Priority::Normal,
+            CpuMask::ALL,
+            "interactive",
         );
     }
 
@@ -208,7 +275,8 @@ pub fn init_services() {
             "GPU UI daemon - handles keyboard input and display updates",
             gpuid_service,
             Priority::High,
-            None,  // Can run on any hart (touch driver is thread-safe)
+            CpuMask::ALL,  // Can run on any hart (touch driver is thread-safe)
+            "interactive",
         );
         
         // GUI command process - executes terminal commands in U-mode
@@ -217,10 +285,30 @@ pub fn init_services() {
             "GUI command executor - runs terminal commands in user mode",
             crate::services::gui_cmd::gui_cmd_service,
             Priority::Normal,
-            None,
+            CpuMask::ALL,
+            "interactive",
+        );
+
+        schedule_service(
+            "kioskd",
+            "Kiosk daemon - cycles full-screen info pages when the GUI is idle (/etc/kiosk.conf)",
+            kioskd::kioskd_service,
+            Priority::Low,
+            CpuMask::ALL,
+            "system",
         );
     }
 
+    schedule_service(
+        "hostbridged",
+        "Host bridge daemon - clipboard and file drop from the page embedding the emulator",
+        hostbridged::hostbridged_service,
+        Priority::Low,
+        CpuMask::single(0),  // Owns the host bridge MMIO
+        "system",
+    );
+
+    start_wasm_services();
 
     let services = init::service_count();
     print_status( &format!("System services started ({})", services),  services > 0);
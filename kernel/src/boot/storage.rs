@@ -2,10 +2,15 @@ use alloc::boxed::Box;
 use alloc::format;
 
 use crate::boot::console::{print_section, print_status, print_info};
-use crate::fs::{FileSystemState, Vfs, GlobalSfs, P9FileSystem};
+use crate::fs::fsck::{self, Repair};
+use crate::fs::{FileSystemState, Vfs, GlobalSfs, P9FileSystem, ProcFs};
+use crate::locale::msg;
+use crate::lock::state::fs::FORMAT_MINOR;
 use crate::lock::utils::{BLK_DEV, FS_STATE, VFS_STATE};
 use crate::platform;
 
+/// Problems listed on the boot console (the rest are only counted)
+const MAX_BOOT_PROBLEMS: usize = 5;
 
 /// Ensure required system directories exist
 fn ensure_directories() {
@@ -21,7 +26,7 @@ fn ensure_directories() {
 
 
 pub fn init_storage() {
-    print_section("STORAGE SUBSYSTEM");
+    print_section(&msg("boot.section.storage"));
     
     // Initialize block device
     let mut blk = platform::d1_mmc::D1Mmc::new();
@@ -31,22 +36,84 @@ pub fn init_storage() {
         *BLK_DEV.write() = Some(blk);
         print_status("D1 MMC driver loaded", true);
         ensure_directories();
+        check_root();
     } else {
         print_status("No storage device found", false);
     }
 
     // Initialize filesystem on block device
+    mount_root();
+}
+
+/// Boot-time fsck: fixes the allocation bitmap (reclaiming sectors leaked by
+/// overwrites and deletes) and reports anything else for the rescue shell.
+/// Must run before the filesystem is mounted.
+fn check_root() {
+    let mut blk_guard = BLK_DEV.write();
+    let dev = match blk_guard.as_mut() {
+        Some(dev) => dev,
+        None => return,
+    };
+
+    match fsck::check(dev, Repair::Bitmap) {
+        Ok(report) if report.is_clean() => print_status("Filesystem clean", true),
+        Ok(report) => {
+            // Bitmap problems alone are always fixed in this mode
+            if report.problems.iter().all(|p| p.starts_with("bitmap:")) {
+                for problem in &report.problems {
+                    print_info("fsck", problem);
+                }
+                print_status("Allocation bitmap repaired", true);
+                return;
+            }
+            for problem in report.problems.iter().take(MAX_BOOT_PROBLEMS) {
+                print_info("fsck", problem);
+            }
+            if report.problems.len() > MAX_BOOT_PROBLEMS {
+                print_info("fsck", &format!("... {} more", report.problems.len() - MAX_BOOT_PROBLEMS));
+            }
+            print_status("Filesystem has errors, orphaned sectors kept", false);
+        }
+        Err(e) => print_status(&format!("fsck failed: {}", e), false),
+    }
+}
+
+/// Mount SFS from the block device as root and (re)build the VFS.
+/// Returns whether the root filesystem is mounted.
+pub fn mount_root() -> bool {
     let mut blk_guard = BLK_DEV.write();
     if let Some(ref mut blk) = *blk_guard {
-        if let Some(fs) = FileSystemState::init(blk) {
-            print_status("SFS Mounted (R/W)", true);
-            *FS_STATE.write() = Some(fs);
+        match FileSystemState::init(blk) {
+            Ok(fs) => {
+                let format = fs.format();
+                if fs.is_read_only() {
+                    print_status(&format!("SFS {} mounted read-only (newer than this kernel)", format), false);
+                } else {
+                    print_status(&format!("SFS {} mounted (R/W)", format), true);
+                    if format.minor < FORMAT_MINOR {
+                        print_info("SFS", "older format revision, run sfs-upgrade");
+                    }
+                }
+                *FS_STATE.write() = Some(fs);
+            }
+            Err(e) => print_status(&format!("SFS mount failed: {}", e), false),
         }
     }
     drop(blk_guard);
 
     // Initialize VFS
     init_vfs();
+    is_root_mounted()
+}
+
+/// Whether a block device was found at boot
+pub fn has_block_device() -> bool {
+    BLK_DEV.read().is_some()
+}
+
+/// Whether the SFS root filesystem is mounted
+pub fn is_root_mounted() -> bool {
+    FS_STATE.read().is_some()
 }
 
 /// Initialize the Virtual File System and mount available filesystems
@@ -66,6 +133,10 @@ fn init_vfs() {
         print_info("9P Mount", "/mnt/disk1");
     }
 
+    // /proc needs no device, so it is always available
+    vfs.mount(crate::fs::procfs::MOUNT_POINT, Box::new(ProcFs));
+    print_info("procfs", crate::fs::procfs::MOUNT_POINT);
+
     // Store VFS if we have any mounts
     if !vfs.list_mounts().is_empty() {
         *VFS_STATE.write() = Some(vfs);
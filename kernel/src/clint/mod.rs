@@ -1,6 +1,15 @@
 use crate::constants::CLINT_MTIME;
 
+/// Nanoseconds per tick of the 10 MHz `mtime` counter
+pub(crate) const NS_PER_TICK: u64 = 100;
+
 pub(crate) fn get_time_ms() -> i64 {
     let mtime = unsafe { core::ptr::read_volatile(CLINT_MTIME as *const u64) };
     (mtime / 10_000) as i64
-}
\ No newline at end of file
+}
+
+/// Monotonic time since boot in nanoseconds (100 ns resolution)
+pub(crate) fn get_time_ns() -> u64 {
+    let mtime = unsafe { core::ptr::read_volatile(CLINT_MTIME as *const u64) };
+    mtime * NS_PER_TICK
+}
@@ -0,0 +1,335 @@
+//! DEFLATE decompression (RFC 1951) with gzip (RFC 1952) and zlib
+//! (RFC 1950) framing, for `Content-Encoding: gzip` / `deflate` responses.
+//!
+//! A straightforward canonical-Huffman decoder in the style of zlib's
+//! `puff`: small and allocation-light rather than fast, which is plenty for
+//! web pages fetched over the emulated NIC.
+
+use alloc::vec::Vec;
+
+/// Order in which code length code lengths are stored in a dynamic block
+const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+/// Base lengths and extra bits for length symbols 257..=285
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+
+/// Base distances and extra bits for distance symbols 0..=29
+const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145,
+    8193, 12289, 16385, 24577,
+];
+const DIST_EXTRA: [u8; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+
+/// LSB-first bit reader over the compressed data
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    bit_buf: u32,
+    bit_count: u32,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0, bit_buf: 0, bit_count: 0 }
+    }
+
+    fn bits(&mut self, need: u32) -> Result<u32, &'static str> {
+        while self.bit_count < need {
+            let byte = *self.data.get(self.pos).ok_or("Truncated deflate stream")?;
+            self.pos += 1;
+            self.bit_buf |= (byte as u32) << self.bit_count;
+            self.bit_count += 8;
+        }
+        let value = self.bit_buf & ((1u32 << need) - 1);
+        self.bit_buf >>= need;
+        self.bit_count -= need;
+        Ok(value)
+    }
+
+    /// Drop the rest of the current byte (stored blocks start byte-aligned)
+    fn align(&mut self) {
+        self.bit_buf = 0;
+        self.bit_count = 0;
+    }
+}
+
+/// Canonical Huffman code: number of codes of each length, and the symbols
+/// ordered by code
+struct Huffman {
+    counts: [u16; 16],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Result<Self, &'static str> {
+        let mut counts = [0u16; 16];
+        for &len in lengths {
+            counts[len as usize] += 1;
+        }
+
+        // Reject over-subscribed codes (incomplete ones are allowed)
+        let mut left: i32 = 1;
+        for &count in &counts[1..] {
+            left = (left << 1) - count as i32;
+            if left < 0 {
+                return Err("Invalid Huffman code");
+            }
+        }
+
+        let mut offsets = [0u16; 16];
+        for len in 1..15 {
+            offsets[len + 1] = offsets[len] + counts[len];
+        }
+        let mut symbols = alloc::vec![0u16; lengths.len()];
+        for (symbol, &len) in lengths.iter().enumerate() {
+            if len != 0 {
+                symbols[offsets[len as usize] as usize] = symbol as u16;
+                offsets[len as usize] += 1;
+            }
+        }
+        Ok(Self { counts, symbols })
+    }
+
+    fn decode(&self, bits: &mut BitReader) -> Result<u16, &'static str> {
+        let mut code: i32 = 0;
+        let mut first: i32 = 0;
+        let mut index: i32 = 0;
+        for len in 1..16 {
+            code |= bits.bits(1)? as i32;
+            let count = self.counts[len] as i32;
+            if code - count < first {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err("Invalid Huffman code")
+    }
+}
+
+/// Decompress a raw DEFLATE stream, giving up past `max_len` bytes of output.
+/// Returns the output and the number of input bytes consumed.
+pub fn inflate(data: &[u8], max_len: usize) -> Result<(Vec<u8>, usize), &'static str> {
+    let mut bits = BitReader::new(data);
+    let mut out = Vec::with_capacity(data.len().saturating_mul(4).min(max_len));
+
+    loop {
+        let last = bits.bits(1)? == 1;
+        match bits.bits(2)? {
+            0 => stored_block(&mut bits, &mut out)?,
+            1 => {
+                let (lit, dist) = fixed_codes()?;
+                codes_block(&mut bits, &mut out, &lit, &dist, max_len)?;
+            }
+            2 => {
+                let (lit, dist) = dynamic_codes(&mut bits)?;
+                codes_block(&mut bits, &mut out, &lit, &dist, max_len)?;
+            }
+            _ => return Err("Invalid deflate block type"),
+        }
+        if out.len() > max_len {
+            return Err("Decompressed body too large");
+        }
+        if last {
+            return Ok((out, bits.pos));
+        }
+    }
+}
+
+fn stored_block(bits: &mut BitReader, out: &mut Vec<u8>) -> Result<(), &'static str> {
+    bits.align();
+    let header = bits.data.get(bits.pos..bits.pos + 4).ok_or("Truncated deflate stream")?;
+    let len = u16::from_le_bytes([header[0], header[1]]);
+    let nlen = u16::from_le_bytes([header[2], header[3]]);
+    if len != !nlen {
+        return Err("Corrupt stored block");
+    }
+    bits.pos += 4;
+    let block = bits.data.get(bits.pos..bits.pos + len as usize).ok_or("Truncated deflate stream")?;
+    out.extend_from_slice(block);
+    bits.pos += len as usize;
+    Ok(())
+}
+
+fn fixed_codes() -> Result<(Huffman, Huffman), &'static str> {
+    let mut lengths = [0u8; 288];
+    for (symbol, len) in lengths.iter_mut().enumerate() {
+        *len = match symbol {
+            0..=143 => 8,
+            144..=255 => 9,
+            256..=279 => 7,
+            _ => 8,
+        };
+    }
+    Ok((Huffman::new(&lengths)?, Huffman::new(&[5u8; 30])?))
+}
+
+fn dynamic_codes(bits: &mut BitReader) -> Result<(Huffman, Huffman), &'static str> {
+    let nlen = bits.bits(5)? as usize + 257;
+    let ndist = bits.bits(5)? as usize + 1;
+    let ncode = bits.bits(4)? as usize + 4;
+    if nlen > 286 || ndist > 30 {
+        return Err("Invalid dynamic block header");
+    }
+
+    let mut code_lengths = [0u8; 19];
+    for &index in &CODE_LENGTH_ORDER[..ncode] {
+        code_lengths[index] = bits.bits(3)? as u8;
+    }
+    let code_huffman = Huffman::new(&code_lengths)?;
+
+    // Literal/length and distance code lengths, run-length encoded together
+    let mut lengths = [0u8; 286 + 30];
+    let mut i = 0;
+    while i < nlen + ndist {
+        let symbol = code_huffman.decode(bits)?;
+        let (value, repeat) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            16 => {
+                let prev = *lengths[..i].last().ok_or("Repeat with no previous length")?;
+                (prev, 3 + bits.bits(2)? as usize)
+            }
+            17 => (0, 3 + bits.bits(3)? as usize),
+            _ => (0, 11 + bits.bits(7)? as usize),
+        };
+        if i + repeat > nlen + ndist {
+            return Err("Too many code lengths");
+        }
+        lengths[i..i + repeat].fill(value);
+        i += repeat;
+    }
+    if lengths[256] == 0 {
+        return Err("No end-of-block code");
+    }
+
+    Ok((Huffman::new(&lengths[..nlen])?, Huffman::new(&lengths[nlen..nlen + ndist])?))
+}
+
+fn codes_block(
+    bits: &mut BitReader,
+    out: &mut Vec<u8>,
+    lit: &Huffman,
+    dist: &Huffman,
+    max_len: usize,
+) -> Result<(), &'static str> {
+    loop {
+        let symbol = lit.decode(bits)? as usize;
+        match symbol {
+            0..=255 => out.push(symbol as u8),
+            256 => return Ok(()),
+            _ => {
+                let index = symbol - 257;
+                if index >= LENGTH_BASE.len() {
+                    return Err("Invalid length symbol");
+                }
+                let len = LENGTH_BASE[index] as usize + bits.bits(LENGTH_EXTRA[index] as u32)? as usize;
+
+                let index = dist.decode(bits)? as usize;
+                if index >= DIST_BASE.len() {
+                    return Err("Invalid distance symbol");
+                }
+                let distance = DIST_BASE[index] as usize + bits.bits(DIST_EXTRA[index] as u32)? as usize;
+                if distance > out.len() {
+                    return Err("Distance too far back");
+                }
+
+                // The copy may overlap what it produces, so go byte by byte
+                let start = out.len() - distance;
+                for k in 0..len {
+                    out.push(out[start + k]);
+                }
+            }
+        }
+        if out.len() > max_len {
+            return Err("Decompressed body too large");
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// gzip and zlib framing
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// CRC-32 (IEEE), as used by the gzip trailer
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xEDB8_8320 & (crc & 1).wrapping_neg());
+        }
+    }
+    !crc
+}
+
+/// Decompress a gzip member (`Content-Encoding: gzip`)
+pub fn gunzip(data: &[u8], max_len: usize) -> Result<Vec<u8>, &'static str> {
+    const FHCRC: u8 = 0x02;
+    const FEXTRA: u8 = 0x04;
+    const FNAME: u8 = 0x08;
+    const FCOMMENT: u8 = 0x10;
+
+    if data.len() < 18 || data[0] != 0x1f || data[1] != 0x8b || data[2] != 8 {
+        return Err("Not a gzip stream");
+    }
+    let flags = data[3];
+    let mut pos = 10;
+    if flags & FEXTRA != 0 {
+        let xlen = u16::from_le_bytes([*data.get(pos).ok_or("Truncated gzip header")?, *data.get(pos + 1).ok_or("Truncated gzip header")?]);
+        pos += 2 + xlen as usize;
+    }
+    for flag in [FNAME, FCOMMENT] {
+        if flags & flag != 0 {
+            let end = data.get(pos..).and_then(|rest| rest.iter().position(|&b| b == 0)).ok_or("Truncated gzip header")?;
+            pos += end + 1;
+        }
+    }
+    if flags & FHCRC != 0 {
+        pos += 2;
+    }
+
+    let (out, used) = inflate(data.get(pos..).ok_or("Truncated gzip header")?, max_len)?;
+    let trailer = data.get(pos + used..pos + used + 8).ok_or("Truncated gzip trailer")?;
+    let crc = u32::from_le_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+    let size = u32::from_le_bytes([trailer[4], trailer[5], trailer[6], trailer[7]]);
+    if crc != crc32(&out) || size != out.len() as u32 {
+        return Err("gzip checksum mismatch");
+    }
+    Ok(out)
+}
+
+/// Decompress `Content-Encoding: deflate`: a zlib stream, or a raw DEFLATE
+/// stream as some servers send instead
+pub fn zlib_decompress(data: &[u8], max_len: usize) -> Result<Vec<u8>, &'static str> {
+    let is_zlib = data.len() >= 2 && data[0] & 0x0F == 8 && u16::from_be_bytes([data[0], data[1]]) % 31 == 0;
+    if !is_zlib {
+        return inflate(data, max_len).map(|(out, _)| out);
+    }
+    if data[1] & 0x20 != 0 {
+        return Err("zlib preset dictionaries are not supported");
+    }
+
+    let (out, used) = inflate(&data[2..], max_len)?;
+    let trailer = data.get(2 + used..2 + used + 4).ok_or("Truncated zlib trailer")?;
+    if u32::from_be_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]) != adler32(&out) {
+        return Err("zlib checksum mismatch");
+    }
+    Ok(out)
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    (b << 16) | a
+}
@@ -5,16 +5,35 @@
 //! - Custom headers
 //! - Response parsing with status, headers, and body
 //! - Automatic redirect following (301, 302, 303, 307, 308)
+//! - gzip/deflate compressed responses (`Accept-Encoding`), decompressed
+//!   transparently
+//! - Streaming downloads (`download`) that hand the body over as it
+//!   arrives, for files larger than the heap
 
 use alloc::collections::BTreeMap;
 use alloc::format;
 use alloc::string::{String, ToString};
 use alloc::vec::Vec;
+use embedded_io::{Read, Write};
 use smoltcp::wire::Ipv4Address;
 
+use crate::tls::{BlockingTcpSocket, TlsError};
+
+mod inflate;
+mod stream;
+
+pub use stream::ResponseHead;
+use stream::ResponseStream;
+
 /// Maximum number of redirects to follow before giving up
 const MAX_REDIRECTS: u8 = 10;
 
+/// Largest body a compressed response may decompress to
+const MAX_DECODED_BODY: usize = 8 * 1024 * 1024;
+
+/// Value of the Accept-Encoding header sent by `accept_compressed`
+const ACCEPT_ENCODING: &str = "gzip, deflate";
+
 /// HTTP methods
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum HttpMethod {
@@ -46,6 +65,8 @@ pub struct HttpRequest {
     pub headers: BTreeMap<String, String>,
     pub body: Option<Vec<u8>>,
     pub is_https: bool,
+    /// Skip server certificate validation for HTTPS
+    pub insecure: bool,
 }
 
 impl HttpRequest {
@@ -80,6 +101,7 @@ impl HttpRequest {
             headers,
             body: None,
             is_https: parsed.is_https,
+            insecure: false,
         })
     }
 
@@ -89,6 +111,23 @@ impl HttpRequest {
         self
     }
 
+    /// Ask for a gzip/deflate compressed body (`Accept-Encoding`); the
+    /// response is decompressed before it is returned
+    pub fn accept_compressed(mut self, compressed: bool) -> Self {
+        if compressed {
+            self.headers.insert("Accept-Encoding".to_string(), ACCEPT_ENCODING.to_string());
+        } else {
+            self.headers.remove("Accept-Encoding");
+        }
+        self
+    }
+
+    /// Skip server certificate validation (like curl's --insecure)
+    pub fn insecure(mut self, insecure: bool) -> Self {
+        self.insecure = insecure;
+        self
+    }
+
     /// Set the request body
     pub fn body(mut self, body: Vec<u8>) -> Self {
         let len = body.len();
@@ -270,15 +309,10 @@ fn resolve_redirect_url(original: &HttpRequest, location: &str) -> Result<String
 
 /// Parse raw HTTP response bytes into HttpResponse
 pub fn parse_response(data: &[u8]) -> Result<HttpResponse, &'static str> {
-    // Convert to string for easier parsing
-    let response_str = core::str::from_utf8(data).map_err(|_| "Invalid UTF-8 in response")?;
+    // Find header/body separator (the body may be binary, e.g. gzip)
+    let header_end = find_header_end(data).ok_or("No header/body separator found")?;
 
-    // Find header/body separator
-    let header_end = response_str
-        .find("\r\n\r\n")
-        .ok_or("No header/body separator found")?;
-
-    let header_section = &response_str[..header_end];
+    let header_section = core::str::from_utf8(&data[..header_end]).map_err(|_| "Invalid UTF-8 in response")?;
     let body_start = header_end + 4;
 
     // Parse status line
@@ -296,6 +330,7 @@ pub fn parse_response(data: &[u8]) -> Result<HttpResponse, &'static str> {
     // Parse headers
     let mut headers = BTreeMap::new();
     let mut is_chunked = false;
+    let mut encoding = None;
     for line in lines {
         if let Some(colon_idx) = line.find(':') {
             let key = line[..colon_idx].trim().to_string();
@@ -305,6 +340,9 @@ pub fn parse_response(data: &[u8]) -> Result<HttpResponse, &'static str> {
             if key.to_lowercase() == "transfer-encoding" && value.to_lowercase().contains("chunked") {
                 is_chunked = true;
             }
+            if key.to_lowercase() == "content-encoding" {
+                encoding = Some((key.clone(), value.to_lowercase()));
+            }
             
             headers.insert(key, value);
         }
@@ -318,6 +356,19 @@ pub fn parse_response(data: &[u8]) -> Result<HttpResponse, &'static str> {
         raw_body.to_vec()
     };
 
+    // Undo Content-Encoding; the header goes so callers see a plain body
+    let body = match encoding {
+        Some((key, value)) if value == "gzip" || value == "deflate" => {
+            headers.remove(&key);
+            if value == "gzip" {
+                inflate::gunzip(&body, MAX_DECODED_BODY)?
+            } else {
+                inflate::zlib_decompress(&body, MAX_DECODED_BODY)?
+            }
+        }
+        _ => body,
+    };
+
     Ok(HttpResponse {
         status_code,
         status_text,
@@ -398,145 +449,89 @@ pub fn http_request(
 
     // HTTP (non-TLS) request
     let dest_ip = resolve_host(net, &request.host, timeout_ms, get_time_ms)?;
+    let socket = BlockingTcpSocket::new(net, timeout_ms, get_time_ms);
 
-    let start_time = get_time_ms();
-
-    // Connect to the server
-    net.tcp_connect(dest_ip, request.port, start_time)?;
-
-    // Wait for connection to establish
-    loop {
-        let now = get_time_ms();
-        if now - start_time > timeout_ms {
-            net.tcp_abort();
-            return Err("Connection timeout");
-        }
-
-        net.poll(now);
-
-        if net.tcp_is_connected() {
-            break;
+    let mut response_buf = Vec::with_capacity(8192);
+    let mut content_length: Option<usize> = None;
+    let mut body_start = None;
+
+    http_exchange(socket, dest_ip, request, &mut |data| {
+        response_buf.extend_from_slice(data);
+
+        // Check if we've received all headers
+        if body_start.is_none() {
+            if let Some(pos) = find_header_end(&response_buf) {
+                body_start = Some(pos + 4);
+
+                // Parse content-length from headers
+                if let Ok(s) = core::str::from_utf8(&response_buf[..pos]) {
+                    for line in s.lines() {
+                        if line.to_lowercase().starts_with("content-length:") {
+                            if let Some(len_str) = line.split(':').nth(1) {
+                                content_length = len_str.trim().parse().ok();
+                            }
+                        }
+                    }
+                }
+            }
         }
 
-        if net.tcp_connection_failed() {
-            return Err("Connection failed");
-        }
+        // Without a content-length, the response ends at connection close
+        Ok(match (body_start, content_length) {
+            (Some(start), Some(expected)) => response_buf.len() - start >= expected,
+            _ => false,
+        })
+    })?;
 
-        // Small delay to avoid busy-waiting
-        for _ in 0..10000 {
-            core::hint::spin_loop();
-        }
+    // Parse the response
+    if response_buf.is_empty() {
+        return Err("Empty response");
     }
 
-    // Send the HTTP request
-    let request_bytes = request.build();
-    let mut sent = 0;
-
-    while sent < request_bytes.len() {
-        let now = get_time_ms();
-        if now - start_time > timeout_ms {
-            net.tcp_abort();
-            return Err("Send timeout");
-        }
-
-        net.poll(now);
+    parse_response(&response_buf)
+}
 
-        match net.tcp_send(&request_bytes[sent..], now) {
-            Ok(n) if n > 0 => sent += n,
-            Ok(_) => {}
-            Err(e) => {
-                net.tcp_abort();
-                return Err(e);
-            }
-        }
+/// Send a plain HTTP request over `socket` and pass the response bytes to
+/// `on_data` as they arrive, until it returns true or the server closes the
+/// connection. The socket's timeout applies to each wait.
+fn http_exchange(
+    mut socket: BlockingTcpSocket<'_>,
+    dest_ip: Ipv4Address,
+    request: &HttpRequest,
+    on_data: &mut dyn FnMut(&[u8]) -> Result<bool, &'static str>,
+) -> Result<(), &'static str> {
+    socket.connect(dest_ip, request.port).map_err(|e| match e {
+        TlsError::Timeout => "Connection timeout",
+        _ => "Connection failed",
+    })?;
 
-        // Small delay
-        for _ in 0..5000 {
-            core::hint::spin_loop();
-        }
-    }
+    // Send the HTTP request
+    socket.write_all(&request.build()).map_err(|e| match e {
+        TlsError::Timeout => "Send timeout",
+        _ => "Send failed",
+    })?;
 
     // Receive the response
-    let mut response_buf = Vec::with_capacity(8192);
     let mut recv_buf = [0u8; 1024];
-    let mut headers_complete = false;
-    let mut content_length: Option<usize> = None;
-    let mut body_start = 0;
-
+    let mut received_any = false;
     loop {
-        let now = get_time_ms();
-        if now - start_time > timeout_ms {
-            net.tcp_abort();
-            return Err("Receive timeout");
-        }
-
-        net.poll(now);
-
-        match net.tcp_recv(&mut recv_buf, now) {
-            Ok(n) if n > 0 => {
-                response_buf.extend_from_slice(&recv_buf[..n]);
-
-                // Check if we've received all headers
-                if !headers_complete {
-                    if let Some(pos) = find_header_end(&response_buf) {
-                        headers_complete = true;
-                        body_start = pos + 4;
-
-                        // Parse content-length from headers
-                        if let Ok(s) = core::str::from_utf8(&response_buf[..pos]) {
-                            for line in s.lines() {
-                                if line.to_lowercase().starts_with("content-length:") {
-                                    if let Some(len_str) = line.split(':').nth(1) {
-                                        content_length = len_str.trim().parse().ok();
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-
-                // Check if we've received the complete response
-                if headers_complete {
-                    let body_len = response_buf.len() - body_start;
-                    match content_length {
-                        Some(expected) if body_len >= expected => break,
-                        None => {
-                            // No content-length, wait for connection close
-                        }
-                        _ => {}
-                    }
-                }
-            }
-            Ok(_) => {
-                // No data available, check if connection closed
-                if net.tcp_connection_failed() {
+        match socket.read(&mut recv_buf) {
+            Ok(0) => break,
+            Ok(n) => {
+                received_any = true;
+                if on_data(&recv_buf[..n])? {
                     break;
                 }
             }
-            Err(e) => {
-                if e == "Connection closed by peer" && response_buf.len() > 0 {
-                    break;
-                }
-                net.tcp_abort();
-                return Err(e);
-            }
-        }
-
-        // Small delay
-        for _ in 0..5000 {
-            core::hint::spin_loop();
+            // Without a content-length, the response ends at connection close
+            Err(TlsError::ConnectionClosed) if received_any => break,
+            Err(TlsError::Timeout) => return Err("Receive timeout"),
+            Err(_) => return Err("Connection failed"),
         }
     }
 
-    // Close the connection
-    net.tcp_close(get_time_ms());
-
-    // Parse the response
-    if response_buf.is_empty() {
-        return Err("Empty response");
-    }
-
-    parse_response(&response_buf)
+    // Dropping the socket closes the connection
+    Ok(())
 }
 
 /// Perform an HTTP request with automatic redirect following
@@ -564,6 +559,7 @@ pub fn http_request_follow_redirects(
         headers: request.headers.clone(),
         body: request.body.clone(),
         is_https: request.is_https,
+        insecure: request.insecure,
     };
 
     let mut redirects = 0u8;
@@ -616,6 +612,9 @@ pub fn http_request_follow_redirects(
         );
         new_headers.insert("Accept".to_string(), "*/*".to_string());
         new_headers.insert("Connection".to_string(), "close".to_string());
+        if let Some(encoding) = current_request.headers.get("Accept-Encoding") {
+            new_headers.insert("Accept-Encoding".to_string(), encoding.clone());
+        }
 
         // Drop body for GET requests
         let new_body = if new_method == HttpMethod::Get {
@@ -632,12 +631,13 @@ pub fn http_request_follow_redirects(
             headers: new_headers,
             body: new_body,
             is_https: parsed.is_https,
+            insecure: current_request.insecure,
         };
     }
 }
 
 /// Resolve hostname to IP address (handles both IPs and hostnames)
-fn resolve_host(
+pub(crate) fn resolve_host(
     net: &mut crate::net::NetState,
     host: &str,
     timeout_ms: i64,
@@ -660,7 +660,7 @@ fn resolve_host(
 }
 
 /// Find the end of HTTP headers (double CRLF)
-fn find_header_end(data: &[u8]) -> Option<usize> {
+pub(crate) fn find_header_end(data: &[u8]) -> Option<usize> {
     for i in 0..data.len().saturating_sub(3) {
         if data[i] == b'\r' && data[i + 1] == b'\n' && data[i + 2] == b'\r' && data[i + 3] == b'\n'
         {
@@ -688,7 +688,18 @@ pub fn get_follow_redirects(
     timeout_ms: i64,
     get_time_ms: fn() -> i64,
 ) -> Result<HttpResponse, &'static str> {
-    let request = HttpRequest::get(url)?;
+    get_follow_redirects_insecure(net, url, false, timeout_ms, get_time_ms)
+}
+
+/// GET that follows redirects, optionally without validating certificates
+pub fn get_follow_redirects_insecure(
+    net: &mut crate::net::NetState,
+    url: &str,
+    insecure: bool,
+    timeout_ms: i64,
+    get_time_ms: fn() -> i64,
+) -> Result<HttpResponse, &'static str> {
+    let request = HttpRequest::get(url)?.insecure(insecure).accept_compressed(true);
     http_request_follow_redirects(net, &request, timeout_ms, get_time_ms)
 }
 
@@ -712,7 +723,10 @@ pub fn post(
 // ═══════════════════════════════════════════════════════════════════════════════
 
 /// Perform an HTTPS request using TLS
-/// Tries TLS 1.3 first, falls back to TLS 1.2 if needed
+///
+/// Certificates are validated over TLS 1.2 (the TLS 1.3 client cannot check
+/// them). `--insecure` requests try TLS 1.3 first and fall back to TLS 1.2,
+/// unless the host has a cached TLS 1.2 session to resume.
 fn https_request(
     net: &mut crate::net::NetState,
     request: &HttpRequest,
@@ -728,21 +742,29 @@ fn https_request(
     // Use longer timeout for HTTPS (TLS handshake needs multiple round trips)
     let https_timeout = timeout_ms.max(30000);
 
-    // Try TLS 1.3 first (embedded-tls)
-    let response_bytes = match crate::tls::https_request(
-        net,
-        dest_ip,
-        request.port,
-        &request.host,
-        &request_bytes,
-        https_timeout,
-        get_time_ms,
-    ) {
-        Ok(bytes) => bytes,
-        Err(_) => {
-            // TLS 1.3 failed, try TLS 1.2
+    // Try TLS 1.3 first (embedded-tls) for insecure requests, unless the
+    // host has a cached TLS 1.2 session to resume
+    let tls13 = if !request.insecure || crate::tls12::has_session(&request.host) {
+        None
+    } else {
+        let result = crate::tls::https_request(
+            net,
+            dest_ip,
+            request.port,
+            &request.host,
+            &request_bytes,
+            https_timeout,
+            get_time_ms,
+        );
+        if result.is_err() {
             crate::uart::write_line("TLS 1.3 failed, trying TLS 1.2...");
+        }
+        result.ok()
+    };
 
+    let response_bytes = match tls13 {
+        Some(bytes) => bytes,
+        None => {
             crate::tls12::https_request_tls12(
                 net,
                 dest_ip,
@@ -751,18 +773,9 @@ fn https_request(
                 &request_bytes,
                 https_timeout,
                 get_time_ms,
+                request.insecure,
             )
-            .map_err(|e| match e {
-                crate::tls::TlsError::ConnectionError => "HTTPS: TCP connection failed",
-                crate::tls::TlsError::TlsProtocolError => "HTTPS: TLS handshake failed",
-                crate::tls::TlsError::Timeout => "HTTPS: Connection timeout",
-                crate::tls::TlsError::InvalidData => "HTTPS: Invalid TLS data",
-                crate::tls::TlsError::Io => "HTTPS: I/O error",
-                crate::tls::TlsError::ConnectionClosed => "HTTPS: Connection closed",
-                crate::tls::TlsError::NotConnected => "HTTPS: Not connected",
-                crate::tls::TlsError::DnsError => "HTTPS: DNS resolution failed",
-                crate::tls::TlsError::InternalError => "HTTPS: Internal TLS error",
-            })?
+            .map_err(tls_error_message)?
         }
     };
 
@@ -773,3 +786,124 @@ fn https_request(
 
     parse_response(&response_bytes)
 }
+
+/// User-facing message for a TLS failure
+fn tls_error_message(e: TlsError) -> &'static str {
+    match e {
+        TlsError::ConnectionError => "HTTPS: TCP connection failed",
+        TlsError::TlsProtocolError => "HTTPS: TLS handshake failed",
+        TlsError::Timeout => "HTTPS: Connection timeout",
+        TlsError::InvalidData => "HTTPS: Invalid TLS data",
+        TlsError::Io => "HTTPS: I/O error",
+        TlsError::ConnectionClosed => "HTTPS: Connection closed",
+        TlsError::NotConnected => "HTTPS: Not connected",
+        TlsError::DnsError => "HTTPS: DNS resolution failed",
+        TlsError::InternalError => "HTTPS: Internal TLS error",
+        TlsError::BadCertificate => {
+            "HTTPS: Certificate not trusted (use --insecure to skip the check)"
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// STREAMING DOWNLOADS
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Outcome of `download`
+pub struct Download {
+    /// Status of the final response (after redirects)
+    pub status_code: u16,
+    /// Body bytes handed to the sink
+    pub bytes: u64,
+}
+
+/// GET `url`, following redirects, and pass the body of a successful (2xx)
+/// response to `sink` piece by piece as it arrives instead of buffering it.
+/// The body of an error response is skipped; check `status_code`.
+///
+/// Unlike the other requests, this locks `NET_STATE` itself and only while
+/// the connection polls, so `sink` may write files (which goes through
+/// hart 0). HTTPS downloads always use TLS 1.2. `timeout_ms` bounds each
+/// wait for data rather than the whole transfer.
+pub fn download(
+    url: &str,
+    insecure: bool,
+    timeout_ms: i64,
+    get_time_ms: fn() -> i64,
+    sink: &mut dyn FnMut(&ResponseHead, &[u8]) -> Result<(), &'static str>,
+) -> Result<Download, &'static str> {
+    let mut request = HttpRequest::get(url)?.insecure(insecure);
+
+    for _ in 0..=MAX_REDIRECTS {
+        let mut stream = ResponseStream::new();
+        let mut on_data = |data: &[u8]| -> Result<bool, &'static str> {
+            stream.feed(data, &mut |head, body| {
+                if (200..300).contains(&head.status_code) { sink(head, body) } else { Ok(()) }
+            })?;
+            // Only a successful response's body is worth waiting for
+            let skip = stream.head().is_some_and(|head| !(200..300).contains(&head.status_code));
+            Ok(stream.is_complete() || skip)
+        };
+        let dest_ip = {
+            let mut net_guard = crate::NET_STATE.lock();
+            let net = net_guard.as_mut().ok_or("Network not available")?;
+            resolve_host(net, &request.host, timeout_ms, get_time_ms)?
+        };
+        if request.is_https {
+            https_exchange(dest_ip, &request, timeout_ms, get_time_ms, &mut on_data)?;
+        } else {
+            let socket = BlockingTcpSocket::shared(timeout_ms, get_time_ms);
+            http_exchange(socket, dest_ip, &request, &mut on_data)?;
+        }
+
+        let head = stream.head().ok_or("Empty response")?;
+        if head.is_redirect() {
+            let location = head.header("Location").ok_or("Redirect without Location header")?;
+            let new_url = resolve_redirect_url(&request, location)?;
+            crate::uart::write_str("Redirecting to: ");
+            crate::uart::write_line(&new_url);
+            request = HttpRequest::get(&new_url)?.insecure(insecure);
+            continue;
+        }
+        let success = (200..300).contains(&head.status_code);
+        if success && head.content_length.is_some() && !stream.is_complete() {
+            return Err("Connection closed before the download completed");
+        }
+        return Ok(Download { status_code: head.status_code, bytes: stream.received });
+    }
+    Err("Too many redirects")
+}
+
+/// HTTPS counterpart of `http_exchange` (over TLS 1.2, locking the network
+/// stack per operation)
+fn https_exchange(
+    dest_ip: Ipv4Address,
+    request: &HttpRequest,
+    timeout_ms: i64,
+    get_time_ms: fn() -> i64,
+    on_data: &mut dyn FnMut(&[u8]) -> Result<bool, &'static str>,
+) -> Result<(), &'static str> {
+    let request_bytes = request.build();
+
+    // An error from `on_data` travels through the TLS layer as `Io`
+    let mut data_error = None;
+    let result = crate::tls12::https_request_tls12_streaming(
+        dest_ip,
+        request.port,
+        &request.host,
+        &request_bytes,
+        timeout_ms.max(30000),
+        get_time_ms,
+        request.insecure,
+        &mut |data| {
+            on_data(data).map_err(|e| {
+                data_error = Some(e);
+                TlsError::Io
+            })
+        },
+    );
+    match (result, data_error) {
+        (Err(_), Some(e)) => Err(e),
+        (result, _) => result.map_err(tls_error_message),
+    }
+}
@@ -0,0 +1,205 @@
+//! Streaming response parser
+//!
+//! Parses an HTTP/1.1 response as it arrives in arbitrary pieces and hands
+//! the body to a sink without keeping it, undoing chunked transfer encoding
+//! on the way. Used for downloads that may not fit in memory.
+
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use super::find_header_end;
+
+/// Longest accepted header section
+const MAX_HEADER_SIZE: usize = 16 * 1024;
+
+/// Status line and headers of a streamed response
+pub struct ResponseHead {
+    pub status_code: u16,
+    pub headers: BTreeMap<String, String>,
+    /// Declared body size (None for chunked or close-delimited bodies)
+    pub content_length: Option<u64>,
+}
+
+impl ResponseHead {
+    /// Get a header value (case-insensitive)
+    pub fn header(&self, name: &str) -> Option<&String> {
+        let name_lower = name.to_lowercase();
+        self.headers.iter().find(|(k, _)| k.to_lowercase() == name_lower).map(|(_, v)| v)
+    }
+
+    pub fn is_redirect(&self) -> bool {
+        matches!(self.status_code, 301 | 302 | 303 | 307 | 308)
+    }
+}
+
+enum Body {
+    /// Still collecting the header section
+    Head(Vec<u8>),
+    /// Plain body; `remaining` is None when it ends at connection close
+    Plain { remaining: Option<u64> },
+    /// Reading a chunk size line
+    ChunkSize(Vec<u8>),
+    /// Inside a chunk
+    ChunkData(u64),
+    /// CRLF after a chunk's data
+    ChunkEnd(u8),
+    /// Trailer lines after the last chunk
+    Trailer(Vec<u8>),
+    Done,
+}
+
+/// Incremental parser for one response
+pub struct ResponseStream {
+    head: Option<ResponseHead>,
+    body: Body,
+    /// Body bytes delivered to the sink so far
+    pub received: u64,
+}
+
+impl ResponseStream {
+    pub fn new() -> Self {
+        Self { head: None, body: Body::Head(Vec::new()), received: 0 }
+    }
+
+    /// Status line and headers, once they have arrived
+    pub fn head(&self) -> Option<&ResponseHead> {
+        self.head.as_ref()
+    }
+
+    /// Whether the whole body has been delivered (close-delimited bodies
+    /// are only complete when the connection closes)
+    pub fn is_complete(&self) -> bool {
+        matches!(self.body, Body::Done)
+    }
+
+    /// Feed received bytes, passing body data to `sink` along with the
+    /// response's headers
+    pub fn feed(
+        &mut self,
+        mut data: &[u8],
+        sink: &mut dyn FnMut(&ResponseHead, &[u8]) -> Result<(), &'static str>,
+    ) -> Result<(), &'static str> {
+        while !data.is_empty() {
+            match &mut self.body {
+                Body::Head(buf) => {
+                    buf.extend_from_slice(data);
+                    let Some(end) = find_header_end(buf) else {
+                        if buf.len() > MAX_HEADER_SIZE {
+                            return Err("Response headers too large");
+                        }
+                        return Ok(());
+                    };
+                    let rest = buf.split_off(end + 4);
+                    let head = parse_head(&buf[..end])?;
+                    let chunked = head
+                        .header("Transfer-Encoding")
+                        .is_some_and(|v| v.to_lowercase().contains("chunked"));
+                    self.body = if chunked {
+                        Body::ChunkSize(Vec::new())
+                    } else if head.content_length == Some(0) {
+                        Body::Done
+                    } else {
+                        Body::Plain { remaining: head.content_length }
+                    };
+                    self.head = Some(head);
+                    return self.feed(&rest, sink);
+                }
+                Body::Plain { remaining } => {
+                    let take = match remaining {
+                        Some(left) => (*left).min(data.len() as u64) as usize,
+                        None => data.len(),
+                    };
+                    if let Some(left) = remaining {
+                        *left -= take as u64;
+                        if *left == 0 {
+                            self.body = Body::Done;
+                        }
+                    }
+                    self.received += take as u64;
+                    sink(self.head.as_ref().ok_or("Missing headers")?, &data[..take])?;
+                    data = &data[take..];
+                }
+                Body::ChunkSize(line) => {
+                    let Some(text) = take_line(line, &mut data)? else {
+                        return Ok(());
+                    };
+                    // Chunk extensions (size;ext=value) are ignored
+                    let size = text.split(';').next().unwrap_or("").trim();
+                    self.body = match u64::from_str_radix(size, 16).map_err(|_| "Malformed chunked encoding")? {
+                        0 => Body::Trailer(Vec::new()),
+                        size => Body::ChunkData(size),
+                    };
+                }
+                Body::Trailer(line) => {
+                    let Some(text) = take_line(line, &mut data)? else {
+                        return Ok(());
+                    };
+                    self.body = if text.is_empty() { Body::Done } else { Body::Trailer(Vec::new()) };
+                }
+                Body::ChunkData(left) => {
+                    let take = (*left).min(data.len() as u64) as usize;
+                    *left -= take as u64;
+                    if *left == 0 {
+                        self.body = Body::ChunkEnd(2);
+                    }
+                    self.received += take as u64;
+                    sink(self.head.as_ref().ok_or("Missing headers")?, &data[..take])?;
+                    data = &data[take..];
+                }
+                Body::ChunkEnd(left) => {
+                    // Tolerate a bare LF
+                    if data[0] == b'\n' {
+                        *left = 1;
+                    }
+                    *left -= 1;
+                    data = &data[1..];
+                    if *left == 0 {
+                        self.body = Body::ChunkSize(Vec::new());
+                    }
+                }
+                Body::Done => return Ok(()),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Collect a line into `line`, consuming it from `data`; returns the
+/// trimmed line once its LF has arrived
+fn take_line(line: &mut Vec<u8>, data: &mut &[u8]) -> Result<Option<String>, &'static str> {
+    let Some(nl) = data.iter().position(|&b| b == b'\n') else {
+        line.extend_from_slice(data);
+        *data = &[];
+        if line.len() > 1024 {
+            return Err("Malformed chunked encoding");
+        }
+        return Ok(None);
+    };
+    line.extend_from_slice(&data[..nl]);
+    *data = &data[nl + 1..];
+    let text = core::str::from_utf8(line).map_err(|_| "Malformed chunked encoding")?;
+    Ok(Some(text.trim().to_string()))
+}
+
+fn parse_head(bytes: &[u8]) -> Result<ResponseHead, &'static str> {
+    let text = core::str::from_utf8(bytes).map_err(|_| "Invalid UTF-8 in response")?;
+    let mut lines = text.lines();
+    let status_line = lines.next().ok_or("Missing status line")?;
+    let status_code = status_line
+        .split(' ')
+        .nth(1)
+        .ok_or("Missing status code")?
+        .parse()
+        .map_err(|_| "Invalid status code")?;
+
+    let mut headers = BTreeMap::new();
+    for line in lines {
+        if let Some((key, value)) = line.split_once(':') {
+            headers.insert(key.trim().to_string(), value.trim().to_string());
+        }
+    }
+    let mut head = ResponseHead { status_code, headers, content_length: None };
+    head.content_length = head.header("Content-Length").and_then(|v| v.parse().ok());
+    Ok(head)
+}
@@ -0,0 +1,527 @@
+//! WebSocket client (RFC 6455)
+//!
+//! `WebSocket::connect` performs the HTTP Upgrade handshake over plain TCP
+//! (`ws://`) or TLS 1.2 (`wss://`); after that, messages are exchanged as
+//! frames. Pings are answered automatically, fragmented messages are
+//! reassembled, and a close from the server is echoed back.
+//!
+//! Like `http::download`, a connection locks `NET_STATE` only while it
+//! polls, so it can be kept open across syscalls. Connections opened by
+//! userspace programs live in a small table (`open`, `with_connection`,
+//! `close`) and are closed when their program exits.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use embedded_io::{Read, Write};
+use rand_core::RngCore;
+
+use super::http::{find_header_end, resolve_host};
+use crate::random::KernelRng;
+use crate::tls::{BlockingTcpSocket, TlsError};
+use crate::tls12::Tls12Connection;
+use crate::Spinlock;
+
+/// GUID the server appends to our key for `Sec-WebSocket-Accept`
+const ACCEPT_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// Largest message (after reassembly) we accept
+const MAX_MESSAGE_SIZE: usize = 1024 * 1024;
+
+/// Largest TLS record payload, so a TLS read never truncates
+const READ_CHUNK: usize = 16 * 1024;
+
+/// Longest accepted handshake response
+const MAX_HANDSHAKE_SIZE: usize = 8 * 1024;
+
+/// Frame opcodes
+const OP_CONTINUATION: u8 = 0x0;
+const OP_TEXT: u8 = 0x1;
+const OP_BINARY: u8 = 0x2;
+const OP_CLOSE: u8 = 0x8;
+const OP_PING: u8 = 0x9;
+const OP_PONG: u8 = 0xA;
+
+/// Close status for a normal closure
+const CLOSE_NORMAL: u16 = 1000;
+
+/// A complete message
+pub enum Message {
+    Text(Vec<u8>),
+    Binary(Vec<u8>),
+    /// The server closed the connection (with its status code, if any)
+    Close(Option<u16>),
+}
+
+/// One decoded frame
+pub struct Frame {
+    pub fin: bool,
+    pub opcode: u8,
+    pub payload: Vec<u8>,
+}
+
+/// Encode a frame; client frames must be masked with a random `mask`
+pub fn encode_frame(fin: bool, opcode: u8, payload: &[u8], mask: Option<[u8; 4]>) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(payload.len() + 14);
+    frame.push(if fin { 0x80 } else { 0 } | opcode);
+    let mask_bit = if mask.is_some() { 0x80 } else { 0 };
+    match payload.len() {
+        len @ 0..=125 => frame.push(mask_bit | len as u8),
+        len @ 126..=0xFFFF => {
+            frame.push(mask_bit | 126);
+            frame.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            frame.push(mask_bit | 127);
+            frame.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    match mask {
+        Some(mask) => {
+            frame.extend_from_slice(&mask);
+            frame.extend(payload.iter().enumerate().map(|(i, b)| b ^ mask[i % 4]));
+        }
+        None => frame.extend_from_slice(payload),
+    }
+    frame
+}
+
+/// Decode the frame at the start of `data`, returning it and the bytes it
+/// used, or None if it has not fully arrived
+pub fn decode_frame(data: &[u8]) -> Result<Option<(Frame, usize)>, &'static str> {
+    if data.len() < 2 {
+        return Ok(None);
+    }
+    if data[0] & 0x70 != 0 {
+        return Err("WebSocket: reserved bits set");
+    }
+    let fin = data[0] & 0x80 != 0;
+    let opcode = data[0] & 0x0F;
+    let masked = data[1] & 0x80 != 0;
+    let (len, mut pos) = match data[1] & 0x7F {
+        126 if data.len() >= 4 => (u16::from_be_bytes([data[2], data[3]]) as u64, 4),
+        127 if data.len() >= 10 => {
+            let mut bytes = [0u8; 8];
+            bytes.copy_from_slice(&data[2..10]);
+            (u64::from_be_bytes(bytes), 10)
+        }
+        126 | 127 => return Ok(None),
+        len => (len as u64, 2),
+    };
+    if len > MAX_MESSAGE_SIZE as u64 {
+        return Err("WebSocket: frame too large");
+    }
+    if opcode >= OP_CLOSE && (len > 125 || !fin) {
+        return Err("WebSocket: malformed control frame");
+    }
+    let mask = if masked {
+        let Some(key) = data.get(pos..pos + 4) else {
+            return Ok(None);
+        };
+        pos += 4;
+        Some([key[0], key[1], key[2], key[3]])
+    } else {
+        None
+    };
+    let end = pos + len as usize;
+    let Some(payload) = data.get(pos..end) else {
+        return Ok(None);
+    };
+    let payload = match mask {
+        Some(mask) => payload.iter().enumerate().map(|(i, b)| b ^ mask[i % 4]).collect(),
+        None => payload.to_vec(),
+    };
+    Ok(Some((Frame { fin, opcode, payload }, end)))
+}
+
+/// The connection underneath
+enum Transport {
+    Plain(BlockingTcpSocket<'static>),
+    Tls(alloc::boxed::Box<Tls12Connection<'static>>),
+}
+
+impl Transport {
+    fn socket(&mut self) -> &mut BlockingTcpSocket<'static> {
+        match self {
+            Transport::Plain(socket) => socket,
+            Transport::Tls(tls) => tls.socket(),
+        }
+    }
+
+    fn write_all(&mut self, data: &[u8]) -> Result<(), TlsError> {
+        match self {
+            Transport::Plain(socket) => socket.write_all(data),
+            Transport::Tls(tls) => tls.write(data).map(|_| ()),
+        }
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, TlsError> {
+        match self {
+            Transport::Plain(socket) => socket.read(buf),
+            Transport::Tls(tls) => tls.read(buf),
+        }
+    }
+
+    fn close(&mut self) {
+        match self {
+            Transport::Plain(socket) => socket.close(),
+            Transport::Tls(tls) => {
+                let _ = tls.close();
+            }
+        }
+    }
+}
+
+/// An open WebSocket connection
+pub struct WebSocket {
+    transport: Transport,
+    /// Received bytes not yet decoded into frames
+    pending: Vec<u8>,
+    /// Opcode and data of a fragmented message being reassembled
+    partial: Option<(u8, Vec<u8>)>,
+    /// Whether a close frame has been sent or received
+    closed: bool,
+    rng: KernelRng,
+}
+
+impl WebSocket {
+    /// Connect to a `ws://` or `wss://` URL (`insecure` skips certificate
+    /// checks). `timeout_ms` bounds each wait for the server.
+    pub fn connect(url: &str, insecure: bool, timeout_ms: i64, get_time_ms: fn() -> i64) -> Result<Self, &'static str> {
+        let (rest, secure, default_port) = if let Some(rest) = url.strip_prefix("wss://") {
+            (rest, true, 443)
+        } else if let Some(rest) = url.strip_prefix("ws://") {
+            (rest, false, 80)
+        } else {
+            return Err("WebSocket URLs start with ws:// or wss://");
+        };
+        let (authority, path) = match rest.find('/') {
+            Some(idx) => (&rest[..idx], &rest[idx..]),
+            None => (rest, "/"),
+        };
+        let (host, port) = match authority.split_once(':') {
+            Some((host, port)) => (host, port.parse().map_err(|_| "Invalid port number")?),
+            None => (authority, default_port),
+        };
+
+        let ip = {
+            let mut net_guard = crate::NET_STATE.lock();
+            let net = net_guard.as_mut().ok_or("Network not available")?;
+            resolve_host(net, host, timeout_ms, get_time_ms)?
+        };
+        let mut socket = BlockingTcpSocket::shared(timeout_ms, get_time_ms);
+        socket.connect(ip, port).map_err(|e| match e {
+            TlsError::Timeout => "Connection timeout",
+            _ => "Connection failed",
+        })?;
+        let transport = if secure {
+            let mut tls = Tls12Connection::new(socket);
+            tls.set_verify(!insecure);
+            tls.handshake(host).map_err(|e| match e {
+                TlsError::BadCertificate => "wss: Certificate not trusted",
+                _ => "wss: TLS handshake failed",
+            })?;
+            Transport::Tls(alloc::boxed::Box::new(tls))
+        } else {
+            Transport::Plain(socket)
+        };
+
+        let mut ws = WebSocket { transport, pending: Vec::new(), partial: None, closed: false, rng: KernelRng };
+        let mut key = [0u8; 16];
+        ws.rng.fill_bytes(&mut key);
+        let key = base64_encode(&key);
+        let host_header = if port == default_port { host.to_string() } else { format!("{}:{}", host, port) };
+        let request = format!(
+            "GET {} HTTP/1.1\r\nHost: {}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+             Sec-WebSocket-Key: {}\r\nSec-WebSocket-Version: 13\r\nUser-Agent: BAVY OS/{}\r\n\r\n",
+            path,
+            host_header,
+            key,
+            env!("CARGO_PKG_VERSION")
+        );
+        ws.transport.write_all(request.as_bytes()).map_err(|_| "Send failed")?;
+        ws.finish_handshake(&key)
+    }
+
+    /// Read the server's 101 response and check its accept key
+    fn finish_handshake(mut self, key: &str) -> Result<Self, &'static str> {
+        let end = loop {
+            if let Some(end) = find_header_end(&self.pending) {
+                break end;
+            }
+            if self.pending.len() > MAX_HANDSHAKE_SIZE {
+                return Err("WebSocket: handshake response too large");
+            }
+            self.fill()?;
+        };
+        let head = core::str::from_utf8(&self.pending[..end]).map_err(|_| "Invalid UTF-8 in response")?;
+        let mut lines = head.lines();
+        let status = lines.next().and_then(|line| line.split(' ').nth(1)).unwrap_or("");
+        if status != "101" {
+            return Err("WebSocket: server refused the upgrade");
+        }
+        let expected = base64_encode(&sha1(format!("{}{}", key, ACCEPT_GUID).as_bytes()));
+        let accepted = lines.filter_map(|line| line.split_once(':')).any(|(name, value)| {
+            name.trim().eq_ignore_ascii_case("Sec-WebSocket-Accept") && value.trim() == expected
+        });
+        if !accepted {
+            return Err("WebSocket: bad Sec-WebSocket-Accept");
+        }
+        // Frames may follow the headers in the same read
+        self.pending.drain(..end + 4);
+        Ok(self)
+    }
+
+    /// Whether the connection has been closed by either side
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    /// Send a text (`binary == false`) or binary message
+    pub fn send(&mut self, data: &[u8], binary: bool) -> Result<(), &'static str> {
+        if self.closed {
+            return Err("WebSocket: connection closed");
+        }
+        self.send_frame(if binary { OP_BINARY } else { OP_TEXT }, data)
+    }
+
+    fn send_frame(&mut self, opcode: u8, payload: &[u8]) -> Result<(), &'static str> {
+        let mut mask = [0u8; 4];
+        self.rng.fill_bytes(&mut mask);
+        let frame = encode_frame(true, opcode, payload, Some(mask));
+        self.transport.write_all(&frame).map_err(|_| "WebSocket: send failed")
+    }
+
+    /// Wait up to `wait_ms` for the next message (None if none arrived in
+    /// time). Control frames are handled here and not returned, except a
+    /// close, which ends the connection.
+    pub fn recv(&mut self, wait_ms: i64) -> Result<Option<Message>, &'static str> {
+        loop {
+            if let Some((frame, used)) = decode_frame(&self.pending)? {
+                self.pending.drain(..used);
+                if let Some(message) = self.handle_frame(frame)? {
+                    return Ok(Some(message));
+                }
+                continue;
+            }
+            if self.closed {
+                return Err("WebSocket: connection closed");
+            }
+            let ready = self.transport.socket().wait_readable(wait_ms).map_err(|_| {
+                self.closed = true;
+                "WebSocket: connection lost"
+            })?;
+            if !ready {
+                return Ok(None);
+            }
+            self.fill()?;
+        }
+    }
+
+    fn handle_frame(&mut self, frame: Frame) -> Result<Option<Message>, &'static str> {
+        match frame.opcode {
+            OP_PING => {
+                self.send_frame(OP_PONG, &frame.payload)?;
+                Ok(None)
+            }
+            OP_PONG => Ok(None),
+            OP_CLOSE => {
+                let code = frame.payload.get(..2).map(|b| u16::from_be_bytes([b[0], b[1]]));
+                if !self.closed {
+                    self.closed = true;
+                    let _ = self.send_frame(OP_CLOSE, &frame.payload[..frame.payload.len().min(2)]);
+                }
+                self.transport.close();
+                Ok(Some(Message::Close(code)))
+            }
+            OP_TEXT | OP_BINARY if self.partial.is_none() => {
+                if frame.fin {
+                    return Ok(Some(message(frame.opcode, frame.payload)));
+                }
+                self.partial = Some((frame.opcode, frame.payload));
+                Ok(None)
+            }
+            OP_CONTINUATION => {
+                let (opcode, mut data) = self.partial.take().ok_or("WebSocket: unexpected continuation")?;
+                if data.len() + frame.payload.len() > MAX_MESSAGE_SIZE {
+                    return Err("WebSocket: message too large");
+                }
+                data.extend_from_slice(&frame.payload);
+                if frame.fin {
+                    return Ok(Some(message(opcode, data)));
+                }
+                self.partial = Some((opcode, data));
+                Ok(None)
+            }
+            _ => Err("WebSocket: unexpected frame"),
+        }
+    }
+
+    /// Read whatever the server has sent into `pending`
+    fn fill(&mut self) -> Result<(), &'static str> {
+        let mut buf = vec![0u8; READ_CHUNK];
+        match self.transport.read(&mut buf) {
+            Ok(0) | Err(TlsError::ConnectionClosed) => {
+                self.closed = true;
+                Err("WebSocket: connection closed by server")
+            }
+            Ok(n) => {
+                self.pending.extend_from_slice(&buf[..n]);
+                Ok(())
+            }
+            Err(TlsError::Timeout) => Err("WebSocket: receive timeout"),
+            Err(_) => Err("WebSocket: receive failed"),
+        }
+    }
+
+    /// Send a normal close and shut the connection down
+    pub fn close(mut self) {
+        if !self.closed {
+            self.closed = true;
+            let _ = self.send_frame(OP_CLOSE, &CLOSE_NORMAL.to_be_bytes());
+        }
+        self.transport.close();
+    }
+}
+
+fn message(opcode: u8, data: Vec<u8>) -> Message {
+    if opcode == OP_TEXT {
+        Message::Text(data)
+    } else {
+        Message::Binary(data)
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// CONNECTION TABLE
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Connections userspace programs can hold at once
+const MAX_CONNECTIONS: usize = 8;
+
+struct Slot {
+    /// Hart running the program that opened it
+    owner: usize,
+    /// None while a syscall is using the connection
+    ws: Option<WebSocket>,
+}
+
+static CONNECTIONS: Spinlock<[Option<Slot>; MAX_CONNECTIONS]> =
+    Spinlock::new([const { None }; MAX_CONNECTIONS]);
+
+/// Store a connection for the program on `hart`, returning its handle
+pub fn open(ws: WebSocket, hart: usize) -> Option<usize> {
+    let mut table = CONNECTIONS.lock();
+    let id = table.iter().position(|slot| slot.is_none())?;
+    table[id] = Some(Slot { owner: hart, ws: Some(ws) });
+    Some(id)
+}
+
+/// Run `f` on connection `id` if `hart` owns it. The table is not locked
+/// meanwhile, so `f` may block on the network.
+pub fn with_connection<R>(id: usize, hart: usize, f: impl FnOnce(&mut WebSocket) -> R) -> Option<R> {
+    let mut ws = {
+        let mut table = CONNECTIONS.lock();
+        let slot = table.get_mut(id)?.as_mut().filter(|slot| slot.owner == hart)?;
+        slot.ws.take()?
+    };
+    let result = f(&mut ws);
+    if let Some(Some(slot)) = CONNECTIONS.lock().get_mut(id) {
+        slot.ws = Some(ws);
+    }
+    Some(result)
+}
+
+/// Close connection `id` if `hart` owns it
+pub fn close(id: usize, hart: usize) -> bool {
+    let ws = {
+        let mut table = CONNECTIONS.lock();
+        match table.get_mut(id) {
+            Some(entry) if entry.as_ref().is_some_and(|slot| slot.owner == hart && slot.ws.is_some()) => {
+                entry.take().and_then(|slot| slot.ws)
+            }
+            _ => None,
+        }
+    };
+    match ws {
+        Some(ws) => {
+            ws.close();
+            true
+        }
+        None => false,
+    }
+}
+
+/// Close every connection the program on `hart` left open (called when it exits)
+pub fn close_owned(hart: usize) {
+    for id in 0..MAX_CONNECTIONS {
+        close(id, hart);
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// HANDSHAKE HELPERS
+// ═══════════════════════════════════════════════════════════════════════════════
+
+fn base64_encode(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - i * 6)) as usize & 0x3F] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// SHA-1, needed only for `Sec-WebSocket-Accept`
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+
+    for block in message.chunks(64) {
+        let mut w = [0u32; 80];
+        for (i, word) in block.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, &word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A827999),
+                20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+            let temp = a.rotate_left(5).wrapping_add(f).wrapping_add(e).wrapping_add(k).wrapping_add(word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+        for (state, value) in h.iter_mut().zip([a, b, c, d, e]) {
+            *state = state.wrapping_add(value);
+        }
+    }
+
+    let mut digest = [0u8; 20];
+    for (chunk, word) in digest.chunks_mut(4).zip(h) {
+        chunk.copy_from_slice(&word.to_be_bytes());
+    }
+    digest
+}
@@ -19,4 +19,10 @@ pub(crate) const SCHED_DIAG_CAN_SCHEDULE: usize = SCHED_DIAG_BASE + 0x30;   // u
 pub(crate) const SCHED_DIAG_REQUEUE_OK: usize = SCHED_DIAG_BASE + 0x34;     // u32
 pub(crate) const SCHED_DIAG_QUEUE_DEPTH: usize = SCHED_DIAG_BASE + 0x38;    // u32
 
+// Hibernate device (emulator-assisted memory snapshot, see hibernate.rs)
+pub(crate) const HIBERNATE_BASE: usize = 0x0011_2000;
+pub(crate) const HIBERNATE_CTRL: usize = HIBERNATE_BASE + 0x00;    // u32 (write 1 = snapshot)
+pub(crate) const HIBERNATE_STATUS: usize = HIBERNATE_BASE + 0x04;  // u32 (0=absent, 1=ready, 2=saved, 3=resumed, 4=no space)
+pub(crate) const HIBERNATE_SECTOR: usize = HIBERNATE_BASE + 0x08;  // u64 (image header sector)
+
 pub(crate) const CLINT_MTIME: usize = 0x0200_BFF8;
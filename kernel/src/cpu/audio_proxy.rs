@@ -20,20 +20,23 @@
 
 use alloc::vec::Vec;
 
-use crate::cpu::io_router::{DeviceType, IoOp, IoRequest, IoResult, request_io};
+use crate::cpu::io_router::{self, DeviceType};
 use crate::platform::d1_audio;
 
 // Timeout for I/O requests (5 seconds - audio operations are fast)
 const IO_TIMEOUT_MS: u64 = 5000;
 
 // ═══════════════════════════════════════════════════════════════════════════════
-// Helper: Submit I/O request to Hart 0
+// Helper: Run on Hart 0
 // ═══════════════════════════════════════════════════════════════════════════════
 
-/// Submit an I/O request and wait for the result (blocking).
-fn request_io_blocking(operation: IoOp) -> IoResult {
-    let request = IoRequest::new(DeviceType::Audio, operation);
-    request_io(request, IO_TIMEOUT_MS)
+/// Run `f` on Hart 0 (directly if we already are Hart 0) and wait for the result.
+fn on_hart0<R, F>(f: F) -> Result<R, &'static str>
+where
+    R: Send + 'static,
+    F: FnOnce() -> R + Send + 'static,
+{
+    io_router::call(DeviceType::Audio, IO_TIMEOUT_MS, f)
 }
 
 // ═══════════════════════════════════════════════════════════════════════════════
@@ -52,16 +55,7 @@ fn request_io_blocking(operation: IoOp) -> IoResult {
 /// Returns `true` if the sample was written, `false` if the buffer was full
 #[inline]
 pub fn write_sample(sample: u32) -> bool {
-    let hart_id = crate::get_hart_id();
-    
-    if hart_id == 0 {
-        d1_audio::write_sample(sample)
-    } else {
-        match request_io_blocking(IoOp::AudioWriteSample { sample }) {
-            IoResult::Ok(data) => data.first() == Some(&1),
-            IoResult::Err(_) => false,
-        }
-    }
+    on_hart0(move || d1_audio::write_sample(sample)).unwrap_or(false)
 }
 
 /// Write stereo samples from separate left/right i16 channels.
@@ -73,19 +67,21 @@ pub fn write_stereo(left: i16, right: i16) -> bool {
     write_sample(sample)
 }
 
+/// Write a batch of samples in a single Hart 0 round trip.
+///
+/// Returns how many samples from the front of `samples` fit in the FIFO
+#[inline]
+pub fn write_samples(samples: Vec<u32>) -> usize {
+    on_hart0(move || d1_audio::write_samples(&samples)).unwrap_or(0)
+}
+
 /// Enable or disable audio playback.
 ///
 /// On Hart 0: Direct access via d1_audio
 /// On secondary harts: Delegates to Hart 0 via io_router
 #[inline]
 pub fn set_enabled(enabled: bool) {
-    let hart_id = crate::get_hart_id();
-    
-    if hart_id == 0 {
-        d1_audio::set_enabled(enabled);
-    } else {
-        let _ = request_io_blocking(IoOp::AudioSetEnabled { enabled });
-    }
+    let _ = on_hart0(move || d1_audio::set_enabled(enabled));
 }
 
 /// Set the sample rate in Hz (e.g., 44100, 48000).
@@ -94,13 +90,7 @@ pub fn set_enabled(enabled: bool) {
 /// On secondary harts: Delegates to Hart 0 via io_router
 #[inline]
 pub fn set_sample_rate(rate: u32) {
-    let hart_id = crate::get_hart_id();
-    
-    if hart_id == 0 {
-        d1_audio::set_sample_rate(rate);
-    } else {
-        let _ = request_io_blocking(IoOp::AudioSetSampleRate { rate });
-    }
+    let _ = on_hart0(move || d1_audio::set_sample_rate(rate));
 }
 
 /// Get the current buffer fill level (number of samples in buffer).
@@ -109,18 +99,7 @@ pub fn set_sample_rate(rate: u32) {
 /// On secondary harts: Delegates to Hart 0 via io_router
 #[inline]
 pub fn buffer_level() -> u32 {
-    let hart_id = crate::get_hart_id();
-    
-    if hart_id == 0 {
-        d1_audio::buffer_level()
-    } else {
-        match request_io_blocking(IoOp::AudioGetBufferLevel) {
-            IoResult::Ok(data) if data.len() >= 4 => {
-                u32::from_le_bytes([data[0], data[1], data[2], data[3]])
-            }
-            _ => 0,
-        }
-    }
+    on_hart0(d1_audio::buffer_level).unwrap_or(0)
 }
 
 /// Check if the audio buffer is full.
@@ -129,16 +108,8 @@ pub fn buffer_level() -> u32 {
 /// On secondary harts: Delegates to Hart 0 via io_router
 #[inline]
 pub fn is_buffer_full() -> bool {
-    let hart_id = crate::get_hart_id();
-    
-    if hart_id == 0 {
-        d1_audio::is_buffer_full()
-    } else {
-        match request_io_blocking(IoOp::AudioIsBufferFull) {
-            IoResult::Ok(data) => data.first() == Some(&1),
-            IoResult::Err(_) => true, // Assume full on error (safer)
-        }
-    }
+    // Assume full on error (safer)
+    on_hart0(d1_audio::is_buffer_full).unwrap_or(true)
 }
 
 /// Check if the audio buffer is empty.
@@ -147,16 +118,8 @@ pub fn is_buffer_full() -> bool {
 /// On secondary harts: Delegates to Hart 0 via io_router
 #[inline]
 pub fn is_buffer_empty() -> bool {
-    let hart_id = crate::get_hart_id();
-    
-    if hart_id == 0 {
-        d1_audio::is_buffer_empty()
-    } else {
-        match request_io_blocking(IoOp::AudioIsBufferEmpty) {
-            IoResult::Ok(data) => data.first() == Some(&1),
-            IoResult::Err(_) => true, // Assume empty on error
-        }
-    }
+    // Assume empty on error
+    on_hart0(d1_audio::is_buffer_empty).unwrap_or(true)
 }
 
 /// Check if audio is initialized.
@@ -165,14 +128,5 @@ pub fn is_buffer_empty() -> bool {
 /// On secondary harts: Delegates to Hart 0 via io_router
 #[inline]
 pub fn is_initialized() -> bool {
-    let hart_id = crate::get_hart_id();
-    
-    if hart_id == 0 {
-        d1_audio::is_initialized()
-    } else {
-        match request_io_blocking(IoOp::Status) {
-            IoResult::Ok(data) => data == b"online",
-            IoResult::Err(_) => false,
-        }
-    }
+    on_hart0(d1_audio::is_initialized).unwrap_or(false)
 }
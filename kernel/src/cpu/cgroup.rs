@@ -0,0 +1,227 @@
+//! Resource Control Groups
+//!
+//! Services declared in init belong to a control group (`define`, then
+//! `join`) that bounds what its members take from everyone else together:
+//!
+//! - CPU shares: the group as a whole gets the weight of one nice-0
+//!   process scaled by `shares / NICE_0_WEIGHT`. Each member's own weight
+//!   is scaled the same way and divided among the members that were busy
+//!   at the last sample (`sample`, run with the CPU usage sampling), so a
+//!   group with 512 shares gets about half of what a nice-0 process gets
+//!   next to it however many of its members are spinning.
+//! - Memory cap: heap bytes allocated by the members are charged to the
+//!   group as well as to each process (see the allocator's per-process
+//!   accounting). An allocation that would take the group past its cap
+//!   fails, whichever member makes it.
+//!
+//! Processes in no group (ROOT) are scheduled and accounted as before.
+//! Spawned children join their parent's group, and a restarted daemon
+//! stays in the group it was in.
+//!
+//! The counters live in fixed slots updated with atomics only, so the
+//! allocator and the scheduler's charging path read them without locking.
+//! Only the names sit behind a lock.
+
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU32, AtomicU64, AtomicUsize, Ordering};
+
+use crate::cpu::process::{Pid, Process, ProcessState, NICE_0_WEIGHT, PROCESS_TABLE};
+use crate::Spinlock;
+
+/// Index of a group in the table
+pub type GroupId = usize;
+
+/// Processes that belong to no group
+pub const ROOT: GroupId = 0;
+
+/// Groups that can be defined, ROOT included
+pub const MAX_GROUPS: usize = 8;
+
+/// Bounds on CPU shares (NICE_0_WEIGHT = one nice-0 process)
+const MIN_SHARES: u64 = 2;
+const MAX_SHARES: u64 = 262_144;
+
+/// Recent CPU usage (permille of a hart) from which a member counts as
+/// busy and takes its part of the group's shares
+const BUSY_PERMILLE: u32 = 50;
+
+/// Counters of one group
+struct Group {
+    /// CPU weight of the whole group
+    shares: AtomicU64,
+    /// Members busy at the last sample
+    busy: AtomicUsize,
+    /// Live members at the last sample
+    members: AtomicUsize,
+    /// Recent CPU usage of all members (permille of a hart)
+    cpu_usage: AtomicU32,
+    /// Heap bytes allocated by members and not freed
+    mem_bytes: AtomicUsize,
+    /// Cap on `mem_bytes` (0 = none)
+    mem_limit: AtomicUsize,
+}
+
+impl Group {
+    const fn new() -> Self {
+        Self {
+            shares: AtomicU64::new(NICE_0_WEIGHT),
+            busy: AtomicUsize::new(0),
+            members: AtomicUsize::new(0),
+            cpu_usage: AtomicU32::new(0),
+            mem_bytes: AtomicUsize::new(0),
+            mem_limit: AtomicUsize::new(0),
+        }
+    }
+}
+
+static GROUPS: [Group; MAX_GROUPS] = [const { Group::new() }; MAX_GROUPS];
+
+/// Group names ("" = free slot)
+static NAMES: Spinlock<[&'static str; MAX_GROUPS]> = Spinlock::new({
+    let mut names = [""; MAX_GROUPS];
+    names[ROOT] = "root";
+    names
+});
+
+/// Snapshot of a group for reporting
+#[derive(Clone)]
+pub struct GroupInfo {
+    pub id: GroupId,
+    pub name: &'static str,
+    pub shares: u64,
+    pub members: usize,
+    pub busy: usize,
+    /// Recent CPU usage of all members (permille of a hart)
+    pub cpu_usage: u32,
+    pub mem_bytes: usize,
+    pub mem_limit: Option<usize>,
+}
+
+/// Define group `name` with `shares` of CPU weight and an optional heap cap
+/// in bytes, or change them if it exists. Returns its id.
+pub fn define(name: &'static str, shares: u64, mem_limit: Option<usize>) -> Result<GroupId, &'static str> {
+    if name.is_empty() {
+        return Err("Invalid group name");
+    }
+    let mut names = NAMES.lock();
+    let id = match names.iter().position(|&n| n == name) {
+        Some(id) => id,
+        None => {
+            let id = names.iter().position(|n| n.is_empty()).ok_or("Too many control groups")?;
+            names[id] = name;
+            id
+        }
+    };
+    if id == ROOT {
+        return Err("The root group cannot be changed");
+    }
+    let group = &GROUPS[id];
+    group.shares.store(shares.clamp(MIN_SHARES, MAX_SHARES), Ordering::Relaxed);
+    group.mem_limit.store(mem_limit.map_or(0, |bytes| bytes.max(1)), Ordering::Relaxed);
+    Ok(id)
+}
+
+/// Id of group `name`
+pub fn find(name: &str) -> Option<GroupId> {
+    NAMES.lock().iter().position(|&n| !n.is_empty() && n == name)
+}
+
+/// Name of group `id`
+pub fn name(id: GroupId) -> Option<&'static str> {
+    NAMES.lock().get(id).copied().filter(|n| !n.is_empty())
+}
+
+/// Move process `pid` into group `id`; the heap it holds moves with it
+pub fn join(pid: Pid, id: GroupId) -> Result<(), &'static str> {
+    name(id).ok_or("No such group")?;
+    let process = PROCESS_TABLE.get(pid).ok_or("No such process")?;
+    process.set_cgroup(id);
+    crate::allocator::set_heap_group(pid, id)
+}
+
+/// Weight a member of group `id` is charged with, from its own `weight`
+pub fn scale_weight(id: GroupId, weight: u64) -> u64 {
+    match GROUPS.get(id) {
+        Some(group) if id != ROOT => {
+            let busy = group.busy.load(Ordering::Relaxed).max(1) as u64;
+            (weight * group.shares.load(Ordering::Relaxed) / NICE_0_WEIGHT / busy).max(1)
+        }
+        _ => weight,
+    }
+}
+
+/// Charge `size` heap bytes to group `id`; false if it would go past the cap
+pub fn charge_memory(id: GroupId, size: usize) -> bool {
+    let Some(group) = GROUPS.get(id).filter(|_| id != ROOT) else {
+        return true;
+    };
+    let limit = group.mem_limit.load(Ordering::Relaxed);
+    group
+        .mem_bytes
+        .fetch_update(Ordering::AcqRel, Ordering::Acquire, |bytes| {
+            let total = bytes.checked_add(size)?;
+            (limit == 0 || total <= limit).then_some(total)
+        })
+        .is_ok()
+}
+
+/// Give `size` freed heap bytes back to group `id`
+pub fn credit_memory(id: GroupId, size: usize) {
+    if let Some(group) = GROUPS.get(id).filter(|_| id != ROOT) {
+        let _ = group.mem_bytes.fetch_update(Ordering::AcqRel, Ordering::Acquire, |bytes| Some(bytes.saturating_sub(size)));
+    }
+}
+
+/// Move `size` heap bytes from group `from` to group `to`, cap or not
+pub fn move_memory(from: GroupId, to: GroupId, size: usize) {
+    credit_memory(from, size);
+    if let Some(group) = GROUPS.get(to).filter(|_| to != ROOT) {
+        group.mem_bytes.fetch_add(size, Ordering::AcqRel);
+    }
+}
+
+/// Count the busy and live members of every group and add up their CPU
+/// usage; `processes` have just had their usage sampled
+pub fn sample(processes: &[Arc<Process>]) {
+    let mut busy = [0usize; MAX_GROUPS];
+    let mut members = [0usize; MAX_GROUPS];
+    let mut usage = [0u32; MAX_GROUPS];
+    for process in processes.iter().filter(|p| p.state() != ProcessState::Zombie) {
+        let id = process.cgroup().min(MAX_GROUPS - 1);
+        members[id] += 1;
+        usage[id] = usage[id].saturating_add(process.cpu_usage());
+        if process.cpu_usage() >= BUSY_PERMILLE {
+            busy[id] += 1;
+        }
+    }
+    for (id, group) in GROUPS.iter().enumerate() {
+        group.busy.store(busy[id], Ordering::Relaxed);
+        group.members.store(members[id], Ordering::Relaxed);
+        group.cpu_usage.store(usage[id], Ordering::Relaxed);
+    }
+}
+
+/// Every defined group, ROOT first
+pub fn list() -> Vec<GroupInfo> {
+    let names = *NAMES.lock();
+    names
+        .iter()
+        .enumerate()
+        .filter(|(_, name)| !name.is_empty())
+        .map(|(id, &name)| {
+            let group = &GROUPS[id];
+            let mem_limit = group.mem_limit.load(Ordering::Relaxed);
+            GroupInfo {
+                id,
+                name,
+                shares: group.shares.load(Ordering::Relaxed),
+                members: group.members.load(Ordering::Relaxed),
+                busy: group.busy.load(Ordering::Relaxed),
+                cpu_usage: group.cpu_usage.load(Ordering::Relaxed),
+                mem_bytes: group.mem_bytes.load(Ordering::Relaxed),
+                mem_limit: (mem_limit != 0).then_some(mem_limit),
+            }
+        })
+        .collect()
+}
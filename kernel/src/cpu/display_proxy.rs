@@ -16,9 +16,7 @@
 //! display_proxy::flush();
 //! ```
 
-use alloc::vec::Vec;
-
-use crate::cpu::io_router::{DeviceType, IoOp, IoRequest, IoResult, request_io};
+use crate::cpu::io_router::{self, DeviceType};
 use crate::platform::d1_touch::InputEvent;
 use crate::platform::{d1_display, d1_touch};
 
@@ -26,13 +24,16 @@ use crate::platform::{d1_display, d1_touch};
 const IO_TIMEOUT_MS: u64 = 5000;
 
 // ═══════════════════════════════════════════════════════════════════════════════
-// Helper: Submit I/O request to Hart 0
+// Helper: Run on Hart 0
 // ═══════════════════════════════════════════════════════════════════════════════
 
-/// Submit an I/O request and wait for the result (blocking).
-fn request_io_blocking(operation: IoOp) -> IoResult {
-    let request = IoRequest::new(DeviceType::Display, operation);
-    request_io(request, IO_TIMEOUT_MS)
+/// Run `f` on Hart 0 (directly if we already are Hart 0) and wait for the result.
+fn on_hart0<R, F>(f: F) -> Result<R, &'static str>
+where
+    R: Send + 'static,
+    F: FnOnce() -> R + Send + 'static,
+{
+    io_router::call(DeviceType::Display, IO_TIMEOUT_MS, f)
 }
 
 // ═══════════════════════════════════════════════════════════════════════════════
@@ -45,13 +46,7 @@ fn request_io_blocking(operation: IoOp) -> IoResult {
 /// On secondary harts: Delegates to Hart 0 via io_router
 #[inline]
 pub fn flush() {
-    let hart_id = crate::get_hart_id();
-    
-    if hart_id == 0 {
-        d1_display::flush();
-    } else {
-        let _ = request_io_blocking(IoOp::DisplayFlush);
-    }
+    let _ = on_hart0(d1_display::flush);
 }
 
 /// Clear the display to black.
@@ -60,13 +55,7 @@ pub fn flush() {
 /// On secondary harts: Delegates to Hart 0 via io_router
 #[inline]
 pub fn clear_display() {
-    let hart_id = crate::get_hart_id();
-    
-    if hart_id == 0 {
-        d1_display::clear_display();
-    } else {
-        let _ = request_io_blocking(IoOp::DisplayClear);
-    }
+    let _ = on_hart0(d1_display::clear_display);
 }
 
 /// Mark entire screen as dirty.
@@ -75,13 +64,7 @@ pub fn clear_display() {
 /// On secondary harts: Delegates to Hart 0 via io_router
 #[inline]
 pub fn mark_all_dirty() {
-    let hart_id = crate::get_hart_id();
-    
-    if hart_id == 0 {
-        d1_display::mark_all_dirty();
-    } else {
-        let _ = request_io_blocking(IoOp::DisplayMarkAllDirty);
-    }
+    let _ = on_hart0(d1_display::mark_all_dirty);
 }
 
 /// Check if display is available.
@@ -90,16 +73,21 @@ pub fn mark_all_dirty() {
 /// On secondary harts: Delegates to Hart 0 via io_router
 #[inline]
 pub fn is_available() -> bool {
-    let hart_id = crate::get_hart_id();
-    
-    if hart_id == 0 {
-        d1_display::is_available()
-    } else {
-        match request_io_blocking(IoOp::DisplayIsAvailable) {
-            IoResult::Ok(data) => data.first() == Some(&1),
-            IoResult::Err(_) => false,
-        }
-    }
+    on_hart0(d1_display::is_available).unwrap_or(false)
+}
+
+/// Set brightness, contrast and gamma (applied when frames are presented).
+///
+/// On Hart 0: Direct access via d1_display
+/// On secondary harts: Delegates to Hart 0 via io_router
+pub fn set_color_adjust(adjust: d1_display::ColorAdjust) -> Result<(), &'static str> {
+    on_hart0(move || d1_display::set_color_adjust(adjust))?
+}
+
+/// Current brightness, contrast and gamma.
+#[inline]
+pub fn color_adjust() -> d1_display::ColorAdjust {
+    d1_display::color_adjust()
 }
 
 // ═══════════════════════════════════════════════════════════════════════════════
@@ -112,13 +100,7 @@ pub fn is_available() -> bool {
 /// On secondary harts: Delegates to Hart 0 via io_router
 #[inline]
 pub fn touch_poll() {
-    let hart_id = crate::get_hart_id();
-    
-    if hart_id == 0 {
-        d1_touch::poll();
-    } else {
-        let _ = request_io_blocking(IoOp::TouchPoll);
-    }
+    let _ = on_hart0(d1_touch::poll);
 }
 
 /// Get the next touch event from the queue.
@@ -129,23 +111,7 @@ pub fn touch_poll() {
 /// Returns `None` if no events are pending.
 #[inline]
 pub fn touch_next_event() -> Option<InputEvent> {
-    let hart_id = crate::get_hart_id();
-    
-    if hart_id == 0 {
-        d1_touch::next_event()
-    } else {
-        match request_io_blocking(IoOp::TouchNextEvent) {
-            IoResult::Ok(data) if data.len() == 8 => {
-                // Deserialize: [type:2][code:2][value:4]
-                Some(InputEvent {
-                    event_type: u16::from_le_bytes([data[0], data[1]]),
-                    code: u16::from_le_bytes([data[2], data[3]]),
-                    value: i32::from_le_bytes([data[4], data[5], data[6], data[7]]),
-                })
-            }
-            _ => None, // Empty response or error = no event
-        }
-    }
+    on_hart0(d1_touch::next_event).ok().flatten()
 }
 
 /// Check if there are pending touch events.
@@ -154,14 +120,5 @@ pub fn touch_next_event() -> Option<InputEvent> {
 /// On secondary harts: Delegates to Hart 0 via io_router
 #[inline]
 pub fn touch_has_events() -> bool {
-    let hart_id = crate::get_hart_id();
-    
-    if hart_id == 0 {
-        d1_touch::has_events()
-    } else {
-        match request_io_blocking(IoOp::TouchHasEvents) {
-            IoResult::Ok(data) => data.first() == Some(&1),
-            IoResult::Err(_) => false,
-        }
-    }
+    on_hart0(d1_touch::has_events).unwrap_or(false)
 }
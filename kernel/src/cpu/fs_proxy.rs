@@ -1,8 +1,16 @@
 //! Filesystem Proxy - Hart-aware filesystem access
 //!
 //! This module provides transparent filesystem access that works on any hart.
-//! On Hart 0: Direct access via VFS_STATE
-//! On secondary harts: Delegates to Hart 0 via io_router
+//! Operations run on the calling hart, straight into the VFS (or the legacy
+//! FS_STATE), for every filesystem that works from any hart. Only mounts
+//! whose device secondary harts cannot reach (9P) are delegated to Hart 0
+//! via io_router.
+//!
+//! SFS takes its own per-file and allocator locks (see `lock::state::fs`)
+//! and only read-locks VFS_STATE, FS_STATE and BLK_DEV, so harts use it at
+//! the same time. Its block transfers still reach the card through Hart 0
+//! (see `platform::d1_mmc`), one sector at a time; everything else - path
+//! lookup, cache hits, copying - runs in parallel.
 //!
 //! # Example
 //! ```
@@ -17,93 +25,38 @@
 use alloc::string::String;
 use alloc::vec::Vec;
 
-use crate::cpu::io_router::{
-    DeviceType, IoOp, IoRequest, IoResult, RequestId,
-    request_io, request_io_async, poll_io, is_io_complete,
-};
+use crate::cpu::io_router::{self, DeviceType};
+use crate::lock::state::fs::Format;
 use crate::lock::utils::{VFS_STATE, FS_STATE, BLK_DEV};
 
-// Timeout for I/O requests (10 seconds)
+pub use crate::cpu::io_router::IoFuture;
+
+// Timeout for I/O requests (30 seconds)
 const IO_TIMEOUT_MS: u64 = 30000;
 
 // ═══════════════════════════════════════════════════════════════════════════════
-// Async I/O Future Type
+// Helper: Run on Hart 0
 // ═══════════════════════════════════════════════════════════════════════════════
 
-/// A future representing a pending I/O operation.
-/// 
-/// Use `poll()` to check for completion, or `is_complete()` to check without
-/// consuming the result.
-/// 
-/// # Example
-/// ```
-/// let future = fs_read_async("/usr/bin/hello");
-/// // Do other work...
-/// if future.is_complete() {
-///     let result = future.poll();
-/// }
-/// ```
-pub struct IoFuture {
-    request_id: RequestId,
+/// Run `f` on Hart 0 (directly if we already are Hart 0) and wait for the result.
+fn on_hart0<R, F>(f: F) -> Result<R, &'static str>
+where
+    R: Send + 'static,
+    F: FnOnce() -> R + Send + 'static,
+{
+    io_router::call(DeviceType::Mmc, IO_TIMEOUT_MS, f)
 }
 
-impl IoFuture {
-    /// Create a new I/O future for the given request ID
-    pub fn new(request_id: RequestId) -> Self {
-        Self { request_id }
-    }
-    
-    /// Get the request ID for this future
-    pub fn request_id(&self) -> RequestId {
-        self.request_id
-    }
-    
-    /// Poll for completion.
-    /// Returns `Some(result)` if complete, `None` if still pending.
-    /// 
-    /// Note: This consumes the result - subsequent calls will return None.
-    pub fn poll(&self) -> Option<IoResult> {
-        poll_io(self.request_id)
-    }
-    
-    /// Check if the I/O operation is complete without consuming the result.
-    pub fn is_complete(&self) -> bool {
-        is_io_complete(self.request_id)
-    }
-    
-    /// Block until the I/O operation completes (with timeout).
-    /// 
-    /// This is a convenience method for code transitioning from blocking I/O.
-    pub fn wait(&self, timeout_ms: u64) -> IoResult {
-        use core::arch::asm;
-        let start = crate::get_time_ms();
-        
-        loop {
-            if let Some(result) = self.poll() {
-                return result;
-            }
-            
-            if timeout_ms > 0 {
-                let elapsed = crate::get_time_ms() - start;
-                if elapsed >= timeout_ms as i64 {
-                    return IoResult::Err("I/O future timeout");
-                }
-            }
-            
-            // Yield CPU
-            unsafe { asm!("wfi", options(nomem, nostack)); }
+/// Whether the filesystems holding `paths` work from this hart (`paths`
+/// empty: every mount). Without a VFS there is only the root SFS, which does.
+fn here(paths: &[&str]) -> bool {
+    VFS_STATE.read().as_ref().is_none_or(|vfs| {
+        if paths.is_empty() {
+            vfs.mounts_any_hart()
+        } else {
+            paths.iter().all(|path| vfs.any_hart(path))
         }
-    }
-}
-
-// ═══════════════════════════════════════════════════════════════════════════════
-// Helper: Submit I/O request to Hart 0
-// ═══════════════════════════════════════════════════════════════════════════════
-
-/// Submit an I/O request and wait for the result (blocking).
-fn request_io_blocking(device: DeviceType, operation: IoOp) -> IoResult {
-    let request = IoRequest::new(device, operation);
-    request_io(request, IO_TIMEOUT_MS)
+    })
 }
 
 // ═══════════════════════════════════════════════════════════════════════════════
@@ -115,17 +68,17 @@ fn read_with_vfs_or_legacy(path: &str) -> Option<Vec<u8>> {
     use crate::device::uart::write_str;
     
     // Try VFS first
-    let mut vfs = VFS_STATE.write();
-    if let Some(vfs) = vfs.as_mut() {
+    let vfs = VFS_STATE.read();
+    if let Some(vfs) = vfs.as_ref() {
         let result = vfs.read_file(path);
         return result;
     }
     drop(vfs);
     
     // Fall back to legacy FS_STATE
-    let mut fs = FS_STATE.write();
-    let mut blk = BLK_DEV.write();
-    if let (Some(fs), Some(dev)) = (fs.as_mut(), blk.as_mut()) {
+    let fs = FS_STATE.read();
+    let blk = BLK_DEV.read();
+    if let (Some(fs), Some(dev)) = (fs.as_ref(), blk.as_ref()) {
         let result = fs.read_file(dev, path);
         result
     } else {
@@ -134,65 +87,29 @@ fn read_with_vfs_or_legacy(path: &str) -> Option<Vec<u8>> {
 }
 
 /// Write using VFS if available, otherwise fall back to legacy FS_STATE
-/// Uses non-blocking try_write to avoid deadlocks across harts
 fn write_with_vfs_or_legacy(path: &str, data: &[u8]) -> Result<(), &'static str> {
-    use crate::device::uart::{write_str, write_line};
-    use core::arch::asm;
-    
-    let start = crate::get_time_ms();
-    let timeout_ms = 5000; // 5 second timeout
+    // Try VFS first
+    let vfs = VFS_STATE.read();
+    if let Some(vfs) = vfs.as_ref() {
+        return vfs.write_file(path, data);
+    }
+    drop(vfs);
     
-    loop {
-        // Try VFS first with non-blocking lock
-        if let Some(mut vfs_guard) = VFS_STATE.try_write() {
-            write_line("Got VFS lock");
-            if let Some(vfs) = vfs_guard.as_mut() {
-                write_line("Calling vfs.write_file...");
-                let result = vfs.write_file(path, data);
-                write_line("vfs.write_file returned");
-                if result.is_err() {
-                    write_str("VFS write_file error for: ");
-                    write_line(path);
-                }
-                return result;
-            }
-            drop(vfs_guard);
-            
-            // VFS not initialized, try legacy FS_STATE
-            if let Some(mut fs_guard) = FS_STATE.try_write() {
-                if let Some(mut blk_guard) = BLK_DEV.try_write() {
-                    if let (Some(fs), Some(dev)) = (fs_guard.as_mut(), blk_guard.as_mut()) {
-                        let result = fs.write_file(dev, path, data);
-                        if result.is_err() {
-                            write_str("Legacy FS write_file error for: ");
-                            write_line(path);
-                        }
-                        return result;
-                    }
-                }
-            }
-            
-            write_line("FS not available for write");
-            return Err("Filesystem not available");
-        }
-        
-        // Check timeout
-        let elapsed = crate::get_time_ms() - start;
-        if elapsed >= timeout_ms as i64 {
-            write_line("fs_write: lock timeout");
-            return Err("Lock timeout");
-        }
-        
-        // Yield CPU briefly
-        unsafe { asm!("wfi", options(nomem, nostack)); }
+    // Fall back to legacy FS_STATE
+    let fs = FS_STATE.read();
+    let blk = BLK_DEV.read();
+    if let (Some(fs), Some(dev)) = (fs.as_ref(), blk.as_ref()) {
+        fs.write_file(dev, path, data)
+    } else {
+        Err("Filesystem not available")
     }
 }
 
 /// List using VFS if available, otherwise fall back to legacy FS_STATE
 fn list_with_vfs_or_legacy(path: &str) -> Vec<FileInfo> {
     // Try VFS first
-    let mut vfs = VFS_STATE.write();
-    if let Some(vfs) = vfs.as_mut() {
+    let vfs = VFS_STATE.read();
+    if let Some(vfs) = vfs.as_ref() {
         return vfs.list_dir(path)
             .into_iter()
             .map(|e| FileInfo {
@@ -205,9 +122,9 @@ fn list_with_vfs_or_legacy(path: &str) -> Vec<FileInfo> {
     drop(vfs);
     
     // Fall back to legacy FS_STATE
-    let mut fs = FS_STATE.write();
-    let mut blk = BLK_DEV.write();
-    if let (Some(fs), Some(dev)) = (fs.as_mut(), blk.as_mut()) {
+    let fs = FS_STATE.read();
+    let blk = BLK_DEV.read();
+    if let (Some(fs), Some(dev)) = (fs.as_ref(), blk.as_ref()) {
         fs.list_dir(dev, path)
             .into_iter()
             .map(|e| FileInfo {
@@ -224,16 +141,16 @@ fn list_with_vfs_or_legacy(path: &str) -> Vec<FileInfo> {
 /// Check exists using VFS if available, otherwise fall back to legacy FS_STATE
 fn exists_with_vfs_or_legacy(path: &str) -> bool {
     // Try VFS first
-    let mut vfs = VFS_STATE.write();
-    if let Some(vfs) = vfs.as_mut() {
+    let vfs = VFS_STATE.read();
+    if let Some(vfs) = vfs.as_ref() {
         return vfs.exists(path);
     }
     drop(vfs);
     
     // Fall back to legacy FS_STATE
-    let mut fs = FS_STATE.write();
-    let mut blk = BLK_DEV.write();
-    if let (Some(fs), Some(dev)) = (fs.as_mut(), blk.as_mut()) {
+    let fs = FS_STATE.read();
+    let blk = BLK_DEV.read();
+    if let (Some(fs), Some(dev)) = (fs.as_ref(), blk.as_ref()) {
         fs.read_file(dev, path).is_some()
     } else {
         false
@@ -243,99 +160,314 @@ fn exists_with_vfs_or_legacy(path: &str) -> bool {
 /// Sync using VFS if available, otherwise fall back to legacy FS_STATE
 fn sync_with_vfs_or_legacy() -> Result<(), &'static str> {
     // Try VFS first
-    let mut vfs = VFS_STATE.write();
-    if let Some(vfs) = vfs.as_mut() {
+    let vfs = VFS_STATE.read();
+    if let Some(vfs) = vfs.as_ref() {
         return vfs.sync().map(|_| ());
     }
     drop(vfs);
     
     // Fall back to legacy FS_STATE
-    let mut fs = FS_STATE.write();
-    let mut blk = BLK_DEV.write();
-    if let (Some(fs), Some(dev)) = (fs.as_mut(), blk.as_mut()) {
+    let fs = FS_STATE.read();
+    let blk = BLK_DEV.read();
+    if let (Some(fs), Some(dev)) = (fs.as_ref(), blk.as_ref()) {
         fs.sync(dev).map(|_| ())
     } else {
         Err("Filesystem not available")
     }
 }
 
+/// Partial write using VFS if available, otherwise fall back to legacy FS_STATE.
+/// `offset` of None appends.
+fn write_at_with_vfs_or_legacy(path: &str, offset: Option<usize>, data: &[u8]) -> Result<usize, &'static str> {
+    // Try VFS first
+    let vfs = VFS_STATE.read();
+    if let Some(vfs) = vfs.as_ref() {
+        return match offset {
+            Some(offset) => vfs.write_at(path, offset, data),
+            None => vfs.append(path, data),
+        };
+    }
+    drop(vfs);
+    
+    // Fall back to legacy FS_STATE
+    let fs = FS_STATE.read();
+    let blk = BLK_DEV.read();
+    if let (Some(fs), Some(dev)) = (fs.as_ref(), blk.as_ref()) {
+        match offset {
+            Some(offset) => fs.write_at(dev, path, offset, data),
+            None => fs.append(dev, path, data),
+        }
+    } else {
+        Err("Filesystem not available")
+    }
+}
+
+/// Partial read using VFS if available, otherwise fall back to legacy FS_STATE
+fn read_at_with_vfs_or_legacy(path: &str, offset: usize, buf: &mut [u8]) -> Result<usize, &'static str> {
+    // Try VFS first
+    let vfs = VFS_STATE.read();
+    if let Some(vfs) = vfs.as_ref() {
+        return vfs.read_at(path, offset, buf);
+    }
+    drop(vfs);
+    
+    // Fall back to legacy FS_STATE
+    let fs = FS_STATE.read();
+    let blk = BLK_DEV.read();
+    if let (Some(fs), Some(dev)) = (fs.as_ref(), blk.as_ref()) {
+        fs.read_at(dev, path, offset, buf)
+    } else {
+        Err("Filesystem not available")
+    }
+}
+
+/// File length using VFS if available, otherwise fall back to legacy FS_STATE
+fn len_with_vfs_or_legacy(path: &str) -> Option<usize> {
+    // Try VFS first
+    let vfs = VFS_STATE.read();
+    if let Some(vfs) = vfs.as_ref() {
+        return vfs.file_len(path);
+    }
+    drop(vfs);
+    
+    // Fall back to legacy FS_STATE
+    let fs = FS_STATE.read();
+    let blk = BLK_DEV.read();
+    if let (Some(fs), Some(dev)) = (fs.as_ref(), blk.as_ref()) {
+        fs.file_len(dev, path)
+    } else {
+        None
+    }
+}
+
+/// Rename using VFS if available, otherwise fall back to legacy FS_STATE
+fn rename_with_vfs_or_legacy(from: &str, to: &str) -> Result<(), &'static str> {
+    // Try VFS first
+    let vfs = VFS_STATE.read();
+    if let Some(vfs) = vfs.as_ref() {
+        return vfs.rename(from, to);
+    }
+    drop(vfs);
+    
+    // Fall back to legacy FS_STATE
+    let fs = FS_STATE.read();
+    let blk = BLK_DEV.read();
+    if let (Some(fs), Some(dev)) = (fs.as_ref(), blk.as_ref()) {
+        fs.rename(dev, from, to)
+    } else {
+        Err("Filesystem not available")
+    }
+}
+
+/// Remove using VFS if available, otherwise fall back to legacy FS_STATE
+fn remove_with_vfs_or_legacy(path: &str) -> Result<(), &'static str> {
+    // Try VFS first
+    let vfs = VFS_STATE.read();
+    if let Some(vfs) = vfs.as_ref() {
+        return vfs.remove(path);
+    }
+    drop(vfs);
+    
+    // Fall back to legacy FS_STATE
+    let fs = FS_STATE.read();
+    let blk = BLK_DEV.read();
+    if let (Some(fs), Some(dev)) = (fs.as_ref(), blk.as_ref()) {
+        fs.remove(dev, path)
+    } else {
+        Err("Filesystem not available")
+    }
+}
+
+/// Check for a directory using VFS if available, otherwise fall back to legacy FS_STATE
+fn is_dir_with_vfs_or_legacy(path: &str) -> bool {
+    // Try VFS first
+    let vfs = VFS_STATE.read();
+    if let Some(vfs) = vfs.as_ref() {
+        return vfs.is_dir(path);
+    }
+    drop(vfs);
+    
+    // Fall back to legacy FS_STATE
+    let fs = FS_STATE.read();
+    let blk = BLK_DEV.read();
+    if let (Some(fs), Some(dev)) = (fs.as_ref(), blk.as_ref()) {
+        fs.is_dir(dev, path)
+    } else {
+        false
+    }
+}
+
+/// Create a directory using VFS if available, otherwise fall back to legacy FS_STATE
+fn mkdir_with_vfs_or_legacy(path: &str) -> Result<(), &'static str> {
+    // Try VFS first
+    let vfs = VFS_STATE.read();
+    if let Some(vfs) = vfs.as_ref() {
+        return vfs.mkdir(path);
+    }
+    drop(vfs);
+    
+    // Fall back to legacy FS_STATE
+    let fs = FS_STATE.read();
+    let blk = BLK_DEV.read();
+    if let (Some(fs), Some(dev)) = (fs.as_ref(), blk.as_ref()) {
+        fs.mkdir(dev, path)
+    } else {
+        Err("Filesystem not available")
+    }
+}
+
+/// Create a symlink using VFS if available, otherwise fall back to legacy FS_STATE
+fn symlink_with_vfs_or_legacy(target: &str, path: &str) -> Result<(), &'static str> {
+    // Try VFS first
+    let vfs = VFS_STATE.read();
+    if let Some(vfs) = vfs.as_ref() {
+        return vfs.symlink(target, path);
+    }
+    drop(vfs);
+    
+    // Fall back to legacy FS_STATE
+    let fs = FS_STATE.read();
+    let blk = BLK_DEV.read();
+    if let (Some(fs), Some(dev)) = (fs.as_ref(), blk.as_ref()) {
+        fs.symlink(dev, target, path)
+    } else {
+        Err("Filesystem not available")
+    }
+}
+
+/// Read a symlink using VFS if available, otherwise fall back to legacy FS_STATE
+fn readlink_with_vfs_or_legacy(path: &str) -> Option<String> {
+    // Try VFS first
+    let vfs = VFS_STATE.read();
+    if let Some(vfs) = vfs.as_ref() {
+        return vfs.readlink(path);
+    }
+    drop(vfs);
+    
+    // Fall back to legacy FS_STATE
+    let fs = FS_STATE.read();
+    let blk = BLK_DEV.read();
+    if let (Some(fs), Some(dev)) = (fs.as_ref(), blk.as_ref()) {
+        fs.readlink(dev, path)
+    } else {
+        None
+    }
+}
+
+/// Find the first symlink in a path using VFS if available, otherwise fall
+/// back to legacy FS_STATE
+fn first_symlink_with_vfs_or_legacy(path: &str, follow_last: bool) -> Option<(String, String)> {
+    // Try VFS first
+    let vfs = VFS_STATE.read();
+    if let Some(vfs) = vfs.as_ref() {
+        return vfs.first_symlink(path, follow_last);
+    }
+    drop(vfs);
+    
+    // Fall back to legacy FS_STATE
+    let fs = FS_STATE.read();
+    let blk = BLK_DEV.read();
+    if let (Some(fs), Some(dev)) = (fs.as_ref(), blk.as_ref()) {
+        fs.first_symlink(dev, path, follow_last)
+    } else {
+        None
+    }
+}
+
 // ═══════════════════════════════════════════════════════════════════════════════
 // Public API: Hart-aware filesystem functions
 // ═══════════════════════════════════════════════════════════════════════════════
 
 /// Read a file from the filesystem.
 /// 
-/// On Hart 0: Direct access via VFS_STATE (or legacy FS_STATE)
-/// On secondary harts: Delegates to Hart 0 via io_router
+/// Runs on the calling hart (on Hart 0 for 9P mounts, see the module docs)
 pub fn fs_read(path: &str) -> Option<Vec<u8>> {
-    let hart_id = crate::get_hart_id();
-    
-    if hart_id == 0 {
+    let data = if here(&[path]) {
         read_with_vfs_or_legacy(path)
     } else {
-        // Delegate to Hart 0 via io_router
-        let op = IoOp::FsRead { path: String::from(path) };
-        let result = request_io_blocking(DeviceType::Mmc, op);
-        
-        match result {
-            IoResult::Ok(data) => Some(data),
-            IoResult::Err(_) => None,
-        }
+        let path = String::from(path);
+        on_hart0(move || read_with_vfs_or_legacy(&path)).ok().flatten()
+    };
+    if let Some(data) = &data {
+        crate::scripting::shell_cmd_io(data.len(), 0);
     }
+    data
 }
 
 /// Write data to a file.
 ///
-/// On Hart 0: Direct access via VFS_STATE (or legacy FS_STATE)
-/// On secondary harts: Delegates to Hart 0 via io_router
-/// (Secondary harts in WASM don't have access to D1 MMC device)
+/// Runs on the calling hart (on Hart 0 for 9P mounts, see the module docs)
 pub fn fs_write(path: &str, data: &[u8]) -> Result<(), &'static str> {
-    use crate::device::uart::{write_str, write_line};
-    
-    let hart_id = crate::get_hart_id();
-    
-    write_str("fs_write on hart ");
-    write_hex(hart_id as u64);
-    write_line("");
-    
-    if hart_id == 0 {
-        write_with_vfs_or_legacy(path, data)
+    crate::scripting::shell_cmd_io(0, data.len());
+    if here(&[path]) {
+        return write_with_vfs_or_legacy(path, data);
+    }
+    let path = String::from(path);
+    let data = data.to_vec();
+    on_hart0(move || write_with_vfs_or_legacy(&path, &data))?
+}
+
+/// Read `len` bytes (at most) at `offset`
+fn read_at(path: &str, offset: usize, len: usize) -> Result<Vec<u8>, &'static str> {
+    // Never allocate more than is left of the file
+    let left = len_with_vfs_or_legacy(path).ok_or("File not found")?.saturating_sub(offset);
+    let mut buf = alloc::vec![0u8; len.min(left)];
+    let n = read_at_with_vfs_or_legacy(path, offset, &mut buf)?;
+    buf.truncate(n);
+    Ok(buf)
+}
+
+/// Read up to `len` bytes from a byte offset without loading the whole file
+/// (an empty result means end of file).
+///
+/// Runs on the calling hart (on Hart 0 for 9P mounts, see the module docs)
+pub fn fs_read_at(path: &str, offset: usize, len: usize) -> Result<Vec<u8>, &'static str> {
+    let data = if here(&[path]) {
+        read_at(path, offset, len)?
     } else {
-        // Delegate to Hart 0 via io_router - secondary harts don't have D1 MMC
-        write_line("fs_write: delegating to hart 0");
-        let op = IoOp::FsWrite { 
-            path: String::from(path), 
-            data: data.to_vec() 
-        };
-        let result = request_io_blocking(DeviceType::Mmc, op);
-        
-        match result {
-            IoResult::Ok(_) => {
-                write_line("fs_write: delegation success");
-                Ok(())
-            }
-            IoResult::Err(e) => {
-                write_str("fs_write: delegation failed - ");
-                write_line(e);
-                Err(e)
-            }
-        }
+        let path = String::from(path);
+        on_hart0(move || read_at(&path, offset, len))??
+    };
+    crate::scripting::shell_cmd_io(data.len(), 0);
+    Ok(data)
+}
+
+/// Length of a file in bytes (None if it does not exist).
+///
+/// Runs on the calling hart (on Hart 0 for 9P mounts, see the module docs)
+pub fn fs_len(path: &str) -> Option<usize> {
+    if here(&[path]) {
+        return len_with_vfs_or_legacy(path);
     }
+    let path = String::from(path);
+    on_hart0(move || len_with_vfs_or_legacy(&path)).ok().flatten()
 }
 
-fn write_hex(val: u64) {
-    use crate::device::uart::write_str;
-    let mut buf = [0u8; 18];
-    buf[0] = b'0';
-    buf[1] = b'x';
-    let hex_chars = b"0123456789abcdef";
-    for i in 0..16 {
-        let nibble = ((val >> (60 - i * 4)) & 0xF) as usize;
-        buf[2 + i] = hex_chars[nibble];
+/// Write data at a byte offset, growing the file if needed.
+/// Returns the new file size.
+///
+/// Runs on the calling hart (on Hart 0 for 9P mounts, see the module docs)
+pub fn fs_write_at(path: &str, offset: usize, data: &[u8]) -> Result<usize, &'static str> {
+    crate::scripting::shell_cmd_io(0, data.len());
+    if here(&[path]) {
+        return write_at_with_vfs_or_legacy(path, Some(offset), data);
     }
-    if let Ok(s) = core::str::from_utf8(&buf[..18]) {
-        write_str(s);
+    let path = String::from(path);
+    let data = data.to_vec();
+    on_hart0(move || write_at_with_vfs_or_legacy(&path, Some(offset), &data))?
+}
+
+/// Append data to a file without rewriting it. Returns the new file size.
+///
+/// Runs on the calling hart (on Hart 0 for 9P mounts, see the module docs)
+pub fn fs_append(path: &str, data: &[u8]) -> Result<usize, &'static str> {
+    crate::scripting::shell_cmd_io(0, data.len());
+    if here(&[path]) {
+        return write_at_with_vfs_or_legacy(path, None, data);
     }
+    let path = String::from(path);
+    let data = data.to_vec();
+    on_hart0(move || write_at_with_vfs_or_legacy(&path, None, &data))?
 }
 
 /// File info returned by fs_list
@@ -348,90 +480,173 @@ pub struct FileInfo {
 
 /// List directory contents.
 ///
-/// On Hart 0: Direct access via VFS_STATE (or legacy FS_STATE)
-/// On secondary harts: Delegates to Hart 0 via io_router
+/// Runs on the calling hart (on Hart 0 for 9P mounts, see the module docs)
 pub fn fs_list(path: &str) -> Vec<FileInfo> {
-    let hart_id = crate::get_hart_id();
-    
-    if hart_id == 0 {
-        list_with_vfs_or_legacy(path)
-    } else {
-        // Delegate to Hart 0
-        let op = IoOp::FsList { path: String::from(path) };
-        let result = request_io_blocking(DeviceType::Mmc, op);
-        
-        match result {
-            IoResult::Ok(data) => {
-                // Parse newline-separated "name:size" entries
-                let text = core::str::from_utf8(&data).unwrap_or("");
-                text.lines()
-                    .filter(|s| !s.is_empty())
-                    .filter_map(|line| {
-                        // Parse "name:size" format
-                        if let Some(colon_pos) = line.rfind(':') {
-                            let name = &line[..colon_pos];
-                            let size_str = &line[colon_pos + 1..];
-                            let size = size_str.parse::<u64>().unwrap_or(0);
-                            Some(FileInfo {
-                                name: String::from(name),
-                                is_dir: name.ends_with('/'),
-                                size,
-                            })
-                        } else {
-                            // No colon - treat whole line as name
-                            Some(FileInfo {
-                                name: String::from(line),
-                                is_dir: line.ends_with('/'),
-                                size: 0,
-                            })
-                        }
-                    })
-                    .collect()
-            }
-            IoResult::Err(_) => Vec::new(),
-        }
+    if here(&[path]) {
+        return list_with_vfs_or_legacy(path);
     }
+    let path = String::from(path);
+    on_hart0(move || list_with_vfs_or_legacy(&path)).unwrap_or_default()
 }
 
 /// Check if a file exists.
 ///
-/// On Hart 0: Direct access via VFS_STATE (or legacy FS_STATE)
-/// On secondary harts: Delegates to Hart 0 via io_router
+/// Runs on the calling hart (on Hart 0 for 9P mounts, see the module docs)
 pub fn fs_exists(path: &str) -> bool {
-    let hart_id = crate::get_hart_id();
-    
-    if hart_id == 0 {
-        exists_with_vfs_or_legacy(path)
-    } else {
-        // Delegate to Hart 0
-        let op = IoOp::FsExists { path: String::from(path) };
-        let result = request_io_blocking(DeviceType::Mmc, op);
-        
-        match result {
-            IoResult::Ok(data) => data.first() == Some(&1),
-            IoResult::Err(_) => false,
-        }
+    if here(&[path]) {
+        return exists_with_vfs_or_legacy(path);
+    }
+    let path = String::from(path);
+    on_hart0(move || exists_with_vfs_or_legacy(&path)).unwrap_or(false)
+}
+
+/// Check if a path is a directory.
+///
+/// Runs on the calling hart (on Hart 0 for 9P mounts, see the module docs)
+pub fn fs_is_dir(path: &str) -> bool {
+    if here(&[path]) {
+        return is_dir_with_vfs_or_legacy(path);
     }
+    let path = String::from(path);
+    on_hart0(move || is_dir_with_vfs_or_legacy(&path)).unwrap_or(false)
+}
+
+/// Create a directory.
+///
+/// Runs on the calling hart (on Hart 0 for 9P mounts, see the module docs)
+pub fn fs_mkdir(path: &str) -> Result<(), &'static str> {
+    if here(&[path]) {
+        return mkdir_with_vfs_or_legacy(path);
+    }
+    let path = String::from(path);
+    on_hart0(move || mkdir_with_vfs_or_legacy(&path))?
 }
 
 /// Sync filesystem to disk.
 ///
-/// On Hart 0: Direct access via VFS_STATE (or legacy FS_STATE)
-/// On secondary harts: Delegates to Hart 0 via io_router
+/// Runs on the calling hart (on Hart 0 for 9P mounts, see the module docs)
 pub fn fs_sync() -> Result<(), &'static str> {
-    let hart_id = crate::get_hart_id();
-    
-    if hart_id == 0 {
-        sync_with_vfs_or_legacy()
-    } else {
-        // Delegate to Hart 0
-        let result = request_io_blocking(DeviceType::Mmc, IoOp::FsSync);
-        
-        match result {
-            IoResult::Ok(_) => Ok(()),
-            IoResult::Err(e) => Err(e),
+    if here(&[]) {
+        return sync_with_vfs_or_legacy();
+    }
+    on_hart0(sync_with_vfs_or_legacy)?
+}
+
+/// Rename (move) a file or directory without copying its data.
+///
+/// Runs on the calling hart (on Hart 0 for 9P mounts, see the module docs)
+pub fn fs_rename(from: &str, to: &str) -> Result<(), &'static str> {
+    if here(&[from, to]) {
+        return rename_with_vfs_or_legacy(from, to);
+    }
+    let from = String::from(from);
+    let to = String::from(to);
+    on_hart0(move || rename_with_vfs_or_legacy(&from, &to))?
+}
+
+/// Remove a file.
+///
+/// Runs on the calling hart (on Hart 0 for 9P mounts, see the module docs)
+pub fn fs_remove(path: &str) -> Result<(), &'static str> {
+    if here(&[path]) {
+        return remove_with_vfs_or_legacy(path);
+    }
+    let path = String::from(path);
+    on_hart0(move || remove_with_vfs_or_legacy(&path))?
+}
+
+/// Migrate the root SFS image to the latest format revision.
+/// Returns the revision before and after.
+///
+/// On Hart 0: Direct access via FS_STATE
+/// On secondary harts: Delegates to Hart 0 via io_router
+pub fn sfs_upgrade() -> Result<(Format, Format), &'static str> {
+    on_hart0(|| {
+        let mut fs = FS_STATE.write();
+        let mut blk = BLK_DEV.write();
+        match (fs.as_mut(), blk.as_mut()) {
+            (Some(fs), Some(dev)) => fs.upgrade(dev).map(|before| (before, fs.format())),
+            _ => Err("Filesystem not available"),
         }
+    })?
+}
+
+/// Create a symbolic link at `path` pointing to `target`.
+///
+/// Runs on the calling hart (on Hart 0 for 9P mounts, see the module docs)
+pub fn fs_symlink(target: &str, path: &str) -> Result<(), &'static str> {
+    if here(&[path]) {
+        return symlink_with_vfs_or_legacy(target, path);
+    }
+    let target = String::from(target);
+    let path = String::from(path);
+    on_hart0(move || symlink_with_vfs_or_legacy(&target, &path))?
+}
+
+/// Read the target of a symbolic link (None if `path` is not a link).
+///
+/// Runs on the calling hart (on Hart 0 for 9P mounts, see the module docs)
+pub fn fs_readlink(path: &str) -> Option<String> {
+    if here(&[path]) {
+        return readlink_with_vfs_or_legacy(path);
+    }
+    let path = String::from(path);
+    on_hart0(move || readlink_with_vfs_or_legacy(&path)).ok().flatten()
+}
+
+/// The first symlink among the components of `path` (the last one only if
+/// `follow_last`): the link's path and its target, looked up in one pass.
+///
+/// Runs on the calling hart (on Hart 0 for 9P mounts, see the module docs)
+pub fn fs_first_symlink(path: &str, follow_last: bool) -> Option<(String, String)> {
+    if here(&[path]) {
+        return first_symlink_with_vfs_or_legacy(path, follow_last);
     }
+    let path = String::from(path);
+    on_hart0(move || first_symlink_with_vfs_or_legacy(&path, follow_last)).ok().flatten()
+}
+
+/// Mount a 9P server reached over TCP at `mount_point`.
+///
+/// Runs on Hart 0, which owns both the VFS and the network device.
+pub fn fs_mount_9p(ip: [u8; 4], port: u16, mount_point: &str) -> Result<(), &'static str> {
+    let mount_point = String::from(mount_point);
+    on_hart0(move || {
+        if VFS_STATE.read().as_ref().map(|vfs| vfs.is_mount_point(&mount_point)).unwrap_or(false) {
+            return Err("Already mounted");
+        }
+        // Connect before taking the VFS lock, so file access isn't held up
+        let ip = smoltcp::wire::Ipv4Address::new(ip[0], ip[1], ip[2], ip[3]);
+        let p9fs = crate::fs::P9FileSystem::connect_tcp(ip, port)?;
+        let mut vfs = VFS_STATE.write();
+        let vfs = vfs.as_mut().ok_or("Filesystem not available")?;
+        vfs.mount(&mount_point, alloc::boxed::Box::new(p9fs));
+        Ok(())
+    })?
+}
+
+/// Unmount the filesystem at `mount_point`.
+///
+/// On Hart 0: Direct access via VFS_STATE
+/// On secondary harts: Delegates to Hart 0 via io_router
+pub fn fs_unmount(mount_point: &str) -> Result<(), &'static str> {
+    let mount_point = String::from(mount_point);
+    on_hart0(move || {
+        let mut vfs = VFS_STATE.write();
+        vfs.as_mut().ok_or("Filesystem not available")?.unmount(&mount_point)
+    })?
+}
+
+/// List mount points.
+///
+/// On Hart 0: Direct access via VFS_STATE
+/// On secondary harts: Delegates to Hart 0 via io_router
+pub fn fs_mounts() -> Vec<String> {
+    on_hart0(|| {
+        VFS_STATE.read().as_ref()
+            .map(|vfs| vfs.list_mounts().into_iter().map(String::from).collect())
+            .unwrap_or_default()
+    }).unwrap_or_default()
 }
 
 // ═══════════════════════════════════════════════════════════════════════════════
@@ -447,58 +662,43 @@ pub fn fs_sync() -> Result<(), &'static str> {
 /// ```
 /// let future = fs_read_async("/usr/bin/hello");
 /// // Do other work while I/O is pending...
-/// if future.is_complete() {
-///     if let Some(IoResult::Ok(data)) = future.poll() {
-///         // Process data
-///     }
+/// if let Some(Some(data)) = future.poll() {
+///     // Process data
 /// }
 /// ```
-pub fn fs_read_async(path: &str) -> IoFuture {
-    let op = IoOp::FsRead { path: String::from(path) };
-    let request = IoRequest::new(DeviceType::Mmc, op);
-    let request_id = request_io_async(request);
-    IoFuture::new(request_id)
+pub fn fs_read_async(path: &str) -> IoFuture<Option<Vec<u8>>> {
+    let path = String::from(path);
+    io_router::call_async(DeviceType::Mmc, move || read_with_vfs_or_legacy(&path))
 }
 
 /// Write data to a file asynchronously (non-blocking).
 ///
 /// Returns immediately with an IoFuture that can be polled for completion.
-pub fn fs_write_async(path: &str, data: &[u8]) -> IoFuture {
-    let op = IoOp::FsWrite { 
-        path: String::from(path), 
-        data: data.to_vec() 
-    };
-    let request = IoRequest::new(DeviceType::Mmc, op);
-    let request_id = request_io_async(request);
-    IoFuture::new(request_id)
+pub fn fs_write_async(path: &str, data: &[u8]) -> IoFuture<Result<(), &'static str>> {
+    let path = String::from(path);
+    let data = data.to_vec();
+    io_router::call_async(DeviceType::Mmc, move || write_with_vfs_or_legacy(&path, &data))
 }
 
 /// List directory contents asynchronously (non-blocking).
 ///
 /// Returns immediately with an IoFuture that can be polled for completion.
-/// The result will be a serialized list that needs to be parsed.
-pub fn fs_list_async(path: &str) -> IoFuture {
-    let op = IoOp::FsList { path: String::from(path) };
-    let request = IoRequest::new(DeviceType::Mmc, op);
-    let request_id = request_io_async(request);
-    IoFuture::new(request_id)
+pub fn fs_list_async(path: &str) -> IoFuture<Vec<FileInfo>> {
+    let path = String::from(path);
+    io_router::call_async(DeviceType::Mmc, move || list_with_vfs_or_legacy(&path))
 }
 
 /// Check if a file exists asynchronously (non-blocking).
 ///
 /// Returns immediately with an IoFuture that can be polled for completion.
-pub fn fs_exists_async(path: &str) -> IoFuture {
-    let op = IoOp::FsExists { path: String::from(path) };
-    let request = IoRequest::new(DeviceType::Mmc, op);
-    let request_id = request_io_async(request);
-    IoFuture::new(request_id)
+pub fn fs_exists_async(path: &str) -> IoFuture<bool> {
+    let path = String::from(path);
+    io_router::call_async(DeviceType::Mmc, move || exists_with_vfs_or_legacy(&path))
 }
 
 /// Sync filesystem asynchronously (non-blocking).
 ///
 /// Returns immediately with an IoFuture that can be polled for completion.
-pub fn fs_sync_async() -> IoFuture {
-    let request = IoRequest::new(DeviceType::Mmc, IoOp::FsSync);
-    let request_id = request_io_async(request);
-    IoFuture::new(request_id)
+pub fn fs_sync_async() -> IoFuture<Result<(), &'static str>> {
+    io_router::call_async(DeviceType::Mmc, sync_with_vfs_or_legacy)
 }
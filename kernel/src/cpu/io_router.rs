@@ -1,44 +1,53 @@
-//! I/O Request Routing for Multi-Hart SMP
+//! Typed Cross-Hart RPC for Multi-Hart SMP
 //!
 //! Secondary harts in the emulator cannot access MMIO devices directly.
-//! This module provides a request/response pattern to delegate I/O operations
-//! to Hart 0, which has exclusive access to hardware devices.
+//! This module provides a generic request/response layer that runs a typed
+//! closure on Hart 0, which has exclusive access to hardware devices, and
+//! hands the typed result back to the caller.
 //!
 //! ## Architecture
 //!
 //! ```text
 //! ┌─────────────────┐     ┌─────────────┐     ┌─────────────────┐
-//! │ Secondary Hart  │────>│  IO_QUEUE   │────>│  Hart 0         │
-//! │ (request_io)    │     │  (shared)   │     │  (dispatch_io)  │
+//! │ Any Hart        │────>│  IO_QUEUES  │────>│  Hart 0         │
+//! │ (call/call_async)│    │ (per hart)  │     │  (dispatch_io)  │
 //! └─────────────────┘     └─────────────┘     └─────────────────┘
 //!          │                                           │
 //!          │              ┌─────────────┐              │
-//!          └──────────────│ IO_RESULTS  │<─────────────┘
-//!           (poll/wait)   │  (shared)   │   (store result)
+//!          └──────────────│ ReplySlot<R>│<─────────────┘
+//!           (poll/wait)   │ (per call)  │   (store result, IPI, wake)
 //!                         └─────────────┘
 //! ```
 //!
+//! Proxies (fs_proxy, display_proxy, net_proxy, audio_proxy) are thin
+//! wrappers around [`call`]: they pass the same closure they would run on
+//! Hart 0, so no request enum or byte marshalling is needed. A new proxied
+//! subsystem only needs a `DeviceType` tag.
+//!
 //! ## Device Ownership (from spec)
 //!
 //! | Device          | Hart 0       | Secondary Harts    |
 //! |-----------------|--------------|-------------------|
-//! | D1 MMC (SD)     | Direct MMIO  | Via this module   |
+//! | D1 MMC (SD)     | Direct MMIO  | Per-sector slots  |
 //! | D1 Display      | Direct MMIO  | Via this module   |
 //! | D1 EMAC (Net)   | Direct MMIO  | Via this module   |
 //! | VirtIO Disk/Net | Direct MMIO  | Via this module   |
 //! | UART            | Direct MMIO  | Shared buffer     |
 //! | CLINT           | Shared       | Shared            |
+//!
+//! SD transfers bypass this module: `d1_mmc` hands each sector to Hart 0
+//! through a per-hart slot without allocating, so filesystem code can run
+//! on any hart (see `fs_proxy`).
 
+use alloc::boxed::Box;
 use alloc::collections::VecDeque;
-use alloc::vec::Vec;
+use alloc::sync::Arc;
 use core::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 
+use crate::allocator::{enter_subsystem, Subsystem};
 use crate::Spinlock;
 use crate::cpu::{get_hart_id, MAX_HARTS};
 
-/// Maximum number of pending I/O requests
-const MAX_PENDING_REQUESTS: usize = 64;
-
 // ═══════════════════════════════════════════════════════════════════════════════
 // TYPES
 // ═══════════════════════════════════════════════════════════════════════════════
@@ -75,227 +84,151 @@ impl DeviceType {
             DeviceType::Audio => "audio",
         }
     }
-}
 
-/// I/O operation types
-#[derive(Clone, Debug)]
-pub enum IoOp {
-    /// Read data from device at offset
-    Read { offset: u64, len: usize },
-    /// Write data to device at offset
-    Write { offset: u64, data: Vec<u8> },
-    /// Device-specific control operation
-    Ioctl { cmd: u32, arg: u64 },
-    /// Flush pending writes to device
-    Flush,
-    /// Query device status/capabilities
-    Status,
-    
-    // ═══════════════════════════════════════════════════════════════════════
-    // Filesystem operations (for fs_proxy)
-    // ═══════════════════════════════════════════════════════════════════════
-    
-    /// Read a file from the filesystem
-    FsRead { path: alloc::string::String },
-    /// Write data to a file
-    FsWrite { path: alloc::string::String, data: Vec<u8> },
-    /// List directory contents
-    FsList { path: alloc::string::String },
-    /// Check if file exists
-    FsExists { path: alloc::string::String },
-    /// Sync filesystem to disk
-    FsSync,
-    
-    // ═══════════════════════════════════════════════════════════════════════
-    // Display operations (for display_proxy)
-    // ═══════════════════════════════════════════════════════════════════════
-    
-    /// Flush display (copy dirty region from back buffer to front buffer)
-    DisplayFlush,
-    /// Clear display to black
-    DisplayClear,
-    /// Mark entire screen as dirty
-    DisplayMarkAllDirty,
-    /// Check if display is available
-    DisplayIsAvailable,
-    
-    // ═══════════════════════════════════════════════════════════════════════
-    // Touch operations (for display_proxy)
-    // ═══════════════════════════════════════════════════════════════════════
-    
-    /// Poll for touch events
-    TouchPoll,
-    /// Get next touch event (serialized as [type:2][code:2][value:4])
-    TouchNextEvent,
-    /// Check if touch events are pending
-    TouchHasEvents,
-    
-    // ═══════════════════════════════════════════════════════════════════════
-    // Network operations (for net_proxy)
-    // ═══════════════════════════════════════════════════════════════════════
-    
-    /// Poll the network stack
-    NetPoll { timestamp_ms: i64 },
-    /// Check if IP is assigned
-    NetIsIpAssigned,
-    /// Get assigned IP address (returns 4 bytes)
-    NetGetIp,
-    
-    // ═══════════════════════════════════════════════════════════════════════
-    // Audio operations (for audio_proxy)
-    // ═══════════════════════════════════════════════════════════════════════
-    
-    /// Write an audio sample to the FIFO (32-bit stereo: L[15:0], R[31:16])
-    AudioWriteSample { sample: u32 },
-    /// Enable or disable audio playback
-    AudioSetEnabled { enabled: bool },
-    /// Set sample rate in Hz (e.g., 48000)
-    AudioSetSampleRate { rate: u32 },
-    /// Get current buffer fill level
-    AudioGetBufferLevel,
-    /// Check if buffer is full
-    AudioIsBufferFull,
-    /// Check if buffer is empty
-    AudioIsBufferEmpty,
+    /// Subsystem the heap blocks allocated by work for this device count
+    /// against (`memstat`)
+    pub fn subsystem(&self) -> Subsystem {
+        match self {
+            DeviceType::Mmc | DeviceType::VirtioBlock => Subsystem::Fs,
+            DeviceType::Network | DeviceType::VirtioNet => Subsystem::Net,
+            DeviceType::Display => Subsystem::Ui,
+            DeviceType::Audio => Subsystem::Audio,
+            DeviceType::Uart => Subsystem::Kernel,
+        }
+    }
 }
 
-/// Callback function type for async I/O completion
-/// Called by Hart 0 when an I/O request completes
-pub type IoCallback = fn(RequestId, IoResult);
-
 /// Request ID type
 pub type RequestId = u64;
 
-/// I/O request from a secondary hart to Hart 0
-#[derive(Clone)]
+/// Type-erased work item executed on Hart 0
+type Job = Box<dyn FnOnce() + Send>;
+
+/// I/O request queued for Hart 0
 pub struct IoRequest {
-    /// Unique request ID (for matching responses)
+    /// Unique request ID (for task wakeups)
     pub request_id: RequestId,
     /// Hart that submitted this request
     pub source_hart: usize,
     /// Target device
     pub device: DeviceType,
-    /// Operation to perform
-    pub operation: IoOp,
+    /// Work to run on Hart 0 (stores its own typed reply)
+    job: Job,
 }
 
 impl IoRequest {
-    /// Create a new I/O request
-    pub fn new(device: DeviceType, operation: IoOp) -> Self {
+    fn new(device: DeviceType, job: Job) -> Self {
         static NEXT_REQUEST_ID: AtomicU64 = AtomicU64::new(1);
-        
+
         Self {
             request_id: NEXT_REQUEST_ID.fetch_add(1, Ordering::SeqCst),
             source_hart: get_hart_id(),
             device,
-            operation,
+            job,
         }
     }
-    
-    /// Create a read request
-    pub fn read(device: DeviceType, offset: u64, len: usize) -> Self {
-        Self::new(device, IoOp::Read { offset, len })
-    }
-    
-    /// Create a write request
-    pub fn write(device: DeviceType, offset: u64, data: Vec<u8>) -> Self {
-        Self::new(device, IoOp::Write { offset, data })
-    }
-    
-    /// Create an ioctl request
-    pub fn ioctl(device: DeviceType, cmd: u32, arg: u64) -> Self {
-        Self::new(device, IoOp::Ioctl { cmd, arg })
-    }
 }
 
-/// Result of an I/O operation
-#[derive(Clone, Debug)]
-pub enum IoResult {
-    /// Operation completed successfully with optional data
-    Ok(Vec<u8>),
-    /// Operation failed with error message
-    Err(&'static str),
+/// Typed completion slot shared between the caller and Hart 0
+///
+/// Each call owns its slot, so late completions after a timeout are
+/// harmless: Hart 0 writes into a slot nobody reads any more.
+struct ReplySlot<R> {
+    /// Whether the result has been stored
+    complete: AtomicBool,
+    /// The result (protected by complete flag)
+    value: Spinlock<Option<R>>,
 }
 
-impl IoResult {
-    /// Check if the result is Ok
-    pub fn is_ok(&self) -> bool {
-        matches!(self, IoResult::Ok(_))
+impl<R> ReplySlot<R> {
+    fn new() -> Self {
+        Self {
+            complete: AtomicBool::new(false),
+            value: Spinlock::new(None),
+        }
     }
-    
-    /// Check if the result is Err
-    pub fn is_err(&self) -> bool {
-        matches!(self, IoResult::Err(_))
+
+    fn store(&self, value: R) {
+        *self.value.lock() = Some(value);
+        self.complete.store(true, Ordering::Release);
     }
-    
-    /// Get the data if Ok, or None if Err
-    pub fn data(&self) -> Option<&[u8]> {
-        match self {
-            IoResult::Ok(data) => Some(data),
-            IoResult::Err(_) => None,
-        }
+
+    fn is_complete(&self) -> bool {
+        self.complete.load(Ordering::Acquire)
     }
-    
-    /// Get the error message if Err, or None if Ok
-    pub fn error(&self) -> Option<&'static str> {
-        match self {
-            IoResult::Ok(_) => None,
-            IoResult::Err(e) => Some(e),
+
+    fn take(&self) -> Option<R> {
+        if self.is_complete() {
+            self.value.lock().take()
+        } else {
+            None
         }
     }
 }
 
-// ═══════════════════════════════════════════════════════════════════════════════
-// GLOBAL STATE
-// ═══════════════════════════════════════════════════════════════════════════════
-
-/// Completion slot for a single request
-pub struct CompletionSlot {
-    /// Whether this slot has a completed result
-    complete: AtomicBool,
-    /// The result (protected by complete flag)
-    result: Spinlock<Option<IoResult>>,
+/// A pending cross-hart call.
+///
+/// Use `poll()` to check for completion, or `wait()` to block with a timeout.
+///
+/// # Example
+/// ```
+/// let future = io_router::call_async(DeviceType::Audio, d1_audio::buffer_level);
+/// // Do other work...
+/// if future.is_complete() {
+///     let result = future.poll();
+/// }
+/// ```
+pub struct IoFuture<R> {
+    request_id: RequestId,
+    slot: Arc<ReplySlot<R>>,
 }
 
-impl CompletionSlot {
-    pub const fn new() -> Self {
-        Self {
-            complete: AtomicBool::new(false),
-            result: Spinlock::new(None),
-        }
+impl<R> IoFuture<R> {
+    /// Get the request ID for this future
+    pub fn request_id(&self) -> RequestId {
+        self.request_id
     }
-    
-    /// Store a result in this slot
-    pub fn store(&self, result: IoResult) {
-        *self.result.lock() = Some(result);
-        self.complete.store(true, Ordering::Release);
+
+    /// Poll for completion.
+    /// Returns `Some(result)` if complete, `None` if still pending.
+    ///
+    /// Note: This consumes the result - subsequent calls will return None.
+    pub fn poll(&self) -> Option<R> {
+        self.slot.take()
     }
-    
-    /// Check if result is ready
+
+    /// Check if the call is complete without consuming the result.
     pub fn is_complete(&self) -> bool {
-        self.complete.load(Ordering::Acquire)
+        self.slot.is_complete()
     }
-    
-    /// Take the result (returns None if not complete or already taken)
-    pub fn take(&self) -> Option<IoResult> {
-        if self.complete.load(Ordering::Acquire) {
-            let result = self.result.lock().take();
-            if result.is_some() {
-                self.complete.store(false, Ordering::Release);
+
+    /// Block until the call completes (0 = no timeout).
+    pub fn wait(&self, timeout_ms: u64) -> Result<R, &'static str> {
+        let start = crate::get_time_ms();
+        loop {
+            if let Some(result) = self.poll() {
+                return Ok(result);
+            }
+
+            if timeout_ms > 0 {
+                let elapsed = crate::get_time_ms() - start;
+                if elapsed >= timeout_ms as i64 {
+                    return Err("I/O request timeout");
+                }
+            }
+
+            // Yield CPU (use WFI to save power)
+            unsafe {
+                core::arch::asm!("wfi", options(nomem, nostack));
             }
-            result
-        } else {
-            None
         }
     }
-    
-    /// Reset this slot for reuse
-    pub fn reset(&self) {
-        self.complete.store(false, Ordering::Release);
-        *self.result.lock() = None;
-    }
 }
 
+// ═══════════════════════════════════════════════════════════════════════════════
+// GLOBAL STATE
+// ═══════════════════════════════════════════════════════════════════════════════
+
 /// Per-hart I/O request queues (eliminates lock contention)
 /// Each hart pushes to its own queue; Hart 0 scans all queues for dispatch
 static IO_QUEUES: [Spinlock<VecDeque<IoRequest>>; MAX_HARTS] = {
@@ -306,156 +239,61 @@ static IO_QUEUES: [Spinlock<VecDeque<IoRequest>>; MAX_HARTS] = {
 /// Round-robin cursor for fair dispatch across all hart queues
 static DISPATCH_CURSOR: AtomicUsize = AtomicUsize::new(0);
 
-/// Completion slots indexed by (request_id % MAX_PENDING_REQUESTS)
-static IO_COMPLETIONS: [CompletionSlot; MAX_PENDING_REQUESTS] = {
-    const INIT: CompletionSlot = CompletionSlot::new();
-    [INIT; MAX_PENDING_REQUESTS]
-};
-
 /// Statistics
 static REQUESTS_SUBMITTED: AtomicU64 = AtomicU64::new(0);
 static REQUESTS_COMPLETED: AtomicU64 = AtomicU64::new(0);
 
-/// Callback registry for async I/O completion notifications
-/// Indexed by (request_id % MAX_PENDING_REQUESTS)
-static IO_CALLBACKS: Spinlock<[Option<IoCallback>; MAX_PENDING_REQUESTS]> = Spinlock::new([None; MAX_PENDING_REQUESTS]);
-
 // ═══════════════════════════════════════════════════════════════════════════════
-// API FOR SECONDARY HARTS
+// API FOR ALL HARTS
 // ═══════════════════════════════════════════════════════════════════════════════
 
-/// Submit an I/O request and wait for completion.
+/// Run `f` on Hart 0 and return its result.
 ///
-/// This function is called by secondary harts to request I/O from Hart 0.
-/// It blocks until the request is completed or times out.
-///
-/// # Arguments
-/// * `request` - The I/O request to submit
-/// * `timeout_ms` - Maximum time to wait in milliseconds (0 = no timeout)
-///
-/// # Returns
-/// * `IoResult::Ok(data)` - Operation succeeded with returned data
-/// * `IoResult::Err(msg)` - Operation failed with error message
-pub fn request_io(request: IoRequest, timeout_ms: u64) -> IoResult {
-    let request_id = request.request_id;
-    let slot_idx = (request_id as usize) % MAX_PENDING_REQUESTS;
-    
-    // Reset the completion slot
-    IO_COMPLETIONS[slot_idx].reset();
-    
-    // Submit request to caller's hart queue (reduces lock contention)
-    let hart_id = get_hart_id();
-    IO_QUEUES[hart_id].lock().push_back(request);
-    REQUESTS_SUBMITTED.fetch_add(1, Ordering::Relaxed);
-    
-    // Send IPI to Hart 0 to wake it up
-    if get_hart_id() != 0 {
-        crate::cpu::send_ipi(0);
-    }
-    
-    // Wait for completion
-    let start = crate::get_time_ms();
-    loop {
-        // Check if complete
-        if let Some(result) = IO_COMPLETIONS[slot_idx].take() {
-            return result;
-        }
-        
-        // Check timeout
-        if timeout_ms > 0 {
-            let elapsed = crate::get_time_ms() - start;
-            if elapsed >= timeout_ms as i64 {
-                return IoResult::Err("I/O request timeout");
-            }
-        }
-        
-        // Yield CPU (use WFI to save power)
-        unsafe {
-            core::arch::asm!("wfi");
-        }
+/// On Hart 0: Runs `f` directly
+/// On secondary harts: Queues `f` for Hart 0 and waits (0 = no timeout)
+pub fn call<R, F>(device: DeviceType, timeout_ms: u64, f: F) -> Result<R, &'static str>
+where
+    R: Send + 'static,
+    F: FnOnce() -> R + Send + 'static,
+{
+    if get_hart_id() == 0 {
+        let _subsystem = enter_subsystem(device.subsystem());
+        return Ok(f());
     }
+    call_async(device, f).wait(timeout_ms)
 }
 
-/// Submit an I/O request without waiting (fire-and-forget).
-///
-/// Useful for operations where the caller doesn't need the result.
+/// Queue `f` for Hart 0 without waiting.
 ///
-/// # Returns
-/// * Request ID that can be used to poll for completion later
-pub fn request_io_async(request: IoRequest) -> RequestId {
+/// Always goes through the queue, even on Hart 0 (the closure then runs on
+/// the next `dispatch_io`). Tasks can sleep on the returned request ID with
+/// `task::wait_io_request`; they are woken when the call completes.
+pub fn call_async<R, F>(device: DeviceType, f: F) -> IoFuture<R>
+where
+    R: Send + 'static,
+    F: FnOnce() -> R + Send + 'static,
+{
+    let slot = Arc::new(ReplySlot::new());
+    let reply = slot.clone();
+    let request = IoRequest::new(device, Box::new(move || reply.store(f())));
     let request_id = request.request_id;
-    let slot_idx = (request_id as usize) % MAX_PENDING_REQUESTS;
-    
-    // Reset the completion slot
-    IO_COMPLETIONS[slot_idx].reset();
-    
-    // Submit request to caller's hart queue (reduces lock contention)
-    let hart_id = get_hart_id();
-    IO_QUEUES[hart_id].lock().push_back(request);
-    REQUESTS_SUBMITTED.fetch_add(1, Ordering::Relaxed);
-    
-    // Send IPI to Hart 0
-    if get_hart_id() != 0 {
-        crate::cpu::send_ipi(0);
-    }
-    
-    request_id
-}
 
-/// Check if an async request has completed.
-///
-/// # Returns
-/// * `Some(result)` if the request is complete
-/// * `None` if the request is still pending
-pub fn poll_io(request_id: RequestId) -> Option<IoResult> {
-    let slot_idx = (request_id as usize) % MAX_PENDING_REQUESTS;
-    IO_COMPLETIONS[slot_idx].take()
-}
+    submit(request);
 
-/// Check if a request is complete without consuming the result.
-pub fn is_io_complete(request_id: RequestId) -> bool {
-    let slot_idx = (request_id as usize) % MAX_PENDING_REQUESTS;
-    IO_COMPLETIONS[slot_idx].is_complete()
+    IoFuture { request_id, slot }
 }
 
-/// Submit an I/O request with a completion callback.
-///
-/// The callback will be invoked by Hart 0 when the request completes.
-/// This allows truly non-blocking I/O where the caller doesn't need to poll.
-///
-/// # Arguments
-/// * `device` - Target device type
-/// * `operation` - I/O operation to perform
-/// * `callback` - Function to call on completion (receives request_id and result)
-///
-/// # Returns
-/// * Request ID that can still be used with `poll_io` if needed
-pub fn submit_async_io_with_callback(
-    device: DeviceType,
-    operation: IoOp,
-    callback: IoCallback,
-) -> RequestId {
-    let request = IoRequest::new(device, operation);
-    let request_id = request.request_id;
-    let slot_idx = (request_id as usize) % MAX_PENDING_REQUESTS;
-    
-    // Reset the completion slot
-    IO_COMPLETIONS[slot_idx].reset();
-    
-    // Register the callback
-    IO_CALLBACKS.lock()[slot_idx] = Some(callback);
-    
+/// Push a request to the caller's hart queue and kick Hart 0
+fn submit(request: IoRequest) {
     // Submit request to caller's hart queue (reduces lock contention)
     let hart_id = get_hart_id();
     IO_QUEUES[hart_id].lock().push_back(request);
     REQUESTS_SUBMITTED.fetch_add(1, Ordering::Relaxed);
-    
-    // Send IPI to Hart 0
-    if get_hart_id() != 0 {
+
+    // Send IPI to Hart 0 to wake it up
+    if hart_id != 0 {
         crate::cpu::send_ipi(0);
     }
-    
-    request_id
 }
 
 // ═══════════════════════════════════════════════════════════════════════════════
@@ -493,406 +331,42 @@ fn dequeue_request_round_robin(num_harts: usize) -> Option<IoRequest> {
     None
 }
 
-/// Complete an I/O request with a result.
-///
-/// Called by Hart 0 after processing a request.
-/// This function:
-/// 1. Stores the result in the completion slot
-/// 2. Invokes any registered callback
-/// 3. Wakes any task waiting on this specific request
-pub fn complete_request(request_id: RequestId, result: IoResult) {
-    let slot_idx = (request_id as usize) % MAX_PENDING_REQUESTS;
-    
-    // Store in completion slot for poll_io
-    IO_COMPLETIONS[slot_idx].store(result.clone());
-    REQUESTS_COMPLETED.fetch_add(1, Ordering::Relaxed);
-    
-    // Take and invoke callback if registered
-    let callback = IO_CALLBACKS.lock()[slot_idx].take();
-    if let Some(cb) = callback {
-        cb(request_id, result);
-    }
-    
-    // Wake any task waiting on this specific request ID
-    crate::task::wake_io_request(request_id);
-}
-
 /// Process all pending I/O requests from all hart queues.
 ///
 /// This is the main dispatcher function called by Hart 0.
-/// It scans all per-hart queues in round-robin order and processes requests.
+/// It scans all per-hart queues in round-robin order, runs each request,
+/// then wakes the requesting hart and any task waiting on the request ID.
 ///
 /// # Returns
 /// Number of requests processed
 pub fn dispatch_io() -> usize {
-    let hart_id = get_hart_id();
-    
     // Only Hart 0 should run the dispatcher
-    if hart_id != 0 {
+    if get_hart_id() != 0 {
         return 0;
     }
-    
+
     let mut processed = 0;
     let num_harts = crate::cpu::get_expected_harts();
-    
-    // Process up to one request per hart per dispatch cycle for fairness
-    while let Some(request) = dequeue_request_round_robin(num_harts) {
-        let result = handle_request(&request);
-        complete_request(request.request_id, result);
-        
-        // Wake the requesting hart
-        if request.source_hart != 0 {
-            crate::cpu::send_ipi(request.source_hart);
-        }
-        
-        processed += 1;
-    }
-    
-    processed
-}
 
-/// Handle a single I/O request by routing to the appropriate device.
-fn handle_request(request: &IoRequest) -> IoResult {
-    match request.device {
-        DeviceType::Mmc | DeviceType::VirtioBlock => handle_block_request(request),
-        DeviceType::Network | DeviceType::VirtioNet => handle_network_request(request),
-        DeviceType::Display => handle_display_request(request),
-        DeviceType::Uart => handle_uart_request(request),
-        DeviceType::Audio => handle_audio_request(request),
-    }
-}
-
-/// Handle block device (MMC/VirtIO) requests
-fn handle_block_request(request: &IoRequest) -> IoResult {
-    match &request.operation {
-        IoOp::Read { offset, len } => {
-            // Route to block device driver
-            let fs = crate::lock::utils::FS_STATE.read();
-            let blk = crate::lock::utils::BLK_DEV.read();
-            
-            if let (Some(_fs), Some(_dev)) = (fs.as_ref(), blk.as_ref()) {
-                // Note: Direct block reads would go here
-                // For now, return placeholder - actual implementation depends on FS API
-                let _ = (offset, len);
-                IoResult::Err("Block read not implemented via I/O router")
-            } else {
-                IoResult::Err("Block device not available")
-            }
-        }
-        IoOp::Write { offset, data } => {
-            let _ = (offset, data);
-            IoResult::Err("Block write not implemented via I/O router")
-        }
-        IoOp::Flush => {
-            let mut fs = crate::lock::utils::FS_STATE.write();
-            let mut blk = crate::lock::utils::BLK_DEV.write();
-            
-            if let (Some(fs), Some(dev)) = (fs.as_mut(), blk.as_mut()) {
-                match fs.sync(dev) {
-                    Ok(_) => IoResult::Ok(Vec::new()),
-                    Err(e) => IoResult::Err(e),
-                }
-            } else {
-                IoResult::Err("Block device not available")
-            }
-        }
-        IoOp::Status => {
-            let blk = crate::lock::utils::BLK_DEV.read();
-            if blk.is_some() {
-                IoResult::Ok(b"online".to_vec())
-            } else {
-                IoResult::Ok(b"offline".to_vec())
-            }
-        }
-        IoOp::Ioctl { cmd, arg } => {
-            let _ = (cmd, arg);
-            IoResult::Err("Block ioctl not implemented")
-        }
-        
-        // ═══════════════════════════════════════════════════════════════════════
-        // Filesystem operations
-        // ═══════════════════════════════════════════════════════════════════════
-        
-        IoOp::FsRead { path } => {
-            // Check if path is under a VFS mount point (9P filesystem)
-            if path.starts_with("/mnt/") {
-                // Use VFS for mounted filesystems
-                let mut vfs_guard = crate::lock::utils::VFS_STATE.write();
-                if let Some(vfs) = vfs_guard.as_mut() {
-                    let result = vfs.read_file(path);
-                    drop(vfs_guard);
-                    match result {
-                        Some(data) => IoResult::Ok(data),
-                        None => IoResult::Err("File not found"),
-                    }
-                } else {
-                    IoResult::Err("VFS not available")
-                }
-            } else {
-                // Use FS_STATE directly for root filesystem (SFS)
-                let mut fs = crate::lock::utils::FS_STATE.write();
-                let mut blk = crate::lock::utils::BLK_DEV.write();
-                
-                if let (Some(fs), Some(dev)) = (fs.as_mut(), blk.as_mut()) {
-                    match fs.read_file(dev, path) {
-                        Some(data) => IoResult::Ok(data),
-                        None => IoResult::Err("File not found"),
-                    }
-                } else {
-                    IoResult::Err("Filesystem not available")
-                }
-            }
-        }
-        
-        IoOp::FsWrite { path, data } => {
-            use crate::device::uart::{write_str, write_line};
-            
-            write_str("io_router FsWrite: ");
-            write_str(path);
-            write_line("");
-            
-            // Use VFS for mount point routing
-            let mut vfs_guard = crate::lock::utils::VFS_STATE.write();
-            write_line("io_router: got VFS lock");
-            
-            if let Some(vfs) = vfs_guard.as_mut() {
-                write_line("io_router: calling vfs.write_file...");
-                let result = vfs.write_file(path, data);
-                write_line("io_router: vfs.write_file returned");
-                
-                match result {
-                    Ok(()) => IoResult::Ok(Vec::new()),
-                    Err(e) => IoResult::Err(e),
-                }
-            } else {
-                drop(vfs_guard);
-                // Fallback to legacy FS_STATE
-                let mut fs = crate::lock::utils::FS_STATE.write();
-                let mut blk = crate::lock::utils::BLK_DEV.write();
-                
-                if let (Some(fs), Some(dev)) = (fs.as_mut(), blk.as_mut()) {
-                    match fs.write_file(dev, path, data) {
-                        Ok(()) => IoResult::Ok(Vec::new()),
-                        Err(e) => IoResult::Err(e),
-                    }
-                } else {
-                    IoResult::Err("Filesystem not available")
-                }
-            }
-        }
-        
-        IoOp::FsList { path } => {
-            // Use VFS for mount point visibility
-            let mut vfs_guard = crate::lock::utils::VFS_STATE.write();
-            if let Some(vfs) = vfs_guard.as_mut() {
-                let entries = vfs.list_dir(path);
-                // Serialize entries as "name:size\n" format (compatible with ls binary)
-                let mut result = Vec::new();
-                for entry in entries {
-                    result.extend_from_slice(entry.name.as_bytes());
-                    result.push(b':');
-                    // Format size as string
-                    let size_str = alloc::format!("{}", entry.size);
-                    result.extend_from_slice(size_str.as_bytes());
-                    result.push(b'\n');
-                }
-                IoResult::Ok(result)
-            } else {
-                // Fall back to legacy FS_STATE
-                let mut fs = crate::lock::utils::FS_STATE.write();
-                let mut blk = crate::lock::utils::BLK_DEV.write();
-                
-                if let (Some(fs), Some(dev)) = (fs.as_mut(), blk.as_mut()) {
-                    let entries = fs.list_dir(dev, path);
-                    let mut result = Vec::new();
-                    for entry in entries {
-                        result.extend_from_slice(entry.name.as_bytes());
-                        result.push(b':');
-                        let size_str = alloc::format!("{}", entry.size);
-                        result.extend_from_slice(size_str.as_bytes());
-                        result.push(b'\n');
-                    }
-                    IoResult::Ok(result)
-                } else {
-                    IoResult::Err("Filesystem not available")
-                }
-            }
-        }
-        
-        IoOp::FsExists { path } => {
-            let mut fs = crate::lock::utils::FS_STATE.write();
-            let mut blk = crate::lock::utils::BLK_DEV.write();
-            
-            if let (Some(fs), Some(dev)) = (fs.as_mut(), blk.as_mut()) {
-                // Try to read file to check existence
-                let exists = fs.read_file(dev, path).is_some();
-                IoResult::Ok(alloc::vec![if exists { 1 } else { 0 }])
-            } else {
-                IoResult::Err("Filesystem not available")
-            }
-        }
-        
-        IoOp::FsSync => {
-            let mut fs = crate::lock::utils::FS_STATE.write();
-            let mut blk = crate::lock::utils::BLK_DEV.write();
-            
-            if let (Some(fs), Some(dev)) = (fs.as_mut(), blk.as_mut()) {
-                match fs.sync(dev) {
-                    Ok(_) => IoResult::Ok(Vec::new()),
-                    Err(e) => IoResult::Err(e),
-                }
-            } else {
-                IoResult::Err("Filesystem not available")
-            }
-        }
-        
-        // Display/Touch/Network operations should not reach block handler
-        _ => IoResult::Err("Operation not supported for block device"),
-    }
-}
+    while let Some(request) = dequeue_request_round_robin(num_harts) {
+        let IoRequest { request_id, source_hart, device, job } = request;
+        let subsystem = enter_subsystem(device.subsystem());
+        job();
+        drop(subsystem);
+        REQUESTS_COMPLETED.fetch_add(1, Ordering::Relaxed);
 
-/// Handle network device requests
-fn handle_network_request(request: &IoRequest) -> IoResult {
-    match &request.operation {
-        IoOp::Status => {
-            let net = crate::lock::utils::NET_STATE.lock();
-            if net.is_some() {
-                if crate::net::is_ip_assigned() {
-                    let ip = crate::net::get_my_ip();
-                    let octets = ip.octets();
-                    let status = alloc::format!(
-                        "online {}.{}.{}.{}",
-                        octets[0], octets[1], octets[2], octets[3]
-                    );
-                    IoResult::Ok(status.into_bytes())
-                } else {
-                    IoResult::Ok(b"online no-ip".to_vec())
-                }
-            } else {
-                IoResult::Ok(b"offline".to_vec())
-            }
-        }
-        IoOp::NetPoll { timestamp_ms } => {
-            let mut net = crate::lock::utils::NET_STATE.lock();
-            if let Some(state) = net.as_mut() {
-                state.poll(*timestamp_ms);
-            }
-            IoResult::Ok(Vec::new())
-        }
-        IoOp::NetIsIpAssigned => {
-            let assigned = crate::net::is_ip_assigned();
-            IoResult::Ok(alloc::vec![if assigned { 1 } else { 0 }])
-        }
-        IoOp::NetGetIp => {
-            let ip = crate::net::get_my_ip();
-            let octets = ip.octets();
-            IoResult::Ok(octets.to_vec())
-        }
-        _ => IoResult::Err("Network operation not implemented via I/O router"),
-    }
-}
+        // Wake any task waiting on this specific request ID
+        crate::task::wake_io_request(request_id);
 
-/// Handle display device requests
-fn handle_display_request(request: &IoRequest) -> IoResult {
-    match &request.operation {
-        IoOp::Status => {
-            if crate::platform::d1_display::is_available() {
-                IoResult::Ok(b"online".to_vec())
-            } else {
-                IoResult::Ok(b"offline".to_vec())
-            }
-        }
-        IoOp::Flush | IoOp::DisplayFlush => {
-            crate::platform::d1_display::flush();
-            IoResult::Ok(Vec::new())
-        }
-        IoOp::DisplayClear => {
-            crate::platform::d1_display::clear_display();
-            IoResult::Ok(Vec::new())
-        }
-        IoOp::DisplayMarkAllDirty => {
-            crate::platform::d1_display::mark_all_dirty();
-            IoResult::Ok(Vec::new())
-        }
-        IoOp::DisplayIsAvailable => {
-            let available = crate::platform::d1_display::is_available();
-            IoResult::Ok(alloc::vec![if available { 1 } else { 0 }])
-        }
-        IoOp::TouchPoll => {
-            crate::platform::d1_touch::poll();
-            IoResult::Ok(Vec::new())
-        }
-        IoOp::TouchNextEvent => {
-            if let Some(event) = crate::platform::d1_touch::next_event() {
-                // Serialize event: [type:2][code:2][value:4] = 8 bytes
-                let mut data = Vec::with_capacity(8);
-                data.extend_from_slice(&event.event_type.to_le_bytes());
-                data.extend_from_slice(&event.code.to_le_bytes());
-                data.extend_from_slice(&event.value.to_le_bytes());
-                IoResult::Ok(data)
-            } else {
-                IoResult::Ok(Vec::new()) // Empty = no event
-            }
-        }
-        IoOp::TouchHasEvents => {
-            let has = crate::platform::d1_touch::has_events();
-            IoResult::Ok(alloc::vec![if has { 1 } else { 0 }])
+        // Wake the requesting hart
+        if source_hart != 0 {
+            crate::cpu::send_ipi(source_hart);
         }
-        _ => IoResult::Err("Display operation not implemented via I/O router"),
-    }
-}
 
-/// Handle audio device requests
-fn handle_audio_request(request: &IoRequest) -> IoResult {
-    match &request.operation {
-        IoOp::AudioWriteSample { sample } => {
-            let success = crate::platform::d1_audio::write_sample(*sample);
-            IoResult::Ok(alloc::vec![if success { 1 } else { 0 }])
-        }
-        IoOp::AudioSetEnabled { enabled } => {
-            crate::platform::d1_audio::set_enabled(*enabled);
-            IoResult::Ok(Vec::new())
-        }
-        IoOp::AudioSetSampleRate { rate } => {
-            crate::platform::d1_audio::set_sample_rate(*rate);
-            IoResult::Ok(Vec::new())
-        }
-        IoOp::AudioGetBufferLevel => {
-            let level = crate::platform::d1_audio::buffer_level();
-            IoResult::Ok(level.to_le_bytes().to_vec())
-        }
-        IoOp::AudioIsBufferFull => {
-            let full = crate::platform::d1_audio::is_buffer_full();
-            IoResult::Ok(alloc::vec![if full { 1 } else { 0 }])
-        }
-        IoOp::AudioIsBufferEmpty => {
-            let empty = crate::platform::d1_audio::is_buffer_empty();
-            IoResult::Ok(alloc::vec![if empty { 1 } else { 0 }])
-        }
-        IoOp::Status => {
-            if crate::platform::d1_audio::is_initialized() {
-                IoResult::Ok(b"online".to_vec())
-            } else {
-                IoResult::Ok(b"offline".to_vec())
-            }
-        }
-        _ => IoResult::Err("Audio operation not implemented via I/O router"),
+        processed += 1;
     }
-}
 
-/// Handle UART device requests
-fn handle_uart_request(request: &IoRequest) -> IoResult {
-    match &request.operation {
-        IoOp::Write { data, .. } => {
-            // Write to UART (this is safe from any hart via shared buffer)
-            crate::device::uart::write_bytes(data);
-            IoResult::Ok(Vec::new())
-        }
-        IoOp::Status => {
-            IoResult::Ok(b"online".to_vec())
-        }
-        _ => IoResult::Err("UART operation not implemented via I/O router"),
-    }
+    processed
 }
 
 // ═══════════════════════════════════════════════════════════════════════════════
@@ -3,6 +3,8 @@
 //! Provides message-passing primitives for task communication:
 //! - Channels: Unidirectional, bounded message queues
 //! - Pipes: Byte-stream communication (like Unix pipes)
+//! - Message queues: Named, bounded queues any process can open by name
+//!   (`mq_open`), with receives that sleep on a wait queue
 //!
 //! Tasks can block waiting for data, enabling efficient IPC without polling.
 
@@ -13,6 +15,7 @@ use alloc::vec::Vec;
 use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 
 use crate::cpu::process::Pid;
+use crate::lock::wait::WaitQueue;
 use crate::Spinlock;
 use crate::services::klogd::{klog_debug, klog_trace};
 
@@ -28,6 +31,12 @@ pub const DEFAULT_CHANNEL_CAPACITY: usize = 16;
 /// Maximum pipe buffer size (8KB)
 pub const PIPE_BUFFER_SIZE: usize = 8192;
 
+/// Default number of messages a message queue holds
+pub const MQ_DEFAULT_CAPACITY: usize = 32;
+
+/// Most messages a message queue can hold
+pub const MQ_MAX_CAPACITY: usize = 256;
+
 // ═══════════════════════════════════════════════════════════════════════════════
 // MESSAGE TYPES
 // ═══════════════════════════════════════════════════════════════════════════════
@@ -300,6 +309,7 @@ impl Pipe {
 
         // Wake up readers
         if written > 0 {
+            crate::lock::wait::IO_EVENTS.wake_all();
             if let Some(pid) = self.read_waiters.lock().pop_front() {
                 klog_trace(
                     "ipc",
@@ -362,6 +372,7 @@ impl Pipe {
     /// Close the write end
     pub fn close_write(&self) {
         self.writer_active.store(false, Ordering::Release);
+        crate::lock::wait::IO_EVENTS.wake_all();
         // Wake all readers (they'll get EOF)
         let mut waiters = self.read_waiters.lock();
         while waiters.pop_front().is_some() {}
@@ -381,6 +392,160 @@ impl Pipe {
     }
 }
 
+// ═══════════════════════════════════════════════════════════════════════════════
+// MESSAGE QUEUES
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Message queue identifier
+pub type QueueId = u32;
+
+/// Woken whenever any message queue gets a message, loses one or closes;
+/// waiters re-check their own queue
+static MQ_EVENTS: WaitQueue = WaitQueue::new();
+
+/// A named, bounded message queue with any number of senders and receivers
+pub struct MessageQueue {
+    /// Unique queue ID
+    pub id: QueueId,
+    /// Name it is opened by
+    pub name: String,
+    /// Queued messages, oldest first
+    messages: Spinlock<VecDeque<Message>>,
+    /// Maximum number of messages
+    capacity: usize,
+    /// Queue is closed (unlinked)
+    closed: AtomicBool,
+}
+
+impl MessageQueue {
+    fn new(id: QueueId, name: &str, capacity: usize) -> Self {
+        Self {
+            id,
+            name: String::from(name),
+            messages: Spinlock::new(VecDeque::with_capacity(capacity)),
+            capacity: capacity.clamp(1, MQ_MAX_CAPACITY),
+            closed: AtomicBool::new(false),
+        }
+    }
+
+    /// Queue a message without waiting
+    pub fn try_send(&self, msg: Message) -> Result<(), &'static str> {
+        if self.closed.load(Ordering::Acquire) {
+            return Err("Queue closed");
+        }
+        if msg.data.len() > MAX_MESSAGE_SIZE {
+            return Err("Message too large");
+        }
+        {
+            let mut messages = self.messages.lock();
+            if messages.len() >= self.capacity {
+                return Err("Queue full");
+            }
+            messages.push_back(msg);
+        }
+        MQ_EVENTS.wake_all();
+        Ok(())
+    }
+
+    /// Queue a message, waiting for room until the clock reaches `until_ms`
+    /// (ms since boot)
+    pub fn send(&self, msg: Message, until_ms: i64) -> Result<(), &'static str> {
+        let mut msg = Some(msg);
+        let mut result = Err("Queue full");
+        MQ_EVENTS.wait_until(until_ms, || {
+            let Some(pending) = msg.take() else {
+                return true;
+            };
+            if self.closed.load(Ordering::Acquire) || pending.data.len() > MAX_MESSAGE_SIZE {
+                result = self.try_send(pending);
+                return true;
+            }
+            let mut messages = self.messages.lock();
+            if messages.len() >= self.capacity {
+                msg = Some(pending);
+                return false;
+            }
+            messages.push_back(pending);
+            result = Ok(());
+            true
+        });
+        if result.is_ok() {
+            MQ_EVENTS.wake_all();
+        }
+        result
+    }
+
+    /// Take the oldest message without waiting
+    pub fn try_recv(&self) -> Option<Message> {
+        let msg = self.messages.lock().pop_front();
+        if msg.is_some() {
+            MQ_EVENTS.wake_all();
+        }
+        msg
+    }
+
+    /// Take the oldest message, sleeping until one arrives or the clock
+    /// reaches `until_ms` (ms since boot). Queued messages are still handed
+    /// out after the queue closes.
+    pub fn recv(&self, until_ms: i64) -> Result<Option<Message>, &'static str> {
+        let mut received = None;
+        MQ_EVENTS.wait_until(until_ms, || {
+            received = self.messages.lock().pop_front();
+            received.is_some() || self.closed.load(Ordering::Acquire)
+        });
+        match received {
+            Some(msg) => {
+                MQ_EVENTS.wake_all();
+                Ok(Some(msg))
+            }
+            None if self.is_closed() => Err("Queue closed"),
+            None => Ok(None),
+        }
+    }
+
+    /// Number of queued messages
+    pub fn pending_count(&self) -> usize {
+        self.messages.lock().len()
+    }
+
+    /// Maximum number of messages
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Close the queue: sends fail and receivers wake once it is empty
+    pub fn close(&self) {
+        self.closed.store(true, Ordering::Release);
+        MQ_EVENTS.wake_all();
+    }
+
+    /// Check if the queue is closed
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::Acquire)
+    }
+}
+
+/// Open the message queue `name`, creating it with room for `capacity`
+/// messages (at most MQ_MAX_CAPACITY) if it does not exist yet
+pub fn mq_open(name: &str, capacity: usize) -> Arc<MessageQueue> {
+    IPC.open_queue(name, capacity)
+}
+
+/// Send a message to queue `name`, waiting for room until `until_ms`
+pub fn mq_send(name: &str, msg: Message, until_ms: i64) -> Result<(), &'static str> {
+    IPC.get_queue_by_name(name).ok_or("No such queue")?.send(msg, until_ms)
+}
+
+/// Receive from queue `name`, waiting for a message until `until_ms`
+pub fn mq_recv(name: &str, until_ms: i64) -> Result<Option<Message>, &'static str> {
+    IPC.get_queue_by_name(name).ok_or("No such queue")?.recv(until_ms)
+}
+
+/// Remove queue `name`; processes that still hold it see it closed
+pub fn mq_unlink(name: &str) -> bool {
+    IPC.remove_queue(name)
+}
+
 // ═══════════════════════════════════════════════════════════════════════════════
 // GLOBAL IPC REGISTRY
 // ═══════════════════════════════════════════════════════════════════════════════
@@ -395,10 +560,14 @@ pub struct IpcRegistry {
     channel_names: Spinlock<BTreeMap<String, ChannelId>>,
     /// Pipes
     pipes: Spinlock<BTreeMap<PipeId, Arc<Pipe>>>,
+    /// Message queues by name
+    queues: Spinlock<BTreeMap<String, Arc<MessageQueue>>>,
     /// Next channel ID
     next_channel_id: AtomicUsize,
     /// Next pipe ID
     next_pipe_id: AtomicUsize,
+    /// Next message queue ID
+    next_queue_id: AtomicUsize,
 }
 
 impl IpcRegistry {
@@ -407,8 +576,10 @@ impl IpcRegistry {
             channels: Spinlock::new(BTreeMap::new()),
             channel_names: Spinlock::new(BTreeMap::new()),
             pipes: Spinlock::new(BTreeMap::new()),
+            queues: Spinlock::new(BTreeMap::new()),
             next_channel_id: AtomicUsize::new(1),
             next_pipe_id: AtomicUsize::new(1),
+            next_queue_id: AtomicUsize::new(1),
         }
     }
 
@@ -482,6 +653,57 @@ impl IpcRegistry {
         }
     }
 
+    /// Get the message queue `name`, creating it if needed
+    pub fn open_queue(&self, name: &str, capacity: usize) -> Arc<MessageQueue> {
+        let mut queues = self.queues.lock();
+        if let Some(queue) = queues.get(name) {
+            return queue.clone();
+        }
+        let id = self.next_queue_id.fetch_add(1, Ordering::SeqCst) as QueueId;
+        let queue = Arc::new(MessageQueue::new(id, name, capacity));
+        queues.insert(String::from(name), queue.clone());
+        drop(queues);
+
+        klog_debug(
+            "ipc",
+            &alloc::format!("Created message queue '{}' (id={})", name, id),
+        );
+
+        queue
+    }
+
+    /// Get a message queue by name
+    pub fn get_queue_by_name(&self, name: &str) -> Option<Arc<MessageQueue>> {
+        self.queues.lock().get(name).cloned()
+    }
+
+    /// Get a message queue by ID
+    pub fn get_queue(&self, id: QueueId) -> Option<Arc<MessageQueue>> {
+        self.queues.lock().values().find(|queue| queue.id == id).cloned()
+    }
+
+    /// Remove and close a message queue
+    pub fn remove_queue(&self, name: &str) -> bool {
+        let Some(queue) = self.queues.lock().remove(name) else {
+            return false;
+        };
+        queue.close();
+        klog_debug(
+            "ipc",
+            &alloc::format!("Removed message queue '{}' (id={})", name, queue.id),
+        );
+        true
+    }
+
+    /// List all message queues: id, name, queued messages, capacity
+    pub fn list_queues(&self) -> Vec<(QueueId, String, usize, usize)> {
+        self.queues
+            .lock()
+            .values()
+            .map(|queue| (queue.id, queue.name.clone(), queue.pending_count(), queue.capacity()))
+            .collect()
+    }
+
     /// List all channels
     pub fn list_channels(&self) -> Vec<(ChannelId, String, usize)> {
         self.channels
@@ -8,6 +8,8 @@ use crate::dtb::DTB_ADDR;
 pub mod sched;
 pub mod process;
 pub mod ipc;
+pub mod signal;
+pub mod cgroup;
 pub mod io_router;
 pub mod fs_proxy;
 pub mod display_proxy;
@@ -73,8 +75,9 @@ pub(crate) fn spin_delay_ms(ms: u64) {
 /// Universal hart idle loop - Round-Robin Scheduler
 ///
 /// This loop continuously runs all processes assigned to this hart in round-robin fashion.
-/// Each process's entry function is called repeatedly (it should do one "tick" of work).
-/// This is cooperative multitasking - processes must return promptly to allow others to run.
+/// Each process's entry function is called repeatedly (it should do one "tick" of work),
+/// on the process's own stack. A tick that runs longer than sched::TIME_SLICE_MS is
+/// preempted by the timer: the process is requeued and its tick resumes on a later round.
 ///
 /// All harts (including hart 0) use this same loop after initialization is complete.
 /// This ensures all harts are treated equally for process scheduling.
@@ -111,11 +114,11 @@ pub(crate) fn hart_loop(hart_id: usize) -> ! {
 
                 let start_time = get_time_ms() as u64;
 
-                // Execute ONE TICK of the process
+                // Execute ONE TICK of the process (or the rest of a preempted one)
                 // Daemons should do one iteration of work and return
-                (process.entry)();
+                let finished = sched::run_slice(&process, hart_id);
 
-                // Process returned - update stats
+                // Process returned or was preempted - update stats
                 let elapsed = (get_time_ms() as u64).saturating_sub(start_time);
                 process.add_cpu_time(elapsed);
 
@@ -124,9 +127,11 @@ pub(crate) fn hart_loop(hart_id: usize) -> ! {
                     cpu.clear_process(get_time_ms() as u64, elapsed);
                 }
 
-                // Requeue daemon processes for the next round
+                // Requeue daemon processes for the next round, and preempted
+                // processes to finish their tick, unless they exited or were
+                // killed during this slice
                 // Non-daemon processes are one-shot and exit
-                if process.is_daemon() {
+                if (process.is_daemon() || !finished) && process.state() != process::ProcessState::Zombie {
                     sched::requeue(process, hart_id);
                 } else {
                     sched::SCHEDULER.exit(process.pid, 0);
@@ -136,12 +141,20 @@ pub(crate) fn hart_loop(hart_id: usize) -> ! {
 
         // Hart 0 runs periodic tasks (log buffer flush, sysinfo update, etc.)
         if hart_id == 0 {
+            // Move queued work off overloaded harts
+            sched::SCHEDULER.rebalance_tick(get_time_ms() as u64);
             klogd::flush_log_buffer();
             klogd::klogd_tick();
             sysmond::sysmond_tick();
+            // Pick up edited config files
+            crate::settings::reload_if_changed();
+            crate::dns::reload_hosts_if_changed();
+            crate::net::firewall::reload_if_changed();
+            crate::x509::reload_trust_store_if_changed();
             // Update system info MMIO device (for emulator UI)
             update_sysinfo();
             // Process I/O requests from secondary harts
+            crate::platform::d1_mmc::service_transfers();
             io_router::dispatch_io();
         }
 
@@ -438,6 +451,12 @@ pub struct Cpu {
     /// Number of interrupts handled on this CPU
     pub interrupts: AtomicU64,
 
+    /// `busy_time_ms` at the last usage sample
+    busy_sampled: AtomicU64,
+
+    /// Recent busy share in permille (0-1000), see `sample_usage`
+    recent_usage: AtomicU32,
+
     /// Timestamp of when this CPU went idle (for idle time tracking)
     idle_start: AtomicU64,
 
@@ -464,6 +483,8 @@ impl Cpu {
             idle_time_ms: AtomicU64::new(0),
             context_switches: AtomicU64::new(0),
             interrupts: AtomicU64::new(0),
+            busy_sampled: AtomicU64::new(0),
+            recent_usage: AtomicU32::new(0),
             idle_start: AtomicU64::new(0),
             in_interrupt: AtomicBool::new(false),
             scheduler_context: UnsafeCell::new(Context::zero()),
@@ -577,6 +598,22 @@ impl Cpu {
         }
     }
 
+    /// Fold the busy time since the last call (`elapsed_ms` ago) into the
+    /// recent usage; sysmond calls it every USAGE_SAMPLE_MS
+    pub fn sample_usage(&self, elapsed_ms: u64) {
+        let busy = self.busy_time_ms.load(Ordering::Relaxed);
+        let used = busy.saturating_sub(self.busy_sampled.swap(busy, Ordering::Relaxed));
+        let recent = (used * 1000 / elapsed_ms.max(1)).min(1000) as u32;
+        let old = self.recent_usage.load(Ordering::Relaxed);
+        self.recent_usage.store(cpu::process::decay_usage(old, recent), Ordering::Relaxed);
+    }
+
+    /// Busy share over the last few seconds in permille (0-1000), unlike
+    /// `utilization`, which averages since boot
+    pub fn recent_usage(&self) -> u32 {
+        self.recent_usage.load(Ordering::Relaxed)
+    }
+
     /// Get total context switches
     pub fn context_switch_count(&self) -> u64 {
         self.context_switches.load(Ordering::Relaxed)
@@ -15,23 +15,25 @@
 //! }
 //! ```
 
-use alloc::vec::Vec;
 use smoltcp::wire::Ipv4Address;
 
-use crate::cpu::io_router::{DeviceType, IoOp, IoRequest, IoResult, request_io};
+use crate::cpu::io_router::{self, DeviceType};
 use crate::lock::utils::NET_STATE;
 
 // Timeout for I/O requests (5 seconds)
 const IO_TIMEOUT_MS: u64 = 5000;
 
 // ═══════════════════════════════════════════════════════════════════════════════
-// Helper: Submit I/O request to Hart 0
+// Helper: Run on Hart 0
 // ═══════════════════════════════════════════════════════════════════════════════
 
-/// Submit an I/O request and wait for the result (blocking).
-fn request_io_blocking(operation: IoOp) -> IoResult {
-    let request = IoRequest::new(DeviceType::Network, operation);
-    request_io(request, IO_TIMEOUT_MS)
+/// Run `f` on Hart 0 (directly if we already are Hart 0) and wait for the result.
+fn on_hart0<R, F>(f: F) -> Result<R, &'static str>
+where
+    R: Send + 'static,
+    F: FnOnce() -> R + Send + 'static,
+{
+    io_router::call(DeviceType::Network, IO_TIMEOUT_MS, f)
 }
 
 // ═══════════════════════════════════════════════════════════════════════════════
@@ -44,16 +46,12 @@ fn request_io_blocking(operation: IoOp) -> IoResult {
 /// On secondary harts: Delegates to Hart 0 via io_router
 #[inline]
 pub fn poll(timestamp_ms: i64) {
-    let hart_id = crate::get_hart_id();
-    
-    if hart_id == 0 {
+    let _ = on_hart0(move || {
         let mut net = NET_STATE.lock();
         if let Some(state) = net.as_mut() {
             state.poll(timestamp_ms);
         }
-    } else {
-        let _ = request_io_blocking(IoOp::NetPoll { timestamp_ms });
-    }
+    });
 }
 
 /// Check if IP has been assigned.
@@ -62,16 +60,7 @@ pub fn poll(timestamp_ms: i64) {
 /// On secondary harts: Delegates to Hart 0 via io_router
 #[inline]
 pub fn is_ip_assigned() -> bool {
-    let hart_id = crate::get_hart_id();
-    
-    if hart_id == 0 {
-        crate::net::is_ip_assigned()
-    } else {
-        match request_io_blocking(IoOp::NetIsIpAssigned) {
-            IoResult::Ok(data) => data.first() == Some(&1),
-            IoResult::Err(_) => false,
-        }
-    }
+    on_hart0(crate::net::is_ip_assigned).unwrap_or(false)
 }
 
 /// Get the assigned IP address.
@@ -80,16 +69,5 @@ pub fn is_ip_assigned() -> bool {
 /// On secondary harts: Delegates to Hart 0 via io_router
 #[inline]
 pub fn get_ip() -> Ipv4Address {
-    let hart_id = crate::get_hart_id();
-    
-    if hart_id == 0 {
-        crate::net::get_my_ip()
-    } else {
-        match request_io_blocking(IoOp::NetGetIp) {
-            IoResult::Ok(data) if data.len() == 4 => {
-                Ipv4Address::new(data[0], data[1], data[2], data[3])
-            }
-            _ => Ipv4Address::new(0, 0, 0, 0),
-        }
-    }
+    on_hart0(crate::net::get_my_ip).unwrap_or(Ipv4Address::new(0, 0, 0, 0))
 }
@@ -21,14 +21,16 @@
 //!          Blocked    Exit
 //! ```
 
+use crate::cpu::cgroup::GroupId;
+use crate::syscall_trace::TraceMode;
 use crate::Spinlock;
 use alloc::boxed::Box;
-use alloc::collections::BTreeMap;
+use alloc::collections::{BTreeMap, VecDeque};
 use alloc::string::String;
 use alloc::sync::Arc;
 use alloc::vec::Vec;
 use core::cell::UnsafeCell;
-use core::sync::atomic::{AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use core::sync::atomic::{AtomicBool, AtomicI32, AtomicPtr, AtomicU32, AtomicU64, AtomicU8, AtomicUsize, Ordering};
 
 // Include the context switch assembly
 core::arch::global_asm!(include_str!("switch_context.S"));
@@ -44,6 +46,12 @@ extern "C" {
     /// Both pointers must be valid Context structures.
     /// The new context must have a valid stack pointer and return address.
     pub fn switch_context(old: *mut Context, new: *mut Context);
+
+    /// Save f0-f31 and fcsr into `state` (slot 32 is fcsr)
+    pub fn save_fp_state(state: *mut [u64; 33]);
+
+    /// Load f0-f31 and fcsr from `state`
+    pub fn restore_fp_state(state: *const [u64; 33]);
 }
 
 // ═══════════════════════════════════════════════════════════════════════════════
@@ -151,6 +159,35 @@ impl Default for Priority {
     }
 }
 
+/// Nice value range: lower is more CPU, like Unix
+pub const NICE_MIN: i32 = -20;
+pub const NICE_MAX: i32 = 19;
+
+/// Scheduling weight of nice 0; a process is charged virtual time scaled
+/// by NICE_0_WEIGHT / its weight
+pub const NICE_0_WEIGHT: u64 = 1024;
+
+/// Weight for each nice value from -20 to 19: every step is about 1.25x,
+/// so one nice level is roughly 10% CPU between two busy processes
+const NICE_TO_WEIGHT: [u64; 40] = [
+    88761, 71755, 56483, 46273, 36291, 29154, 23254, 18705, 14949, 11916,
+    9548, 7620, 6100, 4904, 3906, 3121, 2501, 1991, 1586, 1277,
+    1024, 820, 655, 526, 423, 335, 272, 215, 172, 137,
+    110, 87, 70, 56, 45, 36, 29, 23, 18, 15,
+];
+
+impl Priority {
+    /// Nice offset the class adds within the weighted (non real-time,
+    /// non idle) level
+    fn nice_offset(self) -> i32 {
+        match self {
+            Priority::Low => 5,
+            Priority::High => -5,
+            _ => 0,
+        }
+    }
+}
+
 // ═══════════════════════════════════════════════════════════════════════════════
 // PROCESS FLAGS
 // ═══════════════════════════════════════════════════════════════════════════════
@@ -180,18 +217,272 @@ impl Default for ProcessFlags {
     }
 }
 
+// ═══════════════════════════════════════════════════════════════════════════════
+// FILE DESCRIPTORS
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Open for reading only
+pub const O_RDONLY: u32 = 0;
+/// Open for writing only
+pub const O_WRONLY: u32 = 1;
+/// Open for reading and writing
+pub const O_RDWR: u32 = 2;
+/// Mask for the access mode bits
+pub const O_ACCMODE: u32 = 3;
+/// Create the file if it does not exist
+pub const O_CREAT: u32 = 0x40;
+/// Truncate the file to zero length on open
+pub const O_TRUNC: u32 = 0x200;
+/// Every write goes to the end of the file
+pub const O_APPEND: u32 = 0x400;
+
+/// Seek relative to the start of the file
+pub const SEEK_SET: u32 = 0;
+/// Seek relative to the current position
+pub const SEEK_CUR: u32 = 1;
+/// Seek relative to the end of the file
+pub const SEEK_END: u32 = 2;
+
+/// First descriptor handed out (0-2 are reserved for stdio)
+pub const FD_FIRST: i32 = 3;
+/// Maximum open files per process
+pub const MAX_FDS: usize = 32;
+
+/// An open file.
+///
+/// Only the path and offset are kept: reads and writes go straight to the
+/// filesystem at the offset (see `fs_proxy::fs_read_at`/`fs_write_at`), so
+/// files can be larger than the heap and appended to without rewriting.
+///
+/// A watch fd (see `fs::watch`) is an OpenFile with `watch` set;
+/// a pipe fd (the output of a WASM pipeline) one with `pipe` set.
+#[derive(Clone)]
+pub struct OpenFile {
+    /// Absolute path the file was opened with
+    pub path: String,
+    /// Open flags (O_*)
+    pub flags: u32,
+    /// Current file offset
+    pub pos: usize,
+    /// Watch read through this descriptor instead of the file
+    pub watch: Option<crate::fs::watch::WatchId>,
+    /// Pipe read through this descriptor instead of the file
+    pub pipe: Option<Arc<crate::cpu::ipc::Pipe>>,
+}
+
+impl OpenFile {
+    /// Create an open file at offset 0
+    pub fn new(path: &str, flags: u32) -> Self {
+        Self {
+            path: String::from(path),
+            flags,
+            pos: 0,
+            watch: None,
+            pipe: None,
+        }
+    }
+
+    /// Create a descriptor reading the events of a watch on `path`
+    pub fn watch(path: &str, id: crate::fs::watch::WatchId) -> Self {
+        let mut file = Self::new(path, O_RDONLY);
+        file.watch = Some(id);
+        file
+    }
+
+    /// Create a descriptor reading from `pipe`
+    pub fn pipe(path: &str, pipe: Arc<crate::cpu::ipc::Pipe>) -> Self {
+        let mut file = Self::new(path, O_RDONLY);
+        file.pipe = Some(pipe);
+        file
+    }
+
+    /// Whether the file was opened for reading
+    pub fn readable(&self) -> bool {
+        self.flags & O_ACCMODE != O_WRONLY
+    }
+
+    /// Whether the file was opened for writing
+    pub fn writable(&self) -> bool {
+        self.flags & O_ACCMODE != O_RDONLY
+    }
+
+    /// Whether every write goes to the end of the file
+    pub fn appends(&self) -> bool {
+        self.flags & O_APPEND != 0
+    }
+
+    /// Move the file offset given the file's current length, returning the
+    /// new offset
+    pub fn seek(&mut self, offset: i64, whence: u32, len: usize) -> Result<usize, &'static str> {
+        let base = match whence {
+            SEEK_SET => 0,
+            SEEK_CUR => self.pos as i64,
+            SEEK_END => len as i64,
+            _ => return Err("Invalid whence"),
+        };
+        let pos = base.checked_add(offset).ok_or("Invalid offset")?;
+        if pos < 0 {
+            return Err("Invalid offset");
+        }
+        self.pos = pos as usize;
+        Ok(self.pos)
+    }
+}
+
+/// Per-process table of open files
+pub struct FdTable {
+    files: BTreeMap<i32, OpenFile>,
+}
+
+impl FdTable {
+    /// Create an empty descriptor table
+    pub const fn new() -> Self {
+        Self {
+            files: BTreeMap::new(),
+        }
+    }
+
+    /// Install an open file at the lowest free descriptor
+    pub fn insert(&mut self, file: OpenFile) -> Result<i32, &'static str> {
+        if self.files.len() >= MAX_FDS {
+            return Err("Too many open files");
+        }
+        let mut fd = FD_FIRST;
+        while self.files.contains_key(&fd) {
+            fd += 1;
+        }
+        self.files.insert(fd, file);
+        Ok(fd)
+    }
+
+    /// Get an open file by descriptor
+    pub fn get_mut(&mut self, fd: i32) -> Option<&mut OpenFile> {
+        self.files.get_mut(&fd)
+    }
+
+    /// Remove a descriptor, returning the file so the caller can flush it
+    pub fn remove(&mut self, fd: i32) -> Option<OpenFile> {
+        self.files.remove(&fd)
+    }
+
+    /// Remove all descriptors (used when a process exits)
+    pub fn take_all(&mut self) -> Vec<OpenFile> {
+        core::mem::take(&mut self.files).into_values().collect()
+    }
+
+    /// Number of open descriptors
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+}
+
+impl Default for FdTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// CPU AFFINITY MASK
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Set of harts a process may run on (bit N = hart N)
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct CpuMask(pub u128);
+
+impl CpuMask {
+    /// Any hart
+    pub const ALL: CpuMask = CpuMask(u128::MAX);
+
+    /// Every hart but hart 0, which owns MMIO and runs the periodic kernel work
+    pub const SECONDARY: CpuMask = CpuMask(u128::MAX << 1);
+
+    /// Just `hart`
+    pub const fn single(hart: usize) -> Self {
+        CpuMask(1 << hart)
+    }
+
+    pub fn contains(self, hart: usize) -> bool {
+        hart < 128 && self.0 & (1 << hart) != 0
+    }
+
+    pub fn is_all(self) -> bool {
+        self == Self::ALL
+    }
+
+    /// Harts in both masks
+    pub fn intersect(self, other: CpuMask) -> CpuMask {
+        CpuMask(self.0 & other.0)
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Parse a hart list such as `1-3,5`, or `all`
+    pub fn parse(text: &str) -> Option<Self> {
+        if text == "all" {
+            return Some(Self::ALL);
+        }
+        let mut mask = 0u128;
+        for part in text.split(',') {
+            let (first, last) = match part.split_once('-') {
+                Some((first, last)) => (first.parse::<usize>().ok()?, last.parse::<usize>().ok()?),
+                None => {
+                    let hart = part.parse::<usize>().ok()?;
+                    (hart, hart)
+                }
+            };
+            if first > last || last >= 128 {
+                return None;
+            }
+            for hart in first..=last {
+                mask |= 1 << hart;
+            }
+        }
+        (mask != 0).then_some(CpuMask(mask))
+    }
+
+    /// Hart list in the form `parse` reads (`all` for ALL)
+    pub fn to_list(self) -> String {
+        if self.is_all() {
+            return String::from("all");
+        }
+        let mut out = String::new();
+        let mut hart = 0;
+        while hart < 128 {
+            if !self.contains(hart) {
+                hart += 1;
+                continue;
+            }
+            let first = hart;
+            while hart + 1 < 128 && self.contains(hart + 1) {
+                hart += 1;
+            }
+            if !out.is_empty() {
+                out.push(',');
+            }
+            out.push_str(&if first == hart { alloc::format!("{}", first) } else { alloc::format!("{}-{}", first, hart) });
+            hart += 1;
+        }
+        out
+    }
+}
+
 // ═══════════════════════════════════════════════════════════════════════════════
 // CONTEXT (CPU Register State for Context Switching)
 // ═══════════════════════════════════════════════════════════════════════════════
 
-/// Default kernel stack size per process (4KB)
-pub const KSTACK_SIZE: usize = 4096;
+/// Kernel stack size per process (as large as a hart's boot stack: ticks
+/// run WASM programs and TLS handshakes on it)
+pub const KSTACK_SIZE: usize = 128 * 1024;
 
 /// Saved CPU context for context switching.
 ///
 /// On RISC-V, we save all callee-saved registers plus ra (return address)
 /// and sp (stack pointer). The caller-saved registers are saved by the
-/// caller before calling switch_context, so we don't need them here.
+/// caller before calling switch_context, so we don't need them here (a
+/// preempted process has them in its trap frame).
 ///
 /// Layout matches what switch_context.S expects.
 #[repr(C)]
@@ -203,6 +494,8 @@ pub struct Context {
     pub sp: u64,
     /// Callee-saved registers s0-s11
     pub s: [u64; 12],
+    /// Callee-saved floating point registers fs0-fs11
+    pub fs: [u64; 12],
 }
 
 impl Context {
@@ -212,6 +505,7 @@ impl Context {
             ra: 0,
             sp: 0,
             s: [0; 12],
+            fs: [0; 12],
         }
     }
 
@@ -221,6 +515,7 @@ impl Context {
             ra: entry,
             sp,
             s: [0; 12],
+            fs: [0; 12],
         }
     }
 }
@@ -238,6 +533,123 @@ impl Default for Context {
 /// Process entry point function type
 pub type ProcessEntry = fn();
 
+// ═══════════════════════════════════════════════════════════════════════════════
+// I/O ACCOUNTING
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Kind of I/O a syscall did
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum IoKind {
+    FsRead,
+    FsWrite,
+    NetRx,
+    NetTx,
+}
+
+/// I/O counters of a process or shell command
+#[derive(Clone, Copy, Default)]
+pub struct IoCounters {
+    pub fs_read_bytes: u64,
+    pub fs_write_bytes: u64,
+    pub net_rx_bytes: u64,
+    pub net_tx_bytes: u64,
+    pub syscalls: u64,
+}
+
+impl IoCounters {
+    pub const fn new() -> Self {
+        Self { fs_read_bytes: 0, fs_write_bytes: 0, net_rx_bytes: 0, net_tx_bytes: 0, syscalls: 0 }
+    }
+
+    /// Count one syscall that moved `bytes` of `kind` I/O
+    pub fn add(&mut self, kind: Option<IoKind>, bytes: u64) {
+        self.syscalls += 1;
+        match kind {
+            Some(IoKind::FsRead) => self.fs_read_bytes += bytes,
+            Some(IoKind::FsWrite) => self.fs_write_bytes += bytes,
+            Some(IoKind::NetRx) => self.net_rx_bytes += bytes,
+            Some(IoKind::NetTx) => self.net_tx_bytes += bytes,
+            None => {}
+        }
+    }
+}
+
+/// Lock-free I/O counters kept in the PCB
+pub struct IoStats {
+    fs_read_bytes: AtomicU64,
+    fs_write_bytes: AtomicU64,
+    net_rx_bytes: AtomicU64,
+    net_tx_bytes: AtomicU64,
+    syscalls: AtomicU64,
+}
+
+impl IoStats {
+    pub const fn new() -> Self {
+        Self {
+            fs_read_bytes: AtomicU64::new(0),
+            fs_write_bytes: AtomicU64::new(0),
+            net_rx_bytes: AtomicU64::new(0),
+            net_tx_bytes: AtomicU64::new(0),
+            syscalls: AtomicU64::new(0),
+        }
+    }
+
+    /// Count one syscall that moved `bytes` of `kind` I/O
+    pub fn add(&self, kind: Option<IoKind>, bytes: u64) {
+        self.syscalls.fetch_add(1, Ordering::Relaxed);
+        let counter = match kind {
+            Some(IoKind::FsRead) => &self.fs_read_bytes,
+            Some(IoKind::FsWrite) => &self.fs_write_bytes,
+            Some(IoKind::NetRx) => &self.net_rx_bytes,
+            Some(IoKind::NetTx) => &self.net_tx_bytes,
+            None => return,
+        };
+        counter.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> IoCounters {
+        IoCounters {
+            fs_read_bytes: self.fs_read_bytes.load(Ordering::Relaxed),
+            fs_write_bytes: self.fs_write_bytes.load(Ordering::Relaxed),
+            net_rx_bytes: self.net_rx_bytes.load(Ordering::Relaxed),
+            net_tx_bytes: self.net_tx_bytes.load(Ordering::Relaxed),
+            syscalls: self.syscalls.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Charge a syscall to the program that made it: the foreground shell
+/// command if it runs on this hart, else the process scheduled on this hart
+pub fn charge_syscall(kind: Option<IoKind>, bytes: u64) {
+    if crate::scripting::shell_cmd_charge(kind, bytes) {
+        return;
+    }
+    let hart = crate::get_hart_id();
+    let Some(pid) = crate::cpu::CPU_TABLE.get(hart).and_then(|cpu| cpu.running_process()) else {
+        return;
+    };
+    if let Some(process) = PROCESS_TABLE.get(pid) {
+        process.io.add(kind, bytes);
+    }
+}
+
+/// How often sysmond samples per-process CPU usage (ms)
+pub const USAGE_SAMPLE_MS: i64 = 1000;
+
+/// Weight of the history in the usage average: each new sample counts for
+/// 1/USAGE_DECAY, so the average follows roughly the last few seconds
+const USAGE_DECAY: u32 = 4;
+
+/// Fold the usage of the last sampling window (permille) into a running
+/// average (used for processes and harts alike)
+pub fn decay_usage(average: u32, recent: u32) -> u32 {
+    (average * (USAGE_DECAY - 1) + recent) / USAGE_DECAY
+}
+
+/// Minimum time between two load-balancer migrations of the same process,
+/// so a process is not bounced back and forth between harts
+pub const MIGRATION_COOLDOWN_MS: u64 = 2000;
+
 /// Process Control Block - the kernel's representation of a process
 ///
 /// This is analogous to Linux's `task_struct` but simplified for our needs.
@@ -251,14 +663,26 @@ pub struct Process {
     pub name: String,
     /// Parent process ID (0 for init)
     pub ppid: Pid,
+    /// Process group; a negative PID signals every process in it
+    pgid: AtomicU32,
+    /// Control group whose CPU shares and memory cap apply (see `cgroup`)
+    cgroup: AtomicUsize,
+    /// Where its syscalls are logged (a `syscall_trace::TraceMode`)
+    trace: AtomicU8,
 
     // ─── Scheduling ─────────────────────────────────────────────────────────
     /// Current process state (atomic for cross-CPU visibility)
     state: AtomicUsize,
     /// Process priority
     pub priority: Priority,
-    /// CPU affinity (-1 = any CPU, else specific CPU ID)
-    pub cpu_affinity: AtomicUsize,
+    /// Nice value (NICE_MIN..=NICE_MAX), adjusts the share within the priority
+    nice: AtomicI32,
+    /// Weighted CPU time consumed (µs scaled by NICE_0_WEIGHT / weight);
+    /// the run queue picks the lowest
+    vruntime: AtomicU64,
+    /// Harts the process may run on (CpuMask, low and high 64 bits)
+    affinity_lo: AtomicU64,
+    affinity_hi: AtomicU64,
     /// CPU currently executing this process (usize::MAX if not running)
     pub current_cpu: AtomicUsize,
 
@@ -275,17 +699,56 @@ pub struct Process {
     /// Wrapped in UnsafeCell because it's mutated during context switch
     /// while the Process is behind an Arc.
     pub context: UnsafeCell<Context>,
-    /// Kernel stack for this process (heap allocated)
+    /// Kernel stack for this process (heap allocated, 16-byte aligned)
     /// The top of this stack is used as SP when context switching into the process.
-    pub kstack: Option<Box<[u8; KSTACK_SIZE]>>,
+    pub kstack: Box<[u128]>,
+    /// Hart whose timer switched this process out in the middle of a tick
+    /// (usize::MAX if it is between ticks); it resumes there
+    preempted_on: AtomicUsize,
+
+    // ─── Waiting ────────────────────────────────────────────────────────────
+    /// Event counter of the wait queue a blocked process sleeps on (null:
+    /// none, only the timeout wakes it)
+    wait_on: AtomicPtr<AtomicU64>,
+    /// Value of `wait_on` when the process went to sleep; any change wakes it
+    wait_seen: AtomicU64,
+    /// When a blocked process wakes regardless (ms since boot)
+    wake_at: AtomicU64,
+    /// The wait is for user input (see `lock::wait::WaitQueue::interactive`)
+    wait_interactive: AtomicBool,
+    /// Runs ahead of the other weighted processes on its hart until then
+    /// (ms since boot), after user input woke it
+    boost_until: AtomicU64,
+
+    // ─── Signals ────────────────────────────────────────────────────────────
+    /// Pending signals (bit N = signal N)
+    signals: AtomicU32,
+    /// A user binary is running in the process and takes its signals
+    runs_binary: AtomicBool,
+    /// The binary is stopped until SIGCONT
+    stopped: AtomicBool,
 
     // ─── Statistics ─────────────────────────────────────────────────────────
     /// Creation timestamp (ms since boot)
     pub created_at: u64,
     /// Total CPU time consumed (ms)
     pub cpu_time_ms: AtomicU64,
+    /// Decayed recent CPU usage, in permille of one hart
+    pub cpu_usage: AtomicU32,
+    /// `cpu_time_ms` at the last usage sample
+    pub cpu_time_sampled: AtomicU64,
     /// Number of times scheduled
     pub schedule_count: AtomicU64,
+    /// Number of times the load balancer moved this process to another hart
+    pub migrations: AtomicU32,
+    /// Time of the last migration (ms since boot)
+    pub migrated_at: AtomicU64,
+    /// Syscall and I/O byte counters
+    pub io: IoStats,
+
+    // ─── Resources ──────────────────────────────────────────────────────────
+    /// Open file descriptors
+    pub fds: Spinlock<FdTable>,
 }
 
 // SAFETY: Process uses UnsafeCell for context, but context is only accessed
@@ -310,8 +773,9 @@ impl Process {
     /// The process context is initialized so that when switched to,
     /// it will start executing at the entry point function.
     pub fn new(pid: Pid, name: &str, entry: ProcessEntry) -> Self {
-        // Allocate kernel stack
-        let kstack = Box::new([0u8; KSTACK_SIZE]);
+        // Allocate kernel stack (on the heap directly: it is too big to
+        // build on the current stack first)
+        let kstack = alloc::vec![0u128; KSTACK_SIZE / 16].into_boxed_slice();
         // Stack grows down on RISC-V, so SP points to top of stack
         let stack_top = kstack.as_ptr() as u64 + KSTACK_SIZE as u64;
         // Initialize context to start at entry function with proper stack
@@ -321,18 +785,39 @@ impl Process {
             pid,
             name: String::from(name),
             ppid: 0,
+            pgid: AtomicU32::new(pid),
+            cgroup: AtomicUsize::new(crate::cpu::cgroup::ROOT),
+            trace: AtomicU8::new(TraceMode::Off as u8),
             state: AtomicUsize::new(ProcessState::Created as usize),
             priority: Priority::Normal,
-            cpu_affinity: AtomicUsize::new(usize::MAX), // Any CPU
+            nice: AtomicI32::new(0),
+            vruntime: AtomicU64::new(0),
+            affinity_lo: AtomicU64::new(u64::MAX), // Any CPU
+            affinity_hi: AtomicU64::new(u64::MAX),
             current_cpu: AtomicUsize::new(usize::MAX),  // Not running
             entry,
             flags: ProcessFlags::empty(),
             exit_code: AtomicUsize::new(0),
             context: UnsafeCell::new(context),
-            kstack: Some(kstack),
+            kstack,
+            preempted_on: AtomicUsize::new(usize::MAX),
+            wait_on: AtomicPtr::new(core::ptr::null_mut()),
+            wait_seen: AtomicU64::new(0),
+            wake_at: AtomicU64::new(u64::MAX),
+            wait_interactive: AtomicBool::new(false),
+            boost_until: AtomicU64::new(0),
+            signals: AtomicU32::new(0),
+            runs_binary: AtomicBool::new(false),
+            stopped: AtomicBool::new(false),
             created_at: crate::get_time_ms() as u64,
             cpu_time_ms: AtomicU64::new(0),
+            cpu_usage: AtomicU32::new(0),
+            cpu_time_sampled: AtomicU64::new(0),
             schedule_count: AtomicU64::new(0),
+            migrations: AtomicU32::new(0),
+            migrated_at: AtomicU64::new(0),
+            io: IoStats::new(),
+            fds: Spinlock::new(FdTable::new()),
         }
     }
 
@@ -391,32 +876,249 @@ impl Process {
         self.set_state(ProcessState::Zombie);
     }
 
+    /// Exit status for the parent (valid when Zombie)
+    pub fn exit_status(&self) -> ExitStatus {
+        ExitStatus {
+            pid: self.pid,
+            ppid: self.ppid,
+            code: self.exit_code.load(Ordering::Acquire) as i32,
+        }
+    }
+
     // ─── CPU Affinity ───────────────────────────────────────────────────────
 
+    /// Restrict the process to the harts in `mask` (the two halves are
+    /// stored separately, so a reader racing a change may briefly see a mix)
+    pub fn set_affinity(&self, mask: CpuMask) {
+        self.affinity_lo.store(mask.0 as u64, Ordering::Release);
+        self.affinity_hi.store((mask.0 >> 64) as u64, Ordering::Release);
+    }
+
+    /// Harts the process may run on
+    pub fn affinity(&self) -> CpuMask {
+        let lo = self.affinity_lo.load(Ordering::Acquire) as u128;
+        let hi = self.affinity_hi.load(Ordering::Acquire) as u128;
+        CpuMask(hi << 64 | lo)
+    }
+
     /// Set CPU affinity (restrict to specific CPU)
     pub fn set_cpu_affinity(&self, cpu_id: usize) {
-        self.cpu_affinity.store(cpu_id, Ordering::Release);
+        self.set_affinity(CpuMask::single(cpu_id));
     }
 
     /// Clear CPU affinity (can run on any CPU)
     pub fn clear_cpu_affinity(&self) {
-        self.cpu_affinity.store(usize::MAX, Ordering::Release);
+        self.set_affinity(CpuMask::ALL);
     }
 
-    /// Get CPU affinity (usize::MAX = any)
-    pub fn get_cpu_affinity(&self) -> Option<usize> {
-        let affinity = self.cpu_affinity.load(Ordering::Acquire);
-        if affinity == usize::MAX {
-            None
-        } else {
-            Some(affinity)
+    /// Check if process can run on specified CPU (a preempted process only
+    /// on the hart it was preempted on, whatever its mask: its registers are
+    /// on that hart's terms)
+    pub fn can_run_on_cpu(&self, cpu_id: usize) -> bool {
+        match self.preempted_on() {
+            Some(hart) => hart == cpu_id,
+            None => self.affinity().contains(cpu_id),
         }
     }
 
-    /// Check if process can run on specified CPU
-    pub fn can_run_on_cpu(&self, cpu_id: usize) -> bool {
-        let affinity = self.cpu_affinity.load(Ordering::Acquire);
-        affinity == usize::MAX || affinity == cpu_id
+    /// Whether the load balancer may move this process (to a hart its mask
+    /// allows): it is not in the middle of a preempted tick and was not
+    /// migrated within the last MIGRATION_COOLDOWN_MS
+    pub fn can_migrate(&self, now_ms: u64) -> bool {
+        self.preempted_on().is_none()
+            && (self.migrations() == 0
+                || now_ms.saturating_sub(self.migrated_at.load(Ordering::Relaxed)) >= MIGRATION_COOLDOWN_MS)
+    }
+
+    /// Record a move to another hart's run queue
+    pub fn note_migration(&self, now_ms: u64) {
+        self.migrated_at.store(now_ms, Ordering::Relaxed);
+        self.migrations.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Number of load-balancer migrations
+    pub fn migrations(&self) -> u32 {
+        self.migrations.load(Ordering::Relaxed)
+    }
+
+    // ─── Nice & Weights ─────────────────────────────────────────────────────
+
+    /// Current nice value
+    pub fn nice(&self) -> i32 {
+        self.nice.load(Ordering::Relaxed)
+    }
+
+    /// Set the nice value (clamped to NICE_MIN..=NICE_MAX); returns the old one
+    pub fn set_nice(&self, nice: i32) -> i32 {
+        self.nice.swap(nice.clamp(NICE_MIN, NICE_MAX), Ordering::Relaxed)
+    }
+
+    /// Scheduling weight from the priority class and nice value
+    pub fn weight(&self) -> u64 {
+        let nice = (self.nice() + self.priority.nice_offset()).clamp(NICE_MIN, NICE_MAX);
+        NICE_TO_WEIGHT[(nice - NICE_MIN) as usize]
+    }
+
+    /// Weighted CPU time consumed so far
+    pub fn vruntime(&self) -> u64 {
+        self.vruntime.load(Ordering::Relaxed)
+    }
+
+    /// Charge `us` microseconds of CPU time, scaled by the weight (shared
+    /// with the rest of the control group)
+    pub fn charge_vruntime(&self, us: u64) {
+        let weight = crate::cpu::cgroup::scale_weight(self.cgroup(), self.weight());
+        self.vruntime.fetch_add(us * NICE_0_WEIGHT / weight, Ordering::Relaxed);
+    }
+
+    /// Raise the virtual time to at least `floor`, so a newcomer (or a
+    /// process that sat idle) does not monopolize the queue catching up
+    pub fn lift_vruntime(&self, floor: u64) {
+        self.vruntime.fetch_max(floor, Ordering::Relaxed);
+    }
+
+    /// Let the process run ahead of the other weighted ones until `until_ms`
+    pub fn boost(&self, until_ms: u64) {
+        self.boost_until.fetch_max(until_ms, Ordering::Relaxed);
+    }
+
+    /// Whether the process is boosted at `now_ms`, or due a boost because
+    /// the user input it waits for has arrived
+    pub fn boosted(&self, now_ms: u64) -> bool {
+        now_ms < self.boost_until.load(Ordering::Relaxed) || self.woken_by_input()
+    }
+
+    // ─── Preemption ─────────────────────────────────────────────────────────
+
+    /// Hart the process was preempted on, if it is in the middle of a tick
+    pub fn preempted_on(&self) -> Option<usize> {
+        let hart = self.preempted_on.load(Ordering::Acquire);
+        (hart != usize::MAX).then_some(hart)
+    }
+
+    /// Record that the tick was switched out on `hart` (None: it finished)
+    pub fn set_preempted_on(&self, hart: Option<usize>) {
+        self.preempted_on.store(hart.unwrap_or(usize::MAX), Ordering::Release);
+    }
+
+    /// Initial stack pointer (the stack grows down from here)
+    pub fn kstack_top(&self) -> u64 {
+        self.kstack.as_ptr() as u64 + (self.kstack.len() * 16) as u64
+    }
+
+    // ─── Waiting ────────────────────────────────────────────────────────────
+
+    /// Mark the process blocked until `events` moves past `seen` or the
+    /// clock reaches `wake_at` (ms since boot)
+    pub fn block_on(&self, events: &'static AtomicU64, seen: u64, wake_at: u64) {
+        self.wait_on.store(events as *const AtomicU64 as *mut AtomicU64, Ordering::Relaxed);
+        self.wait_seen.store(seen, Ordering::Relaxed);
+        self.wake_at.store(wake_at, Ordering::Relaxed);
+        self.mark_blocked();
+    }
+
+    /// Mark the wait as one for user input: the process is boosted when
+    /// the input arrives
+    pub fn set_wait_interactive(&self, interactive: bool) {
+        self.wait_interactive.store(interactive, Ordering::Relaxed);
+    }
+
+    /// Whether a blocked process has something to wake up for at `now_ms`
+    pub fn wake_due(&self, now_ms: u64) -> bool {
+        if self.state() != ProcessState::Blocked {
+            return false;
+        }
+        now_ms >= self.wake_at.load(Ordering::Relaxed) || self.event_arrived()
+    }
+
+    /// Whether a blocked process waiting for user input has been woken by it
+    pub fn woken_by_input(&self) -> bool {
+        self.wait_interactive.load(Ordering::Relaxed) && self.state() == ProcessState::Blocked && self.event_arrived()
+    }
+
+    /// Whether the wait queue the process sleeps on has been woken since
+    fn event_arrived(&self) -> bool {
+        let events = self.wait_on.load(Ordering::Relaxed);
+        // SAFETY: block_on only takes 'static counters
+        !events.is_null() && unsafe { &*events }.load(Ordering::Acquire) != self.wait_seen.load(Ordering::Relaxed)
+    }
+
+    /// Forget the wait once the process runs again
+    pub fn end_wait(&self) {
+        self.wait_on.store(core::ptr::null_mut(), Ordering::Relaxed);
+        self.wake_at.store(u64::MAX, Ordering::Relaxed);
+        self.wait_interactive.store(false, Ordering::Relaxed);
+    }
+
+    // ─── Signals ────────────────────────────────────────────────────────────
+
+    /// Make signal `sig` pending
+    pub fn raise(&self, sig: u32) {
+        self.signals.fetch_or(1 << sig, Ordering::AcqRel);
+    }
+
+    /// Pending signals (bit N = signal N)
+    pub fn pending_signals(&self) -> u32 {
+        self.signals.load(Ordering::Acquire)
+    }
+
+    /// Take the pending signals in `mask`, returning them
+    pub fn take_signals(&self, mask: u32) -> u32 {
+        self.signals.fetch_and(!mask, Ordering::AcqRel) & mask
+    }
+
+    /// Note that a user binary starts or stops running in the process;
+    /// signals left from before are dropped either way
+    pub fn set_runs_binary(&self, running: bool) {
+        self.signals.store(0, Ordering::Release);
+        self.stopped.store(false, Ordering::Release);
+        self.runs_binary.store(running, Ordering::Release);
+    }
+
+    /// Note that the binary stopped (SIGSTOP, SIGTSTP) or was continued
+    pub fn set_stopped(&self, stopped: bool) {
+        self.stopped.store(stopped, Ordering::Release);
+    }
+
+    /// Whether the binary is stopped, waiting for SIGCONT
+    pub fn stopped(&self) -> bool {
+        self.stopped.load(Ordering::Acquire)
+    }
+
+    /// Whether a user binary is running in the process
+    pub fn runs_binary(&self) -> bool {
+        self.runs_binary.load(Ordering::Acquire)
+    }
+
+    /// Process group the process belongs to
+    pub fn pgid(&self) -> Pid {
+        self.pgid.load(Ordering::Acquire)
+    }
+
+    /// Move the process to group `pgid`
+    pub fn set_pgid(&self, pgid: Pid) {
+        self.pgid.store(pgid, Ordering::Release);
+    }
+
+    /// Control group the process belongs to
+    pub fn cgroup(&self) -> GroupId {
+        self.cgroup.load(Ordering::Relaxed)
+    }
+
+    /// Put the process in control group `id` (see `cgroup::join`, which
+    /// also moves its heap)
+    pub fn set_cgroup(&self, id: GroupId) {
+        self.cgroup.store(id, Ordering::Relaxed);
+    }
+
+    /// Where the process's syscalls are traced
+    pub fn trace(&self) -> TraceMode {
+        TraceMode::from_u8(self.trace.load(Ordering::Relaxed))
+    }
+
+    /// Start or stop tracing the process's syscalls; returns the old mode
+    pub fn set_trace(&self, mode: TraceMode) -> TraceMode {
+        TraceMode::from_u8(self.trace.swap(mode as u8, Ordering::Relaxed))
     }
 
     // ─── Statistics ─────────────────────────────────────────────────────────
@@ -431,6 +1133,21 @@ impl Process {
         self.cpu_time_ms.load(Ordering::Relaxed)
     }
 
+    /// Fold the CPU time used since the last sample, taken `elapsed_ms` ago,
+    /// into the decayed usage
+    pub fn sample_cpu_usage(&self, elapsed_ms: u64) {
+        let total = self.cpu_time();
+        let used = total.saturating_sub(self.cpu_time_sampled.swap(total, Ordering::Relaxed));
+        let recent = (used * 1000 / elapsed_ms.max(1)).min(1000) as u32;
+        let old = self.cpu_usage.load(Ordering::Relaxed);
+        self.cpu_usage.store(decay_usage(old, recent), Ordering::Relaxed);
+    }
+
+    /// Recent CPU usage in permille of one hart (0-1000)
+    pub fn cpu_usage(&self) -> u32 {
+        self.cpu_usage.load(Ordering::Relaxed)
+    }
+
     /// Get current CPU (usize::MAX if not running)
     pub fn current_cpu(&self) -> Option<usize> {
         let cpu = self.current_cpu.load(Ordering::Acquire);
@@ -453,6 +1170,11 @@ impl Process {
         self.flags.contains(ProcessFlags::DAEMON)
     }
 
+    /// Check if this is the init process
+    pub fn is_init(&self) -> bool {
+        self.flags.contains(ProcessFlags::INIT)
+    }
+
     /// Check if process should restart on exit
     pub fn should_restart(&self) -> bool {
         self.flags.contains(ProcessFlags::RESTART_ON_EXIT)
@@ -468,13 +1190,22 @@ impl Process {
 pub struct ProcessInfo {
     pub pid: Pid,
     pub ppid: Pid,
+    pub pgid: Pid,
+    /// Control group
+    pub cgroup: GroupId,
     pub name: String,
     pub state: ProcessState,
     pub priority: Priority,
+    pub nice: i32,
     pub cpu: Option<usize>,
     pub cpu_time_ms: u64,
+    /// Recent CPU usage in permille of one hart
+    pub cpu_usage: u32,
     pub uptime_ms: u64,
     pub flags: ProcessFlags,
+    pub io: IoCounters,
+    /// The binary in it is stopped
+    pub stopped: bool,
 }
 
 impl Process {
@@ -483,13 +1214,19 @@ impl Process {
         ProcessInfo {
             pid: self.pid,
             ppid: self.ppid,
+            pgid: self.pgid(),
+            cgroup: self.cgroup(),
             name: self.name.clone(),
             state: self.state(),
             priority: self.priority,
+            nice: self.nice(),
             cpu: self.current_cpu(),
             cpu_time_ms: self.cpu_time(),
+            cpu_usage: self.cpu_usage(),
             uptime_ms: current_time.saturating_sub(self.created_at),
             flags: self.flags,
+            io: self.io.snapshot(),
+            stopped: self.stopped(),
         }
     }
 }
@@ -498,10 +1235,28 @@ impl Process {
 // PROCESS TABLE
 // ═══════════════════════════════════════════════════════════════════════════════
 
+/// How many reaped processes' exit statuses are kept for their parents
+const MAX_EXIT_STATUSES: usize = 64;
+
+/// How many exited children a live parent keeps as zombies before the
+/// oldest are reaped without waiting for it
+const MAX_ZOMBIES_PER_PARENT: usize = 32;
+
+/// Exit status of a process, kept until its parent waits for it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ExitStatus {
+    pub pid: Pid,
+    pub ppid: Pid,
+    pub code: i32,
+}
+
 /// Global process table
 pub struct ProcessTable {
     /// All processes indexed by PID
     processes: Spinlock<BTreeMap<Pid, Arc<Process>>>,
+    /// Exit statuses of processes already removed from `processes` that no
+    /// parent has waited for yet (oldest dropped past MAX_EXIT_STATUSES)
+    exited: Spinlock<VecDeque<ExitStatus>>,
 }
 
 impl ProcessTable {
@@ -509,6 +1264,7 @@ impl ProcessTable {
     pub const fn new() -> Self {
         Self {
             processes: Spinlock::new(BTreeMap::new()),
+            exited: Spinlock::new(VecDeque::new()),
         }
     }
 
@@ -519,6 +1275,7 @@ impl ProcessTable {
 
     /// Unregister a process
     pub fn unregister(&self, pid: Pid) -> Option<Arc<Process>> {
+        crate::allocator::forget_process(pid);
         self.processes.lock().remove(&pid)
     }
 
@@ -527,6 +1284,12 @@ impl ProcessTable {
         self.processes.lock().get(&pid).cloned()
     }
 
+    /// Get a process by PID without waiting for the table lock (for the
+    /// out-of-memory path); None if it is busy
+    pub fn try_get(&self, pid: Pid) -> Option<Arc<Process>> {
+        self.processes.try_lock()?.get(&pid).cloned()
+    }
+
     /// List all processes
     pub fn list(&self) -> Vec<Arc<Process>> {
         self.processes.lock().values().cloned().collect()
@@ -550,23 +1313,106 @@ impl ProcessTable {
             .collect()
     }
 
-    /// Reap zombie processes (remove from table)
+    /// Whether `ppid` is a live process that can still wait for its children
+    /// (the kernel, PID 0, never does)
+    pub fn parent_waits(&self, ppid: Pid) -> bool {
+        ppid != 0
+            && self
+                .processes
+                .lock()
+                .get(&ppid)
+                .is_some_and(|parent| parent.state() != ProcessState::Zombie)
+    }
+
+    /// Reap zombie processes (remove from table). A zombie stays until its
+    /// parent waits for it, unless the parent is the kernel or has exited
+    /// too, or it is among the oldest past MAX_ZOMBIES_PER_PARENT. The
+    /// exit statuses of reaped zombies are kept in `exited`.
     pub fn reap_zombies(&self) -> Vec<Pid> {
         let mut processes = self.processes.lock();
-        let zombies: Vec<Pid> = processes
-            .iter()
-            .filter(|(_, p)| {
-                p.state() == ProcessState::Zombie && !p.should_restart()
-            })
-            .map(|(pid, _)| *pid)
-            .collect();
+        let parent_waits = |ppid: Pid| {
+            ppid != 0 && processes.get(&ppid).is_some_and(|parent| parent.state() != ProcessState::Zombie)
+        };
+        let mut kept: BTreeMap<Pid, usize> = BTreeMap::new();
+        let mut zombies = Vec::new();
+        // Newest first, so the oldest are the ones past the limit
+        for (pid, p) in processes.iter().rev() {
+            if p.state() != ProcessState::Zombie || p.should_restart() {
+                continue;
+            }
+            if parent_waits(p.ppid) {
+                let count = kept.entry(p.ppid).or_insert(0);
+                if *count < MAX_ZOMBIES_PER_PARENT {
+                    *count += 1;
+                    continue;
+                }
+            }
+            zombies.push(*pid);
+        }
 
         for pid in &zombies {
-            processes.remove(pid);
+            if let Some(process) = processes.remove(pid) {
+                crate::allocator::forget_process(*pid);
+                self.record_exit(process.exit_status());
+            }
         }
 
         zombies
     }
+
+    /// Keep the exit status of a process leaving the table for its parent
+    pub fn record_exit(&self, status: ExitStatus) {
+        let mut exited = self.exited.lock();
+        if exited.len() >= MAX_EXIT_STATUSES {
+            exited.pop_front();
+        }
+        exited.push_back(status);
+    }
+
+    /// Collect the exit status of an exited child of `ppid` (`pid`, or any
+    /// child if None), removing it from the table
+    pub fn take_exited(&self, ppid: Pid, pid: Option<Pid>) -> Option<ExitStatus> {
+        let matches = |child: Pid, parent: Pid| parent == ppid && pid.map_or(true, |pid| pid == child);
+        {
+            let mut processes = self.processes.lock();
+            let zombie = processes
+                .values()
+                .find(|p| matches(p.pid, p.ppid) && p.state() == ProcessState::Zombie && !p.should_restart())
+                .map(|p| p.pid);
+            if let Some(process) = zombie.and_then(|zombie| processes.remove(&zombie)) {
+                crate::allocator::forget_process(process.pid);
+                return Some(process.exit_status());
+            }
+        }
+        let mut exited = self.exited.lock();
+        let index = exited.iter().position(|status| matches(status.pid, status.ppid))?;
+        exited.remove(index)
+    }
+
+    /// Whether `ppid` has a child (`pid`, or any if None) that is running or
+    /// whose exit status has not been collected
+    pub fn has_child(&self, ppid: Pid, pid: Option<Pid>) -> bool {
+        let matches = |child: Pid, parent: Pid| parent == ppid && pid.map_or(true, |pid| pid == child);
+        self.processes.lock().values().any(|p| matches(p.pid, p.ppid))
+            || self.exited.lock().iter().any(|status| matches(status.pid, status.ppid))
+    }
+
+    /// Live members of process group `pgid`
+    pub fn group(&self, pgid: Pid) -> Vec<Arc<Process>> {
+        self.find(|p| p.pgid() == pgid && p.state() != ProcessState::Zombie)
+    }
+
+    /// Move `pid` into process group `pgid`: its own PID starts a new group,
+    /// anything else must be a group with live members
+    pub fn set_group(&self, pid: Pid, pgid: Pid) -> Result<(), &'static str> {
+        let processes = self.processes.lock();
+        let process = processes.get(&pid).ok_or("No such process")?;
+        if pgid != pid && !processes.values().any(|p| p.pgid() == pgid && p.state() != ProcessState::Zombie) {
+            return Err("No such process group");
+        }
+        process.set_pgid(pgid);
+        Ok(())
+    }
 }
 
 /// Global process table instance
@@ -609,6 +1455,141 @@ mod tests {
         assert_eq!(proc.state(), ProcessState::Zombie);
     }
 
+    #[test]
+    fn test_exit_status_collection() {
+        let table = ProcessTable::new();
+        let mut child = Process::new(2, "child", dummy_entry);
+        child.ppid = 1;
+        let child = Arc::new(child);
+        table.register(child.clone());
+
+        assert!(table.has_child(1, None));
+        assert!(table.take_exited(1, None).is_none());
+
+        child.mark_exited(-3i32 as usize);
+        assert_eq!(table.take_exited(1, Some(3)), None);
+        assert_eq!(table.take_exited(1, Some(2)), Some(ExitStatus { pid: 2, ppid: 1, code: -3 }));
+        assert!(!table.has_child(1, None));
+
+        // Reaped before the parent waited: the status is still there
+        table.record_exit(ExitStatus { pid: 4, ppid: 1, code: 7 });
+        assert!(table.has_child(1, Some(4)));
+        assert_eq!(table.take_exited(1, None).map(|status| status.code), Some(7));
+        assert!(table.take_exited(1, None).is_none());
+    }
+
+    #[test]
+    fn test_zombies_wait_for_parent() {
+        let table = ProcessTable::new();
+        let parent = Arc::new(Process::new(1, "parent", dummy_entry));
+        table.register(parent.clone());
+        let mut child = Process::new(2, "child", dummy_entry);
+        child.ppid = 1;
+        let child = Arc::new(child);
+        table.register(child.clone());
+        let orphan = Arc::new(Process::new(3, "orphan", dummy_entry));
+        table.register(orphan.clone());
+
+        child.mark_exited(5);
+        orphan.mark_exited(6);
+        assert_eq!(table.reap_zombies(), alloc::vec![3]);
+        assert!(table.get(2).is_some());
+
+        // Once the parent has exited, nobody will wait for the child
+        parent.mark_exited(0);
+        assert!(!table.parent_waits(1));
+        assert_eq!(table.reap_zombies(), alloc::vec![2, 1]);
+        assert!(table.get(2).is_none());
+        assert_eq!(table.take_exited(1, Some(2)).map(|status| status.code), Some(5));
+    }
+
+    #[test]
+    fn test_wait_wakeups() {
+        static EVENTS: AtomicU64 = AtomicU64::new(0);
+        let proc = Process::new(1, "test", dummy_entry);
+
+        proc.block_on(&EVENTS, 0, 100);
+        assert_eq!(proc.state(), ProcessState::Blocked);
+        assert!(!proc.wake_due(50));
+        assert!(proc.wake_due(100));
+
+        EVENTS.fetch_add(1, Ordering::Release);
+        assert!(proc.wake_due(50));
+
+        // Only a blocked process wakes
+        proc.mark_running(0);
+        assert!(!proc.wake_due(100));
+        proc.end_wait();
+    }
+
+    #[test]
+    fn test_interactive_boost() {
+        static INPUT: AtomicU64 = AtomicU64::new(0);
+        let proc = Process::new(1, "shell", dummy_entry);
+
+        proc.block_on(&INPUT, 0, 1000);
+        proc.set_wait_interactive(true);
+        assert!(!proc.boosted(10));
+
+        // A timeout is not input
+        assert!(proc.wake_due(1000));
+        assert!(!proc.boosted(1000));
+
+        INPUT.fetch_add(1, Ordering::Release);
+        assert!(proc.woken_by_input());
+        assert!(proc.boosted(10));
+
+        proc.boost(60);
+        proc.mark_running(0);
+        proc.end_wait();
+        assert!(!proc.woken_by_input());
+        assert!(proc.boosted(59));
+        assert!(!proc.boosted(60));
+    }
+
+    #[test]
+    fn test_pending_signals() {
+        let proc = Process::new(1, "test", dummy_entry);
+        proc.set_runs_binary(true);
+        assert!(proc.runs_binary());
+
+        proc.raise(2);
+        proc.raise(15);
+        assert_eq!(proc.pending_signals(), (1 << 2) | (1 << 15));
+        assert_eq!(proc.take_signals(1 << 2), 1 << 2);
+        assert_eq!(proc.take_signals(1 << 2), 0);
+        assert_eq!(proc.pending_signals(), 1 << 15);
+
+        // Signals do not outlive the binary, nor does being stopped
+        proc.set_stopped(true);
+        assert!(proc.info(0).stopped);
+        proc.set_runs_binary(false);
+        assert_eq!(proc.pending_signals(), 0);
+        assert!(!proc.stopped());
+    }
+
+    #[test]
+    fn test_process_groups() {
+        let table = ProcessTable::new();
+        let leader = Arc::new(Process::new(1, "leader", dummy_entry));
+        let member = Arc::new(Process::new(2, "member", dummy_entry));
+        table.register(leader.clone());
+        table.register(member.clone());
+
+        // Every process starts out leading its own group
+        assert_eq!(member.pgid(), 2);
+        assert_eq!(table.set_group(2, 1), Ok(()));
+        assert_eq!(table.group(1).len(), 2);
+        assert_eq!(table.set_group(2, 7), Err("No such process group"));
+        assert_eq!(table.set_group(9, 1), Err("No such process"));
+
+        // Exited members are not signalled
+        leader.mark_exited(0);
+        assert_eq!(table.group(1).len(), 1);
+        assert_eq!(table.set_group(2, 2), Ok(()));
+        assert!(table.group(1).is_empty());
+    }
+
     #[test]
     fn test_cpu_affinity() {
         let proc = Process::new(1, "test", dummy_entry);
@@ -627,5 +1608,142 @@ mod tests {
         // Clear affinity
         proc.clear_cpu_affinity();
         assert!(proc.can_run_on_cpu(0));
+
+        // Masks
+        proc.set_affinity(CpuMask::parse("1-2,100").unwrap());
+        assert!(!proc.can_run_on_cpu(0));
+        assert!(proc.can_run_on_cpu(2));
+        assert!(proc.can_run_on_cpu(100));
+        assert_eq!(proc.affinity().to_list(), "1-2,100");
+        assert_eq!(CpuMask::parse("all"), Some(CpuMask::ALL));
+        assert_eq!(CpuMask::parse("3-1"), None);
+        proc.clear_cpu_affinity();
+
+        // A preempted process resumes where it was switched out
+        proc.set_preempted_on(Some(1));
+        assert!(!proc.can_run_on_cpu(0));
+        assert!(proc.can_run_on_cpu(1));
+        assert!(!proc.can_migrate(0));
+        proc.set_preempted_on(None);
+        assert!(proc.can_run_on_cpu(0));
+    }
+
+    #[test]
+    fn test_nice_weights() {
+        let proc = Process::new(1, "test", dummy_entry);
+        assert_eq!(proc.weight(), NICE_0_WEIGHT);
+
+        // Out of range values are clamped; the old value is returned
+        assert_eq!(proc.set_nice(-40), 0);
+        assert_eq!(proc.nice(), NICE_MIN);
+        assert_eq!(proc.set_nice(5), NICE_MIN);
+
+        // A nicer process is charged more virtual time for the same work
+        proc.charge_vruntime(1000);
+        assert!(proc.vruntime() > 1000);
+        proc.lift_vruntime(100);
+        assert!(proc.vruntime() > 1000);
+        proc.lift_vruntime(1_000_000);
+        assert_eq!(proc.vruntime(), 1_000_000);
+    }
+
+    #[test]
+    fn test_control_group_shares_and_cap() {
+        use crate::cpu::cgroup;
+
+        let half = cgroup::define("test-half", NICE_0_WEIGHT / 2, Some(100)).unwrap();
+        assert_eq!(cgroup::find("test-half"), Some(half));
+        assert!(cgroup::define("root", NICE_0_WEIGHT, None).is_err());
+
+        // A member of a half-share group is charged twice the virtual time
+        let proc = Process::new(1, "test", dummy_entry);
+        proc.set_cgroup(half);
+        proc.charge_vruntime(1000);
+        assert_eq!(proc.vruntime(), 2000);
+        assert_eq!(cgroup::scale_weight(cgroup::ROOT, NICE_0_WEIGHT), NICE_0_WEIGHT);
+
+        // Members share the cap; the root group has none
+        assert!(cgroup::charge_memory(half, 60));
+        assert!(!cgroup::charge_memory(half, 60));
+        cgroup::credit_memory(half, 60);
+        assert!(cgroup::charge_memory(half, 100));
+        assert!(cgroup::charge_memory(cgroup::ROOT, usize::MAX));
+    }
+
+    #[test]
+    fn test_io_stats() {
+        let proc = Process::new(1, "test", dummy_entry);
+
+        proc.io.add(Some(IoKind::FsRead), 512);
+        proc.io.add(Some(IoKind::NetTx), 100);
+        proc.io.add(None, 0);
+
+        let io = proc.io.snapshot();
+        assert_eq!(io.fs_read_bytes, 512);
+        assert_eq!(io.fs_write_bytes, 0);
+        assert_eq!(io.net_tx_bytes, 100);
+        assert_eq!(io.syscalls, 3);
+    }
+
+    #[test]
+    fn test_cpu_usage_decay() {
+        let proc = Process::new(1, "test", dummy_entry);
+
+        // Busy for a whole window: usage climbs towards 100%
+        for _ in 0..20 {
+            proc.add_cpu_time(1000);
+            proc.sample_cpu_usage(1000);
+        }
+        assert!(proc.cpu_usage() > 950);
+
+        // Idle: usage decays back to zero
+        for _ in 0..40 {
+            proc.sample_cpu_usage(1000);
+        }
+        assert_eq!(proc.cpu_usage(), 0);
+    }
+
+    #[test]
+    fn test_migration_cooldown() {
+        let proc = Process::new(1, "test", dummy_entry);
+        assert!(proc.can_migrate(0));
+
+        proc.note_migration(10_000);
+        assert_eq!(proc.migrations(), 1);
+        assert!(!proc.can_migrate(10_000 + MIGRATION_COOLDOWN_MS - 1));
+        assert!(proc.can_migrate(10_000 + MIGRATION_COOLDOWN_MS));
+
+        // Pinned processes never migrate
+        proc.set_cpu_affinity(1);
+        assert!(!proc.can_migrate(60_000));
+    }
+
+    #[test]
+    fn test_open_file_seek() {
+        let mut file = OpenFile::new("/tmp/a", O_RDWR);
+        assert!(file.readable() && file.writable() && !file.appends());
+
+        assert_eq!(file.seek(0, SEEK_END, 5), Ok(5));
+        assert_eq!(file.seek(6, SEEK_CUR, 5), Ok(11));
+        assert_eq!(file.seek(-5, SEEK_CUR, 11), Ok(6));
+        assert_eq!(file.seek(2, SEEK_SET, 11), Ok(2));
+        assert!(file.seek(-1, SEEK_SET, 11).is_err());
+        assert!(file.seek(0, 7, 11).is_err());
+        assert_eq!(file.pos, 2);
+
+        let file = OpenFile::new("/tmp/a", O_WRONLY | O_APPEND);
+        assert!(!file.readable() && file.writable() && file.appends());
+    }
+
+    #[test]
+    fn test_fd_table_reuses_lowest_fd() {
+        let mut fds = FdTable::new();
+        let a = fds.insert(OpenFile::new("/a", O_RDONLY)).unwrap();
+        let b = fds.insert(OpenFile::new("/b", O_RDONLY)).unwrap();
+        assert_eq!((a, b), (FD_FIRST, FD_FIRST + 1));
+
+        assert!(fds.remove(a).is_some());
+        assert_eq!(fds.insert(OpenFile::new("/c", O_APPEND | O_WRONLY)), Ok(a));
+        assert_eq!(fds.len(), 2);
     }
 }
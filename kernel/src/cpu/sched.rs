@@ -6,6 +6,7 @@
 //! - Maintaining per-CPU run queues
 //! - Picking the next process to run on each CPU
 //! - Work stealing (idle CPUs take work from busy ones)
+//! - Periodic rebalancing (migrating queued processes off overloaded CPUs)
 //! - Priority-based scheduling
 //!
 //! ## Architecture
@@ -28,15 +29,75 @@
 //!
 //! CPUs (Web Workers in browser) are just execution units. The scheduler
 //! assigns processes to CPUs based on load and affinity.
+//!
+//! A process without affinity stays on the hart it last ran on. Every
+//! REBALANCE_INTERVAL_MS a rebalance pass compares hart loads and moves
+//! queued floating processes from the busiest hart to the idlest one. Moves
+//! only happen when the loads differ by at least two (moving one process
+//! then never just swaps the imbalance), and a process is not moved again
+//! within MIGRATION_COOLDOWN_MS. Counters are exported in /proc/schedstat.
+//!
+//! ## Affinity
+//!
+//! Every process has a CpuMask of harts it may run on (all by default).
+//! Spawning, requeueing, stealing and rebalancing only place it on harts in
+//! the mask; `set_affinity` moves it if it waits elsewhere. A tick that was
+//! preempted still finishes on its hart first.
+//!
+//! ## Priorities
+//!
+//! Each run queue has three levels. Realtime processes always run first,
+//! in FIFO order. Low, Normal and High processes share the hart by weight:
+//! each is charged virtual time for the CPU it used, scaled down by its
+//! weight, and the one with the least virtual time runs next. The weight
+//! comes from the nice value (-20..19, see `set_nice`) shifted by the class
+//! (High counts as 5 levels less nice, Low as 5 more). Idle processes only
+//! run when nothing else on the hart is runnable.
+//!
+//! ## Preemption
+//!
+//! Each tick runs on the process's own kernel stack (`run_slice` switches
+//! to it from the hart's scheduler context). A tick still running
+//! TIME_SLICE_MS after it started is switched out by the next timer
+//! interrupt and the hart moves on to other work; the process is requeued
+//! and later resumes exactly where it stopped, on the same hart. Ticks are
+//! never switched out while they hold a lock (see `lock::preemptible`) or
+//! while a user program is running in U-mode.
+//!
+//! ## Blocking
+//!
+//! A tick waiting on a `lock::wait::WaitQueue` is switched out the same way
+//! (`block_current`) but marked Blocked, so the run queue passes it over
+//! until the queue is woken or its timeout passes. The hart meanwhile runs
+//! other work or sleeps in `wfi` until the next interrupt.
+//!
+//! ## Interactivity
+//!
+//! A process woken from an interactive wait queue (console input, see
+//! `WaitQueue::interactive`) is boosted: for INTERACTIVE_BOOST_MS it is
+//! picked before the other Low, Normal and High processes on its hart,
+//! whatever their virtual times, so the shell echoes keystrokes and starts
+//! commands promptly while daemons keep the hart busy. It is still charged
+//! for the CPU it uses, and Realtime processes still come first.
+//!
+//! ## Control Groups
+//!
+//! A process in a control group (`cgroup`) is charged virtual time as if
+//! its weight were scaled by the group's CPU shares and split among the
+//! group's busy members, so a group of services competes for the hart as
+//! one process of that weight.
 
 use alloc::collections::VecDeque;
 use alloc::sync::Arc;
 use alloc::vec::Vec;
-use core::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use core::ptr;
+use core::sync::atomic::{AtomicBool, AtomicPtr, AtomicU64, AtomicUsize, Ordering};
 
-use crate::cpu::{ self, CPU_TABLE, MAX_HARTS};
+use crate::cpu::{ self, cgroup, CPU_TABLE, MAX_HARTS};
 use crate::cpu::chase_lev::{WorkStealingDeque, StealResult};
-use crate::cpu::process::{allocate_pid, Priority, Process, ProcessEntry, ProcessInfo,  Pid, PROCESS_TABLE};
+use crate::cpu::process::{allocate_pid, Context, CpuMask, ExitStatus, Priority, Process, ProcessEntry, ProcessInfo, ProcessState,  Pid, PROCESS_TABLE};
+use crate::lock::wait::WaitQueue;
+use crate::cpu::is_hart_ready;
 use crate::Spinlock;
 use crate::services::klogd::{klog_debug, klog_info, klog_trace};
 
@@ -44,10 +105,21 @@ use crate::services::klogd::{klog_debug, klog_info, klog_trace};
 // RUN QUEUE
 // ═══════════════════════════════════════════════════════════════════════════════
 
+/// Smallest virtual time charged for a slice (µs): even a tick that
+/// returns at once costs something, so weights also shape how often
+/// short ticks run
+const MIN_SLICE_CHARGE_US: u64 = 50;
+
+/// How long a process woken by user input runs ahead of the other weighted
+/// processes on its hart (ms)
+const INTERACTIVE_BOOST_MS: u64 = 50;
+
 /// Per-CPU run queue containing ready processes
 pub struct RunQueue {
     /// Processes waiting to run (priority sorted, higher priority first)
     queue: VecDeque<Arc<Process>>,
+    /// Virtual time of the last weighted pick; newcomers start here
+    min_vruntime: u64,
 }
 
 impl RunQueue {
@@ -55,12 +127,14 @@ impl RunQueue {
     pub const fn new() -> Self {
         Self {
             queue: VecDeque::new(),
+            min_vruntime: 0,
         }
     }
 
     /// Add a process to the queue (maintains priority order)
     pub fn enqueue(&mut self, process: Arc<Process>) {
         let priority = process.priority;
+        process.lift_vruntime(self.min_vruntime);
         
         // Find insertion point (higher priority = earlier position)
         let mut insert_pos = self.queue.len();
@@ -74,20 +148,37 @@ impl RunQueue {
         self.queue.insert(insert_pos, process);
     }
 
+    /// Index of the process to run next: the first runnable Realtime one,
+    /// else the weighted one with the least virtual time (boosted ones
+    /// first, earliest queued on ties), else the first runnable Idle one
+    fn pick_index(&self) -> Option<usize> {
+        let now = crate::get_time_ms() as u64;
+        let runnable = || self.queue.iter().enumerate().filter(move |(_, p)| p.state().is_runnable() || p.wake_due(now));
+        if let Some((i, _)) = runnable().find(|(_, p)| p.priority == Priority::Realtime) {
+            return Some(i);
+        }
+        runnable()
+            .filter(|(_, p)| p.priority != Priority::Idle)
+            .min_by_key(|(i, p)| (!p.boosted(now), p.vruntime(), *i))
+            .or_else(|| runnable().next())
+            .map(|(i, _)| i)
+    }
+
     /// Get the next runnable process
     pub fn dequeue(&mut self) -> Option<Arc<Process>> {
-        // Find first process in Ready state
-        for i in 0..self.queue.len() {
-            if self.queue[i].state().is_runnable() {
-                return self.queue.remove(i);
-            }
+        let process = self.queue.remove(self.pick_index()?)?;
+        // A boosted pick jumped the queue: its virtual time says nothing
+        // about where the others are
+        let boosted = process.boosted(crate::get_time_ms() as u64);
+        if matches!(process.priority, Priority::Low | Priority::Normal | Priority::High) && !boosted {
+            self.min_vruntime = self.min_vruntime.max(process.vruntime());
         }
-        None
+        Some(process)
     }
 
     /// Peek at the next process without removing it
     pub fn peek(&self) -> Option<&Arc<Process>> {
-        self.queue.iter().find(|p| p.state().is_runnable())
+        self.pick_index().map(|i| &self.queue[i])
     }
 
     /// Remove a specific process by PID
@@ -124,6 +215,21 @@ impl RunQueue {
     pub fn contains(&self, pid: Pid) -> bool {
         self.queue.iter().any(|p| p.pid == pid)
     }
+
+    /// Number of runnable processes in queue
+    pub fn runnable(&self) -> usize {
+        self.queue.iter().filter(|p| p.state().is_runnable()).count()
+    }
+
+    /// Take the lowest priority runnable process the load balancer may move
+    /// to `target`
+    pub fn take_migratable(&mut self, now_ms: u64, target: usize) -> Option<Arc<Process>> {
+        let pos = self
+            .queue
+            .iter()
+            .rposition(|p| p.state().is_runnable() && p.can_migrate(now_ms) && p.can_run_on_cpu(target))?;
+        self.queue.remove(pos)
+    }
 }
 
 // ═══════════════════════════════════════════════════════════════════════════════
@@ -132,9 +238,7 @@ impl RunQueue {
 
 /// Creates const-initialized run queue array
 const fn create_queue_array() -> [Spinlock<RunQueue>; MAX_HARTS] {
-    const INIT_QUEUE: Spinlock<RunQueue> = Spinlock::new(RunQueue {
-        queue: VecDeque::new(),
-    });
+    const INIT_QUEUE: Spinlock<RunQueue> = Spinlock::new(RunQueue::new());
     [INIT_QUEUE; MAX_HARTS]
 }
 
@@ -154,6 +258,34 @@ fn next_random() -> usize {
     }
 }
 
+/// Time between two rebalance passes (ms)
+pub const REBALANCE_INTERVAL_MS: u64 = 500;
+
+/// Smallest load difference between two harts worth a migration
+const REBALANCE_MIN_IMBALANCE: usize = 2;
+
+/// Most processes moved by one rebalance pass
+const MAX_MIGRATIONS_PER_PASS: usize = 2;
+
+/// Load balancing counters
+#[derive(Clone, Copy, Default)]
+pub struct SchedStats {
+    /// Rebalance passes run
+    pub rebalance_passes: u64,
+    /// Processes moved by rebalance passes
+    pub migrations: u64,
+    /// Processes taken by idle harts from other harts' queues
+    pub steals: u64,
+    /// Load difference between the busiest and idlest hart at the last pass
+    pub last_imbalance: usize,
+    /// Ticks switched out because their time slice ran out
+    pub preemptions: u64,
+    /// Ticks switched out to wait on a wait queue
+    pub sleeps: u64,
+    /// Processes boosted after user input woke them
+    pub boosts: u64,
+}
+
 /// The process scheduler
 pub struct Scheduler {
     /// Per-CPU run queues (for priority scheduling)
@@ -171,6 +303,18 @@ pub struct Scheduler {
     
     /// Total processes spawned
     spawn_count: AtomicUsize,
+
+    /// Time of the last rebalance pass (ms since boot)
+    last_rebalance: AtomicU64,
+
+    /// Load balancing counters (see SchedStats)
+    rebalance_passes: AtomicU64,
+    migrations: AtomicU64,
+    steals: AtomicU64,
+    last_imbalance: AtomicUsize,
+    preemptions: AtomicU64,
+    sleeps: AtomicU64,
+    boosts: AtomicU64,
 }
 
 impl Scheduler {
@@ -185,6 +329,14 @@ impl Scheduler {
             num_cpus: AtomicUsize::new(1),
             active: AtomicBool::new(false),
             spawn_count: AtomicUsize::new(0),
+            last_rebalance: AtomicU64::new(0),
+            rebalance_passes: AtomicU64::new(0),
+            migrations: AtomicU64::new(0),
+            steals: AtomicU64::new(0),
+            last_imbalance: AtomicUsize::new(0),
+            preemptions: AtomicU64::new(0),
+            sleeps: AtomicU64::new(0),
+            boosts: AtomicU64::new(0),
         }
     }
 
@@ -224,21 +376,34 @@ impl Scheduler {
         priority: Priority,
         cpu_affinity: Option<usize>,
     ) -> Pid {
+        self.spawn_with_affinity(name, entry, priority, cpu_affinity.map_or(CpuMask::ALL, CpuMask::single))
+    }
+
+    /// Spawn a process restricted to the harts in `affinity`
+    pub fn spawn_with_affinity(&self, name: &str, entry: ProcessEntry, priority: Priority, affinity: CpuMask) -> Pid {
         let pid = allocate_pid();
         let mut process = Process::new(pid, name, entry);
-        process.priority = priority;
-        
-        if let Some(cpu_id) = cpu_affinity {
-            process.set_cpu_affinity(cpu_id);
+        process.ppid = current_pid();
+        // Children join their parent's process group; daemons lead their own
+        let parent = PROCESS_TABLE.get(process.ppid);
+        if let Some(parent) = &parent {
+            process.set_pgid(parent.pgid());
         }
+        process.priority = priority;
+        process.set_affinity(self.usable_mask(affinity));
         
         let process = Arc::new(process);
         
         // Register in process table
         PROCESS_TABLE.register(process.clone());
+
+        // ...and their parent's control group
+        if let Some(parent) = parent.filter(|p| p.cgroup() != cgroup::ROOT) {
+            let _ = cgroup::join(pid, parent.cgroup());
+        }
         
         // Determine target CPU
-        let target_cpu = cpu_affinity.unwrap_or_else(|| self.find_least_loaded_cpu());
+        let target_cpu = self.least_loaded_in(process.affinity());
         
         // Mark as ready and enqueue
         process.mark_ready();
@@ -269,21 +434,24 @@ impl Scheduler {
         priority: Priority,
         cpu_affinity: Option<usize>,
     ) -> Pid {
+        self.spawn_daemon_with_affinity(name, entry, priority, cpu_affinity.map_or(CpuMask::ALL, CpuMask::single))
+    }
+
+    /// Spawn a daemon restricted to the harts in `affinity`
+    pub fn spawn_daemon_with_affinity(&self, name: &str, entry: ProcessEntry, priority: Priority, affinity: CpuMask) -> Pid {
         let pid = allocate_pid();
         let mut process = Process::new_daemon(pid, name, entry);
+        process.ppid = current_pid();
         
         // Set the requested priority (new_daemon defaults to Normal)
         process.priority = priority;
-        
-        if let Some(cpu_id) = cpu_affinity {
-            process.set_cpu_affinity(cpu_id);
-        }
+        process.set_affinity(self.usable_mask(affinity));
         
         let process = Arc::new(process);
         
         PROCESS_TABLE.register(process.clone());
         
-        let target_cpu = cpu_affinity.unwrap_or_else(|| self.find_least_loaded_cpu());
+        let target_cpu = self.least_loaded_in(process.affinity());
         process.mark_ready();
         self.enqueue(target_cpu, process);
         
@@ -328,6 +496,7 @@ impl Scheduler {
         if let Some(process) = self.queues[cpu_id].lock().dequeue() {
             // Also pop from steal queue to keep them in sync
             let _ = self.steal_queues[cpu_id].pop();
+            self.start_boost(&process);
             return Some(process);
         }
         
@@ -353,23 +522,29 @@ impl Scheduler {
             match self.steal_queues[victim].steal() {
                 StealResult::Success(process) => {
                     // Verify the process can run on this CPU
-                    if process.can_run_on_cpu(cpu_id) {
-                        // Remove from victim's priority queue to keep in sync
-                        self.queues[victim].lock().remove(process.pid);
-                        
-                        klog_trace(
-                            "sched",
-                            &alloc::format!(
-                                "CPU {} stole '{}' from CPU {} (lock-free)",
-                                cpu_id, process.name, victim
-                            ),
-                        );
-                        return Some(process);
-                    } else {
-                        // Wrong affinity - put it back
-                        self.steal_queues[victim].push(process.clone());
-                        self.queues[victim].lock().enqueue(process);
+                    if !process.can_run_on_cpu(cpu_id) {
+                        // Wrong affinity - put it back (it never left the victim's priority queue)
+                        self.steal_queues[victim].push(process);
+                        continue;
+                    }
+
+                    // Remove from victim's priority queue to keep in sync. If it is
+                    // no longer there, the entry is stale: the process was already
+                    // dequeued or migrated, so it must not run here as well.
+                    if self.queues[victim].lock().remove(process.pid).is_none() {
+                        continue;
                     }
+                    self.steals.fetch_add(1, Ordering::Relaxed);
+                    self.start_boost(&process);
+
+                    klog_trace(
+                        "sched",
+                        &alloc::format!(
+                            "CPU {} stole '{}' from CPU {} (lock-free)",
+                            cpu_id, process.name, victim
+                        ),
+                    );
+                    return Some(process);
                 }
                 StealResult::Retry => {
                     // CAS failed - another thief got it, try next victim
@@ -385,21 +560,30 @@ impl Scheduler {
         None
     }
 
+    /// Boost a process picked because user input woke it
+    fn start_boost(&self, process: &Process) {
+        if process.woken_by_input() {
+            process.boost(crate::get_time_ms() as u64 + INTERACTIVE_BOOST_MS);
+            self.boosts.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
     /// Re-queue a process after its time slice expires.
     /// 
     /// For processes with CPU affinity, requeues to the pinned hart.
-    /// For floating processes (no affinity), rebalances to the least loaded ready hart.
-    pub fn requeue(&self, process: Arc<Process>, _current_cpu: usize) {
-        process.mark_ready();
+    /// Floating processes (no affinity) stay on the current hart; the
+    /// periodic rebalance pass moves them if that hart is overloaded.
+    pub fn requeue(&self, process: Arc<Process>, current_cpu: usize) {
+        // A blocked process stays blocked until its wait queue wakes it
+        if process.state() != ProcessState::Blocked {
+            process.mark_ready();
+        }
         
         // Check if process has CPU affinity
-        let target_cpu = match process.get_cpu_affinity() {
-            Some(pinned_cpu) => pinned_cpu, // Pinned: MUST go to pinned hart
-            None => {
-                // Floating: rebalance to least loaded ready hart
-                // This enables load distribution across harts
-                self.find_least_loaded_cpu()
-            }
+        let target_cpu = match process.preempted_on() {
+            Some(hart) => hart, // Preempted: resumes where it stopped
+            None if process.can_run_on_cpu(current_cpu) => current_cpu,
+            None => self.least_loaded_in(process.affinity()), // MUST go to an allowed hart
         };
         
         self.enqueue(target_cpu, process);
@@ -453,6 +637,168 @@ impl Scheduler {
         best_cpu
     }
 
+    /// Least loaded hart in `mask` (ready harts first)
+    pub fn least_loaded_in(&self, mask: CpuMask) -> usize {
+        if mask.is_all() {
+            return self.find_least_loaded_cpu();
+        }
+        (0..self.num_cpus())
+            .filter(|&cpu| mask.contains(cpu))
+            .min_by_key(|&cpu| (!is_hart_ready(cpu), self.queue_length(cpu), cpu == 0))
+            .unwrap_or_else(|| self.find_least_loaded_cpu())
+    }
+
+    /// Harts that exist, as a mask
+    pub fn online_mask(&self) -> CpuMask {
+        match self.num_cpus() {
+            n if n >= 128 => CpuMask::ALL,
+            n => CpuMask((1u128 << n) - 1),
+        }
+    }
+
+    /// `mask`, or every hart if none of its harts exists (a mask written
+    /// for a bigger machine must not leave the process nowhere to run)
+    fn usable_mask(&self, mask: CpuMask) -> CpuMask {
+        if mask.intersect(self.online_mask()).is_empty() {
+            CpuMask::ALL
+        } else {
+            mask
+        }
+    }
+
+    /// Restrict a process to the harts in `mask`, moving it off a hart it
+    /// may no longer use if it is waiting there. A running (or preempted)
+    /// process moves when its tick ends.
+    pub fn set_affinity(&self, pid: Pid, mask: CpuMask) -> Result<(), &'static str> {
+        let process = PROCESS_TABLE.get(pid).ok_or("No such process")?;
+        if mask.intersect(self.online_mask()).is_empty() {
+            return Err("No such hart");
+        }
+        process.set_affinity(mask);
+        for cpu in 0..self.num_cpus() {
+            if process.can_run_on_cpu(cpu) {
+                continue;
+            }
+            let queued = self.queues[cpu].lock().remove(pid);
+            if let Some(process) = queued {
+                self.enqueue(self.least_loaded_in(mask), process);
+            }
+        }
+        klog_debug(
+            "sched",
+            &alloc::format!("Affinity of '{}' (PID {}) set to {}", process.name, pid, mask.to_list()),
+        );
+        Ok(())
+    }
+
+    /// Load of a CPU: runnable queued processes plus the one it is running
+    pub fn cpu_load(&self, cpu_id: usize) -> usize {
+        if cpu_id >= MAX_HARTS {
+            return 0;
+        }
+        let running = CPU_TABLE
+            .get(cpu_id)
+            .is_some_and(|cpu| cpu.running_process().is_some());
+        self.queues[cpu_id].lock().runnable() + running as usize
+    }
+
+    /// Run a rebalance pass if REBALANCE_INTERVAL_MS has passed since the last one
+    pub fn rebalance_tick(&self, now_ms: u64) {
+        let last = self.last_rebalance.load(Ordering::Relaxed);
+        if now_ms.saturating_sub(last) < REBALANCE_INTERVAL_MS {
+            return;
+        }
+        // Only one hart runs the pass
+        if self
+            .last_rebalance
+            .compare_exchange(last, now_ms, Ordering::AcqRel, Ordering::Relaxed)
+            .is_ok()
+        {
+            self.rebalance(now_ms);
+        }
+    }
+
+    /// Move queued floating processes from overloaded harts to idle ones.
+    ///
+    /// Processes only move onto ready secondary harts (hart 0 runs the
+    /// critical services). Returns the number of processes migrated.
+    pub fn rebalance(&self, now_ms: u64) -> usize {
+        let num_cpus = self.num_cpus();
+        if num_cpus <= 1 {
+            return 0;
+        }
+        self.rebalance_passes.fetch_add(1, Ordering::Relaxed);
+
+        let mut loads = [0usize; MAX_HARTS];
+        for (cpu_id, load) in loads.iter_mut().enumerate().take(num_cpus) {
+            *load = self.cpu_load(cpu_id);
+        }
+        // Harts with nothing left to give this pass
+        let mut exhausted = [false; MAX_HARTS];
+        let mut moved = 0;
+        let mut first = true;
+
+        while moved < MAX_MIGRATIONS_PER_PASS {
+            let Some(idlest) = (1..num_cpus)
+                .filter(|&cpu| is_hart_ready(cpu))
+                .min_by_key(|&cpu| loads[cpu])
+            else {
+                break;
+            };
+            let Some(busiest) = (0..num_cpus)
+                .filter(|&cpu| cpu != idlest && !exhausted[cpu])
+                .max_by_key(|&cpu| loads[cpu])
+            else {
+                break;
+            };
+
+            let imbalance = loads[busiest].saturating_sub(loads[idlest]);
+            if first {
+                self.last_imbalance.store(imbalance, Ordering::Relaxed);
+                first = false;
+            }
+            if imbalance < REBALANCE_MIN_IMBALANCE {
+                break;
+            }
+
+            let Some(process) = self.queues[busiest].lock().take_migratable(now_ms, idlest) else {
+                // Everything queued there is pinned or was just moved
+                exhausted[busiest] = true;
+                continue;
+            };
+            // The stale entry left in the busiest hart's steal queue is
+            // skipped by thieves since the process is no longer in its queue
+            process.note_migration(now_ms);
+            self.enqueue(idlest, process.clone());
+            loads[busiest] -= 1;
+            loads[idlest] += 1;
+            moved += 1;
+            self.migrations.fetch_add(1, Ordering::Relaxed);
+
+            klog_trace(
+                "sched",
+                &alloc::format!(
+                    "Migrated '{}' (PID {}) from CPU {} to CPU {}",
+                    process.name, process.pid, busiest, idlest
+                ),
+            );
+        }
+        moved
+    }
+
+    /// Load balancing counters
+    pub fn stats(&self) -> SchedStats {
+        SchedStats {
+            rebalance_passes: self.rebalance_passes.load(Ordering::Relaxed),
+            migrations: self.migrations.load(Ordering::Relaxed),
+            steals: self.steals.load(Ordering::Relaxed),
+            last_imbalance: self.last_imbalance.load(Ordering::Relaxed),
+            preemptions: self.preemptions.load(Ordering::Relaxed),
+            sleeps: self.sleeps.load(Ordering::Relaxed),
+            boosts: self.boosts.load(Ordering::Relaxed),
+        }
+    }
+
     /// Get queue length for a CPU
     pub fn queue_length(&self, cpu_id: usize) -> usize {
         if cpu_id < MAX_HARTS {
@@ -486,10 +832,13 @@ impl Scheduler {
                 self.queues[cpu_id].lock().remove(pid);
             }
             
-            // If daemon with restart, let it restart
-            if !process.should_restart() {
+            // If daemon with restart, let it restart. A parent that can
+            // still wait collects the zombie itself.
+            if !process.should_restart() && !PROCESS_TABLE.parent_waits(process.ppid) {
                 PROCESS_TABLE.unregister(pid);
+                PROCESS_TABLE.record_exit(process.exit_status());
             }
+            CHILD_EXIT.wake_all();
             
             klog_info(
                 "sched",
@@ -502,10 +851,29 @@ impl Scheduler {
         }
     }
 
+    /// Change a process's nice value (clamped to NICE_MIN..=NICE_MAX);
+    /// returns the old value, or None if there is no such process. Takes
+    /// effect from the next pick.
+    pub fn set_nice(&self, pid: Pid, nice: i32) -> Option<i32> {
+        let process = PROCESS_TABLE.get(pid)?;
+        let old = process.set_nice(nice);
+        if old != process.nice() {
+            klog_debug(
+                "sched",
+                &alloc::format!("Reniced '{}' (PID {}) from {} to {}", process.name, pid, old, process.nice()),
+            );
+        }
+        Some(old)
+    }
+
     /// Complete a process (exit with code)
     pub fn exit(&self, pid: Pid, exit_code: usize) {
         if let Some(process) = PROCESS_TABLE.get(pid) {
-            process.mark_exited(exit_code);
+            // A killed process finishing its last tick keeps the kill's code
+            if process.state() != ProcessState::Zombie {
+                process.mark_exited(exit_code);
+            }
+            CHILD_EXIT.wake_all();
             
             klog_debug(
                 "sched",
@@ -526,11 +894,34 @@ impl Scheduler {
                     &alloc::format!("Restarting daemon '{}'", name),
                 );
                 
-                self.spawn_daemon(&name, entry, priority);
+                // The restarted daemon keeps its nice value, affinity and
+                // control group
+                let pid = self.spawn_daemon_with_affinity(&name, entry, priority, process.affinity());
+                self.set_nice(pid, process.nice());
+                if process.cgroup() != cgroup::ROOT {
+                    let _ = cgroup::join(pid, process.cgroup());
+                }
             }
         }
     }
 
+    /// Wait until a child of `ppid` (`pid`, or any child if None) has
+    /// exited, or the clock reaches `until_ms`, and collect its status.
+    /// Ok(None) on timeout; an error if there is no such child.
+    pub fn wait_child(&self, ppid: Pid, pid: Option<Pid>, until_ms: i64) -> Result<Option<ExitStatus>, &'static str> {
+        let mut status = None;
+        let mut orphan = false;
+        CHILD_EXIT.wait_until(until_ms, || {
+            status = PROCESS_TABLE.take_exited(ppid, pid);
+            orphan = status.is_none() && !PROCESS_TABLE.has_child(ppid, pid);
+            status.is_some() || orphan
+        });
+        if orphan {
+            return Err("No child processes");
+        }
+        Ok(status)
+    }
+
     /// Reap zombie processes
     pub fn reap_zombies(&self) -> usize {
         PROCESS_TABLE.reap_zombies().len()
@@ -548,6 +939,21 @@ impl Scheduler {
             .collect()
     }
 
+    /// Update the decayed CPU usage of every process, control group and
+    /// hart; `elapsed_ms` is the time since the previous call
+    pub fn sample_cpu_usage(&self, elapsed_ms: u64) {
+        let processes = PROCESS_TABLE.list();
+        for process in &processes {
+            process.sample_cpu_usage(elapsed_ms);
+        }
+        cgroup::sample(&processes);
+        for id in CPU_TABLE.online_cpus() {
+            if let Some(cpu) = CPU_TABLE.get(id) {
+                cpu.sample_usage(elapsed_ms);
+            }
+        }
+    }
+
     /// Get process count
     pub fn process_count(&self) -> usize {
         PROCESS_TABLE.count()
@@ -596,6 +1002,21 @@ pub fn kill(pid: Pid) -> bool {
     SCHEDULER.kill(pid)
 }
 
+/// Restrict a process to a set of harts
+pub fn set_affinity(pid: Pid, mask: CpuMask) -> Result<(), &'static str> {
+    SCHEDULER.set_affinity(pid, mask)
+}
+
+/// Change a process's nice value
+pub fn set_nice(pid: Pid, nice: i32) -> Option<i32> {
+    SCHEDULER.set_nice(pid, nice)
+}
+
+/// Wait for a child of `ppid` to exit and collect its status
+pub fn wait_child(ppid: Pid, pid: Option<Pid>, until_ms: i64) -> Result<Option<ExitStatus>, &'static str> {
+    SCHEDULER.wait_child(ppid, pid, until_ms)
+}
+
 /// List all processes
 pub fn list_processes() -> Vec<ProcessInfo> {
     SCHEDULER.list_processes()
@@ -605,103 +1026,212 @@ pub fn list_processes() -> Vec<ProcessInfo> {
 // CONTEXT SWITCHING & YIELDING
 // ═══════════════════════════════════════════════════════════════════════════════
 
-/// Flag indicating if a yield was requested from interrupt context
-static YIELD_PENDING: [AtomicBool; MAX_HARTS] = {
+/// Longest a tick runs before the timer switches it out (ms)
+pub const TIME_SLICE_MS: u64 = 10;
+
+/// Context of the tick running on each hart (null between ticks)
+static RUNNING_CONTEXT: [AtomicPtr<Context>; MAX_HARTS] = {
+    const INIT: AtomicPtr<Context> = AtomicPtr::new(ptr::null_mut());
+    [INIT; MAX_HARTS]
+};
+
+/// When the running tick was last switched to (ms since boot)
+static SLICE_START: [AtomicU64; MAX_HARTS] = {
+    const INIT: AtomicU64 = AtomicU64::new(0);
+    [INIT; MAX_HARTS]
+};
+
+/// Woken whenever a process exits, for parents waiting on children
+static CHILD_EXIT: WaitQueue = WaitQueue::new();
+
+/// PID of the process running on this hart (0 outside any process)
+pub fn current_pid() -> Pid {
+    CPU_TABLE.get(crate::get_hart_id()).and_then(|cpu| cpu.running_process()).unwrap_or(0)
+}
+
+/// Process whose tick is running on each hart (null between ticks)
+static SLICE_PROCESS: [AtomicPtr<Process>; MAX_HARTS] = {
+    const INIT: AtomicPtr<Process> = AtomicPtr::new(ptr::null_mut());
+    [INIT; MAX_HARTS]
+};
+
+/// Entry point the trampoline calls for a fresh tick
+static SLICE_ENTRY: [AtomicUsize; MAX_HARTS] = {
+    const INIT: AtomicUsize = AtomicUsize::new(0);
+    [INIT; MAX_HARTS]
+};
+
+/// The last slice ended by being switched out rather than by finishing
+static SLICE_INTERRUPTED: [AtomicBool; MAX_HARTS] = {
     const INIT: AtomicBool = AtomicBool::new(false);
     [INIT; MAX_HARTS]
 };
 
-/// Voluntarily yield the CPU to another process.
+/// Run `process` on its own stack until its tick finishes or its time
+/// slice runs out. Returns true if the tick finished; otherwise the process
+/// was preempted and must be requeued to resume on this hart.
 ///
-/// This should be called by processes that want to give up their time slice.
-/// For cooperative multitasking, processes should call this periodically.
-pub fn yield_now() {
-    let hart_id = crate::get_hart_id();
-    
-    // Get current process if any
-    if let Some(cpu) = crate::cpu::CPU_TABLE.get(hart_id) {
-        if let Some(pid) = cpu.running_process() {
-            if let Some(process) = SCHEDULER.get_process(pid) {
-                // Mark process as ready (it's voluntarily yielding)
-                process.mark_ready();
-                
-                // Requeue the process
-                SCHEDULER.requeue(process, hart_id);
-            }
-        }
+/// Called from hart_loop with the process marked running on `hart`.
+pub fn run_slice(process: &Process, hart: usize) -> bool {
+    let Some(cpu) = CPU_TABLE.get(hart) else {
+        (process.entry)();
+        return true;
+    };
+    let ctx = process.context_ptr();
+
+    // A fresh tick starts at the trampoline with an empty stack; a
+    // preempted one carries on from its saved context
+    if process.preempted_on().is_none() {
+        unsafe { *ctx = Context::new(tick_trampoline as usize as u64, process.kstack_top()) };
     }
-    
-    // The actual context switch happens in the hart loop
-    // This function just marks the yield as pending
-    if hart_id < MAX_HARTS {
-        YIELD_PENDING[hart_id].store(true, core::sync::atomic::Ordering::Release);
+    process.set_preempted_on(None);
+
+    // No timer interrupt between here and the switch: it would see a
+    // running context that is not running yet
+    crate::trap::disable_interrupts();
+    SLICE_ENTRY[hart].store(process.entry as usize, Ordering::Relaxed);
+    SLICE_INTERRUPTED[hart].store(false, Ordering::Relaxed);
+    SLICE_START[hart].store(crate::get_time_ms() as u64, Ordering::Relaxed);
+    SLICE_PROCESS[hart].store(process as *const Process as *mut Process, Ordering::Relaxed);
+    RUNNING_CONTEXT[hart].store(ctx, Ordering::Release);
+    let started_us = now_us();
+
+    unsafe { cpu::process::switch_context(cpu.scheduler_context_ptr(), ctx) };
+
+    // Back on the scheduler stack, interrupts still off
+    RUNNING_CONTEXT[hart].store(ptr::null_mut(), Ordering::Release);
+    SLICE_PROCESS[hart].store(ptr::null_mut(), Ordering::Relaxed);
+    crate::trap::enable_interrupts();
+    process.charge_vruntime(now_us().saturating_sub(started_us).max(MIN_SLICE_CHARGE_US));
+
+    if SLICE_INTERRUPTED[hart].load(Ordering::Relaxed) {
+        process.set_preempted_on(Some(hart));
+        if process.state() == ProcessState::Blocked {
+            SCHEDULER.sleeps.fetch_add(1, Ordering::Relaxed);
+        } else {
+            SCHEDULER.preemptions.fetch_add(1, Ordering::Relaxed);
+        }
+        return false;
     }
+    true
 }
 
-/// Called from interrupt handler to request a yield.
-///
-/// This is used by the timer interrupt to trigger preemption.
-/// The actual context switch is deferred until it's safe.
-pub fn yield_from_interrupt() {
-    let hart_id = crate::get_hart_id();
-    
-    if hart_id < MAX_HARTS {
-        YIELD_PENDING[hart_id].store(true, core::sync::atomic::Ordering::Release);
-    }
+/// Microseconds since boot (the timer runs at 10 MHz)
+fn now_us() -> u64 {
+    crate::trap::read_mtime() / 10
 }
 
-/// Yield the current process and switch back to the scheduler.
-///
-/// This performs an actual context switch:
-/// 1. Saves the current process's registers to its Context
-/// 2. Restores the scheduler's registers from its Context
-/// 3. Returns to the scheduler's hart_loop to pick the next process
-///
-/// Called from:
-/// - Timer interrupt (preemptive scheduling)
-/// - Process voluntarily yielding (cooperative scheduling)
+/// First code of a fresh tick, on the process's stack: run the entry point
+/// with interrupts on, then hand the hart back to the scheduler
+extern "C" fn tick_trampoline() -> ! {
+    let hart = crate::get_hart_id();
+    let entry = SLICE_ENTRY[hart].load(Ordering::Relaxed);
+    // SAFETY: run_slice stored a ProcessEntry here
+    let entry: ProcessEntry = unsafe { core::mem::transmute::<usize, ProcessEntry>(entry) };
+
+    crate::trap::enable_interrupts();
+    entry();
+    // Partial output of the tick goes out before another process prints
+    crate::device::uart::flush();
+    crate::trap::disable_interrupts();
+
+    // A preempted tick only resumes on the hart it stopped on, so `hart`
+    // is still ours
+    leave_slice(hart, false);
+    unreachable!("finished tick resumed");
+}
+
+/// Switch from the running tick back to `hart`'s scheduler context (with
+/// interrupts off). Returns when a preempted tick is resumed.
+fn leave_slice(hart: usize, interrupted: bool) {
+    let Some(cpu) = CPU_TABLE.get(hart) else {
+        return;
+    };
+    let ctx = RUNNING_CONTEXT[hart].load(Ordering::Acquire);
+    SLICE_INTERRUPTED[hart].store(interrupted, Ordering::Relaxed);
+    // Whatever runs meanwhile allocates for itself, not for this tick
+    let subsystem = crate::allocator::set_subsystem(crate::allocator::Subsystem::Kernel);
+    unsafe { cpu::process::switch_context(ctx, cpu.scheduler_context_ptr()) };
+    crate::allocator::set_subsystem(subsystem);
+}
+
+/// Called by the timer interrupt: switch the running tick out if its time
+/// slice is over. `from_kernel` is false when the interrupt came from U-mode,
+/// whose registers live in the user program's own trap frame and are not
+/// safe to leave behind.
 ///
-/// # Safety
-/// Only safe to call from the current hart's running process context.
-pub fn yield_current() {
-    let hart_id = crate::get_hart_id();
-    
-    // Get current process and CPU contexts
-    if let Some(cpu) = crate::cpu::CPU_TABLE.get(hart_id) {
-        if let Some(pid) = cpu.running_process() {
-            if let Some(process) = cpu::process::PROCESS_TABLE.get(pid) {
-                let scheduler_ctx = cpu.scheduler_context_ptr();
-                let process_ctx = process.context_ptr();
-                
-                // Mark process as ready (can be scheduled again)
-                process.mark_ready();
-                
-                // Switch from process context back to scheduler context
-                // This saves all callee-saved registers to process_ctx
-                // and restores them from scheduler_ctx, then returns to
-                // wherever the scheduler was when it switched to us.
-                unsafe {
-                    cpu::process::switch_context(process_ctx, scheduler_ctx);
-                }
-                
-                // We return here when the scheduler switches back to us
-            }
-        }
+/// The interrupted registers stay in the trap frame on the process's stack
+/// until it is resumed and the trap returns.
+pub fn preempt_if_expired(hart: usize, from_kernel: bool) {
+    if hart >= MAX_HARTS || !from_kernel || RUNNING_CONTEXT[hart].load(Ordering::Acquire).is_null() {
+        return;
+    }
+    let started = SLICE_START[hart].load(Ordering::Relaxed);
+    if (crate::get_time_ms() as u64).saturating_sub(started) < TIME_SLICE_MS {
+        return;
+    }
+    // A lock holder must not be parked: whoever waits for the lock next
+    // could be spinning on this very hart
+    if !crate::lock::preemptible(hart) {
+        return;
     }
+    switch_out(hart);
 }
 
-/// Check if a yield is pending for this hart
-pub fn yield_pending(hart_id: usize) -> bool {
-    if hart_id < MAX_HARTS {
-        YIELD_PENDING[hart_id].swap(false, core::sync::atomic::Ordering::AcqRel)
-    } else {
-        false
+/// Voluntarily give up the rest of the time slice. The tick resumes after
+/// other work on this hart had its turn. Does nothing outside a tick or
+/// while a lock is held.
+pub fn yield_now() {
+    let hart = crate::get_hart_id();
+    if hart >= MAX_HARTS || RUNNING_CONTEXT[hart].load(Ordering::Acquire).is_null() || !crate::lock::preemptible(hart) {
+        return;
+    }
+    let enabled = crate::trap::interrupts_enabled();
+    crate::trap::disable_interrupts();
+    switch_out(hart);
+    if enabled {
+        crate::trap::enable_interrupts();
     }
 }
 
-/// Clear yield pending flag
-pub fn clear_yield_pending(hart_id: usize) {
-    if hart_id < MAX_HARTS {
-        YIELD_PENDING[hart_id].store(false, core::sync::atomic::Ordering::Release);
+/// Switch the running tick out until `events` moves past `seen` or the
+/// clock reaches `until_ms` (ms since boot), letting the hart run other
+/// work meanwhile. `interactive` waits are for user input and earn a boost
+/// when it comes. Returns false without waiting outside a tick, while a
+/// lock is held or inside a user program's syscall; the caller then has to
+/// poll.
+pub fn block_current(events: &'static AtomicU64, seen: u64, until_ms: u64, interactive: bool) -> bool {
+    let hart = crate::get_hart_id();
+    if hart >= MAX_HARTS
+        || RUNNING_CONTEXT[hart].load(Ordering::Acquire).is_null()
+        || !crate::lock::preemptible(hart)
+        || crate::trap::in_user_syscall(hart)
+    {
+        return false;
+    }
+    // SAFETY: run_slice keeps the process alive until the tick ends, and
+    // this is the tick
+    let Some(process) = (unsafe { SLICE_PROCESS[hart].load(Ordering::Relaxed).as_ref() }) else {
+        return false;
+    };
+    let enabled = crate::trap::interrupts_enabled();
+    crate::trap::disable_interrupts();
+    // A wake between the caller's check and here moved `events` past
+    // `seen`, so the process is runnable again straight away
+    process.set_wait_interactive(interactive);
+    process.block_on(events, seen, until_ms);
+    switch_out(hart);
+    process.end_wait();
+    if enabled {
+        crate::trap::enable_interrupts();
     }
+    true
+}
+
+/// Park the running tick with every register it might have live
+fn switch_out(hart: usize) {
+    let mut fp_state = [0u64; 33];
+    unsafe { cpu::process::save_fp_state(&mut fp_state) };
+    leave_slice(hart, true);
+    unsafe { cpu::process::restore_fp_state(&fp_state) };
 }
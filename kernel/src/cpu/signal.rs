@@ -0,0 +1,192 @@
+//! Signals
+//!
+//! Sending a signal sets a bit in the target process's pending mask. A user
+//! binary acts on its pending signals when one of its syscalls returns and
+//! when a timer interrupt catches it in user mode
+//! (`elf_loader::deliver_signals`): it runs the handler the binary registered
+//! with `SYS_SIGNAL`, drops the signal if it is ignored, or ends the binary
+//! with exit code 128 + the signal number. SIGKILL cannot be caught or
+//! ignored.
+//!
+//! A binary that polls `should_cancel` gets a pending SIGINT from there
+//! instead of being ended, so it can clean up the way it always has.
+//!
+//! SIGTSTP (Ctrl+Z in a job brought to the foreground with `fg`) and
+//! SIGSTOP stop a binary where it is until SIGCONT resumes it; SIGSTOP
+//! cannot be caught or ignored either.
+//!
+//! Kernel processes have no handlers: a signal ends them right away, except
+//! the stop and continue signals, which they ignore.
+//!
+//! A WASM module has no handlers either. A signal sent to the PID it runs
+//! for (the foreground command, or the worker process running a job)
+//! aborts the module at its next fuel slice and leaves the process running.
+//!
+//! Every process belongs to a process group, identified by the PID of the
+//! process that leads it. Spawned children join their parent's group and
+//! can be moved with `SYS_SETPGID`, so a pipeline put in one group can be
+//! signalled at once with `kill(-pgid, sig)` (`send_group`). The shell runs
+//! each background job in a group of its own and makes it the terminal's
+//! foreground group while `fg` waits for it, so Ctrl+C and Ctrl+Z reach the
+//! whole job.
+
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use crate::cpu::process::{Pid, Process, PROCESS_TABLE};
+use crate::cpu::sched::SCHEDULER;
+
+/// Signal number
+pub type Signal = u32;
+
+/// Interrupt from the terminal (Ctrl+C)
+pub const SIGINT: Signal = 2;
+/// Illegal instruction
+pub const SIGILL: Signal = 4;
+/// Misaligned access
+pub const SIGBUS: Signal = 7;
+/// Kill (cannot be caught or ignored)
+pub const SIGKILL: Signal = 9;
+/// Access to memory the binary does not own
+pub const SIGSEGV: Signal = 11;
+/// Polite request to terminate
+pub const SIGTERM: Signal = 15;
+/// Resume a stopped program
+pub const SIGCONT: Signal = 18;
+/// Stop (cannot be caught or ignored)
+pub const SIGSTOP: Signal = 19;
+/// Stop request from the terminal (Ctrl+Z)
+pub const SIGTSTP: Signal = 20;
+
+/// Signal numbers are 1..NSIG
+pub const NSIG: usize = 32;
+
+/// Handler value: take the default action
+pub const SIG_DFL: usize = 0;
+/// Handler value: drop the signal
+pub const SIG_IGN: usize = 1;
+
+/// Whether `sig` is a signal number
+pub fn is_valid(sig: Signal) -> bool {
+    sig >= 1 && (sig as usize) < NSIG
+}
+
+/// Bit of `sig` in a pending mask
+pub fn bit(sig: Signal) -> u32 {
+    1 << sig
+}
+
+/// Whether `sig` stops a program rather than ending it
+pub fn is_stop(sig: Signal) -> bool {
+    sig == SIGSTOP || sig == SIGTSTP
+}
+
+/// Exit code of a program ended by `sig`
+pub fn exit_code(sig: Signal) -> i32 {
+    128 + sig as i32
+}
+
+/// Name of a signal, for messages
+pub fn name(sig: Signal) -> &'static str {
+    match sig {
+        SIGINT => "SIGINT",
+        SIGILL => "SIGILL",
+        SIGBUS => "SIGBUS",
+        SIGKILL => "SIGKILL",
+        SIGSEGV => "SIGSEGV",
+        SIGTERM => "SIGTERM",
+        SIGCONT => "SIGCONT",
+        SIGSTOP => "SIGSTOP",
+        SIGTSTP => "SIGTSTP",
+        _ => "signal",
+    }
+}
+
+/// Send `sig` to process `pid`
+pub fn send(pid: Pid, sig: Signal) -> Result<(), &'static str> {
+    if !is_valid(sig) {
+        return Err("Invalid signal");
+    }
+    if crate::wasm::abort(pid, sig) {
+        return Ok(());
+    }
+    let process = PROCESS_TABLE.get(pid).ok_or("No such process")?;
+    deliver(&process, sig);
+    Ok(())
+}
+
+/// Send `sig` to every live process in group `pgid`; returns how many it
+/// reached
+pub fn send_group(pgid: Pid, sig: Signal) -> Result<usize, &'static str> {
+    if !is_valid(sig) {
+        return Err("Invalid signal");
+    }
+    let members = PROCESS_TABLE.group(pgid);
+    if members.is_empty() {
+        return Err("No such process group");
+    }
+    for process in &members {
+        deliver(process, sig);
+    }
+    Ok(members.len())
+}
+
+/// Make `sig` pending for the binary in `process`, or end a kernel process
+fn deliver(process: &Process, sig: Signal) {
+    let stop_mask = bit(SIGSTOP) | bit(SIGTSTP);
+    if !process.runs_binary() {
+        if sig != SIGCONT && !is_stop(sig) {
+            SCHEDULER.exit(process.pid, exit_code(sig) as usize);
+        }
+        return;
+    }
+    // Stopping and continuing cancel each other out
+    if sig == SIGCONT {
+        process.take_signals(stop_mask);
+        process.set_stopped(false);
+    } else if is_stop(sig) {
+        process.take_signals(bit(SIGCONT));
+    }
+    process.raise(sig);
+}
+
+/// Process group of the job the shell runs in the foreground (0 = none)
+static FOREGROUND: AtomicU32 = AtomicU32::new(0);
+
+/// Make `pgid` the terminal's foreground group (0 = the shell itself)
+pub fn set_foreground(pgid: Pid) {
+    FOREGROUND.store(pgid, Ordering::Release);
+}
+
+/// The terminal's foreground group; 0 while the shell has the terminal
+pub fn foreground() -> Pid {
+    FOREGROUND.load(Ordering::Acquire)
+}
+
+/// Send `sig` to the binary the shell is running in the foreground (Ctrl+C
+/// in the terminal), or to the job `fg` waits for. Returns false if no
+/// binary is running.
+pub fn send_foreground(sig: Signal) -> bool {
+    let group = foreground();
+    if group != 0 {
+        return send_group(group, sig).is_ok();
+    }
+    let hart = {
+        let state = crate::lock::utils::SHELL_CMD_STATE.lock();
+        if !state.is_running {
+            return false;
+        }
+        state.hart
+    };
+    let Some(process) = crate::cpu::CPU_TABLE
+        .get(hart)
+        .and_then(|cpu| cpu.running_process())
+        .and_then(|pid| PROCESS_TABLE.get(pid))
+    else {
+        return false;
+    };
+    if !process.runs_binary() {
+        return false;
+    }
+    process.raise(sig);
+    true
+}
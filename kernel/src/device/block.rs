@@ -59,6 +59,9 @@ pub trait BlockDevice: Send + Sync {
     }
 
     /// Flush any cached writes to the device
+    ///
+    /// Also a write barrier: every write issued before the flush must be on
+    /// the medium when it returns, before any later write is issued.
     fn flush(&self) -> Result<(), BlockError> {
         Ok(())
     }
@@ -0,0 +1,194 @@
+//! DMA Buffer Pool
+//!
+//! Memory a device reads or writes by address (virtqueues, request and
+//! event buffers) comes from a region the allocator sets aside at the top of
+//! the heap (`allocator::dma_region`) instead of the general heap:
+//!
+//! - the region is physically contiguous and identity-mapped, so the
+//!   address of a buffer is the one the device is given
+//! - every buffer starts on a cache line of its own and covers whole lines,
+//!   so no CPU data shares a line with memory the device writes
+//! - a buffer is aligned to its type: queue memory declared
+//!   `#[repr(align(4096))]` comes out page-aligned without padding by hand
+//! - drivers do not compete with the rest of the kernel for heap, and a
+//!   fragmented heap cannot make a queue refill fail
+//!
+//! Buffers are `DmaBuffer<T>` handles: they own their memory, give it back
+//! to the pool when dropped and deref to `T`. Only `DmaSafe` types (plain
+//! data for which any bytes are a valid value) can be placed there, since
+//! the device may write anything.
+//!
+//! # Usage
+//! ```no_run
+//! use crate::device::dma::DmaBuffer;
+//!
+//! let mut buf: DmaBuffer<[u8; 512]> = DmaBuffer::zeroed()?;
+//! desc.addr = buf.addr();
+//! buf[0] = 1;
+//! ```
+
+use core::ops::{Deref, DerefMut};
+use core::ptr::NonNull;
+
+use crate::Spinlock;
+
+/// Allocation unit of the pool
+pub const CACHE_LINE: usize = 64;
+
+/// Lines the bitmap can track (a 256 KiB pool)
+const MAX_LINES: usize = 4096;
+
+/// Types a device may fill in: any byte pattern, zero included, is a valid
+/// value, and there are no references or pointers to corrupt.
+///
+/// # Safety
+/// Only implement this for plain data (integers, arrays of them and
+/// `#[repr(C)]` structs of them).
+pub unsafe trait DmaSafe {}
+
+unsafe impl DmaSafe for u8 {}
+unsafe impl DmaSafe for u16 {}
+unsafe impl DmaSafe for u32 {}
+unsafe impl DmaSafe for u64 {}
+unsafe impl DmaSafe for i32 {}
+unsafe impl<T: DmaSafe, const N: usize> DmaSafe for [T; N] {}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Pool
+// ═══════════════════════════════════════════════════════════════════════════════
+
+struct Pool {
+    /// First byte of the region (0 until `init`)
+    base: usize,
+    lines: usize,
+    /// Bit set = line in use
+    used: [u64; MAX_LINES / 64],
+    used_lines: usize,
+    /// Where to start looking for free lines
+    next: usize,
+}
+
+impl Pool {
+    fn is_free(&self, line: usize) -> bool {
+        self.used[line / 64] & (1 << (line % 64)) == 0
+    }
+
+    fn mark(&mut self, first: usize, count: usize, used: bool) {
+        for line in first..first + count {
+            if used {
+                self.used[line / 64] |= 1 << (line % 64);
+            } else {
+                self.used[line / 64] &= !(1 << (line % 64));
+            }
+        }
+    }
+
+    /// First run of `count` free lines starting on a multiple of `align`
+    /// lines, searched from `next` and then from the start
+    fn find(&self, count: usize, align: usize) -> Option<usize> {
+        let fits = |first: usize| first + count <= self.lines && (first..first + count).all(|line| self.is_free(line));
+        let from = self.next.next_multiple_of(align);
+        (from..self.lines)
+            .step_by(align)
+            .find(|&first| fits(first))
+            .or_else(|| (0..from.min(self.lines)).step_by(align).find(|&first| fits(first)))
+    }
+
+    fn allocate(&mut self, size: usize, align: usize) -> Option<usize> {
+        let count = size.max(1).div_ceil(CACHE_LINE);
+        let align = align.div_ceil(CACHE_LINE).max(1);
+        let first = self.find(count, align)?;
+        self.mark(first, count, true);
+        self.used_lines += count;
+        self.next = first + count;
+        Some(self.base + first * CACHE_LINE)
+    }
+
+    fn release(&mut self, addr: usize, size: usize) {
+        let first = (addr - self.base) / CACHE_LINE;
+        let count = size.max(1).div_ceil(CACHE_LINE);
+        self.mark(first, count, false);
+        self.used_lines -= count;
+        self.next = self.next.min(first);
+    }
+}
+
+static POOL: Spinlock<Pool> = Spinlock::new(Pool {
+    base: 0,
+    lines: 0,
+    used: [0; MAX_LINES / 64],
+    used_lines: 0,
+    next: 0,
+});
+
+/// Take over the region the allocator reserved (boot, before any driver)
+pub fn init() {
+    let (start, len) = crate::allocator::dma_region();
+    let mut pool = POOL.lock();
+    pool.base = start;
+    pool.lines = (len / CACHE_LINE).min(MAX_LINES);
+}
+
+/// Bytes of the pool in use and in total
+pub fn stats() -> (usize, usize) {
+    let pool = POOL.lock();
+    (pool.used_lines * CACHE_LINE, pool.lines * CACHE_LINE)
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Buffers
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// A `T` in DMA memory, returned to the pool when dropped
+pub struct DmaBuffer<T: DmaSafe> {
+    ptr: NonNull<T>,
+}
+
+// The buffer owns its memory like a Box
+unsafe impl<T: DmaSafe + Send> Send for DmaBuffer<T> {}
+unsafe impl<T: DmaSafe + Sync> Sync for DmaBuffer<T> {}
+
+impl<T: DmaSafe> DmaBuffer<T> {
+    /// A zeroed `T` on its own cache lines, aligned to `T` if that is
+    /// stricter. Fails when the pool is exhausted.
+    pub fn zeroed() -> Result<Self, &'static str> {
+        let size = core::mem::size_of::<T>();
+        let align = core::mem::align_of::<T>().max(CACHE_LINE);
+        let addr = POOL.lock().allocate(size, align).ok_or("DMA pool exhausted")?;
+        unsafe { core::ptr::write_bytes(addr as *mut u8, 0, size) };
+        Ok(Self { ptr: NonNull::new(addr as *mut T).ok_or("DMA pool exhausted")? })
+    }
+
+    /// Address of the buffer as the device sees it
+    pub fn addr(&self) -> u64 {
+        self.ptr.as_ptr() as u64
+    }
+
+    pub fn as_ptr(&self) -> *const T {
+        self.ptr.as_ptr()
+    }
+
+    pub fn as_mut_ptr(&mut self) -> *mut T {
+        self.ptr.as_ptr()
+    }
+}
+
+impl<T: DmaSafe> Deref for DmaBuffer<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { self.ptr.as_ref() }
+    }
+}
+
+impl<T: DmaSafe> DerefMut for DmaBuffer<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { self.ptr.as_mut() }
+    }
+}
+
+impl<T: DmaSafe> Drop for DmaBuffer<T> {
+    fn drop(&mut self) {
+        POOL.lock().release(self.ptr.as_ptr() as usize, core::mem::size_of::<T>());
+    }
+}
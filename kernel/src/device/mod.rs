@@ -27,11 +27,15 @@
 //! ```
 
 pub mod block;
+pub mod dma;
 pub mod network;
 pub mod display;
 pub mod rtc;
 pub mod uart;
+pub mod p9;
+pub mod p9_tcp;
 pub mod virtio_p9;
+pub mod virtio_rng;
 
 pub use block::{BlockDevice, BlockError};
 pub use network::{NetworkDevice, NetworkError};
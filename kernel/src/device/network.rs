@@ -3,9 +3,13 @@
 //! Provides a unified interface for network devices:
 //! - D1 EMAC (DWMAC Ethernet)
 //! - (Legacy) VirtIO network
+//!
+//! Drivers also keep the interface counters here (`count_rx`, `count_tx`,
+//! ...), read back with `interface_stats` for `net_info` and `ifconfig`.
 
 use alloc::boxed::Box;
 use core::ptr::addr_of_mut;
+use core::sync::atomic::{AtomicU64, Ordering};
 
 /// Network device error types
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -86,3 +90,79 @@ pub fn network_device_mut() -> Option<&'static mut dyn NetworkDevice> {
 pub fn get_mac_address() -> Option<[u8; 6]> {
     unsafe { NETWORK_DEVICE.as_ref().map(|d| d.mac_address()) }
 }
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Interface Counters
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Interface counters since boot
+#[derive(Clone, Copy, Default)]
+pub struct InterfaceStats {
+    pub rx_packets: u64,
+    pub rx_bytes: u64,
+    /// Frames received fine but thrown away (firewall)
+    pub rx_dropped: u64,
+    /// Frames that could not be received (too large, bad descriptor)
+    pub rx_errors: u64,
+    pub tx_packets: u64,
+    pub tx_bytes: u64,
+    /// Frames not sent because the TX ring was full
+    pub tx_dropped: u64,
+    /// Frames that could not be sent (device not ready, too large)
+    pub tx_errors: u64,
+}
+
+static RX_PACKETS: AtomicU64 = AtomicU64::new(0);
+static RX_BYTES: AtomicU64 = AtomicU64::new(0);
+static RX_DROPPED: AtomicU64 = AtomicU64::new(0);
+static RX_ERRORS: AtomicU64 = AtomicU64::new(0);
+static TX_PACKETS: AtomicU64 = AtomicU64::new(0);
+static TX_BYTES: AtomicU64 = AtomicU64::new(0);
+static TX_DROPPED: AtomicU64 = AtomicU64::new(0);
+static TX_ERRORS: AtomicU64 = AtomicU64::new(0);
+
+/// Count a received frame of `len` bytes
+pub fn count_rx(len: usize) {
+    RX_PACKETS.fetch_add(1, Ordering::Relaxed);
+    RX_BYTES.fetch_add(len as u64, Ordering::Relaxed);
+}
+
+/// Count a received frame that was thrown away
+pub fn count_rx_dropped() {
+    RX_DROPPED.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Count a frame that could not be received
+pub fn count_rx_error() {
+    RX_ERRORS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Count a sent frame of `len` bytes
+pub fn count_tx(len: usize) {
+    TX_PACKETS.fetch_add(1, Ordering::Relaxed);
+    TX_BYTES.fetch_add(len as u64, Ordering::Relaxed);
+}
+
+/// Count a frame dropped for lack of TX room
+pub fn count_tx_dropped() {
+    TX_DROPPED.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Count a frame that could not be sent
+pub fn count_tx_error() {
+    TX_ERRORS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Current interface counters
+pub fn interface_stats() -> InterfaceStats {
+    InterfaceStats {
+        rx_packets: RX_PACKETS.load(Ordering::Relaxed),
+        rx_bytes: RX_BYTES.load(Ordering::Relaxed),
+        rx_dropped: RX_DROPPED.load(Ordering::Relaxed),
+        rx_errors: RX_ERRORS.load(Ordering::Relaxed),
+        tx_packets: TX_PACKETS.load(Ordering::Relaxed),
+        tx_bytes: TX_BYTES.load(Ordering::Relaxed),
+        tx_dropped: TX_DROPPED.load(Ordering::Relaxed),
+        tx_errors: TX_ERRORS.load(Ordering::Relaxed),
+    }
+}
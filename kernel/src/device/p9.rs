@@ -0,0 +1,556 @@
+//! 9P2000.L Client
+//!
+//! Transport-independent half of the 9P stack: builds T-messages, parses
+//! R-messages and manages fids. A `P9Transport` moves one complete message
+//! each way per call; the VirtIO device (`virtio_p9`) and a TCP connection
+//! (`p9_tcp`) both implement it, so the same client mounts a host directory
+//! either through the emulator or over the network.
+//!
+//! Only one request is in flight at a time, so tags are informational.
+
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Constants
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Largest message size we negotiate
+pub const DEFAULT_MSIZE: u32 = 8192;
+
+/// Bytes of a Twrite/Rread message that are not payload
+const IO_HEADER_SIZE: u32 = 24;
+
+// 9P2000.L Message Types
+const T_VERSION: u8 = 100;
+const T_ATTACH: u8 = 104;
+const T_WALK: u8 = 110;
+const T_LOPEN: u8 = 12;
+const T_READ: u8 = 116;
+const T_WRITE: u8 = 118;
+const T_CLUNK: u8 = 120;
+const T_READDIR: u8 = 40;
+const T_GETATTR: u8 = 24;
+const T_LCREATE: u8 = 14;
+const T_MKDIR: u8 = 72;
+const T_RENAMEAT: u8 = 74;
+const T_UNLINKAT: u8 = 76;
+const R_LERROR: u8 = 7;
+
+// Linux open flags
+pub const O_RDONLY: u32 = 0;
+pub const O_WRONLY: u32 = 1;
+pub const O_RDWR: u32 = 2;
+pub const O_CREAT: u32 = 0x40;
+pub const O_TRUNC: u32 = 0x200;
+
+/// unlinkat flag: remove a directory
+const AT_REMOVEDIR: u32 = 0x200;
+
+/// Tgetattr request mask: mode and size
+const GETATTR_MODE_SIZE: u64 = 0x0000_0001 | 0x0000_0200;
+
+/// File type bits of a mode, and the directory type
+const S_IFMT: u32 = 0o170000;
+const S_IFDIR: u32 = 0o040000;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Transport
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Carries 9P messages to a server
+pub trait P9Transport: Send {
+    /// Send one complete T-message and return the complete R-message
+    fn transact(&mut self, request: &[u8]) -> Result<Vec<u8>, &'static str>;
+
+    /// Largest message the transport can carry
+    fn max_msize(&self) -> u32 {
+        DEFAULT_MSIZE
+    }
+
+    /// Short name for mount listings ("virtio", "tcp")
+    fn kind(&self) -> &'static str;
+}
+
+/// Directory entry from readdir
+#[derive(Clone, Debug)]
+pub struct DirEntry {
+    pub name: String,
+    pub is_dir: bool,
+    /// Server cookie to continue reading after this entry
+    pub offset: u64,
+}
+
+/// Attributes from getattr
+#[derive(Clone, Copy, Debug)]
+pub struct Attr {
+    pub mode: u32,
+    pub size: u64,
+}
+
+impl Attr {
+    pub fn is_dir(&self) -> bool {
+        self.mode & S_IFMT == S_IFDIR
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Client
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// 9P2000.L client over any transport
+pub struct P9Client {
+    transport: Box<dyn P9Transport>,
+    /// Negotiated message size
+    msize: u32,
+    /// Root FID (established during attach)
+    root_fid: u32,
+    /// Next available FID
+    next_fid: u32,
+    /// Message tag counter
+    next_tag: u16,
+}
+
+impl P9Client {
+    /// Negotiate the protocol version and attach to the server's root
+    pub fn new(transport: Box<dyn P9Transport>) -> Result<Self, &'static str> {
+        let msize = transport.max_msize().min(DEFAULT_MSIZE);
+        let mut client = Self {
+            transport,
+            msize,
+            root_fid: 0,
+            next_fid: 1,
+            next_tag: 1,
+        };
+        client.negotiate_version()?;
+        client.attach()?;
+        Ok(client)
+    }
+
+    /// Transport name, e.g. for mount listings
+    pub fn transport_kind(&self) -> &'static str {
+        self.transport.kind()
+    }
+
+    /// Get a new unique FID
+    fn alloc_fid(&mut self) -> u32 {
+        let fid = self.next_fid;
+        self.next_fid = self.next_fid.wrapping_add(1).max(1);
+        fid
+    }
+
+    /// Start a message: size placeholder, type and tag
+    fn message(&mut self, msg_type: u8, capacity: usize) -> Vec<u8> {
+        let tag = self.next_tag;
+        self.next_tag = self.next_tag.wrapping_add(1);
+        if self.next_tag == 0xFFFF {
+            self.next_tag = 1; // NOTAG is reserved for Tversion
+        }
+        let mut buf = Vec::with_capacity(capacity);
+        buf.extend_from_slice(&0u32.to_le_bytes());
+        buf.push(msg_type);
+        buf.extend_from_slice(&tag.to_le_bytes());
+        buf
+    }
+
+    /// Fill in the size field, send the message and check for Rlerror
+    fn send(&mut self, mut req: Vec<u8>) -> Result<Vec<u8>, &'static str> {
+        let size = req.len() as u32;
+        req[0..4].copy_from_slice(&size.to_le_bytes());
+        let resp = self.transport.transact(&req)?;
+        if resp.len() < 7 {
+            return Err("9P response too short");
+        }
+        if resp[4] == R_LERROR {
+            return Err("9P error response");
+        }
+        Ok(resp)
+    }
+
+    // ═══════════════════════════════════════════════════════════════════════════
+    // 9P Protocol Operations
+    // ═══════════════════════════════════════════════════════════════════════════
+
+    /// Negotiate protocol version (Tversion/Rversion)
+    fn negotiate_version(&mut self) -> Result<(), &'static str> {
+        let version = b"9P2000.L";
+
+        let mut req = self.message(T_VERSION, 32);
+        req[5..7].copy_from_slice(&0xFFFFu16.to_le_bytes()); // NOTAG
+        req.extend_from_slice(&self.msize.to_le_bytes());
+        push_str(&mut req, version);
+
+        let resp = self.send(req)?;
+        if resp.len() >= 11 {
+            let negotiated_msize = u32::from_le_bytes(resp[7..11].try_into().unwrap());
+            self.msize = negotiated_msize.min(self.msize);
+        }
+        if self.msize <= IO_HEADER_SIZE {
+            return Err("9P message size too small");
+        }
+        Ok(())
+    }
+
+    /// Attach to filesystem root (Tattach/Rattach)
+    fn attach(&mut self) -> Result<(), &'static str> {
+        let fid = 0u32; // Root FID
+        let afid = 0xFFFFFFFFu32; // No auth
+
+        let mut req = self.message(T_ATTACH, 64);
+        req.extend_from_slice(&fid.to_le_bytes());
+        req.extend_from_slice(&afid.to_le_bytes());
+        push_str(&mut req, b""); // uname
+        push_str(&mut req, b""); // aname
+        req.extend_from_slice(&0u32.to_le_bytes()); // n_uname
+        self.send(req)?;
+
+        self.root_fid = fid;
+        Ok(())
+    }
+
+    /// Walk to a path (Twalk/Rwalk)
+    pub fn walk(&mut self, path: &str) -> Result<u32, &'static str> {
+        let fid = self.root_fid;
+        let new_fid = self.alloc_fid();
+
+        let components: Vec<&str> = path
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .collect();
+
+        let mut req = self.message(T_WALK, 256);
+        req.extend_from_slice(&fid.to_le_bytes());
+        req.extend_from_slice(&new_fid.to_le_bytes());
+        req.extend_from_slice(&(components.len() as u16).to_le_bytes());
+        for name in &components {
+            push_str(&mut req, name.as_bytes());
+        }
+        let resp = self.send(req)?;
+
+        // nwqid must equal components.len() for a successful walk
+        if resp.len() >= 9 {
+            let nwqid = u16::from_le_bytes(resp[7..9].try_into().unwrap()) as usize;
+            if nwqid != components.len() {
+                return Err("Path not found");
+            }
+        }
+        Ok(new_fid)
+    }
+
+    /// Open a file (Tlopen/Rlopen)
+    pub fn open(&mut self, fid: u32, flags: u32) -> Result<(), &'static str> {
+        let mut req = self.message(T_LOPEN, 32);
+        req.extend_from_slice(&fid.to_le_bytes());
+        req.extend_from_slice(&flags.to_le_bytes());
+        self.send(req)?;
+        Ok(())
+    }
+
+    /// Create a new file in a directory (Tlcreate/Rlcreate)
+    ///
+    /// On success `dir_fid` now refers to the new file, opened for writing.
+    pub fn lcreate(&mut self, dir_fid: u32, name: &str) -> Result<u32, &'static str> {
+        let mut req = self.message(T_LCREATE, 32 + name.len());
+        req.extend_from_slice(&dir_fid.to_le_bytes());
+        push_str(&mut req, name.as_bytes());
+        req.extend_from_slice(&(O_WRONLY | O_CREAT | O_TRUNC).to_le_bytes());
+        req.extend_from_slice(&0o644u32.to_le_bytes()); // mode: rw-r--r--
+        req.extend_from_slice(&0u32.to_le_bytes()); // gid
+        self.send(req)?;
+        Ok(dir_fid)
+    }
+
+    /// Create a directory (Tmkdir/Rmkdir)
+    pub fn mkdir(&mut self, dir_fid: u32, name: &str) -> Result<(), &'static str> {
+        let mut req = self.message(T_MKDIR, 32 + name.len());
+        req.extend_from_slice(&dir_fid.to_le_bytes());
+        push_str(&mut req, name.as_bytes());
+        req.extend_from_slice(&0o755u32.to_le_bytes()); // mode: rwxr-xr-x
+        req.extend_from_slice(&0u32.to_le_bytes()); // gid
+        self.send(req)?;
+        Ok(())
+    }
+
+    /// Remove a file, or an empty directory with `dir` (Tunlinkat/Runlinkat)
+    pub fn unlinkat(&mut self, dir_fid: u32, name: &str, dir: bool) -> Result<(), &'static str> {
+        let mut req = self.message(T_UNLINKAT, 32 + name.len());
+        req.extend_from_slice(&dir_fid.to_le_bytes());
+        push_str(&mut req, name.as_bytes());
+        req.extend_from_slice(&(if dir { AT_REMOVEDIR } else { 0 }).to_le_bytes());
+        self.send(req)?;
+        Ok(())
+    }
+
+    /// Rename `old_name` in one directory to `new_name` in another (Trenameat/Rrenameat)
+    pub fn renameat(&mut self, old_dir: u32, old_name: &str, new_dir: u32, new_name: &str) -> Result<(), &'static str> {
+        let mut req = self.message(T_RENAMEAT, 32 + old_name.len() + new_name.len());
+        req.extend_from_slice(&old_dir.to_le_bytes());
+        push_str(&mut req, old_name.as_bytes());
+        req.extend_from_slice(&new_dir.to_le_bytes());
+        push_str(&mut req, new_name.as_bytes());
+        self.send(req)?;
+        Ok(())
+    }
+
+    /// Get mode and size (Tgetattr/Rgetattr)
+    pub fn getattr(&mut self, fid: u32) -> Result<Attr, &'static str> {
+        let mut req = self.message(T_GETATTR, 32);
+        req.extend_from_slice(&fid.to_le_bytes());
+        req.extend_from_slice(&GETATTR_MODE_SIZE.to_le_bytes());
+        let resp = self.send(req)?;
+
+        // valid[8] qid[13] mode[4] uid[4] gid[4] nlink[8] rdev[8] size[8] ...
+        if resp.len() < 64 {
+            return Err("9P getattr response too short");
+        }
+        Ok(Attr {
+            mode: u32::from_le_bytes(resp[28..32].try_into().unwrap()),
+            size: u64::from_le_bytes(resp[56..64].try_into().unwrap()),
+        })
+    }
+
+    /// Read data from file (Tread/Rread)
+    pub fn read(&mut self, fid: u32, offset: u64, count: u32) -> Result<Vec<u8>, &'static str> {
+        let mut req = self.message(T_READ, 32);
+        req.extend_from_slice(&fid.to_le_bytes());
+        req.extend_from_slice(&offset.to_le_bytes());
+        req.extend_from_slice(&count.to_le_bytes());
+        let resp = self.send(req)?;
+
+        // Response: size[4] + type[1] + tag[2] + count[4] + data[count]
+        if resp.len() >= 11 {
+            let data_len = u32::from_le_bytes(resp[7..11].try_into().unwrap()) as usize;
+            let data_end = (11 + data_len).min(resp.len());
+            return Ok(resp[11..data_end].to_vec());
+        }
+        Ok(Vec::new())
+    }
+
+    /// Write data to file (Twrite/Rwrite), at most one message's worth
+    pub fn write(&mut self, fid: u32, offset: u64, data: &[u8]) -> Result<u32, &'static str> {
+        let data = &data[..data.len().min((self.msize - IO_HEADER_SIZE) as usize)];
+        let mut req = self.message(T_WRITE, 32 + data.len());
+        req.extend_from_slice(&fid.to_le_bytes());
+        req.extend_from_slice(&offset.to_le_bytes());
+        req.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        req.extend_from_slice(data);
+        let resp = self.send(req)?;
+
+        // Response: size[4] + type[1] + tag[2] + count[4]
+        if resp.len() >= 11 {
+            return Ok(u32::from_le_bytes(resp[7..11].try_into().unwrap()));
+        }
+        Ok(0)
+    }
+
+    /// Write all of `data` starting at `offset`, split into as many messages as needed
+    pub fn write_all(&mut self, fid: u32, mut offset: u64, mut data: &[u8]) -> Result<(), &'static str> {
+        while !data.is_empty() {
+            let written = self.write(fid, offset, data)? as usize;
+            if written == 0 {
+                return Err("9P write made no progress");
+            }
+            offset += written as u64;
+            data = &data[written.min(data.len())..];
+        }
+        Ok(())
+    }
+
+    /// Close a FID (Tclunk/Rclunk)
+    pub fn clunk(&mut self, fid: u32) -> Result<(), &'static str> {
+        let mut req = self.message(T_CLUNK, 16);
+        req.extend_from_slice(&fid.to_le_bytes());
+        self.send(req)?;
+        Ok(())
+    }
+
+    /// Read directory entries (Treaddir/Rreaddir)
+    pub fn readdir(&mut self, fid: u32, offset: u64, count: u32) -> Result<Vec<DirEntry>, &'static str> {
+        let mut req = self.message(T_READDIR, 32);
+        req.extend_from_slice(&fid.to_le_bytes());
+        req.extend_from_slice(&offset.to_le_bytes());
+        req.extend_from_slice(&count.to_le_bytes());
+        let resp = self.send(req)?;
+
+        let mut entries = Vec::new();
+        if resp.len() >= 11 {
+            let data_len = u32::from_le_bytes(resp[7..11].try_into().unwrap()) as usize;
+            let data = &resp[11..(11 + data_len).min(resp.len())];
+
+            let mut i = 0;
+            while i + 24 <= data.len() {
+                // dirent format: qid[13] + offset[8] + type[1] + name[s]
+                let qtype = data[i]; // First byte of QID
+                let offset = u64::from_le_bytes(data[i + 13..i + 21].try_into().unwrap());
+                i += 13 + 8 + 1;
+
+                let name_len = u16::from_le_bytes(data[i..i + 2].try_into().unwrap()) as usize;
+                i += 2;
+                if i + name_len > data.len() {
+                    break;
+                }
+                let name = core::str::from_utf8(&data[i..i + name_len])
+                    .unwrap_or("")
+                    .to_string();
+                i += name_len;
+
+                entries.push(DirEntry {
+                    name,
+                    is_dir: qtype & 0x80 != 0,
+                    offset,
+                });
+            }
+        }
+        Ok(entries)
+    }
+
+    // ═══════════════════════════════════════════════════════════════════════════
+    // High-Level File Operations
+    // ═══════════════════════════════════════════════════════════════════════════
+
+    /// Read an entire file by path
+    pub fn read_file(&mut self, path: &str) -> Option<Vec<u8>> {
+        let fid = self.walk(path).ok()?;
+        if self.open(fid, O_RDONLY).is_err() {
+            let _ = self.clunk(fid);
+            return None;
+        }
+
+        let mut data = Vec::new();
+        let mut offset = 0u64;
+        let chunk_size = (self.msize - IO_HEADER_SIZE).min(4096);
+        loop {
+            match self.read(fid, offset, chunk_size) {
+                Ok(chunk) if !chunk.is_empty() => {
+                    offset += chunk.len() as u64;
+                    data.extend_from_slice(&chunk);
+                }
+                _ => break,
+            }
+        }
+
+        let _ = self.clunk(fid);
+        Some(data)
+    }
+
+    /// List directory contents by path (without "." and "..")
+    pub fn list_dir(&mut self, path: &str) -> Vec<DirEntry> {
+        let fid = match self.walk(path) {
+            Ok(f) => f,
+            Err(_) => return Vec::new(),
+        };
+        if self.open(fid, O_RDONLY).is_err() {
+            let _ = self.clunk(fid);
+            return Vec::new();
+        }
+
+        let mut entries = Vec::new();
+        let mut offset = 0u64;
+        let count = self.msize - IO_HEADER_SIZE;
+        loop {
+            match self.readdir(fid, offset, count) {
+                Ok(batch) if !batch.is_empty() => {
+                    offset = batch[batch.len() - 1].offset;
+                    entries.extend(batch.into_iter().filter(|e| e.name != "." && e.name != ".."));
+                }
+                _ => break,
+            }
+        }
+
+        let _ = self.clunk(fid);
+        entries
+    }
+
+    /// Mode and size of a path
+    pub fn stat(&mut self, path: &str) -> Result<Attr, &'static str> {
+        let fid = self.walk(path)?;
+        let attr = self.getattr(fid);
+        let _ = self.clunk(fid);
+        attr
+    }
+
+    /// Write data at `offset` (or replace the whole file with `None`),
+    /// creating the file if needed. Returns the new file size.
+    pub fn write_file_at(&mut self, path: &str, offset: Option<u64>, data: &[u8]) -> Result<u64, &'static str> {
+        let fid = match self.walk(path) {
+            Ok(fid) => {
+                let flags = if offset.is_some() { O_WRONLY } else { O_WRONLY | O_TRUNC };
+                if self.open(fid, flags).is_err() {
+                    let _ = self.clunk(fid);
+                    return Err("Failed to open file");
+                }
+                fid
+            }
+            Err(_) => {
+                let (parent, name) = split_parent(path);
+                let parent_fid = self.walk(parent).map_err(|_| "Parent directory not found")?;
+                match self.lcreate(parent_fid, name) {
+                    Ok(fid) => fid,
+                    Err(_) => {
+                        let _ = self.clunk(parent_fid);
+                        return Err("Failed to create file");
+                    }
+                }
+            }
+        };
+
+        let start = offset.unwrap_or(0);
+        let result = self.write_all(fid, start, data).and_then(|_| self.getattr(fid));
+        let _ = self.clunk(fid);
+        result.map(|attr| attr.size.max(start + data.len() as u64))
+    }
+
+    /// Create a directory at `path`
+    pub fn mkdir_path(&mut self, path: &str) -> Result<(), &'static str> {
+        let (parent, name) = split_parent(path);
+        let parent_fid = self.walk(parent).map_err(|_| "Parent directory not found")?;
+        let result = self.mkdir(parent_fid, name);
+        let _ = self.clunk(parent_fid);
+        result
+    }
+
+    /// Remove a file or empty directory at `path`
+    pub fn remove_path(&mut self, path: &str) -> Result<(), &'static str> {
+        let is_dir = self.stat(path)?.is_dir();
+        let (parent, name) = split_parent(path);
+        let parent_fid = self.walk(parent)?;
+        let result = self.unlinkat(parent_fid, name, is_dir);
+        let _ = self.clunk(parent_fid);
+        result
+    }
+
+    /// Rename `from` to `to`
+    pub fn rename_path(&mut self, from: &str, to: &str) -> Result<(), &'static str> {
+        let (old_parent, old_name) = split_parent(from);
+        let (new_parent, new_name) = split_parent(to);
+        let old_fid = self.walk(old_parent)?;
+        let new_fid = match self.walk(new_parent) {
+            Ok(fid) => fid,
+            Err(e) => {
+                let _ = self.clunk(old_fid);
+                return Err(e);
+            }
+        };
+        let result = self.renameat(old_fid, old_name, new_fid, new_name);
+        let _ = self.clunk(old_fid);
+        let _ = self.clunk(new_fid);
+        result
+    }
+}
+
+/// Append a 9P string: len[2] + data
+fn push_str(buf: &mut Vec<u8>, s: &[u8]) {
+    buf.extend_from_slice(&(s.len() as u16).to_le_bytes());
+    buf.extend_from_slice(s);
+}
+
+/// Split "/a/b/c" into ("/a/b", "c")
+fn split_parent(path: &str) -> (&str, &str) {
+    let path = path.trim_end_matches('/');
+    match path.rfind('/') {
+        Some(0) => ("/", &path[1..]),
+        Some(i) => (&path[..i], &path[i + 1..]),
+        None => ("/", path),
+    }
+}
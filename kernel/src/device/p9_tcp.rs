@@ -0,0 +1,157 @@
+//! 9P over TCP Transport
+//!
+//! Carries 9P2000.L messages over a TCP connection (e.g. to `diod` or
+//! `u9fs` on a development host, usually port 564), so a host directory can
+//! be mounted over the network on real hardware.
+//!
+//! The connection has its own slot in the `NetState` socket table, so it
+//! coexists with other TCP connections. 9P messages are length-prefixed
+//! (size[4] includes itself), which is how responses are reassembled from
+//! the byte stream.
+
+use alloc::vec::Vec;
+
+use smoltcp::wire::Ipv4Address;
+
+use super::p9::{P9Transport, DEFAULT_MSIZE};
+use crate::clint::get_time_ms;
+use crate::net::server::TcpSocketId;
+use crate::NET_STATE;
+
+/// Conventional 9P port
+pub const DEFAULT_PORT: u16 = 564;
+
+/// How long to wait for the connection to be established
+const CONNECT_TIMEOUT_MS: i64 = 5000;
+
+/// How long a single request/response may take
+const TRANSACT_TIMEOUT_MS: i64 = 10_000;
+
+/// 9P transport over a dedicated TCP connection
+pub struct P9TcpTransport {
+    socket: TcpSocketId,
+}
+
+impl P9TcpTransport {
+    /// Connect to a 9P server and wait until the connection is established
+    pub fn connect(ip: Ipv4Address, port: u16) -> Result<Self, &'static str> {
+        let start = get_time_ms();
+        let socket = {
+            let mut net_guard = NET_STATE.lock();
+            let net = net_guard.as_mut().ok_or("Network not available")?;
+            net.tcp_connect(ip, port, start)?
+        };
+        // From here on, Drop releases the socket slot on failure
+        let transport = Self { socket };
+
+        loop {
+            let now = get_time_ms();
+            {
+                let mut net_guard = NET_STATE.lock();
+                let net = net_guard.as_mut().ok_or("Network not available")?;
+                net.poll(now);
+                match net.tcp_state(transport.socket) {
+                    "Established" => return Ok(transport),
+                    "SynSent" | "SynReceived" => {}
+                    _ => return Err("Connection refused"),
+                }
+            }
+            if now - start > CONNECT_TIMEOUT_MS {
+                return Err("Connection timed out");
+            }
+            wait_ms(1);
+        }
+    }
+
+    /// Send all of `data`, waiting for TX buffer space as needed
+    fn send_all(&mut self, mut data: &[u8], deadline: i64) -> Result<(), &'static str> {
+        while !data.is_empty() {
+            let now = get_time_ms();
+            let sent = {
+                let mut net_guard = NET_STATE.lock();
+                let net = net_guard.as_mut().ok_or("Network not available")?;
+                let sent = net.tcp_send(self.socket, data, now)?;
+                net.poll(now);
+                sent
+            };
+            data = &data[sent..];
+            if sent == 0 {
+                if now > deadline {
+                    return Err("9P transaction timeout");
+                }
+                wait_ms(1);
+            }
+        }
+        Ok(())
+    }
+
+    /// Receive one complete 9P message
+    fn recv_message(&mut self, deadline: i64) -> Result<Vec<u8>, &'static str> {
+        let mut message = Vec::new();
+        let mut chunk = [0u8; 1024];
+        loop {
+            // Only read what belongs to this message; one request is in flight
+            let wanted = match message_size(&message) {
+                Some(size) if size < 7 || size > DEFAULT_MSIZE as usize => {
+                    return Err("Invalid 9P message size");
+                }
+                Some(size) if message.len() == size => return Ok(message),
+                Some(size) => size - message.len(),
+                None => 4 - message.len(),
+            };
+
+            let now = get_time_ms();
+            let (received, state) = {
+                let mut net_guard = NET_STATE.lock();
+                let net = net_guard.as_mut().ok_or("Network not available")?;
+                let len = wanted.min(chunk.len());
+                let received = net.tcp_recv(self.socket, &mut chunk[..len], now)?;
+                (received, net.tcp_state(self.socket))
+            };
+            message.extend_from_slice(&chunk[..received]);
+
+            if received == 0 {
+                if state != "Established" {
+                    return Err("9P connection closed");
+                }
+                if now > deadline {
+                    return Err("9P transaction timeout");
+                }
+                wait_ms(1);
+            }
+        }
+    }
+}
+
+impl P9Transport for P9TcpTransport {
+    fn transact(&mut self, request: &[u8]) -> Result<Vec<u8>, &'static str> {
+        let deadline = get_time_ms() + TRANSACT_TIMEOUT_MS;
+        self.send_all(request, deadline)?;
+        self.recv_message(deadline)
+    }
+
+    fn kind(&self) -> &'static str {
+        "tcp"
+    }
+}
+
+impl Drop for P9TcpTransport {
+    fn drop(&mut self) {
+        let mut net_guard = NET_STATE.lock();
+        if let Some(net) = net_guard.as_mut() {
+            net.tcp_disconnect(self.socket, get_time_ms());
+        }
+    }
+}
+
+/// Size field of a partially received message, once its 4 bytes are in
+fn message_size(buf: &[u8]) -> Option<usize> {
+    buf.get(..4).map(|size| u32::from_le_bytes([size[0], size[1], size[2], size[3]]) as usize)
+}
+
+fn wait_ms(ms: i64) {
+    let target = get_time_ms() + ms;
+    while get_time_ms() < target {
+        unsafe { core::arch::asm!("wfi", options(nomem, nostack)); }
+    }
+}
@@ -4,6 +4,7 @@
 //! The VM must provide the Unix timestamp at this address.
 
 use core::ptr;
+use core::sync::atomic::{AtomicU64, Ordering};
 
 /// RTC MMIO base address
 const RTC_BASE: usize = 0x10100000;
@@ -18,6 +19,36 @@ pub fn get_host_timestamp() -> u64 {
     }
 }
 
+/// Wall clock anchor: RTC second and the monotonic time it was seen at
+static ANCHOR_SECS: AtomicU64 = AtomicU64::new(0);
+static ANCHOR_NS: AtomicU64 = AtomicU64::new(0);
+
+/// Wall-clock time in nanoseconds since the Unix epoch, None without an RTC
+///
+/// The RTC only counts seconds, so the time within the second comes from
+/// the monotonic clock, measured from an anchor. The anchor moves to each
+/// RTC second boundary this sees, so after the first boundary the result
+/// is accurate to about the interval between calls, and it follows the
+/// RTC if the host clock is changed.
+pub fn realtime_ns() -> Option<u64> {
+    let secs = get_host_timestamp();
+    if secs == 0 {
+        return None;
+    }
+    let now = crate::clint::get_time_ns();
+    let anchor_secs = ANCHOR_SECS.load(Ordering::Acquire);
+    let anchor_ns = ANCHOR_NS.load(Ordering::Acquire);
+    let since_anchor = now.saturating_sub(anchor_ns);
+    let predicted = anchor_secs + since_anchor / 1_000_000_000;
+    if anchor_secs == 0 || predicted != secs {
+        // First read, a second boundary just passed, or the clocks drifted
+        ANCHOR_NS.store(now, Ordering::Release);
+        ANCHOR_SECS.store(secs, Ordering::Release);
+        return Some(secs * 1_000_000_000);
+    }
+    Some(anchor_secs * 1_000_000_000 + since_anchor)
+}
+
 /// Simple date/time representation
 #[derive(Clone, Copy)]
 pub struct DateTime {
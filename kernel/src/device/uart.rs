@@ -1,12 +1,16 @@
 use core::fmt::{self, Write};
 use core::sync::atomic::{AtomicU32, Ordering};
 
+use alloc::collections::VecDeque;
 use alloc::vec::Vec;
 
+use crate::Spinlock;
+use crate::cpu::{get_hart_id, MAX_HARTS};
+use crate::lock::wait::{WaitQueue, IO_EVENTS};
 use crate::lock::utils::OUTPUT_CAPTURE;
 use crate::scripting::execute_command;
-use crate::utils::{poll_tail_follow, resolve_path};
-use crate::services::{klogd, sysmond};
+use crate::utils::resolve_path;
+use crate::services::{klogd, sysmond, telnetd};
 
 const UART_BASE: usize = 0x1000_0000;
 
@@ -38,6 +42,129 @@ fn uart_lock_release() {
     UART_LOCK.swap(UART_UNLOCKED, Ordering::Release);
 }
 
+// ============================================================================
+// LINE BUFFERS - Keeps each hart's lines whole on the wire
+// ============================================================================
+//
+// UART_LOCK alone only protects a single call: `write_str("x = ")` followed
+// by `write_u64(x)` could still have another hart's line land in between.
+// Every buffered writer below appends to the calling hart's line buffer and
+// the UART only sees complete lines. A `\r` also ends a line, so progress
+// output that redraws itself shows up. Other partial lines (prompts, echo)
+// are pushed out when the buffer fills, when the hart polls for input, at
+// the end of every syscall and scheduler tick, or on an explicit `flush()`.
+
+/// Bytes buffered per hart before a partial line is forced out
+const LINE_BUFFER_SIZE: usize = 256;
+
+struct LineBuffer {
+    buf: [u8; LINE_BUFFER_SIZE],
+    len: usize,
+}
+
+impl LineBuffer {
+    const fn new() -> Self {
+        Self {
+            buf: [0; LINE_BUFFER_SIZE],
+            len: 0,
+        }
+    }
+
+    /// Append bytes, emitting every completed line (ended by `\n` or `\r`).
+    fn push(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.buf[self.len] = b;
+            self.len += 1;
+            if b == b'\n' || b == b'\r' || self.len == LINE_BUFFER_SIZE {
+                self.emit();
+            }
+        }
+    }
+
+    /// Write the pending bytes to the UART as one unit.
+    fn emit(&mut self) {
+        if self.len == 0 {
+            return;
+        }
+        emit_locked(&[&self.buf[..self.len]]);
+        self.len = 0;
+    }
+}
+
+/// One line buffer per hart (only ever locked by its own hart)
+static LINE_BUFFERS: [Spinlock<LineBuffer>; MAX_HARTS] = {
+    const INIT: Spinlock<LineBuffer> = Spinlock::new(LineBuffer::new());
+    [INIT; MAX_HARTS]
+};
+
+/// Write byte slices straight to the UART under UART_LOCK (and to the
+/// telnet client, if one is attached).
+fn emit_locked(parts: &[&[u8]]) {
+    uart_lock_acquire();
+    let mut console = Console::new();
+    for part in parts {
+        for &b in *part {
+            console.write_byte(b);
+        }
+        telnetd::mirror_output(part);
+    }
+    uart_lock_release();
+}
+
+/// Append byte slices to the current hart's line buffer.
+fn write_buffered(parts: &[&[u8]]) {
+    // The buffer is only contended when a trap handler prints while this
+    // hart is mid-write; fall back to an unbuffered write in that case.
+    match LINE_BUFFERS[get_hart_id()].try_lock() {
+        Some(mut line) => {
+            for part in parts {
+                line.push(part);
+            }
+        }
+        None => emit_locked(parts),
+    }
+}
+
+/// Push out the current hart's partial line, if any.
+///
+/// Called automatically before reading console input and at the end of
+/// every syscall and scheduler tick, so prompts show up; call it directly
+/// after printing a prompt that isn't followed by any of these.
+pub fn flush() {
+    if let Some(mut line) = LINE_BUFFERS[get_hart_id()].try_lock() {
+        line.emit();
+    }
+}
+
+/// fmt adapter that feeds the current hart's line buffer
+struct LineWriter;
+
+impl Write for LineWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        write_buffered(&[s.as_bytes()]);
+        Ok(())
+    }
+}
+
+// ============================================================================
+// RAW OUTPUT - Escape hatch for panic and fault paths
+// ============================================================================
+
+/// Write directly to the UART, bypassing the line buffers and UART_LOCK.
+///
+/// Only for paths that must not take locks (panic handler, fatal traps):
+/// the holder of UART_LOCK or of a line buffer may be the hart that died.
+pub fn write_str_raw(s: &str) {
+    let mut console = Console::new();
+    let _ = console.write_str(s);
+}
+
+/// Format directly to the UART, bypassing the line buffers and UART_LOCK.
+pub fn print_fmt_raw(args: fmt::Arguments) {
+    let mut console = Console::new();
+    let _ = core::fmt::write(&mut console, args);
+}
+
 // NS16550A UART register offsets
 const RBR: usize = 0x00; // Receiver Buffer Register (read)
 const THR: usize = 0x00; // Transmitter Holding Register (write)
@@ -105,6 +232,7 @@ impl Console {
     /// Use this for guaranteed input reception.
     /// While waiting, periodically runs background tasks on hart 0.
     pub fn read_byte_blocking(&self) -> u8 {
+        flush();
         let mut poll_counter: u32 = 0;
         // Spin until data is ready
         while !Self::is_rx_ready() {
@@ -116,9 +244,6 @@ impl Console {
                 // Run hart0 background tasks (klogd, sysmond)
                 klogd::klogd_tick();
                 sysmond::sysmond_tick();
-                
-                // Poll tail -f for new content
-                poll_tail_follow();
             }
         }
         unsafe { core::ptr::read_volatile((UART_BASE + RBR) as *const u8) }
@@ -142,8 +267,10 @@ impl Console {
         (Self::lsr() & LSR_RX_READY) != 0
     }
 
-    /// Public version of is_rx_ready for external use
+    /// Public version of is_rx_ready for external use.
+    /// Flushes this hart's partial line first so prompts are visible.
     pub fn is_rx_ready_public() -> bool {
+        flush();
         Self::is_rx_ready()
     }
 
@@ -155,6 +282,7 @@ impl Console {
     }
 
     pub fn read_byte(&self) -> u8 {
+        flush();
         // Only return a byte if data is ready, otherwise return 0
         if Self::is_rx_ready() {
             unsafe { core::ptr::read_volatile((UART_BASE + RBR) as *const u8) }
@@ -173,118 +301,153 @@ impl Write for Console {
     }
 }
 
-/// Write a raw string to the UART without using `core::fmt`.
-/// Protected by UART_LOCK to prevent interleaved output from multiple harts.
+/// Write a string to the console without using `core::fmt`.
+/// Line-buffered per hart so output from different harts doesn't interleave.
 pub fn write_str(s: &str) {
-    uart_lock_acquire();
-    let mut console = Console::new();
-    let _ = console.write_str(s);
-    uart_lock_release();
+    write_buffered(&[s.as_bytes()]);
 }
 
-/// Write a raw string followed by `\n`.
-/// Protected by UART_LOCK.
+/// Write a string followed by `\n` (emitted as a single line).
 pub fn write_line(s: &str) {
-    uart_lock_acquire();
-    let mut console = Console::new();
-    let _ = console.write_str(s);
-    let _ = console.write_str("\n");
-    uart_lock_release();
+    write_buffered(&[s.as_bytes(), b"\n"]);
 }
 
-/// Write a raw byte slice to the UART.
+/// Write a byte slice to the console.
 pub fn write_bytes(bytes: &[u8]) {
-    let mut console = Console::new();
-    for &b in bytes {
-        console.write_byte(b);
-    }
+    write_buffered(&[bytes]);
 }
 
-/// Write a single byte to the UART.
+/// Write a single byte to the console.
 pub fn write_byte(byte: u8) {
-    Console::new().write_byte(byte);
+    write_buffered(&[&[byte]]);
 }
 
 /// Write an unsigned integer in decimal.
 pub fn write_u64(mut n: u64) {
-    let mut console = Console::new();
-
-    if n == 0 {
-        console.write_byte(b'0');
-        return;
-    }
-
     let mut buf = [0u8; 20]; // enough for u64
-    let mut i = 0;
+    let mut i = buf.len();
 
-    while n > 0 && i < buf.len() {
-        let digit = (n % 10) as u8;
-        buf[i] = b'0' + digit;
+    loop {
+        i -= 1;
+        buf[i] = b'0' + (n % 10) as u8;
         n /= 10;
-        i += 1;
+        if n == 0 {
+            break;
+        }
     }
 
-    while i > 0 {
-        i -= 1;
-        console.write_byte(buf[i]);
-    }
+    write_buffered(&[&buf[i..]]);
 }
 
 /// Write an unsigned integer in hexadecimal.
 pub fn write_hex(mut n: u64) {
-    let mut console = Console::new();
     let hex_digits = b"0123456789abcdef";
-
-    if n == 0 {
-        console.write_byte(b'0');
-        return;
-    }
-
     let mut buf = [0u8; 16]; // enough for u64 hex
-    let mut i = 0;
+    let mut i = buf.len();
 
-    while n > 0 && i < buf.len() {
+    loop {
+        i -= 1;
         buf[i] = hex_digits[(n & 0xf) as usize];
         n >>= 4;
-        i += 1;
+        if n == 0 {
+            break;
+        }
     }
 
-    while i > 0 {
-        i -= 1;
-        console.write_byte(buf[i]);
-    }
+    write_buffered(&[&buf[i..]]);
 }
 
 /// Write a single byte in hexadecimal (2 characters).
 pub fn write_hex_byte(b: u8) {
-    let mut console = Console::new();
     let hex_digits = b"0123456789abcdef";
-    console.write_byte(hex_digits[(b >> 4) as usize]);
-    console.write_byte(hex_digits[(b & 0xf) as usize]);
+    write_buffered(&[&[hex_digits[(b >> 4) as usize], hex_digits[(b & 0xf) as usize]]]);
 }
 
-/// Check if console has pending input
+/// Check if console has pending input (on the UART, from a telnet client
+/// or typed ahead)
 pub fn has_pending_input() -> bool {
-    Console::is_rx_ready_public()
+    !TYPED_AHEAD.lock().is_empty() || Console::is_rx_ready_public() || telnetd::has_input()
 }
 
 /// Read a character from console (non-blocking)
 /// Returns None if no character is available
 pub fn read_char_nonblocking() -> Option<u8> {
+    if let Some(byte) = TYPED_AHEAD.lock().pop_front() {
+        return Some(byte);
+    }
+    read_device_nonblocking()
+}
+
+/// Read a character from the UART or a telnet client (non-blocking),
+/// leaving the keys typed ahead alone
+pub fn read_device_nonblocking() -> Option<u8> {
     if Console::is_rx_ready_public() {
         Some(Console::new().read_byte())
     } else {
-        None
+        telnetd::read_input()
+    }
+}
+
+// ============================================================================
+// TYPE-AHEAD
+// ============================================================================
+//
+// While `fg` waits for a job, the shell reads the console to catch Ctrl+C
+// and Ctrl+Z and hands every other key back here for the job to read.
+
+/// Most keys kept for a program that has not read them yet
+const TYPE_AHEAD_SIZE: usize = 256;
+
+/// Keys given back to the console, read before new input
+static TYPED_AHEAD: Spinlock<VecDeque<u8>> = Spinlock::new(VecDeque::new());
+
+/// Give a key back to the console: the next read returns it. Keys past
+/// TYPE_AHEAD_SIZE are dropped.
+pub fn unread_char(byte: u8) {
+    let mut typed = TYPED_AHEAD.lock();
+    if typed.len() < TYPE_AHEAD_SIZE {
+        typed.push_back(byte);
     }
 }
 
-/// Format and print to UART using core::fmt::Arguments
-/// Protected by UART_LOCK to prevent interleaved output from multiple harts.
+// ============================================================================
+// INPUT WAITING
+// ============================================================================
+//
+// The UART's receive interrupt is not wired up, so hart 0's timer interrupt
+// looks at the receive flag every tick and telnetd reports what a client
+// typed. Waiters are woken through an interactive wait queue, which gives
+// them the scheduler's interactivity boost.
+
+/// Woken when console input arrives
+static CONSOLE_INPUT: WaitQueue = WaitQueue::interactive();
+
+/// Wake the processes waiting for console input if the UART has some
+/// (called from hart 0's timer interrupt)
+pub fn poll_input() {
+    if Console::is_rx_ready() {
+        crate::random::add_event_timing();
+        CONSOLE_INPUT.wake_all();
+        IO_EVENTS.wake_all();
+    }
+}
+
+/// Wake the processes waiting for console input (a telnet client typed)
+pub fn input_arrived() {
+    CONSOLE_INPUT.wake_all();
+    IO_EVENTS.wake_all();
+}
+
+/// Wait until console input is pending or the clock reaches `until_ms` (ms
+/// since boot). Returns whether there is input.
+pub fn wait_for_input(until_ms: i64) -> bool {
+    CONSOLE_INPUT.wait_until(until_ms, has_pending_input)
+}
+
+/// Format and print to the console using core::fmt::Arguments
+/// Line-buffered per hart so output from different harts doesn't interleave.
 pub fn print_fmt(args: fmt::Arguments) {
-    uart_lock_acquire();
-    let mut console = Console::new();
-    let _ = core::fmt::write(&mut console, args);
-    uart_lock_release();
+    let _ = core::fmt::write(&mut LineWriter, args);
 }
 
 #[macro_export]
@@ -365,7 +528,15 @@ pub fn handle_line(buffer: &[u8], len: usize, _count: &mut usize) {
         return;
     }
 
-    let full_line = &buffer[start..end];
+    // `$?` is the exit status of the previous command
+    let expanded = crate::scripting::expand_status(&buffer[start..end]);
+    let mut full_line = &expanded[..];
+
+    // A trailing `&` starts the command as a background job
+    let background = full_line.last() == Some(&b'&');
+    if background {
+        full_line = trim_bytes(&full_line[..full_line.len() - 1]);
+    }
 
     // Parse for redirection
     let (line, redirect_mode, redirect_file) = parse_redirection(full_line);
@@ -390,6 +561,17 @@ pub fn handle_line(buffer: &[u8], len: usize, _count: &mut usize) {
     }
     let args = &line[arg_start..];
 
+    if background {
+        if cmd.is_empty() {
+            write_line("\x1b[1;31mError:\x1b[0m Missing command before &");
+        } else if redirect_mode != RedirectMode::None {
+            write_line("\x1b[1;31mError:\x1b[0m Output of background jobs cannot be redirected");
+        } else {
+            crate::services::shelld::run_background(cmd, args);
+        }
+        return;
+    }
+
     // Start capturing if redirecting
     if redirect_mode != RedirectMode::None {
         output_capture_start();
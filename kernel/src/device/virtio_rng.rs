@@ -0,0 +1,241 @@
+//! VirtIO Entropy Device Driver
+//!
+//! Reads random bytes from the VirtIO entropy device (Device ID 4), which
+//! hands out entropy from the host. It feeds the kernel's random number
+//! generator (`crate::random`) and is not read directly by anything else.
+//!
+//! # Usage
+//! ```no_run
+//! use crate::device::virtio_rng;
+//!
+//! virtio_rng::init()?;
+//! let mut seed = [0u8; 32];
+//! let n = virtio_rng::read(&mut seed);
+//! ```
+
+use core::sync::atomic::Ordering;
+
+use crate::device::dma::{DmaBuffer, DmaSafe};
+use crate::Spinlock;
+
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Constants
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// VirtIO Entropy Device ID
+const VIRTIO_RNG_DEVICE_ID: u32 = 4;
+
+// MMIO register offsets
+const MAGIC_VALUE_OFFSET: usize = 0x000;
+const DEVICE_ID_OFFSET: usize = 0x008;
+const STATUS_OFFSET: usize = 0x070;
+const QUEUE_SEL_OFFSET: usize = 0x030;
+const QUEUE_NUM_OFFSET: usize = 0x038;
+const QUEUE_PFN_OFFSET: usize = 0x040;
+const GUEST_PAGE_SIZE_OFFSET: usize = 0x028;
+const QUEUE_NOTIFY_OFFSET: usize = 0x050;
+const INTERRUPT_STATUS_OFFSET: usize = 0x060;
+const INTERRUPT_ACK_OFFSET: usize = 0x064;
+
+// Device status flags
+const STATUS_ACKNOWLEDGE: u32 = 1;
+const STATUS_DRIVER: u32 = 2;
+const STATUS_DRIVER_OK: u32 = 4;
+const STATUS_FEATURES_OK: u32 = 8;
+
+// Queue constants
+const PAGE_SIZE: usize = 4096;
+const QUEUE_SIZE: u16 = 8;
+const QUEUE_MEM_SIZE: usize = PAGE_SIZE * 2;
+
+/// Bytes asked for per request
+const REQUEST_SIZE: usize = 64;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// VirtQueue Memory
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Page-aligned queue memory for VirtIO descriptors
+#[repr(C, align(4096))]
+struct RngQueueMem {
+    data: [u8; QUEUE_MEM_SIZE],
+}
+
+unsafe impl DmaSafe for RngQueueMem {}
+
+/// VirtIO descriptor structure
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct VirtqDesc {
+    addr: u64,
+    len: u32,
+    flags: u16,
+    next: u16,
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Driver
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// VirtIO entropy device: one device-writable buffer per request
+pub struct VirtioRng {
+    base: usize,
+    queue_mem: DmaBuffer<RngQueueMem>,
+    /// Buffer the device fills
+    buf: DmaBuffer<[u8; REQUEST_SIZE]>,
+    /// Last used ring index
+    last_used_idx: u16,
+}
+
+impl VirtioRng {
+    /// Probe for the VirtIO entropy device using DTB discovery or fallback addresses
+    pub fn probe() -> Option<Self> {
+        let virtio_devices = crate::dtb::find_by_compatible("virtio,mmio");
+        for device in &virtio_devices {
+            let base = device.reg_base as usize;
+            if Self::check_device_id(base) {
+                return Self::create_driver(base);
+            }
+        }
+
+        // Fallback to legacy hardcoded addresses if DTB discovery didn't find anything
+        if virtio_devices.is_empty() {
+            const VIRTIO_BASE: usize = 0x1000_1000;
+            const VIRTIO_STRIDE: usize = 0x1000;
+
+            for i in 0..8 {
+                let base = VIRTIO_BASE + i * VIRTIO_STRIDE;
+                if Self::check_device_id(base) {
+                    return Self::create_driver(base);
+                }
+            }
+        }
+
+        None
+    }
+
+    fn check_device_id(base: usize) -> bool {
+        unsafe {
+            let magic = core::ptr::read_volatile((base + MAGIC_VALUE_OFFSET) as *const u32);
+            let device_id = core::ptr::read_volatile((base + DEVICE_ID_OFFSET) as *const u32);
+            magic == 0x7472_6976 && device_id == VIRTIO_RNG_DEVICE_ID
+        }
+    }
+
+    fn create_driver(base: usize) -> Option<Self> {
+        Some(Self {
+            base,
+            queue_mem: DmaBuffer::zeroed().ok()?,
+            buf: DmaBuffer::zeroed().ok()?,
+            last_used_idx: 0,
+        })
+    }
+
+    /// Reset the device and set up its request queue
+    pub fn init(&mut self) {
+        unsafe {
+            core::ptr::write_volatile((self.base + STATUS_OFFSET) as *mut u32, 0);
+            for _ in 0..1000 {
+                core::hint::spin_loop();
+            }
+            core::ptr::write_volatile((self.base + STATUS_OFFSET) as *mut u32, STATUS_ACKNOWLEDGE | STATUS_DRIVER);
+            core::ptr::write_volatile((self.base + GUEST_PAGE_SIZE_OFFSET) as *mut u32, PAGE_SIZE as u32);
+            core::ptr::write_volatile((self.base + QUEUE_SEL_OFFSET) as *mut u32, 0);
+            core::ptr::write_volatile((self.base + QUEUE_NUM_OFFSET) as *mut u32, QUEUE_SIZE as u32);
+            let pfn = self.queue_mem.addr() / PAGE_SIZE as u64;
+            core::ptr::write_volatile((self.base + QUEUE_PFN_OFFSET) as *mut u32, pfn as u32);
+            core::ptr::write_volatile(
+                (self.base + STATUS_OFFSET) as *mut u32,
+                STATUS_ACKNOWLEDGE | STATUS_DRIVER | STATUS_FEATURES_OK | STATUS_DRIVER_OK,
+            );
+        }
+    }
+
+    /// Fill up to 64 bytes of `out` with entropy; returns how many were filled
+    pub fn read(&mut self, out: &mut [u8]) -> usize {
+        let want = out.len().min(REQUEST_SIZE);
+        if want == 0 {
+            return 0;
+        }
+        let queue_mem_ptr = self.queue_mem.data.as_mut_ptr();
+        let desc_table = queue_mem_ptr as *mut VirtqDesc;
+        let avail_ring = unsafe { queue_mem_ptr.add(QUEUE_SIZE as usize * 16) };
+
+        unsafe {
+            // Descriptor 0: buffer the device writes
+            let desc = &mut *desc_table;
+            desc.addr = self.buf.addr();
+            desc.len = want as u32;
+            desc.flags = 2; // VRING_DESC_F_WRITE
+            desc.next = 0;
+
+            let avail_idx_ptr = avail_ring.add(2) as *mut u16;
+            let avail_idx = core::ptr::read_volatile(avail_idx_ptr);
+            let ring_ptr = avail_ring.add(4 + (avail_idx % QUEUE_SIZE) as usize * 2) as *mut u16;
+            *ring_ptr = 0;
+            core::sync::atomic::fence(Ordering::SeqCst);
+            core::ptr::write_volatile(avail_idx_ptr, avail_idx.wrapping_add(1));
+
+            core::ptr::write_volatile((self.base + QUEUE_NOTIFY_OFFSET) as *mut u32, 0);
+        }
+
+        let used_ring = unsafe {
+            let avail_ring_end = queue_mem_ptr.add(QUEUE_SIZE as usize * 16 + 6 + QUEUE_SIZE as usize * 2) as usize;
+            avail_ring_end.next_multiple_of(PAGE_SIZE) as *const u8
+        };
+
+        for _ in 0..100_000 {
+            let used_idx = unsafe { core::ptr::read_volatile(used_ring.add(2) as *const u16) };
+            if used_idx != self.last_used_idx {
+                let slot = (self.last_used_idx % QUEUE_SIZE) as usize;
+                self.last_used_idx = used_idx;
+                unsafe {
+                    let status = core::ptr::read_volatile((self.base + INTERRUPT_STATUS_OFFSET) as *const u32);
+                    if status != 0 {
+                        core::ptr::write_volatile((self.base + INTERRUPT_ACK_OFFSET) as *mut u32, status);
+                    }
+                }
+                // Used element: id[4] + len[4]
+                let written = unsafe { core::ptr::read_volatile(used_ring.add(4 + slot * 8 + 4) as *const u32) } as usize;
+                let n = written.min(want);
+                out[..n].copy_from_slice(&self.buf[..n]);
+                return n;
+            }
+            core::hint::spin_loop();
+        }
+        0
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Global Driver Instance
+// ═══════════════════════════════════════════════════════════════════════════════
+
+static RNG_DRIVER: Spinlock<Option<VirtioRng>> = Spinlock::new(None);
+
+/// Initialize the entropy driver
+pub fn init() -> Result<(), &'static str> {
+    let mut driver = VirtioRng::probe().ok_or("VirtIO entropy device not found")?;
+    driver.init();
+    *RNG_DRIVER.lock() = Some(driver);
+    Ok(())
+}
+
+/// Fill `out` with entropy from the device; returns how many bytes were
+/// filled (0 without a device)
+pub fn read(out: &mut [u8]) -> usize {
+    let mut guard = RNG_DRIVER.lock();
+    let Some(driver) = guard.as_mut() else {
+        return 0;
+    };
+    let mut filled = 0;
+    while filled < out.len() {
+        let n = driver.read(&mut out[filled..]);
+        if n == 0 {
+            break;
+        }
+        filled += n;
+    }
+    filled
+}
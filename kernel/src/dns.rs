@@ -1,13 +1,34 @@
 //! DNS client implementation for hostname resolution.
 //!
 //! This module provides DNS query building and response parsing
-//! to resolve hostnames to IPv4 addresses.
+//! to resolve hostnames to IPv4 (A) and IPv6 (AAAA) addresses.
+//!
+//! `resolve` and `resolve6` answer from `/etc/hosts` first, then from a
+//! cache of earlier answers kept for their TTL, and only then query the
+//! server. `flush` empties the cache and re-reads `/etc/hosts`; edits to
+//! `/etc/hosts` are also picked up on their own.
+//!
+//! Both follow CNAMEs: when the server answers with an alias but no address
+//! for it, the alias is looked up in turn. `query` returns the raw records
+//! of any type (A, AAAA, CNAME, TXT, SRV and others by number) for `nslookup`.
 
+use alloc::collections::BTreeMap;
+use alloc::string::String;
 use alloc::vec::Vec;
-use smoltcp::wire::Ipv4Address;
+use core::fmt;
+use smoltcp::wire::{IpAddress, Ipv4Address, Ipv6Address};
+
+use crate::fs::watch::{PathWatch, WATCH_ALL};
+use crate::Spinlock;
 
 /// DNS query type for A records (IPv4 address)
 const DNS_TYPE_A: u16 = 1;
+/// DNS query type for AAAA records (IPv6 address)
+const DNS_TYPE_AAAA: u16 = 28;
+/// DNS record types only `query` asks for
+pub const DNS_TYPE_CNAME: u16 = 5;
+pub const DNS_TYPE_TXT: u16 = 16;
+pub const DNS_TYPE_SRV: u16 = 33;
 /// DNS class for Internet
 const DNS_CLASS_IN: u16 = 1;
 
@@ -21,6 +42,34 @@ const DNS_RCODE_MASK: u16 = 0x000F;
 const DNS_RCODE_OK: u16 = 0;
 const DNS_RCODE_NXDOMAIN: u16 = 3;
 
+/// Most aliases followed before giving up (a CNAME loop ends here too)
+const MAX_CNAME_CHAIN: usize = 8;
+
+/// Most compression pointers followed in one name
+const MAX_NAME_POINTERS: usize = 16;
+
+/// Static host table, read at boot and by `flush`
+pub const HOSTS_PATH: &str = "/etc/hosts";
+/// Watch on /etc/hosts so edits apply without `flushdns`
+static HOSTS_WATCH: PathWatch = PathWatch::new(HOSTS_PATH, WATCH_ALL);
+
+/// Most answers kept in the cache
+const CACHE_MAX_ENTRIES: usize = 64;
+/// Longest an answer is cached, whatever its TTL says (seconds)
+const CACHE_MAX_TTL_SECS: u32 = 3600;
+
+/// A cached answer
+struct CacheEntry {
+    addr: IpAddress,
+    expires_ms: i64,
+}
+
+/// Cached answers by (lowercase hostname, query type)
+static CACHE: Spinlock<BTreeMap<(String, u16), CacheEntry>> = Spinlock::new(BTreeMap::new());
+
+/// Entries of /etc/hosts as (lowercase hostname, address)
+static HOSTS: Spinlock<Vec<(String, IpAddress)>> = Spinlock::new(Vec::new());
+
 /// Transaction ID counter
 static mut DNS_TRANSACTION_ID: u16 = 0x1234;
 
@@ -36,6 +85,11 @@ fn next_transaction_id() -> u16 {
 ///
 /// Returns (transaction_id, query_packet)
 pub fn build_query(hostname: &[u8]) -> (u16, Vec<u8>) {
+    build_query_for(hostname, DNS_TYPE_A)
+}
+
+/// Build a DNS query packet for a record of type `qtype`
+fn build_query_for(hostname: &[u8], qtype: u16) -> (u16, Vec<u8>) {
     let txid = next_transaction_id();
 
     // Estimate packet size: header (12) + name (hostname.len() + 2 for length bytes + 1 for null) + qtype (2) + qclass (2)
@@ -59,8 +113,8 @@ pub fn build_query(hostname: &[u8]) -> (u16, Vec<u8>) {
     // QNAME: domain name encoded as labels
     encode_domain_name(hostname, &mut packet);
 
-    // QTYPE: A record (1)
-    packet.extend_from_slice(&DNS_TYPE_A.to_be_bytes());
+    // QTYPE: A (1) or AAAA (28)
+    packet.extend_from_slice(&qtype.to_be_bytes());
     // QCLASS: IN (1)
     packet.extend_from_slice(&DNS_CLASS_IN.to_be_bytes());
 
@@ -90,8 +144,14 @@ fn encode_domain_name(hostname: &[u8], packet: &mut Vec<u8>) {
 /// DNS response parsing result
 #[derive(Debug)]
 pub enum DnsResult {
-    /// Successfully resolved to one or more IPv4 addresses
-    Resolved(Vec<Ipv4Address>),
+    /// Successfully resolved to one or more IPv4 addresses, with the
+    /// smallest TTL of those records (seconds)
+    Resolved(Vec<Ipv4Address>, u32),
+    /// Successfully resolved to one or more IPv6 addresses, with their TTL
+    Resolved6(Vec<Ipv6Address>, u32),
+    /// The name is an alias whose target the answer has no address for,
+    /// with the smallest TTL along the chain
+    Alias(String, u32),
     /// Domain does not exist (NXDOMAIN)
     NotFound,
     /// Server error or malformed response
@@ -100,17 +160,136 @@ pub enum DnsResult {
     WrongId,
 }
 
-/// Parse a DNS response packet
+/// A resource record from an answer
+#[derive(Debug)]
+pub struct DnsRecord {
+    /// Owner name, without the trailing dot
+    pub name: String,
+    pub ttl: u32,
+    pub data: RecordData,
+}
+
+/// What a record holds
+#[derive(Debug)]
+pub enum RecordData {
+    A(Ipv4Address),
+    Aaaa(Ipv6Address),
+    Cname(String),
+    /// The character-strings of a TXT record
+    Txt(Vec<String>),
+    Srv { priority: u16, weight: u16, port: u16, target: String },
+    /// Any other type: its number and RDATA length
+    Other(u16, usize),
+}
+
+impl RecordData {
+    /// Record type number
+    pub fn rtype(&self) -> u16 {
+        match self {
+            RecordData::A(_) => DNS_TYPE_A,
+            RecordData::Aaaa(_) => DNS_TYPE_AAAA,
+            RecordData::Cname(_) => DNS_TYPE_CNAME,
+            RecordData::Txt(_) => DNS_TYPE_TXT,
+            RecordData::Srv { .. } => DNS_TYPE_SRV,
+            RecordData::Other(rtype, _) => *rtype,
+        }
+    }
+}
+
+/// Zone file style: "A 192.0.2.1", "TXT \"v=spf1\" \"-all\"", "SRV 10 5 5060 sip.example.com"
+impl fmt::Display for RecordData {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RecordData::A(addr) => write!(f, "A {}", addr),
+            RecordData::Aaaa(addr) => write!(f, "AAAA {}", addr),
+            RecordData::Cname(target) => write!(f, "CNAME {}", target),
+            RecordData::Txt(strings) => {
+                f.write_str("TXT")?;
+                for text in strings {
+                    write!(f, " {:?}", text)?;
+                }
+                Ok(())
+            }
+            RecordData::Srv { priority, weight, port, target } => write!(f, "SRV {} {} {} {}", priority, weight, port, target),
+            RecordData::Other(rtype, len) => write!(f, "TYPE{} ({} bytes)", rtype, len),
+        }
+    }
+}
+
+/// Parse a DNS response packet into the address answer for its question,
+/// following CNAMEs within the answer
 pub fn parse_response(packet: &[u8], expected_txid: u16) -> DnsResult {
+    let (qname, records) = match parse_records(packet, expected_txid) {
+        Ok(parsed) => parsed,
+        Err(e) => return e,
+    };
+    let Some(first) = records.first() else {
+        return DnsResult::NotFound;
+    };
+    // Without a question section, start from the first record
+    let qname = if qname.is_empty() { first.name.clone() } else { qname };
+    let (name, chain_ttl) = follow_cnames(&records, &qname);
+
+    let mut addresses = Vec::new();
+    let mut addresses6 = Vec::new();
+    let mut ttl4 = chain_ttl;
+    let mut ttl6 = chain_ttl;
+    for record in records.iter().filter(|r| r.name.eq_ignore_ascii_case(&name)) {
+        match record.data {
+            RecordData::A(addr) => {
+                addresses.push(addr);
+                ttl4 = ttl4.min(record.ttl);
+            }
+            RecordData::Aaaa(addr) => {
+                addresses6.push(addr);
+                ttl6 = ttl6.min(record.ttl);
+            }
+            _ => {}
+        }
+    }
+
+    if !addresses.is_empty() {
+        DnsResult::Resolved(addresses, ttl4)
+    } else if !addresses6.is_empty() {
+        DnsResult::Resolved6(addresses6, ttl6)
+    } else if !name.eq_ignore_ascii_case(&qname) {
+        DnsResult::Alias(name, chain_ttl)
+    } else {
+        DnsResult::NotFound
+    }
+}
+
+/// The end of the CNAME chain starting at `name` among `records`, and the
+/// smallest TTL along it (u32::MAX if `name` is not an alias)
+fn follow_cnames(records: &[DnsRecord], name: &str) -> (String, u32) {
+    let mut name = String::from(name);
+    let mut ttl = u32::MAX;
+    for _ in 0..MAX_CNAME_CHAIN {
+        let alias = records.iter().find_map(|r| match &r.data {
+            RecordData::Cname(target) if r.name.eq_ignore_ascii_case(&name) => Some((target, r.ttl)),
+            _ => None,
+        });
+        let Some((target, alias_ttl)) = alias else {
+            break;
+        };
+        name = target.clone();
+        ttl = ttl.min(alias_ttl);
+    }
+    (name, ttl)
+}
+
+/// Parse a DNS response packet: the name asked about (empty if the
+/// response has no question) and every record in the answer section
+pub fn parse_records(packet: &[u8], expected_txid: u16) -> Result<(String, Vec<DnsRecord>), DnsResult> {
     // Minimum DNS header size
     if packet.len() < 12 {
-        return DnsResult::Error("Packet too short");
+        return Err(DnsResult::Error("Packet too short"));
     }
 
     // Check transaction ID
     let txid = u16::from_be_bytes([packet[0], packet[1]]);
     if txid != expected_txid {
-        return DnsResult::WrongId;
+        return Err(DnsResult::WrongId);
     }
 
     // Check flags
@@ -118,126 +297,155 @@ pub fn parse_response(packet: &[u8], expected_txid: u16) -> DnsResult {
 
     // Verify this is a response
     if flags & DNS_FLAG_QR == 0 {
-        return DnsResult::Error("Not a response");
+        return Err(DnsResult::Error("Not a response"));
     }
 
     // Check response code
     let rcode = flags & DNS_RCODE_MASK;
     if rcode == DNS_RCODE_NXDOMAIN {
-        return DnsResult::NotFound;
+        return Err(DnsResult::NotFound);
     }
     if rcode != 0 {
-        return DnsResult::Error("DNS server error");
+        return Err(DnsResult::Error("DNS server error"));
     }
 
     // Get counts
     let qdcount = u16::from_be_bytes([packet[4], packet[5]]) as usize;
     let ancount = u16::from_be_bytes([packet[6], packet[7]]) as usize;
 
-    if ancount == 0 {
-        return DnsResult::NotFound;
-    }
-
     // Skip the header
     let mut pos = 12;
 
-    // Skip question section
-    for _ in 0..qdcount {
-        // Skip QNAME
-        pos = match skip_name(packet, pos) {
-            Ok(p) => p,
-            Err(e) => return e,
-        };
+    // Question section: keep the first name
+    let mut qname = String::new();
+    for i in 0..qdcount {
+        let (name, next) = read_name(packet, pos)?;
+        if i == 0 {
+            qname = name;
+        }
         // Skip QTYPE and QCLASS (4 bytes)
-        pos += 4;
+        pos = next + 4;
         if pos > packet.len() {
-            return DnsResult::Error("Truncated question");
+            return Err(DnsResult::Error("Truncated question"));
         }
     }
 
     // Parse answer section
-    let mut addresses = Vec::new();
-
+    let mut records = Vec::new();
     for _ in 0..ancount {
         if pos >= packet.len() {
             break;
         }
 
-        // Skip NAME (may be a pointer)
-        pos = match skip_name(packet, pos) {
-            Ok(p) => p,
-            Err(e) => return e,
-        };
+        let (name, next) = read_name(packet, pos)?;
+        pos = next;
 
         // Need at least 10 bytes for TYPE, CLASS, TTL, RDLENGTH
         if pos + 10 > packet.len() {
-            return DnsResult::Error("Truncated answer");
+            return Err(DnsResult::Error("Truncated answer"));
         }
 
         let rtype = u16::from_be_bytes([packet[pos], packet[pos + 1]]);
         let rclass = u16::from_be_bytes([packet[pos + 2], packet[pos + 3]]);
-        // TTL is at pos+4..pos+8 (we skip it)
+        let ttl = u32::from_be_bytes([packet[pos + 4], packet[pos + 5], packet[pos + 6], packet[pos + 7]]);
         let rdlength = u16::from_be_bytes([packet[pos + 8], packet[pos + 9]]) as usize;
         pos += 10;
 
         if pos + rdlength > packet.len() {
-            return DnsResult::Error("Truncated RDATA");
+            return Err(DnsResult::Error("Truncated RDATA"));
         }
 
-        // Check if this is an A record (type 1, class IN)
-        if rtype == DNS_TYPE_A && rclass == DNS_CLASS_IN && rdlength == 4 {
-            let addr = Ipv4Address::new(
-                packet[pos],
-                packet[pos + 1],
-                packet[pos + 2],
-                packet[pos + 3],
-            );
-            addresses.push(addr);
+        if rclass == DNS_CLASS_IN {
+            let data = parse_rdata(packet, pos, rtype, rdlength)?;
+            records.push(DnsRecord { name, ttl, data });
         }
 
         pos += rdlength;
     }
 
-    if addresses.is_empty() {
-        DnsResult::NotFound
-    } else {
-        DnsResult::Resolved(addresses)
-    }
+    Ok((qname, records))
 }
 
-/// Skip a DNS name (handles compression pointers)
-/// Returns the position after the name, or Error
-fn skip_name(packet: &[u8], mut pos: usize) -> Result<usize, DnsResult> {
-    loop {
-        if pos >= packet.len() {
-            return Err(DnsResult::Error("Name extends past packet"));
+/// Decode the RDATA of a record of type `rtype` at `pos`
+fn parse_rdata(packet: &[u8], pos: usize, rtype: u16, rdlength: usize) -> Result<RecordData, DnsResult> {
+    let rdata = &packet[pos..pos + rdlength];
+    Ok(match rtype {
+        DNS_TYPE_A if rdlength == 4 => RecordData::A(Ipv4Address::new(rdata[0], rdata[1], rdata[2], rdata[3])),
+        DNS_TYPE_AAAA if rdlength == 16 => {
+            let mut octets = [0u8; 16];
+            octets.copy_from_slice(rdata);
+            RecordData::Aaaa(Ipv6Address::from(octets))
         }
+        // Names in RDATA may point back into the packet
+        DNS_TYPE_CNAME => RecordData::Cname(read_name(packet, pos)?.0),
+        DNS_TYPE_SRV if rdlength > 6 => RecordData::Srv {
+            priority: u16::from_be_bytes([rdata[0], rdata[1]]),
+            weight: u16::from_be_bytes([rdata[2], rdata[3]]),
+            port: u16::from_be_bytes([rdata[4], rdata[5]]),
+            target: read_name(packet, pos + 6)?.0,
+        },
+        DNS_TYPE_TXT => {
+            // Length-prefixed character-strings
+            let mut strings = Vec::new();
+            let mut rest = rdata;
+            while let Some((&len, tail)) = rest.split_first() {
+                let len = (len as usize).min(tail.len());
+                strings.push(String::from_utf8_lossy(&tail[..len]).into_owned());
+                rest = &tail[len..];
+            }
+            RecordData::Txt(strings)
+        }
+        _ => RecordData::Other(rtype, rdlength),
+    })
+}
 
-        let len = packet[pos];
+/// Read a DNS name (following compression pointers)
+/// Returns the dotted name and the position after it in the record
+fn read_name(packet: &[u8], mut pos: usize) -> Result<(String, usize), DnsResult> {
+    let mut name = String::new();
+    // Where the name ends in the record, once a pointer was taken
+    let mut end = None;
+    let mut pointers = 0;
+    loop {
+        let Some(&len) = packet.get(pos) else {
+            return Err(DnsResult::Error("Name extends past packet"));
+        };
 
         if len == 0 {
             // End of name (null terminator)
-            return Ok(pos + 1);
+            return Ok((name, end.unwrap_or(pos + 1)));
         }
 
         if len & 0xC0 == 0xC0 {
-            // Compression pointer (2 bytes) - just skip it
-            return Ok(pos + 2);
+            // Compression pointer (2 bytes) to the rest of the name
+            let Some(&low) = packet.get(pos + 1) else {
+                return Err(DnsResult::Error("Name extends past packet"));
+            };
+            pointers += 1;
+            if pointers > MAX_NAME_POINTERS {
+                return Err(DnsResult::Error("Name compression loop"));
+            }
+            end.get_or_insert(pos + 2);
+            pos = (((len & 0x3F) as usize) << 8) | low as usize;
+            continue;
         }
 
-        // Regular label: skip length byte + label content
-        pos += 1 + (len as usize);
-
-        // Safety check
-        if pos > packet.len() {
+        // Regular label
+        let Some(label) = packet.get(pos + 1..pos + 1 + len as usize) else {
             return Err(DnsResult::Error("Label extends past packet"));
+        };
+        if !name.is_empty() {
+            name.push('.');
         }
+        name.push_str(&String::from_utf8_lossy(label));
+        pos += 1 + len as usize;
     }
 }
 
 /// High-level DNS resolution function
 ///
-/// This performs a DNS lookup using the provided NetState.
+/// This performs a DNS lookup using the provided NetState, unless
+/// /etc/hosts or the cache already has the answer.
 /// Returns the first resolved IPv4 address or None on failure.
 pub fn resolve(
     net: &mut crate::net::NetState,
@@ -246,10 +454,246 @@ pub fn resolve(
     timeout_ms: i64,
     get_time_ms: fn() -> i64,
 ) -> Option<Ipv4Address> {
+    match cached_lookup(net, hostname, DNS_TYPE_A, dns_server, timeout_ms, get_time_ms)? {
+        IpAddress::Ipv4(addr) => Some(addr),
+        _ => None,
+    }
+}
+
+/// Like `resolve`, but for the AAAA record: the first IPv6 address
+pub fn resolve6(
+    net: &mut crate::net::NetState,
+    hostname: &[u8],
+    dns_server: Ipv4Address,
+    timeout_ms: i64,
+    get_time_ms: fn() -> i64,
+) -> Option<Ipv6Address> {
+    match cached_lookup(net, hostname, DNS_TYPE_AAAA, dns_server, timeout_ms, get_time_ms)? {
+        IpAddress::Ipv6(addr) => Some(addr),
+        _ => None,
+    }
+}
+
+/// Re-read /etc/hosts if it changed since the last call (called
+/// periodically on hart 0)
+pub fn reload_hosts_if_changed() {
+    if HOSTS_WATCH.changed() {
+        load_hosts();
+    }
+}
+
+/// Read /etc/hosts ("address name [aliases...]" lines, '#' comments).
+/// Returns the number of names loaded.
+pub fn load_hosts() -> usize {
+    let mut entries = Vec::new();
+    if let Some(data) = crate::cpu::fs_proxy::fs_read(HOSTS_PATH) {
+        let text = String::from_utf8_lossy(&data);
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or("");
+            let mut fields = line.split_whitespace();
+            let Some(addr) = fields.next().and_then(parse_address) else {
+                continue;
+            };
+            for name in fields {
+                entries.push((name.to_ascii_lowercase(), addr));
+            }
+        }
+    }
+    let count = entries.len();
+    *HOSTS.lock() = entries;
+    count
+}
+
+/// Empty the cache and re-read /etc/hosts.
+/// Returns (cached answers dropped, host names loaded).
+pub fn flush() -> (usize, usize) {
+    let dropped = {
+        let mut cache = CACHE.lock();
+        let count = cache.len();
+        cache.clear();
+        count
+    };
+    (dropped, load_hosts())
+}
+
+/// Empty the cache unless it is locked (out-of-memory path); returns the
+/// answers dropped
+pub fn try_drop_cache() -> usize {
+    let Some(mut cache) = CACHE.try_lock() else {
+        return 0;
+    };
+    let count = cache.len();
+    cache.clear();
+    count
+}
+
+/// An IPv4 or IPv6 address in text form
+fn parse_address(s: &str) -> Option<IpAddress> {
+    if let Some(addr) = crate::net::parse_ipv4(s.as_bytes()) {
+        return Some(IpAddress::Ipv4(addr));
+    }
+    s.parse::<Ipv6Address>().ok().map(IpAddress::Ipv6)
+}
+
+/// Address of `name` in /etc/hosts matching the query type
+fn hosts_lookup(name: &str, qtype: u16) -> Option<IpAddress> {
+    HOSTS.lock().iter().find_map(|(host, addr)| {
+        let family_matches = matches!(
+            (qtype, addr),
+            (DNS_TYPE_A, IpAddress::Ipv4(_)) | (DNS_TYPE_AAAA, IpAddress::Ipv6(_))
+        );
+        (host == name && family_matches).then_some(*addr)
+    })
+}
+
+/// Answer from /etc/hosts or the cache, else query the server and cache its answer
+fn cached_lookup(
+    net: &mut crate::net::NetState,
+    hostname: &[u8],
+    qtype: u16,
+    dns_server: Ipv4Address,
+    timeout_ms: i64,
+    get_time_ms: fn() -> i64,
+) -> Option<IpAddress> {
+    let name = String::from_utf8_lossy(hostname).to_ascii_lowercase();
+    if let Some(addr) = hosts_lookup(&name, qtype) {
+        return Some(addr);
+    }
+
+    let key = (name, qtype);
+    let now = get_time_ms();
+    {
+        let mut cache = CACHE.lock();
+        match cache.get(&key) {
+            Some(entry) if entry.expires_ms > now => return Some(entry.addr),
+            Some(_) => {
+                cache.remove(&key);
+            }
+            None => {}
+        }
+    }
+
+    // Look up aliases the server left unresolved in turn; the answer is
+    // cached under the name asked for, for as long as the whole chain holds
+    let mut target = key.0.clone();
+    let mut chain_ttl = u32::MAX;
+    for _ in 0..MAX_CNAME_CHAIN {
+        let (addr, ttl) = match lookup(net, target.as_bytes(), qtype, dns_server, timeout_ms, get_time_ms)? {
+            DnsResult::Resolved(addrs, ttl) => (IpAddress::Ipv4(*addrs.first()?), ttl),
+            DnsResult::Resolved6(addrs, ttl) => (IpAddress::Ipv6(*addrs.first()?), ttl),
+            DnsResult::Alias(alias, ttl) => {
+                if let Some(addr) = hosts_lookup(&alias, qtype) {
+                    return Some(addr);
+                }
+                target = alias;
+                chain_ttl = chain_ttl.min(ttl);
+                continue;
+            }
+            _ => return None,
+        };
+        cache_insert(key, addr, ttl.min(chain_ttl), now);
+        return Some(addr);
+    }
+    None
+}
+
+/// Remember an answer for `ttl_secs` (capped), making room if the cache is full
+fn cache_insert(key: (String, u16), addr: IpAddress, ttl_secs: u32, now: i64) {
+    let mut cache = CACHE.lock();
+    if cache.len() >= CACHE_MAX_ENTRIES && !cache.contains_key(&key) {
+        cache.retain(|_, entry| entry.expires_ms > now);
+        // Still full: drop the answer closest to expiry
+        if cache.len() >= CACHE_MAX_ENTRIES {
+            let soonest = cache.iter().min_by_key(|(_, entry)| entry.expires_ms).map(|(k, _)| k.clone());
+            if let Some(soonest) = soonest {
+                cache.remove(&soonest);
+            }
+        }
+    }
+    let ttl_ms = ttl_secs.min(CACHE_MAX_TTL_SECS) as i64 * 1000;
+    cache.insert(key, CacheEntry { addr, expires_ms: now + ttl_ms });
+}
+
+/// Send a query of type `qtype` and wait for the matching answer
+fn lookup(
+    net: &mut crate::net::NetState,
+    hostname: &[u8],
+    qtype: u16,
+    dns_server: Ipv4Address,
+    timeout_ms: i64,
+    get_time_ms: fn() -> i64,
+) -> Option<DnsResult> {
     use crate::uart;
 
+    let (txid, packet) = match exchange(net, hostname, qtype, dns_server, timeout_ms, get_time_ms) {
+        Ok(response) => response,
+        Err(e) => {
+            uart::write_line(e);
+            return None;
+        }
+    };
+    match parse_response(&packet, txid) {
+        DnsResult::NotFound => {
+            uart::write_line("DNS: domain not found");
+            None
+        }
+        DnsResult::Error(e) => {
+            uart::write_str("DNS error: ");
+            uart::write_line(e);
+            None
+        }
+        DnsResult::WrongId => None,
+        result => Some(result),
+    }
+}
+
+/// Every record the server answers for `hostname` and `qtype`, including
+/// the CNAMEs leading to them. Aliases the server did not resolve itself
+/// are queried in turn. Skips /etc/hosts and the cache (for `nslookup`).
+/// Fails with `NotFound` if the name does not exist, else `Error`.
+pub fn query(
+    net: &mut crate::net::NetState,
+    hostname: &[u8],
+    qtype: u16,
+    dns_server: Ipv4Address,
+    timeout_ms: i64,
+    get_time_ms: fn() -> i64,
+) -> Result<Vec<DnsRecord>, DnsResult> {
+    let mut records = Vec::new();
+    let mut name = String::from_utf8_lossy(hostname).into_owned();
+    for _ in 0..MAX_CNAME_CHAIN {
+        let (txid, packet) = exchange(net, name.as_bytes(), qtype, dns_server, timeout_ms, get_time_ms).map_err(DnsResult::Error)?;
+        let answers = match parse_records(&packet, txid) {
+            Ok((_, answers)) => answers,
+            // The alias points nowhere; show the chain so far
+            Err(DnsResult::NotFound) if !records.is_empty() => break,
+            Err(e) => return Err(e),
+        };
+        let (target, _) = follow_cnames(&answers, &name);
+        let answered = qtype == DNS_TYPE_CNAME
+            || target.eq_ignore_ascii_case(&name)
+            || answers.iter().any(|r| r.data.rtype() == qtype && r.name.eq_ignore_ascii_case(&target));
+        records.extend(answers);
+        if answered {
+            break;
+        }
+        name = target;
+    }
+    Ok(records)
+}
+
+/// Send a query and wait for the response with its transaction ID
+/// Returns (transaction_id, response_packet)
+fn exchange(
+    net: &mut crate::net::NetState,
+    hostname: &[u8],
+    qtype: u16,
+    dns_server: Ipv4Address,
+    timeout_ms: i64,
+    get_time_ms: fn() -> i64,
+) -> Result<(u16, Vec<u8>), &'static str> {
     // Build query
-    let (txid, query) = build_query(hostname);
+    let (txid, query) = build_query_for(hostname, qtype);
 
     // Send query
     let start_time = get_time_ms();
@@ -257,8 +701,7 @@ pub fn resolve(
         .udp_send(dns_server, crate::net::DNS_PORT, &query, start_time)
         .is_err()
     {
-        uart::write_line("Failed to send DNS query");
-        return None;
+        return Err("Failed to send DNS query");
     }
 
     // Wait for response with timeout
@@ -267,38 +710,21 @@ pub fn resolve(
     loop {
         let now = get_time_ms();
         if now - start_time > timeout_ms {
-            uart::write_line("DNS query timed out");
-            return None;
+            return Err("DNS query timed out");
         }
 
         // Poll network
         net.poll(now);
 
-        // Try to receive response
+        // Try to receive response, ignoring those with the wrong transaction ID
         if let Some((_src_ip, _src_port, len)) = net.udp_recv(&mut buf, now) {
-            match parse_response(&buf[..len], txid) {
-                DnsResult::Resolved(addrs) => {
-                    return addrs.into_iter().next();
-                }
-                DnsResult::NotFound => {
-                    uart::write_line("DNS: domain not found");
-                    return None;
-                }
-                DnsResult::Error(e) => {
-                    uart::write_str("DNS error: ");
-                    uart::write_line(e);
-                    return None;
-                }
-                DnsResult::WrongId => {
-                    // Ignore responses with wrong transaction ID
-                    continue;
-                }
+            if len >= 2 && buf[..2] == txid.to_be_bytes() {
+                return Ok((txid, Vec::from(&buf[..len])));
             }
+            continue;
         }
 
-        // Small delay to avoid busy-waiting
-        for _ in 0..10000 {
-            core::hint::spin_loop();
-        }
+        // Sleep until a frame may have arrived
+        crate::net::wait_for_frame(start_time + timeout_ms);
     }
 }
@@ -7,12 +7,60 @@
 //! - RISC-V 64-bit little-endian ELF
 //! - Position-independent executables (PIE)
 //! - PT_LOAD segments
+//! - `R_RISCV_RELATIVE` relocations from PT_DYNAMIC (static PIE)
+//!
+//! ## Validation
+//!
+//! Every header is checked before anything is copied: the file must be a
+//! RISC-V executable (ET_EXEC or ET_DYN), each PT_LOAD segment must lie
+//! within the file, have a power-of-two alignment its offset and address
+//! agree on, and not overlap another, and the entry point must be in an
+//! executable segment. No segment may be both writable and executable
+//! (W^X); the segments' permissions are kept in `LoadedElf::segments` and
+//! become the permissions of their pages (see "Address Space" below).
+//!
+//! ## Load Address
+//!
+//! Each binary is loaded at a page-aligned base plus a random number of
+//! pages (up to `MAX_SLIDE_PAGES`), so no two runs see the same addresses
+//! and the link-time addresses do not matter. Pointers stored in the data
+//! segments (vtables, string tables, function pointers) are fixed up from
+//! the binary's `R_RISCV_RELATIVE` relocations; any other relocation type
+//! needs a symbol lookup and the binary is refused.
+//!
+//! ## Initial Stack
+//!
+//! `_start` gets the stack the RISC-V psABI (System V) describes, so crt0
+//! code written for it finds its arguments where it expects them:
+//!
+//! ```text
+//! sp ->  argc
+//!        argv[0] .. argv[argc-1], NULL     argv[0] is the command as typed
+//!        envp[0] .. envp[n-1], NULL        "KEY=value" (see `env_get`)
+//!        auxv pairs: AT_PAGESZ, AT_ENTRY, AT_RANDOM, then AT_NULL
+//!        ... padding, 16 random bytes, the strings ...
+//! ```
+//!
+//! a0 is 0 (no `rtld_fini`). The arguments can still be fetched with
+//! `arg_get` as before.
+//!
+//! ## Address Space
+//!
+//! With an Sv39 MMU a binary runs in an address space of its own
+//! (`paging::AddressSpace`): its segments (read-only, read-execute or
+//! read-write as their headers say), its stack and its `mmap` blocks are the
+//! only memory it can touch. The image and the stack are page-aligned so no
+//! kernel data shares their pages. Touching anything else ends the binary
+//! with SIGSEGV (`user_fault`).
 
 use alloc::vec::Vec;
 use alloc::vec;
 use alloc::boxed::Box;
 use core::slice;
 
+use crate::allocator::{enter_subsystem, Subsystem};
+use crate::cpu::signal::{self, Signal};
+
 /// ELF Magic: 0x7f 'E' 'L' 'F'
 const ELF_MAGIC: [u8; 4] = [0x7f, b'E', b'L', b'F'];
 
@@ -25,9 +73,52 @@ const ELFDATA2LSB: u8 = 1;
 /// ELF Machine: RISC-V
 const EM_RISCV: u16 = 0xf3;
 
+// ELF types
+const ET_EXEC: u16 = 2;
+const ET_DYN: u16 = 3;
+
 /// Program header type: Loadable segment
 const PT_LOAD: u32 = 1;
 
+/// Program header type: Dynamic linking information
+const PT_DYNAMIC: u32 = 2;
+
+// Dynamic section tags
+const DT_NULL: u64 = 0;
+const DT_RELA: u64 = 7;
+const DT_RELASZ: u64 = 8;
+const DT_RELAENT: u64 = 9;
+
+// Relocation types
+const R_RISCV_NONE: u32 = 0;
+const R_RISCV_RELATIVE: u32 = 3;
+
+// Segment permission flags
+pub const PF_X: u32 = 1;
+pub const PF_W: u32 = 2;
+pub const PF_R: u32 = 4;
+
+/// Program headers a binary may have
+const MAX_PROGRAM_HEADERS: usize = 64;
+
+/// Largest image (lowest to highest loaded address) a binary may need
+const MAX_IMAGE_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Load bases are page aligned
+const PAGE_SIZE: usize = 4096;
+
+/// The load base is slid by up to this many pages
+const MAX_SLIDE_PAGES: usize = 256;
+
+/// Stack a binary gets, besides its arguments and environment
+const STACK_SIZE: usize = 8192;
+
+// Auxiliary vector entry types
+const AT_NULL: u64 = 0;
+const AT_PAGESZ: u64 = 6;
+const AT_ENTRY: u64 = 9;
+const AT_RANDOM: u64 = 25;
+
 /// Debug flag: set when ELF exits, checked by shell_tick
 pub static ELF_JUST_EXITED: core::sync::atomic::AtomicBool = 
     core::sync::atomic::AtomicBool::new(false);
@@ -67,6 +158,17 @@ struct Elf64ProgramHeader {
     p_align: u64,
 }
 
+/// A loaded segment, relative to the load base
+#[derive(Debug, Clone, Copy)]
+pub struct Segment {
+    /// Offset of its first byte from the load base
+    pub offset: u64,
+    /// Size in memory
+    pub len: u64,
+    /// PF_R | PF_W | PF_X
+    pub flags: u32,
+}
+
 /// Result of loading an ELF binary
 pub struct LoadedElf {
     /// Entry point address (adjusted for load address)
@@ -75,6 +177,8 @@ pub struct LoadedElf {
     pub memory: Box<[u8]>,
     /// Base address where binary was loaded
     pub load_base: u64,
+    /// PT_LOAD segments and their permissions
+    pub segments: Vec<Segment>,
 }
 
 /// ELF loading error
@@ -85,8 +189,22 @@ pub enum ElfError {
     WrongEndian,
     WrongArch,
     TooSmall,
+    /// Not an executable (ET_EXEC or ET_DYN)
+    WrongType,
     InvalidProgramHeader,
     NoLoadableSegments,
+    /// A segment outside the file, misaligned or overlapping another
+    InvalidSegment,
+    /// The segments span more than `MAX_IMAGE_SIZE`
+    ImageTooLarge,
+    /// A segment is both writable and executable
+    WritableExecutable,
+    /// The entry point is not in an executable segment
+    InvalidEntry,
+    /// A relocation points outside the loaded image
+    InvalidRelocation,
+    /// A relocation type other than `R_RISCV_RELATIVE`
+    UnsupportedRelocation(u32),
 }
 
 /// Validate an ELF header
@@ -103,14 +221,76 @@ fn validate_header(header: &Elf64Header) -> Result<(), ElfError> {
     if header.e_machine != EM_RISCV {
         return Err(ElfError::WrongArch);
     }
+    if header.e_type != ET_EXEC && header.e_type != ET_DYN {
+        return Err(ElfError::WrongType);
+    }
     Ok(())
 }
 
+/// Read and check the program headers: returns the PT_LOAD and PT_DYNAMIC
+/// headers, each segment within the file and sane
+fn validate_program_headers(header: &Elf64Header, bytes: &[u8]) -> Result<(Vec<Elf64ProgramHeader>, Vec<Elf64ProgramHeader>), ElfError> {
+    let phoff = header.e_phoff as usize;
+    let phnum = header.e_phnum as usize;
+    if header.e_phentsize as usize != core::mem::size_of::<Elf64ProgramHeader>() || phnum > MAX_PROGRAM_HEADERS {
+        return Err(ElfError::InvalidProgramHeader);
+    }
+    let table_end = phnum
+        .checked_mul(core::mem::size_of::<Elf64ProgramHeader>())
+        .and_then(|size| phoff.checked_add(size))
+        .ok_or(ElfError::InvalidProgramHeader)?;
+    if table_end > bytes.len() {
+        return Err(ElfError::InvalidProgramHeader);
+    }
+
+    let mut loads: Vec<Elf64ProgramHeader> = Vec::new();
+    let mut dynamics = Vec::new();
+    for i in 0..phnum {
+        let ph: Elf64ProgramHeader = unsafe {
+            core::ptr::read_unaligned(bytes.as_ptr().add(phoff + i * core::mem::size_of::<Elf64ProgramHeader>()) as *const Elf64ProgramHeader)
+        };
+        let file_end = ph.p_offset.checked_add(ph.p_filesz).ok_or(ElfError::InvalidSegment)?;
+        match ph.p_type {
+            PT_LOAD => {
+                let align = ph.p_align.max(1);
+                if ph.p_filesz > ph.p_memsz
+                    || file_end > bytes.len() as u64
+                    || ph.p_vaddr.checked_add(ph.p_memsz).is_none()
+                    || !align.is_power_of_two()
+                    || ph.p_vaddr % align != ph.p_offset % align
+                {
+                    return Err(ElfError::InvalidSegment);
+                }
+                if ph.p_flags & PF_W != 0 && ph.p_flags & PF_X != 0 {
+                    return Err(ElfError::WritableExecutable);
+                }
+                let end = ph.p_vaddr + ph.p_memsz;
+                if loads.iter().any(|other| ph.p_vaddr < other.p_vaddr + other.p_memsz && other.p_vaddr < end) {
+                    return Err(ElfError::InvalidSegment);
+                }
+                loads.push(ph);
+            }
+            PT_DYNAMIC => {
+                if file_end > bytes.len() as u64 {
+                    return Err(ElfError::InvalidSegment);
+                }
+                dynamics.push(ph);
+            }
+            _ => {}
+        }
+    }
+    if loads.is_empty() {
+        return Err(ElfError::NoLoadableSegments);
+    }
+    Ok((loads, dynamics))
+}
+
 /// Load an ELF binary into heap-allocated memory
 ///
-/// For PIE binaries, we allocate memory and load at offset 0.
-/// The entry point is adjusted to the allocated address.
+/// The image is placed at a randomly slid, page-aligned base inside the
+/// allocation, relocated, and the entry point adjusted to it.
 pub fn load_elf(bytes: &[u8]) -> Result<LoadedElf, ElfError> {
+    let _subsystem = enter_subsystem(Subsystem::Elf);
     if bytes.len() < core::mem::size_of::<Elf64Header>() {
         return Err(ElfError::TooSmall);
     }
@@ -121,97 +301,195 @@ pub fn load_elf(bytes: &[u8]) -> Result<LoadedElf, ElfError> {
     };
     
     validate_header(&header)?;
+    let (loads, dynamics) = validate_program_headers(&header, bytes)?;
     
     // Find the memory range needed
-    let phoff = header.e_phoff as usize;
-    let phentsize = header.e_phentsize as usize;
-    let phnum = header.e_phnum as usize;
-    
-    let mut min_vaddr: u64 = u64::MAX;
-    let mut max_vaddr: u64 = 0;
-    
-    for i in 0..phnum {
-        let ph_offset = phoff + i * phentsize;
-        if ph_offset + phentsize > bytes.len() {
-            return Err(ElfError::InvalidProgramHeader);
-        }
-        
-        let ph: Elf64ProgramHeader = unsafe {
-            core::ptr::read_unaligned(bytes.as_ptr().add(ph_offset) as *const Elf64ProgramHeader)
-        };
-        
-        if ph.p_type != PT_LOAD {
-            continue;
-        }
-        
-        let vaddr = ph.p_vaddr;
-        let memsz = ph.p_memsz;
-        
-        if vaddr < min_vaddr {
-            min_vaddr = vaddr;
-        }
-        if vaddr + memsz > max_vaddr {
-            max_vaddr = vaddr + memsz;
-        }
+    let min_vaddr = loads.iter().map(|ph| ph.p_vaddr).min().unwrap_or(0);
+    let max_vaddr = loads.iter().map(|ph| ph.p_vaddr + ph.p_memsz).max().unwrap_or(0);
+    if max_vaddr - min_vaddr > MAX_IMAGE_SIZE {
+        return Err(ElfError::ImageTooLarge);
     }
-    
-    if min_vaddr == u64::MAX {
-        return Err(ElfError::NoLoadableSegments);
+    let segments: Vec<Segment> = loads
+        .iter()
+        .map(|ph| Segment { offset: ph.p_vaddr - min_vaddr, len: ph.p_memsz, flags: ph.p_flags })
+        .collect();
+    let entry_offset = header.e_entry.wrapping_sub(min_vaddr);
+    if !segments.iter().any(|seg| seg.flags & PF_X != 0 && entry_offset >= seg.offset && entry_offset < seg.offset + seg.len) {
+        return Err(ElfError::InvalidEntry);
     }
     
-    // Allocate memory for the entire binary
+    // Allocate memory for the entire binary, with room to align and slide it
     let total_size = (max_vaddr - min_vaddr) as usize;
-    let mut memory: Box<[u8]> = vec![0u8; total_size].into_boxed_slice();
-    let load_base = memory.as_ptr() as u64;
+    let slide = (crate::random::next_u64() as usize % MAX_SLIDE_PAGES) * PAGE_SIZE;
+    // Whole pages, so none is shared with kernel data
+    let mut allocation: Box<[u8]> = vec![0u8; total_size.next_multiple_of(PAGE_SIZE) + PAGE_SIZE + slide].into_boxed_slice();
+    let start = (allocation.as_ptr() as usize).next_multiple_of(PAGE_SIZE) - allocation.as_ptr() as usize + slide;
+    let load_base = allocation.as_ptr() as u64 + start as u64;
+    let memory = &mut allocation[start..start + total_size];
     
     // Load segments into allocated memory
-    for i in 0..phnum {
-        let ph_offset = phoff + i * phentsize;
-        let ph: Elf64ProgramHeader = unsafe {
-            core::ptr::read_unaligned(bytes.as_ptr().add(ph_offset) as *const Elf64ProgramHeader)
-        };
-        
-        if ph.p_type != PT_LOAD {
-            continue;
-        }
-        
-        let vaddr = ph.p_vaddr;
+    for ph in &loads {
         let filesz = ph.p_filesz as usize;
         let offset = ph.p_offset as usize;
         
         // Calculate destination in our allocated buffer
-        let dest_offset = (vaddr - min_vaddr) as usize;
+        let dest_offset = (ph.p_vaddr - min_vaddr) as usize;
         
-        // Copy file data
-        if filesz > 0 && offset + filesz <= bytes.len() {
-            memory[dest_offset..dest_offset + filesz]
-                .copy_from_slice(&bytes[offset..offset + filesz]);
-        }
+        // Copy file data (validated to be within the file)
+        memory[dest_offset..dest_offset + filesz]
+            .copy_from_slice(&bytes[offset..offset + filesz]);
         // .bss is already zeroed from vec![0u8; ...]
     }
     
+    // Fix up absolute pointers for the load base
+    for ph in &dynamics {
+        let start = ph.p_offset as usize;
+        let dynamic = &bytes[start..start + ph.p_filesz as usize];
+        apply_relocations(memory, dynamic, min_vaddr, load_base)?;
+    }
+    
     // Calculate entry point adjusted for our load base
-    let entry = load_base + (header.e_entry - min_vaddr);
+    let entry = load_base + entry_offset;
     
     Ok(LoadedElf {
         entry,
-        memory,
+        memory: allocation,
         load_base,
+        segments,
     })
 }
 
+/// Read the little-endian u64 at `offset` of `bytes`
+fn read_u64(bytes: &[u8], offset: usize) -> Option<u64> {
+    let word = bytes.get(offset..offset.checked_add(8)?)?;
+    Some(u64::from_le_bytes(word.try_into().ok()?))
+}
+
+/// Apply the RELA table named by the `dynamic` section to the loaded
+/// `image`, whose first byte was linked at `min_vaddr` and now sits at
+/// `load_base`
+fn apply_relocations(image: &mut [u8], dynamic: &[u8], min_vaddr: u64, load_base: u64) -> Result<(), ElfError> {
+    let mut rela = None;
+    let mut rela_size = 0;
+    let mut rela_ent = 24;
+    for entry in dynamic.chunks_exact(16) {
+        let tag = u64::from_le_bytes(entry[..8].try_into().unwrap());
+        let value = u64::from_le_bytes(entry[8..].try_into().unwrap());
+        match tag {
+            DT_NULL => break,
+            DT_RELA => rela = Some(value),
+            DT_RELASZ => rela_size = value as usize,
+            DT_RELAENT => rela_ent = value as usize,
+            _ => {}
+        }
+    }
+    let Some(rela) = rela else {
+        return Ok(());
+    };
+    if rela_ent < 24 {
+        return Err(ElfError::InvalidRelocation);
+    }
+
+    // The table itself is in a loaded segment, at its link address
+    let table_start = rela.checked_sub(min_vaddr).ok_or(ElfError::InvalidRelocation)? as usize;
+    let table_end = table_start.checked_add(rela_size).ok_or(ElfError::InvalidRelocation)?;
+    if table_end > image.len() {
+        return Err(ElfError::InvalidRelocation);
+    }
+    let mut offset = table_start;
+    while offset + rela_ent <= table_end {
+        let r_offset = read_u64(image, offset).ok_or(ElfError::InvalidRelocation)?;
+        let r_info = read_u64(image, offset + 8).ok_or(ElfError::InvalidRelocation)?;
+        let r_addend = read_u64(image, offset + 16).ok_or(ElfError::InvalidRelocation)?;
+        offset += rela_ent;
+
+        match r_info as u32 {
+            R_RISCV_NONE => {}
+            R_RISCV_RELATIVE => {
+                let target = r_offset.checked_sub(min_vaddr).ok_or(ElfError::InvalidRelocation)? as usize;
+                let slot = image
+                    .get_mut(target..target.checked_add(8).ok_or(ElfError::InvalidRelocation)?)
+                    .ok_or(ElfError::InvalidRelocation)?;
+                let value = load_base.wrapping_add(r_addend).wrapping_sub(min_vaddr);
+                slot.copy_from_slice(&value.to_le_bytes());
+            }
+            other => return Err(ElfError::UnsupportedRelocation(other)),
+        }
+    }
+    Ok(())
+}
+
 /// Check if bytes appear to be an ELF file
 #[inline]
 pub fn is_elf(bytes: &[u8]) -> bool {
     bytes.len() >= 4 && bytes[0..4] == ELF_MAGIC
 }
 
+/// A binary's stack: whole pages inside `memory`
+struct UserStack {
+    memory: Box<[u8]>,
+    /// Address of the lowest page
+    base: u64,
+    /// Size in bytes (whole pages)
+    len: usize,
+}
+
+/// Allocate a binary's stack and lay out its argc, argv, envp and auxv at
+/// the top (see "Initial Stack" above). Returns the stack and the initial
+/// stack pointer, 16-byte aligned and pointing at argc.
+fn initial_stack(entry: u64, program: &str, args: &[&str]) -> (UserStack, u64) {
+    let _subsystem = enter_subsystem(Subsystem::Elf);
+    let env: Vec<alloc::string::String> = crate::syscall::environment()
+        .into_iter()
+        .map(|(key, value)| alloc::format!("{}={}", key, value))
+        .collect();
+    let strings: Vec<&str> = core::iter::once(program)
+        .chain(args.iter().copied())
+        .chain(env.iter().map(|entry| entry.as_str()))
+        .collect();
+
+    // argc, argv + NULL, envp + NULL, 4 auxv pairs
+    let words = 1 + (args.len() + 2) + (env.len() + 1) + 8;
+    let strings_len: usize = strings.iter().map(|s| s.len() + 1).sum();
+    let len = (STACK_SIZE + strings_len + 16 + words * 8 + 16).next_multiple_of(PAGE_SIZE);
+    let mut memory = vec![0u8; len + PAGE_SIZE].into_boxed_slice();
+    let start = (memory.as_ptr() as usize).next_multiple_of(PAGE_SIZE) - memory.as_ptr() as usize;
+    let base = memory.as_ptr() as u64 + start as u64;
+    let stack = &mut memory[start..start + len];
+
+    // Strings (NUL-terminated) and the AT_RANDOM bytes at the very top
+    let mut cursor = stack.len();
+    let mut addresses = Vec::with_capacity(strings.len());
+    for string in &strings {
+        cursor -= string.len() + 1;
+        stack[cursor..cursor + string.len()].copy_from_slice(string.as_bytes());
+        addresses.push(base + cursor as u64);
+    }
+    cursor -= 16;
+    crate::random::fill(&mut stack[cursor..cursor + 16]);
+    let auxv = [AT_PAGESZ, 4096, AT_ENTRY, entry, AT_RANDOM, base + cursor as u64, AT_NULL, 0];
+
+    // The table from the 16-byte aligned sp up
+    let sp = ((((base + cursor as u64) - words as u64 * 8) & !15) - base) as usize;
+    let (argv, envp) = addresses.split_at(args.len() + 1);
+    let table = core::iter::once(argv.len() as u64)
+        .chain(argv.iter().copied())
+        .chain(core::iter::once(0))
+        .chain(envp.iter().copied())
+        .chain(core::iter::once(0))
+        .chain(auxv);
+    for (i, word) in table.enumerate() {
+        stack[sp + i * 8..sp + i * 8 + 8].copy_from_slice(&word.to_le_bytes());
+    }
+    (UserStack { memory, base, len }, base + sp as u64)
+}
+
 /// Execute a loaded ELF binary in S-mode (no user mode switch)
 /// 
 /// This is a simpler execution model that runs the binary as a function call
 /// in supervisor mode. Used by GUI terminal where we need normal return flow.
 /// The binary's ecalls will trap and be handled, but we stay in S-mode.
-pub fn execute_elf_smode(loaded: &LoadedElf, args: &[&str]) -> i32 {
+pub fn execute_elf_smode(loaded: &LoadedElf, program: &str, args: &[&str]) -> i32 {
     use core::arch::asm;
     
     // Convert args to static refs for syscall context
@@ -223,10 +501,7 @@ pub fn execute_elf_smode(loaded: &LoadedElf, args: &[&str]) -> i32 {
     crate::syscall::init_context(static_args);
     
     let entry = loaded.entry;
-    
-    // Allocate a stack for the binary (8KB)
-    let stack: alloc::boxed::Box<[u8]> = alloc::vec![0u8; 8192].into_boxed_slice();
-    let stack_top = stack.as_ptr() as u64 + stack.len() as u64;
+    let (stack, stack_top) = initial_stack(entry, program, args);
     
     // Run the binary as a function call in S-mode
     // Save callee-saved registers, switch stack, call entry, restore
@@ -307,7 +582,7 @@ pub fn execute_elf_smode(loaded: &LoadedElf, args: &[&str]) -> i32 {
     crate::syscall::clear_context();
     
     // Keep memory alive until here
-    drop(stack);
+    drop(stack.memory);
     drop(loaded.memory.as_ref());
     
     exit_code as i32
@@ -333,13 +608,37 @@ pub struct KernelContext {
     pub s11: u64,
     pub exit_code: i32,
     pub exited: bool,
-    /// True if executed from GUI context - restore_kernel_context should return, not jump to hart_loop
+    /// True if executed from GUI context - the GUI command service is
+    /// signalled on exit
     pub gui_mode: bool,
+    /// Process the binary runs in, which receives its signals
+    process: Option<alloc::sync::Arc<crate::cpu::process::Process>>,
+    /// Signal handlers: SIG_DFL, SIG_IGN or a user address
+    handlers: [usize; signal::NSIG],
+    /// Where handlers return to: the binary's code that makes `SYS_SIGRETURN`
+    restorer: usize,
+    /// Trap frame a running handler interrupted, and the stack pointer the
+    /// handler started with
+    interrupted: Option<(usize, [u64; crate::trap::FRAME_WORDS])>,
+    /// The current syscall is `SYS_SIGRETURN`
+    returning: bool,
+    /// The binary polls `should_cancel`, which takes SIGINT for it
+    polls_cancel: bool,
+    /// Page tables the binary runs with (None without an Sv39 MMU)
+    address_space: Option<crate::paging::AddressSpace>,
 }
 
-/// Global kernel context for returning from user mode
-/// SAFETY: Only accessed from the hart running the binary
-static mut KERNEL_CTX: Option<KernelContext> = None;
+/// Kernel context of the binary running on each hart, for returning from
+/// user mode. A binary runs to its exit on one hart (user mode is never
+/// preempted and its syscalls never block), so binaries on different harts
+/// can run at the same time.
+/// SAFETY: each slot is only accessed from its own hart
+static mut KERNEL_CTX: [Option<KernelContext>; crate::cpu::MAX_HARTS] = [const { None }; crate::cpu::MAX_HARTS];
+
+/// This hart's kernel context slot
+fn kernel_ctx() -> &'static mut Option<KernelContext> {
+    unsafe { &mut (*core::ptr::addr_of_mut!(KERNEL_CTX))[crate::get_hart_id()] }
+}
 
 /// Flag set when GUI execution completes - checked by caller
 static GUI_EXECUTION_DONE: core::sync::atomic::AtomicBool = 
@@ -352,42 +651,43 @@ pub fn check_gui_done() -> bool {
 
 /// Get exit code from last execution
 pub fn get_last_exit_code() -> i32 {
-    unsafe {
-        KERNEL_CTX.as_ref().map(|ctx| ctx.exit_code).unwrap_or(0)
-    }
+    kernel_ctx().as_ref().map(|ctx| ctx.exit_code).unwrap_or(0)
 }
 
 /// Called by SYS_EXIT to signal binary termination
 pub fn signal_exit(code: i32) {
-    unsafe {
-        if let Some(ctx) = KERNEL_CTX.as_mut() {
-            ctx.exit_code = code;
-            ctx.exited = true;
-        }
+    if let Some(ctx) = kernel_ctx().as_mut() {
+        ctx.exit_code = code;
+        ctx.exited = true;
     }
 }
 
 /// Check if binary has exited
 pub fn has_exited() -> Option<i32> {
-    unsafe {
-        KERNEL_CTX.as_ref().and_then(|ctx| {
-            if ctx.exited { 
-                Some(ctx.exit_code) 
-            } else { 
-                None 
-            }
-        })
-    }
+    kernel_ctx().as_ref().and_then(|ctx| {
+        if ctx.exited { 
+            Some(ctx.exit_code) 
+        } else { 
+            None 
+        }
+    })
 }
 
 /// Execute a loaded ELF binary
 ///
 /// This function sets up the syscall context and uses `sret` to switch to U-mode
 /// for proper ecall handling. The binary's ecalls will trap to S-mode.
-/// 
-/// caller_ra and caller_sp are the return frame from the caller (run_script_bytes),
-/// captured BEFORE calling this function to avoid Rust prologue clobbering them.
-pub fn execute_elf(loaded: &LoadedElf, args: &[&str], caller_ra: u64, caller_sp: u64) -> i32 {
+///
+/// The kernel registers are saved right before the `sret`; when the binary
+/// exits, `restore_kernel_context` loads them back and execution continues
+/// after the `sret` as if it had returned, with the exit code.
+pub fn execute_elf(loaded: &LoadedElf, program: &str, args: &[&str]) -> i32 {
+    run_binary(loaded, program, args, crate::scripting::is_gui_context())
+}
+
+/// Run a binary to its exit; `gui_mode` reports the exit to the GUI
+/// command service
+fn run_binary(loaded: &LoadedElf, program: &str, args: &[&str], gui_mode: bool) -> i32 {
     use core::arch::asm;
     
     // Convert args to static refs
@@ -397,40 +697,62 @@ pub fn execute_elf(loaded: &LoadedElf, args: &[&str], caller_ra: u64, caller_sp:
     
     // Initialize syscall context
     crate::syscall::init_context(static_args);
-
     
     // Initialize kernel context for return
-    // Check if we're in GUI mode - execution should return normally
-    let in_gui_mode = crate::scripting::is_gui_context();
-    unsafe {
-        KERNEL_CTX = Some(KernelContext {
-            ra: 0, sp: 0,
-            s0: 0, s1: 0, s2: 0, s3: 0, s4: 0, s5: 0,
-            s6: 0, s7: 0, s8: 0, s9: 0, s10: 0, s11: 0,
-            exit_code: 0,
-            exited: false,
-            gui_mode: in_gui_mode,
-        });
+    *kernel_ctx() = Some(KernelContext {
+        ra: 0, sp: 0,
+        s0: 0, s1: 0, s2: 0, s3: 0, s4: 0, s5: 0,
+        s6: 0, s7: 0, s8: 0, s9: 0, s10: 0, s11: 0,
+        exit_code: 0,
+        exited: false,
+        gui_mode,
+        process: None,
+        handlers: [signal::SIG_DFL; signal::NSIG],
+        restorer: 0,
+        interrupted: None,
+        returning: false,
+        polls_cancel: false,
+        address_space: None,
+    });
+    let process = crate::cpu::process::PROCESS_TABLE.get(crate::cpu::sched::current_pid());
+    if let Some(process) = process.as_ref() {
+        process.set_runs_binary(true);
     }
+    kernel_ctx().as_mut().unwrap().process = process.clone();
     
     let entry = loaded.entry;
+    let (stack, stack_top) = initial_stack(entry, program, args);
     
-    // Allocate a stack for the binary (8KB)  
-    let stack: alloc::boxed::Box<[u8]> = alloc::vec![0u8; 8192].into_boxed_slice();
-    let stack_top = stack.as_ptr() as u64 + stack.len() as u64;
+    // Its own address space, entered right before the sret
+    if crate::paging::enabled() {
+        match user_address_space(loaded, &stack) {
+            Ok(space) => kernel_ctx().as_mut().unwrap().address_space = Some(space),
+            Err(e) => {
+                *kernel_ctx() = None;
+                if let Some(process) = process {
+                    process.set_runs_binary(false);
+                }
+                crate::syscall::clear_context();
+                crate::services::klogd::klog_warning("elf", e);
+                return 126;
+            }
+        }
+    }
     
     // Get pointer to kernel context
-    let ctx_ptr = unsafe { KERNEL_CTX.as_mut().unwrap() as *mut KernelContext };
+    let ctx_ptr = kernel_ctx().as_mut().unwrap() as *mut KernelContext;
+    if let Some(space) = kernel_ctx().as_ref().and_then(|ctx| ctx.address_space.as_ref()) {
+        space.activate();
+    }
     
-    // Save kernel context using the caller's ra/sp passed as parameters
-    // (captured in run_script_bytes before calling this function)
+    let exit_code: i64;
     unsafe {
         asm!(
-            // Save caller's ra (return address to run_script_bytes' caller)
-            "sd {caller_ra}, 0({ctx})",
-            // Save caller's sp  
-            "sd {caller_sp}, 8({ctx})",
-            // Save callee-saved registers (these are still valid)
+            // Save where to resume (label 1 below) and the kernel stack
+            "la t0, 1f",
+            "sd t0, 0({ctx})",
+            "sd sp, 8({ctx})",
+            // Save callee-saved registers
             "sd s0, 16({ctx})",
             "sd s1, 24({ctx})",
             "sd s2, 32({ctx})",
@@ -443,6 +765,8 @@ pub fn execute_elf(loaded: &LoadedElf, args: &[&str], caller_ra: u64, caller_sp:
             "sd s9, 88({ctx})",
             "sd s10, 96({ctx})",
             "sd s11, 104({ctx})",
+            // Traps from U-mode continue on this stack
+            "csrw sscratch, sp",
             
             // Set sepc to entry point
             "csrw sepc, {entry}",
@@ -460,111 +784,415 @@ pub fn execute_elf(loaded: &LoadedElf, args: &[&str], caller_ra: u64, caller_sp:
             // sret to user mode
             "sret",
             
+            // restore_kernel_context lands here with the kernel registers
+            // back and the exit code in a0
+            "1:",
+            
             ctx = in(reg) ctx_ptr,
-            caller_ra = in(reg) caller_ra,
-            caller_sp = in(reg) caller_sp,
             entry = in(reg) entry,
             user_sp = in(reg) stack_top,
-            options(noreturn)
+            // No rtld_fini for the binary to register
+            inout("a0") 0u64 => exit_code,
+            // Clobbers - all caller-saved registers
+            out("ra") _,
+            out("t0") _,
+            out("t1") _,
+            out("t2") _,
+            out("t3") _,
+            out("t4") _,
+            out("t5") _,
+            out("t6") _,
+            out("a1") _,
+            out("a2") _,
+            out("a3") _,
+            out("a4") _,
+            out("a5") _,
+            out("a6") _,
+            out("a7") _,
         );
     }
+    
+    // Back from the trap handler, which runs with interrupts off
+    crate::paging::deactivate();
+    *kernel_ctx() = None;
+    if let Some(process) = process {
+        process.set_runs_binary(false);
+    }
+    crate::syscall::clear_context();
+    crate::trap::enable_interrupts();
+    
+    // Keep memory alive until here
+    drop(stack.memory);
+    drop(loaded.memory.as_ref());
+    
+    exit_code as i32
 }
 
 /// Restore kernel context and return from user mode
-/// Called by trap handler when SYS_EXIT is detected
+/// Called by trap handler when SYS_EXIT is detected: continues in
+/// `execute_elf` after its `sret`, abandoning the trap frame below the
+/// kernel stack it left
 #[inline(never)]
 pub fn restore_kernel_context() -> ! {
     use core::arch::asm;
     
-    // Check if we're in GUI mode BEFORE clearing context
-    let gui_mode = unsafe {
-        KERNEL_CTX.as_ref().map(|ctx| ctx.gui_mode).unwrap_or(false)
-    };
+    let ctx = kernel_ctx().as_mut().expect("no binary running on this hart");
     
-    if gui_mode {
-        // GUI MODE: Signal completion to GUI subsystem and return to hart_loop
-        // We cannot jump back to the caller because we're in trap context
-        
-        // Get exit code
-        let exit_code = unsafe {
-            KERNEL_CTX.as_ref().map(|ctx| ctx.exit_code).unwrap_or(0)
-        };
-        
-        // Signal completion to GUI command service
-        crate::services::gui_cmd::signal_completion(exit_code);
-        
-        // Clear syscall context
-        crate::syscall::clear_context();
-        
-        // Clear the kernel context
-        unsafe {
-            KERNEL_CTX = None;
+    if ctx.gui_mode {
+        // GUI MODE: Signal completion to GUI command service
+        crate::scripting::set_last_status(ctx.exit_code);
+        crate::services::gui_cmd::signal_completion(ctx.exit_code);
+        GUI_EXECUTION_DONE.store(true, core::sync::atomic::Ordering::SeqCst);
+    } else {
+        // SHELL MODE: the caller reports the exit code
+        ELF_JUST_EXITED.store(true, core::sync::atomic::Ordering::Release);
+    }
+    
+    let exit_code = ctx.exit_code as i64;
+    let ctx_ptr = ctx as *const KernelContext;
+    
+    // Fixed registers: the loads below overwrite every callee-saved one
+    unsafe {
+        asm!(
+            "ld ra, 0(t0)",
+            "ld sp, 8(t0)",
+            "ld s0, 16(t0)",
+            "ld s1, 24(t0)",
+            "ld s2, 32(t0)",
+            "ld s3, 40(t0)",
+            "ld s4, 48(t0)",
+            "ld s5, 56(t0)",
+            "ld s6, 64(t0)",
+            "ld s7, 72(t0)",
+            "ld s8, 80(t0)",
+            "ld s9, 88(t0)",
+            "ld s10, 96(t0)",
+            "ld s11, 104(t0)",
+            "jr ra",
+            in("t0") ctx_ptr,
+            in("a0") exit_code,
+            options(noreturn)
+        );
+    }
+}
+
+/// Page tables for `loaded` running on `stack`: its segments with their
+/// permissions (pages two segments share get both), the stack and the
+/// kernel log page (read-only)
+fn user_address_space(loaded: &LoadedElf, stack: &UserStack) -> Result<crate::paging::AddressSpace, &'static str> {
+    use crate::paging::{PTE_R, PTE_W, PTE_X};
+
+    let mut space = crate::paging::AddressSpace::new().ok_or("Out of memory for page tables")?;
+    for segment in &loaded.segments {
+        let mut flags = 0;
+        if segment.flags & PF_R != 0 {
+            flags |= PTE_R;
         }
-        
-        // Get current hart ID
-        let hart_id = crate::get_hart_id();
-        
-        // CRITICAL: Enable interrupts before entering hart_loop
-        unsafe {
-            asm!(
-                "csrsi sstatus, 0x2",  // Set SIE (bit 1) to enable interrupts
-                options(nomem, nostack)
-            );
+        if segment.flags & PF_W != 0 {
+            flags |= PTE_R | PTE_W;
         }
-        
-        // Find and re-queue the gpuid process so it can pick up the result
-        // Also re-queue gui_cmd for handling the next command
-        for process in crate::cpu::process::PROCESS_TABLE.list() {
-            if process.name == "gpuid" || process.name == "gui_cmd" {
-                process.mark_ready();
-                crate::cpu::sched::requeue(process, hart_id);
-            }
+        if segment.flags & PF_X != 0 {
+            flags |= PTE_X;
         }
-        
-        // Re-enter the main hart loop - this never returns
-        crate::cpu::hart_loop(hart_id);
-    } else {
-        // SHELL MODE: Original behavior - jump to hart_loop
-        // Get exit code before clearing context  
-        let _exit_code = unsafe {
-            KERNEL_CTX.as_ref().map(|ctx| ctx.exit_code).unwrap_or(-1)
-        };
-        
-        // Clear the kernel context
-        unsafe {
-            KERNEL_CTX = None;
+        space.map_user((loaded.load_base + segment.offset) as usize, segment.len as usize, flags)?;
+    }
+    space.map_user(stack.base as usize, stack.len, PTE_R | PTE_W)?;
+    space.map_user(crate::klog_page::address(), PAGE_SIZE, PTE_R)?;
+    Ok(space)
+}
+
+/// Let the binary running on this hart access `[start, start + len)`
+/// (page-aligned, e.g. an `mmap` block); `writable` adds write access and
+/// `executable` execute access. Does nothing without paging.
+pub fn map_user(start: usize, len: usize, writable: bool, executable: bool) -> Result<(), &'static str> {
+    use crate::paging::{PTE_R, PTE_W, PTE_X};
+
+    let Some(space) = kernel_ctx().as_mut().and_then(|ctx| ctx.address_space.as_mut()) else {
+        return Ok(());
+    };
+    let flags = PTE_R | if writable { PTE_W } else { 0 } | if executable { PTE_X } else { 0 };
+    space.map_user(start, len, flags)
+}
+
+/// Take `[start, start + len)` back from the binary running on this hart
+pub fn unmap_user(start: usize, len: usize) {
+    if let Some(space) = kernel_ctx().as_mut().and_then(|ctx| ctx.address_space.as_mut()) {
+        space.unmap_user(start, len);
+    }
+}
+
+/// Whether the binary running on this hart may read (and, if `write`,
+/// write) `[start, start + len)`; syscalls check the buffers they are
+/// given. Always true without paging or outside a binary.
+pub fn user_accessible(start: usize, len: usize, write: bool) -> bool {
+    match kernel_ctx().as_ref().and_then(|ctx| ctx.address_space.as_ref()) {
+        Some(space) => space.user_accessible(start, len, write),
+        None => true,
+    }
+}
+
+/// The binary running on this hart faulted (exception `cause` at `sepc`,
+/// address `stval`): end it with the matching signal
+pub fn user_fault(cause: usize, sepc: usize, stval: usize) -> ! {
+    use crate::trap::cause::*;
+
+    let sig = match cause {
+        ILLEGAL_INSTRUCTION => signal::SIGILL,
+        INSTRUCTION_ADDRESS_MISALIGNED | LOAD_ADDRESS_MISALIGNED | STORE_ADDRESS_MISALIGNED => signal::SIGBUS,
+        _ => signal::SIGSEGV,
+    };
+    crate::services::klogd::klog_warning(
+        "elf",
+        &alloc::format!("{} (cause {}) at pc {:#x}, address {:#x}", signal::name(sig), cause, sepc, stval),
+    );
+    signal_exit(signal::exit_code(sig));
+    restore_kernel_context();
+}
+
+/// Register `handler` (SIG_DFL, SIG_IGN or a user address) for `sig` in the
+/// binary running on this hart; handlers return to `restorer`. Returns the
+/// previous handler.
+pub fn set_signal_handler(sig: Signal, handler: usize, restorer: usize) -> Result<usize, &'static str> {
+    if !signal::is_valid(sig) || sig == signal::SIGKILL || sig == signal::SIGSTOP {
+        return Err("Invalid signal");
+    }
+    let ctx = kernel_ctx().as_mut().ok_or("No binary running")?;
+    if handler > signal::SIG_IGN {
+        if restorer == 0 {
+            return Err("No restorer");
         }
-        
-        // Set debug flag - shell_tick will check this
-        ELF_JUST_EXITED.store(true, core::sync::atomic::Ordering::Release);
-        
-        // Print a new shell prompt
-        crate::utils::print_prompt();
-        
-        // Get current hart ID
-        let hart_id = crate::get_hart_id();
-        
-        // CRITICAL: Enable interrupts before entering hart_loop
-        unsafe {
-            asm!(
-                "csrsi sstatus, 0x2",  // Set SIE (bit 1) to enable interrupts
-                options(nomem, nostack)
-            );
+        ctx.restorer = restorer;
+    }
+    Ok(core::mem::replace(&mut ctx.handlers[sig as usize], handler))
+}
+
+/// Pending signals the binary would act on now
+fn deliverable(ctx: &KernelContext) -> u32 {
+    let Some(process) = ctx.process.as_ref() else {
+        return 0;
+    };
+    let mut pending = process.pending_signals();
+    for sig in 1..signal::NSIG {
+        if ctx.handlers[sig] == signal::SIG_IGN {
+            pending &= !signal::bit(sig as Signal);
         }
-        
-        // CRITICAL: Re-queue the shell process
-        crate::services::shelld::clear_buffer();
-        
-        for process in crate::cpu::process::PROCESS_TABLE.list() {
-            if process.name == "shelld" {
-                process.mark_ready();
-                crate::cpu::sched::requeue(process, hart_id);
-                break;
-            }
+    }
+    // Left for should_cancel
+    if ctx.polls_cancel && ctx.handlers[signal::SIGINT as usize] == signal::SIG_DFL {
+        pending &= !signal::bit(signal::SIGINT);
+    }
+    // Handlers do not nest; only SIGKILL cuts one short
+    if ctx.interrupted.is_some() {
+        pending &= signal::bit(signal::SIGKILL);
+    }
+    pending
+}
+
+/// Whether the binary running on this hart has a signal to act on; long
+/// syscalls check it to return early
+pub fn signal_pending() -> bool {
+    kernel_ctx().as_ref().is_some_and(|ctx| deliverable(ctx) != 0)
+}
+
+/// Called by `should_cancel`: from now on the binary takes SIGINT as a
+/// cancel request. Returns whether one was pending.
+pub fn take_interrupt() -> bool {
+    let Some(ctx) = kernel_ctx().as_mut() else {
+        return false;
+    };
+    ctx.polls_cancel = true;
+    if ctx.handlers[signal::SIGINT as usize] != signal::SIG_DFL {
+        return false;
+    }
+    ctx.process.as_ref().is_some_and(|process| process.take_signals(signal::bit(signal::SIGINT)) != 0)
+}
+
+/// Ctrl+C read from the console by the binary running on this hart: raise
+/// SIGINT, unless the binary ignores it and wants the byte
+pub fn console_interrupt() -> bool {
+    let Some(ctx) = kernel_ctx().as_ref() else {
+        return false;
+    };
+    let Some(process) = ctx.process.as_ref() else {
+        return false;
+    };
+    if ctx.handlers[signal::SIGINT as usize] == signal::SIG_IGN {
+        return false;
+    }
+    process.raise(signal::SIGINT);
+    true
+}
+
+/// Ctrl+Z read from the console by the binary running on this hart: stop
+/// its job with SIGTSTP if it is the foreground job. A binary the shell runs
+/// itself cannot stop without stopping the shell, so it gets the byte.
+pub fn console_suspend() -> bool {
+    let Some(ctx) = kernel_ctx().as_ref() else {
+        return false;
+    };
+    let Some(process) = ctx.process.as_ref() else {
+        return false;
+    };
+    let group = signal::foreground();
+    if group == 0 || process.pgid() != group || ctx.handlers[signal::SIGTSTP as usize] == signal::SIG_IGN {
+        return false;
+    }
+    signal::send_group(group, signal::SIGTSTP).is_ok()
+}
+
+/// Hold a stopped binary until SIGCONT (or SIGKILL) arrives. Its hart waits
+/// with it in wfi, as it does while the binary sleeps; the trap it stopped
+/// in cannot switch to other work.
+fn wait_while_stopped(process: &crate::cpu::process::Process) {
+    process.set_stopped(true);
+    let resume = signal::bit(signal::SIGCONT) | signal::bit(signal::SIGKILL);
+    while process.stopped() && process.pending_signals() & resume == 0 {
+        unsafe { core::arch::asm!("wfi", options(nomem, nostack)) };
+    }
+    process.set_stopped(false);
+}
+
+/// Act on the pending signals of the binary running on this hart before a
+/// trap returns to it: end it (does not return then), or make `frame` enter
+/// its handler
+pub fn deliver_signals(frame: *mut u64) {
+    use crate::trap::{FRAME_A0, FRAME_RA, FRAME_SEPC, FRAME_SP, FRAME_WORDS};
+
+    let Some(ctx) = kernel_ctx().as_mut() else {
+        return;
+    };
+    let Some(process) = ctx.process.clone() else {
+        return;
+    };
+    let ignored = (1..signal::NSIG)
+        .filter(|&sig| ctx.handlers[sig] == signal::SIG_IGN)
+        .fold(0, |mask, sig| mask | signal::bit(sig as Signal));
+    process.take_signals(ignored);
+
+    let pending = deliverable(ctx);
+    if pending == 0 {
+        return;
+    }
+    let sig = if pending & signal::bit(signal::SIGKILL) != 0 {
+        signal::SIGKILL
+    } else {
+        pending.trailing_zeros()
+    };
+    process.take_signals(signal::bit(sig));
+
+    let handler = ctx.handlers[sig as usize];
+    if handler == signal::SIG_DFL {
+        if signal::is_stop(sig) {
+            wait_while_stopped(&process);
+            // SIGKILL, or a SIGCONT handler, may be waiting
+            return deliver_signals(frame);
         }
-        
-        // Re-enter the main hart loop - this never returns
-        crate::cpu::hart_loop(hart_id);
+        if sig == signal::SIGCONT {
+            return;
+        }
+        signal_exit(signal::exit_code(sig));
+        restore_kernel_context();
+    }
+
+    // The handler runs on the binary's stack from where it was interrupted;
+    // SYS_SIGRETURN puts this frame back
+    let mut saved = [0u64; FRAME_WORDS];
+    unsafe {
+        core::ptr::copy_nonoverlapping(frame, saved.as_mut_ptr(), FRAME_WORDS);
+        *frame.add(FRAME_RA) = ctx.restorer as u64;
+        *frame.add(FRAME_A0) = sig as u64;
+        *frame.add(FRAME_SEPC) = handler as u64;
     }
+    ctx.interrupted = Some((saved[FRAME_SP] as usize, saved));
+}
+
+/// Called by SYS_SIGRETURN; false if no handler is running
+pub fn signal_return() -> bool {
+    let Some(ctx) = kernel_ctx().as_mut() else {
+        return false;
+    };
+    ctx.returning = ctx.interrupted.is_some();
+    ctx.returning
 }
 
+/// After a syscall: if it was SYS_SIGRETURN, put the registers the handler
+/// interrupted back into `frame`. Returns whether it did.
+pub fn finish_sigreturn(frame: *mut u64) -> bool {
+    let Some(ctx) = kernel_ctx().as_mut() else {
+        return false;
+    };
+    if !core::mem::take(&mut ctx.returning) {
+        return false;
+    }
+    let Some((at, saved)) = ctx.interrupted.take() else {
+        return false;
+    };
+    // The restorer must trap with the stack the handler got, or the
+    // binary's registers are lost
+    if at != unsafe { *frame.add(crate::trap::FRAME_SP) } as usize {
+        signal_exit(signal::exit_code(signal::SIGKILL));
+        restore_kernel_context();
+    }
+    unsafe { core::ptr::copy_nonoverlapping(saved.as_ptr(), frame, crate::trap::FRAME_WORDS) };
+    true
+}
+
+/// Binaries started with `spawn`, waiting for their process to first run
+static SPAWNED: crate::Spinlock<alloc::collections::BTreeMap<crate::cpu::process::Pid, SpawnedProgram>> =
+    crate::Spinlock::new(alloc::collections::BTreeMap::new());
+
+/// A binary and the arguments it was spawned with
+struct SpawnedProgram {
+    elf: LoadedElf,
+    /// Path it was started as (its argv[0])
+    path: alloc::string::String,
+    args: Vec<alloc::string::String>,
+}
+
+/// Start the binary `path` (resolved like a shell command) in a new process
+/// that is a child of the calling one. Returns its PID; the parent collects
+/// the exit code with `sched::wait_child`.
+///
+/// The child goes to another hart when there is one: a parent waiting for
+/// it inside a syscall keeps its own hart busy.
+pub fn spawn(path: &str, args: &str) -> Result<crate::cpu::process::Pid, &'static str> {
+    use crate::cpu::process::{CpuMask, Priority};
+    use crate::cpu::sched::SCHEDULER;
+
+    let name = path.rsplit('/').next().unwrap_or(path);
+    let (full_path, bytes) = crate::scripting::find_script_path(path).ok_or("Not found")?;
+    // A `#!` script runs as its interpreter
+    let (path, bytes, args) = crate::scripting::resolve_interpreter(path, full_path, bytes, args)?;
+    if !is_elf(&bytes) {
+        return Err("Not an ELF binary");
+    }
+    let elf = load_elf(&bytes).map_err(|_| "Invalid ELF binary")?;
+    drop(bytes);
+
+    let others = CpuMask(SCHEDULER.online_mask().0 & !(1u128 << crate::get_hart_id()));
+    let affinity = if others.is_empty() { CpuMask::ALL } else { others };
+
+    // Hold the table until the program is in it, so the new process cannot
+    // look for it first
+    let mut spawned = SPAWNED.lock();
+    let pid = SCHEDULER.spawn_with_affinity(name, run_spawned, Priority::Normal, affinity);
+    spawned.insert(pid, SpawnedProgram {
+        elf,
+        path,
+        args: args.split_whitespace().map(alloc::string::String::from).collect(),
+    });
+    Ok(pid)
+}
+
+/// Entry point of spawned processes: run the binary once and exit with its
+/// code
+fn run_spawned() {
+    let pid = crate::cpu::sched::current_pid();
+    let Some(program) = SPAWNED.lock().remove(&pid) else {
+        return;
+    };
+    let args: Vec<&str> = program.args.iter().map(|arg| arg.as_str()).collect();
+    // Not a shell command, even while one runs in the GUI
+    let exit_code = run_binary(&program.elf, &program.path, &args, false);
+    crate::cpu::sched::SCHEDULER.exit(pid, exit_code as usize);
+}
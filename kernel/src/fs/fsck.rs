@@ -0,0 +1,350 @@
+//! SFS Consistency Checker
+//!
+//! Works on the raw block device (no buffer cache, no FS_STATE), so it can
+//! run on an image that refuses to mount. Callers must make sure the
+//! filesystem is not mounted while repairing.
+//!
+//! Checks:
+//! - Superblock magic, sector count and format revision
+//! - Directory entry names
+//! - Data sector chains (range, loops, cross-links, length vs size)
+//! - Allocation bitmap (orphaned sectors, in-use sectors not marked)
+//!
+//! Repairs are conservative: broken chains are truncated to the part that
+//! can still be read, and entries with unreadable names are cleared.
+//! `Repair::Bitmap` (used at boot) only rewrites the bitmap, and only
+//! reclaims orphaned sectors when nothing else is wrong. Images in a newer
+//! format revision than this kernel's are only checked, never repaired.
+//!
+//! The same checks are available on images from the host: `mkfs fsck`.
+
+use alloc::collections::BTreeSet;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::lock::state::fs::{
+    Format, COMPRESSED_FLAG, DIR_ENTRY_SIZE, ENTRIES_PER_SECTOR, MAGIC, SEC_DATA_START, SEC_DIR_COUNT,
+    SEC_DIR_START, SEC_MAP_COUNT, SEC_MAP_START, SEC_SUPER, SYMLINK_FLAG,
+};
+use crate::platform::d1_mmc::D1Mmc as BlockDev;
+
+/// Payload bytes per data sector (after the 4-byte next pointer)
+const BLOCK_DATA: usize = 508;
+
+/// Sectors tracked by one bitmap sector
+const SECTORS_PER_MAP: u64 = 512 * 8;
+
+/// What `check` may write to disk
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Repair {
+    /// Report only
+    None,
+    /// Fix the allocation bitmap, nothing else
+    Bitmap,
+    /// Fix everything that can be fixed
+    All,
+}
+
+/// Outcome of a check
+pub struct FsckReport {
+    /// Directory entries checked
+    pub files: usize,
+    /// Data sectors reachable from directory entries
+    pub sectors: usize,
+    /// Sectors marked in the bitmap but not reachable from any entry
+    pub orphans: usize,
+    /// One line per problem found
+    pub problems: Vec<String>,
+    /// Number of fixes written to disk
+    pub repaired: usize,
+}
+
+impl FsckReport {
+    pub fn is_clean(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// End of an intact chain that still points past the entry's size
+struct Tail {
+    name: String,
+    last: u32,
+    next: u32,
+}
+
+/// Check the filesystem on `dev`, fixing what `repair` allows
+pub fn check(dev: &mut BlockDev, mut repair: Repair) -> Result<FsckReport, &'static str> {
+    let mut report = FsckReport {
+        files: 0,
+        sectors: 0,
+        orphans: 0,
+        problems: Vec::new(),
+        repaired: 0,
+    };
+
+    let mut superblock = [0u8; 512];
+    dev.read_sector(SEC_SUPER, &mut superblock)?;
+    let magic = u32::from_le_bytes(superblock[0..4].try_into().unwrap());
+    let recorded = u32::from_le_bytes(superblock[4..8].try_into().unwrap()) as u64;
+    let capacity = dev.capacity();
+
+    let mut superblock_bad = false;
+    if magic != MAGIC {
+        report.problems.push(format!("superblock: bad magic {:#010x}", magic));
+        superblock_bad = true;
+    }
+    let format = Format::parse(&superblock);
+    if magic == MAGIC && (!format.is_supported() || format.is_newer()) {
+        // Repairs could undo what the newer revision relies on
+        report.problems.push(format!("superblock: format {} is newer than this kernel, not repairing", format));
+        repair = Repair::None;
+    }
+    let total = if recorded < SEC_DATA_START || recorded > capacity {
+        report.problems.push(format!(
+            "superblock: sector count {} out of range (device has {})",
+            recorded, capacity
+        ));
+        superblock_bad = true;
+        capacity
+    } else {
+        recorded
+    };
+
+    let mut used = BTreeSet::new();
+    let full = repair == Repair::All;
+    check_directory(dev, total, full, &mut used, &mut report)?;
+
+    if superblock_bad && full {
+        // Without a single sane directory entry this probably isn't SFS at all
+        if magic != MAGIC && report.files == 0 {
+            report
+                .problems
+                .push(String::from("superblock: no directory entries found, not rewriting"));
+        } else {
+            superblock[0..4].copy_from_slice(&MAGIC.to_le_bytes());
+            superblock[4..8].copy_from_slice(&(total as u32).to_le_bytes());
+            dev.write_sector(SEC_SUPER, &superblock)?;
+            report.repaired += 1;
+            superblock_bad = false;
+        }
+    }
+
+    // Never write a bitmap onto something that may not be SFS
+    let fix_bitmap = repair != Repair::None && !superblock_bad;
+    // At boot, orphans are only reclaimed from an otherwise clean filesystem
+    let reclaim = full || report.problems.is_empty();
+    check_bitmap(dev, total, &used, fix_bitmap, reclaim, &mut report)?;
+
+    Ok(report)
+}
+
+/// Validate every directory entry and walk its sector chain, collecting
+/// every sector in use into `used`
+fn check_directory(
+    dev: &mut BlockDev,
+    total: u64,
+    repair: bool,
+    used: &mut BTreeSet<u32>,
+    report: &mut FsckReport,
+) -> Result<(), &'static str> {
+    let mut tails: Vec<Tail> = Vec::new();
+
+    for i in 0..SEC_DIR_COUNT {
+        let sector = SEC_DIR_START + i;
+        let mut buf = [0u8; 512];
+        dev.read_sector(sector, &mut buf)?;
+        let mut dirty = false;
+
+        for j in 0..ENTRIES_PER_SECTOR {
+            let offset = j * DIR_ENTRY_SIZE;
+            if buf[offset] == 0 {
+                continue;
+            }
+            let entry = &mut buf[offset..offset + DIR_ENTRY_SIZE];
+
+            let name_len = entry[..64].iter().position(|&c| c == 0).unwrap_or(64);
+            let name = match core::str::from_utf8(&entry[..name_len]) {
+                // Root entries written by mkfs have no leading '/'
+                Ok(name) if !name.chars().any(char::is_control) => String::from(name),
+                _ => {
+                    report
+                        .problems
+                        .push(format!("directory sector {} slot {}: invalid name", sector, j));
+                    if repair {
+                        entry.fill(0);
+                        dirty = true;
+                        report.repaired += 1;
+                    }
+                    continue;
+                }
+            };
+            report.files += 1;
+
+            let raw_size = u32::from_le_bytes(entry[64..68].try_into().unwrap());
+            let size = (raw_size & !(SYMLINK_FLAG | COMPRESSED_FLAG)) as usize;
+            let head = u32::from_le_bytes(entry[68..72].try_into().unwrap());
+
+            // Walk the chain until it covers `size` bytes
+            let needed = (size + BLOCK_DATA - 1) / BLOCK_DATA;
+            let mut next = head;
+            let mut last: Option<u32> = None;
+            let mut count = 0;
+            let mut broken: Option<String> = None;
+
+            while count < needed {
+                if next == 0 {
+                    broken = Some(format!("chain ends after {} of {} sectors", count, needed));
+                    break;
+                }
+                if (next as u64) < SEC_DATA_START || next as u64 >= total {
+                    broken = Some(format!("sector {} out of range", next));
+                    break;
+                }
+                if !used.insert(next) {
+                    broken = Some(format!("sector {} already in use", next));
+                    break;
+                }
+
+                let mut data = [0u8; 512];
+                dev.read_sector(next as u64, &mut data)?;
+                last = Some(next);
+                next = u32::from_le_bytes(data[0..4].try_into().unwrap());
+                count += 1;
+            }
+            report.sectors += count;
+
+            let reason = match broken {
+                Some(reason) => reason,
+                None if next == 0 => continue,
+                None => match last {
+                    // Writes past the end follow the chain, so a tail counts as in use
+                    Some(last) => {
+                        tails.push(Tail { name, last, next });
+                        continue;
+                    }
+                    None => String::from("empty file has a data chain"),
+                },
+            };
+            report.problems.push(format!("{}: {}", name, reason));
+            if !repair {
+                continue;
+            }
+
+            // Keep the readable prefix: end the chain at the last good sector
+            match last {
+                Some(sector) => terminate_chain(dev, sector)?,
+                None => entry[68..72].copy_from_slice(&0u32.to_le_bytes()),
+            }
+            let new_size = (count * BLOCK_DATA) as u32 | (raw_size & (SYMLINK_FLAG | COMPRESSED_FLAG));
+            entry[64..68].copy_from_slice(&new_size.to_le_bytes());
+            dirty = true;
+            report.repaired += 1;
+        }
+
+        if dirty {
+            dev.write_sector(sector, &buf)?;
+        }
+    }
+
+    // Tails are walked last so they can never claim another file's sectors
+    for tail in tails {
+        let mut last = tail.last;
+        let mut next = tail.next;
+        while next != 0 {
+            if (next as u64) < SEC_DATA_START || next as u64 >= total || used.contains(&next) {
+                report
+                    .problems
+                    .push(format!("{}: chain continues into sector {} past end of file", tail.name, next));
+                if repair {
+                    terminate_chain(dev, last)?;
+                    report.repaired += 1;
+                }
+                break;
+            }
+            used.insert(next);
+            report.sectors += 1;
+
+            let mut data = [0u8; 512];
+            dev.read_sector(next as u64, &mut data)?;
+            last = next;
+            next = u32::from_le_bytes(data[0..4].try_into().unwrap());
+        }
+    }
+
+    Ok(())
+}
+
+/// Clear the next pointer of `sector`
+fn terminate_chain(dev: &mut BlockDev, sector: u32) -> Result<(), &'static str> {
+    let mut data = [0u8; 512];
+    dev.read_sector(sector as u64, &mut data)?;
+    data[0..4].copy_from_slice(&0u32.to_le_bytes());
+    dev.write_sector(sector as u64, &data)
+}
+
+/// Compare the allocation bitmap with the sectors actually in use
+fn check_bitmap(
+    dev: &mut BlockDev,
+    total: u64,
+    used: &BTreeSet<u32>,
+    repair: bool,
+    reclaim: bool,
+    report: &mut FsckReport,
+) -> Result<(), &'static str> {
+    let mut unmarked = 0;
+
+    for i in 0..SEC_MAP_COUNT {
+        let first = i * SECTORS_PER_MAP;
+        if first >= total {
+            break;
+        }
+        let mut buf = [0u8; 512];
+        dev.read_sector(SEC_MAP_START + i, &mut buf)?;
+        let mut dirty = false;
+
+        for bit in 0..SECTORS_PER_MAP.min(total - first) {
+            let sector = first + bit;
+            let (byte, mask) = ((bit / 8) as usize, 1u8 << (bit % 8));
+            let marked = buf[byte] & mask != 0;
+            let in_use = sector < SEC_DATA_START || used.contains(&(sector as u32));
+
+            if marked && !in_use {
+                report.orphans += 1;
+                if repair && reclaim {
+                    buf[byte] &= !mask;
+                    dirty = true;
+                }
+            } else if !marked && in_use {
+                unmarked += 1;
+                if repair {
+                    buf[byte] |= mask;
+                    dirty = true;
+                }
+            }
+        }
+
+        if dirty {
+            dev.write_sector(SEC_MAP_START + i, &buf)?;
+        }
+    }
+
+    if report.orphans > 0 {
+        report
+            .problems
+            .push(format!("bitmap: {} orphaned sectors", report.orphans));
+        if repair && reclaim {
+            report.repaired += 1;
+        }
+    }
+    if unmarked > 0 {
+        report
+            .problems
+            .push(format!("bitmap: {} sectors in use but not marked", unmarked));
+        if repair {
+            report.repaired += 1;
+        }
+    }
+
+    Ok(())
+}
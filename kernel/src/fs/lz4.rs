@@ -0,0 +1,70 @@
+//! LZ4 Block Decompression
+//!
+//! Files written by `mkfs --compress` carry `COMPRESSED_FLAG` in their
+//! directory entry; their data is the original length (u32 LE) followed by
+//! one LZ4 block. SFS decompresses them on read. The kernel never writes
+//! compressed files: a write to one stores it uncompressed.
+
+use alloc::vec::Vec;
+
+/// Shortest match the format can encode
+const MIN_MATCH: usize = 4;
+
+/// Decompress one LZ4 block that expands to exactly `len` bytes
+pub fn decompress(input: &[u8], len: usize) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(len);
+    let mut i = 0;
+
+    loop {
+        let token = *input.get(i)?;
+        i += 1;
+
+        let mut literals = (token >> 4) as usize;
+        if literals == 15 {
+            literals += read_length(input, &mut i)?;
+        }
+        if out.len() + literals > len {
+            return None;
+        }
+        out.extend_from_slice(input.get(i..i + literals)?);
+        i += literals;
+        if i == input.len() {
+            break;
+        }
+
+        let offset = u16::from_le_bytes([*input.get(i)?, *input.get(i + 1)?]) as usize;
+        i += 2;
+        let mut match_len = (token & 0x0F) as usize;
+        if match_len == 15 {
+            match_len += read_length(input, &mut i)?;
+        }
+        match_len += MIN_MATCH;
+        if offset == 0 || offset > out.len() || out.len() + match_len > len {
+            return None;
+        }
+
+        // Byte by byte: the match may overlap what it is copying
+        let start = out.len() - offset;
+        for k in 0..match_len {
+            out.push(out[start + k]);
+        }
+    }
+
+    if out.len() == len {
+        Some(out)
+    } else {
+        None
+    }
+}
+
+fn read_length(input: &[u8], i: &mut usize) -> Option<usize> {
+    let mut len = 0;
+    loop {
+        let byte = *input.get(*i)?;
+        *i += 1;
+        len += byte as usize;
+        if byte != 255 {
+            return Some(len);
+        }
+    }
+}
@@ -5,6 +5,9 @@
 //!
 //! - **SFS**: Simple File System on block devices (default root filesystem)
 //! - **P9**: 9P protocol filesystem for host directory mounting
+//! - **procfs**: Synthetic `/proc` generated from kernel tables
+//!
+//! Changes made through the VFS are reported to file watches (`watch`).
 //!
 //! # Architecture
 //!
@@ -32,11 +35,16 @@
 pub mod vfs;
 pub mod sfs;
 pub mod p9;
+pub mod fsck;
+pub mod lz4;
+pub mod procfs;
+pub mod watch;
 
 // Re-export key types
 pub use vfs::{FileSystem, Vfs, FileInfo};
 pub use sfs::{Sfs, GlobalSfs};
 pub use p9::P9FileSystem;
+pub use procfs::ProcFs;
 
 
 // Re-export legacy types for backwards compatibility
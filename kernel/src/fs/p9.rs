@@ -1,145 +1,136 @@
 //! 9P Filesystem Implementation
 //!
-//! Implements the `FileSystem` trait for accessing host-mounted directories
-//! via the VirtIO 9P driver.
+//! Implements the `FileSystem` trait for host directories served over 9P,
+//! either through the VirtIO 9P device or over TCP (`mount -t 9p`).
 
 use alloc::boxed::Box;
 use alloc::string::String;
 use alloc::vec::Vec;
 
+use smoltcp::wire::Ipv4Address;
+
 use super::vfs::{FileSystem, FileInfo};
-use crate::device::virtio_p9::{self, VirtioP9Driver, DirEntry};
+use crate::device::p9::P9Client;
+use crate::device::p9_tcp::P9TcpTransport;
+use crate::device::virtio_p9;
 use crate::Spinlock;
 
 /// 9P Filesystem implementing the VFS FileSystem trait
 pub struct P9FileSystem {
-    /// The underlying VirtIO 9P driver (wrapped in Spinlock for interior mutability)
-    driver: Spinlock<VirtioP9Driver>,
+    /// The 9P client (wrapped in Spinlock for interior mutability)
+    client: Spinlock<P9Client>,
 }
 
 impl P9FileSystem {
-    /// Create a new P9FileSystem from an initialized driver
-    pub fn new(driver: VirtioP9Driver) -> Self {
+    /// Create a new P9FileSystem from an attached client
+    pub fn new(client: P9Client) -> Self {
         Self {
-            driver: Spinlock::new(driver),
+            client: Spinlock::new(client),
         }
     }
 
     /// Try to initialize and return a P9FileSystem
     /// Returns None if no VirtIO 9P device is found
     pub fn probe() -> Option<Self> {
-        let mut driver = VirtioP9Driver::probe()?;
-        driver.init().ok()?;
-        Some(Self::new(driver))
+        virtio_p9::probe_client().map(Self::new)
+    }
+
+    /// Connect to a 9P server over TCP and attach to its root
+    pub fn connect_tcp(ip: Ipv4Address, port: u16) -> Result<Self, &'static str> {
+        let transport = P9TcpTransport::connect(ip, port)?;
+        Ok(Self::new(P9Client::new(Box::new(transport))?))
+    }
+
+    /// Transport name ("virtio" or "tcp")
+    pub fn transport_kind(&self) -> &'static str {
+        self.client.lock().transport_kind()
     }
 }
 
 impl FileSystem for P9FileSystem {
-    fn read_file(&mut self, path: &str) -> Option<Vec<u8>> {
-        let mut driver = self.driver.lock();
-        driver.read_file(path)
+    fn read_file(&self, path: &str) -> Option<Vec<u8>> {
+        self.client.lock().read_file(path)
     }
 
-    fn write_file(&mut self, path: &str, data: &[u8]) -> Result<(), &'static str> {
-        let mut driver = self.driver.lock();
-        
-        // Try to walk to the file
-        let fid = match driver.walk(path) {
-            Ok(f) => {
-                // File exists, open for writing
-                if driver.open(f, 1).is_err() { // O_WRONLY
-                    let _ = driver.clunk(f);
-                    return Err("Failed to open file");
-                }
-                f
-            }
-            Err(_) => {
-                // File doesn't exist - walk to parent and create
-                let (parent, filename) = if let Some(last_slash) = path.rfind('/') {
-                    if last_slash == 0 {
-                        ("/", &path[1..])
-                    } else {
-                        (&path[..last_slash], &path[last_slash + 1..])
-                    }
-                } else {
-                    ("/", path)
-                };
-                
-                // Walk to parent directory
-                let parent_fid = match driver.walk(parent) {
-                    Ok(f) => f,
-                    Err(_) => return Err("Parent directory not found"),
-                };
-                
-                // Create the file using lcreate
-                match driver.lcreate(parent_fid, filename) {
-                    Ok(f) => f,
-                    Err(_) => {
-                        let _ = driver.clunk(parent_fid);
-                        return Err("Failed to create file");
-                    }
-                }
-            }
-        };
-        
-        // Write data
-        let result = driver.write(fid, 0, data);
-        
-        // Close
-        let _ = driver.clunk(fid);
-        
-        result.map(|_| ())
+    fn write_file(&self, path: &str, data: &[u8]) -> Result<(), &'static str> {
+        self.client.lock().write_file_at(path, None, data).map(|_| ())
     }
 
-    fn list_dir(&mut self, path: &str) -> Vec<FileInfo> {
-        let mut driver = self.driver.lock();
-        driver.list_dir(path)
+    fn write_at(&self, path: &str, offset: usize, data: &[u8]) -> Result<usize, &'static str> {
+        self.client.lock()
+            .write_file_at(path, Some(offset as u64), data)
+            .map(|size| size as usize)
+    }
+
+    fn append(&self, path: &str, data: &[u8]) -> Result<usize, &'static str> {
+        let mut client = self.client.lock();
+        // Only the size is needed, not the contents
+        let len = client.stat(path).map(|attr| attr.size).unwrap_or(0);
+        client.write_file_at(path, Some(len), data).map(|size| size as usize)
+    }
+
+    fn list_dir(&self, path: &str) -> Vec<FileInfo> {
+        let mut client = self.client.lock();
+        let entries = client.list_dir(path);
+        entries
             .into_iter()
-            .map(|e| FileInfo {
-                name: e.name,
-                size: 0, // Would need getattr to get size
-                is_dir: e.is_dir,
+            .map(|e| {
+                let size = if e.is_dir {
+                    0
+                } else {
+                    client.stat(&join(path, &e.name)).map(|a| a.size as u32).unwrap_or(0)
+                };
+                FileInfo {
+                    name: e.name,
+                    size,
+                    is_dir: e.is_dir,
+                }
             })
             .collect()
     }
 
-    fn exists(&mut self, path: &str) -> bool {
-        let mut driver = self.driver.lock();
+    fn exists(&self, path: &str) -> bool {
+        let mut client = self.client.lock();
         // Try to walk to the path - if successful, it exists
-        match driver.walk(path) {
+        match client.walk(path) {
             Ok(fid) => {
-                let _ = driver.clunk(fid);
+                let _ = client.clunk(fid);
                 true
             }
             Err(_) => false,
         }
     }
 
-    fn is_dir(&mut self, path: &str) -> bool {
+    fn is_dir(&self, path: &str) -> bool {
         // Root is always a directory
         if path == "/" || path.is_empty() {
             return true;
         }
-        
-        let mut driver = self.driver.lock();
-        // List parent and check entry type
-        let entries = driver.list_dir(path);
-        // If we got entries, it's a directory
-        !entries.is_empty()
+        self.client.lock().stat(path).map(|a| a.is_dir()).unwrap_or(false)
     }
 
-    fn remove(&mut self, _path: &str) -> Result<(), &'static str> {
-        // 9P remove not implemented in this minimal driver
-        Err("Remove not supported on 9P mount")
+    fn remove(&self, path: &str) -> Result<(), &'static str> {
+        self.client.lock().remove_path(path)
     }
 
-    fn sync(&mut self) -> Result<usize, &'static str> {
+    fn sync(&self) -> Result<usize, &'static str> {
         // 9P sync is handled by host
         Ok(0)
     }
 
-    fn mkdir(&mut self, _path: &str) -> Result<(), &'static str> {
-        // 9P mkdir not implemented in this minimal driver
-        Err("Mkdir not supported on 9P mount")
+    fn mkdir(&self, path: &str) -> Result<(), &'static str> {
+        self.client.lock().mkdir_path(path)
+    }
+
+    fn rename(&self, from: &str, to: &str) -> Result<(), &'static str> {
+        self.client.lock().rename_path(from, to)
     }
 }
+
+/// Join a directory path and an entry name
+fn join(dir: &str, name: &str) -> String {
+    let mut path = String::from(dir.trim_end_matches('/'));
+    path.push('/');
+    path.push_str(name);
+    path
+}
@@ -0,0 +1,322 @@
+//! Process Filesystem (/proc)
+//!
+//! A read-only synthetic filesystem whose files are generated on every read
+//! from `PROCESS_TABLE`, `CPU_TABLE` and the allocator, so `cat` can show
+//! system statistics without a dedicated syscall for each one:
+//!
+//! - `/proc/meminfo` - heap and memory usage
+//! - `/proc/cpuinfo` - one block per online hart
+//! - `/proc/uptime`  - seconds since boot and total idle seconds
+//! - `/proc/schedstat` - load balancing counters and per-hart load
+//! - `/proc/mqueue` - message queues with their queued messages
+//! - `/proc/cgroups` - control groups with their shares, CPU and heap use
+//! - `/proc/<pid>/status` - state, priority and accounting of a process
+//! - `/proc/<pid>/io`     - syscalls and bytes read/written (files, network)
+//!
+//! Like SFS, `list_dir` returns full paths (`/proc/1/status`) and callers
+//! group them into directories.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write;
+use core::sync::atomic::Ordering;
+
+use super::vfs::{FileInfo, FileSystem};
+use crate::cpu::process::{Pid, ProcessFlags, ProcessState};
+use crate::cpu::process::PROCESS_TABLE;
+use crate::cpu::CPU_TABLE;
+
+/// Where the filesystem is mounted, used to build `list_dir` names
+pub const MOUNT_POINT: &str = "/proc";
+
+/// Files at the top of /proc
+const SYSTEM_FILES: &[&str] = &["meminfo", "cpuinfo", "uptime", "schedstat", "mqueue", "cgroups"];
+
+/// Files inside each /proc/<pid>
+const PROCESS_FILES: &[&str] = &["status", "io"];
+
+/// Synthetic /proc filesystem (stateless: everything comes from kernel tables)
+pub struct ProcFs;
+
+/// A path inside /proc, relative to the mount point
+enum Node<'a> {
+    Root,
+    System(&'a str),
+    ProcessDir(Pid),
+    ProcessFile(Pid, &'a str),
+}
+
+fn parse(path: &str) -> Option<Node<'_>> {
+    let path = path.trim_matches('/');
+    if path.is_empty() {
+        return Some(Node::Root);
+    }
+    let (first, rest) = match path.split_once('/') {
+        Some((first, rest)) => (first, Some(rest)),
+        None => (path, None),
+    };
+
+    if let Ok(pid) = first.parse::<Pid>() {
+        PROCESS_TABLE.get(pid)?;
+        return match rest {
+            None => Some(Node::ProcessDir(pid)),
+            Some(file) if PROCESS_FILES.contains(&file) => Some(Node::ProcessFile(pid, file)),
+            Some(_) => None,
+        };
+    }
+    match rest {
+        None if SYSTEM_FILES.contains(&first) => Some(Node::System(first)),
+        _ => None,
+    }
+}
+
+impl ProcFs {
+    /// Generate a file's contents
+    fn generate(&self, path: &str) -> Option<String> {
+        match parse(path)? {
+            Node::System("meminfo") => Some(meminfo()),
+            Node::System("cpuinfo") => Some(cpuinfo()),
+            Node::System("uptime") => Some(uptime()),
+            Node::System("schedstat") => Some(schedstat()),
+            Node::System("mqueue") => Some(mqueue()),
+            Node::System("cgroups") => Some(cgroups()),
+            Node::ProcessFile(pid, "status") => process_status(pid),
+            Node::ProcessFile(pid, "io") => process_io(pid),
+            _ => None,
+        }
+    }
+}
+
+impl FileSystem for ProcFs {
+    fn read_file(&self, path: &str) -> Option<Vec<u8>> {
+        self.generate(path).map(String::into_bytes)
+    }
+
+    fn write_file(&self, _path: &str, _data: &[u8]) -> Result<(), &'static str> {
+        Err("/proc is read-only")
+    }
+
+    fn write_at(&self, _path: &str, _offset: usize, _data: &[u8]) -> Result<usize, &'static str> {
+        Err("/proc is read-only")
+    }
+
+    fn list_dir(&self, path: &str) -> Vec<FileInfo> {
+        let file = |name: String, content: Option<String>| FileInfo {
+            size: content.map(|c| c.len() as u32).unwrap_or(0),
+            name,
+            is_dir: false,
+        };
+        let process_files = |pid: Pid| {
+            PROCESS_FILES.iter().map(move |f| {
+                let relative = format!("/{}/{}", pid, f);
+                file(format!("{}{}", MOUNT_POINT, relative), ProcFs.generate(&relative))
+            })
+        };
+
+        match parse(path) {
+            Some(Node::Root) => {
+                let mut entries: Vec<FileInfo> = SYSTEM_FILES
+                    .iter()
+                    .map(|f| file(format!("{}/{}", MOUNT_POINT, f), self.generate(f)))
+                    .collect();
+                for process in PROCESS_TABLE.list() {
+                    entries.extend(process_files(process.pid));
+                }
+                entries
+            }
+            Some(Node::ProcessDir(pid)) => process_files(pid).collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    fn exists(&self, path: &str) -> bool {
+        parse(path).is_some()
+    }
+
+    fn is_dir(&self, path: &str) -> bool {
+        matches!(parse(path), Some(Node::Root | Node::ProcessDir(_)))
+    }
+
+    fn remove(&self, _path: &str) -> Result<(), &'static str> {
+        Err("/proc is read-only")
+    }
+
+    fn sync(&self) -> Result<usize, &'static str> {
+        Ok(0)
+    }
+
+    fn mkdir(&self, _path: &str) -> Result<(), &'static str> {
+        Err("/proc is read-only")
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// File Generators
+// ═══════════════════════════════════════════════════════════════════════════════
+
+fn meminfo() -> String {
+    let harts = crate::HARTS_ONLINE.load(Ordering::Relaxed);
+    let gpu = crate::cpu::display_proxy::is_available();
+    let stats = crate::allocator::memory_stats(harts, gpu);
+    let kb = |bytes: usize| bytes / 1024;
+
+    let mut out = String::new();
+    let _ = writeln!(out, "MemTotal:      {:>8} kB", kb(stats.total_available));
+    let _ = writeln!(out, "MemUsed:       {:>8} kB", kb(stats.total_used));
+    let _ = writeln!(out, "MemFree:       {:>8} kB", kb(stats.total_available.saturating_sub(stats.total_used)));
+    let _ = writeln!(out, "HeapTotal:     {:>8} kB", kb(stats.heap_total));
+    let _ = writeln!(out, "HeapUsed:      {:>8} kB", kb(stats.heap_used));
+    let _ = writeln!(out, "HeapFree:      {:>8} kB", kb(stats.heap_free));
+    let _ = writeln!(out, "HeapPeak:      {:>8} kB", kb(crate::allocator::heap_peak()));
+    let (dma_used, dma_total) = crate::device::dma::stats();
+    let _ = writeln!(out, "DmaTotal:      {:>8} kB", kb(dma_total));
+    let _ = writeln!(out, "DmaUsed:       {:>8} kB", kb(dma_used));
+    let _ = writeln!(out, "KernelStatic:  {:>8} kB", kb(stats.static_size));
+    let _ = writeln!(out, "Stacks:        {:>8} kB", kb(stats.stack_size));
+    let _ = writeln!(out, "Framebuffer:   {:>8} kB", kb(stats.framebuffer_size));
+    out
+}
+
+fn cpuinfo() -> String {
+    let mut out = String::new();
+    for id in CPU_TABLE.online_cpus() {
+        let Some(cpu) = CPU_TABLE.get(id) else { continue };
+        let info = cpu.info();
+        let _ = writeln!(out, "processor       : {}", info.id);
+        let _ = writeln!(out, "hart            : {}", info.id);
+        let _ = writeln!(out, "bsp             : {}", if info.is_bsp { "yes" } else { "no" });
+        let _ = writeln!(out, "state           : {:?}", info.state);
+        match info.running_process {
+            Some(pid) => { let _ = writeln!(out, "running pid     : {}", pid); }
+            None => { let _ = writeln!(out, "running pid     : -"); }
+        }
+        let _ = writeln!(out, "utilization     : {}%", info.utilization);
+        let _ = writeln!(out, "recent usage    : {}.{}%", cpu.recent_usage() / 10, cpu.recent_usage() % 10);
+        let _ = writeln!(out, "busy ms         : {}", cpu.busy_time_ms.load(Ordering::Relaxed));
+        let _ = writeln!(out, "idle ms         : {}", cpu.idle_time_ms.load(Ordering::Relaxed));
+        let _ = writeln!(out, "context switches: {}", info.context_switches);
+        let _ = writeln!(out, "interrupts      : {}", info.interrupts);
+        out.push('\n');
+    }
+    out
+}
+
+/// "<uptime> <idle>" in seconds with two decimals, like Linux
+fn uptime() -> String {
+    let uptime_ms = crate::get_time_ms().max(0) as u64;
+    let idle_ms: u64 = CPU_TABLE
+        .online_cpus()
+        .into_iter()
+        .filter_map(|id| CPU_TABLE.get(id))
+        .map(|cpu| cpu.idle_time_ms.load(Ordering::Relaxed))
+        .sum();
+    format!(
+        "{}.{:02} {}.{:02}\n",
+        uptime_ms / 1000,
+        uptime_ms % 1000 / 10,
+        idle_ms / 1000,
+        idle_ms % 1000 / 10
+    )
+}
+
+fn schedstat() -> String {
+    let sched = &crate::cpu::sched::SCHEDULER;
+    let stats = sched.stats();
+    let mut out = String::new();
+    let _ = writeln!(out, "rebalance_passes: {}", stats.rebalance_passes);
+    let _ = writeln!(out, "migrations:       {}", stats.migrations);
+    let _ = writeln!(out, "steals:           {}", stats.steals);
+    let _ = writeln!(out, "imbalance:        {}", stats.last_imbalance);
+    let _ = writeln!(out, "preemptions:      {}", stats.preemptions);
+    let _ = writeln!(out, "sleeps:           {}", stats.sleeps);
+    let _ = writeln!(out, "boosts:           {}", stats.boosts);
+    for id in CPU_TABLE.online_cpus() {
+        let _ = writeln!(out, "cpu{} load {} queued {}", id, sched.cpu_load(id), sched.queue_length(id));
+    }
+    out
+}
+
+fn mqueue() -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "{:>4} {:>6} {:>8} NAME", "ID", "QUEUED", "CAPACITY");
+    for (id, name, queued, capacity) in crate::cpu::ipc::IPC.list_queues() {
+        let _ = writeln!(out, "{:>4} {:>6} {:>8} {}", id, queued, capacity, name);
+    }
+    out
+}
+
+fn cgroups() -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "{:>2} {:>6} {:>7} {:>4} {:>6} {:>10} {:>10} NAME", "ID", "SHARES", "MEMBERS", "BUSY", "CPU%", "HEAP", "LIMIT");
+    for group in crate::cpu::cgroup::list() {
+        let limit = group.mem_limit.map_or(String::from("-"), |limit| format!("{}", limit));
+        let _ = writeln!(
+            out,
+            "{:>2} {:>6} {:>7} {:>4} {:>4}.{} {:>10} {:>10} {}",
+            group.id, group.shares, group.members, group.busy,
+            group.cpu_usage / 10, group.cpu_usage % 10, group.mem_bytes, limit, group.name
+        );
+    }
+    out
+}
+
+fn process_status(pid: Pid) -> Option<String> {
+    let process = PROCESS_TABLE.get(pid)?;
+    let info = process.info(crate::get_time_ms().max(0) as u64);
+    let state = match info.state {
+        ProcessState::Created => "created",
+        ProcessState::Ready => "ready",
+        ProcessState::Running => "running",
+        ProcessState::Blocked => "sleeping",
+        ProcessState::Stopped => "stopped",
+        ProcessState::Zombie => "zombie",
+    };
+    let kind = if info.flags.contains(ProcessFlags::DAEMON) {
+        "daemon"
+    } else if info.flags.contains(ProcessFlags::KERNEL) {
+        "kernel"
+    } else {
+        "user"
+    };
+
+    let mut out = String::new();
+    let _ = writeln!(out, "Name:       {}", info.name);
+    let _ = writeln!(out, "Pid:        {}", info.pid);
+    let _ = writeln!(out, "PPid:       {}", info.ppid);
+    let _ = writeln!(out, "Pgid:       {}", info.pgid);
+    let _ = writeln!(out, "Cgroup:     {}", crate::cpu::cgroup::name(info.cgroup).unwrap_or("-"));
+    let _ = writeln!(out, "State:      {} ({})", info.state.code(), state);
+    let _ = writeln!(out, "Kind:       {}", kind);
+    let _ = writeln!(out, "Priority:   {:?}", info.priority);
+    let _ = writeln!(out, "Nice:       {}", info.nice);
+    match info.cpu {
+        Some(cpu) => { let _ = writeln!(out, "Cpu:        {}", cpu); }
+        None => { let _ = writeln!(out, "Cpu:        -"); }
+    }
+    let _ = writeln!(out, "Affinity:   {}", process.affinity().to_list());
+    let _ = writeln!(out, "CpuTimeMs:  {}", info.cpu_time_ms);
+    let _ = writeln!(out, "CpuUsage:   {}.{}%", info.cpu_usage / 10, info.cpu_usage % 10);
+    let _ = writeln!(out, "UptimeMs:   {}", info.uptime_ms);
+    let _ = writeln!(out, "Scheduled:  {}", process.schedule_count.load(Ordering::Relaxed));
+    let _ = writeln!(out, "Migrations: {}", process.migrations());
+    let _ = writeln!(out, "OpenFiles:  {}", process.fds.lock().len());
+    let (heap_bytes, heap_blocks) = crate::allocator::process_heap(pid);
+    let _ = writeln!(out, "HeapBytes:  {} ({} blocks)", heap_bytes, heap_blocks);
+    match crate::allocator::heap_limit(pid) {
+        Some(limit) => { let _ = writeln!(out, "HeapLimit:  {}", limit); }
+        None => { let _ = writeln!(out, "HeapLimit:  -"); }
+    }
+    Some(out)
+}
+
+fn process_io(pid: Pid) -> Option<String> {
+    let io = PROCESS_TABLE.get(pid)?.io.snapshot();
+    let mut out = String::new();
+    let _ = writeln!(out, "syscalls:    {}", io.syscalls);
+    let _ = writeln!(out, "fs_read:     {}", io.fs_read_bytes);
+    let _ = writeln!(out, "fs_written:  {}", io.fs_write_bytes);
+    let _ = writeln!(out, "net_rx:      {}", io.net_rx_bytes);
+    let _ = writeln!(out, "net_tx:      {}", io.net_tx_bytes);
+    Some(out)
+}
@@ -3,6 +3,7 @@
 //! This module provides a `FileSystem` trait implementation around the
 //! existing `FileSystemState` from `lock/state/fs.rs`.
 
+use alloc::string::String;
 use alloc::vec::Vec;
 use crate::lock::state::fs::FileSystemState;
 use crate::platform::d1_mmc::D1Mmc as BlockDev;
@@ -54,20 +55,20 @@ impl Sfs {
 }
 
 impl FileSystem for Sfs {
-    fn read_file(&mut self, path: &str) -> Option<Vec<u8>> {
+    fn read_file(&self, path: &str) -> Option<Vec<u8>> {
         // NOTE: Don't strip leading slash - SFS stores files with full paths including /
-        self.state.read_file(&mut self.dev, path)
+        self.state.read_file(&self.dev, path)
     }
 
-    fn write_file(&mut self, path: &str, data: &[u8]) -> Result<(), &'static str> {
+    fn write_file(&self, path: &str, data: &[u8]) -> Result<(), &'static str> {
         // NOTE: Don't strip leading slash - SFS stores files with full paths including /
-        self.state.write_file(&mut self.dev, path, data)
+        self.state.write_file(&self.dev, path, data)
     }
 
-    fn list_dir(&mut self, path: &str) -> Vec<FileInfo> {
+    fn list_dir(&self, path: &str) -> Vec<FileInfo> {
         // SFS has flat structure, path is mostly ignored
         self.state
-            .list_dir(&mut self.dev, path)
+            .list_dir(&self.dev, path)
             .into_iter()
             .map(|e| FileInfo {
                 name: e.name,
@@ -77,28 +78,64 @@ impl FileSystem for Sfs {
             .collect()
     }
 
-    fn exists(&mut self, path: &str) -> bool {
+    fn exists(&self, path: &str) -> bool {
         // NOTE: Don't strip leading slash - SFS stores files with full paths including /
-        self.state.exists(&mut self.dev, path)
+        self.state.exists(&self.dev, path)
     }
 
-    fn is_dir(&mut self, path: &str) -> bool {
+    fn is_dir(&self, path: &str) -> bool {
         // NOTE: Don't strip leading slash - SFS stores files with full paths including /
-        self.state.is_dir(&mut self.dev, path)
+        self.state.is_dir(&self.dev, path)
     }
 
-    fn remove(&mut self, path: &str) -> Result<(), &'static str> {
+    fn remove(&self, path: &str) -> Result<(), &'static str> {
         // NOTE: Don't strip leading slash - SFS stores files with full paths including /
-        self.state.remove(&mut self.dev, path)
+        self.state.remove(&self.dev, path)
     }
 
-    fn sync(&mut self) -> Result<usize, &'static str> {
-        self.state.sync(&mut self.dev)
+    fn sync(&self) -> Result<usize, &'static str> {
+        self.state.sync(&self.dev)
     }
 
-    fn mkdir(&mut self, path: &str) -> Result<(), &'static str> {
+    fn mkdir(&self, path: &str) -> Result<(), &'static str> {
         // NOTE: Don't strip leading slash - SFS stores files with full paths including /
-        self.state.mkdir(&mut self.dev, path)
+        self.state.mkdir(&self.dev, path)
+    }
+
+    fn write_at(&self, path: &str, offset: usize, data: &[u8]) -> Result<usize, &'static str> {
+        self.state.write_at(&self.dev, path, offset, data)
+    }
+
+    fn append(&self, path: &str, data: &[u8]) -> Result<usize, &'static str> {
+        self.state.append(&self.dev, path, data)
+    }
+
+    fn read_at(&self, path: &str, offset: usize, buf: &mut [u8]) -> Result<usize, &'static str> {
+        self.state.read_at(&self.dev, path, offset, buf)
+    }
+
+    fn file_len(&self, path: &str) -> Option<usize> {
+        self.state.file_len(&self.dev, path)
+    }
+
+    fn rename(&self, from: &str, to: &str) -> Result<(), &'static str> {
+        self.state.rename(&self.dev, from, to)
+    }
+
+    fn symlink(&self, target: &str, path: &str) -> Result<(), &'static str> {
+        self.state.symlink(&self.dev, target, path)
+    }
+
+    fn readlink(&self, path: &str) -> Option<String> {
+        self.state.readlink(&self.dev, path)
+    }
+
+    fn first_symlink(&self, path: &str, follow_last: bool) -> Option<(String, String)> {
+        self.state.first_symlink(&self.dev, path, follow_last)
+    }
+
+    fn any_hart(&self) -> bool {
+        true
     }
 }
 
@@ -113,64 +150,43 @@ use crate::lock::utils::{FS_STATE, BLK_DEV};
 /// Adapts the global `FS_STATE` and `BLK_DEV` locks into a `FileSystem` trait object.
 /// This allows mounting the SFS in VFS without taking ownership of the globals,
 /// preserving compatibility with legacy code.
+///
+/// Both are only read-locked: `FileSystemState` does its own per-file and
+/// allocator locking, so every hart can be in it at once.
 pub struct GlobalSfs;
 
 impl FileSystem for GlobalSfs {
-    fn read_file(&mut self, path: &str) -> Option<Vec<u8>> {
+    fn read_file(&self, path: &str) -> Option<Vec<u8>> {
         use crate::device::uart::write_str;
         
 
         
-        let mut fs_guard = FS_STATE.write();
-        let mut blk_guard = BLK_DEV.write();
+        let fs_guard = FS_STATE.read();
+        let blk_guard = BLK_DEV.read();
         
-        if let (Some(fs), Some(dev)) = (fs_guard.as_mut(), blk_guard.as_mut()) {
+        if let (Some(fs), Some(dev)) = (fs_guard.as_ref(), blk_guard.as_ref()) {
             let result = fs.read_file(dev, path);
             return result;
         }
         None
     }
 
-    fn write_file(&mut self, path: &str, data: &[u8]) -> Result<(), &'static str> {
-        use core::arch::asm;
-        
-        let start = crate::get_time_ms();
-        let timeout_ms = 25000i64;
-        
-        loop {
-            // Try to acquire both locks non-blocking
-            if let Some(mut fs_guard) = FS_STATE.try_write() {
-                if let Some(mut blk_guard) = BLK_DEV.try_write() {
-                    if let (Some(fs), Some(dev)) = (fs_guard.as_mut(), blk_guard.as_mut()) {
-                        let result = fs.write_file(dev, path, data);
-                        if result.is_ok() {
-                            // Sync the cache to disk so list_dir sees the new file
-                            if let Err(e) = fs.sync(dev) {
-                                return Err(e);
-                            }
-                        }
-                        return result;
-                    }
-                    return Err("Filesystem not initialized");
-                }
-            }
-            
-            // Check timeout
-            let elapsed = crate::get_time_ms() - start;
-            if elapsed >= timeout_ms {
-                return Err("Lock timeout");
-            }
-            
-            // Yield CPU briefly
-            unsafe { asm!("wfi", options(nomem, nostack)); }
-        }
-    }
-
-    fn list_dir(&mut self, path: &str) -> Vec<FileInfo> {
-        let mut fs_guard = FS_STATE.write();
-        let mut blk_guard = BLK_DEV.write();
-        
-        if let (Some(fs), Some(dev)) = (fs_guard.as_mut(), blk_guard.as_mut()) {
+    fn write_file(&self, path: &str, data: &[u8]) -> Result<(), &'static str> {
+        let fs_guard = FS_STATE.read();
+        let blk_guard = BLK_DEV.read();
+        
+        if let (Some(fs), Some(dev)) = (fs_guard.as_ref(), blk_guard.as_ref()) {
+            // Write-back: blocks stay dirty in the cache until sync()
+            return fs.write_file(dev, path, data);
+        }
+        Err("Filesystem not initialized")
+    }
+
+    fn list_dir(&self, path: &str) -> Vec<FileInfo> {
+        let fs_guard = FS_STATE.read();
+        let blk_guard = BLK_DEV.read();
+        
+        if let (Some(fs), Some(dev)) = (fs_guard.as_ref(), blk_guard.as_ref()) {
             return fs.list_dir(dev, path)
                 .into_iter()
                 .map(|e| FileInfo {
@@ -183,57 +199,142 @@ impl FileSystem for GlobalSfs {
         Vec::new()
     }
 
-    fn exists(&mut self, path: &str) -> bool {
-        let mut fs_guard = FS_STATE.write();
-        let mut blk_guard = BLK_DEV.write();
+    fn exists(&self, path: &str) -> bool {
+        let fs_guard = FS_STATE.read();
+        let blk_guard = BLK_DEV.read();
         
-        if let (Some(fs), Some(dev)) = (fs_guard.as_mut(), blk_guard.as_mut()) {
+        if let (Some(fs), Some(dev)) = (fs_guard.as_ref(), blk_guard.as_ref()) {
             // NOTE: Don't strip leading slash - SFS stores files with full paths including /
             return fs.exists(dev, path);
         }
         false
     }
 
-    fn is_dir(&mut self, path: &str) -> bool {
-        let mut fs_guard = FS_STATE.write();
-        let mut blk_guard = BLK_DEV.write();
+    fn is_dir(&self, path: &str) -> bool {
+        let fs_guard = FS_STATE.read();
+        let blk_guard = BLK_DEV.read();
         
-        if let (Some(fs), Some(dev)) = (fs_guard.as_mut(), blk_guard.as_mut()) {
+        if let (Some(fs), Some(dev)) = (fs_guard.as_ref(), blk_guard.as_ref()) {
             // NOTE: Don't strip leading slash - SFS stores files with full paths including /
             return fs.is_dir(dev, path);
         }
         false
     }
 
-    fn remove(&mut self, path: &str) -> Result<(), &'static str> {
-        let mut fs_guard = FS_STATE.write();
-        let mut blk_guard = BLK_DEV.write();
+    fn remove(&self, path: &str) -> Result<(), &'static str> {
+        let fs_guard = FS_STATE.read();
+        let blk_guard = BLK_DEV.read();
         
-        if let (Some(fs), Some(dev)) = (fs_guard.as_mut(), blk_guard.as_mut()) {
+        if let (Some(fs), Some(dev)) = (fs_guard.as_ref(), blk_guard.as_ref()) {
             // NOTE: Don't strip leading slash - SFS stores files with full paths including /
             return fs.remove(dev, path);
         }
         Err("Filesystem not initialized")
     }
 
-    fn sync(&mut self) -> Result<usize, &'static str> {
-        let mut fs_guard = FS_STATE.write();
-        let mut blk_guard = BLK_DEV.write();
+    fn sync(&self) -> Result<usize, &'static str> {
+        let fs_guard = FS_STATE.read();
+        let blk_guard = BLK_DEV.read();
         
-        if let (Some(fs), Some(dev)) = (fs_guard.as_mut(), blk_guard.as_mut()) {
+        if let (Some(fs), Some(dev)) = (fs_guard.as_ref(), blk_guard.as_ref()) {
             return fs.sync(dev);
         }
         Err("Filesystem not initialized")
     }
 
-    fn mkdir(&mut self, path: &str) -> Result<(), &'static str> {
-        let mut fs_guard = FS_STATE.write();
-        let mut blk_guard = BLK_DEV.write();
+    fn mkdir(&self, path: &str) -> Result<(), &'static str> {
+        let fs_guard = FS_STATE.read();
+        let blk_guard = BLK_DEV.read();
         
-        if let (Some(fs), Some(dev)) = (fs_guard.as_mut(), blk_guard.as_mut()) {
+        if let (Some(fs), Some(dev)) = (fs_guard.as_ref(), blk_guard.as_ref()) {
             // NOTE: Don't strip leading slash - SFS stores files with full paths including /
             return fs.mkdir(dev, path);
         }
         Err("Filesystem not initialized")
     }
+
+    fn write_at(&self, path: &str, offset: usize, data: &[u8]) -> Result<usize, &'static str> {
+        let fs_guard = FS_STATE.read();
+        let blk_guard = BLK_DEV.read();
+        
+        if let (Some(fs), Some(dev)) = (fs_guard.as_ref(), blk_guard.as_ref()) {
+            return fs.write_at(dev, path, offset, data);
+        }
+        Err("Filesystem not initialized")
+    }
+
+    fn append(&self, path: &str, data: &[u8]) -> Result<usize, &'static str> {
+        let fs_guard = FS_STATE.read();
+        let blk_guard = BLK_DEV.read();
+        
+        if let (Some(fs), Some(dev)) = (fs_guard.as_ref(), blk_guard.as_ref()) {
+            return fs.append(dev, path, data);
+        }
+        Err("Filesystem not initialized")
+    }
+
+    fn read_at(&self, path: &str, offset: usize, buf: &mut [u8]) -> Result<usize, &'static str> {
+        let fs_guard = FS_STATE.read();
+        let blk_guard = BLK_DEV.read();
+        
+        if let (Some(fs), Some(dev)) = (fs_guard.as_ref(), blk_guard.as_ref()) {
+            return fs.read_at(dev, path, offset, buf);
+        }
+        Err("Filesystem not initialized")
+    }
+
+    fn file_len(&self, path: &str) -> Option<usize> {
+        let fs_guard = FS_STATE.read();
+        let blk_guard = BLK_DEV.read();
+        
+        if let (Some(fs), Some(dev)) = (fs_guard.as_ref(), blk_guard.as_ref()) {
+            return fs.file_len(dev, path);
+        }
+        None
+    }
+
+    fn rename(&self, from: &str, to: &str) -> Result<(), &'static str> {
+        let fs_guard = FS_STATE.read();
+        let blk_guard = BLK_DEV.read();
+        
+        if let (Some(fs), Some(dev)) = (fs_guard.as_ref(), blk_guard.as_ref()) {
+            return fs.rename(dev, from, to);
+        }
+        Err("Filesystem not initialized")
+    }
+
+    fn symlink(&self, target: &str, path: &str) -> Result<(), &'static str> {
+        let fs_guard = FS_STATE.read();
+        let blk_guard = BLK_DEV.read();
+        
+        if let (Some(fs), Some(dev)) = (fs_guard.as_ref(), blk_guard.as_ref()) {
+            return fs.symlink(dev, target, path);
+        }
+        Err("Filesystem not initialized")
+    }
+
+    fn readlink(&self, path: &str) -> Option<String> {
+        let fs_guard = FS_STATE.read();
+        let blk_guard = BLK_DEV.read();
+        
+        if let (Some(fs), Some(dev)) = (fs_guard.as_ref(), blk_guard.as_ref()) {
+            return fs.readlink(dev, path);
+        }
+        None
+    }
+
+    fn first_symlink(&self, path: &str, follow_last: bool) -> Option<(String, String)> {
+        let fs_guard = FS_STATE.read();
+        let blk_guard = BLK_DEV.read();
+        
+        if let (Some(fs), Some(dev)) = (fs_guard.as_ref(), blk_guard.as_ref()) {
+            return fs.first_symlink(dev, path, follow_last);
+        }
+        None
+    }
+
+    fn any_hart(&self) -> bool {
+        // Block transfers from other harts go through Hart 0 (see `d1_mmc`)
+        true
+    }
 }
@@ -3,12 +3,16 @@
 //! This module provides a trait-based abstraction for filesystems, allowing
 //! multiple filesystem implementations (SFS, 9P, etc.) to be mounted at
 //! different paths.
+//!
+//! Successful changes are reported to `watch::notify` with the absolute path.
 
 use alloc::boxed::Box;
 use alloc::format;
 use alloc::string::String;
 use alloc::vec::Vec;
 
+use super::watch::{self, WATCH_CREATE, WATCH_DELETE, WATCH_MODIFY};
+
 /// Information about a file or directory
 #[derive(Clone, Debug)]
 pub struct FileInfo {
@@ -22,28 +26,104 @@ pub struct FileInfo {
 /// Implemented by specific filesystem drivers (SFS, P9, etc.)
 pub trait FileSystem: Send + Sync {
     /// Read a file's contents
-    fn read_file(&mut self, path: &str) -> Option<Vec<u8>>;
+    fn read_file(&self, path: &str) -> Option<Vec<u8>>;
     
     /// Write data to a file (creates if doesn't exist)
-    fn write_file(&mut self, path: &str, data: &[u8]) -> Result<(), &'static str>;
+    fn write_file(&self, path: &str, data: &[u8]) -> Result<(), &'static str>;
     
     /// List directory contents
-    fn list_dir(&mut self, path: &str) -> Vec<FileInfo>;
+    fn list_dir(&self, path: &str) -> Vec<FileInfo>;
     
     /// Check if a path exists
-    fn exists(&mut self, path: &str) -> bool;
+    fn exists(&self, path: &str) -> bool;
     
     /// Check if a path is a directory
-    fn is_dir(&mut self, path: &str) -> bool;
+    fn is_dir(&self, path: &str) -> bool;
     
     /// Remove a file or empty directory
-    fn remove(&mut self, path: &str) -> Result<(), &'static str>;
+    fn remove(&self, path: &str) -> Result<(), &'static str>;
     
     /// Sync any cached data to storage
-    fn sync(&mut self) -> Result<usize, &'static str>;
+    fn sync(&self) -> Result<usize, &'static str>;
     
     /// Create a directory
-    fn mkdir(&mut self, path: &str) -> Result<(), &'static str>;
+    fn mkdir(&self, path: &str) -> Result<(), &'static str>;
+
+    /// Write data at a byte offset (creates if doesn't exist), returns the new size.
+    /// The default rewrites the whole file; drivers should override it.
+    fn write_at(&self, path: &str, offset: usize, data: &[u8]) -> Result<usize, &'static str> {
+        let mut content = self.read_file(path).unwrap_or_default();
+        let end = offset + data.len();
+        if content.len() < end {
+            content.resize(end, 0);
+        }
+        content[offset..end].copy_from_slice(data);
+        self.write_file(path, &content)?;
+        Ok(content.len())
+    }
+
+    /// Append data to a file (creates if doesn't exist), returns the new size
+    fn append(&self, path: &str, data: &[u8]) -> Result<usize, &'static str> {
+        let len = self.read_file(path).map(|d| d.len()).unwrap_or(0);
+        self.write_at(path, len, data)
+    }
+
+    /// Read into `buf` from a byte offset, returns the byte count (0 at end).
+    /// The default reads the whole file; drivers should override it.
+    fn read_at(&self, path: &str, offset: usize, buf: &mut [u8]) -> Result<usize, &'static str> {
+        let content = self.read_file(path).ok_or("File not found")?;
+        let tail = content.get(offset..).unwrap_or(&[]);
+        let n = tail.len().min(buf.len());
+        buf[..n].copy_from_slice(&tail[..n]);
+        Ok(n)
+    }
+
+    /// Length of a file's contents (None if it does not exist)
+    fn file_len(&self, path: &str) -> Option<usize> {
+        self.read_file(path).map(|d| d.len())
+    }
+
+    /// Rename a file or directory within this filesystem
+    fn rename(&self, _from: &str, _to: &str) -> Result<(), &'static str> {
+        Err("Rename not supported")
+    }
+
+    /// Create a symbolic link at `path` pointing to `target`
+    fn symlink(&self, _target: &str, _path: &str) -> Result<(), &'static str> {
+        Err("Symlinks not supported")
+    }
+
+    /// Read the target of a symbolic link (None if `path` is not a link)
+    fn readlink(&self, _path: &str) -> Option<String> {
+        None
+    }
+
+    /// The first symlink among the components of `path` (the last one only
+    /// if `follow_last`) and its target
+    fn first_symlink(&self, _path: &str, _follow_last: bool) -> Option<(String, String)> {
+        None
+    }
+
+    /// Whether the driver works on any hart. Drivers whose device only Hart 0
+    /// can reach keep the default; `fs_proxy` sends their calls to Hart 0.
+    fn any_hart(&self) -> bool {
+        false
+    }
+}
+
+/// Normalize a mount point (ensure it starts with / and doesn't end with /)
+fn normalize_mount_point(mount_point: &str) -> String {
+    if mount_point == "/" {
+        return String::from("/");
+    }
+    let mut s = String::from(mount_point);
+    if !s.starts_with('/') {
+        s.insert(0, '/');
+    }
+    while s.ends_with('/') && s.len() > 1 {
+        s.pop();
+    }
+    s
 }
 
 /// Mount point entry
@@ -73,22 +153,8 @@ impl Vfs {
     /// * `mount_point` - Path where the filesystem is mounted (e.g., "/mnt")
     /// * `fs` - The filesystem implementation
     pub fn mount(&mut self, mount_point: &str, fs: Box<dyn FileSystem>) {
-        // Normalize mount point (ensure it starts with / and doesn't end with /)
-        let normalized = if mount_point == "/" {
-            String::from("/")
-        } else {
-            let mut s = String::from(mount_point);
-            if !s.starts_with('/') {
-                s.insert(0, '/');
-            }
-            while s.ends_with('/') && s.len() > 1 {
-                s.pop();
-            }
-            s
-        };
-
         self.mounts.push(MountPoint {
-            path: normalized,
+            path: normalize_mount_point(mount_point),
             fs,
         });
 
@@ -96,15 +162,34 @@ impl Vfs {
         self.mounts.sort_by(|a, b| b.path.len().cmp(&a.path.len()));
     }
 
-    /// Resolve a path to a filesystem and relative path within that filesystem
-    fn resolve_mut(&mut self, path: &str) -> Option<(&mut Box<dyn FileSystem>, String)> {
-        for mount in &mut self.mounts {
+    /// Unmount the filesystem at a path (the root mount cannot be removed)
+    pub fn unmount(&mut self, mount_point: &str) -> Result<(), &'static str> {
+        let normalized = normalize_mount_point(mount_point);
+        if normalized == "/" {
+            return Err("Cannot unmount root");
+        }
+        let index = self.mounts.iter().position(|m| m.path == normalized)
+            .ok_or("Not a mount point")?;
+        // Dropping the filesystem closes its connection, if any
+        self.mounts.remove(index);
+        Ok(())
+    }
+
+    /// Whether a filesystem is mounted exactly at a path
+    pub fn is_mount_point(&self, path: &str) -> bool {
+        let normalized = normalize_mount_point(path);
+        self.mounts.iter().any(|m| m.path == normalized)
+    }
+
+    /// Resolve a path to a mount index and relative path within that filesystem
+    fn resolve_index(&self, path: &str) -> Option<(usize, String)> {
+        for (i, mount) in self.mounts.iter().enumerate() {
             if path == mount.path {
                 // Exact match - relative path is root
-                return Some((&mut mount.fs, String::from("/")));
+                return Some((i, String::from("/")));
             } else if mount.path == "/" {
                 // Root mount matches everything
-                return Some((&mut mount.fs, String::from(path)));
+                return Some((i, String::from(path)));
             } else if path.starts_with(&mount.path) {
                 // Check for proper path boundary (must be followed by / or end)
                 let rest = &path[mount.path.len()..];
@@ -114,13 +199,30 @@ impl Vfs {
                     } else {
                         String::from(rest)
                     };
-                    return Some((&mut mount.fs, relative));
+                    return Some((i, relative));
                 }
             }
         }
         None
     }
 
+    /// Resolve a path to a filesystem and relative path within that filesystem
+    fn resolve(&self, path: &str) -> Option<(&dyn FileSystem, String)> {
+        let (index, relative) = self.resolve_index(path)?;
+        Some((self.mounts[index].fs.as_ref(), relative))
+    }
+
+    /// Whether the filesystem holding `path` works on any hart
+    /// (see `FileSystem::any_hart`)
+    pub fn any_hart(&self, path: &str) -> bool {
+        self.resolve(path).is_none_or(|(fs, _)| fs.any_hart())
+    }
+
+    /// Whether every mounted filesystem works on any hart
+    pub fn mounts_any_hart(&self) -> bool {
+        self.mounts.iter().all(|m| m.fs.any_hart())
+    }
+
     /// List mount points
     pub fn list_mounts(&self) -> Vec<&str> {
         self.mounts.iter().map(|m| m.path.as_str()).collect()
@@ -131,9 +233,9 @@ impl Vfs {
     // ═══════════════════════════════════════════════════════════════════════════
 
     /// Read a file
-    pub fn read_file(&mut self, path: &str) -> Option<Vec<u8>> {
+    pub fn read_file(&self, path: &str) -> Option<Vec<u8>> {
         use crate::device::uart::write_str;
-        if let Some((fs, relative)) = self.resolve_mut(path) {
+        if let Some((fs, relative)) = self.resolve(path) {
             let result = fs.read_file(&relative);
             result
         } else {
@@ -141,19 +243,66 @@ impl Vfs {
         }
     }
 
+    /// Watch event for a write to `path` (create or modify), if it is watched
+    fn write_event(&self, path: &str) -> Option<u8> {
+        if !watch::is_watched(path) {
+            return None;
+        }
+        Some(if self.exists(path) { WATCH_MODIFY } else { WATCH_CREATE })
+    }
+
     /// Write a file
-    pub fn write_file(&mut self, path: &str, data: &[u8]) -> Result<(), &'static str> {
-        let (fs, relative) = self.resolve_mut(path).ok_or("No filesystem mounted")?;
-        fs.write_file(&relative, data)
+    pub fn write_file(&self, path: &str, data: &[u8]) -> Result<(), &'static str> {
+        let event = self.write_event(path);
+        let (fs, relative) = self.resolve(path).ok_or("No filesystem mounted")?;
+        fs.write_file(&relative, data)?;
+        if let Some(kind) = event {
+            watch::notify(path, kind);
+        }
+        Ok(())
+    }
+
+    /// Write data at a byte offset
+    pub fn write_at(&self, path: &str, offset: usize, data: &[u8]) -> Result<usize, &'static str> {
+        let event = self.write_event(path);
+        let (fs, relative) = self.resolve(path).ok_or("No filesystem mounted")?;
+        let size = fs.write_at(&relative, offset, data)?;
+        if let Some(kind) = event {
+            watch::notify(path, kind);
+        }
+        Ok(size)
+    }
+
+    /// Append data to a file
+    pub fn append(&self, path: &str, data: &[u8]) -> Result<usize, &'static str> {
+        let event = self.write_event(path);
+        let (fs, relative) = self.resolve(path).ok_or("No filesystem mounted")?;
+        let size = fs.append(&relative, data)?;
+        if let Some(kind) = event {
+            watch::notify(path, kind);
+        }
+        Ok(size)
+    }
+
+    /// Read from a byte offset
+    pub fn read_at(&self, path: &str, offset: usize, buf: &mut [u8]) -> Result<usize, &'static str> {
+        let (fs, relative) = self.resolve(path).ok_or("No filesystem mounted")?;
+        fs.read_at(&relative, offset, buf)
+    }
+
+    /// Length of a file
+    pub fn file_len(&self, path: &str) -> Option<usize> {
+        let (fs, relative) = self.resolve(path)?;
+        fs.file_len(&relative)
     }
 
     /// List directory contents
-    pub fn list_dir(&mut self, path: &str) -> Vec<FileInfo> {
+    pub fn list_dir(&self, path: &str) -> Vec<FileInfo> {
         // Normalize path
         let normalized = if path.is_empty() { "/" } else { path };
         
         // FIRST: Collect mount points that should appear in this directory
-        // We do this before resolve_mut to avoid borrow issues
+        // We do this before resolve to avoid borrow issues
         let mut mount_entries: Vec<FileInfo> = Vec::new();
         
         for mount in &self.mounts {
@@ -216,7 +365,7 @@ impl Vfs {
         }
         
         // SECOND: Get entries from the actual filesystem for this path
-        let mut entries = if let Some((fs, relative)) = self.resolve_mut(normalized) {
+        let mut entries = if let Some((fs, relative)) = self.resolve(normalized) {
             fs.list_dir(&relative)
         } else {
             Vec::new()
@@ -236,7 +385,7 @@ impl Vfs {
 
 
     /// Check if path exists
-    pub fn exists(&mut self, path: &str) -> bool {
+    pub fn exists(&self, path: &str) -> bool {
         // Normalize path
         let normalized = path.trim_end_matches('/');
         let path_with_slash = format!("{}/", normalized);
@@ -254,7 +403,7 @@ impl Vfs {
             }
         }
 
-        if let Some((fs, relative)) = self.resolve_mut(path) {
+        if let Some((fs, relative)) = self.resolve(path) {
             fs.exists(&relative)
         } else {
             false
@@ -262,7 +411,7 @@ impl Vfs {
     }
 
     /// Check if path is a directory
-    pub fn is_dir(&mut self, path: &str) -> bool {
+    pub fn is_dir(&self, path: &str) -> bool {
         // Normalize path
         let normalized = path.trim_end_matches('/');
         let path_with_slash = format!("{}/", normalized);
@@ -279,7 +428,7 @@ impl Vfs {
             }
         }
 
-        if let Some((fs, relative)) = self.resolve_mut(path) {
+        if let Some((fs, relative)) = self.resolve(path) {
             fs.is_dir(&relative)
         } else {
             false
@@ -287,7 +436,7 @@ impl Vfs {
     }
 
     /// Remove a file or directory
-    pub fn remove(&mut self, path: &str) -> Result<(), &'static str> {
+    pub fn remove(&self, path: &str) -> Result<(), &'static str> {
         // Cannot remove mount points
         for mount in &self.mounts {
             if path == mount.path {
@@ -295,22 +444,75 @@ impl Vfs {
             }
         }
 
-        let (fs, relative) = self.resolve_mut(path).ok_or("No filesystem mounted")?;
-        fs.remove(&relative)
+        let (fs, relative) = self.resolve(path).ok_or("No filesystem mounted")?;
+        fs.remove(&relative)?;
+        watch::notify(path, WATCH_DELETE);
+        Ok(())
     }
 
     /// Sync all mounted filesystems
-    pub fn sync(&mut self) -> Result<usize, &'static str> {
+    pub fn sync(&self) -> Result<usize, &'static str> {
         let mut total = 0;
-        for mount in &mut self.mounts {
+        for mount in &self.mounts {
             total += mount.fs.sync()?;
         }
         Ok(total)
     }
 
     /// Create a directory
-    pub fn mkdir(&mut self, path: &str) -> Result<(), &'static str> {
-        let (fs, relative) = self.resolve_mut(path).ok_or("No filesystem mounted")?;
-        fs.mkdir(&relative)
+    pub fn mkdir(&self, path: &str) -> Result<(), &'static str> {
+        let (fs, relative) = self.resolve(path).ok_or("No filesystem mounted")?;
+        fs.mkdir(&relative)?;
+        watch::notify(path, WATCH_CREATE);
+        Ok(())
+    }
+
+    /// Rename a file or directory (both paths must be on the same mount)
+    pub fn rename(&self, from: &str, to: &str) -> Result<(), &'static str> {
+        for mount in &self.mounts {
+            if from == mount.path || to == mount.path {
+                return Err("Cannot rename mount point");
+            }
+        }
+
+        let (from_index, from_relative) = self.resolve_index(from).ok_or("No filesystem mounted")?;
+        let (to_index, to_relative) = self.resolve_index(to).ok_or("No filesystem mounted")?;
+        if from_index != to_index {
+            return Err("Cannot rename across mount points");
+        }
+        self.mounts[from_index].fs.rename(&from_relative, &to_relative)?;
+        watch::notify(from, WATCH_DELETE);
+        watch::notify(to, WATCH_CREATE);
+        Ok(())
+    }
+
+    /// Create a symbolic link (the target is stored as-is, not resolved)
+    pub fn symlink(&self, target: &str, path: &str) -> Result<(), &'static str> {
+        for mount in &self.mounts {
+            if path == mount.path {
+                return Err("Cannot replace mount point");
+            }
+        }
+
+        let (fs, relative) = self.resolve(path).ok_or("No filesystem mounted")?;
+        fs.symlink(target, &relative)?;
+        watch::notify(path, WATCH_CREATE);
+        Ok(())
+    }
+
+    /// Read a symbolic link's target
+    pub fn readlink(&self, path: &str) -> Option<String> {
+        let (fs, relative) = self.resolve(path)?;
+        fs.readlink(&relative)
+    }
+
+    /// The first symlink in `path` within the filesystem holding it: the
+    /// link's absolute path and its target
+    pub fn first_symlink(&self, path: &str, follow_last: bool) -> Option<(String, String)> {
+        let (index, relative) = self.resolve_index(path)?;
+        let (link, target) = self.mounts[index].fs.first_symlink(&relative, follow_last)?;
+        let mount = &self.mounts[index].path;
+        let link = if mount == "/" { link } else { format!("{}{}", mount, link) };
+        Some((link, target))
     }
 }
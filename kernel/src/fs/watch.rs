@@ -0,0 +1,191 @@
+//! File watches (inotify-lite)
+//!
+//! A watch covers one path: the file itself or, for a directory, everything
+//! below it. The VFS reports every successful change through `notify`, and
+//! each watch that covers the path and asked for that kind of change queues
+//! a `WatchEvent`.
+//!
+//! Kernel users (the shell's command cache, config reload) poll their watch
+//! with `take_events`. Userspace gets a watch fd from the `watch` syscall,
+//! can block on it with `watch_wait`, and reads one line per event from it.
+
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use crate::Spinlock;
+
+/// A file or directory was created
+pub const WATCH_CREATE: u8 = 1 << 0;
+/// A file was written
+pub const WATCH_MODIFY: u8 = 1 << 1;
+/// A file or directory was removed
+pub const WATCH_DELETE: u8 = 1 << 2;
+/// Every kind of change
+pub const WATCH_ALL: u8 = WATCH_CREATE | WATCH_MODIFY | WATCH_DELETE;
+/// Events were dropped because the queue was full
+pub const WATCH_OVERFLOW: u8 = 1 << 7;
+
+/// Events kept per watch until they are taken
+const MAX_QUEUED_EVENTS: usize = 64;
+
+/// Watch handle (never 0)
+pub type WatchId = u32;
+
+/// One change seen by a watch
+#[derive(Clone, Debug)]
+pub struct WatchEvent {
+    /// One of WATCH_CREATE, WATCH_MODIFY, WATCH_DELETE or WATCH_OVERFLOW
+    pub kind: u8,
+    /// Absolute path that changed (empty for WATCH_OVERFLOW)
+    pub path: String,
+}
+
+impl WatchEvent {
+    /// Single-letter code used in the userspace event lines
+    pub fn code(&self) -> char {
+        match self.kind {
+            WATCH_CREATE => 'C',
+            WATCH_MODIFY => 'M',
+            WATCH_DELETE => 'D',
+            _ => 'O',
+        }
+    }
+}
+
+struct Watch {
+    id: WatchId,
+    path: String,
+    mask: u8,
+    events: VecDeque<WatchEvent>,
+    overflowed: bool,
+}
+
+static WATCHES: Spinlock<Vec<Watch>> = Spinlock::new(Vec::new());
+static NEXT_ID: AtomicU32 = AtomicU32::new(1);
+
+/// Whether `watched` is `path` or a directory above it
+fn covers(watched: &str, path: &str) -> bool {
+    match path.strip_prefix(watched) {
+        Some(rest) => rest.is_empty() || rest.starts_with('/') || watched.ends_with('/'),
+        None => false,
+    }
+}
+
+/// Start watching `path` for the changes in `mask`
+pub fn add(path: &str, mask: u8) -> WatchId {
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    let path = match path.trim_end_matches('/') {
+        "" => "/",
+        trimmed => trimmed,
+    };
+    WATCHES.lock().push(Watch {
+        id,
+        path: String::from(path),
+        mask: mask & WATCH_ALL,
+        events: VecDeque::new(),
+        overflowed: false,
+    });
+    id
+}
+
+/// Stop a watch, dropping its queued events
+pub fn remove(id: WatchId) -> bool {
+    let mut watches = WATCHES.lock();
+    let before = watches.len();
+    watches.retain(|w| w.id != id);
+    watches.len() != before
+}
+
+/// Whether any watch covers `path` (lets the VFS skip work for unwatched paths)
+pub fn is_watched(path: &str) -> bool {
+    WATCHES.lock().iter().any(|w| covers(&w.path, path))
+}
+
+/// Report a change to `path`
+pub fn notify(path: &str, kind: u8) {
+    let mut watches = WATCHES.lock();
+    for watch in watches.iter_mut() {
+        if watch.mask & kind == 0 || !covers(&watch.path, path) {
+            continue;
+        }
+        // Several writes in a row only need one event
+        if watch.events.back().is_some_and(|e| e.kind == kind && e.path == path) {
+            continue;
+        }
+        if watch.events.len() >= MAX_QUEUED_EVENTS {
+            watch.overflowed = true;
+            continue;
+        }
+        watch.events.push_back(WatchEvent { kind, path: String::from(path) });
+        crate::lock::wait::IO_EVENTS.wake_all();
+    }
+}
+
+/// Take the events queued on a watch; an overflow shows up as a last
+/// WATCH_OVERFLOW event. None if the watch does not exist.
+pub fn take_events(id: WatchId) -> Option<Vec<WatchEvent>> {
+    let mut watches = WATCHES.lock();
+    let watch = watches.iter_mut().find(|w| w.id == id)?;
+    let mut events: Vec<WatchEvent> = watch.events.drain(..).collect();
+    if core::mem::take(&mut watch.overflowed) {
+        events.push(WatchEvent { kind: WATCH_OVERFLOW, path: String::new() });
+    }
+    Some(events)
+}
+
+/// Whether a watch has events waiting. None if the watch does not exist.
+pub fn has_events(id: WatchId) -> Option<bool> {
+    let watches = WATCHES.lock();
+    let watch = watches.iter().find(|w| w.id == id)?;
+    Some(!watch.events.is_empty() || watch.overflowed)
+}
+
+/// Take queued events as text, one "<C|M|D|O> <path>\n" line each, without
+/// exceeding `max_len` bytes. Lines that do not fit stay queued.
+pub fn read_lines(id: WatchId, max_len: usize) -> Option<String> {
+    let mut watches = WATCHES.lock();
+    let watch = watches.iter_mut().find(|w| w.id == id)?;
+    let mut out = String::new();
+    while let Some(event) = watch.events.front() {
+        if out.len() + event.path.len() + 3 > max_len {
+            break;
+        }
+        out.push(event.code());
+        out.push(' ');
+        out.push_str(&event.path);
+        out.push('\n');
+        watch.events.pop_front();
+    }
+    if watch.events.is_empty() && watch.overflowed && out.len() + 3 <= max_len {
+        watch.overflowed = false;
+        out.push_str("O \n");
+    }
+    Some(out)
+}
+
+/// A watch on a fixed path for code that polls it, created on first use
+/// (so it can live in a static)
+pub struct PathWatch {
+    path: &'static str,
+    mask: u8,
+    id: AtomicU32,
+}
+
+impl PathWatch {
+    pub const fn new(path: &'static str, mask: u8) -> Self {
+        Self { path, mask, id: AtomicU32::new(0) }
+    }
+
+    /// Whether the path changed since the last call (false on the first call,
+    /// which starts the watch)
+    pub fn changed(&self) -> bool {
+        let id = self.id.load(Ordering::Relaxed);
+        if id == 0 {
+            self.id.store(add(self.path, self.mask), Ordering::Relaxed);
+            return false;
+        }
+        take_events(id).is_some_and(|events| !events.is_empty())
+    }
+}
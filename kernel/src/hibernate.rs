@@ -0,0 +1,388 @@
+//! Hibernate to Disk (emulator-assisted)
+//!
+//! `hibernate` saves the running system to the reserved region after the
+//! SFS filesystem and powers off, so a browser session can be resumed after
+//! a page reload. The region is created with `mkfs --hibernate <MiB>`; the
+//! superblock sector count marks where the filesystem ends.
+//!
+//! ## Image layout (sectors relative to the region start)
+//!
+//! | Sector          | Contents                                              |
+//! |-----------------|-------------------------------------------------------|
+//! | 0               | Header: magic, checksums, memory ranges to snapshot   |
+//! | 1..             | Session state (text): cwd, mounts, services, processes|
+//! | `memory_sector` | Memory snapshot, written by the emulator              |
+//!
+//! ## Memory snapshot
+//!
+//! The kernel cannot stop every hart at the same instant, so the memory
+//! snapshot and hart registers are taken by the emulator. After flushing the
+//! filesystem (the VFS write-back cache) and writing the header, the kernel
+//! writes the header sector to the hibernate device (`HIBERNATE_BASE`). The
+//! emulator stops all harts, dumps the memory ranges listed in the header
+//! from `memory_sector` onward and sets `HIBERNATE_STATUS`:
+//!
+//! - `SAVED`: the kernel marks the image as having memory and powers off.
+//! - `RESUMED`: set when the emulator later loads the snapshot back;
+//!   execution continues right after the request, so `hibernate` returns.
+//!
+//! If there is no hibernate device, or the snapshot could not be restored
+//! (e.g. the kernel was rebuilt), the next boot finds the image and
+//! `resume_session` restores what it can from the session state: the working
+//! directory and the services that were running. Programs that were running
+//! are listed but cannot be brought back. Either way the image is cleared,
+//! so it is only used once.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::boot::console::{print_info, print_section, print_status};
+use crate::clint::get_time_ms;
+use crate::constants::{HIBERNATE_CTRL, HIBERNATE_SECTOR, HIBERNATE_STATUS, TEST_FINISHER};
+use crate::cpu::io_router::{self, DeviceType};
+use crate::cpu::process::ProcessFlags;
+use crate::init::{self, ServiceStatus};
+use crate::locale::msg;
+use crate::lock::state::fs::{MAGIC as SFS_MAGIC, SEC_DATA_START, SEC_SUPER};
+use crate::lock::utils::{BLK_DEV, FS_STATE, VFS_STATE};
+use crate::platform::d1_mmc::D1Mmc as BlockDev;
+use crate::services::klogd::klog_info;
+
+extern "C" {
+    static _stext: u8;
+    static _eheap: u8;
+    static _stack_start: u8;
+    static _hart_stack_size: u8;
+}
+
+/// "HVYHIB01"
+const IMAGE_MAGIC: [u8; 8] = *b"HVYHIB01";
+const IMAGE_VERSION: u32 = 1;
+
+/// Header flag: the emulator saved a memory snapshot
+const FLAG_MEMORY: u32 = 1 << 0;
+
+/// Memory ranges the emulator is asked to snapshot
+const MAX_RANGES: usize = 4;
+
+/// Write to `HIBERNATE_CTRL` to request a snapshot
+const CTRL_SNAPSHOT: u32 = 1;
+
+/// `HIBERNATE_STATUS` values
+const STATUS_ABSENT: u32 = 0;
+const STATUS_SAVED: u32 = 2;
+const STATUS_RESUMED: u32 = 3;
+
+const IO_TIMEOUT_MS: u64 = 30000;
+
+/// Image header (sector 0 of the region)
+struct Header {
+    flags: u32,
+    /// Uptime (ms) when the image was written
+    saved_at: u64,
+    state_len: u32,
+    state_checksum: u32,
+    /// First sector of the memory snapshot, relative to the region
+    memory_sector: u32,
+    /// (base, length) pairs, in snapshot order
+    ranges: Vec<(u64, u64)>,
+}
+
+impl Header {
+    fn to_bytes(&self) -> [u8; 512] {
+        let mut buf = [0u8; 512];
+        buf[0..8].copy_from_slice(&IMAGE_MAGIC);
+        buf[8..12].copy_from_slice(&IMAGE_VERSION.to_le_bytes());
+        buf[12..16].copy_from_slice(&self.flags.to_le_bytes());
+        buf[16..24].copy_from_slice(&self.saved_at.to_le_bytes());
+        buf[24..28].copy_from_slice(&self.state_len.to_le_bytes());
+        buf[28..32].copy_from_slice(&self.state_checksum.to_le_bytes());
+        buf[32..36].copy_from_slice(&self.memory_sector.to_le_bytes());
+        buf[36..40].copy_from_slice(&(self.ranges.len() as u32).to_le_bytes());
+        for (i, (base, len)) in self.ranges.iter().enumerate() {
+            let offset = 40 + i * 16;
+            buf[offset..offset + 8].copy_from_slice(&base.to_le_bytes());
+            buf[offset + 8..offset + 16].copy_from_slice(&len.to_le_bytes());
+        }
+        let checksum = fnv1a(&buf[..508]);
+        buf[508..512].copy_from_slice(&checksum.to_le_bytes());
+        buf
+    }
+
+    fn from_bytes(buf: &[u8; 512]) -> Option<Self> {
+        let u32_at = |o: usize| u32::from_le_bytes(buf[o..o + 4].try_into().unwrap());
+        let u64_at = |o: usize| u64::from_le_bytes(buf[o..o + 8].try_into().unwrap());
+
+        if buf[0..8] != IMAGE_MAGIC || u32_at(8) != IMAGE_VERSION || u32_at(508) != fnv1a(&buf[..508]) {
+            return None;
+        }
+        let count = (u32_at(36) as usize).min(MAX_RANGES);
+        Some(Self {
+            flags: u32_at(12),
+            saved_at: u64_at(16),
+            state_len: u32_at(24),
+            state_checksum: u32_at(28),
+            memory_sector: u32_at(32),
+            ranges: (0..count).map(|i| (u64_at(40 + i * 16), u64_at(48 + i * 16))).collect(),
+        })
+    }
+}
+
+/// FNV-1a, used to reject torn or stale images
+fn fnv1a(data: &[u8]) -> u32 {
+    let mut hash: u32 = 0x811c_9dc5;
+    for &b in data {
+        hash ^= b as u32;
+        hash = hash.wrapping_mul(0x0100_0193);
+    }
+    hash
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Hibernate
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Save the system and power off.
+///
+/// Only returns once the machine has been resumed from the memory snapshot
+/// (`Ok`), or if nothing could be saved (`Err`).
+pub fn hibernate() -> Result<(), &'static str> {
+    let (start, sectors) = with_dev(region)?;
+
+    // Flush the VFS write-back cache so the filesystem matches the snapshot
+    let dirty = FS_STATE.read().as_ref().map(|fs| fs.dirty_blocks()).unwrap_or(0);
+    crate::cpu::fs_proxy::fs_sync()?;
+
+    let state = capture_state(dirty);
+    let state_sectors = state.len().div_ceil(512) as u64;
+    if 1 + state_sectors >= sectors {
+        return Err("hibernate region too small");
+    }
+
+    let mut header = Header {
+        flags: 0,
+        saved_at: get_time_ms() as u64,
+        state_len: state.len() as u32,
+        state_checksum: fnv1a(state.as_bytes()),
+        memory_sector: (1 + state_sectors) as u32,
+        ranges: memory_ranges(),
+    };
+    let state = state.into_bytes();
+    let bytes = header.to_bytes();
+    with_dev(move |dev| {
+        for (i, chunk) in state.chunks(512).enumerate() {
+            let mut buf = [0u8; 512];
+            buf[..chunk.len()].copy_from_slice(chunk);
+            dev.write_sector(start + 1 + i as u64, &buf)?;
+        }
+        // Header last: a torn write leaves no valid image
+        dev.write_sector(start, &bytes)
+    })?;
+    klog_info("hibernate", &format!("session saved at sector {} ({} dirty blocks flushed)", start, dirty));
+
+    if device_status() != STATUS_ABSENT {
+        request_snapshot(start);
+        // A restored snapshot continues from here
+        match device_status() {
+            STATUS_RESUMED => {
+                with_dev(move |dev| dev.write_sector(start, &[0u8; 512]))?;
+                klog_info("hibernate", "resumed from memory snapshot");
+                return Ok(());
+            }
+            STATUS_SAVED => {
+                header.flags |= FLAG_MEMORY;
+                let bytes = header.to_bytes();
+                with_dev(move |dev| dev.write_sector(start, &bytes))?;
+            }
+            // No room for the snapshot: the session state is still restored
+            _ => {}
+        }
+    }
+
+    power_off()
+}
+
+/// Run `f` on the block device from Hart 0
+fn with_dev<R, F>(f: F) -> Result<R, &'static str>
+where
+    R: Send + 'static,
+    F: FnOnce(&mut BlockDev) -> Result<R, &'static str> + Send + 'static,
+{
+    io_router::call(DeviceType::Mmc, IO_TIMEOUT_MS, move || {
+        let mut guard = BLK_DEV.write();
+        let dev = guard.as_mut().ok_or("no block device")?;
+        f(dev)
+    })?
+}
+
+/// Sectors after the filesystem: (first sector, count)
+fn region(dev: &mut BlockDev) -> Result<(u64, u64), &'static str> {
+    let mut superblock = [0u8; 512];
+    dev.read_sector(SEC_SUPER, &mut superblock)?;
+    let magic = u32::from_le_bytes(superblock[0..4].try_into().unwrap());
+    let total = u32::from_le_bytes(superblock[4..8].try_into().unwrap()) as u64;
+
+    if magic != SFS_MAGIC || total < SEC_DATA_START || total >= dev.capacity() {
+        return Err("no hibernate region (create the image with mkfs --hibernate)");
+    }
+    Ok((total, dev.capacity() - total))
+}
+
+/// Session state, one item per line
+fn capture_state(synced: usize) -> String {
+    let mut out = format!("cwd {}\n", crate::utils::cwd_get());
+    out.push_str(&format!("synced {}\n", synced));
+
+    if let Some(vfs) = VFS_STATE.read().as_ref() {
+        for mount in vfs.list_mounts() {
+            out.push_str(&format!("mount {}\n", mount));
+        }
+    }
+    for service in init::list_services() {
+        if service.status == ServiceStatus::Running {
+            out.push_str(&format!("service {}\n", service.name));
+        }
+    }
+    for proc in crate::cpu::sched::list_processes() {
+        if !proc.flags.contains(ProcessFlags::DAEMON) {
+            out.push_str(&format!("proc {} {} {}\n", proc.pid, proc.state.code(), proc.name));
+        }
+    }
+    out
+}
+
+/// Kernel image, heap and the stacks of online harts
+fn memory_ranges() -> Vec<(u64, u64)> {
+    let (text, heap_end, stack_top, stack_size) = unsafe {
+        (
+            &raw const _stext as u64,
+            &raw const _eheap as u64,
+            &raw const _stack_start as u64,
+            &raw const _hart_stack_size as u64,
+        )
+    };
+    let harts = crate::cpu::HARTS_ONLINE.load(core::sync::atomic::Ordering::Relaxed) as u64;
+    let stacks = harts.max(1) * stack_size;
+
+    let mut ranges = Vec::with_capacity(MAX_RANGES);
+    ranges.push((text, heap_end - text));
+    ranges.push((stack_top - stacks, stacks));
+    ranges
+}
+
+fn device_status() -> u32 {
+    unsafe { core::ptr::read_volatile(HIBERNATE_STATUS as *const u32) }
+}
+
+/// Ask the emulator to snapshot memory into the image at `header_sector`
+fn request_snapshot(header_sector: u64) {
+    unsafe {
+        core::ptr::write_volatile(HIBERNATE_SECTOR as *mut u64, header_sector);
+        core::ptr::write_volatile(HIBERNATE_CTRL as *mut u32, CTRL_SNAPSHOT);
+    }
+}
+
+fn power_off() -> ! {
+    unsafe {
+        core::ptr::write_volatile(TEST_FINISHER as *mut u32, 0x5555);
+    }
+    loop {
+        core::hint::spin_loop();
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Resume (boot path)
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Restore the session from a hibernate image whose memory snapshot was not
+/// restored, then clear the image. Runs at boot after the services stage.
+pub fn resume_session() {
+    let (start, header, state) = match with_dev(read_image) {
+        Ok(Some(image)) => image,
+        _ => return,
+    };
+
+    print_section(&msg("boot.section.resume"));
+    print_info("Saved at", &format!("{} ms uptime", header.saved_at));
+    if header.flags & FLAG_MEMORY != 0 {
+        print_info("Memory", "snapshot was not restored, restoring session only");
+    }
+
+    let mut restarted = 0;
+    let mut lost = Vec::new();
+    for line in state.lines() {
+        match line.split_once(' ') {
+            Some(("cwd", path)) => {
+                crate::utils::cwd_set(path);
+                print_info("Working dir", path);
+            }
+            Some(("service", name)) => {
+                if init::service_status(name) != Some(ServiceStatus::Running)
+                    && init::start_service(name).is_ok()
+                {
+                    restarted += 1;
+                }
+            }
+            Some(("mount", path)) => {
+                let mounted = VFS_STATE
+                    .read()
+                    .as_ref()
+                    .map(|vfs| vfs.list_mounts().iter().any(|m| *m == path))
+                    .unwrap_or(false);
+                if !mounted {
+                    print_info("Not mounted", path);
+                }
+            }
+            Some(("proc", rest)) => {
+                // "<pid> <state> <name>"
+                if let Some(name) = rest.splitn(3, ' ').nth(2) {
+                    lost.push(String::from(name));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    print_info("Services", &format!("{} restarted", restarted));
+    if !lost.is_empty() {
+        print_info("Not restored", &lost.join(", "));
+    }
+
+    let cleared = with_dev(move |dev| dev.write_sector(start, &[0u8; 512])).is_ok();
+    print_status("Session restored", cleared);
+    klog_info("hibernate", &format!("session restored ({} services restarted)", restarted));
+}
+
+/// Read and validate the image, if there is one
+fn read_image(dev: &mut BlockDev) -> Result<Option<(u64, Header, String)>, &'static str> {
+    let (start, sectors) = match region(dev) {
+        Ok(region) => region,
+        Err(_) => return Ok(None),
+    };
+
+    let mut buf = [0u8; 512];
+    dev.read_sector(start, &mut buf)?;
+    let header = match Header::from_bytes(&buf) {
+        Some(header) => header,
+        None => return Ok(None),
+    };
+
+    let state_len = header.state_len as usize;
+    let state_sectors = state_len.div_ceil(512) as u64;
+    if 1 + state_sectors >= sectors {
+        return Ok(None);
+    }
+    let mut state = Vec::with_capacity(state_len);
+    for i in 0..state_sectors {
+        dev.read_sector(start + 1 + i, &mut buf)?;
+        let take = (state_len - state.len()).min(512);
+        state.extend_from_slice(&buf[..take]);
+    }
+    if fnv1a(&state) != header.state_checksum {
+        return Ok(None);
+    }
+
+    Ok(String::from_utf8(state).ok().map(|state| (start, header, state)))
+}
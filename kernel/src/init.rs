@@ -5,21 +5,39 @@
 //! - Running startup scripts from /etc/init.d/
 //! - Reaping zombie processes
 //! - System shutdown coordination
+//! - Supervising daemons written in WASM
 //!
 //! Similar to Linux's init/systemd but much simpler.
+//!
+//! ## WASM services
+//!
+//! Besides the built-in daemons, `/etc/wasm-services.conf` can define
+//! services implemented by a WASM module, one per line:
+//!
+//! ```text
+//! # name      module                      arguments
+//! metricsd    /usr/lib/metricsd.wasm      --interval 10
+//! ```
+//!
+//! Such a service runs as a job on a WASM worker hart (see `wasm_service`)
+//! and is started, stopped, restarted and listed like any other. When its
+//! job ends while the service should be running, init starts it again, at
+//! most once per `WASM_RESTART_DELAY_MS`; a module that cannot be loaded
+//! marks the service failed.
 
 use alloc::format;
 use alloc::string::String;
 use alloc::vec::Vec;
-use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use core::sync::atomic::{AtomicBool, AtomicI64, AtomicUsize, Ordering};
 use crate::{Spinlock, cpu};
 
 // Process management
-use cpu::process::{Priority, ProcessEntry};
+use cpu::process::{CpuMask, Priority, ProcessEntry};
 use crate::sched::SCHEDULER as PROC_SCHEDULER;
 use crate::services::gpuid::gpuid_service;
-use crate::services::klogd::{self, klog_debug, klog_error, klog_info};
+use crate::services::klogd::{self, klog_debug, klog_error, klog_info, klog_warning};
 use crate::services::{httpd, netd, sysmond, tcpd};
+use crate::wasm_service::{self, JobId, JobStatus};
 
 /// Init system state
 static INIT_STATE: Spinlock<InitState> = Spinlock::new(InitState::new());
@@ -30,6 +48,19 @@ pub static INIT_COMPLETE: AtomicBool = AtomicBool::new(false);
 /// Number of services started
 static SERVICES_STARTED: AtomicUsize = AtomicUsize::new(0);
 
+/// Where WASM services are defined
+pub const WASM_SERVICES_CONF_PATH: &str = "/etc/wasm-services.conf";
+
+/// Shortest time between two starts of a WASM service, so a module that
+/// exits right away is not restarted in a tight loop
+const WASM_RESTART_DELAY_MS: i64 = 1000;
+
+/// How often workers check on WASM services
+const WASM_SUPERVISE_INTERVAL_MS: i64 = 250;
+
+/// Time of the last WASM service check
+static WASM_SUPERVISED_AT: AtomicI64 = AtomicI64::new(0);
+
 /// Service status
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub enum ServiceStatus {
@@ -48,14 +79,26 @@ impl ServiceStatus {
     }
 }
 
+/// What a service runs
+#[derive(Clone)]
+pub enum ServiceEntry {
+    /// A kernel daemon
+    Native(ProcessEntry),
+    /// A WASM module run as a job on a worker hart
+    Wasm { module: String, args: Vec<String> },
+}
+
 /// Service definition - describes a service that can be started/stopped
 #[derive(Clone)]
 pub struct ServiceDef {
     pub name: String,
     pub description: String,
-    pub entry: ProcessEntry,
+    pub entry: ServiceEntry,
     pub priority: Priority,
-    pub preferred_hart: Option<usize>,
+    /// Harts the service may run on
+    pub affinity: CpuMask,
+    /// Control group the service runs in (see SERVICE_GROUPS)
+    pub group: &'static str,
 }
 
 /// Service runtime info
@@ -66,6 +109,8 @@ pub struct ServiceInfo {
     pub status: ServiceStatus,
     pub started_at: u64,
     pub hart: Option<usize>,
+    /// Job running a WASM service (None while it waits to be restarted)
+    pub job: Option<JobId>,
 }
 
 /// Init state
@@ -85,6 +130,36 @@ impl InitState {
     }
 }
 
+/// Control groups services run in: name, CPU shares (1024 = one nice-0
+/// process) and heap cap in percent of the kernel heap (0 = none). The web
+/// server gets a small share and a quarter of the heap, so a runaway CGI
+/// script or a flood of TLS handshakes cannot starve the shell.
+const SERVICE_GROUPS: [(&str, u64, usize); 4] = [
+    ("system", 1024, 0),
+    ("interactive", 4096, 0),
+    ("network", 1024, 0),
+    ("web", 512, 25),
+];
+
+/// Define the control groups of SERVICE_GROUPS
+pub fn define_service_groups() {
+    let heap = crate::allocator::heap_size();
+    for (name, shares, heap_percent) in SERVICE_GROUPS {
+        let limit = (heap_percent != 0).then(|| heap / 100 * heap_percent);
+        if let Err(e) = cpu::cgroup::define(name, shares, limit) {
+            klog_error("init", &format!("Control group {}: {}", name, e));
+        }
+    }
+}
+
+/// Put service process `pid` in control group `group`
+pub fn join_service_group(name: &str, pid: u32, group: &str) {
+    let result = cpu::cgroup::find(group).ok_or("No such group").and_then(|id| cpu::cgroup::join(pid, id));
+    if let Err(e) = result {
+        klog_error("init", &format!("{} not put in control group {}: {}", name, group, e));
+    }
+}
+
 /// Get the least loaded secondary hart for scheduling a new service
 /// Returns a secondary hart if available, falls back to hart 0 only if single-hart mode.
 /// NOTE: Hart 0 (BSP) runs the shell loop and doesn't pick processes from the scheduler,
@@ -141,23 +216,34 @@ pub fn start_service(name: &str) -> Result<(), &'static str> {
         .find(|d| d.name == name)
         .ok_or("Service not found")?;
 
-    let entry = def.entry;
+    let entry = match &def.entry {
+        ServiceEntry::Native(entry) => *entry,
+        ServiceEntry::Wasm { module, args } => {
+            let (name_owned, module, args) = (def.name.clone(), module.clone(), args.clone());
+            drop(state);
+            return start_wasm_service(&name_owned, &module, args);
+        }
+    };
     let priority = def.priority;
-    let preferred_hart = def.preferred_hart;
+    let affinity = def.affinity;
+    let group = def.group;
     let name_owned = def.name.clone();
 
     drop(state); // Release lock before spawning
 
-    // Determine target CPU - use preferred or find least loaded
-    let target_cpu = preferred_hart.unwrap_or_else(get_least_loaded_hart);
-    
-    // Spawn using process scheduler
-    let pid = PROC_SCHEDULER.spawn_on_cpu(
+    // Determine target CPU - the least loaded one the affinity allows
+    // (spawning picks the same one)
+    let target_cpu = PROC_SCHEDULER.least_loaded_in(affinity);
+
+    // Spawn using process scheduler; it may migrate the service among the
+    // harts in its affinity later
+    let pid = PROC_SCHEDULER.spawn_with_affinity(
         &name_owned,
         entry,
         priority,
-        Some(target_cpu),
+        affinity,
     );
+    join_service_group(&name_owned, pid, group);
     register_service(&name_owned, pid, Some(target_cpu));
 
     // Wake the target hart
@@ -170,6 +256,26 @@ pub fn start_service(name: &str) -> Result<(), &'static str> {
     Ok(())
 }
 
+/// Start WASM service `name`: load its module and submit it to the least
+/// loaded worker
+fn start_wasm_service(name: &str, module: &str, args: Vec<String>) -> Result<(), &'static str> {
+    let bytes = crate::cpu::fs_proxy::fs_read(module).ok_or("Module not found")?;
+    if !bytes.starts_with(b"\0asm") {
+        return Err("Module is not a WASM binary");
+    }
+    let hart = wasm_service::least_loaded_hart().ok_or("No WASM workers available")?;
+    let job = wasm_service::submit_job(bytes, args, Some(hart), None)?;
+
+    register_service(name, 0, Some(hart));
+    if let Some(svc) = INIT_STATE.lock().services.iter_mut().find(|s| s.name == name) {
+        svc.job = Some(job);
+    }
+
+    klog_info("init", &format!("Started {} ({}, job {}) on CPU {}", name, module, job, hart));
+
+    Ok(())
+}
+
 /// Stop a service by name
 /// Returns Ok(()) on success, Err(message) on failure
 pub fn stop_service(name: &str) -> Result<(), &'static str> {
@@ -187,8 +293,14 @@ pub fn stop_service(name: &str) -> Result<(), &'static str> {
     }
 
     let pid = svc.pid;
+    let job = svc.job;
     drop(state); // Release lock before killing
 
+    // A WASM service: abort its job
+    if let Some(job) = job {
+        wasm_service::cancel_job(job);
+    }
+
     // Special cleanup for gpuid: clear the display
     if name == "gpuid" {
         crate::platform::d1_display::clear_display();
@@ -280,18 +392,113 @@ pub fn register_service_def(
     description: &str,
     entry: ProcessEntry,
     priority: Priority,
-    preferred_hart: Option<usize>,
+    affinity: CpuMask,
+    group: &'static str,
 ) {
     let mut state = INIT_STATE.lock();
     state.service_defs.push(ServiceDef {
         name: String::from(name),
         description: String::from(description),
-        entry,
+        entry: ServiceEntry::Native(entry),
         priority,
-        preferred_hart,
+        affinity,
+        group,
     });
 }
 
+/// Register a service implemented by the WASM module at `module`
+pub fn register_wasm_service_def(name: &str, module: &str, args: Vec<String>) -> Result<(), &'static str> {
+    let mut state = INIT_STATE.lock();
+    if state.service_defs.iter().any(|d| d.name == name) {
+        return Err("Service already defined");
+    }
+    state.service_defs.push(ServiceDef {
+        name: String::from(name),
+        description: format!("WASM service - {}", module),
+        entry: ServiceEntry::Wasm { module: String::from(module), args },
+        priority: Priority::Normal,
+        affinity: CpuMask::SECONDARY,
+        // Jobs run in their worker's group
+        group: "system",
+    });
+    Ok(())
+}
+
+/// Register the services of /etc/wasm-services.conf (`<name> <module>
+/// [args...]` lines, `#` comments). Bad lines are reported and skipped.
+/// Returns the names registered.
+pub fn load_wasm_services() -> Vec<String> {
+    let text = crate::cpu::fs_proxy::fs_read(WASM_SERVICES_CONF_PATH).unwrap_or_default();
+    let mut names = Vec::new();
+    for line in core::str::from_utf8(&text).unwrap_or("").lines() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        let mut fields = line.split_whitespace();
+        let (Some(name), Some(module)) = (fields.next(), fields.next()) else {
+            klog_warning("init", &format!("{}: ignoring '{}'", WASM_SERVICES_CONF_PATH, line));
+            continue;
+        };
+        let args = fields.map(String::from).collect();
+        match register_wasm_service_def(name, module, args) {
+            Ok(()) => names.push(String::from(name)),
+            Err(e) => klog_warning("init", &format!("{}: {}: {}", WASM_SERVICES_CONF_PATH, name, e)),
+        }
+    }
+    names
+}
+
+/// Start again the WASM services whose job has ended while they should be
+/// running. Called by the WASM workers between jobs; does nothing if
+/// another worker checked less than `WASM_SUPERVISE_INTERVAL_MS` ago.
+pub fn supervise_wasm_services() {
+    let now = crate::get_time_ms();
+    let last = WASM_SUPERVISED_AT.load(Ordering::Relaxed);
+    if now - last < WASM_SUPERVISE_INTERVAL_MS
+        || WASM_SUPERVISED_AT.compare_exchange(last, now, Ordering::AcqRel, Ordering::Relaxed).is_err()
+    {
+        return;
+    }
+
+    let mut restart = Vec::new();
+    {
+        let Some(mut state) = INIT_STATE.try_lock() else {
+            return;
+        };
+        let InitState { service_defs, services } = &mut *state;
+        for svc in services.iter_mut().filter(|s| s.status == ServiceStatus::Running) {
+            let Some(ServiceEntry::Wasm { module, args }) =
+                service_defs.iter().find(|d| d.name == svc.name).map(|d| &d.entry)
+            else {
+                continue;
+            };
+            if let Some(job) = svc.job {
+                let status = wasm_service::job_status(job);
+                if matches!(status, Some(JobStatus::Pending | JobStatus::Running)) {
+                    continue;
+                }
+                let reason = status.map_or("job gone", |status| status.as_str());
+                klog_warning("init", &format!("WASM service {} exited ({}), restarting", svc.name, reason));
+                svc.job = None;
+                svc.hart = None;
+            }
+            if now - svc.started_at as i64 >= WASM_RESTART_DELAY_MS {
+                restart.push((svc.name.clone(), module.clone(), args.clone()));
+            }
+        }
+    }
+
+    for (name, module, args) in restart {
+        if let Err(e) = start_wasm_service(&name, &module, args) {
+            klog_error("init", &format!("WASM service {} not restarted: {}", name, e));
+            if let Some(svc) = INIT_STATE.lock().services.iter_mut().find(|s| s.name == name) {
+                svc.status = ServiceStatus::Failed;
+            }
+        }
+    }
+}
+
 /// Register a running service instance
 pub fn register_service(name: &str, pid: u32, hart: Option<usize>) {
     let mut state = INIT_STATE.lock();
@@ -302,6 +509,7 @@ pub fn register_service(name: &str, pid: u32, hart: Option<usize>) {
         svc.status = ServiceStatus::Running;
         svc.started_at = crate::get_time_ms() as u64;
         svc.hart = hart;
+        svc.job = None;
     } else {
         state.services.push(ServiceInfo {
             name: String::from(name),
@@ -309,6 +517,7 @@ pub fn register_service(name: &str, pid: u32, hart: Option<usize>) {
             status: ServiceStatus::Running,
             started_at: crate::get_time_ms() as u64,
             hart,
+            job: None,
         });
     }
     SERVICES_STARTED.fetch_add(1, Ordering::Relaxed);
@@ -321,6 +530,7 @@ fn mark_service_stopped(name: &str) {
         svc.status = ServiceStatus::Stopped;
         svc.pid = 0;
         svc.hart = None;
+        svc.job = None;
     }
 }
 
@@ -360,6 +570,7 @@ pub fn list_services() -> Vec<ServiceInfo> {
                 status: ServiceStatus::Stopped,
                 started_at: 0,
                 hart: None,
+                job: None,
             });
         }
     }
@@ -0,0 +1,140 @@
+//! Shared Kernel Log Page
+//!
+//! A copy of the kernel log ring laid out for lock-free reading, so `dmesg -f`
+//! and other log followers can poll for new messages without a syscall per
+//! poll. `SYS_KLOG_MAP` returns the page's address once; after that readers
+//! only load memory. Every message that reaches `KLOG` is published here too.
+//!
+//! The page is kernel memory mapped read-only into every binary's address
+//! space at its own address (see `paging`); without an Sv39 MMU binaries
+//! address it directly and must treat it as read-only.
+//!
+//! # Layout (`#[repr(C)]`, little-endian)
+//! ```text
+//! header (64 bytes): magic "KLOG" u32, version u32, slot_count u32,
+//!                    slot_size u32, head u64, reserved
+//! slot (256 bytes):  seq u64, timestamp_ms u64, level u8, hart u8,
+//!                    len u16, reserved u32, text [u8; 232]
+//! ```
+//! `head` counts the records published so far. Record `n` lives in slot
+//! `n % slot_count`, and `text` holds "subsystem: message" (UTF-8).
+//!
+//! # Reading (sequence protocol)
+//! A slot's `seq` is `n + 1` while it holds record `n` and 0 while the kernel
+//! rewrites it. To read record `n` (for `n < head`): load `seq`, copy the
+//! slot, load `seq` again; the copy is good if both loads are `n + 1`. A
+//! larger `seq` means the record was overwritten because the reader fell more
+//! than a ring behind; resume from `head - slot_count`.
+
+use alloc::format;
+use core::cell::UnsafeCell;
+use core::sync::atomic::{fence, AtomicU64, Ordering};
+
+use crate::lock::state::log::LogLevel;
+
+/// "KLOG", little-endian
+const MAGIC: u32 = u32::from_le_bytes(*b"KLOG");
+const VERSION: u32 = 1;
+
+/// Records kept in the page
+const SLOT_COUNT: usize = 256;
+const SLOT_SIZE: usize = 256;
+/// Bytes of "subsystem: message" kept per record
+const TEXT_LEN: usize = SLOT_SIZE - 24;
+
+#[repr(C)]
+struct Header {
+    magic: u32,
+    version: u32,
+    slot_count: u32,
+    slot_size: u32,
+    head: AtomicU64,
+    _reserved: [u8; 40],
+}
+
+#[repr(C)]
+struct SlotData {
+    timestamp_ms: u64,
+    level: u8,
+    hart: u8,
+    len: u16,
+    _reserved: u32,
+    text: [u8; TEXT_LEN],
+}
+
+#[repr(C)]
+struct Slot {
+    seq: AtomicU64,
+    data: UnsafeCell<SlotData>,
+}
+
+#[repr(C, align(4096))]
+struct KlogPage {
+    header: Header,
+    slots: [Slot; SLOT_COUNT],
+}
+
+// SAFETY: slot data is only written by `publish`, which runs under the KLOG
+// buffer lock; readers detect torn copies through `seq`.
+unsafe impl Sync for KlogPage {}
+
+static PAGE: KlogPage = KlogPage {
+    header: Header {
+        magic: MAGIC,
+        version: VERSION,
+        slot_count: SLOT_COUNT as u32,
+        slot_size: SLOT_SIZE as u32,
+        head: AtomicU64::new(0),
+        _reserved: [0; 40],
+    },
+    slots: [const {
+        Slot {
+            seq: AtomicU64::new(0),
+            data: UnsafeCell::new(SlotData {
+                timestamp_ms: 0,
+                level: 0,
+                hart: 0,
+                len: 0,
+                _reserved: 0,
+                text: [0; TEXT_LEN],
+            }),
+        }
+    }; SLOT_COUNT],
+};
+
+const _: () = assert!(core::mem::size_of::<Header>() == 64);
+const _: () = assert!(core::mem::size_of::<Slot>() == SLOT_SIZE);
+
+/// Address of the page, for `SYS_KLOG_MAP`
+pub fn address() -> usize {
+    &PAGE as *const KlogPage as usize
+}
+
+/// Append a record. Callers must be serialized (the KLOG buffer lock does).
+pub fn publish(timestamp_ms: u64, level: LogLevel, hart_id: usize, subsystem: &str, message: &str) {
+    let mut text = format!("{}: {}", subsystem, message);
+    if text.len() > TEXT_LEN {
+        let mut end = TEXT_LEN;
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        text.truncate(end);
+    }
+
+    let head = PAGE.header.head.load(Ordering::Relaxed);
+    let slot = &PAGE.slots[(head % SLOT_COUNT as u64) as usize];
+
+    slot.seq.store(0, Ordering::Relaxed);
+    fence(Ordering::Release);
+    // SAFETY: the only writer; see `KlogPage`
+    unsafe {
+        let data = &mut *slot.data.get();
+        data.timestamp_ms = timestamp_ms;
+        data.level = level as u8;
+        data.hart = hart_id as u8;
+        data.len = text.len() as u16;
+        data.text[..text.len()].copy_from_slice(text.as_bytes());
+    }
+    slot.seq.store(head + 1, Ordering::Release);
+    PAGE.header.head.store(head + 1, Ordering::Release);
+}
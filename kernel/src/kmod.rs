@@ -0,0 +1,569 @@
+//! Kernel Modules (lite) - signed WASM kernel extensions
+//!
+//! A kernel module is a WASM binary loaded with `kmod load`. Unlike normal
+//! WASM programs it stays resident after its init function returns and can
+//! hook into the kernel through a small host ABI. No native code is linked:
+//! every call into a module runs in wasmi with a fuel budget and a memory
+//! cap, and a module that traps is disabled instead of taking the kernel down.
+//!
+//! ## Signing
+//!
+//! `<module>.wasm` must come with `<module>.wasm.sig`: an ECDSA P-256/SHA-256
+//! signature over the module bytes (DER, as written by
+//! `openssl dgst -sha256 -sign`, or raw 64-byte r||s). It is checked against
+//! the hex SEC1 public keys in `/etc/kmod/trusted_keys` (one per line), which
+//! are read once on first load and kept for the rest of the boot.
+//!
+//! ## Host ABI (import module "kmod")
+//!
+//! | Import                                  | Description                          |
+//! |-----------------------------------------|--------------------------------------|
+//! | `register_command(ptr, len) -> i32`     | Add a shell command                  |
+//! | `register_klog_filter() -> i32`         | Receive kernel log messages          |
+//! | `register_http_route(ptr, len) -> i32`  | Serve an httpd path prefix (`/api/`) |
+//! | `input_len() -> i32`                    | Size of the current call's input     |
+//! | `input_read(ptr, len) -> i32`           | Copy the current call's input        |
+//! | `output(ptr, len)`                      | Append to the call's output          |
+//! | `log(level, ptr, len)`                  | Write to the kernel log              |
+//! | `time() -> i64`                         | Milliseconds since boot              |
+//!
+//! ## Module exports
+//!
+//! | Export                         | Input                  | Output / return               |
+//! |--------------------------------|------------------------|-------------------------------|
+//! | `kmod_init() -> i32`           | -                      | 0 = loaded, else load fails   |
+//! | `kmod_exit()` (optional)       | -                      | -                             |
+//! | `kmod_command() -> i32`        | `"<cmd> <args>"`       | printed text / exit code      |
+//! | `kmod_klog_filter(level) -> i32` | `"<subsystem>: <msg>"` | 0 = drop the message        |
+//! | `kmod_http() -> i32`           | raw HTTP request       | JSON body / status code       |
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use p256::ecdsa::signature::Verifier;
+use p256::ecdsa::{Signature, VerifyingKey};
+use wasmi::{Caller, Config, Engine, Func, Instance, Linker, Module, Store, StoreLimits, StoreLimitsBuilder};
+
+use crate::cpu::fs_proxy;
+use crate::lock::state::log::LogLevel;
+use crate::services::klogd::{klog_error, klog_info, KLOG};
+use crate::Spinlock;
+
+/// Trusted signing keys (hex SEC1, one per line)
+const TRUSTED_KEYS_PATH: &str = "/etc/kmod/trusted_keys";
+
+/// Fuel for one call into a module (init, command, filter or request)
+const FUEL_PER_CALL: u64 = 20_000_000;
+
+/// Linear memory cap per module
+const MAX_MODULE_MEMORY: usize = 4 * 1024 * 1024;
+
+/// Largest buffer a module may pass to the host in one call
+const MAX_HOST_BUFFER: usize = 64 * 1024;
+
+/// Loaded modules. Held for the duration of every call into a module.
+static MODULES: Spinlock<Vec<KernelModule>> = Spinlock::new(Vec::new());
+
+/// Trusted keys, read on first load
+static TRUSTED_KEYS: Spinlock<Option<Vec<VerifyingKey>>> = Spinlock::new(None);
+
+/// Number of loaded modules that registered a klog filter, faulted or not
+/// (fast path for `klog_filter`)
+static KLOG_FILTERS: AtomicUsize = AtomicUsize::new(0);
+
+/// Per-module host state
+struct KmodContext {
+    name: String,
+    /// Input for the call in progress
+    input: Vec<u8>,
+    /// Output of the call in progress
+    output: Vec<u8>,
+    commands: Vec<String>,
+    http_routes: Vec<String>,
+    klog_filter: bool,
+    limits: StoreLimits,
+}
+
+struct KernelModule {
+    store: Store<KmodContext>,
+    instance: Instance,
+    /// Set when a call traps or runs out of fuel; the module gets no more calls
+    faulted: bool,
+}
+
+impl KernelModule {
+    fn name(&self) -> &str {
+        &self.store.data().name
+    }
+
+    fn has_filter(&self) -> bool {
+        !self.faulted && self.store.data().klog_filter
+    }
+
+    /// Call `export` with `input`; returns its result and output
+    fn call(&mut self, export: &str, arg: Option<i32>, input: &[u8]) -> Option<(i32, Vec<u8>)> {
+        if self.faulted {
+            return None;
+        }
+
+        let ctx = self.store.data_mut();
+        ctx.input = input.to_vec();
+        ctx.output.clear();
+        if self.store.set_fuel(FUEL_PER_CALL).is_err() {
+            return None;
+        }
+
+        let result = match arg {
+            Some(arg) => self
+                .instance
+                .get_typed_func::<i32, i32>(&self.store, export)
+                .map_err(|e| format!("{:?}", e))
+                .and_then(|f| f.call(&mut self.store, arg).map_err(|e| format!("{:?}", e))),
+            None => self
+                .instance
+                .get_typed_func::<(), i32>(&self.store, export)
+                .map_err(|e| format!("{:?}", e))
+                .and_then(|f| f.call(&mut self.store, ()).map_err(|e| format!("{:?}", e))),
+        };
+
+        match result {
+            Ok(ret) => Some((ret, core::mem::take(&mut self.store.data_mut().output))),
+            Err(e) => {
+                self.faulted = true;
+                klog_error("kmod", &format!("{}: {} failed, module disabled: {}", self.name(), export, e));
+                None
+            }
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// LOADING
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Verify, instantiate and initialize the module at `path`
+pub fn load(path: &str) -> Result<(), String> {
+    let name = path
+        .rsplit('/')
+        .next()
+        .unwrap_or(path)
+        .trim_end_matches(".wasm");
+    if name.is_empty() {
+        return Err(String::from("invalid module path"));
+    }
+    if MODULES.lock().iter().any(|m| m.name() == name) {
+        return Err(format!("module '{}' is already loaded", name));
+    }
+
+    let bytes = fs_proxy::fs_read(path).ok_or_else(|| format!("cannot read {}", path))?;
+    let sig_path = format!("{}.sig", path);
+    let signature = fs_proxy::fs_read(&sig_path).ok_or_else(|| format!("missing signature {}", sig_path))?;
+    verify_signature(&bytes, &signature)?;
+
+    let mut module = instantiate(name, &bytes)?;
+    match module.call("kmod_init", None, &[]) {
+        Some((0, _)) => {}
+        Some((code, _)) => return Err(format!("kmod_init returned {}", code)),
+        None => return Err(String::from("kmod_init trapped")),
+    }
+
+    let mut modules = MODULES.lock();
+
+    // Registrations must not clash with modules that are already loaded
+    let ctx = module.store.data();
+    for other in modules.iter().filter(|m| !m.faulted) {
+        let theirs = other.store.data();
+        if let Some(cmd) = ctx.commands.iter().find(|c| theirs.commands.contains(c)) {
+            return Err(format!("command '{}' already provided by '{}'", cmd, theirs.name));
+        }
+        if let Some(route) = ctx.http_routes.iter().find(|r| theirs.http_routes.contains(r)) {
+            return Err(format!("route '{}' already provided by '{}'", route, theirs.name));
+        }
+    }
+
+    klog_info(
+        "kmod",
+        &format!(
+            "Loaded {} ({} commands, {} routes{})",
+            name,
+            ctx.commands.len(),
+            ctx.http_routes.len(),
+            if ctx.klog_filter { ", klog filter" } else { "" }
+        ),
+    );
+    if ctx.klog_filter {
+        KLOG_FILTERS.fetch_add(1, Ordering::Relaxed);
+    }
+    modules.push(module);
+    Ok(())
+}
+
+/// Unload a module, calling its `kmod_exit` if it has one
+pub fn unload(name: &str) -> Result<(), &'static str> {
+    let mut modules = MODULES.lock();
+    let index = modules.iter().position(|m| m.name() == name).ok_or("Module not loaded")?;
+    let mut module = modules.remove(index);
+    drop(modules);
+
+    if module.store.data().klog_filter {
+        KLOG_FILTERS.fetch_sub(1, Ordering::Relaxed);
+    }
+    if !module.faulted {
+        if let Ok(exit) = module.instance.get_typed_func::<(), ()>(&module.store, "kmod_exit") {
+            let _ = module.store.set_fuel(FUEL_PER_CALL);
+            let _ = exit.call(&mut module.store, ());
+        }
+    }
+    klog_info("kmod", &format!("Unloaded {}", name));
+    Ok(())
+}
+
+/// One line per module: name, state and what it registered
+pub fn list() -> String {
+    let modules = MODULES.lock();
+    let mut out = String::new();
+    for module in modules.iter() {
+        let ctx = module.store.data();
+        out.push_str(&ctx.name);
+        out.push_str(if module.faulted { "\tfaulted" } else { "\tloaded" });
+        if !ctx.commands.is_empty() {
+            out.push_str("\tcommands=");
+            out.push_str(&ctx.commands.join(","));
+        }
+        if !ctx.http_routes.is_empty() {
+            out.push_str("\troutes=");
+            out.push_str(&ctx.http_routes.join(","));
+        }
+        if ctx.klog_filter {
+            out.push_str("\tklog-filter");
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Check a module against the trusted keys
+fn verify_signature(bytes: &[u8], signature: &[u8]) -> Result<(), String> {
+    let signature = Signature::from_der(signature)
+        .or_else(|_| Signature::from_slice(signature))
+        .map_err(|_| String::from("malformed signature"))?;
+
+    let mut keys = TRUSTED_KEYS.lock();
+    if keys.is_none() {
+        *keys = Some(read_trusted_keys());
+    }
+    let keys = keys.as_ref().unwrap();
+    if keys.is_empty() {
+        return Err(format!("no trusted keys in {}", TRUSTED_KEYS_PATH));
+    }
+
+    if keys.iter().any(|key| key.verify(bytes, &signature).is_ok()) {
+        Ok(())
+    } else {
+        Err(String::from("signature not valid for any trusted key"))
+    }
+}
+
+fn read_trusted_keys() -> Vec<VerifyingKey> {
+    let content = fs_proxy::fs_read(TRUSTED_KEYS_PATH).unwrap_or_default();
+    let text = core::str::from_utf8(&content).unwrap_or("");
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let key = decode_hex(line).and_then(|bytes| VerifyingKey::from_sec1_bytes(&bytes).ok());
+            if key.is_none() {
+                klog_error("kmod", &format!("Ignoring malformed key in {}", TRUSTED_KEYS_PATH));
+            }
+            key
+        })
+        .collect()
+}
+
+fn decode_hex(s: &str) -> Option<Vec<u8>> {
+    if s.len() % 2 != 0 {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// HOST ABI
+// ═══════════════════════════════════════════════════════════════════════════════
+
+fn instantiate(name: &str, bytes: &[u8]) -> Result<KernelModule, String> {
+    let mut config = Config::default();
+    config.consume_fuel(true);
+    let engine = Engine::new(&config);
+    let module = Module::new(&engine, bytes).map_err(|e| format!("Invalid WASM: {:?}", e))?;
+
+    let ctx = KmodContext {
+        name: String::from(name),
+        input: Vec::new(),
+        output: Vec::new(),
+        commands: Vec::new(),
+        http_routes: Vec::new(),
+        klog_filter: false,
+        limits: StoreLimitsBuilder::new()
+            .memory_size(MAX_MODULE_MEMORY)
+            .instances(1)
+            .build(),
+    };
+    let mut store = Store::new(&engine, ctx);
+    store.limiter(|ctx| &mut ctx.limits);
+    let mut linker = Linker::new(&engine);
+
+    // register_command(ptr, len) -> i32
+    linker
+        .define(
+            "kmod",
+            "register_command",
+            Func::wrap(&mut store, |mut caller: Caller<'_, KmodContext>, ptr: i32, len: i32| -> i32 {
+                let Some(name) = read_string(&caller, ptr, len) else {
+                    return -1;
+                };
+                let valid = !name.is_empty() && name.len() <= 32 && name.bytes().all(|b| b.is_ascii_graphic() && b != b'/');
+                let ctx = caller.data_mut();
+                if !valid || ctx.commands.contains(&name) {
+                    return -1;
+                }
+                ctx.commands.push(name);
+                0
+            }),
+        )
+        .map_err(|e| format!("define register_command: {:?}", e))?;
+
+    // register_klog_filter() -> i32
+    linker
+        .define(
+            "kmod",
+            "register_klog_filter",
+            Func::wrap(&mut store, |mut caller: Caller<'_, KmodContext>| -> i32 {
+                caller.data_mut().klog_filter = true;
+                0
+            }),
+        )
+        .map_err(|e| format!("define register_klog_filter: {:?}", e))?;
+
+    // register_http_route(ptr, len) -> i32 (prefix must be under /api/)
+    linker
+        .define(
+            "kmod",
+            "register_http_route",
+            Func::wrap(&mut store, |mut caller: Caller<'_, KmodContext>, ptr: i32, len: i32| -> i32 {
+                let Some(prefix) = read_string(&caller, ptr, len) else {
+                    return -1;
+                };
+                let ctx = caller.data_mut();
+                if !prefix.starts_with("/api/") || prefix.len() <= 5 || ctx.http_routes.contains(&prefix) {
+                    return -1;
+                }
+                ctx.http_routes.push(prefix);
+                0
+            }),
+        )
+        .map_err(|e| format!("define register_http_route: {:?}", e))?;
+
+    // input_len() -> i32
+    linker
+        .define(
+            "kmod",
+            "input_len",
+            Func::wrap(&mut store, |caller: Caller<'_, KmodContext>| -> i32 {
+                caller.data().input.len() as i32
+            }),
+        )
+        .map_err(|e| format!("define input_len: {:?}", e))?;
+
+    // input_read(ptr, len) -> i32 (bytes copied)
+    linker
+        .define(
+            "kmod",
+            "input_read",
+            Func::wrap(&mut store, |mut caller: Caller<'_, KmodContext>, ptr: i32, len: i32| -> i32 {
+                if ptr < 0 || len < 0 {
+                    return -1;
+                }
+                let Some(mem) = caller.get_export("memory").and_then(|e| e.into_memory()) else {
+                    return -1;
+                };
+                let n = caller.data().input.len().min(len as usize);
+                let data = caller.data().input[..n].to_vec();
+                match mem.write(&mut caller, ptr as usize, &data) {
+                    Ok(()) => n as i32,
+                    Err(_) => -1,
+                }
+            }),
+        )
+        .map_err(|e| format!("define input_read: {:?}", e))?;
+
+    // output(ptr, len)
+    linker
+        .define(
+            "kmod",
+            "output",
+            Func::wrap(&mut store, |mut caller: Caller<'_, KmodContext>, ptr: i32, len: i32| {
+                if let Some(data) = read_bytes(&caller, ptr, len) {
+                    let output = &mut caller.data_mut().output;
+                    let room = MAX_HOST_BUFFER.saturating_sub(output.len());
+                    output.extend_from_slice(&data[..data.len().min(room)]);
+                }
+            }),
+        )
+        .map_err(|e| format!("define output: {:?}", e))?;
+
+    // log(level, ptr, len)
+    linker
+        .define(
+            "kmod",
+            "log",
+            Func::wrap(&mut store, |caller: Caller<'_, KmodContext>, level: i32, ptr: i32, len: i32| {
+                if let Some(message) = read_string(&caller, ptr, len) {
+                    let subsystem = format!("kmod:{}", caller.data().name);
+                    KLOG.log(log_level(level), &subsystem, &message);
+                }
+            }),
+        )
+        .map_err(|e| format!("define log: {:?}", e))?;
+
+    // time() -> i64
+    linker
+        .define(
+            "kmod",
+            "time",
+            Func::wrap(&mut store, |_caller: Caller<'_, KmodContext>| -> i64 {
+                crate::get_time_ms()
+            }),
+        )
+        .map_err(|e| format!("define time: {:?}", e))?;
+
+    store.set_fuel(FUEL_PER_CALL).map_err(|e| format!("Fuel: {:?}", e))?;
+    let instance = linker
+        .instantiate(&mut store, &module)
+        .map_err(|e| format!("Instantiate: {:?}", e))?
+        .ensure_no_start(&mut store)
+        .map_err(|e| format!("Start: {:?}", e))?;
+
+    Ok(KernelModule {
+        store,
+        instance,
+        faulted: false,
+    })
+}
+
+fn read_bytes(caller: &Caller<'_, KmodContext>, ptr: i32, len: i32) -> Option<Vec<u8>> {
+    if ptr < 0 || len < 0 || len as usize > MAX_HOST_BUFFER {
+        return None;
+    }
+    let mem = caller.get_export("memory").and_then(|e| e.into_memory())?;
+    let mut buf = vec![0u8; len as usize];
+    mem.read(caller, ptr as usize, &mut buf).ok()?;
+    Some(buf)
+}
+
+fn read_string(caller: &Caller<'_, KmodContext>, ptr: i32, len: i32) -> Option<String> {
+    String::from_utf8(read_bytes(caller, ptr, len)?).ok()
+}
+
+fn log_level(level: i32) -> LogLevel {
+    match level {
+        0 => LogLevel::Emergency,
+        1 => LogLevel::Alert,
+        2 => LogLevel::Critical,
+        3 => LogLevel::Error,
+        4 => LogLevel::Warning,
+        5 => LogLevel::Notice,
+        6 => LogLevel::Info,
+        7 => LogLevel::Debug,
+        _ => LogLevel::Trace,
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// KERNEL HOOKS
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Run a module-provided shell command. Returns false if no module has it.
+pub fn run_command(cmd: &str, args: &str) -> bool {
+    let mut modules = MODULES.lock();
+    let Some(module) = modules
+        .iter_mut()
+        .find(|m| !m.faulted && m.store.data().commands.iter().any(|c| c == cmd))
+    else {
+        return false;
+    };
+
+    let input = format!("{} {}", cmd, args);
+    let result = module.call("kmod_command", None, input.trim_end().as_bytes());
+    drop(modules);
+
+    match result {
+        Some((code, output)) => {
+            crate::scripting::out_str(&String::from_utf8_lossy(&output));
+            if code != 0 {
+                crate::scripting::out_str(&format!("\x1b[1;31mExited with code:\x1b[0m {}\n", code));
+            }
+        }
+        None => crate::scripting::out_str("\x1b[1;31mError:\x1b[0m kernel module failed\n"),
+    }
+    true
+}
+
+/// Whether a loaded module provides `cmd`
+pub fn has_command(cmd: &str) -> bool {
+    let modules = MODULES.lock();
+    modules
+        .iter()
+        .any(|m| !m.faulted && m.store.data().commands.iter().any(|c| c == cmd))
+}
+
+/// Names of all module-provided commands (for tab completion)
+pub fn command_names() -> Vec<String> {
+    let modules = MODULES.lock();
+    modules
+        .iter()
+        .filter(|m| !m.faulted)
+        .flat_map(|m| m.store.data().commands.iter().cloned())
+        .collect()
+}
+
+/// Pass a log message through the module filters. Returns false to drop it.
+///
+/// Never blocks: if the module table is busy (including a module logging
+/// from inside a call) the message is kept.
+pub fn klog_filter(level: LogLevel, subsystem: &str, message: &str) -> bool {
+    if KLOG_FILTERS.load(Ordering::Relaxed) == 0 {
+        return true;
+    }
+    let Some(mut modules) = MODULES.try_lock() else {
+        return true;
+    };
+
+    let input = format!("{}: {}", subsystem, message);
+    for module in modules.iter_mut().filter(|m| m.has_filter()) {
+        if let Some((0, _)) = module.call("kmod_klog_filter", Some(level as i32), input.as_bytes()) {
+            return false;
+        }
+    }
+    true
+}
+
+/// Serve an httpd request from a module route. Returns (status, JSON body).
+pub fn handle_http(path: &str, request: &[u8]) -> Option<(u16, Vec<u8>)> {
+    let mut modules = MODULES.lock();
+    let module = modules.iter_mut().find(|m| {
+        !m.faulted && m.store.data().http_routes.iter().any(|r| path.starts_with(r.as_str()))
+    })?;
+
+    match module.call("kmod_http", None, request) {
+        Some((status, body)) if (100..600).contains(&status) => Some((status as u16, body)),
+        Some((_, body)) => Some((200, body)),
+        None => Some((500, Vec::from(&b"{\"error\":\"kernel module failed\"}"[..]))),
+    }
+}
@@ -0,0 +1,122 @@
+//! Message Catalog
+//!
+//! User-visible kernel strings (boot section titles, the shutdown sequence,
+//! shell errors) are looked up here by key instead of being hardcoded, so
+//! the system can be localized without code changes. The language comes
+//! from the `system.lang` setting (exposed to programs as `LANG`); its
+//! catalog is `/etc/locale/<lang>.msg`:
+//!
+//! ```text
+//! # key = message
+//! boot.section.network = NETZWERK
+//! shutdown.goodbye = Auf Wiedersehen!
+//! ```
+//!
+//! Keys missing from the file, and everything printed before the root
+//! filesystem is mounted, fall back to the built-in English text. `{}` in a
+//! message is replaced by the arguments of `msg_fmt`, in order.
+
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::String;
+
+use crate::cpu::fs_proxy;
+use crate::services::klogd::{klog_info, klog_warning};
+use crate::Spinlock;
+
+/// Directory holding one `<lang>.msg` catalog per language
+pub const LOCALE_DIR: &str = "/etc/locale";
+
+/// Built-in English messages, used for keys the catalog lacks
+const DEFAULT_MESSAGES: &[(&str, &str)] = &[
+    ("boot.section.cpu", "CPU & ARCHITECTURE"),
+    ("boot.section.process", "PROCESS MANAGER"),
+    ("boot.section.memory", "MEMORY SUBSYSTEM"),
+    ("boot.section.storage", "STORAGE SUBSYSTEM"),
+    ("boot.section.network", "NETWORK SUBSYSTEM"),
+    ("boot.section.services", "SERVICES"),
+    ("boot.section.rescue", "RESCUE MODE"),
+    ("boot.section.resume", "RESUME FROM HIBERNATION"),
+    ("boot.time", "Boot time"),
+    ("boot.time_detail", "{} ms (run 'bootchart' for details)"),
+    ("boot.ready", "System ready, starting GUI..."),
+    ("shutdown.title", "System Shutdown Initiated"),
+    ("shutdown.sync", "Syncing filesystems..."),
+    ("shutdown.network", "Stopping network services..."),
+    ("shutdown.poweroff", "Powering off CPU..."),
+    ("shutdown.goodbye", "Goodbye!"),
+    ("shell.not_found", "Command not found:"),
+    ("shell.not_found_hint", "Try 'help' for available commands, or check /usr/bin/ for scripts"),
+    ("rescue.hint", "No services are running. Type 'help' for commands,"),
+    ("rescue.hint_continue", "'continue' to resume boot once the problem is fixed."),
+];
+
+/// Messages loaded from the current language's catalog
+static CATALOG: Spinlock<BTreeMap<String, String>> = Spinlock::new(BTreeMap::new());
+
+/// Message for `key` in the current language
+pub fn msg(key: &str) -> String {
+    if let Some(text) = CATALOG.lock().get(key) {
+        return text.clone();
+    }
+    let default = DEFAULT_MESSAGES.iter().find(|(k, _)| *k == key).map(|(_, text)| *text);
+    String::from(default.unwrap_or(key))
+}
+
+/// Message for `key` with each `{}` replaced by the next argument
+pub fn msg_fmt(key: &str, args: &[&str]) -> String {
+    let template = msg(key);
+    let mut out = String::with_capacity(template.len());
+    let mut args = args.iter();
+    let mut rest = template.as_str();
+    while let Some(pos) = rest.find("{}") {
+        out.push_str(&rest[..pos]);
+        out.push_str(args.next().copied().unwrap_or(""));
+        rest = &rest[pos + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// `text` padded with spaces to be centered in `width` columns
+pub fn center(text: &str, width: usize) -> String {
+    let len = text.chars().count();
+    let left = width.saturating_sub(len) / 2;
+    let right = width.saturating_sub(len + left);
+    format!("{:left$}{}{:right$}", "", text, "", left = left, right = right)
+}
+
+/// Parse `key = message` lines; `#` starts a comment line
+fn parse(text: &str) -> BTreeMap<String, String> {
+    let mut catalog = BTreeMap::new();
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some((key, value)) = line.split_once('=') {
+            catalog.insert(String::from(key.trim()), String::from(value.trim()));
+        }
+    }
+    catalog
+}
+
+/// Load the catalog for the `system.lang` setting (at boot and when it
+/// changes). A missing catalog leaves the built-in English messages.
+pub fn load() {
+    let lang = crate::settings::lang();
+    let path = format!("{}/{}.msg", LOCALE_DIR, lang);
+    let catalog = match fs_proxy::fs_read(&path) {
+        Some(data) => parse(core::str::from_utf8(&data).unwrap_or("")),
+        None => {
+            if lang != crate::settings::DEFAULT_LANG {
+                klog_warning("locale", &format!("{} not found, using built-in messages", path));
+            }
+            BTreeMap::new()
+        }
+    };
+    if !catalog.is_empty() {
+        klog_info("locale", &format!("Loaded {} messages for '{}'", catalog.len(), lang));
+    }
+    *CATALOG.lock() = catalog;
+}
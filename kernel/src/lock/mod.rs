@@ -4,6 +4,8 @@
 //! - `Spinlock` - Basic mutual exclusion with swap-based acquisition
 //! - `TicketLock` - Fair spinlock with FIFO ordering (no starvation)
 //! - `RwLock` - Reader-writer lock (multiple readers OR one writer)
+//! - `wait::WaitQueue` - Blocks the running process until an event or timeout
+//! - `mutex::Mutex` - Lock whose waiters sleep (long sections such as fs I/O)
 //!
 //! ## Lock Ordering Protocol
 //!
@@ -16,16 +18,23 @@
 //! 6. NET_STATE
 //! 7. KLOG
 //! 8. HEAP_ALLOCATOR (implicit in alloc)
+//!
+//! ## Preemption
+//!
+//! Every lock counts as held on its hart from the moment acquisition starts
+//! until the guard is dropped. The timer only preempts a process while its
+//! hart holds nothing (`preemptible`), so a preempted process never leaves a
+//! lock behind for the scheduler loop or the next process to spin on.
 
 use core::cell::UnsafeCell;
 use core::hint::spin_loop;
 use core::ops::{Deref, DerefMut};
-use core::sync::atomic::{AtomicU32, Ordering};
-#[cfg(debug_assertions)]
-use core::sync::atomic::AtomicUsize;
+use core::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
 
 pub mod utils;
 pub mod state;
+pub mod wait;
+pub mod mutex;
 
 // ============================================================================
 // Lock IDs for Lock Ordering Validation (Debug Mode)
@@ -47,6 +56,54 @@ pub enum LockId {
     Unordered = 255,
 }
 
+// ============================================================================
+// Preemption Control
+// ============================================================================
+
+/// Locks held, or being acquired, on each hart
+static LOCK_DEPTH: [AtomicUsize; crate::cpu::MAX_HARTS] = {
+    const ZERO: AtomicUsize = AtomicUsize::new(0);
+    [ZERO; crate::cpu::MAX_HARTS]
+};
+
+/// Keep the timer from preempting this hart's process until the matching
+/// `preempt_enable` (taken for every lock; also for other non-reentrant
+/// state such as the heap)
+#[inline]
+pub fn preempt_disable() {
+    if let Some(depth) = LOCK_DEPTH.get(get_hart_id()) {
+        depth.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Undo one `preempt_disable`
+#[inline]
+pub fn preempt_enable() {
+    if let Some(depth) = LOCK_DEPTH.get(get_hart_id()) {
+        depth.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Whether the process running on `hart` may be switched out: it holds no lock
+pub fn preemptible(hart: usize) -> bool {
+    LOCK_DEPTH.get(hart).is_some_and(|depth| depth.load(Ordering::Relaxed) == 0)
+}
+
+// ============================================================================
+// Spinning
+// ============================================================================
+
+/// Pause between attempts to take a lock. Hart 0 also runs the block
+/// transfers other harts are waiting for (see `d1_mmc`): their submitters
+/// may hold the lock, and only hart 0 can finish them.
+#[inline]
+pub fn relax() {
+    spin_loop();
+    if get_hart_id() == 0 {
+        crate::platform::d1_mmc::service_transfers();
+    }
+}
+
 // ============================================================================
 // BASIC SPINLOCK
 // ============================================================================
@@ -130,6 +187,7 @@ impl<T> Spinlock<T> {
     #[inline]
     pub fn lock(&self) -> SpinlockGuard<T> {
         let mut spin_count = 0u32;
+        preempt_disable();
 
         loop {
             // Try to acquire using swap (AMOSWAP.W instruction on RISC-V)
@@ -153,7 +211,7 @@ impl<T> Spinlock<T> {
             // the emulator's AMO operations are properly serialized while
             // regular loads may not have proper visibility across harts.
             loop {
-                spin_loop();
+                relax();
                 spin_count = spin_count.wrapping_add(1);
 
                 // Detect potential deadlock in debug mode
@@ -192,6 +250,7 @@ impl<T> Spinlock<T> {
     /// Returns `Some(guard)` if successful, `None` if lock is held.
     #[inline]
     pub fn try_lock(&self) -> Option<SpinlockGuard<T>> {
+        preempt_disable();
         // Use swap instead of compare_exchange to ensure AMOSWAP.W is used
         if self.locked.swap(LOCKED, Ordering::Acquire) == UNLOCKED {
             #[cfg(debug_assertions)]
@@ -201,6 +260,7 @@ impl<T> Spinlock<T> {
                 _not_send: core::marker::PhantomData,
             })
         } else {
+            preempt_enable();
             None
         }
     }
@@ -274,6 +334,7 @@ impl<T> Drop for SpinlockGuard<'_, T> {
         // Using swap instead of store because the emulator serializes AMO operations
         // but may not properly synchronize regular store visibility across hart threads.
         self.lock.locked.swap(UNLOCKED, Ordering::Release);
+        preempt_enable();
     }
 }
 
@@ -328,13 +389,14 @@ impl<T> TicketLock<T> {
     /// This ensures FIFO ordering - first come, first served.
     #[inline]
     pub fn lock(&self) -> TicketLockGuard<T> {
+        preempt_disable();
         // Atomically get a ticket number
         let my_ticket = self.next_ticket.fetch_add(1, Ordering::Relaxed);
 
         // Wait until our ticket is being served
         let mut spin_count = 0u32;
         while self.now_serving.load(Ordering::Acquire) != my_ticket {
-            spin_loop();
+            relax();
             spin_count = spin_count.wrapping_add(1);
 
             #[cfg(debug_assertions)]
@@ -361,6 +423,7 @@ impl<T> TicketLock<T> {
     /// Returns `Some(guard)` if we got the lock, `None` if busy.
     #[inline]
     pub fn try_lock(&self) -> Option<TicketLockGuard<T>> {
+        preempt_disable();
         let current = self.now_serving.load(Ordering::Acquire);
         let next = self.next_ticket.load(Ordering::Relaxed);
 
@@ -380,6 +443,7 @@ impl<T> TicketLock<T> {
                 // This is a limitation - just return None and let caller retry
             }
         }
+        preempt_enable();
         None
     }
 
@@ -420,6 +484,7 @@ impl<T> Drop for TicketLockGuard<'_, T> {
 
         // Move to next ticket - this wakes the next waiter
         self.lock.now_serving.fetch_add(1, Ordering::Release);
+        preempt_enable();
     }
 }
 
@@ -485,6 +550,7 @@ impl<T> RwLock<T> {
     /// Blocks if a writer is holding or waiting for the lock.
     pub fn read(&self) -> RwLockReadGuard<T> {
         let mut spin_count = 0u32;
+        preempt_disable();
 
         loop {
             let state = self.state.load(Ordering::Relaxed);
@@ -506,7 +572,7 @@ impl<T> RwLock<T> {
                 }
             }
 
-            spin_loop();
+            relax();
             spin_count = spin_count.wrapping_add(1);
 
             #[cfg(debug_assertions)]
@@ -524,6 +590,7 @@ impl<T> RwLock<T> {
     ///
     /// Returns `Some(guard)` if successful, `None` if a writer is active.
     pub fn try_read(&self) -> Option<RwLockReadGuard<T>> {
+        preempt_disable();
         let state = self.state.load(Ordering::Relaxed);
 
         // If no writer is holding/waiting, try to add ourselves as a reader
@@ -537,6 +604,7 @@ impl<T> RwLock<T> {
             }
         }
 
+        preempt_enable();
         None
     }
 
@@ -545,6 +613,7 @@ impl<T> RwLock<T> {
     /// Blocks until all readers release and no other writer is active.
     pub fn write(&self) -> RwLockWriteGuard<T> {
         let mut spin_count = 0u32;
+        preempt_disable();
 
         // First, set the writer bit to prevent new readers
         loop {
@@ -566,7 +635,7 @@ impl<T> RwLock<T> {
                 }
             }
 
-            spin_loop();
+            relax();
             spin_count = spin_count.wrapping_add(1);
 
             #[cfg(debug_assertions)]
@@ -578,7 +647,7 @@ impl<T> RwLock<T> {
         // Now wait for all readers to finish
         spin_count = 0;
         while self.state.load(Ordering::Acquire) != WRITER_BIT {
-            spin_loop();
+            relax();
             spin_count = spin_count.wrapping_add(1);
 
             #[cfg(debug_assertions)]
@@ -602,6 +671,7 @@ impl<T> RwLock<T> {
     ///
     /// Returns `Some(guard)` if we got exclusive access, `None` if busy.
     pub fn try_write(&self) -> Option<RwLockWriteGuard<T>> {
+        preempt_disable();
         // Try to atomically go from 0 to WRITER_BIT
         if self
             .state
@@ -612,6 +682,7 @@ impl<T> RwLock<T> {
             self.writer_hart.store(get_hart_id(), Ordering::Relaxed);
             return Some(RwLockWriteGuard { lock: self });
         }
+        preempt_enable();
         None
     }
 
@@ -650,6 +721,7 @@ impl<T> Drop for RwLockReadGuard<'_, T> {
     fn drop(&mut self) {
         // Decrement reader count
         self.lock.state.fetch_sub(1, Ordering::Release);
+        preempt_enable();
     }
 }
 
@@ -682,6 +754,7 @@ impl<T> Drop for RwLockWriteGuard<'_, T> {
 
         // Clear writer bit (releases the lock)
         self.lock.state.fetch_and(!WRITER_BIT, Ordering::Release);
+        preempt_enable();
     }
 }
 
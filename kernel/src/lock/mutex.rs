@@ -0,0 +1,198 @@
+//! Sleeping locks for long critical sections (filesystem operations, which
+//! may wait for the SD card).
+//!
+//! A process tick that finds the lock taken is switched out until a holder
+//! releases one (`wait::WaitQueue`), so the hart runs other work meanwhile.
+//! Where the waiter cannot be switched out (it holds another lock, or is in
+//! a user program's syscall) it spins instead, and on hart 0 keeps running
+//! other harts' block transfers (`relax`): their submitters may be the
+//! holders.
+//!
+//! Holders count as holding a lock (`preempt_disable`) like with any other
+//! lock here, so they are never switched out with the lock taken.
+//!
+//! - `Mutex` - one lock around a value
+//! - `NameLocks` - a lock per name, made on demand (one per file path)
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cell::UnsafeCell;
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use super::wait::WaitQueue;
+use super::{preempt_disable, preempt_enable, preemptible, relax, Spinlock};
+use crate::cpu::get_hart_id;
+
+/// Woken whenever any sleeping lock is released
+static RELEASED: WaitQueue = WaitQueue::new();
+
+/// Attempts made spinning before a waiter sleeps: most holders are done
+/// within a block transfer, well before a sleeper would run again
+const SPIN_ATTEMPTS: usize = 1000;
+
+/// Wait until `take` succeeds. `take` runs with preemption disabled, which
+/// stays disabled once it returns true.
+fn acquire(mut take: impl FnMut() -> bool) {
+    let mut attempt = || {
+        preempt_disable();
+        if take() {
+            return true;
+        }
+        preempt_enable();
+        relax();
+        false
+    };
+    let hart = get_hart_id();
+    if !preemptible(hart) || crate::trap::in_user_syscall(hart) {
+        while !attempt() {}
+        return;
+    }
+    for _ in 0..SPIN_ATTEMPTS {
+        if attempt() {
+            return;
+        }
+    }
+    RELEASED.wait_until(i64::MAX, attempt);
+}
+
+/// Undo `acquire` once the lock itself is released
+fn release() {
+    preempt_enable();
+    RELEASED.wake_all();
+}
+
+// ============================================================================
+// MUTEX
+// ============================================================================
+
+/// A mutual exclusion lock whose waiters sleep instead of spinning.
+pub struct Mutex<T> {
+    locked: AtomicU32,
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T: Send> Sync for Mutex<T> {}
+unsafe impl<T: Send> Send for Mutex<T> {}
+
+impl<T> Mutex<T> {
+    /// Create a new unlocked mutex
+    pub const fn new(data: T) -> Self {
+        Self {
+            locked: AtomicU32::new(0),
+            data: UnsafeCell::new(data),
+        }
+    }
+
+    /// Acquire the mutex, sleeping while it is held elsewhere
+    pub fn lock(&self) -> MutexGuard<T> {
+        acquire(|| self.locked.swap(1, Ordering::Acquire) == 0);
+        MutexGuard { lock: self }
+    }
+
+    /// Get mutable access without locking (the mutex is borrowed exclusively)
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+}
+
+/// RAII guard that releases the mutex when dropped
+pub struct MutexGuard<'a, T> {
+    lock: &'a Mutex<T>,
+}
+
+impl<T> Deref for MutexGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<T> DerefMut for MutexGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+
+impl<T> Drop for MutexGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.locked.store(0, Ordering::Release);
+        release();
+    }
+}
+
+// ============================================================================
+// NAME LOCKS
+// ============================================================================
+
+/// Exclusive locks on names, made when first taken and gone when released,
+/// so any number of names can be locked without a table of mutexes.
+///
+/// A name ending in `/` covers every name under it (a directory and the
+/// files in it). Several names are taken all at once or not at all, so two
+/// holders never wait on each other.
+pub struct NameLocks {
+    held: Spinlock<Vec<String>>,
+}
+
+/// Whether holding one name keeps the other from being taken
+fn overlaps(a: &str, b: &str) -> bool {
+    a == b || (a.ends_with('/') && b.starts_with(a)) || (b.ends_with('/') && a.starts_with(b))
+}
+
+impl NameLocks {
+    /// Create a set with nothing locked
+    pub const fn new() -> Self {
+        Self {
+            held: Spinlock::new(Vec::new()),
+        }
+    }
+
+    /// Lock `name`, sleeping while it is held elsewhere
+    pub fn lock(&self, name: &str) -> NameGuard {
+        self.lock_all(&[name])
+    }
+
+    /// Lock every name in `names`, sleeping until none is held elsewhere
+    pub fn lock_all(&self, names: &[&str]) -> NameGuard {
+        acquire(|| {
+            let mut held = self.held.lock();
+            if held.iter().any(|h| names.iter().any(|n| overlaps(h, n))) {
+                return false;
+            }
+            held.extend(names.iter().map(|&n| String::from(n)));
+            true
+        });
+        NameGuard {
+            locks: self,
+            names: names.iter().map(|&n| String::from(n)).collect(),
+        }
+    }
+}
+
+impl Default for NameLocks {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// RAII guard that unlocks its names when dropped
+pub struct NameGuard<'a> {
+    locks: &'a NameLocks,
+    names: Vec<String>,
+}
+
+impl Drop for NameGuard<'_> {
+    fn drop(&mut self) {
+        {
+            let mut held = self.locks.held.lock();
+            for name in &self.names {
+                if let Some(i) = held.iter().position(|h| h == name) {
+                    held.swap_remove(i);
+                }
+            }
+        }
+        release();
+    }
+}
@@ -1,26 +1,64 @@
 //! Simple File System (SFS) State with write caching
 //!
 //! Features:
-//! - Block-level write caching (BufferCache)
+//! - Block-level write-back caching (BufferCache)
 //! - Dirty block tracking for efficient sync
 //! - LRU eviction for cache management
+//!
+//! Reads and writes both go through the cache, so dirty blocks stay in memory
+//! until `sync()` is called (the `sync` syscall, or sysmond's periodic flush).
+//! Sync writes file data before metadata, with a device barrier in between,
+//! and eviction keeps the same order (see `BufferCache::evict_lru`).
+//!
+//! ## Locking
+//!
+//! Every hart uses the filesystem directly. Writers lock the file they
+//! change by path (`files`), and creating, removing or renaming an entry
+//! also takes `dir`. Allocating a block takes `bitmap` (the allocator lock)
+//! and each cache access takes `cache`, only for as long as it needs. Take
+//! them in that order: files, `dir`, `bitmap`, `cache`.
+//!
+//! Readers take no file lock. A writer links new blocks before the entry's
+//! size or head points at them, so a reader sees the file before or after
+//! each change, never an entry pointing past its chain.
 
 // Block device type alias (D1 MMC/SD card)
 use crate::platform::d1_mmc::D1Mmc as BlockDev;
 
 use alloc::{collections::BTreeMap, vec::Vec};
+use alloc::format;
 use alloc::string::String;
 use core::sync::atomic::{AtomicU64, Ordering};
 
+use crate::lock::mutex::{Mutex, NameLocks};
+use crate::lock::Spinlock;
+
 // Must match mkfs constants
-const MAGIC: u32 = 0x53465331;
-const SEC_SUPER: u64 = 0;
-const SEC_MAP_START: u64 = 1;
+pub const MAGIC: u32 = 0x53465331;
+pub const SEC_SUPER: u64 = 0;
+pub const SEC_MAP_START: u64 = 1;
+pub const SEC_MAP_COUNT: u64 = 64;
 pub const SEC_DIR_START: u64 = 65;
 pub const SEC_DIR_COUNT: u64 = 64;
+pub const SEC_DATA_START: u64 = 129;
 
-/// Maximum number of cached blocks
-const CACHE_MAX_BLOCKS: usize = 64;
+// Superblock: magic u32, sector count u32, format major u16, format minor
+// u16, feature flags u32 (little-endian). Images made before the format was
+// versioned have zeros where the version goes; they are revision 1.0.
+
+/// On-disk format revision this kernel writes. Must match mkfs.
+pub const FORMAT_MAJOR: u16 = 1;
+pub const FORMAT_MINOR: u16 = 1;
+
+/// Feature flag: the image has symbolic links
+pub const FEATURE_SYMLINKS: u32 = 1 << 0;
+/// Feature flag: the image has LZ4-compressed files
+pub const FEATURE_LZ4: u32 = 1 << 1;
+/// Features this kernel understands; any other forces a read-only mount
+pub const KNOWN_FEATURES: u32 = FEATURE_SYMLINKS | FEATURE_LZ4;
+
+/// Maximum number of cached blocks (256 KiB of write-back buffering)
+const CACHE_MAX_BLOCKS: usize = 512;
 
 /// Cache entry access counter for LRU
 static CACHE_ACCESS_COUNTER: AtomicU64 = AtomicU64::new(0);
@@ -33,10 +71,87 @@ struct DirEntry {
     head: u32,
 }
 
+/// High bit of `DirEntry::size` marks a symbolic link; the entry's data
+/// holds the link target. Must match mkfs.
+pub const SYMLINK_FLAG: u32 = 0x8000_0000;
+
+/// Bit 30 of `DirEntry::size` marks data compressed by `mkfs --compress`:
+/// the original length (u32 LE) followed by one LZ4 block. Must match mkfs.
+pub const COMPRESSED_FLAG: u32 = 0x4000_0000;
+
+/// Largest file an entry can describe (the top two bits of its size are flags)
+pub const MAX_FILE_SIZE: u32 = COMPRESSED_FLAG - 1;
+
+impl DirEntry {
+    /// Data length in bytes as stored on disk (without the flags)
+    fn len(&self) -> u32 {
+        self.size & !(SYMLINK_FLAG | COMPRESSED_FLAG)
+    }
+
+    fn is_symlink(&self) -> bool {
+        self.size & SYMLINK_FLAG != 0
+    }
+
+    fn is_compressed(&self) -> bool {
+        self.size & COMPRESSED_FLAG != 0
+    }
+}
+
+/// Format revision and features recorded in the superblock
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Format {
+    pub major: u16,
+    pub minor: u16,
+    pub features: u32,
+}
+
+impl Format {
+    pub fn parse(superblock: &[u8; 512]) -> Self {
+        let major = u16::from_le_bytes([superblock[8], superblock[9]]);
+        let minor = u16::from_le_bytes([superblock[10], superblock[11]]);
+        if major == 0 && minor == 0 {
+            return Self { major: 1, minor: 0, features: 0 };
+        }
+        let features = u32::from_le_bytes(superblock[12..16].try_into().unwrap());
+        Self { major, minor, features }
+    }
+
+    pub fn write(&self, superblock: &mut [u8; 512]) {
+        superblock[8..10].copy_from_slice(&self.major.to_le_bytes());
+        superblock[10..12].copy_from_slice(&self.minor.to_le_bytes());
+        superblock[12..16].copy_from_slice(&self.features.to_le_bytes());
+    }
+
+    /// Whether this kernel understands the layout at all
+    pub fn is_supported(&self) -> bool {
+        self.major == FORMAT_MAJOR
+    }
+
+    /// Whether the image is newer than this kernel: it can be read, but
+    /// writing could break what the newer revision added
+    pub fn is_newer(&self) -> bool {
+        self.minor > FORMAT_MINOR || self.features & !KNOWN_FEATURES != 0
+    }
+
+    /// Revision 1.0, which has no feature flags
+    fn is_legacy(&self) -> bool {
+        self.major == 1 && self.minor == 0
+    }
+}
+
+impl core::fmt::Display for Format {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+/// Longest name a directory entry can hold
+const MAX_NAME_LEN: usize = 64;
+
 /// Directory entry size: 64 (name) + 4 (size) + 4 (head) = 72 bytes
-const DIR_ENTRY_SIZE: usize = 72;
+pub const DIR_ENTRY_SIZE: usize = 72;
 /// Entries per sector: 512 / 72 = 7
-const ENTRIES_PER_SECTOR: usize = 7;
+pub const ENTRIES_PER_SECTOR: usize = 7;
 
 /// Information about a file in the filesystem
 /// Used by the scripting engine to expose directory listing
@@ -85,6 +200,8 @@ pub struct BufferCache {
     misses: u64,
     /// Number of writebacks
     writebacks: u64,
+    /// Eviction wrote data blocks no barrier has followed yet
+    unfenced_data: bool,
 }
 
 impl BufferCache {
@@ -94,12 +211,12 @@ impl BufferCache {
             hits: 0,
             misses: 0,
             writebacks: 0,
+            unfenced_data: false,
         }
     }
 
     /// Read a block, using cache if available
-    #[allow(dead_code)]
-    pub fn read(&mut self, dev: &mut BlockDev, sector: u64) -> Result<&[u8; 512], &'static str> {
+    pub fn read(&mut self, dev: &BlockDev, sector: u64) -> Result<&[u8; 512], &'static str> {
         // Check cache first
         if self.blocks.contains_key(&sector) {
             self.hits += 1;
@@ -126,7 +243,7 @@ impl BufferCache {
     /// Read a block into a mutable buffer (for modification)
     pub fn read_mut(
         &mut self,
-        dev: &mut BlockDev,
+        dev: &BlockDev,
         sector: u64,
     ) -> Result<&mut [u8; 512], &'static str> {
         // Ensure block is in cache
@@ -152,7 +269,7 @@ impl BufferCache {
     /// Write a block (cached, not immediately flushed)
     pub fn write(
         &mut self,
-        dev: &mut BlockDev,
+        dev: &BlockDev,
         sector: u64,
         data: &[u8; 512],
     ) -> Result<(), &'static str> {
@@ -183,10 +300,19 @@ impl BufferCache {
     }
 
     /// Flush all dirty blocks to disk
-    pub fn sync(&mut self, dev: &mut BlockDev) -> Result<usize, &'static str> {
+    pub fn sync(&mut self, dev: &BlockDev) -> Result<usize, &'static str> {
+        self.sync_where(dev, |_| true)
+    }
+
+    /// Flush the dirty blocks whose sector matches `filter`
+    pub fn sync_where(
+        &mut self,
+        dev: &BlockDev,
+        filter: impl Fn(u64) -> bool,
+    ) -> Result<usize, &'static str> {
         let mut count = 0;
         for (&sector, entry) in self.blocks.iter_mut() {
-            if entry.dirty {
+            if entry.dirty && filter(sector) {
                 dev.write_sector(sector, &entry.data)?;
                 entry.dirty = false;
                 self.writebacks += 1;
@@ -198,7 +324,7 @@ impl BufferCache {
 
     /// Flush a specific block to disk
     #[allow(dead_code)]
-    pub fn sync_block(&mut self, dev: &mut BlockDev, sector: u64) -> Result<bool, &'static str> {
+    pub fn sync_block(&mut self, dev: &BlockDev, sector: u64) -> Result<bool, &'static str> {
         if let Some(entry) = self.blocks.get_mut(&sector) {
             if entry.dirty {
                 dev.write_sector(sector, &entry.data)?;
@@ -210,29 +336,54 @@ impl BufferCache {
         Ok(false)
     }
 
-    /// Evict the least recently used block
-    fn evict_lru(&mut self, dev: &mut BlockDev) -> Result<(), &'static str> {
-        // Find LRU entry
-        let lru_sector = self
-            .blocks
+    /// Least recently used block among those `pick` accepts
+    fn lru_where(&self, pick: impl Fn(u64, &CacheEntry) -> bool) -> Option<u64> {
+        self.blocks
             .iter()
+            .filter(|&(&sector, entry)| pick(sector, entry))
             .min_by_key(|(_, e)| e.last_access)
-            .map(|(&s, _)| s);
-
-        if let Some(sector) = lru_sector {
-            // Write back if dirty
-            if let Some(entry) = self.blocks.get(&sector) {
-                if entry.dirty {
-                    dev.write_sector(sector, &entry.data)?;
-                    self.writebacks += 1;
+            .map(|(&s, _)| s)
+    }
+
+    /// Evict a block: the least recently used clean one, else the least
+    /// recently used dirty data block, and dirty metadata only when nothing
+    /// else is left. Metadata is written after the dirty data blocks and a
+    /// barrier, keeping the order `FileSystemState::sync` promises.
+    fn evict_lru(&mut self, dev: &BlockDev) -> Result<(), &'static str> {
+        let victim = self
+            .lru_where(|_, e| !e.dirty)
+            .or_else(|| self.lru_where(|sector, _| sector >= SEC_DATA_START))
+            .or_else(|| self.lru_where(|_, _| true));
+        let Some(sector) = victim else {
+            return Ok(());
+        };
+
+        if self.blocks.get(&sector).is_some_and(|e| e.dirty) {
+            if sector >= SEC_DATA_START {
+                self.unfenced_data = true;
+            } else {
+                // Data first: what the metadata points at must be on the card
+                if self.sync_where(dev, |s| s >= SEC_DATA_START)? > 0 || self.unfenced_data {
+                    dev.barrier()?;
+                    self.unfenced_data = false;
                 }
             }
-            self.blocks.remove(&sector);
+            let entry = &self.blocks[&sector];
+            dev.write_sector(sector, &entry.data)?;
+            self.writebacks += 1;
         }
+        self.blocks.remove(&sector);
 
         Ok(())
     }
 
+    /// Drop every block that is not dirty; returns how many were dropped
+    pub fn drop_clean(&mut self) -> usize {
+        let before = self.blocks.len();
+        self.blocks.retain(|_, entry| entry.dirty);
+        before - self.blocks.len()
+    }
+
     /// Invalidate a cached block (e.g., after external modification)
     #[allow(dead_code)]
     pub fn invalidate(&mut self, sector: u64) {
@@ -241,7 +392,7 @@ impl BufferCache {
 
     /// Clear the entire cache (flushes dirty blocks first)
     #[allow(dead_code)]
-    pub fn clear(&mut self, dev: &mut BlockDev) -> Result<(), &'static str> {
+    pub fn clear(&mut self, dev: &BlockDev) -> Result<(), &'static str> {
         self.sync(dev)?;
         self.blocks.clear();
         Ok(())
@@ -258,77 +409,214 @@ impl BufferCache {
     }
 }
 
-/// File system state with block caching
-pub struct FileSystemState {
+/// The first sector of the block bitmap
+struct Bitmap {
     // Only cache first sector of bitmap for now to save RAM
     // A production FS would cache on demand
-    bitmap_cache: [u8; 512],
-    bitmap_dirty: bool,
+    cache: [u8; 512],
+    dirty: bool,
+}
+
+/// File system state with block caching, shared by every hart (see the
+/// module docs for the locking)
+pub struct FileSystemState {
+    /// Allocator lock
+    bitmap: Mutex<Bitmap>,
     /// Block cache for improved performance
-    cache: BufferCache,
+    cache: Mutex<BufferCache>,
+    /// Held while a directory entry is created, removed or renamed
+    dir: Mutex<()>,
+    /// Held by whoever is changing a file, by path
+    files: NameLocks,
+    format: Spinlock<Format>,
+    /// Mounted read-only because the format is newer than this kernel
+    read_only: bool,
 }
 
 impl FileSystemState {
-    pub fn init(dev: &mut BlockDev) -> Option<Self> {
+    pub fn init(dev: &BlockDev) -> Result<Self, &'static str> {
         let mut buf = [0u8; 512];
-        if dev.read_sector(SEC_SUPER, &mut buf).is_err() {
-            return None;
-        }
+        dev.read_sector(SEC_SUPER, &mut buf)?;
 
         let magic = u32::from_le_bytes(buf[0..4].try_into().unwrap());
         if magic != MAGIC {
-            return None;
+            return Err("Not an SFS image");
+        }
+        let format = Format::parse(&buf);
+        if !format.is_supported() {
+            return Err("Unsupported format revision");
         }
 
         // Load first sector of bitmap
-        if dev.read_sector(SEC_MAP_START, &mut buf).is_err() {
-            return None;
+        dev.read_sector(SEC_MAP_START, &mut buf)?;
+
+        Ok(Self {
+            bitmap: Mutex::new(Bitmap { cache: buf, dirty: false }),
+            cache: Mutex::new(BufferCache::new()),
+            dir: Mutex::new(()),
+            files: NameLocks::new(),
+            format: Spinlock::new(format),
+            read_only: format.is_newer(),
+        })
+    }
+
+    /// Format revision and features of the mounted image
+    pub fn format(&self) -> Format {
+        *self.format.lock()
+    }
+
+    /// Whether the image is mounted read-only (its format is newer)
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    fn check_writable(&self) -> Result<(), &'static str> {
+        if self.read_only {
+            return Err("Read-only file system");
         }
+        Ok(())
+    }
 
-        Some(Self {
-            bitmap_cache: buf,
-            bitmap_dirty: false,
-            cache: BufferCache::new(),
-        })
+    /// Copy a block out of the cache
+    fn block(&self, dev: &BlockDev, sector: u64) -> Result<[u8; 512], &'static str> {
+        Ok(*self.cache.lock().read(dev, sector)?)
+    }
+
+    /// Change a block in the cache and mark it dirty
+    fn modify<R>(
+        &self,
+        dev: &BlockDev,
+        sector: u64,
+        change: impl FnOnce(&mut [u8; 512]) -> R,
+    ) -> Result<R, &'static str> {
+        let mut cache = self.cache.lock();
+        let result = change(cache.read_mut(dev, sector)?);
+        cache.mark_dirty(sector);
+        Ok(result)
+    }
+
+    /// Record in the superblock that the image now uses `feature`
+    fn note_feature(&self, dev: &BlockDev, feature: u32) -> Result<(), &'static str> {
+        {
+            let mut format = self.format.lock();
+            if format.is_legacy() || format.features & feature != 0 {
+                return Ok(());
+            }
+            format.features |= feature;
+        }
+        // Written from the current flags, so the last writer has them all
+        self.modify(dev, SEC_SUPER, |buf| self.format.lock().write(buf))
+    }
+
+    /// Migrate the image in place to revision FORMAT_MAJOR.FORMAT_MINOR, one
+    /// step at a time, and sync. Returns the revision it had before.
+    pub fn upgrade(&mut self, dev: &BlockDev) -> Result<Format, &'static str> {
+        if self.read_only {
+            return Err("Image format is newer than this kernel");
+        }
+        let before = self.format();
+        while self.format().minor < FORMAT_MINOR {
+            match self.format().minor {
+                0 => self.upgrade_from_1_0(dev)?,
+                _ => return Err("No migration from this format revision"),
+            }
+        }
+        if self.format() != before {
+            self.modify(dev, SEC_SUPER, |buf| self.format.lock().write(buf))?;
+            self.sync(dev)?;
+        }
+        Ok(before)
+    }
+
+    /// 1.0 -> 1.1: adds the version and feature flags to the superblock; the
+    /// rest of the layout is unchanged. Must match mkfs.
+    fn upgrade_from_1_0(&mut self, dev: &BlockDev) -> Result<(), &'static str> {
+        let mut features = 0;
+        let mut cache = self.cache.lock();
+        for sector in SEC_DIR_START..SEC_DIR_START + SEC_DIR_COUNT {
+            let buf = cache.read(dev, sector)?;
+            for j in 0..ENTRIES_PER_SECTOR {
+                let offset = j * DIR_ENTRY_SIZE;
+                if buf[offset] == 0 {
+                    continue;
+                }
+                let size = u32::from_le_bytes(buf[offset + 64..offset + 68].try_into().unwrap());
+                if size & SYMLINK_FLAG != 0 {
+                    features |= FEATURE_SYMLINKS;
+                }
+                if size & COMPRESSED_FLAG != 0 {
+                    features |= FEATURE_LZ4;
+                }
+            }
+        }
+        *self.format.lock() = Format { major: 1, minor: 1, features };
+        Ok(())
     }
 
-    /// Sync all cached data to disk
-    pub fn sync(&mut self, dev: &mut BlockDev) -> Result<usize, &'static str> {
-        // Sync bitmap if dirty
-        if self.bitmap_dirty {
-            dev.write_sector(SEC_MAP_START, &self.bitmap_cache)?;
-            self.bitmap_dirty = false;
+    /// Sync all cached data to disk, in order: file data, a barrier, then
+    /// the bitmap and directory, then a second barrier.
+    ///
+    /// Metadata is only written once the blocks it points at are on the card,
+    /// so a power cut mid-sync leaves either the old directory or the new one
+    /// with its data, never an entry pointing at unwritten blocks. The
+    /// allocator and cache stay locked throughout, so nothing changed on
+    /// another hart slips in between the two passes.
+    pub fn sync(&self, dev: &BlockDev) -> Result<usize, &'static str> {
+        let mut bitmap = self.bitmap.lock();
+        let mut cache = self.cache.lock();
+
+        let data = cache.sync_where(dev, |sector| sector >= SEC_DATA_START)?;
+        if data > 0 {
+            dev.barrier()?;
+        }
+
+        let mut metadata = 0;
+        if bitmap.dirty {
+            dev.write_sector(SEC_MAP_START, &bitmap.cache)?;
+            bitmap.dirty = false;
+            metadata += 1;
+        }
+        metadata += cache.sync(dev)?;
+        if metadata > 0 {
+            dev.barrier()?;
         }
 
-        // Sync block cache
-        self.cache.sync(dev)
+        Ok(data + metadata)
     }
 
     /// Get cache statistics: (hits, misses, writebacks, cached_blocks)
     pub fn cache_stats(&self) -> (u64, u64, u64, usize) {
-        self.cache.stats()
+        self.cache.lock().stats()
+    }
+
+    /// Drop the cached blocks that match the disk (out-of-memory path)
+    pub fn drop_clean_blocks(&mut self) -> usize {
+        self.cache.get_mut().drop_clean()
     }
 
     /// Get number of dirty blocks waiting to be written
     pub fn dirty_blocks(&self) -> usize {
-        self.cache.dirty_count() + if self.bitmap_dirty { 1 } else { 0 }
+        let bitmap_dirty = self.bitmap.lock().dirty;
+        self.cache.lock().dirty_count() + if bitmap_dirty { 1 } else { 0 }
     }
 
     /// Get disk usage statistics: (used_blocks, total_blocks)
-    /// 
+    ///
     /// This counts set bits in the bitmap to determine used blocks.
     /// The bitmap tracks which 512-byte sectors are allocated.
     pub fn disk_stats(&self) -> (u64, u64) {
+        let bitmap = self.bitmap.lock();
+
         // Count set bits in the bitmap (used blocks)
         let mut used_blocks: u64 = 0;
-        for byte in self.bitmap_cache.iter() {
+        for byte in bitmap.cache.iter() {
             used_blocks += byte.count_ones() as u64;
         }
-        
+
         // Total blocks in the first bitmap sector = 512 * 8 = 4096 blocks
         // Each bit represents one 512-byte block
-        let total_blocks: u64 = (self.bitmap_cache.len() * 8) as u64;
-        
+        let total_blocks: u64 = (bitmap.cache.len() * 8) as u64;
+
         (used_blocks, total_blocks)
     }
 
@@ -340,14 +628,17 @@ impl FileSystemState {
 
     /// List all files in the root directory
     /// Returns a Vec of FileInfo structs for use by the scripting engine
-    pub fn list_dir(&mut self, dev: &mut BlockDev, _path: &str) -> Vec<FileInfo> {
+    pub fn list_dir(&self, dev: &BlockDev, _path: &str) -> Vec<FileInfo> {
         let mut entries = Vec::new();
+        // (index, entry) of compressed files, sized once the scan is done
+        let mut compressed = Vec::new();
         let mut consecutive_empty = 0;
 
+        let mut cache = self.cache.lock();
         for i in 0..SEC_DIR_COUNT {
             let sector = SEC_DIR_START + i;
             // Use cache for faster repeated access
-            let buf = match self.cache.read_mut(dev, sector) {
+            let buf = match cache.read(dev, sector) {
                 Ok(b) => b,
                 Err(_) => break,
             };
@@ -368,9 +659,12 @@ impl FileSystemState {
                     .unwrap_or("???")
                     .into();
 
+                if entry.is_compressed() {
+                    compressed.push((entries.len(), *entry));
+                }
                 entries.push(FileInfo {
                     name,
-                    size: entry.size,
+                    size: entry.len(),
                     is_dir: false, // Simple FS - everything is a file
                 });
             }
@@ -386,18 +680,23 @@ impl FileSystemState {
                 consecutive_empty = 0;
             }
         }
+        drop(cache);
+
+        for (index, entry) in compressed {
+            entries[index].size = self.content_len(dev, &entry) as u32;
+        }
         entries
     }
 
     /// Legacy ls function that prints directly to UART
-    pub fn ls(&mut self, dev: &mut BlockDev) {
+    pub fn ls(&self, dev: &BlockDev) {
         crate::uart::write_line("SIZE        NAME");
         crate::uart::write_line("----------  --------------------");
 
         let mut consecutive_empty = 0;
         for i in 0..SEC_DIR_COUNT {
             let sector = SEC_DIR_START + i;
-            let buf = match self.cache.read_mut(dev, sector) {
+            let buf = match self.block(dev, sector) {
                 Ok(b) => b,
                 Err(_) => break,
             };
@@ -417,10 +716,11 @@ impl FileSystemState {
                 let name = core::str::from_utf8(&entry.name[..name_len]).unwrap_or("???");
 
                 // Print
-                crate::uart::write_u64(entry.size as u64);
-                if entry.size < 10 {
+                let size = entry.len();
+                crate::uart::write_u64(size as u64);
+                if size < 10 {
                     crate::uart::write_str("         ");
-                } else if entry.size < 100 {
+                } else if size < 100 {
                     crate::uart::write_str("        ");
                 } else {
                     crate::uart::write_str("       ");
@@ -439,57 +739,199 @@ impl FileSystemState {
         }
     }
 
-    pub fn read_file(&self, dev: &mut BlockDev, filename: &str) -> Option<Vec<u8>> {
-        use crate::device::uart::{write_str, write_line};
-   
-        
-        let entry = match self.find_entry(dev, filename) {
-            Some(e) => {
-                e
-            },
-            None => {
-                return None;
-            },
-        };
-        
-        let mut data = Vec::with_capacity(entry.size as usize);
-        let mut next = entry.head;
-        let mut buf = [0u8; 512];
+    /// Read a regular file (symlinks are resolved by the caller, see `readlink`)
+    pub fn read_file(&self, dev: &BlockDev, filename: &str) -> Option<Vec<u8>> {
+        let entry = self.find_entry(dev, filename)?;
+        if entry.is_symlink() {
+            return None;
+        }
+        self.read_data(dev, &entry)
+    }
+
+    /// Create a symbolic link at `path` pointing to `target`
+    pub fn symlink(&self, dev: &BlockDev, target: &str, path: &str) -> Result<(), &'static str> {
+        self.check_writable()?;
+        if target.is_empty() {
+            return Err("Empty link target");
+        }
+        let _file = self.files.lock(path);
+        if self.find_entry_pos(dev, path).is_some() {
+            return Err("File exists");
+        }
+
+        self.write_file_locked(dev, path, target.as_bytes(), SYMLINK_FLAG)?;
+        self.note_feature(dev, FEATURE_SYMLINKS)
+    }
 
-        while next != 0 && (data.len() < entry.size as usize) {
-            if dev.read_sector(next as u64, &mut buf).is_err() {
-                return None;
+    /// Read the target of a symbolic link (None if `path` is not a link)
+    pub fn readlink(&self, dev: &BlockDev, path: &str) -> Option<String> {
+        let entry = self.find_entry(dev, path)?;
+        if !entry.is_symlink() {
+            return None;
+        }
+        let data = self.read_data(dev, &entry)?;
+        String::from_utf8(data).ok()
+    }
+
+    /// The shortest symlink among the leading components of `path` (the
+    /// last one only if `follow_last`), with its target, found in one pass
+    /// over the directory. Images that never had a link are not scanned.
+    pub fn first_symlink(&self, dev: &BlockDev, path: &str, follow_last: bool) -> Option<(String, String)> {
+        let format = self.format();
+        if !format.is_legacy() && format.features & FEATURE_SYMLINKS == 0 {
+            return None;
+        }
+        let mut found: Option<(String, DirEntry)> = None;
+        let mut cache = self.cache.lock();
+        for i in 0..SEC_DIR_COUNT {
+            let buf = cache.read(dev, SEC_DIR_START + i).ok()?;
+            for j in 0..ENTRIES_PER_SECTOR {
+                let offset = j * DIR_ENTRY_SIZE;
+                if buf[offset] == 0 {
+                    continue;
+                }
+                let entry = unsafe { &*(buf[offset..offset + DIR_ENTRY_SIZE].as_ptr() as *const DirEntry) };
+                if !entry.is_symlink() {
+                    continue;
+                }
+                let len = entry.name.iter().position(|&c| c == 0).unwrap_or(64);
+                let name = core::str::from_utf8(&entry.name[..len]).unwrap_or("");
+                let covers = (follow_last && path == name)
+                    || path.strip_prefix(name).is_some_and(|rest| rest.starts_with('/'));
+                if covers && found.as_ref().is_none_or(|(shortest, _)| name.len() < shortest.len()) {
+                    found = Some((String::from(name), *entry));
+                }
             }
+        }
+        drop(cache);
+        let (link, entry) = found?;
+        let target = String::from_utf8(self.read_data(dev, &entry)?).ok()?;
+        Some((link, target))
+    }
+
+    /// Follow an entry's block chain and collect its data
+    fn read_data(&self, dev: &BlockDev, entry: &DirEntry) -> Option<Vec<u8>> {
+        let size = entry.len() as usize;
+        let mut data = Vec::with_capacity(size);
+        let mut next = entry.head;
+
+        // Read through the cache so blocks still waiting for writeback are visible
+        while next != 0 && (data.len() < size) {
+            let buf = self.block(dev, next as u64).ok()?;
             let next_ptr = u32::from_le_bytes(buf[0..4].try_into().unwrap());
 
-            let remaining = entry.size as usize - data.len();
+            let remaining = size - data.len();
             let chunk = core::cmp::min(remaining, 508);
             data.extend_from_slice(&buf[4..4 + chunk]);
 
             next = next_ptr;
         }
-        
+
+        if entry.is_compressed() {
+            let original = u32::from_le_bytes(data.get(0..4)?.try_into().ok()?) as usize;
+            return crate::fs::lz4::decompress(&data[4..], original);
+        }
         Some(data)
     }
 
+    /// Read up to `buf.len()` bytes at byte `offset` of a regular file.
+    ///
+    /// Only the blocks covering the range are read (compressed files are
+    /// decompressed whole). Returns the byte count, 0 at end of file.
+    pub fn read_at(
+        &self,
+        dev: &BlockDev,
+        filename: &str,
+        offset: usize,
+        buf: &mut [u8],
+    ) -> Result<usize, &'static str> {
+        let entry = self.find_entry(dev, filename).ok_or("File not found")?;
+        if entry.is_symlink() {
+            return Err("Is a symbolic link");
+        }
+        if entry.is_compressed() {
+            let data = self.read_data(dev, &entry).ok_or("Corrupt compressed file")?;
+            let tail = data.get(offset..).unwrap_or(&[]);
+            let n = tail.len().min(buf.len());
+            buf[..n].copy_from_slice(&tail[..n]);
+            return Ok(n);
+        }
+
+        let size = entry.len() as usize;
+        let end = size.min(offset.saturating_add(buf.len()));
+        if offset >= end {
+            return Ok(0);
+        }
+
+        // Skip whole blocks before `offset`
+        let mut current = entry.head;
+        for _ in 0..offset / 508 {
+            if current == 0 {
+                return Err("Corrupt block chain");
+            }
+            let block = self.block(dev, current as u64)?;
+            current = u32::from_le_bytes(block[0..4].try_into().unwrap());
+        }
+
+        let mut pos = offset;
+        while pos < end {
+            if current == 0 {
+                return Err("Corrupt block chain");
+            }
+            let block = self.block(dev, current as u64)?;
+            let within = pos % 508;
+            let n = (508 - within).min(end - pos);
+            buf[pos - offset..pos - offset + n].copy_from_slice(&block[4 + within..4 + within + n]);
+            current = u32::from_le_bytes(block[0..4].try_into().unwrap());
+            pos += n;
+        }
+        Ok(end - offset)
+    }
+
+    /// Length of a regular file's contents (None if it does not exist)
+    pub fn file_len(&self, dev: &BlockDev, filename: &str) -> Option<usize> {
+        let entry = self.find_entry(dev, filename)?;
+        if entry.is_symlink() {
+            return None;
+        }
+        Some(self.content_len(dev, &entry))
+    }
+
+    /// Length of a file's contents: the original length for compressed files
+    fn content_len(&self, dev: &BlockDev, entry: &DirEntry) -> usize {
+        if !entry.is_compressed() || entry.head == 0 {
+            return entry.len() as usize;
+        }
+        match self.block(dev, entry.head as u64) {
+            Ok(buf) => u32::from_le_bytes(buf[4..8].try_into().unwrap()) as usize,
+            Err(_) => 0,
+        }
+    }
+
     pub fn write_file(
-        &mut self,
-        dev: &mut BlockDev,
+        &self,
+        dev: &BlockDev,
+        filename: &str,
+        data: &[u8],
+    ) -> Result<(), &'static str> {
+        self.check_writable()?;
+        let _file = self.files.lock(filename);
+        self.write_file_locked(dev, filename, data, 0)
+    }
+
+    /// `write_file` with the file already locked; `flags` go in the entry's
+    /// size field
+    fn write_file_locked(
+        &self,
+        dev: &BlockDev,
         filename: &str,
         data: &[u8],
+        flags: u32,
     ) -> Result<(), &'static str> {
-        use crate::device::uart::{write_str, write_line};
-        
-      
-        
         // Simple implementation: Overwrite existing or Create new
-        let (sector, index) = match self.find_entry_pos(dev, filename) {
-            Some(pos) => {
-                pos
-            },
-            None => {
-                self.find_free_dir_entry(dev).ok_or("Root dir full")?
-            },
+        let pos = match self.find_entry_pos(dev, filename) {
+            Some(pos) => pos,
+            None => self.create_entry(dev, filename)?,
         };
 
         // Note: This implementation leaks old blocks if overwriting (simplification)
@@ -499,159 +941,251 @@ impl FileSystemState {
         let mut head = 0;
         let mut prev = 0;
 
-        // Special case: empty file
-        if data.is_empty() {
-            // head stays 0
-        } else {
-            let mut blocks_written = 0u32;
-            while !remaining.is_empty() {
-                let current = self.alloc_block(dev).ok_or("Disk full")?;
-                if head == 0 {
-                    head = current;
-                }
+        // Empty file: head stays 0
+        while !remaining.is_empty() {
+            let current = self.alloc_block(dev).ok_or("Disk full")?;
+            if head == 0 {
+                head = current;
+            }
 
-                if prev != 0 {
-                    // Link previous (using cache)
-                    self.link_block_cached(dev, prev, current)?;
-                }
+            if prev != 0 {
+                // Link previous (using cache)
+                self.link_block_cached(dev, prev, current)?;
+            }
 
-                let len = core::cmp::min(remaining.len(), 508);
-                let mut buf = [0u8; 512];
-                // Next = 0 (for now)
-                buf[4..4 + len].copy_from_slice(&remaining[..len]);
+            let len = core::cmp::min(remaining.len(), 508);
+            let mut buf = [0u8; 512];
+            // Next = 0 (for now)
+            buf[4..4 + len].copy_from_slice(&remaining[..len]);
 
-                // Write to cache instead of directly to disk
-                self.cache.write(dev, current as u64, &buf)?;
+            // Write to cache instead of directly to disk
+            self.cache.lock().write(dev, current as u64, &buf)?;
 
-                remaining = &remaining[len..];
-                prev = current;
-                blocks_written += 1;
-            }
+            remaining = &remaining[len..];
+            prev = current;
         }
 
-        // Update Dir Entry
-        let mut name = [0u8; 64];
-        let fname_bytes = filename.as_bytes();
-        let len = core::cmp::min(fname_bytes.len(), 64);
-        name[..len].copy_from_slice(&fname_bytes[..len]);
+        // Update Dir Entry: the new chain is complete before the entry
+        // points at it
+        // Note: sync() is NOT called here - writes are cached until explicit sync()
+        // Call fs.sync() when you need durability (e.g., after closing a file)
+        self.set_entry(dev, pos, data.len() as u32 | flags, head)
+    }
+
+    /// Write `data` at byte `offset` of a file, creating it if needed.
+    ///
+    /// Only the blocks covering the written range are modified; writing past
+    /// the end grows the file (the gap is zero-filled). Returns the new size.
+    pub fn write_at(
+        &self,
+        dev: &BlockDev,
+        filename: &str,
+        offset: usize,
+        data: &[u8],
+    ) -> Result<usize, &'static str> {
+        self.check_writable()?;
+        let _file = self.files.lock(filename);
+        self.write_at_locked(dev, filename, offset, data)
+    }
 
-        let entry = DirEntry {
-            name,
-            size: data.len() as u32,
-            head,
+    /// `write_at` with the file already locked
+    fn write_at_locked(
+        &self,
+        dev: &BlockDev,
+        filename: &str,
+        offset: usize,
+        data: &[u8],
+    ) -> Result<usize, &'static str> {
+        let pos = match self.find_entry_pos(dev, filename) {
+            Some(pos) => pos,
+            None => self.create_entry(dev, filename)?,
         };
 
-        // Write Entry (using cache)
-        {
-            let buf = self.cache.read_mut(dev, sector)?;
-            let offset = index * DIR_ENTRY_SIZE;
-            let ptr = &mut buf[offset] as *mut u8 as *mut DirEntry;
-            unsafe {
-                *ptr = entry;
+        let mut entry = self.entry_at(dev, pos)?;
+        if entry.is_symlink() {
+            return Err("Is a symbolic link");
+        }
+        if entry.is_compressed() {
+            // Patching LZ4 in place is not possible: rewrite the file uncompressed
+            let mut contents = self.read_data(dev, &entry).ok_or("Corrupt compressed file")?;
+            let end = offset + data.len();
+            if contents.len() < end {
+                contents.resize(end, 0);
             }
+            contents[offset..end].copy_from_slice(data);
+            self.write_file_locked(dev, filename, &contents, 0)?;
+            return Ok(contents.len());
         }
-        self.cache.mark_dirty(sector);
 
-        // Note: sync() is NOT called here - writes are cached until explicit sync()
-        // Call fs.sync() when you need durability (e.g., after closing a file)
+        let old_len = entry.len() as usize;
+        let end = offset + data.len();
+        let new_len = old_len.max(end);
+        let blocks = (new_len + 507) / 508;
 
-        Ok(())
+        // Walk the chain, allocating past the current end
+        let mut current = entry.head;
+        let mut prev = 0;
+        for block in 0..blocks {
+            let block_start = block * 508;
+            let block_end = block_start + 508;
+
+            let fresh = current == 0;
+            if fresh {
+                current = self.alloc_block(dev).ok_or("Disk full")?;
+                self.cache.lock().write(dev, current as u64, &[0u8; 512])?;
+                if prev == 0 {
+                    entry.head = current;
+                } else {
+                    self.link_block_cached(dev, prev, current)?;
+                }
+            }
+
+            // Stale bytes between the old end and `offset`, then the new data
+            let gap = (old_len.max(block_start), offset.min(block_end));
+            let span = (offset.max(block_start), end.min(block_end));
+            let next = if (gap.0 < gap.1 && !fresh) || span.0 < span.1 {
+                self.modify(dev, current as u64, |buf| {
+                    if gap.0 < gap.1 {
+                        buf[4 + gap.0 - block_start..4 + gap.1 - block_start].fill(0);
+                    }
+                    if span.0 < span.1 {
+                        buf[4 + span.0 - block_start..4 + span.1 - block_start]
+                            .copy_from_slice(&data[span.0 - offset..span.1 - offset]);
+                    }
+                    u32::from_le_bytes(buf[0..4].try_into().unwrap())
+                })?
+            } else {
+                let buf = self.block(dev, current as u64)?;
+                u32::from_le_bytes(buf[0..4].try_into().unwrap())
+            };
+
+            prev = current;
+            current = next;
+        }
+
+        // Update Dir Entry: a reader sees the new size only once the blocks
+        // behind it are linked
+        self.set_entry(dev, pos, new_len as u32, entry.head)?;
+
+        Ok(new_len)
+    }
+
+    /// Append `data` to a file, creating it if needed. Returns the new size.
+    ///
+    /// The file stays locked from reading its length to the write, so
+    /// appends from several harts never overwrite each other.
+    pub fn append(&self, dev: &BlockDev, filename: &str, data: &[u8]) -> Result<usize, &'static str> {
+        self.check_writable()?;
+        let _file = self.files.lock(filename);
+        let len = match self.find_entry(dev, filename) {
+            Some(entry) => self.content_len(dev, &entry),
+            None => 0,
+        };
+        self.write_at_locked(dev, filename, len, data)
     }
 
     /// Link two blocks using cached writes
     fn link_block_cached(
-        &mut self,
-        dev: &mut BlockDev,
+        &self,
+        dev: &BlockDev,
         prev: u32,
         next: u32,
     ) -> Result<(), &'static str> {
-        let buf = self.cache.read_mut(dev, prev as u64)?;
-        buf[0..4].copy_from_slice(&next.to_le_bytes());
-        self.cache.mark_dirty(prev as u64);
-        Ok(())
+        self.modify(dev, prev as u64, |buf| buf[0..4].copy_from_slice(&next.to_le_bytes()))
     }
 
     // --- Helpers ---
 
-    fn find_entry(&self, dev: &mut BlockDev, name: &str) -> Option<DirEntry> {
-        if let Some((sec, idx)) = self.find_entry_pos(dev, name) {
-            let mut buf = [0u8; 512];
-            dev.read_sector(sec, &mut buf).ok()?;
-            let offset = idx * DIR_ENTRY_SIZE;
-            let entry = unsafe { &*(buf[offset..offset + DIR_ENTRY_SIZE].as_ptr() as *const DirEntry) };
-            return Some(*entry);
-        }
-        None
+    fn find_entry(&self, dev: &BlockDev, name: &str) -> Option<DirEntry> {
+        let pos = self.find_entry_pos(dev, name)?;
+        self.entry_at(dev, pos).ok()
     }
 
-    fn find_entry_pos(&self, dev: &mut BlockDev, name: &str) -> Option<(u64, usize)> {
-        use crate::device::uart::{write_str, write_hex};
-        
- 
-        
-        let mut buf = [0u8; 512];
-        let mut entries_found = 0u64;
-        
+    /// The directory entry at `(sector, index)`
+    fn entry_at(&self, dev: &BlockDev, (sector, index): (u64, usize)) -> Result<DirEntry, &'static str> {
+        let buf = self.block(dev, sector)?;
+        let offset = index * DIR_ENTRY_SIZE;
+        let entry = unsafe { &*(buf[offset..offset + DIR_ENTRY_SIZE].as_ptr() as *const DirEntry) };
+        Ok(*entry)
+    }
+
+    /// Set the size and head of the directory entry at `(sector, index)`.
+    /// The name is left alone, so this commutes with a concurrent rename.
+    fn set_entry(
+        &self,
+        dev: &BlockDev,
+        (sector, index): (u64, usize),
+        size: u32,
+        head: u32,
+    ) -> Result<(), &'static str> {
+        self.modify(dev, sector, |buf| {
+            let offset = index * DIR_ENTRY_SIZE + MAX_NAME_LEN;
+            buf[offset..offset + 4].copy_from_slice(&size.to_le_bytes());
+            buf[offset + 4..offset + 8].copy_from_slice(&head.to_le_bytes());
+        })
+    }
+
+    /// Claim a free directory entry for `name` (an empty file)
+    fn create_entry(&self, dev: &BlockDev, name: &str) -> Result<(u64, usize), &'static str> {
+        let _dir = self.dir.lock();
+        let (sector, index) = self.find_free_dir_entry(dev).ok_or("Root dir full")?;
+        let len = core::cmp::min(name.len(), MAX_NAME_LEN);
+        self.modify(dev, sector, |buf| {
+            let offset = index * DIR_ENTRY_SIZE;
+            buf[offset..offset + DIR_ENTRY_SIZE].fill(0);
+            buf[offset..offset + len].copy_from_slice(&name.as_bytes()[..len]);
+        })?;
+        Ok((sector, index))
+    }
+
+    fn find_entry_pos(&self, dev: &BlockDev, name: &str) -> Option<(u64, usize)> {
+        let mut cache = self.cache.lock();
         for i in 0..SEC_DIR_COUNT {
             let sector = SEC_DIR_START + i;
-            
-            if dev.read_sector(sector, &mut buf).is_err() {
-         
-                return None;
-            }
-            
+            let buf = cache.read(dev, sector).ok()?;
 
-            
             for j in 0..ENTRIES_PER_SECTOR {
                 let offset = j * DIR_ENTRY_SIZE;
                 if buf[offset] == 0 {
                     continue;
                 }
-                entries_found += 1;
                 let entry = unsafe { &*(buf[offset..offset + DIR_ENTRY_SIZE].as_ptr() as *const DirEntry) };
                 let len = entry.name.iter().position(|&c| c == 0).unwrap_or(64);
                 let entry_name = core::str::from_utf8(&entry.name[..len]).unwrap_or("");
-                
+
                 if entry_name == name {
                     return Some((sector, j));
                 }
             }
         }
-        
+
         None
     }
 
-    fn find_free_dir_entry(&self, dev: &mut BlockDev) -> Option<(u64, usize)> {
-        let mut buf = [0u8; 512];
-        let mut entries_checked = 0u64;
-        let mut non_empty_count = 0u64;
-        
+    fn find_free_dir_entry(&self, dev: &BlockDev) -> Option<(u64, usize)> {
+        let mut cache = self.cache.lock();
         for i in 0..SEC_DIR_COUNT {
             let sector = SEC_DIR_START + i;
-            if dev.read_sector(sector, &mut buf).is_err() {
-                return None;
-            }
+            let buf = cache.read(dev, sector).ok()?;
             for j in 0..ENTRIES_PER_SECTOR {
-                entries_checked += 1;
-                let first_byte = buf[j * DIR_ENTRY_SIZE];
-                if first_byte == 0 {
+                if buf[j * DIR_ENTRY_SIZE] == 0 {
                     return Some((sector, j));
                 }
-                non_empty_count += 1;
             }
         }
-        
+
         None
     }
 
-    fn alloc_block(&mut self, _dev: &mut BlockDev) -> Option<u32> {
+    fn alloc_block(&self, _dev: &BlockDev) -> Option<u32> {
+        let mut bitmap = self.bitmap.lock();
         // Naive: Only searches the cached first sector of bitmap
-        for i in 0..self.bitmap_cache.len() {
-            if self.bitmap_cache[i] != 0xFF {
+        for i in 0..bitmap.cache.len() {
+            if bitmap.cache[i] != 0xFF {
                 for bit in 0..8 {
-                    if (self.bitmap_cache[i] & (1 << bit)) == 0 {
-                        self.bitmap_cache[i] |= 1 << bit;
-                        self.bitmap_dirty = true;
+                    if (bitmap.cache[i] & (1 << bit)) == 0 {
+                        bitmap.cache[i] |= 1 << bit;
+                        bitmap.dirty = true;
                         // Bitmap will be synced on next fs.sync() call
 
                         let sector = (i * 8 + bit) as u32;
@@ -667,7 +1201,7 @@ impl FileSystemState {
     }
 
     #[allow(dead_code)]
-    fn link_block(&self, dev: &mut BlockDev, prev: u32, next: u32) -> Result<(), &'static str> {
+    fn link_block(&self, dev: &BlockDev, prev: u32, next: u32) -> Result<(), &'static str> {
         let mut buf = [0u8; 512];
         dev.read_sector(prev as u64, &mut buf)?;
         buf[0..4].copy_from_slice(&next.to_le_bytes());
@@ -677,7 +1211,8 @@ impl FileSystemState {
     /// Create a directory (creates a placeholder file with trailing /)
     /// In SFS, directories are represented by files with names ending in /
     /// and containing references to their children
-    pub fn mkdir(&mut self, dev: &mut BlockDev, path: &str) -> Result<(), &'static str> {
+    pub fn mkdir(&self, dev: &BlockDev, path: &str) -> Result<(), &'static str> {
+        self.check_writable()?;
         // Normalize path - ensure it ends with /
         let dir_path = if path.ends_with('/') {
             String::from(path)
@@ -686,6 +1221,7 @@ impl FileSystemState {
             s.push('/');
             s
         };
+        let _file = self.files.lock(&dir_path);
 
         // Check if directory already exists
         if self.find_entry_pos(dev, &dir_path).is_some() {
@@ -694,13 +1230,21 @@ impl FileSystemState {
 
         // Create a placeholder file for the directory
         // The directory "file" contains a simple marker
-        self.write_file(dev, &dir_path, b"DIR")?;
+        self.write_file_locked(dev, &dir_path, b"DIR", 0)?;
 
         Ok(())
     }
 
     /// Remove a file or empty directory
-    pub fn remove(&mut self, dev: &mut BlockDev, path: &str) -> Result<(), &'static str> {
+    pub fn remove(&self, dev: &BlockDev, path: &str) -> Result<(), &'static str> {
+        self.check_writable()?;
+        let _file = self.files.lock(path);
+        let _dir = self.dir.lock();
+        self.remove_entry(dev, path)
+    }
+
+    /// `remove` with the file and the directory already locked
+    fn remove_entry(&self, dev: &BlockDev, path: &str) -> Result<(), &'static str> {
         let (sector, index) = self.find_entry_pos(dev, path).ok_or("File not found")?;
 
         // Check if it's a directory with children
@@ -713,32 +1257,115 @@ impl FileSystemState {
         }
 
         // Zero out the directory entry
-        let buf = self.cache.read_mut(dev, sector)?;
-        let offset = index * DIR_ENTRY_SIZE;
-        for i in 0..DIR_ENTRY_SIZE {
-            buf[offset + i] = 0;
-        }
-        self.cache.mark_dirty(sector);
+        self.modify(dev, sector, |buf| {
+            let offset = index * DIR_ENTRY_SIZE;
+            buf[offset..offset + DIR_ENTRY_SIZE].fill(0);
+        })?;
 
         // Note: This doesn't free the data blocks (simplification)
         // A production FS would mark them as free in the bitmap
 
-        self.cache.sync(dev)?;
+        // Like write_file, the change stays in the cache until the next sync()
         Ok(())
     }
 
+    /// Rename a file, symlink or directory.
+    ///
+    /// Only directory entries change (no data is copied). An existing
+    /// destination file is replaced; a directory is moved by renaming every
+    /// entry under it.
+    pub fn rename(&self, dev: &BlockDev, from: &str, to: &str) -> Result<(), &'static str> {
+        self.check_writable()?;
+        if from == to {
+            return Ok(());
+        }
+        if to.len() > MAX_NAME_LEN {
+            return Err("Name too long");
+        }
+
+        // Directory: every entry under `from/`, including its marker entry
+        let from_dir = format!("{}/", from.trim_end_matches('/'));
+        let to_dir = format!("{}/", to.trim_end_matches('/'));
+
+        // Both names and everything under them, so no file changes name
+        // (or is replaced) while it is being written
+        let _files = self.files.lock_all(&[from, to, &from_dir, &to_dir]);
+        let _dir = self.dir.lock();
+
+        // Plain file or symlink
+        if let Some(pos) = self.find_entry_pos(dev, from) {
+            if self.is_dir(dev, to) {
+                return Err("Destination is a directory");
+            }
+            if self.find_entry_pos(dev, to).is_some() {
+                self.remove_entry(dev, to)?;
+            }
+            return self.set_entry_name(dev, pos, to);
+        }
+
+        if to_dir.starts_with(&from_dir) {
+            return Err("Cannot move a directory into itself");
+        }
+
+        let children: Vec<String> = self
+            .list_dir(dev, "/")
+            .into_iter()
+            .map(|f| f.name)
+            .filter(|name| name.starts_with(&from_dir))
+            .collect();
+        if children.is_empty() {
+            return Err("File not found");
+        }
+        if self.exists(dev, to) {
+            return Err("Destination exists");
+        }
+
+        // Check every new name fits before touching anything
+        if children
+            .iter()
+            .any(|name| name.len() - from_dir.len() + to_dir.len() > MAX_NAME_LEN)
+        {
+            return Err("Name too long");
+        }
+
+        for name in &children {
+            let pos = self.find_entry_pos(dev, name).ok_or("File not found")?;
+            let new_name = format!("{}{}", to_dir, &name[from_dir.len()..]);
+            self.set_entry_name(dev, pos, &new_name)?;
+        }
+        Ok(())
+    }
+
+    /// Overwrite the name of the directory entry at `(sector, index)`
+    fn set_entry_name(
+        &self,
+        dev: &BlockDev,
+        (sector, index): (u64, usize),
+        name: &str,
+    ) -> Result<(), &'static str> {
+        if name.len() > MAX_NAME_LEN {
+            return Err("Name too long");
+        }
+
+        self.modify(dev, sector, |buf| {
+            let offset = index * DIR_ENTRY_SIZE;
+            buf[offset..offset + MAX_NAME_LEN].fill(0);
+            buf[offset..offset + name.len()].copy_from_slice(name.as_bytes());
+        })
+    }
+
     /// Check if a path exists
-    pub fn exists(&mut self, dev: &mut BlockDev, path: &str) -> bool {
+    pub fn exists(&self, dev: &BlockDev, path: &str) -> bool {
         // Root always exists
         if path == "/" {
             return true;
         }
-        
+
         // Check exact match first
         if self.find_entry_pos(dev, path).is_some() {
             return true;
         }
-        
+
         // Check with trailing slash (directories in SFS end with /)
         if !path.ends_with('/') {
             let mut dir_path = String::from(path);
@@ -746,7 +1373,7 @@ impl FileSystemState {
             if self.find_entry_pos(dev, &dir_path).is_some() {
                 return true;
             }
-            
+
             // Check if any files exist under this path prefix (it's a parent directory)
             let prefix = dir_path;
             let files = self.list_dir(dev, "/");
@@ -756,12 +1383,12 @@ impl FileSystemState {
                 }
             }
         }
-        
+
         false
     }
 
     /// Check if a path is a directory
-    pub fn is_dir(&mut self, dev: &mut BlockDev, path: &str) -> bool {
+    pub fn is_dir(&self, dev: &BlockDev, path: &str) -> bool {
         // Check if path ends with / or has children
         if path.ends_with('/') {
             return self.find_entry_pos(dev, path).is_some();
@@ -161,6 +161,11 @@ impl LogBufferState {
             return;
         }
 
+        // Kernel module filters may drop the message
+        if !crate::kmod::klog_filter(level, subsystem, message) {
+            return;
+        }
+
         let timestamp = crate::get_time_ms() as u64;
         let hart_id = crate::get_hart_id();
 
@@ -186,11 +191,12 @@ impl LogBufferState {
             crate::uart::write_line(&entry.format_colored());
         }
 
-        // Add to buffer
+        // Add to buffer (and the shared page, serialized by the same lock)
         let mut buffer = self.entries.lock();
         if buffer.len() >= LOG_BUFFER_SIZE {
             buffer.pop_front(); // Drop oldest
         }
+        crate::klog_page::publish(timestamp, level, hart_id, &entry.subsystem, &entry.message);
         buffer.push_back(entry);
 
         self.sequence.fetch_add(1, Ordering::Relaxed);
@@ -208,6 +214,30 @@ impl LogBufferState {
         buffer.iter().rev().take(count).cloned().collect()
     }
 
+    /// Visit the newest `count` entries in order without allocating or
+    /// blocking (for the panic path). Returns false if the buffer is locked.
+    pub fn try_for_each_recent<F: FnMut(&LogEntry)>(&self, count: usize, mut f: F) -> bool {
+        let Some(buffer) = self.entries.try_lock() else {
+            return false;
+        };
+        let skip = buffer.len().saturating_sub(count);
+        for entry in buffer.iter().skip(skip) {
+            f(entry);
+        }
+        true
+    }
+
+    /// Drop all but the newest `keep` entries without blocking (for the
+    /// out-of-memory path). Returns how many were dropped.
+    pub fn try_trim(&self, keep: usize) -> usize {
+        let Some(mut buffer) = self.entries.try_lock() else {
+            return 0;
+        };
+        let dropped = buffer.len().saturating_sub(keep);
+        buffer.drain(..dropped);
+        dropped
+    }
+
     /// Get all entries without removing them
     pub fn all(&self) -> Vec<LogEntry> {
         self.entries.lock().iter().cloned().collect()
@@ -6,5 +6,4 @@ pub mod net;
 pub mod output;
 pub mod ping;
 pub mod shell;
-pub mod tail;
 pub mod waitq;
\ No newline at end of file
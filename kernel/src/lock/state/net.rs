@@ -2,22 +2,38 @@
 //!
 //! Simplified network state for D1 EMAC devices (real hardware and VM emulation).
 //! Provides basic smoltcp integration without VirtIO-specific features.
+//!
+//! TCP sockets live in a `TcpSocketTable`: every listening, accepted or
+//! outgoing socket has its own slot and buffers, addressed by `TcpSocketId`.
+//!
+//! The interface is dual-stack: next to the IPv4 address it carries a
+//! link-local IPv6 address and, per the `network.ipv6` setting, a SLAAC or
+//! static global one (see `net::ipv6`).
+//!
+//! Traceroute probes go out on their own ICMP and UDP sockets with a hop
+//! limit set per probe; the replies are picked out of received frames by
+//! `net::traceroute`.
 
 use alloc::collections::VecDeque;
+use alloc::vec::Vec;
 
 use smoltcp::iface::{Interface, SocketHandle, SocketSet, Config, SocketStorage};
 use smoltcp::socket::{icmp, tcp, udp};
 use smoltcp::time::Instant;
-use smoltcp::wire::{EthernetAddress, HardwareAddress, IpAddress, IpCidr, IpEndpoint, Ipv4Address};
+use smoltcp::wire::{EthernetAddress, HardwareAddress, IpAddress, IpCidr, IpEndpoint, Ipv4Address, Ipv6Address};
 
-use crate::platform::d1_emac::{D1Emac, D1EmacDevice};
+use crate::platform::d1_emac::{self, D1Emac, D1EmacDevice};
 use crate::device::NetworkDevice;  // Trait for mac_address()
 use crate::net::config::*;
+use crate::net::ipv6::{self, IPV6_PREFIX_LEN, RS_INTERVAL_MS, RS_MAX_COUNT};
 use crate::net::server::*;
+use crate::net::sockstats::{self, FlowKey, SocketStats, PROTO_TCP, PROTO_UDP};
+use crate::net::traceroute::{self, ProbeProto, ProbeReply, ProbeResult, PROBE_BASE_PORT, PROBE_IDENT, PROBE_LOCAL_PORT};
+use crate::settings::Ipv6Mode;
 
 /// Pending loopback ping reply
 struct LoopbackReply {
-    from: Ipv4Address,
+    from: IpAddress,
     ident: u16,
     seq: u16,
 }
@@ -29,12 +45,23 @@ pub struct NetState {
     sockets: SocketSet<'static>,
     icmp_handle: SocketHandle,
     udp_handle: SocketHandle,
-    tcp_handle: SocketHandle,
+    /// Traceroute probe sockets (hop limit set per probe)
+    probe_icmp_handle: SocketHandle,
+    probe_udp_handle: SocketHandle,
     loopback_replies: VecDeque<LoopbackReply>,
-    server_sockets: TcpServerManager,
+    tcp_sockets: TcpSocketTable,
+    /// Listeners opened by userspace programs (see `TcpListener`)
+    tcp_listeners: [Option<TcpListener>; MAX_TCP_LISTENERS],
     mac: [u8; 6],
     /// Whether IP has been assigned from relay
     ip_assigned: bool,
+    /// Default IPv6 router, learned from Router Advertisements
+    ipv6_router: Option<Ipv6Address>,
+    /// Advertised /64 prefix used for SLAAC
+    ipv6_prefix: Option<[u8; 8]>,
+    /// Router Solicitations sent so far, and when the last one went out
+    rs_sent: u8,
+    rs_last_ms: i64,
 }
 
 impl NetState {
@@ -99,10 +126,15 @@ impl NetState {
         let mut udp_socket = udp::Socket::new(udp_rx_buffer, udp_tx_buffer);
         udp_socket.bind(DNS_LOCAL_PORT).ok();
 
-        // Create TCP socket
-        let tcp_rx_buffer = unsafe { tcp::SocketBuffer::new(&mut TCP_RX_DATA[..]) };
-        let tcp_tx_buffer = unsafe { tcp::SocketBuffer::new(&mut TCP_TX_DATA[..]) };
-        let tcp_socket = tcp::Socket::new(tcp_rx_buffer, tcp_tx_buffer);
+        // Traceroute probe sockets
+        let probe_icmp_rx = unsafe { icmp::PacketBuffer::new(&mut PROBE_ICMP_RX_META[..], &mut PROBE_ICMP_RX_DATA[..]) };
+        let probe_icmp_tx = unsafe { icmp::PacketBuffer::new(&mut PROBE_ICMP_TX_META[..], &mut PROBE_ICMP_TX_DATA[..]) };
+        let mut probe_icmp_socket = icmp::Socket::new(probe_icmp_rx, probe_icmp_tx);
+        probe_icmp_socket.bind(icmp::Endpoint::Ident(PROBE_IDENT)).ok();
+        let probe_udp_rx = unsafe { udp::PacketBuffer::new(&mut PROBE_UDP_RX_META[..], &mut PROBE_UDP_RX_DATA[..]) };
+        let probe_udp_tx = unsafe { udp::PacketBuffer::new(&mut PROBE_UDP_TX_META[..], &mut PROBE_UDP_TX_DATA[..]) };
+        let mut probe_udp_socket = udp::Socket::new(probe_udp_rx, probe_udp_tx);
+        probe_udp_socket.bind(PROBE_LOCAL_PORT).ok();
 
         let mut state = NetState {
             device,
@@ -110,21 +142,32 @@ impl NetState {
             sockets,
             icmp_handle: SocketHandle::default(),
             udp_handle: SocketHandle::default(),
-            tcp_handle: SocketHandle::default(),
+            probe_icmp_handle: SocketHandle::default(),
+            probe_udp_handle: SocketHandle::default(),
             loopback_replies: VecDeque::new(),
-            server_sockets: TcpServerManager::new(),
+            tcp_sockets: TcpSocketTable::new(),
+            tcp_listeners: [None; MAX_TCP_LISTENERS],
             mac,
             ip_assigned: false,
+            ipv6_router: None,
+            ipv6_prefix: None,
+            rs_sent: 0,
+            rs_last_ms: 0,
         };
 
         state.icmp_handle = state.sockets.add(icmp_socket);
         state.udp_handle = state.sockets.add(udp_socket);
-        state.tcp_handle = state.sockets.add(tcp_socket);
+        state.probe_icmp_handle = state.sockets.add(probe_icmp_socket);
+        state.probe_udp_handle = state.sockets.add(probe_udp_socket);
+        state.configure_ipv6();
 
         Ok(state)
     }
 
     /// Poll the network stack
+    ///
+    /// Once RX interrupts are known to work, smoltcp is only polled when a
+    /// frame is pending or one of its timers (or queued data) is due.
     pub fn poll(&mut self, timestamp_ms: i64) {
         let timestamp = Instant::from_millis(timestamp_ms);
         
@@ -133,24 +176,118 @@ impl NetState {
             if let Some(ip_bytes) = self.device.get_config_ip() {
                 let new_ip = Ipv4Address::new(ip_bytes[0], ip_bytes[1], ip_bytes[2], ip_bytes[3]);
                 
-                // Update interface IP
-                self.iface.update_ip_addrs(|addrs| {
-                    addrs.clear();
-                    addrs.push(IpCidr::new(IpAddress::Ipv4(new_ip), PREFIX_LEN)).ok();
-                });
-                
-                // Update global IP
-                unsafe { MY_IP_ADDR = new_ip; }
-                
+                // Update interface and global IP
+                self.set_ipv4_addr(new_ip);
                 self.ip_assigned = true;
             }
         }
+
+        // Pick up Router Advertisements, or ask for one while SLAAC is waiting
+        if let Some(advert) = ipv6::take_router_advert() {
+            self.ipv6_router = Some(advert.router);
+            if advert.prefix.is_some() {
+                self.ipv6_prefix = advert.prefix;
+            }
+            self.configure_ipv6();
+        } else if crate::settings::ipv6_mode() == Ipv6Mode::Slaac
+            && self.ipv6_router.is_none()
+            && self.rs_sent < RS_MAX_COUNT
+            && timestamp_ms - self.rs_last_ms >= RS_INTERVAL_MS
+        {
+            let _ = self.device.transmit(&ipv6::router_solicitation(&self.mac));
+            self.rs_sent += 1;
+            self.rs_last_ms = timestamp_ms;
+        }
         
-        self.iface.poll(
-            timestamp,
-            &mut D1EmacDevice(&mut self.device),
-            &mut self.sockets,
-        );
+        if !self.idle(timestamp) {
+            self.iface.poll(
+                timestamp,
+                &mut D1EmacDevice(&mut self.device),
+                &mut self.sockets,
+            );
+        }
+        self.tcp_reap_closed();
+    }
+
+    /// Nothing for smoltcp to do: no frame pending and no timer due
+    fn idle(&mut self, timestamp: Instant) -> bool {
+        if !d1_emac::rx_interrupts_active() || d1_emac::take_rx_pending() {
+            return false;
+        }
+        match self.iface.poll_at(timestamp, &self.sockets) {
+            Some(at) => at > timestamp,
+            None => true,
+        }
+    }
+
+    /// Configure a static IP address (stops waiting for relay assignment)
+    pub fn set_static_ip(&mut self, ip: Ipv4Address) {
+        self.set_ipv4_addr(ip);
+        self.ip_assigned = true;
+    }
+
+    /// Replace the interface's IPv4 address, keeping its IPv6 ones
+    fn set_ipv4_addr(&mut self, ip: Ipv4Address) {
+        self.iface.update_ip_addrs(|addrs| {
+            addrs.retain(|cidr| !matches!(cidr.address(), IpAddress::Ipv4(_)));
+            addrs.push(IpCidr::new(IpAddress::Ipv4(ip), PREFIX_LEN)).ok();
+        });
+        unsafe { MY_IP_ADDR = ip; }
+    }
+
+    /// Set the IPv6 addresses and default route from the `network.ipv6`
+    /// setting and what the router advertised (at start, on change and per RA)
+    pub fn configure_ipv6(&mut self) {
+        let mode = crate::settings::ipv6_mode();
+        let global = match mode {
+            Ipv6Mode::Off => None,
+            Ipv6Mode::Slaac => self.ipv6_prefix.map(|prefix| ipv6::address_from_prefix(prefix, &self.mac)),
+            Ipv6Mode::Static => Some(crate::settings::ipv6_static_address()),
+        };
+        let link_local = ipv6::link_local(&self.mac);
+
+        self.iface.update_ip_addrs(|addrs| {
+            addrs.retain(|cidr| !matches!(cidr.address(), IpAddress::Ipv6(_)));
+            if mode != Ipv6Mode::Off {
+                addrs.push(IpCidr::new(IpAddress::Ipv6(link_local), IPV6_PREFIX_LEN)).ok();
+            }
+            if let Some(addr) = global {
+                addrs.push(IpCidr::new(IpAddress::Ipv6(addr), IPV6_PREFIX_LEN)).ok();
+            }
+        });
+
+        match self.ipv6_router {
+            Some(router) if mode != Ipv6Mode::Off => {
+                self.iface.routes_mut().add_default_ipv6_route(router).ok();
+            }
+            _ => {
+                self.iface.routes_mut().remove_default_ipv6_route();
+            }
+        }
+        if mode == Ipv6Mode::Slaac && self.ipv6_router.is_none() {
+            // Solicit again (e.g. after switching to SLAAC)
+            self.rs_sent = 0;
+        }
+    }
+
+    /// IPv6 addresses of the interface (link-local first)
+    pub fn ipv6_addresses(&self) -> Vec<Ipv6Address> {
+        self.iface.ip_addrs().iter()
+            .filter_map(|cidr| match cidr.address() {
+                IpAddress::Ipv6(addr) => Some(addr),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Default IPv6 router, if one has advertised itself
+    pub fn ipv6_router(&self) -> Option<Ipv6Address> {
+        self.ipv6_router
+    }
+
+    /// Go back to the address assigned by the relay (picked up by the next poll)
+    pub fn use_relay_ip(&mut self) {
+        self.ip_assigned = false;
     }
 
     /// Get MAC address
@@ -239,7 +376,9 @@ impl NetState {
         // Try to receive
         match socket.recv_slice(buf) {
             Ok((len, meta)) => {
-                let IpAddress::Ipv4(src_ip) = meta.endpoint.addr;
+                let IpAddress::Ipv4(src_ip) = meta.endpoint.addr else {
+                    return None;
+                };
                 Some((src_ip, meta.endpoint.port, len))
             }
             Err(_) => None,
@@ -247,28 +386,49 @@ impl NetState {
     }
 
     // =========================================================================
-    // TCP SERVER METHODS (for tcpd/httpd)
+    // TCP SOCKET TABLE
     // =========================================================================
 
-    /// Listen on a TCP port (returns socket ID for accepting connections)
-    pub fn tcp_listen(&mut self, port: u16) -> Result<TcpSocketId, &'static str> {
-        // Allocate a server socket slot
-        let socket_id = self.server_sockets.allocate()
-            .ok_or("No server socket slots available")?;
-        
-        // Get server socket buffers
+    /// Create a TCP socket in a free slot of the socket table
+    fn tcp_allocate(&mut self) -> Result<(TcpSocketId, tcp::Socket<'static>), &'static str> {
+        let socket_id = self.tcp_sockets.allocate()
+            .ok_or("No TCP socket slots available")?;
         let (rx_data, tx_data) = unsafe {
             let idx = socket_id as usize;
-            if idx >= MAX_SERVER_SOCKETS {
-                return Err("Invalid socket index");
-            }
-            (&mut TCP_SERVER_RX_DATA[idx][..], &mut TCP_SERVER_TX_DATA[idx][..])
+            (&mut TCP_RX_DATA[idx][..], &mut TCP_TX_DATA[idx][..])
         };
-        
-        // Create TCP socket
-        let tcp_rx_buffer = tcp::SocketBuffer::new(rx_data);
-        let tcp_tx_buffer = tcp::SocketBuffer::new(tx_data);
-        let mut tcp_socket = tcp::Socket::new(tcp_rx_buffer, tcp_tx_buffer);
+        Ok((socket_id, tcp::Socket::new(tcp::SocketBuffer::new(rx_data), tcp::SocketBuffer::new(tx_data))))
+    }
+
+    /// smoltcp socket for an open slot
+    fn tcp_socket(&mut self, socket_id: TcpSocketId) -> Result<&mut tcp::Socket<'static>, &'static str> {
+        let handle = self.tcp_sockets.handle(socket_id).ok_or("Invalid socket ID")?;
+        Ok(self.sockets.get_mut::<tcp::Socket>(handle))
+    }
+
+    /// Free slots of sockets closed with `tcp_disconnect` once their connection is gone
+    fn tcp_reap_closed(&mut self) {
+        for id in 0..MAX_TCP_SOCKETS as TcpSocketId {
+            let handle = match self.tcp_sockets.get(id) {
+                Some(slot) if slot.state == TcpSlotState::Closing => slot.handle,
+                _ => continue,
+            };
+            let done = handle.is_none_or(|h| {
+                matches!(self.sockets.get::<tcp::Socket>(h).state(), tcp::State::Closed | tcp::State::TimeWait)
+            });
+            if done {
+                self.tcp_release(id);
+            }
+        }
+    }
+
+    // =========================================================================
+    // TCP SERVER METHODS (for tcpd/httpd)
+    // =========================================================================
+
+    /// Listen on a TCP port (returns socket ID for accepting connections)
+    pub fn tcp_listen(&mut self, port: u16) -> Result<TcpSocketId, &'static str> {
+        let (socket_id, mut tcp_socket) = self.tcp_allocate()?;
         
         // Listen on the port
         let local_ip = get_my_ip();
@@ -282,11 +442,11 @@ impl NetState {
         // Add socket to the socket set
         let handle = self.sockets.add(tcp_socket);
         
-        // Update server socket manager
-        if let Some(slot) = self.server_sockets.get_mut(socket_id) {
+        // Update the socket table
+        if let Some(slot) = self.tcp_sockets.get_mut(socket_id) {
             slot.handle = Some(handle);
             slot.port = port;
-            slot.state = ServerSocketState::Listening;
+            slot.state = TcpSlotState::Listening;
         }
         
         Ok(socket_id)
@@ -295,8 +455,8 @@ impl NetState {
     /// Accept an incoming connection on a listening socket
     pub fn tcp_accept(&mut self, listen_id: TcpSocketId) -> Option<(TcpSocketId, Ipv4Address, u16)> {
         let (handle, port) = {
-            let slot = self.server_sockets.get(listen_id)?;
-            if slot.state != ServerSocketState::Listening {
+            let slot = self.tcp_sockets.get(listen_id)?;
+            if slot.state != TcpSlotState::Listening {
                 return None;
             }
             (slot.handle?, slot.port)
@@ -313,8 +473,8 @@ impl NetState {
                 };
                 let remote_port = remote.port;
                 
-                if let Some(slot) = self.server_sockets.get_mut(listen_id) {
-                    slot.state = ServerSocketState::Connected;
+                if let Some(slot) = self.tcp_sockets.get_mut(listen_id) {
+                    slot.state = TcpSlotState::Connected;
                 }
                 
                 Some((listen_id, remote_ip, remote_port))
@@ -326,8 +486,8 @@ impl NetState {
                     port,
                 };
                 if socket.listen(local_endpoint).is_ok() {
-                    if let Some(slot) = self.server_sockets.get_mut(listen_id) {
-                        slot.state = ServerSocketState::Listening;
+                    if let Some(slot) = self.tcp_sockets.get_mut(listen_id) {
+                        slot.state = TcpSlotState::Listening;
                     }
                 }
                 None
@@ -336,151 +496,126 @@ impl NetState {
         }
     }
 
-    /// Get TCP server socket state as string
-    pub fn tcp_server_state(&mut self, socket_id: TcpSocketId) -> &'static str {
-        let handle = match self.server_sockets.get(socket_id).and_then(|s| s.handle) {
-            Some(h) => h,
-            None => return "Invalid",
-        };
-        let socket = self.sockets.get_mut::<tcp::Socket>(handle);
-        match socket.state() {
-            tcp::State::Closed => "Closed",
-            tcp::State::Listen => "Listen",
-            tcp::State::SynSent => "SynSent",
-            tcp::State::SynReceived => "SynReceived",
-            tcp::State::Established => "Established",
-            tcp::State::FinWait1 => "FinWait1",
-            tcp::State::FinWait2 => "FinWait2",
-            tcp::State::CloseWait => "CloseWait",
-            tcp::State::Closing => "Closing",
-            tcp::State::LastAck => "LastAck",
-            tcp::State::TimeWait => "TimeWait",
+    /// Open a listener on `port` for the userspace program running on `hart`
+    pub fn tcp_listener_open(&mut self, port: u16, hart: usize) -> Result<TcpListenerId, &'static str> {
+        if port == 0 {
+            return Err("Invalid port");
         }
+        let in_use = self.tcp_sockets.sockets.iter().any(|s| s.state == TcpSlotState::Listening && s.port == port)
+            || self.tcp_listeners.iter().flatten().any(|l| l.port == port);
+        if in_use {
+            return Err("Port already in use");
+        }
+        let index = self.tcp_listeners.iter().position(|l| l.is_none())
+            .ok_or("No listener slots available")?;
+
+        self.tcp_reap_closed();
+        let socket = self.tcp_listen(port)?;
+        self.tcp_set_owner(socket, hart);
+        self.tcp_listeners[index] = Some(TcpListener { port, socket: Some(socket), owner: hart });
+        Ok((MAX_TCP_SOCKETS + index) as TcpListenerId)
     }
 
-    /// Send data on a specific server socket
-    pub fn tcp_send_on(&mut self, socket_id: TcpSocketId, data: &[u8], timestamp_ms: i64) 
-        -> Result<usize, &'static str> 
-    {
-        let timestamp = Instant::from_millis(timestamp_ms);
-        
-        let handle = self.server_sockets.get(socket_id)
-            .and_then(|s| s.handle)
-            .ok_or("Invalid socket ID")?;
-        
-        let socket = self.sockets.get_mut::<tcp::Socket>(handle);
-        
-        if !socket.may_send() {
-            return Err("Socket cannot send");
-        }
-        
-        let sent = socket.send_slice(data)
-            .map_err(|_| "Failed to send data")?;
-        
-        // Poll to transmit
-        self.iface.poll(
-            timestamp,
-            &mut D1EmacDevice(&mut self.device),
-            &mut self.sockets,
-        );
-        
-        Ok(sent)
+    /// Index of a listener opened by `hart`
+    fn tcp_listener_index(&self, id: TcpListenerId, hart: usize) -> Option<usize> {
+        let index = (id as usize).checked_sub(MAX_TCP_SOCKETS)?;
+        self.tcp_listeners.get(index)?.filter(|l| l.owner == hart).map(|_| index)
     }
 
-    /// Close a server socket
-    pub fn tcp_close_on(&mut self, socket_id: TcpSocketId, timestamp_ms: i64) {
-        let timestamp = Instant::from_millis(timestamp_ms);
-        
-        if let Some(handle) = self.server_sockets.get(socket_id).and_then(|s| s.handle) {
-            let socket = self.sockets.get_mut::<tcp::Socket>(handle);
-            socket.close();
-            
-            self.iface.poll(
-                timestamp,
-                &mut D1EmacDevice(&mut self.device),
-                &mut self.sockets,
-            );
+    /// Take a connection waiting on a listener (non-blocking). The returned
+    /// socket belongs to the listener's owner like one from `tcp_connect`.
+    pub fn tcp_listener_accept(&mut self, id: TcpListenerId, hart: usize)
+        -> Result<Option<(TcpSocketId, Ipv4Address, u16)>, &'static str>
+    {
+        let index = self.tcp_listener_index(id, hart).ok_or("Invalid listener")?;
+        let Some(mut listener) = self.tcp_listeners[index] else {
+            return Err("Invalid listener");
+        };
+
+        // Drop a socket that is no longer ours (closed by ID behind our back)
+        listener.socket = listener.socket.filter(|&s| {
+            self.tcp_sockets.get(s).is_some_and(|slot| slot.state == TcpSlotState::Listening && slot.owner == Some(hart))
+        });
+
+        let accepted = listener.socket.and_then(|s| self.tcp_accept(s));
+        if accepted.is_some() {
+            listener.socket = None;
         }
-    }
 
-    /// Release a server socket slot back to the pool
-    pub fn tcp_release_server(&mut self, socket_id: TcpSocketId) {
-        if let Some(slot) = self.server_sockets.get_mut(socket_id) {
-            if let Some(handle) = slot.handle.take() {
-                self.sockets.remove(handle);
+        // Listen again on a fresh socket: after an accept, or when no slot was free before
+        if listener.socket.is_none() {
+            self.tcp_reap_closed();
+            if let Ok(socket) = self.tcp_listen(listener.port) {
+                self.tcp_set_owner(socket, hart);
+                listener.socket = Some(socket);
             }
-            slot.state = ServerSocketState::Free;
-            slot.port = 0;
         }
+        self.tcp_listeners[index] = Some(listener);
+        Ok(accepted)
     }
 
-    /// Receive data on a specific server socket
-    pub fn tcp_recv_on(&mut self, socket_id: TcpSocketId, buf: &mut [u8], timestamp_ms: i64) 
-        -> Result<usize, &'static str> 
-    {
-        let timestamp = Instant::from_millis(timestamp_ms);
-        
-        // Poll first to receive any pending data
-        self.iface.poll(
-            timestamp,
-            &mut D1EmacDevice(&mut self.device),
-            &mut self.sockets,
-        );
-        
-        let handle = self.server_sockets.get(socket_id)
-            .and_then(|s| s.handle)
-            .ok_or("Invalid socket ID")?;
-        
-        let socket = self.sockets.get_mut::<tcp::Socket>(handle);
-        
-        if !socket.may_recv() {
-            return Ok(0);
-        }
-        
-        match socket.recv_slice(buf) {
-            Ok(len) => Ok(len),
-            Err(_) => Ok(0),
+    /// Close a listener opened by `hart`; accepted connections stay open
+    pub fn tcp_listener_close(&mut self, id: TcpListenerId, hart: usize) -> bool {
+        let Some(index) = self.tcp_listener_index(id, hart) else {
+            return false;
+        };
+        if let Some(socket) = self.tcp_listeners[index].take().and_then(|l| l.socket) {
+            if self.tcp_is_owned_by(socket, hart) {
+                self.tcp_abort(socket);
+            }
         }
+        true
     }
 
     // =========================================================================
     // TCP CLIENT METHODS (for outgoing connections)
     // =========================================================================
 
-    /// Connect to a remote TCP server (uses the main tcp_handle)
-    pub fn tcp_connect(&mut self, dest_ip: Ipv4Address, dest_port: u16, timestamp_ms: i64) 
-        -> Result<(), &'static str> 
+    /// Connect to a remote TCP server on a socket of its own.
+    /// Returns the socket ID to use with the other `tcp_*` methods.
+    pub fn tcp_connect(&mut self, dest_ip: Ipv4Address, dest_port: u16, timestamp_ms: i64)
+        -> Result<TcpSocketId, &'static str>
     {
         let timestamp = Instant::from_millis(timestamp_ms);
-        let socket = self.sockets.get_mut::<tcp::Socket>(self.tcp_handle);
-        
-        // Abort any existing connection
-        socket.abort();
-        
-        // Get our IP and pick a local port
+        // Make room from sockets that finished closing
+        self.tcp_reap_closed();
+        let (socket_id, mut tcp_socket) = self.tcp_allocate()?;
+
+        // Ephemeral port, offset per slot so concurrent connections differ
         let local_ip = get_my_ip();
-        let local_port = 49152 + ((timestamp_ms as u16) % 16384); // Ephemeral port
-        
+        let local_port = 49152 + ((timestamp_ms as u16).wrapping_add(4099 * socket_id as u16) % 16384);
         let local_endpoint = IpEndpoint::new(IpAddress::Ipv4(local_ip), local_port);
         let remote_endpoint = IpEndpoint::new(IpAddress::Ipv4(dest_ip), dest_port);
-        
-        socket.connect(self.iface.context(), remote_endpoint, local_endpoint)
+        tcp_socket.connect(self.iface.context(), remote_endpoint, local_endpoint)
             .map_err(|_| "Failed to initiate TCP connection")?;
-        
+
+        let handle = self.sockets.add(tcp_socket);
+        if let Some(slot) = self.tcp_sockets.get_mut(socket_id) {
+            slot.handle = Some(handle);
+            slot.port = local_port;
+            slot.state = TcpSlotState::Connected;
+        }
+
         // Poll to send SYN
         self.iface.poll(
             timestamp,
             &mut D1EmacDevice(&mut self.device),
             &mut self.sockets,
         );
-        
-        Ok(())
+
+        Ok(socket_id)
     }
 
-    /// Send data on the client TCP socket
-    pub fn tcp_send(&mut self, data: &[u8], timestamp_ms: i64) -> Result<usize, &'static str> {
+    // =========================================================================
+    // TCP SOCKET METHODS (any connected socket)
+    // =========================================================================
+
+    /// Send data on a socket
+    pub fn tcp_send(&mut self, socket_id: TcpSocketId, data: &[u8], timestamp_ms: i64) 
+        -> Result<usize, &'static str> 
+    {
         let timestamp = Instant::from_millis(timestamp_ms);
-        let socket = self.sockets.get_mut::<tcp::Socket>(self.tcp_handle);
+        let socket = self.tcp_socket(socket_id)?;
         
         if !socket.may_send() {
             return Err("Socket cannot send");
@@ -499,8 +634,10 @@ impl NetState {
         Ok(sent)
     }
 
-    /// Receive data on the client TCP socket
-    pub fn tcp_recv(&mut self, buf: &mut [u8], timestamp_ms: i64) -> Result<usize, &'static str> {
+    /// Receive data on a socket
+    pub fn tcp_recv(&mut self, socket_id: TcpSocketId, buf: &mut [u8], timestamp_ms: i64) 
+        -> Result<usize, &'static str> 
+    {
         let timestamp = Instant::from_millis(timestamp_ms);
         
         // Poll first to receive any pending data
@@ -510,7 +647,7 @@ impl NetState {
             &mut self.sockets,
         );
         
-        let socket = self.sockets.get_mut::<tcp::Socket>(self.tcp_handle);
+        let socket = self.tcp_socket(socket_id)?;
         
         if !socket.may_recv() {
             return Ok(0);
@@ -522,28 +659,85 @@ impl NetState {
         }
     }
 
-    /// Close the client TCP socket
-    pub fn tcp_close(&mut self, timestamp_ms: i64) {
+    /// Close a socket gracefully (the slot stays allocated until `tcp_release`)
+    pub fn tcp_close(&mut self, socket_id: TcpSocketId, timestamp_ms: i64) {
         let timestamp = Instant::from_millis(timestamp_ms);
-        let socket = self.sockets.get_mut::<tcp::Socket>(self.tcp_handle);
-        socket.close();
         
-        self.iface.poll(
-            timestamp,
-            &mut D1EmacDevice(&mut self.device),
-            &mut self.sockets,
-        );
+        if let Ok(socket) = self.tcp_socket(socket_id) {
+            socket.close();
+            
+            self.iface.poll(
+                timestamp,
+                &mut D1EmacDevice(&mut self.device),
+                &mut self.sockets,
+            );
+        }
+    }
+
+    /// Close a socket gracefully and give up its ID; the slot is freed
+    /// once the connection is gone
+    pub fn tcp_disconnect(&mut self, socket_id: TcpSocketId, timestamp_ms: i64) {
+        if self.tcp_sockets.handle(socket_id).is_none() {
+            return;
+        }
+        self.tcp_close(socket_id, timestamp_ms);
+        if let Some(slot) = self.tcp_sockets.get_mut(socket_id) {
+            slot.state = TcpSlotState::Closing;
+            slot.owner = None;
+        }
+        self.tcp_reap_closed();
+    }
+
+    /// Abort a socket (forceful reset) and free its slot
+    pub fn tcp_abort(&mut self, socket_id: TcpSocketId) {
+        if let Ok(socket) = self.tcp_socket(socket_id) {
+            socket.abort();
+        }
+        self.tcp_release(socket_id);
     }
 
-    /// Abort the client TCP socket (forceful reset)
-    pub fn tcp_abort(&mut self) {
-        let socket = self.sockets.get_mut::<tcp::Socket>(self.tcp_handle);
-        socket.abort();
+    /// Free a socket slot immediately
+    pub fn tcp_release(&mut self, socket_id: TcpSocketId) {
+        if let Some(handle) = self.tcp_sockets.get_mut(socket_id).and_then(|s| s.handle.take()) {
+            self.sockets.remove(handle);
+        }
+        self.tcp_sockets.release(socket_id);
+    }
+
+    /// Record the hart whose userspace program owns a socket
+    pub fn tcp_set_owner(&mut self, socket_id: TcpSocketId, hart: usize) {
+        if let Some(slot) = self.tcp_sockets.get_mut(socket_id) {
+            slot.owner = Some(hart);
+        }
     }
 
-    /// Get TCP client socket state as string
-    pub fn tcp_client_state(&mut self) -> &'static str {
-        let socket = self.sockets.get_mut::<tcp::Socket>(self.tcp_handle);
+    /// Whether a socket is open and owned by `hart`
+    pub fn tcp_is_owned_by(&self, socket_id: TcpSocketId, hart: usize) -> bool {
+        self.tcp_sockets.get(socket_id)
+            .is_some_and(|s| s.state != TcpSlotState::Free && s.owner == Some(hart))
+    }
+
+    /// Close every socket and listener a userspace program left open (called when it exits)
+    pub fn tcp_close_owned(&mut self, hart: usize, timestamp_ms: i64) {
+        // Their listening sockets are owned by `hart` too and closed below
+        for listener in self.tcp_listeners.iter_mut() {
+            if listener.is_some_and(|l| l.owner == hart) {
+                *listener = None;
+            }
+        }
+        for id in 0..MAX_TCP_SOCKETS as TcpSocketId {
+            if self.tcp_is_owned_by(id, hart) {
+                self.tcp_disconnect(id, timestamp_ms);
+            }
+        }
+    }
+
+    /// Get socket state as string
+    pub fn tcp_state(&mut self, socket_id: TcpSocketId) -> &'static str {
+        let socket = match self.tcp_socket(socket_id) {
+            Ok(s) => s,
+            Err(_) => return "Invalid",
+        };
         match socket.state() {
             tcp::State::Closed => "Closed",
             tcp::State::Listen => "Listen",
@@ -559,27 +753,83 @@ impl NetState {
         }
     }
 
-    /// Alias for tcp_client_state (backward compatibility)
-    pub fn tcp_state(&mut self) -> &'static str {
-        self.tcp_client_state()
+    /// Every TCP socket and the UDP sockets, with their traffic counters
+    pub fn socket_stats(&mut self) -> Vec<SocketStats> {
+        let mut list = Vec::new();
+        for id in 0..MAX_TCP_SOCKETS as TcpSocketId {
+            let Some(handle) = self.tcp_sockets.handle(id) else {
+                continue;
+            };
+            let state = self.tcp_state(id);
+            let socket = self.sockets.get::<tcp::Socket>(handle);
+            let (local, remote) = match socket.local_endpoint() {
+                Some(local) => ((Some(local.addr), local.port), socket.remote_endpoint().map(|r| (r.addr, r.port))),
+                None => {
+                    let listen = socket.listen_endpoint();
+                    ((listen.addr, listen.port), None)
+                }
+            };
+            // A listening socket has no traffic of its own; connections count
+            let stats = match remote {
+                Some(_) => sockstats::stats(FlowKey { proto: PROTO_TCP, local_port: local.1, remote }),
+                None => None,
+            };
+            list.push(SocketStats { proto: PROTO_TCP, local, remote, state, stats: stats.unwrap_or_default() });
+        }
+        for handle in [self.udp_handle, self.probe_udp_handle] {
+            let endpoint = self.sockets.get::<udp::Socket>(handle).endpoint();
+            let stats = sockstats::stats(FlowKey { proto: PROTO_UDP, local_port: endpoint.port, remote: None });
+            list.push(SocketStats {
+                proto: PROTO_UDP,
+                local: (endpoint.addr, endpoint.port),
+                remote: None,
+                state: "Bound",
+                stats: stats.unwrap_or_default(),
+            });
+        }
+        list
+    }
+
+    /// Check if a socket is connected
+    pub fn tcp_is_connected(&mut self, socket_id: TcpSocketId) -> bool {
+        self.tcp_socket(socket_id).is_ok_and(|s| s.state() == tcp::State::Established)
+    }
+
+    /// Check if a socket is still connecting (SYN sent, waiting for SYN-ACK)
+    pub fn tcp_is_connecting(&mut self, socket_id: TcpSocketId) -> bool {
+        self.tcp_socket(socket_id)
+            .is_ok_and(|s| matches!(s.state(), tcp::State::SynSent | tcp::State::SynReceived))
+    }
+
+    /// Whether received data is waiting to be read on a socket
+    pub fn tcp_can_recv(&mut self, socket_id: TcpSocketId) -> bool {
+        self.tcp_socket(socket_id).is_ok_and(|s| s.can_recv())
+    }
+
+    /// Check if a connection failed or is gone (socket closed, or invalid ID)
+    pub fn tcp_connection_failed(&mut self, socket_id: TcpSocketId) -> bool {
+        self.tcp_socket(socket_id).map_or(true, |s| s.state() == tcp::State::Closed)
     }
 
-    /// Check if client TCP socket is connected
-    pub fn tcp_is_connected(&mut self) -> bool {
-        let socket = self.sockets.get_mut::<tcp::Socket>(self.tcp_handle);
-        socket.state() == tcp::State::Established
+    /// Whether there is room to queue data for sending on a socket
+    pub fn tcp_can_send(&mut self, socket_id: TcpSocketId) -> bool {
+        self.tcp_socket(socket_id).is_ok_and(|s| s.can_send())
     }
 
-    /// Check if client TCP socket is still connecting (SYN sent, waiting for SYN-ACK)
-    pub fn tcp_is_connecting(&mut self) -> bool {
-        let socket = self.sockets.get_mut::<tcp::Socket>(self.tcp_handle);
-        matches!(socket.state(), tcp::State::SynSent | tcp::State::SynReceived)
+    /// Whether the peer has finished sending (reads return 0 once the
+    /// buffered data is gone)
+    pub fn tcp_peer_closed(&mut self, socket_id: TcpSocketId) -> bool {
+        self.tcp_socket(socket_id).map_or(true, |s| {
+            !s.may_recv() && !matches!(s.state(), tcp::State::Listen | tcp::State::SynSent | tcp::State::SynReceived)
+        })
     }
 
-    /// Check if TCP connection failed (socket closed without establishing)
-    pub fn tcp_connection_failed(&mut self) -> bool {
-        let socket = self.sockets.get_mut::<tcp::Socket>(self.tcp_handle);
-        socket.state() == tcp::State::Closed
+    /// Whether a connection is waiting on a listener opened by `hart`, so
+    /// `tcp_listener_accept` would return it. None if there is no such listener.
+    pub fn tcp_listener_ready(&mut self, id: TcpListenerId, hart: usize) -> Option<bool> {
+        let index = self.tcp_listener_index(id, hart)?;
+        let socket = self.tcp_listeners[index].and_then(|l| l.socket);
+        Some(socket.is_some_and(|s| self.tcp_is_connected(s)))
     }
 
     // =========================================================================
@@ -606,7 +856,7 @@ impl NetState {
         // Handle loopback addresses (127.x.x.x) and self-ping locally
         if Self::is_loopback(&target) || Self::is_self(&target) {
             self.loopback_replies.push_back(LoopbackReply {
-                from: target,
+                from: IpAddress::Ipv4(target),
                 ident: ICMP_IDENT,
                 seq,
             });
@@ -676,42 +926,169 @@ impl NetState {
         !(sum as u16)
     }
 
+    /// Send an ICMPv6 echo request (ping6)
+    pub fn send_ping6(
+        &mut self,
+        target: Ipv6Address,
+        seq: u16,
+        timestamp_ms: i64,
+    ) -> Result<(), &'static str> {
+        // Loopback and our own addresses answer locally
+        if target == Ipv6Address::LOCALHOST || self.ipv6_addresses().contains(&target) {
+            self.loopback_replies.push_back(LoopbackReply {
+                from: IpAddress::Ipv6(target),
+                ident: ICMP_IDENT,
+                seq,
+            });
+            return Ok(());
+        }
+        if self.ipv6_addresses().is_empty() {
+            return Err("IPv6 is off");
+        }
+
+        let timestamp = Instant::from_millis(timestamp_ms);
+        let socket = self.sockets.get_mut::<icmp::Socket>(self.icmp_handle);
+        if !socket.can_send() {
+            return Err("ICMP socket cannot send");
+        }
+
+        // Echo request: type 128, code 0; smoltcp fills in the checksum,
+        // which covers the IPv6 pseudo-header
+        let echo_payload = b"RISCV_PING";
+        let mut icmp_packet = alloc::vec![0u8; 8 + echo_payload.len()];
+        icmp_packet[0] = 128;
+        icmp_packet[4..6].copy_from_slice(&ICMP_IDENT.to_be_bytes());
+        icmp_packet[6..8].copy_from_slice(&seq.to_be_bytes());
+        icmp_packet[8..].copy_from_slice(echo_payload);
+
+        socket.send_slice(&icmp_packet, IpAddress::Ipv6(target))
+            .map_err(|_| "Failed to send ICMPv6")?;
+
+        self.iface.poll(timestamp, &mut D1EmacDevice(&mut self.device), &mut self.sockets);
+        Ok(())
+    }
+
     /// Check for ICMP echo reply
     pub fn check_ping_reply(&mut self) -> Option<(Ipv4Address, u16, u16)> {
-        // First check loopback replies
+        match self.recv_echo_reply()? {
+            (IpAddress::Ipv4(from), ident, seq) => Some((from, ident, seq)),
+            _ => None,
+        }
+    }
+
+    /// Check for ICMPv6 echo reply
+    pub fn check_ping6_reply(&mut self) -> Option<(Ipv6Address, u16, u16)> {
+        match self.recv_echo_reply()? {
+            (IpAddress::Ipv6(from), ident, seq) => Some((from, ident, seq)),
+            _ => None,
+        }
+    }
+
+    /// Next echo reply of either IP version (loopback replies first)
+    fn recv_echo_reply(&mut self) -> Option<(IpAddress, u16, u16)> {
         if let Some(reply) = self.loopback_replies.pop_front() {
             return Some((reply.from, reply.ident, reply.seq));
         }
 
-        // Check ICMP socket for received replies
         let socket = self.sockets.get_mut::<icmp::Socket>(self.icmp_handle);
-        
-        if socket.can_recv() {
-            let mut buf = [0u8; 64];
-            if let Ok((size, addr)) = socket.recv_slice(&mut buf) {
-                // Parse ICMP echo reply using the received data
-                let data = &buf[..size];
-                if data.len() >= 8 {
-                    let icmp_type = data[0];
-                    let _icmp_code = data[1];
-                    
-                    // Check for echo reply (type 0, code 0)
-                    if icmp_type == 0 {
-                        let ident = u16::from_be_bytes([data[4], data[5]]);
-                        let seq = u16::from_be_bytes([data[6], data[7]]);
-                        
-                        // Extract source IP from address
-                        if let IpAddress::Ipv4(src_ip) = addr {
-                            if ident == ICMP_IDENT {
-                                return Some((src_ip, ident, seq));
-                            }
-                        }
-                    }
+        if !socket.can_recv() {
+            return None;
+        }
+        let mut buf = [0u8; 64];
+        let (size, addr) = socket.recv_slice(&mut buf).ok()?;
+        let data = &buf[..size];
+        if data.len() < 8 {
+            return None;
+        }
+
+        // Echo reply is type 0 in ICMPv4 and 129 in ICMPv6
+        let is_reply = match addr {
+            IpAddress::Ipv4(_) => data[0] == 0,
+            IpAddress::Ipv6(_) => data[0] == 129,
+        };
+        let ident = u16::from_be_bytes([data[4], data[5]]);
+        let seq = u16::from_be_bytes([data[6], data[7]]);
+        if is_reply && ident == ICMP_IDENT {
+            Some((addr, ident, seq))
+        } else {
+            None
+        }
+    }
+
+    // =========================================================================
+    // TRACEROUTE PROBES
+    // =========================================================================
+
+    /// Send traceroute probe `seq` to `target`, allowed `hop_limit` hops
+    ///
+    /// The answer, from a router on the way or from the target, is collected
+    /// with `check_probe_reply`. Probe 0 starts a new trace and forgets the
+    /// replies to the last one. A probe to ourselves is answered at once.
+    pub fn send_probe(
+        &mut self,
+        target: Ipv4Address,
+        proto: ProbeProto,
+        hop_limit: u8,
+        seq: u16,
+        timestamp_ms: i64,
+    ) -> Result<(), &'static str> {
+        if hop_limit == 0 {
+            return Err("Hop limit must be at least 1");
+        }
+        if seq == 0 {
+            traceroute::clear();
+        }
+        if Self::is_loopback(&target) || Self::is_self(&target) {
+            traceroute::push_reply(ProbeReply { from: target, seq, result: ProbeResult::Reached });
+            return Ok(());
+        }
+
+        let timestamp = Instant::from_millis(timestamp_ms);
+        match proto {
+            ProbeProto::Icmp => {
+                let socket = self.sockets.get_mut::<icmp::Socket>(self.probe_icmp_handle);
+                if !socket.can_send() {
+                    return Err("ICMP socket cannot send");
+                }
+                let echo_payload = b"RISCV_TRACE";
+                let mut icmp_packet = alloc::vec![0u8; 8 + echo_payload.len()];
+                icmp_packet[0] = 8; // echo request
+                icmp_packet[4..6].copy_from_slice(&PROBE_IDENT.to_be_bytes());
+                icmp_packet[6..8].copy_from_slice(&seq.to_be_bytes());
+                icmp_packet[8..].copy_from_slice(echo_payload);
+                let checksum = Self::icmp_checksum(&icmp_packet);
+                icmp_packet[2..4].copy_from_slice(&checksum.to_be_bytes());
+
+                socket.set_hop_limit(Some(hop_limit));
+                socket.send_slice(&icmp_packet, IpAddress::Ipv4(target))
+                    .map_err(|_| "Failed to send ICMP")?;
+            }
+            ProbeProto::Udp => {
+                let socket = self.sockets.get_mut::<udp::Socket>(self.probe_udp_handle);
+                if !socket.can_send() {
+                    return Err("UDP socket cannot send");
                 }
+                let port = PROBE_BASE_PORT.wrapping_add(seq);
+                socket.set_hop_limit(Some(hop_limit));
+                socket.send_slice(b"RISCV_TRACE", IpEndpoint::new(IpAddress::Ipv4(target), port))
+                    .map_err(|_| "Failed to send UDP")?;
             }
         }
-        
-        None
+
+        self.iface.poll(timestamp, &mut D1EmacDevice(&mut self.device), &mut self.sockets);
+        Ok(())
+    }
+
+    /// Reply to traceroute probe `seq`, if one has arrived
+    pub fn check_probe_reply(&mut self, seq: u16) -> Option<ProbeReply> {
+        // Echo replies also land on the probe socket; `traceroute` has
+        // already seen them, so just keep the socket's buffer from filling
+        let socket = self.sockets.get_mut::<icmp::Socket>(self.probe_icmp_handle);
+        while socket.recv().is_ok() {}
+        let socket = self.sockets.get_mut::<udp::Socket>(self.probe_udp_handle);
+        while socket.recv().is_ok() {}
+
+        traceroute::take_reply(seq)
     }
 }
 
@@ -726,17 +1103,24 @@ static mut ICMP_RX_DATA: [u8; 512] = [0; 512];
 static mut ICMP_TX_META: [icmp::PacketMetadata; 8] = [icmp::PacketMetadata::EMPTY; 8];
 static mut ICMP_TX_DATA: [u8; 512] = [0; 512];
 
+static mut PROBE_ICMP_RX_META: [icmp::PacketMetadata; 4] = [icmp::PacketMetadata::EMPTY; 4];
+static mut PROBE_ICMP_RX_DATA: [u8; 256] = [0; 256];
+static mut PROBE_ICMP_TX_META: [icmp::PacketMetadata; 4] = [icmp::PacketMetadata::EMPTY; 4];
+static mut PROBE_ICMP_TX_DATA: [u8; 256] = [0; 256];
+
 static mut UDP_RX_META: [udp::PacketMetadata; 8] = [udp::PacketMetadata::EMPTY; 8];
 static mut UDP_RX_DATA: [u8; 1024] = [0; 1024];
 static mut UDP_TX_META: [udp::PacketMetadata; 8] = [udp::PacketMetadata::EMPTY; 8];
 static mut UDP_TX_DATA: [u8; 1024] = [0; 1024];
 
-static mut TCP_RX_DATA: [u8; 8192] = [0; 8192];
-static mut TCP_TX_DATA: [u8; 4096] = [0; 4096];
+static mut PROBE_UDP_RX_META: [udp::PacketMetadata; 4] = [udp::PacketMetadata::EMPTY; 4];
+static mut PROBE_UDP_RX_DATA: [u8; 256] = [0; 256];
+static mut PROBE_UDP_TX_META: [udp::PacketMetadata; 4] = [udp::PacketMetadata::EMPTY; 4];
+static mut PROBE_UDP_TX_DATA: [u8; 256] = [0; 256];
 
-// Server socket buffers
-static mut TCP_SERVER_RX_DATA: [[u8; 2048]; MAX_SERVER_SOCKETS] = [[0; 2048]; MAX_SERVER_SOCKETS];
-static mut TCP_SERVER_TX_DATA: [[u8; 1024]; MAX_SERVER_SOCKETS] = [[0; 1024]; MAX_SERVER_SOCKETS];
+// TCP socket table buffers, one pair per slot
+static mut TCP_RX_DATA: [[u8; 8192]; MAX_TCP_SOCKETS] = [[0; 8192]; MAX_TCP_SOCKETS];
+static mut TCP_TX_DATA: [[u8; 4096]; MAX_TCP_SOCKETS] = [[0; 4096]; MAX_TCP_SOCKETS];
 
 // Type alias for backwards compatibility
 pub type D1NetState = NetState;
@@ -2,6 +2,8 @@
 //!
 //! Tracks CPU time and other metrics for shell commands.
 
+use crate::cpu::process::{IoCounters, IoKind};
+
 /// State for tracking the currently running shell command's CPU time
 pub struct ShellCmdState {
     /// Command name (limited to 32 chars)
@@ -17,6 +19,29 @@ pub struct ShellCmdState {
     pub accumulated_cpu_time: u64,
     /// Time when this shell session started
     pub session_start: u64,
+    /// Hart the current command runs on (I/O is only charged from there)
+    pub hart: usize,
+    /// Print a resource summary after every command (`timing on`)
+    pub timing: bool,
+    /// Time spent sleeping in commands, session total (ms)
+    pub sleep_ms: u64,
+    /// File bytes read by commands, session total
+    pub io_read_bytes: u64,
+    /// File bytes written by commands, session total
+    pub io_write_bytes: u64,
+    /// Syscalls and I/O of the current (or last) command, for ps and top
+    pub syscall_io: IoCounters,
+    /// Exit status of the last command (`$?`)
+    pub last_status: i32,
+}
+
+/// Snapshot of the session counters, taken before and after a command
+#[derive(Clone, Copy)]
+pub struct CmdUsage {
+    pub time_ms: u64,
+    pub sleep_ms: u64,
+    pub io_read_bytes: u64,
+    pub io_write_bytes: u64,
 }
 
 impl ShellCmdState {
@@ -29,6 +54,13 @@ impl ShellCmdState {
             is_running: false,
             accumulated_cpu_time: 0,
             session_start: 0,
+            hart: 0,
+            timing: false,
+            sleep_ms: 0,
+            io_read_bytes: 0,
+            io_write_bytes: 0,
+            syscall_io: IoCounters::new(),
+            last_status: 0,
         }
     }
 
@@ -40,8 +72,10 @@ impl ShellCmdState {
         self.name_len = copy_len;
         self.start_time = current_time;
         self.is_running = true;
+        self.hart = crate::get_hart_id();
         // Reset CPU time for this command (don't accumulate from previous commands)
         self.accumulated_cpu_time = 0;
+        self.syscall_io = IoCounters::new();
         // Allocate a real PID from the process module
         self.pid = crate::cpu::process::allocate_pid();
     }
@@ -57,6 +91,40 @@ impl ShellCmdState {
     pub fn get_name(&self) -> &str {
         core::str::from_utf8(&self.name[..self.name_len]).unwrap_or("unknown")
     }
+
+    /// Charge sleep time to the running command
+    pub fn add_sleep(&mut self, ms: u64) {
+        if self.is_running {
+            self.sleep_ms = self.sleep_ms.saturating_add(ms);
+        }
+    }
+
+    /// Charge file I/O to the running command if it comes from its hart
+    pub fn add_io(&mut self, hart: usize, read: usize, written: usize) {
+        if self.is_running && hart == self.hart {
+            self.io_read_bytes = self.io_read_bytes.saturating_add(read as u64);
+            self.io_write_bytes = self.io_write_bytes.saturating_add(written as u64);
+        }
+    }
+
+    /// Charge a syscall to the running command if it comes from its hart
+    pub fn charge_syscall(&mut self, hart: usize, kind: Option<IoKind>, bytes: u64) -> bool {
+        if self.is_running && hart == self.hart {
+            self.syscall_io.add(kind, bytes);
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn usage(&self, current_time: u64) -> CmdUsage {
+        CmdUsage {
+            time_ms: current_time,
+            sleep_ms: self.sleep_ms,
+            io_read_bytes: self.io_read_bytes,
+            io_write_bytes: self.io_write_bytes,
+        }
+    }
 }
 
 
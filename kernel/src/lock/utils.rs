@@ -7,7 +7,6 @@ use crate::{RwLock, Spinlock, lock::state::{
     output::OutputCaptureState,
     ping::PingState,
     shell::ShellCmdState,
-    tail::TailFollowState,
     waitq::WaitQueueState,
 }};
 use crate::fs::Vfs;
@@ -28,7 +27,6 @@ pub(crate) static IPC_WAITQ: Spinlock<Option<WaitQueueState>> = Spinlock::new(No
 pub(crate) static LOG_BUFFER: Spinlock<LogBufferState> = Spinlock::new(LogBufferState::new());
 pub(crate) static PING_STATE: Spinlock<Option<PingState>> = Spinlock::new(None);
 pub(crate) static COMMAND_RUNNING: Spinlock<bool> = Spinlock::new(false);
-pub(crate) static TAIL_FOLLOW_STATE: Spinlock<TailFollowState> = Spinlock::new(TailFollowState::new());
 pub(crate) static BLK_DEV: RwLock<Option<BlockDeviceState>> = RwLock::new(None);
 pub(crate) static OUTPUT_CAPTURE: Spinlock<OutputCaptureState> = Spinlock::new(OutputCaptureState::new());
 pub(crate) static SHELL_CMD_STATE: Spinlock<ShellCmdState> = Spinlock::new(ShellCmdState::new());
@@ -0,0 +1,99 @@
+//! Wait queues: block the running process until an event or a timeout.
+//!
+//! A waiter checks its condition, and if it does not hold yet the scheduler
+//! switches its tick out (`sched::block_current`) so the hart can run other
+//! work. `wake_all` only bumps the queue's event counter, which is lock-free
+//! and therefore safe from interrupt handlers; the run queue sees the counter
+//! move and makes the waiters runnable again, and they re-check.
+//!
+//! Outside a process tick (boot code, the scheduler loop), while a lock is
+//! held or inside a user program's syscall, waiting falls back to `wfi`, so
+//! the hart still sleeps until the next interrupt (at most a timer tick)
+//! rather than spinning.
+//!
+//! Unlike `state::waitq`, which only keeps a list of waiting PIDs, these
+//! queues take the waiter off the CPU.
+//!
+//! Queues made with `WaitQueue::interactive` are for user input: a process
+//! they wake gets the scheduler's interactivity boost.
+//!
+//! `IO_EVENTS` is woken by every source a descriptor can become ready from
+//! (console input, a received frame, a pipe write, a watch event), so
+//! `SYS_POLL` can wait on many descriptors at once.
+
+use core::sync::atomic::{AtomicU64, Ordering};
+
+/// Processes waiting for something to happen
+pub struct WaitQueue {
+    /// Bumped by every wake; a waiter sleeps until it moves
+    events: AtomicU64,
+    /// Waiters wait for user input and are boosted when it comes
+    interactive: bool,
+}
+
+impl WaitQueue {
+    /// Create an empty wait queue
+    pub const fn new() -> Self {
+        Self {
+            events: AtomicU64::new(0),
+            interactive: false,
+        }
+    }
+
+    /// Create a wait queue for user input (see the module docs)
+    pub const fn interactive() -> Self {
+        Self {
+            events: AtomicU64::new(0),
+            interactive: true,
+        }
+    }
+
+    /// Wake every process waiting on the queue (callable from interrupts)
+    pub fn wake_all(&self) {
+        self.events.fetch_add(1, Ordering::Release);
+    }
+
+    /// Wait until `ready` returns true or the clock reaches `until_ms` (ms
+    /// since boot). Returns whether `ready` held.
+    ///
+    /// `ready` runs again after every wake, so it must be cheap and must not
+    /// hold locks when it returns.
+    pub fn wait_until(&'static self, until_ms: i64, mut ready: impl FnMut() -> bool) -> bool {
+        loop {
+            // Read before checking: a wake after the check moves the counter
+            // past `seen` and the block below returns at once
+            let seen = self.events.load(Ordering::Acquire);
+            if ready() {
+                return true;
+            }
+            if crate::get_time_ms() >= until_ms {
+                return false;
+            }
+            if !crate::cpu::sched::block_current(&self.events, seen, until_ms.max(0) as u64, self.interactive) {
+                unsafe { core::arch::asm!("wfi", options(nomem, nostack)) };
+            }
+        }
+    }
+}
+
+impl Default for WaitQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Woken whenever a descriptor may have become ready (see the module docs)
+pub static IO_EVENTS: WaitQueue = WaitQueue::new();
+
+/// Nothing ever wakes it: sleepers only wait for their timeout
+static SLEEPERS: WaitQueue = WaitQueue::new();
+
+/// Sleep until the clock reaches `until_ms` (ms since boot)
+pub fn sleep_until(until_ms: i64) {
+    SLEEPERS.wait_until(until_ms, || false);
+}
+
+/// Sleep for `ms` milliseconds
+pub fn sleep_ms(ms: u64) {
+    sleep_until(crate::get_time_ms().saturating_add(ms as i64));
+}
@@ -15,6 +15,7 @@ mod lock;
 mod platform;   
 mod wasm;
 mod wasm_service;
+mod wasi;
 mod utils;
 mod dtb;
 mod boot;
@@ -34,6 +35,8 @@ mod net;
 mod scripting;
 mod tls;
 mod tls12;
+mod tls_server;
+mod x509;
 mod ui;
 mod constants;
 mod services;
@@ -45,21 +48,32 @@ mod trap;
 mod sbi;
 mod syscall_numbers;
 mod syscall;
+mod syscall_trace;
 mod elf_loader;
+mod panic;
+mod kmod;
+mod hibernate;
+mod tone;
+mod settings;
+mod locale;
+mod klog_page;
+mod stress;
+mod random;
+mod paging;
+mod oom;
 
 pub use cpu::CPU_TABLE;
 pub use cpu::process::PROCESS_TABLE;
 pub use sched::SCHEDULER as PROC_SCHEDULER;
 
 extern crate alloc;
-use panic_halt as _;
 use riscv_rt::entry;
 use crate::boot::init_boot;
 use crate::clint::get_time_ms;
 use crate::cpu::{HARTS_ONLINE, get_hart_id, hart_loop, sched, send_ipi};
 use crate::device::uart;
 use crate::lock::utils::{FS_STATE, NET_STATE, PING_STATE};
-use crate::utils::{ resolve_path};
+use crate::utils::{resolve_path, resolve_path_nofollow};
 use lock::state::shell::ShellCmdState;
 use crate::lock::utils::SHELL_CMD_STATE;
 
@@ -67,8 +81,8 @@ use crate::lock::utils::SHELL_CMD_STATE;
 fn main() -> ! {
     uart::Console::init();
     allocator::init();
+    device::dma::init();
     init_boot();
     hart_loop(0);
 }
 
-
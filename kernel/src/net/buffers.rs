@@ -6,9 +6,9 @@
 use smoltcp::iface::SocketStorage;
 use smoltcp::socket::{icmp, udp};
 
-use super::server::MAX_SERVER_SOCKETS;
+use super::server::MAX_TCP_SOCKETS;
 
-/// Static storage for sockets (expanded for server sockets)
+/// Static storage for sockets (ICMP, UDP and the TCP socket table)
 pub static mut SOCKET_STORAGE: [SocketStorage<'static>; 16] = [SocketStorage::EMPTY; 16];
 
 /// Static storage for ICMP buffers - need larger buffers for proper ICMP
@@ -23,10 +23,6 @@ pub static mut UDP_TX_META: [udp::PacketMetadata; 8] = [udp::PacketMetadata::EMP
 pub static mut UDP_RX_DATA: [u8; 1024] = [0; 1024];
 pub static mut UDP_TX_DATA: [u8; 1024] = [0; 1024];
 
-/// Static storage for TCP buffers (for HTTP client connections)
-pub static mut TCP_RX_DATA: [u8; 8192] = [0; 8192];
-pub static mut TCP_TX_DATA: [u8; 4096] = [0; 4096];
-
-/// Static storage for server TCP buffers
-pub static mut TCP_SERVER_RX_DATA: [[u8; 2048]; MAX_SERVER_SOCKETS] = [[0; 2048]; MAX_SERVER_SOCKETS];
-pub static mut TCP_SERVER_TX_DATA: [[u8; 1024]; MAX_SERVER_SOCKETS] = [[0; 1024]; MAX_SERVER_SOCKETS];
+/// Static storage for TCP buffers, one pair per socket table slot
+pub static mut TCP_RX_DATA: [[u8; 8192]; MAX_TCP_SOCKETS] = [[0; 8192]; MAX_TCP_SOCKETS];
+pub static mut TCP_TX_DATA: [[u8; 4096]; MAX_TCP_SOCKETS] = [[0; 4096]; MAX_TCP_SOCKETS];
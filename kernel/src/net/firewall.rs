@@ -0,0 +1,382 @@
+//! Stateless packet filter.
+//!
+//! Every IP packet the EMAC receives is checked against the rules in
+//! `/etc/firewall.rules` before smoltcp sees it; the first matching rule
+//! decides, and the policy applies when none matches. ARP and other non-IP
+//! frames always pass. The file is re-read when it changes, and `fw` edits
+//! it through `SYS_FIREWALL`.
+//!
+//! ```text
+//! # <allow|deny> <tcp|udp|icmp|any> [port N[-M]] [from ADDR[/LEN]] [established]
+//! allow tcp port 80 from 10.0.2.0/24
+//! deny tcp port 80
+//! policy allow
+//! ```
+//! Ports are destination ports. `from` matches IPv4 sources only, so such
+//! rules never match IPv6 packets. `established` (tcp only) matches segments
+//! with ACK set, i.e. anything but a new connection request: with
+//! `policy deny`, `allow tcp established` keeps outgoing connections working
+//! (and `allow udp port 10053` DNS replies). The last `policy` line wins.
+//! A file with an invalid line is rejected as a whole and the rules in force
+//! stay as they were.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use smoltcp::wire::Ipv4Address;
+
+use crate::fs::watch::{PathWatch, WATCH_ALL};
+use crate::Spinlock;
+
+/// Rule file, read at boot and whenever it changes
+pub const RULES_PATH: &str = "/etc/firewall.rules";
+static RULES_WATCH: PathWatch = PathWatch::new(RULES_PATH, WATCH_ALL);
+
+/// Most rules in force
+pub const MAX_RULES: usize = 128;
+
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_IPV6: u16 = 0x86DD;
+const IP_PROTO_ICMP: u8 = 1;
+const IP_PROTO_TCP: u8 = 6;
+const IP_PROTO_UDP: u8 = 17;
+const IP_PROTO_ICMPV6: u8 = 58;
+const TCP_FLAG_ACK: u8 = 0x10;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Allow,
+    Deny,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Proto {
+    Any,
+    Tcp,
+    Udp,
+    /// ICMP and ICMPv6
+    Icmp,
+}
+
+#[derive(Clone, Copy)]
+pub struct Rule {
+    pub action: Action,
+    pub proto: Proto,
+    /// Destination port range (tcp/udp)
+    pub ports: Option<(u16, u16)>,
+    /// IPv4 source network and prefix length
+    pub from: Option<(Ipv4Address, u8)>,
+    /// Only TCP segments with ACK set
+    pub established: bool,
+}
+
+/// A line of the rule file
+enum Line {
+    Rule(Rule),
+    Policy(Action),
+}
+
+struct RuleSet {
+    rules: Vec<(Rule, u64)>,
+    policy: Action,
+}
+
+static RULES: Spinlock<RuleSet> = Spinlock::new(RuleSet { rules: Vec::new(), policy: Action::Allow });
+/// False while there is nothing to check (no rules, policy allow)
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+/// Packets dropped since boot
+static DROPPED: AtomicU64 = AtomicU64::new(0);
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Action::Allow => "allow",
+            Action::Deny => "deny",
+        })
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let proto = match self.proto {
+            Proto::Any => "any",
+            Proto::Tcp => "tcp",
+            Proto::Udp => "udp",
+            Proto::Icmp => "icmp",
+        };
+        write!(f, "{} {}", self.action, proto)?;
+        match self.ports {
+            Some((lo, hi)) if lo == hi => write!(f, " port {}", lo)?,
+            Some((lo, hi)) => write!(f, " port {}-{}", lo, hi)?,
+            None => {}
+        }
+        if let Some((net, len)) = self.from {
+            write!(f, " from {}/{}", net, len)?;
+        }
+        if self.established {
+            f.write_str(" established")?;
+        }
+        Ok(())
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// FILTERING
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// What the filter looks at in a packet
+struct Packet {
+    proto: u8,
+    /// IPv4 source (None for IPv6)
+    src: Option<Ipv4Address>,
+    dst_port: Option<u16>,
+    tcp_flags: u8,
+}
+
+/// Whether a received Ethernet frame may go on to the network stack
+pub fn allows(frame: &[u8]) -> bool {
+    if !ACTIVE.load(Ordering::Relaxed) {
+        return true;
+    }
+    let Some(packet) = parse_packet(frame) else {
+        return true;
+    };
+    let mut set = RULES.lock();
+    let action = match set.rules.iter_mut().find(|(rule, _)| matches(rule, &packet)) {
+        Some((rule, hits)) => {
+            *hits += 1;
+            rule.action
+        }
+        None => set.policy,
+    };
+    if action == Action::Deny {
+        DROPPED.fetch_add(1, Ordering::Relaxed);
+    }
+    action == Action::Allow
+}
+
+fn matches(rule: &Rule, packet: &Packet) -> bool {
+    let proto = match rule.proto {
+        Proto::Any => true,
+        Proto::Tcp => packet.proto == IP_PROTO_TCP,
+        Proto::Udp => packet.proto == IP_PROTO_UDP,
+        Proto::Icmp => packet.proto == IP_PROTO_ICMP || packet.proto == IP_PROTO_ICMPV6,
+    };
+    if !proto {
+        return false;
+    }
+    if let Some((lo, hi)) = rule.ports {
+        match packet.dst_port {
+            Some(port) if (lo..=hi).contains(&port) => {}
+            _ => return false,
+        }
+    }
+    if let Some((net, len)) = rule.from {
+        match packet.src {
+            Some(src) if same_network(src, net, len) => {}
+            _ => return false,
+        }
+    }
+    !rule.established || packet.tcp_flags & TCP_FLAG_ACK != 0
+}
+
+fn same_network(addr: Ipv4Address, net: Ipv4Address, len: u8) -> bool {
+    let mask = if len == 0 { 0 } else { u32::MAX << (32 - len as u32) };
+    u32::from_be_bytes(addr.octets()) & mask == u32::from_be_bytes(net.octets()) & mask
+}
+
+/// Protocol, source and destination port of an IPv4 or IPv6 packet
+fn parse_packet(frame: &[u8]) -> Option<Packet> {
+    if frame.len() < 14 {
+        return None;
+    }
+    let ip = &frame[14..];
+    let (proto, src, payload) = match u16::from_be_bytes([frame[12], frame[13]]) {
+        ETHERTYPE_IPV4 => {
+            let header_len = (*ip.first()? & 0x0F) as usize * 4;
+            if ip.len() < 20 || header_len < 20 {
+                return None;
+            }
+            let src = Ipv4Address::new(ip[12], ip[13], ip[14], ip[15]);
+            // Later fragments carry no transport header
+            let fragment_offset = u16::from_be_bytes([ip[6], ip[7]]) & 0x1FFF;
+            let payload = if fragment_offset == 0 { ip.get(header_len..).unwrap_or(&[]) } else { &[] };
+            (ip[9], Some(src), payload)
+        }
+        // Extension headers are not followed: ports only for a plain header
+        ETHERTYPE_IPV6 if ip.len() >= 40 => (ip[6], None, &ip[40..]),
+        _ => return None,
+    };
+
+    let (dst_port, tcp_flags) = match proto {
+        IP_PROTO_TCP if payload.len() >= 14 => (Some(u16::from_be_bytes([payload[2], payload[3]])), payload[13]),
+        IP_PROTO_UDP if payload.len() >= 4 => (Some(u16::from_be_bytes([payload[2], payload[3]])), 0),
+        _ => (None, 0),
+    };
+    Some(Packet { proto, src, dst_port, tcp_flags })
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// RULE FILE
+// ═══════════════════════════════════════════════════════════════════════════════
+
+fn parse_line(line: &str) -> Result<Option<Line>, &'static str> {
+    let line = line.split('#').next().unwrap_or("");
+    let mut words = line.split_whitespace();
+    let Some(first) = words.next() else {
+        return Ok(None);
+    };
+    let parse_action = |word: Option<&str>| match word {
+        Some("allow") => Ok(Action::Allow),
+        Some("deny") => Ok(Action::Deny),
+        _ => Err("Expected allow or deny"),
+    };
+    if first == "policy" {
+        let action = parse_action(words.next())?;
+        if words.next().is_some() {
+            return Err("Unexpected text after the policy");
+        }
+        return Ok(Some(Line::Policy(action)));
+    }
+
+    let action = parse_action(Some(first))?;
+    let proto = match words.next() {
+        Some("any") => Proto::Any,
+        Some("tcp") => Proto::Tcp,
+        Some("udp") => Proto::Udp,
+        Some("icmp") => Proto::Icmp,
+        _ => return Err("Expected a protocol: tcp, udp, icmp or any"),
+    };
+    let mut rule = Rule { action, proto, ports: None, from: None, established: false };
+    while let Some(word) = words.next() {
+        match word {
+            "port" if rule.ports.is_none() => {
+                if !matches!(proto, Proto::Tcp | Proto::Udp) {
+                    return Err("Ports need tcp or udp");
+                }
+                rule.ports = Some(parse_ports(words.next().ok_or("Expected a port")?)?);
+            }
+            "from" if rule.from.is_none() => {
+                rule.from = Some(parse_network(words.next().ok_or("Expected an address")?)?);
+            }
+            "established" if !rule.established => {
+                if proto != Proto::Tcp {
+                    return Err("established needs tcp");
+                }
+                rule.established = true;
+            }
+            _ => return Err("Expected port, from or established"),
+        }
+    }
+    Ok(Some(Line::Rule(rule)))
+}
+
+fn parse_ports(s: &str) -> Result<(u16, u16), &'static str> {
+    let (lo, hi) = s.split_once('-').unwrap_or((s, s));
+    match (lo.parse::<u16>(), hi.parse::<u16>()) {
+        (Ok(lo), Ok(hi)) if lo <= hi => Ok((lo, hi)),
+        _ => Err("Invalid port or port range"),
+    }
+}
+
+fn parse_network(s: &str) -> Result<(Ipv4Address, u8), &'static str> {
+    let (addr, len) = s.split_once('/').unwrap_or((s, "32"));
+    let addr = crate::net::parse_ipv4(addr.as_bytes()).ok_or("Invalid IPv4 address")?;
+    match len.parse::<u8>() {
+        Ok(len) if len <= 32 => Ok((addr, len)),
+        _ => Err("Invalid prefix length"),
+    }
+}
+
+/// Parse a whole rule file. Errors carry the 1-based line number.
+fn parse_file(text: &str) -> Result<(Vec<Rule>, Action), (usize, &'static str)> {
+    let mut rules = Vec::new();
+    let mut policy = Action::Allow;
+    for (i, line) in text.lines().enumerate() {
+        match parse_line(line).map_err(|e| (i + 1, e))? {
+            Some(Line::Rule(rule)) if rules.len() < MAX_RULES => rules.push(rule),
+            Some(Line::Rule(_)) => return Err((i + 1, "Too many rules")),
+            Some(Line::Policy(action)) => policy = action,
+            None => {}
+        }
+    }
+    Ok((rules, policy))
+}
+
+/// Re-read the rule file if it changed since the last call (called
+/// periodically on hart 0)
+pub fn reload_if_changed() {
+    if RULES_WATCH.changed() {
+        if let Err(e) = load() {
+            crate::services::klogd::klog_warning("firewall", &e);
+        }
+    }
+}
+
+/// Read the rule file (a missing file means no rules). On error the rules in
+/// force are kept. Returns the number of rules loaded.
+pub fn load() -> Result<usize, String> {
+    let text = crate::cpu::fs_proxy::fs_read(RULES_PATH).unwrap_or_default();
+    let (rules, policy) = parse_file(&String::from_utf8_lossy(&text))
+        .map_err(|(line, e)| format!("{} line {}: {}; rules unchanged", RULES_PATH, line, e))?;
+    let count = rules.len();
+    let mut set = RULES.lock();
+    set.rules = rules.into_iter().map(|rule| (rule, 0)).collect();
+    set.policy = policy;
+    ACTIVE.store(count > 0 || policy == Action::Deny, Ordering::Relaxed);
+    Ok(count)
+}
+
+/// Rules in force with their hit counts, the policy, and packets dropped
+pub fn list() -> (Vec<(Rule, u64)>, Action, u64) {
+    let set = RULES.lock();
+    (set.rules.clone(), set.policy, DROPPED.load(Ordering::Relaxed))
+}
+
+/// Append a rule (or policy) line to the rule file and load it
+pub fn add(line: &str) -> Result<usize, String> {
+    let line = line.trim();
+    if parse_line(line)?.is_none() {
+        return Err(String::from("Empty rule"));
+    }
+    let mut text = String::from_utf8_lossy(&crate::cpu::fs_proxy::fs_read(RULES_PATH).unwrap_or_default()).into_owned();
+    if !text.is_empty() && !text.ends_with('\n') {
+        text.push('\n');
+    }
+    text.push_str(line);
+    text.push('\n');
+    save(&text)
+}
+
+/// Remove the `index`th rule (1-based, as listed) from the rule file and reload
+pub fn delete(index: usize) -> Result<usize, String> {
+    let text = String::from_utf8_lossy(&crate::cpu::fs_proxy::fs_read(RULES_PATH).unwrap_or_default()).into_owned();
+    let mut seen = 0;
+    let mut kept = String::new();
+    let mut found = false;
+    for line in text.lines() {
+        if let Ok(Some(Line::Rule(_))) = parse_line(line) {
+            seen += 1;
+            if seen == index {
+                found = true;
+                continue;
+            }
+        }
+        kept.push_str(line);
+        kept.push('\n');
+    }
+    if !found {
+        return Err(String::from("No such rule"));
+    }
+    save(&kept)
+}
+
+/// Check, write and load a new rule file
+fn save(text: &str) -> Result<usize, String> {
+    parse_file(text).map_err(|(line, e)| format!("line {}: {}", line, e))?;
+    crate::cpu::fs_proxy::fs_write(RULES_PATH, text.as_bytes())?;
+    load()
+}
@@ -0,0 +1,175 @@
+//! IPv6 address configuration.
+//!
+//! The interface always has a link-local address built from the MAC
+//! (EUI-64). With SLAAC, a global address is formed the same way from the
+//! /64 prefix of a Router Advertisement, and the advertising router becomes
+//! the default route.
+//!
+//! smoltcp does not hand Router Advertisements to sockets, so received
+//! frames are inspected in the device layer (`note_frame`) and the newest
+//! advertisement is picked up by `NetState::poll`.
+
+use alloc::vec::Vec;
+
+use smoltcp::wire::Ipv6Address;
+
+use crate::Spinlock;
+
+/// Prefix length of SLAAC, link-local and static addresses
+pub const IPV6_PREFIX_LEN: u8 = 64;
+
+/// Router Solicitations sent while waiting for an advertisement
+pub const RS_MAX_COUNT: u8 = 3;
+/// Delay between Router Solicitations
+pub const RS_INTERVAL_MS: i64 = 4000;
+
+const ETHERTYPE_IPV6: u16 = 0x86DD;
+const NEXT_HEADER_ICMPV6: u8 = 58;
+const ICMPV6_ROUTER_SOLICIT: u8 = 133;
+const ICMPV6_ROUTER_ADVERT: u8 = 134;
+const NDP_OPT_SOURCE_LL_ADDR: u8 = 1;
+const NDP_OPT_PREFIX_INFO: u8 = 3;
+/// "Autonomous address-configuration" flag of the Prefix Information option
+const PREFIX_FLAG_AUTONOMOUS: u8 = 0x40;
+
+/// What a Router Advertisement told us
+#[derive(Clone, Copy)]
+pub struct RouterAdvert {
+    /// Link-local address of the router
+    pub router: Ipv6Address,
+    /// First 64 bits of an autonomous /64 prefix, if one was advertised
+    pub prefix: Option<[u8; 8]>,
+}
+
+/// Newest Router Advertisement, waiting for the next poll
+static PENDING_ADVERT: Spinlock<Option<RouterAdvert>> = Spinlock::new(None);
+
+/// Interface identifier (EUI-64) for a MAC address
+fn interface_id(mac: &[u8; 6]) -> [u8; 8] {
+    [mac[0] ^ 0x02, mac[1], mac[2], 0xFF, 0xFE, mac[3], mac[4], mac[5]]
+}
+
+/// Address made of a /64 prefix and the interface identifier of `mac`
+pub fn address_from_prefix(prefix: [u8; 8], mac: &[u8; 6]) -> Ipv6Address {
+    let mut octets = [0u8; 16];
+    octets[..8].copy_from_slice(&prefix);
+    octets[8..].copy_from_slice(&interface_id(mac));
+    Ipv6Address::from(octets)
+}
+
+/// Link-local address (fe80::/64) for a MAC address
+pub fn link_local(mac: &[u8; 6]) -> Ipv6Address {
+    address_from_prefix([0xFE, 0x80, 0, 0, 0, 0, 0, 0], mac)
+}
+
+/// Whether `addr` is link-local (fe80::/10)
+pub fn is_link_local(addr: &Ipv6Address) -> bool {
+    let octets = addr.octets();
+    octets[0] == 0xFE && octets[1] & 0xC0 == 0x80
+}
+
+/// Look at a received Ethernet frame and remember it if it is a Router Advertisement
+pub fn note_frame(frame: &[u8]) {
+    if let Some(advert) = parse_router_advert(frame) {
+        *PENDING_ADVERT.lock() = Some(advert);
+    }
+}
+
+/// Take the Router Advertisement received since the last call, if any
+pub fn take_router_advert() -> Option<RouterAdvert> {
+    PENDING_ADVERT.lock().take()
+}
+
+/// Parse an Ethernet frame carrying an ICMPv6 Router Advertisement
+fn parse_router_advert(frame: &[u8]) -> Option<RouterAdvert> {
+    // Ethernet (14) + IPv6 (40) + RA header (16)
+    if frame.len() < 14 + 40 + 16 || u16::from_be_bytes([frame[12], frame[13]]) != ETHERTYPE_IPV6 {
+        return None;
+    }
+    let ip = &frame[14..];
+    // Hop limit must be 255: the advertisement came from this link
+    if ip[6] != NEXT_HEADER_ICMPV6 || ip[7] != 255 {
+        return None;
+    }
+    let payload_len = u16::from_be_bytes([ip[4], ip[5]]) as usize;
+    let icmp = ip.get(40..40 + payload_len)?;
+    if icmp.len() < 16 || icmp[0] != ICMPV6_ROUTER_ADVERT {
+        return None;
+    }
+
+    let mut src = [0u8; 16];
+    src.copy_from_slice(&ip[8..24]);
+    let mut advert = RouterAdvert { router: Ipv6Address::from(src), prefix: None };
+
+    // Options follow the 16-byte header; lengths are in units of 8 bytes
+    let mut options = &icmp[16..];
+    while options.len() >= 8 {
+        let len = options[1] as usize * 8;
+        if len == 0 || len > options.len() {
+            break;
+        }
+        if options[0] == NDP_OPT_PREFIX_INFO && len == 32 {
+            let prefix_len = options[2];
+            let flags = options[3];
+            if prefix_len == IPV6_PREFIX_LEN && flags & PREFIX_FLAG_AUTONOMOUS != 0 && advert.prefix.is_none() {
+                let mut prefix = [0u8; 8];
+                prefix.copy_from_slice(&options[16..24]);
+                advert.prefix = Some(prefix);
+            }
+        }
+        options = &options[len..];
+    }
+    Some(advert)
+}
+
+/// Build an Ethernet frame with a Router Solicitation to all routers
+pub fn router_solicitation(mac: &[u8; 6]) -> Vec<u8> {
+    let src = link_local(mac).octets();
+    // ff02::2 (all routers) and its multicast MAC
+    let mut dst = [0u8; 16];
+    dst[0] = 0xFF;
+    dst[1] = 0x02;
+    dst[15] = 0x02;
+
+    // RS: type, code, checksum, reserved (8), then the Source Link-Layer Address option (8)
+    let mut icmp = [0u8; 16];
+    icmp[0] = ICMPV6_ROUTER_SOLICIT;
+    icmp[8] = NDP_OPT_SOURCE_LL_ADDR;
+    icmp[9] = 1;
+    icmp[10..16].copy_from_slice(mac);
+    let checksum = icmpv6_checksum(&src, &dst, &icmp);
+    icmp[2..4].copy_from_slice(&checksum.to_be_bytes());
+
+    let mut frame = Vec::with_capacity(14 + 40 + icmp.len());
+    frame.extend_from_slice(&[0x33, 0x33, 0, 0, 0, 0x02]);
+    frame.extend_from_slice(mac);
+    frame.extend_from_slice(&ETHERTYPE_IPV6.to_be_bytes());
+    frame.extend_from_slice(&[0x60, 0, 0, 0]); // Version 6, no traffic class or flow label
+    frame.extend_from_slice(&(icmp.len() as u16).to_be_bytes());
+    frame.push(NEXT_HEADER_ICMPV6);
+    frame.push(255);
+    frame.extend_from_slice(&src);
+    frame.extend_from_slice(&dst);
+    frame.extend_from_slice(&icmp);
+    frame
+}
+
+/// ICMPv6 checksum over the pseudo-header and the message
+fn icmpv6_checksum(src: &[u8; 16], dst: &[u8; 16], message: &[u8]) -> u16 {
+    let mut sum: u32 = 0;
+    let mut add = |data: &[u8]| {
+        for chunk in data.chunks(2) {
+            let word = if chunk.len() == 2 { u16::from_be_bytes([chunk[0], chunk[1]]) } else { (chunk[0] as u16) << 8 };
+            sum += word as u32;
+        }
+    };
+    add(src);
+    add(dst);
+    add(&(message.len() as u32).to_be_bytes());
+    add(&[0, 0, 0, NEXT_HEADER_ICMPV6]);
+    add(message);
+    while sum > 0xFFFF {
+        sum = (sum & 0xFFFF) + (sum >> 16);
+    }
+    !(sum as u16)
+}
@@ -6,17 +6,27 @@
 //! ## Module Structure
 //! 
 //! - `config` - Network configuration constants and IP address management
+//! - `firewall` - Stateless packet filter applied to received frames
+//! - `ipv6` - IPv6 link-local and SLAAC address configuration
+//! - `neighbors` - IPv4 neighbor (ARP) table mirrored for inspection
 //! - `patching` - TCP patching state for smoltcp bug workarounds
 //! - `buffers` - Static buffer storage for sockets
-//! - `server` - TCP server socket infrastructure
+//! - `server` - TCP socket table (listening, accepted and outgoing sockets)
+//! - `sockstats` - Per-connection byte, packet, retransmit and RTT counters
+//! - `traceroute` - Replies to traceroute probes, picked out of received frames
 //! - `utils` - Utility functions for IP parsing/formatting
 //!
 //! Note: NetState is now defined in `lock::state::net` and re-exported here for compatibility.
 
 pub(crate) mod config;
+pub(crate) mod firewall;
+pub(crate) mod ipv6;
+pub(crate) mod neighbors;
 mod patching;
 mod buffers;
 pub(crate) mod server;
+pub(crate) mod sockstats;
+pub(crate) mod traceroute;
 mod utils;
 
 // Re-export public items from config
@@ -31,6 +41,7 @@ pub use config::{
 
 // Re-export public items from server
 pub use server::{
+    TcpListenerId,
     TcpSocketId,
 };
 
@@ -42,3 +53,19 @@ pub type D1NetState = NetState;
 
 // Re-export utility functions
 pub use utils::{parse_ipv4, format_ipv4};
+
+/// Wait until a frame may have arrived or `until_ms` passes
+///
+/// For loops waiting on a reply: the process blocks (the hart runs other
+/// work) until the RX interrupt wakes it. Until an RX interrupt has been
+/// seen they cannot be relied on, so it only sleeps for a millisecond.
+pub fn wait_for_frame(until_ms: i64) {
+    use crate::platform::d1_emac;
+
+    let until_ms = if d1_emac::rx_interrupts_active() {
+        until_ms
+    } else {
+        until_ms.min(crate::get_time_ms() + 1)
+    };
+    d1_emac::RX_WAIT.wait_until(until_ms, d1_emac::rx_pending);
+}
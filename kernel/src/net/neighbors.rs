@@ -0,0 +1,103 @@
+//! IPv4 neighbor (ARP) table.
+//!
+//! smoltcp keeps its neighbor cache private, so for inspection (`arp`) the
+//! device layer shows every received frame to `note_frame`, which learns
+//! mappings from ARP packets the same way smoltcp does: the sender of any
+//! request or reply on our subnet. smoltcp forgets an entry
+//! `NEIGHBOR_LIFETIME_MS` after learning it and asks again on the next send,
+//! so older entries here are reported as stale.
+
+use alloc::vec::Vec;
+
+use smoltcp::wire::Ipv4Address;
+
+use crate::Spinlock;
+
+/// How long smoltcp trusts a neighbor cache entry
+pub const NEIGHBOR_LIFETIME_MS: i64 = 60_000;
+
+/// Entries kept; the oldest is dropped to make room
+const MAX_NEIGHBORS: usize = 64;
+
+const ETHERTYPE_ARP: u16 = 0x0806;
+const ARP_HTYPE_ETHERNET: u16 = 1;
+const ARP_PTYPE_IPV4: u16 = 0x0800;
+const ARP_OP_REQUEST: u16 = 1;
+const ARP_OP_REPLY: u16 = 2;
+
+/// One IP to MAC mapping
+#[derive(Clone, Copy)]
+pub struct Neighbor {
+    pub ip: Ipv4Address,
+    pub mac: [u8; 6],
+    /// When the mapping was last (re)learned
+    pub updated_ms: i64,
+}
+
+static NEIGHBORS: Spinlock<Vec<Neighbor>> = Spinlock::new(Vec::new());
+
+/// Look at a received Ethernet frame and learn from it if it is ARP
+pub fn note_frame(frame: &[u8]) {
+    let Some((ip, mac)) = parse_arp(frame) else {
+        return;
+    };
+    if !in_our_subnet(ip) {
+        return;
+    }
+    let now = crate::clint::get_time_ms();
+    let mut table = NEIGHBORS.lock();
+    if let Some(entry) = table.iter_mut().find(|n| n.ip == ip) {
+        entry.mac = mac;
+        entry.updated_ms = now;
+        return;
+    }
+    if table.len() >= MAX_NEIGHBORS {
+        if let Some(oldest) = (0..table.len()).min_by_key(|&i| table[i].updated_ms) {
+            table.swap_remove(oldest);
+        }
+    }
+    table.push(Neighbor { ip, mac, updated_ms: now });
+}
+
+/// Snapshot of the table, sorted by address
+pub fn neighbors() -> Vec<Neighbor> {
+    let mut list = NEIGHBORS.lock().clone();
+    list.sort_unstable_by_key(|n| n.ip.octets());
+    list
+}
+
+/// Sender address and MAC of an Ethernet ARP request or reply for IPv4
+fn parse_arp(frame: &[u8]) -> Option<(Ipv4Address, [u8; 6])> {
+    // Ethernet (14) + ARP for Ethernet/IPv4 (28)
+    if frame.len() < 14 + 28 || u16::from_be_bytes([frame[12], frame[13]]) != ETHERTYPE_ARP {
+        return None;
+    }
+    let arp = &frame[14..];
+    let htype = u16::from_be_bytes([arp[0], arp[1]]);
+    let ptype = u16::from_be_bytes([arp[2], arp[3]]);
+    if htype != ARP_HTYPE_ETHERNET || ptype != ARP_PTYPE_IPV4 || arp[4] != 6 || arp[5] != 4 {
+        return None;
+    }
+    let op = u16::from_be_bytes([arp[6], arp[7]]);
+    if op != ARP_OP_REQUEST && op != ARP_OP_REPLY {
+        return None;
+    }
+
+    let mut mac = [0u8; 6];
+    mac.copy_from_slice(&arp[8..14]);
+    let ip = Ipv4Address::new(arp[14], arp[15], arp[16], arp[17]);
+    // Multicast/broadcast senders and ARP probes (sender 0.0.0.0) teach nothing
+    if mac[0] & 1 != 0 || ip.is_unspecified() {
+        return None;
+    }
+    Some((ip, mac))
+}
+
+fn in_our_subnet(ip: Ipv4Address) -> bool {
+    if !super::config::is_ip_assigned() {
+        return false;
+    }
+    let mask = u32::MAX << (32 - super::config::PREFIX_LEN as u32);
+    let ours = u32::from_be_bytes(super::config::get_my_ip().octets());
+    u32::from_be_bytes(ip.octets()) & mask == ours & mask
+}
@@ -1,41 +1,50 @@
-//! TCP server socket infrastructure.
+//! TCP socket table.
 //!
-//! This module provides the server socket management for TCP listen/accept operations.
+//! Every TCP socket (listening, accepted or outgoing) lives in a slot of
+//! `TcpSocketTable` and is addressed by its `TcpSocketId`, so several
+//! connections can be open at once: an HTTP download does not block telnet.
+//!
+//! Userspace servers get a `TcpListener` instead of a raw listening socket:
+//! smoltcp turns a listening socket into the accepted connection, so the
+//! listener moves to a fresh socket after every accept while its handle stays.
 
 use smoltcp::iface::SocketHandle;
 
 // =============================================================================
-// TCP SERVER SOCKET INFRASTRUCTURE
+// TCP SOCKET TABLE
 // =============================================================================
 
-/// Maximum number of server TCP sockets
-pub const MAX_SERVER_SOCKETS: usize = 4;
+/// Maximum number of TCP sockets (all kinds together)
+pub const MAX_TCP_SOCKETS: usize = 8;
 
-/// TCP socket ID for multi-socket operations
+/// TCP socket ID (index into the socket table)
 pub type TcpSocketId = u8;
 
-/// Server socket state
+/// Socket slot state
 #[derive(Clone, Copy, PartialEq)]
-pub enum ServerSocketState {
+pub enum TcpSlotState {
     /// Socket slot is free
     Free,
     /// Socket is listening for connections
     Listening,
-    /// Socket has an active connection
+    /// Socket has an active (or connecting) connection
     Connected,
-    /// Socket is closing
+    /// Socket is closing; the slot is freed once the connection is gone
     Closing,
 }
 
-/// A server socket entry with per-socket TCP patching state
-pub struct ServerSocket {
+/// A socket table entry with per-socket TCP patching state
+pub struct TcpSocketSlot {
     pub handle: Option<SocketHandle>,
+    /// Listening port, or local port of an outgoing connection
     pub port: u16,
-    pub state: ServerSocketState,
+    pub state: TcpSlotState,
+    /// Hart whose userspace program opened this socket (released when it exits)
+    pub owner: Option<usize>,
     /// Per-socket TCP patching state (to support multiple concurrent server connections)
     /// Store the last received SYN's sequence number for patching
     pub last_syn_seq: Option<u32>,
-    /// Store our SYN-ACK seq number for patching incoming ACKs  
+    /// Store our SYN-ACK seq number for patching incoming ACKs
     pub synack_seq: Option<u32>,
     /// Track the expected ACK we should be sending
     pub expected_ack: Option<u32>,
@@ -45,12 +54,13 @@ pub struct ServerSocket {
     pub peer_ack_expected: Option<u32>,
 }
 
-impl ServerSocket {
+impl TcpSocketSlot {
     pub const fn new() -> Self {
         Self {
             handle: None,
             port: 0,
-            state: ServerSocketState::Free,
+            state: TcpSlotState::Free,
+            owner: None,
             last_syn_seq: None,
             synack_seq: None,
             expected_ack: None,
@@ -58,7 +68,7 @@ impl ServerSocket {
             peer_ack_expected: None,
         }
     }
-    
+
     /// Reset patching state for this socket
     pub fn reset_patching(&mut self) {
         self.last_syn_seq = None;
@@ -69,62 +79,87 @@ impl ServerSocket {
     }
 }
 
-/// Manager for server TCP sockets
-pub struct TcpServerManager {
-    pub sockets: [ServerSocket; MAX_SERVER_SOCKETS],
+/// Table of all TCP sockets
+pub struct TcpSocketTable {
+    pub sockets: [TcpSocketSlot; MAX_TCP_SOCKETS],
 }
 
-impl TcpServerManager {
+impl TcpSocketTable {
     pub const fn new() -> Self {
+        const FREE: TcpSocketSlot = TcpSocketSlot::new();
         Self {
-            sockets: [
-                ServerSocket::new(),
-                ServerSocket::new(),
-                ServerSocket::new(),
-                ServerSocket::new(),
-            ],
+            sockets: [FREE; MAX_TCP_SOCKETS],
         }
     }
-    
+
     /// Allocate a free socket slot, returns socket ID
     pub fn allocate(&mut self) -> Option<TcpSocketId> {
         for (i, slot) in self.sockets.iter_mut().enumerate() {
-            if slot.state == ServerSocketState::Free {
+            if slot.state == TcpSlotState::Free {
                 return Some(i as TcpSocketId);
             }
         }
         None
     }
-    
+
     /// Get socket info by ID
-    pub fn get(&self, id: TcpSocketId) -> Option<&ServerSocket> {
+    pub fn get(&self, id: TcpSocketId) -> Option<&TcpSocketSlot> {
         self.sockets.get(id as usize)
     }
-    
-    /// Get mutable socket info by ID    
-    pub fn get_mut(&mut self, id: TcpSocketId) -> Option<&mut ServerSocket> {
+
+    /// Get mutable socket info by ID
+    pub fn get_mut(&mut self, id: TcpSocketId) -> Option<&mut TcpSocketSlot> {
         self.sockets.get_mut(id as usize)
     }
-    
+
+    /// smoltcp handle of an open socket
+    pub fn handle(&self, id: TcpSocketId) -> Option<SocketHandle> {
+        self.get(id)
+            .filter(|s| s.state != TcpSlotState::Free)
+            .and_then(|s| s.handle)
+    }
+
     /// Find socket by port (for TCP patching)
     #[allow(dead_code)]
-    pub fn find_by_port(&self, port: u16) -> Option<&ServerSocket> {
-        self.sockets.iter().find(|s| s.port == port && s.state != ServerSocketState::Free)
+    pub fn find_by_port(&self, port: u16) -> Option<&TcpSocketSlot> {
+        self.sockets.iter().find(|s| s.port == port && s.state != TcpSlotState::Free)
     }
-    
+
     /// Find mutable socket by port (for TCP patching)
     #[allow(dead_code)]
-    pub fn find_by_port_mut(&mut self, port: u16) -> Option<&mut ServerSocket> {
-        self.sockets.iter_mut().find(|s| s.port == port && s.state != ServerSocketState::Free)
+    pub fn find_by_port_mut(&mut self, port: u16) -> Option<&mut TcpSocketSlot> {
+        self.sockets.iter_mut().find(|s| s.port == port && s.state != TcpSlotState::Free)
     }
-    
+
     /// Release a socket slot
     pub fn release(&mut self, id: TcpSocketId) {
         if let Some(slot) = self.sockets.get_mut(id as usize) {
             slot.handle = None;
             slot.port = 0;
-            slot.state = ServerSocketState::Free;
+            slot.state = TcpSlotState::Free;
+            slot.owner = None;
             slot.reset_patching();  // Reset per-socket patching state
         }
     }
 }
+
+// =============================================================================
+// TCP LISTENERS
+// =============================================================================
+
+/// Maximum number of userspace listeners
+pub const MAX_TCP_LISTENERS: usize = 4;
+
+/// Listener handle. Handles are numbered after the socket IDs (from
+/// MAX_TCP_SOCKETS up), so `tcp_close` can take either.
+pub type TcpListenerId = u8;
+
+/// A port a userspace program listens on
+#[derive(Clone, Copy)]
+pub struct TcpListener {
+    pub port: u16,
+    /// Socket currently listening (None until a slot is free again)
+    pub socket: Option<TcpSocketId>,
+    /// Hart whose userspace program opened the listener
+    pub owner: usize,
+}
@@ -0,0 +1,245 @@
+//! Per-connection traffic counters.
+//!
+//! smoltcp keeps no statistics and does not expose its RTT estimate, so the
+//! device layer shows every frame it sends and receives to `note_tx` and
+//! `note_rx`. TCP and UDP segments are counted per flow: bytes and packets
+//! each way, and for TCP retransmissions (a segment that does not go past
+//! the highest sequence number already sent) and a smoothed round trip time
+//! taken from segments acknowledged without being retransmitted (Karn's rule).
+//!
+//! A TCP flow is one connection (local port, remote address and port); UDP is
+//! counted per local port. `NetState::socket_stats` matches flows to sockets.
+//! Flows outlive their sockets until the table is full; the one idle longest
+//! makes room.
+
+use alloc::vec::Vec;
+
+use smoltcp::wire::{IpAddress, Ipv4Address, Ipv6Address};
+
+use crate::Spinlock;
+
+/// Flows kept; the one idle longest is dropped to make room
+const MAX_FLOWS: usize = 64;
+
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_IPV6: u16 = 0x86DD;
+pub const PROTO_TCP: u8 = 6;
+pub const PROTO_UDP: u8 = 17;
+
+const TCP_FIN: u8 = 0x01;
+const TCP_SYN: u8 = 0x02;
+const TCP_ACK: u8 = 0x10;
+
+/// What a flow is counted under
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct FlowKey {
+    pub proto: u8,
+    pub local_port: u16,
+    /// Remote end of a TCP connection (None for UDP, counted per port)
+    pub remote: Option<(IpAddress, u16)>,
+}
+
+/// Counters of one flow
+#[derive(Clone, Copy, Default)]
+pub struct FlowStats {
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+    pub packets_in: u64,
+    pub packets_out: u64,
+    pub retransmits: u64,
+    /// Smoothed round trip time (0 until the first sample)
+    pub srtt_ms: u32,
+}
+
+/// A socket and its counters, as listed by `NetState::socket_stats`
+pub struct SocketStats {
+    pub proto: u8,
+    /// Local address (None while listening on all) and port
+    pub local: (Option<IpAddress>, u16),
+    pub remote: Option<(IpAddress, u16)>,
+    pub state: &'static str,
+    pub stats: FlowStats,
+}
+
+struct Flow {
+    key: FlowKey,
+    stats: FlowStats,
+    last_seen_ms: i64,
+    /// One past the highest sequence number sent
+    snd_max: Option<u32>,
+    /// Segment being timed: the ACK that covers it, and when it was sent
+    rtt_sample: Option<(u32, i64)>,
+}
+
+static FLOWS: Spinlock<Vec<Flow>> = Spinlock::new(Vec::new());
+
+/// A TCP or UDP segment seen in a frame
+struct Segment {
+    proto: u8,
+    src: IpAddress,
+    dst: IpAddress,
+    src_port: u16,
+    dst_port: u16,
+    payload_len: usize,
+    /// TCP only
+    seq: u32,
+    ack: u32,
+    flags: u8,
+}
+
+/// Count a frame about to be sent
+pub fn note_tx(frame: &[u8]) {
+    let Some(seg) = parse_segment(frame) else {
+        return;
+    };
+    let key = flow_key(seg.proto, seg.src_port, seg.dst, seg.dst_port);
+    with_flow(key, |flow, now| {
+        flow.stats.bytes_out += seg.payload_len as u64;
+        flow.stats.packets_out += 1;
+        if seg.proto != PROTO_TCP {
+            return;
+        }
+        // SYN and FIN take up a sequence number each
+        let len = seg.payload_len as u32 + (seg.flags & TCP_SYN != 0) as u32 + (seg.flags & TCP_FIN != 0) as u32;
+        if len == 0 {
+            return;
+        }
+        let end = seg.seq.wrapping_add(len);
+        match flow.snd_max {
+            Some(max) if seq_le(end, max) => {
+                flow.stats.retransmits += 1;
+                // Karn: an ACK for retransmitted data says nothing about the RTT
+                if flow.rtt_sample.is_some_and(|(sample_end, _)| seq_le(sample_end, end)) {
+                    flow.rtt_sample = None;
+                }
+            }
+            _ => {
+                flow.snd_max = Some(end);
+                if flow.rtt_sample.is_none() {
+                    flow.rtt_sample = Some((end, now));
+                }
+            }
+        }
+    });
+}
+
+/// Count a received frame
+pub fn note_rx(frame: &[u8]) {
+    let Some(seg) = parse_segment(frame) else {
+        return;
+    };
+    let key = flow_key(seg.proto, seg.dst_port, seg.src, seg.src_port);
+    with_flow(key, |flow, now| {
+        flow.stats.bytes_in += seg.payload_len as u64;
+        flow.stats.packets_in += 1;
+        if seg.proto != PROTO_TCP || seg.flags & TCP_ACK == 0 {
+            return;
+        }
+        if let Some((sample_end, sent_ms)) = flow.rtt_sample {
+            if seq_le(sample_end, seg.ack) {
+                let rtt = (now - sent_ms).max(0) as u32;
+                flow.stats.srtt_ms = match flow.stats.srtt_ms {
+                    0 => rtt.max(1),
+                    srtt => (srtt * 7 + rtt) / 8,
+                };
+                flow.rtt_sample = None;
+            }
+        }
+    });
+}
+
+/// Counters of a flow, if any traffic was seen
+pub fn stats(key: FlowKey) -> Option<FlowStats> {
+    FLOWS.lock().iter().find(|f| f.key == key).map(|f| f.stats)
+}
+
+fn flow_key(proto: u8, local_port: u16, remote: IpAddress, remote_port: u16) -> FlowKey {
+    let remote = (proto == PROTO_TCP).then_some((remote, remote_port));
+    FlowKey { proto, local_port, remote }
+}
+
+/// Run `f` on the flow for `key`, creating it if needed
+fn with_flow(key: FlowKey, f: impl FnOnce(&mut Flow, i64)) {
+    let now = crate::clint::get_time_ms();
+    let mut flows = FLOWS.lock();
+    let index = match flows.iter().position(|flow| flow.key == key) {
+        Some(index) => index,
+        None => {
+            if flows.len() >= MAX_FLOWS {
+                if let Some(idle) = (0..flows.len()).min_by_key(|&i| flows[i].last_seen_ms) {
+                    flows.swap_remove(idle);
+                }
+            }
+            flows.push(Flow { key, stats: FlowStats::default(), last_seen_ms: now, snd_max: None, rtt_sample: None });
+            flows.len() - 1
+        }
+    };
+    let flow = &mut flows[index];
+    flow.last_seen_ms = now;
+    f(flow, now);
+}
+
+/// `a` is at or before `b` in sequence space
+fn seq_le(a: u32, b: u32) -> bool {
+    (b.wrapping_sub(a) as i32) >= 0
+}
+
+/// The TCP or UDP segment in an Ethernet frame (IPv4, or IPv6 without
+/// extension headers)
+fn parse_segment(frame: &[u8]) -> Option<Segment> {
+    if frame.len() < 14 {
+        return None;
+    }
+    let packet = &frame[14..];
+    let (proto, src, dst, l4) = match u16::from_be_bytes([frame[12], frame[13]]) {
+        ETHERTYPE_IPV4 => {
+            if packet.len() < 20 || packet[0] >> 4 != 4 {
+                return None;
+            }
+            let header_len = (packet[0] & 0x0F) as usize * 4;
+            let total_len = (u16::from_be_bytes([packet[2], packet[3]]) as usize).min(packet.len());
+            if header_len < 20 || total_len < header_len {
+                return None;
+            }
+            let src = Ipv4Address::new(packet[12], packet[13], packet[14], packet[15]);
+            let dst = Ipv4Address::new(packet[16], packet[17], packet[18], packet[19]);
+            (packet[9], IpAddress::Ipv4(src), IpAddress::Ipv4(dst), &packet[header_len..total_len])
+        }
+        ETHERTYPE_IPV6 => {
+            if packet.len() < 40 || packet[0] >> 4 != 6 {
+                return None;
+            }
+            let payload_len = (u16::from_be_bytes([packet[4], packet[5]]) as usize).min(packet.len() - 40);
+            let mut src = [0u8; 16];
+            let mut dst = [0u8; 16];
+            src.copy_from_slice(&packet[8..24]);
+            dst.copy_from_slice(&packet[24..40]);
+            (packet[6], IpAddress::Ipv6(Ipv6Address::from(src)), IpAddress::Ipv6(Ipv6Address::from(dst)), &packet[40..40 + payload_len])
+        }
+        _ => return None,
+    };
+
+    let ports = |l4: &[u8]| (u16::from_be_bytes([l4[0], l4[1]]), u16::from_be_bytes([l4[2], l4[3]]));
+    match proto {
+        PROTO_TCP if l4.len() >= 20 => {
+            let header_len = (l4[12] >> 4) as usize * 4;
+            let (src_port, dst_port) = ports(l4);
+            Some(Segment {
+                proto,
+                src,
+                dst,
+                src_port,
+                dst_port,
+                payload_len: l4.len().saturating_sub(header_len),
+                seq: u32::from_be_bytes([l4[4], l4[5], l4[6], l4[7]]),
+                ack: u32::from_be_bytes([l4[8], l4[9], l4[10], l4[11]]),
+                flags: l4[13],
+            })
+        }
+        PROTO_UDP if l4.len() >= 8 => {
+            let (src_port, dst_port) = ports(l4);
+            Some(Segment { proto, src, dst, src_port, dst_port, payload_len: l4.len() - 8, seq: 0, ack: 0, flags: 0 })
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,156 @@
+//! Traceroute probe replies.
+//!
+//! Probes are ICMP echo requests or UDP datagrams sent with a small hop limit
+//! (see `NetState::send_probe`). Routers answer them with ICMP Time Exceeded,
+//! which smoltcp hands to no socket, so the device layer shows every received
+//! frame to `note_frame`. It keeps the replies that quote one of our probes:
+//! Time Exceeded from a router on the way, Destination Unreachable (port
+//! unreachable from the target ends a UDP trace), and echo replies.
+//!
+//! A probe's sequence number is its echo sequence number, or for UDP its
+//! offset from `PROBE_BASE_PORT` in the destination port, so the quoted
+//! header alone tells which probe a reply belongs to.
+
+use alloc::collections::VecDeque;
+
+use smoltcp::wire::Ipv4Address;
+
+use crate::Spinlock;
+
+/// Echo identifier of ICMP probes (ping uses `ICMP_IDENT`)
+pub const PROBE_IDENT: u16 = 0x7472;
+
+/// Source port of UDP probes
+pub const PROBE_LOCAL_PORT: u16 = 43434;
+
+/// Destination port of UDP probe 0, as in classic traceroute
+pub const PROBE_BASE_PORT: u16 = 33434;
+
+/// Replies kept until collected; the oldest is dropped to make room
+const MAX_REPLIES: usize = 16;
+
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const PROTO_ICMP: u8 = 1;
+const PROTO_UDP: u8 = 17;
+
+const ICMP_ECHO_REPLY: u8 = 0;
+const ICMP_DEST_UNREACHABLE: u8 = 3;
+const ICMP_ECHO_REQUEST: u8 = 8;
+const ICMP_TIME_EXCEEDED: u8 = 11;
+const CODE_PORT_UNREACHABLE: u8 = 3;
+
+/// Transport a probe is sent over
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ProbeProto {
+    Icmp,
+    Udp,
+}
+
+/// What answered a probe
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ProbeResult {
+    /// A router on the way dropped it (hop limit reached)
+    TimeExceeded,
+    /// The destination answered (echo reply, or port unreachable)
+    Reached,
+    /// A router or the destination refused it, with the ICMP code
+    Unreachable(u8),
+}
+
+/// A reply to one of our probes
+#[derive(Clone, Copy)]
+pub struct ProbeReply {
+    pub from: Ipv4Address,
+    pub seq: u16,
+    pub result: ProbeResult,
+}
+
+static REPLIES: Spinlock<VecDeque<ProbeReply>> = Spinlock::new(VecDeque::new());
+
+/// Look at a received Ethernet frame and keep it if it answers a probe
+pub fn note_frame(frame: &[u8]) {
+    if let Some(reply) = parse_reply(frame) {
+        push_reply(reply);
+    }
+}
+
+/// Record a reply that never went over the wire (probes to ourselves)
+pub fn push_reply(reply: ProbeReply) {
+    let mut replies = REPLIES.lock();
+    if replies.len() >= MAX_REPLIES {
+        replies.pop_front();
+    }
+    replies.push_back(reply);
+}
+
+/// Take the reply to probe `seq`, if it has arrived
+pub fn take_reply(seq: u16) -> Option<ProbeReply> {
+    let mut replies = REPLIES.lock();
+    let index = replies.iter().position(|r| r.seq == seq)?;
+    replies.remove(index)
+}
+
+/// Forget replies to earlier probes
+pub fn clear() {
+    REPLIES.lock().clear();
+}
+
+/// IPv4 header length, protocol, source and destination of a packet
+fn parse_ipv4(packet: &[u8]) -> Option<(usize, u8, Ipv4Address, Ipv4Address)> {
+    if packet.len() < 20 || packet[0] >> 4 != 4 {
+        return None;
+    }
+    let header_len = (packet[0] & 0x0F) as usize * 4;
+    if header_len < 20 || packet.len() < header_len {
+        return None;
+    }
+    let src = Ipv4Address::new(packet[12], packet[13], packet[14], packet[15]);
+    let dst = Ipv4Address::new(packet[16], packet[17], packet[18], packet[19]);
+    Some((header_len, packet[9], src, dst))
+}
+
+/// The probe reply carried by an Ethernet frame, if it is one
+fn parse_reply(frame: &[u8]) -> Option<ProbeReply> {
+    if frame.len() < 14 || u16::from_be_bytes([frame[12], frame[13]]) != ETHERTYPE_IPV4 {
+        return None;
+    }
+    let (header_len, proto, from, _) = parse_ipv4(&frame[14..])?;
+    if proto != PROTO_ICMP {
+        return None;
+    }
+    let icmp = &frame[14 + header_len..];
+    if icmp.len() < 8 {
+        return None;
+    }
+
+    let (icmp_type, code) = (icmp[0], icmp[1]);
+    if icmp_type == ICMP_ECHO_REPLY {
+        let ident = u16::from_be_bytes([icmp[4], icmp[5]]);
+        let seq = u16::from_be_bytes([icmp[6], icmp[7]]);
+        return (ident == PROBE_IDENT).then_some(ProbeReply { from, seq, result: ProbeResult::Reached });
+    }
+    if icmp_type != ICMP_TIME_EXCEEDED && icmp_type != ICMP_DEST_UNREACHABLE {
+        return None;
+    }
+
+    // The error quotes the probe's IP header and first 8 bytes of payload
+    let quoted = &icmp[8..];
+    let (quoted_len, quoted_proto, _, target) = parse_ipv4(quoted)?;
+    let payload = quoted.get(quoted_len..quoted_len + 8)?;
+    let seq = match quoted_proto {
+        PROTO_ICMP if payload[0] == ICMP_ECHO_REQUEST && u16::from_be_bytes([payload[4], payload[5]]) == PROBE_IDENT => {
+            u16::from_be_bytes([payload[6], payload[7]])
+        }
+        PROTO_UDP if u16::from_be_bytes([payload[0], payload[1]]) == PROBE_LOCAL_PORT => {
+            u16::from_be_bytes([payload[2], payload[3]]).wrapping_sub(PROBE_BASE_PORT)
+        }
+        _ => return None,
+    };
+
+    let result = match icmp_type {
+        ICMP_TIME_EXCEEDED => ProbeResult::TimeExceeded,
+        _ if code == CODE_PORT_UNREACHABLE && from == target => ProbeResult::Reached,
+        _ => ProbeResult::Unreachable(code),
+    };
+    Some(ProbeReply { from, seq, result })
+}
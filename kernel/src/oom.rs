@@ -0,0 +1,178 @@
+//! Out-of-Memory Handling
+//!
+//! When the kernel heap cannot satisfy an allocation, the allocator hands
+//! the failure to `recover` instead of giving up at once. It frees memory
+//! in stages, retrying the allocation after each:
+//!
+//! 1. drop caches that are rebuilt on demand: clean blocks of the buffer
+//!    cache, cached DNS answers and all but the newest kernel log entries
+//! 2. kill the user binary holding the most heap that is not essential
+//!    (daemons, kernel processes and init are spared, and so is the process
+//!    that is allocating) with SIGKILL. If it runs on another hart, wait
+//!    up to `KILL_WAIT_MS` for it to exit and retry once; a victim that
+//!    only this hart could run cannot exit before the allocation returns,
+//!    so the allocation fails at once
+//!
+//! Each event is reported to klog with what was freed. If nothing helped,
+//! the failure is logged and the allocation fails, which panics as before.
+//!
+//! This runs inside the allocator with whatever locks the caller holds, so
+//! it only ever tries locks and skips whatever is busy. A nested allocation
+//! that fails while it runs fails right away.
+
+use core::fmt::Write;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use crate::cpu::process::{Process, ProcessState, PROCESS_TABLE};
+use crate::cpu::signal::SIGKILL;
+use crate::cpu::MAX_HARTS;
+use crate::services::klogd::{klog_critical, klog_warning, KLOG};
+
+/// How long to wait for a killed binary on another hart to exit
+const KILL_WAIT_MS: i64 = 500;
+
+/// Kernel log entries kept when the backlog is dropped
+const KLOG_KEPT: usize = 16;
+
+/// Harts currently in `recover`
+static RECOVERING: [AtomicBool; MAX_HARTS] = [const { AtomicBool::new(false) }; MAX_HARTS];
+
+/// Allocation failures seen since boot
+static EVENTS: AtomicUsize = AtomicUsize::new(0);
+
+/// Failures `recover` could not resolve
+static FAILURES: AtomicUsize = AtomicUsize::new(0);
+
+/// Fixed-size message buffer, so reports are built without the heap
+struct Message {
+    buf: [u8; 160],
+    len: usize,
+}
+
+impl Message {
+    const fn new() -> Self {
+        Self { buf: [0; 160], len: 0 }
+    }
+
+    fn as_str(&self) -> &str {
+        core::str::from_utf8(&self.buf[..self.len]).unwrap_or("")
+    }
+}
+
+impl Write for Message {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        let mut n = s.len().min(self.buf.len() - self.len);
+        while !s.is_char_boundary(n) {
+            n -= 1;
+        }
+        self.buf[self.len..self.len + n].copy_from_slice(&s.as_bytes()[..n]);
+        self.len += n;
+        Ok(())
+    }
+}
+
+/// What the caches gave back
+#[derive(Default)]
+struct Dropped {
+    blocks: usize,
+    dns: usize,
+    klog: usize,
+}
+
+/// Drop the caches that can be rebuilt, skipping any that are locked
+fn drop_caches() -> Dropped {
+    let blocks = crate::lock::utils::FS_STATE
+        .try_write()
+        .and_then(|mut fs| fs.as_mut().map(|fs| fs.drop_clean_blocks()))
+        .unwrap_or(0);
+    Dropped {
+        blocks,
+        dns: crate::dns::try_drop_cache(),
+        klog: KLOG.try_trim(KLOG_KEPT),
+    }
+}
+
+/// The binary holding the most heap that may be killed: (pid, bytes)
+fn pick_victim() -> Option<(u32, usize)> {
+    let current = crate::cpu::CPU_TABLE
+        .get(crate::get_hart_id())
+        .and_then(|cpu| cpu.running_process())
+        .unwrap_or(0);
+    crate::allocator::heap_holders()
+        .filter(|&(pid, _)| pid != current)
+        .filter(|&(pid, _)| {
+            PROCESS_TABLE.try_get(pid).is_some_and(|process| {
+                process.runs_binary() && !process.is_daemon() && !process.is_kernel() && !process.is_init()
+            })
+        })
+        .max_by_key(|&(_, bytes)| bytes)
+}
+
+/// Whether `process` can run, and so exit, while `hart` is stuck in the
+/// allocator
+fn runs_elsewhere(process: &Process, hart: usize) -> bool {
+    match process.current_cpu().or(process.preempted_on()) {
+        Some(cpu) => cpu != hart,
+        None => (0..MAX_HARTS).any(|other| other != hart && process.can_run_on_cpu(other)),
+    }
+}
+
+/// Whether a killed binary is gone, and its memory with it
+fn exited(process: &Process) -> bool {
+    process.state() == ProcessState::Zombie || !process.runs_binary()
+}
+
+/// Try to free enough memory for a failed allocation of `size` bytes,
+/// calling `retry` after each stage. Returns its first non-null result, or
+/// null if the heap stays exhausted.
+pub fn recover(size: usize, retry: &dyn Fn() -> *mut u8) -> *mut u8 {
+    let hart = crate::get_hart_id();
+    let Some(recovering) = RECOVERING.get(hart) else {
+        return core::ptr::null_mut();
+    };
+    if recovering.swap(true, Ordering::Acquire) {
+        return core::ptr::null_mut();
+    }
+    EVENTS.fetch_add(1, Ordering::Relaxed);
+
+    let dropped = drop_caches();
+    let mut block = retry();
+    let mut message = Message::new();
+    let _ = write!(
+        message,
+        "Heap exhausted ({} bytes): dropped {} cached blocks, {} DNS answers, {} log entries",
+        size, dropped.blocks, dropped.dns, dropped.klog
+    );
+
+    if block.is_null() {
+        if let Some((pid, bytes)) = pick_victim() {
+            let _ = write!(message, "; killed pid {} ({} bytes)", pid, bytes);
+            if let Some(process) = PROCESS_TABLE.try_get(pid) {
+                process.raise(SIGKILL);
+                if runs_elsewhere(&process, hart) {
+                    let deadline = crate::get_time_ms() + KILL_WAIT_MS;
+                    while !exited(&process) && crate::get_time_ms() < deadline {
+                        core::hint::spin_loop();
+                    }
+                    block = retry();
+                }
+            }
+        }
+    }
+
+    if block.is_null() {
+        FAILURES.fetch_add(1, Ordering::Relaxed);
+        let _ = write!(message, "; giving up");
+        klog_critical("oom", message.as_str());
+    } else {
+        klog_warning("oom", message.as_str());
+    }
+    recovering.store(false, Ordering::Release);
+    block
+}
+
+/// Allocation failures since boot and how many of them could not be
+/// resolved
+pub fn stats() -> (usize, usize) {
+    (EVENTS.load(Ordering::Relaxed), FAILURES.load(Ordering::Relaxed))
+}
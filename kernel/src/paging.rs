@@ -0,0 +1,285 @@
+//! Sv39 Virtual Memory
+//!
+//! The kernel runs without translation (`satp` = Bare). A native binary
+//! gets an address space of its own for as long as it runs
+//! (`elf_loader::run_binary`), which identity-maps:
+//!
+//! - the first 256 GiB of physical memory (RAM and MMIO) with 1 GiB pages
+//!   the binary cannot touch (no U bit), so the kernel keeps running
+//!   unchanged on traps and syscalls without switching `satp`
+//! - the binary's own pages with the U bit: its segments with the
+//!   permissions of their program headers, its stack, its `mmap` blocks and
+//!   the kernel log page (read-only)
+//!
+//! Because the mapping is the identity, a pointer means the same thing to
+//! the binary and to the kernel, and the syscall layer works on user
+//! pointers as before, with `sstatus.SUM` set so it may access U pages.
+//! A load, store or jump by the binary anywhere else faults, and the binary
+//! is ended with SIGSEGV instead of scribbling over the kernel heap.
+//!
+//! User memory comes from the kernel heap, so the large pages covering it
+//! are split (1 GiB -> 2 MiB -> 4 KiB) as user pages are mapped. Page
+//! table pages come from `alloc_page`, which keeps a few freed ones around
+//! for the next binary.
+//!
+//! Harts whose MMU has no Sv39 (`satp` keeps Bare when written) run
+//! binaries in the flat address space as before.
+
+use alloc::vec::Vec;
+use core::arch::asm;
+use core::ptr::NonNull;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use crate::services::klogd::{klog_info, klog_warning};
+use crate::Spinlock;
+
+pub const PAGE_SIZE: usize = 4096;
+
+// Page table entry bits
+const PTE_V: u64 = 1 << 0;
+pub const PTE_R: u64 = 1 << 1;
+pub const PTE_W: u64 = 1 << 2;
+pub const PTE_X: u64 = 1 << 3;
+const PTE_U: u64 = 1 << 4;
+const PTE_A: u64 = 1 << 6;
+const PTE_D: u64 = 1 << 7;
+
+/// What the kernel's identity mapping allows
+const KERNEL_FLAGS: u64 = PTE_V | PTE_R | PTE_W | PTE_X | PTE_A | PTE_D;
+
+/// Entries per table
+const ENTRIES: usize = 512;
+
+/// Root entries of the identity mapping (256 x 1 GiB: the positive half of
+/// the Sv39 address space)
+const IDENTITY_GIGAPAGES: usize = 256;
+
+/// `satp` MODE field for Sv39
+const SATP_SV39: u64 = 8 << 60;
+
+/// `sstatus.SUM`: S-mode may access U pages
+const SSTATUS_SUM: usize = 1 << 18;
+
+/// Freed page table pages kept for reuse
+const FREE_PAGES_KEPT: usize = 64;
+
+/// The MMU does Sv39 (probed at boot)
+static SUPPORTED: AtomicBool = AtomicBool::new(false);
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Page allocator
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// One page table page
+#[repr(C, align(4096))]
+struct PageTable([u64; ENTRIES]);
+
+/// Freed table pages
+static FREE_PAGES: Spinlock<Vec<usize>> = Spinlock::new(Vec::new());
+
+const PAGE_LAYOUT: core::alloc::Layout = core::alloc::Layout::new::<PageTable>();
+
+/// A zeroed, page-aligned page for a page table
+fn alloc_page() -> Option<NonNull<PageTable>> {
+    if let Some(page) = FREE_PAGES.lock().pop() {
+        let table = page as *mut PageTable;
+        unsafe { (*table).0.fill(0) };
+        return NonNull::new(table);
+    }
+    NonNull::new(unsafe { alloc::alloc::alloc_zeroed(PAGE_LAYOUT) } as *mut PageTable)
+}
+
+/// Give back a page from `alloc_page`
+fn free_page(page: NonNull<PageTable>) {
+    let mut free = FREE_PAGES.lock();
+    if free.len() < FREE_PAGES_KEPT {
+        free.push(page.as_ptr() as usize);
+    } else {
+        drop(free);
+        unsafe { alloc::alloc::dealloc(page.as_ptr() as *mut u8, PAGE_LAYOUT) };
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Address spaces
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Leaf or pointer entry for physical address `pa`
+fn pte(pa: u64, flags: u64) -> u64 {
+    ((pa >> 12) << 10) | flags
+}
+
+/// Physical address an entry points to
+fn pte_address(entry: u64) -> u64 {
+    ((entry >> 10) & ((1 << 44) - 1)) << 12
+}
+
+fn is_leaf(entry: u64) -> bool {
+    entry & (PTE_R | PTE_W | PTE_X) != 0
+}
+
+/// Index into the table of `level` (2 = root) for `va`
+fn index(va: u64, level: usize) -> usize {
+    ((va >> (12 + 9 * level)) & 0x1ff) as usize
+}
+
+/// Page tables of one binary
+pub struct AddressSpace {
+    root: NonNull<PageTable>,
+    /// Every table page, the root included
+    tables: Vec<NonNull<PageTable>>,
+}
+
+impl AddressSpace {
+    /// An address space holding only the kernel's identity mapping
+    pub fn new() -> Option<Self> {
+        let root = alloc_page()?;
+        let table = unsafe { &mut (*root.as_ptr()).0 };
+        for (i, entry) in table.iter_mut().take(IDENTITY_GIGAPAGES).enumerate() {
+            *entry = pte((i as u64) << 30, KERNEL_FLAGS);
+        }
+        Some(Self { root, tables: alloc::vec![root] })
+    }
+
+    /// The 4 KiB leaf entry for `va`, splitting the large pages above it
+    fn leaf(&mut self, va: u64) -> Option<&mut u64> {
+        if index(va, 2) >= IDENTITY_GIGAPAGES || va >> 39 != 0 {
+            return None;
+        }
+        let mut table = self.root;
+        for level in (1..=2).rev() {
+            let entry = unsafe { &mut (*table.as_ptr()).0[index(va, level)] };
+            if is_leaf(*entry) {
+                // Split into 512 pages of the next size with the same flags
+                let child = alloc_page()?;
+                let size = 1u64 << (12 + 9 * (level - 1));
+                let base = pte_address(*entry);
+                let flags = *entry & 0x3ff;
+                for (i, sub) in unsafe { &mut (*child.as_ptr()).0 }.iter_mut().enumerate() {
+                    *sub = pte(base + i as u64 * size, flags);
+                }
+                self.tables.push(child);
+                *entry = pte(child.as_ptr() as u64, PTE_V);
+            }
+            table = NonNull::new(pte_address(*entry) as *mut PageTable)?;
+        }
+        Some(unsafe { &mut (*table.as_ptr()).0[index(va, 0)] })
+    }
+
+    /// The 4 KiB leaf entry for `va` if there is one, or the large page
+    /// covering it
+    fn lookup(&self, va: u64) -> Option<u64> {
+        if index(va, 2) >= IDENTITY_GIGAPAGES || va >> 39 != 0 {
+            return None;
+        }
+        let mut table = self.root;
+        for level in (0..=2).rev() {
+            let entry = unsafe { (*table.as_ptr()).0[index(va, level)] };
+            if entry & PTE_V == 0 || level == 0 || is_leaf(entry) {
+                return Some(entry);
+            }
+            table = NonNull::new(pte_address(entry) as *mut PageTable)?;
+        }
+        None
+    }
+
+    /// Let the binary access the pages of `[start, start + len)` with
+    /// `flags` (PTE_R / PTE_W / PTE_X), added to what it already has there
+    pub fn map_user(&mut self, start: usize, len: usize, flags: u64) -> Result<(), &'static str> {
+        let first = start & !(PAGE_SIZE - 1);
+        let end = start.checked_add(len).ok_or("Invalid range")?.next_multiple_of(PAGE_SIZE);
+        for page in (first..end).step_by(PAGE_SIZE) {
+            let entry = self.leaf(page as u64).ok_or("Out of memory for page tables")?;
+            let granted = if *entry & PTE_U != 0 { *entry & (PTE_R | PTE_W | PTE_X) } else { 0 };
+            *entry = pte(page as u64, PTE_V | PTE_U | PTE_A | PTE_D | granted | flags);
+        }
+        sfence();
+        Ok(())
+    }
+
+    /// Take the pages of `[start, start + len)` back from the binary
+    pub fn unmap_user(&mut self, start: usize, len: usize) {
+        let first = start & !(PAGE_SIZE - 1);
+        let end = start.saturating_add(len).next_multiple_of(PAGE_SIZE);
+        for page in (first..end).step_by(PAGE_SIZE) {
+            if let Some(entry) = self.leaf(page as u64) {
+                *entry = pte(page as u64, KERNEL_FLAGS);
+            }
+        }
+        sfence();
+    }
+
+    /// Whether the binary may read (and, if `write`, write) all of
+    /// `[start, start + len)`
+    pub fn user_accessible(&self, start: usize, len: usize, write: bool) -> bool {
+        let Some(end) = start.checked_add(len) else {
+            return false;
+        };
+        let needed = PTE_V | PTE_U | PTE_R | if write { PTE_W } else { 0 };
+        let first = start & !(PAGE_SIZE - 1);
+        (first..end).step_by(PAGE_SIZE).all(|page| {
+            self.lookup(page as u64).is_some_and(|entry| entry & needed == needed)
+        })
+    }
+
+    /// `satp` value that selects this address space
+    fn satp(&self) -> u64 {
+        SATP_SV39 | (self.root.as_ptr() as u64 >> 12)
+    }
+
+    /// Switch this hart to the address space
+    pub fn activate(&self) {
+        unsafe {
+            asm!("csrw satp, {}", in(reg) self.satp(), options(nostack));
+            asm!("csrs sstatus, {}", in(reg) SSTATUS_SUM, options(nostack));
+        }
+        sfence();
+    }
+}
+
+impl Drop for AddressSpace {
+    fn drop(&mut self) {
+        for table in self.tables.drain(..) {
+            free_page(table);
+        }
+    }
+}
+
+/// Flush this hart's TLB
+fn sfence() {
+    unsafe { asm!("sfence.vma zero, zero", options(nostack)) };
+}
+
+/// Switch this hart back to untranslated addressing
+pub fn deactivate() {
+    unsafe {
+        asm!("csrw satp, zero", options(nostack));
+        asm!("csrc sstatus, {}", in(reg) SSTATUS_SUM, options(nostack));
+    }
+    sfence();
+}
+
+/// Whether binaries get address spaces
+pub fn enabled() -> bool {
+    SUPPORTED.load(Ordering::Relaxed)
+}
+
+/// Probe for Sv39 by selecting an identity-mapped address space (boot)
+pub fn init() {
+    let Some(probe) = AddressSpace::new() else {
+        klog_warning("paging", "No memory for page tables");
+        return;
+    };
+    let satp: u64;
+    unsafe {
+        asm!("csrw satp, {}", in(reg) probe.satp(), options(nostack));
+        asm!("csrr {}, satp", out(reg) satp, options(nostack));
+    }
+    deactivate();
+    if satp == probe.satp() {
+        SUPPORTED.store(true, Ordering::Relaxed);
+        klog_info("paging", "Sv39: binaries run in their own address space");
+    } else {
+        klog_warning("paging", "No Sv39 MMU, binaries share the kernel's address space");
+    }
+}
@@ -0,0 +1,182 @@
+//! Kernel panic handler
+//!
+//! Composes a single report (message, location, hart, backtrace summary and
+//! the tail of the kernel log) into a static emergency buffer, then sends it
+//! to the UART and, when the GPU is up, to the framebuffer panic screen.
+//!
+//! The heap, the scheduler or any lock may be what broke, so this path never
+//! allocates and never blocks: the klog tail is read with `try_lock` and the
+//! UART is driven through the raw (unlocked) writer.
+
+use core::fmt::{self, Write};
+use core::panic::PanicInfo;
+use core::ptr::{addr_of, addr_of_mut};
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::cpu::get_hart_id;
+use crate::services::klogd::KLOG;
+use crate::{uart, CPU_TABLE};
+
+/// Size of the emergency report buffer
+const REPORT_SIZE: usize = 4096;
+/// Stack words scanned for return addresses
+const STACK_SCAN_WORDS: usize = 1024;
+/// Maximum backtrace entries in the report
+const MAX_FRAMES: usize = 8;
+/// Kernel log entries in the report
+const KLOG_TAIL: usize = 10;
+
+/// PANIC_OWNER value while no hart has panicked
+const NO_OWNER: usize = usize::MAX;
+
+/// The first hart to panic owns the report buffer and the screen
+static PANIC_OWNER: AtomicUsize = AtomicUsize::new(NO_OWNER);
+
+/// Fixed-size text buffer; anything past the end is dropped
+struct EmergencyBuffer {
+    buf: [u8; REPORT_SIZE],
+    len: usize,
+}
+
+impl EmergencyBuffer {
+    fn as_str(&self) -> &str {
+        // Only whole chars are ever copied in, so this is always valid UTF-8
+        core::str::from_utf8(&self.buf[..self.len]).unwrap_or("")
+    }
+}
+
+impl Write for EmergencyBuffer {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let mut n = s.len().min(REPORT_SIZE - self.len);
+        while !s.is_char_boundary(n) {
+            n -= 1;
+        }
+        self.buf[self.len..self.len + n].copy_from_slice(&s.as_bytes()[..n]);
+        self.len += n;
+        Ok(())
+    }
+}
+
+/// Pre-allocated so reporting works with a corrupted or exhausted heap
+static mut REPORT: EmergencyBuffer = EmergencyBuffer {
+    buf: [0; REPORT_SIZE],
+    len: 0,
+};
+
+extern "C" {
+    static _stext: u8;
+    static _etext: u8;
+    static _stack_start: u8;
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    let hart = get_hart_id();
+
+    // Another hart already owns the report (or we panicked while writing
+    // it): a single raw line is all that's safe
+    if PANIC_OWNER
+        .compare_exchange(NO_OWNER, hart, Ordering::AcqRel, Ordering::Acquire)
+        .is_err()
+    {
+        uart::print_fmt_raw(format_args!(
+            "\n\x1b[1;31m[PANIC] hart {}: {}\x1b[0m\n",
+            hart, info
+        ));
+        halt();
+    }
+
+    // SAFETY: only the PANIC_OWNER hart gets here, once
+    let report = unsafe { &mut *addr_of_mut!(REPORT) };
+    let _ = write_report(report, hart, info);
+
+    uart::write_str_raw("\n\x1b[1;31m");
+    uart::write_str_raw(report.as_str());
+    uart::write_str_raw("\x1b[0m\n");
+
+    crate::ui::panic_screen::render(report.as_str());
+
+    halt();
+}
+
+/// Park this hart for good
+fn halt() -> ! {
+    loop {
+        unsafe { core::arch::asm!("wfi", options(nomem, nostack)) };
+    }
+}
+
+fn write_report(out: &mut EmergencyBuffer, hart: usize, info: &PanicInfo) -> fmt::Result {
+    writeln!(out, "KERNEL PANIC on hart {}", hart)?;
+    writeln!(out, "{}", info.message())?;
+    if let Some(location) = info.location() {
+        writeln!(out, "  at {}:{}:{}", location.file(), location.line(), location.column())?;
+    }
+    match CPU_TABLE.get(hart).and_then(|cpu| cpu.running_process()) {
+        Some(pid) => writeln!(out, "  while running pid {}", pid)?,
+        None => writeln!(out, "  in kernel context")?,
+    }
+
+    writeln!(out)?;
+    writeln!(out, "Backtrace (stack scan, newest first):")?;
+    let mut frames = [0usize; MAX_FRAMES];
+    let count = scan_stack(&mut frames);
+    if count == 0 {
+        writeln!(out, "  <no return addresses found>")?;
+    }
+    for (i, addr) in frames[..count].iter().enumerate() {
+        writeln!(out, "  #{} {:#x}", i, addr)?;
+    }
+
+    writeln!(out)?;
+    writeln!(out, "Recent kernel log:")?;
+    let mut result = Ok(());
+    let read = KLOG.try_for_each_recent(KLOG_TAIL, |entry| {
+        if result.is_ok() {
+            result = writeln!(
+                out,
+                "  [{:>6}.{:03}] {} [{}] {}: {}",
+                entry.timestamp / 1000,
+                entry.timestamp % 1000,
+                entry.level.as_str().trim_end(),
+                entry.hart_id,
+                entry.subsystem,
+                entry.message
+            );
+        }
+    });
+    result?;
+    if !read {
+        writeln!(out, "  <log buffer locked>")?;
+    }
+    Ok(())
+}
+
+/// Collect likely return addresses: words on the stack that point into
+/// .text. The kernel is built without frame pointers, so this is a
+/// heuristic and may include stale entries; resolve with addr2line.
+fn scan_stack(frames: &mut [usize]) -> usize {
+    let sp: usize;
+    unsafe { core::arch::asm!("mv {}, sp", out(reg) sp, options(nomem, nostack)) };
+
+    let (text_start, text_end, stack_top) = unsafe {
+        (
+            addr_of!(_stext) as usize,
+            addr_of!(_etext) as usize,
+            addr_of!(_stack_start) as usize,
+        )
+    };
+    let end = stack_top.min(sp + STACK_SCAN_WORDS * 8);
+
+    let mut count = 0;
+    let mut addr = sp;
+    while addr + 8 <= end && count < frames.len() {
+        let word = unsafe { core::ptr::read_volatile(addr as *const usize) };
+        if word > text_start && word < text_end && word % 2 == 0 {
+            frames[count] = word;
+            count += 1;
+        }
+        addr += 8;
+    }
+    count
+}
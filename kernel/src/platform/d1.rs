@@ -69,6 +69,13 @@ pub const UART1_BASE: usize = 0x0250_0400;
 /// UART2 base address
 pub const UART2_BASE: usize = 0x0250_0800;
 
+// ============================================================================
+// Interrupt Controller
+// ============================================================================
+
+/// PLIC base address
+pub const PLIC_BASE: usize = 0x1000_0000;
+
 // ============================================================================
 // SD/MMC Controller
 // ============================================================================
@@ -171,6 +171,20 @@ pub fn write_stereo(left: i16, right: i16) -> bool {
     write_sample(sample)
 }
 
+/// Write as many samples as fit in the FIFO
+///
+/// Returns the number of samples written (0 if the buffer was already full)
+pub fn write_samples(samples: &[u32]) -> usize {
+    let mut written = 0;
+    for &sample in samples {
+        if !write_sample(sample) {
+            break;
+        }
+        written += 1;
+    }
+    written
+}
+
 /// Get the current buffer fill level (number of samples in buffer)
 #[inline]
 pub fn buffer_level() -> u32 {
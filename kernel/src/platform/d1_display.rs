@@ -53,6 +53,9 @@ pub const DIRTY_RECT_ADDR: usize = 0x80FF_FFE0;
 /// Global flag to track if display was initialized
 static D1_DISPLAY_AVAILABLE: AtomicBool = AtomicBool::new(false);
 
+/// Set once the panic screen is up, so other harts can't flush over it
+static DISPLAY_FROZEN: AtomicBool = AtomicBool::new(false);
+
 // =============================================================================
 // Dirty Rectangle Tracking
 // =============================================================================
@@ -136,6 +139,121 @@ pub fn end_pixel_batch() {
     unsafe { PIXEL_BATCH_MODE = false; }
 }
 
+// =============================================================================
+// Color Adjustment (brightness / contrast / gamma)
+// =============================================================================
+//
+// Neither the emulator nor our DE2 setup exposes the mixer's enhancement
+// block, so adjustments are a software lookup table applied while flush()
+// copies the back buffer to the front buffer. The back buffer keeps the
+// original colors, so reading pixels back (window backing stores, cursor)
+// is unaffected and a change can be re-applied with one full-screen flush.
+
+/// Brightness range in percent (below 10 the panel is unreadable)
+pub const BRIGHTNESS_RANGE: (u16, u16) = (10, 100);
+/// Contrast range in percent (100 = unchanged)
+pub const CONTRAST_RANGE: (u16, u16) = (50, 150);
+/// Gamma range in hundredths (100 = 1.0, unchanged; higher brightens midtones)
+pub const GAMMA_RANGE: (u16, u16) = (50, 300);
+
+/// Display color adjustment
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ColorAdjust {
+    /// Percent of full brightness
+    pub brightness: u16,
+    /// Percent of original contrast
+    pub contrast: u16,
+    /// Gamma in hundredths
+    pub gamma: u16,
+}
+
+impl ColorAdjust {
+    pub const DEFAULT: ColorAdjust = ColorAdjust { brightness: 100, contrast: 100, gamma: 100 };
+
+    /// Check every value is within its range
+    pub fn validate(&self) -> Result<(), &'static str> {
+        let within = |v: u16, (lo, hi): (u16, u16)| v >= lo && v <= hi;
+        if !within(self.brightness, BRIGHTNESS_RANGE) {
+            return Err("brightness out of range (10-100)");
+        }
+        if !within(self.contrast, CONTRAST_RANGE) {
+            return Err("contrast out of range (50-150)");
+        }
+        if !within(self.gamma, GAMMA_RANGE) {
+            return Err("gamma out of range (0.50-3.00)");
+        }
+        Ok(())
+    }
+
+    /// Build the per-channel lookup table
+    fn build_lut(&self) -> [u8; 256] {
+        let mut lut = [0u8; 256];
+        let inv_gamma = 100.0 / self.gamma as f32;
+        for (i, entry) in lut.iter_mut().enumerate() {
+            // Contrast pivots around mid-gray
+            let c = (i as i32 - 128) * self.contrast as i32 / 100 + 128;
+            let c = c.clamp(0, 255) as f32 / 255.0;
+            let g = if c <= 0.0 { 0.0 } else { exp(ln(c) * inv_gamma) };
+            let v = g * 255.0 * self.brightness as f32 / 100.0 + 0.5;
+            *entry = if v >= 255.0 { 255 } else { v as u8 };
+        }
+        lut
+    }
+}
+
+/// Current adjustment
+static mut COLOR_ADJUST: ColorAdjust = ColorAdjust::DEFAULT;
+
+/// Lookup table applied to each color channel in flush()
+static mut COLOR_LUT: [u8; 256] = [0; 256];
+
+/// Whether COLOR_LUT is in use (false means identity: plain copy)
+static COLOR_LUT_ACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// Set the display color adjustment and re-present the whole screen
+pub fn set_color_adjust(adjust: ColorAdjust) -> Result<(), &'static str> {
+    adjust.validate()?;
+    unsafe {
+        COLOR_ADJUST = adjust;
+        if adjust != ColorAdjust::DEFAULT {
+            COLOR_LUT = adjust.build_lut();
+        }
+    }
+    COLOR_LUT_ACTIVE.store(adjust != ColorAdjust::DEFAULT, Ordering::Release);
+    mark_all_dirty();
+    Ok(())
+}
+
+/// Current display color adjustment
+pub fn color_adjust() -> ColorAdjust {
+    unsafe { COLOR_ADJUST }
+}
+
+/// Natural logarithm for x > 0 (no libm in the kernel)
+fn ln(x: f32) -> f32 {
+    // x = m * 2^e with m in [1, 2)
+    let bits = x.to_bits();
+    let e = ((bits >> 23) & 0xFF) as i32 - 127;
+    let m = f32::from_bits((bits & 0x007F_FFFF) | 0x3F80_0000);
+    // ln(m) = 2 * atanh((m - 1) / (m + 1))
+    let s = (m - 1.0) / (m + 1.0);
+    let s2 = s * s;
+    let series = s * (1.0 + s2 * (1.0 / 3.0 + s2 * (1.0 / 5.0 + s2 * (1.0 / 7.0 + s2 / 9.0))));
+    e as f32 * core::f32::consts::LN_2 + 2.0 * series
+}
+
+/// e^y for y <= 0 (all the LUT needs)
+fn exp(y: f32) -> f32 {
+    // y = n * ln2 + r with r in (-ln2, 0]
+    let n = (y / core::f32::consts::LN_2) as i32;
+    let r = y - n as f32 * core::f32::consts::LN_2;
+    let taylor = 1.0 + r * (1.0 + r * (0.5 + r * (1.0 / 6.0 + r * (1.0 / 24.0 + r / 120.0))));
+    if n < -126 {
+        return 0.0;
+    }
+    taylor * f32::from_bits(((n + 127) as u32) << 23)
+}
+
 // =============================================================================
 // GpuDriver - Main rendering interface
 // =============================================================================
@@ -494,6 +612,25 @@ impl GpuDriver {
         FRAMEBUFFER_ADDR as *const u32
     }
 
+    /// FNV-1a checksum of a rectangle of the front buffer (what is on screen),
+    /// clipped to the display
+    pub fn checksum_rect(&self, x: u32, y: u32, w: u32, h: u32) -> u32 {
+        let mut hash: u32 = 0x811c_9dc5;
+        let x_end = x.saturating_add(w).min(self.width);
+        let y_end = y.saturating_add(h).min(self.height);
+        let fb_ptr = FRAMEBUFFER_ADDR as *const u32;
+        for cy in y.min(y_end)..y_end {
+            for cx in x.min(x_end)..x_end {
+                let pixel = unsafe { core::ptr::read_volatile(fb_ptr.add((cy * self.width + cx) as usize)) };
+                for b in pixel.to_le_bytes() {
+                    hash ^= b as u32;
+                    hash = hash.wrapping_mul(0x0100_0193);
+                }
+            }
+        }
+        hash
+    }
+
     /// Get framebuffer as bytes
     pub fn framebuffer_bytes(&self) -> &[u8] {
         let fb_size = (self.width * self.height * 4) as usize;
@@ -613,6 +750,9 @@ where
 /// Only copies the dirty rectangle region from back buffer to front buffer.
 /// Skips copy entirely if nothing has changed since last flush.
 pub fn flush() {
+    if DISPLAY_FROZEN.load(Ordering::Acquire) {
+        return;
+    }
     unsafe {
         // Skip if nothing changed
         if !FRAME_DIRTY {
@@ -637,11 +777,29 @@ pub fn flush() {
         let src_base = BACK_BUFFER_ADDR as *const u8;
         let dst_base = FRAMEBUFFER_ADDR as *mut u8;
         
+        let lut = if COLOR_LUT_ACTIVE.load(Ordering::Acquire) {
+            Some(&*core::ptr::addr_of!(COLOR_LUT))
+        } else {
+            None
+        };
         for y in min_y..max_y {
             let row_offset = (y * DISPLAY_WIDTH + min_x) as usize * 4;
             let src_row = src_base.add(row_offset);
             let dst_row = dst_base.add(row_offset);
-            core::ptr::copy_nonoverlapping(src_row, dst_row, dirty_width * 4);
+            match lut {
+                None => core::ptr::copy_nonoverlapping(src_row, dst_row, dirty_width * 4),
+                Some(lut) => {
+                    // Map each color channel, keep the alpha byte
+                    let src = core::slice::from_raw_parts(src_row as *const u32, dirty_width);
+                    let dst = core::slice::from_raw_parts_mut(dst_row as *mut u32, dirty_width);
+                    for (d, &p) in dst.iter_mut().zip(src) {
+                        *d = (p & 0xFF00_0000)
+                            | (lut[(p >> 16) as usize & 0xFF] as u32) << 16
+                            | (lut[(p >> 8) as usize & 0xFF] as u32) << 8
+                            | lut[p as usize & 0xFF] as u32;
+                    }
+                }
+            }
         }
         
         // Increment frame version so browser knows to fetch new frame
@@ -664,6 +822,12 @@ pub fn flush() {
     }
 }
 
+/// Stop presenting frames: later `flush()` calls become no-ops.
+/// Used by the panic screen to keep its diagnostics on the display.
+pub fn freeze() {
+    DISPLAY_FROZEN.store(true, Ordering::Release);
+}
+
 /// Clear the display to black and flush
 /// Used when gpuid service is stopped to clear the framebuffer
 /// NOTE: This writes directly to both buffers for immediate effect
@@ -6,9 +6,18 @@
 //! # Memory Map
 //! - EMAC: 0x0450_0000
 //! - SYSCON for EMAC: 0x0300_0030
-
-use crate::device::{NetworkDevice, NetworkError};
+//!
+//! # Receive Interrupt
+//! The EMAC raises its PLIC line when a frame lands in the RX ring.
+//! `handle_interrupt` acknowledges it and marks a frame pending; once one
+//! has been seen, `NetState::poll` skips polls with nothing pending. Until
+//! then (no PLIC in the emulator, or interrupts not routed) every poll
+//! reads the ring as before.
+
+use crate::device::{network, NetworkDevice, NetworkError};
+use crate::lock::wait::WaitQueue;
 use core::ptr::{read_volatile, write_volatile};
+use core::sync::atomic::{AtomicBool, Ordering};
 use alloc::vec::Vec;
 
 // =============================================================================
@@ -65,6 +74,8 @@ const TX_CTL1_TX_DMA_EN: u32 = 1 << 30;
 const RX_CTL0_RX_EN: u32 = 1 << 31;
 const RX_CTL1_RX_DMA_EN: u32 = 1 << 30;
 
+const INT_RX: u32 = 1 << 8;              // Frame received
+
 // PHY Address (RTL8201F)
 const PHY_ADDR: u32 = 1;
 
@@ -188,6 +199,10 @@ impl D1Emac {
         let speed_ctl = self.get_speed_ctl();
         self.write_reg(EMAC_BASIC_CTL0, speed_ctl | CTL0_FULL_DUPLEX);
 
+        // Interrupt on received frames (acknowledged in handle_interrupt)
+        self.write_reg(EMAC_INT_STA, 0xFFFF_FFFF);
+        self.write_reg(EMAC_INT_EN, INT_RX);
+
         // Enable TX and RX
         self.write_reg(EMAC_TX_CTL0, TX_CTL0_TX_EN);
         self.write_reg(EMAC_RX_CTL0, RX_CTL0_RX_EN);
@@ -397,20 +412,26 @@ impl NetworkDevice for D1Emac {
 
     fn transmit(&mut self, packet: &[u8]) -> Result<(), NetworkError> {
         if !self.initialized {
+            network::count_tx_error();
             return Err(NetworkError::NotReady);
         }
+        if packet.len() > BUFFER_SIZE {
+            network::count_tx_error();
+            return Err(NetworkError::TxFailed);
+        }
 
         let desc = &mut self.tx_desc[self.tx_head];
         
         // Check if descriptor is free
         if (desc.status & DESC_OWN) != 0 {
+            network::count_tx_dropped();
             return Err(NetworkError::TxFailed);
         }
 
         // Copy packet to buffer
         let buf = &mut self.tx_buffers[self.tx_head];
-        let len = packet.len().min(BUFFER_SIZE);
-        buf[..len].copy_from_slice(&packet[..len]);
+        let len = packet.len();
+        buf[..len].copy_from_slice(packet);
 
         // Setup descriptor
         desc.size = len as u32;
@@ -420,6 +441,7 @@ impl NetworkDevice for D1Emac {
         self.write_reg(EMAC_TX_CTL1, self.read_reg(EMAC_TX_CTL1) | (1 << 31));
 
         self.tx_head = (self.tx_head + 1) % TX_DESC_COUNT;
+        network::count_tx(len);
         Ok(())
     }
 
@@ -454,6 +476,7 @@ impl NetworkDevice for D1Emac {
             // Give back to DMA and skip
             unsafe { core::ptr::write_volatile(&mut desc.status, DESC_OWN); }
             self.rx_head = (self.rx_head + 1) % RX_DESC_COUNT;
+            network::count_rx_error();
             return Err(NetworkError::RxBufferTooSmall);
         }
 
@@ -465,6 +488,7 @@ impl NetworkDevice for D1Emac {
         unsafe { core::ptr::write_volatile(&mut desc.status, DESC_OWN); }
 
         self.rx_head = (self.rx_head + 1) % RX_DESC_COUNT;
+        network::count_rx(frame_len);
         Ok(frame_len)
     }
 
@@ -494,6 +518,46 @@ pub fn probe() -> bool {
     id1 != 0xFFFF && id1 != 0x0000
 }
 
+/// A received frame is waiting in the RX ring (set by the interrupt)
+static RX_PENDING: AtomicBool = AtomicBool::new(false);
+
+/// An RX interrupt has been taken, so they can be relied on
+static RX_IRQ_SEEN: AtomicBool = AtomicBool::new(false);
+
+/// Processes waiting for a frame to arrive
+pub static RX_WAIT: WaitQueue = WaitQueue::new();
+
+/// EMAC interrupt handler (from the trap handler, on hart 0)
+///
+/// Acknowledges the interrupt and, for received frames, marks them pending
+/// and wakes tasks waiting for I/O.
+pub fn handle_interrupt() {
+    let status = unsafe { read_volatile((EMAC_BASE + EMAC_INT_STA) as *const u32) };
+    unsafe { write_volatile((EMAC_BASE + EMAC_INT_STA) as *mut u32, status) };
+    if status & INT_RX != 0 {
+        RX_PENDING.store(true, Ordering::Release);
+        RX_IRQ_SEEN.store(true, Ordering::Release);
+        RX_WAIT.wake_all();
+        crate::lock::wait::IO_EVENTS.wake_all();
+        crate::task::wake_io();
+    }
+}
+
+/// Whether RX interrupts are known to work (one has been taken)
+pub fn rx_interrupts_active() -> bool {
+    RX_IRQ_SEEN.load(Ordering::Acquire)
+}
+
+/// Whether a frame is pending, clearing the flag
+pub fn take_rx_pending() -> bool {
+    RX_PENDING.swap(false, Ordering::AcqRel)
+}
+
+/// Whether a frame is pending
+pub fn rx_pending() -> bool {
+    RX_PENDING.load(Ordering::Acquire)
+}
+
 /// Create a new D1 EMAC device instance
 /// Returns initialized EMAC or error if init fails
 pub fn create_device() -> Result<D1Emac, NetworkError> {
@@ -540,21 +604,31 @@ impl Device for D1EmacDevice<'_> {
     }
 
     fn receive(&mut self, _timestamp: Instant) -> Option<(Self::RxToken<'_>, Self::TxToken<'_>)> {
-        if !self.0.has_packet() {
-            return None;
-        }
-        
-        // Receive packet into buffer
-        let mut buf = alloc::vec![0u8; BUFFER_SIZE];
-        match self.0.receive(&mut buf) {
-            Ok(len) => {
-                buf.truncate(len);
-                Some((
-                    D1RxToken { buffer: buf },
-                    D1TxToken { device: self.0 },
-                ))
+        loop {
+            if !self.0.has_packet() {
+                return None;
+            }
+
+            // Receive packet into buffer
+            let mut buf = alloc::vec![0u8; BUFFER_SIZE];
+            let len = self.0.receive(&mut buf).ok()?;
+            buf.truncate(len);
+            // Frames the firewall denies are dropped before anything sees them
+            if !crate::net::firewall::allows(&buf) {
+                network::count_rx_dropped();
+                continue;
             }
-            Err(_) => None,
+            // Router Advertisements and ICMP Time Exceeded never reach a socket;
+            // note them for SLAAC and traceroute, and mirror ARP traffic into
+            // the neighbor table for `arp`
+            crate::net::ipv6::note_frame(&buf);
+            crate::net::traceroute::note_frame(&buf);
+            crate::net::neighbors::note_frame(&buf);
+            crate::net::sockstats::note_rx(&buf);
+            return Some((
+                D1RxToken { buffer: buf },
+                D1TxToken { device: self.0 },
+            ));
         }
     }
 
@@ -590,6 +664,7 @@ impl TxToken for D1TxToken<'_> {
     {
         let mut buffer = alloc::vec![0u8; len];
         let result = f(&mut buffer);
+        crate::net::sockstats::note_tx(&buffer);
         
         // Send the packet (ignore errors, smoltcp handles retransmission)
         let _ = self.device.transmit(&buffer);
@@ -8,8 +8,12 @@
 //! - MMC1: 0x0402_1000
 //! - MMC2: 0x0402_2000 (eMMC if present)
 
+use crate::cpu::{get_hart_id, MAX_HARTS};
 use crate::device::{BlockDevice, BlockError};
+use crate::Spinlock;
+use core::cell::UnsafeCell;
 use core::ptr::{read_volatile, write_volatile};
+use core::sync::atomic::{AtomicBool, AtomicPtr, AtomicU64, AtomicUsize, Ordering};
 
 // =============================================================================
 // Register Definitions
@@ -88,6 +92,8 @@ pub struct D1Mmc {
     sector_count: u64,
     /// Partition offset in sectors (for accessing SFS on partition 2)
     partition_offset: u64,
+    /// Relative card address from CMD3 (upper 16 bits, as CMD7/9/13 expect)
+    rca: u32,
     initialized: bool,
 }
 
@@ -98,6 +104,7 @@ impl D1Mmc {
             base: MMC0_BASE,
             sector_count: 0,
             partition_offset: 0,
+            rca: 0,
             initialized: false,
         }
     }
@@ -161,6 +168,7 @@ impl D1Mmc {
         // CMD3 (SEND_RELATIVE_ADDR)
         self.send_cmd(3, 0, CMD_RESP_EXP)?;
         let rca = self.read_reg(SMHC_RESP0) & 0xFFFF0000;
+        self.rca = rca;
 
         // CMD7 (SELECT_CARD)
         self.send_cmd(7, rca, CMD_RESP_EXP)?;
@@ -416,26 +424,213 @@ impl D1Mmc {
         Err(BlockError::Timeout)
     }
 
-    /// Read a sector from the block device (fs.rs compatibility wrapper)
-    /// Includes retry logic to handle transient MMC failures (especially from secondary harts)
-    pub fn read_sector(&mut self, sector: u64, buf: &mut [u8]) -> Result<(), &'static str> {
-        // Retry up to 3 times with increasing delays
-        for attempt in 0..3 {
-            if self.read_block(sector, buf).is_ok() {
+    /// Wait until the card has programmed every block written so far.
+    ///
+    /// CMD24 completes once the data is in the card's buffer; the card then
+    /// holds DAT0 busy while it writes flash, and may reorder or lose that
+    /// buffered data on power loss. Returning from here means the earlier
+    /// writes are on the medium.
+    fn wait_programmed(&self) -> Result<(), BlockError> {
+        // DAT0 busy
+        let mut idle = false;
+        for _ in 0..1_000_000 {
+            if (self.read_reg(SMHC_STATUS) & STATUS_DATA_BUSY) == 0 {
+                idle = true;
+                break;
+            }
+            core::hint::spin_loop();
+        }
+        if !idle {
+            return Err(BlockError::Timeout);
+        }
+
+        // CMD13 (SEND_STATUS) until the card is back in the transfer state
+        // (CURRENT_STATE = 4) and ready for data
+        for _ in 0..1000 {
+            self.send_cmd(13, self.rca, CMD_RESP_EXP | CMD_CHK_RESP_CRC)?;
+            let status = self.read_reg(SMHC_RESP0);
+            if (status >> 9) & 0xF == 4 && (status & (1 << 8)) != 0 {
                 return Ok(());
             }
-            // Delay before retry
-            for _ in 0..(attempt + 1) * 10000 {
+            for _ in 0..1000 {
                 core::hint::spin_loop();
             }
         }
-        Err("IO Error")
+        Err(BlockError::Timeout)
+    }
+
+    /// Write barrier (fs.rs compatibility wrapper): writes issued before it
+    /// reach the card before any write issued after it
+    pub fn barrier(&self) -> Result<(), &'static str> {
+        self.transfer(Op::Barrier, 0, core::ptr::null_mut(), 0)
+    }
+
+    /// Read a sector from the block device (fs.rs compatibility wrapper).
+    /// Works on any hart (see "Transfers From Any Hart").
+    pub fn read_sector(&self, sector: u64, buf: &mut [u8]) -> Result<(), &'static str> {
+        self.transfer(Op::Read, sector, buf.as_mut_ptr(), buf.len())
+    }
+
+    /// Write a sector to the block device (fs.rs compatibility wrapper).
+    /// Works on any hart (see "Transfers From Any Hart").
+    pub fn write_sector(&self, sector: u64, buf: &[u8]) -> Result<(), &'static str> {
+        self.transfer(Op::Write, sector, buf.as_ptr() as *mut u8, buf.len())
+    }
+
+    /// Run a transfer on the controller: directly on hart 0, through hart 0
+    /// elsewhere
+    fn transfer(&self, op: Op, sector: u64, buf: *mut u8, len: usize) -> Result<(), &'static str> {
+        if get_hart_id() != 0 {
+            return submit(self, op, sector, buf, len);
+        }
+        let busy = CONTROLLER.lock();
+        let result = self.run(op, sector, buf, len);
+        drop(busy);
+        // Transfers queued meanwhile were left to us
+        service_transfers();
+        result
+    }
+
+    /// Perform a transfer (hart 0, controller held)
+    fn run(&self, op: Op, sector: u64, buf: *mut u8, len: usize) -> Result<(), &'static str> {
+        match op {
+            Op::Read => {
+                let buf = unsafe { core::slice::from_raw_parts_mut(buf, len) };
+                // Retry up to 3 times with increasing delays
+                for attempt in 0..3 {
+                    if self.read_block(sector, buf).is_ok() {
+                        return Ok(());
+                    }
+                    // Delay before retry
+                    for _ in 0..(attempt + 1) * 10000 {
+                        core::hint::spin_loop();
+                    }
+                }
+                Err("IO Error")
+            }
+            Op::Write => {
+                let buf = unsafe { core::slice::from_raw_parts(buf, len) };
+                self.write_block(sector, buf).map_err(|_| "IO Error")
+            }
+            Op::Barrier => self.wait_programmed().map_err(|_| "IO Error"),
+        }
     }
+}
+
+// =============================================================================
+// Transfers From Any Hart
+// =============================================================================
+//
+// Secondary harts cannot reach the controller's MMIO, so they hand each
+// transfer to hart 0: the transfer (on the submitter's stack) goes in the
+// submitting hart's slot and hart 0 gets an IPI. Its handler runs the
+// transfer straight away, whatever hart 0 was doing, unless hart 0 is in
+// the middle of a transfer of its own; that one drains the slots when it
+// finishes. Hart 0 also drains them from its scheduler loop and while it
+// waits for a filesystem lock (interrupts are off during syscalls), so a
+// transfer never waits on a lock its submitter holds.
+//
+// A hart has at most one transfer in flight, as it waits for it with
+// preemption off. Draining takes no lock but `CONTROLLER` (only ever
+// tried) and allocates nothing, so it is safe in the IPI handler.
+
+#[derive(Clone, Copy)]
+enum Op {
+    Read,
+    Write,
+    Barrier,
+}
 
-    /// Write a sector to the block device (fs.rs compatibility wrapper)
-    pub fn write_sector(&mut self, sector: u64, buf: &[u8]) -> Result<(), &'static str> {
-        self.write_block(sector, buf).map_err(|_| "IO Error")
+/// A transfer waiting in a slot
+struct Transfer {
+    mmc: *const D1Mmc,
+    op: Op,
+    sector: u64,
+    buf: *mut u8,
+    len: usize,
+    result: UnsafeCell<Result<(), &'static str>>,
+    done: AtomicBool,
+}
+
+/// Held on hart 0 while the controller is busy
+static CONTROLLER: Spinlock<()> = Spinlock::new(());
+
+/// Per-hart transfer waiting for hart 0 (null when none)
+static SLOTS: [AtomicPtr<Transfer>; MAX_HARTS] = {
+    const EMPTY: AtomicPtr<Transfer> = AtomicPtr::new(core::ptr::null_mut());
+    [EMPTY; MAX_HARTS]
+};
+
+/// Transfers in the slots
+static PENDING: AtomicUsize = AtomicUsize::new(0);
+
+/// Transfers run for other harts (for `stats`)
+static SERVED: AtomicU64 = AtomicU64::new(0);
+
+/// Queue a transfer for hart 0 and wait for it
+fn submit(mmc: &D1Mmc, op: Op, sector: u64, buf: *mut u8, len: usize) -> Result<(), &'static str> {
+    let hart = get_hart_id();
+    let Some(slot) = SLOTS.get(hart) else {
+        return Err("IO Error");
+    };
+    let transfer = Transfer {
+        mmc,
+        op,
+        sector,
+        buf,
+        len,
+        result: UnsafeCell::new(Err("IO Error")),
+        done: AtomicBool::new(false),
+    };
+
+    // Not switched out while the slot points at this stack frame
+    crate::lock::preempt_disable();
+    slot.store(&transfer as *const Transfer as *mut Transfer, Ordering::Release);
+    PENDING.fetch_add(1, Ordering::AcqRel);
+    crate::cpu::send_ipi(0);
+    while !transfer.done.load(Ordering::Acquire) {
+        // Woken by hart 0's IPI (or the next timer tick)
+        unsafe { core::arch::asm!("wfi", options(nomem, nostack)) };
     }
+    crate::lock::preempt_enable();
+
+    unsafe { *transfer.result.get() }
+}
+
+/// Run the transfers other harts queued (hart 0 only; does nothing if the
+/// controller is busy, its user drains them when done)
+pub fn service_transfers() {
+    if get_hart_id() != 0 {
+        return;
+    }
+    while PENDING.load(Ordering::Acquire) > 0 {
+        let Some(busy) = CONTROLLER.try_lock() else {
+            return;
+        };
+        for (hart, slot) in SLOTS.iter().enumerate() {
+            let ptr = slot.swap(core::ptr::null_mut(), Ordering::AcqRel);
+            if ptr.is_null() {
+                continue;
+            }
+            PENDING.fetch_sub(1, Ordering::AcqRel);
+            // SAFETY: the submitter waits on its stack until `done` is set
+            let transfer = unsafe { &*ptr };
+            let mmc = unsafe { &*transfer.mmc };
+            let result = mmc.run(transfer.op, transfer.sector, transfer.buf, transfer.len);
+            unsafe { *transfer.result.get() = result };
+            transfer.done.store(true, Ordering::Release);
+            SERVED.fetch_add(1, Ordering::Relaxed);
+            crate::cpu::send_ipi(hart);
+        }
+        // A transfer queued after its slot was passed is picked up on the
+        // next round (its IPI may have found the controller busy)
+        drop(busy);
+    }
+}
+
+/// Transfers hart 0 ran for other harts since boot
+pub fn transfers_served() -> u64 {
+    SERVED.load(Ordering::Relaxed)
 }
 
 // =============================================================================
@@ -480,6 +675,13 @@ impl BlockDevice for D1Mmc {
     fn sector_count(&self) -> u64 {
         self.sector_count
     }
+
+    fn flush(&self) -> Result<(), BlockError> {
+        if !self.initialized {
+            return Err(BlockError::NotReady);
+        }
+        self.wait_programmed()
+    }
 }
 
 // =============================================================================
@@ -5,6 +5,12 @@
 //!
 //! Thread-safe: All state is protected by a Spinlock, allowing any hart to poll.
 //!
+//! Key repeat is done here rather than by the host: host repeats of a held
+//! key are dropped, and `poll` queues EV_KEY events with value 2 after the
+//! `input.key_repeat_delay_ms` setting, at `input.key_repeat_rate_hz`.
+//! Typed characters (EV_CHAR) are repeated by the host keyboard layout and
+//! are not affected.
+//!
 //! # Registers (emulator-specific MMIO at 0x0250_2000)
 //! - 0x100: INT status (1 = touch event pending)
 //! - 0x104: Touch status (bit 7 = data ready, bits 0-3 = touch count)
@@ -73,12 +79,20 @@ pub struct InputEvent {
 }
 
 impl InputEvent {
-    /// Check if this is a key press event (EV_KEY with value 1)
+    /// Check if this is a key press event (EV_KEY with value 1, or 2 for auto-repeat)
     pub fn is_key_press(&self) -> bool {
-        self.event_type == EV_KEY && self.value == 1
+        self.event_type == EV_KEY && (self.value == 1 || self.value == 2)
     }
 }
 
+/// Keyboard key being held down, for software key repeat
+#[derive(Clone, Copy)]
+struct HeldKey {
+    code: u16,
+    /// Time of the next repeat event (ms)
+    next_repeat: i64,
+}
+
 /// Touch driver state - protected by Spinlock for thread safety
 struct TouchState {
     /// Whether touch is currently pressed
@@ -95,6 +109,8 @@ struct TouchState {
     tail: usize,
     /// Total events processed (for debugging)
     event_count: u32,
+    /// Key to auto-repeat (keyboard keys only, not buttons)
+    held_key: Option<HeldKey>,
 }
 
 impl TouchState {
@@ -107,6 +123,7 @@ impl TouchState {
             head: 0,
             tail: 0,
             event_count: 0,
+            held_key: None,
         }
     }
 
@@ -205,23 +222,71 @@ pub fn poll() {
         let key_state = read_reg(KEY_STATE) as i32;
         
         let mut state = TOUCH_STATE.lock();
-        state.event_count = state.event_count.wrapping_add(1);
-        state.push_event(InputEvent {
-            event_type: EV_KEY,
-            code: key_code,
-            value: key_state,
-        });
-        state.push_event(InputEvent {
-            event_type: EV_SYN,
-            code: 0,
-            value: 0,
-        });
+        let held = state.held_key.map(|k| k.code);
+        let forward = match key_state {
+            // A press of the key already held is a host repeat; we repeat it ourselves
+            1 if held == Some(key_code) => false,
+            1 => {
+                if key_code < 0x100 {
+                    let (delay_ms, _) = crate::settings::key_repeat();
+                    state.held_key = Some(HeldKey {
+                        code: key_code,
+                        next_repeat: crate::get_time_ms() + delay_ms as i64,
+                    });
+                }
+                true
+            }
+            0 => {
+                if held == Some(key_code) {
+                    state.held_key = None;
+                }
+                true
+            }
+            _ => false,
+        };
+        if forward {
+            state.event_count = state.event_count.wrapping_add(1);
+            state.push_event(InputEvent {
+                event_type: EV_KEY,
+                code: key_code,
+                value: key_state,
+            });
+            state.push_event(InputEvent {
+                event_type: EV_SYN,
+                code: 0,
+                value: 0,
+            });
+        }
         drop(state);
         
         // Clear the key event (by reading - or write 0 to acknowledge)
         write_reg(KEY_COUNT, 0);
     }
     
+    // Software key repeat for the held key (at most one event per poll)
+    {
+        let mut state = TOUCH_STATE.lock();
+        if let Some(mut held) = state.held_key {
+            let (_, rate_hz) = crate::settings::key_repeat();
+            let now = crate::get_time_ms();
+            if rate_hz > 0 && now >= held.next_repeat {
+                held.next_repeat = now + (1000 / rate_hz) as i64;
+                state.held_key = Some(held);
+                state.event_count = state.event_count.wrapping_add(1);
+                state.push_event(InputEvent {
+                    event_type: EV_KEY,
+                    code: held.code,
+                    value: 2,
+                });
+                state.push_event(InputEvent {
+                    event_type: EV_SYN,
+                    code: 0,
+                    value: 0,
+                });
+            }
+        }
+    }
+    
     // Then poll for touch events
     let int_status = read_reg(TOUCH_INT_STATUS);
     if int_status == 0 {
@@ -310,8 +375,12 @@ pub fn get_event_count() -> u32 {
 
 /// Get the next event from the queue
 /// Thread-safe: can be called from any hart
+///
+/// Every consumer takes input through here, so this is also where events
+/// are recorded and where a replay substitutes its own (see `input_replay`).
 pub fn next_event() -> Option<InputEvent> {
-    TOUCH_STATE.lock().pop_event()
+    let event = TOUCH_STATE.lock().pop_event();
+    super::input_replay::route(event)
 }
 
 /// Check if there are pending events
@@ -0,0 +1,169 @@
+//! Host bridge driver
+//!
+//! Cooperative channel to the page embedding the browser emulator: the page
+//! can drop files into the guest and push clipboard text, and the guest can
+//! hand clipboard text back. The device only exists in the emulator; on
+//! hardware `probe` finds no magic and the bridge stays off.
+//!
+//! Host to guest messages wait in a queue on the host side. The head message
+//! is read through `HB_IN_READ`, 4 bytes at a time: first its name, then its
+//! data, each padded to a whole word. Writing `HB_IN_POP` moves to the next.
+//!
+//! Not thread-safe: only called from the hostbridged service on hart 0.
+//!
+//! # Registers (emulator-specific MMIO at 0x0203_1000)
+//! - 0x00: HB_MAGIC - "HBRG" (read-only)
+//! - 0x04: HB_VERSION - Interface version (read-only)
+//! - 0x08: HB_IN_COUNT - Number of queued host messages
+//! - 0x0C: HB_IN_KIND - Kind of the head message (1 = clipboard, 2 = file)
+//! - 0x10: HB_IN_NAME_LEN - Name length of the head message in bytes
+//! - 0x14: HB_IN_DATA_LEN - Data length of the head message in bytes
+//! - 0x18: HB_IN_READ - Next word of the head message's name, then data
+//! - 0x1C: HB_IN_POP - Write 1 to drop the head message
+//! - 0x20: HB_OUT_LEN - Write the length of outgoing clipboard text (starts a transfer)
+//! - 0x24: HB_OUT_WRITE - Next word of the outgoing clipboard text
+//! - 0x28: HB_OUT_COMMIT - Write 1 to set the page's clipboard to the text
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ptr::{read_volatile, write_volatile};
+use core::sync::atomic::{AtomicBool, Ordering};
+
+// =============================================================================
+// Register Definitions
+// =============================================================================
+
+/// Host bridge base address
+const HOST_BRIDGE_BASE: usize = 0x0203_1000;
+
+// Register offsets
+const HB_MAGIC: usize = HOST_BRIDGE_BASE + 0x00;
+const HB_VERSION: usize = HOST_BRIDGE_BASE + 0x04;
+const HB_IN_COUNT: usize = HOST_BRIDGE_BASE + 0x08;
+const HB_IN_KIND: usize = HOST_BRIDGE_BASE + 0x0C;
+const HB_IN_NAME_LEN: usize = HOST_BRIDGE_BASE + 0x10;
+const HB_IN_DATA_LEN: usize = HOST_BRIDGE_BASE + 0x14;
+const HB_IN_READ: usize = HOST_BRIDGE_BASE + 0x18;
+const HB_IN_POP: usize = HOST_BRIDGE_BASE + 0x1C;
+const HB_OUT_LEN: usize = HOST_BRIDGE_BASE + 0x20;
+const HB_OUT_WRITE: usize = HOST_BRIDGE_BASE + 0x24;
+const HB_OUT_COMMIT: usize = HOST_BRIDGE_BASE + 0x28;
+
+/// "HBRG" in little-endian
+const MAGIC: u32 = 0x4752_4248;
+
+// Message kinds
+const KIND_CLIPBOARD: u32 = 1;
+const KIND_FILE: u32 = 2;
+
+/// Largest file the page may drop in (bigger ones are refused)
+pub const MAX_FILE_SIZE: usize = 1024 * 1024;
+
+/// Longest clipboard text passed either way
+pub const MAX_CLIPBOARD_LEN: usize = 64 * 1024;
+
+/// Longest file name accepted from the page
+const MAX_NAME_LEN: usize = 255;
+
+// =============================================================================
+// Driver State
+// =============================================================================
+
+static AVAILABLE: AtomicBool = AtomicBool::new(false);
+
+/// Message from the embedding page
+pub enum HostMessage {
+    /// The page's clipboard changed
+    Clipboard(String),
+    /// A file was dropped onto the emulator
+    File { name: String, data: Vec<u8> },
+    /// A message that could not be taken (too big, or of an unknown kind)
+    Refused { kind: u32, name: String, len: usize },
+}
+
+// =============================================================================
+// Register Access
+// =============================================================================
+
+/// Read a 32-bit register
+#[inline]
+fn read_reg(addr: usize) -> u32 {
+    unsafe { read_volatile(addr as *const u32) }
+}
+
+/// Write a 32-bit register
+#[inline]
+fn write_reg(addr: usize, value: u32) {
+    unsafe { write_volatile(addr as *mut u32, value) }
+}
+
+/// Read `len` bytes of the head message from HB_IN_READ
+fn read_bytes(len: usize) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(len.next_multiple_of(4));
+    while bytes.len() < len {
+        bytes.extend_from_slice(&read_reg(HB_IN_READ).to_le_bytes());
+    }
+    bytes.truncate(len);
+    bytes
+}
+
+// =============================================================================
+// Public API
+// =============================================================================
+
+/// Look for the bridge; returns whether the page offers one
+pub fn probe() -> bool {
+    let found = read_reg(HB_MAGIC) == MAGIC;
+    AVAILABLE.store(found, Ordering::Relaxed);
+    found
+}
+
+/// Whether `probe` found the bridge
+#[inline]
+pub fn is_available() -> bool {
+    AVAILABLE.load(Ordering::Relaxed)
+}
+
+/// Interface version reported by the page
+pub fn version() -> u32 {
+    read_reg(HB_VERSION)
+}
+
+/// Take the next message from the page, if any
+pub fn next_message() -> Option<HostMessage> {
+    if !is_available() || read_reg(HB_IN_COUNT) == 0 {
+        return None;
+    }
+    let kind = read_reg(HB_IN_KIND);
+    let name_len = read_reg(HB_IN_NAME_LEN) as usize;
+    let data_len = read_reg(HB_IN_DATA_LEN) as usize;
+
+    // Whole words are read, so the name's padding is consumed with it
+    let name = String::from_utf8_lossy(&read_bytes(name_len.min(MAX_NAME_LEN))).into_owned();
+    let message = match kind {
+        KIND_CLIPBOARD if data_len <= MAX_CLIPBOARD_LEN && name_len == 0 => {
+            HostMessage::Clipboard(String::from_utf8_lossy(&read_bytes(data_len)).into_owned())
+        }
+        KIND_FILE if data_len <= MAX_FILE_SIZE && name_len <= MAX_NAME_LEN => {
+            HostMessage::File { name, data: read_bytes(data_len) }
+        }
+        _ => HostMessage::Refused { kind, name, len: data_len },
+    };
+    write_reg(HB_IN_POP, 1);
+    Some(message)
+}
+
+/// Set the page's clipboard to `text` (cut to MAX_CLIPBOARD_LEN)
+pub fn send_clipboard(text: &str) {
+    if !is_available() {
+        return;
+    }
+    let bytes = &text.as_bytes()[..text.len().min(MAX_CLIPBOARD_LEN)];
+    write_reg(HB_OUT_LEN, bytes.len() as u32);
+    for chunk in bytes.chunks(4) {
+        let mut word = [0u8; 4];
+        word[..chunk.len()].copy_from_slice(chunk);
+        write_reg(HB_OUT_WRITE, u32::from_le_bytes(word));
+    }
+    write_reg(HB_OUT_COMMIT, 1);
+}
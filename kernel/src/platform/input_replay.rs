@@ -0,0 +1,297 @@
+//! Input Record/Replay
+//!
+//! Records the input events the GUI receives (touch, mouse, keys) with
+//! their timing to a file, and feeds such a file back in place of live input
+//! at the recorded pace, so GUI changes can be regression-tested in the
+//! emulator. While recording, checkpoints store a checksum of a screen
+//! rectangle; replaying recomputes it at the same moment and reports any
+//! that differ.
+//!
+//! Hooked into `d1_touch::next_event`, which every input consumer goes
+//! through. During a replay, live input is dropped so the run stays
+//! deterministic.
+//!
+//! # File format
+//! ```text
+//! # inputrec v1
+//! <ms> ev <type> <code> <value>          # an InputEvent
+//! <ms> check <x> <y> <w> <h> <checksum>  # screen checksum (hex)
+//! ```
+//! `<ms>` counts from the start of the recording.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use super::d1_display;
+use super::d1_touch::InputEvent;
+use crate::cpu::fs_proxy;
+use crate::Spinlock;
+
+const HEADER: &str = "# inputrec v1\n";
+
+/// Fastest allowed replay speed multiplier
+pub const MAX_SPEED: u64 = 16;
+
+#[derive(Clone, Copy)]
+enum Entry {
+    Event(InputEvent),
+    Check { rect: [u32; 4], checksum: u32 },
+}
+
+#[derive(Clone, Copy)]
+struct Timed {
+    at_ms: u64,
+    entry: Entry,
+}
+
+/// Outcome of one replayed checkpoint
+struct CheckResult {
+    at_ms: u64,
+    expected: u32,
+    actual: u32,
+}
+
+enum Mode {
+    Idle,
+    Recording {
+        path: String,
+        started_ms: i64,
+        entries: Vec<Timed>,
+    },
+    Replaying {
+        started_ms: i64,
+        speed: u64,
+        entries: Vec<Timed>,
+        next: usize,
+        /// A due checkpoint waits one frame so the events before it are drawn
+        check_armed: bool,
+        events: usize,
+        results: Vec<CheckResult>,
+    },
+}
+
+struct ReplayState {
+    mode: Mode,
+    /// Report of the last replay and its number of failed checkpoints
+    report: Option<(String, usize)>,
+}
+
+static REPLAY: Spinlock<ReplayState> = Spinlock::new(ReplayState { mode: Mode::Idle, report: None });
+
+/// Checksum of a screen rectangle as currently displayed
+fn screen_checksum(rect: [u32; 4]) -> u32 {
+    d1_display::with_gpu(|gpu| gpu.checksum_rect(rect[0], rect[1], rect[2], rect[3])).unwrap_or(0)
+}
+
+fn elapsed_ms(started_ms: i64) -> u64 {
+    (crate::get_time_ms() - started_ms).max(0) as u64
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Input Hook
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Pass a live event (or its absence) through: recorded while recording,
+/// replaced by the next due recorded event while replaying
+pub fn route(live: Option<InputEvent>) -> Option<InputEvent> {
+    let mut state = REPLAY.lock();
+    match &mut state.mode {
+        Mode::Idle => live,
+        Mode::Recording { started_ms, entries, .. } => {
+            if let Some(event) = live {
+                entries.push(Timed { at_ms: elapsed_ms(*started_ms), entry: Entry::Event(event) });
+            }
+            live
+        }
+        Mode::Replaying { started_ms, speed, entries, next, check_armed, events, results } => {
+            let now = elapsed_ms(*started_ms) * *speed;
+            while let Some(timed) = entries.get(*next).copied() {
+                if timed.at_ms > now {
+                    return None;
+                }
+                match timed.entry {
+                    Entry::Event(event) => {
+                        *next += 1;
+                        *events += 1;
+                        return Some(event);
+                    }
+                    Entry::Check { rect, checksum } => {
+                        if !*check_armed {
+                            *check_armed = true;
+                            return None;
+                        }
+                        *check_armed = false;
+                        *next += 1;
+                        results.push(CheckResult { at_ms: timed.at_ms, expected: checksum, actual: screen_checksum(rect) });
+                    }
+                }
+            }
+            let report = finish_replay(&mut state.mode, "");
+            state.report = Some(report);
+            None
+        }
+    }
+}
+
+/// End the replay in `mode`, returning its report and failed check count
+fn finish_replay(mode: &mut Mode, note: &str) -> (String, usize) {
+    let Mode::Replaying { started_ms, events, results, .. } = core::mem::replace(mode, Mode::Idle) else {
+        return (String::new(), 0);
+    };
+    let mut report = format!("Replayed {} events in {} ms{}\n", events, elapsed_ms(started_ms), note);
+    let mut failed = 0;
+    for (i, check) in results.iter().enumerate() {
+        if check.actual == check.expected {
+            report.push_str(&format!("check {} at {} ms: ok ({:08x})\n", i + 1, check.at_ms, check.actual));
+        } else {
+            failed += 1;
+            report.push_str(&format!(
+                "check {} at {} ms: FAILED (expected {:08x}, got {:08x})\n",
+                i + 1,
+                check.at_ms,
+                check.expected,
+                check.actual
+            ));
+        }
+    }
+    report.push_str(&format!("{} checks, {} failed\n", results.len(), failed));
+    (report, failed)
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Recording
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Start recording input events, to be saved to `path` by `stop`
+pub fn start_recording(path: &str) -> Result<(), &'static str> {
+    let mut state = REPLAY.lock();
+    if !matches!(state.mode, Mode::Idle) {
+        return Err("Already recording or replaying");
+    }
+    state.mode = Mode::Recording { path: String::from(path), started_ms: crate::get_time_ms(), entries: Vec::new() };
+    Ok(())
+}
+
+/// Add a checkpoint for a screen rectangle to the recording, returning its checksum
+pub fn add_check(x: u32, y: u32, w: u32, h: u32) -> Result<u32, &'static str> {
+    let rect = [x, y, w, h];
+    let checksum = screen_checksum(rect);
+    let mut state = REPLAY.lock();
+    let Mode::Recording { started_ms, entries, .. } = &mut state.mode else {
+        return Err("Not recording");
+    };
+    entries.push(Timed { at_ms: elapsed_ms(*started_ms), entry: Entry::Check { rect, checksum } });
+    Ok(checksum)
+}
+
+/// Stop recording and save it (returning the number of entries saved), or
+/// abandon a running replay (returning 0)
+pub fn stop() -> Result<usize, &'static str> {
+    let (path, entries) = {
+        let mut state = REPLAY.lock();
+        match core::mem::replace(&mut state.mode, Mode::Idle) {
+            Mode::Recording { path, entries, .. } => (path, entries),
+            replaying @ Mode::Replaying { .. } => {
+                state.mode = replaying;
+                let report = finish_replay(&mut state.mode, " (stopped)");
+                state.report = Some(report);
+                return Ok(0);
+            }
+            Mode::Idle => return Err("Not recording or replaying"),
+        }
+    };
+
+    let mut text = String::from(HEADER);
+    for timed in &entries {
+        match timed.entry {
+            Entry::Event(e) => {
+                text.push_str(&format!("{} ev {} {} {}\n", timed.at_ms, e.event_type, e.code, e.value))
+            }
+            Entry::Check { rect: [x, y, w, h], checksum } => {
+                text.push_str(&format!("{} check {} {} {} {} {:08x}\n", timed.at_ms, x, y, w, h, checksum))
+            }
+        }
+    }
+    fs_proxy::fs_write(&path, text.as_bytes())?;
+    Ok(entries.len())
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Replay
+// ═══════════════════════════════════════════════════════════════════════════════
+
+fn parse_line(line: &str) -> Option<Timed> {
+    let mut words = line.split_whitespace();
+    let at_ms = words.next()?.parse().ok()?;
+    let entry = match words.next()? {
+        "ev" => Entry::Event(InputEvent {
+            event_type: words.next()?.parse().ok()?,
+            code: words.next()?.parse().ok()?,
+            value: words.next()?.parse().ok()?,
+        }),
+        "check" => {
+            let mut rect = [0u32; 4];
+            for v in rect.iter_mut() {
+                *v = words.next()?.parse().ok()?;
+            }
+            Entry::Check { rect, checksum: u32::from_str_radix(words.next()?, 16).ok()? }
+        }
+        _ => return None,
+    };
+    Some(Timed { at_ms, entry })
+}
+
+/// Replay the recording at `path`, `speed` times faster than recorded.
+/// Returns the number of entries; the report is available from `status`
+/// once the replay has finished.
+pub fn start_replay(path: &str, speed: u64) -> Result<usize, &'static str> {
+    if !(1..=MAX_SPEED).contains(&speed) {
+        return Err("Invalid replay speed");
+    }
+    let data = fs_proxy::fs_read(path).ok_or("Recording not found")?;
+    let text = core::str::from_utf8(&data).map_err(|_| "Recording is not text")?;
+    let entries = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(parse_line)
+        .collect::<Option<Vec<_>>>()
+        .ok_or("Malformed recording")?;
+    let count = entries.len();
+
+    let mut state = REPLAY.lock();
+    if !matches!(state.mode, Mode::Idle) {
+        return Err("Already recording or replaying");
+    }
+    state.report = None;
+    state.mode = Mode::Replaying {
+        started_ms: crate::get_time_ms(),
+        speed,
+        entries,
+        next: 0,
+        check_armed: false,
+        events: 0,
+        results: Vec::new(),
+    };
+    Ok(count)
+}
+
+/// What the facility is doing
+pub enum Status {
+    Idle,
+    Recording,
+    Replaying,
+    /// Report of the last replay and its number of failed checkpoints
+    Finished(String, usize),
+}
+
+pub fn status() -> Status {
+    let state = REPLAY.lock();
+    match (&state.mode, &state.report) {
+        (Mode::Recording { .. }, _) => Status::Recording,
+        (Mode::Replaying { .. }, _) => Status::Replaying,
+        (Mode::Idle, Some((report, failed))) => Status::Finished(report.clone(), *failed),
+        (Mode::Idle, None) => Status::Idle,
+    }
+}
@@ -11,6 +11,9 @@ pub mod d1_emac;        // D1 EMAC Ethernet driver (for D1 hardware and VM D1 em
 pub mod d1_mmc;         // D1 MMC/SD card driver
 pub mod d1_touch;       // D1 Touch (GT911) driver
 pub mod d1_audio;       // D1 Audio codec driver
+pub mod host_bridge;    // Clipboard and file drop from the page embedding the emulator
+pub mod input_replay;   // Input event record/replay for GUI regression tests
+pub mod plic;           // Platform-Level Interrupt Controller (device interrupts)
 
 // Re-export D1 as the active platform
 pub use d1 as current;
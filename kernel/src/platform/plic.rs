@@ -0,0 +1,66 @@
+//! D1 Platform-Level Interrupt Controller (PLIC)
+//!
+//! Routes device interrupts to the harts' S-mode external interrupt line.
+//! The kernel only takes device interrupts on hart 0 (which owns MMIO), so
+//! sources are enabled for hart 0's S-mode context. The trap handler claims
+//! the pending source, runs its driver's handler and completes it.
+//!
+//! On the C906 OpenSBI grants S-mode access to the PLIC (PLIC_CTRL) before
+//! the kernel starts.
+
+use core::ptr::{read_volatile, write_volatile};
+
+use super::d1::PLIC_BASE;
+
+/// Interrupt sources on the D1
+pub const EMAC_IRQ: u32 = 62;
+
+/// Highest source number the D1 PLIC has
+const MAX_IRQ: u32 = 256;
+
+const PRIORITY_BASE: usize = 0x0000;
+const ENABLE_BASE: usize = 0x2000;
+const ENABLE_STRIDE: usize = 0x80;
+const CONTEXT_BASE: usize = 0x20_0000;
+const CONTEXT_STRIDE: usize = 0x1000;
+const CONTEXT_THRESHOLD: usize = 0x0;
+const CONTEXT_CLAIM: usize = 0x4;
+
+/// PLIC context of a hart's S-mode (M-mode is the even one before it)
+fn s_context(hart: usize) -> usize {
+    hart * 2 + 1
+}
+
+fn read_reg(offset: usize) -> u32 {
+    unsafe { read_volatile((PLIC_BASE + offset) as *const u32) }
+}
+
+fn write_reg(offset: usize, value: u32) {
+    unsafe { write_volatile((PLIC_BASE + offset) as *mut u32, value) }
+}
+
+/// Let every enabled source through to `hart` (threshold 0)
+pub fn init_hart(hart: usize) {
+    write_reg(CONTEXT_BASE + s_context(hart) * CONTEXT_STRIDE + CONTEXT_THRESHOLD, 0);
+}
+
+/// Route source `irq` to `hart`'s S-mode at the lowest non-zero priority
+pub fn enable(irq: u32, hart: usize) {
+    if irq == 0 || irq >= MAX_IRQ {
+        return;
+    }
+    write_reg(PRIORITY_BASE + irq as usize * 4, 1);
+    let word = ENABLE_BASE + s_context(hart) * ENABLE_STRIDE + (irq as usize / 32) * 4;
+    write_reg(word, read_reg(word) | (1 << (irq % 32)));
+}
+
+/// Take the highest priority pending source for `hart` (None if spurious)
+pub fn claim(hart: usize) -> Option<u32> {
+    let irq = read_reg(CONTEXT_BASE + s_context(hart) * CONTEXT_STRIDE + CONTEXT_CLAIM);
+    (irq != 0).then_some(irq)
+}
+
+/// Tell the PLIC `hart` is done with `irq`, so it can fire again
+pub fn complete(hart: usize, irq: u32) {
+    write_reg(CONTEXT_BASE + s_context(hart) * CONTEXT_STRIDE + CONTEXT_CLAIM, irq);
+}
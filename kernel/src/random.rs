@@ -0,0 +1,224 @@
+//! Kernel Random Number Generator
+//!
+//! A ChaCha20-based CSPRNG that serves `getrandom`, the WASM `random()`
+//! import and TLS. It is seeded at boot from:
+//!
+//! - the VirtIO entropy device (`device::virtio_rng`), when there is one
+//! - jitter of the `time` CSR around memory accesses, always mixed in and
+//!   the only source on hardware without an entropy device
+//!
+//! Each request is generated with the current key, after which the key is
+//! replaced by fresh keystream ("fast key erasure"), so earlier output
+//! cannot be recovered from the state. The key is also reseeded from the
+//! sources every `RESEED_INTERVAL_MS` or `RESEED_BYTES` of output, and
+//! events with unpredictable timing (keystrokes) are mixed in as they come.
+
+use sha2::{Digest, Sha256};
+
+use crate::services::klogd::{klog_info, klog_warning};
+use crate::Spinlock;
+
+/// Reseed from the entropy sources at least this often
+const RESEED_INTERVAL_MS: i64 = 60_000;
+/// ... or after this much output
+const RESEED_BYTES: u64 = 1024 * 1024;
+
+/// Timer samples hashed into one jitter seed
+const JITTER_SAMPLES: usize = 4096;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// ChaCha20 block function (RFC 8439)
+// ═══════════════════════════════════════════════════════════════════════════════
+
+#[inline(always)]
+fn quarter_round(s: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+    s[a] = s[a].wrapping_add(s[b]);
+    s[d] = (s[d] ^ s[a]).rotate_left(16);
+    s[c] = s[c].wrapping_add(s[d]);
+    s[b] = (s[b] ^ s[c]).rotate_left(12);
+    s[a] = s[a].wrapping_add(s[b]);
+    s[d] = (s[d] ^ s[a]).rotate_left(8);
+    s[c] = s[c].wrapping_add(s[d]);
+    s[b] = (s[b] ^ s[c]).rotate_left(7);
+}
+
+/// One 64-byte keystream block for `key` at block `counter` (zero nonce)
+fn chacha20_block(key: &[u8; 32], counter: u64, out: &mut [u8; 64]) {
+    let mut input = [0u32; 16];
+    input[..4].copy_from_slice(&[0x6170_7865, 0x3320_646e, 0x7962_2d32, 0x6b20_6574]);
+    for (word, bytes) in input[4..12].iter_mut().zip(key.chunks_exact(4)) {
+        *word = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    }
+    input[12] = counter as u32;
+    input[13] = (counter >> 32) as u32;
+
+    let mut state = input;
+    for _ in 0..10 {
+        quarter_round(&mut state, 0, 4, 8, 12);
+        quarter_round(&mut state, 1, 5, 9, 13);
+        quarter_round(&mut state, 2, 6, 10, 14);
+        quarter_round(&mut state, 3, 7, 11, 15);
+        quarter_round(&mut state, 0, 5, 10, 15);
+        quarter_round(&mut state, 1, 6, 11, 12);
+        quarter_round(&mut state, 2, 7, 8, 13);
+        quarter_round(&mut state, 3, 4, 9, 14);
+    }
+    for (i, bytes) in out.chunks_exact_mut(4).enumerate() {
+        bytes.copy_from_slice(&state[i].wrapping_add(input[i]).to_le_bytes());
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Generator
+// ═══════════════════════════════════════════════════════════════════════════════
+
+struct Generator {
+    key: [u8; 32],
+    /// Whether the key has been seeded
+    seeded: bool,
+    /// Output since the last reseed
+    output_bytes: u64,
+    /// When the key was last reseeded (ms)
+    reseeded_at: i64,
+}
+
+static GENERATOR: Spinlock<Generator> = Spinlock::new(Generator {
+    key: [0; 32],
+    seeded: false,
+    output_bytes: 0,
+    reseeded_at: 0,
+});
+
+impl Generator {
+    /// Replace the key with a hash of itself and `input`
+    fn mix(&mut self, input: &[u8]) {
+        let mut hasher = Sha256::new();
+        hasher.update(self.key);
+        hasher.update(input);
+        self.key.copy_from_slice(&hasher.finalize());
+    }
+
+    fn fill(&mut self, buf: &mut [u8]) {
+        let mut block = [0u8; 64];
+        // Block 0 becomes the next key, the output starts at block 1
+        chacha20_block(&self.key, 0, &mut block);
+        let next_key: [u8; 32] = block[..32].try_into().unwrap();
+        for (i, chunk) in buf.chunks_mut(64).enumerate() {
+            chacha20_block(&self.key, i as u64 + 1, &mut block);
+            chunk.copy_from_slice(&block[..chunk.len()]);
+        }
+        self.key = next_key;
+        block.fill(0);
+        self.output_bytes += buf.len() as u64;
+    }
+}
+
+/// Hash `JITTER_SAMPLES` readings of the `time` CSR taken around memory
+/// accesses whose latency varies with cache and bus state
+fn timer_jitter() -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    let mut scratch = [0u64; 64];
+    let mut last = crate::trap::read_mtime();
+    for i in 0..JITTER_SAMPLES {
+        let slot = (last as usize ^ i.wrapping_mul(31)) % scratch.len();
+        scratch[slot] = scratch[slot].wrapping_add(last).rotate_left(7);
+        let now = unsafe { core::ptr::read_volatile(&scratch[slot]) } ^ crate::trap::read_mtime();
+        hasher.update(now.wrapping_sub(last).to_le_bytes());
+        last = crate::trap::read_mtime();
+    }
+    hasher.finalize().into()
+}
+
+/// Collect a seed from every source; returns it and whether the entropy
+/// device contributed
+fn gather_seed() -> ([u8; 64], bool) {
+    let mut seed = [0u8; 64];
+    let from_device = crate::device::virtio_rng::read(&mut seed[..32]) == 32;
+    seed[32..].copy_from_slice(&timer_jitter());
+    (seed, from_device)
+}
+
+fn reseed(generator: &mut Generator) {
+    let (mut seed, _) = gather_seed();
+    generator.mix(&seed);
+    seed.fill(0);
+    generator.output_bytes = 0;
+    generator.reseeded_at = crate::get_time_ms();
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Public API
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Probe the entropy device and seed the generator (boot)
+pub fn init() {
+    if crate::device::virtio_rng::init().is_err() {
+        klog_warning("random", "No entropy device, seeding from timer jitter only");
+    }
+    let (mut seed, from_device) = gather_seed();
+    let mut generator = GENERATOR.lock();
+    generator.mix(&seed);
+    seed.fill(0);
+    generator.seeded = true;
+    generator.reseeded_at = crate::get_time_ms();
+    drop(generator);
+    klog_info("random", if from_device { "Seeded from VirtIO entropy device" } else { "Seeded from timer jitter" });
+}
+
+/// Fill `buf` with cryptographically secure random bytes
+pub fn fill(buf: &mut [u8]) {
+    let mut generator = GENERATOR.lock();
+    let now = crate::get_time_ms();
+    if !generator.seeded {
+        // Used before boot seeded it
+        reseed(&mut generator);
+        generator.seeded = true;
+    } else if generator.output_bytes >= RESEED_BYTES || now - generator.reseeded_at >= RESEED_INTERVAL_MS {
+        reseed(&mut generator);
+    }
+    // Rekey every 64 KiB of a large request too
+    for chunk in buf.chunks_mut(64 * 1024) {
+        generator.fill(chunk);
+    }
+}
+
+/// A random u64
+pub fn next_u64() -> u64 {
+    let mut bytes = [0u8; 8];
+    fill(&mut bytes);
+    u64::from_le_bytes(bytes)
+}
+
+/// Mix the time of an unpredictable event (e.g. a keystroke) into the
+/// key; skipped if the generator is busy
+pub fn add_event_timing() {
+    let sample = crate::trap::read_mtime();
+    if let Some(mut generator) = GENERATOR.try_lock() {
+        generator.mix(&sample.to_le_bytes());
+    }
+}
+
+/// `rand_core` adapter over the kernel generator, for TLS
+#[derive(Clone, Copy, Default)]
+pub struct KernelRng;
+
+impl rand_core::RngCore for KernelRng {
+    fn next_u32(&mut self) -> u32 {
+        next_u64() as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        fill(dest);
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+        fill(dest);
+        Ok(())
+    }
+}
+
+impl rand_core::CryptoRng for KernelRng {}
@@ -9,6 +9,10 @@ use alloc::string::String;
 use alloc::vec::Vec;
 use core::sync::atomic::{AtomicBool, Ordering};
 
+use crate::cpu::process::{IoCounters, IoKind};
+use crate::fs::watch::{self, WatchId, WATCH_CREATE, WATCH_DELETE};
+use crate::locale::msg;
+use crate::Spinlock;
 use crate::{clint::get_time_ms, device::uart, lock::utils::{OUTPUT_BUFFER_SIZE, OUTPUT_CAPTURE, SHELL_CMD_STATE}, scripting, wasm};
 
 /// Flag indicating we're running from GUI context (need S-mode execution)
@@ -41,6 +45,62 @@ pub fn shell_cmd_end() {
     state.end_command(get_time_ms() as u64);
 }
 
+/// Record the exit status of the last command (`$?`)
+pub fn set_last_status(code: i32) {
+    SHELL_CMD_STATE.lock().last_status = code;
+}
+
+/// Exit status of the last command
+pub fn last_status() -> i32 {
+    SHELL_CMD_STATE.lock().last_status
+}
+
+/// Record a program's exit code, reporting a failure
+pub fn report_exit(code: i32) {
+    set_last_status(code);
+    if code != 0 {
+        out_str("\x1b[1;31mExited with code:\x1b[0m ");
+        out_line(&alloc::format!("{}", code));
+    }
+}
+
+/// Replace `$?` in a command line with the last command's exit status
+pub fn expand_status(line: &[u8]) -> Vec<u8> {
+    let status = format!("{}", last_status());
+    let mut out = Vec::with_capacity(line.len());
+    let mut i = 0;
+    while i < line.len() {
+        if line[i..].starts_with(b"$?") {
+            out.extend_from_slice(status.as_bytes());
+            i += 2;
+        } else {
+            out.push(line[i]);
+            i += 1;
+        }
+    }
+    out
+}
+
+/// Charge sleep time to the current shell command
+pub fn shell_cmd_sleep(ms: u64) {
+    SHELL_CMD_STATE.lock().add_sleep(ms);
+}
+
+/// Charge file I/O made from this hart to the current shell command
+pub fn shell_cmd_io(read: usize, written: usize) {
+    SHELL_CMD_STATE.lock().add_io(crate::get_hart_id(), read, written);
+}
+
+/// Charge a syscall to the current shell command if it runs on this hart
+pub fn shell_cmd_charge(kind: Option<IoKind>, bytes: u64) -> bool {
+    SHELL_CMD_STATE.lock().charge_syscall(crate::get_hart_id(), kind, bytes)
+}
+
+/// Syscall and I/O counters of the current (or last) shell command
+pub fn shell_cmd_syscall_io() -> IoCounters {
+    SHELL_CMD_STATE.lock().syscall_io
+}
+
 /// Write a string - respects capture mode
 pub fn out_str(s: &str) {
     let mut cap = OUTPUT_CAPTURE.lock();
@@ -62,11 +122,47 @@ pub fn out_str(s: &str) {
 
 
 /// Write a string with newline - respects capture mode
-fn out_line(s: &str) {
+pub fn out_line(s: &str) {
     out_str(s);
     out_str("\n");
 }
 
+/// Names of the programs in /usr/bin, valid until the watch sees a file
+/// appear or disappear there
+struct PathCache {
+    watch: Option<WatchId>,
+    names: Vec<String>,
+}
+
+static PATH_CACHE: Spinlock<PathCache> = Spinlock::new(PathCache { watch: None, names: Vec::new() });
+
+/// Programs in /usr/bin (for command completion), listed again only after
+/// /usr/bin changed
+pub fn path_commands() -> Vec<String> {
+    let mut cache = PATH_CACHE.lock();
+    let fresh = cache
+        .watch
+        .and_then(watch::take_events)
+        .is_some_and(|events| events.is_empty());
+    if fresh {
+        return cache.names.clone();
+    }
+    // Watch before listing so a change made meanwhile marks the new list stale
+    if cache.watch.is_none() {
+        cache.watch = Some(watch::add("/usr/bin", WATCH_CREATE | WATCH_DELETE));
+    }
+    // Don't hold the lock while hart 0 does the listing
+    drop(cache);
+
+    let names: Vec<String> = crate::cpu::fs_proxy::fs_list("/")
+        .into_iter()
+        .filter_map(|f| f.name.strip_prefix("/usr/bin/").map(String::from))
+        .filter(|name| !name.is_empty())
+        .collect();
+    PATH_CACHE.lock().names = names.clone();
+    names
+}
+
 /// Find a script/binary by name
 /// 
 /// Search order:
@@ -76,6 +172,11 @@ fn out_line(s: &str) {
 /// 
 /// Uses fs_proxy for hart-aware filesystem access - works on any hart.
 pub fn find_script(cmd: &str) -> Option<Vec<u8>> {
+    find_script_path(cmd).map(|(_, content)| content)
+}
+
+/// Like `find_script`, also returning the path the script was found at
+pub fn find_script_path(cmd: &str) -> Option<(String, Vec<u8>)> {
     use crate::cpu::fs_proxy;
     
     // If command contains '/', treat as path
@@ -85,39 +186,64 @@ pub fn find_script(cmd: &str) -> Option<Vec<u8>> {
         } else {
             crate::resolve_path(cmd)
         };
-        return fs_proxy::fs_read(&full_path);
+        return fs_proxy::fs_read(&full_path).map(|content| (full_path, content));
     }
 
     // Search /usr/bin/ first
     let usr_bin_path = format!("/usr/bin/{}", cmd);
     if let Some(content) = fs_proxy::fs_read(&usr_bin_path) {
-        return Some(content);
+        return Some((usr_bin_path, content));
     }
 
     // Search root as fallback
-    fs_proxy::fs_read(cmd)
+    fs_proxy::fs_read(cmd).map(|content| (format!("/{}", cmd), content))
 }
 
+/// `#!` scripts an interpreter may itself be, before a binary is reached
+const MAX_INTERPRETER_DEPTH: usize = 4;
 
-/// Run a script from its bytes
+/// The interpreter line of a `#!` script: the interpreter and its optional
+/// single argument (`#!/usr/bin/rhai -q`)
+pub fn parse_shebang(bytes: &[u8]) -> Option<(&str, Option<&str>)> {
+    let line = bytes.strip_prefix(b"#!")?;
+    let line = &line[..line.iter().position(|&b| b == b'\n').unwrap_or(line.len())];
+    let line = core::str::from_utf8(line).ok()?.trim();
+    let (interpreter, arg) = match line.find(|c: char| c == ' ' || c == '\t') {
+        Some(pos) => (&line[..pos], Some(line[pos..].trim()).filter(|arg| !arg.is_empty())),
+        None => (line, None),
+    };
+    (!interpreter.is_empty()).then_some((interpreter, arg))
+}
+
+/// Program to run for the file `path` (run as `name`): the file itself, or
+/// the interpreter its `#!` line names, followed through interpreters that
+/// are scripts too. Returns the name to run it as, its bytes and its
+/// arguments; an interpreter gets its own argument, then the script's path,
+/// then `args`.
+pub fn resolve_interpreter(name: &str, path: String, bytes: Vec<u8>, args: &str) -> Result<(String, Vec<u8>, String), &'static str> {
+    let mut program = (String::from(name), path, bytes, String::from(args));
+    for _ in 0..=MAX_INTERPRETER_DEPTH {
+        let (interpreter, arg) = match parse_shebang(&program.2) {
+            Some((interpreter, arg)) => (String::from(interpreter), arg.map(String::from)),
+            None => return Ok((program.0, program.2, program.3)),
+        };
+        let (interpreter_path, interpreter_bytes) = find_script_path(&interpreter).ok_or("Interpreter not found")?;
+        let mut interpreter_args = String::new();
+        for part in arg.iter().chain(core::iter::once(&program.1)) {
+            interpreter_args.push_str(part);
+            interpreter_args.push(' ');
+        }
+        interpreter_args.push_str(&program.3);
+        program = (interpreter, interpreter_path, interpreter_bytes, interpreter_args);
+    }
+    Err("Too many levels of interpreters")
+}
+
+
+/// Run a script from its bytes; `name` is the command it was run as
 /// 
 /// Supports both native RISC-V ELF binaries (preferred) and WASM binaries (legacy).
-pub fn run_script_bytes(bytes: &[u8], args: &str) {
-    use core::arch::asm;
-    
-    // CRITICAL: Capture return frame at ABSOLUTE FUNCTION START
-    // before ANY function calls (is_elf, load_elf, split, collect, etc.)
-    let caller_ra: u64;
-    let caller_sp: u64;
-    unsafe {
-        asm!(
-            "mv {ra}, ra",
-            "mv {sp}, sp",
-            ra = out(reg) caller_ra,
-            sp = out(reg) caller_sp,
-        );
-    }
-    
+pub fn run_script_bytes(name: &str, bytes: &[u8], args: &str) {
     // Detect ELF magic (0x7f 'E' 'L' 'F') - native RISC-V binary (preferred)
     if crate::elf_loader::is_elf(bytes) {
         match crate::elf_loader::load_elf(bytes) {
@@ -129,14 +255,11 @@ pub fn run_script_bytes(bytes: &[u8], args: &str) {
                 // The gui_cmd process handles GUI execution, shell uses shelld
                 // Both use the same execute_elf path - the difference is in how
                 // restore_kernel_context handles the exit (via gui_mode flag)
-                let exit_code = crate::elf_loader::execute_elf(&loaded, &args_vec, caller_ra, caller_sp);
-                
-                if exit_code != 0 {
-                    out_str("\x1b[1;31mExited with code:\x1b[0m ");
-                    out_line(&alloc::format!("{}", exit_code));
-                }
+                let exit_code = crate::elf_loader::execute_elf(&loaded, name, &args_vec);
+                report_exit(exit_code);
             }
             Err(e) => {
+                set_last_status(126);
                 out_str("\x1b[1;31mELF load error:\x1b[0m ");
                 out_line(&alloc::format!("{:?}", e));
             }
@@ -153,6 +276,7 @@ pub fn run_script_bytes(bytes: &[u8], args: &str) {
     {
         let args_vec: Vec<&str> = args.split_whitespace().collect();
         if let Err(e) = wasm::execute(bytes, &args_vec) {
+            set_last_status(1);
             out_str("\x1b[1;31mError:\x1b[0m ");
             out_line(&e);
         }
@@ -160,6 +284,7 @@ pub fn run_script_bytes(bytes: &[u8], args: &str) {
     }
 
     // Not a recognized binary format
+    set_last_status(126);
     out_line("\x1b[1;31mError:\x1b[0m Not a valid binary (expected ELF or WASM)");
 }
 
@@ -173,16 +298,218 @@ pub fn run_script_bytes(bytes: &[u8], args: &str) {
 pub fn execute_command(cmd: &[u8], args: &[u8]) {
     let cmd_str = core::str::from_utf8(cmd).unwrap_or("");
     let args_str = core::str::from_utf8(args).unwrap_or("");
+    // Built-ins succeed unless they say otherwise
+    set_last_status(0);
 
-    
+    // =============================================================================
+    // ESSENTIAL BUILT-INS
+    // These wrap other commands or change shell state, so they can't be scripts
+    // =============================================================================
+    match cmd_str {
+        "time" => {
+            let args_str = args_str.trim();
+            let (inner_cmd, inner_args) = match args_str.find(|c: char| c == ' ' || c == '\t') {
+                Some(pos) => (&args_str[..pos], args_str[pos..].trim_start()),
+                None => (args_str, ""),
+            };
+            if inner_cmd.is_empty() {
+                out_line("Usage: time <command> [args]");
+                return;
+            }
+            timed(inner_cmd.as_bytes(), inner_args.as_bytes());
+            return;
+        }
+        "nice" => {
+            nice_command(args_str.trim());
+            return;
+        }
+        "strace" => {
+            strace_command(args_str.trim());
+            return;
+        }
+        "jobs" => {
+            crate::services::shelld::jobs_command(args_str);
+            return;
+        }
+        "fg" => {
+            crate::services::shelld::fg_command(args_str);
+            return;
+        }
+        "bg" => {
+            crate::services::shelld::bg_command(args_str);
+            return;
+        }
+        "timing" => {
+            let mut state = SHELL_CMD_STATE.lock();
+            match args_str.trim() {
+                "on" => state.timing = true,
+                "off" => state.timing = false,
+                "" => {}
+                _ => {
+                    drop(state);
+                    out_line("Usage: timing [on|off]");
+                    return;
+                }
+            }
+            let enabled = state.timing;
+            drop(state);
+            out_line(if enabled { "timing: on" } else { "timing: off" });
+            return;
+        }
+        _ => {}
+    }
+
+    if SHELL_CMD_STATE.lock().timing {
+        timed(cmd, args);
+    } else {
+        execute_resolved(cmd_str, args_str);
+    }
+}
+
+/// `nice`: show the shell's nice value, or run a command with it raised by
+/// `-n N` (default 10) and put it back afterwards. Foreground commands run in
+/// the shell's own process, so that is the process reniced.
+fn nice_command(args: &str) {
+    const USAGE: &str = "Usage: nice [-n <adjustment>] <command> [args]";
+    let pid = crate::cpu::CPU_TABLE.get(crate::get_hart_id()).and_then(|cpu| cpu.running_process());
+    let Some(process) = pid.and_then(|pid| crate::cpu::sched::SCHEDULER.get_process(pid)) else {
+        out_line("nice: not running in a scheduled process");
+        return;
+    };
+    if args.is_empty() {
+        out_line(&format!("{}", process.nice()));
+        return;
+    }
+
+    let (adjustment, rest) = match args.strip_prefix("-n") {
+        Some(rest) => {
+            let rest = rest.trim_start();
+            let (value, rest) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+            match value.parse::<i32>() {
+                Ok(value) => (value, rest.trim_start()),
+                Err(_) => {
+                    out_line(USAGE);
+                    return;
+                }
+            }
+        }
+        None => (10, args),
+    };
+    let (inner_cmd, inner_args) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+    if inner_cmd.is_empty() {
+        out_line(USAGE);
+        return;
+    }
+
+    let old = crate::cpu::sched::set_nice(process.pid, process.nice().saturating_add(adjustment)).unwrap_or(0);
+    execute_command(inner_cmd.as_bytes(), inner_args.trim_start().as_bytes());
+    crate::cpu::sched::set_nice(process.pid, old);
+}
+
+/// `strace`: trace the syscalls of process PID (again to stop), or of a
+/// command for as long as it runs. Foreground commands run in the shell's own
+/// process, so that is the process traced. Lines go to the kernel log, or
+/// with `-o` to `/var/log/strace.<pid>.log`.
+fn strace_command(args: &str) {
+    use crate::syscall_trace::{log_path, TraceMode};
+    const USAGE: &str = "Usage: strace [-o] <pid> | strace [-o] <command> [args]";
+
+    let (mode, rest) = match args.strip_prefix("-o") {
+        Some(rest) if rest.is_empty() || rest.starts_with(char::is_whitespace) => (TraceMode::File, rest.trim_start()),
+        _ => (TraceMode::Klog, args),
+    };
+    let (target, inner_args) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+    if target.is_empty() {
+        out_line(USAGE);
+        return;
+    }
+    let destination = |pid: u32| match mode {
+        TraceMode::File => log_path(pid),
+        _ => String::from("the kernel log (dmesg)"),
+    };
+
+    if let Ok(pid) = target.parse::<u32>() {
+        let Some(process) = crate::cpu::sched::SCHEDULER.get_process(pid) else {
+            set_last_status(1);
+            out_line(&format!("strace: no process {}", pid));
+            return;
+        };
+        if process.trace() != TraceMode::Off {
+            process.set_trace(TraceMode::Off);
+            out_line(&format!("strace: stopped tracing {}", pid));
+        } else {
+            process.set_trace(mode);
+            out_line(&format!("strace: tracing {} to {}", pid, destination(pid)));
+        }
+        return;
+    }
+
+    let pid = crate::cpu::CPU_TABLE.get(crate::get_hart_id()).and_then(|cpu| cpu.running_process());
+    let Some(process) = pid.and_then(|pid| crate::cpu::sched::SCHEDULER.get_process(pid)) else {
+        out_line("strace: not running in a scheduled process");
+        return;
+    };
+    let old = process.set_trace(mode);
+    execute_command(target.as_bytes(), inner_args.trim_start().as_bytes());
+    process.set_trace(old);
+    uart::write_line(&format!("\x1b[90m[strace] syscalls logged to {}\x1b[0m", destination(process.pid)));
+}
+
+/// Run a command and print its resource usage summary
+///
+/// The summary goes straight to the UART (like stderr), so it is never
+/// captured by `>` redirection.
+fn timed(cmd: &[u8], args: &[u8]) {
+    let cmd_str = core::str::from_utf8(cmd).unwrap_or("");
+    let args_str = core::str::from_utf8(args).unwrap_or("");
+
+    let heap_before = crate::allocator::reset_heap_peak();
+    let before = SHELL_CMD_STATE.lock().usage(get_time_ms() as u64);
+
+    execute_resolved(cmd_str, args_str);
+
+    let after = SHELL_CMD_STATE.lock().usage(get_time_ms() as u64);
+    let peak = crate::allocator::heap_peak().saturating_sub(heap_before);
+
+    let real = after.time_ms.saturating_sub(before.time_ms);
+    let slept = after.sleep_ms.saturating_sub(before.sleep_ms);
+    uart::write_line(&format!(
+        "\x1b[90m[time] real {} ms  cpu {} ms  heap peak +{} KiB  io {} B read / {} B written\x1b[0m",
+        real,
+        real.saturating_sub(slept),
+        (peak + 1023) / 1024,
+        after.io_read_bytes.saturating_sub(before.io_read_bytes),
+        after.io_write_bytes.saturating_sub(before.io_write_bytes),
+    ));
+}
+
+/// Resolve and run a (non built-in) command
+fn execute_resolved(cmd_str: &str, args_str: &str) {
     // =============================================================================
     // SCRIPT RESOLUTION (PATH-like)
     // Fallback to script-based commands for flexibility/customization
     // =============================================================================
-    if let Some(script_bytes) = scripting::find_script(cmd_str) {
+    if let Some((path, script_bytes)) = scripting::find_script_path(cmd_str) {
         // Track command CPU time
         shell_cmd_start(cmd_str);
-        run_script_bytes(&script_bytes, args_str);
+        match resolve_interpreter(cmd_str, path, script_bytes, args_str) {
+            Ok((name, bytes, args)) => run_script_bytes(&name, &bytes, &args),
+            Err(e) => {
+                set_last_status(126);
+                out_line(&format!("\x1b[1;31m{}:\x1b[0m bad interpreter: {}", cmd_str, e));
+            }
+        }
+        shell_cmd_end();
+        return;
+    }
+
+    // =============================================================================
+    // KERNEL MODULE COMMANDS
+    // Registered at runtime by modules loaded with `kmod load`
+    // =============================================================================
+    if crate::kmod::has_command(cmd_str) {
+        shell_cmd_start(cmd_str);
+        crate::kmod::run_command(cmd_str, args_str);
         shell_cmd_end();
         return;
     }
@@ -190,7 +517,8 @@ pub fn execute_command(cmd: &[u8], args: &[u8]) {
     // =============================================================================
     // COMMAND NOT FOUND
     // =============================================================================
-    out_str("\x1b[1;31mCommand not found:\x1b[0m ");
+    set_last_status(127);
+    out_str(&format!("\x1b[1;31m{}\x1b[0m ", msg("shell.not_found")));
     out_line(cmd_str);
-    out_line("\x1b[0;90mTry 'help' for available commands, or check /usr/bin/ for scripts\x1b[0m");
+    out_line(&format!("\x1b[0;90m{}\x1b[0m", msg("shell.not_found_hint")));
 }
\ No newline at end of file
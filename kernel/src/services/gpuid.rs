@@ -17,7 +17,7 @@ pub fn gpuid_service() {
         
         // Transition to GUI mode
         ui::boot::print_line("");
-        ui::boot::print_boot_msg("BOOT", "System ready, starting GUI...");
+        ui::boot::print_boot_msg("BOOT", &crate::locale::msg("boot.ready"));
         ui::boot::render();
         
         // Clear framebuffer and switch to GUI phase
@@ -52,9 +52,14 @@ pub fn gpuid_service() {
     // This prevents lag from intermediate mouse positions
     let mut had_input = false;
     let mut had_button_action = false;
+    // Input only dismisses a kiosk page; it must not reach the screen underneath
+    let kiosk_showing = ui::kiosk::is_showing();
     
     while let Some(event) = display_proxy::touch_next_event() {
         had_input = true;
+        if kiosk_showing {
+            continue;
+        }
         
         if is_main_screen {
             // For mouse movement (EV_ABS), just update position - don't process fully
@@ -86,9 +91,16 @@ pub fn gpuid_service() {
         // Check for GUI command completion (async polling)
         ui::main_screen::check_gui_command_completion();
         
-        // Periodically update hardware stats (no input needed)
-        if !had_input {
-            ui::update_main_screen_hardware_stats();
+        // A kiosk page covers the whole screen while it is up
+        if !ui::kiosk::tick(had_input) {
+            ui::main_screen::check_qr_request();
+            ui::main_screen::check_redraw_request();
+            ui::main_screen::check_host_events();
+            
+            // Periodically update hardware stats (no input needed)
+            if !had_input {
+                ui::update_main_screen_hardware_stats();
+            }
         }
     } else {
         ui::with_ui(|ui_mgr| {
@@ -114,6 +126,8 @@ pub fn gpuid_tick() {
     use crate::ui;
     use crate::cpu::display_proxy;
     use crate::platform::d1_touch::{EV_ABS, ABS_X, ABS_Y}; // Constants only
+
+    let _subsystem = crate::allocator::enter_subsystem(crate::allocator::Subsystem::Ui);
     
     // Skip if GPU not available (proxied check)
     if !display_proxy::is_available() {
@@ -123,7 +137,7 @@ pub fn gpuid_tick() {
     // Handle boot phase transition
     if ui::boot::get_phase() == ui::boot::BootPhase::Console {
         ui::boot::print_line("");
-        ui::boot::print_boot_msg("BOOT", "System ready, starting GUI...");
+        ui::boot::print_boot_msg("BOOT", &crate::locale::msg("boot.ready"));
         ui::boot::render();
         
         // Clear and switch to GUI
@@ -150,8 +164,12 @@ pub fn gpuid_tick() {
     
     // COALESCED event processing (same as gpuid_service)
     let mut had_input = false;
+    let kiosk_showing = ui::kiosk::is_showing();
     while let Some(event) = display_proxy::touch_next_event() {
         had_input = true;
+        if kiosk_showing {
+            continue;
+        }
         if is_main_screen {
             // For mouse movement, just update position - coalesce multiple events
             if event.event_type == EV_ABS {
@@ -175,9 +193,16 @@ pub fn gpuid_tick() {
         // Check for GUI command completion (async polling)
         ui::main_screen::check_gui_command_completion();
         
-        // Periodically update hardware stats (no input needed)
-        if !had_input {
-            ui::update_main_screen_hardware_stats();
+        // A kiosk page covers the whole screen while it is up
+        if !ui::kiosk::tick(had_input) {
+            ui::main_screen::check_qr_request();
+            ui::main_screen::check_redraw_request();
+            ui::main_screen::check_host_events();
+            
+            // Periodically update hardware stats (no input needed)
+            if !had_input {
+                ui::update_main_screen_hardware_stats();
+            }
         }
     } else {
         ui::with_ui(|ui_mgr| {
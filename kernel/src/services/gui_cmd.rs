@@ -214,12 +214,8 @@ pub fn gui_cmd_service() {
     // signal_completion() via restore_kernel_context()
     crate::scripting::execute_command(cmd.cmd.as_bytes(), cmd.args.as_bytes());
     
-    // Note: For U-mode execution, control flow does NOT return here.
-    // The command exits via SYS_EXIT -> trap_handler -> restore_kernel_context
-    // which calls signal_completion() and re-enters hart_loop.
-    //
-    // For S-mode execution (fallback), control returns here normally.
-    // In that case, we need to manually signal completion:
+    // A U-mode binary has signalled completion on its way back here.
+    // Anything else (built-ins, WASM) has not, so signal it now:
     if GUI_CMD_RUNNING.load(Ordering::SeqCst) {
         use crate::device::uart::write_line;
         write_line("[GUI_CMD] S-mode fallback - command returned");
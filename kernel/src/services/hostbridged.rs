@@ -0,0 +1,170 @@
+//! hostbridged - Host bridge daemon
+//!
+//! Serves the emulator's host bridge (see `platform::host_bridge`): files the
+//! embedding page drops in are saved under /home, and clipboard text goes
+//! both ways through a kernel clipboard. The GUI picks up what happened with
+//! `next_event` (the terminal pastes clipboard text and reports new files);
+//! programs use the clipboard syscall (`clip`).
+//!
+//! Pinned to hart 0, which owns the device.
+
+use alloc::collections::VecDeque;
+use alloc::format;
+use alloc::string::String;
+use core::sync::atomic::{AtomicI64, Ordering};
+
+use crate::cpu::fs_proxy;
+use crate::platform::host_bridge::{self, HostMessage, MAX_CLIPBOARD_LEN};
+use crate::services::klogd::{klog_info, klog_warning};
+use crate::Spinlock;
+
+/// Where dropped files are saved
+pub const DROP_DIR: &str = "/home";
+
+/// How often the daemon looks for messages from the page
+const TICK_INTERVAL_MS: i64 = 100;
+
+/// Messages handled per tick, so a burst of drops doesn't hog hart 0
+const MESSAGES_PER_TICK: usize = 4;
+
+/// Longest path SFS stores
+const MAX_PATH_LEN: usize = 63;
+
+/// Events kept for the GUI before the oldest is dropped
+const MAX_PENDING_EVENTS: usize = 16;
+
+static HOSTBRIDGED_LAST_TICK: AtomicI64 = AtomicI64::new(0);
+
+/// Kernel clipboard, shared by the GUI, programs and the page
+static CLIPBOARD: Spinlock<String> = Spinlock::new(String::new());
+
+/// Clipboard text waiting to be handed to the page
+static OUTGOING: Spinlock<Option<String>> = Spinlock::new(None);
+
+/// Something the page did, for the GUI
+pub enum HostEvent {
+    /// The page pushed clipboard text
+    Clipboard(String),
+    /// A dropped file was saved
+    FileDropped { path: String, size: usize },
+}
+
+static EVENTS: Spinlock<VecDeque<HostEvent>> = Spinlock::new(VecDeque::new());
+
+/// Current clipboard text
+pub fn clipboard() -> String {
+    CLIPBOARD.lock().clone()
+}
+
+/// Replace the clipboard text and pass it on to the page (on the next tick)
+pub fn set_clipboard(text: &str) {
+    let text = &text[..floor_char_boundary(text, MAX_CLIPBOARD_LEN)];
+    *CLIPBOARD.lock() = String::from(text);
+    *OUTGOING.lock() = Some(String::from(text));
+}
+
+/// Next event for the GUI, oldest first
+pub fn next_event() -> Option<HostEvent> {
+    EVENTS.lock().pop_front()
+}
+
+fn push_event(event: HostEvent) {
+    let mut events = EVENTS.lock();
+    if events.len() >= MAX_PENDING_EVENTS {
+        events.pop_front();
+    }
+    events.push_back(event);
+}
+
+/// Largest index <= `max` on a char boundary of `text`
+fn floor_char_boundary(text: &str, max: usize) -> usize {
+    let mut end = text.len().min(max);
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    end
+}
+
+/// Path under DROP_DIR for a file the page calls `name`
+///
+/// Only the last path component is kept, characters SFS paths should not
+/// hold become '_', and an existing file is never overwritten: "name.1",
+/// "name.2", ... are tried instead.
+fn drop_path(name: &str) -> String {
+    let base = name.rsplit(['/', '\\']).next().unwrap_or("");
+    let mut clean: String = base
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    if clean.is_empty() || clean.chars().all(|c| c == '.') {
+        clean = String::from("dropped");
+    }
+    // Room for the directory, the slash and a ".NN" suffix
+    clean.truncate(MAX_PATH_LEN - DROP_DIR.len() - 4);
+
+    let path = format!("{}/{}", DROP_DIR, clean);
+    if !fs_proxy::fs_exists(&path) {
+        return path;
+    }
+    (1..100)
+        .map(|n| format!("{}.{}", path, n))
+        .find(|candidate| !fs_proxy::fs_exists(candidate))
+        .unwrap_or(path)
+}
+
+/// Save a dropped file and tell the GUI
+fn save_file(name: &str, data: &[u8]) {
+    let path = drop_path(name);
+    match fs_proxy::fs_write(&path, data) {
+        Ok(()) => {
+            klog_info("hostbridged", &format!("Saved dropped file {} ({} bytes)", path, data.len()));
+            push_event(HostEvent::FileDropped { path, size: data.len() });
+        }
+        Err(e) => klog_warning("hostbridged", &format!("Could not save dropped file {}: {}", path, e)),
+    }
+}
+
+/// hostbridged tick: hand over the clipboard and take messages from the page
+pub fn tick() {
+    let now = crate::get_time_ms();
+    let last = HOSTBRIDGED_LAST_TICK.load(Ordering::Relaxed);
+    if last != 0 && now - last < TICK_INTERVAL_MS {
+        return;
+    }
+    HOSTBRIDGED_LAST_TICK.store(now, Ordering::Relaxed);
+
+    if last == 0 {
+        if host_bridge::probe() {
+            klog_info("hostbridged", &format!("Host bridge v{} found", host_bridge::version()));
+        } else {
+            klog_info("hostbridged", "No host bridge, clipboard stays local");
+        }
+    }
+    if !host_bridge::is_available() {
+        return;
+    }
+
+    if let Some(text) = OUTGOING.lock().take() {
+        host_bridge::send_clipboard(&text);
+    }
+
+    for _ in 0..MESSAGES_PER_TICK {
+        match host_bridge::next_message() {
+            Some(HostMessage::Clipboard(text)) => {
+                *CLIPBOARD.lock() = text.clone();
+                push_event(HostEvent::Clipboard(text));
+            }
+            Some(HostMessage::File { name, data }) => save_file(&name, &data),
+            Some(HostMessage::Refused { kind, name, len }) => klog_warning(
+                "hostbridged",
+                &format!("Refused message of kind {} ({:?}, {} bytes)", kind, name, len),
+            ),
+            None => break,
+        }
+    }
+}
+
+/// hostbridged service entry point (for scheduler)
+pub fn hostbridged_service() {
+    tick();
+}
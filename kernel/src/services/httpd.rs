@@ -3,19 +3,49 @@
 //! A background service that listens on TCP port 80 and responds
 //! with HTTP content to incoming connections.
 //!
+//! When `/etc/httpd/cert.pem` and `/etc/httpd/key.pem` exist at startup it
+//! also serves the same content over HTTPS on port 443 (see `tls_server` for
+//! the supported certificates).
+//!
+//! Paths other than the built-in pages and kernel module routes are served
+//! from `/var/www`, with a content type picked from the file extension.
+//! `/etc/httpd/httpd.conf`, read at startup, can change the ports, the
+//! document root and index file, and give host names their own roots
+//! (picked by the Host header; see `parse_config`).
+//! Directories serve their `index.html`, or a generated listing. Files carry
+//! an ETag, answered with 304 on If-None-Match (there are no modification
+//! times, so If-Modified-Since is not), and single byte ranges are served
+//! with 206. Error pages come from `/etc/httpd/html/<status>.html` templates
+//! when present.
+//!
+//! Every request is logged to `/var/log/httpd.log` (time, method, path,
+//! status, bytes sent, duration), along with CGI and TLS failures. The log is
+//! moved to `httpd.log.1` when it reaches 64 KiB.
+//!
+//! `/cgi/<name>` runs the WASM module `/usr/lib/cgi/<name>.wasm` for any
+//! method (see `build_cgi_response`): the request is passed in CGI-style
+//! environment variables and the body as console input, and what the module
+//! prints is the response.
+//!
 //! This implementation uses embassy-net types and patterns for async networking,
 //! integrated with the existing smoltcp infrastructure.
 
 use alloc::format;
 use alloc::string::{String, ToString};
+use alloc::sync::Arc;
 use alloc::vec::Vec;
 use core::sync::atomic::{AtomicBool, AtomicI64, AtomicUsize, Ordering};
 
 use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
 use embassy_sync::signal::Signal;
 
+use crate::cpu::fs_proxy;
 use crate::lock::utils::BLK_DEV;
-use crate::services::klogd::klog_info;
+use crate::net::TcpSocketId;
+use crate::services::klogd::{klog_info, klog_warning};
+use crate::tls::BlockingTcpSocket;
+use crate::tls_server::{ServerIdentity, TlsServerConnection};
+use crate::Spinlock;
 
 // ═══════════════════════════════════════════════════════════════════════════════
 // Filesystem Access Helpers (Thread-Safe)
@@ -24,8 +54,8 @@ use crate::services::klogd::klog_info;
 /// Read a file from the filesystem with proper locking.
 /// 
 /// Lock Ordering (per lock.rs protocol):
-/// 1. FS_STATE (Level 4) - Read lock allows concurrent readers
-/// 2. BLK_DEV (Level 5) - Write lock serializes hardware access
+/// 1. FS_STATE (Level 4) - Read lock (the filesystem locks its own cache)
+/// 2. BLK_DEV (Level 5) - Read lock (the driver serializes hardware access)
 /// 
 /// This ordering prevents deadlocks with other services (klogd, sysmond).
 fn read_from_fs(path: &str) -> Option<Vec<u8>> {
@@ -33,18 +63,19 @@ fn read_from_fs(path: &str) -> Option<Vec<u8>> {
     crate::uart::write_line(path);
     
     // 1. Acquire Read Lock on FS_STATE (Level 4)
+    // The block cache has its own lock, so other harts keep going
     let fs_guard = crate::FS_STATE.read();
     crate::uart::write_line("[httpd] FS_STATE lock acquired");
     
     if let Some(ref fs) = *fs_guard {
         crate::uart::write_line("[httpd] FS is Some, acquiring BLK_DEV...");
         
-        // 2. Acquire Write Lock on BLK_DEV (Level 5)
-        // VirtioBlock requires &mut self for all operations
-        let mut blk_guard = BLK_DEV.write();
+        // 2. Acquire Read Lock on BLK_DEV (Level 5)
+        // Transfers are serialized by the driver itself
+        let blk_guard = BLK_DEV.read();
         crate::uart::write_line("[httpd] BLK_DEV lock acquired");
         
-        if let Some(ref mut dev) = *blk_guard {
+        if let Some(ref dev) = *blk_guard {
             crate::uart::write_line("[httpd] DEV is Some, calling read_file...");
             
             // 3. Perform Read Operation
@@ -74,14 +105,82 @@ fn read_from_fs(path: &str) -> Option<Vec<u8>> {
 /// HTTP daemon listen port (default HTTP port)
 pub const HTTPD_PORT: u16 = 80;
 
+/// HTTPS listen port
+pub const HTTPS_PORT: u16 = 443;
+
+/// Files are served from here (URL path /a/b is /var/www/a/b)
+pub const DOCUMENT_ROOT: &str = "/var/www";
+
+/// Served for a directory URL when present
+const INDEX_FILE: &str = "index.html";
+
+/// Overrides for the defaults above, read by `init`
+pub const HTTPD_CONF_PATH: &str = "/etc/httpd/httpd.conf";
+
+/// Most `host` lines in httpd.conf
+const MAX_VIRTUAL_HOSTS: usize = 16;
+
+/// Error page templates are /etc/httpd/html/<status>.html
+const ERROR_TEMPLATE_DIR: &str = "/etc/httpd/html";
+
+/// Access and error log, and where it goes once it reaches MAX_LOG_SIZE
+pub const ACCESS_LOG_PATH: &str = "/var/log/httpd.log";
+const ROTATED_LOG_PATH: &str = "/var/log/httpd.log.1";
+const MAX_LOG_SIZE: usize = 64 * 1024;
+
+/// URLs under /cgi/<name> run the WASM module /usr/lib/cgi/<name>.wasm
+const CGI_URL_PREFIX: &str = "/cgi/";
+pub const CGI_DIR: &str = "/usr/lib/cgi";
+
 /// Maximum request buffer size (4KB)
 const MAX_REQUEST_SIZE: usize = 4096;
 
+/// Time a plaintext response may go without progress before it is cut off
+const SEND_IDLE_TIMEOUT_MS: i64 = 2000;
+
+/// Time allowed per read/write on an HTTPS connection. The handshake does
+/// real public-key work, so this is far above the plaintext budget.
+const TLS_TIMEOUT_MS: i64 = 3000;
+
 /// Daemon state
 static HTTPD_INITIALIZED: AtomicBool = AtomicBool::new(false);
 static HTTPD_LAST_RUN: AtomicI64 = AtomicI64::new(0);
 static HTTPD_REQUESTS_SERVED: AtomicUsize = AtomicUsize::new(0);
 
+/// Settings from /etc/httpd/httpd.conf
+#[derive(Clone)]
+struct HttpdConfig {
+    port: u16,
+    https_port: u16,
+    document_root: String,
+    index_file: String,
+    /// (host name, document root), picked by the request's Host header
+    virtual_hosts: Vec<(String, String)>,
+}
+
+impl HttpdConfig {
+    fn defaults() -> Self {
+        Self {
+            port: HTTPD_PORT,
+            https_port: HTTPS_PORT,
+            document_root: String::from(DOCUMENT_ROOT),
+            index_file: String::from(INDEX_FILE),
+            virtual_hosts: Vec::new(),
+        }
+    }
+}
+
+/// None until `init` has read the configuration
+static CONFIG: Spinlock<Option<HttpdConfig>> = Spinlock::new(None);
+
+/// The configuration in effect
+fn config() -> HttpdConfig {
+    CONFIG.lock().clone().unwrap_or_else(HttpdConfig::defaults)
+}
+
+/// Certificate and key for HTTPS (None: HTTPS is off)
+static HTTPS_IDENTITY: Spinlock<Option<Arc<ServerIdentity>>> = Spinlock::new(None);
+
 /// Signal to notify the executor to poll (for future fully async implementation)
 #[allow(dead_code)]
 static POLL_SIGNAL: Signal<CriticalSectionRawMutex, ()> = Signal::new();
@@ -102,13 +201,57 @@ fn build_http_response(request: &[u8]) -> Vec<u8> {
     
     klog_info("httpd", &format!("{} {}", method, path));
     
-    match (method, path) {
-        ("GET", "/") | ("GET", "/index.html") => build_index_response(),
+    // HEAD gets the headers GET would, without the body
+    let head = method == "HEAD";
+    let method = if head { "GET" } else { method };
+    
+    // A virtual host's own files replace the built-in front page
+    let config = config();
+    let root = document_root(&config, request);
+    let default_host = root == config.document_root;
+    
+    let response = match (method, path) {
+        ("GET", "/") | ("GET", "/index.html") if default_host => build_index_response(),
         ("GET", "/status") => build_status_response(),
         ("GET", "/api/status") => build_json_response(),
         ("GET", "/favicon.ico") => build_simple_response(204, "No Content", "image/x-icon", b""),
-        ("HEAD", _) => build_simple_response(200, "OK", "text/html", b""),
-        _ => build_404_response(path),
+        _ if path.starts_with(CGI_URL_PREFIX) => {
+            build_cgi_response(if head { "HEAD" } else { method }, path, request)
+        }
+        _ => match crate::kmod::handle_http(path, request) {
+            Some((status, body)) => {
+                build_simple_response(status, status_reason(status), "application/json", &body)
+            }
+            None if method == "GET" => build_file_response(path, request, root, &config.index_file),
+            None => build_error_response(405, path, "method not allowed"),
+        },
+    };
+    
+    if head {
+        let header_end = response.windows(4).position(|w| w == b"\r\n\r\n").map_or(response.len(), |p| p + 4);
+        return Vec::from(&response[..header_end]);
+    }
+    response
+}
+
+/// Reason phrase for status codes returned by kernel module routes
+fn status_reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        204 => "No Content",
+        206 => "Partial Content",
+        301 => "Moved Permanently",
+        302 => "Found",
+        304 => "Not Modified",
+        400 => "Bad Request",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        416 => "Range Not Satisfiable",
+        500 => "Internal Server Error",
+        503 => "Service Unavailable",
+        _ => "Unknown",
     }
 }
 
@@ -185,28 +328,510 @@ fn build_json_response() -> Vec<u8> {
     let num_harts = crate::HARTS_ONLINE.load(Ordering::Relaxed);
     let requests = HTTPD_REQUESTS_SERVED.load(Ordering::Relaxed);
     let version = env!("CARGO_PKG_VERSION");
+    let config = config();
+    let https_port = if HTTPS_IDENTITY.lock().is_some() { config.https_port.to_string() } else { String::from("null") };
     
     let body = format!(
-        r#"{{"status":"ok","uptime_ms":{},"cpu_cores":{},"requests_served":{},"http_port":{},"version":"{}","runtime":"embassy-net"}}"#,
-        uptime_ms, num_harts, requests, HTTPD_PORT, version
+        r#"{{"status":"ok","uptime_ms":{},"cpu_cores":{},"requests_served":{},"http_port":{},"https_port":{},"version":"{}","runtime":"embassy-net"}}"#,
+        uptime_ms, num_harts, requests, config.port, https_port, version
     );
     
     build_simple_response(200, "OK", "application/json", body.as_bytes())
 }
 
-/// Build 404 response from filesystem template
-fn build_404_response(path: &str) -> Vec<u8> {
-    // Try to read template from filesystem, fallback to minimal error page
-    let template = read_from_fs("/etc/httpd/html/404.html")
+/// Build an error page from /etc/httpd/html/<status>.html, or a minimal
+/// built-in one. Templates may use {{STATUS}}, {{REASON}}, {{PATH}} and
+/// {{ERROR}} (what went wrong).
+fn build_error_response(status: u16, path: &str, error: &str) -> Vec<u8> {
+    let reason = status_reason(status);
+    let template = fs_proxy::fs_read(&format!("{}/{}.html", ERROR_TEMPLATE_DIR, status))
         .and_then(|bytes| String::from_utf8(bytes).ok())
         .unwrap_or_else(|| String::from(
-            "<html><body><h1>404 Not Found</h1><p>Path: {{PATH}}</p></body></html>"
+            "<html><body><h1>{{STATUS}} {{REASON}}</h1><p>Path: {{PATH}}</p><p>{{ERROR}}</p></body></html>"
         ));
     
     // Perform template substitution
-    let body = template.replace("{{PATH}}", path);
+    let body = template
+        .replace("{{STATUS}}", &status.to_string())
+        .replace("{{REASON}}", reason)
+        .replace("{{PATH}}", &html_escape(path))
+        .replace("{{ERROR}}", &html_escape(error));
     
-    build_simple_response(404, "Not Found", "text/html; charset=utf-8", body.as_bytes())
+    build_simple_response(status, reason, "text/html; charset=utf-8", body.as_bytes())
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Static Files
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Content types by file extension; anything else is application/octet-stream
+const MIME_TYPES: &[(&str, &str)] = &[
+    ("html", "text/html; charset=utf-8"),
+    ("htm", "text/html; charset=utf-8"),
+    ("css", "text/css; charset=utf-8"),
+    ("js", "text/javascript; charset=utf-8"),
+    ("mjs", "text/javascript; charset=utf-8"),
+    ("json", "application/json"),
+    ("txt", "text/plain; charset=utf-8"),
+    ("md", "text/markdown; charset=utf-8"),
+    ("csv", "text/csv; charset=utf-8"),
+    ("log", "text/plain; charset=utf-8"),
+    ("conf", "text/plain; charset=utf-8"),
+    ("xml", "application/xml"),
+    ("svg", "image/svg+xml"),
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("webp", "image/webp"),
+    ("bmp", "image/bmp"),
+    ("ico", "image/x-icon"),
+    ("wasm", "application/wasm"),
+    ("pdf", "application/pdf"),
+    ("zip", "application/zip"),
+    ("gz", "application/gzip"),
+    ("tar", "application/x-tar"),
+    ("woff", "font/woff"),
+    ("woff2", "font/woff2"),
+    ("ttf", "font/ttf"),
+    ("mp3", "audio/mpeg"),
+    ("wav", "audio/wav"),
+    ("ogg", "audio/ogg"),
+    ("mp4", "video/mp4"),
+    ("webm", "video/webm"),
+];
+
+/// Content type for a file, from its extension
+fn mime_type(path: &str) -> &'static str {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    let ext = name.rsplit_once('.').map_or("", |(_, ext)| ext);
+    MIME_TYPES
+        .iter()
+        .find(|(known, _)| known.eq_ignore_ascii_case(ext))
+        .map_or("application/octet-stream", |(_, mime)| mime)
+}
+
+/// Decode %XX escapes; None if one is malformed or the result isn't UTF-8
+fn percent_decode(s: &str) -> Option<String> {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = core::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+            out.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(out).ok()
+}
+
+/// Escape text for an HTML page
+fn html_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Escape a file name for use in a URL path
+fn url_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for &b in s.as_bytes() {
+        if b.is_ascii_alphanumeric() || b"-._~".contains(&b) {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{:02X}", b));
+        }
+    }
+    out
+}
+
+/// An entry of a directory listing
+struct DirEntry {
+    name: String,
+    is_dir: bool,
+    size: u64,
+}
+
+/// What `dir` holds, directories first, then by name. Flat filesystems
+/// list every file by its full path, so entries are matched by prefix and
+/// deeper files show up as their first directory.
+fn list_directory(dir: &str) -> Vec<DirEntry> {
+    let prefix = format!("{}/", dir.trim_end_matches('/'));
+    let mut entries: Vec<DirEntry> = Vec::new();
+    for info in fs_proxy::fs_list(dir) {
+        let full = if info.name.starts_with('/') { info.name.clone() } else { format!("{}{}", prefix, info.name) };
+        let Some(rest) = full.strip_prefix(&prefix) else {
+            continue;
+        };
+        let (name, is_dir) = match rest.split_once('/') {
+            Some((first, _)) => (first, true),
+            None => (rest, info.is_dir),
+        };
+        if name.is_empty() || entries.iter().any(|e| e.name == name) {
+            continue;
+        }
+        entries.push(DirEntry { name: String::from(name), is_dir, size: info.size });
+    }
+    entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
+    entries
+}
+
+/// Serve a file or directory under `root`, a directory's `index_file` in
+/// place of its listing
+fn build_file_response(url: &str, request: &[u8], root: &str, index_file: &str) -> Vec<u8> {
+    let path = url.split(['?', '#']).next().unwrap_or("/");
+    let Some(decoded) = percent_decode(path) else {
+        return build_error_response(400, url, "malformed path");
+    };
+    let mut segments = Vec::new();
+    for segment in decoded.split('/').filter(|s| !s.is_empty() && *s != ".") {
+        if segment == ".." {
+            return build_error_response(403, url, "path leaves the document root");
+        }
+        segments.push(segment);
+    }
+    let fs_path = if segments.is_empty() {
+        String::from(root)
+    } else {
+        format!("{}/{}", root, segments.join("/"))
+    };
+    
+    if let Some(body) = fs_proxy::fs_read(&fs_path) {
+        return build_content_response(request, mime_type(&fs_path), &body);
+    }
+    
+    let entries = list_directory(&fs_path);
+    if entries.is_empty() {
+        return build_error_response(404, path, "not found");
+    }
+    // Relative links in the listing need the trailing slash
+    if !path.ends_with('/') {
+        return build_redirect_response(&format!("{}/", path));
+    }
+    let index_path = format!("{}/{}", fs_path, index_file);
+    if let Some(body) = fs_proxy::fs_read(&index_path) {
+        return build_content_response(request, mime_type(&index_path), &body);
+    }
+    build_listing_response(&decoded, &entries)
+}
+
+/// Value of a request header (the first, if repeated)
+fn header_value<'a>(request: &'a [u8], name: &str) -> Option<&'a str> {
+    let header_end = request.windows(4).position(|w| w == b"\r\n\r\n").unwrap_or(request.len());
+    core::str::from_utf8(&request[..header_end])
+        .ok()?
+        .lines()
+        .skip(1)
+        .filter_map(|line| line.split_once(':'))
+        .find(|(header, _)| header.trim().eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.trim())
+}
+
+/// Strong entity tag for a file's contents (the filesystem keeps no
+/// modification times, so there is no Last-Modified to go with it)
+fn entity_tag(body: &[u8]) -> String {
+    // FNV-1a
+    let hash = body.iter().fold(0xcbf2_9ce4_8422_2325u64, |h, &b| (h ^ b as u64).wrapping_mul(0x0100_0000_01b3));
+    format!("\"{:x}-{:016x}\"", body.len(), hash)
+}
+
+/// What a Range header asks of a body
+enum ByteRange {
+    /// No range, or one we don't serve (several ranges, other units)
+    Whole,
+    /// Bytes start..=end
+    Part(usize, usize),
+    Unsatisfiable,
+}
+
+/// Parse `bytes=a-b`, `bytes=a-` or `bytes=-n` against a body of `len` bytes
+fn parse_range(range: &str, len: usize) -> ByteRange {
+    let Some(spec) = range.strip_prefix("bytes=") else {
+        return ByteRange::Whole;
+    };
+    let Some((first, last)) = spec.trim().split_once('-') else {
+        return ByteRange::Whole;
+    };
+    if spec.contains(',') {
+        return ByteRange::Whole;
+    }
+    let (first, last) = (first.trim(), last.trim());
+    let (start, end) = match (first.parse::<usize>(), last.parse::<usize>()) {
+        // Suffix: the last n bytes
+        (Err(_), Ok(n)) if first.is_empty() => {
+            if n == 0 {
+                return ByteRange::Unsatisfiable;
+            }
+            (len.saturating_sub(n), len.saturating_sub(1))
+        }
+        (Ok(start), Err(_)) if last.is_empty() => (start, len.saturating_sub(1)),
+        (Ok(start), Ok(end)) if start <= end => (start, end.min(len.saturating_sub(1))),
+        _ => return ByteRange::Whole,
+    };
+    if start >= len {
+        return ByteRange::Unsatisfiable;
+    }
+    ByteRange::Part(start, end)
+}
+
+/// Serve a file's contents, answering 304 when the client's copy (by
+/// If-None-Match) is current and 206 for a Range. Clients revalidate on every
+/// use (Cache-Control: no-cache), which costs a request but no transfer.
+fn build_content_response(request: &[u8], content_type: &str, body: &[u8]) -> Vec<u8> {
+    let etag = entity_tag(body);
+    let validators = format!("ETag: {}\r\nCache-Control: no-cache\r\nAccept-Ranges: bytes\r\n", etag);
+    
+    let client_has_it = header_value(request, "If-None-Match").is_some_and(|tags| {
+        tags == "*" || tags.split(',').any(|tag| tag.trim().trim_start_matches("W/") == etag)
+    });
+    if client_has_it {
+        // No body, and no Content-Length: it would have to be the full size
+        return format!(
+            "HTTP/1.1 304 Not Modified\r\n\
+             {}\
+             Server: BAVY-OS/0.1 httpd (embassy-net)\r\n\
+             Connection: close\r\n\
+             \r\n",
+            validators
+        )
+        .into_bytes();
+    }
+    
+    // If-Range: only the range of the version the client already has
+    let range = match header_value(request, "Range") {
+        Some(range) if header_value(request, "If-Range").is_none_or(|tag| tag == etag) => parse_range(range, body.len()),
+        _ => ByteRange::Whole,
+    };
+    let mut response = match range {
+        ByteRange::Whole => build_simple_response(200, "OK", content_type, body),
+        ByteRange::Part(start, end) => {
+            let mut response = build_simple_response(206, status_reason(206), content_type, &body[start..=end]);
+            insert_headers(&mut response, &format!("Content-Range: bytes {}-{}/{}\r\n", start, end, body.len()));
+            response
+        }
+        ByteRange::Unsatisfiable => {
+            let mut response = build_simple_response(416, status_reason(416), "text/plain; charset=utf-8", b"");
+            insert_headers(&mut response, &format!("Content-Range: bytes */{}\r\n", body.len()));
+            response
+        }
+    };
+    insert_headers(&mut response, &validators);
+    response
+}
+
+/// 301 to `location`
+fn build_redirect_response(location: &str) -> Vec<u8> {
+    let body = format!("<html><body><a href=\"{0}\">{0}</a></body></html>", html_escape(location));
+    let mut response = build_simple_response(301, status_reason(301), "text/html; charset=utf-8", body.as_bytes());
+    insert_headers(&mut response, &format!("Location: {}\r\n", location));
+    response
+}
+
+/// Slot extra header lines (each ending in CRLF) in after the status line
+fn insert_headers(response: &mut Vec<u8>, headers: &str) {
+    let status_line_end = response.windows(2).position(|w| w == b"\r\n").map_or(0, |p| p + 2);
+    response.splice(status_line_end..status_line_end, headers.bytes());
+}
+
+/// Generated index page for a directory without index.html
+fn build_listing_response(url_path: &str, entries: &[DirEntry]) -> Vec<u8> {
+    let title = html_escape(url_path);
+    let mut body = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Index of {0}</title>\n\
+         <style>body{{font-family:system-ui,sans-serif;background:#0f0f1a;color:#e0e0e0;margin:2rem}}\
+         a{{color:#00d9ff;text-decoration:none}}a:hover{{text-decoration:underline}}\
+         td{{padding:0.2rem 1.5rem 0.2rem 0}}.size{{text-align:right;color:#a0a0a0}}</style>\n\
+         </head>\n<body>\n<h1>Index of {0}</h1>\n<table>\n",
+        title
+    );
+    if url_path != "/" {
+        body.push_str("<tr><td><a href=\"../\">../</a></td><td></td></tr>\n");
+    }
+    for entry in entries {
+        let slash = if entry.is_dir { "/" } else { "" };
+        let size = if entry.is_dir { String::from("-") } else { entry.size.to_string() };
+        body.push_str(&format!(
+            "<tr><td><a href=\"{}{}\">{}{}</a></td><td class=\"size\">{}</td></tr>\n",
+            url_escape(&entry.name), slash, html_escape(&entry.name), slash, size
+        ));
+    }
+    body.push_str("</table>\n<hr style=\"border-color:#333\">\n<p><i>BAVY OS httpd (embassy-net)</i></p>\n</body>\n</html>\n");
+    
+    build_simple_response(200, "OK", "text/html; charset=utf-8", body.as_bytes())
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// CGI
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Run the CGI module for /cgi/<name>[/path info][?query]. The module sees
+/// the request through env_get (REQUEST_METHOD, PATH_INFO, QUERY_STRING,
+/// CONTENT_TYPE, HTTP_<HEADER>, ...) and its body as console input; what it
+/// prints becomes the response.
+fn build_cgi_response(method: &str, url: &str, request: &[u8]) -> Vec<u8> {
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    let rest = &path[CGI_URL_PREFIX.len()..];
+    let (name, path_info) = rest.find('/').map_or((rest, ""), |i| rest.split_at(i));
+    if name.is_empty() || name.starts_with('.') {
+        return build_error_response(404, path, "no CGI module named");
+    }
+    let module_path = format!("{}/{}.wasm", CGI_DIR, name);
+    let Some(wasm_bytes) = fs_proxy::fs_read(&module_path) else {
+        return build_error_response(404, path, "no such CGI module");
+    };
+    
+    let header_end = request.windows(4).position(|w| w == b"\r\n\r\n").unwrap_or(request.len());
+    let headers = core::str::from_utf8(&request[..header_end]).unwrap_or("");
+    let body_start = (header_end + 4).min(request.len());
+    let body_len = content_length(&request[..header_end]).min(request.len() - body_start);
+    let body = Vec::from(&request[body_start..body_start + body_len]);
+    
+    let mut env: Vec<(String, String)> = [
+        ("GATEWAY_INTERFACE", "CGI/1.1"),
+        ("SERVER_SOFTWARE", "BAVY-OS/0.1 httpd"),
+        ("SERVER_PROTOCOL", "HTTP/1.1"),
+        ("REQUEST_METHOD", method),
+        ("REQUEST_URI", url),
+        ("SCRIPT_NAME", &path[..CGI_URL_PREFIX.len() + name.len()]),
+        ("QUERY_STRING", query),
+    ]
+    .iter()
+    .map(|&(key, value)| (String::from(key), String::from(value)))
+    .collect();
+    env.push((String::from("PATH_INFO"), percent_decode(path_info).unwrap_or_else(|| String::from(path_info))));
+    env.push((String::from("CONTENT_LENGTH"), body.len().to_string()));
+    for (header, value) in headers.lines().skip(1).filter_map(|line| line.split_once(':')) {
+        let key = if header.trim().eq_ignore_ascii_case("content-type") {
+            String::from("CONTENT_TYPE")
+        } else if header.trim().eq_ignore_ascii_case("content-length") {
+            continue;
+        } else {
+            format!("HTTP_{}", header.trim().to_ascii_uppercase().replace('-', "_"))
+        };
+        env.push((key, String::from(value.trim())));
+    }
+    
+    match crate::wasm::execute_cgi(&wasm_bytes, &[], env, body) {
+        Ok(output) => build_cgi_output_response(&output),
+        Err(e) => {
+            klog_warning("httpd", &format!("{}: {}", module_path, e));
+            log_error(&format!("{}: {}", module_path, e));
+            build_error_response(500, path, &e)
+        }
+    }
+}
+
+/// Turn what a CGI module printed into a response. Output may start with
+/// header lines (`Status: 404 Not Found`, `Content-Type: ...`, `Location:`,
+/// or anything to pass on) and a blank line; without them it is all body,
+/// served as HTML.
+fn build_cgi_output_response(output: &[u8]) -> Vec<u8> {
+    let split = [&b"\r\n\r\n"[..], &b"\n\n"[..]]
+        .iter()
+        .filter_map(|sep| output.windows(sep.len()).position(|w| w == *sep).map(|p| (p, p + sep.len())))
+        .min();
+    let header_lines: Option<Vec<(&str, &str)>> = split.and_then(|(end, _)| {
+        core::str::from_utf8(&output[..end])
+            .ok()?
+            .lines()
+            .map(|line| {
+                let (name, value) = line.split_once(':')?;
+                let token = !name.is_empty() && name.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-');
+                token.then(|| (name, value.trim()))
+            })
+            .collect()
+    });
+    let (Some(header_lines), Some((_, body_start))) = (header_lines, split) else {
+        return build_simple_response(200, "OK", "text/html; charset=utf-8", output);
+    };
+    
+    let mut status = 200;
+    let mut reason = String::from(status_reason(200));
+    let mut content_type = "text/html; charset=utf-8";
+    let mut extra = String::new();
+    for (name, value) in header_lines {
+        if name.eq_ignore_ascii_case("status") {
+            let (code, text) = value.split_once(' ').unwrap_or((value, ""));
+            status = code.parse().unwrap_or(500);
+            reason = String::from(if text.is_empty() { status_reason(status) } else { text });
+        } else if name.eq_ignore_ascii_case("content-type") {
+            content_type = value;
+        } else if !name.eq_ignore_ascii_case("content-length") && !name.eq_ignore_ascii_case("connection") {
+            if name.eq_ignore_ascii_case("location") && status == 200 {
+                status = 302;
+                reason = String::from(status_reason(302));
+            }
+            extra.push_str(&format!("{}: {}\r\n", name, value));
+        }
+    }
+    
+    let mut response = build_simple_response(status, &reason, content_type, &output[body_start..]);
+    insert_headers(&mut response, &extra);
+    response
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Logging
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Host date and time, or the uptime when there is no RTC
+fn log_timestamp() -> String {
+    match crate::device::rtc::get_datetime() {
+        Some(dt) => format!(
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+            dt.year, dt.month, dt.day, dt.hour, dt.minute, dt.second
+        ),
+        None => {
+            let ms = crate::get_time_ms();
+            format!("+{}.{:03}s", ms / 1000, ms % 1000)
+        }
+    }
+}
+
+/// Append a line to the log, moving it aside to httpd.log.1 once it is full
+fn append_log(line: &str) {
+    let Ok(size) = fs_proxy::fs_append(ACCESS_LOG_PATH, line.as_bytes()) else {
+        return;
+    };
+    if size >= MAX_LOG_SIZE {
+        let _ = fs_proxy::fs_remove(ROTATED_LOG_PATH);
+        let _ = fs_proxy::fs_rename(ACCESS_LOG_PATH, ROTATED_LOG_PATH);
+    }
+}
+
+/// `<time> <method> <path> <status> <bytes sent> <duration>ms`
+fn log_access(request: &[u8], response: &[u8], sent: usize, started: i64) {
+    let request_line = core::str::from_utf8(request).unwrap_or("").lines().next().unwrap_or("");
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or("-");
+    let path = parts.next().unwrap_or("-");
+    let status = response
+        .split(|&b| b == b' ')
+        .nth(1)
+        .and_then(|code| core::str::from_utf8(code).ok())
+        .unwrap_or("-");
+    append_log(&format!(
+        "{} {} {} {} {} {}ms\n",
+        log_timestamp(), method, path, status, sent, crate::get_time_ms() - started
+    ));
+}
+
+/// `<time> error: <message>`
+fn log_error(message: &str) {
+    append_log(&format!("{} error: {}\n", log_timestamp(), message));
 }
 
 // ═══════════════════════════════════════════════════════════════════════════════
@@ -229,10 +854,10 @@ pub fn init() -> Result<(), &'static str> {
     
     // Verify httpd templates exist in filesystem
     {
-        let mut fs_guard = crate::FS_STATE.write();
-        let mut blk_guard = BLK_DEV.write();
+        let fs_guard = crate::FS_STATE.read();
+        let blk_guard = BLK_DEV.read();
         
-        if let (Some(ref mut fs), Some(ref mut dev)) = (fs_guard.as_mut(), blk_guard.as_mut()) {
+        if let (Some(fs), Some(dev)) = (fs_guard.as_ref(), blk_guard.as_ref()) {
             let files = fs.list_dir(dev, "/");
             let httpd_files: usize = files.iter().filter(|f| f.name.contains("httpd")).count();
             if httpd_files > 0 {
@@ -243,12 +868,116 @@ pub fn init() -> Result<(), &'static str> {
         }
     }
     
+    load_config();
+    load_https_identity();
+    
     HTTPD_INITIALIZED.store(true, Ordering::Release);
-    klog_info("httpd", &format!("HTTP server initialized on port {}", HTTPD_PORT));
+    klog_info("httpd", &format!("HTTP server initialized on port {}", config().port));
     
     Ok(())
 }
 
+/// Read /etc/httpd/httpd.conf; without it the defaults apply
+fn load_config() {
+    let config = match read_from_fs(HTTPD_CONF_PATH) {
+        Some(text) => parse_config(core::str::from_utf8(&text).unwrap_or("")),
+        None => HttpdConfig::defaults(),
+    };
+    for (host, root) in &config.virtual_hosts {
+        klog_info("httpd", &format!("Virtual host {} served from {}", host, root));
+    }
+    *CONFIG.lock() = Some(config);
+}
+
+/// Parse httpd.conf: one `<directive> <value...>` per line, `#` comments.
+/// Bad lines are reported and skipped.
+///
+/// ```text
+/// listen 8080              # HTTP port
+/// listen_tls 8443          # HTTPS port (with cert.pem/key.pem)
+/// root /var/www            # document root for any other host name
+/// index index.htm          # file served for a directory URL
+/// host blog.local /var/blog
+/// ```
+fn parse_config(text: &str) -> HttpdConfig {
+    let mut config = HttpdConfig::defaults();
+    for line in text.lines() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let ok = match fields.as_slice() {
+            ["listen", port] => match port.parse::<u16>() {
+                Ok(port) if port != 0 => {
+                    config.port = port;
+                    true
+                }
+                _ => false,
+            },
+            ["listen_tls", port] => match port.parse::<u16>() {
+                Ok(port) if port != 0 => {
+                    config.https_port = port;
+                    true
+                }
+                _ => false,
+            },
+            ["root", dir] if dir.starts_with('/') => {
+                config.document_root = String::from(dir.trim_end_matches('/'));
+                true
+            }
+            ["index", file] if !file.contains('/') => {
+                config.index_file = String::from(*file);
+                true
+            }
+            ["host", name, dir] if dir.starts_with('/') && config.virtual_hosts.len() < MAX_VIRTUAL_HOSTS => {
+                config.virtual_hosts.push((name.to_ascii_lowercase(), String::from(dir.trim_end_matches('/'))));
+                true
+            }
+            _ => false,
+        };
+        if !ok {
+            klog_warning("httpd", &format!("{}: ignoring '{}'", HTTPD_CONF_PATH, line));
+        }
+    }
+    if config.port == config.https_port {
+        klog_warning("httpd", &format!("{}: HTTP and HTTPS both on port {}, HTTPS off", HTTPD_CONF_PATH, config.port));
+    }
+    config
+}
+
+/// Document root for a request: its Host's, or the default one
+fn document_root<'a>(config: &'a HttpdConfig, request: &[u8]) -> &'a str {
+    let host = header_value(request, "Host").unwrap_or("");
+    // Drop the port, but not from a bracketed IPv6 literal
+    let host = match host.rsplit_once(':') {
+        Some((name, port)) if !port.contains(']') => name,
+        _ => host,
+    };
+    config
+        .virtual_hosts
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(host))
+        .map_or(&config.document_root, |(_, root)| root)
+}
+
+/// Load the HTTPS certificate and key from /etc/httpd/, if present
+fn load_https_identity() {
+    let cert = read_from_fs(crate::tls_server::CERT_PATH);
+    let key = read_from_fs(crate::tls_server::KEY_PATH);
+    let (Some(cert), Some(key)) = (cert, key) else {
+        klog_info("httpd", "HTTPS off: no cert.pem/key.pem in /etc/httpd/");
+        return;
+    };
+    match ServerIdentity::from_pem(&cert, &key) {
+        Ok(identity) => {
+            *HTTPS_IDENTITY.lock() = Some(Arc::new(identity));
+            klog_info("httpd", &format!("HTTPS enabled on port {}", config().https_port));
+        }
+        Err(e) => klog_info("httpd", &format!("HTTPS off: {}", e)),
+    }
+}
+
 /// Check if httpd is initialized and running
 pub fn is_running() -> bool {
     HTTPD_INITIALIZED.load(Ordering::Acquire)
@@ -282,56 +1011,107 @@ pub fn tick() {
     tick_impl(now);
 }
 
-/// Static listen socket (shared between VirtIO and D1 implementations)
-static mut LISTEN_SOCKET: Option<crate::net::TcpSocketId> = None;
+/// Listening sockets for HTTP and HTTPS
+static HTTP_LISTENER: Spinlock<Option<TcpSocketId>> = Spinlock::new(None);
+static HTTPS_LISTENER: Spinlock<Option<TcpSocketId>> = Spinlock::new(None);
 
 /// Network tick implementation
+///
+/// Requests are read and responses sent with `NET_STATE` held, but the
+/// response is built without it: CGI modules may use the network themselves.
 fn tick_impl(now: i64) {
-    let mut net = match crate::NET_STATE.try_lock() {
-        Some(guard) => guard,
-        None => return,
-    };
-    
-    let net = match net.as_mut() {
-        Some(n) => n,
-        None => return,
+    let (http_conn, https_conn, identity) = {
+        let mut net = match crate::NET_STATE.try_lock() {
+            Some(guard) => guard,
+            None => return,
+        };
+        
+        let net = match net.as_mut() {
+            Some(n) => n,
+            None => return,
+        };
+        
+        net.poll(now);
+        
+        let (port, https_port) = CONFIG.lock().as_ref().map_or((HTTPD_PORT, HTTPS_PORT), |c| (c.port, c.https_port));
+        let http_conn = accept_on(net, &mut HTTP_LISTENER.lock(), port)
+            .map(|conn_id| (conn_id, receive_request(net, conn_id, now)));
+        
+        let identity = HTTPS_IDENTITY.lock().clone();
+        let https_conn = match identity {
+            Some(_) if https_port != port => accept_on(net, &mut HTTPS_LISTENER.lock(), https_port),
+            _ => None,
+        };
+        
+        net.poll(now);
+        (http_conn, https_conn, identity)
     };
     
-    net.poll(now);
-    
-    if unsafe { LISTEN_SOCKET.is_none() } {
-        if let Ok(sock) = net.tcp_listen(HTTPD_PORT) {
-            unsafe { LISTEN_SOCKET = Some(sock); }
-            klog_info("httpd", &format!("Listening on port {}", HTTPD_PORT));
+    if let Some((conn_id, request)) = http_conn {
+        let response = (!request.is_empty()).then(|| build_http_response(&request));
+        let sent = match crate::NET_STATE.lock().as_mut() {
+            Some(net) => send_response(net, conn_id, response.as_deref()),
+            None => 0,
+        };
+        if let Some(response) = response {
+            log_access(&request, &response, sent, now);
         }
     }
     
-    if let Some(listen_id) = unsafe { LISTEN_SOCKET } {
-        let state = net.tcp_server_state(listen_id);
-        
-        if state == "Established" {
-            klog_info("httpd", "Connection established, handling request...");
-            handle_connection(net, listen_id, now);
-            unsafe { LISTEN_SOCKET = None; }
-        } else if let Some((conn_id, remote_ip, remote_port)) = net.tcp_accept(listen_id) {
-            let o = remote_ip.octets();
-            klog_info("httpd", &format!("Connection from {}.{}.{}.{}:{}", o[0], o[1], o[2], o[3], remote_port));
-            handle_connection(net, conn_id, now);
-            unsafe { LISTEN_SOCKET = None; }
-        }
+    if let (Some(conn_id), Some(identity)) = (https_conn, identity) {
+        handle_tls_connection(conn_id, &identity);
     }
-    
-    if unsafe { LISTEN_SOCKET.is_none() } {
-        if let Ok(sock) = net.tcp_listen(HTTPD_PORT) {
-            unsafe { LISTEN_SOCKET = Some(sock); }
+}
+
+/// Take a connection waiting on `listener` (listening on `port` first if
+/// needed) and start listening again for the next one
+fn accept_on(net: &mut crate::net::NetState, listener: &mut Option<TcpSocketId>, port: u16) -> Option<TcpSocketId> {
+    if listener.is_none() {
+        if let Ok(sock) = net.tcp_listen(port) {
+            *listener = Some(sock);
+            klog_info("httpd", &format!("Listening on port {}", port));
         }
     }
+    let listen_id = (*listener)?;
     
-    net.poll(now);
+    // The listening socket itself becomes the connection
+    let conn_id = if net.tcp_state(listen_id) == "Established" {
+        klog_info("httpd", "Connection established, handling request...");
+        listen_id
+    } else {
+        let (conn_id, remote_ip, remote_port) = net.tcp_accept(listen_id)?;
+        let o = remote_ip.octets();
+        klog_info("httpd", &format!("Connection from {}.{}.{}.{}:{}", o[0], o[1], o[2], o[3], remote_port));
+        conn_id
+    };
+    
+    *listener = net.tcp_listen(port).ok();
+    Some(conn_id)
+}
+
+/// The request has its headers and all of the body they announce (or has
+/// filled the buffer)
+fn request_complete(request: &[u8]) -> bool {
+    let Some(header_end) = request.windows(4).position(|w| w == b"\r\n\r\n") else {
+        return request.len() >= MAX_REQUEST_SIZE;
+    };
+    let body_len = content_length(&request[..header_end]);
+    request.len() >= (header_end + 4 + body_len).min(MAX_REQUEST_SIZE)
+}
+
+/// Content-Length of a request (0 if absent)
+fn content_length(headers: &[u8]) -> usize {
+    core::str::from_utf8(headers)
+        .unwrap_or("")
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, value)| value.trim().parse().ok())
+        .unwrap_or(0)
 }
 
-/// Handle a connection - receive request and send response
-fn handle_connection(net: &mut crate::net::NetState, socket_id: crate::net::TcpSocketId, now: i64) {
+/// Receive a request on a new connection (empty if none came)
+fn receive_request(net: &mut crate::net::NetState, socket_id: crate::net::TcpSocketId, now: i64) -> Vec<u8> {
     let mut request_buf = [0u8; MAX_REQUEST_SIZE];
     let mut request_len = 0;
     let timeout = 100; // 100ms max - cooperative, let scheduler retry
@@ -340,13 +1120,10 @@ fn handle_connection(net: &mut crate::net::NetState, socket_id: crate::net::TcpS
     loop {
         net.poll(crate::get_time_ms());
         
-        match net.tcp_recv_on(socket_id, &mut request_buf[request_len..], crate::get_time_ms()) {
+        match net.tcp_recv(socket_id, &mut request_buf[request_len..], crate::get_time_ms()) {
             Ok(n) if n > 0 => {
                 request_len += n;
-                if request_len >= 4 {
-                    let has_end = request_buf[..request_len].windows(4).any(|w| w == b"\r\n\r\n");
-                    if has_end { break; }
-                }
+                if request_complete(&request_buf[..request_len]) { break; }
             }
             Ok(_) => {}
             Err(_) => break,
@@ -355,31 +1132,81 @@ fn handle_connection(net: &mut crate::net::NetState, socket_id: crate::net::TcpS
         if crate::get_time_ms() - start > timeout { break; }
     }
     
-    if request_len == 0 {
-        net.tcp_close_on(socket_id, crate::get_time_ms());
-        return;
-    }
+    Vec::from(&request_buf[..request_len])
+}
+
+/// Send the response to a request (None: there was no request) and close;
+/// returns the bytes the client took
+fn send_response(net: &mut crate::net::NetState, socket_id: crate::net::TcpSocketId, response: Option<&[u8]>) -> usize {
+    let Some(response) = response else {
+        net.tcp_close(socket_id, crate::get_time_ms());
+        return 0;
+    };
     
-    let response = build_http_response(&request_buf[..request_len]);
     let mut sent = 0;
-    let start = crate::get_time_ms();
+    let mut last_progress = crate::get_time_ms();
     
+    // Files can be large: keep going as long as the client keeps taking data
     while sent < response.len() {
         net.poll(crate::get_time_ms());
-        match net.tcp_send_on(socket_id, &response[sent..], crate::get_time_ms()) {
-            Ok(n) if n > 0 => sent += n,
+        match net.tcp_send(socket_id, &response[sent..], crate::get_time_ms()) {
+            Ok(n) if n > 0 => {
+                sent += n;
+                last_progress = crate::get_time_ms();
+            }
             Ok(_) => {}
             Err(_) => break,
         }
-        if crate::get_time_ms() - start > timeout { break; }
+        if crate::get_time_ms() - last_progress > SEND_IDLE_TIMEOUT_MS { break; }
     }
     
-    net.tcp_close_on(socket_id, crate::get_time_ms());
+    net.tcp_close(socket_id, crate::get_time_ms());
     net.poll(crate::get_time_ms());
     
-    net.tcp_release_server(socket_id);
+    net.tcp_release(socket_id);
     HTTPD_REQUESTS_SERVED.fetch_add(1, Ordering::Relaxed);
     klog_info("httpd", "Request completed");
+    sent
+}
+
+/// Handle an HTTPS connection - TLS handshake, then one request and response
+fn handle_tls_connection(socket_id: TcpSocketId, identity: &ServerIdentity) {
+    let started = crate::get_time_ms();
+    let socket = BlockingTcpSocket::accepted(socket_id, TLS_TIMEOUT_MS, crate::get_time_ms);
+    let mut tls = match TlsServerConnection::accept(socket, identity) {
+        Ok(tls) => tls,
+        Err(e) => {
+            klog_info("httpd", &format!("TLS handshake failed: {}", e));
+            log_error(&format!("TLS handshake failed: {}", e));
+            return;
+        }
+    };
+    
+    let mut request = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !request_complete(&request) {
+        match tls.read(&mut buf) {
+            Ok(n) if n > 0 => request.extend_from_slice(&buf[..n]),
+            _ => break,
+        }
+    }
+    
+    if !request.is_empty() {
+        let response = build_http_response(&request);
+        let sent = match tls.write(&response) {
+            Ok(()) => response.len(),
+            Err(e) => {
+                klog_info("httpd", &format!("TLS write failed: {}", e));
+                log_error(&format!("TLS write failed: {}", e));
+                0
+            }
+        };
+        log_access(&request, &response, sent, started);
+        HTTPD_REQUESTS_SERVED.fetch_add(1, Ordering::Relaxed);
+        klog_info("httpd", "HTTPS request completed");
+    }
+    // Closing hands the socket to the stack, which frees it once the FIN is through
+    tls.close();
 }
 
 /// httpd service entry point (for scheduler)
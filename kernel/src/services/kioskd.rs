@@ -0,0 +1,164 @@
+//! Kiosk daemon
+//!
+//! When kiosk mode is enabled and nobody has touched the GUI for a while,
+//! cycles the full-screen info pages (see `ui::kiosk`) on a timer. Input
+//! hands the screen back; the cycle starts over once the GUI is idle again.
+//!
+//! Configured in `/etc/kiosk.conf`, re-read when the file changes:
+//!
+//! ```text
+//! enabled = true
+//! interval = 10                   # seconds per page
+//! idle = 60                       # seconds without input before starting
+//! pages = sysinfo, network, log
+//! ```
+
+use alloc::format;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicI64, Ordering};
+
+use crate::cpu::fs_proxy;
+use crate::fs::watch::{PathWatch, WATCH_ALL};
+use crate::services::klogd::{klog_info, klog_warning};
+use crate::ui::kiosk::{self, KioskPage};
+use crate::Spinlock;
+
+/// Where the kiosk configuration lives
+pub const KIOSK_CONF_PATH: &str = "/etc/kiosk.conf";
+
+/// How often the daemon looks at the clock and the config file
+const TICK_INTERVAL_MS: i64 = 250;
+
+/// Watch on the config file for live reload
+static KIOSK_CONF_WATCH: PathWatch = PathWatch::new(KIOSK_CONF_PATH, WATCH_ALL);
+
+static KIOSK_LAST_TICK: AtomicI64 = AtomicI64::new(0);
+
+/// Kiosk settings from /etc/kiosk.conf
+struct KioskConfig {
+    enabled: bool,
+    interval_ms: i64,
+    idle_ms: i64,
+    pages: Vec<KioskPage>,
+}
+
+impl KioskConfig {
+    const fn new() -> Self {
+        Self { enabled: false, interval_ms: 10_000, idle_ms: 60_000, pages: Vec::new() }
+    }
+
+    /// Parse `key = value` lines; unknown keys and bad values are reported and skipped
+    fn parse(text: &str) -> Self {
+        let mut config = Self::new();
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let (key, value) = (key.trim(), value.trim());
+            let ok = match key {
+                "enabled" => parse_bool(value).map(|on| config.enabled = on),
+                "interval" => parse_secs(value).map(|ms| config.interval_ms = ms),
+                "idle" => parse_secs(value).map(|ms| config.idle_ms = ms),
+                "pages" => value
+                    .split(',')
+                    .map(|name| KioskPage::from_name(name.trim()))
+                    .collect::<Option<Vec<_>>>()
+                    .map(|pages| config.pages = pages),
+                _ => None,
+            }
+            .is_some();
+            if !ok {
+                klog_warning("kioskd", &format!("{}: ignoring '{}'", KIOSK_CONF_PATH, line));
+            }
+        }
+        if config.pages.is_empty() {
+            config.pages.extend(KioskPage::ALL);
+        }
+        config
+    }
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value {
+        "true" | "on" | "1" => Some(true),
+        "false" | "off" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+/// Positive whole seconds, in ms
+fn parse_secs(value: &str) -> Option<i64> {
+    value.parse::<i64>().ok().filter(|&secs| secs > 0).map(|secs| secs * 1000)
+}
+
+/// Cycle position
+struct KioskState {
+    config: KioskConfig,
+    /// Index into `config.pages` of the page on screen
+    current: Option<usize>,
+    switched_ms: i64,
+}
+
+static KIOSK: Spinlock<KioskState> = Spinlock::new(KioskState {
+    config: KioskConfig::new(),
+    current: None,
+    switched_ms: 0,
+});
+
+/// Read /etc/kiosk.conf (a missing file leaves kiosk mode off)
+fn load_config() -> KioskConfig {
+    let text = fs_proxy::fs_read(KIOSK_CONF_PATH).unwrap_or_default();
+    KioskConfig::parse(core::str::from_utf8(&text).unwrap_or(""))
+}
+
+/// kioskd tick: reload the config on change and move the cycle along
+pub fn tick() {
+    let now = crate::get_time_ms();
+    let last = KIOSK_LAST_TICK.load(Ordering::Relaxed);
+    if last != 0 && now - last < TICK_INTERVAL_MS {
+        return;
+    }
+    KIOSK_LAST_TICK.store(now, Ordering::Relaxed);
+
+    // The first `changed` starts the watch, so load on the first tick too
+    if last == 0 || KIOSK_CONF_WATCH.changed() {
+        let config = load_config();
+        klog_info(
+            "kioskd",
+            &format!(
+                "Kiosk mode {} ({} pages, {} s each, after {} s idle)",
+                if config.enabled { "enabled" } else { "disabled" },
+                config.pages.len(),
+                config.interval_ms / 1000,
+                config.idle_ms / 1000
+            ),
+        );
+        let mut state = KIOSK.lock();
+        state.config = config;
+        state.current = None;
+    }
+
+    let mut state = KIOSK.lock();
+    let idle = now - kiosk::last_input_ms() >= state.config.idle_ms;
+    if !state.config.enabled || !idle {
+        state.current = None;
+        kiosk::request(None, 0, 0);
+        return;
+    }
+
+    let count = state.config.pages.len();
+    let next = match state.current {
+        None => 0,
+        Some(i) if now - state.switched_ms >= state.config.interval_ms => (i + 1) % count,
+        Some(_) => return,
+    };
+    state.current = Some(next);
+    state.switched_ms = now;
+    kiosk::request(Some(state.config.pages[next]), next, count);
+}
+
+/// kioskd service entry point (for scheduler)
+pub fn kioskd_service() {
+    tick();
+}
@@ -170,52 +170,33 @@ pub fn flush_logs() -> usize {
     }
     
     // Write to files (need FS access)
-    let fs_guard = crate::lock::utils::FS_STATE.write();
-    let blk_guard = crate::lock::utils::BLK_DEV.write();
-    
-    if let (Some(ref mut fs), Some(ref mut dev)) = (
-        fs_guard.as_ref().map(|_| ()),
-        blk_guard.as_ref().map(|_| ()),
-    ) {
-        // Get mutable references
-        drop(fs_guard);
-        drop(blk_guard);
-        
-        let mut fs_guard = crate::lock::utils::FS_STATE.write();
-        let mut blk_guard = crate::lock::utils::BLK_DEV.write();
-        
-        if let (Some(ref mut fs), Some(ref mut dev)) = (fs_guard.as_mut(), blk_guard.as_mut()) {
-            // Append kernel log lines
-            if !kernel_lines.is_empty() {
-                let mut content = fs.read_file(dev, "/var/log/kernel.log")
-                    .map(|v| String::from_utf8_lossy(&v).into_owned())
-                    .unwrap_or_default();
-                
-                for line in kernel_lines {
-                    content.push_str(&line);
-                    content.push('\n');
-                }
-                
-                let _ = fs.write_file(dev, "/var/log/kernel.log", content.as_bytes());
+    let fs_guard = crate::lock::utils::FS_STATE.read();
+    let blk_guard = crate::lock::utils::BLK_DEV.read();
+
+    if let (Some(fs), Some(dev)) = (fs_guard.as_ref(), blk_guard.as_ref()) {
+        // Append kernel log lines (only the tail blocks are rewritten)
+        if !kernel_lines.is_empty() {
+            let mut content = String::new();
+            for line in kernel_lines {
+                content.push_str(&line);
+                content.push('\n');
             }
             
-            // Append sysmond log lines
-            if !sysmond_lines.is_empty() {
-                let mut content = fs.read_file(dev, "/var/log/sysmond.log")
-                    .map(|v| String::from_utf8_lossy(&v).into_owned())
-                    .unwrap_or_default();
-                
-                for line in sysmond_lines {
-                    content.push_str(&line);
-                    content.push('\n');
-                }
-                
-                let _ = fs.write_file(dev, "/var/log/sysmond.log", content.as_bytes());
+            let _ = fs.append(dev, "/var/log/kernel.log", content.as_bytes());
+        }
+        
+        // Append sysmond log lines
+        if !sysmond_lines.is_empty() {
+            let mut content = String::new();
+            for line in sysmond_lines {
+                content.push_str(&line);
+                content.push('\n');
             }
             
-            // Sync once at the end
-            let _ = fs.sync(dev);
+            let _ = fs.append(dev, "/var/log/sysmond.log", content.as_bytes());
         }
+
+        // Not synced here: sysmond flushes dirty blocks periodically
     }
     
     count
@@ -3,7 +3,11 @@ pub mod httpd;
 pub mod klogd;
 pub mod shelld;
 pub mod tcpd;
+pub mod telnetd;
 pub mod netd;
+pub mod portfwd;
 pub mod gpuid;
+pub mod kioskd;
 pub mod sysmond;
-pub mod gui_cmd;
\ No newline at end of file
+pub mod gui_cmd;
+pub mod hostbridged;
\ No newline at end of file
@@ -43,7 +43,8 @@ pub fn has_ip() -> bool {
 /// Multi-hart safe: Uses net_proxy to delegate MMIO operations to Hart 0.
 pub(crate) fn poll_network() {
     use crate::cpu::net_proxy;
-    
+
+    let _subsystem = crate::allocator::enter_subsystem(crate::allocator::Subsystem::Net);
     let timestamp = get_time_ms();
 
     // Poll the unified network state (proxied to Hart 0 if needed)
@@ -0,0 +1,304 @@
+//! portfwd - TCP port forwarding daemon
+//!
+//! Listens on local ports and relays each connection to a configured
+//! destination, copying bytes both ways until either side is done. Useful
+//! for exposing a service on another host through this one, and as a load
+//! test of the TCP socket table (every forwarded connection uses two).
+//!
+//! Configured in `/etc/portfwd.conf`, re-read when the file changes:
+//!
+//! ```text
+//! # listen port   destination
+//! 8080            10.0.2.2:80
+//! 2323            bbs.example.org:23
+//! ```
+//!
+//! Destinations are resolved (hosts file, cache, DNS) when a connection
+//! comes in. Reloading replaces the listeners; open connections carry on.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicI64, Ordering};
+
+use smoltcp::wire::Ipv4Address;
+
+use crate::cpu::fs_proxy;
+use crate::fs::watch::{PathWatch, WATCH_ALL};
+use crate::net::{NetState, TcpSocketId, DNS_SERVER};
+use crate::services::klogd::{klog_info, klog_warning};
+use crate::Spinlock;
+
+/// Where the forwarding rules live
+pub const PORTFWD_CONF_PATH: &str = "/etc/portfwd.conf";
+
+/// How often connections are pumped
+const TICK_INTERVAL_MS: i64 = 5;
+
+/// Most forwards and simultaneous connections (each uses two sockets)
+const MAX_FORWARDS: usize = 4;
+const MAX_SESSIONS: usize = 3;
+
+/// Bytes read from one side per tick, and most kept waiting for the other
+const CHUNK_SIZE: usize = 1024;
+const MAX_PENDING: usize = 4096;
+
+/// Time allowed for the destination to accept
+const CONNECT_TIMEOUT_MS: i64 = 5000;
+
+/// Time allowed for a name lookup
+const RESOLVE_TIMEOUT_MS: i64 = 2000;
+
+static PORTFWD_CONF_WATCH: PathWatch = PathWatch::new(PORTFWD_CONF_PATH, WATCH_ALL);
+static PORTFWD_LAST_TICK: AtomicI64 = AtomicI64::new(0);
+
+/// One line of /etc/portfwd.conf
+struct Forward {
+    listen_port: u16,
+    host: String,
+    port: u16,
+    listener: Option<TcpSocketId>,
+}
+
+/// A forwarded connection
+struct Session {
+    client: TcpSocketId,
+    upstream: TcpSocketId,
+    /// Bytes read from the client, not yet taken by the destination
+    to_upstream: Vec<u8>,
+    /// Bytes read from the destination, not yet taken by the client
+    to_client: Vec<u8>,
+    /// We closed our side towards the destination / client
+    upstream_closed: bool,
+    client_closed: bool,
+    started_ms: i64,
+    /// Bytes relayed, both ways
+    relayed: usize,
+}
+
+struct PortfwdState {
+    forwards: Vec<Forward>,
+    sessions: Vec<Session>,
+}
+
+static PORTFWD: Spinlock<PortfwdState> = Spinlock::new(PortfwdState { forwards: Vec::new(), sessions: Vec::new() });
+
+/// Parse `<listen port> <host>:<port>` lines; bad lines are reported and skipped
+fn parse_config(text: &str) -> Vec<Forward> {
+    let mut forwards: Vec<Forward> = Vec::new();
+    for line in text.lines() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        let mut fields = line.split_whitespace();
+        let forward = match (fields.next(), fields.next(), fields.next()) {
+            (Some(listen), Some(dest), None) => parse_forward(listen, dest),
+            _ => None,
+        };
+        match forward {
+            Some(f) if forwards.iter().any(|other| other.listen_port == f.listen_port) => {
+                klog_warning("portfwd", &format!("{}: port {} forwarded twice, ignoring '{}'", PORTFWD_CONF_PATH, f.listen_port, line));
+            }
+            Some(_) if forwards.len() >= MAX_FORWARDS => {
+                klog_warning("portfwd", &format!("{}: more than {} forwards, ignoring '{}'", PORTFWD_CONF_PATH, MAX_FORWARDS, line));
+            }
+            Some(f) => forwards.push(f),
+            None => klog_warning("portfwd", &format!("{}: ignoring '{}'", PORTFWD_CONF_PATH, line)),
+        }
+    }
+    forwards
+}
+
+fn parse_forward(listen: &str, dest: &str) -> Option<Forward> {
+    let listen_port = listen.parse::<u16>().ok().filter(|&p| p != 0)?;
+    let (host, port) = dest.rsplit_once(':')?;
+    let port = port.parse::<u16>().ok().filter(|&p| p != 0)?;
+    if host.is_empty() {
+        return None;
+    }
+    Some(Forward { listen_port, host: String::from(host), port, listener: None })
+}
+
+/// Read /etc/portfwd.conf and listen on its ports instead of the old ones
+fn reload(state: &mut PortfwdState, net: &mut NetState) {
+    for forward in state.forwards.drain(..) {
+        if let Some(listener) = forward.listener {
+            net.tcp_abort(listener);
+        }
+    }
+    let text = fs_proxy::fs_read(PORTFWD_CONF_PATH).unwrap_or_default();
+    state.forwards = parse_config(core::str::from_utf8(&text).unwrap_or(""));
+    for forward in state.forwards.iter_mut() {
+        match net.tcp_listen(forward.listen_port) {
+            Ok(socket) => {
+                forward.listener = Some(socket);
+                klog_info("portfwd", &format!("Forwarding port {} to {}:{}", forward.listen_port, forward.host, forward.port));
+            }
+            Err(e) => klog_warning("portfwd", &format!("Cannot listen on port {}: {}", forward.listen_port, e)),
+        }
+    }
+}
+
+/// Destination address: a literal, or resolved through hosts/cache/DNS
+fn resolve(net: &mut NetState, host: &str) -> Option<Ipv4Address> {
+    crate::net::parse_ipv4(host.as_bytes())
+        .or_else(|| crate::dns::resolve(net, host.as_bytes(), DNS_SERVER, RESOLVE_TIMEOUT_MS, crate::get_time_ms))
+}
+
+/// Take new connections and open their upstream side
+fn accept(state: &mut PortfwdState, net: &mut NetState, now: i64) {
+    for forward in state.forwards.iter_mut() {
+        // Listen again if the last attempt found no free socket
+        let listener = match forward.listener {
+            Some(listener) => listener,
+            None => match net.tcp_listen(forward.listen_port) {
+                Ok(listener) => {
+                    forward.listener = Some(listener);
+                    listener
+                }
+                Err(_) => continue,
+            },
+        };
+        if state.sessions.len() >= MAX_SESSIONS {
+            continue;
+        }
+        let Some((client, remote_ip, remote_port)) = net.tcp_accept(listener) else {
+            continue;
+        };
+        // The listening socket became the connection
+        forward.listener = net.tcp_listen(forward.listen_port).ok();
+
+        let upstream = resolve(net, &forward.host)
+            .ok_or("cannot resolve destination")
+            .and_then(|ip| net.tcp_connect(ip, forward.port, now));
+        match upstream {
+            Ok(upstream) => {
+                klog_info(
+                    "portfwd",
+                    &format!("{}:{} -> port {} -> {}:{}", remote_ip, remote_port, forward.listen_port, forward.host, forward.port),
+                );
+                state.sessions.push(Session {
+                    client,
+                    upstream,
+                    to_upstream: Vec::new(),
+                    to_client: Vec::new(),
+                    upstream_closed: false,
+                    client_closed: false,
+                    started_ms: now,
+                    relayed: 0,
+                });
+            }
+            Err(e) => {
+                klog_warning("portfwd", &format!("Cannot reach {}:{}: {}", forward.host, forward.port, e));
+                net.tcp_abort(client);
+            }
+        }
+    }
+}
+
+/// The peer has nothing more to send
+fn peer_done(tcp_state: &str) -> bool {
+    matches!(tcp_state, "CloseWait" | "LastAck" | "Closing" | "TimeWait" | "Closed" | "Invalid")
+}
+
+/// Move bytes from `from` to `to` through `pending`, returning how many were
+/// passed on and whether `from` has finished sending and all of it went
+/// through. Bytes `to` can no longer take are dropped.
+fn relay(net: &mut NetState, from: TcpSocketId, to: TcpSocketId, pending: &mut Vec<u8>, now: i64) -> (usize, bool) {
+    if pending.len() < MAX_PENDING {
+        let mut buf = [0u8; CHUNK_SIZE];
+        let room = (MAX_PENDING - pending.len()).min(CHUNK_SIZE);
+        if let Ok(n) = net.tcp_recv(from, &mut buf[..room], now) {
+            pending.extend_from_slice(&buf[..n]);
+        }
+    }
+    let mut sent = 0;
+    if !pending.is_empty() {
+        match net.tcp_send(to, pending, now) {
+            Ok(n) => {
+                pending.drain(..n);
+                sent = n;
+            }
+            // `to` is gone; nothing will ever take these
+            Err(_) => pending.clear(),
+        }
+    }
+    (sent, pending.is_empty() && peer_done(net.tcp_state(from)))
+}
+
+/// Relay a connection's traffic; true once it is finished
+fn pump(session: &mut Session, net: &mut NetState, now: i64) -> bool {
+    if matches!(net.tcp_state(session.upstream), "SynSent" | "SynReceived") {
+        if now - session.started_ms > CONNECT_TIMEOUT_MS {
+            klog_warning("portfwd", "Destination did not answer");
+            return true;
+        }
+        return false;
+    }
+
+    let (to_upstream, client_done) = relay(net, session.client, session.upstream, &mut session.to_upstream, now);
+    let (to_client, upstream_done) = relay(net, session.upstream, session.client, &mut session.to_client, now);
+    session.relayed += to_upstream + to_client;
+
+    // Pass half-closes on once everything before them was delivered
+    if client_done && !session.upstream_closed {
+        net.tcp_close(session.upstream, now);
+        session.upstream_closed = true;
+    }
+    if upstream_done && !session.client_closed {
+        net.tcp_close(session.client, now);
+        session.client_closed = true;
+    }
+    client_done && upstream_done
+}
+
+/// portfwd tick: reload the config on change, accept and relay
+pub fn tick() {
+    let now = crate::get_time_ms();
+    let last = PORTFWD_LAST_TICK.load(Ordering::Relaxed);
+    if last != 0 && now - last < TICK_INTERVAL_MS {
+        return;
+    }
+    PORTFWD_LAST_TICK.store(now, Ordering::Relaxed);
+
+    let Some(mut net_guard) = crate::NET_STATE.try_lock() else {
+        return;
+    };
+    let Some(net) = net_guard.as_mut() else {
+        return;
+    };
+    let mut state = PORTFWD.lock();
+
+    // The first `changed` starts the watch, so load on the first tick too
+    if last == 0 || PORTFWD_CONF_WATCH.changed() {
+        reload(&mut state, net);
+    }
+    if state.forwards.is_empty() && state.sessions.is_empty() {
+        return;
+    }
+
+    net.poll(now);
+    accept(&mut state, net, now);
+
+    let mut i = 0;
+    while i < state.sessions.len() {
+        if pump(&mut state.sessions[i], net, now) {
+            let session = state.sessions.swap_remove(i);
+            klog_info(
+                "portfwd",
+                &format!("Connection closed after {} bytes, {} ms", session.relayed, now - session.started_ms),
+            );
+            // Let the closes finish in the background; the slots free themselves
+            net.tcp_disconnect(session.client, now);
+            net.tcp_disconnect(session.upstream, now);
+        } else {
+            i += 1;
+        }
+    }
+}
+
+/// portfwd service entry point (for scheduler)
+pub fn portfwd_service() {
+    tick();
+}
@@ -8,12 +8,15 @@
 //! The shell is implemented as a high-priority daemon process that:
 //! - Polls UART for input (non-blocking with yield)
 //! - Processes commands when a full line is received
-//! - Yields to the scheduler between iterations
+//! - Sleeps on the console input queue when there is nothing to read
 //!
 //! This cooperative design allows other processes to run even on single-hart systems.
+//! Input waking the shell earns it the scheduler's interactivity boost, so
+//! typing stays responsive while daemons keep the harts busy.
 
 use core::sync::atomic::{AtomicUsize, Ordering};
 
+use alloc::format;
 use alloc::string::String;
 use alloc::vec::Vec;
 
@@ -21,12 +24,13 @@ use crate::PING_STATE;
 use crate::lock::utils::BLK_DEV;
 use crate::lock::utils::COMMAND_RUNNING;
 use crate::cpu::fs_proxy;
-use crate::lock::utils::TAIL_FOLLOW_STATE;
+use crate::cpu::process::{Pid, PROCESS_TABLE};
+use crate::cpu::{sched, signal};
 use crate::net;
+use crate::scripting::{out_line, set_last_status};
 use crate::services::netd;
 use crate::uart;
 use crate::Spinlock;
-use crate::utils::poll_tail_follow;
 use crate::utils::print_prompt;
 use crate::utils::resolve_path;
 
@@ -62,12 +66,6 @@ struct ShellState {
     
     /// Whether shell is initialized
     initialized: bool,
-    
-    /// Whether shell is in tail follow mode
-    tail_follow_mode: bool,
-    tail_follow_path: [u8; BUFFER_SIZE],
-    tail_follow_path_len: usize,
-    tail_follow_last_size: usize,
 }
 
 impl ShellState {
@@ -83,10 +81,6 @@ impl ShellState {
             last_newline: 0,
             esc_state: 0,
             initialized: false,
-            tail_follow_mode: false,
-            tail_follow_path: [0u8; BUFFER_SIZE],
-            tail_follow_path_len: 0,
-            tail_follow_last_size: 0,
         }
     }
 }
@@ -97,6 +91,9 @@ static SHELL_STATE: Spinlock<ShellState> = Spinlock::new(ShellState::new());
 /// Shell PID (for process tracking)
 static SHELL_PID: AtomicUsize = AtomicUsize::new(0);
 
+/// Longest the shell sleeps waiting for input before its next tick (ms)
+const INPUT_WAIT_MS: i64 = 50;
+
 /// Find common prefix among strings
 fn find_common_prefix(strings: &[alloc::string::String]) -> alloc::string::String {
     use alloc::string::String;
@@ -155,9 +152,11 @@ pub fn handle_tab_completion(buffer: &mut [u8], len: usize) -> usize {
     if is_command {
         // Complete commands - check built-ins first
         let builtins = [
-            "clear", "pwd", "ping", "nslookup", "node", "help", "ls", "cat",
+            "clear", "pwd", "ping", "ping6", "nslookup", "flushdns", "node", "help", "ls", "cat",
             "echo", "cowsay", "sysinfo", "ip", "netstat", "memstats", "uptime", "write", "wget", "cd",
-            "shutdown",
+            "shutdown", "sync", "ln", "mv", "kmod", "time", "timing", "nice", "strace", "bootchart",
+            "heapdiff", "memstat", "hibernate", "gopher", "finger", "irc", "tcpecho", "qrcode", "beep", "mount", "umount",
+            "snake", "displayctl", "settings", "fswatch", "wasmpipe", "inputrec", "jobs", "fg", "bg",
         ];
 
         for cmd in builtins.iter() {
@@ -167,18 +166,17 @@ pub fn handle_tab_completion(buffer: &mut [u8], len: usize) -> usize {
         }
 
         // Also check /usr/bin/ for scripts
-        {
-            let files = fs_proxy::fs_list("/");
-            for f in files {
-                if f.name.starts_with("/usr/bin/") {
-                    let script_name = &f.name[9..]; // Strip "/usr/bin/"
-                    if script_name.starts_with(word_to_complete) {
-                        // Avoid duplicates with builtins
-                        if !matches.iter().any(|m| m == script_name) {
-                            matches.push(String::from(script_name));
-                        }
-                    }
-                }
+        for script_name in crate::scripting::path_commands() {
+            // Avoid duplicates with builtins
+            if script_name.starts_with(word_to_complete) && !matches.contains(&script_name) {
+                matches.push(script_name);
+            }
+        }
+
+        // And commands registered by kernel modules
+        for name in crate::kmod::command_names() {
+            if name.starts_with(word_to_complete) && !matches.iter().any(|m| *m == name) {
+                matches.push(name);
             }
         }
     } else {
@@ -378,22 +376,23 @@ pub fn shell_service() {
     
     // Do one iteration of shell work
     shell_tick();
+
+    // Nothing left to read: sleep until the next keystroke
+    uart::wait_for_input(crate::get_time_ms() + INPUT_WAIT_MS);
 }
 
 /// One iteration of shell work
 ///
-/// Polls UART for input and processes any available bytes.
+/// Polls the console for input and processes any available bytes.
 /// Returns quickly to allow other processes to run.
 pub fn shell_tick() {
     
-    // Poll for input (non-blocking)
+    // Poll for input (non-blocking), from the UART or a telnet client
     // Process up to 64 bytes per tick for faster input response
     for _ in 0..64 {
-        if !uart::Console::is_rx_ready_public() {
+        let Some(byte) = uart::read_char_nonblocking() else {
             break;
-        }
-        
-        let byte = uart::Console::new().read_byte();
+        };
         if byte == 0 {
             break;
         }
@@ -401,9 +400,6 @@ pub fn shell_tick() {
         process_input_byte(byte);
     }
     
-    // Poll tail follow mode (always)
-    poll_tail_follow();
-    
     // NOTE: poll_network() removed - it acquires NET_STATE/PING_STATE locks
     // which blocks shell input when other harts hold these locks.
     // Network polling happens in netd::tick() called from hart_loop.
@@ -415,15 +411,6 @@ fn process_input_byte(byte: u8) {
     
     // Handle Ctrl+C
     if byte == 0x03 {
-        if state.tail_follow_mode {
-            state.tail_follow_mode = false;
-            drop(state);
-            TAIL_FOLLOW_STATE.lock().stop();
-            uart::write_line("");
-            uart::write_line("\x1b[2m--- tail -f stopped ---\x1b[0m");
-            print_prompt();
-            return;
-        }
         drop(state);
         if cancel_running_command() {
             print_prompt();
@@ -431,22 +418,6 @@ fn process_input_byte(byte: u8) {
         return;
     }
     
-    // In follow mode, 'q' also exits
-    if state.tail_follow_mode && (byte == b'q' || byte == b'Q') {
-        state.tail_follow_mode = false;
-        drop(state);
-        TAIL_FOLLOW_STATE.lock().stop();
-        uart::write_line("");
-        uart::write_line("\x1b[2m--- tail -f stopped ---\x1b[0m");
-        print_prompt();
-        return;
-    }
-    
-    // Ignore other input while in follow mode
-    if state.tail_follow_mode {
-        return;
-    }
-    
     // Handle escape sequences
     if state.esc_state == 1 {
         if byte == b'[' {
@@ -529,107 +500,6 @@ fn process_input_byte(byte: u8) {
 }
 
 
-/// Parse a command to see if it's a tail -f command
-/// Returns Some((filepath, num_lines)) if it's a follow command, None otherwise
-pub fn parse_tail_follow_command(cmd: &[u8]) -> Option<(String, usize)> {
-    let cmd_str = core::str::from_utf8(cmd).ok()?;
-    let cmd_str = cmd_str.trim();
-
-    // Must start with "tail"
-    if !cmd_str.starts_with("tail ") && cmd_str != "tail" {
-        return None;
-    }
-
-    // Parse arguments
-    let parts: Vec<&str> = cmd_str.split_whitespace().collect();
-    if parts.len() < 2 {
-        return None;
-    }
-
-    let mut has_follow = false;
-    let mut num_lines: usize = 10;
-    let mut filepath: Option<&str> = None;
-
-    let mut i = 1;
-    while i < parts.len() {
-        let part = parts[i];
-
-        if part == "-f" || part == "--follow" {
-            has_follow = true;
-        } else if part.starts_with("-f") && part.len() > 2 {
-            // -f is in combined flags like -fn20 or just -f alone
-            has_follow = true;
-        } else if part.starts_with("--follow=") {
-            has_follow = true;
-        } else if part == "-n" {
-            // Next arg is number of lines
-            if i + 1 < parts.len() {
-                i += 1;
-                if let Ok(n) = parts[i].parse::<usize>() {
-                    num_lines = n;
-                }
-            }
-        } else if part.starts_with("-n") {
-            // -nNUM format
-            if let Ok(n) = part[2..].parse::<usize>() {
-                num_lines = n;
-            }
-        } else if part.starts_with("--lines=") {
-            if let Ok(n) = part[8..].parse::<usize>() {
-                num_lines = n;
-            }
-        } else if !part.starts_with("-") {
-            // It's the filepath
-            filepath = Some(part);
-        }
-
-        i += 1;
-    }
-
-    // Must have -f flag and a file path
-    if has_follow {
-        if let Some(path) = filepath {
-            return Some((String::from(path), num_lines));
-        }
-    }
-
-    None
-}
-
-
-/// Start tail follow mode for a file
-/// Returns (success, initial_size)
-pub fn start_tail_follow(path: &str, num_lines: usize) -> (bool, usize) {
-    if let Some(content) = fs_proxy::fs_read(path) {
-        // Show last N lines
-        if let Ok(text) = core::str::from_utf8(&content) {
-            let lines: Vec<&str> = text.lines().collect();
-            let start = if lines.len() > num_lines {
-                lines.len() - num_lines
-            } else {
-                0
-            };
-
-            for i in start..lines.len() {
-                uart::write_line(lines[i]);
-            }
-        }
-
-        uart::write_line("");
-        uart::write_line("\x1b[2m--- Following (Ctrl+C or 'q' to stop) ---\x1b[0m");
-
-        return (true, content.len());
-    } else {
-        uart::write_str("\x1b[1;31mtail: cannot open '");
-        uart::write_str(path);
-        uart::write_line("': No such file\x1b[0m");
-    }
-
-    (false, 0)
-}
-
-
-
 /// Handle Enter key - execute command
 fn handle_enter() {
     let mut state = SHELL_STATE.lock();
@@ -644,27 +514,12 @@ fn handle_enter() {
         state.history_count += 1;
     }
     
-    // Check for tail -f command
-    if let Some((path, num_lines)) = parse_tail_follow_command(&buffer[..len]) {
-        let resolved = crate::resolve_path(&path);
-        drop(state);
-        
-        let (success, initial_size) = start_tail_follow(&resolved, num_lines);
-        if success {
-            let mut state = SHELL_STATE.lock();
-            state.tail_follow_mode = true;
-            drop(state);
-            crate::lock::utils::TAIL_FOLLOW_STATE.lock().start(&resolved, initial_size);
-        } else {
-            print_prompt();
-        }
-    } else {
-        // Execute command
-        let mut count = 0;
-        drop(state);
-        uart::handle_line(&buffer, len, &mut count);
-        print_prompt();
-    }
+    // Execute command
+    let mut count = 0;
+    drop(state);
+    uart::handle_line(&buffer, len, &mut count);
+    report_jobs();
+    print_prompt();
     
     // Reset state for next command
     let mut state = SHELL_STATE.lock();
@@ -816,6 +671,258 @@ fn handle_history_down(state: &mut ShellState) {
 
 
 
+// ═══════════════════════════════════════════════════════════════════════════════
+// JOB CONTROL
+// ═══════════════════════════════════════════════════════════════════════════════
+//
+// `cmd &` starts a program in a process of its own instead of running it in
+// the shell, in a new process group, and keeps it in the job table. `fg`
+// waits for a job with the terminal handed to it: Ctrl+C and Ctrl+Z go to
+// its group, other keys to the job. Commands run without `&` run in the
+// shell's own process, so only Ctrl+C reaches them.
+//
+// User programs are not preempted, so a job, running or stopped, keeps its
+// hart to itself; jobs start on the harts the shell is not using.
+
+/// Most jobs the shell keeps track of
+const MAX_JOBS: usize = 16;
+
+/// How often `fg` checks on the job it waits for (ms)
+const FG_POLL_MS: i64 = 20;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum JobState {
+    Running,
+    Stopped,
+}
+
+/// A program started with `&`
+struct Job {
+    /// Number the user refers to it by (`fg %1`)
+    id: usize,
+    /// Its process, which leads the job's process group
+    pid: Pid,
+    /// Process that started it and collects its exit status
+    parent: Pid,
+    command: String,
+    state: JobState,
+}
+
+impl Job {
+    fn line(&self, status: &str, long: bool) -> String {
+        if long {
+            format!("[{}]  {:<5} {:<10} {}", self.id, self.pid, status, self.command)
+        } else {
+            format!("[{}]  {:<10} {}", self.id, status, self.command)
+        }
+    }
+}
+
+/// Jobs, oldest first
+static JOBS: Spinlock<Vec<Job>> = Spinlock::new(Vec::new());
+
+/// Start `cmd` in the background (`cmd args &`) as a new job
+pub fn run_background(cmd: &[u8], args: &[u8]) {
+    let cmd = core::str::from_utf8(cmd).unwrap_or("");
+    let args = core::str::from_utf8(args).unwrap_or("");
+    if JOBS.lock().len() >= MAX_JOBS {
+        set_last_status(1);
+        out_line("\x1b[1;31mError:\x1b[0m Too many jobs");
+        return;
+    }
+
+    let pid = match crate::elf_loader::spawn(cmd, args) {
+        Ok(pid) => pid,
+        Err("Not found") => {
+            set_last_status(127);
+            out_line(&format!("\x1b[1;31mError:\x1b[0m {}: command not found", cmd));
+            return;
+        }
+        Err(_) => {
+            set_last_status(126);
+            out_line("\x1b[1;31mError:\x1b[0m Only programs in /usr/bin can run in the background");
+            return;
+        }
+    };
+    // A group of its own: signals for the job reach whatever it starts
+    let _ = PROCESS_TABLE.set_group(pid, pid);
+
+    let command = if args.is_empty() { String::from(cmd) } else { format!("{} {}", cmd, args) };
+    let mut jobs = JOBS.lock();
+    let id = jobs.last().map_or(1, |job| job.id + 1);
+    jobs.push(Job { id, pid, parent: sched::current_pid(), command, state: JobState::Running });
+    drop(jobs);
+    out_line(&format!("[{}] {}", id, pid));
+}
+
+/// Print the jobs that finished or stopped since the last look, and forget
+/// the finished ones
+pub fn report_jobs() {
+    let mut lines = Vec::new();
+    JOBS.lock().retain_mut(|job| {
+        match sched::wait_child(job.parent, Some(job.pid), 0) {
+            Ok(Some(status)) if status.code == 0 => lines.push(job.line("Done", false)),
+            Ok(Some(status)) => lines.push(job.line(&format!("Exit {}", status.code), false)),
+            // Collected by someone else
+            Err(_) => lines.push(job.line("Done", false)),
+            Ok(None) => {
+                let stopped = PROCESS_TABLE.get(job.pid).is_some_and(|process| process.stopped());
+                if stopped && job.state == JobState::Running {
+                    lines.push(job.line("Stopped", false));
+                }
+                job.state = if stopped { JobState::Stopped } else { JobState::Running };
+                return true;
+            }
+        }
+        false
+    });
+    for line in lines {
+        out_line(&line);
+    }
+}
+
+/// Index of the job `spec` names: "%2" or "2", the latest if empty
+fn find_job(jobs: &[Job], spec: &str) -> Result<usize, &'static str> {
+    if jobs.is_empty() {
+        return Err("no current job");
+    }
+    let spec = spec.trim();
+    if spec.is_empty() || spec == "%" || spec == "%+" {
+        return Ok(jobs.len() - 1);
+    }
+    let id = spec.trim_start_matches('%').parse::<usize>().map_err(|_| "no such job")?;
+    jobs.iter().position(|job| job.id == id).ok_or("no such job")
+}
+
+/// `jobs [-l]`: list the jobs, with their PIDs for -l
+pub fn jobs_command(args: &str) {
+    let long = match args.trim() {
+        "" => false,
+        "-l" => true,
+        _ => {
+            set_last_status(2);
+            out_line("Usage: jobs [-l]");
+            return;
+        }
+    };
+    report_jobs();
+    let lines: Vec<String> = JOBS
+        .lock()
+        .iter()
+        .map(|job| match job.state {
+            JobState::Running => job.line("Running", long) + " &",
+            JobState::Stopped => job.line("Stopped", long),
+        })
+        .collect();
+    for line in lines {
+        out_line(&line);
+    }
+}
+
+/// `fg [%job]`: continue a job if stopped and wait for it with the terminal
+pub fn fg_command(args: &str) {
+    report_jobs();
+    let (pid, parent, command, stopped) = {
+        let jobs = JOBS.lock();
+        match find_job(&jobs, args) {
+            Ok(index) => {
+                let job = &jobs[index];
+                (job.pid, job.parent, job.command.clone(), job.state == JobState::Stopped)
+            }
+            Err(e) => {
+                drop(jobs);
+                set_last_status(1);
+                out_line(&format!("fg: {}", e));
+                return;
+            }
+        }
+    };
+    out_line(&command);
+
+    signal::set_foreground(pid);
+    if stopped {
+        let _ = signal::send_group(pid, signal::SIGCONT);
+    }
+    let exited = wait_foreground(parent, pid);
+    signal::set_foreground(0);
+
+    let mut jobs = JOBS.lock();
+    let Some(index) = jobs.iter().position(|job| job.pid == pid) else {
+        return;
+    };
+    match exited {
+        Some(code) => {
+            jobs.remove(index);
+            drop(jobs);
+            set_last_status(code);
+        }
+        None => {
+            jobs[index].state = JobState::Stopped;
+            let line = jobs[index].line("Stopped", false);
+            drop(jobs);
+            set_last_status(signal::exit_code(signal::SIGTSTP));
+            uart::write_line("");
+            out_line(&line);
+        }
+    }
+}
+
+/// Wait for the foreground job `pid` to exit (its exit code) or stop
+/// (None), passing Ctrl+C and Ctrl+Z on to its group and every other key
+/// to the job
+fn wait_foreground(parent: Pid, pid: Pid) -> Option<i32> {
+    loop {
+        match sched::wait_child(parent, Some(pid), 0) {
+            Ok(Some(status)) => return Some(status.code),
+            Err(_) => return Some(0),
+            Ok(None) => {}
+        }
+        if PROCESS_TABLE.get(pid).is_some_and(|process| process.stopped()) {
+            return None;
+        }
+        while let Some(byte) = uart::read_device_nonblocking() {
+            match byte {
+                0x03 => {
+                    let _ = signal::send_group(pid, signal::SIGINT);
+                }
+                0x1a => {
+                    let _ = signal::send_group(pid, signal::SIGTSTP);
+                }
+                _ => uart::unread_char(byte),
+            }
+        }
+        crate::lock::wait::sleep_until(crate::get_time_ms() + FG_POLL_MS);
+    }
+}
+
+/// `bg [%job]`: continue a stopped job in the background
+pub fn bg_command(args: &str) {
+    report_jobs();
+    let mut jobs = JOBS.lock();
+    let index = match find_job(&jobs, args) {
+        Ok(index) => index,
+        Err(e) => {
+            drop(jobs);
+            set_last_status(1);
+            out_line(&format!("bg: {}", e));
+            return;
+        }
+    };
+    let job = &mut jobs[index];
+    if job.state == JobState::Running {
+        let id = job.id;
+        drop(jobs);
+        set_last_status(1);
+        out_line(&format!("bg: job {} already in background", id));
+        return;
+    }
+    job.state = JobState::Running;
+    let (pid, line) = (job.pid, format!("[{}]  {} &", job.id, job.command));
+    drop(jobs);
+    let _ = signal::send_group(pid, signal::SIGCONT);
+    out_line(&line);
+}
+
 // ═══════════════════════════════════════════════════════════════════════════════
 // PUBLIC API
 // ═══════════════════════════════════════════════════════════════════════════════
@@ -3,13 +3,15 @@ use core::sync::atomic::{AtomicBool, AtomicI64, AtomicUsize, Ordering};
 
 use alloc::format;
 
-use crate::{PROC_SCHEDULER, services::klogd::LogTarget};
+use crate::{PROC_SCHEDULER, cpu::process::USAGE_SAMPLE_MS, services::klogd::LogTarget};
 
 
 /// State for sysmond daemon  
 static SYSMOND_LAST_RUN: AtomicI64 = AtomicI64::new(0);
 static SYSMOND_TICK: AtomicUsize = AtomicUsize::new(0);
 static SYSMOND_INITIALIZED: AtomicBool = AtomicBool::new(false);
+/// Time of the last per-process CPU usage sample
+static USAGE_LAST_SAMPLE: AtomicI64 = AtomicI64::new(0);
 
 /// Append a line to the sysmond log (queued for hart 0 to flush)
 /// Safe to call from any hart
@@ -50,25 +52,65 @@ pub fn sysmond_tick() {
     let process_count = PROC_SCHEDULER.process_count();
     let queued_count = PROC_SCHEDULER.total_queued();
     let num_harts = crate::HARTS_ONLINE.load(Ordering::Relaxed);
+    let sched_stats = PROC_SCHEDULER.stats();
 
     // Reap zombies
     let reaped = PROC_SCHEDULER.reap_zombies();
 
+    // Flush write-back cache
+    let flushed = flush_dirty_blocks();
+
     let log_line = format!(
-        "[{}] sysmond[{}]: procs={} queued={} harts={} reaped={}",
-        now, tick, process_count, queued_count, num_harts, reaped
+        "[{}] sysmond[{}]: procs={} queued={} harts={} reaped={} flushed={} migrations={} steals={} imbalance={}",
+        now, tick, process_count, queued_count, num_harts, reaped, flushed,
+        sched_stats.migrations, sched_stats.steals, sched_stats.last_imbalance
     );
     append_to_sysmond_log(&log_line);
 }
 
+/// Sample per-process CPU usage (for %CPU in ps and top) once per
+/// USAGE_SAMPLE_MS. Runs from the start, unlike the 10 second stats tick.
+fn sample_cpu_usage(now: i64) {
+    let last = USAGE_LAST_SAMPLE.load(Ordering::Relaxed);
+    if now - last < USAGE_SAMPLE_MS {
+        return;
+    }
+    USAGE_LAST_SAMPLE.store(now, Ordering::Relaxed);
+    PROC_SCHEDULER.sample_cpu_usage((now - last) as u64);
+}
+
+/// Sync the filesystem if the block cache holds dirty blocks.
+/// Returns the number of dirty blocks that were pending.
+fn flush_dirty_blocks() -> usize {
+    // Skip this round rather than wait if someone is using the filesystem
+    let dirty = match crate::lock::utils::FS_STATE.try_read() {
+        Some(guard) => match *guard {
+            Some(ref fs) => fs.dirty_blocks(),
+            None => 0,
+        },
+        None => return 0,
+    };
+
+    if dirty == 0 {
+        return 0;
+    }
+
+    match crate::cpu::fs_proxy::fs_sync() {
+        Ok(()) => dirty,
+        Err(_) => 0,
+    }
+}
+
 
 /// Daemon service entry point for sysmond
 /// Cooperative time-slicing: does one tick of work and returns.
 /// The scheduler will requeue this daemon to run again.
 /// Note: sysmond_tick has internal timing (runs every 10 seconds)
 pub fn sysmond_service() {
-    // Quick check: only do real work if 9+ seconds since last run
     let now = crate::get_time_ms();
+    sample_cpu_usage(now);
+
+    // Quick check: only do real work if 9+ seconds since last run
     let last = SYSMOND_LAST_RUN.load(Ordering::Relaxed);
     
     if SYSMOND_INITIALIZED.load(Ordering::Relaxed) && (now - last) < 9000 {
@@ -116,7 +116,7 @@ fn tick_impl(now: i64) {
     
     // Check listening socket state
     if let Some(listen_id) = unsafe { TCPD_LISTEN_SOCKET } {
-        let state = net.tcp_server_state(listen_id);
+        let state = net.tcp_state(listen_id);
         
         if state == "Established" {
             for slot in unsafe { (*addr_of_mut!(TCPD_CONNECTIONS)).iter_mut() } {
@@ -147,11 +147,11 @@ fn tick_impl(now: i64) {
     // Service existing connections
     for slot in unsafe { (*addr_of_mut!(TCPD_CONNECTIONS)).iter_mut() } {
         if let Some(sock_id) = slot.socket_id {
-            let state = net.tcp_server_state(sock_id);
+            let state = net.tcp_state(sock_id);
             
             if slot.close_pending {
                 if state == "Closed" || state == "TimeWait" {
-                    net.tcp_release_server(sock_id);
+                    net.tcp_release(sock_id);
                     slot.reset();
                     if unsafe { TCPD_LISTEN_SOCKET.is_none() } {
                         if let Ok(new_listen) = net.tcp_listen(TCPD_PORT) {
@@ -161,7 +161,7 @@ fn tick_impl(now: i64) {
                 }
             } else if state != "Established" {
                 if state == "Closed" || state == "TimeWait" {
-                    net.tcp_release_server(sock_id);
+                    net.tcp_release(sock_id);
                     slot.reset();
                     if unsafe { TCPD_LISTEN_SOCKET.is_none() } {
                         if let Ok(new_listen) = net.tcp_listen(TCPD_PORT) {
@@ -170,7 +170,7 @@ fn tick_impl(now: i64) {
                     }
                 }
             } else if !slot.sent_hello {
-                match net.tcp_send_on(sock_id, b"works\n", now) {
+                match net.tcp_send(sock_id, b"works\n", now) {
                     Ok(sent) if sent > 0 => {
                         slot.sent_hello = true;
                         klog_info("tcpd", &format!("Sent 'works' ({} bytes)", sent));
@@ -178,12 +178,12 @@ fn tick_impl(now: i64) {
                     Ok(_) => {}
                     Err(e) => {
                         klog_info("tcpd", &format!("Send error: {}", e));
-                        net.tcp_close_on(sock_id, now);
+                        net.tcp_close(sock_id, now);
                         slot.close_pending = true;
                     }
                 }
             } else {
-                net.tcp_close_on(sock_id, now);
+                net.tcp_close(sock_id, now);
                 slot.close_pending = true;
                 klog_info("tcpd", "Closing connection");
             }
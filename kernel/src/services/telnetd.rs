@@ -0,0 +1,296 @@
+//! telnetd - Remote shell over TCP
+//!
+//! Accepts one telnet connection at a time on port 23 and attaches it to the
+//! console: what the client types is read by the shell (and by programs
+//! reading the console) alongside the UART, and everything written to the
+//! console is copied to the client. The client is asked to leave echo to the
+//! server and to send keys as they are typed, so line editing, history and
+//! Ctrl+C work as they do on the UART.
+//!
+//! There is no login: anyone who can reach port 23 gets a shell. Limit who
+//! can with `/etc/firewall.rules` on networks that are not trusted.
+
+use alloc::collections::VecDeque;
+use alloc::format;
+use core::sync::atomic::{AtomicBool, AtomicI64, Ordering};
+
+use crate::net::{NetState, TcpSocketId};
+use crate::services::klogd::{klog_info, klog_warning};
+use crate::Spinlock;
+
+/// Telnet listen port
+pub const TELNETD_PORT: u16 = 23;
+
+/// How often the connection is serviced
+const TICK_INTERVAL_MS: i64 = 10;
+
+/// Bytes moved each way per tick
+const CHUNK_SIZE: usize = 512;
+
+/// Most typed bytes waiting for the shell, and output waiting for the client
+/// (the oldest output is dropped when a slow client falls behind)
+const MAX_INPUT: usize = 1024;
+const MAX_OUTPUT: usize = 16 * 1024;
+
+// Telnet commands (RFC 854) and the options we negotiate
+const IAC: u8 = 255;
+const DONT: u8 = 254;
+const DO: u8 = 253;
+const WONT: u8 = 252;
+const WILL: u8 = 251;
+const SB: u8 = 250;
+const SE: u8 = 240;
+/// Interrupt Process, sent by some clients for Ctrl+C
+const IP: u8 = 244;
+const OPT_ECHO: u8 = 1;
+const OPT_SGA: u8 = 3;
+
+/// Sent on connect: we echo, and neither side waits for Go Ahead
+const NEGOTIATION: [u8; 9] = [IAC, WILL, OPT_ECHO, IAC, WILL, OPT_SGA, IAC, DO, OPT_SGA];
+
+const GREETING: &str = "\r\nConnected to the havy-os console. Press Enter for a prompt.\r\n";
+
+static TELNETD_LAST_TICK: AtomicI64 = AtomicI64::new(0);
+
+/// A client is attached; checked before taking any lock on the output path
+static CONNECTED: AtomicBool = AtomicBool::new(false);
+
+/// Bytes shared with the console
+struct RemoteConsole {
+    /// Typed by the client, not yet read
+    input: VecDeque<u8>,
+    /// Console output not yet sent, already in telnet form
+    output: VecDeque<u8>,
+}
+
+/// Never print while holding this: console output takes it too
+static REMOTE: Spinlock<RemoteConsole> = Spinlock::new(RemoteConsole { input: VecDeque::new(), output: VecDeque::new() });
+
+/// Where the telnet command parser is within the input stream
+#[derive(Clone, Copy, PartialEq)]
+enum Parse {
+    Data,
+    /// Last byte was CR (a NUL or LF after it is part of the newline)
+    Cr,
+    Iac,
+    /// WILL/WONT/DO/DONT, waiting for the option
+    Option(u8),
+    /// Inside a subnegotiation, which only IAC SE ends
+    Sub,
+    SubIac,
+}
+
+struct TelnetdState {
+    listener: Option<TcpSocketId>,
+    client: Option<TcpSocketId>,
+    parse: Parse,
+}
+
+static TELNETD: Spinlock<TelnetdState> = Spinlock::new(TelnetdState { listener: None, client: None, parse: Parse::Data });
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// CONSOLE SIDE
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Copy console output to the client, if one is attached
+pub fn mirror_output(bytes: &[u8]) {
+    if !CONNECTED.load(Ordering::Relaxed) {
+        return;
+    }
+    let mut remote = REMOTE.lock();
+    for &b in bytes {
+        match b {
+            b'\n' => remote.output.extend([b'\r', b'\n']),
+            IAC => remote.output.extend([IAC, IAC]),
+            _ => remote.output.push_back(b),
+        }
+    }
+    let excess = remote.output.len().saturating_sub(MAX_OUTPUT);
+    remote.output.drain(..excess);
+}
+
+/// The client typed something not yet read
+pub fn has_input() -> bool {
+    CONNECTED.load(Ordering::Relaxed) && !REMOTE.lock().input.is_empty()
+}
+
+/// Next byte typed by the client
+pub fn read_input() -> Option<u8> {
+    if !CONNECTED.load(Ordering::Relaxed) {
+        return None;
+    }
+    REMOTE.lock().input.pop_front()
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// CONNECTION SIDE
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Strip telnet commands from `data`, queueing what was typed; returns the
+/// negotiation replies to send
+fn parse_input(parse: &mut Parse, data: &[u8], input: &mut VecDeque<u8>) -> VecDeque<u8> {
+    let mut replies = VecDeque::new();
+    for &b in data {
+        *parse = match (*parse, b) {
+            (Parse::Iac, IAC) => {
+                typed(input, IAC);
+                Parse::Data
+            }
+            (Parse::Iac, WILL | WONT | DO | DONT) => Parse::Option(b),
+            (Parse::Iac, SB) => Parse::Sub,
+            (Parse::Iac, IP) => {
+                typed(input, 0x03);
+                Parse::Data
+            }
+            // NOP, Go Ahead, Are You There and the like
+            (Parse::Iac, _) => Parse::Data,
+            (Parse::Option(verb), option) => {
+                // Refuse everything but what we asked for; never answer a refusal
+                match verb {
+                    DO if option != OPT_ECHO && option != OPT_SGA => replies.extend([IAC, WONT, option]),
+                    WILL if option != OPT_SGA => replies.extend([IAC, DONT, option]),
+                    _ => {}
+                }
+                Parse::Data
+            }
+            (Parse::Sub, IAC) => Parse::SubIac,
+            (Parse::Sub, _) => Parse::Sub,
+            (Parse::SubIac, SE) => Parse::Data,
+            (Parse::SubIac, _) => Parse::Sub,
+            (_, IAC) => Parse::Iac,
+            (Parse::Cr, 0) => Parse::Data,
+            (_, b'\r') => {
+                typed(input, b'\r');
+                Parse::Cr
+            }
+            (_, _) => {
+                typed(input, b);
+                Parse::Data
+            }
+        };
+    }
+    replies
+}
+
+/// Queue a typed byte, unless the shell is that far behind
+fn typed(input: &mut VecDeque<u8>, b: u8) {
+    if input.len() < MAX_INPUT {
+        input.push_back(b);
+    }
+}
+
+/// Detach the client and listen for the next one
+fn disconnect(state: &mut TelnetdState, net: &mut NetState, client: TcpSocketId, now: i64) {
+    CONNECTED.store(false, Ordering::Relaxed);
+    {
+        let mut remote = REMOTE.lock();
+        remote.input.clear();
+        remote.output.clear();
+    }
+    net.tcp_disconnect(client, now);
+    state.client = None;
+    klog_info("telnetd", "Client disconnected");
+}
+
+/// Move typed bytes to the console and console output to the client
+fn service(state: &mut TelnetdState, net: &mut NetState, client: TcpSocketId, now: i64) {
+    if matches!(net.tcp_state(client), "CloseWait" | "LastAck" | "Closing" | "TimeWait" | "Closed" | "Invalid") {
+        disconnect(state, net, client, now);
+        return;
+    }
+
+    let mut buf = [0u8; CHUNK_SIZE];
+    let replies = match net.tcp_recv(client, &mut buf, now) {
+        Ok(n) if n > 0 => {
+            let replies = parse_input(&mut state.parse, &buf[..n], &mut REMOTE.lock().input);
+            crate::uart::input_arrived();
+            replies
+        }
+        _ => VecDeque::new(),
+    };
+
+    let pending = {
+        let mut remote = REMOTE.lock();
+        remote.output.extend(replies);
+        let n = remote.output.len().min(CHUNK_SIZE);
+        for (slot, &b) in buf.iter_mut().zip(remote.output.iter().take(n)) {
+            *slot = b;
+        }
+        n
+    };
+    if pending == 0 {
+        return;
+    }
+    match net.tcp_send(client, &buf[..pending], now) {
+        Ok(sent) => {
+            REMOTE.lock().output.drain(..sent);
+        }
+        Err(_) => disconnect(state, net, client, now),
+    }
+}
+
+/// telnetd tick: accept a client, or service the attached one
+pub fn tick() {
+    let now = crate::get_time_ms();
+    let last = TELNETD_LAST_TICK.load(Ordering::Relaxed);
+    if last != 0 && now - last < TICK_INTERVAL_MS {
+        return;
+    }
+    TELNETD_LAST_TICK.store(now, Ordering::Relaxed);
+
+    let Some(mut net_guard) = crate::NET_STATE.try_lock() else {
+        return;
+    };
+    let Some(net) = net_guard.as_mut() else {
+        return;
+    };
+    let mut state = TELNETD.lock();
+
+    if let Some(client) = state.client {
+        net.poll(now);
+        service(&mut state, net, client, now);
+        return;
+    }
+
+    // One client at a time: the listener only exists while nobody is attached
+    let listener = match state.listener {
+        Some(listener) => listener,
+        None => match net.tcp_listen(TELNETD_PORT) {
+            Ok(listener) => {
+                if last == 0 {
+                    klog_info("telnetd", &format!("Listening on TCP port {}", TELNETD_PORT));
+                }
+                state.listener = Some(listener);
+                listener
+            }
+            Err(e) => {
+                if last == 0 {
+                    klog_warning("telnetd", &format!("Cannot listen on port {}: {}", TELNETD_PORT, e));
+                }
+                return;
+            }
+        },
+    };
+    net.poll(now);
+    let Some((client, remote_ip, remote_port)) = net.tcp_accept(listener) else {
+        return;
+    };
+    // The listening socket became the connection
+    state.listener = None;
+    state.client = Some(client);
+    state.parse = Parse::Data;
+    {
+        let mut remote = REMOTE.lock();
+        remote.input.clear();
+        remote.output.clear();
+        remote.output.extend(NEGOTIATION);
+        remote.output.extend(GREETING.bytes());
+    }
+    CONNECTED.store(true, Ordering::Relaxed);
+    klog_info("telnetd", &format!("Client connected from {}:{}", remote_ip, remote_port));
+}
+
+/// telnetd service entry point (for scheduler)
+pub fn telnetd_service() {
+    tick();
+}
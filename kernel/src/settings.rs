@@ -0,0 +1,475 @@
+//! System settings
+//!
+//! System preferences, persisted in `/etc/settings.toml`:
+//!
+//! ```toml
+//! [system]
+//! hostname = "bavy"
+//! lang = "en"
+//!
+//! [network]
+//! profile = "relay"
+//! address = "10.0.2.15"
+//! ipv6 = "slaac"
+//! ipv6_address = "fd00::15"
+//!
+//! [ui]
+//! theme = "dark"
+//! large_font = false
+//!
+//! [input]
+//! keymap = "us"
+//! key_repeat_delay_ms = 500
+//! key_repeat_rate_hz = 30
+//!
+//! [services]
+//! sysmond = true
+//! tcpd = true
+//! httpd = true
+//!
+//! [wasm]
+//! fuel_budget = 0
+//! ```
+//!
+//! Values live in atomics (or a small lock for strings) so the renderer and
+//! the input layer can read them on every frame cheaply. `set` applies a
+//! change immediately (redrawing the UI, reconfiguring the network) and writes
+//! the file back. Edits made to the file directly are picked up by
+//! `reload_if_changed` through a file watch. Service autostart flags take
+//! effect at the next boot.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, AtomicU8, AtomicU32, Ordering};
+
+use smoltcp::wire::{Ipv4Address, Ipv6Address};
+
+use crate::cpu::fs_proxy;
+use crate::fs::watch::{PathWatch, WATCH_CREATE, WATCH_MODIFY};
+use crate::Spinlock;
+
+/// Where settings are stored
+pub const SETTINGS_PATH: &str = "/etc/settings.toml";
+
+/// Watch on the settings file for live reload
+static SETTINGS_WATCH: PathWatch = PathWatch::new(SETTINGS_PATH, WATCH_CREATE | WATCH_MODIFY);
+
+/// Setting names (`section.key`), in file order
+pub const KEYS: &[&str] = &[
+    "system.hostname",
+    "system.lang",
+    "network.profile",
+    "network.address",
+    "network.ipv6",
+    "network.ipv6_address",
+    "ui.theme",
+    "ui.large_font",
+    "input.keymap",
+    "input.key_repeat_delay_ms",
+    "input.key_repeat_rate_hz",
+    "services.sysmond",
+    "services.tcpd",
+    "services.httpd",
+    "wasm.fuel_budget",
+];
+
+/// Services that can be kept from starting at boot
+pub const OPTIONAL_SERVICES: &[&str] = &["sysmond", "tcpd", "httpd"];
+
+/// Longest accepted hostname
+pub const HOSTNAME_MAX: usize = 32;
+
+/// Language used when none is set (the built-in messages)
+pub const DEFAULT_LANG: &str = "en";
+
+/// Color theme
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Theme {
+    Dark,
+    Light,
+    HighContrast,
+}
+
+impl Theme {
+    pub const ALL: [Theme; 3] = [Theme::Dark, Theme::Light, Theme::HighContrast];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Theme::Dark => "dark",
+            Theme::Light => "light",
+            Theme::HighContrast => "high_contrast",
+        }
+    }
+}
+
+/// Keyboard layout used to turn key codes into characters
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Keymap {
+    Us,
+    De,
+    Fr,
+}
+
+impl Keymap {
+    pub const ALL: [Keymap; 3] = [Keymap::Us, Keymap::De, Keymap::Fr];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Keymap::Us => "us",
+            Keymap::De => "de",
+            Keymap::Fr => "fr",
+        }
+    }
+}
+
+/// Where the IPv4 address comes from
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum NetProfile {
+    /// Assigned by the relay (default)
+    Relay,
+    /// Fixed `network.address`
+    Static,
+}
+
+impl NetProfile {
+    pub const ALL: [NetProfile; 2] = [NetProfile::Relay, NetProfile::Static];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            NetProfile::Relay => "relay",
+            NetProfile::Static => "static",
+        }
+    }
+}
+
+/// How the global IPv6 address is configured
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Ipv6Mode {
+    /// No IPv6 at all
+    Off,
+    /// From Router Advertisements (default)
+    Slaac,
+    /// Fixed `network.ipv6_address`
+    Static,
+}
+
+impl Ipv6Mode {
+    pub const ALL: [Ipv6Mode; 3] = [Ipv6Mode::Off, Ipv6Mode::Slaac, Ipv6Mode::Static];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Ipv6Mode::Off => "off",
+            Ipv6Mode::Slaac => "slaac",
+            Ipv6Mode::Static => "static",
+        }
+    }
+}
+
+static HOSTNAME: Spinlock<String> = Spinlock::new(String::new());
+static LANG: Spinlock<String> = Spinlock::new(String::new());
+static NET_PROFILE: AtomicU8 = AtomicU8::new(NetProfile::Relay as u8);
+/// Static address, octets in big-endian order
+static STATIC_ADDRESS: AtomicU32 = AtomicU32::new(u32::from_be_bytes([10, 0, 2, 15]));
+static IPV6_MODE: AtomicU8 = AtomicU8::new(Ipv6Mode::Slaac as u8);
+static IPV6_STATIC_ADDRESS: Spinlock<[u8; 16]> =
+    Spinlock::new([0xFD, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x15]);
+static THEME: AtomicU8 = AtomicU8::new(Theme::Dark as u8);
+static LARGE_FONT: AtomicBool = AtomicBool::new(false);
+static KEYMAP: AtomicU8 = AtomicU8::new(Keymap::Us as u8);
+static KEY_REPEAT_DELAY_MS: AtomicU32 = AtomicU32::new(500);
+static KEY_REPEAT_RATE_HZ: AtomicU32 = AtomicU32::new(30);
+/// Bit per entry of `OPTIONAL_SERVICES`; set = start at boot
+static AUTOSTART: AtomicU8 = AtomicU8::new(0xFF);
+/// Fuel one WASM program may use, in millions of instructions (0 = no limit)
+static WASM_FUEL_BUDGET: AtomicU32 = AtomicU32::new(0);
+
+/// System hostname (shown in the shell prompt)
+pub fn hostname() -> String {
+    let name = HOSTNAME.lock();
+    if name.is_empty() { String::from("bavy") } else { name.clone() }
+}
+
+/// Language of kernel messages (see `locale`), also the `LANG` variable
+pub fn lang() -> String {
+    let lang = LANG.lock();
+    if lang.is_empty() { String::from(DEFAULT_LANG) } else { lang.clone() }
+}
+
+/// Network profile
+pub fn net_profile() -> NetProfile {
+    NetProfile::ALL[NET_PROFILE.load(Ordering::Relaxed) as usize]
+}
+
+/// Address used by the static network profile
+pub fn static_address() -> [u8; 4] {
+    STATIC_ADDRESS.load(Ordering::Relaxed).to_be_bytes()
+}
+
+/// IPv6 configuration mode
+pub fn ipv6_mode() -> Ipv6Mode {
+    Ipv6Mode::ALL[IPV6_MODE.load(Ordering::Relaxed) as usize]
+}
+
+/// Address used by the static IPv6 mode (a /64 is assumed)
+pub fn ipv6_static_address() -> Ipv6Address {
+    Ipv6Address::from(*IPV6_STATIC_ADDRESS.lock())
+}
+
+/// Color theme
+pub fn theme() -> Theme {
+    Theme::ALL[THEME.load(Ordering::Relaxed) as usize]
+}
+
+/// Larger fonts in the terminal and widgets
+pub fn large_font() -> bool {
+    LARGE_FONT.load(Ordering::Relaxed)
+}
+
+/// Keyboard layout
+pub fn keymap() -> Keymap {
+    Keymap::ALL[KEYMAP.load(Ordering::Relaxed) as usize]
+}
+
+/// Key repeat as (delay before the first repeat in ms, repeats per second; 0 = off)
+pub fn key_repeat() -> (u32, u32) {
+    (KEY_REPEAT_DELAY_MS.load(Ordering::Relaxed), KEY_REPEAT_RATE_HZ.load(Ordering::Relaxed))
+}
+
+/// Whether an optional service should be started at boot (core services always are)
+pub fn autostart(service: &str) -> bool {
+    match OPTIONAL_SERVICES.iter().position(|&s| s == service) {
+        Some(bit) => AUTOSTART.load(Ordering::Relaxed) & (1 << bit) != 0,
+        None => true,
+    }
+}
+
+/// Instructions a WASM program may run before it is aborted, if limited
+pub fn wasm_fuel_budget() -> Option<u64> {
+    match WASM_FUEL_BUDGET.load(Ordering::Relaxed) {
+        0 => None,
+        millions => Some(millions as u64 * 1_000_000),
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Reading and Writing
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Load settings from disk (missing file or unknown keys keep the defaults)
+pub fn load() {
+    let Some(data) = fs_proxy::fs_read(SETTINGS_PATH) else {
+        return;
+    };
+    let Ok(text) = core::str::from_utf8(&data) else {
+        return;
+    };
+
+    let mut section = "";
+    for line in text.lines() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            section = name.trim();
+        } else if let Some((key, value)) = line.split_once('=') {
+            let value = value.trim();
+            let value = value.strip_prefix('"').and_then(|v| v.strip_suffix('"')).unwrap_or(value);
+            let _ = apply(&format!("{}.{}", section, key.trim()), value);
+        }
+    }
+}
+
+/// Change a setting, apply it live and save all settings
+pub fn set(key: &str, value: &str) -> Result<(), &'static str> {
+    apply(key, value)?;
+    if key.starts_with("network.") {
+        apply_network();
+    }
+    if key == "system.lang" {
+        crate::locale::load();
+    }
+    crate::ui::main_screen::request_redraw();
+    fs_proxy::fs_write(SETTINGS_PATH, to_toml().as_bytes())
+}
+
+/// Re-read the settings file if it changed on disk and apply what differs
+/// (called periodically on hart 0)
+pub fn reload_if_changed() {
+    if !SETTINGS_WATCH.changed() {
+        return;
+    }
+    let network = || -> Vec<Option<String>> {
+        KEYS.iter().filter(|k| k.starts_with("network.")).map(|k| get(k)).collect()
+    };
+    let before = to_toml();
+    let network_before = network();
+    let lang_before = lang();
+    load();
+    // Our own `set` rewrites the file too; nothing to do then
+    if to_toml() == before {
+        return;
+    }
+    if network() != network_before {
+        apply_network();
+    }
+    if lang() != lang_before {
+        crate::locale::load();
+    }
+    crate::ui::main_screen::request_redraw();
+    crate::services::klogd::klog_info("settings", "Reloaded /etc/settings.toml");
+}
+
+/// Configure the interface for the network profile (at boot and on change)
+pub fn apply_network() {
+    let mut net_guard = crate::NET_STATE.lock();
+    let Some(net) = net_guard.as_mut() else {
+        return;
+    };
+    match net_profile() {
+        NetProfile::Static => {
+            let [a, b, c, d] = static_address();
+            net.set_static_ip(Ipv4Address::new(a, b, c, d));
+        }
+        NetProfile::Relay => net.use_relay_ip(),
+    }
+    net.configure_ipv6();
+}
+
+/// Current value of a setting, as written in the file (without quotes)
+pub fn get(key: &str) -> Option<String> {
+    let (delay, rate) = key_repeat();
+    Some(match key {
+        "system.hostname" => hostname(),
+        "system.lang" => lang(),
+        "network.profile" => String::from(net_profile().name()),
+        "network.address" => {
+            let [a, b, c, d] = static_address();
+            format!("{}.{}.{}.{}", a, b, c, d)
+        }
+        "network.ipv6" => String::from(ipv6_mode().name()),
+        "network.ipv6_address" => format!("{}", ipv6_static_address()),
+        "ui.theme" => String::from(theme().name()),
+        "ui.large_font" => format!("{}", large_font()),
+        "input.keymap" => String::from(keymap().name()),
+        "input.key_repeat_delay_ms" => format!("{}", delay),
+        "input.key_repeat_rate_hz" => format!("{}", rate),
+        "wasm.fuel_budget" => format!("{}", WASM_FUEL_BUDGET.load(Ordering::Relaxed)),
+        _ => format!("{}", autostart(key.strip_prefix("services.")?)),
+    })
+}
+
+/// All settings in file format
+pub fn to_toml() -> String {
+    let mut out = String::new();
+    let mut section = "";
+    for &key in KEYS {
+        let (sect, name) = key.split_once('.').unwrap_or(("", key));
+        if sect != section {
+            if !section.is_empty() {
+                out.push('\n');
+            }
+            out.push_str(&format!("[{}]\n", sect));
+            section = sect;
+        }
+        let value = get(key).unwrap_or_default();
+        if value == "true" || value == "false" || value.parse::<u32>().is_ok() {
+            out.push_str(&format!("{} = {}\n", name, value));
+        } else {
+            out.push_str(&format!("{} = \"{}\"\n", name, value));
+        }
+    }
+    out
+}
+
+/// Validate and store one setting
+fn apply(key: &str, value: &str) -> Result<(), &'static str> {
+    match key {
+        "system.hostname" => {
+            let valid = !value.is_empty()
+                && value.len() <= HOSTNAME_MAX
+                && !value.starts_with('-')
+                && value.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-');
+            if !valid {
+                return Err("hostname must be 1-32 letters, digits or '-'");
+            }
+            *HOSTNAME.lock() = String::from(value);
+        }
+        "system.lang" => {
+            let valid = (2..=8).contains(&value.len())
+                && value.bytes().all(|b| b.is_ascii_lowercase() || b == b'_');
+            if !valid {
+                return Err("lang must be 2-8 lowercase letters or '_' (e.g. en, de)");
+            }
+            *LANG.lock() = String::from(value);
+        }
+        "network.profile" => {
+            let profile = NetProfile::ALL.iter().position(|p| p.name() == value)
+                .ok_or("expected relay or static")?;
+            NET_PROFILE.store(profile as u8, Ordering::Relaxed);
+        }
+        "network.address" => {
+            let ip = crate::net::parse_ipv4(value.as_bytes()).ok_or("expected an IPv4 address")?;
+            STATIC_ADDRESS.store(u32::from_be_bytes(ip.octets()), Ordering::Relaxed);
+        }
+        "network.ipv6" => {
+            let mode = Ipv6Mode::ALL.iter().position(|m| m.name() == value)
+                .ok_or("expected off, slaac or static")?;
+            IPV6_MODE.store(mode as u8, Ordering::Relaxed);
+        }
+        "network.ipv6_address" => {
+            let ip = value.parse::<Ipv6Address>().map_err(|_| "expected an IPv6 address")?;
+            *IPV6_STATIC_ADDRESS.lock() = ip.octets();
+        }
+        "ui.theme" => {
+            let theme = Theme::ALL.iter().position(|t| t.name() == value)
+                .ok_or("expected dark, light or high_contrast")?;
+            THEME.store(theme as u8, Ordering::Relaxed);
+        }
+        // Older settings files had an on/off switch instead of a theme
+        "ui.high_contrast" => {
+            let theme = if parse_bool(value)? { Theme::HighContrast } else { Theme::Dark };
+            THEME.store(theme as u8, Ordering::Relaxed);
+        }
+        "ui.large_font" => LARGE_FONT.store(parse_bool(value)?, Ordering::Relaxed),
+        "input.keymap" => {
+            let keymap = Keymap::ALL.iter().position(|k| k.name() == value)
+                .ok_or("expected us, de or fr")?;
+            KEYMAP.store(keymap as u8, Ordering::Relaxed);
+        }
+        "input.key_repeat_delay_ms" => {
+            let ms = value.parse::<u32>().map_err(|_| "expected a number")?;
+            if !(100..=2000).contains(&ms) {
+                return Err("key repeat delay must be 100-2000 ms");
+            }
+            KEY_REPEAT_DELAY_MS.store(ms, Ordering::Relaxed);
+        }
+        "input.key_repeat_rate_hz" => {
+            let hz = value.parse::<u32>().map_err(|_| "expected a number")?;
+            if hz > 60 {
+                return Err("key repeat rate must be 0-60 per second");
+            }
+            KEY_REPEAT_RATE_HZ.store(hz, Ordering::Relaxed);
+        }
+        "wasm.fuel_budget" => {
+            let millions = value.parse::<u32>().map_err(|_| "expected millions of instructions (0 = no limit)")?;
+            WASM_FUEL_BUDGET.store(millions, Ordering::Relaxed);
+        }
+        _ => {
+            let service = key.strip_prefix("services.").ok_or("unknown setting")?;
+            let bit = OPTIONAL_SERVICES.iter().position(|&s| s == service).ok_or("unknown setting")?;
+            if parse_bool(value)? {
+                AUTOSTART.fetch_or(1 << bit, Ordering::Relaxed);
+            } else {
+                AUTOSTART.fetch_and(!(1 << bit), Ordering::Relaxed);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn parse_bool(value: &str) -> Result<bool, &'static str> {
+    match value {
+        "true" | "on" | "1" => Ok(true),
+        "false" | "off" | "0" => Ok(false),
+        _ => Err("expected true or false"),
+    }
+}
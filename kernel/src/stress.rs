@@ -0,0 +1,474 @@
+//! Stress Test
+//!
+//! The soak test run before releases (`stress`): CPU, filesystem and
+//! network workloads spread over the harts for a fixed time, then a
+//! pass/fail verdict. Each worker is a daemon pinned to a hart (hart 0 only
+//! on single-hart systems, since it runs everyone's device transfers) that
+//! does one slice of work per scheduler tick and exits itself at the
+//! deadline.
+//!
+//! - **cpu** workers recompute a fixed checksum, which must never change,
+//!   and bump two shared counters: one atomic, one behind a `Spinlock`.
+//!   Both must equal the sum of the workers' own counts at the end.
+//! - **io** workers write a file under `STRESS_DIR`, read it back, compare,
+//!   and remove it. No file may be left over. Their report line doubles as
+//!   the filesystem benchmark: bytes written and read back per second, and
+//!   the sector transfers hart 0 ran for the other harts. Compare one io
+//!   worker with one per hart to see how filesystem work scales.
+//! - **net** workers fetch a URL, which must answer 2xx with the same body
+//!   every time (so point it at static content).
+//!
+//! A run also fails if any worker never got to run.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use crate::clint::get_time_ms;
+use crate::cpu::fs_proxy;
+use crate::cpu::process::{Pid, Priority};
+use crate::services::klogd::{klog_info, klog_warning};
+use crate::Spinlock;
+
+/// Directory io workers write their files in
+pub const STRESS_DIR: &str = "/var/stress";
+
+/// URL net workers fetch by default (httpd on this machine)
+pub const DEFAULT_URL: &str = "http://127.0.0.1/";
+
+/// Most workers of one kind
+pub const MAX_WORKERS: usize = 32;
+
+/// Longest run
+pub const MAX_SECONDS: u64 = 24 * 60 * 60;
+
+/// Time a cpu worker spins per tick
+const CPU_SLICE_MS: i64 = 10;
+
+/// Timeout for each wait during a fetch
+const NET_TIMEOUT_MS: i64 = 10_000;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Cpu,
+    Io,
+    Net,
+}
+
+impl Kind {
+    fn name(self) -> &'static str {
+        match self {
+            Kind::Cpu => "cpu",
+            Kind::Io => "io",
+            Kind::Net => "net",
+        }
+    }
+
+    /// What one unit of work is called in the report
+    fn unit(self) -> &'static str {
+        match self {
+            Kind::Cpu => "rounds",
+            Kind::Io => "cycles",
+            Kind::Net => "fetches",
+        }
+    }
+}
+
+struct Worker {
+    kind: Kind,
+    pid: Pid,
+    hart: usize,
+    /// Units of work completed
+    ops: u64,
+    errors: u64,
+    /// Shared counter increments (cpu workers)
+    increments: u64,
+    /// Bytes written and read back (io workers)
+    bytes: u64,
+    /// Checksum or body hash every unit of work must reproduce
+    expected: Option<u64>,
+    done: bool,
+}
+
+/// What to run
+pub struct StressConfig {
+    pub cpu: usize,
+    pub io: usize,
+    pub net: usize,
+    pub seconds: u64,
+    pub url: String,
+}
+
+struct Run {
+    config: StressConfig,
+    started_ms: i64,
+    deadline_ms: i64,
+    workers: Vec<Worker>,
+    first_error: Option<String>,
+    /// Set by `stop`; workers exit on their next tick
+    stopped: bool,
+    /// Verdict, once every worker has finished
+    verdict: Option<bool>,
+    /// `d1_mmc::transfers_served` when the run started
+    transfers_at_start: u64,
+}
+
+/// Current or last run
+static RUN: Spinlock<Option<Run>> = Spinlock::new(None);
+
+/// Counters cpu workers increment concurrently
+static ATOMIC_COUNTER: AtomicU64 = AtomicU64::new(0);
+static LOCKED_COUNTER: Spinlock<u64> = Spinlock::new(0);
+
+/// State of a run, as reported by `status`
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum StressState {
+    Running = 1,
+    Passed = 2,
+    Failed = 3,
+}
+
+/// Start a run. Fails if one is already running.
+pub fn start(config: StressConfig) -> Result<(), &'static str> {
+    if config.cpu + config.io + config.net == 0 {
+        return Err("Nothing to run");
+    }
+    if config.cpu.max(config.io).max(config.net) > MAX_WORKERS {
+        return Err("Too many workers");
+    }
+    if config.seconds == 0 || config.seconds > MAX_SECONDS {
+        return Err("Invalid duration");
+    }
+
+    // Held while spawning, so workers find themselves in the table
+    let mut run = RUN.lock();
+    if run.as_ref().is_some_and(|r| r.verdict.is_none()) {
+        return Err("A stress test is already running");
+    }
+
+    let num_cpus = crate::PROC_SCHEDULER.num_cpus();
+    let mut harts: Vec<usize> = (1..num_cpus).filter(|&h| crate::cpu::is_hart_ready(h)).collect();
+    if harts.is_empty() {
+        harts.push(0);
+    }
+
+    ATOMIC_COUNTER.store(0, Ordering::Relaxed);
+    *LOCKED_COUNTER.lock() = 0;
+
+    let kinds = [(Kind::Cpu, config.cpu), (Kind::Io, config.io), (Kind::Net, config.net)];
+    let mut workers = Vec::new();
+    for (kind, count) in kinds {
+        let (name, entry): (&str, fn()) = match kind {
+            Kind::Cpu => ("stress-cpu", cpu_worker),
+            Kind::Io => ("stress-io", io_worker),
+            Kind::Net => ("stress-net", net_worker),
+        };
+        for _ in 0..count {
+            // Round-robin over all workers, so each hart gets a mix
+            let hart = harts[workers.len() % harts.len()];
+            let pid = crate::PROC_SCHEDULER.spawn_daemon_on_cpu(name, entry, Priority::Normal, Some(hart));
+            workers.push(Worker { kind, pid, hart, ops: 0, errors: 0, increments: 0, bytes: 0, expected: None, done: false });
+        }
+    }
+
+    let now = get_time_ms();
+    klog_info(
+        "stress",
+        &format!(
+            "Started: {} cpu, {} io, {} net workers on {} hart(s) for {}s",
+            config.cpu,
+            config.io,
+            config.net,
+            harts.len(),
+            config.seconds
+        ),
+    );
+    *run = Some(Run {
+        started_ms: now,
+        deadline_ms: now + (config.seconds * 1000) as i64,
+        config,
+        workers,
+        first_error: None,
+        stopped: false,
+        verdict: None,
+        transfers_at_start: crate::platform::d1_mmc::transfers_served(),
+    });
+    Ok(())
+}
+
+/// End the current run early (it still gets a verdict)
+pub fn stop() -> bool {
+    match RUN.lock().as_mut() {
+        Some(run) if run.verdict.is_none() => {
+            run.stopped = true;
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Report on the current or last run
+pub fn status() -> Option<(String, StressState)> {
+    let run = RUN.lock();
+    let run = run.as_ref()?;
+    let state = match run.verdict {
+        None => StressState::Running,
+        Some(true) => StressState::Passed,
+        Some(false) => StressState::Failed,
+    };
+    Some((report(run), state))
+}
+
+fn report(run: &Run) -> String {
+    let now = get_time_ms().min(run.deadline_ms);
+    let mut out = format!("Elapsed: {}s of {}s\n", (now - run.started_ms) / 1000, run.config.seconds);
+    for kind in [Kind::Cpu, Kind::Io, Kind::Net] {
+        let workers: Vec<&Worker> = run.workers.iter().filter(|w| w.kind == kind).collect();
+        if workers.is_empty() {
+            continue;
+        }
+        let ops: u64 = workers.iter().map(|w| w.ops).sum();
+        let errors: u64 = workers.iter().map(|w| w.errors).sum();
+        let mut harts: Vec<usize> = workers.iter().map(|w| w.hart).collect();
+        harts.sort_unstable();
+        harts.dedup();
+        out.push_str(&format!(
+            "  {:<4} {:>2} workers  {:>10} {:<8} {:>4} errors  harts {:?}\n",
+            kind.name(),
+            workers.len(),
+            ops,
+            kind.unit(),
+            errors,
+            harts
+        ));
+    }
+    if run.config.io > 0 {
+        let workers = run.workers.iter().filter(|w| w.kind == Kind::Io);
+        let (cycles, bytes) = workers.fold((0, 0), |(c, b), w| (c + w.ops, b + w.bytes));
+        let ms = (now - run.started_ms).max(1) as u64;
+        out.push_str(&format!(
+            "  fs   {} KiB/s, {}.{:02} cycles/s, {} transfers run by hart 0 for others\n",
+            bytes * 1000 / 1024 / ms,
+            cycles * 1000 / ms,
+            cycles * 100_000 / ms % 100,
+            crate::platform::d1_mmc::transfers_served() - run.transfers_at_start
+        ));
+    }
+    if run.config.cpu > 0 {
+        let increments: u64 = run.workers.iter().map(|w| w.increments).sum();
+        out.push_str(&format!(
+            "  smp  atomic counter {}/{}, locked counter {}/{}\n",
+            ATOMIC_COUNTER.load(Ordering::Acquire),
+            increments,
+            *LOCKED_COUNTER.lock(),
+            increments
+        ));
+    }
+    if let Some(error) = &run.first_error {
+        out.push_str(&format!("First error: {}\n", error));
+    }
+    match run.verdict {
+        None => {}
+        Some(true) => out.push_str("Result: PASS\n"),
+        Some(false) => out.push_str("Result: FAIL\n"),
+    }
+    out
+}
+
+/// Decide the outcome once the last worker has finished
+fn finish(run: &mut Run, files_left: bool) {
+    let mut passed = run.first_error.is_none();
+    if let Some(idle) = run.workers.iter().find(|w| w.ops == 0) {
+        passed = false;
+        run.first_error.get_or_insert_with(|| format!("{} worker on hart {} never ran", idle.kind.name(), idle.hart));
+    }
+    if run.config.cpu > 0 {
+        let increments: u64 = run.workers.iter().map(|w| w.increments).sum();
+        if ATOMIC_COUNTER.load(Ordering::Acquire) != increments || *LOCKED_COUNTER.lock() != increments {
+            passed = false;
+            run.first_error.get_or_insert_with(|| "Shared counters lost updates".to_string());
+        }
+    }
+    if files_left {
+        passed = false;
+        run.first_error.get_or_insert_with(|| format!("Files left over in {}", STRESS_DIR));
+    }
+    run.verdict = Some(passed);
+    let message = format!("Finished: {}", if passed { "PASS" } else { "FAIL" });
+    if passed {
+        klog_info("stress", &message);
+    } else {
+        klog_warning("stress", &message);
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// WORKERS
+// ═══════════════════════════════════════════════════════════════════════════════
+
+fn cpu_worker() {
+    tick(Kind::Cpu);
+}
+
+fn io_worker() {
+    tick(Kind::Io);
+}
+
+fn net_worker() {
+    tick(Kind::Net);
+}
+
+/// Outcome of one unit of work
+struct Work {
+    /// Value every unit must reproduce (None if not checked)
+    fingerprint: Option<u64>,
+    increments: u64,
+    /// Bytes moved to and from the filesystem
+    bytes: u64,
+}
+
+/// One scheduler tick of a worker
+fn tick(kind: Kind) {
+    let hart = crate::get_hart_id();
+    let Some(pid) = crate::CPU_TABLE.get(hart).and_then(|cpu| cpu.running_process()) else {
+        return;
+    };
+
+    let (ops, url) = {
+        let mut guard = RUN.lock();
+        let Some(run) = guard.as_mut() else {
+            crate::PROC_SCHEDULER.exit(pid, 0);
+            return;
+        };
+        let ended = run.stopped || get_time_ms() >= run.deadline_ms;
+        let Some(worker) = run.workers.iter_mut().find(|w| w.pid == pid && !w.done) else {
+            crate::PROC_SCHEDULER.exit(pid, 0);
+            return;
+        };
+        if ended {
+            worker.done = true;
+            crate::PROC_SCHEDULER.exit(pid, 0);
+            if !run.workers.iter().all(|w| w.done) {
+                return;
+            }
+            let io = run.config.io > 0;
+            // Not held while listing, which may wait for other harts'
+            // filesystem locks
+            drop(guard);
+            let files_left = io && !fs_proxy::fs_list(STRESS_DIR).is_empty();
+            if let Some(run) = RUN.lock().as_mut() {
+                finish(run, files_left);
+            }
+            return;
+        }
+        (worker.ops, if kind == Kind::Net { run.config.url.clone() } else { String::new() })
+    };
+
+    // The work itself runs without the lock
+    let result = match kind {
+        Kind::Cpu => cpu_slice(),
+        Kind::Io => io_cycle(pid, ops),
+        Kind::Net => net_fetch(&url),
+    };
+
+    let mut guard = RUN.lock();
+    let Some(run) = guard.as_mut() else {
+        return;
+    };
+    let Some(worker) = run.workers.iter_mut().find(|w| w.pid == pid) else {
+        return;
+    };
+    worker.ops += 1;
+    let error = match result {
+        Ok(work) => {
+            worker.increments += work.increments;
+            worker.bytes += work.bytes;
+            match (work.fingerprint, worker.expected) {
+                (Some(value), None) => {
+                    worker.expected = Some(value);
+                    None
+                }
+                (Some(value), Some(expected)) if value != expected => Some(match kind {
+                    Kind::Cpu => "checksum changed".to_string(),
+                    _ => "response body changed".to_string(),
+                }),
+                _ => None,
+            }
+        }
+        Err(e) => Some(e),
+    };
+    if let Some(error) = error {
+        worker.errors += 1;
+        let error = format!("{} worker on hart {}: {}", kind.name(), worker.hart, error);
+        if run.first_error.is_none() {
+            klog_warning("stress", &error);
+            run.first_error = Some(error);
+        }
+    }
+}
+
+/// Checksum of a fixed pseudo-random sequence
+fn checksum() -> u64 {
+    let mut x: u64 = 0x9E37_79B9_7F4A_7C15;
+    let mut sum: u64 = 0;
+    for _ in 0..4096 {
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        sum = sum.rotate_left(5) ^ x;
+    }
+    sum
+}
+
+/// Spin for CPU_SLICE_MS, recomputing the checksum and bumping the shared counters
+fn cpu_slice() -> Result<Work, String> {
+    let end = get_time_ms() + CPU_SLICE_MS;
+    let expected = checksum();
+    let mut increments = 0;
+    while get_time_ms() < end {
+        if core::hint::black_box(checksum()) != expected {
+            return Err("checksum differs within a slice".to_string());
+        }
+        ATOMIC_COUNTER.fetch_add(1, Ordering::AcqRel);
+        *LOCKED_COUNTER.lock() += 1;
+        increments += 1;
+    }
+    Ok(Work { fingerprint: Some(expected), increments, bytes: 0 })
+}
+
+/// Write, read back, compare and remove one file
+fn io_cycle(pid: Pid, ops: u64) -> Result<Work, String> {
+    let path = format!("{}/{}.{}", STRESS_DIR, pid, ops);
+    // Vary the size so writes cross sector boundaries differently
+    let size = 512 + (ops as usize * 1531) % (16 * 1024);
+    let data: Vec<u8> = (0..size).map(|i| (i as u64 ^ ops ^ pid as u64) as u8).collect();
+
+    fs_proxy::fs_write(&path, &data).map_err(|e| format!("write {}: {}", path, e))?;
+    let read = fs_proxy::fs_read(&path).ok_or_else(|| format!("read {}: not found", path))?;
+    let verified = read == data;
+    fs_proxy::fs_remove(&path).map_err(|e| format!("remove {}: {}", path, e))?;
+    if !verified {
+        return Err(format!("{} read back differs from what was written", path));
+    }
+    if fs_proxy::fs_exists(&path) {
+        return Err(format!("{} still exists after removal", path));
+    }
+    Ok(Work { fingerprint: None, increments: 0, bytes: 2 * size as u64 })
+}
+
+/// Fetch the URL and hash the body (FNV-1a)
+fn net_fetch(url: &str) -> Result<Work, String> {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    let download = crate::commands::http::download(url, false, NET_TIMEOUT_MS, get_time_ms, &mut |_, data| {
+        for &b in data {
+            hash = (hash ^ b as u64).wrapping_mul(0x0100_0000_01b3);
+        }
+        Ok(())
+    })
+    .map_err(|e| format!("{}: {}", url, e))?;
+    if !(200..300).contains(&download.status_code) {
+        return Err(format!("{}: HTTP {}", url, download.status_code));
+    }
+    Ok(Work { fingerprint: Some(hash), increments: 0, bytes: 0 })
+}
@@ -9,16 +9,17 @@
 //! - a0-a5 = arguments
 //! - a0 = return value (negative on error)
 
-use alloc::{format, string::String, vec};
+use alloc::{format, string::String, vec, vec::Vec};
 use core::slice;
 
 use crate::syscall_numbers::*;
+use crate::cpu::process::{FdTable, OpenFile, O_CREAT, O_TRUNC, PROCESS_TABLE, SEEK_END};
 use crate::{
     clint::get_time_ms,
     cpu::fs_proxy,
     lock::utils::BLK_DEV,
-    services::klogd::KLOG,
-    scripting, uart,
+    services::klogd::{klog_error, klog_info, klog_warning, KLOG},
+    scripting, uart, Spinlock,
 };
 
 // ═══════════════════════════════════════════════════════════════════════════════
@@ -32,34 +33,55 @@ pub struct SyscallContext {
     pub args: &'static [&'static str],
     /// Exit code (set by SYS_EXIT)
     pub exit_code: Option<i32>,
+    /// Memory mapped with SYS_MMAP and not unmapped yet
+    mappings: Vec<Mapping>,
 }
 
-/// Thread-local syscall context
-/// SAFETY: Only accessed from the hart running the binary
-static mut SYSCALL_CTX: Option<SyscallContext> = None;
+/// Syscall context of the binary running on each hart
+/// SAFETY: each slot is only accessed from its own hart
+static mut SYSCALL_CTX: [Option<SyscallContext>; crate::cpu::MAX_HARTS] = [const { None }; crate::cpu::MAX_HARTS];
+
+/// This hart's syscall context slot
+fn context_slot() -> &'static mut Option<SyscallContext> {
+    unsafe { &mut (*core::ptr::addr_of_mut!(SYSCALL_CTX))[crate::get_hart_id()] }
+}
 
 /// Initialize syscall context with arguments
 pub fn init_context(args: &'static [&'static str]) {
-    unsafe {
-        SYSCALL_CTX = Some(SyscallContext {
-            args,
-            exit_code: None,
-        });
-    }
+    *context_slot() = Some(SyscallContext {
+        args,
+        exit_code: None,
+        mappings: Vec::new(),
+    });
 }
 
 /// Get the current syscall context
 fn get_context() -> Option<&'static SyscallContext> {
-    unsafe { SYSCALL_CTX.as_ref() }
+    context_slot().as_ref()
 }
 
 /// Clear syscall context after binary exits
 pub fn clear_context() -> Option<i32> {
-    unsafe {
-        let exit_code = SYSCALL_CTX.as_ref().and_then(|c| c.exit_code);
-        SYSCALL_CTX = None;
-        exit_code
+    // Unmap what the binary left mapped, shared mappings reaching their
+    // files before those are closed
+    if let Some(ctx) = context_slot().as_mut() {
+        for mapping in core::mem::take(&mut ctx.mappings) {
+            unmap(mapping);
+        }
+    }
+    // Close anything the binary left open
+    for file in with_fd_table(|fds| fds.take_all()) {
+        release_file(&file);
     }
+    // ...and any TCP connections or WebSockets it did not close
+    crate::commands::ws::close_owned(crate::get_hart_id());
+    if let Some(net) = crate::NET_STATE.lock().as_mut() {
+        net.tcp_close_owned(crate::get_hart_id(), get_time_ms());
+    }
+    // Don't leave a trailing partial line sitting in this hart's buffer
+    uart::flush();
+
+    context_slot().take().and_then(|c| c.exit_code)
 }
 
 // ═══════════════════════════════════════════════════════════════════════════════
@@ -84,9 +106,9 @@ pub fn handle_syscall(
     a2: u64,
     a3: u64,
     a4: u64,
-    _a5: u64,
+    a5: u64,
 ) -> i64 {
-    match syscall_num {
+    let result = match syscall_num {
         // Core
         SYS_PRINT => sys_print(a0 as *const u8, a1 as usize),
         SYS_TIME => sys_time(),
@@ -108,16 +130,17 @@ pub fn handle_syscall(
         SYS_FS_REMOVE => sys_fs_remove(a0 as *const u8, a1 as usize),
         SYS_FS_MKDIR => sys_fs_mkdir(a0 as *const u8, a1 as usize),
         SYS_FS_IS_DIR => sys_fs_is_dir(a0 as *const u8, a1 as usize),
+        SYS_FS_SYNC => sys_fs_sync(),
 
         // Network
         SYS_NET_AVAILABLE => sys_net_available(),
         SYS_DNS_RESOLVE => sys_dns_resolve(a0 as *const u8, a1 as usize, a2 as *mut u8, a3 as usize),
         SYS_SEND_PING => sys_send_ping(a0 as *const u8, a1 as i32, a2 as i32, a3 as *mut u8),
         SYS_TCP_CONNECT => sys_tcp_connect(a0 as *const u8, a1 as u16),
-        SYS_TCP_SEND => sys_tcp_send(a0 as *const u8, a1 as usize),
-        SYS_TCP_RECV => sys_tcp_recv(a0 as *mut u8, a1 as usize),
-        SYS_TCP_CLOSE => sys_tcp_close(),
-        SYS_TCP_STATUS => sys_tcp_status(),
+        SYS_TCP_SEND => sys_tcp_send(a0, a1 as *const u8, a2 as usize),
+        SYS_TCP_RECV => sys_tcp_recv(a0, a1 as *mut u8, a2 as usize),
+        SYS_TCP_CLOSE => sys_tcp_close(a0),
+        SYS_TCP_STATUS => sys_tcp_status(a0),
         SYS_HTTP_GET => sys_http_get(a0 as *const u8, a1 as usize, a2 as *mut u8, a3 as usize),
 
         // Console
@@ -126,7 +149,7 @@ pub fn handle_syscall(
 
         // Process
         SYS_PS_LIST => sys_ps_list(a0 as *mut u8, a1 as usize),
-        SYS_KILL => sys_kill(a0 as u32),
+        SYS_KILL => sys_kill(a0 as i64, a1 as u32),
         SYS_CPU_INFO => sys_cpu_info(a0 as i32, a1 as *mut u8),
 
         // System
@@ -147,9 +170,197 @@ pub fn handle_syscall(
         SYS_HEAP_STATS => sys_heap_stats(a0 as *mut u8),
         SYS_SLEEP => sys_sleep(a0 as u64),
 
+        // File descriptors
+        SYS_OPEN => sys_open(a0 as *const u8, a1 as usize, a2 as u32),
+        SYS_READ => sys_read(a0 as i32, a1 as *mut u8, a2 as usize),
+        SYS_WRITE => sys_write(a0 as i32, a1 as *const u8, a2 as usize),
+        SYS_SEEK => sys_seek(a0 as i32, a1 as i64, a2 as u32),
+        SYS_CLOSE => sys_close(a0 as i32),
+
+        // Symbolic links
+        SYS_SYMLINK => sys_symlink(a0 as *const u8, a1 as usize, a2 as *const u8, a3 as usize),
+        SYS_READLINK => sys_readlink(a0 as *const u8, a1 as usize, a2 as *mut u8, a3 as usize),
+
+        // Rename
+        SYS_RENAME => sys_rename(a0 as *const u8, a1 as usize, a2 as *const u8, a3 as usize),
+
+        // Partial writes
+        SYS_FS_APPEND => sys_fs_append(a0 as *const u8, a1 as usize, a2 as *const u8, a3 as usize),
+        SYS_FS_PWRITE => sys_fs_pwrite(a0 as *const u8, a1 as usize, a2 as *const u8, a3 as usize, a4 as usize),
+
+        // Kernel modules
+        SYS_KMOD_LOAD => sys_kmod_load(a0 as *const u8, a1 as usize),
+        SYS_KMOD_UNLOAD => sys_kmod_unload(a0 as *const u8, a1 as usize),
+        SYS_KMOD_LIST => sys_kmod_list(a0 as *mut u8, a1 as usize),
+
+        // Boot profiling
+        SYS_BOOTCHART => sys_bootchart(a0 as *mut u8, a1 as usize),
+
+        // Hibernation
+        SYS_HIBERNATE => sys_hibernate(),
+
+        // QR codes
+        SYS_QRCODE => sys_qrcode(a0 as *const u8, a1 as usize, a2 as *mut u8, a3 as usize),
+
+        // Tones
+        SYS_BEEP => sys_beep(a0 as u32, a1 as u32, a2),
+        SYS_EVENT_SOUNDS => sys_event_sounds(a0),
+
+        // Mounts
+        SYS_MOUNT_9P => sys_mount_9p(a0 as *const u8, a1 as u16, a2 as *const u8, a3 as usize),
+        SYS_UMOUNT => sys_umount(a0 as *const u8, a1 as usize),
+        SYS_MOUNTS => sys_mounts(a0 as *mut u8, a1 as usize),
+
+        // Display
+        SYS_DISPLAY_ADJUST => sys_display_adjust(a0 as u16, a1 as u16, a2 as u16),
+
+        // Settings
+        SYS_SETTINGS_GET => sys_settings_get(a0 as *mut u8, a1 as usize),
+        SYS_SETTINGS_SET => sys_settings_set(a0 as *const u8, a1 as usize, a2 as *const u8, a3 as usize),
+
+        // IPv6
+        SYS_NET_INFO6 => sys_net_info6(a0 as *mut u8, a1 as usize),
+        SYS_PING6 => sys_ping6(a0 as *const u8, a1 as i32, a2 as i32, a3 as *mut u8),
+        SYS_DNS_RESOLVE6 => sys_dns_resolve6(a0 as *const u8, a1 as usize, a2 as *mut u8, a3 as usize),
+
+        // DNS
+        SYS_DNS_FLUSH => sys_dns_flush(),
+
+        // TCP listeners
+        SYS_TCP_LISTEN => sys_tcp_listen(a0 as u16),
+        SYS_TCP_ACCEPT => sys_tcp_accept(a0, a1 as *mut u8),
+
+        // File watches
+        SYS_WATCH => sys_watch(a0 as *const u8, a1 as usize, a2 as u8),
+        SYS_WATCH_WAIT => sys_watch_wait(a0 as i32, a1),
+
+        // HTTP options
+        SYS_HTTP_GET_EX => sys_http_get_ex(a0 as *const u8, a1 as usize, a2 as *mut u8, a3 as usize, a4),
+
+        // Heap leak detector
+        SYS_HEAPDIFF => sys_heapdiff(a0, a1 as *mut u8, a2 as usize),
+
+        // WASM pipelines
+        SYS_WASM_PIPE => sys_wasm_pipe(a0 as *const u8, a1 as usize),
+
+        // Input record/replay
+        SYS_INPUT_REPLAY => sys_input_replay(a0, a1 as *const u8, a2 as usize, a3),
+
+        // HTTP downloads
+        SYS_HTTP_DOWNLOAD => sys_http_download(a0 as *const u8, a1 as usize, a2 as *const u8, a3 as usize, a4),
+
+        // Shared kernel log
+        SYS_KLOG_MAP => sys_klog_map(),
+
+        // WebSockets
+        SYS_WS_CONNECT => sys_ws_connect(a0 as *const u8, a1 as usize, a2),
+        SYS_WS_SEND => sys_ws_send(a0, a1 as *const u8, a2 as usize, a3),
+        SYS_WS_RECV => sys_ws_recv(a0, a1 as *mut u8, a2 as usize, a3),
+        SYS_WS_CLOSE => sys_ws_close(a0),
+
+        // Stress test
+        SYS_STRESS => sys_stress(a0, a1, a2, a3, a4),
+
+        // Neighbor table
+        SYS_NET_NEIGHBORS => sys_net_neighbors(a0 as *mut u8, a1 as usize),
+
+        // Firewall
+        SYS_FIREWALL => sys_firewall(a0, a1 as *mut u8, a2 as usize),
+
+        // Filesystem format
+        SYS_SFS_FORMAT => sys_sfs_format(a0, a1 as *mut u8, a2 as usize),
+
+        // Clipboard
+        SYS_CLIPBOARD => sys_clipboard(a0, a1 as *mut u8, a2 as usize),
+
+        // Traceroute
+        SYS_TRACE_PROBE => sys_trace_probe(a0 as *const u8, a1, a2 as u16, a3 as i64, a4 as *mut u8),
+
+        // Socket statistics
+        SYS_NET_STATS => sys_net_stats(a0 as *mut u8, a1 as usize),
+
+        // DNS records
+        SYS_DNS_QUERY => sys_dns_query(a0 as *const u8, a1 as usize, a2 as u16, a3 as *mut u8, a4 as usize),
+
+        // Process priority
+        SYS_GET_NICE => sys_get_nice(a0 as u32),
+        SYS_SET_NICE => sys_set_nice(a0 as u32, a1 as i64),
+
+        // CPU affinity
+        SYS_GET_AFFINITY => sys_get_affinity(a0 as u32, a1 as *mut u8),
+        SYS_SET_AFFINITY => sys_set_affinity(a0 as u32, a1 as *const u8),
+
+        // Exit status
+        SYS_WAIT => sys_waitpid(0, a0 as *mut u8, 0),
+        SYS_WAITPID => sys_waitpid(a0 as u32, a1 as *mut u8, a2 as u32),
+
+        // Spawn
+        SYS_SPAWN => sys_spawn(a0 as *const u8, a1 as usize, a2 as *const u8, a3 as usize),
+
+        // Signals
+        SYS_SIGNAL => sys_signal(a0 as u32, a1 as usize, a2 as usize),
+        SYS_SIGRETURN => sys_sigreturn(),
+
+        // Message queues
+        SYS_MQ_OPEN => sys_mq_open(a0 as *const u8, a1 as usize, a2 as usize),
+        SYS_MQ_SEND => sys_mq_send(a0 as u32, a1 as *const u8, a2 as usize, a3),
+        SYS_MQ_RECV => sys_mq_recv(a0 as u32, a1 as *mut u8, a2 as usize, a3),
+        SYS_MQ_UNLINK => sys_mq_unlink(a0 as *const u8, a1 as usize),
+
+        // Memory limits
+        SYS_GET_MEM_LIMIT => sys_get_mem_limit(a0 as u32, a1 as *mut u8),
+        SYS_SET_MEM_LIMIT => sys_set_mem_limit(a0 as u32, a1),
+
+        // CPU statistics
+        SYS_PROC_STATS => sys_proc_stats(a0 as *mut u8, a1 as usize),
+
+        // Process groups
+        SYS_SETPGID => sys_setpgid(a0 as u32, a1 as u32),
+        SYS_GETPGID => sys_getpgid(a0 as u32),
+
+        // Polling
+        SYS_POLL => sys_poll(a0 as *mut u8, a1 as usize, a2),
+
+        // Memory mappings
+        SYS_MMAP => sys_mmap(a0, a1 as usize, a2, a3, a4 as i32, a5),
+        SYS_MUNMAP => sys_munmap(a0, a1 as usize),
+
+        // Randomness
+        SYS_GETRANDOM => sys_getrandom(a0 as *mut u8, a1 as usize, a2),
+
+        // Clocks
+        SYS_CLOCK_GETTIME => sys_clock_gettime(a0),
+
+        // Heap accounting
+        SYS_MEMSTAT => sys_memstat(a0 as *mut u8, a1 as usize),
+
         // Unknown syscall
         _ => -1, // ENOSYS
-    }
+    };
+    account_syscall(syscall_num, a0, result);
+    crate::syscall_trace::record(syscall_num, &[a0, a1, a2, a3, a4, a5], result);
+    // A prompt printed without a newline must not wait for the next line
+    uart::flush();
+    result
+}
+
+/// Charge a finished syscall, and the bytes it moved, to the calling program
+fn account_syscall(syscall_num: u64, a0: u64, result: i64) {
+    use crate::cpu::process::{charge_syscall, IoKind};
+
+    let fd = a0 as i32;
+    let kind = match syscall_num {
+        SYS_FS_READ => Some(IoKind::FsRead),
+        SYS_READ if fd != 0 => Some(IoKind::FsRead),
+        SYS_FS_WRITE | SYS_FS_APPEND | SYS_FS_PWRITE => Some(IoKind::FsWrite),
+        SYS_WRITE if fd != 1 && fd != 2 => Some(IoKind::FsWrite),
+        SYS_TCP_RECV | SYS_HTTP_GET | SYS_HTTP_GET_EX | SYS_HTTP_DOWNLOAD | SYS_WS_RECV => Some(IoKind::NetRx),
+        SYS_TCP_SEND | SYS_WS_SEND => Some(IoKind::NetTx),
+        _ => None,
+    };
+    // ws_recv packs the message kind above the byte count
+    let bytes = if syscall_num == SYS_WS_RECV { result.max(0) & 0xFFFF_FFFF } else { result.max(0) };
+    charge_syscall(kind, bytes as u64);
 }
 
 // ═══════════════════════════════════════════════════════════════════════════════
@@ -159,7 +370,7 @@ pub fn handle_syscall(
 /// Read a string from userspace memory
 /// SAFETY: Caller must ensure ptr is valid and len is correct
 unsafe fn read_str(ptr: *const u8, len: usize) -> Option<&'static str> {
-    if ptr.is_null() || len == 0 {
+    if ptr.is_null() || len == 0 || !crate::elf_loader::user_accessible(ptr as usize, len, false) {
         return None;
     }
     let bytes = slice::from_raw_parts(ptr, len);
@@ -169,10 +380,10 @@ unsafe fn read_str(ptr: *const u8, len: usize) -> Option<&'static str> {
 /// Write bytes to userspace memory
 /// SAFETY: Caller must ensure ptr is valid and has enough capacity
 unsafe fn write_bytes(ptr: *mut u8, data: &[u8], max_len: usize) -> i64 {
-    if ptr.is_null() {
+    let to_copy = data.len().min(max_len);
+    if ptr.is_null() || !crate::elf_loader::user_accessible(ptr as usize, to_copy, true) {
         return -1;
     }
-    let to_copy = data.len().min(max_len);
     core::ptr::copy_nonoverlapping(data.as_ptr(), ptr, to_copy);
     to_copy as i64
 }
@@ -260,6 +471,7 @@ fn sys_cwd_set(path_ptr: *const u8, path_len: usize) -> i64 {
 fn sys_fs_exists(path_ptr: *const u8, path_len: usize) -> i64 {
     unsafe {
         if let Some(path) = read_str(path_ptr, path_len) {
+            let path = &crate::resolve_path(path);
             return if fs_proxy::fs_exists(path) { 1 } else { 0 };
         }
     }
@@ -269,6 +481,7 @@ fn sys_fs_exists(path_ptr: *const u8, path_len: usize) -> i64 {
 fn sys_fs_read(path_ptr: *const u8, path_len: usize, buf_ptr: *mut u8, buf_len: usize) -> i64 {
     unsafe {
         if let Some(path) = read_str(path_ptr, path_len) {
+            let path = &crate::resolve_path(path);
             if let Some(data) = fs_proxy::fs_read(path) {
                 return write_bytes(buf_ptr, &data, buf_len);
             }
@@ -282,6 +495,7 @@ fn sys_fs_write(path_ptr: *const u8, path_len: usize, data_ptr: *const u8, data_
     
     unsafe {
         if let Some(path) = read_str(path_ptr, path_len) {
+            let path = &crate::resolve_path(path);
             write_str("fs_write syscall: ");
             write_str(path);
             write_str(" (");
@@ -325,6 +539,7 @@ fn sys_fs_list(buf_ptr: *mut u8, buf_len: usize) -> i64 {
 fn sys_fs_list_dir(path_ptr: *const u8, path_len: usize, buf_ptr: *mut u8, buf_len: usize) -> i64 {
     unsafe {
         if let Some(path) = read_str(path_ptr, path_len) {
+            let path = &crate::resolve_path(path);
             let files = fs_proxy::fs_list(path);
             let mut output = String::new();
             for file in files {
@@ -342,9 +557,10 @@ fn sys_fs_list_dir(path_ptr: *const u8, path_len: usize, buf_ptr: *mut u8, buf_l
 fn sys_fs_stat(path_ptr: *const u8, path_len: usize, out_ptr: *mut u8) -> i64 {
     unsafe {
         if let Some(path) = read_str(path_ptr, path_len) {
-            let mut fs_guard = crate::FS_STATE.write();
-            let mut blk_guard = BLK_DEV.write();
-            if let (Some(fs), Some(dev)) = (fs_guard.as_mut(), blk_guard.as_mut()) {
+            let path = &crate::resolve_path(path);
+            let fs_guard = crate::FS_STATE.read();
+            let blk_guard = BLK_DEV.read();
+            if let (Some(fs), Some(dev)) = (fs_guard.as_ref(), blk_guard.as_ref()) {
                 let file_data = fs.read_file(dev, path);
                 let (size, exists, is_dir): (u32, u8, u8) = match file_data {
                     Some(data) => (data.len() as u32, 1, 0),
@@ -377,12 +593,19 @@ fn sys_fs_remove(_path_ptr: *const u8, _path_len: usize) -> i64 {
     -1
 }
 
+fn sys_fs_sync() -> i64 {
+    match fs_proxy::fs_sync() {
+        Ok(()) => 0,
+        Err(_) => -1,
+    }
+}
+
 fn sys_fs_mkdir(path_ptr: *const u8, path_len: usize) -> i64 {
     unsafe {
         if let Some(path) = read_str(path_ptr, path_len) {
-            let mut fs_guard = crate::FS_STATE.write();
-            let mut blk_guard = BLK_DEV.write();
-            if let (Some(fs), Some(dev)) = (fs_guard.as_mut(), blk_guard.as_mut()) {
+            let fs_guard = crate::FS_STATE.read();
+            let blk_guard = BLK_DEV.read();
+            if let (Some(fs), Some(dev)) = (fs_guard.as_ref(), blk_guard.as_ref()) {
                 let keep_path = format!("{}/.keep", path.trim_end_matches('/'));
                 if fs.write_file(dev, &keep_path, &[]).is_ok() {
                     return 0;
@@ -502,11 +725,17 @@ fn sys_send_ping(ip_ptr: *const u8, seq: i32, timeout_ms: i32, out_ptr: *mut u8)
                 }
             }
             
-            core::hint::spin_loop();
+            crate::net::wait_for_frame(deadline);
         }
     }
 }
 
+/// The socket `sock` if it was opened by the program running on this hart
+fn user_socket(net: &crate::net::NetState, sock: u64) -> Option<crate::net::TcpSocketId> {
+    let id = u8::try_from(sock).ok()?;
+    net.tcp_is_owned_by(id, crate::get_hart_id()).then_some(id)
+}
+
 fn sys_tcp_connect(ip_ptr: *const u8, port: u16) -> i64 {
     unsafe {
         if ip_ptr.is_null() {
@@ -518,16 +747,17 @@ fn sys_tcp_connect(ip_ptr: *const u8, port: u16) -> i64 {
         if let Some(ref mut net) = *net_guard {
             let ip = smoltcp::wire::Ipv4Address::new(ip_bytes[0], ip_bytes[1], ip_bytes[2], ip_bytes[3]);
             let now = get_time_ms();
-            if net.tcp_connect(ip, port, now).is_ok() {
+            if let Ok(sock) = net.tcp_connect(ip, port, now) {
+                net.tcp_set_owner(sock, crate::get_hart_id());
                 net.poll(now);
-                return 0;
+                return sock as i64;
             }
         }
     }
     -1
 }
 
-fn sys_tcp_send(data_ptr: *const u8, data_len: usize) -> i64 {
+fn sys_tcp_send(sock: u64, data_ptr: *const u8, data_len: usize) -> i64 {
     unsafe {
         if data_ptr.is_null() {
             return -1;
@@ -536,8 +766,11 @@ fn sys_tcp_send(data_ptr: *const u8, data_len: usize) -> i64 {
         
         let mut net_guard = crate::NET_STATE.lock();
         if let Some(ref mut net) = *net_guard {
+            let Some(sock) = user_socket(net, sock) else {
+                return -1;
+            };
             let now = get_time_ms();
-            if let Ok(sent) = net.tcp_send(data, now) {
+            if let Ok(sent) = net.tcp_send(sock, data, now) {
                 net.poll(now);
                 return sent as i64;
             }
@@ -546,7 +779,7 @@ fn sys_tcp_send(data_ptr: *const u8, data_len: usize) -> i64 {
     -1
 }
 
-fn sys_tcp_recv(buf_ptr: *mut u8, buf_len: usize) -> i64 {
+fn sys_tcp_recv(sock: u64, buf_ptr: *mut u8, buf_len: usize) -> i64 {
     unsafe {
         if buf_ptr.is_null() {
             return -1;
@@ -554,10 +787,13 @@ fn sys_tcp_recv(buf_ptr: *mut u8, buf_len: usize) -> i64 {
         
         let mut net_guard = crate::NET_STATE.lock();
         if let Some(ref mut net) = *net_guard {
+            let Some(sock) = user_socket(net, sock) else {
+                return -1;
+            };
             let now = get_time_ms();
             net.poll(now);
             let mut temp_buf = vec![0u8; buf_len];
-            if let Ok(received) = net.tcp_recv(&mut temp_buf, now) {
+            if let Ok(received) = net.tcp_recv(sock, &mut temp_buf, now) {
                 if received > 0 {
                     core::ptr::copy_nonoverlapping(temp_buf.as_ptr(), buf_ptr, received);
                 }
@@ -568,23 +804,33 @@ fn sys_tcp_recv(buf_ptr: *mut u8, buf_len: usize) -> i64 {
     -1
 }
 
-fn sys_tcp_close() -> i64 {
+fn sys_tcp_close(sock: u64) -> i64 {
     let mut net_guard = crate::NET_STATE.lock();
     if let Some(ref mut net) = *net_guard {
-        let now = get_time_ms();
-        net.tcp_close(now);
-        return 0;
+        if let Some(sock) = user_socket(net, sock) {
+            net.tcp_disconnect(sock, get_time_ms());
+            return 0;
+        }
+        // Listener handles follow the socket IDs
+        if let Ok(id) = u8::try_from(sock) {
+            if net.tcp_listener_close(id, crate::get_hart_id()) {
+                return 0;
+            }
+        }
     }
     -1
 }
 
-fn sys_tcp_status() -> i64 {
+fn sys_tcp_status(sock: u64) -> i64 {
     let mut net_guard = crate::NET_STATE.lock();
     if let Some(ref mut net) = *net_guard {
+        let Some(sock) = user_socket(net, sock) else {
+            return 0; // closed
+        };
         // 0=closed, 1=connecting, 2=connected, 3=failed
-        if net.tcp_is_connected() {
+        if net.tcp_is_connected(sock) {
             return 2;
-        } else if net.tcp_connection_failed() {
+        } else if net.tcp_connection_failed(sock) {
             return 3;
         }
         return 1; // connecting
@@ -593,20 +839,7 @@ fn sys_tcp_status() -> i64 {
 }
 
 fn sys_http_get(url_ptr: *const u8, url_len: usize, resp_ptr: *mut u8, resp_len: usize) -> i64 {
-    unsafe {
-        if let Some(url) = read_str(url_ptr, url_len) {
-            let mut net_guard = crate::NET_STATE.lock();
-            if let Some(ref mut net) = *net_guard {
-                match crate::commands::http::get_follow_redirects(net, url, 30000, get_time_ms) {
-                    Ok(response) => {
-                        return write_bytes(resp_ptr, &response.body, resp_len);
-                    }
-                    Err(_) => return -1,
-                }
-            }
-        }
-    }
-    -1
+    sys_http_get_ex(url_ptr, url_len, resp_ptr, resp_len, 0)
 }
 
 // ═══════════════════════════════════════════════════════════════════════════════
@@ -619,6 +852,14 @@ fn sys_console_available() -> i64 {
 
 fn sys_console_read(buf_ptr: *mut u8, _buf_len: usize) -> i64 {
     if let Some(ch) = uart::read_char_nonblocking() {
+        // Ctrl+C interrupts the binary rather than being read, and Ctrl+Z
+        // stops a foreground job
+        if ch == 0x03 && crate::elf_loader::console_interrupt() {
+            return 0;
+        }
+        if ch == 0x1a && crate::elf_loader::console_suspend() {
+            return 0;
+        }
         unsafe {
             if !buf_ptr.is_null() {
                 *buf_ptr = ch;
@@ -641,42 +882,83 @@ fn sys_ps_list(buf_ptr: *mut u8, buf_len: usize) -> i64 {
     
     // Get processes from scheduler
     for proc in SCHEDULER.list_processes() {
-        let is_running = proc.state == ProcessState::Running;
+        let state = match proc.state {
+            ProcessState::Zombie => "Z",
+            _ if proc.stopped => "T",
+            ProcessState::Running => "R",
+            _ => "S",
+        };
+        let io = &proc.io;
+        let (heap_bytes, _) = crate::allocator::process_heap(proc.pid);
         output.push_str(&format!(
-            "{}:{}:{}:{}:{}:{}\n",
+            "{}:{}:{}:{}:{}:{}:{}:{}:{}:{}:{}:{}:{}:{}:{}\n",
             proc.pid,
             proc.name,
-            if is_running { "R" } else { "S" },
+            state,
             proc.priority as u8,
             proc.cpu_time_ms,
-            proc.uptime_ms
+            proc.uptime_ms,
+            proc.cpu_usage,
+            io.fs_read_bytes,
+            io.fs_write_bytes,
+            io.net_rx_bytes,
+            io.net_tx_bytes,
+            io.syscalls,
+            heap_bytes,
+            proc.ppid,
+            proc.pgid
         ));
     }
     
     // Also include shell command if running
     if let Some((name, pid, cpu, uptime, running)) = crate::wasm::get_shell_cmd_info() {
+        // Foreground commands are not sampled, so their usage reads 0
+        let io = scripting::shell_cmd_syscall_io();
+        let (heap_bytes, _) = crate::allocator::process_heap(pid);
         output.push_str(&format!(
-            "{}:{}:{}:0:{}:{}\n",
+            "{}:{}:{}:0:{}:{}:0:{}:{}:{}:{}:{}:{}:0:{}\n",
             pid,
             name,
             if running { "R" } else { "S" },
             uptime,
-            cpu
+            cpu,
+            io.fs_read_bytes,
+            io.fs_write_bytes,
+            io.net_rx_bytes,
+            io.net_tx_bytes,
+            io.syscalls,
+            heap_bytes,
+            pid
         ));
     }
     
     unsafe { write_bytes(buf_ptr, output.as_bytes(), buf_len) }
 }
 
-fn sys_kill(pid: u32) -> i64 {
-    use crate::cpu::sched::SCHEDULER;
-    
+fn sys_kill(pid: i64, sig: u32) -> i64 {
+    use crate::cpu::signal;
+
     if pid == 0 {
         return -2; // Cannot kill init
     }
+    let sig = if sig == 0 { signal::SIGKILL } else { sig };
+    if !signal::is_valid(sig) {
+        return -3;
+    }
     
-    SCHEDULER.exit(pid, 9);
-    0
+    // A negative PID names a process group
+    let Ok(target) = u32::try_from(pid.unsigned_abs()) else {
+        return -1;
+    };
+    let result = if pid < 0 {
+        signal::send_group(target, sig).map(|_| ())
+    } else {
+        signal::send(target, sig)
+    };
+    match result {
+        Ok(()) => 0,
+        Err(_) => -1,
+    }
 }
 
 fn sys_cpu_info(cpu_id: i32, out_ptr: *mut u8) -> i64 {
@@ -710,10 +992,16 @@ fn sys_cpu_info(cpu_id: i32, out_ptr: *mut u8) -> i64 {
 fn sys_shutdown() -> i64 {
     uart::write_line("");
     uart::write_line("\x1b[1;31m+===================================================================+\x1b[0m");
-    uart::write_line("\x1b[1;31m|\x1b[0m                    \x1b[1;97mSystem Shutdown Initiated\x1b[0m                       \x1b[1;31m|\x1b[0m");
+    uart::write_line(&format!(
+        "\x1b[1;31m|\x1b[0m\x1b[1;97m{}\x1b[0m\x1b[1;31m|\x1b[0m",
+        crate::locale::center(&crate::locale::msg("shutdown.title"), 67)
+    ));
     uart::write_line("\x1b[1;31m+===================================================================+\x1b[0m");
     uart::write_line("");
     
+    // Flush the write-back cache so nothing is lost at power-off
+    let _ = fs_proxy::fs_sync();
+    
     unsafe {
         core::ptr::write_volatile(crate::constants::TEST_FINISHER as *mut u32, 0x5555);
     }
@@ -724,51 +1012,53 @@ fn sys_shutdown() -> i64 {
 }
 
 fn sys_should_cancel() -> i64 {
+    // A binary polling for cancellation takes Ctrl+C (SIGINT) here
+    let interrupted = crate::elf_loader::take_interrupt();
+    if interrupted || cancel_requested() { 1 } else { 0 }
+}
+
+/// Whether a blocking syscall should give up: the command was cancelled or
+/// the binary has a signal to act on
+fn interrupted() -> bool {
+    cancel_requested() || crate::elf_loader::signal_pending()
+}
+
+/// Whether the terminal asked the running command to stop (clears the request)
+fn cancel_requested() -> bool {
     // Check shared cancellation flag from SharedArrayBuffer (WASM path)
     let shared_cancel = unsafe {
         core::ptr::read_volatile((0x0250_2000 + 0x130) as *const u32)
     };
     if shared_cancel != 0 {
-        return 1;
+        return true;
     }
     
     // Check kernel-side cancellation flag
-    if crate::ui::main_screen::should_cancel() {
-        return 1;
-    }
-    
-    0
+    crate::ui::main_screen::should_cancel()
 }
 
 fn sys_random(buf_ptr: *mut u8, buf_len: usize) -> i64 {
-    // Simple PRNG based on time
-    let mut seed = get_time_ms() as u64;
-    let mut random_bytes = vec![0u8; buf_len];
-    for byte in random_bytes.iter_mut() {
-        seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
-        *byte = (seed >> 16) as u8;
-    }
-    unsafe { write_bytes(buf_ptr, &random_bytes, buf_len) }
+    sys_getrandom(buf_ptr, buf_len, 0)
+}
+
+/// Environment variables a program sees (`env_get` and its envp)
+pub fn environment() -> Vec<(&'static str, String)> {
+    alloc::vec![
+        ("HOME", String::from("/home")),
+        ("PATH", String::from("/usr/bin")),
+        ("USER", String::from("root")),
+        ("SHELL", String::from("/usr/bin/sh")),
+        ("TERM", String::from("xterm-256color")),
+        ("LANG", crate::settings::lang()),
+        ("PWD", crate::utils::cwd_get()),
+    ]
 }
 
 fn sys_env_get(key_ptr: *const u8, key_len: usize, val_ptr: *mut u8, val_len: usize) -> i64 {
     unsafe {
         if let Some(key) = read_str(key_ptr, key_len) {
-            let value = match key {
-                "HOME" => Some("/home"),
-                "PATH" => Some("/usr/bin"),
-                "USER" => Some("root"),
-                "SHELL" => Some("/usr/bin/sh"),
-                "TERM" => Some("xterm-256color"),
-                "PWD" => {
-                    let cwd = crate::utils::cwd_get();
-                    return write_bytes(val_ptr, cwd.as_bytes(), val_len);
-                }
-                _ => None,
-            };
-            
-            if let Some(val) = value {
-                return write_bytes(val_ptr, val.as_bytes(), val_len);
+            if let Some((_, value)) = environment().into_iter().find(|(name, _)| *name == key) {
+                return write_bytes(val_ptr, value.as_bytes(), val_len);
             }
         }
     }
@@ -786,6 +1076,10 @@ fn sys_klog_get(count: usize, buf_ptr: *mut u8, buf_len: usize) -> i64 {
     unsafe { write_bytes(buf_ptr, output.as_bytes(), buf_len) }
 }
 
+fn sys_klog_map() -> i64 {
+    crate::klog_page::address() as i64
+}
+
 // ═══════════════════════════════════════════════════════════════════════════════
 // Service Syscalls
 // ═══════════════════════════════════════════════════════════════════════════════
@@ -854,18 +1148,28 @@ fn sys_net_info(out_ptr: *mut u8, out_len: usize) -> i64 {
     }
     
     let my_ip = get_my_ip();
+    let mac = crate::NET_STATE.lock().as_ref().map(|net| net.mac()).unwrap_or_default();
     
-    let mut buf = [0u8; 19];
+    let mut buf = [0u8; 83];
     // IP (4 bytes)
     buf[0..4].copy_from_slice(&my_ip.octets());
-    // MAC (6 bytes) - use a default/fake MAC for now
-    buf[4..10].copy_from_slice(&[0x52, 0x54, 0x00, 0x12, 0x34, 0x56]);
+    // MAC (6 bytes)
+    buf[4..10].copy_from_slice(&mac);
     // Gateway (4 bytes)
     buf[10..14].copy_from_slice(&GATEWAY.octets());
     // DNS (4 bytes)
     buf[14..18].copy_from_slice(&DNS_SERVER.octets());
     // Prefix length (1 byte)
     buf[18] = PREFIX_LEN;
+    // Interface counters (8 x u64 LE)
+    let stats = crate::device::network::interface_stats();
+    let counters = [
+        stats.rx_packets, stats.rx_bytes, stats.rx_dropped, stats.rx_errors,
+        stats.tx_packets, stats.tx_bytes, stats.tx_dropped, stats.tx_errors,
+    ];
+    for (i, counter) in counters.iter().enumerate() {
+        buf[19 + i * 8..27 + i * 8].copy_from_slice(&counter.to_le_bytes());
+    }
     
     unsafe { write_bytes(out_ptr, &buf, out_len) }
 }
@@ -887,16 +1191,1826 @@ fn sys_heap_stats(out_ptr: *mut u8) -> i64 {
 }
 
 /// Sleep for the given number of milliseconds
+/// How often a sleeping binary checks for signals (ms)
+const SIGNAL_CHECK_MS: i64 = 10;
+
 fn sys_sleep(ms: u64) -> i64 {
     let start = get_time_ms();
     let target = start + ms as i64;
     
-    // Busy-wait loop with WFI for power efficiency
-    while get_time_ms() < target {
-        // Hint to the processor we're waiting
-        unsafe { core::arch::asm!("wfi", options(nomem, nostack)); }
+    // Sleeps in wfi between timer ticks (see lock::wait); a signal cuts the
+    // sleep short
+    while get_time_ms() < target && !crate::elf_loader::signal_pending() {
+        crate::lock::wait::sleep_until(target.min(get_time_ms() + SIGNAL_CHECK_MS));
     }
+    scripting::shell_cmd_sleep((get_time_ms() - start) as u64);
     
     0
 }
 
+// ═══════════════════════════════════════════════════════════════════════════════
+// File Descriptor Syscalls
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Descriptors used when no process is running on this hart (e.g. boot scripts)
+static KERNEL_FDS: Spinlock<FdTable> = Spinlock::new(FdTable::new());
+
+/// Run `f` on the fd table of the process running on this hart
+fn with_fd_table<R>(f: impl FnOnce(&mut FdTable) -> R) -> R {
+    let process = crate::cpu::CPU_TABLE
+        .get(crate::get_hart_id())
+        .and_then(|cpu| cpu.running_process())
+        .and_then(|pid| PROCESS_TABLE.get(pid));
+
+    match process {
+        Some(process) => f(&mut process.fds.lock()),
+        None => f(&mut KERNEL_FDS.lock()),
+    }
+}
+
+/// Release what a descriptor holds: the watch of a watch fd or the pipe of a
+/// pipe fd. Writes to regular files already went to the filesystem.
+fn release_file(file: &OpenFile) {
+    if let Some(id) = file.watch {
+        crate::fs::watch::remove(id);
+    }
+    if let Some(pipe) = &file.pipe {
+        crate::cpu::ipc::IPC.remove_pipe(pipe.id);
+    }
+}
+
+/// A copy of descriptor `fd` if it is a regular file, so the fd table is
+/// not held during disk I/O
+fn regular_file(fd: i32) -> Option<OpenFile> {
+    with_fd_table(|fds| match fds.get_mut(fd) {
+        Some(file) if file.pipe.is_none() && file.watch.is_none() => Some(file.clone()),
+        _ => None,
+    })
+}
+
+/// Move the offset of `fd` after a read or write (if it is still open)
+fn set_file_pos(fd: i32, pos: usize) {
+    with_fd_table(|fds| {
+        if let Some(file) = fds.get_mut(fd) {
+            file.pos = pos;
+        }
+    });
+}
+
+fn sys_open(path_ptr: *const u8, path_len: usize, flags: u32) -> i64 {
+    let path = match unsafe { read_str(path_ptr, path_len) } {
+        Some(p) => p,
+        None => return -1,
+    };
+    let path = &crate::resolve_path(path);
+    let file = OpenFile::new(path, flags);
+
+    match fs_proxy::fs_len(path) {
+        Some(len) if flags & O_TRUNC != 0 && file.writable() && len > 0 => {
+            if fs_proxy::fs_write(path, &[]).is_err() {
+                return -1;
+            }
+        }
+        Some(_) => {}
+        None if flags & O_CREAT != 0 => {
+            if fs_proxy::fs_write(path, &[]).is_err() {
+                return -1;
+            }
+        }
+        None => return -1,
+    }
+
+    match with_fd_table(|fds| fds.insert(file)) {
+        Ok(fd) => fd as i64,
+        Err(_) => -1,
+    }
+}
+
+fn sys_read(fd: i32, buf_ptr: *mut u8, buf_len: usize) -> i64 {
+    if buf_ptr.is_null() {
+        return -1;
+    }
+    if fd == 0 {
+        return sys_console_read(buf_ptr, buf_len);
+    }
+
+    let buf = unsafe { slice::from_raw_parts_mut(buf_ptr, buf_len) };
+    if let Some(pipe) = with_fd_table(|fds| fds.get_mut(fd).and_then(|file| file.pipe.clone())) {
+        return read_pipe(&pipe, buf);
+    }
+    let watch = with_fd_table(|fds| match fds.get_mut(fd) {
+        Some(OpenFile { watch: Some(id), .. }) => Some(*id),
+        _ => None,
+    });
+    if let Some(id) = watch {
+        return match crate::fs::watch::read_lines(id, buf.len()) {
+            Some(lines) => {
+                buf[..lines.len()].copy_from_slice(lines.as_bytes());
+                lines.len() as i64
+            }
+            None => -1,
+        };
+    }
+
+    let Some(file) = regular_file(fd).filter(OpenFile::readable) else {
+        return -1;
+    };
+    match fs_proxy::fs_read_at(&file.path, file.pos, buf.len()) {
+        Ok(data) => {
+            buf[..data.len()].copy_from_slice(&data);
+            set_file_pos(fd, file.pos + data.len());
+            data.len() as i64
+        }
+        Err(_) => -1,
+    }
+}
+
+fn sys_write(fd: i32, data_ptr: *const u8, data_len: usize) -> i64 {
+    if data_ptr.is_null() {
+        return -1;
+    }
+    let data = unsafe { slice::from_raw_parts(data_ptr, data_len) };
+
+    // stdout/stderr go to the console (or the active output capture)
+    if fd == 1 || fd == 2 {
+        scripting::out_str(&String::from_utf8_lossy(data));
+        return data_len as i64;
+    }
+
+    let Some(file) = regular_file(fd).filter(OpenFile::writable) else {
+        return -1;
+    };
+    // Only the blocks covering the write are touched; seeking past the end
+    // leaves a zero-filled hole
+    let end = if file.appends() {
+        fs_proxy::fs_append(&file.path, data)
+    } else {
+        fs_proxy::fs_write_at(&file.path, file.pos, data).map(|_| file.pos + data.len())
+    };
+    match end {
+        Ok(end) => {
+            set_file_pos(fd, end);
+            data_len as i64
+        }
+        Err(_) => -1,
+    }
+}
+
+fn sys_seek(fd: i32, offset: i64, whence: u32) -> i64 {
+    let len = match regular_file(fd) {
+        Some(file) if whence == SEEK_END => match fs_proxy::fs_len(&file.path) {
+            Some(len) => len,
+            None => return -1,
+        },
+        _ => 0,
+    };
+    with_fd_table(|fds| match fds.get_mut(fd) {
+        Some(file) => file.seek(offset, whence, len).map(|pos| pos as i64).unwrap_or(-1),
+        None => -1,
+    })
+}
+
+fn sys_close(fd: i32) -> i64 {
+    match with_fd_table(|fds| fds.remove(fd)) {
+        Some(file) => {
+            release_file(&file);
+            0
+        }
+        None => -1,
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Symbolic Link Syscalls
+// ═══════════════════════════════════════════════════════════════════════════════
+
+fn sys_symlink(target_ptr: *const u8, target_len: usize, path_ptr: *const u8, path_len: usize) -> i64 {
+    let (target, path) = match unsafe { (read_str(target_ptr, target_len), read_str(path_ptr, path_len)) } {
+        (Some(t), Some(p)) => (t, p),
+        _ => return -1,
+    };
+
+    // The target is stored verbatim; only the link's own location is resolved
+    match fs_proxy::fs_symlink(target, &crate::resolve_path_nofollow(path)) {
+        Ok(()) => 0,
+        Err(_) => -1,
+    }
+}
+
+fn sys_readlink(path_ptr: *const u8, path_len: usize, buf_ptr: *mut u8, buf_len: usize) -> i64 {
+    unsafe {
+        if let Some(path) = read_str(path_ptr, path_len) {
+            if let Some(target) = fs_proxy::fs_readlink(&crate::resolve_path_nofollow(path)) {
+                return write_bytes(buf_ptr, target.as_bytes(), buf_len);
+            }
+        }
+    }
+    -1
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Rename Syscall
+// ═══════════════════════════════════════════════════════════════════════════════
+
+fn sys_rename(from_ptr: *const u8, from_len: usize, to_ptr: *const u8, to_len: usize) -> i64 {
+    let (from, to) = match unsafe { (read_str(from_ptr, from_len), read_str(to_ptr, to_len)) } {
+        (Some(f), Some(t)) => (f, t),
+        _ => return -1,
+    };
+
+    // A symlink is renamed itself, not the file it points to
+    match fs_proxy::fs_rename(&crate::resolve_path_nofollow(from), &crate::resolve_path_nofollow(to)) {
+        Ok(()) => 0,
+        Err(_) => -1,
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Partial Write Syscalls
+// ═══════════════════════════════════════════════════════════════════════════════
+
+fn sys_fs_append(path_ptr: *const u8, path_len: usize, data_ptr: *const u8, data_len: usize) -> i64 {
+    sys_fs_write_at(path_ptr, path_len, data_ptr, data_len, None)
+}
+
+fn sys_fs_pwrite(path_ptr: *const u8, path_len: usize, data_ptr: *const u8, data_len: usize, offset: usize) -> i64 {
+    sys_fs_write_at(path_ptr, path_len, data_ptr, data_len, Some(offset))
+}
+
+fn sys_fs_write_at(path_ptr: *const u8, path_len: usize, data_ptr: *const u8, data_len: usize, offset: Option<usize>) -> i64 {
+    if data_ptr.is_null() {
+        return -1;
+    }
+    let path = match unsafe { read_str(path_ptr, path_len) } {
+        Some(p) => crate::resolve_path(p),
+        None => return -1,
+    };
+    let data = unsafe { slice::from_raw_parts(data_ptr, data_len) };
+
+    let result = match offset {
+        Some(offset) => fs_proxy::fs_write_at(&path, offset, data),
+        None => fs_proxy::fs_append(&path, data),
+    };
+    match result {
+        Ok(_) => data_len as i64,
+        Err(_) => -1,
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Kernel Module Syscalls
+// ═══════════════════════════════════════════════════════════════════════════════
+
+fn sys_kmod_load(path_ptr: *const u8, path_len: usize) -> i64 {
+    let path = match unsafe { read_str(path_ptr, path_len) } {
+        Some(p) => crate::resolve_path(p),
+        None => return -1,
+    };
+
+    match crate::kmod::load(&path) {
+        Ok(()) => 0,
+        Err(e) => {
+            klog_error("kmod", &format!("load {}: {}", path, e));
+            -1
+        }
+    }
+}
+
+fn sys_kmod_unload(name_ptr: *const u8, name_len: usize) -> i64 {
+    let name = match unsafe { read_str(name_ptr, name_len) } {
+        Some(n) => n,
+        None => return -1,
+    };
+
+    match crate::kmod::unload(name) {
+        Ok(()) => 0,
+        Err(_) => -1,
+    }
+}
+
+fn sys_kmod_list(buf_ptr: *mut u8, buf_len: usize) -> i64 {
+    let list = crate::kmod::list();
+    unsafe { write_bytes(buf_ptr, list.as_bytes(), buf_len) }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Boot Profiling Syscalls
+// ═══════════════════════════════════════════════════════════════════════════════
+
+fn sys_bootchart(buf_ptr: *mut u8, buf_len: usize) -> i64 {
+    let report = crate::boot::profile::report();
+    unsafe { write_bytes(buf_ptr, report.as_bytes(), buf_len) }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Hibernation Syscalls
+// ═══════════════════════════════════════════════════════════════════════════════
+
+fn sys_hibernate() -> i64 {
+    match crate::hibernate::hibernate() {
+        Ok(()) => 0,
+        Err(e) => {
+            klog_error("hibernate", e);
+            -1
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// QR Code Syscalls
+// ═══════════════════════════════════════════════════════════════════════════════
+
+fn sys_qrcode(text_ptr: *const u8, text_len: usize, out_ptr: *mut u8, out_len: usize) -> i64 {
+    let text = match unsafe { read_str(text_ptr, text_len) } {
+        Some(t) => t,
+        None => return -1,
+    };
+    let code = match crate::ui::qr::QrCode::encode(text.as_bytes()) {
+        Ok(code) => code,
+        Err(_) => return -1,
+    };
+    let size = code.size();
+    if out_len < size * size {
+        return -1;
+    }
+
+    let mut modules = vec![0u8; size * size];
+    for y in 0..size {
+        for x in 0..size {
+            modules[y * size + x] = code.get(x, y) as u8;
+        }
+    }
+    unsafe { write_bytes(out_ptr, &modules, out_len) };
+
+    if crate::cpu::display_proxy::is_available() {
+        crate::ui::main_screen::show_qr(text);
+    }
+    size as i64
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Tone Syscalls
+// ═══════════════════════════════════════════════════════════════════════════════
+
+fn sys_beep(freq: u32, duration_ms: u32, waveform: u64) -> i64 {
+    let waveform = match waveform {
+        0 => crate::tone::Waveform::Sine,
+        1 => crate::tone::Waveform::Square,
+        _ => return -1,
+    };
+    match crate::tone::play(freq, duration_ms, waveform) {
+        Ok(()) => 0,
+        Err(_) => -1,
+    }
+}
+
+fn sys_event_sounds(mode: u64) -> i64 {
+    match mode {
+        0 => crate::tone::set_event_sounds(false),
+        1 => crate::tone::set_event_sounds(true),
+        _ => {}
+    }
+    crate::tone::event_sounds_enabled() as i64
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Mount Syscalls
+// ═══════════════════════════════════════════════════════════════════════════════
+
+fn sys_mount_9p(ip_ptr: *const u8, port: u16, path_ptr: *const u8, path_len: usize) -> i64 {
+    if ip_ptr.is_null() {
+        return -1;
+    }
+    let mut ip = [0u8; 4];
+    ip.copy_from_slice(unsafe { slice::from_raw_parts(ip_ptr, 4) });
+    let path = match unsafe { read_str(path_ptr, path_len) } {
+        Some(p) => crate::resolve_path(p),
+        None => return -1,
+    };
+    match fs_proxy::fs_mount_9p(ip, port, &path) {
+        Ok(()) => 0,
+        Err(_) => -1,
+    }
+}
+
+fn sys_umount(path_ptr: *const u8, path_len: usize) -> i64 {
+    let path = match unsafe { read_str(path_ptr, path_len) } {
+        Some(p) => crate::resolve_path(p),
+        None => return -1,
+    };
+    match fs_proxy::fs_unmount(&path) {
+        Ok(()) => 0,
+        Err(_) => -1,
+    }
+}
+
+fn sys_mounts(buf_ptr: *mut u8, buf_len: usize) -> i64 {
+    let mut output = String::new();
+    for mount in fs_proxy::fs_mounts() {
+        output.push_str(&mount);
+        output.push('\n');
+    }
+    unsafe { write_bytes(buf_ptr, output.as_bytes(), buf_len) }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Display Syscalls
+// ═══════════════════════════════════════════════════════════════════════════════
+
+fn sys_display_adjust(brightness: u16, contrast: u16, gamma: u16) -> i64 {
+    use crate::cpu::display_proxy;
+
+    if !display_proxy::is_available() {
+        return -1;
+    }
+    let mut adjust = display_proxy::color_adjust();
+    if brightness != 0 || contrast != 0 || gamma != 0 {
+        let keep = |new: u16, old: u16| if new == 0 { old } else { new };
+        adjust.brightness = keep(brightness, adjust.brightness);
+        adjust.contrast = keep(contrast, adjust.contrast);
+        adjust.gamma = keep(gamma, adjust.gamma);
+        if display_proxy::set_color_adjust(adjust).is_err() {
+            return -1;
+        }
+    }
+    adjust.brightness as i64 | (adjust.contrast as i64) << 16 | (adjust.gamma as i64) << 32
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Settings Syscalls
+// ═══════════════════════════════════════════════════════════════════════════════
+
+fn sys_settings_get(buf_ptr: *mut u8, buf_len: usize) -> i64 {
+    let output = crate::settings::to_toml();
+    unsafe { write_bytes(buf_ptr, output.as_bytes(), buf_len) }
+}
+
+fn sys_settings_set(key_ptr: *const u8, key_len: usize, value_ptr: *const u8, value_len: usize) -> i64 {
+    let (key, value) = match unsafe { (read_str(key_ptr, key_len), read_str(value_ptr, value_len)) } {
+        (Some(key), Some(value)) => (key, value),
+        _ => return -1,
+    };
+    match crate::settings::set(key.trim(), value.trim()) {
+        Ok(()) => 0,
+        Err(_) => -1,
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// IPv6 Syscalls
+// ═══════════════════════════════════════════════════════════════════════════════
+
+fn sys_net_info6(buf_ptr: *mut u8, buf_len: usize) -> i64 {
+    use crate::net::ipv6::{is_link_local, IPV6_PREFIX_LEN};
+
+    let mut output = String::new();
+    {
+        let net_guard = crate::NET_STATE.lock();
+        let Some(ref net) = *net_guard else {
+            return -2;
+        };
+        for addr in net.ipv6_addresses() {
+            let scope = if is_link_local(&addr) { "link" } else { "global" };
+            output.push_str(&format!("inet6 {}/{} scope {}\n", addr, IPV6_PREFIX_LEN, scope));
+        }
+        if let Some(router) = net.ipv6_router() {
+            output.push_str(&format!("router {}\n", router));
+        }
+    }
+    unsafe { write_bytes(buf_ptr, output.as_bytes(), buf_len) }
+}
+
+fn sys_ping6(ip_ptr: *const u8, seq: i32, timeout_ms: i32, out_ptr: *mut u8) -> i64 {
+    if ip_ptr.is_null() || out_ptr.is_null() {
+        return -2;
+    }
+
+    let mut octets = [0u8; 16];
+    unsafe { octets.copy_from_slice(slice::from_raw_parts(ip_ptr, 16)) };
+    let target = smoltcp::wire::Ipv6Address::from(octets);
+    let seq = seq as u16;
+    let timestamp = get_time_ms();
+
+    {
+        let mut net_guard = crate::NET_STATE.lock();
+        match *net_guard {
+            Some(ref mut state) => {
+                if state.send_ping6(target, seq, timestamp).is_err() {
+                    return -2;
+                }
+            }
+            None => return -2, // No network available
+        }
+    }
+
+    // Wait for reply
+    let deadline = timestamp + timeout_ms as i64;
+    loop {
+        let now = get_time_ms();
+        if now >= deadline {
+            return -1; // Timeout
+        }
+
+        let reply = {
+            let mut net_guard = crate::NET_STATE.lock();
+            if let Some(ref mut state) = *net_guard {
+                state.poll(now);
+                state.check_ping6_reply()
+            } else {
+                None
+            }
+        };
+
+        if let Some((reply_ip, _ident, reply_seq)) = reply {
+            if reply_ip == target && reply_seq == seq {
+                let rtt = (now - timestamp) as u32;
+                unsafe { core::ptr::copy_nonoverlapping(rtt.to_le_bytes().as_ptr(), out_ptr, 4) };
+                return 0;
+            }
+        }
+
+        crate::net::wait_for_frame(deadline);
+    }
+}
+
+fn sys_dns_resolve6(host_ptr: *const u8, host_len: usize, ip_buf_ptr: *mut u8, ip_buf_len: usize) -> i64 {
+    if ip_buf_len < 16 || host_ptr.is_null() {
+        return -1;
+    }
+    let host_bytes = unsafe { slice::from_raw_parts(host_ptr, host_len) };
+    let dns_server = smoltcp::wire::Ipv4Address::new(8, 8, 8, 8);
+
+    let mut net_guard = crate::NET_STATE.lock();
+    if let Some(ref mut net) = *net_guard {
+        if let Some(ip) = crate::dns::resolve6(net, host_bytes, dns_server, 5000, get_time_ms) {
+            unsafe { core::ptr::copy_nonoverlapping(ip.octets().as_ptr(), ip_buf_ptr, 16) };
+            return 16;
+        }
+    }
+    -1
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// DNS Syscalls
+// ═══════════════════════════════════════════════════════════════════════════════
+
+fn sys_dns_flush() -> i64 {
+    let (dropped, hosts) = crate::dns::flush();
+    dropped as i64 | (hosts as i64) << 32
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// TCP Listener Syscalls
+// ═══════════════════════════════════════════════════════════════════════════════
+
+fn sys_tcp_listen(port: u16) -> i64 {
+    let mut net_guard = crate::NET_STATE.lock();
+    if let Some(ref mut net) = *net_guard {
+        let now = get_time_ms();
+        if let Ok(id) = net.tcp_listener_open(port, crate::get_hart_id()) {
+            net.poll(now);
+            return id as i64;
+        }
+    }
+    -1
+}
+
+fn sys_tcp_accept(listener: u64, addr_out: *mut u8) -> i64 {
+    let Ok(id) = u8::try_from(listener) else {
+        return -2;
+    };
+    let mut net_guard = crate::NET_STATE.lock();
+    let Some(ref mut net) = *net_guard else {
+        return -2;
+    };
+    net.poll(get_time_ms());
+    match net.tcp_listener_accept(id, crate::get_hart_id()) {
+        Ok(Some((sock, ip, port))) => {
+            if !addr_out.is_null() {
+                let mut addr = [0u8; 6];
+                addr[..4].copy_from_slice(&ip.octets());
+                addr[4..].copy_from_slice(&port.to_be_bytes());
+                unsafe { core::ptr::copy_nonoverlapping(addr.as_ptr(), addr_out, addr.len()) };
+            }
+            sock as i64
+        }
+        Ok(None) => -1,
+        Err(_) => -2,
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// File Watch Syscalls
+// ═══════════════════════════════════════════════════════════════════════════════
+
+fn sys_watch(path_ptr: *const u8, path_len: usize, mask: u8) -> i64 {
+    use crate::fs::watch::{self, WATCH_ALL};
+
+    let path = match unsafe { read_str(path_ptr, path_len) } {
+        Some(p) => crate::resolve_path(p),
+        None => return -1,
+    };
+    if mask & WATCH_ALL == 0 {
+        return -1;
+    }
+
+    let id = watch::add(&path, mask);
+    match with_fd_table(|fds| fds.insert(OpenFile::watch(&path, id))) {
+        Ok(fd) => fd as i64,
+        Err(_) => {
+            watch::remove(id);
+            -1
+        }
+    }
+}
+
+fn sys_watch_wait(fd: i32, timeout_ms: u64) -> i64 {
+    let Some(id) = with_fd_table(|fds| fds.get_mut(fd).and_then(|file| file.watch)) else {
+        return -1;
+    };
+    let deadline = get_time_ms().saturating_add(timeout_ms as i64);
+
+    loop {
+        match crate::fs::watch::has_events(id) {
+            Some(true) => return 1,
+            Some(false) => {}
+            None => return -1,
+        }
+        if get_time_ms() >= deadline || interrupted() {
+            return 0;
+        }
+        // Nothing wakes this wait, so block until the next timer tick
+        crate::lock::wait::sleep_ms(1);
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// HTTP Options
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// http_get_ex flag: skip HTTPS certificate validation
+const HTTP_INSECURE: u64 = 1;
+
+fn sys_http_get_ex(url_ptr: *const u8, url_len: usize, resp_ptr: *mut u8, resp_len: usize, flags: u64) -> i64 {
+    let insecure = flags & HTTP_INSECURE != 0;
+    unsafe {
+        if let Some(url) = read_str(url_ptr, url_len) {
+            let mut net_guard = crate::NET_STATE.lock();
+            if let Some(ref mut net) = *net_guard {
+                match crate::commands::http::get_follow_redirects_insecure(net, url, insecure, 30000, get_time_ms) {
+                    Ok(response) => {
+                        return write_bytes(resp_ptr, &response.body, resp_len);
+                    }
+                    Err(_) => return -1,
+                }
+            }
+        }
+    }
+    -1
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Heap Leak Detector Syscalls
+// ═══════════════════════════════════════════════════════════════════════════════
+
+const HEAPDIFF_REPORT: u64 = 0;
+const HEAPDIFF_START: u64 = 1;
+const HEAPDIFF_STOP: u64 = 2;
+
+fn sys_heapdiff(op: u64, buf_ptr: *mut u8, buf_len: usize) -> i64 {
+    let report = match op {
+        HEAPDIFF_START => {
+            crate::allocator::leak_tracking_start();
+            crate::services::klogd::klog_info("heapdiff", "Allocation tracking started");
+            return 0;
+        }
+        HEAPDIFF_STOP => crate::allocator::leak_tracking_stop(),
+        HEAPDIFF_REPORT => crate::allocator::leak_report(),
+        _ => None,
+    };
+    match report {
+        Some(report) => unsafe { write_bytes(buf_ptr, report.as_bytes(), buf_len) },
+        None => -1,
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// WASM Pipeline Syscalls
+// ═══════════════════════════════════════════════════════════════════════════════
+
+fn sys_wasm_pipe(spec_ptr: *const u8, spec_len: usize) -> i64 {
+    let Some(spec) = (unsafe { read_str(spec_ptr, spec_len) }) else {
+        return -1;
+    };
+
+    let mut stages = vec![];
+    for line in spec.lines().filter(|l| !l.trim().is_empty()) {
+        let mut words = line.split_whitespace();
+        let Some(path) = words.next() else {
+            return -1;
+        };
+        let Some(bytes) = fs_proxy::fs_read(&crate::resolve_path(path)) else {
+            klog_error("wasm-pipe", &format!("{}: not found", path));
+            return -1;
+        };
+        stages.push((bytes, words.map(String::from).collect()));
+    }
+
+    let pipeline = match crate::wasm_service::submit_pipeline(stages) {
+        Ok(pipeline) => pipeline,
+        Err(e) => {
+            klog_error("wasm-pipe", e);
+            return -1;
+        }
+    };
+    let output = pipeline.output;
+    match with_fd_table(|fds| fds.insert(OpenFile::pipe("wasm-pipe", output.clone()))) {
+        Ok(fd) => fd as i64,
+        Err(_) => {
+            crate::cpu::ipc::IPC.remove_pipe(output.id);
+            -1
+        }
+    }
+}
+
+/// Read a pipe fd: wait until there is data, returning 0 once the writer has
+/// finished (or -1 if the command is cancelled first)
+fn read_pipe(pipe: &crate::cpu::ipc::Pipe, buf: &mut [u8]) -> i64 {
+    loop {
+        match pipe.read(buf) {
+            Ok(0) if !buf.is_empty() => {}
+            Ok(n) => return n as i64,
+            Err(_) => return 0,
+        }
+        if interrupted() {
+            return -1;
+        }
+        // Nothing wakes this wait, so block until the next timer tick
+        crate::lock::wait::sleep_ms(1);
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Input Record/Replay Syscalls
+// ═══════════════════════════════════════════════════════════════════════════════
+
+const INPUT_STATUS: u64 = 0;
+const INPUT_RECORD: u64 = 1;
+const INPUT_CHECK: u64 = 2;
+const INPUT_STOP: u64 = 3;
+const INPUT_REPLAY: u64 = 4;
+
+fn sys_input_replay(op: u64, ptr: *const u8, len: usize, arg: u64) -> i64 {
+    use crate::platform::input_replay::{self, Status};
+
+    let path = || unsafe { read_str(ptr, len) }.map(crate::resolve_path).ok_or("Invalid path");
+    let result = match op {
+        INPUT_STATUS => {
+            return match input_replay::status() {
+                Status::Finished(report, failed) => {
+                    let written = unsafe { write_bytes(ptr as *mut u8, report.as_bytes(), len) };
+                    written | (failed as i64) << 32
+                }
+                Status::Recording | Status::Replaying => -2,
+                Status::Idle => -1,
+            };
+        }
+        INPUT_RECORD => path().and_then(|p| input_replay::start_recording(&p)).map(|()| 0),
+        INPUT_CHECK => {
+            let field = |shift: u32| ((arg >> shift) & 0xFFFF) as u32;
+            input_replay::add_check(field(0), field(16), field(32), field(48)).map(|sum| sum as i64)
+        }
+        INPUT_STOP => input_replay::stop().map(|n| n as i64),
+        INPUT_REPLAY => path().and_then(|p| input_replay::start_replay(&p, arg)).map(|n| n as i64),
+        _ => Err("Unknown op"),
+    };
+    result.unwrap_or_else(|e| {
+        klog_warning("inputrec", e);
+        -1
+    })
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// HTTP Download Syscalls
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// http_download flag: print a progress line while downloading
+const HTTP_PROGRESS: u64 = 2;
+
+/// Body bytes collected before each append to the file
+const DOWNLOAD_FLUSH_SIZE: usize = 32 * 1024;
+
+/// Minimum time between progress line updates
+const DOWNLOAD_PROGRESS_INTERVAL_MS: i64 = 250;
+
+fn sys_http_download(url_ptr: *const u8, url_len: usize, path_ptr: *const u8, path_len: usize, flags: u64) -> i64 {
+    let (Some(url), Some(path)) = (unsafe { read_str(url_ptr, url_len) }, unsafe { read_str(path_ptr, path_len) }) else {
+        return -1;
+    };
+    let path = crate::resolve_path(path);
+    let progress = flags & HTTP_PROGRESS != 0;
+
+    let started = get_time_ms();
+    let mut last_report = 0;
+    let mut saved: u64 = 0;
+    let mut pending = vec![];
+    let mut created = false;
+    let flush = |data: &[u8], created: &mut bool| -> Result<(), &'static str> {
+        if *created {
+            fs_proxy::fs_append(&path, data)?;
+        } else {
+            fs_proxy::fs_write(&path, data)?;
+            *created = true;
+        }
+        Ok(())
+    };
+
+    let result = crate::commands::http::download(url, flags & HTTP_INSECURE != 0, 30000, get_time_ms, &mut |head, data| {
+        if interrupted() {
+            return Err("Cancelled");
+        }
+        pending.extend_from_slice(data);
+        saved += data.len() as u64;
+        if pending.len() >= DOWNLOAD_FLUSH_SIZE {
+            flush(&pending, &mut created)?;
+            pending.clear();
+        }
+        let now = get_time_ms();
+        if progress && now - last_report >= DOWNLOAD_PROGRESS_INTERVAL_MS {
+            last_report = now;
+            scripting::out_str(&download_progress(saved, head.content_length, now - started));
+        }
+        Ok(())
+    });
+
+    let result = result.and_then(|download| {
+        if !(200..300).contains(&download.status_code) {
+            return Ok(-(download.status_code as i64));
+        }
+        // Also creates the file for an empty body
+        if !pending.is_empty() || !created {
+            flush(&pending, &mut created)?;
+        }
+        Ok(download.bytes as i64)
+    });
+    if progress && saved > 0 {
+        scripting::out_str(&download_progress(saved, None, get_time_ms() - started));
+        scripting::out_str("\n");
+    }
+    result.unwrap_or_else(|e| {
+        klog_error("http", &format!("Download of {} failed: {}", url, e));
+        -1
+    })
+}
+
+/// Progress line ("\r" so each update overwrites the last)
+fn download_progress(saved: u64, total: Option<u64>, elapsed_ms: i64) -> String {
+    let rate = saved * 1000 / elapsed_ms.max(1) as u64 / 1024;
+    match total {
+        Some(total) if total > 0 => {
+            format!("\r{} / {} bytes ({}%)  {} KiB/s   ", saved, total, saved * 100 / total, rate)
+        }
+        _ => format!("\r{} bytes  {} KiB/s   ", saved, rate),
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// WebSocket Syscalls
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// ws_send flag: send a binary message instead of text
+const WS_BINARY: u64 = 1;
+
+/// ws_recv checks for Ctrl+C at least this often while waiting
+const WS_RECV_SLICE_MS: i64 = 100;
+
+fn sys_ws_connect(url_ptr: *const u8, url_len: usize, flags: u64) -> i64 {
+    let Some(url) = (unsafe { read_str(url_ptr, url_len) }) else {
+        return -1;
+    };
+    let ws = match crate::commands::ws::WebSocket::connect(url, flags & HTTP_INSECURE != 0, 30000, get_time_ms) {
+        Ok(ws) => ws,
+        Err(e) => {
+            klog_warning("ws", &format!("{}: {}", url, e));
+            return -1;
+        }
+    };
+    match crate::commands::ws::open(ws, crate::get_hart_id()) {
+        Some(handle) => handle as i64,
+        None => {
+            klog_warning("ws", "Too many open WebSockets");
+            -1
+        }
+    }
+}
+
+fn sys_ws_send(handle: u64, data_ptr: *const u8, data_len: usize, flags: u64) -> i64 {
+    if data_ptr.is_null() {
+        return -1;
+    }
+    let data = unsafe { slice::from_raw_parts(data_ptr, data_len) };
+    let result = crate::commands::ws::with_connection(handle as usize, crate::get_hart_id(), |ws| {
+        ws.send(data, flags & WS_BINARY != 0)
+    });
+    match result {
+        Some(Ok(())) => data_len as i64,
+        _ => -1,
+    }
+}
+
+fn sys_ws_recv(handle: u64, buf_ptr: *mut u8, buf_len: usize, timeout_ms: u64) -> i64 {
+    use crate::commands::ws::Message;
+
+    let deadline = get_time_ms().saturating_add(timeout_ms.min(i64::MAX as u64) as i64);
+    let result = crate::commands::ws::with_connection(handle as usize, crate::get_hart_id(), |ws| loop {
+        let wait = (deadline - get_time_ms()).clamp(0, WS_RECV_SLICE_MS);
+        match ws.recv(wait) {
+            Ok(None) if wait > 0 && !interrupted() => continue,
+            other => break other,
+        }
+    });
+    let (kind, data) = match result {
+        Some(Ok(Some(Message::Text(data)))) => (1, data),
+        Some(Ok(Some(Message::Binary(data)))) => (2, data),
+        Some(Ok(Some(Message::Close(_)))) => (8, vec![]),
+        Some(Ok(None)) => return 0,
+        Some(Err(e)) => {
+            klog_warning("ws", e);
+            return -1;
+        }
+        None => return -1,
+    };
+    let copied = unsafe { write_bytes(buf_ptr, &data, buf_len) };
+    if copied < 0 {
+        return -1;
+    }
+    copied | (kind << 32)
+}
+
+fn sys_ws_close(handle: u64) -> i64 {
+    if crate::commands::ws::close(handle as usize, crate::get_hart_id()) { 0 } else { -1 }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Stress Test Syscalls
+// ═══════════════════════════════════════════════════════════════════════════════
+
+const STRESS_STATUS: u64 = 0;
+const STRESS_START: u64 = 1;
+const STRESS_STOP: u64 = 2;
+
+fn sys_stress(op: u64, a1: u64, a2: u64, a3: u64, a4: u64) -> i64 {
+    match op {
+        STRESS_STATUS => {
+            let Some((report, state)) = crate::stress::status() else {
+                return -1;
+            };
+            let len = unsafe { write_bytes(a1 as *mut u8, report.as_bytes(), a2 as usize) };
+            if len < 0 {
+                return -1;
+            }
+            len | ((state as i64) << 32)
+        }
+        STRESS_START => {
+            let url = if a4 == 0 {
+                crate::stress::DEFAULT_URL
+            } else {
+                match unsafe { read_str(a3 as *const u8, a4 as usize) } {
+                    Some(url) => url,
+                    None => return -1,
+                }
+            };
+            let config = crate::stress::StressConfig {
+                cpu: (a1 & 0xFFFF) as usize,
+                io: ((a1 >> 16) & 0xFFFF) as usize,
+                net: ((a1 >> 32) & 0xFFFF) as usize,
+                seconds: a2,
+                url: String::from(url),
+            };
+            match crate::stress::start(config) {
+                Ok(()) => 0,
+                Err(e) => {
+                    klog_warning("stress", e);
+                    -1
+                }
+            }
+        }
+        STRESS_STOP => {
+            if crate::stress::stop() { 0 } else { -1 }
+        }
+        _ => -1,
+    }
+}
+
+fn sys_net_neighbors(buf_ptr: *mut u8, buf_len: usize) -> i64 {
+    use crate::net::neighbors::{neighbors, NEIGHBOR_LIFETIME_MS};
+
+    if !crate::net::is_ip_assigned() {
+        return -2;
+    }
+    let now = get_time_ms();
+    let mut output = String::new();
+    for n in neighbors() {
+        let m = n.mac;
+        let age = now - n.updated_ms;
+        output.push_str(&format!(
+            "{} {:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x} {} {}\n",
+            n.ip,
+            m[0], m[1], m[2], m[3], m[4], m[5],
+            age,
+            if age < NEIGHBOR_LIFETIME_MS { "reachable" } else { "stale" }
+        ));
+    }
+    unsafe { write_bytes(buf_ptr, output.as_bytes(), buf_len) }
+}
+
+const FIREWALL_LIST: u64 = 0;
+const FIREWALL_RELOAD: u64 = 1;
+const FIREWALL_ADD: u64 = 2;
+const FIREWALL_DELETE: u64 = 3;
+
+fn sys_firewall(op: u64, ptr: *mut u8, len: usize) -> i64 {
+    use crate::net::firewall;
+
+    let result = match op {
+        FIREWALL_LIST => {
+            let (rules, policy, dropped) = firewall::list();
+            let mut output = format!("policy {} {}\n", policy, dropped);
+            for (rule, hits) in rules {
+                output.push_str(&format!("{} {}\n", hits, rule));
+            }
+            return unsafe { write_bytes(ptr, output.as_bytes(), len) };
+        }
+        FIREWALL_RELOAD => firewall::load(),
+        FIREWALL_ADD => match unsafe { read_str(ptr, len) } {
+            Some(line) => firewall::add(line),
+            None => return -1,
+        },
+        FIREWALL_DELETE => firewall::delete(ptr as usize),
+        _ => return -1,
+    };
+    match result {
+        Ok(count) => count as i64,
+        Err(e) => {
+            klog_warning("firewall", &e);
+            -1
+        }
+    }
+}
+
+const SFS_FORMAT_STATUS: u64 = 0;
+const SFS_FORMAT_UPGRADE: u64 = 1;
+
+fn sys_sfs_format(op: u64, buf_ptr: *mut u8, buf_len: usize) -> i64 {
+    use crate::lock::state::fs::{FEATURE_LZ4, FEATURE_SYMLINKS, FORMAT_MAJOR, FORMAT_MINOR, KNOWN_FEATURES};
+    use crate::lock::utils::FS_STATE;
+
+    match op {
+        SFS_FORMAT_STATUS => {
+            let Some((format, read_only)) = FS_STATE.read().as_ref().map(|fs| (fs.format(), fs.is_read_only())) else {
+                return -1;
+            };
+            let mut output = format!("format {}\nlatest {}.{}\nfeatures", format, FORMAT_MAJOR, FORMAT_MINOR);
+            for (flag, name) in [(FEATURE_SYMLINKS, "symlinks"), (FEATURE_LZ4, "lz4")] {
+                if format.features & flag != 0 {
+                    output.push(' ');
+                    output.push_str(name);
+                }
+            }
+            let unknown = format.features & !KNOWN_FEATURES;
+            if unknown != 0 {
+                output.push_str(&format!(" {:#x}", unknown));
+            }
+            output.push_str(if read_only { "\nmode ro\n" } else { "\nmode rw\n" });
+            unsafe { write_bytes(buf_ptr, output.as_bytes(), buf_len) }
+        }
+        SFS_FORMAT_UPGRADE => match fs_proxy::sfs_upgrade() {
+            Ok((before, after)) if before == after => 0,
+            Ok((before, after)) => {
+                klog_info("sfs", &format!("Upgraded format {} to {}", before, after));
+                1
+            }
+            Err(e) => {
+                klog_warning("sfs", e);
+                -1
+            }
+        },
+        _ => -1,
+    }
+}
+
+const CLIPBOARD_GET: u64 = 0;
+const CLIPBOARD_SET: u64 = 1;
+
+fn sys_clipboard(op: u64, ptr: *mut u8, len: usize) -> i64 {
+    use crate::services::hostbridged;
+
+    match op {
+        CLIPBOARD_GET => unsafe { write_bytes(ptr, hostbridged::clipboard().as_bytes(), len) },
+        CLIPBOARD_SET if len == 0 => {
+            hostbridged::set_clipboard("");
+            0
+        }
+        CLIPBOARD_SET => match unsafe { read_str(ptr, len) } {
+            Some(text) => {
+                hostbridged::set_clipboard(text);
+                0
+            }
+            None => -1,
+        },
+        _ => -1,
+    }
+}
+
+fn sys_trace_probe(ip_ptr: *const u8, limit_proto: u64, seq: u16, timeout_ms: i64, out_ptr: *mut u8) -> i64 {
+    use crate::net::traceroute::{ProbeProto, ProbeResult};
+
+    if ip_ptr.is_null() || out_ptr.is_null() {
+        return -2;
+    }
+    let ip = unsafe { slice::from_raw_parts(ip_ptr, 4) };
+    let target = smoltcp::wire::Ipv4Address::new(ip[0], ip[1], ip[2], ip[3]);
+    let hop_limit = (limit_proto & 0xFF) as u8;
+    let proto = match limit_proto >> 8 {
+        0 => ProbeProto::Icmp,
+        1 => ProbeProto::Udp,
+        _ => return -2,
+    };
+    let timestamp = get_time_ms();
+
+    {
+        let mut net_guard = crate::NET_STATE.lock();
+        let Some(ref mut state) = *net_guard else {
+            return -2;
+        };
+        if state.send_probe(target, proto, hop_limit, seq, timestamp).is_err() {
+            return -2;
+        }
+    }
+
+    let deadline = timestamp + timeout_ms;
+    loop {
+        let now = get_time_ms();
+        if now >= deadline {
+            return -1;
+        }
+
+        let reply = {
+            let mut net_guard = crate::NET_STATE.lock();
+            match *net_guard {
+                Some(ref mut state) => {
+                    state.poll(now);
+                    state.check_probe_reply(seq)
+                }
+                None => None,
+            }
+        };
+
+        if let Some(reply) = reply {
+            let (result, code) = match reply.result {
+                ProbeResult::TimeExceeded => (0, 0),
+                ProbeResult::Reached => (1, 0),
+                ProbeResult::Unreachable(code) => (2, code as u32),
+            };
+            let mut out = [0u8; 12];
+            out[..4].copy_from_slice(&reply.from.octets());
+            out[4..8].copy_from_slice(&((now - timestamp) as u32).to_le_bytes());
+            out[8..].copy_from_slice(&code.to_le_bytes());
+            unsafe { core::ptr::copy_nonoverlapping(out.as_ptr(), out_ptr, out.len()) };
+            return result;
+        }
+
+        crate::net::wait_for_frame(deadline);
+    }
+}
+
+fn sys_net_stats(buf_ptr: *mut u8, buf_len: usize) -> i64 {
+    use crate::net::sockstats::PROTO_TCP;
+    use smoltcp::wire::IpAddress;
+
+    fn endpoint(addr: Option<IpAddress>, port: u16) -> String {
+        match addr {
+            Some(IpAddress::Ipv6(addr)) => format!("[{}]:{}", addr, port),
+            Some(addr) if !addr.is_unspecified() => format!("{}:{}", addr, port),
+            _ => format!("*:{}", port),
+        }
+    }
+
+    let sockets = {
+        let mut net_guard = crate::NET_STATE.lock();
+        let Some(ref mut state) = *net_guard else {
+            return -2;
+        };
+        state.socket_stats()
+    };
+    let mut output = String::new();
+    for socket in sockets {
+        let s = socket.stats;
+        output.push_str(&format!(
+            "{} {} {} {} {} {} {} {} {} {}\n",
+            if socket.proto == PROTO_TCP { "tcp" } else { "udp" },
+            endpoint(socket.local.0, socket.local.1),
+            socket.remote.map(|(addr, port)| endpoint(Some(addr), port)).unwrap_or_else(|| String::from("-")),
+            socket.state,
+            s.bytes_in, s.bytes_out, s.packets_in, s.packets_out, s.retransmits, s.srtt_ms,
+        ));
+    }
+    unsafe { write_bytes(buf_ptr, output.as_bytes(), buf_len) }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// DNS Record Syscalls
+// ═══════════════════════════════════════════════════════════════════════════════
+
+fn sys_dns_query(host_ptr: *const u8, host_len: usize, qtype: u16, buf_ptr: *mut u8, buf_len: usize) -> i64 {
+    if host_ptr.is_null() || buf_ptr.is_null() {
+        return -1;
+    }
+    let host_bytes = unsafe { slice::from_raw_parts(host_ptr, host_len) };
+    let dns_server = smoltcp::wire::Ipv4Address::new(8, 8, 8, 8);
+
+    let records = {
+        let mut net_guard = crate::NET_STATE.lock();
+        let Some(ref mut net) = *net_guard else {
+            return -1;
+        };
+        match crate::dns::query(net, host_bytes, qtype, dns_server, 5000, get_time_ms) {
+            Ok(records) => records,
+            Err(crate::dns::DnsResult::NotFound) => return -2,
+            Err(_) => return -1,
+        }
+    };
+    let mut output = String::new();
+    for record in records {
+        output.push_str(&format!("{} {} {}\n", record.name, record.ttl, record.data));
+    }
+    unsafe { write_bytes(buf_ptr, output.as_bytes(), buf_len) }
+}
+
+/// Process a nice or affinity syscall refers to (0 = the one running on
+/// this hart)
+fn nice_target(pid: u32) -> Option<crate::cpu::process::Pid> {
+    if pid != 0 {
+        return Some(pid);
+    }
+    crate::cpu::CPU_TABLE.get(crate::get_hart_id())?.running_process()
+}
+
+fn sys_get_nice(pid: u32) -> i64 {
+    match nice_target(pid).and_then(|pid| crate::cpu::sched::SCHEDULER.get_process(pid)) {
+        Some(process) => process.nice() as i64,
+        None => -100,
+    }
+}
+
+fn sys_set_nice(pid: u32, nice: i64) -> i64 {
+    let nice = nice.clamp(i32::MIN as i64, i32::MAX as i64) as i32;
+    match nice_target(pid).and_then(|pid| crate::cpu::sched::set_nice(pid, nice)) {
+        Some(_) => 0,
+        None => -1,
+    }
+}
+
+fn sys_get_affinity(pid: u32, mask_ptr: *mut u8) -> i64 {
+    if mask_ptr.is_null() {
+        return -1;
+    }
+    let Some(process) = nice_target(pid).and_then(|pid| crate::cpu::sched::SCHEDULER.get_process(pid)) else {
+        return -1;
+    };
+    let mask = process.affinity().0.to_le_bytes();
+    unsafe { core::ptr::copy_nonoverlapping(mask.as_ptr(), mask_ptr, mask.len()) };
+    0
+}
+
+fn sys_set_affinity(pid: u32, mask_ptr: *const u8) -> i64 {
+    if mask_ptr.is_null() {
+        return -1;
+    }
+    let mut mask = [0u8; 16];
+    unsafe { core::ptr::copy_nonoverlapping(mask_ptr, mask.as_mut_ptr(), mask.len()) };
+    let mask = crate::cpu::process::CpuMask(u128::from_le_bytes(mask));
+    let Some(pid) = nice_target(pid) else {
+        return -1;
+    };
+    match crate::cpu::sched::set_affinity(pid, mask) {
+        Ok(()) => 0,
+        Err("No such hart") => -2,
+        Err(_) => -1,
+    }
+}
+
+/// waitpid() flag: don't wait for a child that is still running
+const WNOHANG: u32 = 1;
+
+/// How long each wait for a child lasts before checking for cancellation (ms)
+const CHILD_WAIT_SLICE_MS: i64 = 100;
+
+fn sys_waitpid(pid: u32, status_ptr: *mut u8, flags: u32) -> i64 {
+    let parent = crate::cpu::sched::current_pid();
+    let pid = (pid != 0).then_some(pid);
+    loop {
+        let until_ms = if flags & WNOHANG != 0 { 0 } else { get_time_ms() + CHILD_WAIT_SLICE_MS };
+        match crate::cpu::sched::wait_child(parent, pid, until_ms) {
+            Ok(Some(status)) => {
+                if !status_ptr.is_null() {
+                    unsafe { write_bytes(status_ptr, &status.code.to_le_bytes(), 4) };
+                }
+                return status.pid as i64;
+            }
+            Ok(None) if flags & WNOHANG != 0 => return 0,
+            Ok(None) => {
+                if interrupted() {
+                    return -1;
+                }
+            }
+            Err(_) => return -1,
+        }
+    }
+}
+
+
+/// Start an ELF binary as a child of the caller
+fn sys_spawn(path_ptr: *const u8, path_len: usize, args_ptr: *const u8, args_len: usize) -> i64 {
+    let Some(path) = (unsafe { read_str(path_ptr, path_len) }) else {
+        return -1;
+    };
+    let args = unsafe { read_str(args_ptr, args_len) }.unwrap_or("");
+    match crate::elf_loader::spawn(path, args) {
+        Ok(pid) => pid as i64,
+        Err("Not found") => -1,
+        Err(_) => -2,
+    }
+}
+
+/// Set the calling binary's handler for a signal
+fn sys_signal(sig: u32, handler: usize, restorer: usize) -> i64 {
+    match crate::elf_loader::set_signal_handler(sig, handler, restorer) {
+        Ok(previous) => previous as i64,
+        Err(_) => -1,
+    }
+}
+
+/// Return from a signal handler (the trap handler restores its registers)
+fn sys_sigreturn() -> i64 {
+    if crate::elf_loader::signal_return() { 0 } else { -1 }
+}
+
+/// How long each message queue wait lasts before checking for cancellation (ms)
+const MQ_WAIT_SLICE_MS: i64 = 100;
+
+/// Deadline (ms since boot) for a syscall timeout; u64::MAX waits forever
+fn timeout_deadline(timeout_ms: u64) -> i64 {
+    if timeout_ms == u64::MAX {
+        i64::MAX
+    } else {
+        get_time_ms().saturating_add(timeout_ms.min(i64::MAX as u64) as i64)
+    }
+}
+
+fn sys_mq_open(name_ptr: *const u8, name_len: usize, capacity: usize) -> i64 {
+    use crate::cpu::ipc::{mq_open, MQ_DEFAULT_CAPACITY};
+
+    let Some(name) = (unsafe { read_str(name_ptr, name_len) }) else {
+        return -1;
+    };
+    let capacity = if capacity == 0 { MQ_DEFAULT_CAPACITY } else { capacity };
+    mq_open(name, capacity).id as i64
+}
+
+fn sys_mq_send(id: u32, data_ptr: *const u8, data_len: usize, timeout_ms: u64) -> i64 {
+    use crate::cpu::ipc::{Message, IPC};
+
+    let Some(queue) = IPC.get_queue(id) else {
+        return -1;
+    };
+    if data_ptr.is_null() && data_len > 0 {
+        return -1;
+    }
+    let data = if data_len == 0 { vec![] } else { unsafe { slice::from_raw_parts(data_ptr, data_len) }.to_vec() };
+    let msg = Message::new(crate::cpu::sched::current_pid(), data, 0);
+    let deadline = timeout_deadline(timeout_ms);
+    loop {
+        let until_ms = deadline.min(get_time_ms() + MQ_WAIT_SLICE_MS);
+        match queue.send(msg.clone(), until_ms) {
+            Ok(()) => return 0,
+            Err("Queue full") if get_time_ms() < deadline && !interrupted() => {}
+            Err("Queue full") => return -2,
+            Err(_) => return -1,
+        }
+    }
+}
+
+fn sys_mq_recv(id: u32, buf_ptr: *mut u8, buf_len: usize, timeout_ms: u64) -> i64 {
+    let Some(queue) = crate::cpu::ipc::IPC.get_queue(id) else {
+        return -1;
+    };
+    if buf_ptr.is_null() {
+        return -1;
+    }
+    let deadline = timeout_deadline(timeout_ms);
+    loop {
+        let until_ms = deadline.min(get_time_ms() + MQ_WAIT_SLICE_MS);
+        match queue.recv(until_ms) {
+            Ok(Some(msg)) => return unsafe { write_bytes(buf_ptr, &msg.data, buf_len) },
+            Ok(None) if get_time_ms() < deadline && !interrupted() => {}
+            Ok(None) => return -2,
+            Err(_) => return -1,
+        }
+    }
+}
+
+fn sys_mq_unlink(name_ptr: *const u8, name_len: usize) -> i64 {
+    match unsafe { read_str(name_ptr, name_len) } {
+        Some(name) if crate::cpu::ipc::mq_unlink(name) => 0,
+        _ => -1,
+    }
+}
+
+fn sys_get_mem_limit(pid: u32, out_ptr: *mut u8) -> i64 {
+    if out_ptr.is_null() {
+        return -1;
+    }
+    let Some(pid) = nice_target(pid).filter(|&pid| crate::cpu::sched::SCHEDULER.get_process(pid).is_some()) else {
+        return -1;
+    };
+    let (bytes, _) = crate::allocator::process_heap(pid);
+    let limit = crate::allocator::heap_limit(pid).unwrap_or(0);
+    let mut out = [0u8; 16];
+    out[0..8].copy_from_slice(&(bytes as u64).to_le_bytes());
+    out[8..16].copy_from_slice(&(limit as u64).to_le_bytes());
+    unsafe { core::ptr::copy_nonoverlapping(out.as_ptr(), out_ptr, out.len()) };
+    0
+}
+
+fn sys_set_mem_limit(pid: u32, bytes: u64) -> i64 {
+    let Some(pid) = nice_target(pid).filter(|&pid| crate::cpu::sched::SCHEDULER.get_process(pid).is_some()) else {
+        return -1;
+    };
+    let limit = (bytes != 0).then(|| bytes.min(usize::MAX as u64) as usize);
+    match crate::allocator::set_heap_limit(pid, limit) {
+        Ok(()) => 0,
+        Err(_) => -2,
+    }
+}
+
+fn sys_proc_stats(buf_ptr: *mut u8, buf_len: usize) -> i64 {
+    use crate::cpu::CPU_TABLE;
+
+    let harts = CPU_TABLE.online_cpus();
+    let total: u32 = harts.iter().filter_map(|&id| CPU_TABLE.get(id)).map(|cpu| cpu.recent_usage()).sum();
+    let mut output = format!(
+        "all:{}:{}:{}\n",
+        total / harts.len().max(1) as u32,
+        harts.len(),
+        crate::cpu::sched::SCHEDULER.process_count()
+    );
+    for id in harts {
+        let Some(cpu) = CPU_TABLE.get(id) else { continue };
+        output.push_str(&format!(
+            "cpu{}:{}:{}:{}\n",
+            id,
+            cpu.recent_usage(),
+            cpu.running_process().unwrap_or(0),
+            cpu.context_switch_count()
+        ));
+    }
+    unsafe { write_bytes(buf_ptr, output.as_bytes(), buf_len) }
+}
+
+/// Move the caller's process, or one of its children, into a process group
+fn sys_setpgid(pid: u32, pgid: u32) -> i64 {
+    use crate::cpu::process::PROCESS_TABLE;
+
+    let Some(caller) = nice_target(0) else {
+        return -1;
+    };
+    let pid = if pid == 0 { caller } else { pid };
+    match PROCESS_TABLE.get(pid) {
+        Some(process) if pid == caller || process.ppid == caller => {}
+        _ => return -1,
+    }
+    let pgid = if pgid == 0 { pid } else { pgid };
+    match PROCESS_TABLE.set_group(pid, pgid) {
+        Ok(()) => 0,
+        Err(_) => -2,
+    }
+}
+
+fn sys_getpgid(pid: u32) -> i64 {
+    match nice_target(pid).and_then(|pid| crate::cpu::process::PROCESS_TABLE.get(pid)) {
+        Some(process) => process.pgid() as i64,
+        None => -1,
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Poll Syscall
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Entries one poll call can wait on
+const MAX_POLL_ENTRIES: usize = 64;
+/// Bytes per poll entry (fd, events, revents)
+const POLL_ENTRY_SIZE: usize = 8;
+
+const POLLIN: u16 = 0x01;
+const POLLOUT: u16 = 0x04;
+const POLLERR: u16 = 0x08;
+const POLLHUP: u16 = 0x10;
+const POLLNVAL: u16 = 0x20;
+/// The entry is a TCP socket or listener handle, not a file descriptor
+const POLLSOCK: u16 = 0x100;
+
+/// How long a poll waits on IO_EVENTS before checking again (ms): sockets
+/// only turn ready once the network stack has processed their frames,
+/// which nothing signals on its own
+const POLL_RECHECK_MS: i64 = 10;
+
+fn sys_poll(entries_ptr: *mut u8, count: usize, timeout_ms: u64) -> i64 {
+    use crate::lock::wait::IO_EVENTS;
+
+    if count > MAX_POLL_ENTRIES || (entries_ptr.is_null() && count != 0) {
+        return -1;
+    }
+    let raw: &mut [u8] = if count == 0 {
+        &mut []
+    } else {
+        unsafe { slice::from_raw_parts_mut(entries_ptr, count * POLL_ENTRY_SIZE) }
+    };
+    let entries: Vec<(i32, u16)> = raw
+        .chunks_exact(POLL_ENTRY_SIZE)
+        .map(|entry| {
+            let fd = i32::from_le_bytes([entry[0], entry[1], entry[2], entry[3]]);
+            (fd, u16::from_le_bytes([entry[4], entry[5]]))
+        })
+        .collect();
+    let mut revents = vec![0u16; count];
+
+    let deadline = timeout_deadline(timeout_ms);
+    let mut ready = 0;
+    loop {
+        let until_ms = deadline.min(get_time_ms() + POLL_RECHECK_MS);
+        let found = IO_EVENTS.wait_until(until_ms, || {
+            ready = poll_entries(&entries, &mut revents);
+            ready > 0
+        });
+        if found || get_time_ms() >= deadline {
+            break;
+        }
+        if interrupted() {
+            return -2;
+        }
+    }
+
+    for (entry, revents) in raw.chunks_exact_mut(POLL_ENTRY_SIZE).zip(&revents) {
+        entry[6..8].copy_from_slice(&revents.to_le_bytes());
+    }
+    ready as i64
+}
+
+/// Fill in what each entry is ready for; returns how many are
+fn poll_entries(entries: &[(i32, u16)], revents: &mut [u16]) -> usize {
+    let mut net_polled = false;
+    for (&(fd, events), out) in entries.iter().zip(revents.iter_mut()) {
+        let ready = if fd < 0 {
+            0
+        } else if events & POLLSOCK != 0 {
+            poll_socket(fd, &mut net_polled)
+        } else {
+            poll_fd(fd)
+        };
+        *out = ready & (events | POLLERR | POLLHUP | POLLNVAL);
+    }
+    revents.iter().filter(|&&r| r != 0).count()
+}
+
+/// What descriptor `fd` of the caller is ready for
+fn poll_fd(fd: i32) -> u16 {
+    match fd {
+        0 if uart::has_pending_input() => POLLIN,
+        0 => 0,
+        1 | 2 => POLLOUT,
+        _ => {
+            let file = with_fd_table(|fds| {
+                fds.get_mut(fd).map(|file| (file.pipe.clone(), file.watch, file.readable(), file.writable()))
+            });
+            match file {
+                None => POLLNVAL,
+                Some((Some(pipe), ..)) if pipe.can_read() => POLLIN,
+                Some((Some(pipe), ..)) if pipe.is_closed() => POLLHUP,
+                Some((Some(_), ..)) => 0,
+                Some((None, Some(id), ..)) => match crate::fs::watch::has_events(id) {
+                    Some(true) => POLLIN,
+                    Some(false) => 0,
+                    None => POLLERR,
+                },
+                // Regular files never block
+                Some((None, None, readable, writable)) => {
+                    (if readable { POLLIN } else { 0 }) | (if writable { POLLOUT } else { 0 })
+                }
+            }
+        }
+    }
+}
+
+/// What TCP socket or listener `handle` of the caller is ready for; the
+/// network stack is polled once per check (`net_polled`)
+fn poll_socket(handle: i32, net_polled: &mut bool) -> u16 {
+    let mut net_guard = crate::NET_STATE.lock();
+    let Some(ref mut net) = *net_guard else {
+        return POLLNVAL;
+    };
+    if !core::mem::replace(net_polled, true) {
+        net.poll(get_time_ms());
+    }
+    if let Some(sock) = user_socket(net, handle as u64) {
+        let mut revents = 0;
+        if net.tcp_can_recv(sock) {
+            revents |= POLLIN;
+        }
+        if net.tcp_can_send(sock) {
+            revents |= POLLOUT;
+        }
+        if net.tcp_peer_closed(sock) {
+            revents |= POLLHUP;
+        }
+        return revents;
+    }
+    // Listener handles follow the socket IDs
+    match u8::try_from(handle).ok().and_then(|id| net.tcp_listener_ready(id, crate::get_hart_id())) {
+        Some(true) => POLLIN,
+        Some(false) => 0,
+        None => POLLNVAL,
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Memory Mapping Syscalls
+// ═══════════════════════════════════════════════════════════════════════════════
+//
+// A mapping is a page-aligned heap block, identity-mapped into the binary's
+// address space (see `paging`): zeroed for anonymous mappings, a copy of the
+// file for file mappings. A shared, writable file mapping is copied back into the open
+// file when it is unmapped (or the binary exits), and the file reaches the
+// disk when it is closed. The blocks are charged to the process like any
+// other allocation, so its memory cap applies.
+
+const PAGE_SIZE: usize = 4096;
+/// Mappings a binary can hold at once
+const MAX_MAPPINGS: usize = 32;
+/// Largest single mapping (bytes)
+const MMAP_MAX_LEN: usize = 32 * 1024 * 1024;
+
+/// PROT_READ | PROT_WRITE | PROT_EXEC (enforced with paging, where a page
+/// is never both writable and executable)
+const PROT_ALL: u64 = 0x7;
+const PROT_WRITE: u64 = 0x2;
+const PROT_EXEC: u64 = 0x4;
+const MAP_SHARED: u64 = 0x01;
+const MAP_PRIVATE: u64 = 0x02;
+const MAP_ANONYMOUS: u64 = 0x20;
+
+/// Memory handed out by SYS_MMAP
+struct Mapping {
+    ptr: core::ptr::NonNull<u8>,
+    layout: core::alloc::Layout,
+    /// Length asked for
+    len: usize,
+    /// Open file (path, fd and offset) a shared, writable mapping goes back to
+    write_back: Option<(String, i32, usize)>,
+}
+
+impl Drop for Mapping {
+    fn drop(&mut self) {
+        crate::elf_loader::unmap_user(self.ptr.as_ptr() as usize, self.layout.size());
+        unsafe { alloc::alloc::dealloc(self.ptr.as_ptr(), self.layout) };
+    }
+}
+
+fn sys_mmap(_addr: u64, len: usize, prot: u64, flags: u64, fd: i32, offset: u64) -> i64 {
+    let _subsystem = crate::allocator::enter_subsystem(crate::allocator::Subsystem::Elf);
+    let shared = flags & MAP_SHARED != 0;
+    let valid = len != 0
+        && len <= MMAP_MAX_LEN
+        && prot & !PROT_ALL == 0
+        && prot & (PROT_WRITE | PROT_EXEC) != PROT_WRITE | PROT_EXEC
+        && flags & !(MAP_SHARED | MAP_PRIVATE | MAP_ANONYMOUS) == 0
+        && shared != (flags & MAP_PRIVATE != 0)
+        && offset % PAGE_SIZE as u64 == 0;
+    if !valid {
+        return -1;
+    }
+    let Some(ctx) = context_slot().as_mut() else {
+        return -1;
+    };
+    if ctx.mappings.len() >= MAX_MAPPINGS {
+        return -2;
+    }
+    let Ok(layout) = core::alloc::Layout::from_size_align(len.next_multiple_of(PAGE_SIZE), PAGE_SIZE) else {
+        return -1;
+    };
+    // Fails (null) past the process's memory cap rather than panicking
+    let Some(ptr) = core::ptr::NonNull::new(unsafe { alloc::alloc::alloc_zeroed(layout) }) else {
+        return -2;
+    };
+    let mut mapping = Mapping { ptr, layout, len, write_back: None };
+
+    if flags & MAP_ANONYMOUS == 0 {
+        let offset = offset.min(usize::MAX as u64) as usize;
+        let writes_back = shared && prot & PROT_WRITE != 0;
+        // Dropping the mapping frees it
+        let Some(file) = regular_file(fd).filter(|file| file.readable() && (file.writable() || !writes_back)) else {
+            return -1;
+        };
+        let Ok(data) = fs_proxy::fs_read_at(&file.path, offset, len) else {
+            return -1;
+        };
+        unsafe { core::ptr::copy_nonoverlapping(data.as_ptr(), ptr.as_ptr(), data.len()) };
+        let path = file.path;
+        if writes_back {
+            mapping.write_back = Some((path, fd, offset));
+        }
+    }
+
+    if crate::elf_loader::map_user(ptr.as_ptr() as usize, layout.size(), prot & PROT_WRITE != 0, prot & PROT_EXEC != 0).is_err() {
+        return -2;
+    }
+    let addr = mapping.ptr.as_ptr() as i64;
+    ctx.mappings.push(mapping);
+    addr
+}
+
+fn sys_munmap(addr: u64, len: usize) -> i64 {
+    let Some(ctx) = context_slot().as_mut() else {
+        return -1;
+    };
+    // Only whole mappings can be unmapped
+    let index = ctx.mappings.iter().position(|m| {
+        m.ptr.as_ptr() as u64 == addr && len.next_multiple_of(PAGE_SIZE) == m.layout.size()
+    });
+    match index {
+        Some(index) => {
+            unmap(ctx.mappings.swap_remove(index));
+            0
+        }
+        None => -1,
+    }
+}
+
+/// Copy a shared mapping back into its file if that is still open, then
+/// free it. Only the part over the file's current contents is kept.
+fn unmap(mapping: Mapping) {
+    if let Some((path, fd, offset)) = &mapping.write_back {
+        let mapped = unsafe { slice::from_raw_parts(mapping.ptr.as_ptr(), mapping.len) };
+        if regular_file(*fd).is_some_and(|file| file.path == *path) {
+            if let Ok(current) = fs_proxy::fs_read_at(path, *offset, mapping.len) {
+                if current[..] != mapped[..current.len()] {
+                    let _ = fs_proxy::fs_write_at(path, *offset, &mapped[..current.len()]);
+                }
+            }
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Randomness Syscalls
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Most bytes one getrandom call fills
+const GETRANDOM_MAX: usize = 1024 * 1024;
+const GRND_NONBLOCK: u64 = 0x1;
+const GRND_RANDOM: u64 = 0x2;
+
+fn sys_getrandom(buf_ptr: *mut u8, buf_len: usize, flags: u64) -> i64 {
+    // The generator is seeded at boot, so neither flag changes anything
+    if buf_ptr.is_null() || flags & !(GRND_NONBLOCK | GRND_RANDOM) != 0 {
+        return -1;
+    }
+    let len = buf_len.min(GETRANDOM_MAX);
+    crate::random::fill(unsafe { slice::from_raw_parts_mut(buf_ptr, len) });
+    len as i64
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Clock Syscalls
+// ═══════════════════════════════════════════════════════════════════════════════
+
+const CLOCK_REALTIME: u64 = 0;
+const CLOCK_MONOTONIC: u64 = 1;
+
+fn sys_clock_gettime(clock_id: u64) -> i64 {
+    match clock_id {
+        CLOCK_REALTIME => crate::device::rtc::realtime_ns().map_or(-2, |ns| ns as i64),
+        CLOCK_MONOTONIC => crate::clint::get_time_ns() as i64,
+        _ => -1,
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Heap Accounting Syscalls
+// ═══════════════════════════════════════════════════════════════════════════════
+
+fn sys_memstat(buf_ptr: *mut u8, buf_len: usize) -> i64 {
+    let report = crate::allocator::subsystem_report();
+    unsafe { write_bytes(buf_ptr, report.as_bytes(), buf_len) }
+}
@@ -54,6 +54,8 @@ pub const SYS_FS_MKDIR: u64 = 26;
 pub const SYS_FS_IS_DIR: u64 = 27;
 /// List files in directory: fs_list_dir(path_ptr, path_len, buf_ptr, buf_len) -> i32
 pub const SYS_FS_LIST_DIR: u64 = 28;
+/// Flush dirty cached blocks to disk: fs_sync() -> i32
+pub const SYS_FS_SYNC: u64 = 29;
 
 // ═══════════════════════════════════════════════════════════════════════════════
 // Network Operations
@@ -65,15 +67,15 @@ pub const SYS_NET_AVAILABLE: u64 = 30;
 pub const SYS_DNS_RESOLVE: u64 = 31;
 /// Send ICMP ping: send_ping(ip_ptr, seq, timeout_ms, out_ptr) -> i32
 pub const SYS_SEND_PING: u64 = 32;
-/// TCP connect: tcp_connect(ip_ptr, port) -> i32
+/// TCP connect: tcp_connect(ip_ptr, port) -> i32 (socket handle, -1 on error)
 pub const SYS_TCP_CONNECT: u64 = 33;
-/// TCP send: tcp_send(data_ptr, data_len) -> i32
+/// TCP send: tcp_send(sock, data_ptr, data_len) -> i32
 pub const SYS_TCP_SEND: u64 = 34;
-/// TCP receive: tcp_recv(buf_ptr, buf_len) -> i32
+/// TCP receive: tcp_recv(sock, buf_ptr, buf_len) -> i32
 pub const SYS_TCP_RECV: u64 = 35;
-/// TCP close: tcp_close() -> i32
+/// TCP close: tcp_close(sock) -> i32
 pub const SYS_TCP_CLOSE: u64 = 36;
-/// TCP status: tcp_status() -> i32
+/// TCP status: tcp_status(sock) -> i32 (0=closed, 1=connecting, 2=connected, 3=failed)
 pub const SYS_TCP_STATUS: u64 = 37;
 /// HTTP GET: http_get(url_ptr, url_len, resp_ptr, resp_len) -> i32
 pub const SYS_HTTP_GET: u64 = 38;
@@ -91,9 +93,15 @@ pub const SYS_CONSOLE_READ: u64 = 41;
 // Process Management
 // ═══════════════════════════════════════════════════════════════════════════════
 
-/// List processes: ps_list(buf_ptr, buf_len) -> i32
+/// List processes, one line each:
+/// "pid:name:state:priority:cpu_time_ms:uptime_ms:cpu_permille:fs_read:fs_written:net_rx:net_tx:syscalls:heap:ppid:pgid"
+/// (byte counts; state R, S, T (stopped) or Z): ps_list(buf_ptr, buf_len) -> i32
 pub const SYS_PS_LIST: u64 = 50;
-/// Kill process: kill(pid) -> i32
+/// Send a signal: kill(pid, sig) -> i32
+/// - pid: process ID, or -pgid for every process in group pgid
+/// - sig: signal number (0 = SIGKILL)
+/// Returns 0 on success, -1 if there is no such process or group, -2 for
+/// PID 0, -3 for an invalid signal.
 pub const SYS_KILL: u64 = 51;
 /// Get CPU info: cpu_info(cpu_id, out_ptr) -> i32
 pub const SYS_CPU_INFO: u64 = 52;
@@ -106,7 +114,7 @@ pub const SYS_CPU_INFO: u64 = 52;
 pub const SYS_SHUTDOWN: u64 = 60;
 /// Check if cancel requested: should_cancel() -> i32
 pub const SYS_SHOULD_CANCEL: u64 = 61;
-/// Get random bytes: random(buf_ptr, buf_len) -> i32
+/// Get random bytes: random(buf_ptr, buf_len) -> i32 (SYS_GETRANDOM with no flags)
 pub const SYS_RANDOM: u64 = 62;
 /// Get environment variable: env_get(key_ptr, key_len, val_ptr, val_len) -> i32
 pub const SYS_ENV_GET: u64 = 63;
@@ -131,7 +139,10 @@ pub const SYS_SERVICE_RUNNING: u64 = 73;
 // ═══════════════════════════════════════════════════════════════════════════════
 
 /// Get network info: net_info(out_ptr, out_len) -> i32
-/// Returns: IP[4], MAC[6], Gateway[4], DNS[4], prefix_len[1] = 19 bytes
+/// Returns: IP[4], MAC[6], Gateway[4], DNS[4], prefix_len[1] = 19 bytes,
+/// then as room allows the interface counters as u64 LE: rx_packets,
+/// rx_bytes, rx_dropped, rx_errors, tx_packets, tx_bytes, tx_dropped,
+/// tx_errors (83 bytes in all)
 pub const SYS_NET_INFO: u64 = 80;
 
 /// Get heap statistics: heap_stats(out_ptr) -> i32
@@ -141,3 +152,591 @@ pub const SYS_HEAP_STATS: u64 = 81;
 /// Sleep: sleep_ms(milliseconds) -> i32
 pub const SYS_SLEEP: u64 = 82;
 
+// ═══════════════════════════════════════════════════════════════════════════════
+// File Descriptors
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Open file: open(path_ptr, path_len, flags) -> i32 (fd or -1)
+/// Flags: O_RDONLY=0, O_WRONLY=1, O_RDWR=2, O_CREAT=0x40, O_TRUNC=0x200, O_APPEND=0x400
+pub const SYS_OPEN: u64 = 90;
+/// Read from fd: read(fd, buf_ptr, buf_len) -> i32 (bytes read, 0 at EOF)
+pub const SYS_READ: u64 = 91;
+/// Write to fd: write(fd, data_ptr, data_len) -> i32 (bytes written)
+pub const SYS_WRITE: u64 = 92;
+/// Move fd offset: seek(fd, offset, whence) -> i64 (new offset)
+/// Whence: SEEK_SET=0, SEEK_CUR=1, SEEK_END=2
+pub const SYS_SEEK: u64 = 93;
+/// Close fd: close(fd) -> i32
+pub const SYS_CLOSE: u64 = 94;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Symbolic Links
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Create symlink: symlink(target_ptr, target_len, path_ptr, path_len) -> i32
+pub const SYS_SYMLINK: u64 = 100;
+/// Read symlink target: readlink(path_ptr, path_len, buf_ptr, buf_len) -> i32 (bytes or -1)
+pub const SYS_READLINK: u64 = 101;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Rename
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Rename/move file or directory: rename(from_ptr, from_len, to_ptr, to_len) -> i32
+pub const SYS_RENAME: u64 = 102;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Partial Writes
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Append to file: fs_append(path_ptr, path_len, data_ptr, data_len) -> i32 (bytes written)
+pub const SYS_FS_APPEND: u64 = 103;
+/// Write at offset: fs_pwrite(path_ptr, path_len, data_ptr, data_len, offset) -> i32 (bytes written)
+pub const SYS_FS_PWRITE: u64 = 104;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Kernel Modules
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Load signed WASM kernel module: kmod_load(path_ptr, path_len) -> i32
+pub const SYS_KMOD_LOAD: u64 = 105;
+/// Unload kernel module: kmod_unload(name_ptr, name_len) -> i32
+pub const SYS_KMOD_UNLOAD: u64 = 106;
+/// List kernel modules, one per line: kmod_list(buf_ptr, buf_len) -> i32 (bytes written)
+pub const SYS_KMOD_LIST: u64 = 107;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Boot Profiling
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Boot time breakdown as a text chart: bootchart(buf_ptr, buf_len) -> i32 (bytes written)
+pub const SYS_BOOTCHART: u64 = 108;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Hibernation
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Save the system to disk and power off: hibernate() -> i32 (0 after resume, -1 on error)
+pub const SYS_HIBERNATE: u64 = 109;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// QR Codes
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Encode text as a QR code and show it on screen:
+/// qrcode(text_ptr, text_len, out_ptr, out_len) -> i32 (modules per side, -1 on error).
+/// out receives size * size bytes, row by row, 1 for a dark module.
+pub const SYS_QRCODE: u64 = 110;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Tones
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Play a tone and wait for it to finish:
+/// beep(freq_hz, duration_ms, waveform) -> i32 (0 on success, -1 on error).
+/// waveform: 0 = sine, 1 = square
+pub const SYS_BEEP: u64 = 111;
+/// Turn UI event sounds on or off: event_sounds(mode) -> i32 (current state, 1 = on).
+/// mode: 0 = off, 1 = on, anything else = query only
+pub const SYS_EVENT_SOUNDS: u64 = 112;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Mounts
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Mount a 9P server over TCP: mount_9p(ip_ptr, port, path_ptr, path_len) -> i32 (0 on success)
+pub const SYS_MOUNT_9P: u64 = 113;
+/// Unmount a filesystem: umount(path_ptr, path_len) -> i32 (0 on success)
+pub const SYS_UMOUNT: u64 = 114;
+/// List mount points, one per line: mounts(buf_ptr, buf_len) -> i32 (bytes written)
+pub const SYS_MOUNTS: u64 = 115;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Display
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Set and/or query display color adjustment:
+/// display_adjust(brightness, contrast, gamma) -> i64 (current values, -1 on error).
+/// brightness and contrast are percent, gamma is hundredths; 0 keeps a value.
+/// Returns brightness | contrast << 16 | gamma << 32.
+pub const SYS_DISPLAY_ADJUST: u64 = 116;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Settings
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Read all system settings in settings.toml format:
+/// settings_get(buf_ptr, buf_len) -> i32 (bytes written)
+pub const SYS_SETTINGS_GET: u64 = 117;
+
+/// Change a setting ("section.key"), apply it live and save it:
+/// settings_set(key_ptr, key_len, value_ptr, value_len) -> i32 (0 or -1)
+pub const SYS_SETTINGS_SET: u64 = 118;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// IPv6
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// List IPv6 addresses and the default router, one per line
+/// ("inet6 <addr>/<len> scope link|global", "router <addr>"):
+/// net_info6(buf_ptr, buf_len) -> i32 (bytes written, -2 if no network)
+pub const SYS_NET_INFO6: u64 = 119;
+
+/// Send an ICMPv6 echo request and wait for the reply:
+/// ping6(ip16_ptr, seq, timeout_ms, out_ptr) -> i32 (0 with RTT in out, -1 timeout, -2 error)
+pub const SYS_PING6: u64 = 120;
+
+/// Resolve a hostname to an IPv6 address (AAAA record):
+/// dns_resolve6(host_ptr, host_len, ip16_buf_ptr, buf_len) -> i32 (16 on success, -1 on failure)
+pub const SYS_DNS_RESOLVE6: u64 = 121;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// DNS
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Empty the DNS cache and re-read /etc/hosts:
+/// dns_flush() -> i64 (answers dropped | host names loaded << 32)
+pub const SYS_DNS_FLUSH: u64 = 122;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// TCP Listeners
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Listen for TCP connections on a port (close with tcp_close):
+/// tcp_listen(port) -> i32 (listener handle, -1 on error)
+pub const SYS_TCP_LISTEN: u64 = 123;
+
+/// Take a waiting connection without blocking; the peer address (4 IPv4
+/// bytes, then the port big-endian) goes to addr_out if it is not null:
+/// tcp_accept(listener, addr_out) -> i32 (socket handle, -1 if none waiting, -2 on error)
+pub const SYS_TCP_ACCEPT: u64 = 124;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// File Watches
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Watch a file or directory tree for changes:
+/// watch(path_ptr, path_len, mask) -> i32 (fd or -1)
+/// Mask: CREATE=1, MODIFY=2, DELETE=4. read() on the fd returns one
+/// "<C|M|D> <path>\n" line per change ("O" when events were dropped) and 0
+/// when nothing happened since the last read. close() removes the watch.
+pub const SYS_WATCH: u64 = 125;
+
+/// Block until a watch fd has events, the timeout passes or the command is
+/// cancelled: watch_wait(fd, timeout_ms) -> i32 (1 events waiting, 0 none, -1 not a watch fd)
+pub const SYS_WATCH_WAIT: u64 = 126;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// HTTP Options
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// HTTP GET with flags: http_get_ex(url_ptr, url_len, resp_ptr, resp_len, flags) -> i32
+/// Flags: INSECURE=1 (skip HTTPS certificate validation)
+pub const SYS_HTTP_GET_EX: u64 = 127;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Heap Leak Detector
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Record heap allocations and report what was not freed:
+/// heapdiff(op, buf_ptr, buf_len) -> i32 (bytes of report written, -1 if not tracking)
+/// Ops: REPORT=0 (report so far, keep tracking), START=1, STOP=2 (report and stop)
+pub const SYS_HEAPDIFF: u64 = 128;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// WASM Pipelines
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Run WASM programs as a pipeline across the worker harts, each stage's
+/// output streamed into the next stage's console input:
+/// wasm_pipe(spec_ptr, spec_len) -> i32 (fd or -1)
+/// The spec has one "<path> [args...]" line per stage. read() on the fd
+/// waits for the last stage's output and returns 0 once every stage has
+/// exited; close() stops the pipeline's output.
+pub const SYS_WASM_PIPE: u64 = 129;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Input Record/Replay
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Record GUI input to a file, or replay one with screen checksum checks:
+/// input_replay(op, ptr, len, arg) -> i64
+/// Ops:
+/// - STATUS=0: ptr/len is a buffer for the last replay's report; returns
+///   its length | failed checks << 32, -2 while recording or replaying,
+///   -1 if nothing has been replayed
+/// - RECORD=1: ptr/len is the file to record to
+/// - CHECK=2: add a checkpoint for the rectangle x | y << 16 | w << 32 | h << 48
+///   in arg; returns the checksum
+/// - STOP=3: save the recording (returns entries saved) or stop a replay
+/// - REPLAY=4: ptr/len is the file to replay, arg the speed multiplier (1-16);
+///   returns the number of entries
+pub const SYS_INPUT_REPLAY: u64 = 130;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// HTTP Downloads
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Download a URL straight to a file, following redirects, without holding
+/// the body in memory: http_download(url_ptr, url_len, path_ptr, path_len, flags) -> i64
+/// Returns the bytes saved, -status for an HTTP error status (e.g. -404),
+/// or -1 on failure. Flags: INSECURE=1 (skip HTTPS certificate validation),
+/// PROGRESS=2 (print a progress line while downloading)
+pub const SYS_HTTP_DOWNLOAD: u64 = 131;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Shared Kernel Log
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Address of the shared kernel log page, for following the log without a
+/// syscall per poll: klog_map() -> i64 (see kernel/src/klog_page.rs for the
+/// layout and the sequence protocol; read-only)
+pub const SYS_KLOG_MAP: u64 = 132;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// WebSockets
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Open a WebSocket: ws_connect(url_ptr, url_len, flags) -> i64
+/// `ws://` or `wss://` URL; flags: INSECURE=1 (skip certificate validation).
+/// Returns a handle, or -1 on failure. Handles are closed when the program exits.
+pub const SYS_WS_CONNECT: u64 = 133;
+
+/// Send a message: ws_send(handle, data_ptr, data_len, flags) -> i64
+/// Flags: BINARY=1 (default is a text message). Returns data_len or -1.
+pub const SYS_WS_SEND: u64 = 134;
+
+/// Wait for a message: ws_recv(handle, buf_ptr, buf_len, timeout_ms) -> i64
+/// Returns 0 if none arrived in time, otherwise the bytes copied in the low
+/// 32 bits and the kind in the high 32 bits (1=text, 2=binary, 8=closed by
+/// the server); -1 on error. Pings are answered by the kernel.
+pub const SYS_WS_RECV: u64 = 135;
+
+/// Close a WebSocket: ws_close(handle) -> i64 (0 on success)
+pub const SYS_WS_CLOSE: u64 = 136;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Stress Test
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Run the release soak test: stress(op, a1, a2, a3, a4) -> i64
+/// - STATUS=0: a1/a2 is a buffer for the report; returns its length in the
+///   low 32 bits and the state in the high 32 bits (1=running, 2=passed,
+///   3=failed), or -1 if nothing has run yet
+/// - START=1: a1 = cpu | io << 16 | net << 32 worker counts, a2 = seconds,
+///   a3/a4 = URL for the net workers (empty for the local httpd); returns 0,
+///   or -1 if a run is in progress or the arguments are invalid
+/// - STOP=2: end the current run early; returns 0, or -1 if none is running
+pub const SYS_STRESS: u64 = 137;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Neighbor Table
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// List the IPv4 neighbor (ARP) table, one entry per line
+/// ("<ip> <mac> <age_ms> reachable|stale"), sorted by address; stale entries
+/// have outlived smoltcp's cache and will be resolved again on the next send:
+/// net_neighbors(buf_ptr, buf_len) -> i32 (bytes written, -2 if no network)
+pub const SYS_NET_NEIGHBORS: u64 = 138;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Firewall
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Manage the packet filter (/etc/firewall.rules): firewall(op, ptr, len) -> i64
+/// - LIST=0: ptr/len is a buffer for "policy <allow|deny> <dropped>" followed
+///   by "<hits> <rule>" lines in order; returns bytes written
+/// - RELOAD=1: re-read the rule file; returns the number of rules
+/// - ADD=2: ptr/len is a rule or policy line to append to the file
+/// - DELETE=3: ptr is the 1-based number of the rule to remove from the file
+/// ADD and DELETE return the number of rules now in force. All return -1 on
+/// error (the reason is logged); a rejected file leaves the rules unchanged.
+pub const SYS_FIREWALL: u64 = 139;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Filesystem Format
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Root filesystem format revision: sfs_format(op, buf_ptr, buf_len) -> i64
+/// - STATUS=0: writes "format <major.minor>", "latest <major.minor>",
+///   "features <names...>" and "mode rw|ro" lines; returns bytes written
+/// - UPGRADE=1: migrate the image in place to the latest revision; returns 1
+///   if it was upgraded, 0 if it already was current, -1 on error (logged)
+pub const SYS_SFS_FORMAT: u64 = 140;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Clipboard
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Kernel clipboard, shared with the GUI and the page embedding the emulator:
+/// clipboard(op, ptr, len) -> i64
+/// - GET=0: ptr/len is a buffer for the clipboard text; returns bytes written
+/// - SET=1: ptr/len is the new text (len 0 clears it), also handed to the
+///   page when the host bridge is there; returns 0
+/// Both return -1 on a bad op or pointer.
+pub const SYS_CLIPBOARD: u64 = 141;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Traceroute
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Send one traceroute probe and wait for its answer:
+/// trace_probe(ip_ptr, hop_limit | proto << 8, seq, timeout_ms, out_ptr) -> i64
+/// - ip_ptr: 4-byte IPv4 target; proto: 0 = ICMP echo, 1 = UDP
+/// - seq: probe number (0 starts a new trace); UDP probes go to port 33434 + seq
+/// - out_ptr: 12 bytes: address that answered, round trip ms (u32 LE) and
+///   ICMP code (u32 LE, for unreachable)
+/// Returns 0 if a router answered Time Exceeded, 1 if the target answered,
+/// 2 if the probe was reported unreachable, -1 on timeout, -2 on error.
+pub const SYS_TRACE_PROBE: u64 = 142;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Socket Statistics
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// List sockets with their traffic counters: net_stats(buf_ptr, buf_len) -> i64
+/// Writes one line per socket:
+/// "<tcp|udp> <local> <remote|-> <state> <bytes_in> <bytes_out> <packets_in>
+///  <packets_out> <retransmits> <srtt_ms>"
+/// Endpoints are "addr:port" ("[addr]:port" for IPv6, "*" for any address).
+/// Returns bytes written, -2 if the network is down.
+pub const SYS_NET_STATS: u64 = 143;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// DNS Records
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Ask the DNS server for records of any type, bypassing /etc/hosts and the
+/// cache: dns_query(host_ptr, host_len, qtype, buf_ptr, buf_len) -> i64
+/// - qtype: record type number (1 = A, 5 = CNAME, 16 = TXT, 28 = AAAA, 33 = SRV)
+/// Writes one "<name> <ttl> <TYPE> <data>" line per record, aliases leading
+/// to the answer included. Returns bytes written (0 if the name has no such
+/// records), -2 if the name does not exist, -1 on error.
+pub const SYS_DNS_QUERY: u64 = 144;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Process Priority
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Read a process's nice value: get_nice(pid) -> i64
+/// - pid: process ID, or 0 for the process running the caller
+/// Returns the nice value (-20..19, lower gets more CPU), or -100 if there is
+/// no such process.
+pub const SYS_GET_NICE: u64 = 145;
+
+/// Change a process's nice value: set_nice(pid, nice) -> i64
+/// - pid: process ID, or 0 for the process running the caller
+/// - nice: new value, clamped to -20..19
+/// Returns 0 on success, -1 if there is no such process.
+pub const SYS_SET_NICE: u64 = 146;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// CPU Affinity
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Read the harts a process may run on: get_affinity(pid, mask_ptr) -> i64
+/// - pid: process ID, or 0 for the process running the caller
+/// - mask_ptr: 16 bytes, receives the mask (u128 LE, bit N = hart N)
+/// Returns 0 on success, -1 if there is no such process.
+pub const SYS_GET_AFFINITY: u64 = 147;
+
+/// Restrict a process to a set of harts: set_affinity(pid, mask_ptr) -> i64
+/// - pid: process ID, or 0 for the process running the caller
+/// - mask_ptr: 16 bytes, the mask (u128 LE, bit N = hart N)
+/// Returns 0 on success, -1 if there is no such process, -2 if the mask has
+/// no hart that exists.
+pub const SYS_SET_AFFINITY: u64 = 148;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Exit Status
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Wait for any child of the calling process to exit: wait(status_ptr) -> i64
+/// - status_ptr: 4 bytes, receives the exit code (i32 LE); may be null
+/// Returns the child's PID, or -1 if there are no children (or the command
+/// was cancelled).
+pub const SYS_WAIT: u64 = 149;
+
+/// Wait for a child to exit: waitpid(pid, status_ptr, flags) -> i64
+/// - pid: child PID, or 0 for any child
+/// - status_ptr: 4 bytes, receives the exit code (i32 LE); may be null
+/// - flags: WNOHANG (1) returns 0 at once if the child is still running
+/// Returns the child's PID, 0 (WNOHANG only), or -1 if there is no such
+/// child (or the command was cancelled).
+pub const SYS_WAITPID: u64 = 150;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Spawn
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Start an ELF binary as a child process: spawn(path_ptr, path_len, args_ptr, args_len) -> i64
+/// - path: resolved like a shell command (/usr/bin/<name>, then /<name>)
+/// - args: space-separated arguments; may be empty
+/// Returns the child's PID (collect it with wait/waitpid), -1 if the binary
+/// was not found, -2 if it is not a valid ELF binary.
+pub const SYS_SPAWN: u64 = 151;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Signals
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Set how the calling binary handles a signal: signal(sig, handler, restorer) -> i64
+/// - sig: any signal but SIGKILL (9) and SIGSTOP (19), which cannot be
+///   changed; SIGTSTP (20) stops the program and SIGCONT (18) is dropped by
+///   default
+/// - handler: 0 = default (end the program with 128 + sig), 1 = ignore,
+///   otherwise the address of `extern "C" fn(sig: i32)`
+/// - restorer: where handlers return to; it must make SYS_SIGRETURN
+///   without touching the stack
+/// Returns the previous handler, or -1 for an invalid signal.
+pub const SYS_SIGNAL: u64 = 152;
+
+/// Return from a signal handler to the code it interrupted: sigreturn()
+/// Does not return to the caller; -1 if no handler is running.
+pub const SYS_SIGRETURN: u64 = 153;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Message Queues
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Open a named message queue, creating it if needed: mq_open(name_ptr, name_len, capacity) -> i64
+/// - capacity: messages it holds when created (0 = 32)
+/// Returns the queue ID, or -1 for an invalid name.
+pub const SYS_MQ_OPEN: u64 = 154;
+
+/// Send a message: mq_send(id, data_ptr, data_len, timeout_ms) -> i64
+/// - data: at most 4096 bytes
+/// - timeout_ms: how long to wait while the queue is full (0 = not at all,
+///   u64::MAX = forever)
+/// Returns 0, -1 if there is no such queue (or it was removed, or the data
+/// is too large), -2 if it stayed full.
+pub const SYS_MQ_SEND: u64 = 155;
+
+/// Receive the oldest message: mq_recv(id, buf_ptr, buf_len, timeout_ms) -> i64
+/// - timeout_ms: how long to wait for one (0 = not at all, u64::MAX = forever)
+/// Returns the message length (longer messages are truncated to buf_len),
+/// -1 if there is no such queue (or it was removed and is empty), -2 if no
+/// message arrived.
+pub const SYS_MQ_RECV: u64 = 156;
+
+/// Remove a message queue: mq_unlink(name_ptr, name_len) -> i64
+/// Returns 0, or -1 if there is no such queue.
+pub const SYS_MQ_UNLINK: u64 = 157;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Memory Limits
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Read a process's heap use and cap: get_mem_limit(pid, out_ptr) -> i64
+/// - pid: process ID, or 0 for the process running the caller
+/// - out_ptr: 16 bytes, receives bytes in use (u64 LE) then the cap (u64
+///   LE, 0 = none)
+/// Returns 0 on success, -1 if there is no such process.
+pub const SYS_GET_MEM_LIMIT: u64 = 158;
+
+/// Cap a process's heap use: set_mem_limit(pid, bytes) -> i64
+/// - pid: process ID, or 0 for the process running the caller
+/// - bytes: the cap (0 = none); allocations that would go past it fail
+/// Returns 0 on success, -1 if there is no such process, -2 if no more
+/// processes can be tracked.
+pub const SYS_SET_MEM_LIMIT: u64 = 159;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// CPU Statistics
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// CPU usage over the last few seconds: proc_stats(buf_ptr, buf_len) -> i64
+/// First "all:usage:harts:processes", then one "cpuN:usage:pid:switches"
+/// line per online hart (usage in permille of one hart; pid 0 = idle).
+/// Per-process usage is the cpu_permille field of SYS_PS_LIST.
+/// Returns bytes written (truncated to buf_len).
+pub const SYS_PROC_STATS: u64 = 160;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Process Groups
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Move a process into a process group: setpgid(pid, pgid) -> i64
+/// - pid: the caller's process or one of its children (0 = the caller's)
+/// - pgid: its own PID (0) starts a new group, anything else must be a
+///   group with live members
+/// Returns 0 on success, -1 for any other process, -2 if there is no
+/// such group.
+pub const SYS_SETPGID: u64 = 161;
+
+/// Process group of a process: getpgid(pid) -> i64
+/// - pid: process ID, or 0 for the process running the caller
+/// Returns the group ID, or -1 if there is no such process.
+pub const SYS_GETPGID: u64 = 162;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Polling
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Wait until any of several descriptors is ready:
+/// poll(entries_ptr, count, timeout_ms) -> i64
+/// - entries: `count` (at most 64) 8-byte entries, each an i32 fd, a u16
+///   `events` mask and a u16 `revents` the kernel fills in. A negative fd
+///   is skipped.
+/// - events: POLLIN=0x1 (data or end of stream to read, a connection to
+///   accept), POLLOUT=0x4 (room to write); POLLSOCK=0x100 marks the fd as
+///   a TCP socket or listener handle instead of a file descriptor.
+///   POLLERR=0x8, POLLHUP=0x10 (pipe writer or TCP peer gone) and
+///   POLLNVAL=0x20 (not open) are reported whether asked for or not.
+/// - timeout_ms: 0 only checks, u64::MAX waits forever
+/// Returns the number of entries with revents set, 0 on timeout, -1 on
+/// invalid arguments, -2 if interrupted (Ctrl+C or a signal).
+pub const SYS_POLL: u64 = 163;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Memory Mappings
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Map memory: mmap(addr, len, prot, flags, fd, offset) -> i64
+/// - addr: ignored (the kernel picks the address)
+/// - len: bytes (at most 32 MiB), rounded up to whole 4 KiB pages
+/// - prot: PROT_READ=1 | PROT_WRITE=2 | PROT_EXEC=4 (recorded, not enforced)
+/// - flags: MAP_SHARED=1 or MAP_PRIVATE=2, plus MAP_ANONYMOUS=0x20 for
+///   zeroed memory instead of a file
+/// - fd, offset: open file and page-aligned offset to map (ignored for
+///   anonymous mappings). A shared, writable mapping is written back to
+///   the file when unmapped; the file must be open for writing.
+/// Returns the address, -1 on invalid arguments, -2 if out of memory (or
+/// over the process's cap, or 32 mappings already held).
+/// Mappings are freed when the binary exits.
+pub const SYS_MMAP: u64 = 164;
+
+/// Unmap memory from SYS_MMAP: munmap(addr, len) -> i64
+/// Returns 0, or -1 unless addr and len are a whole mapping.
+pub const SYS_MUNMAP: u64 = 165;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Randomness
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Fill a buffer from the kernel CSPRNG: getrandom(buf_ptr, buf_len, flags) -> i64
+/// - flags: GRND_NONBLOCK=1, GRND_RANDOM=2 (accepted for compatibility;
+///   the generator is seeded at boot and never blocks)
+/// Returns the bytes filled (at most 1 MiB per call), -1 on a null buffer
+/// or unknown flags. The output is suitable for keys and tokens.
+pub const SYS_GETRANDOM: u64 = 166;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Clocks
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Read a clock in nanoseconds: clock_gettime(clock_id) -> i64
+/// - CLOCK_REALTIME=0: since the Unix epoch, from the RTC (-2 without one)
+/// - CLOCK_MONOTONIC=1: since boot, never goes back (100 ns resolution)
+/// Returns -1 for an unknown clock.
+pub const SYS_CLOCK_GETTIME: u64 = 167;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Heap Accounting
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Kernel heap use per subsystem: memstat(buf, len) -> i64
+/// Writes a table of current bytes and blocks, peak bytes and allocations
+/// per subsystem (net, fs, wasm, ui, ...) and returns the bytes written, -1
+/// on a bad buffer.
+pub const SYS_MEMSTAT: u64 = 168;
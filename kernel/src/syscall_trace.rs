@@ -0,0 +1,267 @@
+//! Syscall Tracing
+//!
+//! A process can have its syscalls traced (`strace`): every syscall it
+//! makes is logged with its name, decoded arguments and return value,
+//!
+//! ```text
+//! [12] open("/etc/motd", 0) = 3
+//! [12] read(3, 0x80a41000, 4096) = 127
+//! ```
+//!
+//! either to the kernel log or to `/var/log/strace.<pid>.log`. Arguments
+//! the caller passes as (pointer, length) text, such as paths and data
+//! written, are shown as strings (up to `MAX_STRING` bytes); buffers the
+//! kernel fills are shown as addresses.
+//!
+//! Only native binaries go through `handle_syscall`; WASM programs call
+//! their host functions directly and are not traced.
+
+use alloc::format;
+use alloc::string::String;
+use core::fmt::Write;
+
+use crate::cpu::process::PROCESS_TABLE;
+use crate::syscall_numbers::*;
+
+/// Bytes of a string argument shown before it is cut off
+const MAX_STRING: usize = 48;
+
+/// Where a process's syscalls are traced
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum TraceMode {
+    Off = 0,
+    /// Kernel log (dmesg)
+    Klog = 1,
+    /// `/var/log/strace.<pid>.log`
+    File = 2,
+}
+
+impl TraceMode {
+    pub fn from_u8(value: u8) -> Self {
+        match value {
+            1 => TraceMode::Klog,
+            2 => TraceMode::File,
+            _ => TraceMode::Off,
+        }
+    }
+}
+
+/// Trace file of process `pid`
+pub fn log_path(pid: u32) -> String {
+    format!("/var/log/strace.{}.log", pid)
+}
+
+/// Name and argument layout of syscall `num`. Each layout character
+/// consumes arguments in order: `d` a decimal number, `x` an address,
+/// `s` a (pointer, length) pair shown as a string.
+fn signature(num: u64) -> Option<(&'static str, &'static str)> {
+    Some(match num {
+        SYS_PRINT => ("print", "s"),
+        SYS_TIME => ("time", ""),
+        SYS_EXIT => ("exit", "d"),
+        SYS_ARG_COUNT => ("arg_count", ""),
+        SYS_ARG_GET => ("arg_get", "dxd"),
+        SYS_CWD_GET => ("cwd_get", "xd"),
+        SYS_CWD_SET => ("cwd_set", "s"),
+        SYS_FS_EXISTS => ("fs_exists", "s"),
+        SYS_FS_READ => ("fs_read", "sxd"),
+        SYS_FS_WRITE => ("fs_write", "ss"),
+        SYS_FS_LIST => ("fs_list", "xd"),
+        SYS_FS_STAT => ("fs_stat", "sx"),
+        SYS_FS_REMOVE => ("fs_remove", "s"),
+        SYS_FS_MKDIR => ("fs_mkdir", "s"),
+        SYS_FS_IS_DIR => ("fs_is_dir", "s"),
+        SYS_FS_LIST_DIR => ("fs_list_dir", "sxd"),
+        SYS_FS_SYNC => ("fs_sync", ""),
+        SYS_NET_AVAILABLE => ("net_available", ""),
+        SYS_DNS_RESOLVE => ("dns_resolve", "sxd"),
+        SYS_SEND_PING => ("send_ping", "xddx"),
+        SYS_TCP_CONNECT => ("tcp_connect", "xd"),
+        SYS_TCP_SEND => ("tcp_send", "ds"),
+        SYS_TCP_RECV => ("tcp_recv", "dxd"),
+        SYS_TCP_CLOSE => ("tcp_close", "d"),
+        SYS_TCP_STATUS => ("tcp_status", "d"),
+        SYS_HTTP_GET => ("http_get", "sxd"),
+        SYS_CONSOLE_AVAILABLE => ("console_available", ""),
+        SYS_CONSOLE_READ => ("console_read", "xd"),
+        SYS_PS_LIST => ("ps_list", "xd"),
+        SYS_KILL => ("kill", "dd"),
+        SYS_CPU_INFO => ("cpu_info", "dx"),
+        SYS_SHUTDOWN => ("shutdown", ""),
+        SYS_SHOULD_CANCEL => ("should_cancel", ""),
+        SYS_RANDOM => ("random", "xd"),
+        SYS_ENV_GET => ("env_get", "sxd"),
+        SYS_KLOG_GET => ("klog_get", "dxd"),
+        SYS_SERVICE_LIST => ("service_list", "xd"),
+        SYS_SERVICE_START => ("service_start", "s"),
+        SYS_SERVICE_STOP => ("service_stop", "s"),
+        SYS_SERVICE_RUNNING => ("service_running", "xd"),
+        SYS_NET_INFO => ("net_info", "xd"),
+        SYS_HEAP_STATS => ("heap_stats", "x"),
+        SYS_SLEEP => ("sleep", "d"),
+        SYS_OPEN => ("open", "sd"),
+        SYS_READ => ("read", "dxd"),
+        SYS_WRITE => ("write", "ds"),
+        SYS_SEEK => ("seek", "ddd"),
+        SYS_CLOSE => ("close", "d"),
+        SYS_SYMLINK => ("symlink", "ss"),
+        SYS_READLINK => ("readlink", "sxd"),
+        SYS_RENAME => ("rename", "ss"),
+        SYS_FS_APPEND => ("fs_append", "ss"),
+        SYS_FS_PWRITE => ("fs_pwrite", "ssd"),
+        SYS_KMOD_LOAD => ("kmod_load", "s"),
+        SYS_KMOD_UNLOAD => ("kmod_unload", "s"),
+        SYS_KMOD_LIST => ("kmod_list", "xd"),
+        SYS_BOOTCHART => ("bootchart", "xd"),
+        SYS_HIBERNATE => ("hibernate", ""),
+        SYS_QRCODE => ("qrcode", "sxd"),
+        SYS_BEEP => ("beep", "ddd"),
+        SYS_EVENT_SOUNDS => ("event_sounds", "d"),
+        SYS_MOUNT_9P => ("mount_9p", "xds"),
+        SYS_UMOUNT => ("umount", "s"),
+        SYS_MOUNTS => ("mounts", "xd"),
+        SYS_DISPLAY_ADJUST => ("display_adjust", "ddd"),
+        SYS_SETTINGS_GET => ("settings_get", "xd"),
+        SYS_SETTINGS_SET => ("settings_set", "ss"),
+        SYS_NET_INFO6 => ("net_info6", "xd"),
+        SYS_PING6 => ("ping6", "xddx"),
+        SYS_DNS_RESOLVE6 => ("dns_resolve6", "sxd"),
+        SYS_DNS_FLUSH => ("dns_flush", ""),
+        SYS_TCP_LISTEN => ("tcp_listen", "d"),
+        SYS_TCP_ACCEPT => ("tcp_accept", "dx"),
+        SYS_WATCH => ("watch", "sd"),
+        SYS_WATCH_WAIT => ("watch_wait", "dd"),
+        SYS_HTTP_GET_EX => ("http_get_ex", "sxdd"),
+        SYS_HEAPDIFF => ("heapdiff", "dxd"),
+        SYS_WASM_PIPE => ("wasm_pipe", "s"),
+        SYS_INPUT_REPLAY => ("input_replay", "dxdd"),
+        SYS_HTTP_DOWNLOAD => ("http_download", "ssd"),
+        SYS_KLOG_MAP => ("klog_map", ""),
+        SYS_WS_CONNECT => ("ws_connect", "sd"),
+        SYS_WS_SEND => ("ws_send", "dsd"),
+        SYS_WS_RECV => ("ws_recv", "dxdd"),
+        SYS_WS_CLOSE => ("ws_close", "d"),
+        SYS_STRESS => ("stress", "ddddd"),
+        SYS_NET_NEIGHBORS => ("net_neighbors", "xd"),
+        SYS_FIREWALL => ("firewall", "dxd"),
+        SYS_SFS_FORMAT => ("sfs_format", "dxd"),
+        SYS_CLIPBOARD => ("clipboard", "dxd"),
+        SYS_TRACE_PROBE => ("trace_probe", "xdddx"),
+        SYS_NET_STATS => ("net_stats", "xd"),
+        SYS_DNS_QUERY => ("dns_query", "sdxd"),
+        SYS_GET_NICE => ("get_nice", "d"),
+        SYS_SET_NICE => ("set_nice", "dd"),
+        SYS_GET_AFFINITY => ("get_affinity", "dx"),
+        SYS_SET_AFFINITY => ("set_affinity", "dx"),
+        SYS_WAIT => ("wait", "x"),
+        SYS_WAITPID => ("waitpid", "dxd"),
+        SYS_SPAWN => ("spawn", "ss"),
+        SYS_SIGNAL => ("signal", "ddd"),
+        SYS_SIGRETURN => ("sigreturn", ""),
+        SYS_MQ_OPEN => ("mq_open", "sd"),
+        SYS_MQ_SEND => ("mq_send", "dsd"),
+        SYS_MQ_RECV => ("mq_recv", "dxdd"),
+        SYS_MQ_UNLINK => ("mq_unlink", "s"),
+        SYS_GET_MEM_LIMIT => ("get_mem_limit", "dx"),
+        SYS_SET_MEM_LIMIT => ("set_mem_limit", "dd"),
+        SYS_PROC_STATS => ("proc_stats", "xd"),
+        SYS_SETPGID => ("setpgid", "dd"),
+        SYS_GETPGID => ("getpgid", "d"),
+        SYS_POLL => ("poll", "xdd"),
+        SYS_MMAP => ("mmap", "xddddd"),
+        SYS_MUNMAP => ("munmap", "xd"),
+        SYS_GETRANDOM => ("getrandom", "xdd"),
+        SYS_CLOCK_GETTIME => ("clock_gettime", "d"),
+        SYS_MEMSTAT => ("memstat", "xd"),
+        _ => return None,
+    })
+}
+
+/// Append `ptr[..len]` to `line` as a quoted, escaped string
+fn push_string(line: &mut String, ptr: u64, len: u64) {
+    if ptr == 0 {
+        line.push_str("NULL");
+        return;
+    }
+    let shown = (len as usize).min(MAX_STRING);
+    // The syscall has already read these bytes, so they are valid
+    let bytes = unsafe { core::slice::from_raw_parts(ptr as *const u8, shown) };
+    line.push('"');
+    for &b in bytes {
+        match b {
+            b'"' => line.push_str("\\\""),
+            b'\\' => line.push_str("\\\\"),
+            b'\n' => line.push_str("\\n"),
+            b'\t' => line.push_str("\\t"),
+            0x20..=0x7e => line.push(b as char),
+            _ => {
+                let _ = write!(line, "\\x{:02x}", b);
+            }
+        }
+    }
+    line.push('"');
+    if len as usize > shown {
+        line.push_str("...");
+    }
+}
+
+/// One trace line: `name(args) = result`
+fn format_call(num: u64, args: &[u64; 6], result: i64) -> String {
+    let mut line = String::new();
+    match signature(num) {
+        Some((name, layout)) => {
+            line.push_str(name);
+            line.push('(');
+            let mut next = 0;
+            for (i, kind) in layout.chars().enumerate() {
+                if i > 0 {
+                    line.push_str(", ");
+                }
+                let Some(&value) = args.get(next) else {
+                    break;
+                };
+                match kind {
+                    's' => {
+                        push_string(&mut line, value, args.get(next + 1).copied().unwrap_or(0));
+                        next += 2;
+                        continue;
+                    }
+                    'x' => {
+                        let _ = write!(line, "{:#x}", value);
+                    }
+                    _ => {
+                        let _ = write!(line, "{}", value as i64);
+                    }
+                }
+                next += 1;
+            }
+            line.push(')');
+        }
+        None => {
+            let _ = write!(line, "syscall_{}({:#x}, {:#x}, {:#x})", num, args[0], args[1], args[2]);
+        }
+    }
+    let _ = write!(line, " = {}", result);
+    line
+}
+
+/// Log a finished syscall if the calling process is traced
+pub fn record(num: u64, args: &[u64; 6], result: i64) {
+    let Some(pid) = crate::cpu::CPU_TABLE.get(crate::get_hart_id()).and_then(|cpu| cpu.running_process()) else {
+        return;
+    };
+    let Some(process) = PROCESS_TABLE.get(pid) else {
+        return;
+    };
+    match process.trace() {
+        TraceMode::Off => {}
+        TraceMode::Klog => {
+            crate::services::klogd::klog_info("strace", &format!("[{}] {}", pid, format_call(num, args, result)));
+        }
+        TraceMode::File => {
+            let line = format!("{} {}\n", crate::get_time_ms(), format_call(num, args, result));
+            let _ = crate::cpu::fs_proxy::fs_append(&log_path(pid), line.as_bytes());
+        }
+    }
+}
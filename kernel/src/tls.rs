@@ -6,7 +6,9 @@
 //! ## Features
 //! - TLS 1.3 handshake with modern cipher suites
 //! - Blocking I/O wrapper for smoltcp TCP sockets
-//! - Certificate verification disabled for development (NoVerify)
+//! - Certificate verification disabled (NoVerify), so `commands::http` only
+//!   uses this path for `--insecure` requests; verified requests go through
+//!   `tls12`, which checks the chain against the root CA store (`x509`)
 //!
 //! ## Architecture
 //! The TLS implementation uses a single-request-per-connection model:
@@ -22,85 +24,12 @@
 use alloc::vec::Vec;
 use embedded_io::{ErrorType, Read, Write};
 
+use crate::random::KernelRng;
+
 // Re-export embedded-tls types we use
 pub use embedded_tls::blocking::{Aes128GcmSha256, NoVerify, TlsConfig, TlsConnection, TlsContext};
 pub use embedded_tls::TlsError as EmbeddedTlsError;
 
-// ═══════════════════════════════════════════════════════════════════════════════
-// SIMPLE RNG - Using timer-based entropy
-// ═══════════════════════════════════════════════════════════════════════════════
-
-/// Simple RNG using CLINT timer as entropy source.
-///
-/// Note: This is NOT cryptographically secure in a production sense,
-/// but provides functional randomness for TLS handshakes in our
-/// bare-metal environment. For production use, consider adding
-/// a hardware RNG or entropy accumulator.
-pub struct SimpleRng {
-    state: u64,
-}
-
-impl SimpleRng {
-    pub fn new() -> Self {
-        // Seed from timer
-        const CLINT_MTIME: usize = 0x0200_BFF8;
-        let seed = unsafe { core::ptr::read_volatile(CLINT_MTIME as *const u64) };
-        // Mix in some additional entropy from multiple timer reads
-        let mut state = seed ^ 0xdeadbeef_cafebabe;
-        for _ in 0..10 {
-            let t = unsafe { core::ptr::read_volatile(CLINT_MTIME as *const u64) };
-            state = state.wrapping_mul(6364136223846793005).wrapping_add(t);
-        }
-        Self { state }
-    }
-
-    fn next_u64(&mut self) -> u64 {
-        // xorshift128+ style PRNG for better quality
-        let mut s = self.state;
-        s ^= s << 13;
-        s ^= s >> 7;
-        s ^= s << 17;
-        self.state = s;
-        s
-    }
-}
-
-impl Default for SimpleRng {
-    fn default() -> Self {
-        Self::new()
-    }
-}
-
-impl rand_core::RngCore for SimpleRng {
-    fn next_u32(&mut self) -> u32 {
-        self.next_u64() as u32
-    }
-
-    fn next_u64(&mut self) -> u64 {
-        SimpleRng::next_u64(self)
-    }
-
-    fn fill_bytes(&mut self, dest: &mut [u8]) {
-        let mut i = 0;
-        while i < dest.len() {
-            let r = self.next_u64().to_le_bytes();
-            let remaining = dest.len() - i;
-            let to_copy = remaining.min(8);
-            dest[i..i + to_copy].copy_from_slice(&r[..to_copy]);
-            i += to_copy;
-        }
-    }
-
-    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
-        self.fill_bytes(dest);
-        Ok(())
-    }
-}
-
-// Required for TLS - marks this as suitable for cryptographic use
-// WARNING: In production, use a proper CSPRNG with hardware entropy
-impl rand_core::CryptoRng for SimpleRng {}
-
 // ═══════════════════════════════════════════════════════════════════════════════
 // ERROR TYPES
 // ═══════════════════════════════════════════════════════════════════════════════
@@ -126,6 +55,8 @@ pub enum TlsError {
     DnsError,
     /// Internal error
     InternalError,
+    /// Server certificate failed validation
+    BadCertificate,
 }
 
 impl core::fmt::Display for TlsError {
@@ -140,6 +71,7 @@ impl core::fmt::Display for TlsError {
             TlsError::NotConnected => write!(f, "Not connected"),
             TlsError::DnsError => write!(f, "DNS error"),
             TlsError::InternalError => write!(f, "Internal error"),
+            TlsError::BadCertificate => write!(f, "Certificate not trusted"),
         }
     }
 }
@@ -169,30 +101,84 @@ impl From<EmbeddedTlsError> for TlsError {
 // BLOCKING TCP SOCKET
 // ═══════════════════════════════════════════════════════════════════════════════
 
+/// Longest wait between polls when no frame arrives, so smoltcp's
+/// retransmit and keepalive timers still run (ms)
+const POLL_WAIT_MS: i64 = 10;
+
 /// Blocking TCP socket that implements embedded-io traits.
 /// This allows embedded-tls to use our smoltcp-based TCP stack.
 ///
-/// The socket holds mutable references to the network state and timing function,
-/// and provides blocking read/write operations with timeout support.
+/// The socket reaches the network state either through a mutable reference
+/// held for its whole life, or (`shared`) by locking `NET_STATE` for each
+/// operation, and provides blocking read/write operations with timeout
+/// support. The TCP connection is closed when the socket is dropped.
 pub struct BlockingTcpSocket<'a> {
-    net: &'a mut crate::net::NetState,
+    net: NetHandle<'a>,
+    /// Socket table slot, once connected
+    socket: Option<crate::net::TcpSocketId>,
     timeout_ms: i64,
     get_time: fn() -> i64,
     start_time: i64,
 }
 
+/// How a `BlockingTcpSocket` reaches the network stack
+enum NetHandle<'a> {
+    /// Borrowed for the socket's lifetime (the caller holds `NET_STATE`)
+    Borrowed(&'a mut crate::net::NetState),
+    /// `NET_STATE` locked for each operation, so the caller can do other
+    /// work (e.g. file I/O through hart 0) between reads
+    Shared,
+}
+
 impl<'a> BlockingTcpSocket<'a> {
     /// Create a new blocking TCP socket wrapper.
     pub fn new(net: &'a mut crate::net::NetState, timeout_ms: i64, get_time: fn() -> i64) -> Self {
         let start_time = get_time();
         Self {
-            net,
+            net: NetHandle::Borrowed(net),
+            socket: None,
             timeout_ms,
             get_time,
             start_time,
         }
     }
 
+    /// Create a socket that locks `NET_STATE` only while it polls, sends
+    /// or receives.
+    pub fn shared(timeout_ms: i64, get_time: fn() -> i64) -> BlockingTcpSocket<'static> {
+        BlockingTcpSocket {
+            net: NetHandle::Shared,
+            socket: None,
+            timeout_ms,
+            get_time,
+            start_time: get_time(),
+        }
+    }
+
+    /// Run `f` on the network state
+    fn with_net<R>(&mut self, f: impl FnOnce(&mut crate::net::NetState) -> R) -> Result<R, TlsError> {
+        match &mut self.net {
+            NetHandle::Borrowed(net) => Ok(f(net)),
+            NetHandle::Shared => {
+                let mut guard = crate::NET_STATE.lock();
+                guard.as_mut().map(f).ok_or(TlsError::NotConnected)
+            }
+        }
+    }
+
+    /// Wrap a connection accepted on a listening socket (for TLS servers).
+    /// Like `shared`, `NET_STATE` is only locked per operation, so the server
+    /// can run handlers that use the network between reads and writes.
+    pub fn accepted(
+        socket: crate::net::TcpSocketId,
+        timeout_ms: i64,
+        get_time: fn() -> i64,
+    ) -> BlockingTcpSocket<'static> {
+        let mut wrapped = Self::shared(timeout_ms, get_time);
+        wrapped.socket = Some(socket);
+        wrapped
+    }
+
     /// Reset the timeout timer (call after successful operations).
     pub fn reset_timeout(&mut self) {
         self.start_time = (self.get_time)();
@@ -207,39 +193,41 @@ impl<'a> BlockingTcpSocket<'a> {
     /// Poll the network stack.
     fn poll_network(&mut self) {
         let now = (self.get_time)();
-        self.net.poll(now);
+        let _ = self.with_net(|net| net.poll(now));
     }
 
-    /// Small delay to avoid busy-waiting.
+    /// Wait for the next frame (or a few ms, for the stack's timers)
+    /// instead of busy-waiting.
     fn small_delay(&self) {
-        for _ in 0..1000 {
-            core::hint::spin_loop();
-        }
+        crate::net::wait_for_frame((self.get_time)() + POLL_WAIT_MS);
     }
 
     /// Connect to a remote host (TCP only, no TLS).
     pub fn connect(&mut self, ip: smoltcp::wire::Ipv4Address, port: u16) -> Result<(), TlsError> {
         let now = (self.get_time)();
-        self.net
-            .tcp_connect(ip, port, now)
+        self.abort();
+        let socket = self
+            .with_net(|net| net.tcp_connect(ip, port, now))?
             .map_err(|_| TlsError::ConnectionError)?;
+        self.socket = Some(socket);
 
         // Wait for TCP connection to establish
         self.reset_timeout();
         loop {
             if self.check_timeout() {
-                self.net.tcp_abort();
+                self.abort();
                 return Err(TlsError::Timeout);
             }
 
             self.poll_network();
 
-            if self.net.tcp_is_connected() {
+            if self.with_net(|net| net.tcp_is_connected(socket))? {
                 self.reset_timeout();
                 return Ok(());
             }
 
-            if self.net.tcp_connection_failed() {
+            if self.with_net(|net| net.tcp_connection_failed(socket))? {
+                self.abort();
                 return Err(TlsError::ConnectionError);
             }
 
@@ -250,12 +238,49 @@ impl<'a> BlockingTcpSocket<'a> {
     /// Close the TCP connection.
     pub fn close(&mut self) {
         let now = (self.get_time)();
-        self.net.tcp_close(now);
+        if let Some(socket) = self.socket.take() {
+            let _ = self.with_net(|net| net.tcp_disconnect(socket, now));
+        }
     }
 
     /// Abort the TCP connection immediately.
     pub fn abort(&mut self) {
-        self.net.tcp_abort();
+        if let Some(socket) = self.socket.take() {
+            let _ = self.with_net(|net| net.tcp_abort(socket));
+        }
+    }
+
+    /// Wait up to `wait_ms` for received data without consuming it, so a
+    /// following read does not time out halfway through a record. Returns
+    /// false if nothing arrived in time.
+    pub fn wait_readable(&mut self, wait_ms: i64) -> Result<bool, TlsError> {
+        let socket = self.socket_id()?;
+        let deadline = (self.get_time)() + wait_ms;
+        loop {
+            self.poll_network();
+            if self.with_net(|net| net.tcp_can_recv(socket))? {
+                self.reset_timeout();
+                return Ok(true);
+            }
+            if self.with_net(|net| net.tcp_connection_failed(socket))? {
+                return Err(TlsError::ConnectionClosed);
+            }
+            if (self.get_time)() >= deadline {
+                return Ok(false);
+            }
+            self.small_delay();
+        }
+    }
+
+    /// Socket table slot of the connection
+    fn socket_id(&self) -> Result<crate::net::TcpSocketId, TlsError> {
+        self.socket.ok_or(TlsError::ConnectionError)
+    }
+}
+
+impl Drop for BlockingTcpSocket<'_> {
+    fn drop(&mut self) {
+        self.close();
     }
 }
 
@@ -265,6 +290,7 @@ impl ErrorType for BlockingTcpSocket<'_> {
 
 impl Read for BlockingTcpSocket<'_> {
     fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        let socket = self.socket_id()?;
         let mut poll_count = 0u32;
         loop {
             if self.check_timeout() {
@@ -274,7 +300,7 @@ impl Read for BlockingTcpSocket<'_> {
                 let n = format_u32(poll_count, &mut num_buf);
                 crate::uart::write_str(core::str::from_utf8(&num_buf[..n]).unwrap_or("?"));
                 crate::uart::write_str(" polls, state=");
-                crate::uart::write_line(self.net.tcp_state());
+                crate::uart::write_line(self.with_net(|net| net.tcp_state(socket)).unwrap_or("?"));
                 return Err(TlsError::Timeout);
             }
 
@@ -282,16 +308,16 @@ impl Read for BlockingTcpSocket<'_> {
             poll_count += 1;
 
             let now = (self.get_time)();
-            match self.net.tcp_recv(buf, now) {
+            match self.with_net(|net| net.tcp_recv(socket, buf, now))? {
                 Ok(n) if n > 0 => {
                     self.reset_timeout();
                     return Ok(n);
                 }
                 Ok(_) => {
                     // No data available yet
-                    if self.net.tcp_connection_failed() {
+                    if self.with_net(|net| net.tcp_connection_failed(socket))? {
                         crate::uart::write_str("TCP connection failed, state=");
-                        crate::uart::write_line(self.net.tcp_state());
+                        crate::uart::write_line(self.with_net(|net| net.tcp_state(socket)).unwrap_or("?"));
                         return Err(TlsError::ConnectionClosed);
                     }
                     self.small_delay();
@@ -328,6 +354,7 @@ fn format_u32(mut n: u32, buf: &mut [u8]) -> usize {
 
 impl Write for BlockingTcpSocket<'_> {
     fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        let socket = self.socket_id()?;
         let mut total_sent = 0;
 
         while total_sent < buf.len() {
@@ -342,7 +369,7 @@ impl Write for BlockingTcpSocket<'_> {
             self.poll_network();
             let now = (self.get_time)();
 
-            match self.net.tcp_send(&buf[total_sent..], now) {
+            match self.with_net(|net| net.tcp_send(socket, &buf[total_sent..], now))? {
                 Ok(n) if n > 0 => {
                     total_sent += n;
                     self.reset_timeout();
@@ -412,7 +439,7 @@ pub fn https_request(
     // Allocate TLS buffers
     let mut read_buffer = alloc::vec![0u8; TLS_READ_BUFFER_SIZE];
     let mut write_buffer = alloc::vec![0u8; TLS_WRITE_BUFFER_SIZE];
-    let mut rng = SimpleRng::new();
+    let mut rng = KernelRng;
 
     // Create blocking TCP socket and connect
     crate::uart::write_str("TLS: Connecting to port ");
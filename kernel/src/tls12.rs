@@ -1,14 +1,21 @@
 //! TLS 1.2 client implementation for HTTPS connections.
 //!
 //! This module provides TLS 1.2 support using:
-//! - ECDHE_RSA_WITH_AES_128_GCM_SHA256 cipher suite
+//! - ECDHE_RSA / ECDHE_ECDSA_WITH_AES_128_GCM_SHA256 cipher suites
 //! - X25519 or P-256 for key exchange
 //! - AES-128-GCM for encryption
 //! - SHA-256 for PRF and MAC
 //!
-//! Certificate verification is disabled (NoVerify mode) for simplicity.
+//! The server's certificate chain is checked against the root CA store
+//! (`x509`) and its ServerKeyExchange signature against the leaf key, unless
+//! verification was turned off for an `--insecure` request.
+//!
+//! Sessions are cached per host: the next connection to the same host offers
+//! the cached session ID or session ticket (RFC 5077) and, if the server
+//! agrees, runs the abbreviated handshake with no key exchange.
 
 use aes_gcm::{aead::AeadInPlace, Aes128Gcm, KeyInit, Nonce};
+use alloc::string::String;
 use alloc::vec;
 use alloc::vec::Vec;
 use embedded_io::{Read, Write};
@@ -22,7 +29,9 @@ use p256::elliptic_curve::sec1::FromEncodedPoint;
 use p256::EncodedPoint;
 use p256::PublicKey as P256PublicKey;
 
-use crate::tls::{BlockingTcpSocket, SimpleRng, TlsError};
+use crate::random::KernelRng;
+use crate::tls::{BlockingTcpSocket, TlsError};
+use crate::x509::{PublicKey, SignatureAlgorithm};
 
 // ═══════════════════════════════════════════════════════════════════════════════
 // CONSTANTS
@@ -40,6 +49,7 @@ const CONTENT_TYPE_APPLICATION_DATA: u8 = 23;
 /// Handshake message types
 const HANDSHAKE_CLIENT_HELLO: u8 = 1;
 const HANDSHAKE_SERVER_HELLO: u8 = 2;
+const HANDSHAKE_NEW_SESSION_TICKET: u8 = 4;
 const HANDSHAKE_CERTIFICATE: u8 = 11;
 const HANDSHAKE_SERVER_KEY_EXCHANGE: u8 = 12;
 const HANDSHAKE_SERVER_HELLO_DONE: u8 = 14;
@@ -48,12 +58,15 @@ const HANDSHAKE_FINISHED: u8 = 20;
 
 /// Cipher suite: TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256
 const CIPHER_SUITE_ECDHE_RSA_AES128_GCM_SHA256: [u8; 2] = [0xc0, 0x2f];
+/// Cipher suite: TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256
+const CIPHER_SUITE_ECDHE_ECDSA_AES128_GCM_SHA256: [u8; 2] = [0xc0, 0x2b];
 
 /// Extension types
 const EXT_SERVER_NAME: u16 = 0;
 const EXT_EC_POINT_FORMATS: u16 = 11;
 const EXT_SUPPORTED_GROUPS: u16 = 10;
 const EXT_SIGNATURE_ALGORITHMS: u16 = 13;
+const EXT_SESSION_TICKET: u16 = 35;
 
 /// Named curves
 const NAMED_CURVE_SECP256R1: u16 = 23;
@@ -73,6 +86,8 @@ const GCM_EXPLICIT_NONCE_SIZE: usize = 8;
 /// TLS 1.2 connection state
 pub struct Tls12Connection<'a> {
     socket: BlockingTcpSocket<'a>,
+    /// Selected cipher suite
+    cipher: [u8; 2],
     /// Selected curve
     curve: u16,
     /// Our ECDHE private key (X25519)
@@ -85,6 +100,10 @@ pub struct Tls12Connection<'a> {
     client_pubkey_p256: Vec<u8>,
     /// Server's ECDHE public key
     server_pubkey: Option<Vec<u8>>,
+    /// Whether the certificate chain and key exchange signature are checked
+    verify: bool,
+    /// Public key of the validated server certificate
+    server_key: Option<PublicKey>,
     /// Server random (32 bytes)
     server_random: [u8; 32],
     /// Client random (32 bytes)
@@ -103,18 +122,26 @@ pub struct Tls12Connection<'a> {
     client_seq: u64,
     /// Server sequence number for GCM nonce
     server_seq: u64,
+    /// Session ID offered in ClientHello (empty for a fresh session)
+    offered_session_id: Vec<u8>,
+    /// Session ID chosen by the server in ServerHello
+    session_id: Vec<u8>,
+    /// Session ticket offered in ClientHello
+    offered_ticket: Vec<u8>,
+    /// Ticket from the server's NewSessionTicket, with its lifetime hint in seconds
+    new_ticket: Option<(Vec<u8>, u32)>,
     /// Handshake messages for Finished verification
     handshake_hash: Sha256,
     /// Whether encryption is active
     encrypted: bool,
     /// RNG for generating random values
-    rng: SimpleRng,
+    rng: KernelRng,
 }
 
 impl<'a> Tls12Connection<'a> {
     /// Create a new TLS 1.2 connection
     pub fn new(socket: BlockingTcpSocket<'a>) -> Self {
-        let mut rng = SimpleRng::new();
+        let mut rng = KernelRng;
 
         // Generate client random
         let mut client_random = [0u8; 32];
@@ -122,12 +149,15 @@ impl<'a> Tls12Connection<'a> {
 
         Self {
             socket,
+            cipher: [0, 0],
             curve: 0,
             x25519_secret: None,
             p256_secret: None,
             client_pubkey_x25519: Vec::new(),
             client_pubkey_p256: Vec::new(),
             server_pubkey: None,
+            verify: true,
+            server_key: None,
             server_random: [0u8; 32],
             client_random,
             master_secret: [0u8; 48],
@@ -137,16 +167,76 @@ impl<'a> Tls12Connection<'a> {
             server_write_iv: [0u8; 4],
             client_seq: 0,
             server_seq: 0,
+            offered_session_id: Vec::new(),
+            session_id: Vec::new(),
+            offered_ticket: Vec::new(),
+            new_ticket: None,
             handshake_hash: Sha256::new(),
             encrypted: false,
             rng,
         }
     }
 
-    /// Perform TLS 1.2 handshake
+    /// Turn certificate and signature checks on or off (off for `--insecure` requests)
+    pub fn set_verify(&mut self, verify: bool) {
+        self.verify = verify;
+    }
+
+    /// The underlying TCP socket
+    pub fn socket(&mut self) -> &mut BlockingTcpSocket<'a> {
+        &mut self.socket
+    }
+
+    /// Perform TLS 1.2 handshake, resuming the cached session for `hostname`
+    /// when the server accepts it
     pub fn handshake(&mut self, hostname: &str) -> Result<(), TlsError> {
         crate::uart::write_line("TLS 1.2: Starting handshake");
 
+        // Taken out of the cache so a failed resumption is not retried
+        let cached = take_session(hostname, self.verify);
+        let resumed = match &cached {
+            Some(session) => {
+                self.offered_ticket = session.ticket.clone();
+                self.offered_session_id = if session.ticket.is_empty() {
+                    session.session_id.clone()
+                } else {
+                    // RFC 5077: a fresh ID, echoed back if the ticket is accepted
+                    let mut id = vec![0u8; 32];
+                    self.rng.fill_bytes(&mut id);
+                    id
+                };
+                self.send_client_hello(hostname)?;
+                self.recv_server_hello()?;
+                !self.session_id.is_empty() && self.session_id == self.offered_session_id
+            }
+            None => {
+                self.send_client_hello(hostname)?;
+                self.recv_server_hello()?;
+                false
+            }
+        };
+
+        if let (true, Some(session)) = (resumed, &cached) {
+            crate::uart::write_line("TLS 1.2: Resuming cached session");
+            self.master_secret = session.master_secret;
+            self.derive_keys();
+
+            // Abbreviated handshake: the server finishes first
+            self.recv_change_cipher_spec()?;
+            self.recv_finished()?;
+            self.send_change_cipher_spec()?;
+            self.encrypted = true;
+            self.send_finished()?;
+
+            let ticket = match self.new_ticket.take() {
+                Some(ticket) => Some(ticket),
+                None => Some((session.ticket.clone(), 0)),
+            };
+            self.remember(hostname, ticket);
+            crate::uart::write_line("TLS 1.2: Handshake complete (resumed)");
+            return Ok(());
+        }
+
         // Generate X25519 keypair
         let x25519_secret = x25519_dalek::EphemeralSecret::random_from_rng(&mut self.rng);
         let x25519_public = x25519_dalek::PublicKey::from(&x25519_secret);
@@ -161,14 +251,10 @@ impl<'a> Tls12Connection<'a> {
         self.client_pubkey_p256 = encoded.as_bytes().to_vec();
         self.p256_secret = Some(p256_secret);
 
-        // Step 1: Send ClientHello
-        self.send_client_hello(hostname)?;
-
-        // Step 2: Receive ServerHello
-        self.recv_server_hello()?;
+        // Steps 1-2 (ClientHello, ServerHello) were done above
 
         // Step 3: Receive Certificate
-        self.recv_certificate()?;
+        self.recv_certificate(hostname)?;
 
         // Step 4: Receive ServerKeyExchange
         self.recv_server_key_exchange()?;
@@ -197,10 +283,32 @@ impl<'a> Tls12Connection<'a> {
         // Step 11: Receive Finished
         self.recv_finished()?;
 
+        let ticket = self.new_ticket.take();
+        self.remember(hostname, ticket);
         crate::uart::write_line("TLS 1.2: Handshake complete");
         Ok(())
     }
 
+    /// Cache the session just established, if the server made it resumable
+    fn remember(&self, hostname: &str, ticket: Option<(Vec<u8>, u32)>) {
+        let (ticket, lifetime_hint) = ticket.unwrap_or_default();
+        if hostname.is_empty() || (self.session_id.is_empty() && ticket.is_empty()) {
+            return;
+        }
+        let mut lifetime_ms = SESSION_LIFETIME_MS;
+        if lifetime_hint > 0 {
+            lifetime_ms = lifetime_ms.min(lifetime_hint as i64 * 1000);
+        }
+        store_session(CachedSession {
+            host: String::from(hostname),
+            session_id: self.session_id.clone(),
+            ticket,
+            master_secret: self.master_secret,
+            verified: self.verify,
+            expires_at: crate::get_time_ms() + lifetime_ms,
+        });
+    }
+
     /// Send ClientHello
     fn send_client_hello(&mut self, hostname: &str) -> Result<(), TlsError> {
         let mut hello = Vec::with_capacity(512);
@@ -211,12 +319,14 @@ impl<'a> Tls12Connection<'a> {
         // Client random
         hello.extend_from_slice(&self.client_random);
 
-        // Session ID (empty)
-        hello.push(0);
+        // Session ID (empty unless resuming)
+        hello.push(self.offered_session_id.len() as u8);
+        hello.extend_from_slice(&self.offered_session_id);
 
         // Cipher suites
         hello.push(0); // Length high byte
-        hello.push(2); // Length low byte (1 cipher suite = 2 bytes)
+        hello.push(4); // Length low byte (2 cipher suites = 4 bytes)
+        hello.extend_from_slice(&CIPHER_SUITE_ECDHE_ECDSA_AES128_GCM_SHA256);
         hello.extend_from_slice(&CIPHER_SUITE_ECDHE_RSA_AES128_GCM_SHA256);
 
         // Compression methods (null only)
@@ -258,12 +368,27 @@ impl<'a> Tls12Connection<'a> {
         extensions.push(1); // List length
         extensions.push(0); // Uncompressed point format
 
-        // Signature Algorithms
+        // Signature Algorithms - what x509 can verify, as (hash, signature) pairs
+        let signature_algorithms: [[u8; 2]; 6] = [
+            [0x04, 0x03], // ECDSA SHA256
+            [0x05, 0x03], // ECDSA SHA384
+            [0x06, 0x03], // ECDSA SHA512
+            [0x04, 0x01], // RSA SHA256
+            [0x05, 0x01], // RSA SHA384
+            [0x06, 0x01], // RSA SHA512
+        ];
+        let list_len = (signature_algorithms.len() * 2) as u16;
         extensions.extend_from_slice(&EXT_SIGNATURE_ALGORITHMS.to_be_bytes());
-        extensions.extend_from_slice(&4u16.to_be_bytes()); // Extension length
-        extensions.extend_from_slice(&2u16.to_be_bytes()); // List length
-        extensions.push(0x04); // SHA256
-        extensions.push(0x01); // RSA
+        extensions.extend_from_slice(&(list_len + 2).to_be_bytes()); // Extension length
+        extensions.extend_from_slice(&list_len.to_be_bytes()); // List length
+        for algorithm in signature_algorithms {
+            extensions.extend_from_slice(&algorithm);
+        }
+
+        // Session Ticket - the cached ticket, or empty to ask for one
+        extensions.extend_from_slice(&EXT_SESSION_TICKET.to_be_bytes());
+        extensions.extend_from_slice(&(self.offered_ticket.len() as u16).to_be_bytes());
+        extensions.extend_from_slice(&self.offered_ticket);
 
         // Add extensions length
         hello.extend_from_slice(&(extensions.len() as u16).to_be_bytes());
@@ -332,7 +457,13 @@ impl<'a> Tls12Connection<'a> {
 
         // Session ID
         let session_id_len = msg[pos] as usize;
-        pos += 1 + session_id_len;
+        pos += 1;
+        if pos + session_id_len > msg.len() {
+            crate::uart::write_line("TLS 1.2: ServerHello session ID truncated");
+            return Err(TlsError::InvalidData);
+        }
+        self.session_id = msg[pos..pos + session_id_len].to_vec();
+        pos += session_id_len;
 
         // Cipher suite
         if pos + 2 > msg.len() {
@@ -341,17 +472,20 @@ impl<'a> Tls12Connection<'a> {
         }
         let cipher = [msg[pos], msg[pos + 1]];
 
-        if cipher != CIPHER_SUITE_ECDHE_RSA_AES128_GCM_SHA256 {
+        if cipher != CIPHER_SUITE_ECDHE_RSA_AES128_GCM_SHA256
+            && cipher != CIPHER_SUITE_ECDHE_ECDSA_AES128_GCM_SHA256
+        {
             crate::uart::write_line("TLS 1.2: Server selected unsupported cipher suite");
             return Err(TlsError::TlsProtocolError);
         }
+        self.cipher = cipher;
 
         crate::uart::write_line("TLS 1.2: Received ServerHello");
         Ok(())
     }
 
-    /// Receive Certificate (we don't validate it)
-    fn recv_certificate(&mut self) -> Result<(), TlsError> {
+    /// Receive Certificate and validate the chain for `hostname`
+    fn recv_certificate(&mut self, hostname: &str) -> Result<(), TlsError> {
         let record = self.recv_record()?;
 
         if record.content_type != CONTENT_TYPE_HANDSHAKE {
@@ -364,8 +498,36 @@ impl<'a> Tls12Connection<'a> {
             return Err(TlsError::TlsProtocolError);
         }
 
-        crate::uart::write_line("TLS 1.2: Received Certificate (not validated)");
-        Ok(())
+        if !self.verify {
+            crate::uart::write_line("TLS 1.2: Received Certificate (not validated)");
+            return Ok(());
+        }
+
+        // Message header (4), certificate_list length (3), then
+        // length (3) + DER for each certificate, server certificate first
+        let body = record.data.get(7..).ok_or(TlsError::InvalidData)?;
+        let mut chain = Vec::new();
+        let mut pos = 0;
+        while pos + 3 <= body.len() {
+            let len = u32::from_be_bytes([0, body[pos], body[pos + 1], body[pos + 2]]) as usize;
+            pos += 3;
+            let der = body.get(pos..pos + len).ok_or(TlsError::InvalidData)?;
+            chain.push(der.to_vec());
+            pos += len;
+        }
+
+        match crate::x509::verify_chain(&chain, hostname) {
+            Ok(key) => {
+                self.server_key = Some(key);
+                crate::uart::write_line("TLS 1.2: Received Certificate (chain verified)");
+                Ok(())
+            }
+            Err(reason) => {
+                crate::uart::write_str("TLS 1.2: Certificate rejected: ");
+                crate::uart::write_line(reason);
+                Err(TlsError::BadCertificate)
+            }
+        }
     }
 
     /// Receive ServerKeyExchange (ECDHE parameters)
@@ -454,6 +616,34 @@ impl<'a> Tls12Connection<'a> {
 
         self.server_pubkey = Some(msg[4..4 + pubkey_len].to_vec());
 
+        // signed_params: client_random + server_random + the ECDH parameters,
+        // followed by hash (1) + signature algorithm (1) + length (2) + signature
+        if self.verify {
+            let params = &msg[..4 + pubkey_len];
+            let signed = &msg[4 + pubkey_len..];
+            if signed.len() < 4 {
+                crate::uart::write_line("TLS 1.2: ServerKeyExchange not signed");
+                return Err(TlsError::InvalidData);
+            }
+            let sig_len = u16::from_be_bytes([signed[2], signed[3]]) as usize;
+            let signature = signed.get(4..4 + sig_len).ok_or(TlsError::InvalidData)?;
+            let expected_signer = if self.cipher == CIPHER_SUITE_ECDHE_RSA_AES128_GCM_SHA256 { 1 } else { 3 };
+            let algorithm = SignatureAlgorithm::from_tls(signed[0], signed[1])
+                .filter(|_| signed[1] == expected_signer)
+                .ok_or(TlsError::TlsProtocolError)?;
+
+            let mut message = Vec::with_capacity(64 + params.len());
+            message.extend_from_slice(&self.client_random);
+            message.extend_from_slice(&self.server_random);
+            message.extend_from_slice(params);
+
+            let key = self.server_key.as_ref().ok_or(TlsError::BadCertificate)?;
+            if !key.verify(algorithm, &message, signature) {
+                crate::uart::write_line("TLS 1.2: ServerKeyExchange signature does not verify");
+                return Err(TlsError::BadCertificate);
+            }
+        }
+
         crate::uart::write_line("TLS 1.2: Received ServerKeyExchange");
         Ok(())
     }
@@ -550,9 +740,21 @@ impl<'a> Tls12Connection<'a> {
             &mut self.master_secret,
         );
 
-        // Compute key block
+        self.derive_keys();
+
+        // Log which curve was used
+        if self.curve == NAMED_CURVE_X25519 {
+            crate::uart::write_line("TLS 1.2: Keys computed (X25519)");
+        } else {
+            crate::uart::write_line("TLS 1.2: Keys computed (P-256)");
+        }
+        Ok(())
+    }
+
+    /// Derive the record keys from the master secret and both randoms
+    fn derive_keys(&mut self) {
         // key_block = PRF(master_secret, "key expansion", server_random + client_random)
-        seed.clear();
+        let mut seed = Vec::with_capacity(64);
         seed.extend_from_slice(&self.server_random);
         seed.extend_from_slice(&self.client_random);
 
@@ -565,14 +767,6 @@ impl<'a> Tls12Connection<'a> {
         self.server_write_key.copy_from_slice(&key_block[16..32]);
         self.client_write_iv.copy_from_slice(&key_block[32..36]);
         self.server_write_iv.copy_from_slice(&key_block[36..40]);
-
-        // Log which curve was used
-        if self.curve == NAMED_CURVE_X25519 {
-            crate::uart::write_line("TLS 1.2: Keys computed (X25519)");
-        } else {
-            crate::uart::write_line("TLS 1.2: Keys computed (P-256)");
-        }
-        Ok(())
     }
 
     /// Send ClientKeyExchange
@@ -637,9 +831,18 @@ impl<'a> Tls12Connection<'a> {
         Ok(())
     }
 
-    /// Receive ChangeCipherSpec
+    /// Receive ChangeCipherSpec, and the NewSessionTicket that may precede it
     fn recv_change_cipher_spec(&mut self) -> Result<(), TlsError> {
-        let record = self.recv_record()?;
+        let mut record = self.recv_record()?;
+
+        if record.content_type == CONTENT_TYPE_HANDSHAKE
+            && record.data.first() == Some(&HANDSHAKE_NEW_SESSION_TICKET)
+        {
+            self.handshake_hash.update(&record.data);
+            self.new_ticket = parse_new_session_ticket(record.data.get(4..).unwrap_or(&[]));
+            crate::uart::write_line("TLS 1.2: Received NewSessionTicket");
+            record = self.recv_record()?;
+        }
 
         if record.content_type != CONTENT_TYPE_CHANGE_CIPHER_SPEC {
             return Err(TlsError::TlsProtocolError);
@@ -658,10 +861,22 @@ impl<'a> Tls12Connection<'a> {
             return Err(TlsError::TlsProtocolError);
         }
 
-        if record.data.is_empty() || record.data[0] != HANDSHAKE_FINISHED {
+        if record.data.len() != 16 || record.data[0] != HANDSHAKE_FINISHED {
             return Err(TlsError::TlsProtocolError);
         }
 
+        // verify_data = PRF(master_secret, "server finished", Hash(handshake_messages))
+        let handshake_hash = self.handshake_hash.clone().finalize();
+        let mut expected = [0u8; 12];
+        prf_sha256(&self.master_secret, b"server finished", &handshake_hash, &mut expected);
+        if record.data[4..] != expected {
+            crate::uart::write_line("TLS 1.2: Server Finished does not verify");
+            return Err(TlsError::TlsProtocolError);
+        }
+
+        // The client's Finished covers this one when resuming
+        self.handshake_hash.update(&record.data);
+
         crate::uart::write_line("TLS 1.2: Received Finished");
         Ok(())
     }
@@ -897,12 +1112,90 @@ struct TlsRecord {
     data: Vec<u8>,
 }
 
+/// Ticket and lifetime hint from a NewSessionTicket body
+fn parse_new_session_ticket(body: &[u8]) -> Option<(Vec<u8>, u32)> {
+    if body.len() < 6 {
+        return None;
+    }
+    let lifetime_hint = u32::from_be_bytes([body[0], body[1], body[2], body[3]]);
+    let len = u16::from_be_bytes([body[4], body[5]]) as usize;
+    let ticket = body.get(6..6 + len)?;
+    if ticket.is_empty() {
+        return None;
+    }
+    Some((ticket.to_vec(), lifetime_hint))
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// SESSION CACHE
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Hosts whose sessions are kept
+const MAX_CACHED_SESSIONS: usize = 8;
+
+/// Longest a cached session is offered for (tickets may ask for less)
+const SESSION_LIFETIME_MS: i64 = 10 * 60 * 1000;
+
+/// A resumable session
+struct CachedSession {
+    host: String,
+    /// Server-assigned session ID (may be empty when a ticket is used)
+    session_id: Vec<u8>,
+    /// Session ticket (empty when the server uses session IDs only)
+    ticket: Vec<u8>,
+    master_secret: [u8; 48],
+    /// Whether the handshake that made it checked the certificate
+    verified: bool,
+    expires_at: i64,
+}
+
+static SESSION_CACHE: crate::Spinlock<Vec<CachedSession>> = crate::Spinlock::new(Vec::new());
+
+/// Remove and return the live session cached for `host`; `verified_only`
+/// skips sessions set up without checking the certificate
+fn take_session(host: &str, verified_only: bool) -> Option<CachedSession> {
+    let now = crate::get_time_ms();
+    let mut cache = SESSION_CACHE.lock();
+    cache.retain(|s| s.expires_at > now);
+    let index = cache
+        .iter()
+        .position(|s| s.host == host && (s.verified || !verified_only))?;
+    Some(cache.swap_remove(index))
+}
+
+/// Cache a session, replacing the host's previous one and evicting the
+/// session closest to expiry when full
+fn store_session(session: CachedSession) {
+    let mut cache = SESSION_CACHE.lock();
+    cache.retain(|s| s.host != session.host);
+    if cache.len() >= MAX_CACHED_SESSIONS {
+        if let Some(index) = cache
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, s)| s.expires_at)
+            .map(|(i, _)| i)
+        {
+            cache.swap_remove(index);
+        }
+    }
+    cache.push(session);
+}
+
+/// Whether a resumable TLS 1.2 session is cached for `host`
+pub fn has_session(host: &str) -> bool {
+    let now = crate::get_time_ms();
+    SESSION_CACHE
+        .lock()
+        .iter()
+        .any(|s| s.host == host && s.expires_at > now)
+}
+
 // ═══════════════════════════════════════════════════════════════════════════════
 // TLS 1.2 PRF (Pseudo-Random Function)
 // ═══════════════════════════════════════════════════════════════════════════════
 
-/// TLS 1.2 PRF using SHA-256
-fn prf_sha256(secret: &[u8], label: &[u8], seed: &[u8], output: &mut [u8]) {
+/// TLS 1.2 PRF using SHA-256 (shared with the server side in `tls_server`)
+pub(crate) fn prf_sha256(secret: &[u8], label: &[u8], seed: &[u8], output: &mut [u8]) {
     let mut combined_seed = Vec::with_capacity(label.len() + seed.len());
     combined_seed.extend_from_slice(label);
     combined_seed.extend_from_slice(seed);
@@ -942,7 +1235,8 @@ fn p_hash<D: Digest + Clone>(secret: &[u8], seed: &[u8], output: &mut [u8]) {
 // PUBLIC API
 // ═══════════════════════════════════════════════════════════════════════════════
 
-/// Perform an HTTPS request using TLS 1.2
+/// Perform an HTTPS request using TLS 1.2 (`insecure` skips certificate checks)
+#[allow(clippy::too_many_arguments)]
 pub fn https_request_tls12(
     net: &mut crate::net::NetState,
     ip: smoltcp::wire::Ipv4Address,
@@ -951,13 +1245,53 @@ pub fn https_request_tls12(
     request_bytes: &[u8],
     timeout_ms: i64,
     get_time: fn() -> i64,
+    insecure: bool,
 ) -> Result<Vec<u8>, TlsError> {
-    // Create blocking TCP socket and connect
-    let mut socket = BlockingTcpSocket::new(net, timeout_ms, get_time);
+    let socket = BlockingTcpSocket::new(net, timeout_ms, get_time);
+    let mut response = Vec::with_capacity(8192);
+    tls12_exchange(socket, ip, port, hostname, request_bytes, insecure, &mut |data| {
+        response.extend_from_slice(data);
+        Ok(is_http_response_complete(&response))
+    })?;
+    Ok(response)
+}
+
+/// Perform an HTTPS request using TLS 1.2, handing the response bytes to
+/// `on_data` as they are decrypted instead of collecting them. `on_data`
+/// returns true once the response is complete; an error from it aborts.
+///
+/// The network stack is only locked while the connection polls, so
+/// `on_data` may do file I/O.
+#[allow(clippy::too_many_arguments)]
+pub fn https_request_tls12_streaming(
+    ip: smoltcp::wire::Ipv4Address,
+    port: u16,
+    hostname: &str,
+    request_bytes: &[u8],
+    timeout_ms: i64,
+    get_time: fn() -> i64,
+    insecure: bool,
+    on_data: &mut dyn FnMut(&[u8]) -> Result<bool, TlsError>,
+) -> Result<(), TlsError> {
+    let socket = BlockingTcpSocket::shared(timeout_ms, get_time);
+    tls12_exchange(socket, ip, port, hostname, request_bytes, insecure, on_data)
+}
+
+/// Connect, handshake, send the request and pass what comes back to `on_data`
+fn tls12_exchange(
+    mut socket: BlockingTcpSocket<'_>,
+    ip: smoltcp::wire::Ipv4Address,
+    port: u16,
+    hostname: &str,
+    request_bytes: &[u8],
+    insecure: bool,
+    on_data: &mut dyn FnMut(&[u8]) -> Result<bool, TlsError>,
+) -> Result<(), TlsError> {
     socket.connect(ip, port)?;
 
     // Create TLS 1.2 connection
     let mut tls = Tls12Connection::new(socket);
+    tls.set_verify(!insecure);
 
     // Perform handshake
     tls.handshake(hostname)?;
@@ -966,19 +1300,19 @@ pub fn https_request_tls12(
     tls.write(request_bytes)?;
 
     // Receive response
-    let mut response = Vec::with_capacity(8192);
     let mut buf = [0u8; 1024];
 
     loop {
         match tls.read(&mut buf) {
             Ok(0) => break,
-            Ok(n) => {
-                response.extend_from_slice(&buf[..n]);
-                // Check if response is complete
-                if is_http_response_complete(&response) {
-                    break;
+            Ok(n) => match on_data(&buf[..n]) {
+                Ok(true) => break,
+                Ok(false) => {}
+                Err(e) => {
+                    let _ = tls.close();
+                    return Err(e);
                 }
-            }
+            },
             Err(TlsError::ConnectionClosed) => break,
             Err(e) => {
                 let _ = tls.close();
@@ -988,7 +1322,7 @@ pub fn https_request_tls12(
     }
 
     let _ = tls.close();
-    Ok(response)
+    Ok(())
 }
 
 /// Check if HTTP response is complete
@@ -0,0 +1,780 @@
+//! TLS 1.2 server side, used by httpd to serve HTTPS.
+//!
+//! One cipher suite is offered: TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256,
+//! with X25519 or P-256 for the key exchange. The server key therefore has to
+//! be a P-256 (prime256v1) key; RSA keys are rejected when loading.
+//!
+//! Certificate and key are PEM files:
+//! - `/etc/httpd/cert.pem`: the server certificate, then any intermediates
+//! - `/etc/httpd/key.pem`: an "EC PRIVATE KEY" (SEC1) or "PRIVATE KEY" (PKCS#8)
+//!
+//! A self-signed pair can be made on the host with:
+//!
+//! ```text
+//! openssl req -x509 -newkey ec -pkeyopt ec_paramgen_curve:prime256v1 -nodes \
+//!     -keyout key.pem -out cert.pem -days 365 -subj /CN=bavy
+//! ```
+//!
+//! Sessions are not resumed and renegotiation is refused.
+
+use aes_gcm::{aead::AeadInPlace, Aes128Gcm, KeyInit, Nonce};
+use alloc::vec;
+use alloc::vec::Vec;
+use embedded_io::{Read, Write};
+use p256::ecdh::EphemeralSecret as P256Secret;
+use p256::ecdsa::signature::Signer;
+use p256::ecdsa::{Signature, SigningKey};
+use p256::elliptic_curve::sec1::FromEncodedPoint;
+use p256::{EncodedPoint, PublicKey as P256PublicKey};
+use rand_core::RngCore;
+use sha2::{Digest, Sha256};
+
+use crate::random::KernelRng;
+use crate::tls::{BlockingTcpSocket, TlsError};
+use crate::tls12::prf_sha256;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// CONSTANTS
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Server certificate chain (PEM)
+pub const CERT_PATH: &str = "/etc/httpd/cert.pem";
+/// Server private key (PEM)
+pub const KEY_PATH: &str = "/etc/httpd/key.pem";
+
+const TLS_VERSION_1_2: [u8; 2] = [0x03, 0x03];
+
+const CONTENT_TYPE_CHANGE_CIPHER_SPEC: u8 = 20;
+const CONTENT_TYPE_ALERT: u8 = 21;
+const CONTENT_TYPE_HANDSHAKE: u8 = 22;
+const CONTENT_TYPE_APPLICATION_DATA: u8 = 23;
+
+const HANDSHAKE_CLIENT_HELLO: u8 = 1;
+const HANDSHAKE_SERVER_HELLO: u8 = 2;
+const HANDSHAKE_CERTIFICATE: u8 = 11;
+const HANDSHAKE_SERVER_KEY_EXCHANGE: u8 = 12;
+const HANDSHAKE_SERVER_HELLO_DONE: u8 = 14;
+const HANDSHAKE_CLIENT_KEY_EXCHANGE: u8 = 16;
+const HANDSHAKE_FINISHED: u8 = 20;
+
+/// TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256
+const CIPHER_SUITE_ECDHE_ECDSA_AES128_GCM_SHA256: [u8; 2] = [0xc0, 0x2b];
+/// TLS_EMPTY_RENEGOTIATION_INFO_SCSV (RFC 5746)
+const CIPHER_SUITE_RENEGOTIATION_INFO_SCSV: [u8; 2] = [0x00, 0xff];
+
+const EXT_SUPPORTED_GROUPS: u16 = 10;
+const EXT_EC_POINT_FORMATS: u16 = 11;
+const EXT_RENEGOTIATION_INFO: u16 = 0xff01;
+
+const NAMED_CURVE_SECP256R1: u16 = 23;
+const NAMED_CURVE_X25519: u16 = 29;
+
+/// ecdsa_secp256r1_sha256 (hash SHA-256, signature ECDSA)
+const SIGNATURE_ECDSA_SHA256: [u8; 2] = [0x04, 0x03];
+
+const ALERT_LEVEL_WARNING: u8 = 1;
+const ALERT_LEVEL_FATAL: u8 = 2;
+const ALERT_CLOSE_NOTIFY: u8 = 0;
+const ALERT_HANDSHAKE_FAILURE: u8 = 40;
+const ALERT_NO_RENEGOTIATION: u8 = 100;
+
+/// Largest plaintext in one record
+const MAX_RECORD_PLAINTEXT: usize = 16384;
+/// Largest handshake message accepted from a client
+const MAX_HANDSHAKE_MESSAGE: usize = 16384;
+
+const GCM_NONCE_SIZE: usize = 12;
+const GCM_TAG_SIZE: usize = 16;
+const GCM_EXPLICIT_NONCE_SIZE: usize = 8;
+
+const DER_INTEGER: u8 = 0x02;
+const DER_OCTET_STRING: u8 = 0x04;
+const DER_SEQUENCE: u8 = 0x30;
+/// Context tag [0] holding SEC1 curve parameters
+const DER_SEC1_PARAMETERS: u8 = 0xa0;
+/// prime256v1 (1.2.840.10045.3.1.7)
+const OID_PRIME256V1: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07];
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// SERVER IDENTITY
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Certificate chain and private key presented to clients
+pub struct ServerIdentity {
+    /// DER certificates, server certificate first
+    chain: Vec<Vec<u8>>,
+    key: SigningKey,
+}
+
+impl ServerIdentity {
+    /// Build from the contents of cert.pem and key.pem
+    pub fn from_pem(cert_pem: &[u8], key_pem: &[u8]) -> Result<Self, &'static str> {
+        let chain = pem_blocks(cert_pem, "CERTIFICATE");
+        if chain.is_empty() {
+            return Err("no CERTIFICATE in cert.pem");
+        }
+        let key_der = pem_blocks(key_pem, "EC PRIVATE KEY")
+            .into_iter()
+            .chain(pem_blocks(key_pem, "PRIVATE KEY"))
+            .next()
+            .ok_or("no EC PRIVATE KEY or PRIVATE KEY in key.pem")?;
+        let scalar = ec_private_key_scalar(&key_der).ok_or("key.pem is not a P-256 key")?;
+        let key = SigningKey::from_slice(scalar).map_err(|_| "invalid P-256 key")?;
+        Ok(Self { chain, key })
+    }
+}
+
+/// Decode every PEM block with the given label
+pub(crate) fn pem_blocks(pem: &[u8], label: &str) -> Vec<Vec<u8>> {
+    let text = core::str::from_utf8(pem).unwrap_or("");
+    let begin = alloc::format!("-----BEGIN {}-----", label);
+    let end = alloc::format!("-----END {}-----", label);
+    let mut blocks = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find(&begin) {
+        let body = &rest[start + begin.len()..];
+        let Some(stop) = body.find(&end) else {
+            break;
+        };
+        if let Some(der) = base64_decode(&body[..stop]) {
+            blocks.push(der);
+        }
+        rest = &body[stop + end.len()..];
+    }
+    blocks
+}
+
+/// Decode base64, skipping whitespace
+fn base64_decode(text: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(text.len() * 3 / 4);
+    let mut acc: u32 = 0;
+    let mut bits = 0;
+    for c in text.bytes() {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            b'=' => break,
+            c if c.is_ascii_whitespace() => continue,
+            _ => return None,
+        };
+        acc = ((acc << 6) | value as u32) & 0xff_ffff;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+        }
+    }
+    Some(out)
+}
+
+/// Split one DER element into (tag, contents, rest)
+pub(crate) fn der_next(data: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, rest) = data.split_first()?;
+    let (&first, rest) = rest.split_first()?;
+    let (len, rest) = if first < 0x80 {
+        (first as usize, rest)
+    } else {
+        let n = (first & 0x7f) as usize;
+        if n == 0 || n > 2 || rest.len() < n {
+            return None;
+        }
+        let len = rest[..n].iter().fold(0usize, |acc, &b| (acc << 8) | b as usize);
+        (len, &rest[n..])
+    };
+    if rest.len() < len {
+        return None;
+    }
+    Some((tag, &rest[..len], &rest[len..]))
+}
+
+/// Whether DER bytes mention the prime256v1 curve
+fn names_p256(der: &[u8]) -> bool {
+    der.windows(OID_PRIME256V1.len()).any(|w| w == OID_PRIME256V1)
+}
+
+/// Private scalar of a SEC1 ECPrivateKey, or of a PKCS#8 PrivateKeyInfo
+/// wrapping one, if the key is on P-256
+fn ec_private_key_scalar(der: &[u8]) -> Option<&[u8]> {
+    let (tag, body, _) = der_next(der)?;
+    if tag != DER_SEQUENCE {
+        return None;
+    }
+    let (tag, version, rest) = der_next(body)?;
+    if tag != DER_INTEGER {
+        return None;
+    }
+    match version {
+        // SEC1: version 1, privateKey, [0] parameters (optional), [1] publicKey (optional)
+        [1] => {
+            let (tag, scalar, rest) = der_next(rest)?;
+            if tag != DER_OCTET_STRING || scalar.len() != 32 {
+                return None;
+            }
+            match der_next(rest) {
+                Some((DER_SEC1_PARAMETERS, params, _)) if !names_p256(params) => None,
+                _ => Some(scalar),
+            }
+        }
+        // PKCS#8: version 0, AlgorithmIdentifier, privateKey (a SEC1 key)
+        [0] => {
+            let (tag, algorithm, rest) = der_next(rest)?;
+            if tag != DER_SEQUENCE || !names_p256(algorithm) {
+                return None;
+            }
+            let (tag, inner, _) = der_next(rest)?;
+            if tag != DER_OCTET_STRING {
+                return None;
+            }
+            ec_private_key_scalar(inner)
+        }
+        _ => None,
+    }
+}
+
+/// DER INTEGER for a big-endian unsigned value
+fn der_integer(value: &[u8]) -> Vec<u8> {
+    let start = value.iter().position(|&b| b != 0).unwrap_or(value.len() - 1);
+    let value = &value[start..];
+    let mut out = vec![DER_INTEGER, 0];
+    if value[0] & 0x80 != 0 {
+        out.push(0);
+    }
+    out.extend_from_slice(value);
+    out[1] = (out.len() - 2) as u8;
+    out
+}
+
+/// ECDSA signature in the DER form TLS 1.2 sends (SEQUENCE of r and s)
+fn signature_der(signature: &Signature) -> Vec<u8> {
+    let bytes = signature.to_bytes();
+    let (r, s) = bytes.split_at(32);
+    let (r, s) = (der_integer(r), der_integer(s));
+    let mut out = vec![DER_SEQUENCE, (r.len() + s.len()) as u8];
+    out.extend_from_slice(&r);
+    out.extend_from_slice(&s);
+    out
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// CLIENT HELLO
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Cursor over a handshake message
+struct Reader<'b> {
+    data: &'b [u8],
+}
+
+impl<'b> Reader<'b> {
+    fn new(data: &'b [u8]) -> Self {
+        Self { data }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'b [u8], TlsError> {
+        if self.data.len() < n {
+            return Err(TlsError::InvalidData);
+        }
+        let (head, rest) = self.data.split_at(n);
+        self.data = rest;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> Result<u8, TlsError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, TlsError> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    /// Vector with a one-byte length
+    fn vec8(&mut self) -> Result<&'b [u8], TlsError> {
+        let len = self.u8()? as usize;
+        self.take(len)
+    }
+
+    /// Vector with a two-byte length
+    fn vec16(&mut self) -> Result<&'b [u8], TlsError> {
+        let len = self.u16()? as usize;
+        self.take(len)
+    }
+}
+
+/// What we need from a ClientHello
+struct ClientHello {
+    random: [u8; 32],
+    /// Curve chosen for the key exchange
+    curve: u16,
+    /// Client supports secure renegotiation (RFC 5746)
+    secure_renegotiation: bool,
+}
+
+fn parse_client_hello(msg: &[u8]) -> Result<ClientHello, TlsError> {
+    let mut r = Reader::new(msg);
+    if r.take(2)? < &TLS_VERSION_1_2[..] {
+        return Err(TlsError::TlsProtocolError);
+    }
+    let mut random = [0u8; 32];
+    random.copy_from_slice(r.take(32)?);
+    r.vec8()?; // Session ID: sessions are not resumed
+
+    let mut offered = false;
+    let mut secure_renegotiation = false;
+    for suite in r.vec16()?.chunks_exact(2) {
+        match [suite[0], suite[1]] {
+            CIPHER_SUITE_ECDHE_ECDSA_AES128_GCM_SHA256 => offered = true,
+            CIPHER_SUITE_RENEGOTIATION_INFO_SCSV => secure_renegotiation = true,
+            _ => {}
+        }
+    }
+    if !offered {
+        return Err(TlsError::TlsProtocolError);
+    }
+    r.vec8()?; // Compression methods
+
+    let mut groups: &[u8] = &[];
+    let mut extensions = Reader::new(if r.is_empty() { &[] } else { r.vec16()? });
+    while !extensions.is_empty() {
+        let ext_type = extensions.u16()?;
+        let body = extensions.vec16()?;
+        match ext_type {
+            EXT_SUPPORTED_GROUPS => groups = Reader::new(body).vec16()?,
+            EXT_RENEGOTIATION_INFO => secure_renegotiation = true,
+            _ => {}
+        }
+    }
+
+    let offers = |curve: u16| groups.chunks_exact(2).any(|g| u16::from_be_bytes([g[0], g[1]]) == curve);
+    // A client without the extension accepts any curve (RFC 4492)
+    let curve = if offers(NAMED_CURVE_X25519) {
+        NAMED_CURVE_X25519
+    } else if offers(NAMED_CURVE_SECP256R1) || groups.is_empty() {
+        NAMED_CURVE_SECP256R1
+    } else {
+        return Err(TlsError::TlsProtocolError);
+    };
+
+    Ok(ClientHello { random, curve, secure_renegotiation })
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// KEY EXCHANGE
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Our ephemeral ECDHE secret
+enum KeyShare {
+    X25519(x25519_dalek::EphemeralSecret),
+    P256(P256Secret),
+}
+
+impl KeyShare {
+    /// New secret on `curve` and its public key as sent in ServerKeyExchange
+    fn generate(curve: u16, rng: &mut KernelRng) -> (Self, Vec<u8>) {
+        if curve == NAMED_CURVE_X25519 {
+            let secret = x25519_dalek::EphemeralSecret::random_from_rng(&mut *rng);
+            let public = x25519_dalek::PublicKey::from(&secret);
+            (KeyShare::X25519(secret), public.as_bytes().to_vec())
+        } else {
+            let secret = P256Secret::random(rng);
+            let public = EncodedPoint::from(&secret.public_key());
+            (KeyShare::P256(secret), public.as_bytes().to_vec())
+        }
+    }
+
+    /// Pre-master secret shared with the client's public key
+    fn agree(self, client_public: &[u8]) -> Result<Vec<u8>, TlsError> {
+        match self {
+            KeyShare::X25519(secret) => {
+                let bytes: [u8; 32] = client_public.try_into().map_err(|_| TlsError::InvalidData)?;
+                let shared = secret.diffie_hellman(&x25519_dalek::PublicKey::from(bytes));
+                Ok(shared.as_bytes().to_vec())
+            }
+            KeyShare::P256(secret) => {
+                let point = EncodedPoint::from_bytes(client_public).map_err(|_| TlsError::InvalidData)?;
+                let public: Option<P256PublicKey> = P256PublicKey::from_encoded_point(&point).into();
+                let public = public.ok_or(TlsError::InvalidData)?;
+                Ok(secret.diffie_hellman(&public).raw_secret_bytes().to_vec())
+            }
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// CONNECTION
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Server end of a TLS 1.2 connection
+pub struct TlsServerConnection<'a> {
+    socket: BlockingTcpSocket<'a>,
+    rng: KernelRng,
+    client_random: [u8; 32],
+    server_random: [u8; 32],
+    master_secret: [u8; 48],
+    client_write_key: [u8; 16],
+    server_write_key: [u8; 16],
+    client_write_iv: [u8; 4],
+    server_write_iv: [u8; 4],
+    client_seq: u64,
+    server_seq: u64,
+    /// Client records are encrypted (after its ChangeCipherSpec)
+    client_encrypted: bool,
+    /// Our records are encrypted (after our ChangeCipherSpec)
+    server_encrypted: bool,
+    /// Handshake messages so far, for the Finished messages
+    handshake_hash: Sha256,
+    /// Handshake bytes received but not yet split into messages
+    handshake_buf: Vec<u8>,
+    /// Application data received but not yet read
+    pending: Vec<u8>,
+}
+
+impl<'a> TlsServerConnection<'a> {
+    /// Run the server handshake on an accepted connection
+    pub fn accept(socket: BlockingTcpSocket<'a>, identity: &ServerIdentity) -> Result<Self, TlsError> {
+        let mut rng = KernelRng;
+        let mut server_random = [0u8; 32];
+        rng.fill_bytes(&mut server_random);
+
+        let mut conn = Self {
+            socket,
+            rng,
+            client_random: [0u8; 32],
+            server_random,
+            master_secret: [0u8; 48],
+            client_write_key: [0u8; 16],
+            server_write_key: [0u8; 16],
+            client_write_iv: [0u8; 4],
+            server_write_iv: [0u8; 4],
+            client_seq: 0,
+            server_seq: 0,
+            client_encrypted: false,
+            server_encrypted: false,
+            handshake_hash: Sha256::new(),
+            handshake_buf: Vec::new(),
+            pending: Vec::new(),
+        };
+
+        if let Err(e) = conn.handshake(identity) {
+            let _ = conn.send_alert(ALERT_LEVEL_FATAL, ALERT_HANDSHAKE_FAILURE);
+            return Err(e);
+        }
+        Ok(conn)
+    }
+
+    fn handshake(&mut self, identity: &ServerIdentity) -> Result<(), TlsError> {
+        let hello = self.recv_handshake(HANDSHAKE_CLIENT_HELLO)?;
+        let hello = parse_client_hello(&hello)?;
+        self.client_random = hello.random;
+
+        self.send_server_hello(hello.secure_renegotiation)?;
+        self.send_certificate(identity)?;
+        let share = self.send_server_key_exchange(identity, hello.curve)?;
+        self.send_handshake(HANDSHAKE_SERVER_HELLO_DONE, &[])?;
+
+        let key_exchange = self.recv_handshake(HANDSHAKE_CLIENT_KEY_EXCHANGE)?;
+        let client_public = Reader::new(&key_exchange).vec8()?;
+        let pre_master_secret = share.agree(client_public)?;
+        self.derive_keys(&pre_master_secret);
+
+        self.recv_change_cipher_spec()?;
+        // The client's Finished covers the handshake up to itself
+        let expected = self.verify_data(b"client finished");
+        let finished = self.recv_handshake(HANDSHAKE_FINISHED)?;
+        let diff = finished.iter().zip(&expected).fold(0u8, |acc, (a, b)| acc | (a ^ b));
+        if finished.len() != expected.len() || diff != 0 {
+            return Err(TlsError::TlsProtocolError);
+        }
+
+        self.send_record(CONTENT_TYPE_CHANGE_CIPHER_SPEC, &[1])?;
+        self.server_encrypted = true;
+        let verify = self.verify_data(b"server finished");
+        self.send_handshake(HANDSHAKE_FINISHED, &verify)
+    }
+
+    fn send_server_hello(&mut self, secure_renegotiation: bool) -> Result<(), TlsError> {
+        let mut hello = Vec::with_capacity(64);
+        hello.extend_from_slice(&TLS_VERSION_1_2);
+        hello.extend_from_slice(&self.server_random);
+        hello.push(0); // Empty session ID: no resumption
+        hello.extend_from_slice(&CIPHER_SUITE_ECDHE_ECDSA_AES128_GCM_SHA256);
+        hello.push(0); // Null compression
+
+        let mut extensions = Vec::new();
+        if secure_renegotiation {
+            extensions.extend_from_slice(&EXT_RENEGOTIATION_INFO.to_be_bytes());
+            extensions.extend_from_slice(&[0, 1, 0]); // Empty renegotiated_connection
+        }
+        extensions.extend_from_slice(&EXT_EC_POINT_FORMATS.to_be_bytes());
+        extensions.extend_from_slice(&[0, 2, 1, 0]); // Uncompressed points only
+        hello.extend_from_slice(&(extensions.len() as u16).to_be_bytes());
+        hello.extend_from_slice(&extensions);
+
+        self.send_handshake(HANDSHAKE_SERVER_HELLO, &hello)
+    }
+
+    fn send_certificate(&mut self, identity: &ServerIdentity) -> Result<(), TlsError> {
+        let list_len: usize = identity.chain.iter().map(|cert| cert.len() + 3).sum();
+        let mut msg = Vec::with_capacity(list_len + 3);
+        msg.extend_from_slice(&(list_len as u32).to_be_bytes()[1..]);
+        for cert in &identity.chain {
+            msg.extend_from_slice(&(cert.len() as u32).to_be_bytes()[1..]);
+            msg.extend_from_slice(cert);
+        }
+        self.send_handshake(HANDSHAKE_CERTIFICATE, &msg)
+    }
+
+    /// Send our ECDHE public key, signed with the certificate key
+    fn send_server_key_exchange(&mut self, identity: &ServerIdentity, curve: u16) -> Result<KeyShare, TlsError> {
+        let (share, public) = KeyShare::generate(curve, &mut self.rng);
+
+        // ECParameters (named_curve) and the public point
+        let mut params = Vec::with_capacity(public.len() + 4);
+        params.push(3);
+        params.extend_from_slice(&curve.to_be_bytes());
+        params.push(public.len() as u8);
+        params.extend_from_slice(&public);
+
+        let mut signed = Vec::with_capacity(64 + params.len());
+        signed.extend_from_slice(&self.client_random);
+        signed.extend_from_slice(&self.server_random);
+        signed.extend_from_slice(&params);
+        let signature: Signature = identity.key.sign(&signed);
+        let signature = signature_der(&signature);
+
+        let mut msg = params;
+        msg.extend_from_slice(&SIGNATURE_ECDSA_SHA256);
+        msg.extend_from_slice(&(signature.len() as u16).to_be_bytes());
+        msg.extend_from_slice(&signature);
+        self.send_handshake(HANDSHAKE_SERVER_KEY_EXCHANGE, &msg)?;
+        Ok(share)
+    }
+
+    /// Master secret and traffic keys from the pre-master secret
+    fn derive_keys(&mut self, pre_master_secret: &[u8]) {
+        let mut seed = Vec::with_capacity(64);
+        seed.extend_from_slice(&self.client_random);
+        seed.extend_from_slice(&self.server_random);
+        prf_sha256(pre_master_secret, b"master secret", &seed, &mut self.master_secret);
+
+        seed.clear();
+        seed.extend_from_slice(&self.server_random);
+        seed.extend_from_slice(&self.client_random);
+        let mut key_block = [0u8; 40];
+        prf_sha256(&self.master_secret, b"key expansion", &seed, &mut key_block);
+
+        self.client_write_key.copy_from_slice(&key_block[0..16]);
+        self.server_write_key.copy_from_slice(&key_block[16..32]);
+        self.client_write_iv.copy_from_slice(&key_block[32..36]);
+        self.server_write_iv.copy_from_slice(&key_block[36..40]);
+    }
+
+    /// verify_data of a Finished message over the handshake so far
+    fn verify_data(&self, label: &[u8]) -> [u8; 12] {
+        let hash = self.handshake_hash.clone().finalize();
+        let mut verify = [0u8; 12];
+        prf_sha256(&self.master_secret, label, &hash, &mut verify);
+        verify
+    }
+
+    // ═══════════════════════════════════════════════════════════════════════
+    // APPLICATION DATA
+    // ═══════════════════════════════════════════════════════════════════════
+
+    /// Read application data; 0 once the client has closed the connection
+    pub fn read(&mut self, buf: &mut [u8]) -> Result<usize, TlsError> {
+        while self.pending.is_empty() {
+            match self.recv_record() {
+                Ok((CONTENT_TYPE_APPLICATION_DATA, data)) => self.pending = data,
+                // A renegotiation request: decline and carry on
+                Ok((CONTENT_TYPE_HANDSHAKE, _)) => self.send_alert(ALERT_LEVEL_WARNING, ALERT_NO_RENEGOTIATION)?,
+                Ok(_) => return Err(TlsError::TlsProtocolError),
+                Err(TlsError::ConnectionClosed) => return Ok(0),
+                Err(e) => return Err(e),
+            }
+        }
+        let n = buf.len().min(self.pending.len());
+        buf[..n].copy_from_slice(&self.pending[..n]);
+        self.pending.drain(..n);
+        Ok(n)
+    }
+
+    /// Send application data
+    pub fn write(&mut self, data: &[u8]) -> Result<usize, TlsError> {
+        self.send_record(CONTENT_TYPE_APPLICATION_DATA, data)?;
+        Ok(data.len())
+    }
+
+    /// Send close_notify and close the TCP connection
+    pub fn close(mut self) {
+        let _ = self.send_alert(ALERT_LEVEL_WARNING, ALERT_CLOSE_NOTIFY);
+        self.socket.close();
+    }
+
+    // ═══════════════════════════════════════════════════════════════════════
+    // RECORD LAYER
+    // ═══════════════════════════════════════════════════════════════════════
+
+    fn send_alert(&mut self, level: u8, description: u8) -> Result<(), TlsError> {
+        self.send_record(CONTENT_TYPE_ALERT, &[level, description])
+    }
+
+    /// Send a handshake message and add it to the transcript
+    fn send_handshake(&mut self, msg_type: u8, body: &[u8]) -> Result<(), TlsError> {
+        let mut msg = Vec::with_capacity(body.len() + 4);
+        msg.push(msg_type);
+        msg.extend_from_slice(&(body.len() as u32).to_be_bytes()[1..]);
+        msg.extend_from_slice(body);
+        self.handshake_hash.update(&msg);
+        self.send_record(CONTENT_TYPE_HANDSHAKE, &msg)
+    }
+
+    /// Send data as one or more records, encrypted once our keys are active
+    fn send_record(&mut self, content_type: u8, data: &[u8]) -> Result<(), TlsError> {
+        for chunk in data.chunks(MAX_RECORD_PLAINTEXT) {
+            let payload = if self.server_encrypted { self.seal(content_type, chunk)? } else { chunk.to_vec() };
+            let mut record = Vec::with_capacity(payload.len() + 5);
+            record.push(content_type);
+            record.extend_from_slice(&TLS_VERSION_1_2);
+            record.extend_from_slice(&(payload.len() as u16).to_be_bytes());
+            record.extend_from_slice(&payload);
+            self.socket.write_all(&record).map_err(|_| TlsError::Io)?;
+        }
+        self.socket.flush().map_err(|_| TlsError::Io)
+    }
+
+    /// Encrypt a record body: explicit nonce, ciphertext and tag
+    fn seal(&mut self, content_type: u8, plaintext: &[u8]) -> Result<Vec<u8>, TlsError> {
+        let seq = self.server_seq;
+        let nonce = gcm_nonce(&self.server_write_iv, &seq.to_be_bytes());
+        let aad = additional_data(seq, content_type, plaintext.len());
+        let cipher = Aes128Gcm::new_from_slice(&self.server_write_key).map_err(|_| TlsError::InternalError)?;
+
+        let mut body = plaintext.to_vec();
+        cipher
+            .encrypt_in_place(Nonce::from_slice(&nonce), &aad, &mut body)
+            .map_err(|_| TlsError::InternalError)?;
+        self.server_seq += 1;
+
+        let mut payload = Vec::with_capacity(GCM_EXPLICIT_NONCE_SIZE + body.len());
+        payload.extend_from_slice(&seq.to_be_bytes());
+        payload.extend_from_slice(&body);
+        Ok(payload)
+    }
+
+    /// Decrypt a record body from the client
+    fn open(&mut self, content_type: u8, payload: &[u8]) -> Result<Vec<u8>, TlsError> {
+        if payload.len() < GCM_EXPLICIT_NONCE_SIZE + GCM_TAG_SIZE {
+            return Err(TlsError::InvalidData);
+        }
+        let (explicit_nonce, ciphertext) = payload.split_at(GCM_EXPLICIT_NONCE_SIZE);
+        let nonce = gcm_nonce(&self.client_write_iv, explicit_nonce);
+        let aad = additional_data(self.client_seq, content_type, ciphertext.len() - GCM_TAG_SIZE);
+        let cipher = Aes128Gcm::new_from_slice(&self.client_write_key).map_err(|_| TlsError::InternalError)?;
+
+        let mut body = ciphertext.to_vec();
+        cipher
+            .decrypt_in_place(Nonce::from_slice(&nonce), &aad, &mut body)
+            .map_err(|_| TlsError::TlsProtocolError)?;
+        self.client_seq += 1;
+        Ok(body)
+    }
+
+    /// Receive one record, decrypted once the client's keys are active.
+    /// An alert from the client ends the connection.
+    fn recv_record(&mut self) -> Result<(u8, Vec<u8>), TlsError> {
+        let mut header = [0u8; 5];
+        self.read_exact(&mut header)?;
+        let content_type = header[0];
+        let length = u16::from_be_bytes([header[3], header[4]]) as usize;
+        if length > MAX_RECORD_PLAINTEXT + 2048 {
+            return Err(TlsError::InvalidData);
+        }
+
+        let mut data = vec![0u8; length];
+        self.read_exact(&mut data)?;
+        if self.client_encrypted {
+            data = self.open(content_type, &data)?;
+        }
+        if content_type == CONTENT_TYPE_ALERT {
+            return Err(TlsError::ConnectionClosed);
+        }
+        Ok((content_type, data))
+    }
+
+    /// Next handshake message, which must be of type `expected`; added to the transcript
+    fn recv_handshake(&mut self, expected: u8) -> Result<Vec<u8>, TlsError> {
+        loop {
+            if self.handshake_buf.len() >= 4 {
+                let buf = &self.handshake_buf;
+                let len = u32::from_be_bytes([0, buf[1], buf[2], buf[3]]) as usize;
+                if len > MAX_HANDSHAKE_MESSAGE {
+                    return Err(TlsError::InvalidData);
+                }
+                if buf.len() >= 4 + len {
+                    let msg: Vec<u8> = self.handshake_buf.drain(..4 + len).collect();
+                    if msg[0] != expected {
+                        return Err(TlsError::TlsProtocolError);
+                    }
+                    self.handshake_hash.update(&msg);
+                    return Ok(msg[4..].to_vec());
+                }
+            }
+            let (content_type, data) = self.recv_record()?;
+            if content_type != CONTENT_TYPE_HANDSHAKE {
+                return Err(TlsError::TlsProtocolError);
+            }
+            self.handshake_buf.extend_from_slice(&data);
+        }
+    }
+
+    /// Receive the client's ChangeCipherSpec and switch to its keys
+    fn recv_change_cipher_spec(&mut self) -> Result<(), TlsError> {
+        if !self.handshake_buf.is_empty() {
+            return Err(TlsError::TlsProtocolError);
+        }
+        match self.recv_record()? {
+            (CONTENT_TYPE_CHANGE_CIPHER_SPEC, data) if data == [1] => {
+                self.client_encrypted = true;
+                Ok(())
+            }
+            _ => Err(TlsError::TlsProtocolError),
+        }
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), TlsError> {
+        let mut pos = 0;
+        while pos < buf.len() {
+            let n = self.socket.read(&mut buf[pos..])?;
+            if n == 0 {
+                return Err(TlsError::ConnectionClosed);
+            }
+            pos += n;
+        }
+        Ok(())
+    }
+}
+
+/// GCM nonce: implicit IV from the key block, then the explicit part
+fn gcm_nonce(iv: &[u8; 4], explicit: &[u8]) -> [u8; GCM_NONCE_SIZE] {
+    let mut nonce = [0u8; GCM_NONCE_SIZE];
+    nonce[..4].copy_from_slice(iv);
+    nonce[4..].copy_from_slice(explicit);
+    nonce
+}
+
+/// AEAD additional data: sequence number, type, version and plaintext length
+fn additional_data(seq: u64, content_type: u8, len: usize) -> [u8; 13] {
+    let mut aad = [0u8; 13];
+    aad[..8].copy_from_slice(&seq.to_be_bytes());
+    aad[8] = content_type;
+    aad[9..11].copy_from_slice(&TLS_VERSION_1_2);
+    aad[11..13].copy_from_slice(&(len as u16).to_be_bytes());
+    aad
+}
@@ -0,0 +1,230 @@
+//! Tone generator: beeps and UI event sounds
+//!
+//! Synthesizes square or sine tones on the calling hart and streams them to
+//! the D1 codec FIFO through `audio_proxy`, one batch of samples per Hart 0
+//! round trip. There is no mixer: one tone plays at a time and a second
+//! caller gets "audio busy".
+//!
+//! Event sounds (notification, error) are off by default and played from a
+//! short-lived process, so the GUI never blocks on the codec.
+
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use crate::clint::get_time_ms;
+use crate::cpu::audio_proxy;
+use crate::Spinlock;
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Constants
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Output sample rate in Hz
+const SAMPLE_RATE: u32 = 48000;
+
+/// Lowest and highest playable frequency in Hz
+pub const MIN_FREQ: u32 = 20;
+pub const MAX_FREQ: u32 = 20000;
+
+/// Longest single tone in milliseconds
+pub const MAX_DURATION_MS: u32 = 5000;
+
+/// Samples per Hart 0 round trip (10ms)
+const BATCH_SAMPLES: usize = (SAMPLE_RATE / 100) as usize;
+
+/// Fade in/out length, avoids clicks at the edges (5ms)
+const RAMP_SAMPLES: usize = (SAMPLE_RATE / 200) as usize;
+
+/// Square wave amplitude (the sine table peaks at ~16000; squares sound louder)
+const SQUARE_AMPLITUDE: i32 = 8000;
+
+/// One cycle of sine, ~50% of full scale
+pub const SINE_TABLE: [i16; 256] = generate_sine_table();
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Tones
+// ═══════════════════════════════════════════════════════════════════════════════
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Waveform {
+    Sine,
+    Square,
+}
+
+/// Set while a tone is playing
+static PLAYING: AtomicBool = AtomicBool::new(false);
+
+/// Play a tone and wait until it has finished
+pub fn play(freq: u32, duration_ms: u32, waveform: Waveform) -> Result<(), &'static str> {
+    if !(MIN_FREQ..=MAX_FREQ).contains(&freq) {
+        return Err("frequency out of range (20-20000 Hz)");
+    }
+    if duration_ms == 0 || duration_ms > MAX_DURATION_MS {
+        return Err("duration out of range (1-5000 ms)");
+    }
+    if !audio_proxy::is_initialized() {
+        return Err("no audio device");
+    }
+    if PLAYING.swap(true, Ordering::Acquire) {
+        return Err("audio busy");
+    }
+
+    audio_proxy::set_sample_rate(SAMPLE_RATE);
+    audio_proxy::set_enabled(true);
+    let result = stream(freq, duration_ms, waveform);
+    audio_proxy::set_enabled(false);
+
+    PLAYING.store(false, Ordering::Release);
+    result
+}
+
+/// Generate the tone batch by batch, waiting for FIFO space as needed
+fn stream(freq: u32, duration_ms: u32, waveform: Waveform) -> Result<(), &'static str> {
+    let total = (SAMPLE_RATE as u64 * duration_ms as u64 / 1000) as usize;
+    let ramp = RAMP_SAMPLES.min(total / 2).max(1);
+    // Phase accumulator: a full cycle is 2^32
+    let step = ((freq as u64) << 32) / SAMPLE_RATE as u64;
+    let mut phase: u32 = 0;
+
+    // The FIFO drains at the sample rate, so give up well after the tone's length
+    let deadline = get_time_ms() + duration_ms as i64 + 1000;
+
+    let mut index = 0;
+    while index < total {
+        let count = BATCH_SAMPLES.min(total - index);
+        let mut batch = Vec::with_capacity(count);
+        for i in index..index + count {
+            let value = match waveform {
+                Waveform::Sine => SINE_TABLE[(phase >> 24) as usize] as i32,
+                Waveform::Square if phase < 0x8000_0000 => SQUARE_AMPLITUDE,
+                Waveform::Square => -SQUARE_AMPLITUDE,
+            };
+            let gain = (i + 1).min(total - i).min(ramp) as i32;
+            let sample = (value * gain / ramp as i32) as i16;
+            batch.push(((sample as u16 as u32) << 16) | sample as u16 as u32);
+            phase = phase.wrapping_add(step as u32);
+        }
+
+        // Push the batch, resubmitting whatever did not fit
+        let mut sent = 0;
+        while sent < batch.len() {
+            let written = audio_proxy::write_samples(batch[sent..].to_vec());
+            sent += written;
+            if written == 0 {
+                if get_time_ms() > deadline {
+                    return Err("audio device stalled");
+                }
+                wait_ms(1);
+            }
+        }
+        index += count;
+    }
+
+    // Let the FIFO drain before playback is disabled
+    while !audio_proxy::is_buffer_empty() && get_time_ms() < deadline {
+        wait_ms(1);
+    }
+    Ok(())
+}
+
+fn wait_ms(ms: i64) {
+    let target = get_time_ms() + ms;
+    while get_time_ms() < target {
+        unsafe { core::arch::asm!("wfi", options(nomem, nostack)); }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Event Sounds
+// ═══════════════════════════════════════════════════════════════════════════════
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    /// Something finished or appeared: two rising notes
+    Notification,
+    /// Something failed: a low buzz
+    Error,
+}
+
+impl Event {
+    /// Notes as (frequency, milliseconds, waveform)
+    fn notes(self) -> &'static [(u32, u32, Waveform)] {
+        match self {
+            Event::Notification => &[(880, 70, Waveform::Sine), (1320, 110, Waveform::Sine)],
+            Event::Error => &[(220, 180, Waveform::Square)],
+        }
+    }
+}
+
+static EVENT_SOUNDS: AtomicBool = AtomicBool::new(false);
+
+/// Event waiting for the `evsound` process
+static PENDING_EVENT: Spinlock<Option<Event>> = Spinlock::new(None);
+
+/// Turn UI event sounds on or off
+pub fn set_event_sounds(enabled: bool) {
+    EVENT_SOUNDS.store(enabled, Ordering::Relaxed);
+}
+
+pub fn event_sounds_enabled() -> bool {
+    EVENT_SOUNDS.load(Ordering::Relaxed)
+}
+
+/// Play the sound for `event` in the background, if event sounds are on
+///
+/// Returns immediately. An event arriving while another is still waiting
+/// replaces it; one arriving while a tone plays is dropped.
+pub fn event(event: Event) {
+    if !event_sounds_enabled() || PLAYING.load(Ordering::Relaxed) {
+        return;
+    }
+    let spawn = PENDING_EVENT.lock().replace(event).is_none();
+    if spawn {
+        crate::cpu::sched::spawn("evsound", play_pending_event, crate::cpu::process::Priority::Low);
+    }
+}
+
+/// Process entry: play the pending event's notes
+fn play_pending_event() {
+    let Some(event) = PENDING_EVENT.lock().take() else {
+        return;
+    };
+    for &(freq, ms, waveform) in event.notes() {
+        if play(freq, ms, waveform).is_err() {
+            break;
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Sine Table
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Generate a sine table at compile time
+const fn generate_sine_table() -> [i16; 256] {
+    let mut table = [0i16; 256];
+    let mut i = 0;
+    while i < 256 {
+        // Scaled integer math (scale = 10000): x = 2π * i / 256
+        let x_scaled = (i as i32 * 62832) / 256;
+
+        // Normalize to -π to π range
+        let x = if x_scaled > 31416 {
+            x_scaled - 62832
+        } else {
+            x_scaled
+        };
+
+        // Parabola approximation: sin(x) ≈ 4x(π-x) / π² for 0 ≤ x ≤ π
+        let pi = 31416; // π * 10000
+        let abs_x = if x < 0 { -x } else { x };
+        let sign = if x < 0 { -1 } else { 1 };
+        let y = (4 * abs_x * (pi - abs_x)) / ((pi * pi) / 10000);
+
+        // Scale to i16 range (-32767 to 32767), but use ~50% volume
+        table[i] = ((sign * y * 16000) / 10000) as i16;
+
+        i += 1;
+    }
+    table
+}
@@ -3,6 +3,7 @@
 //! This module handles all supervisor-mode traps including:
 //! - Timer interrupts (for preemptive scheduling) via SBI
 //! - Software interrupts (IPIs for cross-hart communication)
+//! - External interrupts (device IRQs claimed from the PLIC)
 //! - Exceptions (illegal instructions, page faults, etc.)
 //!
 //! ## Trap Flow
@@ -30,6 +31,16 @@
 //! │ trap_exit   │ (assembly: restore registers, sret)
 //! └─────────────┘
 //! ```
+//!
+//! ## User Mode
+//!
+//! While a native binary runs in U-mode, `sscratch` holds the kernel stack
+//! it left (0 the rest of the time). A trap from the binary swaps it in, so
+//! the trap frame never lands on the binary's own stack, saves the binary's
+//! sp in the frame, and sets it back up on the way out. `satp` is not
+//! switched: the kernel is identity-mapped in every binary's address space
+//! (see `paging`). A fault the binary causes ends it with a signal
+//! (`elf_loader::user_fault`) instead of panicking the kernel.
 
 use core::arch::asm;
 
@@ -59,6 +70,29 @@ pub mod cause {
     pub const STORE_PAGE_FAULT: usize = 15;
 }
 
+/// Size of the trap frame in u64 slots (one of padding)
+pub const FRAME_WORDS: usize = 34;
+
+/// Trap frame slots (u64 index) of the saved ra, a0, sepc, sstatus and sp
+pub const FRAME_RA: usize = 0;
+pub const FRAME_A0: usize = 8;
+pub const FRAME_SEPC: usize = 30;
+const FRAME_SSTATUS: usize = 31;
+pub const FRAME_SP: usize = 32;
+
+/// A hart is inside a user program's syscall
+static IN_USER_SYSCALL: [core::sync::atomic::AtomicBool; crate::cpu::MAX_HARTS] = {
+    const INIT: core::sync::atomic::AtomicBool = core::sync::atomic::AtomicBool::new(false);
+    [INIT; crate::cpu::MAX_HARTS]
+};
+
+/// Whether `hart` is handling a syscall from a user program (its registers
+/// are in the program's trap frame and its return path is `elf_loader`'s
+/// global kernel context, so the tick must not be switched out)
+pub fn in_user_syscall(hart: usize) -> bool {
+    IN_USER_SYSCALL.get(hart).is_some_and(|flag| flag.load(core::sync::atomic::Ordering::Relaxed))
+}
+
 /// Timer interval in cycles (approximately 1ms at 10MHz for responsive input)
 const TIMER_INTERVAL: u64 = 10_000;
 
@@ -103,7 +137,6 @@ pub fn enable_interrupts() {
 }
 
 /// Disable supervisor-mode interrupts
-#[allow(dead_code)]
 pub fn disable_interrupts() {
     unsafe {
         asm!(
@@ -115,7 +148,6 @@ pub fn disable_interrupts() {
 
 /// Check if interrupts are enabled
 #[inline]
-#[allow(dead_code)]
 pub fn interrupts_enabled() -> bool {
     let sstatus: usize;
     unsafe {
@@ -196,7 +228,7 @@ pub extern "C" fn trap_handler(frame: *mut u64) {
     if is_interrupt {
         match scause {
             cause::SUPERVISOR_TIMER_INTERRUPT => {
-                handle_timer_interrupt(hart_id);
+                handle_timer_interrupt(hart_id, frame);
             }
             cause::SUPERVISOR_SOFTWARE_INTERRUPT => {
                 handle_software_interrupt(hart_id);
@@ -217,23 +249,35 @@ pub extern "C" fn trap_handler(frame: *mut u64) {
 }
 
 /// Handle timer interrupt - triggers preemptive scheduling
-fn handle_timer_interrupt(hart_id: usize) {
+///
+/// If the running tick has used up its time slice, this switches to the
+/// scheduler from inside the trap: the interrupted registers stay in the
+/// trap frame on the process's stack (sepc and sstatus included) and are
+/// restored when the process is resumed and the trap returns.
+fn handle_timer_interrupt(hart_id: usize, frame: *mut u64) {
     if let Some(cpu) = crate::cpu::CPU_TABLE.get(hart_id) {
         cpu.enter_interrupt();
     }
     
     // Schedule next timer interrupt via SBI
     schedule_timer_interrupt(hart_id);
-    
-    // Set yield pending flag - actual context switch happens in hart_loop
-    // NOTE: We cannot call switch_context() from here because we're inside
-    // a trap handler that already saved registers to the stack. The actual
-    // preemption happens when the trap returns and hart_loop checks the flag.
-    crate::sched::yield_from_interrupt();
+
+    if hart_id == 0 {
+        crate::uart::poll_input();
+    }
     
     if let Some(cpu) = crate::cpu::CPU_TABLE.get(hart_id) {
         cpu.exit_interrupt();
     }
+
+    // SPP (bit 8) of the saved sstatus: the trap came from S-mode
+    let from_kernel = unsafe { *frame.add(FRAME_SSTATUS) } & (1 << 8) != 0;
+    crate::sched::preempt_if_expired(hart_id, from_kernel);
+
+    // A binary that makes no syscalls still gets its signals
+    if !from_kernel {
+        crate::elf_loader::deliver_signals(frame);
+    }
 }
 
 /// Handle software interrupt (IPI) via SBI
@@ -242,16 +286,38 @@ fn handle_software_interrupt(hart_id: usize) {
     
     if let Some(cpu) = crate::cpu::CPU_TABLE.get(hart_id) {
         cpu.enter_interrupt();
+        // Other harts ask Hart 0 to run their block transfers
+        if hart_id == 0 {
+            crate::platform::d1_mmc::service_transfers();
+        }
         cpu.exit_interrupt();
     }
 }
 
 /// Handle external interrupt (PLIC)
+///
+/// Claims each pending source, runs its driver's handler and completes it.
 fn handle_external_interrupt(hart_id: usize) {
-    klog_trace(
-        "trap",
-        &alloc::format!("External interrupt on hart {}", hart_id),
-    );
+    use crate::platform::{d1_emac, plic};
+
+    if let Some(cpu) = crate::cpu::CPU_TABLE.get(hart_id) {
+        cpu.enter_interrupt();
+    }
+
+    while let Some(irq) = plic::claim(hart_id) {
+        match irq {
+            plic::EMAC_IRQ => d1_emac::handle_interrupt(),
+            _ => klog_trace(
+                "trap",
+                &alloc::format!("Unhandled external interrupt {} on hart {}", irq, hart_id),
+            ),
+        }
+        plic::complete(hart_id, irq);
+    }
+
+    if let Some(cpu) = crate::cpu::CPU_TABLE.get(hart_id) {
+        cpu.exit_interrupt();
+    }
 }
 
 /// Handle exception (synchronous trap)
@@ -265,6 +331,8 @@ fn handle_external_interrupt(hart_id: usize) {
 /// - a5 at offset 104 (index 13)
 /// - a6 at offset 112 (index 14)
 /// - a7 at offset 120 (index 15)
+/// - sepc at offset 240 (index 30), written back to the CSR on return
+/// - sp at offset 256 (index 32), the binary's own in a trap from U-mode
 fn handle_exception(hart_id: usize, cause: usize, frame: *mut u64) {
     let sepc = read_sepc();
     let stval = read_stval();
@@ -282,7 +350,14 @@ fn handle_exception(hart_id: usize, cause: usize, frame: *mut u64) {
             let syscall_num = unsafe { *frame.add(15) }; // offset 120 (a7)
             
             // Call syscall handler
+            let user = cause == cause::ECALL_FROM_U_MODE && hart_id < crate::cpu::MAX_HARTS;
+            if user {
+                IN_USER_SYSCALL[hart_id].store(true, core::sync::atomic::Ordering::Relaxed);
+            }
             let result = crate::syscall::handle_syscall(syscall_num, a0, a1, a2, a3, a4, a5);
+            if user {
+                IN_USER_SYSCALL[hart_id].store(false, core::sync::atomic::Ordering::Relaxed);
+            }
             
             // Check if binary exited - if so, restore kernel context
             if crate::elf_loader::has_exited().is_some() {
@@ -290,18 +365,18 @@ fn handle_exception(hart_id: usize, cause: usize, frame: *mut u64) {
                 crate::elf_loader::restore_kernel_context();
             }
             
-            // Store result in saved a0 (will be restored on return)
-            unsafe { *frame.add(8) = result as u64; }
+            // A signal handler returning gets back the registers it
+            // interrupted instead of a result
+            if !(user && crate::elf_loader::finish_sigreturn(frame)) {
+                // Store result in saved a0 (will be restored on return)
+                unsafe { *frame.add(FRAME_A0) = result as u64; }
+                
+                // Advance PC past ecall instruction (4 bytes)
+                unsafe { *frame.add(FRAME_SEPC) += 4; }
+            }
             
-            // Advance PC past ecall instruction (4 bytes)
-            unsafe {
-                asm!(
-                    "csrr t0, sepc",
-                    "addi t0, t0, 4",
-                    "csrw sepc, t0",
-                    out("x5") _,
-                    options(nomem, nostack)
-                );
+            if user {
+                crate::elf_loader::deliver_signals(frame);
             }
         }
         cause::BREAKPOINT => {
@@ -309,15 +384,11 @@ fn handle_exception(hart_id: usize, cause: usize, frame: *mut u64) {
                 "trap",
                 &alloc::format!("Breakpoint at {:#x} on hart {}", sepc, hart_id),
             );
-            unsafe {
-                asm!(
-                    "csrr t0, sepc",
-                    "addi t0, t0, 2",
-                    "csrw sepc, t0",
-                    out("x5") _,
-                    options(nomem, nostack)
-                );
-            }
+            unsafe { *frame.add(FRAME_SEPC) += 2; }
+        }
+        // SPP (bit 8) of the saved sstatus clear: the binary faulted
+        _ if unsafe { *frame.add(FRAME_SSTATUS) } & (1 << 8) == 0 => {
+            crate::elf_loader::user_fault(cause, sepc, stval);
         }
         _ => {
             panic!(
@@ -334,7 +405,13 @@ core::arch::global_asm!(r#"
 .global trap_vector_entry
 .align 4
 trap_vector_entry:
-    addi sp, sp, -256
+    # From U-mode sscratch holds the kernel stack; from S-mode it is 0 and
+    # the trap stays on the current stack
+    csrrw sp, sscratch, sp
+    bnez sp, 1f
+    csrrw sp, sscratch, sp
+1:
+    addi sp, sp, -272
     
     sd ra, 0(sp)
     sd t0, 8(sp)
@@ -366,10 +443,35 @@ trap_vector_entry:
     sd s11, 216(sp)
     sd gp, 224(sp)
     sd tp, 232(sp)
+    csrr t0, sepc
+    sd t0, 240(sp)
+    csrr t0, sstatus
+    sd t0, 248(sp)
+    # The interrupted sp: the binary's (left in sscratch) or the one above
+    # this frame. sscratch stays 0 until the return to U-mode.
+    csrr t0, sscratch
+    bnez t0, 2f
+    addi t0, sp, 272
+2:
+    sd t0, 256(sp)
+    csrw sscratch, zero
     
     mv a0, sp           # Pass trap frame pointer as first argument
     call trap_handler
     
+    # The handler may have switched away and back (preemption), so the
+    # CSRs may hold another trap's values: restore this trap's own
+    ld t0, 240(sp)
+    csrw sepc, t0
+    ld t0, 248(sp)
+    csrw sstatus, t0
+    # Back to U-mode (SPP clear): its next trap starts on this stack again
+    andi t0, t0, 0x100
+    bnez t0, 3f
+    addi t0, sp, 272
+    csrw sscratch, t0
+3:
+    
     ld ra, 0(sp)
     ld t0, 8(sp)
     ld t1, 16(sp)
@@ -401,7 +503,7 @@ trap_vector_entry:
     ld gp, 224(sp)
     ld tp, 232(sp)
     
-    addi sp, sp, 256
+    ld sp, 256(sp)
     
     sret
 "#);
@@ -414,6 +516,8 @@ extern "C" {
 pub fn init(hart_id: usize) {
     let handler_addr = trap_vector_entry as usize;
     set_trap_vector(handler_addr);
+    // No binary running in U-mode yet
+    unsafe { asm!("csrw sscratch, zero", options(nomem, nostack)) };
     
     schedule_timer_interrupt(hart_id);
     enable_interrupts();
@@ -1,16 +1,78 @@
 //! UI Theme colors
 //!
-//! Defines the color palette used throughout the UI system.
+//! Defines the color palettes used throughout the UI system. Widgets read
+//! colors through `palette()`, which follows the `ui.theme` setting.
 
 use embedded_graphics::pixelcolor::Rgb888;
 
-pub const BACKGROUND: Rgb888 = Rgb888::new(24, 24, 32);
-pub const FOREGROUND: Rgb888 = Rgb888::new(220, 220, 230);
-pub const ACCENT: Rgb888 = Rgb888::new(80, 140, 200);
-pub const ACCENT_HIGHLIGHT: Rgb888 = Rgb888::new(100, 160, 220);
-pub const SUCCESS: Rgb888 = Rgb888::new(80, 200, 120);
-pub const WARNING: Rgb888 = Rgb888::new(230, 180, 80);
-pub const ERROR: Rgb888 = Rgb888::new(220, 80, 80);
-pub const BORDER: Rgb888 = Rgb888::new(60, 60, 80);
-pub const BUTTON_BG: Rgb888 = Rgb888::new(50, 50, 70);
-pub const BUTTON_SELECTED: Rgb888 = Rgb888::new(80, 140, 200);
+use crate::settings::Theme;
+
+/// A complete set of theme colors
+pub struct Palette {
+    pub background: Rgb888,
+    pub foreground: Rgb888,
+    pub accent: Rgb888,
+    pub accent_highlight: Rgb888,
+    pub success: Rgb888,
+    pub warning: Rgb888,
+    pub error: Rgb888,
+    pub border: Rgb888,
+    pub button_bg: Rgb888,
+    pub button_selected: Rgb888,
+    /// Secondary text such as key hints
+    pub hint: Rgb888,
+}
+
+/// Default dark theme
+pub const DEFAULT: Palette = Palette {
+    background: Rgb888::new(24, 24, 32),
+    foreground: Rgb888::new(220, 220, 230),
+    accent: Rgb888::new(80, 140, 200),
+    accent_highlight: Rgb888::new(100, 160, 220),
+    success: Rgb888::new(80, 200, 120),
+    warning: Rgb888::new(230, 180, 80),
+    error: Rgb888::new(220, 80, 80),
+    border: Rgb888::new(60, 60, 80),
+    button_bg: Rgb888::new(50, 50, 70),
+    button_selected: Rgb888::new(80, 140, 200),
+    hint: Rgb888::new(100, 100, 120),
+};
+
+/// Light theme
+pub const LIGHT: Palette = Palette {
+    background: Rgb888::new(236, 236, 240),
+    foreground: Rgb888::new(30, 30, 40),
+    accent: Rgb888::new(40, 100, 180),
+    accent_highlight: Rgb888::new(60, 120, 200),
+    success: Rgb888::new(20, 130, 60),
+    warning: Rgb888::new(170, 110, 0),
+    error: Rgb888::new(190, 40, 40),
+    border: Rgb888::new(170, 170, 185),
+    button_bg: Rgb888::new(210, 210, 220),
+    button_selected: Rgb888::new(40, 100, 180),
+    hint: Rgb888::new(110, 110, 125),
+};
+
+/// High-contrast theme: pure black and white with saturated accents
+pub const HIGH_CONTRAST: Palette = Palette {
+    background: Rgb888::new(0, 0, 0),
+    foreground: Rgb888::new(255, 255, 255),
+    accent: Rgb888::new(255, 255, 0),
+    accent_highlight: Rgb888::new(255, 255, 128),
+    success: Rgb888::new(0, 255, 0),
+    warning: Rgb888::new(255, 160, 0),
+    error: Rgb888::new(255, 64, 64),
+    border: Rgb888::new(255, 255, 255),
+    button_bg: Rgb888::new(0, 0, 0),
+    button_selected: Rgb888::new(0, 0, 255),
+    hint: Rgb888::new(200, 200, 200),
+};
+
+/// The active palette
+pub fn palette() -> &'static Palette {
+    match crate::settings::theme() {
+        Theme::Dark => &DEFAULT,
+        Theme::Light => &LIGHT,
+        Theme::HighContrast => &HIGH_CONTRAST,
+    }
+}
@@ -0,0 +1,21 @@
+//! UI Fonts
+//!
+//! Font roles used by the widgets and the terminal. Each role maps to a
+//! larger face when the `ui.large_font` setting is on.
+
+use embedded_graphics::mono_font::{ascii, MonoFont};
+
+/// Small text (checkbox and radio labels, panel titles)
+pub fn small() -> &'static MonoFont<'static> {
+    if crate::settings::large_font() { &ascii::FONT_8X13 } else { &ascii::FONT_6X10 }
+}
+
+/// Body text (labels, buttons, terminal)
+pub fn body() -> &'static MonoFont<'static> {
+    if crate::settings::large_font() { &ascii::FONT_9X18 } else { &ascii::FONT_7X14 }
+}
+
+/// Window titles
+pub fn title() -> &'static MonoFont<'static> {
+    if crate::settings::large_font() { &ascii::FONT_10X20 } else { &ascii::FONT_9X15_BOLD }
+}
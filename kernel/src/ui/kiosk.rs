@@ -0,0 +1,309 @@
+//! Kiosk Screens
+//!
+//! Full-screen info pages shown by kiosk mode: system information, network
+//! statistics and the kernel log. The `kioskd` service decides which page is
+//! up and when (see `services::kioskd`); gpuid draws it here, keeps its data
+//! fresh and hands the screen back on the first key press or click.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicI64, AtomicU8, Ordering};
+
+use embedded_graphics::{
+    mono_font::{ascii::FONT_7X14, MonoTextStyle},
+    pixelcolor::Rgb888,
+    prelude::*,
+    text::Text,
+};
+
+use crate::lock::state::log::LogLevel;
+use crate::platform::d1_display;
+use crate::services::klogd::KLOG;
+
+use super::{colors, draw_image, fonts, main_screen, SCREEN_HEIGHT, SCREEN_WIDTH, LOGO_SMALL, LOGO_SMALL_SIZE};
+
+/// A full-screen kiosk page
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum KioskPage {
+    SystemInfo,
+    Network,
+    Log,
+}
+
+impl KioskPage {
+    pub const ALL: [KioskPage; 3] = [KioskPage::SystemInfo, KioskPage::Network, KioskPage::Log];
+
+    /// Name used in /etc/kiosk.conf
+    pub fn name(self) -> &'static str {
+        match self {
+            KioskPage::SystemInfo => "sysinfo",
+            KioskPage::Network => "network",
+            KioskPage::Log => "log",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|page| page.name() == name)
+    }
+
+    fn title(self) -> &'static str {
+        match self {
+            KioskPage::SystemInfo => "System Information",
+            KioskPage::Network => "Network Statistics",
+            KioskPage::Log => "Kernel Log",
+        }
+    }
+
+    fn code(self) -> u8 {
+        self as u8 + 1
+    }
+
+    fn from_code(code: u8) -> Option<Self> {
+        Self::ALL.get((code as usize).checked_sub(1)?).copied()
+    }
+}
+
+/// How often the page on screen is redrawn with fresh data
+const REFRESH_MS: i64 = 2000;
+
+/// Height of the header and footer bars
+const BAR_HEIGHT: i32 = 40;
+
+/// Page kioskd wants on screen (0 = none)
+static REQUESTED: AtomicU8 = AtomicU8::new(0);
+/// Page currently drawn (0 = the normal UI is showing)
+static SHOWN: AtomicU8 = AtomicU8::new(0);
+/// Position of the shown page in the cycle, for the footer ("2 / 3")
+static POSITION: AtomicU8 = AtomicU8::new(0);
+static CYCLE_LEN: AtomicU8 = AtomicU8::new(0);
+/// When the shown page was last drawn
+static LAST_DRAW_MS: AtomicI64 = AtomicI64::new(0);
+/// Last key press, click or pointer movement on the GUI
+static LAST_INPUT_MS: AtomicI64 = AtomicI64::new(0);
+
+/// Ask gpuid to show `page` (`None` hands the screen back); `position` and
+/// `count` describe where it sits in the cycle
+pub fn request(page: Option<KioskPage>, position: usize, count: usize) {
+    POSITION.store(position as u8, Ordering::Relaxed);
+    CYCLE_LEN.store(count as u8, Ordering::Relaxed);
+    REQUESTED.store(page.map_or(0, KioskPage::code), Ordering::Relaxed);
+}
+
+/// Time of the last GUI input (ms since boot)
+pub fn last_input_ms() -> i64 {
+    LAST_INPUT_MS.load(Ordering::Relaxed)
+}
+
+/// Whether a kiosk page covers the screen
+pub fn is_showing() -> bool {
+    SHOWN.load(Ordering::Relaxed) != 0
+}
+
+/// Called by gpuid every tick in main screen mode. Draws or refreshes the
+/// requested page; on input, leaves kiosk mode and asks for the normal
+/// screen to be redrawn. Returns whether a kiosk page covers the screen.
+pub fn tick(had_input: bool) -> bool {
+    let now = crate::get_time_ms();
+    if had_input {
+        LAST_INPUT_MS.store(now, Ordering::Relaxed);
+        REQUESTED.store(0, Ordering::Relaxed);
+    }
+
+    let requested = REQUESTED.load(Ordering::Relaxed);
+    let shown = SHOWN.load(Ordering::Relaxed);
+    if requested == 0 {
+        if shown != 0 {
+            SHOWN.store(0, Ordering::Relaxed);
+            main_screen::request_redraw();
+        }
+        return false;
+    }
+
+    let stale = now - LAST_DRAW_MS.load(Ordering::Relaxed) >= REFRESH_MS;
+    if requested != shown || stale {
+        if let Some(page) = KioskPage::from_code(requested) {
+            draw_page(page);
+        }
+        SHOWN.store(requested, Ordering::Relaxed);
+        LAST_DRAW_MS.store(now, Ordering::Relaxed);
+    }
+    true
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Drawing
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// One line of page content: text and its color
+type Line = (String, Rgb888);
+
+fn draw_page(page: KioskPage) {
+    // Gather everything before taking the GPU (avoid locks inside)
+    let palette = colors::palette();
+    let lines = match page {
+        KioskPage::SystemInfo => system_info_lines(),
+        KioskPage::Network => network_lines(),
+        KioskPage::Log => log_lines(),
+    };
+    let host = crate::settings::hostname();
+    let clock = clock_text();
+    let position = POSITION.load(Ordering::Relaxed) as usize + 1;
+    let count = CYCLE_LEN.load(Ordering::Relaxed).max(1) as usize;
+    let footer = format!("{} / {}", position.min(count), count);
+
+    d1_display::with_gpu(|gpu| {
+        let bg = palette.background;
+        let bar = palette.button_bg;
+        let width = SCREEN_WIDTH as u32;
+        gpu.fill_rect(0, 0, width, SCREEN_HEIGHT as u32, bg.r(), bg.g(), bg.b());
+        gpu.fill_rect(0, 0, width, BAR_HEIGHT as u32, bar.r(), bar.g(), bar.b());
+        gpu.fill_rect(0, (SCREEN_HEIGHT - BAR_HEIGHT) as u32, width, BAR_HEIGHT as u32, bar.r(), bar.g(), bar.b());
+
+        let title_style = MonoTextStyle::new(fonts::title(), palette.foreground);
+        let hint_style = MonoTextStyle::new(fonts::body(), palette.hint);
+        draw_image(gpu, 12, 8, LOGO_SMALL_SIZE, LOGO_SMALL_SIZE, LOGO_SMALL);
+        let _ = Text::new(page.title(), Point::new(48, 26), title_style).draw(gpu);
+        let right = format!("{}  {}", host, clock);
+        let right_x = SCREEN_WIDTH - 16 - (right.len() as i32) * fonts::title().character_size.width as i32;
+        let _ = Text::new(&right, Point::new(right_x, 26), title_style).draw(gpu);
+
+        let footer_y = SCREEN_HEIGHT - BAR_HEIGHT / 2 + 5;
+        let _ = Text::new("Press any key or click to exit kiosk mode", Point::new(16, footer_y), hint_style)
+            .draw(gpu);
+        let _ = Text::new(&footer, Point::new(SCREEN_WIDTH - 80, footer_y), hint_style).draw(gpu);
+
+        // The log page packs more lines in with the small fixed font
+        let (font, x, line_height) = match page {
+            KioskPage::Log => (&FONT_7X14, 16, 16),
+            _ => (fonts::title(), 64, 28),
+        };
+        let mut y = BAR_HEIGHT + 36;
+        for (text, color) in &lines {
+            if y > SCREEN_HEIGHT - BAR_HEIGHT - 8 {
+                break;
+            }
+            let _ = Text::new(text, Point::new(x, y), MonoTextStyle::new(font, *color)).draw(gpu);
+            y += line_height;
+        }
+    });
+}
+
+/// Host date and time, or the uptime without an RTC
+fn clock_text() -> String {
+    match crate::device::rtc::get_datetime() {
+        Some(dt) => format!("{:04}-{:02}-{:02} {:02}:{:02}", dt.year, dt.month, dt.day, dt.hour, dt.minute),
+        None => {
+            let secs = crate::get_time_ms() / 1000;
+            format!("up {:02}:{:02}:{:02}", secs / 3600, (secs % 3600) / 60, secs % 60)
+        }
+    }
+}
+
+fn percent(used: usize, total: usize) -> usize {
+    if total == 0 { 0 } else { used * 100 / total }
+}
+
+fn system_info_lines() -> Vec<Line> {
+    let palette = colors::palette();
+    let hw = main_screen::get_hardware_info();
+    let uptime = crate::get_time_ms() / 1000;
+    let value = palette.foreground;
+    let heading = palette.accent;
+
+    let mut lines = Vec::new();
+    lines.push((String::from("About"), heading));
+    lines.push((format!("  HAVY OS {} on RISC-V RV64GC", env!("CARGO_PKG_VERSION")), value));
+    lines.push((
+        format!("  Uptime     {}d {:02}h {:02}m {:02}s", uptime / 86400, (uptime % 86400) / 3600, (uptime % 3600) / 60, uptime % 60),
+        value,
+    ));
+    lines.push((String::new(), value));
+    lines.push((String::from("Hardware"), heading));
+    lines.push((format!("  CPUs       {} harts online", hw.cpu_count), value));
+    lines.push((
+        format!(
+            "  Memory     {} / {} MB ({}%)",
+            hw.memory_used_kb / 1024,
+            hw.memory_total_kb / 1024,
+            percent(hw.memory_used_kb, hw.memory_total_kb)
+        ),
+        value,
+    ));
+    lines.push((
+        format!(
+            "  Disk       {} / {} KB ({}%)",
+            hw.disk_used_kb,
+            hw.disk_total_kb,
+            percent(hw.disk_used_kb, hw.disk_total_kb)
+        ),
+        value,
+    ));
+    lines.push((String::new(), value));
+    lines.push((String::from("Processes"), heading));
+    lines.push((format!("  Running    {}", crate::PROC_SCHEDULER.process_count()), value));
+    lines.push((format!("  Services   {}", crate::init::service_count()), value));
+    lines
+}
+
+fn network_lines() -> Vec<Line> {
+    let palette = colors::palette();
+    let value = palette.foreground;
+    let heading = palette.accent;
+    let dotted = |o: [u8; 4]| format!("{}.{}.{}.{}", o[0], o[1], o[2], o[3]);
+
+    let mut lines = Vec::new();
+    lines.push((String::from("Interface"), heading));
+    if crate::net::is_ip_assigned() {
+        lines.push((String::from("  Status     ONLINE"), palette.success));
+    } else {
+        lines.push((String::from("  Status     OFFLINE"), palette.error));
+    }
+    lines.push((format!("  Hostname   {}", crate::settings::hostname()), value));
+    lines.push((String::new(), value));
+    lines.push((String::from("Configuration"), heading));
+    lines.push((
+        format!("  Address    {}/{}", dotted(crate::net::get_my_ip().octets()), crate::net::PREFIX_LEN),
+        value,
+    ));
+    lines.push((format!("  Gateway    {}", dotted(crate::net::GATEWAY.octets())), value));
+    lines.push((format!("  DNS        {}", dotted(crate::net::DNS_SERVER.octets())), value));
+    lines.push((String::new(), value));
+    lines.push((String::from("Services"), heading));
+    let httpd = if crate::services::httpd::is_running() { "running" } else { "stopped" };
+    lines.push((format!("  httpd      {}", httpd), value));
+    lines.push((format!("  Requests   {} served", crate::services::httpd::requests_served()), value));
+    lines
+}
+
+/// Kernel log lines that fit on the page, newest last
+const LOG_LINES: usize = 40;
+
+fn log_lines() -> Vec<Line> {
+    let palette = colors::palette();
+    // `recent` is newest first
+    KLOG.recent(LOG_LINES)
+        .iter()
+        .rev()
+        .map(|entry| {
+            let color = match entry.level {
+                LogLevel::Emergency | LogLevel::Alert | LogLevel::Critical | LogLevel::Error => palette.error,
+                LogLevel::Warning => palette.warning,
+                LogLevel::Debug | LogLevel::Trace => palette.hint,
+                _ => palette.foreground,
+            };
+            let mut text = format!(
+                "[{:>6}.{:03}] {:<8} {}",
+                entry.timestamp / 1000,
+                entry.timestamp % 1000,
+                entry.subsystem,
+                entry.message
+            );
+            // Keep to one line at 7px per character
+            if let Some((cut, _)) = text.char_indices().nth(140) {
+                text.truncate(cut);
+            }
+            (text, color)
+        })
+        .collect()
+}
@@ -5,6 +5,7 @@
 
 use alloc::format;
 use core::fmt::Write;
+use core::sync::atomic::{AtomicBool, Ordering};
 
 use embedded_graphics::{
     mono_font::{ascii::{FONT_7X14, FONT_9X15_BOLD}, MonoTextStyle},
@@ -18,11 +19,12 @@ use crate::platform::d1_display;
 use crate::platform::d1_touch::{self, ABS_X, ABS_Y, BTN_LEFT, BTN_MIDDLE, BTN_RIGHT, BTN_TOUCH,
     EV_ABS, KEY_DOWN, KEY_ENTER, KEY_LEFT, KEY_RIGHT, KEY_UP};
 
+use super::{colors, fonts};
 use super::cursor::{
     get_cursor_pos, invalidate_cursor_backup, restore_cursor_backup, set_cursor_pos, set_mouse_button,
 };
 use super::manager::with_ui;
-use super::widgets::Window;
+use super::widgets::{QrView, Window};
 use super::{draw_image, LOGO_SMALL, LOGO_SMALL_SIZE};
 
 // Re-export cursor state for internal use
@@ -45,8 +47,6 @@ pub struct HardwareInfo {
 
 /// Get current hardware information from the system
 pub fn get_hardware_info() -> HardwareInfo {
-    use core::sync::atomic::Ordering;
-    
     // Get CPU count from HARTS_ONLINE
     let cpu_count = crate::HARTS_ONLINE.load(Ordering::Relaxed);
     
@@ -101,6 +101,31 @@ static mut MAIN_SCREEN_LAST_SELECTED: Option<usize> = None;
 /// Currently open child window (None = main screen, Some(index) = button window open)
 static mut MAIN_SCREEN_OPEN_WINDOW: Option<usize> = None;
 
+/// Child window index of the Display settings window (4th button)
+const DISPLAY_WINDOW: usize = 3;
+
+/// Child window index of the Settings window (5th button)
+const SETTINGS_WINDOW: usize = 4;
+
+/// Child window index of the QR Code window (opened by `qrcode`, not by a button)
+const QR_WINDOW: usize = 5;
+
+/// Row selected in the Display window (0 = brightness, 1 = contrast, 2 = gamma)
+static mut DISPLAY_SELECTED_ROW: usize = 0;
+
+/// Row selected in the Settings window (index into `SETTINGS_ROWS`)
+static mut SETTINGS_SELECTED_ROW: usize = 0;
+
+/// Text being typed into a Settings row (None = not editing)
+static mut SETTINGS_EDIT_LEN: Option<usize> = None;
+static mut SETTINGS_EDIT_BUFFER: [u8; crate::settings::HOSTNAME_MAX] = [0; crate::settings::HOSTNAME_MAX];
+
+/// Result of the last change in the Settings window (shown under the rows)
+static mut SETTINGS_MESSAGE: Option<&'static str> = None;
+
+/// Text for the QR Code window, handed from the `qrcode` syscall to the next gpuid tick
+static QR_PENDING: crate::Spinlock<Option<alloc::string::String>> = crate::Spinlock::new(None);
+
 // Window backing store - saves region behind child window for instant restore on close
 // Terminal window: 700x500 at (162, 134), shadow: +8 pixels, total ~708x508
 const WINDOW_BACKING_W: usize = 710;
@@ -135,6 +160,9 @@ pub fn hit_test_main_screen_button(x: i32, y: i32) -> Option<usize> {
     let buttons = [
         (30, 500, 110, 32),   // Network (aligned with left column)
         (150, 500, 110, 32),  // Terminal
+        (270, 500, 110, 32),  // Boot chart
+        (390, 500, 110, 32),  // Display
+        (510, 500, 110, 32),  // Settings
     ];
     
     for (i, (bx, by, bw, bh)) in buttons.iter().enumerate() {
@@ -183,6 +211,10 @@ fn get_button_name(index: usize) -> &'static str {
     match index {
         0 => "Network",
         1 => "Terminal",
+        2 => "Boot Chart",
+        DISPLAY_WINDOW => "Display",
+        SETTINGS_WINDOW => "Settings",
+        QR_WINDOW => "QR Code",
         _ => "Unknown",
     }
 }
@@ -303,14 +335,17 @@ pub fn update_main_screen_buttons(selected_button: usize) {
     restore_cursor_backup();
     
     d1_display::with_gpu(|gpu| {
-        // Button definitions - Network and Terminal, left aligned (adjusted for 1024x768)
+        // Button definitions - Network, Terminal, Boot, Display and Settings, left aligned (adjusted for 1024x768)
         let buttons = [
             ("Network", 30),
             ("Terminal", 150),
+            ("Boot", 270),
+            ("Display", 390),
+            ("Settings", 510),
         ];
         
-        // Clear the buttons area (adjusted for 1024x768: wider for 2 buttons)
-        gpu.fill_rect(28, 498, 240, 38, 28, 28, 38);
+        // Clear the buttons area (adjusted for 1024x768: wide enough for 5 buttons)
+        gpu.fill_rect(28, 498, 600, 38, 28, 28, 38);
         
         // Redraw all buttons
         for (i, (label, x)) in buttons.iter().enumerate() {
@@ -389,6 +424,9 @@ fn draw_child_window(button_index: usize) {
     match button_index {
         0 => draw_network_window(),
         1 => draw_terminal_window(),
+        2 => draw_bootchart_window(),
+        DISPLAY_WINDOW => draw_display_window(),
+        SETTINGS_WINDOW => draw_settings_window(),
         _ => {}
     }
 }
@@ -444,7 +482,7 @@ fn draw_network_window() {
         // Content styles
         let label_style = MonoTextStyle::new(&FONT_7X14, Rgb888::new(230, 180, 80));
         let value_style = MonoTextStyle::new(&FONT_7X14, Rgb888::WHITE);
-        let hint_style = MonoTextStyle::new(&FONT_7X14, Rgb888::new(100, 100, 120));
+        let hint_style = MonoTextStyle::new(&FONT_7X14, colors::palette().hint);
         
         let x = 280;
         let mut y = 240;
@@ -494,6 +532,501 @@ fn draw_network_window() {
     });
 }
 
+/// Maximum boot chart rows that fit in the window
+const BOOTCHART_MAX_ROWS: usize = 17;
+
+/// Draw the Boot Chart window: one bar per boot stage / service start,
+/// positioned on a shared time axis (same data as the `bootchart` command)
+fn draw_bootchart_window() {
+    // Pre-compute rows BEFORE entering GPU closure (avoid locks inside)
+    let stages = crate::boot::profile::stages();
+    let total = crate::boot::profile::total_ms().max(1);
+    let total_str = format!("Total: {} ms from reset", total);
+    let rows: alloc::vec::Vec<_> = stages
+        .iter()
+        .take(BOOTCHART_MAX_ROWS)
+        .map(|stage| {
+            let service = stage.kind == crate::boot::profile::StageKind::Service;
+            let label = if service { format!("  {}", stage.name) } else { stage.name.clone() };
+            (label, format!("{} ms", stage.duration_ms()), stage.start_ms, stage.duration_ms(), service)
+        })
+        .collect();
+
+    d1_display::with_gpu(|gpu| {
+        // Same frame as the Network window (centered for 1024x768)
+        gpu.fill_rect(268, 188, 500, 400, 5, 5, 10);  // Shadow
+        gpu.fill_rect(260, 180, 500, 400, 28, 28, 38);  // Window bg
+        gpu.fill_rect(260, 180, 500, 32, 40, 40, 55);  // Title bar
+
+        let _ = Rectangle::new(Point::new(260, 180), Size::new(500, 400))
+            .into_styled(PrimitiveStyle::with_stroke(Rgb888::new(60, 60, 80), 1))
+            .draw(gpu);
+
+        // Traffic light buttons
+        let _ = Circle::new(Point::new(272, 190), 12)
+            .into_styled(PrimitiveStyle::with_fill(Rgb888::new(220, 80, 80)))
+            .draw(gpu);
+        let _ = Circle::new(Point::new(292, 190), 12)
+            .into_styled(PrimitiveStyle::with_fill(Rgb888::new(230, 180, 80)))
+            .draw(gpu);
+        let _ = Circle::new(Point::new(312, 190), 12)
+            .into_styled(PrimitiveStyle::with_fill(Rgb888::new(80, 200, 120)))
+            .draw(gpu);
+
+        let title_style = MonoTextStyle::new(&FONT_9X15_BOLD, Rgb888::WHITE);
+        let _ = Text::new("Boot Chart", Point::new(465, 202), title_style).draw(gpu);
+        draw_image(gpu, 260 + 500 - LOGO_SMALL_SIZE - 8, 184, LOGO_SMALL_SIZE, LOGO_SMALL_SIZE, LOGO_SMALL);
+
+        let label_style = MonoTextStyle::new(&FONT_7X14, Rgb888::new(230, 180, 80));
+        let value_style = MonoTextStyle::new(&FONT_7X14, Rgb888::WHITE);
+        let hint_style = MonoTextStyle::new(&FONT_7X14, colors::palette().hint);
+
+        // Columns: name | bar (time axis 0..total) | duration
+        let name_x = 280;
+        let bar_x: u32 = 400;
+        let bar_w: u32 = 270;
+        let time_x = 680;
+        let mut y = 238;
+
+        let _ = Text::new(&total_str, Point::new(name_x, y), label_style).draw(gpu);
+        y += 20;
+
+        for (label, duration, start, length, service) in &rows {
+            let _ = Text::new(label, Point::new(name_x, y), value_style).draw(gpu);
+
+            // Track, then the span itself (at least 1px so short stages stay visible)
+            gpu.fill_rect(bar_x, y as u32 - 10, bar_w, 10, 40, 40, 55);
+            let offset = ((*start * bar_w as u64) / total) as u32;
+            let width = (((*length * bar_w as u64) / total) as u32).max(1);
+            let offset = offset.min(bar_w - 1);
+            let width = width.min(bar_w - offset);
+            let (r, g, b) = if *service { (80, 200, 120) } else { (80, 140, 200) };
+            gpu.fill_rect(bar_x + offset, y as u32 - 10, width, 10, r, g, b);
+
+            let _ = Text::new(duration, Point::new(time_x, y), value_style).draw(gpu);
+            y += 17;
+        }
+
+        let _ = Text::new("Press ESC or click red button to close", Point::new(330, 560), hint_style).draw(gpu);
+    });
+}
+
+/// Adjustment steps in the Display window, per row
+const DISPLAY_STEPS: [u16; 3] = [5, 5, 10];
+
+/// Draw the Display window: brightness, contrast and gamma with a bar each
+fn draw_display_window() {
+    let adjust = d1_display::color_adjust();
+    let selected = unsafe { DISPLAY_SELECTED_ROW };
+    let rows = [
+        ("Brightness", adjust.brightness, d1_display::BRIGHTNESS_RANGE, format!("{}%", adjust.brightness)),
+        ("Contrast", adjust.contrast, d1_display::CONTRAST_RANGE, format!("{}%", adjust.contrast)),
+        ("Gamma", adjust.gamma, d1_display::GAMMA_RANGE,
+            format!("{}.{:02}", adjust.gamma / 100, adjust.gamma % 100)),
+    ];
+
+    d1_display::with_gpu(|gpu| {
+        // Same frame as the Network window (centered for 1024x768)
+        gpu.fill_rect(268, 188, 500, 400, 5, 5, 10);  // Shadow
+        gpu.fill_rect(260, 180, 500, 400, 28, 28, 38);  // Window bg
+        gpu.fill_rect(260, 180, 500, 32, 40, 40, 55);  // Title bar
+
+        let _ = Rectangle::new(Point::new(260, 180), Size::new(500, 400))
+            .into_styled(PrimitiveStyle::with_stroke(Rgb888::new(60, 60, 80), 1))
+            .draw(gpu);
+
+        // Traffic light buttons
+        let _ = Circle::new(Point::new(272, 190), 12)
+            .into_styled(PrimitiveStyle::with_fill(Rgb888::new(220, 80, 80)))
+            .draw(gpu);
+        let _ = Circle::new(Point::new(292, 190), 12)
+            .into_styled(PrimitiveStyle::with_fill(Rgb888::new(230, 180, 80)))
+            .draw(gpu);
+        let _ = Circle::new(Point::new(312, 190), 12)
+            .into_styled(PrimitiveStyle::with_fill(Rgb888::new(80, 200, 120)))
+            .draw(gpu);
+
+        let title_style = MonoTextStyle::new(&FONT_9X15_BOLD, Rgb888::WHITE);
+        let _ = Text::new("Display", Point::new(478, 202), title_style).draw(gpu);
+        draw_image(gpu, 260 + 500 - LOGO_SMALL_SIZE - 8, 184, LOGO_SMALL_SIZE, LOGO_SMALL_SIZE, LOGO_SMALL);
+
+        let label_style = MonoTextStyle::new(&FONT_7X14, Rgb888::new(230, 180, 80));
+        let selected_style = MonoTextStyle::new(&FONT_7X14, Rgb888::WHITE);
+        let value_style = MonoTextStyle::new(&FONT_7X14, Rgb888::WHITE);
+        let hint_style = MonoTextStyle::new(&FONT_7X14, colors::palette().hint);
+
+        let bar_x: u32 = 420;
+        let bar_w: u32 = 240;
+        let mut y = 260;
+        for (i, (label, value, (lo, hi), text)) in rows.iter().enumerate() {
+            if i == selected {
+                gpu.fill_rect(272, y as u32 - 22, 476, 34, 45, 55, 80);
+                let _ = Text::new(">", Point::new(280, y), selected_style).draw(gpu);
+            }
+            let style = if i == selected { selected_style } else { label_style };
+            let _ = Text::new(label, Point::new(295, y), style).draw(gpu);
+
+            // Track, then the fill up to the current value
+            gpu.fill_rect(bar_x, y as u32 - 10, bar_w, 10, 40, 40, 55);
+            let fill = ((value - lo) as u32 * bar_w) / (hi - lo) as u32;
+            gpu.fill_rect(bar_x, y as u32 - 10, fill.max(1), 10, 80, 140, 200);
+
+            let _ = Text::new(text, Point::new(bar_x as i32 + bar_w as i32 + 12, y), value_style).draw(gpu);
+            y += 50;
+        }
+
+        let _ = Text::new("Also available from the shell: displayctl", Point::new(295, y), hint_style).draw(gpu);
+        let _ = Text::new("Up/Down select, Left/Right adjust, ESC closes", Point::new(318, 560), hint_style).draw(gpu);
+    });
+}
+
+/// Handle a key in the Display window
+fn handle_display_key(code: u16) {
+    let row = unsafe { DISPLAY_SELECTED_ROW };
+    match code {
+        KEY_UP => unsafe { DISPLAY_SELECTED_ROW = row.saturating_sub(1) },
+        KEY_DOWN => unsafe { DISPLAY_SELECTED_ROW = (row + 1).min(2) },
+        KEY_LEFT | KEY_RIGHT => {
+            let mut adjust = d1_display::color_adjust();
+            let (value, (lo, hi)) = match row {
+                0 => (&mut adjust.brightness, d1_display::BRIGHTNESS_RANGE),
+                1 => (&mut adjust.contrast, d1_display::CONTRAST_RANGE),
+                _ => (&mut adjust.gamma, d1_display::GAMMA_RANGE),
+            };
+            *value = if code == KEY_LEFT {
+                value.saturating_sub(DISPLAY_STEPS[row]).max(lo)
+            } else {
+                (*value + DISPLAY_STEPS[row]).min(hi)
+            };
+            let _ = d1_display::set_color_adjust(adjust);
+        }
+        _ => return,
+    }
+    draw_display_window();
+}
+
+/// Rows of the Settings window: label, setting key and the values Left/Right
+/// cycle through (None = free text, typed after pressing Enter)
+const SETTINGS_ROWS: [(&str, &str, Option<&[&str]>); 9] = [
+    ("Hostname", "system.hostname", None),
+    ("Network", "network.profile", Some(&["relay", "static"])),
+    ("Static address", "network.address", None),
+    ("Theme", "ui.theme", Some(&["dark", "light", "high_contrast"])),
+    ("Large font", "ui.large_font", Some(&["false", "true"])),
+    ("Keyboard", "input.keymap", Some(&["us", "de", "fr"])),
+    ("Start sysmond", "services.sysmond", Some(&["false", "true"])),
+    ("Start tcpd", "services.tcpd", Some(&["false", "true"])),
+    ("Start httpd", "services.httpd", Some(&["false", "true"])),
+];
+
+/// Draw the Settings window: one row per setting, the selected one highlighted
+fn draw_settings_window() {
+    let selected = unsafe { SETTINGS_SELECTED_ROW };
+    let editing = unsafe { SETTINGS_EDIT_LEN };
+    let message = unsafe { SETTINGS_MESSAGE };
+
+    d1_display::with_gpu(|gpu| {
+        // Same frame as the Display window
+        gpu.fill_rect(268, 188, 500, 400, 5, 5, 10);  // Shadow
+        gpu.fill_rect(260, 180, 500, 400, 28, 28, 38);  // Window bg
+        gpu.fill_rect(260, 180, 500, 32, 40, 40, 55);  // Title bar
+
+        let _ = Rectangle::new(Point::new(260, 180), Size::new(500, 400))
+            .into_styled(PrimitiveStyle::with_stroke(Rgb888::new(60, 60, 80), 1))
+            .draw(gpu);
+
+        // Traffic light buttons
+        let _ = Circle::new(Point::new(272, 190), 12)
+            .into_styled(PrimitiveStyle::with_fill(Rgb888::new(220, 80, 80)))
+            .draw(gpu);
+        let _ = Circle::new(Point::new(292, 190), 12)
+            .into_styled(PrimitiveStyle::with_fill(Rgb888::new(230, 180, 80)))
+            .draw(gpu);
+        let _ = Circle::new(Point::new(312, 190), 12)
+            .into_styled(PrimitiveStyle::with_fill(Rgb888::new(80, 200, 120)))
+            .draw(gpu);
+
+        let title_style = MonoTextStyle::new(&FONT_9X15_BOLD, Rgb888::WHITE);
+        let _ = Text::new("Settings", Point::new(474, 202), title_style).draw(gpu);
+        draw_image(gpu, 260 + 500 - LOGO_SMALL_SIZE - 8, 184, LOGO_SMALL_SIZE, LOGO_SMALL_SIZE, LOGO_SMALL);
+
+        let label_style = MonoTextStyle::new(&FONT_7X14, Rgb888::new(230, 180, 80));
+        let selected_style = MonoTextStyle::new(&FONT_7X14, Rgb888::WHITE);
+        let value_style = MonoTextStyle::new(&FONT_7X14, Rgb888::WHITE);
+        let hint_style = MonoTextStyle::new(&FONT_7X14, colors::palette().hint);
+
+        let mut y = 242;
+        for (i, (label, key, choices)) in SETTINGS_ROWS.iter().enumerate() {
+            if i == selected {
+                gpu.fill_rect(272, y as u32 - 18, 476, 26, 45, 55, 80);
+                let _ = Text::new(">", Point::new(280, y), selected_style).draw(gpu);
+            }
+            let style = if i == selected { selected_style } else { label_style };
+            let _ = Text::new(label, Point::new(295, y), style).draw(gpu);
+
+            let value = match editing {
+                Some(len) if i == selected => {
+                    let typed = unsafe { &(*core::ptr::addr_of!(SETTINGS_EDIT_BUFFER))[..len] };
+                    format!("{}_", core::str::from_utf8(typed).unwrap_or(""))
+                }
+                _ => {
+                    let value = crate::settings::get(key).unwrap_or_default();
+                    match (choices.is_some(), value.as_str()) {
+                        (true, "true") => alloc::string::String::from("< on >"),
+                        (true, "false") => alloc::string::String::from("< off >"),
+                        (true, _) => format!("< {} >", value),
+                        (false, _) => value,
+                    }
+                }
+            };
+            let _ = Text::new(&value, Point::new(470, y), value_style).draw(gpu);
+            y += 30;
+        }
+
+        if let Some(message) = message {
+            let _ = Text::new(message, Point::new(295, y + 6), label_style).draw(gpu);
+        }
+        let hint = if editing.is_some() {
+            "Type, then Enter saves, ESC cancels"
+        } else if SETTINGS_ROWS[selected].2.is_none() {
+            "Up/Down select, Enter edits, ESC closes"
+        } else {
+            "Up/Down select, Left/Right change, ESC closes"
+        };
+        let _ = Text::new(hint, Point::new(300, 560), hint_style).draw(gpu);
+    });
+}
+
+/// Save a value from the Settings window, noting the outcome for the window
+fn settings_window_set(key: &str, value: &str) {
+    let message = match crate::settings::set(key, value) {
+        Ok(()) if key.starts_with("services.") => "Saved, applies at next boot",
+        Ok(()) => "Saved",
+        Err(e) => e,
+    };
+    unsafe { SETTINGS_MESSAGE = Some(message); }
+}
+
+/// Handle a key in the Settings window; false for ESC outside an edit (closes the window)
+fn handle_settings_key(code: u16) -> bool {
+    use crate::platform::d1_touch::{KEY_BACKSPACE, KEY_ESC};
+
+    let row = unsafe { SETTINGS_SELECTED_ROW };
+    let (_, key, choices) = SETTINGS_ROWS[row];
+
+    if let Some(len) = unsafe { SETTINGS_EDIT_LEN } {
+        match code {
+            KEY_ESC => unsafe { SETTINGS_EDIT_LEN = None },
+            KEY_BACKSPACE => unsafe { SETTINGS_EDIT_LEN = Some(len.saturating_sub(1)) },
+            KEY_ENTER => {
+                unsafe { SETTINGS_EDIT_LEN = None; }
+                let typed = unsafe { &(*core::ptr::addr_of!(SETTINGS_EDIT_BUFFER))[..len] };
+                settings_window_set(key, core::str::from_utf8(typed).unwrap_or(""));
+            }
+            _ => {
+                let ch = key_code_to_ascii(code);
+                if ch != 0 {
+                    handle_settings_char(ch);
+                }
+                return true;
+            }
+        }
+        draw_settings_window();
+        return true;
+    }
+
+    match code {
+        KEY_ESC => {
+            unsafe { SETTINGS_MESSAGE = None; }
+            return false;
+        }
+        KEY_UP => unsafe { SETTINGS_SELECTED_ROW = row.saturating_sub(1) },
+        KEY_DOWN => unsafe { SETTINGS_SELECTED_ROW = (row + 1).min(SETTINGS_ROWS.len() - 1) },
+        KEY_LEFT | KEY_RIGHT => {
+            let Some(choices) = choices else {
+                return true;
+            };
+            let current = crate::settings::get(key).unwrap_or_default();
+            let index = choices.iter().position(|&c| c == current).unwrap_or(0);
+            let next = if code == KEY_LEFT {
+                (index + choices.len() - 1) % choices.len()
+            } else {
+                (index + 1) % choices.len()
+            };
+            settings_window_set(key, choices[next]);
+        }
+        KEY_ENTER if choices.is_none() => {
+            // Start from the current value
+            let current = crate::settings::get(key).unwrap_or_default();
+            let len = current.len().min(crate::settings::HOSTNAME_MAX);
+            unsafe {
+                SETTINGS_EDIT_BUFFER[..len].copy_from_slice(&current.as_bytes()[..len]);
+                SETTINGS_EDIT_LEN = Some(len);
+            }
+        }
+        _ => return true,
+    }
+    draw_settings_window();
+    true
+}
+
+/// Add a typed character to the Settings row being edited
+fn handle_settings_char(ch: u8) {
+    let Some(len) = (unsafe { SETTINGS_EDIT_LEN }) else {
+        return;
+    };
+    if !ch.is_ascii_graphic() || len == crate::settings::HOSTNAME_MAX {
+        return;
+    }
+    unsafe {
+        SETTINGS_EDIT_BUFFER[len] = ch;
+        SETTINGS_EDIT_LEN = Some(len + 1);
+    }
+    draw_settings_window();
+}
+
+/// Show `text` as a QR code in a window (opened on the next gpuid tick)
+pub fn show_qr(text: &str) {
+    *QR_PENDING.lock() = Some(alloc::string::String::from(text));
+}
+
+/// Open the QR Code window if `show_qr` was called, replacing any open window
+pub fn check_qr_request() {
+    let text = match QR_PENDING.lock().take() {
+        Some(text) => text,
+        None => return,
+    };
+    if unsafe { MAIN_SCREEN_OPEN_WINDOW.is_some() } {
+        restore_window_backing();
+    }
+    unsafe { MAIN_SCREEN_OPEN_WINDOW = Some(QR_WINDOW); }
+    save_window_backing();
+    draw_qr_window(&text);
+}
+
+/// Act on what the page embedding the emulator did (see `services::hostbridged`):
+/// pushed clipboard text is pasted into the terminal input, dropped files are
+/// reported in the terminal output. Events arriving with the terminal closed
+/// are only logged by the daemon.
+pub fn check_host_events() {
+    use crate::services::hostbridged::{self, HostEvent};
+
+    while let Some(event) = hostbridged::next_event() {
+        let (open_window, running) = unsafe { (MAIN_SCREEN_OPEN_WINDOW, TERMINAL_COMMAND_RUNNING) };
+        if open_window != Some(1) || running {
+            continue;
+        }
+        match event {
+            HostEvent::Clipboard(text) => terminal_paste(&text),
+            HostEvent::FileDropped { path, size } => {
+                let line = format!("Received {} ({} bytes)\n", path, size);
+                unsafe {
+                    for &b in line.as_bytes() {
+                        if TERMINAL_OUTPUT_LEN >= TERMINAL_OUTPUT_MAX {
+                            break;
+                        }
+                        TERMINAL_OUTPUT_BUFFER[TERMINAL_OUTPUT_LEN] = b;
+                        TERMINAL_OUTPUT_LEN += 1;
+                    }
+                }
+                draw_terminal_output_only();
+                crate::tone::event(crate::tone::Event::Notification);
+            }
+        }
+    }
+}
+
+/// Set by `request_redraw`, consumed by the next gpuid tick
+static REDRAW_PENDING: AtomicBool = AtomicBool::new(false);
+
+/// Ask for a full redraw on the next gpuid tick (after a theme or font change)
+pub fn request_redraw() {
+    REDRAW_PENDING.store(true, Ordering::Relaxed);
+}
+
+/// Redraw the whole screen, and the open child window, if `request_redraw` was called
+pub fn check_redraw_request() {
+    if !REDRAW_PENDING.swap(false, Ordering::Relaxed) {
+        return;
+    }
+    let hw = get_hardware_info();
+    // Draw the main screen alone first so the backing store picks up the new look;
+    // the QR window has no state to redraw from and is closed instead
+    let open_window = unsafe { MAIN_SCREEN_OPEN_WINDOW.take() }.filter(|&w| w != QR_WINDOW);
+    unsafe {
+        MAIN_SCREEN_STATIC_DRAWN = false;
+        MAIN_SCREEN_LAST_SELECTED = None;
+    }
+    draw_main_screen_content(&hw, unsafe { MAIN_SCREEN_SELECTED_BUTTON });
+    if let Some(win_idx) = open_window {
+        unsafe { MAIN_SCREEN_OPEN_WINDOW = Some(win_idx); }
+        draw_child_window(win_idx);
+    }
+}
+
+/// Longest line of text under the code (7px font in a 500px window)
+const QR_TEXT_MAX_CHARS: usize = 64;
+
+/// Draw the QR Code window: the code, scaled to fit, with its text below
+fn draw_qr_window(text: &str) {
+    // Encode BEFORE entering GPU closure
+    let code = super::qr::QrCode::encode(text.as_bytes());
+    let caption = if text.chars().count() > QR_TEXT_MAX_CHARS {
+        let cut: alloc::string::String = text.chars().take(QR_TEXT_MAX_CHARS - 3).collect();
+        format!("{}...", cut)
+    } else {
+        alloc::string::String::from(text)
+    };
+
+    d1_display::with_gpu(|gpu| {
+        // Same frame as the Network window (centered for 1024x768)
+        gpu.fill_rect(268, 188, 500, 400, 5, 5, 10);  // Shadow
+        gpu.fill_rect(260, 180, 500, 400, 28, 28, 38);  // Window bg
+        gpu.fill_rect(260, 180, 500, 32, 40, 40, 55);  // Title bar
+
+        let _ = Rectangle::new(Point::new(260, 180), Size::new(500, 400))
+            .into_styled(PrimitiveStyle::with_stroke(Rgb888::new(60, 60, 80), 1))
+            .draw(gpu);
+
+        // Traffic light buttons
+        let _ = Circle::new(Point::new(272, 190), 12)
+            .into_styled(PrimitiveStyle::with_fill(Rgb888::new(220, 80, 80)))
+            .draw(gpu);
+        let _ = Circle::new(Point::new(292, 190), 12)
+            .into_styled(PrimitiveStyle::with_fill(Rgb888::new(230, 180, 80)))
+            .draw(gpu);
+        let _ = Circle::new(Point::new(312, 190), 12)
+            .into_styled(PrimitiveStyle::with_fill(Rgb888::new(80, 200, 120)))
+            .draw(gpu);
+
+        let title_style = MonoTextStyle::new(&FONT_9X15_BOLD, Rgb888::WHITE);
+        let _ = Text::new("QR Code", Point::new(478, 202), title_style).draw(gpu);
+        draw_image(gpu, 260 + 500 - LOGO_SMALL_SIZE - 8, 184, LOGO_SMALL_SIZE, LOGO_SMALL_SIZE, LOGO_SMALL);
+
+        let value_style = MonoTextStyle::new(&FONT_7X14, Rgb888::WHITE);
+        let error_style = MonoTextStyle::new(&FONT_7X14, Rgb888::new(220, 80, 80));
+        let hint_style = MonoTextStyle::new(&FONT_7X14, colors::palette().hint);
+
+        match &code {
+            Ok(code) => {
+                // Code area: 290px square between the title bar and the caption
+                let scale = QrView::fit_scale(code, 290);
+                let mut view = QrView::new(code, 0, 222, scale);
+                view.x = 260 + (500 - view.pixel_size() as i32) / 2;
+                let _ = view.draw(gpu);
+
+                let caption_x = 260 + (500 - caption.chars().count() as i32 * 7) / 2;
+                let _ = Text::new(&caption, Point::new(caption_x, 535), value_style).draw(gpu);
+            }
+            Err(e) => {
+                let _ = Text::new(e, Point::new(280, 240), error_style).draw(gpu);
+            }
+        }
+
+        let _ = Text::new("Press ESC or click red button to close", Point::new(330, 560), hint_style).draw(gpu);
+    });
+}
+
 /// Draw the Terminal window content
 fn draw_terminal_window() {
     // Window dimensions: 700x500, centered on 1024x768
@@ -530,8 +1063,9 @@ fn draw_terminal_window() {
         let _ = Text::new("Terminal", Point::new(WIN_X as i32 + 310, WIN_Y as i32 + 22), title_style).draw(gpu);
         draw_image(gpu, WIN_X + WIN_W - LOGO_SMALL_SIZE as u32 - 8, WIN_Y + 4, LOGO_SMALL_SIZE, LOGO_SMALL_SIZE, LOGO_SMALL);
         
-        let hint_style = MonoTextStyle::new(&FONT_7X14, Rgb888::new(100, 100, 120));
-        let value_style = MonoTextStyle::new(&FONT_7X14, Rgb888::WHITE);
+        let hint_style = MonoTextStyle::new(&FONT_7X14, colors::palette().hint);
+        let input_style = MonoTextStyle::new(fonts::body(), Rgb888::WHITE);
+        let (char_w, line_h, max_chars_per_line, max_lines) = terminal_text_metrics();
         
         // Content area starts at WIN_Y + 40
         let content_y = WIN_Y + 45;
@@ -561,10 +1095,10 @@ fn draw_terminal_window() {
         let input_text = unsafe {
             core::str::from_utf8(&TERMINAL_INPUT_BUFFER[..TERMINAL_INPUT_LEN]).unwrap_or("")
         };
-        let _ = Text::new(input_text, Point::new(content_x as i32 + 7, input_y as i32 + 19), value_style).draw(gpu);
+        let _ = Text::new(input_text, Point::new(content_x as i32 + 7, input_y as i32 + 19), input_style).draw(gpu);
         
         // Draw cursor (always visible, simple block cursor)
-        let cursor_x = content_x as i32 + 7 + (unsafe { TERMINAL_INPUT_LEN } as i32 * 7);
+        let cursor_x = content_x as i32 + 7 + (unsafe { TERMINAL_INPUT_LEN } as i32 * char_w);
         if cursor_x < content_x as i32 + 570 {  // Don't draw cursor past input field
             let _ = Rectangle::new(Point::new(cursor_x, input_y as i32 + 5), Size::new(2, 16))
                 .into_styled(PrimitiveStyle::with_fill(Rgb888::new(200, 200, 220)))
@@ -612,18 +1146,17 @@ fn draw_terminal_window() {
         .into_styled(PrimitiveStyle::with_stroke(Rgb888::new(60, 60, 80), 1))
         .draw(gpu);
         
-        // Draw output text (multi-line) - now fits ~22 lines
+        // Draw output text (multi-line) - 22 lines in the normal font
         let output_text = unsafe {
             core::str::from_utf8(&TERMINAL_OUTPUT_BUFFER[..TERMINAL_OUTPUT_LEN]).unwrap_or("")
         };
         
-        let output_style = MonoTextStyle::new(&FONT_7X14, Rgb888::new(80, 200, 120));
+        let output_style = MonoTextStyle::new(fonts::body(), colors::palette().success);
         let mut y = output_y as i32 + 15;
-        let max_chars_per_line = 92;  // 670px / 7px per char ≈ 95, leave margin
         let mut line_count = 0;
         
         for line in output_text.lines() {
-            if line_count >= 22 {
+            if line_count >= max_lines {
                 break;
             }
             // Truncate long lines
@@ -633,7 +1166,7 @@ fn draw_terminal_window() {
                 line
             };
             let _ = Text::new(display_line, Point::new(content_x as i32 + 7, y), output_style).draw(gpu);
-            y += 15;
+            y += line_h;
             line_count += 1;
         }
         
@@ -642,6 +1175,16 @@ fn draw_terminal_window() {
     });
 }
 
+/// Terminal text layout for the current font:
+/// (char width, line height, chars per output line, output lines)
+fn terminal_text_metrics() -> (i32, i32, usize, usize) {
+    let size = fonts::body().character_size;
+    let char_w = size.width as i32;
+    let line_h = size.height as i32 + 1;
+    // Output area is 670x340; leave a margin on the right and at the bottom
+    (char_w, line_h, (650 / char_w) as usize, (330 / line_h) as usize)
+}
+
 /// Fast partial redraw of ONLY the input field (for responsive typing)
 /// This is much faster than redrawing the entire terminal window
 fn draw_terminal_input_only() {
@@ -652,7 +1195,8 @@ fn draw_terminal_input_only() {
     const INPUT_Y: u32 = WIN_Y + 55;
     
     d1_display::with_gpu(|gpu| {
-        let value_style = MonoTextStyle::new(&FONT_7X14, Rgb888::WHITE);
+        let input_style = MonoTextStyle::new(fonts::body(), Rgb888::WHITE);
+        let (char_w, ..) = terminal_text_metrics();
         
         // Clear only the input field interior (not the border)
         // Input field is at (CONTENT_X, INPUT_Y) with size (580, 28)
@@ -662,10 +1206,10 @@ fn draw_terminal_input_only() {
         let input_text = unsafe {
             core::str::from_utf8(&TERMINAL_INPUT_BUFFER[..TERMINAL_INPUT_LEN]).unwrap_or("")
         };
-        let _ = Text::new(input_text, Point::new(CONTENT_X as i32 + 7, INPUT_Y as i32 + 19), value_style).draw(gpu);
+        let _ = Text::new(input_text, Point::new(CONTENT_X as i32 + 7, INPUT_Y as i32 + 19), input_style).draw(gpu);
         
         // Draw cursor (always visible, simple block cursor)
-        let cursor_x = CONTENT_X as i32 + 7 + (unsafe { TERMINAL_INPUT_LEN } as i32 * 7);
+        let cursor_x = CONTENT_X as i32 + 7 + (unsafe { TERMINAL_INPUT_LEN } as i32 * char_w);
         if cursor_x < CONTENT_X as i32 + 570 {  // Don't draw cursor past input field
             let _ = Rectangle::new(Point::new(cursor_x, INPUT_Y as i32 + 5), Size::new(2, 16))
                 .into_styled(PrimitiveStyle::with_fill(Rgb888::new(200, 200, 220)))
@@ -685,7 +1229,8 @@ fn draw_terminal_output_only() {
     const OUTPUT_Y: u32 = WIN_Y + 105;
     
     d1_display::with_gpu(|gpu| {
-        let output_style = MonoTextStyle::new(&FONT_7X14, Rgb888::new(80, 200, 120));
+        let output_style = MonoTextStyle::new(fonts::body(), colors::palette().success);
+        let (_, line_h, max_chars_per_line, max_lines) = terminal_text_metrics();
         
         // Clear only the output area interior (not the border)
         // Output area is at (CONTENT_X, OUTPUT_Y) with size (670, 340)
@@ -696,12 +1241,11 @@ fn draw_terminal_output_only() {
             core::str::from_utf8(&TERMINAL_OUTPUT_BUFFER[..TERMINAL_OUTPUT_LEN]).unwrap_or("")
         };
         
-        let max_chars_per_line = 92;  // 670px / 7px per char ≈ 95, leave margin
         let mut y = OUTPUT_Y as i32 + 15;
         let mut line_count = 0;
         
         for line in output_text.lines() {
-            if line_count >= 22 {
+            if line_count >= max_lines {
                 break;
             }
             let display_line = if line.len() > max_chars_per_line {
@@ -710,7 +1254,7 @@ fn draw_terminal_output_only() {
                 line
             };
             let _ = Text::new(display_line, Point::new(CONTENT_X as i32 + 7, y), output_style).draw(gpu);
-            y += 15;
+            y += line_h;
             line_count += 1;
         }
     });
@@ -759,7 +1303,6 @@ fn terminal_execute_command() {
     use crate::device::uart::write_line;
     use crate::lock::utils::OUTPUT_CAPTURE;
     use crate::services::gui_cmd::GUI_CMD_RUNNING;
-    use core::sync::atomic::Ordering;
     
     
     let cmd_len = unsafe { TERMINAL_INPUT_LEN };
@@ -784,6 +1327,10 @@ fn terminal_execute_command() {
     }
     
     
+    // `$?` is the exit status of the previous command
+    let expanded = crate::scripting::expand_status(cmd_str.as_bytes());
+    let cmd_str = core::str::from_utf8(&expanded).unwrap_or("");
+    
     // Split into command and arguments
     let mut parts = cmd_str.splitn(2, ' ');
     let cmd = parts.next().unwrap_or("");
@@ -815,10 +1362,9 @@ fn terminal_execute_command() {
     }
     
     
-    // Execute command - for U-mode ELFs this does NOT return!
-    // Control goes: sret -> U-mode -> SYS_EXIT -> trap -> restore_kernel_context 
-    //            -> signal_completion -> hart_loop
-    // check_gui_command_completion() in gpuid_tick will poll for the result.
+    // Execute command - for U-mode ELFs control goes:
+    // sret -> U-mode -> SYS_EXIT -> trap -> restore_kernel_context
+    //      -> signal_completion -> back here
     crate::scripting::execute_command(cmd.as_bytes(), args.as_bytes());
   
     // Stop output capture
@@ -901,6 +1447,11 @@ pub fn check_gui_command_completion() {
         
         // Mark command as finished
         unsafe { TERMINAL_COMMAND_RUNNING = false; }
+        crate::tone::event(if result.exit_code == 0 {
+            crate::tone::Event::Notification
+        } else {
+            crate::tone::Event::Error
+        });
         
         // Update UI
         draw_terminal_output_only();
@@ -972,6 +1523,8 @@ pub fn request_cancel() {
     unsafe {
         if TERMINAL_COMMAND_RUNNING {
             TERMINAL_CANCEL_REQUESTED = true;
+            // A native binary gets SIGINT (WASM programs only see the flag)
+            crate::cpu::signal::send_foreground(crate::cpu::signal::SIGINT);
             // Also add "^C" to output
             if TERMINAL_OUTPUT_LEN + 3 < TERMINAL_OUTPUT_MAX {
                 TERMINAL_OUTPUT_BUFFER[TERMINAL_OUTPUT_LEN] = b'^';
@@ -1039,6 +1592,12 @@ fn handle_terminal_char(ch: u8) {
         return;
     }
     
+    // Ctrl+V (0x16) - paste the clipboard
+    if ch == 0x16 {
+        terminal_paste(&crate::services::hostbridged::clipboard());
+        return;
+    }
+    
     if ch >= 0x20 && ch < 0x7F {  // Printable ASCII
         unsafe {
             if TERMINAL_INPUT_LEN < TERMINAL_INPUT_MAX - 1 {
@@ -1052,6 +1611,23 @@ fn handle_terminal_char(ch: u8) {
     }
 }
 
+/// Append the first line of `text` to the terminal input (printable ASCII only)
+fn terminal_paste(text: &str) {
+    let line = text.lines().next().unwrap_or("");
+    unsafe {
+        for &b in line.as_bytes() {
+            if TERMINAL_INPUT_LEN >= TERMINAL_INPUT_MAX - 1 {
+                break;
+            }
+            if (0x20..0x7F).contains(&b) {
+                TERMINAL_INPUT_BUFFER[TERMINAL_INPUT_LEN] = b;
+                TERMINAL_INPUT_LEN += 1;
+            }
+        }
+    }
+    draw_terminal_input_only();
+}
+
 /// Check if terminal send button was clicked
 fn hit_test_terminal_send_button(x: i32, y: i32) -> bool {
     // Send button position for 700x500 window:
@@ -1175,16 +1751,19 @@ fn draw_main_screen_content_inner(hw: &HardwareInfo, selected_button: usize) {
             .draw(gpu);
         
         // Navigation hint
-        let hint_style = MonoTextStyle::new(&FONT_7X14, Rgb888::new(100, 100, 120));
+        let hint_style = MonoTextStyle::new(&FONT_7X14, colors::palette().hint);
         let _ = Text::new("Use arrows to select, Enter to open", Point::new(col1_x, 488), hint_style).draw(gpu);
         
         // Mark static content as drawn so next time we skip the expensive clear
         unsafe { MAIN_SCREEN_STATIC_DRAWN = true; }
         
-        // Network and Terminal buttons, left aligned (adjusted for 1024x768)
+        // Network, Terminal, Boot, Display and Settings buttons, left aligned (adjusted for 1024x768)
         let buttons = [
             ("Network", 30),
             ("Terminal", 150),
+            ("Boot", 270),
+            ("Display", 390),
+            ("Settings", 510),
         ];
         
         for (i, (label, x)) in buttons.iter().enumerate() {
@@ -1234,9 +1813,9 @@ fn draw_main_screen_content_inner(hw: &HardwareInfo, selected_button: usize) {
         
         let services = [
             ("shell", true),
-            ("httpd", true),
-            ("tcpd", true),
-            ("sysmond", true),
+            ("httpd", crate::settings::autostart("httpd")),
+            ("tcpd", crate::settings::autostart("tcpd")),
+            ("sysmond", crate::settings::autostart("sysmond")),
         ];
         
         // Services in a vertical list for cleaner layout
@@ -1358,6 +1937,10 @@ pub fn handle_main_screen_input(event: d1_touch::InputEvent) -> Option<usize> {
                 handle_terminal_char(event.code as u8);
                 return None;
             }
+            if win_idx == SETTINGS_WINDOW && event.code > 0 && event.code < 128 {
+                handle_settings_char(event.code as u8);
+                return None;
+            }
         }
         return None;
     }
@@ -1379,7 +1962,7 @@ pub fn handle_main_screen_input(event: d1_touch::InputEvent) -> Option<usize> {
                     // If child window is open, check for close button click or Terminal send button
                     if let Some(win_idx) = open_window {
                         // Close button position depends on which window is open
-                        // Network window (idx 0): at (260, 180) - close button at (260 + 12, 180 + 10)
+                        // Network, Boot Chart, Display, Settings and QR Code windows (idx 0, 2, 3, 4, 5): at (260, 180) - close button at (260 + 12, 180 + 10)
                         // Terminal window (idx 1): at (162, 134) - close button at (162 + 12, 134 + 10)
                         let (win_x, win_y) = if win_idx == 1 { (162, 134) } else { (260, 180) };
                         let close_btn_x = win_x + 12;
@@ -1393,6 +1976,8 @@ pub fn handle_main_screen_input(event: d1_touch::InputEvent) -> Option<usize> {
                             unsafe {
                                 TERMINAL_INPUT_LEN = 0;
                                 TERMINAL_OUTPUT_LEN = 0;
+                                SETTINGS_EDIT_LEN = None;
+                                SETTINGS_MESSAGE = None;
                                 MAIN_SCREEN_OPEN_WINDOW = None;
                             }
                             restore_window_backing();
@@ -1446,6 +2031,11 @@ pub fn handle_main_screen_input(event: d1_touch::InputEvent) -> Option<usize> {
     if let Some(win_idx) = open_window {
         use crate::platform::d1_touch::KEY_ESC;
         
+        // Settings window: takes every key but ESC, which only cancels an edit
+        if win_idx == SETTINGS_WINDOW && handle_settings_key(event.code) {
+            return None;
+        }
+
         // ESC handling: if command is running, cancel it; otherwise close the window
         if event.code == KEY_ESC {
             if unsafe { TERMINAL_COMMAND_RUNNING } {
@@ -1465,6 +2055,12 @@ pub fn handle_main_screen_input(event: d1_touch::InputEvent) -> Option<usize> {
             }
         }
         
+        // Display window: arrows select and adjust a setting
+        if win_idx == DISPLAY_WINDOW {
+            handle_display_key(event.code);
+            return None;
+        }
+
         // If Terminal window is open, handle keyboard input
         if win_idx == 1 {
             // Handle special keys
@@ -1496,9 +2092,9 @@ pub fn handle_main_screen_input(event: d1_touch::InputEvent) -> Option<usize> {
             None
         }
         KEY_RIGHT => {
-            // Navigate to next button (2 buttons: 0 and 1)
+            // Navigate to next button (4 buttons: 0 to 3)
             unsafe {
-                if MAIN_SCREEN_SELECTED_BUTTON < 1 {
+                if MAIN_SCREEN_SELECTED_BUTTON < SETTINGS_WINDOW {
                     MAIN_SCREEN_SELECTED_BUTTON += 1;
                     update_main_screen_buttons(MAIN_SCREEN_SELECTED_BUTTON);
                 }
@@ -1528,8 +2124,26 @@ pub fn handle_main_screen_input(event: d1_touch::InputEvent) -> Option<usize> {
     }
 }
 
-/// Convert a key code to ASCII character (basic US keyboard layout)
+/// Convert a key code to ASCII character (US layout, with the letters of the
+/// German and French layouts moved to their keys per `input.keymap`)
 fn key_code_to_ascii(code: u16) -> u8 {
+    use crate::settings::Keymap;
+
+    // Pick the US key that prints what this key prints in the active layout
+    let code = match (crate::settings::keymap(), code) {
+        // QWERTZ: Y and Z swapped
+        (Keymap::De, 21) => 44,
+        (Keymap::De, 44) => 21,
+        // AZERTY: A/Q and Z/W swapped, M right of L, comma where M was
+        (Keymap::Fr, 16) => 30,
+        (Keymap::Fr, 30) => 16,
+        (Keymap::Fr, 17) => 44,
+        (Keymap::Fr, 44) => 17,
+        (Keymap::Fr, 39) => 50,
+        (Keymap::Fr, 50) => 51,
+        _ => code,
+    };
+
     // Linux input key codes (from linux/input-event-codes.h)
     // Numbers: KEY_1=2, KEY_2=3, ... KEY_0=11
     // Letters: KEY_Q=16, KEY_W=17, ... 
@@ -127,9 +127,9 @@ impl UiManager {
         d1_display::with_gpu(|gpu| {
             // Clear background
             let _ = gpu.clear(
-                colors::BACKGROUND.r(),
-                colors::BACKGROUND.g(),
-                colors::BACKGROUND.b(),
+                colors::palette().background.r(),
+                colors::palette().background.g(),
+                colors::palette().background.b(),
             );
 
             // Draw all labels
@@ -4,12 +4,16 @@
 //! to the VirtIO GPU framebuffer.
 //!
 //! This module is organized into submodules:
-//! - `colors`: Theme color constants
+//! - `colors`: Theme color palettes (default and high-contrast)
+//! - `fonts`: Font roles, scaled up in large-font mode
 //! - `cursor`: Mouse/cursor handling
 //! - `widgets`: UI widget components (Button, Label, etc.)
 //! - `manager`: UiManager and global state
 //! - `main_screen`: Main screen functionality
+//! - `kiosk`: Full-screen info pages cycled by kiosk mode
 //! - `boot`: Boot screen setup
+//! - `panic_screen`: Red diagnostic screen drawn by the panic handler
+//! - `qr`: QR code encoder (drawn by the `QrView` widget)
 
 use crate::platform::d1_display;
 use crate::uart;
@@ -18,8 +22,12 @@ use crate::uart;
 pub mod boot;
 pub mod colors;
 pub mod cursor;
+pub mod fonts;
+pub mod kiosk;
 pub mod main_screen;
 pub mod manager;
+pub mod panic_screen;
+pub mod qr;
 pub mod widgets;
 
 // Re-export commonly used items at the module root for backwards compatibility
@@ -0,0 +1,92 @@
+//! Panic Screen - Last-gasp diagnostics on the framebuffer
+//!
+//! Drawn from the panic handler, so nothing here allocates or takes locks:
+//! the report text comes from the panic module's static emergency buffer
+//! and pixels go straight to the back buffer through the global GPU driver.
+//! Once presented, the display is frozen so other harts can't flush over it.
+
+use embedded_graphics::{
+    pixelcolor::Rgb888,
+    prelude::*,
+};
+
+use u8g2_fonts::{
+    fonts,
+    types::{FontColor, HorizontalAlignment, VerticalPosition},
+    FontRenderer,
+};
+
+use crate::platform::d1_display::{self, DISPLAY_HEIGHT, DISPLAY_WIDTH};
+
+/// Same 9x15 fixed font as the boot console
+const FONT: FontRenderer = FontRenderer::new::<fonts::u8g2_font_9x15_t_symbols>();
+const FONT_WIDTH: u32 = 9;
+const LINE_HEIGHT: u32 = 17;
+
+const MARGIN: u32 = 16;
+const HEADER_HEIGHT: u32 = 40;
+
+/// Characters that fit on one line between the margins
+const MAX_COLUMNS: usize = ((DISPLAY_WIDTH - 2 * MARGIN) / FONT_WIDTH) as usize;
+
+/// Colors
+const COLOR_BACKGROUND: Rgb888 = Rgb888::new(96, 0, 0);     // Dark red
+const COLOR_HEADER: Rgb888 = Rgb888::new(200, 0, 0);        // Bright red
+const COLOR_TITLE: Rgb888 = Rgb888::new(255, 255, 255);     // White
+const COLOR_SECTION: Rgb888 = Rgb888::new(255, 220, 80);    // Yellow
+const COLOR_TEXT: Rgb888 = Rgb888::new(255, 200, 200);      // Pale red
+
+/// Render a panic report: the first line becomes the header, lines ending
+/// in ':' are section headings, everything else is body text.
+pub fn render(report: &str) {
+    if !d1_display::is_available() {
+        return;
+    }
+
+    d1_display::with_gpu(|gpu| {
+        gpu.clear(COLOR_BACKGROUND.r(), COLOR_BACKGROUND.g(), COLOR_BACKGROUND.b());
+        gpu.fill_rect(0, 0, DISPLAY_WIDTH, HEADER_HEIGHT, COLOR_HEADER.r(), COLOR_HEADER.g(), COLOR_HEADER.b());
+
+        let mut lines = report.lines();
+        if let Some(title) = lines.next() {
+            draw_text(gpu, title, MARGIN, HEADER_HEIGHT / 2 + 6, COLOR_TITLE);
+        }
+
+        let mut y = HEADER_HEIGHT + MARGIN + LINE_HEIGHT;
+        for line in lines {
+            if y + MARGIN > DISPLAY_HEIGHT {
+                break;
+            }
+            let color = if line.ends_with(':') && !line.starts_with(' ') {
+                COLOR_SECTION
+            } else {
+                COLOR_TEXT
+            };
+            draw_text(gpu, line, MARGIN, y, color);
+            y += LINE_HEIGHT;
+        }
+    });
+
+    // Force the frame out, then keep it there
+    d1_display::mark_all_dirty();
+    d1_display::flush();
+    d1_display::freeze();
+}
+
+/// Draw one line at baseline `y`, clipped to the screen width
+fn draw_text(gpu: &mut d1_display::GpuDriver, text: &str, x: u32, y: u32, color: Rgb888) {
+    let end = text
+        .char_indices()
+        .nth(MAX_COLUMNS)
+        .map(|(i, _)| i)
+        .unwrap_or(text.len());
+
+    let _ = FONT.render_aligned(
+        &text[..end],
+        Point::new(x as i32, y as i32),
+        VerticalPosition::Baseline,
+        HorizontalAlignment::Left,
+        FontColor::Transparent(color),
+        gpu,
+    );
+}
@@ -0,0 +1,441 @@
+//! QR Code Encoder (ISO/IEC 18004)
+//!
+//! Byte mode at error correction level M, versions 1 to 10 (21x21 to 57x57
+//! modules, up to 213 bytes): enough for the URLs and addresses shown on
+//! screen for a phone to scan. As the standard asks, all eight masks are
+//! tried and the one with the lowest penalty score is kept.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Largest supported version
+const MAX_VERSION: usize = 10;
+
+/// Error correction codewords per block at level M, by version
+const ECC_PER_BLOCK: [usize; MAX_VERSION + 1] = [0, 10, 16, 26, 18, 24, 16, 18, 22, 22, 26];
+
+/// Error correction blocks at level M, by version
+const NUM_BLOCKS: [usize; MAX_VERSION + 1] = [0, 1, 1, 1, 2, 2, 4, 4, 4, 5, 5];
+
+/// Level M in the format information (L = 01, M = 00, Q = 11, H = 10)
+const FORMAT_LEVEL_M: u32 = 0b00;
+
+/// An encoded QR code
+pub struct QrCode {
+    size: usize,
+    modules: Vec<bool>,
+    /// Finder, timing, alignment and format modules: never masked
+    function: Vec<bool>,
+}
+
+impl QrCode {
+    /// Encode `data` in the smallest version that holds it
+    pub fn encode(data: &[u8]) -> Result<QrCode, &'static str> {
+        let version = (1..=MAX_VERSION)
+            .find(|&v| 4 + count_bits(v) + data.len() * 8 <= data_codewords(v) * 8)
+            .ok_or("Text too long for a QR code")?;
+        let codewords = add_ecc(&encode_data(data, version), version);
+
+        let size = version * 4 + 17;
+        let mut qr = QrCode {
+            size,
+            modules: vec![false; size * size],
+            function: vec![false; size * size],
+        };
+        qr.draw_function_patterns(version);
+        qr.draw_codewords(&codewords);
+
+        // Masks are XOR: applying one twice undoes it
+        let mut best = (u32::MAX, 0);
+        for mask in 0..8 {
+            qr.apply_mask(mask);
+            qr.draw_format_bits(mask);
+            let penalty = qr.penalty();
+            if penalty < best.0 {
+                best = (penalty, mask);
+            }
+            qr.apply_mask(mask);
+        }
+        qr.apply_mask(best.1);
+        qr.draw_format_bits(best.1);
+        Ok(qr)
+    }
+
+    /// Modules per side (without the quiet zone)
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Whether the module at (x, y) is dark; outside the code is light
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        x < self.size && y < self.size && self.modules[y * self.size + x]
+    }
+
+    fn set_function(&mut self, x: usize, y: usize, dark: bool) {
+        self.modules[y * self.size + x] = dark;
+        self.function[y * self.size + x] = true;
+    }
+
+    // ═══════════════════════════════════════════════════════════════════════════════
+    // Function Patterns
+    // ═══════════════════════════════════════════════════════════════════════════════
+
+    fn draw_function_patterns(&mut self, version: usize) {
+        let size = self.size;
+
+        // Timing patterns (the finders below overwrite their ends)
+        for i in 0..size {
+            self.set_function(6, i, i % 2 == 0);
+            self.set_function(i, 6, i % 2 == 0);
+        }
+
+        self.draw_finder(3, 3);
+        self.draw_finder(size - 4, 3);
+        self.draw_finder(3, size - 4);
+
+        // Alignment patterns everywhere except on top of the finders
+        let positions = alignment_positions(version, size);
+        let last = positions.len().saturating_sub(1);
+        for (i, &y) in positions.iter().enumerate() {
+            for (j, &x) in positions.iter().enumerate() {
+                let on_finder = (i == 0 && j == 0) || (i == 0 && j == last) || (i == last && j == 0);
+                if !on_finder {
+                    self.draw_alignment(x, y);
+                }
+            }
+        }
+
+        // Reserve the format areas now; the real bits depend on the mask
+        self.draw_format_bits(0);
+        self.draw_version(version);
+    }
+
+    /// 7x7 finder with its light separator, centred on (x, y)
+    fn draw_finder(&mut self, x: usize, y: usize) {
+        for dy in -4..=4isize {
+            for dx in -4..=4isize {
+                let (xx, yy) = (x as isize + dx, y as isize + dy);
+                if xx < 0 || yy < 0 || xx >= self.size as isize || yy >= self.size as isize {
+                    continue;
+                }
+                let dist = dx.abs().max(dy.abs());
+                self.set_function(xx as usize, yy as usize, dist != 2 && dist != 4);
+            }
+        }
+    }
+
+    /// 5x5 alignment pattern centred on (x, y)
+    fn draw_alignment(&mut self, x: usize, y: usize) {
+        for dy in -2..=2isize {
+            for dx in -2..=2isize {
+                let dark = dx.abs().max(dy.abs()) != 1;
+                self.set_function((x as isize + dx) as usize, (y as isize + dy) as usize, dark);
+            }
+        }
+    }
+
+    /// Both copies of the 15-bit format information, plus the dark module
+    fn draw_format_bits(&mut self, mask: u32) {
+        let data = FORMAT_LEVEL_M << 3 | mask;
+        let mut rem = data;
+        for _ in 0..10 {
+            rem = (rem << 1) ^ ((rem >> 9) * 0x537);
+        }
+        let bits = (data << 10 | rem) ^ 0x5412;
+        let bit = |i: usize| (bits >> i) & 1 != 0;
+        let size = self.size;
+
+        // Around the top left finder
+        for i in 0..6 {
+            self.set_function(8, i, bit(i));
+        }
+        self.set_function(8, 7, bit(6));
+        self.set_function(8, 8, bit(7));
+        self.set_function(7, 8, bit(8));
+        for i in 9..15 {
+            self.set_function(14 - i, 8, bit(i));
+        }
+
+        // Split between the other two finders
+        for i in 0..8 {
+            self.set_function(size - 1 - i, 8, bit(i));
+        }
+        for i in 8..15 {
+            self.set_function(8, size - 15 + i, bit(i));
+        }
+        self.set_function(8, size - 8, true);
+    }
+
+    /// Version information blocks (version 7 and up)
+    fn draw_version(&mut self, version: usize) {
+        if version < 7 {
+            return;
+        }
+        let mut rem = version as u32;
+        for _ in 0..12 {
+            rem = (rem << 1) ^ ((rem >> 11) * 0x1F25);
+        }
+        let bits = (version as u32) << 12 | rem;
+        for i in 0..18 {
+            let dark = (bits >> i) & 1 != 0;
+            let (a, b) = (self.size - 11 + i % 3, i / 3);
+            self.set_function(a, b, dark);
+            self.set_function(b, a, dark);
+        }
+    }
+
+    // ═══════════════════════════════════════════════════════════════════════════════
+    // Data and Masking
+    // ═══════════════════════════════════════════════════════════════════════════════
+
+    /// Place the codewords in the zigzag order, two columns at a time from
+    /// the bottom right, skipping the vertical timing pattern
+    fn draw_codewords(&mut self, codewords: &[u8]) {
+        let size = self.size;
+        let mut i = 0;
+        let mut right = size - 1;
+        while right >= 1 {
+            if right == 6 {
+                right = 5;
+            }
+            let upward = (right + 1) & 2 == 0;
+            for vert in 0..size {
+                let y = if upward { size - 1 - vert } else { vert };
+                for x in [right, right - 1] {
+                    if !self.function[y * size + x] && i < codewords.len() * 8 {
+                        self.modules[y * size + x] = (codewords[i >> 3] >> (7 - (i & 7))) & 1 != 0;
+                        i += 1;
+                    }
+                }
+            }
+            if right < 2 {
+                break;
+            }
+            right -= 2;
+        }
+    }
+
+    fn apply_mask(&mut self, mask: u32) {
+        for y in 0..self.size {
+            for x in 0..self.size {
+                let invert = match mask {
+                    0 => (x + y) % 2 == 0,
+                    1 => y % 2 == 0,
+                    2 => x % 3 == 0,
+                    3 => (x + y) % 3 == 0,
+                    4 => (x / 3 + y / 2) % 2 == 0,
+                    5 => x * y % 2 + x * y % 3 == 0,
+                    6 => (x * y % 2 + x * y % 3) % 2 == 0,
+                    _ => ((x + y) % 2 + x * y % 3) % 2 == 0,
+                };
+                let i = y * self.size + x;
+                if invert && !self.function[i] {
+                    self.modules[i] = !self.modules[i];
+                }
+            }
+        }
+    }
+
+    /// Penalty score of the current mask (lower scans better)
+    fn penalty(&self) -> u32 {
+        let n = self.size;
+        let mut score = 0;
+        const FINDER_LIKE: [bool; 7] = [true, false, true, true, true, false, true];
+
+        for line in 0..n {
+            for horizontal in [true, false] {
+                let at = |i: usize| if horizontal { self.get(i, line) } else { self.get(line, i) };
+
+                // Runs of five or more modules of the same colour
+                let mut run = 1;
+                for i in 1..n {
+                    if at(i) == at(i - 1) {
+                        run += 1;
+                        if run == 5 {
+                            score += 3;
+                        } else if run > 5 {
+                            score += 1;
+                        }
+                    } else {
+                        run = 1;
+                    }
+                }
+
+                // 1:1:3:1:1 patterns with four light modules on either side
+                for i in 0..n - 6 {
+                    if (0..7).all(|k| at(i + k) == FINDER_LIKE[k]) {
+                        let before = (i.saturating_sub(4)..i).all(|k| !at(k));
+                        let after = (i + 7..(i + 11).min(n)).all(|k| !at(k));
+                        if before || after {
+                            score += 40;
+                        }
+                    }
+                }
+            }
+        }
+
+        // 2x2 blocks of the same colour
+        for y in 0..n - 1 {
+            for x in 0..n - 1 {
+                let c = self.get(x, y);
+                if c == self.get(x + 1, y) && c == self.get(x, y + 1) && c == self.get(x + 1, y + 1) {
+                    score += 3;
+                }
+            }
+        }
+
+        // Dark modules far from half
+        let dark = self.modules.iter().filter(|&&m| m).count();
+        let total = n * n;
+        let k = ((dark * 20).abs_diff(total * 10) + total - 1) / total;
+        score + k.saturating_sub(1) as u32 * 10
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Codewords
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Bits of the character count field in byte mode
+fn count_bits(version: usize) -> usize {
+    if version < 10 { 8 } else { 16 }
+}
+
+/// Modules available for data and error correction
+fn raw_modules(version: usize) -> usize {
+    let mut result = (16 * version + 128) * version + 64;
+    if version >= 2 {
+        let align = version / 7 + 2;
+        result -= (25 * align - 10) * align - 55;
+        if version >= 7 {
+            result -= 36;
+        }
+    }
+    result
+}
+
+fn data_codewords(version: usize) -> usize {
+    raw_modules(version) / 8 - ECC_PER_BLOCK[version] * NUM_BLOCKS[version]
+}
+
+fn alignment_positions(version: usize, size: usize) -> Vec<usize> {
+    if version == 1 {
+        return Vec::new();
+    }
+    let count = version / 7 + 2;
+    let step = (version * 8 + count * 3 + 5) / (count * 4 - 4) * 2;
+    let mut result = vec![6];
+    let mut pos = size - 7;
+    for _ in 0..count - 1 {
+        result.insert(1, pos);
+        pos -= step;
+    }
+    result
+}
+
+/// Mode, length, data, terminator and padding, as data codewords
+fn encode_data(data: &[u8], version: usize) -> Vec<u8> {
+    let capacity = data_codewords(version) * 8;
+    let mut bits: Vec<bool> = Vec::with_capacity(capacity);
+    let mut push = |value: u32, len: usize| {
+        for i in (0..len).rev() {
+            bits.push((value >> i) & 1 != 0);
+        }
+    };
+
+    push(0b0100, 4);
+    push(data.len() as u32, count_bits(version));
+    for &byte in data {
+        push(byte as u32, 8);
+    }
+    let used = 4 + count_bits(version) + data.len() * 8;
+    push(0, (capacity - used).min(4));
+    let used = used + (capacity - used).min(4);
+    push(0, (8 - used % 8) % 8);
+
+    let mut bytes: Vec<u8> = bits
+        .chunks(8)
+        .map(|chunk| chunk.iter().fold(0, |acc, &b| acc << 1 | b as u8))
+        .collect();
+    for pad in [0xEC, 0x11].iter().cycle() {
+        if bytes.len() >= capacity / 8 {
+            break;
+        }
+        bytes.push(*pad);
+    }
+    bytes
+}
+
+/// Split into blocks, append each block's Reed-Solomon codewords and interleave
+fn add_ecc(data: &[u8], version: usize) -> Vec<u8> {
+    let blocks_count = NUM_BLOCKS[version];
+    let ecc_len = ECC_PER_BLOCK[version];
+    let raw = raw_modules(version) / 8;
+    let short_blocks = blocks_count - raw % blocks_count;
+    let short_len = raw / blocks_count;
+    let divisor = rs_divisor(ecc_len);
+
+    let mut blocks: Vec<Vec<u8>> = Vec::with_capacity(blocks_count);
+    let mut k = 0;
+    for i in 0..blocks_count {
+        let len = short_len - ecc_len + if i < short_blocks { 0 } else { 1 };
+        let mut block = data[k..k + len].to_vec();
+        k += len;
+        let ecc = rs_remainder(&block, &divisor);
+        // Placeholder so every block has the same length while interleaving
+        if i < short_blocks {
+            block.push(0);
+        }
+        block.extend_from_slice(&ecc);
+        blocks.push(block);
+    }
+
+    let mut result = Vec::with_capacity(raw);
+    for i in 0..blocks[0].len() {
+        for (j, block) in blocks.iter().enumerate() {
+            if i != short_len - ecc_len || j >= short_blocks {
+                result.push(block[i]);
+            }
+        }
+    }
+    result
+}
+
+/// Generator polynomial of the given degree (leading 1 omitted)
+fn rs_divisor(degree: usize) -> Vec<u8> {
+    let mut result = vec![0u8; degree];
+    result[degree - 1] = 1;
+    let mut root = 1u8;
+    for _ in 0..degree {
+        for j in 0..degree {
+            result[j] = gf_mul(result[j], root);
+            if j + 1 < degree {
+                result[j] ^= result[j + 1];
+            }
+        }
+        root = gf_mul(root, 0x02);
+    }
+    result
+}
+
+fn rs_remainder(data: &[u8], divisor: &[u8]) -> Vec<u8> {
+    let mut result = vec![0u8; divisor.len()];
+    for &byte in data {
+        let factor = byte ^ result.remove(0);
+        result.push(0);
+        for (r, &d) in result.iter_mut().zip(divisor) {
+            *r ^= gf_mul(d, factor);
+        }
+    }
+    result
+}
+
+/// Multiply in GF(2^8) modulo x^8 + x^4 + x^3 + x^2 + 1
+fn gf_mul(x: u8, y: u8) -> u8 {
+    let mut z: u16 = 0;
+    for i in (0..8).rev() {
+        z = (z << 1) ^ ((z >> 7) * 0x11D);
+        z ^= ((y as u16 >> i) & 1) * x as u16;
+    }
+    z as u8
+}
@@ -2,14 +2,14 @@
 
 use alloc::string::String;
 use embedded_graphics::{
-    mono_font::{ascii::FONT_7X14, MonoTextStyle},
+    mono_font::MonoTextStyle,
     pixelcolor::Rgb888,
     prelude::*,
     primitives::{PrimitiveStyle, Rectangle, RoundedRectangle},
     text::{Alignment, Text},
 };
 
-use crate::ui::colors;
+use crate::ui::{colors, fonts};
 
 /// A simple button widget
 #[derive(Clone)]
@@ -37,9 +37,9 @@ impl Button {
     /// Draw the button to a DrawTarget
     pub fn draw<D: DrawTarget<Color = Rgb888>>(&self, target: &mut D) -> Result<(), D::Error> {
         let bg_color = if self.selected {
-            colors::BUTTON_SELECTED
+            colors::palette().button_selected
         } else {
-            colors::BUTTON_BG
+            colors::palette().button_bg
         };
 
         // Draw rounded rectangle background
@@ -54,11 +54,11 @@ impl Button {
             .draw(target)?;
 
         // Draw border
-        rect.into_styled(PrimitiveStyle::with_stroke(colors::BORDER, 1))
+        rect.into_styled(PrimitiveStyle::with_stroke(colors::palette().border, 1))
             .draw(target)?;
 
         // Draw label centered
-        let text_style = MonoTextStyle::new(&FONT_7X14, colors::FOREGROUND);
+        let text_style = MonoTextStyle::new(fonts::body(), colors::palette().foreground);
         let center_x = self.x + (self.width as i32 / 2);
         let center_y = self.y + (self.height as i32 / 2) + 4; // +4 for larger font baseline
 
@@ -2,14 +2,14 @@
 
 use alloc::string::String;
 use embedded_graphics::{
-    mono_font::{ascii::FONT_6X10, MonoTextStyle},
+    mono_font::MonoTextStyle,
     pixelcolor::Rgb888,
     prelude::*,
     primitives::{Line, PrimitiveStyle, Rectangle},
     text::Text,
 };
 
-use crate::ui::colors;
+use crate::ui::{colors, fonts};
 
 /// A checkbox widget
 pub struct Checkbox {
@@ -37,7 +37,7 @@ impl Checkbox {
             Point::new(self.x, self.y),
             Size::new(box_size, box_size),
         )
-        .into_styled(PrimitiveStyle::with_fill(colors::BUTTON_BG))
+        .into_styled(PrimitiveStyle::with_fill(colors::palette().button_bg))
         .draw(target)?;
 
         // Checkbox border
@@ -45,12 +45,12 @@ impl Checkbox {
             Point::new(self.x, self.y),
             Size::new(box_size, box_size),
         )
-        .into_styled(PrimitiveStyle::with_stroke(colors::ACCENT, 1))
+        .into_styled(PrimitiveStyle::with_stroke(colors::palette().accent, 1))
         .draw(target)?;
 
         // Checkmark if checked
         if self.checked {
-            let check_color = colors::SUCCESS;
+            let check_color = colors::palette().success;
             Line::new(
                 Point::new(self.x + 3, self.y + 7),
                 Point::new(self.x + 6, self.y + 11),
@@ -66,7 +66,7 @@ impl Checkbox {
         }
 
         // Label
-        let text_style = MonoTextStyle::new(&FONT_6X10, colors::FOREGROUND);
+        let text_style = MonoTextStyle::new(fonts::small(), colors::palette().foreground);
         Text::new(
             &self.label,
             Point::new(self.x + box_size as i32 + 6, self.y + 10),
@@ -2,13 +2,13 @@
 
 use alloc::string::String;
 use embedded_graphics::{
-    mono_font::{ascii::FONT_7X14, MonoTextStyle},
+    mono_font::MonoTextStyle,
     pixelcolor::Rgb888,
     prelude::*,
     text::Text,
 };
 
-use crate::ui::colors;
+use crate::ui::{colors, fonts};
 
 /// A text label widget
 pub struct Label {
@@ -24,7 +24,7 @@ impl Label {
             text: String::from(text),
             x,
             y,
-            color: colors::FOREGROUND,
+            color: colors::palette().foreground,
         }
     }
 
@@ -34,7 +34,7 @@ impl Label {
     }
 
     pub fn draw<D: DrawTarget<Color = Rgb888>>(&self, target: &mut D) -> Result<(), D::Error> {
-        let text_style = MonoTextStyle::new(&FONT_7X14, self.color);
+        let text_style = MonoTextStyle::new(fonts::body(), self.color);
         Text::new(&self.text, Point::new(self.x, self.y), text_style).draw(target)?;
         Ok(())
     }
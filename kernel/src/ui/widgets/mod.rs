@@ -7,9 +7,11 @@ mod checkbox;
 mod label;
 mod panel;
 mod progress_bar;
+mod qr_view;
 mod radio_button;
 mod window;
 
 pub use button::Button;
 pub use label::Label;
+pub use qr_view::QrView;
 pub use window::Window;
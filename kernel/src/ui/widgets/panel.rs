@@ -2,14 +2,14 @@
 
 use alloc::string::String;
 use embedded_graphics::{
-    mono_font::{ascii::FONT_6X10, MonoTextStyle},
+    mono_font::MonoTextStyle,
     pixelcolor::Rgb888,
     prelude::*,
     primitives::{Line, PrimitiveStyle, Rectangle},
     text::Text,
 };
 
-use crate::ui::colors;
+use crate::ui::{colors, fonts};
 
 /// A simple box/panel widget
 pub struct Panel {
@@ -42,7 +42,7 @@ impl Panel {
             Point::new(self.x, self.y),
             Size::new(self.width, self.height),
         )
-        .into_styled(PrimitiveStyle::with_fill(colors::BACKGROUND))
+        .into_styled(PrimitiveStyle::with_fill(colors::palette().background))
         .draw(target)?;
 
         // Border
@@ -50,12 +50,12 @@ impl Panel {
             Point::new(self.x, self.y),
             Size::new(self.width, self.height),
         )
-        .into_styled(PrimitiveStyle::with_stroke(colors::BORDER, 1))
+        .into_styled(PrimitiveStyle::with_stroke(colors::palette().border, 1))
         .draw(target)?;
 
         // Title if present
         if let Some(ref title) = self.title {
-            let text_style = MonoTextStyle::new(&FONT_6X10, colors::ACCENT);
+            let text_style = MonoTextStyle::new(fonts::small(), colors::palette().accent);
             Text::new(title, Point::new(self.x + 8, self.y + 14), text_style).draw(target)?;
 
             // Title underline
@@ -63,7 +63,7 @@ impl Panel {
                 Point::new(self.x + 4, self.y + 18),
                 Point::new(self.x + self.width as i32 - 4, self.y + 18),
             )
-            .into_styled(PrimitiveStyle::with_stroke(colors::BORDER, 1))
+            .into_styled(PrimitiveStyle::with_stroke(colors::palette().border, 1))
             .draw(target)?;
         }
 
@@ -26,7 +26,7 @@ impl ProgressBar {
             width,
             height,
             progress: 0.0,
-            color: colors::ACCENT,
+            color: colors::palette().accent,
         }
     }
 
@@ -40,7 +40,7 @@ impl ProgressBar {
             Point::new(self.x, self.y),
             Size::new(self.width, self.height),
         )
-        .into_styled(PrimitiveStyle::with_fill(colors::BUTTON_BG))
+        .into_styled(PrimitiveStyle::with_fill(colors::palette().button_bg))
         .draw(target)?;
 
         // Fill
@@ -57,7 +57,7 @@ impl ProgressBar {
             Point::new(self.x, self.y),
             Size::new(self.width, self.height),
         )
-        .into_styled(PrimitiveStyle::with_stroke(colors::BORDER, 1))
+        .into_styled(PrimitiveStyle::with_stroke(colors::palette().border, 1))
         .draw(target)?;
 
         Ok(())
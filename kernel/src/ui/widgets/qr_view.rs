@@ -0,0 +1,59 @@
+//! QrView Widget
+
+use embedded_graphics::{
+    pixelcolor::Rgb888,
+    prelude::*,
+    primitives::{PrimitiveStyle, Rectangle},
+};
+
+use crate::ui::qr::QrCode;
+
+/// Light border scanners need around the code, in modules
+const QUIET_ZONE: usize = 4;
+
+/// A QR code drawn dark-on-white with its quiet zone
+pub struct QrView<'a> {
+    pub x: i32,
+    pub y: i32,
+    /// Pixels per module
+    pub scale: u32,
+    code: &'a QrCode,
+}
+
+impl<'a> QrView<'a> {
+    pub fn new(code: &'a QrCode, x: i32, y: i32, scale: u32) -> Self {
+        Self { x, y, scale, code }
+    }
+
+    /// Largest scale at which the code (with quiet zone) fits in `pixels`
+    pub fn fit_scale(code: &QrCode, pixels: u32) -> u32 {
+        (pixels / (code.size() + 2 * QUIET_ZONE) as u32).max(1)
+    }
+
+    /// Width and height in pixels
+    pub fn pixel_size(&self) -> u32 {
+        (self.code.size() + 2 * QUIET_ZONE) as u32 * self.scale
+    }
+
+    pub fn draw<D: DrawTarget<Color = Rgb888>>(&self, target: &mut D) -> Result<(), D::Error> {
+        let side = self.pixel_size();
+        Rectangle::new(Point::new(self.x, self.y), Size::new(side, side))
+            .into_styled(PrimitiveStyle::with_fill(Rgb888::WHITE))
+            .draw(target)?;
+
+        let dark = PrimitiveStyle::with_fill(Rgb888::BLACK);
+        let origin = QUIET_ZONE as i32 * self.scale as i32;
+        for y in 0..self.code.size() {
+            for x in 0..self.code.size() {
+                if self.code.get(x, y) {
+                    let px = self.x + origin + (x as u32 * self.scale) as i32;
+                    let py = self.y + origin + (y as u32 * self.scale) as i32;
+                    Rectangle::new(Point::new(px, py), Size::new(self.scale, self.scale))
+                        .into_styled(dark)
+                        .draw(target)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
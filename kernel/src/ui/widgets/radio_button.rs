@@ -2,14 +2,14 @@
 
 use alloc::string::String;
 use embedded_graphics::{
-    mono_font::{ascii::FONT_6X10, MonoTextStyle},
+    mono_font::MonoTextStyle,
     pixelcolor::Rgb888,
     prelude::*,
     primitives::{Circle, PrimitiveStyle},
     text::Text,
 };
 
-use crate::ui::colors;
+use crate::ui::{colors, fonts};
 
 /// A radio button widget
 pub struct RadioButton {
@@ -34,18 +34,18 @@ impl RadioButton {
         
         // Outer circle (border)
         Circle::new(Point::new(self.x, self.y), radius * 2)
-            .into_styled(PrimitiveStyle::with_stroke(colors::ACCENT, 2))
+            .into_styled(PrimitiveStyle::with_stroke(colors::palette().accent, 2))
             .draw(target)?;
 
         // Inner circle if selected
         if self.selected {
             Circle::new(Point::new(self.x + 4, self.y + 4), (radius - 4) * 2)
-                .into_styled(PrimitiveStyle::with_fill(colors::ACCENT))
+                .into_styled(PrimitiveStyle::with_fill(colors::palette().accent))
                 .draw(target)?;
         }
 
         // Label
-        let text_style = MonoTextStyle::new(&FONT_6X10, colors::FOREGROUND);
+        let text_style = MonoTextStyle::new(fonts::small(), colors::palette().foreground);
         Text::new(
             &self.label,
             Point::new(self.x + (radius * 2) as i32 + 6, self.y + 10),
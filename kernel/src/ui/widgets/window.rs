@@ -2,14 +2,14 @@
 
 use alloc::string::String;
 use embedded_graphics::{
-    mono_font::{ascii::FONT_9X15_BOLD, MonoTextStyle},
+    mono_font::MonoTextStyle,
     pixelcolor::Rgb888,
     prelude::*,
     primitives::{Circle, CornerRadii, Line, PrimitiveStyle, Rectangle, RoundedRectangle},
     text::Text,
 };
 
-use crate::ui::colors;
+use crate::ui::{colors, fonts};
 use crate::ui::{draw_image, LOGO_SMALL, LOGO_SMALL_SIZE};
 
 /// A window widget representing an application window
@@ -46,7 +46,7 @@ impl Window {
     /// Draw the window to a DrawTarget
     pub fn draw<D: DrawTarget<Color = Rgb888>>(&self, target: &mut D) -> Result<(), D::Error> {
         let title_bar_height = 28u32;
-        let border_color = if self.focused { colors::ACCENT } else { colors::BORDER };
+        let border_color = if self.focused { colors::palette().accent } else { colors::palette().border };
         
         // Window shadow (offset dark rectangle)
         Rectangle::new(
@@ -108,21 +108,21 @@ impl Window {
         
         // Close button (red)
         Circle::new(Point::new(self.x + 12, button_y), button_radius * 2)
-            .into_styled(PrimitiveStyle::with_fill(colors::ERROR))
+            .into_styled(PrimitiveStyle::with_fill(colors::palette().error))
             .draw(target)?;
         
         // Minimize button (yellow)
         Circle::new(Point::new(self.x + 32, button_y), button_radius * 2)
-            .into_styled(PrimitiveStyle::with_fill(colors::WARNING))
+            .into_styled(PrimitiveStyle::with_fill(colors::palette().warning))
             .draw(target)?;
         
         // Maximize button (green)
         Circle::new(Point::new(self.x + 52, button_y), button_radius * 2)
-            .into_styled(PrimitiveStyle::with_fill(colors::SUCCESS))
+            .into_styled(PrimitiveStyle::with_fill(colors::palette().success))
             .draw(target)?;
 
         // Window title
-        let title_style = MonoTextStyle::new(&FONT_9X15_BOLD, colors::FOREGROUND);
+        let title_style = MonoTextStyle::new(fonts::title(), colors::palette().foreground);
         let title_x = self.x + 80;
         let title_y = self.y + 18;
         Text::new(&self.title, Point::new(title_x, title_y), title_style).draw(target)?;
@@ -195,8 +195,10 @@ impl Window {
         }
         
         // Title text (centered)
-        let title_style = MonoTextStyle::new(&FONT_9X15_BOLD, Rgb888::WHITE);
-        let title_x = self.x + (self.width as i32 / 2) - ((self.title.len() as i32 * 9) / 2);
+        let title_font = fonts::title();
+        let title_style = MonoTextStyle::new(title_font, Rgb888::WHITE);
+        let title_w = self.title.len() as i32 * title_font.character_size.width as i32;
+        let title_x = self.x + (self.width as i32 / 2) - (title_w / 2);
         let _ = Text::new(&self.title, Point::new(title_x, self.y + 22), title_style).draw(gpu);
         
         // Draw small logo aligned to the right of the header
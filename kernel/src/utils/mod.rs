@@ -2,7 +2,7 @@ use core::sync::atomic::Ordering;
 
 use alloc::{format, string::String};
 
-use crate::{ allocator, clint::get_time_ms, constants::{SYSINFO_CPU_COUNT, SYSINFO_DISK_TOTAL, SYSINFO_DISK_USED, SYSINFO_HEAP_TOTAL, SYSINFO_HEAP_USED, SYSINFO_UPTIME}, cpu::HARTS_ONLINE, lock::utils::{BLK_DEV, CWD_MAX_LEN, CWD_STATE, FS_STATE, TAIL_FOLLOW_STATE}, uart};
+use crate::{ allocator, clint::get_time_ms, constants::{SYSINFO_CPU_COUNT, SYSINFO_DISK_TOTAL, SYSINFO_DISK_USED, SYSINFO_HEAP_TOTAL, SYSINFO_HEAP_USED, SYSINFO_UPTIME}, cpu::HARTS_ONLINE, lock::utils::{BLK_DEV, CWD_MAX_LEN, CWD_STATE, FS_STATE}, uart};
 
 
 /// Initialize CWD to root
@@ -34,12 +34,23 @@ pub fn get_cwd() -> alloc::string::String {
     cwd_get()
 }
 
-/// Resolve a path relative to CWD
+/// Maximum symlinks followed while resolving a single path (loop guard)
+const MAX_SYMLINK_HOPS: usize = 8;
+
+/// Resolve a path relative to CWD, following symbolic links
 pub(crate) fn resolve_path(path: &str) -> alloc::string::String {
-    use alloc::string::String;
-    use alloc::vec::Vec;
+    follow_symlinks(absolute_path(path), true)
+}
 
-    let mut result = String::new();
+/// Resolve a path relative to CWD without following a symlink in the last
+/// component (for operations on the link itself: `ln -s`, `readlink`, `rm`)
+pub(crate) fn resolve_path_nofollow(path: &str) -> alloc::string::String {
+    follow_symlinks(absolute_path(path), false)
+}
+
+/// Make a path absolute against CWD and normalize it (no symlink lookups)
+fn absolute_path(path: &str) -> alloc::string::String {
+    use alloc::string::String;
 
     // Start from root or CWD
     let cwd = cwd_get();
@@ -59,6 +70,16 @@ pub(crate) fn resolve_path(path: &str) -> alloc::string::String {
         s
     };
 
+    normalize_path(&full)
+}
+
+/// Collapse `.`, `..` and repeated slashes in an absolute path
+fn normalize_path(full: &str) -> alloc::string::String {
+    use alloc::string::String;
+    use alloc::vec::Vec;
+
+    let mut result = String::new();
+
     // Split and normalize (handle . and ..)
     let mut parts: Vec<&str> = Vec::new();
     for part in full.split('/') {
@@ -80,11 +101,38 @@ pub(crate) fn resolve_path(path: &str) -> alloc::string::String {
         result.push_str(part);
     }
 
-    if result.is_empty() {
-        result.push('/');
+    result
+}
+
+/// Replace symlinked components of a normalized absolute path with their
+/// targets. Gives up (returning the partly resolved path) after
+/// MAX_SYMLINK_HOPS links, so a link loop resolves to a missing file.
+fn follow_symlinks(mut path: alloc::string::String, follow_last: bool) -> alloc::string::String {
+    for _ in 0..MAX_SYMLINK_HOPS {
+        match expand_first_symlink(&path, follow_last) {
+            Some(next) => path = next,
+            None => break,
+        }
     }
+    path
+}
 
-    result
+/// Expand the first symlink found in `path`, or None if there is none.
+/// The filesystem finds it in one directory pass (see `fs_first_symlink`)
+/// rather than a readlink per component.
+fn expand_first_symlink(path: &str, follow_last: bool) -> Option<alloc::string::String> {
+    let (link, target) = crate::cpu::fs_proxy::fs_first_symlink(path, follow_last)?;
+    let rest = path.strip_prefix(link.as_str())?;
+
+    // Relative targets are relative to the directory holding the link
+    let mut next = if target.starts_with('/') {
+        target
+    } else {
+        let parent = link.rsplit_once('/').map_or("", |(parent, _)| parent);
+        format!("{}/{}", parent, target)
+    };
+    next.push_str(rest);
+    Some(normalize_path(&next))
 }
 
 /// Check if a path exists (has files under it or is a file)
@@ -95,16 +143,16 @@ pub(crate) fn path_exists(path: &str) -> bool {
     }
     
     // Use VFS for mount point visibility
-    let mut vfs_guard = crate::lock::utils::VFS_STATE.write();
-    if let Some(vfs) = vfs_guard.as_mut() {
+    let vfs_guard = crate::lock::utils::VFS_STATE.read();
+    if let Some(vfs) = vfs_guard.as_ref() {
         return vfs.exists(path);
     }
     drop(vfs_guard);
     
     // Fall back to legacy FS_STATE
-    let mut fs_guard = FS_STATE.write();
-    let mut blk_guard = BLK_DEV.write();
-    if let (Some(fs), Some(dev)) = (fs_guard.as_mut(), blk_guard.as_mut()) {
+    let fs_guard = FS_STATE.read();
+    let blk_guard = BLK_DEV.read();
+    if let (Some(fs), Some(dev)) = (fs_guard.as_ref(), blk_guard.as_ref()) {
         let files = fs.list_dir(dev, "/");
         let path_with_slash = if path.ends_with('/') {
             alloc::string::String::from(path)
@@ -138,7 +186,8 @@ pub(crate) fn print_prompt() {
     };
 
     uart::write_str(&format!(
-        "\x1b[1;35mBavy\x1b[0m\x1b[1;34m{}\x1b[0m # ",
+        "\x1b[1;35m{}\x1b[0m\x1b[1;34m{}\x1b[0m # ",
+        crate::settings::hostname(),
         prompt_path
     ));
 }
@@ -176,73 +225,3 @@ pub(crate) fn update_sysinfo() {
         core::ptr::write_volatile(SYSINFO_UPTIME as *mut u64, uptime_ms);
     }
 }
-
-/// Check for new content in a file being followed by tail -f
-/// Returns the new file size if content was found, None otherwise
-/// 
-/// Multi-hart safe: Uses fs_proxy for hart-aware filesystem access.
-pub(crate) fn check_tail_follow(path: &str, last_size: usize) -> Option<usize> {
-    // Use fs_proxy for multi-hart safety
-    if let Some(content) = crate::cpu::fs_proxy::fs_read(path) {
-        let new_size = content.len();
-
-        if new_size > last_size {
-            // Print new content with green highlighting
-            let new_content = &content[last_size..];
-            if let Ok(text) = core::str::from_utf8(new_content) {
-                for line in text.lines() {
-                    uart::write_str("\x1b[1;32m");
-                    uart::write_str(line);
-                    uart::write_line("\x1b[0m");
-                }
-            }
-            return Some(new_size);
-        } else if new_size < last_size {
-            // File was truncated
-            uart::write_line("\x1b[1;33mtail: file truncated\x1b[0m");
-            return Some(new_size);
-        }
-
-        return Some(last_size); // No change
-    }
-    None
-}
-
-
-
-/// Poll tail follow for new content (called from uart during blocking reads)
-/// Returns true if content was found and printed
-pub(crate) fn poll_tail_follow() -> bool {
-    let mut state = TAIL_FOLLOW_STATE.lock();
-    
-    if !state.active {
-        return false;
-    }
-    
-    // Only check every 500ms to avoid excessive filesystem access
-    let now = get_time_ms();
-    if now - state.last_check_ms < 500 {
-        return false;
-    }
-    state.last_check_ms = now;
-    
-    // Get a copy of path before releasing lock
-    let path_copy = if let Some(p) = state.get_path() {
-        alloc::string::String::from(p)
-    } else {
-        return false;
-    };
-    let last_size = state.last_size;
-    
-    // Release lock before filesystem access
-    drop(state);
-    
-    // Check for new content
-    if let Some(new_size) = check_tail_follow(&path_copy, last_size) {
-        let mut state = TAIL_FOLLOW_STATE.lock();
-        state.last_size = new_size;
-        return new_size > last_size;
-    }
-    
-    false
-}
@@ -7,6 +7,8 @@ use core::ptr::addr_of_mut;
 use core::sync::atomic::{AtomicBool, Ordering};
 use alloc::collections::VecDeque;
 
+use crate::device::dma::{DmaBuffer, DmaSafe};
+
 /// VirtIO Input Device ID
 const VIRTIO_INPUT_DEVICE_ID: u32 = 18;
 
@@ -94,6 +96,8 @@ pub struct InputEvent {
     pub value: i32,
 }
 
+unsafe impl DmaSafe for InputEvent {}
+
 impl InputEvent {
     /// Check if this is a key press event
     pub fn is_key_press(&self) -> bool {
@@ -124,13 +128,7 @@ struct InputQueueMem {
     data: [u8; QUEUE_MEM_SIZE],
 }
 
-impl InputQueueMem {
-    fn new() -> alloc::boxed::Box<Self> {
-        alloc::boxed::Box::new(Self {
-            data: [0; QUEUE_MEM_SIZE],
-        })
-    }
-}
+unsafe impl DmaSafe for InputQueueMem {}
 
 /// VirtIO descriptor structure
 #[repr(C)]
@@ -145,10 +143,10 @@ struct VirtqDesc {
 /// Input driver with proper virtqueue support
 pub struct InputDriver {
     base: usize,
-    /// Heap-allocated queue memory
-    queue_mem: alloc::boxed::Box<InputQueueMem>,
-    /// Event buffers (heap-allocated to ensure DRAM address range)
-    event_buffers: alloc::boxed::Box<[InputEvent; QUEUE_SIZE as usize]>,
+    /// Queue memory (DMA pool)
+    queue_mem: DmaBuffer<InputQueueMem>,
+    /// Event buffers the device fills (DMA pool)
+    event_buffers: DmaBuffer<[InputEvent; QUEUE_SIZE as usize]>,
     /// Parsed events ready for consumption
     event_queue: VecDeque<InputEvent>,
     /// Last processed used ring index
@@ -197,10 +195,8 @@ impl InputDriver {
     
     /// Create driver instance for device at base address
     fn create_driver(base: usize) -> Option<Self> {
-        let queue_mem = InputQueueMem::new();
-        let event_buffers = alloc::boxed::Box::new(
-            [InputEvent { event_type: 0, code: 0, value: 0 }; QUEUE_SIZE as usize]
-        );
+        let queue_mem = DmaBuffer::zeroed().ok()?;
+        let event_buffers = DmaBuffer::zeroed().ok()?;
         Some(Self {
             base,
             queue_mem,
@@ -247,7 +243,7 @@ impl InputDriver {
             );
             
             // Set queue PFN (page frame number)
-            let pfn = (self.queue_mem.data.as_ptr() as u64) / PAGE_SIZE as u64;
+            let pfn = self.queue_mem.addr() / PAGE_SIZE as u64;
             core::ptr::write_volatile(
                 (self.base + QUEUE_PFN_OFFSET) as *mut u32,
                 pfn as u32
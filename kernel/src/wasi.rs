@@ -0,0 +1,1058 @@
+//! WASI Preview 1
+//!
+//! Programs built for `wasm32-wasi` (wasm32-wasip1) with an unmodified Rust
+//! or C toolchain import `wasi_snapshot_preview1` instead of the kernel's
+//! `env` ABI. This module provides those imports on top of the same
+//! services the `env` functions use:
+//!
+//! - fds 0, 1 and 2 are the program's console input and output, so pipes,
+//!   CGI bodies and the Terminal window capture work as they do for `print`
+//! - fd 3 is the root directory, preopened as "/": wasi-libc and Rust std
+//!   resolve every path, absolute or relative, against it
+//! - files are read whole when opened and written back through `fs_proxy`
+//!   when closed, synced or when the program exits
+//! - clocks are the RTC and the monotonic timer, `random_get` is the kernel
+//!   CSPRNG, arguments and environment are those `arg_get`/`env_get` see
+//!
+//! Sockets, hard links and timestamps are not supported (ENOSYS).
+//! `proc_exit` ends the program with its status; a status other than 0 is
+//! reported as an error.
+//!
+//! The host (`wasm::WasmContext`) implements `WasiHost` and calls
+//! `add_to_linker` before instantiating a module.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use wasmi::{Caller, Func, Linker, Memory, Store};
+
+use crate::cpu::fs_proxy;
+use crate::cpu::process::{charge_syscall, IoKind};
+use crate::lock::state::fs::MAX_FILE_SIZE;
+
+/// Import module name
+const MODULE: &str = "wasi_snapshot_preview1";
+
+/// argv[0] (the runtime is not told the command name)
+const PROGRAM_NAME: &str = "wasm";
+
+/// Open file descriptors per program
+const MAX_FDS: usize = 64;
+
+/// Largest read from the console in one call
+const INPUT_CHUNK: usize = 4096;
+
+/// Bytes `random_get` generates at a time
+const RANDOM_CHUNK: usize = 256;
+
+// Errno values
+type Errno = i32;
+const ERRNO_SUCCESS: Errno = 0;
+const ERRNO_BADF: Errno = 8;
+const ERRNO_EXIST: Errno = 20;
+const ERRNO_FAULT: Errno = 21;
+const ERRNO_FBIG: Errno = 22;
+const ERRNO_ILSEQ: Errno = 25;
+const ERRNO_INVAL: Errno = 28;
+const ERRNO_IO: Errno = 29;
+const ERRNO_ISDIR: Errno = 31;
+const ERRNO_MFILE: Errno = 33;
+const ERRNO_NOENT: Errno = 44;
+const ERRNO_NOSYS: Errno = 52;
+const ERRNO_NOTDIR: Errno = 54;
+const ERRNO_NOTEMPTY: Errno = 55;
+const ERRNO_SPIPE: Errno = 70;
+
+// File types
+const FILETYPE_CHARACTER_DEVICE: u8 = 2;
+const FILETYPE_DIRECTORY: u8 = 3;
+const FILETYPE_REGULAR_FILE: u8 = 4;
+const FILETYPE_SYMBOLIC_LINK: u8 = 7;
+
+// Clocks
+const CLOCK_REALTIME: i32 = 0;
+const CLOCK_MONOTONIC: i32 = 1;
+const CLOCK_PROCESS_CPUTIME: i32 = 2;
+const CLOCK_THREAD_CPUTIME: i32 = 3;
+
+// path_open flags
+const OFLAGS_CREAT: i32 = 1;
+const OFLAGS_DIRECTORY: i32 = 2;
+const OFLAGS_EXCL: i32 = 4;
+const OFLAGS_TRUNC: i32 = 8;
+const FDFLAGS_APPEND: i32 = 1;
+const LOOKUP_SYMLINK_FOLLOW: i32 = 1;
+const RIGHTS_FD_READ: i64 = 1 << 1;
+const RIGHTS_FD_WRITE: i64 = 1 << 6;
+/// Every right preview1 defines: handles are not restricted beyond how
+/// they were opened
+const RIGHTS_ALL: u64 = (1 << 30) - 1;
+
+// fd_seek whence
+const WHENCE_SET: i32 = 0;
+const WHENCE_CUR: i32 = 1;
+const WHENCE_END: i32 = 2;
+
+// poll_oneoff
+const EVENTTYPE_CLOCK: u8 = 0;
+const SUBCLOCKFLAGS_ABSTIME: u16 = 1;
+const SUBSCRIPTION_SIZE: usize = 48;
+const EVENT_SIZE: usize = 32;
+
+/// Services a WASI program reaches through its host context
+pub(crate) trait WasiHost {
+    fn wasi(&mut self) -> &mut WasiState;
+    /// Command-line arguments, without the program name
+    fn args(&self) -> &[String];
+    fn environ(&self) -> Vec<(String, String)>;
+    /// Console output: `stderr` always goes to the console itself
+    fn write_output(&mut self, data: &[u8], stderr: bool);
+    /// Console input, waiting for some; 0 at end of input
+    fn read_input(&mut self, buf: &mut [u8]) -> usize;
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// File descriptors
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// A file opened with `path_open`
+struct OpenFile {
+    path: String,
+    data: Vec<u8>,
+    pos: u64,
+    readable: bool,
+    writable: bool,
+    append: bool,
+    /// Written since it was last saved
+    dirty: bool,
+}
+
+impl OpenFile {
+    /// Write the contents back if they changed
+    fn flush(&mut self) -> Result<(), Errno> {
+        if self.dirty {
+            fs_proxy::fs_write(&self.path, &self.data).map_err(|_| ERRNO_IO)?;
+            charge_syscall(Some(IoKind::FsWrite), self.data.len() as u64);
+            self.dirty = false;
+        }
+        Ok(())
+    }
+
+    fn read_at(&self, pos: u64, buf: &mut [u8]) -> usize {
+        let start = (pos as usize).min(self.data.len());
+        let n = buf.len().min(self.data.len() - start);
+        buf[..n].copy_from_slice(&self.data[start..start + n]);
+        n
+    }
+
+    fn write_at(&mut self, pos: u64, data: &[u8]) -> Result<(), Errno> {
+        let end = pos
+            .checked_add(data.len() as u64)
+            .filter(|&end| end <= MAX_FILE_SIZE as u64)
+            .ok_or(ERRNO_FBIG)? as usize;
+        if self.data.len() < end {
+            self.data.resize(end, 0);
+        }
+        self.data[pos as usize..end].copy_from_slice(data);
+        self.dirty = true;
+        Ok(())
+    }
+}
+
+enum Handle {
+    Stdin,
+    Stdout,
+    Stderr,
+    Dir { path: String, preopen: bool },
+    File(OpenFile),
+}
+
+impl Handle {
+    fn filetype(&self) -> u8 {
+        match self {
+            Handle::Stdin | Handle::Stdout | Handle::Stderr => FILETYPE_CHARACTER_DEVICE,
+            Handle::Dir { .. } => FILETYPE_DIRECTORY,
+            Handle::File(_) => FILETYPE_REGULAR_FILE,
+        }
+    }
+}
+
+/// Per-program WASI state: the descriptor table
+pub(crate) struct WasiState {
+    fds: Vec<Option<Handle>>,
+}
+
+impl WasiState {
+    pub fn new() -> Self {
+        let mut fds = Vec::new();
+        fds.push(Some(Handle::Stdin));
+        fds.push(Some(Handle::Stdout));
+        fds.push(Some(Handle::Stderr));
+        fds.push(Some(Handle::Dir { path: String::from("/"), preopen: true }));
+        Self { fds }
+    }
+
+    fn get(&mut self, fd: i32) -> Result<&mut Handle, Errno> {
+        usize::try_from(fd)
+            .ok()
+            .and_then(|fd| self.fds.get_mut(fd))
+            .and_then(Option::as_mut)
+            .ok_or(ERRNO_BADF)
+    }
+
+    fn file(&mut self, fd: i32) -> Result<&mut OpenFile, Errno> {
+        match self.get(fd)? {
+            Handle::File(file) => Ok(file),
+            Handle::Dir { .. } => Err(ERRNO_ISDIR),
+            _ => Err(ERRNO_SPIPE),
+        }
+    }
+
+    fn dir(&mut self, fd: i32) -> Result<String, Errno> {
+        match self.get(fd)? {
+            Handle::Dir { path, .. } => Ok(path.clone()),
+            _ => Err(ERRNO_NOTDIR),
+        }
+    }
+
+    /// Lowest free descriptor for `handle`
+    fn insert(&mut self, handle: Handle) -> Result<i32, Errno> {
+        let fd = match self.fds.iter().position(Option::is_none) {
+            Some(fd) => fd,
+            None if self.fds.len() < MAX_FDS => {
+                self.fds.push(None);
+                self.fds.len() - 1
+            }
+            None => return Err(ERRNO_MFILE),
+        };
+        self.fds[fd] = Some(handle);
+        Ok(fd as i32)
+    }
+
+    fn close(&mut self, fd: i32) -> Result<(), Errno> {
+        self.get(fd)?;
+        match self.fds[fd as usize].take() {
+            Some(Handle::File(mut file)) => file.flush(),
+            _ => Ok(()),
+        }
+    }
+
+    /// Save every open file (the program has ended)
+    pub fn close_all(&mut self) {
+        for handle in self.fds.drain(..).flatten() {
+            if let Handle::File(mut file) = handle {
+                let _ = file.flush();
+            }
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Guest memory
+// ═══════════════════════════════════════════════════════════════════════════════
+
+fn memory<T>(caller: &Caller<'_, T>) -> Result<Memory, Errno> {
+    caller.get_export("memory").and_then(|e| e.into_memory()).ok_or(ERRNO_FAULT)
+}
+
+fn read_bytes<T>(caller: &Caller<'_, T>, ptr: i32, len: usize) -> Result<Vec<u8>, Errno> {
+    let start = ptr as u32 as usize;
+    let data = memory(caller)?.data(caller);
+    data.get(start..start.checked_add(len).ok_or(ERRNO_FAULT)?)
+        .map(<[u8]>::to_vec)
+        .ok_or(ERRNO_FAULT)
+}
+
+fn read_string<T>(caller: &Caller<'_, T>, ptr: i32, len: i32) -> Result<String, Errno> {
+    String::from_utf8(read_bytes(caller, ptr, len as u32 as usize)?).map_err(|_| ERRNO_ILSEQ)
+}
+
+fn write_bytes<T>(caller: &mut Caller<'_, T>, ptr: i32, data: &[u8]) -> Result<(), Errno> {
+    memory(caller)?
+        .write(&mut *caller, ptr as u32 as usize, data)
+        .map_err(|_| ERRNO_FAULT)
+}
+
+fn write_u32<T>(caller: &mut Caller<'_, T>, ptr: i32, value: u32) -> Result<(), Errno> {
+    write_bytes(caller, ptr, &value.to_le_bytes())
+}
+
+fn write_u64<T>(caller: &mut Caller<'_, T>, ptr: i32, value: u64) -> Result<(), Errno> {
+    write_bytes(caller, ptr, &value.to_le_bytes())
+}
+
+/// The (buf, len) pairs of an iovec array
+fn iovecs<T>(caller: &Caller<'_, T>, iovs: i32, count: i32) -> Result<Vec<(i32, usize)>, Errno> {
+    let raw = read_bytes(caller, iovs, (count as u32 as usize).checked_mul(8).ok_or(ERRNO_FAULT)?)?;
+    Ok(raw
+        .chunks_exact(8)
+        .map(|iov| {
+            let buf = u32::from_le_bytes([iov[0], iov[1], iov[2], iov[3]]);
+            let len = u32::from_le_bytes([iov[4], iov[5], iov[6], iov[7]]);
+            (buf as i32, len as usize)
+        })
+        .collect())
+}
+
+/// Everything an iovec array points to, concatenated (at most a file's worth)
+fn gather<T>(caller: &Caller<'_, T>, iovs: i32, count: i32) -> Result<Vec<u8>, Errno> {
+    let iovs = iovecs(caller, iovs, count)?;
+    let total = iovs
+        .iter()
+        .try_fold(0usize, |total, &(_, len)| total.checked_add(len))
+        .filter(|&total| total <= MAX_FILE_SIZE as usize)
+        .ok_or(ERRNO_FBIG)?;
+    let mut data = Vec::with_capacity(total);
+    for (buf, len) in iovs {
+        data.extend_from_slice(&read_bytes(caller, buf, len)?);
+    }
+    Ok(data)
+}
+
+/// Copy `data` into the buffers of an iovec array; returns bytes copied
+fn scatter<T>(caller: &mut Caller<'_, T>, iovs: &[(i32, usize)], mut data: &[u8]) -> Result<usize, Errno> {
+    let mut copied = 0;
+    for &(buf, len) in iovs {
+        if data.is_empty() {
+            break;
+        }
+        let n = len.min(data.len());
+        write_bytes(caller, buf, &data[..n])?;
+        data = &data[n..];
+        copied += n;
+    }
+    Ok(copied)
+}
+
+fn status(result: Result<(), Errno>) -> i32 {
+    match result {
+        Ok(()) => ERRNO_SUCCESS,
+        Err(errno) => errno,
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Paths
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Absolute path of `path` under the directory `dir`
+fn resolve(dir: &str, path: &str, follow: bool) -> String {
+    let joined = if path.starts_with('/') {
+        String::from(path)
+    } else {
+        format!("{}/{}", dir.trim_end_matches('/'), path)
+    };
+    if follow {
+        crate::utils::resolve_path(&joined)
+    } else {
+        crate::utils::resolve_path_nofollow(&joined)
+    }
+}
+
+/// Path of `path` relative to the directory open as `fd`
+fn path_at<T: WasiHost>(caller: &mut Caller<'_, T>, fd: i32, ptr: i32, len: i32, follow: bool) -> Result<String, Errno> {
+    let path = read_string(caller, ptr, len)?;
+    let dir = caller.data_mut().wasi().dir(fd)?;
+    Ok(resolve(&dir, &path, follow))
+}
+
+/// File type and size of an absolute path
+fn stat(path: &str, follow: bool) -> Result<(u8, u64), Errno> {
+    if !follow {
+        if let Some(target) = fs_proxy::fs_readlink(path) {
+            return Ok((FILETYPE_SYMBOLIC_LINK, target.len() as u64));
+        }
+    }
+    if fs_proxy::fs_is_dir(path) {
+        return Ok((FILETYPE_DIRECTORY, 0));
+    }
+    let (parent, name) = path.rsplit_once('/').ok_or(ERRNO_NOENT)?;
+    let parent = if parent.is_empty() { "/" } else { parent };
+    fs_proxy::fs_list(parent)
+        .into_iter()
+        .find(|entry| entry.name == name)
+        .map(|entry| (if entry.is_dir { FILETYPE_DIRECTORY } else { FILETYPE_REGULAR_FILE }, entry.size))
+        .ok_or(ERRNO_NOENT)
+}
+
+/// A filestat record (times are not tracked)
+fn filestat(filetype: u8, size: u64, ino: u64) -> [u8; 64] {
+    let mut record = [0u8; 64];
+    record[8..16].copy_from_slice(&ino.to_le_bytes());
+    record[16] = filetype;
+    record[24..32].copy_from_slice(&1u64.to_le_bytes());
+    record[32..40].copy_from_slice(&size.to_le_bytes());
+    record
+}
+
+/// Inode stand-in: a hash of the path, so distinct files compare unequal
+fn path_ino(path: &str) -> u64 {
+    path.bytes().fold(0xcbf2_9ce4_8422_2325, |h, b| (h ^ b as u64).wrapping_mul(0x100_0000_01b3))
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Arguments, environment, clocks, random
+// ═══════════════════════════════════════════════════════════════════════════════
+
+fn argv<T: WasiHost>(caller: &Caller<'_, T>) -> Vec<String> {
+    let mut argv = Vec::with_capacity(caller.data().args().len() + 1);
+    argv.push(String::from(PROGRAM_NAME));
+    argv.extend(caller.data().args().iter().cloned());
+    argv
+}
+
+fn environ<T: WasiHost>(caller: &Caller<'_, T>) -> Vec<String> {
+    caller
+        .data()
+        .environ()
+        .into_iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect()
+}
+
+/// Write NUL-terminated `strings` to `buf` and pointers to them to `list`
+fn write_strings<T>(caller: &mut Caller<'_, T>, strings: &[String], list: i32, buf: i32) -> Result<(), Errno> {
+    let mut offset = buf as u32;
+    for (i, string) in strings.iter().enumerate() {
+        write_u32(caller, list.wrapping_add(4 * i as i32), offset)?;
+        let mut bytes = Vec::with_capacity(string.len() + 1);
+        bytes.extend_from_slice(string.as_bytes());
+        bytes.push(0);
+        write_bytes(caller, offset as i32, &bytes)?;
+        offset = offset.wrapping_add(bytes.len() as u32);
+    }
+    Ok(())
+}
+
+/// Count of `strings` and the buffer size they need
+fn write_sizes<T>(caller: &mut Caller<'_, T>, strings: &[String], count: i32, size: i32) -> Result<(), Errno> {
+    write_u32(caller, count, strings.len() as u32)?;
+    write_u32(caller, size, strings.iter().map(|s| s.len() as u32 + 1).sum())
+}
+
+fn clock_now(id: i32) -> Result<u64, Errno> {
+    match id {
+        CLOCK_REALTIME => crate::device::rtc::realtime_ns().ok_or(ERRNO_NOSYS),
+        CLOCK_MONOTONIC | CLOCK_PROCESS_CPUTIME | CLOCK_THREAD_CPUTIME => Ok(crate::clint::get_time_ns()),
+        _ => Err(ERRNO_INVAL),
+    }
+}
+
+/// Fill the guest buffer from the kernel CSPRNG, a chunk at a time
+fn random_get<T>(caller: &mut Caller<'_, T>, buf: i32, len: i32) -> Result<(), Errno> {
+    let start = buf as u32 as usize;
+    let len = len as u32 as usize;
+    let end = start.checked_add(len).ok_or(ERRNO_FAULT)?;
+    if end > memory(caller)?.data(&*caller).len() {
+        return Err(ERRNO_FAULT);
+    }
+    let mut chunk = [0u8; RANDOM_CHUNK];
+    for offset in (start..end).step_by(RANDOM_CHUNK) {
+        let n = RANDOM_CHUNK.min(end - offset);
+        crate::random::fill(&mut chunk[..n]);
+        write_bytes(caller, offset as i32, &chunk[..n])?;
+    }
+    Ok(())
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Descriptor calls
+// ═══════════════════════════════════════════════════════════════════════════════
+
+fn fd_read<T: WasiHost>(caller: &mut Caller<'_, T>, fd: i32, iovs: i32, count: i32, nread: i32) -> Result<(), Errno> {
+    let iovs = iovecs(caller, iovs, count)?;
+    let wanted: usize = iovs.iter().map(|&(_, len)| len).sum();
+    if matches!(caller.data_mut().wasi().get(fd)?, Handle::Stdin) {
+        let mut buf = alloc::vec![0u8; wanted.min(INPUT_CHUNK)];
+        let n = caller.data_mut().read_input(&mut buf);
+        let n = scatter(caller, &iovs, &buf[..n])?;
+        return write_u32(caller, nread, n as u32);
+    }
+    let data = match caller.data_mut().wasi().get(fd)? {
+        Handle::File(file) if file.readable => {
+            let mut buf = alloc::vec![0u8; wanted.min(file.data.len().saturating_sub(file.pos as usize))];
+            let n = file.read_at(file.pos, &mut buf);
+            file.pos += n as u64;
+            buf
+        }
+        Handle::Dir { .. } => return Err(ERRNO_ISDIR),
+        _ => return Err(ERRNO_BADF),
+    };
+    let n = scatter(caller, &iovs, &data)?;
+    charge_syscall(Some(IoKind::FsRead), n as u64);
+    write_u32(caller, nread, n as u32)
+}
+
+fn fd_write<T: WasiHost>(caller: &mut Caller<'_, T>, fd: i32, iovs: i32, count: i32, nwritten: i32) -> Result<(), Errno> {
+    let data = gather(caller, iovs, count)?;
+    let console = match caller.data_mut().wasi().get(fd)? {
+        Handle::Stdout => Some(false),
+        Handle::Stderr => Some(true),
+        _ => None,
+    };
+    if let Some(stderr) = console {
+        caller.data_mut().write_output(&data, stderr);
+        return write_u32(caller, nwritten, data.len() as u32);
+    }
+    match caller.data_mut().wasi().get(fd)? {
+        Handle::File(file) if file.writable => {
+            if file.append {
+                file.pos = file.data.len() as u64;
+            }
+            file.write_at(file.pos, &data)?;
+            file.pos += data.len() as u64;
+        }
+        Handle::Dir { .. } => return Err(ERRNO_ISDIR),
+        _ => return Err(ERRNO_BADF),
+    }
+    write_u32(caller, nwritten, data.len() as u32)
+}
+
+fn fd_pread<T: WasiHost>(caller: &mut Caller<'_, T>, fd: i32, iovs: i32, count: i32, offset: i64, nread: i32) -> Result<(), Errno> {
+    let iovs = iovecs(caller, iovs, count)?;
+    let wanted: usize = iovs.iter().map(|&(_, len)| len).sum();
+    if offset < 0 {
+        return Err(ERRNO_INVAL);
+    }
+    let file = caller.data_mut().wasi().file(fd)?;
+    if !file.readable {
+        return Err(ERRNO_BADF);
+    }
+    let mut buf = alloc::vec![0u8; wanted.min(file.data.len().saturating_sub(offset as usize))];
+    let n = file.read_at(offset as u64, &mut buf);
+    buf.truncate(n);
+    let n = scatter(caller, &iovs, &buf)?;
+    write_u32(caller, nread, n as u32)
+}
+
+fn fd_pwrite<T: WasiHost>(caller: &mut Caller<'_, T>, fd: i32, iovs: i32, count: i32, offset: i64, nwritten: i32) -> Result<(), Errno> {
+    let data = gather(caller, iovs, count)?;
+    if offset < 0 {
+        return Err(ERRNO_INVAL);
+    }
+    let file = caller.data_mut().wasi().file(fd)?;
+    if !file.writable {
+        return Err(ERRNO_BADF);
+    }
+    file.write_at(offset as u64, &data)?;
+    write_u32(caller, nwritten, data.len() as u32)
+}
+
+fn fd_seek<T: WasiHost>(caller: &mut Caller<'_, T>, fd: i32, offset: i64, whence: i32, newoffset: i32) -> Result<(), Errno> {
+    let file = caller.data_mut().wasi().file(fd)?;
+    let base = match whence {
+        WHENCE_SET => 0,
+        WHENCE_CUR => file.pos as i64,
+        WHENCE_END => file.data.len() as i64,
+        _ => return Err(ERRNO_INVAL),
+    };
+    let pos = base.checked_add(offset).filter(|&pos| pos >= 0).ok_or(ERRNO_INVAL)?;
+    file.pos = pos as u64;
+    write_u64(caller, newoffset, pos as u64)
+}
+
+fn fd_tell<T: WasiHost>(caller: &mut Caller<'_, T>, fd: i32, offset: i32) -> Result<(), Errno> {
+    let pos = caller.data_mut().wasi().file(fd)?.pos;
+    write_u64(caller, offset, pos)
+}
+
+fn fd_sync<T: WasiHost>(caller: &mut Caller<'_, T>, fd: i32) -> Result<(), Errno> {
+    match caller.data_mut().wasi().get(fd)? {
+        Handle::File(file) => file.flush(),
+        _ => Ok(()),
+    }
+}
+
+fn fd_fdstat_get<T: WasiHost>(caller: &mut Caller<'_, T>, fd: i32, buf: i32) -> Result<(), Errno> {
+    let handle = caller.data_mut().wasi().get(fd)?;
+    let mut record = [0u8; 24];
+    record[0] = handle.filetype();
+    if matches!(handle, Handle::File(file) if file.append) {
+        record[2..4].copy_from_slice(&(FDFLAGS_APPEND as u16).to_le_bytes());
+    }
+    record[8..16].copy_from_slice(&RIGHTS_ALL.to_le_bytes());
+    record[16..24].copy_from_slice(&RIGHTS_ALL.to_le_bytes());
+    write_bytes(caller, buf, &record)
+}
+
+fn fd_fdstat_set_flags<T: WasiHost>(caller: &mut Caller<'_, T>, fd: i32, flags: i32) -> Result<(), Errno> {
+    match caller.data_mut().wasi().get(fd)? {
+        Handle::File(file) => {
+            file.append = flags & FDFLAGS_APPEND != 0;
+            Ok(())
+        }
+        _ if flags == 0 => Ok(()),
+        _ => Err(ERRNO_INVAL),
+    }
+}
+
+fn fd_filestat_get<T: WasiHost>(caller: &mut Caller<'_, T>, fd: i32, buf: i32) -> Result<(), Errno> {
+    let record = match caller.data_mut().wasi().get(fd)? {
+        Handle::File(file) => filestat(FILETYPE_REGULAR_FILE, file.data.len() as u64, path_ino(&file.path)),
+        Handle::Dir { path, .. } => filestat(FILETYPE_DIRECTORY, 0, path_ino(path)),
+        handle => filestat(handle.filetype(), 0, fd as u64),
+    };
+    write_bytes(caller, buf, &record)
+}
+
+fn fd_filestat_set_size<T: WasiHost>(caller: &mut Caller<'_, T>, fd: i32, size: i64) -> Result<(), Errno> {
+    let file = caller.data_mut().wasi().file(fd)?;
+    if !file.writable || size < 0 {
+        return Err(ERRNO_INVAL);
+    }
+    if size > MAX_FILE_SIZE as i64 {
+        return Err(ERRNO_FBIG);
+    }
+    file.data.resize(size as usize, 0);
+    file.dirty = true;
+    Ok(())
+}
+
+fn fd_prestat_get<T: WasiHost>(caller: &mut Caller<'_, T>, fd: i32, buf: i32) -> Result<(), Errno> {
+    let Handle::Dir { path, preopen: true } = caller.data_mut().wasi().get(fd)? else {
+        return Err(ERRNO_BADF);
+    };
+    let mut record = [0u8; 8];
+    record[4..8].copy_from_slice(&(path.len() as u32).to_le_bytes());
+    write_bytes(caller, buf, &record)
+}
+
+fn fd_prestat_dir_name<T: WasiHost>(caller: &mut Caller<'_, T>, fd: i32, buf: i32, len: i32) -> Result<(), Errno> {
+    let Handle::Dir { path, preopen: true } = caller.data_mut().wasi().get(fd)? else {
+        return Err(ERRNO_BADF);
+    };
+    let path = path.clone();
+    if path.len() > len as u32 as usize {
+        return Err(ERRNO_INVAL);
+    }
+    write_bytes(caller, buf, path.as_bytes())
+}
+
+fn fd_readdir<T: WasiHost>(caller: &mut Caller<'_, T>, fd: i32, buf: i32, len: i32, cookie: i64, bufused: i32) -> Result<(), Errno> {
+    let path = caller.data_mut().wasi().dir(fd)?;
+    let mut entries: Vec<(String, u8)> = alloc::vec![
+        (String::from("."), FILETYPE_DIRECTORY),
+        (String::from(".."), FILETYPE_DIRECTORY),
+    ];
+    entries.extend(fs_proxy::fs_list(&path).into_iter().map(|entry| {
+        (entry.name, if entry.is_dir { FILETYPE_DIRECTORY } else { FILETYPE_REGULAR_FILE })
+    }));
+
+    // Entries from `cookie` on; the last one may be cut off, which tells
+    // the caller to come back with a larger buffer
+    let len = len as u32 as usize;
+    let mut out = Vec::new();
+    for (i, (name, filetype)) in entries.iter().enumerate().skip(cookie.max(0) as usize) {
+        if out.len() >= len {
+            break;
+        }
+        let mut dirent = [0u8; 24];
+        dirent[0..8].copy_from_slice(&(i as u64 + 1).to_le_bytes());
+        dirent[8..16].copy_from_slice(&path_ino(name).to_le_bytes());
+        dirent[16..20].copy_from_slice(&(name.len() as u32).to_le_bytes());
+        dirent[20] = *filetype;
+        out.extend_from_slice(&dirent);
+        out.extend_from_slice(name.as_bytes());
+    }
+    out.truncate(len);
+    write_bytes(caller, buf, &out)?;
+    write_u32(caller, bufused, out.len() as u32)
+}
+
+fn fd_renumber<T: WasiHost>(caller: &mut Caller<'_, T>, fd: i32, to: i32) -> Result<(), Errno> {
+    let wasi = caller.data_mut().wasi();
+    wasi.get(fd)?;
+    wasi.get(to)?;
+    if fd != to {
+        wasi.close(to)?;
+        wasi.fds[to as usize] = wasi.fds[fd as usize].take();
+    }
+    Ok(())
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Path calls
+// ═══════════════════════════════════════════════════════════════════════════════
+
+#[allow(clippy::too_many_arguments)]
+fn path_open<T: WasiHost>(
+    caller: &mut Caller<'_, T>,
+    fd: i32,
+    dirflags: i32,
+    path: i32,
+    path_len: i32,
+    oflags: i32,
+    rights: i64,
+    fdflags: i32,
+    opened: i32,
+) -> Result<(), Errno> {
+    let path = path_at(caller, fd, path, path_len, dirflags & LOOKUP_SYMLINK_FOLLOW != 0)?;
+    let readable = rights & RIGHTS_FD_READ != 0;
+    let writable = rights & RIGHTS_FD_WRITE != 0 || oflags & OFLAGS_TRUNC != 0;
+
+    let handle = if fs_proxy::fs_is_dir(&path) {
+        if oflags & (OFLAGS_CREAT | OFLAGS_EXCL) == OFLAGS_CREAT | OFLAGS_EXCL {
+            return Err(ERRNO_EXIST);
+        }
+        if writable {
+            return Err(ERRNO_ISDIR);
+        }
+        Handle::Dir { path, preopen: false }
+    } else {
+        let existing = fs_proxy::fs_read(&path);
+        if oflags & OFLAGS_DIRECTORY != 0 {
+            return Err(if existing.is_some() { ERRNO_NOTDIR } else { ERRNO_NOENT });
+        }
+        let (data, dirty) = match existing {
+            Some(_) if oflags & (OFLAGS_CREAT | OFLAGS_EXCL) == OFLAGS_CREAT | OFLAGS_EXCL => return Err(ERRNO_EXIST),
+            Some(_) if oflags & OFLAGS_TRUNC != 0 => (Vec::new(), true),
+            Some(data) => {
+                charge_syscall(Some(IoKind::FsRead), data.len() as u64);
+                (data, false)
+            }
+            None if oflags & OFLAGS_CREAT != 0 => {
+                // Create it now, so a bad path fails here rather than at close
+                fs_proxy::fs_write(&path, &[]).map_err(|_| ERRNO_NOENT)?;
+                (Vec::new(), false)
+            }
+            None => return Err(ERRNO_NOENT),
+        };
+        Handle::File(OpenFile {
+            path,
+            data,
+            pos: 0,
+            readable,
+            writable,
+            append: fdflags & FDFLAGS_APPEND != 0,
+            dirty,
+        })
+    };
+    let fd = caller.data_mut().wasi().insert(handle)?;
+    write_u32(caller, opened, fd as u32)
+}
+
+fn path_filestat_get<T: WasiHost>(caller: &mut Caller<'_, T>, fd: i32, flags: i32, path: i32, path_len: i32, buf: i32) -> Result<(), Errno> {
+    let follow = flags & LOOKUP_SYMLINK_FOLLOW != 0;
+    let path = path_at(caller, fd, path, path_len, follow)?;
+    let (filetype, size) = stat(&path, follow)?;
+    write_bytes(caller, buf, &filestat(filetype, size, path_ino(&path)))
+}
+
+fn path_create_directory<T: WasiHost>(caller: &mut Caller<'_, T>, fd: i32, path: i32, path_len: i32) -> Result<(), Errno> {
+    let path = path_at(caller, fd, path, path_len, false)?;
+    if fs_proxy::fs_exists(&path) || fs_proxy::fs_is_dir(&path) {
+        return Err(ERRNO_EXIST);
+    }
+    fs_proxy::fs_mkdir(&path).map_err(|_| ERRNO_IO)
+}
+
+fn path_unlink_file<T: WasiHost>(caller: &mut Caller<'_, T>, fd: i32, path: i32, path_len: i32) -> Result<(), Errno> {
+    let path = path_at(caller, fd, path, path_len, false)?;
+    match stat(&path, false)? {
+        (FILETYPE_DIRECTORY, _) => Err(ERRNO_ISDIR),
+        _ => fs_proxy::fs_remove(&path).map_err(|_| ERRNO_IO),
+    }
+}
+
+fn path_remove_directory<T: WasiHost>(caller: &mut Caller<'_, T>, fd: i32, path: i32, path_len: i32) -> Result<(), Errno> {
+    let path = path_at(caller, fd, path, path_len, false)?;
+    if !fs_proxy::fs_is_dir(&path) {
+        return Err(if fs_proxy::fs_exists(&path) { ERRNO_NOTDIR } else { ERRNO_NOENT });
+    }
+    if !fs_proxy::fs_list(&path).is_empty() {
+        return Err(ERRNO_NOTEMPTY);
+    }
+    fs_proxy::fs_remove(&path).map_err(|_| ERRNO_IO)
+}
+
+fn path_rename<T: WasiHost>(caller: &mut Caller<'_, T>, fd: i32, from: i32, from_len: i32, to_fd: i32, to: i32, to_len: i32) -> Result<(), Errno> {
+    let from = path_at(caller, fd, from, from_len, false)?;
+    let to = path_at(caller, to_fd, to, to_len, false)?;
+    stat(&from, false)?;
+    fs_proxy::fs_rename(&from, &to).map_err(|_| ERRNO_IO)
+}
+
+fn path_readlink<T: WasiHost>(caller: &mut Caller<'_, T>, fd: i32, path: i32, path_len: i32, buf: i32, len: i32, bufused: i32) -> Result<(), Errno> {
+    let path = path_at(caller, fd, path, path_len, false)?;
+    let target = fs_proxy::fs_readlink(&path).ok_or(ERRNO_INVAL)?;
+    let n = target.len().min(len as u32 as usize);
+    write_bytes(caller, buf, &target.as_bytes()[..n])?;
+    write_u32(caller, bufused, n as u32)
+}
+
+fn path_symlink<T: WasiHost>(caller: &mut Caller<'_, T>, target: i32, target_len: i32, fd: i32, path: i32, path_len: i32) -> Result<(), Errno> {
+    let target = read_string(caller, target, target_len)?;
+    let path = path_at(caller, fd, path, path_len, false)?;
+    if fs_proxy::fs_exists(&path) || fs_proxy::fs_readlink(&path).is_some() {
+        return Err(ERRNO_EXIST);
+    }
+    fs_proxy::fs_symlink(&target, &path).map_err(|_| ERRNO_IO)
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Polling
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Wait for the subscriptions at `subs`. Descriptor subscriptions are
+/// always ready; otherwise this sleeps until the earliest clock fires.
+fn poll_oneoff<T: WasiHost>(caller: &mut Caller<'_, T>, subs: i32, events: i32, count: i32, nevents: i32) -> Result<(), Errno> {
+    if count <= 0 {
+        return Err(ERRNO_INVAL);
+    }
+    let raw = read_bytes(caller, subs, count as usize * SUBSCRIPTION_SIZE)?;
+    let now_ns = crate::clint::get_time_ns();
+    let mut ready = Vec::new();
+    let mut clocks = Vec::new();
+    for sub in raw.chunks_exact(SUBSCRIPTION_SIZE) {
+        let userdata = &sub[0..8];
+        let tag = sub[8];
+        if tag == EVENTTYPE_CLOCK {
+            let id = i32::from_le_bytes([sub[16], sub[17], sub[18], sub[19]]);
+            let timeout = u64::from_le_bytes(sub[24..32].try_into().unwrap());
+            let flags = u16::from_le_bytes([sub[40], sub[41]]);
+            let deadline_ns = if flags & SUBCLOCKFLAGS_ABSTIME != 0 {
+                // Convert the absolute time to the monotonic clock
+                now_ns.saturating_add(timeout.saturating_sub(clock_now(id)?))
+            } else {
+                now_ns.saturating_add(timeout)
+            };
+            clocks.push((deadline_ns, userdata, tag));
+        } else {
+            ready.push((userdata, tag, 1u64));
+        }
+    }
+
+    if ready.is_empty() {
+        if let Some(deadline_ns) = clocks.iter().map(|&(deadline, _, _)| deadline).min() {
+            let deadline_ms = deadline_ns.div_ceil(1_000_000) as i64;
+            let start = crate::get_time_ms();
+            while crate::clint::get_time_ns() < deadline_ns {
+                crate::lock::wait::sleep_until(deadline_ms);
+            }
+            crate::scripting::shell_cmd_sleep((crate::get_time_ms() - start) as u64);
+        }
+        let now_ns = crate::clint::get_time_ns();
+        ready.extend(
+            clocks
+                .iter()
+                .filter(|&&(deadline, _, _)| deadline <= now_ns)
+                .map(|&(_, userdata, tag)| (userdata, tag, 0)),
+        );
+    }
+
+    let mut out = Vec::with_capacity(ready.len() * EVENT_SIZE);
+    for (userdata, tag, nbytes) in &ready {
+        let mut event = [0u8; EVENT_SIZE];
+        event[0..8].copy_from_slice(userdata);
+        event[10] = *tag;
+        event[16..24].copy_from_slice(&nbytes.to_le_bytes());
+        out.extend_from_slice(&event);
+    }
+    write_bytes(caller, events, &out)?;
+    write_u32(caller, nevents, ready.len() as u32)
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Linker
+// ═══════════════════════════════════════════════════════════════════════════════
+
+fn define<T>(linker: &mut Linker<T>, name: &str, func: Func) -> Result<(), String> {
+    linker
+        .define(MODULE, name, func)
+        .map(|_| ())
+        .map_err(|e| format!("define {}: {:?}", name, e))
+}
+
+/// Register the `wasi_snapshot_preview1` imports
+pub(crate) fn add_to_linker<T: WasiHost + 'static>(linker: &mut Linker<T>, store: &mut Store<T>) -> Result<(), String> {
+    // Arguments and environment
+    define(linker, "args_get", Func::wrap(&mut *store, |mut caller: Caller<'_, T>, argv_ptr: i32, buf: i32| -> i32 {
+        let strings = argv(&caller);
+        status(write_strings(&mut caller, &strings, argv_ptr, buf))
+    }))?;
+    define(linker, "args_sizes_get", Func::wrap(&mut *store, |mut caller: Caller<'_, T>, count: i32, size: i32| -> i32 {
+        let strings = argv(&caller);
+        status(write_sizes(&mut caller, &strings, count, size))
+    }))?;
+    define(linker, "environ_get", Func::wrap(&mut *store, |mut caller: Caller<'_, T>, environ_ptr: i32, buf: i32| -> i32 {
+        let strings = environ(&caller);
+        status(write_strings(&mut caller, &strings, environ_ptr, buf))
+    }))?;
+    define(linker, "environ_sizes_get", Func::wrap(&mut *store, |mut caller: Caller<'_, T>, count: i32, size: i32| -> i32 {
+        let strings = environ(&caller);
+        status(write_sizes(&mut caller, &strings, count, size))
+    }))?;
+
+    // Clocks and random
+    define(linker, "clock_res_get", Func::wrap(&mut *store, |mut caller: Caller<'_, T>, id: i32, res: i32| -> i32 {
+        status(clock_now(id).and_then(|_| write_u64(&mut caller, res, crate::clint::NS_PER_TICK)))
+    }))?;
+    define(linker, "clock_time_get", Func::wrap(&mut *store, |mut caller: Caller<'_, T>, id: i32, _precision: i64, time: i32| -> i32 {
+        status(clock_now(id).and_then(|now| write_u64(&mut caller, time, now)))
+    }))?;
+    define(linker, "random_get", Func::wrap(&mut *store, |mut caller: Caller<'_, T>, buf: i32, len: i32| -> i32 {
+        status(random_get(&mut caller, buf, len))
+    }))?;
+
+    // Descriptors
+    define(linker, "fd_read", Func::wrap(&mut *store, |mut caller: Caller<'_, T>, fd: i32, iovs: i32, count: i32, nread: i32| -> i32 {
+        status(fd_read(&mut caller, fd, iovs, count, nread))
+    }))?;
+    define(linker, "fd_write", Func::wrap(&mut *store, |mut caller: Caller<'_, T>, fd: i32, iovs: i32, count: i32, nwritten: i32| -> i32 {
+        status(fd_write(&mut caller, fd, iovs, count, nwritten))
+    }))?;
+    define(linker, "fd_pread", Func::wrap(&mut *store, |mut caller: Caller<'_, T>, fd: i32, iovs: i32, count: i32, offset: i64, nread: i32| -> i32 {
+        status(fd_pread(&mut caller, fd, iovs, count, offset, nread))
+    }))?;
+    define(linker, "fd_pwrite", Func::wrap(&mut *store, |mut caller: Caller<'_, T>, fd: i32, iovs: i32, count: i32, offset: i64, nwritten: i32| -> i32 {
+        status(fd_pwrite(&mut caller, fd, iovs, count, offset, nwritten))
+    }))?;
+    define(linker, "fd_close", Func::wrap(&mut *store, |mut caller: Caller<'_, T>, fd: i32| -> i32 {
+        status(caller.data_mut().wasi().close(fd))
+    }))?;
+    define(linker, "fd_seek", Func::wrap(&mut *store, |mut caller: Caller<'_, T>, fd: i32, offset: i64, whence: i32, newoffset: i32| -> i32 {
+        status(fd_seek(&mut caller, fd, offset, whence, newoffset))
+    }))?;
+    define(linker, "fd_tell", Func::wrap(&mut *store, |mut caller: Caller<'_, T>, fd: i32, offset: i32| -> i32 {
+        status(fd_tell(&mut caller, fd, offset))
+    }))?;
+    define(linker, "fd_sync", Func::wrap(&mut *store, |mut caller: Caller<'_, T>, fd: i32| -> i32 {
+        status(fd_sync(&mut caller, fd))
+    }))?;
+    define(linker, "fd_datasync", Func::wrap(&mut *store, |mut caller: Caller<'_, T>, fd: i32| -> i32 {
+        status(fd_sync(&mut caller, fd))
+    }))?;
+    define(linker, "fd_fdstat_get", Func::wrap(&mut *store, |mut caller: Caller<'_, T>, fd: i32, buf: i32| -> i32 {
+        status(fd_fdstat_get(&mut caller, fd, buf))
+    }))?;
+    define(linker, "fd_fdstat_set_flags", Func::wrap(&mut *store, |mut caller: Caller<'_, T>, fd: i32, flags: i32| -> i32 {
+        status(fd_fdstat_set_flags(&mut caller, fd, flags))
+    }))?;
+    define(linker, "fd_fdstat_set_rights", Func::wrap(&mut *store, |mut caller: Caller<'_, T>, fd: i32, _base: i64, _inheriting: i64| -> i32 {
+        status(caller.data_mut().wasi().get(fd).map(|_| ()))
+    }))?;
+    define(linker, "fd_filestat_get", Func::wrap(&mut *store, |mut caller: Caller<'_, T>, fd: i32, buf: i32| -> i32 {
+        status(fd_filestat_get(&mut caller, fd, buf))
+    }))?;
+    define(linker, "fd_filestat_set_size", Func::wrap(&mut *store, |mut caller: Caller<'_, T>, fd: i32, size: i64| -> i32 {
+        status(fd_filestat_set_size(&mut caller, fd, size))
+    }))?;
+    define(linker, "fd_filestat_set_times", Func::wrap(&mut *store, |_caller: Caller<'_, T>, _fd: i32, _atim: i64, _mtim: i64, _flags: i32| -> i32 {
+        ERRNO_NOSYS
+    }))?;
+    define(linker, "fd_advise", Func::wrap(&mut *store, |mut caller: Caller<'_, T>, fd: i32, _offset: i64, _len: i64, _advice: i32| -> i32 {
+        status(caller.data_mut().wasi().get(fd).map(|_| ()))
+    }))?;
+    define(linker, "fd_allocate", Func::wrap(&mut *store, |_caller: Caller<'_, T>, _fd: i32, _offset: i64, _len: i64| -> i32 {
+        ERRNO_NOSYS
+    }))?;
+    define(linker, "fd_prestat_get", Func::wrap(&mut *store, |mut caller: Caller<'_, T>, fd: i32, buf: i32| -> i32 {
+        status(fd_prestat_get(&mut caller, fd, buf))
+    }))?;
+    define(linker, "fd_prestat_dir_name", Func::wrap(&mut *store, |mut caller: Caller<'_, T>, fd: i32, buf: i32, len: i32| -> i32 {
+        status(fd_prestat_dir_name(&mut caller, fd, buf, len))
+    }))?;
+    define(linker, "fd_readdir", Func::wrap(&mut *store, |mut caller: Caller<'_, T>, fd: i32, buf: i32, len: i32, cookie: i64, bufused: i32| -> i32 {
+        status(fd_readdir(&mut caller, fd, buf, len, cookie, bufused))
+    }))?;
+    define(linker, "fd_renumber", Func::wrap(&mut *store, |mut caller: Caller<'_, T>, fd: i32, to: i32| -> i32 {
+        status(fd_renumber(&mut caller, fd, to))
+    }))?;
+
+    // Paths
+    define(linker, "path_open", Func::wrap(
+        &mut *store,
+        |mut caller: Caller<'_, T>,
+         fd: i32,
+         dirflags: i32,
+         path: i32,
+         path_len: i32,
+         oflags: i32,
+         rights: i64,
+         _inheriting: i64,
+         fdflags: i32,
+         opened: i32|
+         -> i32 {
+            status(path_open(&mut caller, fd, dirflags, path, path_len, oflags, rights, fdflags, opened))
+        },
+    ))?;
+    define(linker, "path_filestat_get", Func::wrap(&mut *store, |mut caller: Caller<'_, T>, fd: i32, flags: i32, path: i32, path_len: i32, buf: i32| -> i32 {
+        status(path_filestat_get(&mut caller, fd, flags, path, path_len, buf))
+    }))?;
+    define(linker, "path_filestat_set_times", Func::wrap(
+        &mut *store,
+        |_caller: Caller<'_, T>, _fd: i32, _flags: i32, _path: i32, _path_len: i32, _atim: i64, _mtim: i64, _fst_flags: i32| -> i32 {
+            ERRNO_NOSYS
+        },
+    ))?;
+    define(linker, "path_create_directory", Func::wrap(&mut *store, |mut caller: Caller<'_, T>, fd: i32, path: i32, path_len: i32| -> i32 {
+        status(path_create_directory(&mut caller, fd, path, path_len))
+    }))?;
+    define(linker, "path_unlink_file", Func::wrap(&mut *store, |mut caller: Caller<'_, T>, fd: i32, path: i32, path_len: i32| -> i32 {
+        status(path_unlink_file(&mut caller, fd, path, path_len))
+    }))?;
+    define(linker, "path_remove_directory", Func::wrap(&mut *store, |mut caller: Caller<'_, T>, fd: i32, path: i32, path_len: i32| -> i32 {
+        status(path_remove_directory(&mut caller, fd, path, path_len))
+    }))?;
+    define(linker, "path_rename", Func::wrap(
+        &mut *store,
+        |mut caller: Caller<'_, T>, fd: i32, from: i32, from_len: i32, to_fd: i32, to: i32, to_len: i32| -> i32 {
+            status(path_rename(&mut caller, fd, from, from_len, to_fd, to, to_len))
+        },
+    ))?;
+    define(linker, "path_readlink", Func::wrap(
+        &mut *store,
+        |mut caller: Caller<'_, T>, fd: i32, path: i32, path_len: i32, buf: i32, len: i32, bufused: i32| -> i32 {
+            status(path_readlink(&mut caller, fd, path, path_len, buf, len, bufused))
+        },
+    ))?;
+    define(linker, "path_symlink", Func::wrap(
+        &mut *store,
+        |mut caller: Caller<'_, T>, target: i32, target_len: i32, fd: i32, path: i32, path_len: i32| -> i32 {
+            status(path_symlink(&mut caller, target, target_len, fd, path, path_len))
+        },
+    ))?;
+    define(linker, "path_link", Func::wrap(
+        &mut *store,
+        |_caller: Caller<'_, T>, _fd: i32, _flags: i32, _from: i32, _from_len: i32, _to_fd: i32, _to: i32, _to_len: i32| -> i32 {
+            ERRNO_NOSYS
+        },
+    ))?;
+
+    // Process
+    define(linker, "poll_oneoff", Func::wrap(&mut *store, |mut caller: Caller<'_, T>, subs: i32, events: i32, count: i32, nevents: i32| -> i32 {
+        status(poll_oneoff(&mut caller, subs, events, count, nevents))
+    }))?;
+    define(linker, "proc_exit", Func::wrap(&mut *store, |_caller: Caller<'_, T>, code: i32| -> Result<(), wasmi::Error> {
+        Err(wasmi::Error::i32_exit(code))
+    }))?;
+    define(linker, "proc_raise", Func::wrap(&mut *store, |_caller: Caller<'_, T>, _signal: i32| -> i32 {
+        ERRNO_NOSYS
+    }))?;
+    define(linker, "sched_yield", Func::wrap(&mut *store, |_caller: Caller<'_, T>| -> i32 {
+        crate::cpu::sched::yield_now();
+        ERRNO_SUCCESS
+    }))?;
+
+    // Sockets
+    define(linker, "sock_accept", Func::wrap(&mut *store, |_caller: Caller<'_, T>, _fd: i32, _flags: i32, _opened: i32| -> i32 {
+        ERRNO_NOSYS
+    }))?;
+    define(linker, "sock_recv", Func::wrap(
+        &mut *store,
+        |_caller: Caller<'_, T>, _fd: i32, _iovs: i32, _count: i32, _flags: i32, _nread: i32, _oflags: i32| -> i32 {
+            ERRNO_NOSYS
+        },
+    ))?;
+    define(linker, "sock_send", Func::wrap(
+        &mut *store,
+        |_caller: Caller<'_, T>, _fd: i32, _iovs: i32, _count: i32, _flags: i32, _nwritten: i32| -> i32 {
+            ERRNO_NOSYS
+        },
+    ))?;
+    define(linker, "sock_shutdown", Func::wrap(&mut *store, |_caller: Caller<'_, T>, _fd: i32, _how: i32| -> i32 {
+        ERRNO_NOSYS
+    }))?;
+
+    Ok(())
+}
@@ -1,9 +1,17 @@
 use alloc::{format, string::String, vec, vec::Vec};
 use alloc::collections::BTreeMap;
-use wasmi::{Caller, Config, Engine, Func, Linker, Module, Store};
+use alloc::sync::Arc;
+use wasmi::{Caller, Config, Engine, Func, Linker, Module, Store, StoreLimits, StoreLimitsBuilder, TypedResumableCall};
+use wasmi::core::TrapCode;
+use wasmi::errors::{ErrorKind, MemoryError};
 use core::ptr;
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 
-use crate::{SHELL_CMD_STATE, ShellCmdState, clint::get_time_ms, commands::http, constants::TEST_FINISHER, cpu, lock::{self, utils::BLK_DEV}, services::klogd::{KLOG, klog_info}, uart, Spinlock};
+use crate::{SHELL_CMD_STATE, ShellCmdState, clint::get_time_ms, commands::http, constants::TEST_FINISHER, cpu, locale, lock::{self, utils::BLK_DEV}, services::klogd::{KLOG, klog_info}, uart, Spinlock};
+use crate::cpu::ipc::Pipe;
+use crate::cpu::signal::{self, Signal, SIGCONT};
+use crate::cpu::process::{charge_syscall, IoKind};
+use crate::wasi::{WasiHost, WasiState};
 
 // ═══════════════════════════════════════════════════════════════════════════════
 // WASM Module Cache - Avoids re-parsing WASM binaries
@@ -42,9 +50,253 @@ static MODULE_CACHE: Spinlock<BTreeMap<u64, CachedModule>> = Spinlock::new(BTree
 /// Maximum cache entries to prevent unbounded growth
 const MAX_CACHE_ENTRIES: usize = 16;
 
+/// Linear memory a program may grow to, unless its job sets a limit
+pub const DEFAULT_MEMORY_LIMIT: usize = 16 * 1024 * 1024;
+
+/// Why a program failed
+pub enum RunError {
+    /// Its linear memory reached the limit (bytes) and it could not go on
+    OutOfMemory(usize),
+    Other(String),
+}
+
+impl From<String> for RunError {
+    fn from(message: String) -> Self {
+        RunError::Other(message)
+    }
+}
+
+impl core::fmt::Display for RunError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            RunError::OutOfMemory(limit) => write!(f, "Out of memory ({} KiB limit)", limit / 1024),
+            RunError::Other(message) => f.write_str(message),
+        }
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Fuel and Aborting - Keeps a runaway module from owning its hart
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Fuel (about one unit per instruction) a module runs before it yields to
+/// other work on its hart and is checked for `abort` and its budget
+const FUEL_SLICE: u64 = 1_000_000;
+
+/// Process each hart's running module belongs to (0 = none): the shell
+/// command's PID for a foreground command, else the process running it
+static MODULE_OWNER: [AtomicU32; cpu::MAX_HARTS] = [const { AtomicU32::new(0) }; cpu::MAX_HARTS];
+
+/// Set by `abort` for the module running on a hart
+static ABORT_REQUESTED: [AtomicBool; cpu::MAX_HARTS] = [const { AtomicBool::new(false) }; cpu::MAX_HARTS];
+
+/// Records who the module running on this hart belongs to, until dropped
+struct RunningModule {
+    hart: usize,
+    previous: u32,
+}
+
+impl RunningModule {
+    fn enter() -> Option<Self> {
+        let hart = crate::get_hart_id();
+        let owner = MODULE_OWNER.get(hart)?;
+        let pid = {
+            let state = SHELL_CMD_STATE.lock();
+            if state.is_running && state.hart == hart { state.pid } else { 0 }
+        };
+        let pid = if pid != 0 {
+            pid
+        } else {
+            cpu::CPU_TABLE.get(hart).and_then(|cpu| cpu.running_process()).unwrap_or(0)
+        };
+        ABORT_REQUESTED[hart].store(false, Ordering::Release);
+        Some(Self { hart, previous: owner.swap(pid, Ordering::AcqRel) })
+    }
+
+    fn aborted(&self) -> bool {
+        ABORT_REQUESTED[self.hart].swap(false, Ordering::AcqRel)
+    }
+}
+
+impl Drop for RunningModule {
+    fn drop(&mut self) {
+        MODULE_OWNER[self.hart].store(self.previous, Ordering::Release);
+    }
+}
+
+/// Abort the module running for `pid` (the foreground command or a worker
+/// running a job) at its next fuel slice. Stop and continue signals leave
+/// it alone. Returns whether there was such a module.
+pub fn abort(pid: u32, sig: Signal) -> bool {
+    if pid == 0 || sig == SIGCONT || signal::is_stop(sig) {
+        return false;
+    }
+    let Some(hart) = MODULE_OWNER.iter().position(|owner| owner.load(Ordering::Acquire) == pid) else {
+        return false;
+    };
+    ABORT_REQUESTED[hart].store(true, Ordering::Release);
+    true
+}
+
+/// Abort whatever module is running on `hart` at its next fuel slice
+/// (cancelling a worker's job). Returns false for a hart that does not exist.
+pub fn abort_on_hart(hart: usize) -> bool {
+    match ABORT_REQUESTED.get(hart) {
+        Some(requested) => {
+            requested.store(true, Ordering::Release);
+            true
+        }
+        None => false,
+    }
+}
+
+/// The outcome of a call that ended with `e`: a WASI exit with status 0 is
+/// success
+fn call_error(e: wasmi::Error) -> Result<(), String> {
+    match e.i32_exit_status() {
+        Some(0) => Ok(()),
+        Some(status) => Err(format!("Exit status {}", status)),
+        None => Err(format!("Runtime: {:?}", e)),
+    }
+}
+
+/// Whether `e` is the store limiter refusing to grow linear memory, at
+/// instantiation or from a `memory.grow`
+fn out_of_memory(e: &wasmi::Error) -> bool {
+    e.as_trap_code() == Some(TrapCode::GrowthOperationLimited)
+        || matches!(e.kind(), ErrorKind::Memory(MemoryError::ResourceLimiterDeniedAllocation))
+}
+
 /// State to pass to host functions - includes command arguments
 struct WasmContext {
     args: Vec<String>, 
+    /// Console input comes from this pipe instead of the UART (pipeline stage)
+    stdin: Option<Arc<Pipe>>,
+    /// Printed output goes to this pipe instead of the console (pipeline stage)
+    stdout: Option<Arc<Pipe>>,
+    /// Set when running as an httpd CGI handler
+    cgi: Option<CgiRequest>,
+    /// Descriptor table of a WASI program
+    wasi: WasiState,
+    /// Caps the linear memory at `memory_limit` bytes; growing past it traps
+    limits: StoreLimits,
+    memory_limit: usize,
+}
+
+impl WasmContext {
+    fn new(args: &[&str], memory_limit: usize) -> Self {
+        Self {
+            args: args.iter().map(|s| String::from(*s)).collect(),
+            stdin: None,
+            stdout: None,
+            cgi: None,
+            wasi: WasiState::new(),
+            limits: StoreLimitsBuilder::new()
+                .memory_size(memory_limit)
+                .trap_on_grow_failure(true)
+                .build(),
+            memory_limit,
+        }
+    }
+}
+
+/// The request a CGI module is handling and the response it has printed
+struct CgiRequest {
+    /// REQUEST_METHOD, QUERY_STRING, HTTP_* and the like, seen through env_get
+    env: Vec<(String, String)>,
+    /// Request body, read through console_read
+    body: Vec<u8>,
+    body_pos: usize,
+    /// Everything printed: optional CGI headers, a blank line, the response body
+    output: Vec<u8>,
+}
+
+/// Write all of `data` to a pipeline pipe, waiting while it is full.
+/// Gives up (dropping the rest) once the reading stage has exited.
+fn pipe_write_all(pipe: &Pipe, mut data: &[u8]) {
+    while !data.is_empty() {
+        match pipe.write(data) {
+            Ok(0) => {
+                // Backpressure: the next stage has not caught up yet
+                for _ in 0..1000 {
+                    core::hint::spin_loop();
+                }
+            }
+            Ok(n) => data = &data[n..],
+            Err(_) => return,
+        }
+    }
+}
+
+impl WasiHost for WasmContext {
+    fn wasi(&mut self) -> &mut WasiState {
+        &mut self.wasi
+    }
+
+    fn args(&self) -> &[String] {
+        &self.args
+    }
+
+    fn environ(&self) -> Vec<(String, String)> {
+        match &self.cgi {
+            Some(cgi) => cgi.env.clone(),
+            None => crate::syscall::environment()
+                .into_iter()
+                .map(|(key, value)| (String::from(key), value))
+                .collect(),
+        }
+    }
+
+    fn write_output(&mut self, data: &[u8], stderr: bool) {
+        if stderr {
+            crate::scripting::out_str(&String::from_utf8_lossy(data));
+        } else if let Some(cgi) = self.cgi.as_mut() {
+            cgi.output.extend_from_slice(data);
+        } else if let Some(pipe) = &self.stdout {
+            pipe_write_all(pipe, data);
+        } else {
+            crate::scripting::out_str(&String::from_utf8_lossy(data));
+        }
+    }
+
+    fn read_input(&mut self, buf: &mut [u8]) -> usize {
+        if let Some(cgi) = self.cgi.as_mut() {
+            let rest = &cgi.body[cgi.body_pos..];
+            let n = rest.len().min(buf.len());
+            buf[..n].copy_from_slice(&rest[..n]);
+            cgi.body_pos += n;
+            return n;
+        }
+        if let Some(pipe) = &self.stdin {
+            loop {
+                match pipe.read(buf) {
+                    Ok(0) => lock::wait::sleep_until(get_time_ms() + 1),
+                    Ok(n) => return n,
+                    Err(_) => return 0,
+                }
+            }
+        }
+        // Console: wait for a key, then take whatever else is pending.
+        // Ctrl+D or a cancel request ends the input.
+        let mut n = 0;
+        while n < buf.len() {
+            match uart::read_char_nonblocking() {
+                Some(0x04) => break,
+                Some(ch) => {
+                    buf[n] = ch;
+                    n += 1;
+                }
+                None if n > 0 => break,
+                None => {
+                    if crate::ui::main_screen::should_cancel() {
+                        break;
+                    }
+                    lock::wait::sleep_until(get_time_ms() + 1);
+                }
+            }
+        }
+        n
+    }
 }
 
 /// Get shell command info for ps_list (returns: name, pid, cpu (hart), uptime_ms, is_running)
@@ -79,8 +331,52 @@ pub fn get_shell_cmd_info() -> Option<(String, u32, i64, u64, bool)> {
 
 
 
+/// The socket `sock` if it was opened by the program running on this hart
+fn wasm_socket(net: &crate::net::NetState, sock: i32) -> Option<crate::net::TcpSocketId> {
+    let id = u8::try_from(sock).ok()?;
+    net.tcp_is_owned_by(id, crate::get_hart_id()).then_some(id)
+}
+
 /// Execute a WASM binary with the given arguments
 pub fn execute(wasm_bytes: &[u8], args: &[&str]) -> Result<String, String> {
+    execute_piped(wasm_bytes, args, None, None, DEFAULT_MEMORY_LIMIT).map_err(|e| format!("{}", e))
+}
+
+/// Execute a WASM binary reading console input from `stdin` and printing to
+/// `stdout` when given (pipeline stages, see `wasm_service`), with its linear
+/// memory capped at `memory_limit` bytes
+pub fn execute_piped(
+    wasm_bytes: &[u8],
+    args: &[&str],
+    stdin: Option<Arc<Pipe>>,
+    stdout: Option<Arc<Pipe>>,
+    memory_limit: usize,
+) -> Result<String, RunError> {
+    let mut ctx = WasmContext::new(args, memory_limit);
+    ctx.stdin = stdin;
+    ctx.stdout = stdout;
+    run(wasm_bytes, ctx)?;
+    Ok(String::new())
+}
+
+/// Execute a WASM binary as a CGI handler: `env` is visible through
+/// `env_get`, `body` is its console input, and everything it prints is
+/// returned instead of reaching the console
+pub fn execute_cgi(
+    wasm_bytes: &[u8],
+    args: &[&str],
+    env: Vec<(String, String)>,
+    body: Vec<u8>,
+) -> Result<Vec<u8>, String> {
+    let mut ctx = WasmContext::new(args, DEFAULT_MEMORY_LIMIT);
+    ctx.cgi = Some(CgiRequest { env, body, body_pos: 0, output: Vec::new() });
+    let ctx = run(wasm_bytes, ctx).map_err(|e| format!("{}", e))?;
+    Ok(ctx.cgi.map(|cgi| cgi.output).unwrap_or_default())
+}
+
+/// Instantiate and run a module's `_start`, handing back its context
+fn run(wasm_bytes: &[u8], ctx: WasmContext) -> Result<WasmContext, RunError> {
+    let _subsystem = crate::allocator::enter_subsystem(crate::allocator::Subsystem::Wasm);
     // Check module cache first
     let hash = hash_wasm(wasm_bytes);
     
@@ -94,7 +390,7 @@ pub fn execute(wasm_bytes: &[u8], args: &[&str]) -> Result<String, String> {
         } else {
             // Cache miss - create new engine and parse module
             let mut config = Config::default();
-            config.consume_fuel(false);
+            config.consume_fuel(true);
             let engine = Engine::new(&config);
             
             let module = Module::new(&engine, wasm_bytes)
@@ -117,10 +413,8 @@ pub fn execute(wasm_bytes: &[u8], args: &[&str]) -> Result<String, String> {
         }
     };
     
-    let ctx = WasmContext {
-        args: args.iter().map(|s| String::from(*s)).collect(),
-    };
     let mut store = Store::new(&engine, ctx);
+    store.limiter(|ctx| &mut ctx.limits);
     let mut linker = Linker::new(&engine);
 
     // Syscall: print(ptr, len)
@@ -131,12 +425,18 @@ pub fn execute(wasm_bytes: &[u8], args: &[&str]) -> Result<String, String> {
             "print",
             Func::wrap(
                 &mut store,
-                |caller: Caller<'_, WasmContext>, ptr: i32, len: i32| {
+                |mut caller: Caller<'_, WasmContext>, ptr: i32, len: i32| {
                     if let Some(mem) = caller.get_export("memory").and_then(|e| e.into_memory()) {
                         let mut buffer = vec![0u8; len as usize];
                         if mem.read(&caller, ptr as usize, &mut buffer).is_ok() {
-                            // Use out_str to respect OUTPUT_CAPTURE mode
-                            crate::scripting::out_str(&String::from_utf8_lossy(&buffer));
+                            if let Some(cgi) = caller.data_mut().cgi.as_mut() {
+                                cgi.output.extend_from_slice(&buffer);
+                            } else if let Some(pipe) = &caller.data().stdout {
+                                pipe_write_all(pipe, &buffer);
+                            } else {
+                                // Use out_str to respect OUTPUT_CAPTURE mode
+                                crate::scripting::out_str(&String::from_utf8_lossy(&buffer));
+                            }
                         }
                     }
                 },
@@ -157,12 +457,19 @@ pub fn execute(wasm_bytes: &[u8], args: &[&str]) -> Result<String, String> {
 
     // Syscall: console_available() -> i32
     // Check if console input is available (non-blocking check)
-    // Returns 1 if input is available, 0 otherwise
+    // Returns 1 if input is available (or a piped input has ended), 0 otherwise
     linker
         .define(
             "env",
             "console_available",
-            Func::wrap(&mut store, |_caller: Caller<'_, WasmContext>| -> i32 {
+            Func::wrap(&mut store, |caller: Caller<'_, WasmContext>| -> i32 {
+                // A CGI handler's request body never blocks, even once read
+                if caller.data().cgi.is_some() {
+                    return 1;
+                }
+                if let Some(pipe) = &caller.data().stdin {
+                    return (pipe.can_read() || pipe.is_closed()) as i32;
+                }
                 // Check if UART has pending input
                 if crate::uart::has_pending_input() {
                     1
@@ -175,6 +482,7 @@ pub fn execute(wasm_bytes: &[u8], args: &[&str]) -> Result<String, String> {
 
     // Syscall: console_read(buf_ptr, buf_len) -> i32
     // Read from console (non-blocking). Returns bytes read, 0 if no data.
+    // A pipeline stage reads its piped input instead: -1 once it has ended.
     linker
         .define(
             "env",
@@ -185,6 +493,38 @@ pub fn execute(wasm_bytes: &[u8], args: &[&str]) -> Result<String, String> {
                     if buf_len <= 0 {
                         return 0;
                     }
+                    // A CGI handler reads the request body: -1 once it has ended
+                    if let Some(cgi) = caller.data().cgi.as_ref() {
+                        let rest = &cgi.body[cgi.body_pos..];
+                        if rest.is_empty() {
+                            return -1;
+                        }
+                        let chunk = rest[..rest.len().min(buf_len as usize)].to_vec();
+                        let Some(mem) = caller.get_export("memory").and_then(|e| e.into_memory()) else {
+                            return -1;
+                        };
+                        if mem.write(&mut caller, buf_ptr as usize, &chunk).is_err() {
+                            return -1;
+                        }
+                        if let Some(cgi) = caller.data_mut().cgi.as_mut() {
+                            cgi.body_pos += chunk.len();
+                        }
+                        return chunk.len() as i32;
+                    }
+                    if let Some(pipe) = caller.data().stdin.clone() {
+                        let mut buf = vec![0u8; (buf_len as usize).min(4096)];
+                        let n = match pipe.read(&mut buf) {
+                            Ok(n) => n,
+                            Err(_) => return -1,
+                        };
+                        let Some(mem) = caller.get_export("memory").and_then(|e| e.into_memory()) else {
+                            return -1;
+                        };
+                        return match mem.write(&mut caller, buf_ptr as usize, &buf[..n]) {
+                            Ok(()) => n as i32,
+                            Err(_) => -1,
+                        };
+                    }
                     if let Some(mem) = caller.get_export("memory").and_then(|e| e.into_memory()) {
                         // Try to read one character (non-blocking)
                         if let Some(ch) = crate::uart::read_char_nonblocking() {
@@ -324,6 +664,7 @@ pub fn execute(wasm_bytes: &[u8], args: &[&str]) -> Result<String, String> {
                                         .write(&mut caller, buf_ptr as usize, &data[..to_copy])
                                         .is_ok()
                                     {
+                                        charge_syscall(Some(IoKind::FsRead), to_copy as u64);
                                         return to_copy as i32;
                                     }
                                 }
@@ -359,6 +700,7 @@ pub fn execute(wasm_bytes: &[u8], args: &[&str]) -> Result<String, String> {
                             if let Ok(path) = core::str::from_utf8(&path_buf) {
                                 // Use fs_proxy for multi-hart safety
                                 if crate::cpu::fs_proxy::fs_write(path, &data_buf).is_ok() {
+                                    charge_syscall(Some(IoKind::FsWrite), data_len as u64);
                                     return data_len;
                                 }
                             }
@@ -370,6 +712,40 @@ pub fn execute(wasm_bytes: &[u8], args: &[&str]) -> Result<String, String> {
         )
         .map_err(|e| format!("define fs_write: {:?}", e))?;
 
+    // Syscall: fs_append(path_ptr, path_len, data_ptr, data_len) -> i32
+    // Multi-hart safe: Uses fs_proxy for filesystem access.
+    linker
+        .define(
+            "env",
+            "fs_append",
+            Func::wrap(
+                &mut store,
+                |caller: Caller<'_, WasmContext>,
+                 path_ptr: i32,
+                 path_len: i32,
+                 data_ptr: i32,
+                 data_len: i32|
+                 -> i32 {
+                    if let Some(mem) = caller.get_export("memory").and_then(|e| e.into_memory()) {
+                        let mut path_buf = vec![0u8; path_len as usize];
+                        let mut data_buf = vec![0u8; data_len as usize];
+                        if mem.read(&caller, path_ptr as usize, &mut path_buf).is_ok()
+                            && mem.read(&caller, data_ptr as usize, &mut data_buf).is_ok()
+                        {
+                            if let Ok(path) = core::str::from_utf8(&path_buf) {
+                                // Only the tail blocks are written, not the whole file
+                                if crate::cpu::fs_proxy::fs_append(path, &data_buf).is_ok() {
+                                    return data_len;
+                                }
+                            }
+                        }
+                    }
+                    -1
+                },
+            ),
+        )
+        .map_err(|e| format!("define fs_append: {:?}", e))?;
+
     // Syscall: fs_list(buf_ptr, buf_len) -> i32
     // Multi-hart safe: Uses fs_proxy for filesystem access with VFS support.
     linker
@@ -550,9 +926,10 @@ pub fn execute(wasm_bytes: &[u8], args: &[&str]) -> Result<String, String> {
                 uart::write_line(
                     "\x1b[1;31m|\x1b[0m                                                                   \x1b[1;31m|\x1b[0m",
                 );
-                uart::write_line(
-                    "\x1b[1;31m|\x1b[0m                    \x1b[1;97mSystem Shutdown Initiated\x1b[0m                       \x1b[1;31m|\x1b[0m",
-                );
+                uart::write_line(&format!(
+                    "\x1b[1;31m|\x1b[0m\x1b[1;97m{}\x1b[0m\x1b[1;31m|\x1b[0m",
+                    locale::center(&locale::msg("shutdown.title"), 67)
+                ));
                 uart::write_line(
                     "\x1b[1;31m|\x1b[0m                                                                   \x1b[1;31m|\x1b[0m",
                 );
@@ -560,11 +937,11 @@ pub fn execute(wasm_bytes: &[u8], args: &[&str]) -> Result<String, String> {
                     "\x1b[1;31m+===================================================================+\x1b[0m",
                 );
                 uart::write_line("");
-                uart::write_line("    \x1b[0;90m[1/3]\x1b[0m Syncing filesystems...");
-                uart::write_line("    \x1b[0;90m[2/3]\x1b[0m Stopping network services...");
-                uart::write_line("    \x1b[0;90m[3/3]\x1b[0m Powering off CPU...");
+                uart::write_line(&format!("    \x1b[0;90m[1/3]\x1b[0m {}", locale::msg("shutdown.sync")));
+                uart::write_line(&format!("    \x1b[0;90m[2/3]\x1b[0m {}", locale::msg("shutdown.network")));
+                uart::write_line(&format!("    \x1b[0;90m[3/3]\x1b[0m {}", locale::msg("shutdown.poweroff")));
                 uart::write_line("");
-                uart::write_line("    \x1b[1;32m[OK] Goodbye!\x1b[0m");
+                uart::write_line(&format!("    \x1b[1;32m[OK] {}\x1b[0m", locale::msg("shutdown.goodbye")));
                 uart::write_line("");
                 unsafe {
                     ptr::write_volatile(TEST_FINISHER as *mut u32, 0x5555);
@@ -638,10 +1015,10 @@ pub fn execute(wasm_bytes: &[u8], args: &[&str]) -> Result<String, String> {
                         let mut path_buf = vec![0u8; path_len as usize];
                         if mem.read(&caller, path_ptr as usize, &mut path_buf).is_ok() {
                             if let Ok(path) = core::str::from_utf8(&path_buf) {
-                                let mut fs_guard = crate::FS_STATE.write();
-                                let mut blk_guard = BLK_DEV.write();
+                                let fs_guard = crate::FS_STATE.read();
+                                let blk_guard = BLK_DEV.read();
                                 if let (Some(fs), Some(dev)) =
-                                    (fs_guard.as_mut(), blk_guard.as_mut())
+                                    (fs_guard.as_ref(), blk_guard.as_ref())
                                 {
                                     // Check if file exists and get its size
                                     let file_data = fs.read_file(dev, path);
@@ -743,10 +1120,10 @@ pub fn execute(wasm_bytes: &[u8], args: &[&str]) -> Result<String, String> {
                         let mut path_buf = vec![0u8; path_len as usize];
                         if mem.read(&caller, path_ptr as usize, &mut path_buf).is_ok() {
                             if let Ok(path) = core::str::from_utf8(&path_buf) {
-                                let mut fs_guard = crate::FS_STATE.write();
-                                let mut blk_guard = BLK_DEV.write();
+                                let fs_guard = crate::FS_STATE.read();
+                                let blk_guard = BLK_DEV.read();
                                 if let (Some(fs), Some(dev)) =
-                                    (fs_guard.as_mut(), blk_guard.as_mut())
+                                    (fs_guard.as_ref(), blk_guard.as_ref())
                                 {
                                     // Create an empty .keep file as directory marker
                                     let keep_path = format!("{}/.keep", path.trim_end_matches('/'));
@@ -781,6 +1158,22 @@ pub fn execute(wasm_bytes: &[u8], args: &[&str]) -> Result<String, String> {
                         let mut key_buf = vec![0u8; key_len as usize];
                         if mem.read(&caller, key_ptr as usize, &mut key_buf).is_ok() {
                             if let Ok(key) = core::str::from_utf8(&key_buf) {
+                                // The request, for a CGI handler
+                                let cgi_value = caller
+                                    .data()
+                                    .cgi
+                                    .as_ref()
+                                    .and_then(|cgi| cgi.env.iter().find(|(name, _)| name == key))
+                                    .map(|(_, value)| value.clone());
+                                if let Some(value) = cgi_value {
+                                    let bytes = value.as_bytes();
+                                    if bytes.len() <= val_len as usize
+                                        && mem.write(&mut caller, val_ptr as usize, bytes).is_ok()
+                                    {
+                                        return bytes.len() as i32;
+                                    }
+                                    return -1;
+                                }
                                 // Built-in environment variables
                                 let value = match key {
                                     "HOME" => Some("/home"),
@@ -798,6 +1191,16 @@ pub fn execute(wasm_bytes: &[u8], args: &[&str]) -> Result<String, String> {
                                     "USER" => Some("root"),
                                     "SHELL" => Some("/usr/bin/sh"),
                                     "TERM" => Some("xterm-256color"),
+                                    "LANG" => {
+                                        let lang = crate::settings::lang();
+                                        let bytes = lang.as_bytes();
+                                        if bytes.len() <= val_len as usize {
+                                            if mem.write(&mut caller, val_ptr as usize, bytes).is_ok() {
+                                                return bytes.len() as i32;
+                                            }
+                                        }
+                                        return -1;
+                                    }
                                     _ => None,
                                 };
 
@@ -827,14 +1230,12 @@ pub fn execute(wasm_bytes: &[u8], args: &[&str]) -> Result<String, String> {
             Func::wrap(
                 &mut store,
                 |mut caller: Caller<'_, WasmContext>, buf_ptr: i32, buf_len: i32| -> i32 {
+                    if buf_len < 0 {
+                        return -1;
+                    }
                     if let Some(mem) = caller.get_export("memory").and_then(|e| e.into_memory()) {
-                        // Simple PRNG based on time
-                        let mut seed = crate::get_time_ms() as u64;
                         let mut random_bytes = vec![0u8; buf_len as usize];
-                        for byte in random_bytes.iter_mut() {
-                            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
-                            *byte = (seed >> 16) as u8;
-                        }
+                        crate::random::fill(&mut random_bytes);
                         if mem.write(&mut caller, buf_ptr as usize, &random_bytes).is_ok() {
                             return buf_len;
                         }
@@ -876,11 +1277,10 @@ pub fn execute(wasm_bytes: &[u8], args: &[&str]) -> Result<String, String> {
                         crate::ui::main_screen::handle_main_screen_input(event);
                     }
                     
-                    // Small delay to avoid burning CPU
-                    for _ in 0..100 {
-                        core::hint::spin_loop();
-                    }
+                    // Block until the next timer tick rather than burning CPU
+                    crate::lock::wait::sleep_until((crate::get_time_ms() + 1).min(target));
                 }
+                crate::scripting::shell_cmd_sleep((crate::get_time_ms() - start) as u64);
             }),
         )
         .map_err(|e| format!("define sleep_ms: {:?}", e))?;
@@ -1109,7 +1509,7 @@ pub fn execute(wasm_bytes: &[u8], args: &[&str]) -> Result<String, String> {
                         
                         let target = if target_hart <= 0 { None } else { Some(target_hart as usize) };
                         
-                        match crate::wasm_service::submit_job(wasm_bytes, args_vec, target) {
+                        match crate::wasm_service::submit_job(wasm_bytes, args_vec, target, None) {
                             Ok(job_id) => job_id as i32,
                             Err(_) => -1,
                         }
@@ -1122,7 +1522,8 @@ pub fn execute(wasm_bytes: &[u8], args: &[&str]) -> Result<String, String> {
         .map_err(|e| format!("define wasm_submit_job: {:?}", e))?;
 
     // Syscall: wasm_job_status(job_id) -> i32
-    // Returns job status: 0=pending, 1=running, 2=completed, 3=failed, -1=not found
+    // Returns job status: 0=pending, 1=running, 2=completed, 3=failed,
+    // 4=out of memory, -1=not found
     linker
         .define(
             "env",
@@ -1406,10 +1807,10 @@ pub fn execute(wasm_bytes: &[u8], args: &[&str]) -> Result<String, String> {
                         let mut path_buf = vec![0u8; path_len as usize];
                         if mem.read(&caller, path_ptr as usize, &mut path_buf).is_ok() {
                             if let Ok(path) = core::str::from_utf8(&path_buf) {
-                                let mut fs_guard = crate::FS_STATE.write();
-                                let mut blk_guard = BLK_DEV.write();
+                                let fs_guard = crate::FS_STATE.read();
+                                let blk_guard = BLK_DEV.read();
                                 if let (Some(fs), Some(dev)) =
-                                    (fs_guard.as_mut(), blk_guard.as_mut())
+                                    (fs_guard.as_ref(), blk_guard.as_ref())
                                 {
                                     if fs.remove(dev, path).is_ok() {
                                         return 0;
@@ -1437,10 +1838,10 @@ pub fn execute(wasm_bytes: &[u8], args: &[&str]) -> Result<String, String> {
                         let mut path_buf = vec![0u8; path_len as usize];
                         if mem.read(&caller, path_ptr as usize, &mut path_buf).is_ok() {
                             if let Ok(path) = core::str::from_utf8(&path_buf) {
-                                let mut fs_guard = crate::FS_STATE.write();
-                                let mut blk_guard = BLK_DEV.write();
+                                let fs_guard = crate::FS_STATE.read();
+                                let blk_guard = BLK_DEV.read();
                                 if let (Some(fs), Some(dev)) =
-                                    (fs_guard.as_mut(), blk_guard.as_mut())
+                                    (fs_guard.as_ref(), blk_guard.as_ref())
                                 {
                                     return if fs.is_dir(dev, path) { 1 } else { 0 };
                                 }
@@ -1721,7 +2122,8 @@ pub fn execute(wasm_bytes: &[u8], args: &[&str]) -> Result<String, String> {
 
     // Syscall: tcp_connect(ip_ptr, ip_len, port) -> i32
     // Connect to a TCP server. ip_ptr points to IP address bytes (4 bytes for IPv4).
-    // Returns 0 on success (connection initiated), -1 on error.
+    // Returns a socket handle (>= 0) once the connection is initiated, -1 on error.
+    // The program may hold several connections; they are closed when it exits.
     linker
         .define(
             "env",
@@ -1741,11 +2143,12 @@ pub fn execute(wasm_bytes: &[u8], args: &[&str]) -> Result<String, String> {
                                 let ip = smoltcp::wire::Ipv4Address::new(ip_buf[0], ip_buf[1], ip_buf[2], ip_buf[3]);
                                 let now = crate::get_time_ms();
                                 match net.tcp_connect(ip, port as u16, now) {
-                                    Ok(()) => {
+                                    Ok(sock) => {
+                                        net.tcp_set_owner(sock, crate::get_hart_id());
                                         // Poll to actually send the SYN packet
                                         net.poll(now);
                                         klog_info("telnet", "SYN sent");
-                                        return 0;
+                                        return sock as i32;
                                     }
                                     Err(e) => {
                                         klog_info("telnet", &alloc::format!("connect error: {}", e));
@@ -1760,7 +2163,7 @@ pub fn execute(wasm_bytes: &[u8], args: &[&str]) -> Result<String, String> {
         )
         .map_err(|e| format!("define tcp_connect: {:?}", e))?;
 
-    // Syscall: tcp_send(data_ptr, data_len) -> i32
+    // Syscall: tcp_send(sock, data_ptr, data_len) -> i32
     // Send data over TCP connection. Returns bytes sent or -1 on error.
     linker
         .define(
@@ -1768,17 +2171,21 @@ pub fn execute(wasm_bytes: &[u8], args: &[&str]) -> Result<String, String> {
             "tcp_send",
             Func::wrap(
                 &mut store,
-                |caller: Caller<'_, WasmContext>, data_ptr: i32, data_len: i32| -> i32 {
+                |caller: Caller<'_, WasmContext>, sock: i32, data_ptr: i32, data_len: i32| -> i32 {
                     if let Some(mem) = caller.get_export("memory").and_then(|e| e.into_memory()) {
                         let mut data_buf = vec![0u8; data_len as usize];
                         if mem.read(&caller, data_ptr as usize, &mut data_buf).is_ok() {
                             let mut net_guard = crate::NET_STATE.lock();
                             if let Some(ref mut net) = *net_guard {
+                                let Some(sock) = wasm_socket(net, sock) else {
+                                    return -1;
+                                };
                                 let now = crate::get_time_ms();
-                                match net.tcp_send(&data_buf, now) {
+                                match net.tcp_send(sock, &data_buf, now) {
                                     Ok(sent) => {
                                         // Poll network to actually transmit
                                         net.poll(now);
+                                        charge_syscall(Some(IoKind::NetTx), sent as u64);
                                         return sent as i32;
                                     }
                                     Err(_) => return -1,
@@ -1792,7 +2199,7 @@ pub fn execute(wasm_bytes: &[u8], args: &[&str]) -> Result<String, String> {
         )
         .map_err(|e| format!("define tcp_send: {:?}", e))?;
 
-    // Syscall: tcp_recv(buf_ptr, buf_len, timeout_ms) -> i32
+    // Syscall: tcp_recv(sock, buf_ptr, buf_len, timeout_ms) -> i32
     // Receive data from TCP connection. Returns bytes received, 0 if no data, -1 on error/closed.
     linker
         .define(
@@ -1800,10 +2207,13 @@ pub fn execute(wasm_bytes: &[u8], args: &[&str]) -> Result<String, String> {
             "tcp_recv",
             Func::wrap(
                 &mut store,
-                |mut caller: Caller<'_, WasmContext>, buf_ptr: i32, buf_len: i32, timeout_ms: i32| -> i32 {
+                |mut caller: Caller<'_, WasmContext>, sock: i32, buf_ptr: i32, buf_len: i32, timeout_ms: i32| -> i32 {
                     if let Some(mem) = caller.get_export("memory").and_then(|e| e.into_memory()) {
                         let mut net_guard = crate::NET_STATE.lock();
                         if let Some(ref mut net) = *net_guard {
+                            let Some(sock) = wasm_socket(net, sock) else {
+                                return -1;
+                            };
                             let start = crate::get_time_ms();
                             let deadline = if timeout_ms > 0 {
                                 start + timeout_ms as i64
@@ -1819,9 +2229,10 @@ pub fn execute(wasm_bytes: &[u8], args: &[&str]) -> Result<String, String> {
                                 // Poll network to process incoming packets and recycle TX buffers
                                 net.poll(now);
                                 
-                                match net.tcp_recv(&mut recv_buf, now) {
+                                match net.tcp_recv(sock, &mut recv_buf, now) {
                                     Ok(len) if len > 0 => {
                                         if mem.write(&mut caller, buf_ptr as usize, &recv_buf[..len]).is_ok() {
+                                            charge_syscall(Some(IoKind::NetRx), len as u64);
                                             return len as i32;
                                         }
                                         return -1;
@@ -1845,7 +2256,7 @@ pub fn execute(wasm_bytes: &[u8], args: &[&str]) -> Result<String, String> {
         )
         .map_err(|e| format!("define tcp_recv: {:?}", e))?;
 
-    // Syscall: tcp_close() -> i32
+    // Syscall: tcp_close(sock) -> i32
     // Close TCP connection. Returns 0 on success.
     linker
         .define(
@@ -1853,12 +2264,13 @@ pub fn execute(wasm_bytes: &[u8], args: &[&str]) -> Result<String, String> {
             "tcp_close",
             Func::wrap(
                 &mut store,
-                |_caller: Caller<'_, WasmContext>| -> i32 {
+                |_caller: Caller<'_, WasmContext>, sock: i32| -> i32 {
                     let mut net_guard = crate::NET_STATE.lock();
                     if let Some(ref mut net) = *net_guard {
-                        let now = crate::get_time_ms();
-                        net.tcp_close(now);
-                        return 0;
+                        if let Some(sock) = wasm_socket(net, sock) {
+                            net.tcp_disconnect(sock, crate::get_time_ms());
+                            return 0;
+                        }
                     }
                     -1
                 },
@@ -1866,7 +2278,7 @@ pub fn execute(wasm_bytes: &[u8], args: &[&str]) -> Result<String, String> {
         )
         .map_err(|e| format!("define tcp_close: {:?}", e))?;
 
-    // Syscall: tcp_status() -> i32
+    // Syscall: tcp_status(sock) -> i32
     // Get TCP connection status.
     // Returns: 0=closed, 1=connecting, 2=connected, 3=failed
     linker
@@ -1875,9 +2287,12 @@ pub fn execute(wasm_bytes: &[u8], args: &[&str]) -> Result<String, String> {
             "tcp_status",
             Func::wrap(
                 &mut store,
-                |_caller: Caller<'_, WasmContext>| -> i32 {
+                |_caller: Caller<'_, WasmContext>, sock: i32| -> i32 {
                     let mut net_guard = crate::NET_STATE.lock();
                     if let Some(ref mut net) = *net_guard {
+                        let Some(sock) = wasm_socket(net, sock) else {
+                            return 0; // Closed
+                        };
                         // Poll multiple times to ensure we catch pending handshake packets
                         // This is critical for TCP connections as SYN-ACK may arrive between polls
                         let now = crate::get_time_ms();
@@ -1886,7 +2301,7 @@ pub fn execute(wasm_bytes: &[u8], args: &[&str]) -> Result<String, String> {
                         }
                         
                         // Get the actual socket state for debugging
-                        let socket_state = net.tcp_client_state();
+                        let socket_state = net.tcp_state(sock);
                         
                         // Log socket state periodically (every ~500ms)
                         static mut LAST_LOG: i64 = 0;
@@ -1901,11 +2316,11 @@ pub fn execute(wasm_bytes: &[u8], args: &[&str]) -> Result<String, String> {
                             }
                         }
                         
-                        if net.tcp_is_connected() {
+                        if net.tcp_is_connected(sock) {
                             return 2; // Connected
-                        } else if net.tcp_is_connecting() {
+                        } else if net.tcp_is_connecting(sock) {
                             return 1; // Connecting
-                        } else if net.tcp_connection_failed() {
+                        } else if net.tcp_connection_failed(sock) {
                             return 3; // Failed
                         } else {
                             return 0; // Closed
@@ -1917,11 +2332,21 @@ pub fn execute(wasm_bytes: &[u8], args: &[&str]) -> Result<String, String> {
         )
         .map_err(|e| format!("define tcp_status: {:?}", e))?;
 
+    // Programs built for wasm32-wasi import these instead of "env"
+    crate::wasi::add_to_linker(&mut linker, &mut store)?;
+
     // Module already obtained from cache at the start of execute()
 
+    let limit = store.data().memory_limit;
     let instance = linker
         .instantiate(&mut store, &module)
-        .map_err(|e| format!("Instantiate: {:?}", e))?
+        .map_err(|e| {
+            if out_of_memory(&e) {
+                RunError::OutOfMemory(limit)
+            } else {
+                RunError::Other(format!("Instantiate: {:?}", e))
+            }
+        })?
         .ensure_no_start(&mut store)
         .map_err(|e| format!("Start: {:?}", e))?;
 
@@ -1929,8 +2354,52 @@ pub fn execute(wasm_bytes: &[u8], args: &[&str]) -> Result<String, String> {
         .get_typed_func::<(), ()>(&store, "_start")
         .map_err(|e| format!("Missing _start: {:?}", e))?;
 
-    run.call(&mut store, ())
-        .map_err(|e| format!("Runtime: {:?}", e))?;
+    // Run in slices of fuel: between them the hart gets to do other work,
+    // and the module is stopped if it was killed or used up its budget
+    let running = RunningModule::enter();
+    let budget = crate::settings::wasm_fuel_budget();
+    let mut used: u64 = 0;
+    store.set_fuel(FUEL_SLICE).map_err(|e| format!("Fuel: {:?}", e))?;
+    let mut call = run.call_resumable(&mut store, ());
+    let mut exhausted = false;
+    let result = loop {
+        match call {
+            Ok(TypedResumableCall::Finished(())) => break Ok(()),
+            Ok(TypedResumableCall::HostTrap(trap)) => break call_error(trap.into_host_error()),
+            Ok(TypedResumableCall::OutOfFuel(invocation)) => {
+                used += FUEL_SLICE;
+                if running.as_ref().is_some_and(RunningModule::aborted) {
+                    break Err(String::from("Killed"));
+                }
+                if let Some(budget) = budget.filter(|&budget| used >= budget) {
+                    break Err(format!("Fuel budget of {} instructions used up", budget));
+                }
+                cpu::sched::yield_now();
+                let _ = store.set_fuel(FUEL_SLICE.max(invocation.required_fuel()));
+                call = invocation.resume(&mut store);
+            }
+            Err(e) => {
+                exhausted = out_of_memory(&e);
+                break call_error(e);
+            }
+        }
+    };
+    drop(running);
+
+    // Close any TCP connections the program left open
+    if let Some(net) = crate::NET_STATE.lock().as_mut() {
+        net.tcp_close_owned(crate::get_hart_id(), crate::get_time_ms());
+    }
 
-    Ok(String::new())
+    // Save the files a WASI program left open
+    store.data_mut().wasi.close_all();
+
+    // A program whose memory could not grow any further fails as out of
+    // memory
+    if exhausted {
+        return Err(RunError::OutOfMemory(limit));
+    }
+
+    result?;
+    Ok(store.into_data())
 }
@@ -24,11 +24,38 @@
 //!
 //! ```ignore
 //! // Submit to specific hart
-//! wasm_service::submit_job(wasm_bytes, args, Some(1));
+//! wasm_service::submit_job(wasm_bytes, args, Some(1), None);
 //!
-//! // Auto-select least loaded hart
-//! wasm_service::submit_job(wasm_bytes, args, None);
+//! // Auto-select least loaded hart, with at most 4 MiB of linear memory
+//! wasm_service::submit_job(wasm_bytes, args, None, Some(4 * 1024 * 1024));
+//!
+//! // Stream each stage's output into the next one's input
+//! let pipeline = wasm_service::submit_pipeline(vec![(map, args), (filter, args)])?;
 //! ```
+//!
+//! ## Pipelines
+//!
+//! A pipeline is a chain of jobs joined by IPC pipes: what one stage prints
+//! becomes what the next reads from its console. Every stage gets its own
+//! worker hart so they run in parallel; a stage that prints faster than the
+//! next one reads waits for room in the pipe (backpressure). When a stage
+//! exits its output pipe is closed and the next stage sees end-of-input;
+//! when a stage exits without reading everything, the stage before it finds
+//! the pipe broken and its further output is dropped.
+//!
+//! ## Memory
+//!
+//! Each job's linear memory is capped (`wasm::DEFAULT_MEMORY_LIMIT` unless
+//! `submit_job` is given a limit), so a module that keeps growing it fails
+//! with `JobStatus::OutOfMemory` instead of exhausting the kernel heap.
+//!
+//! ## Workers
+//!
+//! Boot starts a `wasmworkerd-<hart>` daemon pinned to every secondary
+//! hart. Each run of it executes the jobs queued for its hart and returns,
+//! and it also lets init check on WASM services (`init::supervise_wasm_services`):
+//! a service is a job that runs as long as the daemon it implements, so it
+//! keeps its worker busy and later jobs go to the other workers.
 
 use alloc::collections::BTreeMap;
 use alloc::string::String;
@@ -37,8 +64,9 @@ use alloc::vec::Vec;
 use core::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
 
 use crate::cpu::MAX_HARTS;
-use crate::cpu::ipc::{Channel, ChannelId, Message, IPC};
+use crate::cpu::ipc::{Channel, ChannelId, Message, Pipe, IPC};
 use crate::Spinlock;
+use crate::wasm::RunError;
 use crate::services::klogd::{klog_debug, klog_error, klog_info};
 
 // ═══════════════════════════════════════════════════════════════════════════════
@@ -60,6 +88,8 @@ pub enum JobStatus {
     Completed = 2,
     /// Job failed with error
     Failed = 3,
+    /// Job's linear memory hit its limit
+    OutOfMemory = 4,
 }
 
 impl JobStatus {
@@ -69,6 +99,7 @@ impl JobStatus {
             JobStatus::Running => "running",
             JobStatus::Completed => "completed",
             JobStatus::Failed => "failed",
+            JobStatus::OutOfMemory => "out of memory",
         }
     }
 }
@@ -89,6 +120,14 @@ pub struct WasmJob {
     pub error: Spinlock<Option<String>>,
     /// Execution time in ms (when completed)
     pub exec_time_ms: AtomicU64,
+    /// Pipe read as console input (pipeline edge from the previous stage)
+    pub stdin: Option<Arc<Pipe>>,
+    /// Pipe printed output goes to (pipeline edge to the next stage)
+    pub stdout: Option<Arc<Pipe>>,
+    /// Cap on the module's linear memory (bytes)
+    pub memory_limit: usize,
+    /// Set by `cancel_job`: skip the job, or abort it if it is running
+    pub cancelled: AtomicBool,
 }
 
 impl WasmJob {
@@ -101,6 +140,10 @@ impl WasmJob {
             status: AtomicUsize::new(JobStatus::Pending as usize),
             error: Spinlock::new(None),
             exec_time_ms: AtomicU64::new(0),
+            stdin: None,
+            stdout: None,
+            memory_limit: crate::wasm::DEFAULT_MEMORY_LIMIT,
+            cancelled: AtomicBool::new(false),
         }
     }
 
@@ -109,6 +152,7 @@ impl WasmJob {
             0 => JobStatus::Pending,
             1 => JobStatus::Running,
             2 => JobStatus::Completed,
+            4 => JobStatus::OutOfMemory,
             _ => JobStatus::Failed,
         }
     }
@@ -127,6 +171,14 @@ impl WasmJob {
     }
 }
 
+/// A submitted pipeline
+pub struct Pipeline {
+    /// Stage job IDs, first stage first
+    pub jobs: Vec<JobId>,
+    /// Output of the last stage; the submitter reads it and removes it when done
+    pub output: Arc<Pipe>,
+}
+
 // ═══════════════════════════════════════════════════════════════════════════════
 // WORKER STATE
 // ═══════════════════════════════════════════════════════════════════════════════
@@ -247,6 +299,8 @@ impl WasmService {
     /// * `wasm_bytes` - The WASM binary
     /// * `args` - Command-line arguments
     /// * `target_hart` - Specific hart to run on, or None for auto-selection
+    /// * `memory_limit` - Cap on the linear memory in bytes, or None for
+    ///   `wasm::DEFAULT_MEMORY_LIMIT`
     ///
     /// # Returns
     /// Job ID on success
@@ -255,6 +309,7 @@ impl WasmService {
         wasm_bytes: Vec<u8>,
         args: Vec<String>,
         target_hart: Option<usize>,
+        memory_limit: Option<usize>,
     ) -> Result<JobId, &'static str> {
         if !self.initialized.load(Ordering::Acquire) {
             return Err("WASM service not initialized");
@@ -270,13 +325,78 @@ impl WasmService {
             None => self.find_least_loaded_worker()?,
         };
 
-        // Create job
-        let job = Arc::new(WasmJob::new(job_id, wasm_bytes, args, Some(hart)));
-        self.jobs.lock().insert(job_id, job.clone());
+        let mut job = WasmJob::new(job_id, wasm_bytes, args, Some(hart));
+        if let Some(limit) = memory_limit {
+            job.memory_limit = limit;
+        }
+        self.dispatch(job, hart)?;
+        Ok(job_id)
+    }
+
+    /// Submit a pipeline: each stage's printed output is streamed into the
+    /// console input of the next stage
+    ///
+    /// # Arguments
+    /// * `stages` - WASM binary and arguments of each stage, in order
+    ///
+    /// # Returns
+    /// The stage job IDs and the pipe carrying the last stage's output
+    pub fn submit_pipeline(&self, stages: Vec<(Vec<u8>, Vec<String>)>) -> Result<Pipeline, &'static str> {
+        if !self.initialized.load(Ordering::Acquire) {
+            return Err("WASM service not initialized");
+        }
+        if stages.is_empty() {
+            return Err("Empty pipeline");
+        }
+
+        // A stage waiting on a full pipe must never hold up the stage that
+        // drains it, so each stage gets a hart of its own
+        let harts = self.least_loaded_workers(stages.len())?;
+
+        let output = IPC.create_pipe();
+        let mut jobs = Vec::with_capacity(stages.len());
+        let mut stdin: Option<Arc<Pipe>> = None;
+        let last = stages.len() - 1;
+
+        for (i, ((wasm_bytes, args), hart)) in stages.into_iter().zip(harts).enumerate() {
+            let job_id = self.next_job_id.fetch_add(1, Ordering::SeqCst);
+            let stdout = if i == last { output.clone() } else { IPC.create_pipe() };
+
+            let upstream = stdin.take();
+            let mut job = WasmJob::new(job_id, wasm_bytes, args, Some(hart));
+            job.stdin = upstream.clone();
+            job.stdout = Some(stdout.clone());
+            if let Err(e) = self.dispatch(job, hart) {
+                // Stages already submitted find their output pipe broken and finish
+                if let Some(pipe) = upstream {
+                    IPC.remove_pipe(pipe.id);
+                }
+                IPC.remove_pipe(stdout.id);
+                IPC.remove_pipe(output.id);
+                return Err(e);
+            }
+
+            jobs.push(job_id);
+            stdin = Some(stdout);
+        }
+
+        klog_debug(
+            "wasm-svc",
+            &alloc::format!("Submitted pipeline {:?} (output pipe {})", jobs, output.id),
+        );
+
+        Ok(Pipeline { jobs, output })
+    }
+
+    /// Register a job and notify the worker on `hart`
+    fn dispatch(&self, job: WasmJob, hart: usize) -> Result<(), &'static str> {
+        let job_id = job.id;
+        let job = Arc::new(job);
+        self.jobs.lock().insert(job_id, job);
 
         // Send job notification to worker via IPC
         let channel = self.get_channel(hart).ok_or("Worker channel not found")?;
-        
+
         // Message contains job ID as bytes
         let msg = Message::new(
             0, // sender PID (0 = kernel)
@@ -294,7 +414,21 @@ impl WasmService {
             &alloc::format!("Submitted job {} to hart {}", job_id, hart),
         );
 
-        Ok(job_id)
+        Ok(())
+    }
+
+    /// Pick `count` distinct workers, least loaded first
+    fn least_loaded_workers(&self, count: usize) -> Result<Vec<usize>, &'static str> {
+        let num_workers = self.num_workers.load(Ordering::Relaxed);
+        let mut harts: Vec<usize> = (1..=num_workers)
+            .filter(|&h| self.workers[h].active.load(Ordering::Relaxed))
+            .collect();
+        if harts.len() < count {
+            return Err("Pipeline has more stages than WASM workers");
+        }
+        harts.sort_by_key(|&h| self.workers[h].load_score());
+        harts.truncate(count);
+        Ok(harts)
     }
 
     /// Find the worker with the lowest load
@@ -327,6 +461,20 @@ impl WasmService {
         self.jobs.lock().get(&job_id).cloned()
     }
 
+    /// Cancel a job: a pending one is skipped, a running one aborted.
+    /// Returns false if the job is unknown or already finished.
+    pub fn cancel_job(&self, job_id: JobId) -> bool {
+        let Some(job) = self.get_job(job_id) else {
+            return false;
+        };
+        job.cancelled.store(true, Ordering::SeqCst);
+        match job.get_status() {
+            JobStatus::Pending => true,
+            JobStatus::Running => job.target_hart.is_some_and(crate::wasm::abort_on_hart),
+            _ => false,
+        }
+    }
+
     /// Get worker stats for a hart
     pub fn get_worker_stats(&self, hart_id: usize) -> Option<&WorkerStats> {
         if hart_id < MAX_HARTS && self.workers[hart_id].active.load(Ordering::Relaxed) {
@@ -385,7 +533,7 @@ impl WasmService {
         // Find jobs to remove (completed/failed, oldest first)
         let mut to_remove: Vec<JobId> = jobs
             .iter()
-            .filter(|(_, j)| matches!(j.get_status(), JobStatus::Completed | JobStatus::Failed))
+            .filter(|(_, j)| matches!(j.get_status(), JobStatus::Completed | JobStatus::Failed | JobStatus::OutOfMemory))
             .map(|(id, _)| *id)
             .collect();
 
@@ -406,15 +554,11 @@ impl WasmService {
 
 /// Entry point for WASM worker running on a secondary hart
 ///
-/// This function runs in a loop, polling the IPC channel for job notifications
-/// and executing WASM binaries as they arrive.
+/// Executes the jobs whose notifications are waiting on this hart's IPC
+/// channel, then returns; the worker is a daemon, so the scheduler runs it
+/// again.
 pub fn worker_entry() {
     let hart_id = crate::get_hart_id();
-    
-    klog_info(
-        "wasm-svc",
-        &alloc::format!("WASM worker starting on hart {}", hart_id),
-    );
 
     // Get our channel
     let channel = match WASM_SERVICE.get_channel(hart_id) {
@@ -428,43 +572,41 @@ pub fn worker_entry() {
         }
     };
 
-    loop {
-        // Poll for job notifications
-        if let Some(msg) = channel.try_recv() {
-            if msg.msg_type == 1 && msg.data.len() >= 4 {
-                // Extract job ID
-                let job_id = u32::from_le_bytes([
-                    msg.data[0],
-                    msg.data[1],
-                    msg.data[2],
-                    msg.data[3],
-                ]);
-
-                // Decrement queue depth
-                WASM_SERVICE.workers[hart_id].queue_depth.fetch_sub(1, Ordering::Relaxed);
-
-                // Get the job
-                if let Some(job) = WASM_SERVICE.get_job(job_id) {
-                    execute_job(hart_id, &job);
-                }
-            }
-        } else {
-            // No job available, yield CPU
-            core::hint::spin_loop();
-            // Small delay to avoid burning CPU
-            for _ in 0..1000 {
-                core::hint::spin_loop();
+    // Run the jobs that are waiting
+    while let Some(msg) = channel.try_recv() {
+        if msg.msg_type == 1 && msg.data.len() >= 4 {
+            // Extract job ID
+            let job_id = u32::from_le_bytes([
+                msg.data[0],
+                msg.data[1],
+                msg.data[2],
+                msg.data[3],
+            ]);
+
+            // Decrement queue depth
+            WASM_SERVICE.workers[hart_id].queue_depth.fetch_sub(1, Ordering::Relaxed);
+
+            // Get the job
+            if let Some(job) = WASM_SERVICE.get_job(job_id) {
+                execute_job(hart_id, &job);
             }
         }
     }
+
+    // Restart WASM services whose job has ended
+    crate::init::supervise_wasm_services();
 }
 
 /// Execute a WASM job
 fn execute_job(hart_id: usize, job: &WasmJob) {
     let stats = &WASM_SERVICE.workers[hart_id];
     
-    // Mark job as running
+    // Mark job as running; `cancel_job` aborts it from here on
     job.set_status(JobStatus::Running);
+    if job.cancelled.load(Ordering::SeqCst) {
+        job.set_error(String::from("Cancelled"));
+        return;
+    }
     stats.current_job.store(job.id, Ordering::Release);
 
     klog_debug(
@@ -478,7 +620,24 @@ fn execute_job(hart_id: usize, job: &WasmJob) {
     let args: Vec<&str> = job.args.iter().map(|s| s.as_str()).collect();
 
     // Execute the WASM binary
-    match crate::wasm::execute(&job.wasm_bytes, &args) {
+    let result = crate::wasm::execute_piped(
+        &job.wasm_bytes,
+        &args,
+        job.stdin.clone(),
+        job.stdout.clone(),
+        job.memory_limit,
+    );
+
+    // Close the pipeline edges: the next stage sees end-of-input, the
+    // previous one a broken pipe if it is still printing
+    if let Some(stdout) = &job.stdout {
+        stdout.close_write();
+    }
+    if let Some(stdin) = &job.stdin {
+        IPC.remove_pipe(stdin.id);
+    }
+
+    match result {
         Ok(_) => {
             let exec_time = (crate::get_time_ms() - start_time) as u64;
             job.exec_time_ms.store(exec_time, Ordering::Relaxed);
@@ -493,7 +652,10 @@ fn execute_job(hart_id: usize, job: &WasmJob) {
             );
         }
         Err(e) => {
-            job.set_error(e);
+            job.set_error(alloc::format!("{}", e));
+            if let RunError::OutOfMemory(_) = e {
+                job.set_status(JobStatus::OutOfMemory);
+            }
             stats.jobs_failed.fetch_add(1, Ordering::Relaxed);
 
             klog_error(
@@ -521,12 +683,22 @@ pub fn init(num_harts: usize) {
 /// * `wasm_bytes` - The WASM binary
 /// * `args` - Command-line arguments  
 /// * `target_hart` - Specific hart (1+), or None for auto-selection
+/// * `memory_limit` - Linear memory cap in bytes, or None for the default
 pub fn submit_job(
     wasm_bytes: Vec<u8>,
     args: Vec<String>,
     target_hart: Option<usize>,
+    memory_limit: Option<usize>,
 ) -> Result<JobId, &'static str> {
-    WASM_SERVICE.submit_job(wasm_bytes, args, target_hart)
+    WASM_SERVICE.submit_job(wasm_bytes, args, target_hart, memory_limit)
+}
+
+/// Submit a pipeline of WASM jobs, one worker hart per stage
+///
+/// # Arguments
+/// * `stages` - WASM binary and arguments of each stage, in order
+pub fn submit_pipeline(stages: Vec<(Vec<u8>, Vec<String>)>) -> Result<Pipeline, &'static str> {
+    WASM_SERVICE.submit_pipeline(stages)
 }
 
 /// Get job status
@@ -534,6 +706,11 @@ pub fn job_status(job_id: JobId) -> Option<JobStatus> {
     WASM_SERVICE.get_job(job_id).map(|j| j.get_status())
 }
 
+/// Cancel a pending or running job
+pub fn cancel_job(job_id: JobId) -> bool {
+    WASM_SERVICE.cancel_job(job_id)
+}
+
 /// List all workers with their stats
 pub fn list_workers() -> Vec<(usize, u64, u64, u64, u32, usize)> {
     WASM_SERVICE.list_workers()
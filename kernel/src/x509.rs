@@ -0,0 +1,552 @@
+//! X.509 certificates and the root CA store.
+//!
+//! The TLS 1.2 client hands the chain a server sends to `verify_chain`, which
+//! checks the leaf against the host name, checks validity dates (when the RTC
+//! gives a wall-clock time) and follows issuer links until a certificate is
+//! signed by a trust anchor. The leaf key it returns then verifies the
+//! ServerKeyExchange signature.
+//!
+//! Trust anchors are the CERTIFICATE blocks of `/etc/ssl/certs.pem`. Hart 0
+//! loads the bundle at boot and reloads it when the file changes, so the TLS
+//! code never touches the filesystem while it holds the network lock.
+//!
+//! Supported signatures: RSA PKCS#1 v1.5 and ECDSA on P-256/P-384, with
+//! SHA-256, SHA-384 or SHA-512. Names are compared as raw DER, and name
+//! constraints and revocation are not checked.
+
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use p256::ecdsa::signature::hazmat::PrehashVerifier;
+use rsa::{BigUint, Pkcs1v15Sign, RsaPublicKey};
+use sha2::{Digest, Sha256, Sha384, Sha512};
+
+use crate::fs::watch::{PathWatch, WATCH_ALL};
+use crate::tls_server::{der_next, pem_blocks};
+use crate::Spinlock;
+
+/// Root CA bundle (PEM, any number of CERTIFICATE blocks)
+pub const CA_BUNDLE_PATH: &str = "/etc/ssl/certs.pem";
+
+/// Certificates followed from the leaf before giving up
+const MAX_CHAIN_DEPTH: usize = 8;
+
+const DER_BOOLEAN: u8 = 0x01;
+const DER_INTEGER: u8 = 0x02;
+const DER_BIT_STRING: u8 = 0x03;
+const DER_OCTET_STRING: u8 = 0x04;
+const DER_OID: u8 = 0x06;
+const DER_UTC_TIME: u8 = 0x17;
+const DER_GENERALIZED_TIME: u8 = 0x18;
+const DER_SEQUENCE: u8 = 0x30;
+const DER_SET: u8 = 0x31;
+/// [0] EXPLICIT version
+const DER_VERSION: u8 = 0xa0;
+/// [3] EXPLICIT extensions
+const DER_EXTENSIONS: u8 = 0xa3;
+/// subjectAltName dNSName [2] IMPLICIT IA5String
+const SAN_DNS_NAME: u8 = 0x82;
+/// subjectAltName iPAddress [7] IMPLICIT OCTET STRING
+const SAN_IP_ADDRESS: u8 = 0x87;
+
+const OID_RSA_ENCRYPTION: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01];
+const OID_SHA256_WITH_RSA: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0b];
+const OID_SHA384_WITH_RSA: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0c];
+const OID_SHA512_WITH_RSA: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0d];
+const OID_EC_PUBLIC_KEY: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01];
+const OID_ECDSA_WITH_SHA256: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x02];
+const OID_ECDSA_WITH_SHA384: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x03];
+const OID_ECDSA_WITH_SHA512: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x04];
+const OID_PRIME256V1: &[u8] = &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07];
+const OID_SECP384R1: &[u8] = &[0x2b, 0x81, 0x04, 0x00, 0x22];
+const OID_COMMON_NAME: &[u8] = &[0x55, 0x04, 0x03];
+const OID_SUBJECT_ALT_NAME: &[u8] = &[0x55, 0x1d, 0x11];
+const OID_BASIC_CONSTRAINTS: &[u8] = &[0x55, 0x1d, 0x13];
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// KEYS AND SIGNATURES
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Signature scheme of a certificate or a TLS handshake signature
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SignatureAlgorithm {
+    RsaSha256,
+    RsaSha384,
+    RsaSha512,
+    EcdsaSha256,
+    EcdsaSha384,
+    EcdsaSha512,
+}
+
+impl SignatureAlgorithm {
+    fn from_oid(oid: &[u8]) -> Option<Self> {
+        Some(match oid {
+            OID_SHA256_WITH_RSA => Self::RsaSha256,
+            OID_SHA384_WITH_RSA => Self::RsaSha384,
+            OID_SHA512_WITH_RSA => Self::RsaSha512,
+            OID_ECDSA_WITH_SHA256 => Self::EcdsaSha256,
+            OID_ECDSA_WITH_SHA384 => Self::EcdsaSha384,
+            OID_ECDSA_WITH_SHA512 => Self::EcdsaSha512,
+            _ => return None,
+        })
+    }
+
+    /// From a TLS 1.2 SignatureAndHashAlgorithm (hash, signature) pair
+    pub fn from_tls(hash: u8, signature: u8) -> Option<Self> {
+        Some(match (hash, signature) {
+            (4, 1) => Self::RsaSha256,
+            (5, 1) => Self::RsaSha384,
+            (6, 1) => Self::RsaSha512,
+            (4, 3) => Self::EcdsaSha256,
+            (5, 3) => Self::EcdsaSha384,
+            (6, 3) => Self::EcdsaSha512,
+            _ => return None,
+        })
+    }
+
+    fn digest(self, message: &[u8]) -> Vec<u8> {
+        match self {
+            Self::RsaSha256 | Self::EcdsaSha256 => Sha256::digest(message).to_vec(),
+            Self::RsaSha384 | Self::EcdsaSha384 => Sha384::digest(message).to_vec(),
+            Self::RsaSha512 | Self::EcdsaSha512 => Sha512::digest(message).to_vec(),
+        }
+    }
+}
+
+/// Subject public key of a certificate
+#[derive(Clone)]
+pub enum PublicKey {
+    Rsa(RsaPublicKey),
+    P256(p256::ecdsa::VerifyingKey),
+    P384(p384::ecdsa::VerifyingKey),
+}
+
+impl PublicKey {
+    /// Parse a SubjectPublicKeyInfo's algorithm and key bits
+    fn from_spki(algorithm: &[u8], key: &[u8]) -> Option<Self> {
+        let (tag, oid, params) = der_next(algorithm)?;
+        if tag != DER_OID {
+            return None;
+        }
+        match oid {
+            OID_RSA_ENCRYPTION => {
+                // RSAPublicKey ::= SEQUENCE { modulus INTEGER, publicExponent INTEGER }
+                let (tag, body, _) = der_next(key)?;
+                if tag != DER_SEQUENCE {
+                    return None;
+                }
+                let (tag, n, rest) = der_next(body)?;
+                let (tag_e, e, _) = der_next(rest)?;
+                if tag != DER_INTEGER || tag_e != DER_INTEGER {
+                    return None;
+                }
+                let key = RsaPublicKey::new(BigUint::from_bytes_be(n), BigUint::from_bytes_be(e)).ok()?;
+                Some(Self::Rsa(key))
+            }
+            OID_EC_PUBLIC_KEY => {
+                let (tag, curve, _) = der_next(params)?;
+                if tag != DER_OID {
+                    return None;
+                }
+                match curve {
+                    OID_PRIME256V1 => p256::ecdsa::VerifyingKey::from_sec1_bytes(key).ok().map(Self::P256),
+                    OID_SECP384R1 => p384::ecdsa::VerifyingKey::from_sec1_bytes(key).ok().map(Self::P384),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Check `signature` over `message`. ECDSA signatures are DER, as in
+    /// both certificates and TLS 1.2.
+    pub fn verify(&self, algorithm: SignatureAlgorithm, message: &[u8], signature: &[u8]) -> bool {
+        let digest = algorithm.digest(message);
+        match (self, algorithm) {
+            (Self::Rsa(key), SignatureAlgorithm::RsaSha256) => {
+                key.verify(Pkcs1v15Sign::new::<Sha256>(), &digest, signature).is_ok()
+            }
+            (Self::Rsa(key), SignatureAlgorithm::RsaSha384) => {
+                key.verify(Pkcs1v15Sign::new::<Sha384>(), &digest, signature).is_ok()
+            }
+            (Self::Rsa(key), SignatureAlgorithm::RsaSha512) => {
+                key.verify(Pkcs1v15Sign::new::<Sha512>(), &digest, signature).is_ok()
+            }
+            (Self::Rsa(_), _) => false,
+            (_, SignatureAlgorithm::RsaSha256 | SignatureAlgorithm::RsaSha384 | SignatureAlgorithm::RsaSha512) => {
+                false
+            }
+            (Self::P256(key), _) => ecdsa_raw(signature, 32)
+                .and_then(|raw| p256::ecdsa::Signature::from_slice(&raw).ok())
+                .is_some_and(|sig| key.verify_prehash(&digest, &sig).is_ok()),
+            (Self::P384(key), _) => ecdsa_raw(signature, 48)
+                .and_then(|raw| p384::ecdsa::Signature::from_slice(&raw).ok())
+                .is_some_and(|sig| key.verify_prehash(&digest, &sig).is_ok()),
+        }
+    }
+}
+
+/// r || s, each left-padded to `size`, from a DER ECDSA-Sig-Value
+fn ecdsa_raw(der: &[u8], size: usize) -> Option<Vec<u8>> {
+    let (tag, body, _) = der_next(der)?;
+    if tag != DER_SEQUENCE {
+        return None;
+    }
+    let mut raw = Vec::with_capacity(size * 2);
+    let mut rest = body;
+    for _ in 0..2 {
+        let (tag, int, next) = der_next(rest)?;
+        if tag != DER_INTEGER {
+            return None;
+        }
+        let start = int.iter().position(|&b| b != 0).unwrap_or(int.len());
+        let int = &int[start..];
+        if int.len() > size {
+            return None;
+        }
+        raw.resize(raw.len() + size - int.len(), 0);
+        raw.extend_from_slice(int);
+        rest = next;
+    }
+    Some(raw)
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// CERTIFICATES
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// The parts of an X.509 certificate that validation needs
+#[derive(Clone)]
+pub struct Certificate {
+    der: Vec<u8>,
+    /// TBSCertificate, header included (what the issuer signed)
+    tbs: Vec<u8>,
+    signature_algorithm: Option<SignatureAlgorithm>,
+    signature: Vec<u8>,
+    /// Raw DER Names
+    issuer: Vec<u8>,
+    subject: Vec<u8>,
+    /// Validity, in Unix seconds
+    not_before: u64,
+    not_after: u64,
+    key: Option<PublicKey>,
+    is_ca: bool,
+    dns_names: Vec<String>,
+    ip_addresses: Vec<[u8; 4]>,
+    common_name: Option<String>,
+}
+
+impl Certificate {
+    /// Parse a DER certificate. Unknown key or signature algorithms are
+    /// kept as None, so a bundle holding such roots still loads.
+    pub fn parse(der: &[u8]) -> Option<Self> {
+        let (tag, body, _) = der_next(der)?;
+        if tag != DER_SEQUENCE {
+            return None;
+        }
+        let (tag, tbs_body, rest) = der_next(body)?;
+        if tag != DER_SEQUENCE {
+            return None;
+        }
+        let tbs = &body[..body.len() - rest.len()];
+        let (tag, algorithm, rest) = der_next(rest)?;
+        if tag != DER_SEQUENCE {
+            return None;
+        }
+        let (tag, signature, _) = der_next(rest)?;
+        if tag != DER_BIT_STRING || signature.first() != Some(&0) {
+            return None;
+        }
+        let (_, oid, _) = der_next(algorithm)?;
+
+        let mut cert = Certificate {
+            der: der.to_vec(),
+            tbs: tbs.to_vec(),
+            signature_algorithm: SignatureAlgorithm::from_oid(oid),
+            signature: signature[1..].to_vec(),
+            issuer: Vec::new(),
+            subject: Vec::new(),
+            not_before: 0,
+            not_after: 0,
+            key: None,
+            is_ca: false,
+            dns_names: Vec::new(),
+            ip_addresses: Vec::new(),
+            common_name: None,
+        };
+        cert.parse_tbs(tbs_body)?;
+        Some(cert)
+    }
+
+    fn parse_tbs(&mut self, tbs: &[u8]) -> Option<()> {
+        let mut rest = tbs;
+        if rest.first() == Some(&DER_VERSION) {
+            rest = der_next(rest)?.2;
+        }
+        let (_, _serial, r) = der_next(rest)?;
+        let (_, _signature, r) = der_next(r)?;
+
+        let (tag, _, after_issuer) = der_next(r)?;
+        if tag != DER_SEQUENCE {
+            return None;
+        }
+        self.issuer = r[..r.len() - after_issuer.len()].to_vec();
+
+        let (tag, validity, r) = der_next(after_issuer)?;
+        if tag != DER_SEQUENCE {
+            return None;
+        }
+        let (tag, not_before, v) = der_next(validity)?;
+        self.not_before = parse_time(tag, not_before)?;
+        let (tag, not_after, _) = der_next(v)?;
+        self.not_after = parse_time(tag, not_after)?;
+
+        let (tag, subject, after_subject) = der_next(r)?;
+        if tag != DER_SEQUENCE {
+            return None;
+        }
+        self.subject = r[..r.len() - after_subject.len()].to_vec();
+        self.common_name = common_name(subject);
+
+        let (tag, spki, mut r) = der_next(after_subject)?;
+        if tag != DER_SEQUENCE {
+            return None;
+        }
+        let (_, algorithm, s) = der_next(spki)?;
+        let (tag, key, _) = der_next(s)?;
+        if tag == DER_BIT_STRING && key.first() == Some(&0) {
+            self.key = PublicKey::from_spki(algorithm, &key[1..]);
+        }
+
+        // issuerUniqueID / subjectUniqueID, then extensions
+        while let Some((tag, body, next)) = der_next(r) {
+            if tag == DER_EXTENSIONS {
+                self.parse_extensions(body)?;
+            }
+            r = next;
+        }
+        Some(())
+    }
+
+    fn parse_extensions(&mut self, body: &[u8]) -> Option<()> {
+        let (tag, mut list, _) = der_next(body)?;
+        if tag != DER_SEQUENCE {
+            return None;
+        }
+        while let Some((_, extension, next)) = der_next(list) {
+            list = next;
+            let (tag, oid, mut r) = der_next(extension)?;
+            if tag != DER_OID {
+                return None;
+            }
+            if r.first() == Some(&DER_BOOLEAN) {
+                r = der_next(r)?.2;
+            }
+            let (tag, value, _) = der_next(r)?;
+            if tag != DER_OCTET_STRING {
+                return None;
+            }
+            match oid {
+                OID_BASIC_CONSTRAINTS => {
+                    // SEQUENCE { cA BOOLEAN DEFAULT FALSE, pathLenConstraint INTEGER OPTIONAL }
+                    let (_, constraints, _) = der_next(value)?;
+                    if let Some((DER_BOOLEAN, ca, _)) = der_next(constraints) {
+                        self.is_ca = ca.first().is_some_and(|&b| b != 0);
+                    }
+                }
+                OID_SUBJECT_ALT_NAME => {
+                    let (_, mut names, _) = der_next(value)?;
+                    while let Some((tag, name, next)) = der_next(names) {
+                        names = next;
+                        match tag {
+                            SAN_DNS_NAME => {
+                                if let Ok(name) = core::str::from_utf8(name) {
+                                    self.dns_names.push(name.to_ascii_lowercase());
+                                }
+                            }
+                            SAN_IP_ADDRESS => {
+                                if let Ok(ip) = <[u8; 4]>::try_from(name) {
+                                    self.ip_addresses.push(ip);
+                                }
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        Some(())
+    }
+
+    /// Whether the certificate names `host` (subjectAltName, or the common
+    /// name when there is no dNSName)
+    fn matches_host(&self, host: &str) -> bool {
+        if let Some(ip) = crate::net::parse_ipv4(host.as_bytes()) {
+            return self.ip_addresses.contains(&ip.octets());
+        }
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+        if self.dns_names.is_empty() {
+            return self
+                .common_name
+                .as_deref()
+                .is_some_and(|cn| name_matches(&cn.to_ascii_lowercase(), &host));
+        }
+        self.dns_names.iter().any(|name| name_matches(name, &host))
+    }
+
+    fn valid_at(&self, now: u64) -> bool {
+        self.not_before <= now && now <= self.not_after
+    }
+
+    /// Whether `issuer` signed this certificate
+    fn issued_by(&self, issuer: &Certificate) -> bool {
+        if self.issuer != issuer.subject {
+            return false;
+        }
+        match (&issuer.key, self.signature_algorithm) {
+            (Some(key), Some(algorithm)) => key.verify(algorithm, &self.tbs, &self.signature),
+            _ => false,
+        }
+    }
+}
+
+/// DNS name match, with a leading "*." standing for exactly one label
+fn name_matches(pattern: &str, host: &str) -> bool {
+    let pattern = pattern.trim_end_matches('.');
+    match pattern.strip_prefix("*.") {
+        Some(suffix) => host
+            .split_once('.')
+            .is_some_and(|(label, rest)| !label.is_empty() && rest == suffix),
+        None => pattern == host,
+    }
+}
+
+/// Common name from a Name (SEQUENCE OF SET OF AttributeTypeAndValue)
+fn common_name(name: &[u8]) -> Option<String> {
+    let mut rdns = name;
+    while let Some((tag, rdn, next)) = der_next(rdns) {
+        rdns = next;
+        if tag != DER_SET {
+            continue;
+        }
+        let (_, attribute, _) = der_next(rdn)?;
+        let (tag, oid, rest) = der_next(attribute)?;
+        if tag == DER_OID && oid == OID_COMMON_NAME {
+            let (_, value, _) = der_next(rest)?;
+            return core::str::from_utf8(value).ok().map(String::from);
+        }
+    }
+    None
+}
+
+/// UTCTime (YYMMDDHHMMSSZ) or GeneralizedTime (YYYYMMDDHHMMSSZ) to Unix seconds
+fn parse_time(tag: u8, value: &[u8]) -> Option<u64> {
+    let digits = |s: &[u8]| -> Option<u64> {
+        s.iter().try_fold(0u64, |acc, &b| b.is_ascii_digit().then(|| acc * 10 + (b - b'0') as u64))
+    };
+    let (year, rest) = match tag {
+        DER_UTC_TIME if value.len() >= 12 => {
+            let yy = digits(&value[..2])?;
+            (if yy >= 50 { 1900 + yy } else { 2000 + yy }, &value[2..])
+        }
+        DER_GENERALIZED_TIME if value.len() >= 14 => (digits(&value[..4])?, &value[4..]),
+        _ => return None,
+    };
+    let month = digits(&rest[..2])?;
+    let day = digits(&rest[2..4])?;
+    let hour = digits(&rest[4..6])?;
+    let minute = digits(&rest[6..8])?;
+    let second = digits(&rest[8..10])?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || year < 1970 {
+        return None;
+    }
+
+    // Days since 1970-01-01 (civil-from-days, inverted)
+    let (y, m) = if month <= 2 { (year - 1, month + 9) } else { (year, month - 3) };
+    let era = y / 400;
+    let yoe = y - era * 400;
+    let doy = (153 * m + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146097 + doe - 719468;
+
+    Some(days * 86400 + hour * 3600 + minute * 60 + second)
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// CHAIN VALIDATION
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Validate a server's chain (DER, leaf first) for `host` against the trust
+/// store, returning the leaf's public key
+pub fn verify_chain(chain: &[Vec<u8>], host: &str) -> Result<PublicKey, &'static str> {
+    let anchors = trust_anchors();
+    if anchors.is_empty() {
+        return Err("no trusted root CAs (is /etc/ssl/certs.pem installed?)");
+    }
+
+    let certs = chain
+        .iter()
+        .map(|der| Certificate::parse(der))
+        .collect::<Option<Vec<_>>>()
+        .ok_or("malformed certificate")?;
+    let leaf = certs.first().ok_or("server sent no certificate")?;
+    let key = leaf.key.clone().ok_or("unsupported certificate key")?;
+
+    if !leaf.matches_host(host) {
+        return Err("certificate does not match host name");
+    }
+
+    // Dates are only checked when the RTC has the wall-clock time
+    let now = crate::device::rtc::get_host_timestamp();
+    if now != 0 && certs.iter().any(|c| !c.valid_at(now)) {
+        return Err("certificate expired or not yet valid");
+    }
+
+    let mut current = leaf;
+    for _ in 0..MAX_CHAIN_DEPTH {
+        if anchors.iter().any(|a| a.der == current.der || current.issued_by(a)) {
+            return Ok(key);
+        }
+        current = certs
+            .iter()
+            .filter(|c| c.is_ca && !core::ptr::eq(*c, current))
+            .find(|c| current.issued_by(c))
+            .ok_or("certificate chain does not reach a trusted root")?;
+    }
+    Err("certificate chain too long")
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// TRUST STORE
+// ═══════════════════════════════════════════════════════════════════════════════
+
+static TRUST_STORE: Spinlock<Option<Arc<Vec<Certificate>>>> = Spinlock::new(None);
+static BUNDLE_WATCH: PathWatch = PathWatch::new(CA_BUNDLE_PATH, WATCH_ALL);
+
+/// Current trust anchors (empty until hart 0 has loaded the bundle)
+pub fn trust_anchors() -> Arc<Vec<Certificate>> {
+    TRUST_STORE.lock().clone().unwrap_or_default()
+}
+
+/// Read the CA bundle. Returns the number of certificates loaded.
+pub fn load_trust_store() -> usize {
+    let anchors: Vec<Certificate> = crate::cpu::fs_proxy::fs_read(CA_BUNDLE_PATH)
+        .map(|pem| pem_blocks(&pem, "CERTIFICATE"))
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|der| Certificate::parse(&der))
+        .collect();
+    let count = anchors.len();
+    *TRUST_STORE.lock() = Some(Arc::new(anchors));
+    count
+}
+
+/// Load the CA bundle on first use and again whenever it changes
+pub fn reload_trust_store_if_changed() {
+    let loaded = TRUST_STORE.lock().is_some();
+    if BUNDLE_WATCH.changed() || !loaded {
+        let count = load_trust_store();
+        crate::services::klogd::klog_info("x509", &alloc::format!("{} root CAs loaded from {}", count, CA_BUNDLE_PATH));
+    }
+}
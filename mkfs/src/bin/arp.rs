@@ -0,0 +1,74 @@
+// arp - Show the IPv4 neighbor (ARP) table
+//
+// Usage:
+//   arp          List IP to MAC mappings learned from ARP and their age
+//
+// Entries older than a minute have dropped out of the network stack's cache
+// and are shown as stale; they are resolved again on the next send.
+
+#![cfg_attr(target_arch = "riscv64", no_std)]
+#![cfg_attr(target_arch = "riscv64", no_main)]
+
+#[cfg(target_arch = "riscv64")]
+#[no_mangle]
+pub fn main() {
+    use mkfs::{console_log, get_net_neighbors, is_net_available, print_int};
+
+    if !is_net_available() {
+        console_log("\x1b[1;31m[X]\x1b[0m Network not initialized\n");
+        return;
+    }
+
+    // "<ip> <mac> <age_ms> reachable|stale" lines
+    let mut buf = [0u8; 4096];
+    let Some(len) = get_net_neighbors(&mut buf) else {
+        console_log("\x1b[1;31m[X]\x1b[0m Could not get the neighbor table\n");
+        return;
+    };
+    let table = unsafe { core::str::from_utf8_unchecked(&buf[..len]) };
+    if table.is_empty() {
+        console_log("No neighbors yet\n");
+        return;
+    }
+
+    console_log("\x1b[1mAddress          HWaddress          Age       State\x1b[0m\n");
+    for line in table.lines() {
+        let mut fields = line.split(' ');
+        let (Some(ip), Some(mac), Some(age_ms), Some(state)) = (fields.next(), fields.next(), fields.next(), fields.next())
+        else {
+            continue;
+        };
+        let age = age_ms.parse::<i64>().unwrap_or(0) / 1000;
+
+        console_log(ip);
+        pad_spaces(17 - ip.len().min(16));
+        console_log(mac);
+        console_log("  ");
+        print_int(age);
+        console_log("s");
+        pad_spaces(9 - digit_count(age).min(8));
+        if state == "reachable" {
+            console_log("\x1b[32mreachable\x1b[0m\n");
+        } else {
+            console_log("\x1b[90mstale\x1b[0m\n");
+        }
+    }
+
+    fn pad_spaces(count: usize) {
+        for _ in 0..count {
+            mkfs::console_log(" ");
+        }
+    }
+
+    fn digit_count(mut n: i64) -> usize {
+        let mut count = 1;
+        while n >= 10 {
+            n /= 10;
+            count += 1;
+        }
+        count
+    }
+}
+
+#[cfg(not(target_arch = "riscv64"))]
+fn main() {}
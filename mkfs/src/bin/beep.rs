@@ -0,0 +1,85 @@
+// beep - Play a tone on the audio codec
+//
+// Usage:
+//   beep                        440 Hz for 200 ms
+//   beep <freq> [ms]            Sine tone (20-20000 Hz, up to 5000 ms)
+//   beep -s <freq> [ms]         Square wave instead of sine
+//   beep --events [on|off]      Show or set UI event sounds
+
+#![cfg_attr(target_arch = "riscv64", no_std)]
+#![cfg_attr(target_arch = "riscv64", no_main)]
+
+#[cfg(target_arch = "riscv64")]
+#[no_mangle]
+pub fn main() {
+    use mkfs::{argc, argv, console_log, play_tone, set_event_sounds};
+
+    fn parse_num(s: &[u8]) -> Option<u32> {
+        if s.is_empty() { return None; }
+        let mut result = 0u32;
+        for &c in s {
+            if !c.is_ascii_digit() { return None; }
+            result = result.checked_mul(10)?.checked_add((c - b'0') as u32)?;
+        }
+        Some(result)
+    }
+
+    fn usage() {
+        console_log("Usage: beep [-s] [freq] [ms]\n");
+        console_log("       beep --events [on|off]\n");
+    }
+
+    let mut arg_buf = [0u8; 32];
+    let mut args = [0u32; 2];
+    let mut nums = 0;
+    let mut square = false;
+
+    let arg_count = argc();
+    let mut i = 0;
+    while i < arg_count {
+        let len = argv(i, &mut arg_buf).unwrap_or(0);
+        let arg = &arg_buf[..len];
+        i += 1;
+
+        if arg == b"--events" {
+            let mode = if i < arg_count {
+                let len = argv(i, &mut arg_buf).unwrap_or(0);
+                match &arg_buf[..len] {
+                    b"on" => Some(true),
+                    b"off" => Some(false),
+                    _ => return usage(),
+                }
+            } else {
+                None
+            };
+            let on = set_event_sounds(mode);
+            console_log(if on { "Event sounds: on\n" } else { "Event sounds: off\n" });
+            return;
+        } else if arg == b"-s" {
+            square = true;
+        } else if let (Some(n), true) = (parse_num(arg), nums < args.len()) {
+            args[nums] = n;
+            nums += 1;
+        } else {
+            return usage();
+        }
+    }
+
+    let freq = if nums > 0 { args[0] } else { 440 };
+    let ms = if nums > 1 { args[1] } else { 200 };
+    if !(20..=20000).contains(&freq) {
+        console_log("\x1b[1;31mbeep:\x1b[0m frequency must be 20-20000 Hz\n");
+        return;
+    }
+    if ms == 0 || ms > 5000 {
+        console_log("\x1b[1;31mbeep:\x1b[0m duration must be 1-5000 ms\n");
+        return;
+    }
+
+    if !play_tone(freq, ms, square) {
+        console_log("\x1b[1;31mbeep:\x1b[0m no audio device, or another tone is playing\n");
+    }
+}
+
+#[cfg(not(target_arch = "riscv64"))]
+fn main() {}
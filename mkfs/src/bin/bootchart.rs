@@ -0,0 +1,28 @@
+// bootchart - Show where boot time went
+//
+// Usage:
+//   bootchart     Print each boot stage and service start with its timing
+//
+// The same chart is saved to /var/log/bootchart.log at the end of boot.
+
+#![cfg_attr(target_arch = "riscv64", no_std)]
+#![cfg_attr(target_arch = "riscv64", no_main)]
+
+#[cfg(target_arch = "riscv64")]
+#[no_mangle]
+pub fn main() {
+    use mkfs::{console_log, get_bootchart, print};
+
+    static mut CHART_BUF: [u8; 4096] = [0u8; 4096];
+
+    match unsafe { get_bootchart(&mut *core::ptr::addr_of_mut!(CHART_BUF)) } {
+        Some(len) if len > 0 => {
+            let data = unsafe { &(*core::ptr::addr_of!(CHART_BUF))[..len] };
+            print(data.as_ptr(), data.len());
+        }
+        _ => console_log("\x1b[1;31mbootchart:\x1b[0m no boot profile available\n"),
+    }
+}
+
+#[cfg(not(target_arch = "riscv64"))]
+fn main() {}
@@ -0,0 +1,53 @@
+// clip - Show or set the clipboard
+//
+// Usage:
+//   clip             Print the clipboard text
+//   clip <text>      Set the clipboard to the arguments joined by spaces
+//   clip --clear     Empty the clipboard
+//
+// The clipboard is shared with the GUI terminal (Ctrl+V pastes it) and, in
+// the browser emulator, with the page: text set here lands on the page's
+// clipboard, and text the page pushes shows up here.
+
+#![cfg_attr(target_arch = "riscv64", no_std)]
+#![cfg_attr(target_arch = "riscv64", no_main)]
+
+#[cfg(target_arch = "riscv64")]
+#[no_mangle]
+pub fn main() {
+    use mkfs::{argc, argv, clipboard_get, clipboard_set, console_log, exit};
+
+    if argc() == 0 {
+        let mut buf = [0u8; 16384];
+        let Some(len) = clipboard_get(&mut buf) else {
+            console_log("\x1b[1;31m[X]\x1b[0m Could not read the clipboard\n");
+            exit(1);
+        };
+        let text = core::str::from_utf8(&buf[..len]).unwrap_or("");
+        console_log(text);
+        if !text.is_empty() && !text.ends_with('\n') {
+            console_log("\n");
+        }
+        return;
+    }
+
+    // The text is the arguments joined by spaces
+    let mut text = [0u8; 1024];
+    let mut len = 0;
+    for i in 0..argc() {
+        if len > 0 && len < text.len() {
+            text[len] = b' ';
+            len += 1;
+        }
+        len += argv(i, &mut text[len..]).unwrap_or(0);
+    }
+    let text = core::str::from_utf8(&text[..len]).unwrap_or("");
+    let text = if text == "--clear" { "" } else { text };
+    if !clipboard_set(text) {
+        console_log("\x1b[1;31m[X]\x1b[0m Could not set the clipboard\n");
+        exit(1);
+    }
+}
+
+#[cfg(not(target_arch = "riscv64"))]
+fn main() {}
@@ -0,0 +1,88 @@
+// displayctl - Display brightness, contrast and gamma
+//
+// Usage:
+//   displayctl                       Show current settings
+//   displayctl brightness <10-100>   Brightness in percent
+//   displayctl contrast <50-150>     Contrast in percent (100 = unchanged)
+//   displayctl gamma <0.5-3.0>       Gamma (1.0 = unchanged, higher is lighter)
+//   displayctl reset                 Back to 100% / 100% / 1.0
+//
+// The same settings are in the Display window of the main screen.
+
+#![cfg_attr(target_arch = "riscv64", no_std)]
+#![cfg_attr(target_arch = "riscv64", no_main)]
+
+#[cfg(target_arch = "riscv64")]
+#[no_mangle]
+pub fn main() {
+    use mkfs::{argc, argv, console_log, print_int, set_display_params, DisplayParams};
+
+    fn usage() {
+        console_log("Usage: displayctl [brightness <10-100> | contrast <50-150> | gamma <0.5-3.0> | reset]\n");
+    }
+
+    /// Parse "2", "2.2" or "2.25" as hundredths
+    fn parse_hundredths(s: &str) -> Option<u16> {
+        let (whole, frac) = s.split_once('.').unwrap_or((s, ""));
+        if frac.len() > 2 || (whole.is_empty() && frac.is_empty()) {
+            return None;
+        }
+        let whole: u16 = if whole.is_empty() { 0 } else { whole.parse().ok()? };
+        let mut hundredths: u16 = if frac.is_empty() { 0 } else { frac.parse().ok()? };
+        if frac.len() == 1 {
+            hundredths *= 10;
+        }
+        whole.checked_mul(100)?.checked_add(hundredths)
+    }
+
+    fn show(params: DisplayParams) {
+        console_log("Brightness: ");
+        print_int(params.brightness as i64);
+        console_log("%\nContrast:   ");
+        print_int(params.contrast as i64);
+        console_log("%\nGamma:      ");
+        print_int((params.gamma / 100) as i64);
+        console_log(".");
+        if params.gamma % 100 < 10 {
+            console_log("0");
+        }
+        print_int((params.gamma % 100) as i64);
+        console_log("\n");
+    }
+
+    let mut name_buf = [0u8; 32];
+    let mut value_buf = [0u8; 32];
+    let name_len = if argc() > 0 { argv(0, &mut name_buf).unwrap_or(0) } else { 0 };
+    let value_len = if argc() > 1 { argv(1, &mut value_buf).unwrap_or(0) } else { 0 };
+    let name = unsafe { core::str::from_utf8_unchecked(&name_buf[..name_len]) };
+    let value = unsafe { core::str::from_utf8_unchecked(&value_buf[..value_len]) };
+
+    let result = match (name, argc()) {
+        ("", 0) => set_display_params(None, None, None),
+        ("reset", 1) => set_display_params(Some(100), Some(100), Some(100)),
+        ("brightness", 2) => match value.parse() {
+            Ok(v) if v > 0 => set_display_params(Some(v), None, None),
+            _ => return usage(),
+        },
+        ("contrast", 2) => match value.parse() {
+            Ok(v) if v > 0 => set_display_params(None, Some(v), None),
+            _ => return usage(),
+        },
+        ("gamma", 2) => match parse_hundredths(value) {
+            Some(v) if v > 0 => set_display_params(None, None, Some(v)),
+            _ => return usage(),
+        },
+        _ => return usage(),
+    };
+
+    match result {
+        Some(params) => show(params),
+        None => {
+            console_log("\x1b[1;31mdisplayctl:\x1b[0m no display, or value out of range\n");
+            usage();
+        }
+    }
+}
+
+#[cfg(not(target_arch = "riscv64"))]
+fn main() {}
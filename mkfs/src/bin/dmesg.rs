@@ -3,7 +3,11 @@
 // Usage:
 //   dmesg           Show all kernel log messages
 //   dmesg -n <N>    Show last N messages
+//   dmesg -f        Show messages, then follow new ones (Ctrl+C to stop)
 //   dmesg -h        Show help
+//
+// Following reads the shared kernel log page directly, so it costs no
+// syscalls however fast the kernel logs.
 
 #![cfg_attr(target_arch = "riscv64", no_std)]
 #![cfg_attr(target_arch = "riscv64", no_main)]
@@ -11,7 +15,74 @@
 #[cfg(target_arch = "riscv64")]
 #[no_mangle]
 pub fn main() {
-    use mkfs::{console_log, argc, argv, get_klog, print};
+    use mkfs::{console_log, argc, argv, get_klog, print, should_cancel, sleep, KlogReader, KLOG_TEXT_LEN};
+
+    const LEVELS: [(&str, &str); 9] = [
+        ("EMERG", "\x1b[1;31m"),
+        ("ALERT", "\x1b[1;31m"),
+        ("CRIT ", "\x1b[1;31m"),
+        ("ERROR", "\x1b[31m"),
+        ("WARN ", "\x1b[33m"),
+        ("NOTE ", "\x1b[36m"),
+        ("INFO ", "\x1b[0m"),
+        ("DEBUG", "\x1b[90m"),
+        ("TRACE", "\x1b[90m"),
+    ];
+
+    /// Write `n` right-aligned in `width` columns (zero-padded if `zero`)
+    fn print_num(mut n: u64, width: usize, zero: bool) {
+        let mut buf = [if zero { b'0' } else { b' ' }; 20];
+        let mut i = buf.len();
+        loop {
+            i -= 1;
+            buf[i] = b'0' + (n % 10) as u8;
+            n /= 10;
+            if n == 0 {
+                break;
+            }
+        }
+        let start = i.min(buf.len() - width.min(buf.len()));
+        print(buf[start..].as_ptr(), buf.len() - start);
+    }
+
+    /// Follow the log, formatted like `dmesg` output
+    fn follow() {
+        let Some(mut reader) = KlogReader::open(false) else {
+            console_log("\x1b[31mError: Kernel log page unavailable\x1b[0m\n");
+            return;
+        };
+        let mut text = [0u8; KLOG_TEXT_LEN];
+        let mut lost = 0;
+        while should_cancel() == 0 {
+            let Some(record) = reader.next(&mut text) else {
+                sleep(50);
+                continue;
+            };
+            if reader.lost > lost {
+                console_log("\x1b[90m(");
+                print_num(reader.lost - lost, 0, false);
+                console_log(" messages lost)\x1b[0m\n");
+                lost = reader.lost;
+            }
+            let (name, color) = LEVELS[(record.level as usize).min(LEVELS.len() - 1)];
+            let line = core::str::from_utf8(&text[..record.len]).unwrap_or("");
+            let (subsystem, message) = line.split_once(": ").unwrap_or(("", line));
+            console_log("\x1b[90m[");
+            print_num(record.timestamp_ms / 1000, 10, false);
+            console_log(".");
+            print_num(record.timestamp_ms % 1000, 3, true);
+            console_log("]\x1b[0m ");
+            console_log(color);
+            console_log(name);
+            console_log("\x1b[0m \x1b[36m[");
+            print_num(record.hart as u64, 0, false);
+            console_log("]\x1b[0m \x1b[33m");
+            console_log(subsystem);
+            console_log(":\x1b[0m ");
+            console_log(message);
+            console_log("\n");
+        }
+    }
 
     fn parse_int(s: &[u8]) -> Option<usize> {
         if s.is_empty() {
@@ -29,6 +100,7 @@ pub fn main() {
 
     let arg_count = argc();
     let mut count: usize = 100;
+    let mut follow_mode = false;
 
     let mut i = 0;
     while i < arg_count {
@@ -45,12 +117,17 @@ pub fn main() {
 
         if arg == b"-h" || arg == b"--help" {
             console_log("\x1b[1mdmesg\x1b[0m - Display kernel ring buffer\n\n");
-            console_log("Usage: dmesg [-n <N>]\n\n");
+            console_log("Usage: dmesg [-n <N>] [-f]\n\n");
             console_log("Options:\n");
             console_log("  -n <N>  Show last N messages (default: 100)\n");
+            console_log("  -f      Follow new messages until Ctrl+C\n");
             return;
         }
 
+        if arg == b"-f" || arg == b"--follow" {
+            follow_mode = true;
+        }
+
         if arg == b"-n" {
             if i + 1 < arg_count {
                 let mut num_buf = [0u8; 16];
@@ -84,6 +161,10 @@ pub fn main() {
             console_log("\x1b[31mError: Failed to read kernel log\x1b[0m\n");
         }
     }
+
+    if follow_mode {
+        follow();
+    }
 }
 
 #[cfg(not(target_arch = "riscv64"))]
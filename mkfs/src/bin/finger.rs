@@ -0,0 +1,88 @@
+// finger - Finger protocol client (RFC 1288)
+//
+// Usage:
+//   finger user@host       Show information about a user
+//   finger @host           List the users on host
+//   finger -l user@host    Ask for the long (verbose) form
+//
+// The reply is printed as it arrives. Control characters are filtered
+// out, so a remote .plan cannot send escape sequences to the terminal.
+
+#![cfg_attr(target_arch = "riscv64", no_std)]
+#![cfg_attr(target_arch = "riscv64", no_main)]
+
+#[cfg(target_arch = "riscv64")]
+#[no_mangle]
+pub fn main() {
+    use mkfs::{argc, argv, console_log, is_net_available, print, tcp_request};
+
+    const FINGER_PORT: u16 = 79;
+    const TIMEOUT_MS: i64 = 10000;
+
+    static mut ARG_BUF: [u8; 256] = [0u8; 256];
+    static mut REQ_BUF: [u8; 270] = [0u8; 270];
+
+    let mut flag_buf = [0u8; 8];
+    let long = argc() >= 2 && argv(0, &mut flag_buf) == Some(2) && &flag_buf[..2] == b"-l";
+    let target_idx = if long { 1 } else { 0 };
+
+    let arg_buf = unsafe { &mut *core::ptr::addr_of_mut!(ARG_BUF) };
+    let arg_len = match argv(target_idx, arg_buf) {
+        Some(len) if len > 0 => len,
+        _ => {
+            console_log("Usage: finger [-l] [user]@host\n");
+            return;
+        }
+    };
+    let target = unsafe { core::str::from_utf8_unchecked(&arg_buf[..arg_len]) };
+
+    let (user, host) = match target.rfind('@') {
+        Some(at) if at + 1 < target.len() => (&target[..at], &target[at + 1..]),
+        _ => {
+            console_log("\x1b[1;31mfinger:\x1b[0m expected user@host or @host\n");
+            return;
+        }
+    };
+
+    if !is_net_available() {
+        console_log("\x1b[1;31m[X]\x1b[0m Network not available\n");
+        return;
+    }
+
+    // "{/W }user\r\n"
+    let req = unsafe { &mut *core::ptr::addr_of_mut!(REQ_BUF) };
+    let mut req_len = 0;
+    let parts: [&[u8]; 3] = [if long { b"/W " } else { b"" }, user.as_bytes(), b"\r\n"];
+    for part in parts {
+        req[req_len..req_len + part.len()].copy_from_slice(part);
+        req_len += part.len();
+    }
+
+    console_log("[");
+    console_log(host);
+    console_log("]\n");
+
+    let result = tcp_request(host, FINGER_PORT, &req[..req_len], TIMEOUT_MS, |data| {
+        let mut start = 0;
+        for (i, &c) in data.iter().enumerate() {
+            if (c < 0x20 && c != b'\n' && c != b'\t') || c == 0x7F {
+                print(data[start..i].as_ptr(), i - start);
+                start = i + 1;
+            }
+        }
+        print(data[start..].as_ptr(), data.len() - start);
+    });
+
+    match result {
+        Ok(0) => console_log("(no reply)\n"),
+        Ok(_) => {}
+        Err(e) => {
+            console_log("\x1b[1;31mfinger:\x1b[0m ");
+            console_log(e);
+            console_log("\n");
+        }
+    }
+}
+
+#[cfg(not(target_arch = "riscv64"))]
+fn main() {}
@@ -0,0 +1,26 @@
+// flushdns - Clear the DNS cache
+//
+// Usage:
+//   flushdns       Drop all cached DNS answers and re-read /etc/hosts
+//
+// Run it after editing /etc/hosts, or when a host has moved and the old
+// address is still cached.
+
+#![cfg_attr(target_arch = "riscv64", no_std)]
+#![cfg_attr(target_arch = "riscv64", no_main)]
+
+#[cfg(target_arch = "riscv64")]
+#[no_mangle]
+pub fn main() {
+    use mkfs::{console_log, flush_dns, print_int};
+
+    let (dropped, hosts) = flush_dns();
+    console_log("\x1b[1;32m[OK]\x1b[0m DNS cache flushed (");
+    print_int(dropped as i64);
+    console_log(" entries), ");
+    print_int(hosts as i64);
+    console_log(" names loaded from /etc/hosts\n");
+}
+
+#[cfg(not(target_arch = "riscv64"))]
+fn main() {}
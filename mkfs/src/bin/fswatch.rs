@@ -0,0 +1,59 @@
+// fswatch - Print changes to files as they happen
+//
+// Usage:
+//   fswatch [path]      Watch a file, or a directory and everything below it
+//                       (default: the current directory)
+//
+// Prints one line per change until Ctrl+C:
+//   C <path>   created      M <path>   written
+//   D <path>   removed      O          some changes were missed
+
+#![cfg_attr(target_arch = "riscv64", no_std)]
+#![cfg_attr(target_arch = "riscv64", no_main)]
+
+#[cfg(target_arch = "riscv64")]
+#[no_mangle]
+pub fn main() {
+    use mkfs::{
+        argc, argv, close_file, console_log, fd_read, print, should_cancel, wait_for_changes, watch_path,
+        WATCH_CREATE, WATCH_DELETE, WATCH_MODIFY,
+    };
+
+    let mut arg = [0u8; 256];
+    let path = if argc() > 0 {
+        match argv(0, &mut arg).and_then(|len| core::str::from_utf8(&arg[..len]).ok()) {
+            Some(p) => p,
+            None => {
+                console_log("Usage: fswatch [path]\n");
+                return;
+            }
+        }
+    } else {
+        "."
+    };
+
+    let Some(fd) = watch_path(path, WATCH_CREATE | WATCH_MODIFY | WATCH_DELETE) else {
+        console_log("\x1b[1;31m[X]\x1b[0m Cannot watch ");
+        console_log(path);
+        console_log("\n");
+        return;
+    };
+    console_log("Watching ");
+    console_log(path);
+    console_log(" (Ctrl+C to stop)\n");
+
+    let mut buf = [0u8; 512];
+    while should_cancel() == 0 {
+        match fd_read(fd, &mut buf) {
+            Some(0) => {
+                wait_for_changes(fd, 1000);
+            }
+            Some(len) => print(buf.as_ptr(), len),
+            None => break,
+        }
+    }
+    close_file(fd);
+}
+
+#[cfg(not(target_arch = "riscv64"))]
+fn main() {}
@@ -0,0 +1,132 @@
+// fw - Show and edit the packet filter rules
+//
+// Usage:
+//   fw               List the rules with hit counts, and the policy
+//   fw add <rule>    Append a rule, e.g. "fw add deny tcp port 30"
+//   fw del <n>       Remove rule number n (as listed)
+//   fw reload        Re-read /etc/firewall.rules
+//
+// Rules are "<allow|deny> <tcp|udp|icmp|any> [port N[-M]] [from ADDR[/LEN]]
+// [established]", first match wins; "fw add policy deny" changes what
+// happens to packets no rule matches. See /etc/firewall.rules.
+
+#![cfg_attr(target_arch = "riscv64", no_std)]
+#![cfg_attr(target_arch = "riscv64", no_main)]
+
+#[cfg(target_arch = "riscv64")]
+#[no_mangle]
+pub fn main() {
+    use mkfs::{argc, argv, console_log, exit, firewall_add, firewall_delete, firewall_list, firewall_reload, print_int};
+
+    const USAGE: &str = "Usage: fw [add <rule> | del <n> | reload]\n";
+
+    fn report(result: Option<usize>) {
+        match result {
+            Some(count) => {
+                print_int(count as i64);
+                console_log(" rules in force\n");
+            }
+            None => {
+                console_log("\x1b[1;31m[X]\x1b[0m Rejected (see dmesg)\n");
+                exit(1);
+            }
+        }
+    }
+
+    let mut cmd_buf = [0u8; 16];
+    let cmd = argv(0, &mut cmd_buf).map(|len| &cmd_buf[..len]).unwrap_or(b"");
+    match cmd {
+        b"" => {}
+        b"add" if argc() > 1 => {
+            // The rule is the remaining arguments joined by spaces
+            let mut rule = [0u8; 256];
+            let mut len = 0;
+            for i in 1..argc() {
+                if len > 0 && len < rule.len() {
+                    rule[len] = b' ';
+                    len += 1;
+                }
+                len += argv(i, &mut rule[len..]).unwrap_or(0);
+            }
+            report(firewall_add(core::str::from_utf8(&rule[..len]).unwrap_or("")));
+            return;
+        }
+        b"del" if argc() == 2 => {
+            let mut num_buf = [0u8; 8];
+            let num_len = argv(1, &mut num_buf).unwrap_or(0);
+            match core::str::from_utf8(&num_buf[..num_len]).ok().and_then(|s| s.parse().ok()) {
+                Some(index) => report(firewall_delete(index)),
+                None => console_log(USAGE),
+            }
+            return;
+        }
+        b"reload" => {
+            report(firewall_reload());
+            return;
+        }
+        _ => {
+            console_log(USAGE);
+            return;
+        }
+    }
+
+    // "policy <allow|deny> <dropped>" then "<hits> <rule>" lines
+    let mut buf = [0u8; 8192];
+    let Some(len) = firewall_list(&mut buf) else {
+        console_log("\x1b[1;31m[X]\x1b[0m Could not read the firewall rules\n");
+        return;
+    };
+    let text = unsafe { core::str::from_utf8_unchecked(&buf[..len]) };
+    let mut lines = text.lines();
+    if let Some(policy) = lines.next() {
+        let mut fields = policy.split(' ').skip(1);
+        console_log("Policy: \x1b[1m");
+        console_log(fields.next().unwrap_or("?"));
+        console_log("\x1b[0m (");
+        console_log(fields.next().unwrap_or("0"));
+        console_log(" packets dropped)\n");
+    }
+    let mut index = 0;
+    for line in lines {
+        let Some((hits, rule)) = line.split_once(' ') else {
+            continue;
+        };
+        if index == 0 {
+            console_log("\x1b[1m  #        HITS  RULE\x1b[0m\n");
+        }
+        index += 1;
+        pad_spaces(3 - digit_count(index).min(3));
+        print_int(index as i64);
+        pad_spaces(12 - hits.len().min(11));
+        console_log(hits);
+        console_log("  ");
+        if rule.starts_with("deny") {
+            console_log("\x1b[31m");
+        } else {
+            console_log("\x1b[32m");
+        }
+        console_log(rule);
+        console_log("\x1b[0m\n");
+    }
+    if index == 0 {
+        console_log("No rules\n");
+    }
+
+    fn pad_spaces(count: usize) {
+        for _ in 0..count {
+            mkfs::console_log(" ");
+        }
+    }
+
+    fn digit_count(mut n: usize) -> usize {
+        let mut count = 1;
+        while n >= 10 {
+            n /= 10;
+            count += 1;
+        }
+        count
+    }
+}
+
+#[cfg(not(target_arch = "riscv64"))]
+fn main() {}
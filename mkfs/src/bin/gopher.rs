@@ -0,0 +1,222 @@
+// gopher - Gopher protocol client (RFC 1436)
+//
+// Usage:
+//   gopher <host>[:port] [selector]             Show a menu
+//   gopher gopher://host[:port]/<type><selector>  Open a URL
+//   gopher gopher://host/7<selector> <query>    Run a search
+//
+// Menus are printed with one numbered line per link and the gopher:// URL
+// to open it with. Text documents (type 0) are printed as they arrive.
+// Control characters from the server are filtered out.
+
+#![cfg_attr(target_arch = "riscv64", no_std)]
+#![cfg_attr(target_arch = "riscv64", no_main)]
+
+#[cfg(target_arch = "riscv64")]
+#[no_mangle]
+pub fn main() {
+    use mkfs::{argc, argv, console_log, is_net_available, tcp_request};
+
+    const GOPHER_PORT: u16 = 70;
+    const TIMEOUT_MS: i64 = 10000;
+
+    static mut URL_BUF: [u8; 512] = [0u8; 512];
+    static mut EXTRA_BUF: [u8; 256] = [0u8; 256];
+    static mut REQ_BUF: [u8; 1024] = [0u8; 1024];
+    static mut LINE_BUF: [u8; 1024] = [0u8; 1024];
+
+    let url_buf = unsafe { &mut *core::ptr::addr_of_mut!(URL_BUF) };
+    let extra_buf = unsafe { &mut *core::ptr::addr_of_mut!(EXTRA_BUF) };
+
+    let url_len = match argv(0, url_buf) {
+        Some(len) if len > 0 => len,
+        _ => {
+            console_log("Usage: gopher <host>[:port] [selector]\n");
+            console_log("       gopher gopher://host[:port]/<type><selector> [query]\n");
+            console_log("Example: gopher gopher.floodgap.com\n");
+            return;
+        }
+    };
+    let url = unsafe { core::str::from_utf8_unchecked(&url_buf[..url_len]) };
+    let extra_len = if argc() >= 2 { argv(1, extra_buf).unwrap_or(0) } else { 0 };
+    let extra = unsafe { core::str::from_utf8_unchecked(&extra_buf[..extra_len]) };
+
+    let (host, port, kind, mut selector) = match parse_url(url, GOPHER_PORT) {
+        Some(target) => target,
+        None => {
+            console_log("\x1b[1;31mgopher:\x1b[0m invalid address\n");
+            return;
+        }
+    };
+    // A bare host takes the selector as its second argument; a search takes the query
+    let query = if kind == b'7' { extra } else { "" };
+    if !url.starts_with("gopher://") && !extra.is_empty() {
+        selector = extra;
+    }
+
+    match kind {
+        b'0' | b'1' | b'7' => {}
+        _ => {
+            console_log("\x1b[1;31mgopher:\x1b[0m only menus, text and searches are supported\n");
+            return;
+        }
+    }
+    if !is_net_available() {
+        console_log("\x1b[1;31m[X]\x1b[0m Network not available\n");
+        return;
+    }
+
+    // "selector[\tquery]\r\n"
+    let req = unsafe { &mut *core::ptr::addr_of_mut!(REQ_BUF) };
+    let mut req_len = 0;
+    let tab: &[u8] = if query.is_empty() { b"" } else { b"\t" };
+    let parts: [&[u8]; 4] = [selector.as_bytes(), tab, query.as_bytes(), b"\r\n"];
+    for part in parts {
+        if req_len + part.len() > req.len() {
+            console_log("\x1b[1;31mgopher:\x1b[0m selector too long\n");
+            return;
+        }
+        req[req_len..req_len + part.len()].copy_from_slice(part);
+        req_len += part.len();
+    }
+
+    let menu = kind != b'0';
+    let line = unsafe { &mut *core::ptr::addr_of_mut!(LINE_BUF) };
+    let mut line_len = 0;
+    let mut links = 0;
+    let mut done = false;
+
+    let result = tcp_request(host, port, &req[..req_len], TIMEOUT_MS, |data| {
+        for &c in data {
+            if done {
+                return;
+            }
+            if c == b'\n' || line_len == line.len() {
+                done = show_line(&line[..line_len], menu, &mut links);
+                line_len = 0;
+                if c == b'\n' {
+                    continue;
+                }
+            }
+            line[line_len] = c;
+            line_len += 1;
+        }
+    });
+    if !done && line_len > 0 {
+        show_line(&line[..line_len], menu, &mut links);
+    }
+
+    match result {
+        Ok(0) => console_log("(empty reply)\n"),
+        Ok(_) => {}
+        Err(e) => {
+            console_log("\x1b[1;31mgopher:\x1b[0m ");
+            console_log(e);
+            console_log("\n");
+        }
+    }
+
+    /// "gopher://host[:port]/<type><selector>" or "host[:port]"
+    /// -> (host, port, type, selector)
+    fn parse_url(url: &str, default_port: u16) -> Option<(&str, u16, u8, &str)> {
+        let rest = url.strip_prefix("gopher://").unwrap_or(url);
+        let (authority, path) = match rest.find('/') {
+            Some(slash) => (&rest[..slash], &rest[slash + 1..]),
+            None => (rest, ""),
+        };
+        let (host, port) = match authority.rfind(':') {
+            Some(colon) => (&authority[..colon], authority[colon + 1..].parse().ok()?),
+            None => (authority, default_port),
+        };
+        if host.is_empty() {
+            return None;
+        }
+        match path.as_bytes().first() {
+            Some(&kind) => Some((host, port, kind, &path[1..])),
+            None => Some((host, port, b'1', "")),
+        }
+    }
+
+    /// Print one line of the reply. Returns true at the "." terminator.
+    fn show_line(line: &[u8], menu: bool, links: &mut usize) -> bool {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        if line == b"." {
+            return true;
+        }
+        if !menu {
+            print_clean(line);
+            console_log("\n");
+            return false;
+        }
+        if line.is_empty() {
+            return false;
+        }
+
+        // <type><display>\t<selector>\t<host>\t<port>
+        let kind = line[0];
+        let mut fields = line[1..].split(|&c| c == b'\t');
+        let display = fields.next().unwrap_or(b"");
+        let selector = fields.next().unwrap_or(b"");
+        let host = fields.next().unwrap_or(b"");
+        let port = fields.next().unwrap_or(b"70");
+
+        let label = match kind {
+            b'i' => {
+                console_log("        ");
+                print_clean(display);
+                console_log("\n");
+                return false;
+            }
+            b'3' => "\x1b[1;31mERR \x1b[0m",
+            b'0' => "TXT ",
+            b'1' => "\x1b[1;34mDIR \x1b[0m",
+            b'7' => "FIND",
+            b'h' => "HTML",
+            b'8' | b'T' => "TELN",
+            _ => "BIN ",
+        };
+
+        *links += 1;
+        console_log(match *links {
+            0..=9 => "   ",
+            10..=99 => "  ",
+            _ => " ",
+        });
+        mkfs::print_int(*links as i64);
+        console_log(". ");
+        console_log(label);
+        console_log(" ");
+        print_clean(display);
+        console_log("\n          \x1b[90m");
+        if kind == b'h' && selector.starts_with(b"URL:") {
+            print_clean(&selector[4..]);
+        } else if kind != b'3' {
+            console_log("gopher://");
+            print_clean(host);
+            if port != b"70" {
+                console_log(":");
+                print_clean(port);
+            }
+            console_log("/");
+            print_clean(&[kind]);
+            print_clean(selector);
+        }
+        console_log("\x1b[0m\n");
+        false
+    }
+
+    /// Print without control characters
+    fn print_clean(data: &[u8]) {
+        let mut start = 0;
+        for (i, &c) in data.iter().enumerate() {
+            if c < 0x20 || c == 0x7F {
+                mkfs::print(data[start..i].as_ptr(), i - start);
+                start = i + 1;
+            }
+        }
+        mkfs::print(data[start..].as_ptr(), data.len() - start);
+    }
+}
+
+#[cfg(not(target_arch = "riscv64"))]
+fn main() {}
@@ -12,7 +12,7 @@
 #[cfg(target_arch = "riscv64")]
 #[no_mangle]
 pub fn main() {
-    use mkfs::{console_log, argc, argv, get_cwd, print_int, print, fs_read};
+    use mkfs::{console_log, argc, argv, get_cwd, print_int, print, open, close, seek, map_file, unmap, O_RDONLY, SEEK_END};
 
     fn to_lower(c: u8) -> u8 {
         if c >= b'A' && c <= b'Z' { c + 32 } else { c }
@@ -136,20 +136,26 @@ pub fn main() {
         let mut path_buf = [0u8; 512];
         let path_len = resolve_path(file_arg, &mut path_buf, &cwd, cwd_len);
 
-        // Read file
-        static mut CONTENT: [u8; 65536] = [0u8; 65536]; // 64KB max
-        let read_len = unsafe {
-            fs_read(path_buf.as_ptr(), path_len as i32, (*core::ptr::addr_of_mut!(CONTENT)).as_mut_ptr(), 65536)
-        };
-
-        if read_len < 0 {
+        // Map the whole file, however large
+        let fd = open(path_buf.as_ptr(), path_len as i32, O_RDONLY);
+        if fd < 0 {
             console_log("\x1b[1;31mgrep:\x1b[0m ");
             print(path_buf.as_ptr(), path_len);
             console_log(": No such file\n");
             continue;
         }
+        let size = seek(fd, 0, SEEK_END);
+        let mapping = if size > 0 { map_file(fd, size as usize, 0, false) } else { None };
+        close(fd);
+        let Some(content) = mapping else {
+            if size != 0 {
+                console_log("\x1b[1;31mgrep:\x1b[0m ");
+                print(path_buf.as_ptr(), path_len);
+                console_log(": File too large\n");
+            }
+            continue;
+        };
 
-        let content = unsafe { &(*core::ptr::addr_of!(CONTENT))[..read_len as usize] };
         let mut line_num = 1usize;
         let mut line_start = 0;
 
@@ -194,6 +200,7 @@ pub fn main() {
                 line_start = i + 1;
             }
         }
+        unmap(content);
     }
 }
 
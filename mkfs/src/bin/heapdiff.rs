@@ -0,0 +1,55 @@
+// heapdiff - Find kernel heap leaks
+//
+// Usage:
+//   heapdiff start      Start recording kernel heap allocations
+//   heapdiff report     Show what was allocated since start and not freed
+//   heapdiff stop       Show the same report and stop recording
+//
+// Allocations are grouped by tag (the process that was running when they
+// were made) and call site (return addresses; resolve them with addr2line
+// against the kernel ELF). Start, let a daemon run for a while, then stop:
+// whatever keeps growing between the two points is the leak.
+
+#![cfg_attr(target_arch = "riscv64", no_std)]
+#![cfg_attr(target_arch = "riscv64", no_main)]
+
+#[cfg(target_arch = "riscv64")]
+#[no_mangle]
+pub fn main() {
+    use mkfs::{argc, argv, console_log, heapdiff_report, heapdiff_start, print};
+
+    static mut REPORT_BUF: [u8; 8192] = [0u8; 8192];
+
+    let mut arg = [0u8; 16];
+    let cmd = if argc() > 0 {
+        argv(0, &mut arg).and_then(|len| core::str::from_utf8(&arg[..len]).ok()).unwrap_or("")
+    } else {
+        ""
+    };
+
+    let stop = match cmd {
+        "start" => {
+            if heapdiff_start() {
+                console_log("Recording heap allocations; run `heapdiff stop` to see what leaked\n");
+            } else {
+                console_log("\x1b[1;31m[X]\x1b[0m Cannot start recording\n");
+            }
+            return;
+        }
+        "stop" => true,
+        "report" => false,
+        _ => {
+            console_log("Usage: heapdiff start|report|stop\n");
+            return;
+        }
+    };
+
+    let buf = unsafe { &mut *core::ptr::addr_of_mut!(REPORT_BUF) };
+    match heapdiff_report(buf, stop) {
+        Some(len) => print(buf.as_ptr(), len),
+        None => console_log("heapdiff: not recording (run `heapdiff start` first)\n"),
+    }
+}
+
+#[cfg(not(target_arch = "riscv64"))]
+fn main() {}
@@ -0,0 +1,27 @@
+// hibernate - Save the system to disk and power off
+//
+// Usage:
+//   hibernate      Save the session and power off; the next boot resumes it
+//
+// Needs a disk image with a hibernate region (mkfs --hibernate <MiB>).
+// With emulator support the whole machine resumes where it left off;
+// otherwise the next boot restores the working directory and services.
+
+#![cfg_attr(target_arch = "riscv64", no_std)]
+#![cfg_attr(target_arch = "riscv64", no_main)]
+
+#[cfg(target_arch = "riscv64")]
+#[no_mangle]
+pub fn main() {
+    use mkfs::{console_log, hibernate_system};
+
+    console_log("Hibernating...\n");
+    if hibernate_system() {
+        console_log("\x1b[1;32m[OK]\x1b[0m Resumed from hibernation\n");
+    } else {
+        console_log("\x1b[1;31mhibernate:\x1b[0m could not save the system (see dmesg)\n");
+    }
+}
+
+#[cfg(not(target_arch = "riscv64"))]
+fn main() {}
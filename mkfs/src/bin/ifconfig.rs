@@ -0,0 +1,98 @@
+// ifconfig - Show the network interface and its counters
+//
+// Usage:
+//   ifconfig     Show addresses and traffic counters of eth0
+//
+// RX dropped counts frames the firewall threw away; RX errors frames too
+// large for a receive buffer. TX dropped counts frames sent while the
+// transmit ring was full; TX errors frames the device could not take.
+
+#![cfg_attr(target_arch = "riscv64", no_std)]
+#![cfg_attr(target_arch = "riscv64", no_main)]
+
+#[cfg(target_arch = "riscv64")]
+#[no_mangle]
+pub fn main() {
+    use mkfs::{console_log, format_ipv4, format_mac, get_interface_stats, get_net_info, get_net_info6, is_net_available, print, print_int};
+
+    if !is_net_available() {
+        console_log("\x1b[1;31m[X]\x1b[0m Network not initialized\n");
+        return;
+    }
+
+    let (Some(info), Some(stats)) = (get_net_info(), get_interface_stats()) else {
+        console_log("\x1b[1;31m[X]\x1b[0m Could not get network info\n");
+        return;
+    };
+
+    console_log("\x1b[1;97meth0\x1b[0m: flags=<UP,BROADCAST,RUNNING>  mtu 1500\n");
+
+    let mut ip_buf = [0u8; 16];
+    let ip_len = format_ipv4(&info.ip, &mut ip_buf);
+    console_log("        inet ");
+    print(ip_buf.as_ptr(), ip_len);
+    console_log("  prefixlen ");
+    print_int(info.prefix_len as i64);
+    let gw_len = format_ipv4(&info.gateway, &mut ip_buf);
+    console_log("  gateway ");
+    print(ip_buf.as_ptr(), gw_len);
+    console_log("\n");
+
+    // "inet6 <addr>/<len> scope <scope>" lines; the router is left to `ip`
+    let mut info6 = [0u8; 512];
+    let info6_len = get_net_info6(&mut info6).unwrap_or(0);
+    let info6 = core::str::from_utf8(&info6[..info6_len]).unwrap_or("");
+    for line in info6.lines().filter(|line| line.starts_with("inet6 ")) {
+        console_log("        ");
+        console_log(line);
+        console_log("\n");
+    }
+
+    let mut mac_buf = [0u8; 18];
+    let mac_len = format_mac(&info.mac, &mut mac_buf);
+    console_log("        ether ");
+    print(mac_buf.as_ptr(), mac_len);
+    console_log("\n");
+
+    counter_line("RX packets ", stats.rx_packets, "  bytes ", stats.rx_bytes);
+    print_size(stats.rx_bytes);
+    console_log("\n");
+    counter_line("RX errors ", stats.rx_errors, "  dropped ", stats.rx_dropped);
+    console_log("\n");
+    counter_line("TX packets ", stats.tx_packets, "  bytes ", stats.tx_bytes);
+    print_size(stats.tx_bytes);
+    console_log("\n");
+    counter_line("TX errors ", stats.tx_errors, "  dropped ", stats.tx_dropped);
+    console_log("\n");
+
+    fn counter_line(first: &str, a: u64, second: &str, b: u64) {
+        mkfs::console_log("        ");
+        mkfs::console_log(first);
+        mkfs::print_int(a as i64);
+        mkfs::console_log(second);
+        mkfs::print_int(b as i64);
+    }
+
+    /// " (12.3 KiB)", in the largest unit that keeps the number under 1024
+    fn print_size(bytes: u64) {
+        const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+        let mut unit = 0;
+        let mut tenths = bytes * 10;
+        while tenths >= 10240 && unit < UNITS.len() - 1 {
+            tenths /= 1024;
+            unit += 1;
+        }
+        mkfs::console_log(" (");
+        mkfs::print_int((tenths / 10) as i64);
+        if unit > 0 {
+            mkfs::console_log(".");
+            mkfs::print_int((tenths % 10) as i64);
+        }
+        mkfs::console_log(" ");
+        mkfs::console_log(UNITS[unit]);
+        mkfs::console_log(")");
+    }
+}
+
+#[cfg(not(target_arch = "riscv64"))]
+fn main() {}
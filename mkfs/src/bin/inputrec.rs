@@ -0,0 +1,144 @@
+// inputrec - Record and replay GUI input for regression tests
+//
+// Usage:
+//   inputrec record <file>            Start recording touch, mouse and key events
+//   inputrec check [x y w h]          Add a checkpoint: checksum of a screen
+//                                     rectangle (default: the whole screen)
+//   inputrec stop                     Save the recording (or stop a replay)
+//   inputrec replay <file> [speed]    Replay a recording at its recorded pace
+//                                     (or 2-16x faster) and verify checkpoints
+//
+// Live GUI input is ignored during a replay. `replay` waits for the end and
+// exits with code 1 if any checkpoint's screen checksum differs, so it can
+// drive automated GUI tests in the emulator. Run it from the serial console
+// so the commands themselves are not recorded.
+
+#![cfg_attr(target_arch = "riscv64", no_std)]
+#![cfg_attr(target_arch = "riscv64", no_main)]
+
+#[cfg(target_arch = "riscv64")]
+#[no_mangle]
+pub fn main() {
+    use mkfs::{
+        argc, argv, console_log, exit, inputrec_busy, inputrec_check, inputrec_record, inputrec_replay,
+        inputrec_report, inputrec_stop, print, should_cancel, sleep,
+    };
+
+    const USAGE: &str = "Usage: inputrec record <file> | check [x y w h] | stop | replay <file> [speed]\n";
+
+    fn print_num(mut n: u64) {
+        let mut buf = [0u8; 20];
+        let mut i = buf.len();
+        loop {
+            i -= 1;
+            buf[i] = b'0' + (n % 10) as u8;
+            n /= 10;
+            if n == 0 {
+                break;
+            }
+        }
+        print(buf[i..].as_ptr(), buf.len() - i);
+    }
+
+    fn print_hex(n: u32) {
+        let mut buf = [0u8; 8];
+        for (i, b) in buf.iter_mut().enumerate() {
+            let digit = (n >> (28 - i * 4)) & 0xF;
+            *b = if digit < 10 { b'0' + digit as u8 } else { b'a' + digit as u8 - 10 };
+        }
+        print(buf.as_ptr(), buf.len());
+    }
+
+    fn arg_num(index: usize) -> Option<u64> {
+        let mut buf = [0u8; 16];
+        let len = argv(index, &mut buf)?;
+        core::str::from_utf8(&buf[..len]).ok()?.parse().ok()
+    }
+
+    let mut cmd_buf = [0u8; 16];
+    let cmd = if argc() > 0 {
+        argv(0, &mut cmd_buf).and_then(|len| core::str::from_utf8(&cmd_buf[..len]).ok()).unwrap_or("")
+    } else {
+        ""
+    };
+    let mut path_buf = [0u8; 256];
+    let path = if argc() > 1 {
+        argv(1, &mut path_buf).and_then(|len| core::str::from_utf8(&path_buf[..len]).ok())
+    } else {
+        None
+    };
+
+    match (cmd, path) {
+        ("record", Some(path)) => {
+            if inputrec_record(path) {
+                console_log("Recording GUI input; `inputrec check` adds a checkpoint, `inputrec stop` saves\n");
+            } else {
+                console_log("\x1b[1;31m[X]\x1b[0m Cannot record (already recording or replaying?)\n");
+            }
+        }
+        ("check", _) => {
+            let rect = if argc() == 5 {
+                match (arg_num(1), arg_num(2), arg_num(3), arg_num(4)) {
+                    (Some(x), Some(y), Some(w), Some(h)) => (x as u16, y as u16, w as u16, h as u16),
+                    _ => {
+                        console_log(USAGE);
+                        return;
+                    }
+                }
+            } else {
+                (0, 0, 1024, 768)
+            };
+            match inputrec_check(rect.0, rect.1, rect.2, rect.3) {
+                Some(sum) => {
+                    console_log("Checkpoint ");
+                    print_hex(sum);
+                    console_log("\n");
+                }
+                None => console_log("\x1b[1;31m[X]\x1b[0m Not recording\n"),
+            }
+        }
+        ("stop", _) => match inputrec_stop() {
+            Some(0) => console_log("Replay stopped\n"),
+            Some(n) => {
+                console_log("Saved ");
+                print_num(n as u64);
+                console_log(" entries\n");
+            }
+            None => console_log("\x1b[1;31m[X]\x1b[0m Nothing to stop (or the recording could not be saved)\n"),
+        },
+        ("replay", Some(path)) => {
+            let speed = if argc() > 2 { arg_num(2) } else { Some(1) };
+            let Some(count) = speed.and_then(|speed| inputrec_replay(path, speed)) else {
+                console_log("\x1b[1;31m[X]\x1b[0m Cannot replay ");
+                console_log(path);
+                console_log(" (see dmesg)\n");
+                exit(1);
+            };
+            console_log("Replaying ");
+            print_num(count as u64);
+            console_log(" entries from ");
+            console_log(path);
+            console_log("\n");
+
+            while inputrec_busy() {
+                if should_cancel() != 0 {
+                    inputrec_stop();
+                    break;
+                }
+                sleep(100);
+            }
+
+            let mut report = [0u8; 4096];
+            if let Some((len, failed)) = inputrec_report(&mut report) {
+                print(report.as_ptr(), len);
+                if failed > 0 {
+                    exit(1);
+                }
+            }
+        }
+        _ => console_log(USAGE),
+    }
+}
+
+#[cfg(not(target_arch = "riscv64"))]
+fn main() {}
@@ -3,6 +3,9 @@
 // Usage:
 //   ip           Show network interface configuration
 //   ip addr      Show network addresses
+//
+// IPv6 addresses (link-local and SLAAC/static global) and the IPv6 router
+// are listed after the IPv4 configuration.
 
 #![cfg_attr(target_arch = "riscv64", no_std)]
 #![cfg_attr(target_arch = "riscv64", no_main)]
@@ -10,7 +13,7 @@
 #[cfg(target_arch = "riscv64")]
 #[no_mangle]
 pub fn main() {
-    use mkfs::{console_log, is_net_available, get_net_info, format_ipv4, format_mac, print};
+    use mkfs::{console_log, is_net_available, get_net_info, get_net_info6, format_ipv4, format_mac, print};
 
     if !is_net_available() {
         console_log("\x1b[1;31m[X]\x1b[0m Network not initialized\n");
@@ -60,6 +63,23 @@ pub fn main() {
     pad_spaces(47 - dns_len.min(47));
     console_log("\x1b[1;34m|\x1b[0m\n");
 
+    // IPv6: "inet6 <addr>/<len> scope <scope>" and "router <addr>" lines
+    let mut info6 = [0u8; 512];
+    let info6_len = get_net_info6(&mut info6).unwrap_or(0);
+    let info6 = unsafe { core::str::from_utf8_unchecked(&info6[..info6_len]) };
+    for line in info6.lines() {
+        let Some((key, value)) = line.split_once(' ') else {
+            continue;
+        };
+        let label = if key == "router" { "gateway6   " } else { "inet6      " };
+        console_log("\x1b[1;34m|\x1b[0m  \x1b[1;33m");
+        console_log(label);
+        console_log("\x1b[0m ");
+        console_log(value);
+        pad_spaces(47 - value.len().min(47));
+        console_log("\x1b[1;34m|\x1b[0m\n");
+    }
+
     console_log("\x1b[1;34m|\x1b[0m                                                             \x1b[1;34m|\x1b[0m\n");
     console_log("\x1b[1;34m|\x1b[0m  \x1b[1;32mState: UP\x1b[0m    \x1b[0;90mMTU: 1500    Type: VirtIO-Net\x1b[0m              \x1b[1;34m|\x1b[0m\n");
     console_log("\x1b[1;34m+-------------------------------------------------------------+\x1b[0m\n");
@@ -0,0 +1,554 @@
+// irc - Internet Relay Chat client (RFC 1459)
+//
+// Usage:
+//   irc <host>[:port] <nick> [#channel]    Connect (default port 6667)
+//
+// Typed lines go to the current channel or query. Commands:
+//   /join #channel    /part [reason]     /msg <nick> <text>   /query <nick>
+//   /me <text>        /nick <name>       /raw <line>          /quit [message]
+//
+// The connection is serviced while you type: incoming messages are printed
+// as they arrive above the input line, which is then redrawn. PINGs are
+// answered automatically. Ctrl+C disconnects.
+
+#![cfg_attr(target_arch = "riscv64", no_std)]
+#![cfg_attr(target_arch = "riscv64", no_main)]
+
+#[cfg(target_arch = "riscv64")]
+#[no_mangle]
+pub fn main() {
+    use mkfs::{
+        argc, argv, console_available, console_log, is_net_available, print, read_console,
+        should_cancel, sleep, tcp_disconnect, tcp_get_status, tcp_open, tcp_recv_data,
+        tcp_send_data, TcpStatus,
+    };
+
+    const IRC_PORT: u16 = 6667;
+    const TIMEOUT_MS: i64 = 10000;
+    /// Longest IRC line, including the CR LF
+    const MAX_LINE: usize = 512;
+
+    static mut RECV_BUF: [u8; 2048] = [0u8; 2048];
+    static mut LINE_BUF: [u8; MAX_LINE] = [0u8; MAX_LINE];
+
+    struct Session {
+        /// TCP socket handle of the server connection
+        sock: i32,
+        nick: [u8; 32],
+        nick_len: usize,
+        /// Channel or nick that typed lines are sent to
+        target: [u8; 64],
+        target_len: usize,
+        /// Channel to join once registered
+        autojoin: [u8; 64],
+        autojoin_len: usize,
+        registered: bool,
+        input: [u8; 400],
+        input_len: usize,
+        /// Skipping a terminal escape sequence (arrow keys and the like)
+        escape: bool,
+    }
+
+    impl Session {
+        fn nick(&self) -> &[u8] {
+            &self.nick[..self.nick_len]
+        }
+
+        fn target(&self) -> &[u8] {
+            &self.target[..self.target_len]
+        }
+
+        fn set_nick(&mut self, nick: &[u8]) {
+            self.nick_len = nick.len().min(self.nick.len());
+            self.nick[..self.nick_len].copy_from_slice(&nick[..self.nick_len]);
+        }
+
+        fn set_target(&mut self, target: &[u8]) {
+            self.target_len = target.len().min(self.target.len());
+            self.target[..self.target_len].copy_from_slice(&target[..self.target_len]);
+        }
+
+        /// Redraw the input line
+        fn prompt(&self) {
+            if self.target_len > 0 {
+                console_log("\x1b[1;36m[");
+                out(self.target());
+                console_log("]\x1b[0m ");
+            } else {
+                console_log("\x1b[1;36m>\x1b[0m ");
+            }
+            out(&self.input[..self.input_len]);
+        }
+    }
+
+    let mut addr_buf = [0u8; 128];
+    let mut nick_buf = [0u8; 32];
+    let mut chan_buf = [0u8; 64];
+    let (addr_len, nick_len) = match (argv(0, &mut addr_buf), argv(1, &mut nick_buf)) {
+        (Some(a), Some(n)) if a > 0 && n > 0 => (a, n),
+        _ => {
+            console_log("Usage: irc <host>[:port] <nick> [#channel]\n");
+            console_log("Example: irc irc.libera.chat havy #havy\n");
+            return;
+        }
+    };
+    let chan_len = if argc() >= 3 { argv(2, &mut chan_buf).unwrap_or(0) } else { 0 };
+
+    let addr = unsafe { core::str::from_utf8_unchecked(&addr_buf[..addr_len]) };
+    let (host, port) = match addr.rfind(':') {
+        Some(colon) => match addr[colon + 1..].parse() {
+            Ok(port) => (&addr[..colon], port),
+            Err(_) => {
+                console_log("\x1b[1;31mirc:\x1b[0m invalid port\n");
+                return;
+            }
+        },
+        None => (addr, IRC_PORT),
+    };
+
+    if !is_net_available() {
+        console_log("\x1b[1;31m[X]\x1b[0m Network not available\n");
+        return;
+    }
+
+    console_log("Connecting to ");
+    console_log(addr);
+    console_log("...\n");
+    let sock = match tcp_open(host, port, TIMEOUT_MS) {
+        Ok(sock) => sock,
+        Err(e) => {
+            console_log("\x1b[1;31mirc:\x1b[0m ");
+            console_log(e);
+            console_log("\n");
+            return;
+        }
+    };
+
+    let mut session = Session {
+        sock,
+        nick: [0u8; 32],
+        nick_len: 0,
+        target: [0u8; 64],
+        target_len: 0,
+        autojoin: [0u8; 64],
+        autojoin_len: chan_len,
+        registered: false,
+        input: [0u8; 400],
+        input_len: 0,
+        escape: false,
+    };
+    session.set_nick(&nick_buf[..nick_len]);
+    session.autojoin[..chan_len].copy_from_slice(&chan_buf[..chan_len]);
+
+    send(&session, &[b"NICK ", session.nick()]);
+    send(&session, &[b"USER ", session.nick(), b" 0 * :", session.nick()]);
+    session.prompt();
+
+    let recv = unsafe { &mut *core::ptr::addr_of_mut!(RECV_BUF) };
+    let line = unsafe { &mut *core::ptr::addr_of_mut!(LINE_BUF) };
+    let mut line_len = 0;
+
+    'session: loop {
+        if should_cancel() != 0 {
+            send(&session, &[b"QUIT :Leaving"]);
+            break;
+        }
+
+        // Receive side: handle everything that arrived, whatever is being typed
+        match tcp_recv_data(sock, recv, 0) {
+            Some(len) if len > 0 => {
+                for &c in &recv[..len] {
+                    if c == b'\n' || line_len == line.len() {
+                        handle_line(&line[..line_len], &mut session);
+                        line_len = 0;
+                        if c == b'\n' {
+                            continue;
+                        }
+                    }
+                    line[line_len] = c;
+                    line_len += 1;
+                }
+                continue;
+            }
+            _ => {
+                if tcp_get_status(sock) != TcpStatus::Connected {
+                    begin_line();
+                    console_log("\x1b[33mConnection closed by server\x1b[0m\n");
+                    break;
+                }
+            }
+        }
+
+        // Input side
+        while console_available() > 0 {
+            let mut ch = [0u8; 1];
+            if read_console(&mut ch) == 0 {
+                break;
+            }
+            let c = ch[0];
+            if session.escape {
+                // CSI sequences end with a letter or '~'
+                session.escape = !(c.is_ascii_alphabetic() || c == b'~');
+                continue;
+            }
+            match c {
+                0x03 => {
+                    send(&session, &[b"QUIT :Leaving"]);
+                    break 'session;
+                }
+                0x1B => session.escape = true,
+                b'\r' | b'\n' => {
+                    let mut typed = [0u8; 400];
+                    let typed_len = session.input_len;
+                    typed[..typed_len].copy_from_slice(&session.input[..typed_len]);
+                    session.input_len = 0;
+                    begin_line();
+                    if !handle_input(&typed[..typed_len], &mut session) {
+                        break 'session;
+                    }
+                    session.prompt();
+                }
+                0x7F | 0x08 if session.input_len > 0 => {
+                    session.input_len -= 1;
+                    console_log("\x08 \x08");
+                }
+                0x20..=0x7E if session.input_len < session.input.len() => {
+                    session.input[session.input_len] = c;
+                    session.input_len += 1;
+                    out(&ch);
+                }
+                _ => {}
+            }
+        }
+
+        sleep(10);
+    }
+
+    // Give the server a moment to see the QUIT
+    sleep(200);
+    tcp_disconnect(sock);
+    console_log("\n");
+
+    fn out(data: &[u8]) {
+        print(data.as_ptr(), data.len());
+    }
+
+    /// Clear the input line so a message can be printed in its place
+    fn begin_line() {
+        console_log("\r\x1b[K");
+    }
+
+    /// Finish a message and redraw the input line below it
+    fn end_line(session: &Session) {
+        console_log("\x1b[0m\n");
+        session.prompt();
+    }
+
+    /// Send one command; the parts are joined and CR LF is added
+    fn send(session: &Session, parts: &[&[u8]]) -> bool {
+        let mut buf = [0u8; MAX_LINE];
+        let mut len = 0;
+        for part in parts {
+            for &c in part.iter() {
+                // A stray CR or LF would start a second command
+                if len < MAX_LINE - 2 && c != b'\r' && c != b'\n' {
+                    buf[len] = c;
+                    len += 1;
+                }
+            }
+        }
+        buf[len] = b'\r';
+        buf[len + 1] = b'\n';
+        tcp_send_data(session.sock, &buf[..len + 2]).is_some()
+    }
+
+    /// Print server text without control characters or mIRC formatting codes
+    fn print_text(data: &[u8]) {
+        let mut start = 0;
+        let mut i = 0;
+        while i < data.len() {
+            let c = data[i];
+            if c >= 0x20 && c != 0x7F {
+                i += 1;
+                continue;
+            }
+            out(&data[start..i]);
+            i += 1;
+            // ^C colours carry up to two digits of foreground, then ",bg"
+            if c == 0x03 {
+                let digits = |i: &mut usize| {
+                    let end = (*i + 2).min(data.len());
+                    while *i < end && data[*i].is_ascii_digit() {
+                        *i += 1;
+                    }
+                };
+                digits(&mut i);
+                if i + 1 < data.len() && data[i] == b',' && data[i + 1].is_ascii_digit() {
+                    i += 1;
+                    digits(&mut i);
+                }
+            }
+            start = i;
+        }
+        out(&data[start..]);
+    }
+
+    /// Handle one line from the server
+    fn handle_line(line: &[u8], session: &mut Session) {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        if line.is_empty() {
+            return;
+        }
+
+        // [:prefix] COMMAND params [:trailing]
+        let (prefix, rest) = match line.strip_prefix(b":") {
+            Some(rest) => split_word(rest),
+            None => (&b""[..], line),
+        };
+        let from = match prefix.iter().position(|&c| c == b'!') {
+            Some(bang) => &prefix[..bang],
+            None => prefix,
+        };
+        let (command, mut params) = split_word(rest);
+        let mut args: [&[u8]; 15] = [b""; 15];
+        let mut argn = 0;
+        while argn < args.len() {
+            while params.first() == Some(&b' ') {
+                params = &params[1..];
+            }
+            if params.is_empty() {
+                break;
+            }
+            if let Some(trailing) = params.strip_prefix(b":") {
+                args[argn] = trailing;
+                argn += 1;
+                break;
+            }
+            let (word, rest) = split_word(params);
+            args[argn] = word;
+            argn += 1;
+            params = rest;
+        }
+        let last = if argn > 0 { args[argn - 1] } else { b"" };
+
+        match command {
+            b"PING" => {
+                send(session, &[b"PONG :", last]);
+                return;
+            }
+            b"001" => {
+                // The server may have shortened or changed the nick
+                session.set_nick(args[0]);
+                session.registered = true;
+                if session.autojoin_len > 0 {
+                    let mut chan = [0u8; 64];
+                    chan[..session.autojoin_len].copy_from_slice(&session.autojoin[..session.autojoin_len]);
+                    send(session, &[b"JOIN ", &chan[..session.autojoin_len]]);
+                }
+            }
+            b"433" if !session.registered => {
+                // Nick in use: retry with an underscore appended
+                let mut nick = [0u8; 32];
+                let len = session.nick_len.min(nick.len() - 1);
+                nick[..len].copy_from_slice(&session.nick[..len]);
+                nick[len] = b'_';
+                session.set_nick(&nick[..len + 1]);
+                send(session, &[b"NICK ", session.nick()]);
+            }
+            _ => {}
+        }
+
+        begin_line();
+        match command {
+            b"PRIVMSG" | b"NOTICE" => {
+                let to_me = args[0] == session.nick();
+                let channel = if to_me { from } else { args[0] };
+                if let Some(action) = last.strip_prefix(b"\x01ACTION ") {
+                    console_log("\x1b[35m* ");
+                    out(from);
+                    console_log(" ");
+                    print_text(action.strip_suffix(b"\x01").unwrap_or(action));
+                } else if command == b"NOTICE" {
+                    console_log("\x1b[33m-");
+                    out(if from.is_empty() { &b"server"[..] } else { from });
+                    console_log("- ");
+                    print_text(last);
+                } else {
+                    console_log(if to_me { "\x1b[1;35m[" } else { "\x1b[36m[" });
+                    out(channel);
+                    console_log("]\x1b[0m <\x1b[1m");
+                    out(from);
+                    console_log("\x1b[0m> ");
+                    // Mentions of our nick stand out
+                    if contains(last, session.nick()) {
+                        console_log("\x1b[1;33m");
+                    }
+                    print_text(last);
+                }
+            }
+            b"JOIN" => {
+                if from == session.nick() {
+                    session.set_target(args[0]);
+                }
+                console_log("\x1b[32m--> ");
+                out(from);
+                console_log(" joined ");
+                out(args[0]);
+            }
+            b"PART" | b"QUIT" | b"KICK" => {
+                if command == b"PART" && from == session.nick() && args[0] == session.target() {
+                    session.set_target(b"");
+                }
+                console_log("\x1b[31m<-- ");
+                out(from);
+                console_log(match command {
+                    b"PART" => " left ",
+                    b"QUIT" => " quit",
+                    _ => " kicked ",
+                });
+                if command != b"QUIT" {
+                    out(args[0]);
+                }
+                if command == b"KICK" {
+                    console_log(" ");
+                    out(args[1]);
+                }
+                let reason = match command {
+                    b"PART" => argn > 1,
+                    b"QUIT" => argn > 0,
+                    _ => argn > 2,
+                };
+                if reason {
+                    console_log(" (");
+                    print_text(last);
+                    console_log(")");
+                }
+            }
+            b"NICK" => {
+                if from == session.nick() {
+                    session.set_nick(args[0]);
+                }
+                console_log("\x1b[33m");
+                out(from);
+                console_log(" is now known as ");
+                out(args[0]);
+            }
+            b"TOPIC" | b"332" => {
+                console_log("\x1b[33mTopic for ");
+                out(if command == b"TOPIC" { args[0] } else { args[1] });
+                console_log(": ");
+                print_text(last);
+            }
+            b"353" => {
+                console_log("\x1b[90mUsers on ");
+                out(args[2]);
+                console_log(": ");
+                print_text(last);
+            }
+            b"ERROR" => {
+                console_log("\x1b[1;31mError: ");
+                print_text(last);
+            }
+            _ => {
+                // Numerics and everything else: the human-readable part
+                console_log("\x1b[90m");
+                print_text(last);
+            }
+        }
+        end_line(session);
+    }
+
+    /// Handle a typed line. Returns false to disconnect.
+    fn handle_input(typed: &[u8], session: &mut Session) -> bool {
+        if typed.is_empty() {
+            return true;
+        }
+        let command = match typed.strip_prefix(b"/") {
+            Some(command) => command,
+            None => {
+                say(session, typed);
+                return true;
+            }
+        };
+        let (name, arg) = split_word(command);
+
+        match name {
+            b"join" | b"j" if !arg.is_empty() => {
+                send(session, &[b"JOIN ", arg]);
+            }
+            b"part" if session.target_len > 0 => {
+                send(session, &[b"PART ", session.target(), b" :", arg]);
+            }
+            b"msg" => {
+                let (to, text) = split_word(arg);
+                if to.is_empty() || text.is_empty() {
+                    console_log("Usage: /msg <nick> <text>\n");
+                } else {
+                    send(session, &[b"PRIVMSG ", to, b" :", text]);
+                    console_log("\x1b[35m-> ");
+                    out(to);
+                    console_log(":\x1b[0m ");
+                    out(text);
+                    console_log("\n");
+                }
+            }
+            b"query" if !arg.is_empty() => session.set_target(arg),
+            b"me" if session.target_len > 0 => {
+                send(session, &[b"PRIVMSG ", session.target(), b" :\x01ACTION ", arg, b"\x01"]);
+                console_log("\x1b[35m* ");
+                out(session.nick());
+                console_log(" ");
+                out(arg);
+                console_log("\x1b[0m\n");
+            }
+            b"nick" if !arg.is_empty() => {
+                send(session, &[b"NICK ", arg]);
+            }
+            b"raw" | b"quote" if !arg.is_empty() => {
+                send(session, &[arg]);
+            }
+            b"quit" => {
+                send(session, &[b"QUIT :", if arg.is_empty() { &b"Leaving"[..] } else { arg }]);
+                return false;
+            }
+            b"part" | b"me" => console_log("Not in a channel, /join one first\n"),
+            _ => {
+                console_log("Commands: /join #chan, /part, /msg <nick> <text>, /query <nick>,\n");
+                console_log("          /me <text>, /nick <name>, /raw <line>, /quit [message]\n");
+            }
+        }
+        true
+    }
+
+    /// Send a typed line to the current channel or query
+    fn say(session: &Session, text: &[u8]) {
+        if session.target_len == 0 {
+            console_log("Not in a channel, /join one first\n");
+            return;
+        }
+        send(session, &[b"PRIVMSG ", session.target(), b" :", text]);
+        // Servers do not echo our own messages back
+        console_log("\x1b[36m[");
+        out(session.target());
+        console_log("]\x1b[0m <\x1b[1;32m");
+        out(session.nick());
+        console_log("\x1b[0m> ");
+        out(text);
+        console_log("\n");
+    }
+
+    /// Split at the first space: (word, rest)
+    fn split_word(s: &[u8]) -> (&[u8], &[u8]) {
+        match s.iter().position(|&c| c == b' ') {
+            Some(space) => (&s[..space], &s[space + 1..]),
+            None => (s, b""),
+        }
+    }
+
+    fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+        !needle.is_empty() && haystack.windows(needle.len()).any(|w| w.eq_ignore_ascii_case(needle))
+    }
+}
+
+#[cfg(not(target_arch = "riscv64"))]
+fn main() {}
@@ -1,8 +1,17 @@
-// kill - Terminate a process by PID
+// kill - Send a signal to processes
 //
 // Usage:
-//   kill <pid>     Terminate the process with the given PID
-//   kill           Show usage information
+//   kill <pid>...              Terminate the processes with SIGKILL
+//   kill -<sig> <pid>...       Send a signal by number or name (-15, -TERM)
+//   kill -s <sig> <pid>...     Same, signal as a separate argument
+//   kill -<sig> -- -<pgid>     Signal every process in a process group
+//   kill -l                    List signal names
+//   kill                       Show usage information
+//
+// A negative PID names a process group, so a whole pipeline can be stopped
+// at once. Killing the PID of a running WASM program (a foreground command
+// or the worker running a wasmrun job) aborts the program. After a signal option, "--" ends the options so the group can
+// be given (kill -- -12 signals group 12 with SIGKILL).
 
 #![cfg_attr(target_arch = "riscv64", no_std)]
 #![cfg_attr(target_arch = "riscv64", no_main)]
@@ -10,17 +19,25 @@
 #[cfg(target_arch = "riscv64")]
 #[no_mangle]
 pub fn main() {
-    use mkfs::{argc, argv, console_log, kill_process, print_int, print, KillResult};
+    use mkfs::{argc, argv, console_log, print, print_int, signal_process, KillResult, SIGCONT, SIGINT, SIGKILL, SIGSTOP, SIGTERM, SIGTSTP};
 
-    static mut PID_BUF: [u8; 16] = [0u8; 16];
+    /// Signals known by name
+    const SIGNALS: [(&[u8], i32); 6] = [
+        (b"INT", SIGINT),
+        (b"KILL", SIGKILL),
+        (b"TERM", SIGTERM),
+        (b"CONT", SIGCONT),
+        (b"STOP", SIGSTOP),
+        (b"TSTP", SIGTSTP),
+    ];
 
-    fn parse_pid(bytes: &[u8]) -> Option<u32> {
+    fn parse_u32(bytes: &[u8]) -> Option<u32> {
         if bytes.is_empty() {
             return None;
         }
         let mut result: u32 = 0;
         for &c in bytes {
-            if c < b'0' || c > b'9' {
+            if !c.is_ascii_digit() {
                 return None;
             }
             let digit = (c - b'0') as u32;
@@ -29,58 +46,129 @@ pub fn main() {
         Some(result)
     }
 
-    let arg_count = argc();
-    
-    if arg_count < 1 {
-        console_log("Usage: kill <pid>\n");
+    /// "15", "TERM" or "SIGTERM"
+    fn parse_signal(bytes: &[u8]) -> Option<i32> {
+        if let Some(n) = parse_u32(bytes) {
+            return (1..32).contains(&n).then_some(n as i32);
+        }
+        let name = bytes.strip_prefix(b"SIG").unwrap_or(bytes);
+        SIGNALS.iter().find(|(known, _)| known.eq_ignore_ascii_case(name)).map(|&(_, sig)| sig)
+    }
+
+    /// "12", or "-12" for process group 12
+    fn parse_target(bytes: &[u8]) -> Option<i32> {
+        let (group, digits) = match bytes.strip_prefix(b"-") {
+            Some(digits) => (true, digits),
+            None => (false, bytes),
+        };
+        let n = i32::try_from(parse_u32(digits)?).ok()?;
+        Some(if group { -n } else { n })
+    }
+
+    fn arg(i: usize, buf: &mut [u8; 16]) -> &[u8] {
+        let len = argv(i, buf).unwrap_or(0);
+        &buf[..len]
+    }
+
+    fn usage() {
+        console_log("Usage: kill [-s <sig> | -<sig>] <pid|-pgid>...\n");
+        console_log("       kill -l\n");
         console_log("\n");
-        console_log("Terminate a process by its PID.\n");
+        console_log("Send a signal (default KILL) to processes by PID.\n");
+        console_log("A negative PID signals a whole process group.\n");
         console_log("Use 'ps' to list running processes.\n");
-        return;
     }
 
-    let len = unsafe {
-        match argv(0, &mut *core::ptr::addr_of_mut!(PID_BUF)) {
-            Some(l) => l,
-            None => {
-                console_log("\x1b[1;31mError:\x1b[0m Could not read PID argument\n");
-                return;
+    fn report(target: i32, result: KillResult) {
+        let (what, id) = if target < 0 { ("Process group ", -target) } else { ("Process ", target) };
+        match result {
+            KillResult::Success => {
+                console_log("\x1b[1;32m[OK]\x1b[0m Signalled ");
+                console_log(if target < 0 { "process group " } else { "process " });
+                print_int(id as i64);
+                console_log("\n");
+            }
+            KillResult::CannotKill => {
+                console_log("\x1b[1;31mError:\x1b[0m Cannot kill init (PID 1)\n");
+            }
+            KillResult::NotFound => {
+                console_log("\x1b[1;31mError:\x1b[0m ");
+                console_log(what);
+                print_int(id as i64);
+                console_log(" not found\n");
+            }
+            KillResult::InvalidPid => {
+                console_log("\x1b[1;31mError:\x1b[0m Invalid PID\n");
+            }
+            KillResult::InvalidSignal => {
+                console_log("\x1b[1;31mError:\x1b[0m Invalid signal\n");
             }
         }
-    };
-
-    let pid_bytes = unsafe { &(*core::ptr::addr_of!(PID_BUF))[..len] };
-    let pid = match parse_pid(pid_bytes) {
-        Some(p) => p,
-        None => {
-            console_log("\x1b[1;31mError:\x1b[0m Invalid PID: ");
-            unsafe { print((*core::ptr::addr_of!(PID_BUF)).as_ptr(), len) };
-            console_log("\n");
-            return;
-        }
-    };
+    }
 
-    if pid == 0 {
-        console_log("\x1b[1;31mError:\x1b[0m Invalid PID: 0\n");
+    let arg_count = argc();
+    if arg_count < 1 {
+        usage();
         return;
     }
 
-    match kill_process(pid) {
-        KillResult::Success => {
-            console_log("\x1b[1;32m[OK]\x1b[0m Killed process ");
-            print_int(pid as i64);
+    let mut sig = SIGKILL;
+    let mut first = 0;
+    let mut option_buf = [0u8; 16];
+    let mut buf = [0u8; 16];
+    let option = arg(0, &mut option_buf);
+    if option == b"-l" {
+        for (name, number) in SIGNALS {
+            print_int(number as i64);
+            console_log(") SIG");
+            print(name.as_ptr(), name.len());
             console_log("\n");
         }
-        KillResult::CannotKill => {
-            console_log("\x1b[1;31mError:\x1b[0m Cannot kill init (PID 1)\n");
+        return;
+    } else if option == b"-s" {
+        match parse_signal(arg(1, &mut buf)) {
+            Some(s) => sig = s,
+            None => {
+                console_log("\x1b[1;31mError:\x1b[0m Invalid signal\n");
+                return;
+            }
         }
-        KillResult::NotFound => {
-            console_log("\x1b[1;31mError:\x1b[0m Process ");
-            print_int(pid as i64);
-            console_log(" not found\n");
+        first = 2;
+    } else if option == b"--" {
+        first = 1;
+    } else if let Some(spec) = option.strip_prefix(b"-") {
+        // "-15" is a signal when PIDs follow, a process group otherwise
+        if arg_count > 1 {
+            match parse_signal(spec) {
+                Some(s) => sig = s,
+                None => {
+                    console_log("\x1b[1;31mError:\x1b[0m Invalid signal: ");
+                    print(option.as_ptr(), option.len());
+                    console_log("\n");
+                    return;
+                }
+            }
+            first = 1;
         }
-        KillResult::InvalidPid => {
-            console_log("\x1b[1;31mError:\x1b[0m Invalid PID\n");
+    }
+    if first < arg_count && arg(first, &mut buf) == b"--" {
+        first += 1;
+    }
+    if first >= arg_count {
+        usage();
+        return;
+    }
+
+    for i in first..arg_count {
+        let bytes = arg(i, &mut buf);
+        match parse_target(bytes) {
+            Some(0) => console_log("\x1b[1;31mError:\x1b[0m Invalid PID: 0\n"),
+            Some(target) => report(target, signal_process(target, sig)),
+            None => {
+                console_log("\x1b[1;31mError:\x1b[0m Invalid PID: ");
+                print(bytes.as_ptr(), bytes.len());
+                console_log("\n");
+            }
         }
     }
 }
@@ -0,0 +1,85 @@
+// kmod - Kernel module management
+//
+// Usage:
+//   kmod list                 List loaded kernel modules
+//   kmod load <path>          Load a signed WASM module (needs <path>.sig)
+//   kmod unload <name>        Unload a module by name
+//
+// Modules must be signed by a key listed in /etc/kmod/trusted_keys. Load
+// errors (bad signature, trap in kmod_init, ...) are written to the kernel
+// log; see dmesg.
+
+#![cfg_attr(target_arch = "riscv64", no_std)]
+#![cfg_attr(target_arch = "riscv64", no_main)]
+
+#[cfg(target_arch = "riscv64")]
+#[no_mangle]
+pub fn main() {
+    use mkfs::{console_log, argc, argv, list_modules, load_module, print, unload_module};
+
+    static mut LIST_BUF: [u8; 2048] = [0u8; 2048];
+    static mut ARG_BUF: [u8; 256] = [0u8; 256];
+
+    let mut cmd_buf = [0u8; 32];
+    let cmd_len = if argc() >= 1 { argv(0, &mut cmd_buf).unwrap_or(0) } else { 0 };
+    let cmd = &cmd_buf[..cmd_len];
+
+    if cmd == b"list" || cmd.is_empty() {
+        let len = match unsafe { list_modules(&mut *core::ptr::addr_of_mut!(LIST_BUF)) } {
+            Some(len) => len,
+            None => {
+                console_log("\x1b[1;31mkmod:\x1b[0m could not read module list\n");
+                return;
+            }
+        };
+        if len == 0 {
+            console_log("No kernel modules loaded\n");
+            return;
+        }
+        console_log("\x1b[1mMODULE\tSTATE\tPROVIDES\x1b[0m\n");
+        let data = unsafe { &(*core::ptr::addr_of!(LIST_BUF))[..len] };
+        print(data.as_ptr(), data.len());
+        return;
+    }
+
+    if cmd != b"load" && cmd != b"unload" {
+        console_log("Usage: kmod <list|load <path>|unload <name>>\n");
+        return;
+    }
+
+    let arg_len = match unsafe { argv(1, &mut *core::ptr::addr_of_mut!(ARG_BUF)) } {
+        Some(len) if len > 0 => len,
+        _ => {
+            if cmd == b"load" {
+                console_log("Usage: kmod load <path>\n");
+            } else {
+                console_log("Usage: kmod unload <name>\n");
+            }
+            return;
+        }
+    };
+    let arg = unsafe { core::str::from_utf8_unchecked(&(*core::ptr::addr_of!(ARG_BUF))[..arg_len]) };
+
+    if cmd == b"load" {
+        if load_module(arg) {
+            console_log("\x1b[1;32m[OK]\x1b[0m Loaded ");
+            console_log(arg);
+            console_log("\n");
+        } else {
+            console_log("\x1b[1;31mkmod:\x1b[0m failed to load '");
+            console_log(arg);
+            console_log("' (see dmesg)\n");
+        }
+    } else if unload_module(arg) {
+        console_log("\x1b[1;32m[OK]\x1b[0m Unloaded ");
+        console_log(arg);
+        console_log("\n");
+    } else {
+        console_log("\x1b[1;31mkmod:\x1b[0m module '");
+        console_log(arg);
+        console_log("' is not loaded\n");
+    }
+}
+
+#[cfg(not(target_arch = "riscv64"))]
+fn main() {}
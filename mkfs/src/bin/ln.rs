@@ -0,0 +1,102 @@
+// ln - Make links between files
+//
+// Usage:
+//   ln -s <target> <link>    Create a symbolic link named <link> pointing to <target>
+//   ln -sv <target> <link>   Verbose output
+//
+// The target is stored as given: relative targets are resolved against the
+// directory holding the link when it is followed. Hard links are not
+// supported by SFS.
+
+#![cfg_attr(target_arch = "riscv64", no_std)]
+#![cfg_attr(target_arch = "riscv64", no_main)]
+
+#[cfg(target_arch = "riscv64")]
+#[no_mangle]
+pub fn main() {
+    use mkfs::{console_log, argc, argv, file_exists, print, symlink};
+
+    static mut ARG_BUF: [u8; 256] = [0u8; 256];
+    static mut TARGET_BUF: [u8; 256] = [0u8; 256];
+    static mut LINK_BUF: [u8; 256] = [0u8; 256];
+
+    let arg_count = argc();
+    let mut symbolic = false;
+    let mut verbose = false;
+    let mut target_len = 0;
+    let mut link_len = 0;
+    let mut operands = 0;
+
+    for i in 0..arg_count {
+        let len = match unsafe { argv(i, &mut *core::ptr::addr_of_mut!(ARG_BUF)) } {
+            Some(len) => len,
+            None => continue,
+        };
+        let arg = unsafe { &(*core::ptr::addr_of!(ARG_BUF))[..len] };
+
+        if arg.len() > 1 && arg[0] == b'-' {
+            for &ch in &arg[1..] {
+                match ch {
+                    b's' => symbolic = true,
+                    b'v' => verbose = true,
+                    _ => {
+                        console_log("\x1b[1;31mln:\x1b[0m unknown option '-");
+                        print(&ch as *const u8, 1);
+                        console_log("'\n");
+                        return;
+                    }
+                }
+            }
+            continue;
+        }
+
+        match operands {
+            0 => unsafe {
+                (*core::ptr::addr_of_mut!(TARGET_BUF))[..len].copy_from_slice(arg);
+                target_len = len;
+            },
+            1 => unsafe {
+                (*core::ptr::addr_of_mut!(LINK_BUF))[..len].copy_from_slice(arg);
+                link_len = len;
+            },
+            _ => {}
+        }
+        operands += 1;
+    }
+
+    if operands != 2 {
+        console_log("Usage: ln -s [-v] <target> <link>\n");
+        return;
+    }
+    if !symbolic {
+        console_log("\x1b[1;31mln:\x1b[0m hard links are not supported, use -s\n");
+        return;
+    }
+
+    let target = unsafe { core::str::from_utf8_unchecked(&(*core::ptr::addr_of!(TARGET_BUF))[..target_len]) };
+    let link = unsafe { core::str::from_utf8_unchecked(&(*core::ptr::addr_of!(LINK_BUF))[..link_len]) };
+
+    if file_exists(link) {
+        console_log("\x1b[1;31mln:\x1b[0m '");
+        console_log(link);
+        console_log("' already exists\n");
+        return;
+    }
+
+    if symlink(target, link) {
+        if verbose {
+            console_log("'");
+            console_log(link);
+            console_log("' -> '");
+            console_log(target);
+            console_log("'\n");
+        }
+    } else {
+        console_log("\x1b[1;31mln:\x1b[0m failed to create symbolic link '");
+        console_log(link);
+        console_log("'\n");
+    }
+}
+
+#[cfg(not(target_arch = "riscv64"))]
+fn main() {}
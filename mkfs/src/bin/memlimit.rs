@@ -0,0 +1,114 @@
+// memlimit - Show or set how much heap a process may use
+//
+// Usage:
+//   memlimit <pid>            Show the heap the process uses and its cap
+//   memlimit <size> <pid>     Cap the process's heap use
+//   memlimit none <pid>       Remove the cap
+//
+// Sizes are bytes, or a number with a K or M suffix, e.g. 512K. Once a
+// process reaches its cap its allocations fail instead of using up the
+// heap every process shares.
+// The cap applies to new allocations; memory already in use is kept.
+
+#![cfg_attr(target_arch = "riscv64", no_std)]
+#![cfg_attr(target_arch = "riscv64", no_main)]
+
+#[cfg(target_arch = "riscv64")]
+#[no_mangle]
+pub fn main() {
+    use mkfs::{argc, argv, console_log, print_int, process_memory, set_memory_limit, MemLimitResult};
+
+    const USAGE: &str = "Usage: memlimit [<size>|none] <pid>\n\x1b[0;90mExample: memlimit 512K 12\x1b[0m\n";
+
+    fn arg(i: usize, buf: &mut [u8; 64]) -> &str {
+        let len = argv(i, buf).unwrap_or(0);
+        core::str::from_utf8(&buf[..len]).unwrap_or("")
+    }
+
+    /// "4096", "512K", "2M" or "none" (Some(None))
+    fn parse_size(text: &str) -> Option<Option<u64>> {
+        if text == "none" {
+            return Some(None);
+        }
+        let (digits, unit) = match text.as_bytes().last()? {
+            b'K' | b'k' => (&text[..text.len() - 1], 1024),
+            b'M' | b'm' => (&text[..text.len() - 1], 1024 * 1024),
+            _ => (text, 1),
+        };
+        let bytes = digits.parse::<u64>().ok()?.checked_mul(unit)?;
+        (bytes > 0).then_some(Some(bytes))
+    }
+
+    fn print_size(bytes: u64) {
+        if bytes >= 1024 * 1024 {
+            print_int((bytes / (1024 * 1024)) as i64);
+            console_log(".");
+            print_int((bytes % (1024 * 1024) * 10 / (1024 * 1024)) as i64);
+            console_log("M");
+        } else if bytes >= 1024 {
+            print_int(bytes.div_ceil(1024) as i64);
+            console_log("K");
+        } else {
+            print_int(bytes as i64);
+            console_log("B");
+        }
+    }
+
+    let mut buf = [0u8; 64];
+    let (size, pid_arg) = match argc() {
+        1 => (None, 0),
+        2 => (Some(parse_size(arg(0, &mut buf))), 1),
+        _ => {
+            console_log(USAGE);
+            return;
+        }
+    };
+    let pid = match arg(pid_arg, &mut buf).parse::<u32>() {
+        Ok(pid) if pid > 0 => pid,
+        _ => {
+            console_log(USAGE);
+            return;
+        }
+    };
+
+    match size {
+        None => {}
+        Some(None) => {
+            console_log("\x1b[1;31mError:\x1b[0m Invalid size\n");
+            return;
+        }
+        Some(Some(limit)) => match set_memory_limit(pid, limit) {
+            MemLimitResult::Success => {}
+            MemLimitResult::TooMany => {
+                console_log("\x1b[1;31mError:\x1b[0m Too many processes have limits or use the heap\n");
+                return;
+            }
+            MemLimitResult::NotFound => {
+                console_log("\x1b[1;31mError:\x1b[0m Process ");
+                print_int(pid as i64);
+                console_log(" not found\n");
+                return;
+            }
+        },
+    }
+
+    let Some((used, limit)) = process_memory(pid) else {
+        console_log("\x1b[1;31mError:\x1b[0m Process ");
+        print_int(pid as i64);
+        console_log(" not found\n");
+        return;
+    };
+    console_log("pid ");
+    print_int(pid as i64);
+    console_log(" uses ");
+    print_size(used);
+    console_log(" of heap, limit ");
+    match limit {
+        Some(limit) => print_size(limit),
+        None => console_log("none"),
+    }
+    console_log("\n");
+}
+
+#[cfg(not(target_arch = "riscv64"))]
+fn main() {}
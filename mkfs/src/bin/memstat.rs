@@ -0,0 +1,29 @@
+// memstat - Kernel heap use per subsystem
+//
+// Usage:
+//   memstat             Show bytes and blocks each subsystem holds
+//
+// Every kernel heap block is counted against the subsystem it was allocated
+// for (net, fs, wasm, ui, elf, audio, or kernel for the rest) until it is
+// freed. When the heap runs low, the subsystem whose bytes keep growing is
+// the one to look at; `heapdiff` then narrows it down to call sites.
+
+#![cfg_attr(target_arch = "riscv64", no_std)]
+#![cfg_attr(target_arch = "riscv64", no_main)]
+
+#[cfg(target_arch = "riscv64")]
+#[no_mangle]
+pub fn main() {
+    use mkfs::{console_log, memstat_report, print};
+
+    static mut REPORT_BUF: [u8; 2048] = [0u8; 2048];
+
+    let buf = unsafe { &mut *core::ptr::addr_of_mut!(REPORT_BUF) };
+    match memstat_report(buf) {
+        Some(len) => print(buf.as_ptr(), len),
+        None => console_log("\x1b[1;31m[X]\x1b[0m memstat: cannot read heap statistics\n"),
+    }
+}
+
+#[cfg(not(target_arch = "riscv64"))]
+fn main() {}
@@ -3,6 +3,9 @@
 // Usage:
 //   memtest              Run with 10 iterations
 //   memtest <N>          Run with N iterations
+//
+// Reports how long the iterations took and the fill/verify throughput,
+// timed with the monotonic clock.
 
 #![cfg_attr(target_arch = "riscv64", no_std)]
 #![cfg_attr(target_arch = "riscv64", no_main)]
@@ -10,7 +13,7 @@
 #[cfg(target_arch = "riscv64")]
 #[no_mangle]
 pub fn main() {
-    use mkfs::{console_log, print_int, argc, argv};
+    use mkfs::{console_log, print_int, argc, argv, monotonic_ns};
 
     static mut TEST_BUF: [u8; 1024] = [0u8; 1024];
 
@@ -39,6 +42,7 @@ pub fn main() {
     let mut success_count = 0usize;
     let mut fail_count = 0usize;
 
+    let start = monotonic_ns();
     for i in 0..iterations {
         let pattern = ((i % 256) as u8).wrapping_add(0x42);
 
@@ -62,6 +66,7 @@ pub fn main() {
 
         if ok { success_count += 1; } else { fail_count += 1; }
     }
+    let elapsed_ns = monotonic_ns() - start;
 
     console_log("Results: ");
     print_int(success_count as i64);
@@ -69,6 +74,14 @@ pub fn main() {
     print_int(fail_count as i64);
     console_log(" failed.\n");
 
+    // Each iteration writes and reads the buffer once
+    let bytes = (iterations * 1024 * 2) as u64;
+    console_log("Time: ");
+    print_int((elapsed_ns / 1000) as i64);
+    console_log(" us (");
+    print_int((bytes as u128 * 1_000_000_000 / elapsed_ns.max(1) as u128 / 1024) as i64);
+    console_log(" KiB/s)\n");
+
     console_log("\n\x1b[90mNote: Native memtest uses static buffers (no dynamic allocation)\x1b[0m\n");
 }
 
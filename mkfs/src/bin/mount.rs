@@ -0,0 +1,74 @@
+// mount - Mount a host directory served over 9P
+//
+// Usage:
+//   mount                                 List mount points
+//   mount -t 9p <host>[:port] <dir>       Mount a 9P server (default port 564)
+//
+// The host runs a 9P2000.L server such as diod, e.g.
+//   diod -f -n -e /path/to/project -l 0.0.0.0:564
+
+#![cfg_attr(target_arch = "riscv64", no_std)]
+#![cfg_attr(target_arch = "riscv64", no_main)]
+
+#[cfg(target_arch = "riscv64")]
+#[no_mangle]
+pub fn main() {
+    use mkfs::{argc, argv, console_log, get_mounts, mount_9p_tcp, resolve_host};
+
+    const DEFAULT_PORT: u16 = 564;
+
+    fn usage() {
+        console_log("Usage: mount\n");
+        console_log("       mount -t 9p <host>[:port] <dir>\n");
+    }
+
+    if argc() == 0 {
+        let mut buf = [0u8; 1024];
+        if let Some(len) = get_mounts(&mut buf) {
+            console_log(unsafe { core::str::from_utf8_unchecked(&buf[..len]) });
+        }
+        return;
+    }
+
+    let mut arg_bufs = [[0u8; 128]; 4];
+    let mut lens = [0usize; 4];
+    let count = argc().min(4);
+    for i in 0..count {
+        lens[i] = argv(i, &mut arg_bufs[i]).unwrap_or(0);
+    }
+    let arg = |i: usize| unsafe { core::str::from_utf8_unchecked(&arg_bufs[i][..lens[i]]) };
+
+    if count != 4 || arg(0) != "-t" {
+        return usage();
+    }
+    if arg(1) != "9p" {
+        console_log("\x1b[1;31mmount:\x1b[0m only 9p is supported\n");
+        return;
+    }
+
+    let (host, port) = match arg(2).rsplit_once(':') {
+        Some((host, port)) => match port.parse::<u16>() {
+            Ok(port) => (host, port),
+            Err(_) => {
+                console_log("\x1b[1;31mmount:\x1b[0m invalid port\n");
+                return;
+            }
+        },
+        None => (arg(2), DEFAULT_PORT),
+    };
+    let dir = arg(3);
+
+    let mut ip = [0u8; 4];
+    if !resolve_host(host, &mut ip) {
+        console_log("\x1b[1;31mmount:\x1b[0m could not resolve host\n");
+        return;
+    }
+    if !mount_9p_tcp(&ip, port, dir) {
+        console_log("\x1b[1;31mmount:\x1b[0m could not mount ");
+        console_log(arg(2));
+        console_log(" (already mounted, or no 9P server there)\n");
+    }
+}
+
+#[cfg(not(target_arch = "riscv64"))]
+fn main() {}
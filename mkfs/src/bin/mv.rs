@@ -0,0 +1,117 @@
+// mv - Move (rename) files and directories
+//
+// Usage:
+//   mv <source> <dest>       Rename <source> to <dest>
+//   mv <source> <dir>        Move <source> into an existing directory
+//   mv -v <source> <dest>    Verbose output
+//
+// Only directory entries are rewritten, so moves are atomic and never copy
+// file data. Moving across mount points is not supported.
+
+#![cfg_attr(target_arch = "riscv64", no_std)]
+#![cfg_attr(target_arch = "riscv64", no_main)]
+
+#[cfg(target_arch = "riscv64")]
+#[no_mangle]
+pub fn main() {
+    use mkfs::{console_log, argc, argv, file_exists, is_dir, print, rename};
+
+    static mut ARG_BUF: [u8; 256] = [0u8; 256];
+    static mut SRC_BUF: [u8; 256] = [0u8; 256];
+    static mut DEST_BUF: [u8; 512] = [0u8; 512];
+
+    let arg_count = argc();
+    let mut verbose = false;
+    let mut src_len = 0;
+    let mut dest_len = 0;
+    let mut operands = 0;
+
+    for i in 0..arg_count {
+        let len = match unsafe { argv(i, &mut *core::ptr::addr_of_mut!(ARG_BUF)) } {
+            Some(len) => len,
+            None => continue,
+        };
+        let arg = unsafe { &(*core::ptr::addr_of!(ARG_BUF))[..len] };
+
+        if arg.len() > 1 && arg[0] == b'-' {
+            for &ch in &arg[1..] {
+                match ch {
+                    b'v' => verbose = true,
+                    _ => {
+                        console_log("\x1b[1;31mmv:\x1b[0m unknown option '-");
+                        print(&ch as *const u8, 1);
+                        console_log("'\n");
+                        return;
+                    }
+                }
+            }
+            continue;
+        }
+
+        match operands {
+            0 => unsafe {
+                (*core::ptr::addr_of_mut!(SRC_BUF))[..len].copy_from_slice(arg);
+                src_len = len;
+            },
+            1 => unsafe {
+                (*core::ptr::addr_of_mut!(DEST_BUF))[..len].copy_from_slice(arg);
+                dest_len = len;
+            },
+            _ => {}
+        }
+        operands += 1;
+    }
+
+    if operands != 2 {
+        console_log("Usage: mv [-v] <source> <dest>\n");
+        return;
+    }
+
+    let src = unsafe { core::str::from_utf8_unchecked(&(*core::ptr::addr_of!(SRC_BUF))[..src_len]) };
+
+    if !file_exists(src) && !is_dir(src) {
+        console_log("\x1b[1;31mmv:\x1b[0m cannot stat '");
+        console_log(src);
+        console_log("': No such file or directory\n");
+        return;
+    }
+
+    // Moving into a directory keeps the source's name
+    let dest_dir = unsafe { core::str::from_utf8_unchecked(&(*core::ptr::addr_of!(DEST_BUF))[..dest_len]) };
+    if is_dir(dest_dir) {
+        let trimmed = src.trim_end_matches('/');
+        let name = match trimmed.rfind('/') {
+            Some(pos) => &trimmed[pos + 1..],
+            None => trimmed,
+        };
+        let dest = unsafe { &mut *core::ptr::addr_of_mut!(DEST_BUF) };
+        if dest_len > 0 && dest[dest_len - 1] != b'/' && dest_len < dest.len() {
+            dest[dest_len] = b'/';
+            dest_len += 1;
+        }
+        let n = name.len().min(dest.len() - dest_len);
+        dest[dest_len..dest_len + n].copy_from_slice(&name.as_bytes()[..n]);
+        dest_len += n;
+    }
+
+    let dest = unsafe { core::str::from_utf8_unchecked(&(*core::ptr::addr_of!(DEST_BUF))[..dest_len]) };
+
+    if rename(src, dest) {
+        if verbose {
+            console_log("renamed '");
+            console_log(src);
+            console_log("' -> '");
+            console_log(dest);
+            console_log("'\n");
+        }
+    } else {
+        console_log("\x1b[1;31mmv:\x1b[0m cannot move '");
+        console_log(src);
+        console_log("' to '");
+        console_log(dest);
+        console_log("'\n");
+    }
+}
+
+#[cfg(not(target_arch = "riscv64"))]
+fn main() {}
@@ -2,6 +2,10 @@
 //
 // Usage:
 //   netstat      Display network configuration and statistics
+//
+// Below the configuration, every socket is listed with its state and
+// traffic: bytes received and sent, TCP retransmissions and the smoothed
+// round trip time ("-" until measured).
 
 #![cfg_attr(target_arch = "riscv64", no_std)]
 #![cfg_attr(target_arch = "riscv64", no_main)]
@@ -9,7 +13,7 @@
 #[cfg(target_arch = "riscv64")]
 #[no_mangle]
 pub fn main() {
-    use mkfs::{console_log, is_net_available, get_net_info, format_ipv4, format_mac, print};
+    use mkfs::{console_log, is_net_available, get_net_info, get_net_stats, format_ipv4, format_mac, print};
 
     if !is_net_available() {
         console_log("\x1b[1;31m[X]\x1b[0m Network not initialized\n");
@@ -76,6 +80,51 @@ pub fn main() {
     console_log("\x1b[1;35m+-------------------------------------------------------------+\x1b[0m\n");
     console_log("\n");
 
+    // Socket table
+    let mut stats_buf = [0u8; 4096];
+    let Some(stats_len) = get_net_stats(&mut stats_buf) else {
+        console_log("\x1b[1;31m[X]\x1b[0m Could not get socket statistics\n");
+        return;
+    };
+    console_log("\x1b[1;33mActive sockets:\x1b[0m\n");
+    console_log("\x1b[1mProto Local                 Remote                State         Recv-B    Send-B  Retr   RTT\x1b[0m\n");
+    let text = core::str::from_utf8(&stats_buf[..stats_len]).unwrap_or("");
+    for line in text.lines() {
+        let mut fields = line.split(' ');
+        let mut next = || fields.next().unwrap_or("?");
+        let (proto, local, remote, state) = (next(), next(), next(), next());
+        let (bytes_in, bytes_out, _packets_in, _packets_out) = (next(), next(), next(), next());
+        let (retransmits, srtt_ms) = (next(), next());
+
+        pad_right(proto, 6);
+        pad_right(local, 22);
+        pad_right(remote, 22);
+        pad_right(state, 12);
+        pad_left(bytes_in, 8);
+        pad_left(bytes_out, 10);
+        pad_left(retransmits, 6);
+        if srtt_ms == "0" {
+            pad_left("-", 6);
+        } else {
+            pad_left(srtt_ms, 3);
+            console_log(" ms");
+        }
+        console_log("\n");
+    }
+    console_log("\n");
+
+    /// Print `text`, then spaces up to `width` (at least one)
+    fn pad_right(text: &str, width: usize) {
+        mkfs::console_log(text);
+        pad_spaces(width.saturating_sub(text.len()).max(1));
+    }
+
+    /// Print spaces up to `width`, then `text`
+    fn pad_left(text: &str, width: usize) {
+        pad_spaces(width.saturating_sub(text.len()));
+        mkfs::console_log(text);
+    }
+
     fn pad_spaces(count: usize) {
         for _ in 0..count {
             mkfs::console_log(" ");
@@ -1,7 +1,12 @@
 // nslookup - DNS lookup utility
 //
 // Usage:
-//   nslookup <hostname>    Look up hostname via DNS
+//   nslookup <hostname>               Look up A and AAAA records
+//   nslookup -type=<type> <hostname>  Look up records of one type
+//
+// Types are a, aaaa, cname, txt, srv, a record type number, or all for
+// every type listed. Aliases (CNAMEs) leading to the answer are shown too.
+// Queries always go to the server; /etc/hosts and the cache are skipped.
 
 #![cfg_attr(target_arch = "riscv64", no_std)]
 #![cfg_attr(target_arch = "riscv64", no_main)]
@@ -9,11 +14,49 @@
 #[cfg(target_arch = "riscv64")]
 #[no_mangle]
 pub fn main() {
-    use mkfs::{console_log, is_net_available, argc, argv, resolve_dns, format_ipv4, print};
+    use mkfs::{
+        argc, argv, console_log, is_net_available, print, query_dns, DnsQuery, DNS_TYPE_A, DNS_TYPE_AAAA, DNS_TYPE_CNAME,
+        DNS_TYPE_SRV, DNS_TYPE_TXT,
+    };
+
+    const USAGE: &str = "Usage: nslookup [-type=a|aaaa|cname|txt|srv|all|<number>] <hostname>\n";
+    const ALL_TYPES: [u16; 5] = [DNS_TYPE_A, DNS_TYPE_AAAA, DNS_TYPE_CNAME, DNS_TYPE_TXT, DNS_TYPE_SRV];
+    const ADDRESS_TYPES: [u16; 2] = [DNS_TYPE_A, DNS_TYPE_AAAA];
 
-    if argc() < 1 {
-        console_log("Usage: nslookup <hostname>\n");
-        console_log("\x1b[0;90mExample: nslookup google.com\x1b[0m\n");
+    let mut single = [0u16; 1];
+    let mut types: &[u16] = &ADDRESS_TYPES;
+    let mut host_buf = [0u8; 256];
+    let mut host_len = 0;
+    for i in 0..argc() {
+        let mut arg_buf = [0u8; 256];
+        let len = argv(i, &mut arg_buf).unwrap_or(0);
+        let arg = unsafe { core::str::from_utf8_unchecked(&arg_buf[..len]) };
+        if let Some(name) = arg.strip_prefix("-type=") {
+            let known = [("a", DNS_TYPE_A), ("aaaa", DNS_TYPE_AAAA), ("cname", DNS_TYPE_CNAME), ("txt", DNS_TYPE_TXT), ("srv", DNS_TYPE_SRV)];
+            if name.eq_ignore_ascii_case("all") || name.eq_ignore_ascii_case("any") {
+                types = &ALL_TYPES;
+                continue;
+            }
+            let qtype = match known.iter().find(|(known, _)| name.eq_ignore_ascii_case(known)) {
+                Some(&(_, qtype)) => qtype,
+                None => match name.parse::<u16>() {
+                    Ok(number) if number > 0 => number,
+                    _ => {
+                        console_log(USAGE);
+                        return;
+                    }
+                },
+            };
+            single[0] = qtype;
+            types = &single;
+        } else {
+            host_buf[..len].copy_from_slice(&arg_buf[..len]);
+            host_len = len;
+        }
+    }
+    if host_len == 0 {
+        console_log(USAGE);
+        console_log("\x1b[0;90mExample: nslookup -type=txt google.com\x1b[0m\n");
         return;
     }
 
@@ -22,43 +65,80 @@ pub fn main() {
         return;
     }
 
-    let mut arg_buf = [0u8; 256];
-    let arg_len = match argv(0, &mut arg_buf) {
-        Some(len) => len,
-        None => {
-            console_log("Error: Could not read argument\n");
-            return;
-        }
-    };
-
-    let hostname = &arg_buf[..arg_len];
-    let hostname_str = unsafe { core::str::from_utf8_unchecked(hostname) };
+    let hostname = unsafe { core::str::from_utf8_unchecked(&host_buf[..host_len]) };
 
     console_log("\n");
     console_log("\x1b[1;33mServer:\x1b[0m  8.8.8.8\n");
     console_log("\x1b[1;33mPort:\x1b[0m    53\n\n");
 
     console_log("\x1b[0;90mQuerying ");
-    print(hostname.as_ptr(), hostname.len());
-    console_log("...\x1b[0m\n");
+    console_log(hostname);
+    console_log("...\x1b[0m\n\n");
 
-    let mut ip_bytes = [0u8; 4];
-    if resolve_dns(hostname_str, &mut ip_bytes) {
-        console_log("\n");
-        console_log("\x1b[1;32mName:\x1b[0m    ");
-        print(hostname.as_ptr(), hostname.len());
-        console_log("\n");
+    // Every line printed so far, so an alias shared by several answers is
+    // shown once
+    let mut shown = [0u8; 4096];
+    let mut shown_len = 0;
+    let mut not_found = false;
+    let mut failed = false;
+    for &qtype in types {
+        let mut buf = [0u8; 2048];
+        let len = match query_dns(hostname, qtype, &mut buf) {
+            DnsQuery::Records(len) => len,
+            DnsQuery::NotFound => {
+                not_found = true;
+                break;
+            }
+            DnsQuery::Error => {
+                failed = true;
+                continue;
+            }
+        };
+        let text = unsafe { core::str::from_utf8_unchecked(&buf[..len]) };
+        for line in text.lines() {
+            let seen = unsafe { core::str::from_utf8_unchecked(&shown[..shown_len]) };
+            if seen.lines().any(|other| other == line) {
+                continue;
+            }
+            if shown_len + line.len() < shown.len() {
+                shown[shown_len..shown_len + line.len()].copy_from_slice(line.as_bytes());
+                shown[shown_len + line.len()] = b'\n';
+                shown_len += line.len() + 1;
+            }
+            print_record(line);
+        }
+    }
 
-        let mut ip_buf = [0u8; 16];
-        let ip_len = format_ipv4(&ip_bytes, &mut ip_buf);
-        console_log("\x1b[1;32mAddress:\x1b[0m \x1b[1;97m");
-        print(ip_buf.as_ptr(), ip_len);
-        console_log("\x1b[0m\n\n");
-    } else {
+    if shown_len > 0 {
         console_log("\n");
+    } else {
         console_log("\x1b[1;31m*** Can't find ");
-        print(hostname.as_ptr(), hostname.len());
-        console_log(": No response from server\x1b[0m\n\n");
+        console_log(hostname);
+        console_log(if not_found {
+            ": Non-existent domain\x1b[0m\n\n"
+        } else if failed {
+            ": No response from server\x1b[0m\n\n"
+        } else {
+            ": No answer\x1b[0m\n\n"
+        });
+    }
+
+    /// "<name> <ttl> <TYPE> <data>" as "name  TYPE  data  (ttl N)"
+    fn print_record(line: &str) {
+        let mut fields = line.splitn(4, ' ');
+        let (Some(name), Some(ttl), Some(rtype)) = (fields.next(), fields.next(), fields.next()) else {
+            return;
+        };
+        let data = fields.next().unwrap_or("");
+        console_log("\x1b[1;32m");
+        console_log(name);
+        console_log("\x1b[0m  ");
+        console_log(rtype);
+        console_log("  \x1b[1;97m");
+        print(data.as_ptr(), data.len());
+        console_log("\x1b[0m  \x1b[0;90m(ttl ");
+        console_log(ttl);
+        console_log(")\x1b[0m\n");
     }
 }
 
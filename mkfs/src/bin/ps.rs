@@ -12,7 +12,7 @@ pub fn main() {
     use mkfs::{console_log, print, print_int, ps_list};
 
     // Use a static buffer to avoid large stack allocations
-    static mut BUF: [u8; 2048] = [0u8; 2048];
+    static mut BUF: [u8; 4096] = [0u8; 4096];
 
     fn parse_u64(bytes: &[u8]) -> u64 {
         let mut n: u64 = 0;
@@ -43,12 +43,13 @@ pub fn main() {
     }
 
     fn display_task(line: &[u8]) {
-        // Format from kernel: pid:name:state:priority:cpu_time_ms:uptime_ms
-        let mut colon_pos = [0usize; 5];
+        // Format from kernel: pid:name:state:priority:cpu_time_ms:uptime_ms:cpu_permille
+        // followed by five I/O counters and the heap bytes in use
+        let mut colon_pos = [0usize; 12];
         let mut colon_count = 0;
         
         for (i, &b) in line.iter().enumerate() {
-            if b == b':' && colon_count < 5 {
+            if b == b':' && colon_count < 12 {
                 colon_pos[colon_count] = i;
                 colon_count += 1;
             }
@@ -64,10 +65,14 @@ pub fn main() {
         let priority_slice = &line[colon_pos[2]+1..colon_pos[3]];
         let cpu_time_slice = &line[colon_pos[3]+1..colon_pos[4]];
         let uptime_slice = &line[colon_pos[4]+1..];
+        let usage_slice = if colon_count > 5 { &line[colon_pos[5]+1..] } else { &b"0"[..] };
+        let heap_slice = if colon_count > 11 { &line[colon_pos[11]+1..] } else { &b"0"[..] };
         
         let pid = parse_u64(pid_slice);
         let cpu_time_ms = parse_u64(cpu_time_slice);
         let uptime_ms = parse_u64(uptime_slice);
+        let usage = parse_u64(usage_slice);
+        let heap_kb = parse_u64(heap_slice).div_ceil(1024);
         let uptime_sec = uptime_ms / 1000;
 
         // Color based on state
@@ -77,6 +82,8 @@ pub fn main() {
             "\x1b[33m"
         } else if state_slice == b"Z" {
             "\x1b[31m"
+        } else if state_slice == b"T" {
+            "\x1b[35m"
         } else {
             "\x1b[0m"
         };
@@ -97,13 +104,23 @@ pub fn main() {
         for _ in 0..(4 - priority_slice.len()) { console_log(" "); }
         console_log(" ");
         
+        // %CPU with one decimal (5 chars, right-aligned)
+        print_padded_int((usage / 10) as i64, 3);
+        console_log(".");
+        print_int((usage % 10) as i64);
+        console_log("  ");
+        
         // CPU time in ms (7 chars, right-aligned)
         print_padded_int(cpu_time_ms as i64, 7);
         console_log("ms ");
         
         // Uptime (6 chars + "s", right-aligned)
         print_padded_int(uptime_sec as i64, 6);
-        console_log("s  ");
+        console_log("s ");
+
+        // Heap in use (6 chars + "K", right-aligned)
+        print_padded_int(heap_kb as i64, 6);
+        console_log("K  ");
         
         // Name
         print(name_slice.as_ptr(), name_slice.len());
@@ -111,10 +128,10 @@ pub fn main() {
     }
 
     // Header - updated to reflect actual data: cpu_time_ms and uptime
-    console_log("\x1b[1;36m  PID  STATE  PRI  CPU TIME  UPTIME  NAME\x1b[0m\n");
-    console_log("\x1b[90m-------------------------------------------------------\x1b[0m\n");
+    console_log("\x1b[1;36m  PID  STATE  PRI   %CPU  CPU TIME  UPTIME     RSS  NAME\x1b[0m\n");
+    console_log("\x1b[90m----------------------------------------------------------------------\x1b[0m\n");
 
-    let len = unsafe { ps_list((*core::ptr::addr_of_mut!(BUF)).as_mut_ptr(), 2048) };
+    let len = unsafe { ps_list((*core::ptr::addr_of_mut!(BUF)).as_mut_ptr(), 4096) };
     
     if len < 0 {
         console_log("\x1b[1;31mError:\x1b[0m Failed to get process list\n");
@@ -138,7 +155,7 @@ pub fn main() {
     }
 
     console_log("\n");
-    console_log("\x1b[90mStates: R=Ready/Running S=Sleeping Z=Zombie | CPU TIME: accumulated ms\x1b[0m\n");
+    console_log("\x1b[90mStates: R=Ready/Running S=Sleeping Z=Zombie | %CPU: last few seconds | CPU TIME: accumulated ms | RSS: heap in use\x1b[0m\n");
 }
 
 #[cfg(not(target_arch = "riscv64"))]
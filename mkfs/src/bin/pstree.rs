@@ -0,0 +1,164 @@
+// pstree - Show processes as a tree of parents and children
+//
+// Usage:
+//   pstree          Show every process under the process that started it
+//   pstree <pid>    Show only the process and its descendants
+//
+// Processes started by the kernel, and those whose parent has exited, are
+// shown at the top level. Exited children nobody has waited for yet are
+// shown in red as <zombie>.
+
+#![cfg_attr(target_arch = "riscv64", no_std)]
+#![cfg_attr(target_arch = "riscv64", no_main)]
+
+#[cfg(target_arch = "riscv64")]
+#[no_mangle]
+pub fn main() {
+    use mkfs::{argc, argv, console_log, print, print_int, ps_list};
+
+    const MAX_PROCS: usize = 128;
+    const MAX_DEPTH: usize = 16;
+
+    #[derive(Clone, Copy)]
+    struct Entry {
+        pid: u64,
+        ppid: u64,
+        /// Name within BUF
+        name: (usize, usize),
+        zombie: bool,
+    }
+
+    static mut BUF: [u8; 4096] = [0u8; 4096];
+    static mut ENTRIES: [Entry; MAX_PROCS] = [Entry { pid: 0, ppid: 0, name: (0, 0), zombie: false }; MAX_PROCS];
+
+    fn parse_u64(bytes: &[u8]) -> u64 {
+        let mut n: u64 = 0;
+        for &b in bytes {
+            if b.is_ascii_digit() {
+                n = n.saturating_mul(10).saturating_add((b - b'0') as u64);
+            } else {
+                break;
+            }
+        }
+        n
+    }
+
+    /// Entry for a ps_list line starting at `offset` in the buffer
+    fn parse_line(line: &[u8], offset: usize) -> Option<Entry> {
+        let mut fields = line.split(|&b| b == b':');
+        let pid = parse_u64(fields.next()?);
+        let name = fields.next()?;
+        let name_start = offset + pid_len(line);
+        let zombie = fields.next()? == b"Z";
+        // ppid is the 14th field; older kernels do not send it
+        let ppid = fields.nth(10).map(parse_u64).unwrap_or(0);
+        Some(Entry { pid, ppid, name: (name_start, name_start + name.len()), zombie })
+    }
+
+    /// Length of the pid field and its colon
+    fn pid_len(line: &[u8]) -> usize {
+        line.iter().position(|&b| b == b':').map_or(line.len(), |i| i + 1)
+    }
+
+    fn print_entry(entries: &[Entry], data: &[u8], index: usize) {
+        let entry = entries[index];
+        if entry.zombie {
+            console_log("\x1b[31m");
+        }
+        let name = &data[entry.name.0..entry.name.1];
+        print(name.as_ptr(), name.len());
+        console_log("(");
+        print_int(entry.pid as i64);
+        console_log(")");
+        if entry.zombie {
+            console_log(" <zombie>\x1b[0m");
+        }
+        console_log("\n");
+    }
+
+    /// Print the children of `entries[parent]`, `last[d]` telling whether the
+    /// ancestor at depth d was the last of its siblings
+    fn print_children(entries: &[Entry], data: &[u8], parent: usize, last: &mut [bool; MAX_DEPTH], depth: usize) {
+        let pid = entries[parent].pid;
+        let count = entries.iter().filter(|e| e.ppid == pid && e.pid != pid).count();
+        let mut seen = 0;
+        for (index, entry) in entries.iter().enumerate() {
+            if entry.ppid != pid || entry.pid == pid {
+                continue;
+            }
+            seen += 1;
+            for &ancestor_last in &last[..depth] {
+                console_log(if ancestor_last { "   " } else { "\x1b[90m│\x1b[0m  " });
+            }
+            console_log(if seen == count { "\x1b[90m└─\x1b[0m " } else { "\x1b[90m├─\x1b[0m " });
+            print_entry(entries, data, index);
+            if depth + 1 < MAX_DEPTH {
+                last[depth] = seen == count;
+                print_children(entries, data, index, last, depth + 1);
+            }
+        }
+    }
+
+    let root = if argc() > 0 {
+        let mut buf = [0u8; 16];
+        let len = argv(0, &mut buf).unwrap_or(0);
+        match core::str::from_utf8(&buf[..len]).ok().and_then(|s| s.parse::<u64>().ok()) {
+            Some(pid) => Some(pid),
+            None => {
+                console_log("Usage: pstree [pid]\n");
+                return;
+            }
+        }
+    } else {
+        None
+    };
+
+    let len = unsafe { ps_list((*core::ptr::addr_of_mut!(BUF)).as_mut_ptr(), 4096) };
+    if len < 0 {
+        console_log("\x1b[1;31mError:\x1b[0m Failed to get process list\n");
+        return;
+    }
+    let data = unsafe { &(&*core::ptr::addr_of!(BUF))[..len as usize] };
+    let entries = unsafe { &mut *core::ptr::addr_of_mut!(ENTRIES) };
+
+    let mut count = 0;
+    let mut start = 0;
+    for line in data.split(|&b| b == b'\n') {
+        if count < MAX_PROCS {
+            if let Some(entry) = parse_line(line, start) {
+                entries[count] = entry;
+                count += 1;
+            }
+        }
+        start += line.len() + 1;
+    }
+    let entries = &entries[..count];
+
+    let mut last = [false; MAX_DEPTH];
+    let mut shown = false;
+    for (index, entry) in entries.iter().enumerate() {
+        let top = match root {
+            Some(pid) => entry.pid == pid,
+            // Top level: started by the kernel, or the parent is gone
+            None => entry.ppid == 0 || entry.ppid == entry.pid || !entries.iter().any(|e| e.pid == entry.ppid),
+        };
+        if top {
+            shown = true;
+            print_entry(entries, data, index);
+            print_children(entries, data, index, &mut last, 0);
+        }
+    }
+    if !shown {
+        match root {
+            Some(pid) => {
+                console_log("\x1b[1;31mError:\x1b[0m Process ");
+                print_int(pid as i64);
+                console_log(" not found\n");
+            }
+            None => console_log("\x1b[90m(no processes)\x1b[0m\n"),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "riscv64"))]
+fn main() {}
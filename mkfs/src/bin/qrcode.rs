@@ -0,0 +1,88 @@
+// qrcode - Show text as a QR code
+//
+// Usage:
+//   qrcode                 Encode this machine's web address (http://<ip>/)
+//   qrcode <text...>       Encode text, e.g. a URL (up to 213 bytes)
+//
+// The code is printed in the terminal and, when there is a screen, shown in
+// a window for a phone to scan.
+
+#![cfg_attr(target_arch = "riscv64", no_std)]
+#![cfg_attr(target_arch = "riscv64", no_main)]
+
+#[cfg(target_arch = "riscv64")]
+#[no_mangle]
+pub fn main() {
+    use mkfs::{argc, argv, console_log, format_ipv4, get_net_info, qr_encode};
+
+    /// Light border scanners need around the code, in modules
+    const QUIET_ZONE: usize = 4;
+    /// Largest code: version 10, 57x57 modules
+    const MAX_MODULES: usize = 57 * 57;
+
+    static mut MODULES: [u8; MAX_MODULES] = [0u8; MAX_MODULES];
+
+    // Arguments joined with spaces, or the default address
+    let mut text_buf = [0u8; 256];
+    let mut text_len = 0;
+    for i in 0..argc() {
+        if i > 0 && text_len < text_buf.len() {
+            text_buf[text_len] = b' ';
+            text_len += 1;
+        }
+        text_len += argv(i, &mut text_buf[text_len..]).unwrap_or(0);
+    }
+    if text_len == 0 {
+        let ip = match get_net_info() {
+            Some(info) => info.ip,
+            None => {
+                console_log("\x1b[1;31mqrcode:\x1b[0m no text given and no network address\n");
+                console_log("Usage: qrcode <text>\n");
+                return;
+            }
+        };
+        let prefix = b"http://";
+        text_buf[..prefix.len()].copy_from_slice(prefix);
+        text_len = prefix.len();
+        text_len += format_ipv4(&ip, &mut text_buf[text_len..]);
+        text_buf[text_len] = b'/';
+        text_len += 1;
+    }
+    let text = unsafe { core::str::from_utf8_unchecked(&text_buf[..text_len]) };
+
+    let modules = unsafe { &mut *core::ptr::addr_of_mut!(MODULES) };
+    let size = match qr_encode(text, modules) {
+        Some(size) => size,
+        None => {
+            console_log("\x1b[1;31mqrcode:\x1b[0m text too long (at most 213 bytes)\n");
+            return;
+        }
+    };
+
+    // Two module rows per line with half blocks, dark on a light background
+    let dark = |x: usize, y: usize| {
+        x >= QUIET_ZONE
+            && y >= QUIET_ZONE
+            && x - QUIET_ZONE < size
+            && y - QUIET_ZONE < size
+            && modules[(y - QUIET_ZONE) * size + x - QUIET_ZONE] != 0
+    };
+    let side = size + 2 * QUIET_ZONE;
+    for y in (0..side).step_by(2) {
+        console_log("\x1b[30;107m");
+        for x in 0..side {
+            console_log(match (dark(x, y), dark(x, y + 1)) {
+                (true, true) => "█",
+                (true, false) => "▀",
+                (false, true) => "▄",
+                (false, false) => " ",
+            });
+        }
+        console_log("\x1b[0m\n");
+    }
+    console_log(text);
+    console_log("\n");
+}
+
+#[cfg(not(target_arch = "riscv64"))]
+fn main() {}
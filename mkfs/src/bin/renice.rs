@@ -0,0 +1,70 @@
+// renice - Change the scheduling priority of running processes
+//
+// Usage:
+//   renice <nice> <pid>...       Set the nice value of each process
+//   renice -n <adj> <pid>...     Add to the nice value of each process
+//
+// Nice values go from -20 (most CPU) to 19 (least); out of range values are
+// clamped. Use `ps` to find PIDs and `cat /proc/<pid>/status` to see a
+// process's nice value. To run a command niced, use the `nice` built-in.
+
+#![cfg_attr(target_arch = "riscv64", no_std)]
+#![cfg_attr(target_arch = "riscv64", no_main)]
+
+#[cfg(target_arch = "riscv64")]
+#[no_mangle]
+pub fn main() {
+    use mkfs::{argc, argv, console_log, print_int, process_nice, renice_process};
+
+    const USAGE: &str = "Usage: renice <nice> <pid>...\n       renice -n <adjustment> <pid>...\n";
+
+    fn arg(i: usize, buf: &mut [u8; 32]) -> &str {
+        let len = argv(i, buf).unwrap_or(0);
+        core::str::from_utf8(&buf[..len]).unwrap_or("")
+    }
+
+    let count = argc();
+    let mut buf = [0u8; 32];
+    let (relative, first_pid) = if arg(0, &mut buf) == "-n" { (true, 2) } else { (false, 1) };
+    let value = match arg(first_pid - 1, &mut buf).parse::<i32>() {
+        Ok(value) if count > first_pid => value,
+        _ => {
+            console_log(USAGE);
+            return;
+        }
+    };
+
+    for i in first_pid..count {
+        let pid = match arg(i, &mut buf).parse::<u32>() {
+            Ok(pid) if pid > 0 => pid,
+            _ => {
+                console_log("\x1b[1;31mError:\x1b[0m Invalid PID: ");
+                console_log(arg(i, &mut buf));
+                console_log("\n");
+                continue;
+            }
+        };
+        let Some(old) = process_nice(pid) else {
+            console_log("\x1b[1;31mError:\x1b[0m Process ");
+            print_int(pid as i64);
+            console_log(" not found\n");
+            continue;
+        };
+        let new = if relative { old.saturating_add(value) } else { value }.clamp(-20, 19);
+        if !renice_process(pid, new) {
+            console_log("\x1b[1;31mError:\x1b[0m Process ");
+            print_int(pid as i64);
+            console_log(" not found\n");
+            continue;
+        }
+        print_int(pid as i64);
+        console_log(": old priority ");
+        print_int(old as i64);
+        console_log(", new priority ");
+        print_int(new as i64);
+        console_log("\n");
+    }
+}
+
+#[cfg(not(target_arch = "riscv64"))]
+fn main() {}
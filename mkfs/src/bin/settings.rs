@@ -0,0 +1,85 @@
+// settings - Show or change system settings
+//
+// Usage:
+//   settings                   Show all settings (/etc/settings.toml)
+//   settings <key>             Show one setting
+//   settings <key> <value>     Change a setting; applied immediately and saved
+//
+// Keys:
+//   system.hostname            name       - shown in the shell prompt
+//   system.lang                en/de/...  - language of kernel messages (/etc/locale)
+//   network.profile            relay/static - address from the relay or fixed
+//   network.address            a.b.c.d    - address for the static profile
+//   network.ipv6               off/slaac/static - IPv6 addressing
+//   network.ipv6_address       2001:db8::1 - address for static IPv6
+//   ui.theme                   dark/light/high_contrast - color theme
+//   ui.large_font              true/false - larger terminal and widget fonts
+//   input.keymap               us/de/fr   - keyboard layout
+//   input.key_repeat_delay_ms  100-2000   - delay before a held key repeats
+//   input.key_repeat_rate_hz   0-60       - repeats per second (0 = off)
+//   services.<name>            true/false - start sysmond, tcpd or httpd at boot
+//   wasm.fuel_budget           millions   - instructions a WASM program may run (0 = no limit)
+//
+// The same settings can be changed from the Settings window of the GUI.
+
+#![cfg_attr(target_arch = "riscv64", no_std)]
+#![cfg_attr(target_arch = "riscv64", no_main)]
+
+#[cfg(target_arch = "riscv64")]
+#[no_mangle]
+pub fn main() {
+    use mkfs::{argc, argv, console_log, get_settings, set_setting};
+
+    let mut key_buf = [0u8; 64];
+    let mut value_buf = [0u8; 64];
+    let key_len = if argc() > 0 { argv(0, &mut key_buf).unwrap_or(0) } else { 0 };
+    let value_len = if argc() > 1 { argv(1, &mut value_buf).unwrap_or(0) } else { 0 };
+    let key = unsafe { core::str::from_utf8_unchecked(&key_buf[..key_len]) };
+    let value = unsafe { core::str::from_utf8_unchecked(&value_buf[..value_len]) };
+
+    if argc() > 2 {
+        console_log("Usage: settings [<key> [<value>]]\n");
+        return;
+    }
+
+    if argc() == 2 && !set_setting(key, value) {
+        console_log("settings: invalid key or value (run 'settings' for the list)\n");
+        return;
+    }
+
+    let mut buf = [0u8; 1024];
+    let len = match get_settings(&mut buf) {
+        Some(len) => len,
+        None => {
+            console_log("settings: cannot read settings\n");
+            return;
+        }
+    };
+    let text = unsafe { core::str::from_utf8_unchecked(&buf[..len]) };
+
+    if argc() == 0 {
+        console_log(text);
+        return;
+    }
+
+    // Show just the requested key, found under its [section]
+    let (section, name) = key.split_once('.').unwrap_or(("", key));
+    let mut current = "";
+    for line in text.lines() {
+        if let Some(s) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            current = s;
+        } else if let Some((k, v)) = line.split_once('=') {
+            if current == section && k.trim() == name {
+                console_log(key);
+                console_log(" = ");
+                console_log(v.trim());
+                console_log("\n");
+                return;
+            }
+        }
+    }
+    console_log("settings: unknown key\n");
+}
+
+#[cfg(not(target_arch = "riscv64"))]
+fn main() {}
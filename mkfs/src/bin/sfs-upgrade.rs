@@ -0,0 +1,80 @@
+// sfs-upgrade - Show and upgrade the root filesystem's format revision
+//
+// Usage:
+//   sfs-upgrade          Show the format revision, feature flags and mount mode
+//   sfs-upgrade apply    Migrate the mounted root to the latest revision
+//
+// Images from a newer kernel are mounted read-only and cannot be upgraded
+// here; host images are migrated with "mkfs upgrade <image>".
+
+#![cfg_attr(target_arch = "riscv64", no_std)]
+#![cfg_attr(target_arch = "riscv64", no_main)]
+
+#[cfg(target_arch = "riscv64")]
+#[no_mangle]
+pub fn main() {
+    use mkfs::{argv, console_log, exit, sfs_format_info, sfs_upgrade};
+
+    let mut cmd_buf = [0u8; 16];
+    let cmd = argv(0, &mut cmd_buf).map(|len| &cmd_buf[..len]).unwrap_or(b"");
+    match cmd {
+        b"" => {}
+        b"apply" => {
+            match sfs_upgrade() {
+                Some(true) => console_log("\x1b[1;32m[OK]\x1b[0m Upgraded to the latest format\n"),
+                Some(false) => console_log("Already the latest format\n"),
+                None => {
+                    console_log("\x1b[1;31m[X]\x1b[0m Upgrade failed (see dmesg)\n");
+                    exit(1);
+                }
+            }
+            return;
+        }
+        _ => {
+            console_log("Usage: sfs-upgrade [apply]\n");
+            return;
+        }
+    }
+
+    // "format X.Y", "latest X.Y", "features ...", "mode rw|ro" lines
+    let mut buf = [0u8; 256];
+    let Some(len) = sfs_format_info(&mut buf) else {
+        console_log("\x1b[1;31m[X]\x1b[0m No filesystem mounted\n");
+        return;
+    };
+    let text = unsafe { core::str::from_utf8_unchecked(&buf[..len]) };
+    let (mut format, mut latest, mut features, mut mode) = ("?", "?", "", "rw");
+    for line in text.lines() {
+        let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+        match key {
+            "format" => format = value,
+            "latest" => latest = value,
+            "features" => features = value,
+            "mode" => mode = value,
+            _ => {}
+        }
+    }
+
+    console_log("Format:   \x1b[1m");
+    console_log(format);
+    console_log("\x1b[0m (latest ");
+    console_log(latest);
+    console_log(")\n");
+    console_log("Features: ");
+    console_log(if features.is_empty() { "none" } else { features });
+    console_log("\n");
+    console_log("Mode:     ");
+    if mode == "ro" {
+        console_log("\x1b[33mread-only\x1b[0m (written by a newer kernel)\n");
+    } else {
+        console_log("read-write\n");
+    }
+    if format != latest && mode != "ro" {
+        console_log("Run \x1b[1msfs-upgrade apply\x1b[0m to migrate to ");
+        console_log(latest);
+        console_log("\n");
+    }
+}
+
+#[cfg(not(target_arch = "riscv64"))]
+fn main() {}
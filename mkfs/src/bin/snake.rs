@@ -0,0 +1,226 @@
+// snake - The classic game, in the terminal
+//
+// Usage:
+//   snake          Play on the serial or telnet console
+//
+// Controls: arrow keys or WASD to steer, P to pause, Q or Ctrl+C to quit.
+// The snake speeds up as it grows.
+//
+// Each frame is built in a buffer and written with a single print after
+// homing the cursor, so the board is redrawn in place without flicker.
+
+#![cfg_attr(target_arch = "riscv64", no_std)]
+#![cfg_attr(target_arch = "riscv64", no_main)]
+
+#[cfg(target_arch = "riscv64")]
+#[no_mangle]
+pub fn main() {
+    use mkfs::{console_log, get_random, get_time, int_to_str, print, read_console, should_cancel, sleep};
+
+    const WIDTH: usize = 28;
+    const HEIGHT: usize = 18;
+    const MAX_LEN: usize = WIDTH * HEIGHT;
+    /// Milliseconds per step at the start and at full speed
+    const START_TICK_MS: i64 = 180;
+    const MIN_TICK_MS: i64 = 70;
+
+    static mut BODY: [(u8, u8); MAX_LEN] = [(0, 0); MAX_LEN];
+    static mut FRAME: [u8; 8192] = [0u8; 8192];
+
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    enum Dir {
+        Up,
+        Down,
+        Left,
+        Right,
+    }
+
+    impl Dir {
+        fn opposite(self) -> Dir {
+            match self {
+                Dir::Up => Dir::Down,
+                Dir::Down => Dir::Up,
+                Dir::Left => Dir::Right,
+                Dir::Right => Dir::Left,
+            }
+        }
+    }
+
+    /// Frame under construction
+    struct Frame {
+        buf: &'static mut [u8],
+        len: usize,
+    }
+
+    impl Frame {
+        fn push(&mut self, s: &[u8]) {
+            let n = s.len().min(self.buf.len() - self.len);
+            self.buf[self.len..self.len + n].copy_from_slice(&s[..n]);
+            self.len += n;
+        }
+    }
+
+    fn random_below(n: usize) -> usize {
+        let mut bytes = [0u8; 4];
+        get_random(&mut bytes);
+        u32::from_le_bytes(bytes) as usize % n
+    }
+
+    let body = unsafe { &mut *core::ptr::addr_of_mut!(BODY) };
+    // body[head] is the head; the tail is `len - 1` entries behind it in the ring
+    let mut head = 0usize;
+    let mut len = 3usize;
+    for i in 0..len {
+        body[(head + MAX_LEN - i) % MAX_LEN] = ((WIDTH / 2 - i) as u8, (HEIGHT / 2) as u8);
+    }
+    let occupied = |body: &[(u8, u8); MAX_LEN], head: usize, len: usize, x: u8, y: u8| {
+        (0..len).any(|i| body[(head + MAX_LEN - i) % MAX_LEN] == (x, y))
+    };
+    let place_food = |body: &[(u8, u8); MAX_LEN], head: usize, len: usize| loop {
+        let x = random_below(WIDTH) as u8;
+        let y = random_below(HEIGHT) as u8;
+        if !occupied(body, head, len, x, y) {
+            break (x, y);
+        }
+    };
+
+    let mut dir = Dir::Right;
+    // Direction for the next step; only one turn per step, so a quick
+    // double tap can't reverse the snake into itself
+    let mut next_dir = dir;
+    let mut food = place_food(body, head, len);
+    let mut score = 0i64;
+    let mut paused = false;
+    let mut alive = true;
+    // Escape sequence state: 0 = none, 1 = got ESC, 2 = got ESC [
+    let mut escape = 0u8;
+
+    console_log("\x1b[2J\x1b[?25l"); // Clear screen, hide cursor
+    let mut next_tick = get_time();
+
+    'game: loop {
+        if should_cancel() != 0 {
+            break;
+        }
+
+        // Input: drain everything typed since the last check
+        let mut key = [0u8; 16];
+        let n = read_console(&mut key);
+        for &c in &key[..n] {
+            let turn = match (escape, c) {
+                (0, 0x1b) => { escape = 1; None }
+                (1, b'[') => { escape = 2; None }
+                (2, b'A') => { escape = 0; Some(Dir::Up) }
+                (2, b'B') => { escape = 0; Some(Dir::Down) }
+                (2, b'C') => { escape = 0; Some(Dir::Right) }
+                (2, b'D') => { escape = 0; Some(Dir::Left) }
+                (1..=2, _) => { escape = 0; None }
+                (_, b'w' | b'W') => Some(Dir::Up),
+                (_, b's' | b'S') => Some(Dir::Down),
+                (_, b'a' | b'A') => Some(Dir::Left),
+                (_, b'd' | b'D') => Some(Dir::Right),
+                (_, b'p' | b'P') => { paused = !paused; None }
+                (_, b'q' | b'Q' | 3) => break 'game,
+                _ => None,
+            };
+            if let Some(turn) = turn {
+                if turn != dir.opposite() {
+                    next_dir = turn;
+                }
+            }
+        }
+
+        // Frame clock: step at a fixed rate, whatever the input did
+        let now = get_time();
+        if now < next_tick {
+            sleep(((next_tick - now) as u64).min(10));
+            continue;
+        }
+        let tick = (START_TICK_MS - (len as i64 - 3) * 4).max(MIN_TICK_MS);
+        next_tick += tick;
+        if next_tick < now {
+            // Fell behind (e.g. the console was busy); don't try to catch up
+            next_tick = now + tick;
+        }
+
+        if !paused {
+            dir = next_dir;
+            let (x, y) = body[head];
+            let (nx, ny) = match dir {
+                Dir::Up => (x as i32, y as i32 - 1),
+                Dir::Down => (x as i32, y as i32 + 1),
+                Dir::Left => (x as i32 - 1, y as i32),
+                Dir::Right => (x as i32 + 1, y as i32),
+            };
+            let out_of_bounds = nx < 0 || ny < 0 || nx >= WIDTH as i32 || ny >= HEIGHT as i32;
+            // The tail moves out of the way this step, unless we are growing
+            let grows = (nx, ny) == (food.0 as i32, food.1 as i32);
+            let body_len = if grows { len } else { len - 1 };
+            if out_of_bounds || occupied(body, head, body_len, nx as u8, ny as u8) {
+                alive = false;
+            } else {
+                head = (head + 1) % MAX_LEN;
+                body[head] = (nx as u8, ny as u8);
+                if grows {
+                    len += 1;
+                    score += 10;
+                    if len == MAX_LEN {
+                        break;
+                    }
+                    food = place_food(body, head, len);
+                }
+            }
+        }
+
+        // Render the whole board into one buffer, then print it at once
+        let mut frame = Frame { buf: unsafe { &mut *core::ptr::addr_of_mut!(FRAME) }, len: 0 };
+        frame.push(b"\x1b[H\x1b[1;32m SNAKE \x1b[0m  Score: ");
+        let mut num = [0u8; 20];
+        frame.push(int_to_str(score, &mut num).as_bytes());
+        frame.push(if paused { b"  \x1b[33m(paused)\x1b[0m  \r\n" } else { b"            \r\n" });
+        frame.push(b"\x1b[90m+");
+        for _ in 0..WIDTH * 2 {
+            frame.push(b"-");
+        }
+        frame.push(b"+\x1b[0m\r\n");
+        for y in 0..HEIGHT as u8 {
+            frame.push(b"\x1b[90m|\x1b[0m");
+            for x in 0..WIDTH as u8 {
+                if body[head] == (x, y) {
+                    frame.push(b"\x1b[1;92m@@\x1b[0m");
+                } else if occupied(body, head, len, x, y) {
+                    frame.push(b"\x1b[32m[]\x1b[0m");
+                } else if food == (x, y) {
+                    frame.push(b"\x1b[1;31m<>\x1b[0m");
+                } else {
+                    frame.push(b"  ");
+                }
+            }
+            frame.push(b"\x1b[90m|\x1b[0m\r\n");
+        }
+        frame.push(b"\x1b[90m+");
+        for _ in 0..WIDTH * 2 {
+            frame.push(b"-");
+        }
+        frame.push(b"+\x1b[0m\r\n");
+        frame.push(b"\x1b[90m arrows/WASD steer  P pause  Q quit\x1b[0m\r\n");
+        print(frame.buf.as_ptr(), frame.len);
+
+        if !alive {
+            break;
+        }
+    }
+
+    console_log("\x1b[?25h"); // Show cursor
+    if alive {
+        console_log("\nBye!\n");
+    } else {
+        console_log("\n\x1b[1;31mGame over!\x1b[0m Final score: ");
+        let mut num = [0u8; 20];
+        console_log(int_to_str(score, &mut num));
+        console_log("\n");
+    }
+}
+
+#[cfg(not(target_arch = "riscv64"))]
+fn main() {}
@@ -0,0 +1,108 @@
+// stress - Soak test: CPU, filesystem and network load across all harts
+//
+// Usage:
+//   stress [--cpu N] [--io N] [--net N] [--seconds S] [--url URL]
+//
+// Defaults: 2 cpu, 2 io and 1 net worker for 60 seconds; net workers fetch
+// from the local httpd unless --url names a static page elsewhere. Workers
+// are spread over the harts. At the end the results are checked (checksums,
+// read-back data, shared counters, identical responses) and stress exits
+// with code 1 if anything failed. Ctrl+C ends the run early.
+//
+// The report's fs line is the filesystem benchmark: `stress --cpu 0 --net 0
+// --io 1` against `--io N` (one worker per hart) shows how much faster
+// harts get through filesystem work together than one at a time.
+
+#![cfg_attr(target_arch = "riscv64", no_std)]
+#![cfg_attr(target_arch = "riscv64", no_main)]
+
+#[cfg(target_arch = "riscv64")]
+#[no_mangle]
+pub fn main() {
+    use mkfs::{
+        argc, argv, console_log, exit, print, should_cancel, sleep, stress_start, stress_status, stress_stop,
+        StressState,
+    };
+
+    const USAGE: &str = "Usage: stress [--cpu N] [--io N] [--net N] [--seconds S] [--url URL]\n";
+
+    fn arg_num(index: usize) -> Option<u64> {
+        let mut buf = [0u8; 16];
+        let len = argv(index, &mut buf)?;
+        core::str::from_utf8(&buf[..len]).ok()?.parse().ok()
+    }
+
+    let (mut cpu, mut io, mut net, mut seconds) = (2u64, 2u64, 1u64, 60u64);
+    let mut url_buf = [0u8; 256];
+    let mut url_len = 0;
+    let mut i = 0;
+    while i < argc() {
+        let mut arg_buf = [0u8; 16];
+        let arg = argv(i, &mut arg_buf).map(|len| &arg_buf[..len]).unwrap_or(b"");
+        let target = match arg {
+            b"--cpu" => &mut cpu,
+            b"--io" => &mut io,
+            b"--net" => &mut net,
+            b"--seconds" => &mut seconds,
+            b"--url" => {
+                url_len = argv(i + 1, &mut url_buf).unwrap_or(0);
+                i += 2;
+                continue;
+            }
+            _ => {
+                console_log(USAGE);
+                return;
+            }
+        };
+        match arg_num(i + 1) {
+            Some(n) if n <= u16::MAX as u64 => *target = n,
+            _ => {
+                console_log(USAGE);
+                return;
+            }
+        }
+        i += 2;
+    }
+    let url = core::str::from_utf8(&url_buf[..url_len]).unwrap_or("");
+
+    let mut report = [0u8; 2048];
+    if let Some((_, StressState::Running)) = stress_status(&mut report) {
+        console_log("\x1b[1;31m[X]\x1b[0m A stress test is already running\n");
+        exit(1);
+    }
+    if !stress_start(cpu as u16, io as u16, net as u16, seconds, url) {
+        console_log("\x1b[1;31m[X]\x1b[0m Could not start the stress test (see dmesg)\n");
+        exit(1);
+    }
+    console_log("Stress test running, Ctrl+C to stop early\n");
+
+    let mut cancelled = false;
+    let (len, state) = loop {
+        let Some((len, state)) = stress_status(&mut report) else {
+            console_log("\x1b[1;31m[X]\x1b[0m Lost the stress test\n");
+            exit(1);
+        };
+        if state != StressState::Running {
+            break (len, state);
+        }
+        // The first report line is the elapsed time
+        let first = report[..len].iter().position(|&b| b == b'\n').unwrap_or(len);
+        console_log("\r");
+        print(report.as_ptr(), first);
+        if !cancelled && should_cancel() != 0 {
+            cancelled = true;
+            stress_stop();
+            console_log("  (stopping)");
+        }
+        sleep(500);
+    };
+
+    console_log("\r\x1b[K");
+    print(report.as_ptr(), len);
+    if state == StressState::Failed {
+        exit(1);
+    }
+}
+
+#[cfg(not(target_arch = "riscv64"))]
+fn main() {}
@@ -0,0 +1,20 @@
+// sync - Flush filesystem cache to disk
+//
+// Usage:
+//   sync        Write all dirty cached blocks to the block device
+
+#![cfg_attr(target_arch = "riscv64", no_std)]
+#![cfg_attr(target_arch = "riscv64", no_main)]
+
+#[cfg(target_arch = "riscv64")]
+#[no_mangle]
+pub fn main() {
+    use mkfs::{console_log, sync};
+
+    if !sync() {
+        console_log("sync: failed to flush filesystem\n");
+    }
+}
+
+#[cfg(not(target_arch = "riscv64"))]
+fn main() {}
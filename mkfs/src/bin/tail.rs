@@ -4,6 +4,11 @@
 //   tail <file>           Show last 10 lines
 //   tail -n <N> <file>    Show last N lines
 //   tail -<N> <file>      Show last N lines (shorthand)
+//   tail -f <file>        Show last lines, then print lines as they are
+//                         appended until Ctrl+C
+//
+// Several sessions can follow files at once; each tail blocks on a watch of
+// its file and only reads again after a change.
 
 #![cfg_attr(target_arch = "riscv64", no_std)]
 #![cfg_attr(target_arch = "riscv64", no_main)]
@@ -11,7 +16,10 @@
 #[cfg(target_arch = "riscv64")]
 #[no_mangle]
 pub fn main() {
-    use mkfs::{console_log, argc, argv, get_cwd, print, fs_read};
+    use mkfs::{
+        console_log, argc, argv, close_file, fd_read, fd_seek, get_cwd, open_file, print, fs_read, should_cancel,
+        wait_for_changes, watch_path, O_RDONLY, SEEK_END, SEEK_SET, WATCH_CREATE, WATCH_DELETE, WATCH_MODIFY,
+    };
 
     fn parse_num(s: &[u8]) -> Option<usize> {
         if s.is_empty() { return None; }
@@ -79,14 +87,62 @@ pub fn main() {
         }
     }
 
+    /// Print what gets appended to `path` until Ctrl+C
+    fn follow(path: &str) {
+        let Some(watch) = watch_path(path, WATCH_CREATE | WATCH_MODIFY | WATCH_DELETE) else {
+            console_log("\x1b[1;31mtail:\x1b[0m cannot watch file\n");
+            return;
+        };
+
+        // Start from the current end of the file
+        let mut offset = match open_file(path, O_RDONLY) {
+            Some(fd) => {
+                let end = fd_seek(fd, 0, SEEK_END).unwrap_or(0);
+                close_file(fd);
+                end
+            }
+            None => 0,
+        };
+        console_log("\x1b[2m--- Following (Ctrl+C to stop) ---\x1b[0m\n");
+
+        let mut buf = [0u8; 512];
+        while should_cancel() == 0 {
+            if !wait_for_changes(watch, 1000) {
+                continue;
+            }
+            // Only whether something changed matters, not what
+            while fd_read(watch, &mut buf).is_some_and(|n| n > 0) {}
+
+            // An fd sees the file as it was when opened, so reopen after each
+            // change. A removed file is picked up again once it reappears.
+            let Some(fd) = open_file(path, O_RDONLY) else {
+                continue;
+            };
+            let end = fd_seek(fd, 0, SEEK_END).unwrap_or(0);
+            if end < offset {
+                console_log("\x1b[1;33mtail: file truncated\x1b[0m\n");
+                offset = 0;
+            }
+            if fd_seek(fd, offset as i64, SEEK_SET).is_some() {
+                while let Some(n) = fd_read(fd, &mut buf).filter(|&n| n > 0) {
+                    print(buf.as_ptr(), n);
+                    offset += n as u64;
+                }
+            }
+            close_file(fd);
+        }
+        close_file(watch);
+    }
+
     let arg_count = argc();
 
     if arg_count < 1 {
-        console_log("Usage: tail [-n NUM] <file>\n");
+        console_log("Usage: tail [-f] [-n NUM] <file>\n");
         return;
     }
 
     let mut num_lines = 10usize;
+    let mut follow_mode = false;
     let mut file_path: Option<([u8; 512], usize)> = None;
 
     let mut cwd = [0u8; 256];
@@ -101,7 +157,9 @@ pub fn main() {
         };
         let arg = &arg_buf[..arg_len];
 
-        if arg == b"-n" {
+        if arg == b"-f" || arg == b"--follow" {
+            follow_mode = true;
+        } else if arg == b"-n" {
             i += 1;
             if i < arg_count {
                 let mut num_buf = [0u8; 16];
@@ -131,7 +189,7 @@ pub fn main() {
     let (path_buf, path_len) = match file_path {
         Some(p) => p,
         None => {
-            console_log("Usage: tail [-n NUM] <file>\n");
+            console_log("Usage: tail [-f] [-n NUM] <file>\n");
             return;
         }
     };
@@ -150,6 +208,13 @@ pub fn main() {
 
     let content = unsafe { &(*core::ptr::addr_of!(CONTENT))[..read_len as usize] };
     print_last_lines(content, num_lines);
+
+    if follow_mode {
+        match core::str::from_utf8(&path_buf[..path_len]) {
+            Ok(path) => follow(path),
+            Err(_) => console_log("\x1b[1;31mtail:\x1b[0m invalid path\n"),
+        }
+    }
 }
 
 #[cfg(not(target_arch = "riscv64"))]
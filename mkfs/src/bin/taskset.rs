@@ -0,0 +1,131 @@
+// taskset - Show or set which harts a process may run on
+//
+// Usage:
+//   taskset <pid>            Show the harts the process may run on
+//   taskset <harts> <pid>    Restrict the process to a hart list
+//
+// Hart lists are comma separated harts and ranges, e.g. 1-3,5, or all.
+// A process waiting on a hart outside its new list is moved at once; one
+// that is running moves when its current tick ends.
+
+#![cfg_attr(target_arch = "riscv64", no_std)]
+#![cfg_attr(target_arch = "riscv64", no_main)]
+
+#[cfg(target_arch = "riscv64")]
+#[no_mangle]
+pub fn main() {
+    use mkfs::{argc, argv, console_log, print_int, process_affinity, set_process_affinity, AffinityResult};
+
+    const USAGE: &str = "Usage: taskset [<harts>] <pid>\n\x1b[0;90mExample: taskset 1-3 12\x1b[0m\n";
+
+    fn arg(i: usize, buf: &mut [u8; 64]) -> &str {
+        let len = argv(i, buf).unwrap_or(0);
+        core::str::from_utf8(&buf[..len]).unwrap_or("")
+    }
+
+    /// "1-3,5" or "all" as a mask
+    fn parse_list(text: &str) -> Option<u128> {
+        if text == "all" {
+            return Some(u128::MAX);
+        }
+        let mut mask = 0u128;
+        for part in text.split(',') {
+            let (first, last) = match part.split_once('-') {
+                Some((first, last)) => (first.parse::<u32>().ok()?, last.parse::<u32>().ok()?),
+                None => {
+                    let hart = part.parse::<u32>().ok()?;
+                    (hart, hart)
+                }
+            };
+            if first > last || last >= 128 {
+                return None;
+            }
+            for hart in first..=last {
+                mask |= 1 << hart;
+            }
+        }
+        (mask != 0).then_some(mask)
+    }
+
+    fn print_list(mask: u128) {
+        if mask == u128::MAX {
+            console_log("all");
+            return;
+        }
+        let mut first_range = true;
+        let mut hart = 0;
+        while hart < 128 {
+            if mask & (1 << hart) == 0 {
+                hart += 1;
+                continue;
+            }
+            let first = hart;
+            while hart + 1 < 128 && mask & (1 << (hart + 1)) != 0 {
+                hart += 1;
+            }
+            if !first_range {
+                console_log(",");
+            }
+            first_range = false;
+            print_int(first as i64);
+            if hart != first {
+                console_log("-");
+                print_int(hart as i64);
+            }
+            hart += 1;
+        }
+    }
+
+    let mut buf = [0u8; 64];
+    let (list, pid_arg) = match argc() {
+        1 => (None, 0),
+        2 => (Some(parse_list(arg(0, &mut buf))), 1),
+        _ => {
+            console_log(USAGE);
+            return;
+        }
+    };
+    let pid = match arg(pid_arg, &mut buf).parse::<u32>() {
+        Ok(pid) if pid > 0 => pid,
+        _ => {
+            console_log(USAGE);
+            return;
+        }
+    };
+
+    match list {
+        None => {}
+        Some(None) => {
+            console_log("\x1b[1;31mError:\x1b[0m Invalid hart list\n");
+            return;
+        }
+        Some(Some(mask)) => match set_process_affinity(pid, mask) {
+            AffinityResult::Success => {}
+            AffinityResult::NoSuchHart => {
+                console_log("\x1b[1;31mError:\x1b[0m None of those harts exists\n");
+                return;
+            }
+            AffinityResult::NotFound => {
+                console_log("\x1b[1;31mError:\x1b[0m Process ");
+                print_int(pid as i64);
+                console_log(" not found\n");
+                return;
+            }
+        },
+    }
+
+    let Some(mask) = process_affinity(pid) else {
+        console_log("\x1b[1;31mError:\x1b[0m Process ");
+        print_int(pid as i64);
+        console_log(" not found\n");
+        return;
+    };
+    console_log("pid ");
+    print_int(pid as i64);
+    console_log(if list.is_some() { "'s new affinity list: " } else { "'s current affinity list: " });
+    print_list(mask);
+    console_log("\n");
+}
+
+#[cfg(not(target_arch = "riscv64"))]
+fn main() {}
@@ -0,0 +1,134 @@
+// tcpecho - TCP echo server
+//
+// Usage:
+//   tcpecho [port]      Echo back everything clients send (default port 7)
+//
+// Serves up to four clients at once until Ctrl+C, sleeping in poll until
+// a client connects or sends something. Try it from the host with
+// `nc <vm-ip> 7`.
+
+#![cfg_attr(target_arch = "riscv64", no_std)]
+#![cfg_attr(target_arch = "riscv64", no_main)]
+
+#[cfg(target_arch = "riscv64")]
+#[no_mangle]
+pub fn main() {
+    use mkfs::{
+        argc, argv, console_log, format_ipv4, is_net_available, poll_fds, print, print_int, sleep,
+        tcp_accept_conn, tcp_disconnect, tcp_get_status, tcp_listen_port, tcp_recv_data, tcp_send_data,
+        PollFd, TcpStatus, POLLIN, POLL_FOREVER,
+    };
+
+    const DEFAULT_PORT: u16 = 7;
+    const MAX_CLIENTS: usize = 4;
+
+    if !is_net_available() {
+        console_log("\x1b[1;31m[X]\x1b[0m Network not available\n");
+        return;
+    }
+
+    let mut port = DEFAULT_PORT;
+    if argc() > 0 {
+        let mut arg = [0u8; 8];
+        let parsed = argv(0, &mut arg)
+            .and_then(|len| core::str::from_utf8(&arg[..len]).ok())
+            .and_then(|s| s.parse::<u16>().ok())
+            .filter(|&p| p != 0);
+        match parsed {
+            Some(p) => port = p,
+            None => {
+                console_log("Usage: tcpecho [port]\n");
+                return;
+            }
+        }
+    }
+
+    let Some(listener) = tcp_listen_port(port) else {
+        console_log("\x1b[1;31m[X]\x1b[0m Cannot listen on port ");
+        print_int(port as i64);
+        console_log(" (in use?)\n");
+        return;
+    };
+    console_log("Listening on port ");
+    print_int(port as i64);
+    console_log(" (Ctrl+C to stop)\n");
+
+    let mut clients: [Option<i32>; MAX_CLIENTS] = [None; MAX_CLIENTS];
+    let mut buf = [0u8; 512];
+
+    loop {
+        // Entry 0 is the listener, only polled when there is room so
+        // waiting clients stay queued; the clients follow
+        let mut fds = [PollFd::NONE; MAX_CLIENTS + 1];
+        if clients.iter().any(|c| c.is_none()) {
+            fds[0] = PollFd::socket(listener, POLLIN);
+        }
+        for (entry, client) in fds[1..].iter_mut().zip(&clients) {
+            if let Some(sock) = *client {
+                *entry = PollFd::socket(sock, POLLIN);
+            }
+        }
+        // None on Ctrl+C
+        if poll_fds(&mut fds, POLL_FOREVER).is_none() {
+            break;
+        }
+
+        if fds[0].ready(POLLIN) {
+            if let Some(free) = clients.iter_mut().find(|c| c.is_none()) {
+                match tcp_accept_conn(listener) {
+                    Ok(Some((sock, peer))) => {
+                        let mut ip_buf = [0u8; 16];
+                        let ip_len = format_ipv4(&peer.ip, &mut ip_buf);
+                        console_log("Connection from ");
+                        print(ip_buf.as_ptr(), ip_len);
+                        console_log(":");
+                        print_int(peer.port as i64);
+                        console_log("\n");
+                        *free = Some(sock);
+                    }
+                    Ok(None) => {}
+                    Err(e) => {
+                        console_log("\x1b[1;31m[X]\x1b[0m ");
+                        console_log(e);
+                        console_log("\n");
+                        break;
+                    }
+                }
+            }
+        }
+
+        for (entry, client) in fds[1..].iter().zip(clients.iter_mut()) {
+            let Some(sock) = *client else { continue };
+            if !entry.ready(POLLIN) {
+                continue;
+            }
+            if let Some(len) = tcp_recv_data(sock, &mut buf, 0) {
+                if len > 0 {
+                    let mut sent = 0;
+                    while sent < len {
+                        match tcp_send_data(sock, &buf[sent..len]) {
+                            Some(n) if n > 0 => sent += n,
+                            Some(_) if tcp_get_status(sock) == TcpStatus::Connected => sleep(5),
+                            _ => break,
+                        }
+                    }
+                    continue;
+                }
+            }
+            // Nothing buffered: drop the client once it has gone away
+            if tcp_get_status(sock) != TcpStatus::Connected {
+                tcp_disconnect(sock);
+                *client = None;
+                console_log("Connection closed\n");
+            }
+        }
+    }
+
+    for sock in clients.iter().flatten() {
+        tcp_disconnect(*sock);
+    }
+    tcp_disconnect(listener);
+}
+
+#[cfg(not(target_arch = "riscv64"))]
+fn main() {}
@@ -75,17 +75,17 @@ pub fn main() {
     print_int(port as i64);
     console_log("...\n");
 
-    if !tcp_connect_ip(&ip, port) {
+    let Some(sock) = tcp_connect_ip(&ip, port) else {
         console_log("\x1b[1;31mConnection failed\x1b[0m\n");
         return;
-    }
+    };
 
     // Wait for connection
     let start = get_time();
     let timeout = 10000; // 10 seconds
     
     loop {
-        let status = tcp_get_status();
+        let status = tcp_get_status(sock);
         match status {
             TcpStatus::Connected => {
                 console_log("Connected to ");
@@ -97,17 +97,18 @@ pub fn main() {
             }
             TcpStatus::Failed => {
                 console_log("\x1b[1;31mConnection refused\x1b[0m\n");
-                tcp_disconnect();
+                tcp_disconnect(sock);
                 return;
             }
             TcpStatus::Closed => {
                 console_log("\x1b[1;31mConnection closed\x1b[0m\n");
+                tcp_disconnect(sock);
                 return;
             }
             TcpStatus::Connecting => {
                 if get_time() - start > timeout {
                     console_log("\x1b[1;31mConnection timeout\x1b[0m\n");
-                    tcp_disconnect();
+                    tcp_disconnect(sock);
                     return;
                 }
                 sleep(50);
@@ -127,14 +128,14 @@ pub fn main() {
         }
 
         // Check connection status
-        let status = tcp_get_status();
+        let status = tcp_get_status(sock);
         if status == TcpStatus::Closed || status == TcpStatus::Failed {
             console_log("\n\x1b[33mConnection closed by remote host.\x1b[0m\n");
             break;
         }
 
         // Try to receive data
-        if let Some(len) = tcp_recv_data(&mut recv_buf, 0) {
+        if let Some(len) = tcp_recv_data(sock, &mut recv_buf, 0) {
             if len > 0 {
                 // Filter and print received data (handle telnet control sequences)
                 print_telnet_data(&recv_buf[..len]);
@@ -161,7 +162,7 @@ pub fn main() {
                         input_buf[input_len] = b'\n';
                         input_len += 1;
                     }
-                    let _ = tcp_send_data(&input_buf[..input_len]);
+                    let _ = tcp_send_data(sock, &input_buf[..input_len]);
                     input_len = 0;
                     console_log("\n");
                 } else if ch == 0x7F || ch == 0x08 {
@@ -185,7 +186,7 @@ pub fn main() {
         sleep(10);
     }
 
-    tcp_disconnect();
+    tcp_disconnect(sock);
 
     fn parse_u16(buf: &[u8]) -> Option<u16> {
         let mut n: u16 = 0;
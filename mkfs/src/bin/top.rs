@@ -1,7 +1,19 @@
-// top - System monitor (simplified)
+// top - System monitor
 //
 // Usage:
-//   top          Display running processes
+//   top              Display running processes, busiest first, refreshing
+//   top -i           Start in the I/O view: syscalls and bytes moved
+//   top -d <secs>    Refresh every <secs> seconds (default 2)
+//   top -n <count>   Stop after <count> refreshes (-n 1 prints once)
+//
+// Keys: q quits, i switches between the CPU and I/O views, m sorts the CPU
+// view by heap use instead of %CPU, space refreshes at once. Ctrl+C quits.
+//
+// %CPU is the share of one hart a process used since the previous refresh
+// (on the first screen: over the last few seconds, as sampled by sysmond).
+// The hart lines show each hart's busy share over the last few seconds.
+// The I/O view counts file bytes read/written and network bytes
+// received/sent through syscalls since each process started.
 
 #![cfg_attr(target_arch = "riscv64", no_std)]
 #![cfg_attr(target_arch = "riscv64", no_main)]
@@ -9,36 +21,348 @@
 #[cfg(target_arch = "riscv64")]
 #[no_mangle]
 pub fn main() {
-    use mkfs::{console_log, get_time, print_int, ps_list, print};
+    use mkfs::{argc, argv, console_available, console_log, get_time, print, print_int, proc_stats, ps_list, read_console, should_cancel, sleep};
 
-    static mut BUF: [u8; 2048] = [0u8; 2048];
+    const MAX_ROWS: usize = 64;
+    const MAX_HARTS: usize = 16;
 
-    let uptime_ms = get_time();
-    let uptime_sec = uptime_ms / 1000;
+    static mut BUF: [u8; 4096] = [0u8; 4096];
+    static mut STATS: [u8; 1024] = [0u8; 1024];
+    /// (pid, cpu_time_ms) at the previous refresh
+    static mut PREVIOUS: [(u64, u64); MAX_ROWS] = [(0, 0); MAX_ROWS];
+
+    /// One line of ps_list: pid:name:state:priority:cpu_time_ms:uptime_ms:cpu_permille
+    /// then the I/O counters fs_read:fs_written:net_rx:net_tx:syscalls and
+    /// the heap bytes in use
+    #[derive(Clone, Copy)]
+    struct Row<'a> {
+        pid: u64,
+        name: &'a [u8],
+        state: &'a [u8],
+        priority: &'a [u8],
+        cpu_time_ms: u64,
+        uptime_ms: u64,
+        usage: u64,
+        /// fs_read, fs_written, net_rx, net_tx, syscalls
+        io: [u64; 5],
+        heap: u64,
+    }
 
-    console_log("\x1b[2J\x1b[H"); // Clear screen
+    impl Row<'_> {
+        fn io_bytes(&self) -> u64 {
+            self.io[..4].iter().sum()
+        }
+    }
 
-    console_log("\x1b[7m top - BAVY OS System Monitor \x1b[0m\n");
-    console_log("Uptime: ");
-    print_int(uptime_sec);
-    console_log("s\n\n");
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    enum View {
+        Cpu,
+        Memory,
+        Io,
+    }
 
-    console_log("\x1b[1;36m  PID  STATE  PRI  CPU TIME  UPTIME  NAME\x1b[0m\n");
-    console_log("\x1b[90m-------------------------------------------------------\x1b[0m\n");
+    fn parse_u64(bytes: &[u8]) -> u64 {
+        let mut n: u64 = 0;
+        for &b in bytes {
+            if b.is_ascii_digit() {
+                n = n.saturating_mul(10).saturating_add((b - b'0') as u64);
+            } else {
+                break;
+            }
+        }
+        n
+    }
 
-    let len = unsafe { ps_list((*core::ptr::addr_of_mut!(BUF)).as_mut_ptr(), 2048) };
-    
-    if len < 0 {
-        console_log("\x1b[1;31mError:\x1b[0m Failed to get process list\n");
-        return;
+    fn parse_row(line: &[u8]) -> Option<Row<'_>> {
+        let mut fields = line.split(|&b| b == b':');
+        let mut row = Row {
+            pid: parse_u64(fields.next()?),
+            name: fields.next()?,
+            state: fields.next()?,
+            priority: fields.next()?,
+            cpu_time_ms: parse_u64(fields.next()?),
+            uptime_ms: parse_u64(fields.next()?),
+            usage: fields.next().map(parse_u64).unwrap_or(0),
+            io: [0; 5],
+            heap: 0,
+        };
+        for counter in row.io.iter_mut() {
+            *counter = fields.next().map(parse_u64).unwrap_or(0);
+        }
+        row.heap = fields.next().map(parse_u64).unwrap_or(0);
+        Some(row)
     }
 
-    if len > 0 {
-        let data = unsafe { &(*core::ptr::addr_of!(BUF))[..len as usize] };
-        print(data.as_ptr(), data.len());
+    fn print_padded_int(n: u64, width: usize) {
+        let mut temp = n;
+        let mut digits = 1usize;
+        while temp >= 10 {
+            digits += 1;
+            temp /= 10;
+        }
+        for _ in 0..width.saturating_sub(digits) {
+            console_log(" ");
+        }
+        print_int(n as i64);
     }
 
-    console_log("\n\x1b[90mPress Ctrl+C to exit\x1b[0m\n");
+    /// Byte count in B/K/M, right-aligned in 7 columns
+    fn print_bytes(n: u64) {
+        let (value, unit) = if n >= 10 * 1024 * 1024 {
+            (n / (1024 * 1024), "M")
+        } else if n >= 10 * 1024 {
+            (n / 1024, "K")
+        } else {
+            (n, "B")
+        };
+        print_padded_int(value, 6);
+        console_log(unit);
+    }
+
+    fn print_padded(s: &[u8], width: usize) {
+        print(s.as_ptr(), s.len());
+        for _ in 0..width.saturating_sub(s.len()) {
+            console_log(" ");
+        }
+    }
+
+    /// Permille as a percentage with one decimal, right-aligned in 5 columns
+    fn print_permille(n: u64) {
+        print_padded_int(n / 10, 3);
+        console_log(".");
+        print_int((n % 10) as i64);
+    }
+
+    /// Uptime and one usage bar per hart, from proc_stats
+    fn print_summary(uptime_ms: i64, processes: usize) {
+        console_log("\x1b[7m top - BAVY OS System Monitor \x1b[0m\n");
+        console_log("Uptime: ");
+        print_int(uptime_ms / 1000);
+        console_log("s  Processes: ");
+        print_int(processes as i64);
+        console_log("\n");
+
+        let len = unsafe { proc_stats((*core::ptr::addr_of_mut!(STATS)).as_mut_ptr(), 1024) };
+        if len <= 0 {
+            console_log("\n");
+            return;
+        }
+        let data = unsafe { &(&*core::ptr::addr_of!(STATS))[..len as usize] };
+        for line in data.split(|&b| b == b'\n').take(MAX_HARTS + 1) {
+            let mut fields = line.split(|&b| b == b':');
+            let (Some(label), Some(usage)) = (fields.next(), fields.next().map(parse_u64)) else {
+                continue;
+            };
+            if label == b"all" || !label.starts_with(b"cpu") {
+                continue;
+            }
+            console_log("Hart ");
+            print_padded(&label[3..], 2);
+            console_log(" [");
+            let filled = (usage.min(1000) / 50) as usize;
+            console_log("\x1b[32m");
+            for _ in 0..filled {
+                console_log("|");
+            }
+            console_log("\x1b[0m");
+            for _ in filled..20 {
+                console_log(" ");
+            }
+            console_log("] ");
+            print_permille(usage);
+            console_log("%\n");
+        }
+        console_log("\n");
+    }
+
+    fn print_cpu_rows(rows: &[Row<'_>]) {
+        console_log("\x1b[1;36m  PID  STATE  PRI   %CPU  CPU TIME  UPTIME     RSS  NAME\x1b[0m\n");
+        console_log("\x1b[90m----------------------------------------------------------------------\x1b[0m\n");
+        for row in rows {
+            if row.usage > 0 {
+                console_log("\x1b[1;97m");
+            }
+            print_padded_int(row.pid, 5);
+            console_log("  ");
+            print_padded(row.state, 6);
+            console_log(" ");
+            print_padded(row.priority, 4);
+            console_log(" ");
+            print_permille(row.usage);
+            console_log("  ");
+            print_padded_int(row.cpu_time_ms, 7);
+            console_log("ms ");
+            print_padded_int(row.uptime_ms / 1000, 6);
+            console_log("s ");
+            print_bytes(row.heap);
+            console_log("  ");
+            print(row.name.as_ptr(), row.name.len());
+            console_log("\x1b[0m\n");
+        }
+    }
+
+    fn print_io_rows(rows: &[Row<'_>]) {
+        console_log("\x1b[1;36m  PID  SYSCALLS     READ    WRITE   NET RX   NET TX  NAME\x1b[0m\n");
+        console_log("\x1b[90m--------------------------------------------------------------\x1b[0m\n");
+        for row in rows {
+            if row.io_bytes() > 0 {
+                console_log("\x1b[1;97m");
+            }
+            print_padded_int(row.pid, 5);
+            console_log("  ");
+            print_padded_int(row.io[4], 8);
+            console_log("  ");
+            print_bytes(row.io[0]);
+            console_log("  ");
+            print_bytes(row.io[1]);
+            console_log("  ");
+            print_bytes(row.io[2]);
+            console_log("  ");
+            print_bytes(row.io[3]);
+            console_log("  ");
+            print(row.name.as_ptr(), row.name.len());
+            console_log("\x1b[0m\n");
+        }
+    }
+
+    /// Draw one screen. `elapsed_ms` is the time since the previous one (0
+    /// on the first), over which %CPU is measured.
+    fn draw(view: View, elapsed_ms: u64) -> bool {
+        let len = unsafe { ps_list((*core::ptr::addr_of_mut!(BUF)).as_mut_ptr(), 4096) };
+        if len < 0 {
+            console_log("\x1b[1;31mError:\x1b[0m Failed to get process list\n");
+            return false;
+        }
+
+        let buf = unsafe { &*core::ptr::addr_of!(BUF) };
+        let previous = unsafe { &mut *core::ptr::addr_of_mut!(PREVIOUS) };
+        let data = &buf[..len as usize];
+        let empty = Row { pid: 0, name: b"", state: b"", priority: b"", cpu_time_ms: 0, uptime_ms: 0, usage: 0, io: [0; 5], heap: 0 };
+        let mut rows = [empty; MAX_ROWS];
+        let mut count = 0;
+        for line in data.split(|&b| b == b'\n') {
+            if count == MAX_ROWS {
+                break;
+            }
+            if let Some(mut row) = parse_row(line) {
+                // Measure over the refresh interval when the process was
+                // already there last time
+                if let Some(&(_, before)) = previous.iter().find(|(pid, _)| *pid == row.pid && *pid != 0) {
+                    if let Some(usage) = (row.cpu_time_ms.saturating_sub(before) * 1000).checked_div(elapsed_ms) {
+                        row.usage = usage.min(1000);
+                    }
+                }
+                rows[count] = row;
+                count += 1;
+            }
+        }
+        let rows = &mut rows[..count];
+        for (slot, row) in previous.iter_mut().zip(rows.iter().map(Some).chain(core::iter::repeat(None))) {
+            *slot = row.map_or((0, 0), |row| (row.pid, row.cpu_time_ms));
+        }
+
+        console_log("\x1b[H\x1b[2J"); // Home and clear screen
+        print_summary(get_time(), count);
+        match view {
+            View::Io => {
+                rows.sort_unstable_by(|a, b| b.io_bytes().cmp(&a.io_bytes()).then(b.io[4].cmp(&a.io[4])));
+                print_io_rows(rows);
+            }
+            View::Memory => {
+                rows.sort_unstable_by(|a, b| b.heap.cmp(&a.heap).then(b.usage.cmp(&a.usage)));
+                print_cpu_rows(rows);
+            }
+            View::Cpu => {
+                rows.sort_unstable_by(|a, b| b.usage.cmp(&a.usage).then(b.cpu_time_ms.cmp(&a.cpu_time_ms)));
+                print_cpu_rows(rows);
+            }
+        }
+        true
+    }
+
+    let mut view = View::Cpu;
+    let mut delay_ms: i64 = 2000;
+    let mut iterations: Option<u64> = None;
+
+    let mut arg = [0u8; 16];
+    let mut i = 0;
+    while i < argc() {
+        let len = argv(i, &mut arg).unwrap_or(0);
+        match &arg[..len] {
+            b"-i" => view = View::Io,
+            b"-d" | b"-n" => {
+                let flag = arg[1];
+                i += 1;
+                let len = argv(i, &mut arg).unwrap_or(0);
+                let value = parse_u64(&arg[..len]);
+                if len == 0 || value == 0 {
+                    console_log("Usage: top [-i] [-d <secs>] [-n <count>]\n");
+                    return;
+                }
+                if flag == b'd' {
+                    delay_ms = (value as i64).saturating_mul(1000);
+                } else {
+                    iterations = Some(value);
+                }
+            }
+            _ => {
+                console_log("Usage: top [-i] [-d <secs>] [-n <count>]\n");
+                return;
+            }
+        }
+        i += 1;
+    }
+
+    let mut drawn = 0u64;
+    let mut last_draw = get_time();
+    let mut elapsed_ms = 0u64;
+    loop {
+        if !draw(view, elapsed_ms) {
+            return;
+        }
+        drawn += 1;
+        if iterations.is_some_and(|count| drawn >= count) {
+            return;
+        }
+        console_log("\n\x1b[90mq: quit  i: I/O view  m: sort by memory  space: refresh\x1b[0m\n");
+
+        // Wait for the next refresh, handling keys meanwhile
+        let deadline = last_draw + delay_ms;
+        loop {
+            if should_cancel() != 0 {
+                return;
+            }
+            if console_available() > 0 {
+                let mut key = [0u8; 1];
+                if read_console(&mut key) > 0 {
+                    match key[0] {
+                        b'q' | b'Q' => {
+                            console_log("\n");
+                            return;
+                        }
+                        b'i' | b'I' => {
+                            view = if view == View::Io { View::Cpu } else { View::Io };
+                            break;
+                        }
+                        b'm' | b'M' => {
+                            view = if view == View::Memory { View::Cpu } else { View::Memory };
+                            break;
+                        }
+                        b' ' => break,
+                        _ => {}
+                    }
+                }
+                continue;
+            }
+            if get_time() >= deadline {
+                break;
+            }
+            sleep(50);
+        }
+        let now = get_time();
+        elapsed_ms = (now - last_draw).max(1) as u64;
+        last_draw = now;
+    }
 }
 
 #[cfg(not(target_arch = "riscv64"))]
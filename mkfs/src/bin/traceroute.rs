@@ -0,0 +1,143 @@
+// traceroute - Show the path packets take to a host
+//
+// Usage:
+//   traceroute [-I] [-m <hops>] <host>
+//
+// Sends three probes per hop with a growing hop limit and prints the router
+// that answered each, with its round trip time. Probes are UDP datagrams to
+// port 33434 and up, or ICMP echo requests with -I (for hosts that filter
+// UDP). -m sets the most hops tried (default 30).
+
+#![cfg_attr(target_arch = "riscv64", no_std)]
+#![cfg_attr(target_arch = "riscv64", no_main)]
+
+#[cfg(target_arch = "riscv64")]
+#[no_mangle]
+pub fn main() {
+    use mkfs::{argc, argv, console_log, format_ipv4, is_net_available, print_int, resolve_dns, traceroute_probe, TraceResult};
+
+    const USAGE: &str = "Usage: traceroute [-I] [-m <hops>] <host>\n";
+    const PROBES_PER_HOP: u16 = 3;
+    const TIMEOUT_MS: u32 = 1000;
+
+    let mut udp = true;
+    let mut max_hops: u8 = 30;
+    let mut host_buf = [0u8; 256];
+    let mut host_len = 0;
+    let mut i = 0;
+    while i < argc() {
+        let mut arg_buf = [0u8; 256];
+        let len = argv(i, &mut arg_buf).unwrap_or(0);
+        match &arg_buf[..len] {
+            b"-I" => udp = false,
+            b"-m" => {
+                i += 1;
+                let mut num_buf = [0u8; 8];
+                let num_len = argv(i, &mut num_buf).unwrap_or(0);
+                match core::str::from_utf8(&num_buf[..num_len]).ok().and_then(|s| s.parse().ok()) {
+                    Some(hops) if hops > 0 => max_hops = hops,
+                    _ => {
+                        console_log(USAGE);
+                        return;
+                    }
+                }
+            }
+            arg => {
+                host_buf[..len].copy_from_slice(arg);
+                host_len = len;
+            }
+        }
+        i += 1;
+    }
+    if host_len == 0 {
+        console_log(USAGE);
+        return;
+    }
+
+    if !is_net_available() {
+        console_log("\x1b[1;31m[X]\x1b[0m Network not available\n");
+        return;
+    }
+
+    let host = unsafe { core::str::from_utf8_unchecked(&host_buf[..host_len]) };
+    let mut ip = [0u8; 4];
+    if !resolve_dns(host, &mut ip) {
+        console_log("\x1b[1;31mError:\x1b[0m Could not resolve ");
+        console_log(host);
+        console_log("\n");
+        return;
+    }
+
+    let mut ip_buf = [0u8; 16];
+    let ip_len = format_ipv4(&ip, &mut ip_buf);
+    console_log("traceroute to ");
+    console_log(host);
+    console_log(" (");
+    console_log(unsafe { core::str::from_utf8_unchecked(&ip_buf[..ip_len]) });
+    console_log("), ");
+    print_int(max_hops as i64);
+    console_log(" hops max\n");
+
+    let mut seq: u16 = 0;
+    for hop in 1..=max_hops {
+        if hop < 10 {
+            console_log(" ");
+        }
+        print_int(hop as i64);
+        console_log(" ");
+
+        let mut last_from: Option<[u8; 4]> = None;
+        let mut done = false;
+        for _ in 0..PROBES_PER_HOP {
+            let (from, rtt_ms, mark) = match traceroute_probe(&ip, udp, hop, seq, TIMEOUT_MS) {
+                TraceResult::Hop { from, rtt_ms } => (from, rtt_ms, None),
+                TraceResult::Reached { from, rtt_ms } => {
+                    done = true;
+                    (from, rtt_ms, None)
+                }
+                TraceResult::Unreachable { from, rtt_ms, code } => {
+                    done = true;
+                    (from, rtt_ms, Some(code))
+                }
+                TraceResult::Timeout => {
+                    console_log(" *");
+                    seq = seq.wrapping_add(1);
+                    continue;
+                }
+                TraceResult::NetworkError => {
+                    console_log("\n\x1b[1;31m[X]\x1b[0m Could not send probe\n");
+                    return;
+                }
+            };
+            seq = seq.wrapping_add(1);
+
+            // Print the address once per hop, again only if it changes
+            if last_from != Some(from) {
+                let mut from_buf = [0u8; 16];
+                let from_len = format_ipv4(&from, &mut from_buf);
+                console_log(" ");
+                console_log(unsafe { core::str::from_utf8_unchecked(&from_buf[..from_len]) });
+                last_from = Some(from);
+            }
+            console_log("  ");
+            print_int(rtt_ms as i64);
+            console_log(" ms");
+            if let Some(code) = mark {
+                console_log(match code {
+                    0 => " !N",
+                    1 => " !H",
+                    2 => " !P",
+                    13 => " !X",
+                    _ => " !",
+                });
+            }
+        }
+        console_log("\n");
+        if done {
+            return;
+        }
+    }
+}
+
+#[cfg(not(target_arch = "riscv64"))]
+fn main() {}
@@ -0,0 +1,30 @@
+// umount - Unmount a filesystem
+//
+// Usage:
+//   umount <dir>
+
+#![cfg_attr(target_arch = "riscv64", no_std)]
+#![cfg_attr(target_arch = "riscv64", no_main)]
+
+#[cfg(target_arch = "riscv64")]
+#[no_mangle]
+pub fn main() {
+    use mkfs::{argc, argv, console_log, unmount};
+
+    if argc() != 1 {
+        console_log("Usage: umount <dir>\n");
+        return;
+    }
+
+    let mut buf = [0u8; 128];
+    let len = argv(0, &mut buf).unwrap_or(0);
+    let dir = unsafe { core::str::from_utf8_unchecked(&buf[..len]) };
+    if !unmount(dir) {
+        console_log("\x1b[1;31mumount:\x1b[0m ");
+        console_log(dir);
+        console_log(": not a mount point\n");
+    }
+}
+
+#[cfg(not(target_arch = "riscv64"))]
+fn main() {}
@@ -0,0 +1,74 @@
+// wasmpipe - Run WASM programs as a pipeline across the CPU cores
+//
+// Usage:
+//   wasmpipe <a.wasm> [args...] '|' <b.wasm> [args...] ['|' ...]
+//
+// Each stage runs on its own WASM worker hart. What a stage prints is
+// streamed into the console input of the next stage; a stage that prints
+// faster than the next one reads is held back until it catches up. The last
+// stage's output is printed here. The separator may be written | or '|'.
+
+#![cfg_attr(target_arch = "riscv64", no_std)]
+#![cfg_attr(target_arch = "riscv64", no_main)]
+
+#[cfg(target_arch = "riscv64")]
+#[no_mangle]
+pub fn main() {
+    use mkfs::{argc, argv, close_file, console_log, fd_read, print, start_wasm_pipeline};
+
+    const USAGE: &str = "Usage: wasmpipe <a.wasm> [args...] '|' <b.wasm> [args...]\n";
+
+    // One "<path> [args...]" line per stage
+    let mut spec = [0u8; 1024];
+    let mut len = 0;
+    let mut stage_empty = true;
+    let mut arg = [0u8; 256];
+
+    for i in 0..argc() {
+        let Some(arg_len) = argv(i, &mut arg) else {
+            console_log(USAGE);
+            return;
+        };
+        let word = &arg[..arg_len];
+        let is_bar = matches!(word, b"|" | b"'|'" | b"\"|\"");
+        if (is_bar && stage_empty) || len + word.len() + 1 > spec.len() {
+            console_log(USAGE);
+            return;
+        }
+        if is_bar {
+            spec[len - 1] = b'\n';
+            stage_empty = true;
+        } else {
+            spec[len..len + word.len()].copy_from_slice(word);
+            spec[len + word.len()] = b' ';
+            len += word.len() + 1;
+            stage_empty = false;
+        }
+    }
+    if stage_empty {
+        console_log(USAGE);
+        return;
+    }
+
+    let Some(spec) = core::str::from_utf8(&spec[..len]).ok() else {
+        console_log(USAGE);
+        return;
+    };
+    let Some(fd) = start_wasm_pipeline(spec) else {
+        console_log("\x1b[1;31m[X]\x1b[0m Cannot start pipeline (see dmesg)\n");
+        return;
+    };
+
+    // Returns once every stage has exited (0) or on Ctrl+C (error)
+    let mut buf = [0u8; 512];
+    while let Some(n) = fd_read(fd, &mut buf) {
+        if n == 0 {
+            break;
+        }
+        print(buf.as_ptr(), n);
+    }
+    close_file(fd);
+}
+
+#[cfg(not(target_arch = "riscv64"))]
+fn main() {}
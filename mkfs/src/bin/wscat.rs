@@ -0,0 +1,125 @@
+// wscat - Interactive WebSocket client
+//
+// Usage:
+//   wscat [-k] <ws://host[:port]/path | wss://...>
+//
+// Each line typed is sent as a text message; messages from the server are
+// printed as they arrive. ESC or Ctrl+C disconnects. -k skips certificate
+// validation for wss:// URLs.
+
+#![cfg_attr(target_arch = "riscv64", no_std)]
+#![cfg_attr(target_arch = "riscv64", no_main)]
+
+#[cfg(target_arch = "riscv64")]
+#[no_mangle]
+pub fn main() {
+    use mkfs::{
+        argc, argv, console_available, console_log, is_net_available, print, print_int, read_console,
+        should_cancel, websocket_close, websocket_connect, websocket_recv, websocket_send, WsEvent,
+        HTTP_INSECURE,
+    };
+
+    let mut flags = 0;
+    let mut url_buf = [0u8; 512];
+    let mut url = None;
+    for i in 0..argc() {
+        let mut arg_buf = [0u8; 512];
+        let Some(len) = argv(i, &mut arg_buf) else {
+            continue;
+        };
+        if &arg_buf[..len] == b"-k" {
+            flags |= HTTP_INSECURE;
+        } else {
+            url_buf[..len].copy_from_slice(&arg_buf[..len]);
+            url = core::str::from_utf8(&url_buf[..len]).ok();
+        }
+    }
+    let Some(url) = url else {
+        console_log("Usage: wscat [-k] <ws://host[:port]/path | wss://...>\n");
+        return;
+    };
+
+    if !is_net_available() {
+        console_log("\x1b[1;31m[X]\x1b[0m Network not available\n");
+        return;
+    }
+
+    console_log("Connecting to ");
+    console_log(url);
+    console_log("...\n");
+    let Some(ws) = websocket_connect(url, flags) else {
+        console_log("\x1b[1;31m[X]\x1b[0m Connection failed (see dmesg)\n");
+        return;
+    };
+    console_log("\x1b[1;32mConnected\x1b[0m (ESC to quit)\n> ");
+
+    let mut recv_buf = [0u8; 4096];
+    let mut input_buf = [0u8; 512];
+    let mut input_len = 0usize;
+
+    loop {
+        if should_cancel() != 0 {
+            break;
+        }
+
+        match websocket_recv(ws, &mut recv_buf, 20) {
+            WsEvent::Timeout => {}
+            WsEvent::Text(len) => {
+                console_log("\r\x1b[K\x1b[36m< \x1b[0m");
+                print(recv_buf.as_ptr(), len);
+                console_log("\n> ");
+                print(input_buf.as_ptr(), input_len);
+            }
+            WsEvent::Binary(len) => {
+                console_log("\r\x1b[K\x1b[36m< \x1b[90m[");
+                print_int(len as i64);
+                console_log(" bytes binary]\x1b[0m\n> ");
+                print(input_buf.as_ptr(), input_len);
+            }
+            WsEvent::Closed => {
+                console_log("\n\x1b[33mConnection closed by server.\x1b[0m\n");
+                return;
+            }
+            WsEvent::Error => {
+                console_log("\n\x1b[1;31mConnection lost.\x1b[0m\n");
+                return;
+            }
+        }
+
+        while console_available() > 0 {
+            let mut ch_buf = [0u8; 1];
+            if read_console(&mut ch_buf) == 0 {
+                break;
+            }
+            match ch_buf[0] {
+                0x1B => {
+                    websocket_close(ws);
+                    console_log("\n\x1b[33mConnection closed.\x1b[0m\n");
+                    return;
+                }
+                b'\r' | b'\n' => {
+                    console_log("\n> ");
+                    if input_len > 0 && !websocket_send(ws, &input_buf[..input_len], 0) {
+                        console_log("\x1b[1;31m[X]\x1b[0m Send failed\n> ");
+                    }
+                    input_len = 0;
+                }
+                0x7F | 0x08 if input_len > 0 => {
+                    input_len -= 1;
+                    console_log("\x08 \x08");
+                }
+                ch @ 0x20..=0x7E if input_len < input_buf.len() => {
+                    input_buf[input_len] = ch;
+                    input_len += 1;
+                    print(&ch, 1);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    websocket_close(ws);
+}
+
+#[cfg(not(target_arch = "riscv64"))]
+fn main() {}
@@ -0,0 +1,307 @@
+//! `mkfs fsck` - consistency checker for SFS images
+//!
+//! Same checks as the kernel's `fs::fsck` (keep the two in sync):
+//! superblock (including the format revision), directory entries, sector
+//! chains and the allocation bitmap. Images in a newer revision are only
+//! checked. With `--repair` broken chains are truncated to their readable prefix,
+//! unreadable entries are cleared and the bitmap is rebuilt.
+//!
+//! Exit codes follow e2fsck: 0 clean, 1 errors corrected, 4 errors left.
+
+use std::collections::BTreeSet;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use crate::{
+    COMPRESSED_FLAG, DIR_ENTRY_SIZE, ENTRIES_PER_SECTOR, MAGIC, SECTOR_SIZE, SEC_DATA_START,
+    SEC_DIR_COUNT, SEC_DIR_START, SEC_MAP_COUNT, SEC_MAP_START, SEC_SUPER, SYMLINK_FLAG,
+};
+
+/// Payload bytes per data sector (after the 4-byte next pointer)
+const BLOCK_DATA: usize = 508;
+
+/// Sectors tracked by one bitmap sector
+const SECTORS_PER_MAP: u64 = SECTOR_SIZE * 8;
+
+struct Image {
+    file: File,
+    repair: bool,
+    problems: Vec<String>,
+    repaired: usize,
+}
+
+impl Image {
+    fn read(&mut self, sector: u64) -> std::io::Result<[u8; 512]> {
+        let mut buf = [0u8; 512];
+        self.file.seek(SeekFrom::Start(sector * SECTOR_SIZE))?;
+        self.file.read_exact(&mut buf)?;
+        Ok(buf)
+    }
+
+    fn write(&mut self, sector: u64, buf: &[u8; 512]) -> std::io::Result<()> {
+        self.file.seek(SeekFrom::Start(sector * SECTOR_SIZE))?;
+        self.file.write_all(buf)
+    }
+
+    fn problem(&mut self, message: String) {
+        println!("  ❌ {}", message);
+        self.problems.push(message);
+    }
+
+    /// Clear the next pointer of `sector`
+    fn terminate_chain(&mut self, sector: u32) -> std::io::Result<()> {
+        let mut data = self.read(sector as u64)?;
+        data[0..4].copy_from_slice(&0u32.to_le_bytes());
+        self.write(sector as u64, &data)
+    }
+}
+
+/// Check `path`, returning the process exit code
+pub fn run(path: &Path, repair: bool) -> std::io::Result<i32> {
+    let file = OpenOptions::new().read(true).write(repair).open(path)?;
+    let capacity = file.metadata()?.len() / SECTOR_SIZE;
+    let mut image = Image {
+        file,
+        repair,
+        problems: Vec::new(),
+        repaired: 0,
+    };
+    println!("Checking SFS image: {:?} ({} sectors)", path, capacity);
+
+    let mut superblock = image.read(SEC_SUPER)?;
+    let magic = u32::from_le_bytes(superblock[0..4].try_into().unwrap());
+    let recorded = u32::from_le_bytes(superblock[4..8].try_into().unwrap()) as u64;
+
+    let mut superblock_bad = false;
+    if magic != MAGIC {
+        image.problem(format!("superblock: bad magic {:#010x}", magic));
+        superblock_bad = true;
+    }
+    let (major, minor, features) = crate::upgrade::format_of(&superblock);
+    if magic == MAGIC {
+        println!("  Format:         {}.{}", major, minor);
+        if crate::upgrade::is_newer(major, minor, features) {
+            // Repairs could undo what the newer revision relies on
+            image.problem(format!("superblock: format {}.{} is newer than this tool, not repairing", major, minor));
+            image.repair = false;
+        }
+    }
+    let total = if recorded < SEC_DATA_START || recorded > capacity {
+        image.problem(format!(
+            "superblock: sector count {} out of range (image has {})",
+            recorded, capacity
+        ));
+        superblock_bad = true;
+        capacity
+    } else {
+        recorded
+    };
+
+    let mut used = BTreeSet::new();
+    let (files, sectors) = check_directory(&mut image, total, &mut used)?;
+
+    if superblock_bad && image.repair {
+        if magic != MAGIC && files == 0 {
+            image.problem("superblock: no directory entries found, not rewriting".to_string());
+        } else {
+            superblock[0..4].copy_from_slice(&MAGIC.to_le_bytes());
+            superblock[4..8].copy_from_slice(&(total as u32).to_le_bytes());
+            image.write(SEC_SUPER, &superblock)?;
+            image.repaired += 1;
+            superblock_bad = false;
+        }
+    }
+
+    // Never write a bitmap onto something that may not be SFS
+    if superblock_bad {
+        image.repair = false;
+    }
+    let orphans = check_bitmap(&mut image, total, &used)?;
+
+    println!("\n  Entries:        {}", files);
+    println!("  Data sectors:   {}", sectors);
+    println!("  Orphaned:       {}", orphans);
+
+    if image.problems.is_empty() {
+        println!("\n✅ Filesystem clean");
+        Ok(0)
+    } else if image.repaired > 0 {
+        println!("\n🔧 {} problem(s), {} repaired", image.problems.len(), image.repaired);
+        Ok(1)
+    } else {
+        println!("\n⚠️  {} problem(s) found, run with --repair to fix", image.problems.len());
+        Ok(4)
+    }
+}
+
+/// Validate every directory entry and walk its chain. Returns (entries, sectors).
+fn check_directory(
+    image: &mut Image,
+    total: u64,
+    used: &mut BTreeSet<u32>,
+) -> std::io::Result<(usize, usize)> {
+    let mut files = 0;
+    let mut sectors = 0;
+    // (name, last sector, next) of intact chains that point past their size
+    let mut tails: Vec<(String, u32, u32)> = Vec::new();
+
+    for sector in SEC_DIR_START..SEC_DIR_START + SEC_DIR_COUNT {
+        let mut buf = image.read(sector)?;
+        let mut dirty = false;
+
+        for j in 0..ENTRIES_PER_SECTOR as usize {
+            let offset = j * DIR_ENTRY_SIZE;
+            if buf[offset] == 0 {
+                continue;
+            }
+            let entry = &mut buf[offset..offset + DIR_ENTRY_SIZE];
+
+            let name_len = entry[..64].iter().position(|&c| c == 0).unwrap_or(64);
+            let name = match std::str::from_utf8(&entry[..name_len]) {
+                Ok(name) if !name.chars().any(char::is_control) => name.to_string(),
+                _ => {
+                    image.problem(format!("directory sector {} slot {}: invalid name", sector, j));
+                    if image.repair {
+                        entry.fill(0);
+                        dirty = true;
+                        image.repaired += 1;
+                    }
+                    continue;
+                }
+            };
+            files += 1;
+
+            let raw_size = u32::from_le_bytes(entry[64..68].try_into().unwrap());
+            let size = (raw_size & !(SYMLINK_FLAG | COMPRESSED_FLAG)) as usize;
+            let head = u32::from_le_bytes(entry[68..72].try_into().unwrap());
+
+            let needed = size.div_ceil(BLOCK_DATA);
+            let mut next = head;
+            let mut last: Option<u32> = None;
+            let mut count = 0;
+            let mut broken: Option<String> = None;
+
+            while count < needed {
+                if next == 0 {
+                    broken = Some(format!("chain ends after {} of {} sectors", count, needed));
+                    break;
+                }
+                if (next as u64) < SEC_DATA_START || next as u64 >= total {
+                    broken = Some(format!("sector {} out of range", next));
+                    break;
+                }
+                if !used.insert(next) {
+                    broken = Some(format!("sector {} already in use", next));
+                    break;
+                }
+
+                let data = image.read(next as u64)?;
+                last = Some(next);
+                next = u32::from_le_bytes(data[0..4].try_into().unwrap());
+                count += 1;
+            }
+            sectors += count;
+
+            let reason = match broken {
+                Some(reason) => reason,
+                None if next == 0 => continue,
+                None => match last {
+                    Some(last) => {
+                        tails.push((name, last, next));
+                        continue;
+                    }
+                    None => "empty file has a data chain".to_string(),
+                },
+            };
+            image.problem(format!("{}: {}", name, reason));
+            if !image.repair {
+                continue;
+            }
+
+            match last {
+                Some(sector) => image.terminate_chain(sector)?,
+                None => entry[68..72].copy_from_slice(&0u32.to_le_bytes()),
+            }
+            let new_size = (count * BLOCK_DATA) as u32 | (raw_size & (SYMLINK_FLAG | COMPRESSED_FLAG));
+            entry[64..68].copy_from_slice(&new_size.to_le_bytes());
+            dirty = true;
+            image.repaired += 1;
+        }
+
+        if dirty {
+            image.write(sector, &buf)?;
+        }
+    }
+
+    // Tails are walked last so they can never claim another file's sectors
+    for (name, mut last, mut next) in tails {
+        while next != 0 {
+            if (next as u64) < SEC_DATA_START || next as u64 >= total || used.contains(&next) {
+                image.problem(format!("{}: chain continues into sector {} past end of file", name, next));
+                if image.repair {
+                    image.terminate_chain(last)?;
+                    image.repaired += 1;
+                }
+                break;
+            }
+            used.insert(next);
+            sectors += 1;
+
+            let data = image.read(next as u64)?;
+            last = next;
+            next = u32::from_le_bytes(data[0..4].try_into().unwrap());
+        }
+    }
+
+    Ok((files, sectors))
+}
+
+/// Compare the bitmap with the sectors in use. Returns the orphan count.
+fn check_bitmap(image: &mut Image, total: u64, used: &BTreeSet<u32>) -> std::io::Result<usize> {
+    let mut orphans = 0;
+    let mut unmarked = 0;
+
+    for i in 0..SEC_MAP_COUNT {
+        let first = i * SECTORS_PER_MAP;
+        if first >= total {
+            break;
+        }
+        let mut buf = image.read(SEC_MAP_START + i)?;
+        let mut dirty = false;
+
+        for bit in 0..SECTORS_PER_MAP.min(total - first) {
+            let sector = first + bit;
+            let (byte, mask) = ((bit / 8) as usize, 1u8 << (bit % 8));
+            let marked = buf[byte] & mask != 0;
+            let in_use = sector < SEC_DATA_START || used.contains(&(sector as u32));
+
+            if marked != in_use {
+                if marked {
+                    orphans += 1;
+                } else {
+                    unmarked += 1;
+                }
+                if image.repair {
+                    buf[byte] ^= mask;
+                    dirty = true;
+                }
+            }
+        }
+
+        if dirty {
+            image.write(SEC_MAP_START + i, &buf)?;
+        }
+    }
+
+    for (count, what) in [(orphans, "orphaned sectors"), (unmarked, "sectors in use but not marked")] {
+        if count > 0 {
+            image.problem(format!("bitmap: {} {}", count, what));
+            if image.repair {
+                image.repaired += 1;
+            }
+        }
+    }
+
+    Ok(orphans)
+}
@@ -0,0 +1,304 @@
+//! `mkfs ls`, `mkfs extract` and `mkfs diff` - look inside SFS images
+//!
+//! Read-only: images are never modified. Names are shown as stored (root
+//! entries written by mkfs have no leading '/', directories end in '/').
+//! Damaged chains are read as far as possible and flagged; `mkfs fsck`
+//! explains what is wrong with them. Compressed files are shown and
+//! compared by their decompressed contents.
+
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Component, Path, PathBuf};
+
+use crate::lz4;
+use crate::{
+    COMPRESSED_FLAG, DIR_ENTRY_SIZE, ENTRIES_PER_SECTOR, MAGIC, SECTOR_SIZE, SEC_DATA_START,
+    SEC_DIR_COUNT, SEC_DIR_START, SEC_SUPER, SYMLINK_FLAG,
+};
+
+/// Payload bytes per data sector (after the 4-byte next pointer)
+const BLOCK_DATA: usize = 508;
+
+/// One directory entry with its contents
+struct Entry {
+    name: String,
+    symlink: bool,
+    /// Stored LZ4-compressed; `data` and `size` are decompressed once read
+    compressed: bool,
+    /// Size recorded in the directory entry
+    size: usize,
+    /// Data sectors actually read
+    sectors: usize,
+    data: Vec<u8>,
+    /// The chain ended before `size` bytes were read, or did not decompress
+    broken: bool,
+}
+
+impl Entry {
+    fn is_dir(&self) -> bool {
+        self.name.ends_with('/')
+    }
+
+    fn kind(&self) -> &'static str {
+        if self.is_dir() {
+            "dir"
+        } else if self.symlink {
+            "link"
+        } else {
+            "file"
+        }
+    }
+}
+
+/// Every entry in the image, by name
+fn read_image(path: &Path) -> std::io::Result<BTreeMap<String, Entry>> {
+    let mut file = File::open(path)?;
+    let capacity = file.metadata()?.len() / SECTOR_SIZE;
+
+    let superblock = read_sector(&mut file, SEC_SUPER)?;
+    let magic = u32::from_le_bytes(superblock[0..4].try_into().unwrap());
+    if magic != MAGIC {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("{:?} is not an SFS image (magic {:#010x})", path, magic),
+        ));
+    }
+    let recorded = u32::from_le_bytes(superblock[4..8].try_into().unwrap()) as u64;
+    let total = recorded.min(capacity);
+
+    let mut entries = BTreeMap::new();
+    for sector in SEC_DIR_START..SEC_DIR_START + SEC_DIR_COUNT {
+        let buf = read_sector(&mut file, sector)?;
+
+        for j in 0..ENTRIES_PER_SECTOR as usize {
+            let raw = &buf[j * DIR_ENTRY_SIZE..(j + 1) * DIR_ENTRY_SIZE];
+            if raw[0] == 0 {
+                continue;
+            }
+            let name_len = raw[..64].iter().position(|&c| c == 0).unwrap_or(64);
+            let name = String::from_utf8_lossy(&raw[..name_len]).into_owned();
+            let raw_size = u32::from_le_bytes(raw[64..68].try_into().unwrap());
+            let head = u32::from_le_bytes(raw[68..72].try_into().unwrap());
+
+            let mut entry = Entry {
+                name: name.clone(),
+                symlink: raw_size & SYMLINK_FLAG != 0,
+                compressed: raw_size & COMPRESSED_FLAG != 0,
+                size: (raw_size & !(SYMLINK_FLAG | COMPRESSED_FLAG)) as usize,
+                sectors: 0,
+                data: Vec::new(),
+                broken: false,
+            };
+            read_chain(&mut file, total, head, &mut entry)?;
+            if entry.compressed && !entry.broken {
+                decompress(&mut entry);
+            }
+            entries.insert(name, entry);
+        }
+    }
+    Ok(entries)
+}
+
+/// Follow the entry's sector chain until `size` bytes are read
+fn read_chain(file: &mut File, total: u64, head: u32, entry: &mut Entry) -> std::io::Result<()> {
+    let needed = entry.size.div_ceil(BLOCK_DATA);
+    let mut next = head;
+
+    while entry.sectors < needed {
+        if (next as u64) < SEC_DATA_START || next as u64 >= total {
+            entry.broken = true;
+            break;
+        }
+        let data = read_sector(file, next as u64)?;
+        let take = (entry.size - entry.data.len()).min(BLOCK_DATA);
+        entry.data.extend_from_slice(&data[4..4 + take]);
+        entry.sectors += 1;
+        next = u32::from_le_bytes(data[0..4].try_into().unwrap());
+    }
+    Ok(())
+}
+
+/// Replace the stored bytes with the original contents
+fn decompress(entry: &mut Entry) {
+    let original = match entry.data.get(0..4) {
+        Some(len) => u32::from_le_bytes(len.try_into().unwrap()) as usize,
+        None => {
+            entry.broken = true;
+            return;
+        }
+    };
+    match lz4::decompress(&entry.data[4..], original) {
+        Some(data) => {
+            entry.size = data.len();
+            entry.data = data;
+        }
+        None => entry.broken = true,
+    }
+}
+
+fn read_sector(file: &mut File, sector: u64) -> std::io::Result<[u8; 512]> {
+    let mut buf = [0u8; 512];
+    file.seek(SeekFrom::Start(sector * SECTOR_SIZE))?;
+    file.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+fn warn_broken(entries: &BTreeMap<String, Entry>) {
+    let broken = entries.values().filter(|e| e.broken).count();
+    if broken > 0 {
+        println!("\n⚠️  {} entries have broken chains, run `mkfs fsck` for details", broken);
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// ls
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// List every entry in `path`
+pub fn ls(path: &Path) -> std::io::Result<i32> {
+    let entries = read_image(path)?;
+
+    println!("{:>10} {:>8}  {:<4}  NAME", "SIZE", "SECTORS", "TYPE");
+    for entry in entries.values() {
+        let mut name = entry.name.clone();
+        if entry.symlink {
+            name.push_str(" -> ");
+            name.push_str(&String::from_utf8_lossy(&entry.data));
+        }
+        if entry.compressed {
+            name.push_str("  (lz4)");
+        }
+        if entry.broken {
+            name.push_str("  (broken chain)");
+        }
+        println!("{:>10} {:>8}  {:<4}  {}", entry.size, entry.sectors, entry.kind(), name);
+    }
+
+    let bytes: usize = entries.values().map(|e| e.size).sum();
+    let sectors: usize = entries.values().map(|e| e.sectors).sum();
+    println!("\n{} entries, {} bytes in {} data sectors", entries.len(), bytes, sectors);
+    warn_broken(&entries);
+    Ok(0)
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// extract
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Copy every entry in `path` into `dir`, recreating directories and symlinks
+pub fn extract(path: &Path, dir: &Path) -> std::io::Result<i32> {
+    let entries = read_image(path)?;
+    fs::create_dir_all(dir)?;
+
+    let mut files = 0;
+    let mut skipped = 0;
+    for entry in entries.values() {
+        let target = match host_path(dir, &entry.name) {
+            Some(target) => target,
+            None => {
+                println!("  ⚠️  Skipping {:?}: unsafe path", entry.name);
+                skipped += 1;
+                continue;
+            }
+        };
+
+        if entry.is_dir() {
+            fs::create_dir_all(&target)?;
+            continue;
+        }
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        if entry.symlink {
+            let link = String::from_utf8_lossy(&entry.data).into_owned();
+            if !make_symlink(&link, &target)? {
+                println!("  ⚠️  Skipping symlink {} -> {}: not supported here", entry.name, link);
+                skipped += 1;
+                continue;
+            }
+        } else {
+            fs::write(&target, &entry.data)?;
+        }
+        println!("  {}", entry.name);
+        files += 1;
+    }
+
+    println!("\n✅ Extracted {} files to {:?}", files, dir);
+    if skipped > 0 {
+        println!("⚠️  {} entries skipped", skipped);
+    }
+    warn_broken(&entries);
+    Ok(0)
+}
+
+/// Host path for an SFS name, refusing anything that would escape `dir`
+fn host_path(dir: &Path, name: &str) -> Option<PathBuf> {
+    let relative = Path::new(name.trim_start_matches('/'));
+    if relative.as_os_str().is_empty() {
+        return None;
+    }
+    if relative.components().all(|c| matches!(c, Component::Normal(_))) {
+        Some(dir.join(relative))
+    } else {
+        None
+    }
+}
+
+#[cfg(unix)]
+fn make_symlink(link: &str, target: &Path) -> std::io::Result<bool> {
+    if target.symlink_metadata().is_ok() {
+        fs::remove_file(target)?;
+    }
+    std::os::unix::fs::symlink(link, target)?;
+    Ok(true)
+}
+
+#[cfg(not(unix))]
+fn make_symlink(_link: &str, _target: &Path) -> std::io::Result<bool> {
+    Ok(false)
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// diff
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Compare the entries of two images. Exit code follows diff(1): 0 same, 1 different.
+pub fn diff(a: &Path, b: &Path) -> std::io::Result<i32> {
+    let old = read_image(a)?;
+    let new = read_image(b)?;
+    let mut changes = 0;
+
+    for (name, entry) in &old {
+        match new.get(name) {
+            None => {
+                println!("- {} ({} bytes)", name, entry.size);
+                changes += 1;
+            }
+            Some(other) if other.symlink != entry.symlink => {
+                println!("~ {} ({} -> {})", name, entry.kind(), other.kind());
+                changes += 1;
+            }
+            Some(other) if other.data != entry.data => {
+                println!("~ {} ({} -> {} bytes)", name, entry.size, other.size);
+                changes += 1;
+            }
+            Some(_) => {}
+        }
+    }
+    for (name, entry) in &new {
+        if !old.contains_key(name) {
+            println!("+ {} ({} bytes)", name, entry.size);
+            changes += 1;
+        }
+    }
+
+    if changes == 0 {
+        println!("Images contain the same files");
+        Ok(0)
+    } else {
+        println!("\n{} difference(s)", changes);
+        Ok(1)
+    }
+}
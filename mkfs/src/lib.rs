@@ -83,6 +83,28 @@ pub fn get_time() -> i64 { 0 }
 #[cfg(not(target_arch = "riscv64"))]
 pub fn poweroff() -> ! { loop {} }
 #[cfg(not(target_arch = "riscv64"))]
+pub fn exit(_code: i32) -> ! { unreachable!() }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn hibernate_system() -> bool { false }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn qr_encode(_text: &str, _modules: &mut [u8]) -> Option<usize> { None }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn play_tone(_freq: u32, _duration_ms: u32, _square: bool) -> bool { false }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn set_event_sounds(_enabled: Option<bool>) -> bool { false }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn mount_9p_tcp(_ip: &[u8; 4], _port: u16, _path: &str) -> bool { false }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn unmount(_path: &str) -> bool { false }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn get_mounts(_buf: &mut [u8]) -> Option<usize> { None }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn set_display_params(_brightness: Option<u16>, _contrast: Option<u16>, _gamma: Option<u16>) -> Option<DisplayParams> { None }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn get_settings(_buf: &mut [u8]) -> Option<usize> { None }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn set_setting(_key: &str, _value: &str) -> bool { false }
+#[cfg(not(target_arch = "riscv64"))]
 pub fn is_net_available() -> bool { false }
 #[cfg(not(target_arch = "riscv64"))]
 pub fn env_get(_key_ptr: *const u8, _key_len: i32, _val_ptr: *mut u8, _val_len: i32) -> i32 { -1 }
@@ -97,9 +119,57 @@ pub fn fs_read(_path_ptr: *const u8, _path_len: i32, _buf_ptr: *mut u8, _buf_len
 #[cfg(not(target_arch = "riscv64"))]
 pub fn ps_list(_buf_ptr: *mut u8, _buf_len: i32) -> i32 { -1 }
 #[cfg(not(target_arch = "riscv64"))]
+pub fn proc_stats(_buf_ptr: *mut u8, _buf_len: i32) -> i32 { -1 }
+#[cfg(not(target_arch = "riscv64"))]
 pub fn get_klog(_count: usize, _buf: &mut [u8]) -> Option<usize> { None }
 #[cfg(not(target_arch = "riscv64"))]
 pub fn kill_process(_pid: u32) -> KillResult { KillResult::NotFound }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn signal_process(_pid: i32, _sig: i32) -> KillResult { KillResult::NotFound }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn process_group(_pid: u32) -> Option<u32> { None }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn set_process_group(_pid: u32, _pgid: u32) -> bool { false }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn process_nice(_pid: u32) -> Option<i32> { None }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn renice_process(_pid: u32, _nice: i32) -> bool { false }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn process_affinity(_pid: u32) -> Option<u128> { None }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn set_process_affinity(_pid: u32, _mask: u128) -> AffinityResult { AffinityResult::NotFound }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn wait_for_child(_pid: u32, _block: bool) -> WaitResult { WaitResult::NoChild }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn spawn_program(_path: &str, _args: &str) -> SpawnResult { SpawnResult::NotFound }
+#[cfg(not(target_arch = "riscv64"))]
+pub const SIGINT: i32 = 2;
+#[cfg(not(target_arch = "riscv64"))]
+pub const SIGKILL: i32 = 9;
+#[cfg(not(target_arch = "riscv64"))]
+pub const SIGTERM: i32 = 15;
+#[cfg(not(target_arch = "riscv64"))]
+pub const SIGCONT: i32 = 18;
+#[cfg(not(target_arch = "riscv64"))]
+pub const SIGSTOP: i32 = 19;
+#[cfg(not(target_arch = "riscv64"))]
+pub const SIGTSTP: i32 = 20;
+#[cfg(not(target_arch = "riscv64"))]
+pub fn set_signal_handler(_sig: i32, _handler: SigHandler) -> bool { false }
+#[cfg(not(target_arch = "riscv64"))]
+pub const MQ_FOREVER: u64 = u64::MAX;
+#[cfg(not(target_arch = "riscv64"))]
+pub fn open_queue(_name: &str, _capacity: usize) -> Option<u32> { None }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn queue_send(_id: u32, _data: &[u8], _timeout_ms: u64) -> MqResult { MqResult::Closed }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn queue_recv(_id: u32, _buf: &mut [u8], _timeout_ms: u64) -> MqResult { MqResult::Closed }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn unlink_queue(_name: &str) -> bool { false }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn process_memory(_pid: u32) -> Option<(u64, Option<u64>)> { None }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn set_memory_limit(_pid: u32, _limit: Option<u64>) -> MemLimitResult { MemLimitResult::NotFound }
 
 // Additional stubs for FS commands
 #[cfg(not(target_arch = "riscv64"))]
@@ -119,12 +189,20 @@ pub fn remove_file(_path: &str) -> bool { false }
 #[cfg(not(target_arch = "riscv64"))]
 pub fn is_dir(_path: &str) -> bool { false }
 #[cfg(not(target_arch = "riscv64"))]
+pub fn sync() -> bool { false }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn fs_sync() -> i32 { -1 }
+#[cfg(not(target_arch = "riscv64"))]
 pub fn file_exists(_path: &str) -> bool { false }
 #[cfg(not(target_arch = "riscv64"))]
 pub fn read_file(_path: &str, _buf: &mut [u8]) -> Option<usize> { None }
 #[cfg(not(target_arch = "riscv64"))]
 pub fn write_file(_path: &str, _data: &[u8]) -> bool { false }
 #[cfg(not(target_arch = "riscv64"))]
+pub fn append_file(_path: &str, _data: &[u8]) -> bool { false }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn write_file_at(_path: &str, _offset: u64, _data: &[u8]) -> bool { false }
+#[cfg(not(target_arch = "riscv64"))]
 pub fn file_stat(_path: &str) -> Option<FileStat> { None }
 #[cfg(not(target_arch = "riscv64"))]
 pub fn getenv(_key: &str, _buf: &mut [u8]) -> Option<usize> { None }
@@ -135,35 +213,217 @@ pub fn get_ps_list(_buf: &mut [u8]) -> Option<usize> { None }
 #[cfg(not(target_arch = "riscv64"))]
 pub fn sleep(_ms: u64) {}
 
+// File descriptor stubs
+#[cfg(not(target_arch = "riscv64"))]
+pub const O_RDONLY: i32 = 0;
+#[cfg(not(target_arch = "riscv64"))]
+pub const O_WRONLY: i32 = 1;
+#[cfg(not(target_arch = "riscv64"))]
+pub const O_RDWR: i32 = 2;
+#[cfg(not(target_arch = "riscv64"))]
+pub const O_CREAT: i32 = 0x40;
+#[cfg(not(target_arch = "riscv64"))]
+pub const O_TRUNC: i32 = 0x200;
+#[cfg(not(target_arch = "riscv64"))]
+pub const O_APPEND: i32 = 0x400;
+#[cfg(not(target_arch = "riscv64"))]
+pub const SEEK_SET: i32 = 0;
+#[cfg(not(target_arch = "riscv64"))]
+pub const SEEK_CUR: i32 = 1;
+#[cfg(not(target_arch = "riscv64"))]
+pub const SEEK_END: i32 = 2;
+#[cfg(not(target_arch = "riscv64"))]
+pub fn open(_path_ptr: *const u8, _path_len: i32, _flags: i32) -> i32 { -1 }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn read(_fd: i32, _buf_ptr: *mut u8, _buf_len: i32) -> i32 { -1 }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn write(_fd: i32, _data_ptr: *const u8, _data_len: i32) -> i32 { -1 }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn seek(_fd: i32, _offset: i64, _whence: i32) -> i64 { -1 }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn close(_fd: i32) -> i32 { -1 }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn open_file(_path: &str, _flags: i32) -> Option<i32> { None }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn fd_read(_fd: i32, _buf: &mut [u8]) -> Option<usize> { None }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn fd_write(_fd: i32, _data: &[u8]) -> Option<usize> { None }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn fd_seek(_fd: i32, _offset: i64, _whence: i32) -> Option<u64> { None }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn close_file(_fd: i32) -> bool { false }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn symlink(_target: &str, _path: &str) -> bool { false }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn readlink(_path: &str, _buf: &mut [u8]) -> Option<usize> { None }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn rename(_from: &str, _to: &str) -> bool { false }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn load_module(_path: &str) -> bool { false }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn unload_module(_name: &str) -> bool { false }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn list_modules(_buf: &mut [u8]) -> Option<usize> { None }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn get_bootchart(_buf: &mut [u8]) -> Option<usize> { None }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn heapdiff_start() -> bool { false }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn heapdiff_report(_buf: &mut [u8], _stop: bool) -> Option<usize> { None }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn memstat_report(_buf: &mut [u8]) -> Option<usize> { None }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn start_wasm_pipeline(_spec: &str) -> Option<i32> { None }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn inputrec_record(_path: &str) -> bool { false }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn inputrec_check(_x: u16, _y: u16, _w: u16, _h: u16) -> Option<u32> { None }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn inputrec_stop() -> Option<usize> { None }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn inputrec_replay(_path: &str, _speed: u64) -> Option<usize> { None }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn inputrec_busy() -> bool { false }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn inputrec_report(_buf: &mut [u8]) -> Option<(usize, usize)> { None }
+
 // Network stubs
 #[cfg(not(target_arch = "riscv64"))]
 pub fn resolve_dns(_hostname: &str, _ip_buf: &mut [u8; 4]) -> bool { false }
 #[cfg(not(target_arch = "riscv64"))]
 pub fn ping(_ip: &[u8; 4], _seq: i32, _timeout_ms: i32) -> PingResult { PingResult::Timeout }
 #[cfg(not(target_arch = "riscv64"))]
+pub fn flush_dns() -> (usize, usize) { (0, 0) }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn resolve_dns6(_hostname: &str, _ip_buf: &mut [u8; 16]) -> bool { false }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn query_dns(_hostname: &str, _qtype: u16, _buf: &mut [u8]) -> DnsQuery { DnsQuery::Error }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn ping6(_ip: &[u8; 16], _seq: u16, _timeout_ms: u32) -> PingResult { PingResult::Timeout }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn traceroute_probe(_ip: &[u8; 4], _udp: bool, _hop_limit: u8, _seq: u16, _timeout_ms: u32) -> TraceResult {
+    TraceResult::NetworkError
+}
+#[cfg(not(target_arch = "riscv64"))]
 pub fn http_fetch(_url: &str, _buf: &mut [u8]) -> Option<usize> { None }
 #[cfg(not(target_arch = "riscv64"))]
-pub fn tcp_connect(_ip: &[u8; 4], _port: u16) -> bool { false }
+pub const HTTP_INSECURE: u64 = 1;
+#[cfg(not(target_arch = "riscv64"))]
+pub fn http_fetch_with(_url: &str, _buf: &mut [u8], _flags: u64) -> Option<usize> { None }
+#[cfg(not(target_arch = "riscv64"))]
+pub const HTTP_PROGRESS: u64 = 2;
+#[cfg(not(target_arch = "riscv64"))]
+pub const KLOG_TEXT_LEN: usize = 232;
+#[cfg(not(target_arch = "riscv64"))]
+#[derive(Clone, Copy)]
+pub struct KlogRecord {
+    pub timestamp_ms: u64,
+    pub level: u8,
+    pub hart: u8,
+    pub len: usize,
+}
+#[cfg(not(target_arch = "riscv64"))]
+pub struct KlogReader {
+    pub lost: u64,
+}
+#[cfg(not(target_arch = "riscv64"))]
+impl KlogReader {
+    pub fn open(_from_oldest: bool) -> Option<Self> { None }
+    pub fn head(&self) -> u64 { 0 }
+    pub fn next(&mut self, _text: &mut [u8; KLOG_TEXT_LEN]) -> Option<KlogRecord> { None }
+}
+#[cfg(not(target_arch = "riscv64"))]
+pub fn download_to_file(_url: &str, _path: &str, _flags: u64) -> Result<u64, u16> { Err(0) }
+#[cfg(not(target_arch = "riscv64"))]
+pub const WS_BINARY: u64 = 1;
+#[cfg(not(target_arch = "riscv64"))]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum WsEvent {
+    Timeout,
+    Text(usize),
+    Binary(usize),
+    Closed,
+    Error,
+}
+#[cfg(not(target_arch = "riscv64"))]
+pub fn websocket_connect(_url: &str, _flags: u64) -> Option<i32> { None }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn websocket_send(_handle: i32, _data: &[u8], _flags: u64) -> bool { false }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn websocket_recv(_handle: i32, _buf: &mut [u8], _timeout_ms: u64) -> WsEvent { WsEvent::Error }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn websocket_close(_handle: i32) {}
+#[cfg(not(target_arch = "riscv64"))]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum StressState {
+    Running,
+    Passed,
+    Failed,
+}
+#[cfg(not(target_arch = "riscv64"))]
+pub fn stress_start(_cpu: u16, _io: u16, _net: u16, _seconds: u64, _url: &str) -> bool { false }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn stress_stop() -> bool { false }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn stress_status(_buf: &mut [u8]) -> Option<(usize, StressState)> { None }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn firewall_list(_buf: &mut [u8]) -> Option<usize> { None }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn firewall_reload() -> Option<usize> { None }
 #[cfg(not(target_arch = "riscv64"))]
-pub fn tcp_send(_data: &[u8]) -> i32 { -1 }
+pub fn firewall_add(_rule: &str) -> Option<usize> { None }
 #[cfg(not(target_arch = "riscv64"))]
-pub fn tcp_recv(_buf: &mut [u8], _timeout_ms: i32) -> i32 { -1 }
+pub fn firewall_delete(_index: usize) -> Option<usize> { None }
 #[cfg(not(target_arch = "riscv64"))]
-pub fn tcp_close() {}
+pub fn sfs_format_info(_buf: &mut [u8]) -> Option<usize> { None }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn sfs_upgrade() -> Option<bool> { None }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn clipboard_get(_buf: &mut [u8]) -> Option<usize> { None }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn clipboard_set(_text: &str) -> bool { false }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn tcp_connect(_ip: &[u8; 4], _port: u16) -> i32 { -1 }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn tcp_send(_sock: i32, _data: &[u8]) -> i32 { -1 }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn tcp_recv(_sock: i32, _buf: &mut [u8], _timeout_ms: i32) -> i32 { -1 }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn tcp_close(_sock: i32) {}
 #[cfg(not(target_arch = "riscv64"))]
 pub fn should_cancel() -> i32 { 0 }
 
 // TCP helper stubs
 #[cfg(not(target_arch = "riscv64"))]
-pub fn tcp_connect_ip(_ip: &[u8; 4], _port: u16) -> bool { false }
+pub fn tcp_connect_ip(_ip: &[u8; 4], _port: u16) -> Option<i32> { None }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn tcp_send_data(_sock: i32, _data: &[u8]) -> Option<usize> { None }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn tcp_recv_data(_sock: i32, _buf: &mut [u8], _timeout_ms: u32) -> Option<usize> { None }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn tcp_disconnect(_sock: i32) -> bool { false }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn tcp_get_status(_sock: i32) -> TcpStatus { TcpStatus::Closed }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn tcp_listen_port(_port: u16) -> Option<i32> { None }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn tcp_accept_conn(_listener: i32) -> Result<Option<(i32, TcpPeer)>, &'static str> { Err("no network") }
+#[cfg(not(target_arch = "riscv64"))]
+pub const WATCH_CREATE: u8 = 1 << 0;
 #[cfg(not(target_arch = "riscv64"))]
-pub fn tcp_send_data(_data: &[u8]) -> Option<usize> { None }
+pub const WATCH_MODIFY: u8 = 1 << 1;
 #[cfg(not(target_arch = "riscv64"))]
-pub fn tcp_recv_data(_buf: &mut [u8], _timeout_ms: u32) -> Option<usize> { None }
+pub const WATCH_DELETE: u8 = 1 << 2;
 #[cfg(not(target_arch = "riscv64"))]
-pub fn tcp_disconnect() -> bool { false }
+pub fn watch_path(_path: &str, _mask: u8) -> Option<i32> { None }
 #[cfg(not(target_arch = "riscv64"))]
-pub fn tcp_get_status() -> TcpStatus { TcpStatus::Closed }
+pub fn wait_for_changes(_fd: i32, _timeout_ms: u64) -> bool { false }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn resolve_host(_host: &str, _ip: &mut [u8; 4]) -> bool { false }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn tcp_open(_host: &str, _port: u16, _timeout_ms: i64) -> Result<i32, &'static str> { Err("no network") }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn tcp_request(_host: &str, _port: u16, _request: &[u8], _timeout_ms: i64, _on_data: impl FnMut(&[u8])) -> Result<usize, &'static str> { Err("no network") }
 #[cfg(not(target_arch = "riscv64"))]
 pub fn console_available() -> i32 { 0 }
 #[cfg(not(target_arch = "riscv64"))]
@@ -178,6 +438,82 @@ pub enum TcpStatus {
     Failed = 3,
 }
 
+#[cfg(not(target_arch = "riscv64"))]
+#[derive(Clone, Copy)]
+pub struct TcpPeer {
+    pub ip: [u8; 4],
+    pub port: u16,
+}
+
+#[cfg(not(target_arch = "riscv64"))]
+pub const POLLIN: u16 = 0x01;
+#[cfg(not(target_arch = "riscv64"))]
+pub const POLLOUT: u16 = 0x04;
+#[cfg(not(target_arch = "riscv64"))]
+pub const POLLERR: u16 = 0x08;
+#[cfg(not(target_arch = "riscv64"))]
+pub const POLLHUP: u16 = 0x10;
+#[cfg(not(target_arch = "riscv64"))]
+pub const POLLNVAL: u16 = 0x20;
+#[cfg(not(target_arch = "riscv64"))]
+pub const POLLSOCK: u16 = 0x100;
+#[cfg(not(target_arch = "riscv64"))]
+pub const POLL_FOREVER: u64 = u64::MAX;
+
+#[cfg(not(target_arch = "riscv64"))]
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct PollFd {
+    pub fd: i32,
+    pub events: u16,
+    pub revents: u16,
+}
+
+#[cfg(not(target_arch = "riscv64"))]
+impl PollFd {
+    pub const NONE: PollFd = PollFd { fd: -1, events: 0, revents: 0 };
+    pub const fn fd(fd: i32, events: u16) -> Self { PollFd { fd, events, revents: 0 } }
+    pub const fn socket(handle: i32, events: u16) -> Self { PollFd { fd: handle, events: events | POLLSOCK, revents: 0 } }
+    pub fn ready(&self, events: u16) -> bool { self.revents & (events | POLLERR | POLLHUP | POLLNVAL) != 0 }
+}
+
+#[cfg(not(target_arch = "riscv64"))]
+pub fn poll_fds(_fds: &mut [PollFd], _timeout_ms: u64) -> Option<usize> { None }
+
+#[cfg(not(target_arch = "riscv64"))]
+pub const PROT_READ: i32 = 0x1;
+#[cfg(not(target_arch = "riscv64"))]
+pub const PROT_WRITE: i32 = 0x2;
+#[cfg(not(target_arch = "riscv64"))]
+pub const PROT_EXEC: i32 = 0x4;
+#[cfg(not(target_arch = "riscv64"))]
+pub const MAP_SHARED: i32 = 0x01;
+#[cfg(not(target_arch = "riscv64"))]
+pub const MAP_PRIVATE: i32 = 0x02;
+#[cfg(not(target_arch = "riscv64"))]
+pub const MAP_ANONYMOUS: i32 = 0x20;
+#[cfg(not(target_arch = "riscv64"))]
+pub fn mmap(_addr: *mut u8, _len: usize, _prot: i32, _flags: i32, _fd: i32, _offset: u64) -> i64 { -1 }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn munmap(_addr: *mut u8, _len: usize) -> i32 { -1 }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn map_anonymous(_len: usize) -> Option<&'static mut [u8]> { None }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn map_file(_fd: i32, _len: usize, _offset: u64, _shared: bool) -> Option<&'static mut [u8]> { None }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn unmap(_mapping: &'static mut [u8]) -> bool { false }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn getrandom(_buf_ptr: *mut u8, _buf_len: usize, _flags: u32) -> i64 { -1 }
+#[cfg(not(target_arch = "riscv64"))]
+pub const CLOCK_REALTIME: u32 = 0;
+#[cfg(not(target_arch = "riscv64"))]
+pub const CLOCK_MONOTONIC: u32 = 1;
+#[cfg(not(target_arch = "riscv64"))]
+pub fn clock_gettime(_clock_id: u32) -> i64 { -1 }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn monotonic_ns() -> u64 { 0 }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn realtime_ns() -> Option<u64> { None }
 
 // System info stubs
 #[cfg(not(target_arch = "riscv64"))]
@@ -189,8 +525,16 @@ pub fn get_version() -> &'static str { "" }
 #[cfg(not(target_arch = "riscv64"))]
 pub fn get_net_info() -> Option<NetInfo> { None }
 #[cfg(not(target_arch = "riscv64"))]
+pub fn get_interface_stats() -> Option<InterfaceStats> { None }
+#[cfg(not(target_arch = "riscv64"))]
 pub fn net_info(_out_ptr: *mut u8, _out_len: i32) -> i32 { -1 }
 #[cfg(not(target_arch = "riscv64"))]
+pub fn get_net_info6(_buf: &mut [u8]) -> Option<usize> { None }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn get_net_neighbors(_buf: &mut [u8]) -> Option<usize> { None }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn get_net_stats(_buf: &mut [u8]) -> Option<usize> { None }
+#[cfg(not(target_arch = "riscv64"))]
 pub fn heap_stats(_out_ptr: *mut u8) -> i32 { -1 }
 #[cfg(not(target_arch = "riscv64"))]
 pub fn sleep_ms(_ms: u64) -> i32 { 0 }
@@ -198,9 +542,13 @@ pub fn sleep_ms(_ms: u64) -> i32 { 0 }
 
 // Format helpers
 #[cfg(not(target_arch = "riscv64"))]
-pub fn format_ipv4(_ip: [u8; 4], _buf: &mut [u8]) -> usize { 0 }
+pub fn format_ipv4(_ip: &[u8; 4], _buf: &mut [u8]) -> usize { 0 }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn format_ipv6(_ip: &[u8; 16], _buf: &mut [u8]) -> usize { 0 }
 #[cfg(not(target_arch = "riscv64"))]
-pub fn format_mac(_mac: [u8; 6], _buf: &mut [u8]) -> usize { 0 }
+pub fn parse_ipv6(_s: &str) -> Option<[u8; 16]> { None }
+#[cfg(not(target_arch = "riscv64"))]
+pub fn format_mac(_mac: &[u8; 6], _buf: &mut [u8]) -> usize { 0 }
 
 // Service stubs
 #[cfg(not(target_arch = "riscv64"))]
@@ -233,6 +581,55 @@ pub enum KillResult {
     NotFound,
     CannotKill,
     InvalidPid,
+    InvalidSignal,
+}
+
+#[cfg(not(target_arch = "riscv64"))]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum AffinityResult {
+    Success,
+    NotFound,
+    NoSuchHart,
+}
+
+#[cfg(not(target_arch = "riscv64"))]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum WaitResult {
+    Exited { pid: u32, code: i32 },
+    Running,
+    NoChild,
+}
+
+#[cfg(not(target_arch = "riscv64"))]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SpawnResult {
+    Started(u32),
+    NotFound,
+    NotExecutable,
+}
+
+#[cfg(not(target_arch = "riscv64"))]
+#[derive(Clone, Copy)]
+pub enum SigHandler {
+    Default,
+    Ignore,
+    Handler(extern "C" fn(i32)),
+}
+
+#[cfg(not(target_arch = "riscv64"))]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum MqResult {
+    Done(usize),
+    TimedOut,
+    Closed,
+}
+
+#[cfg(not(target_arch = "riscv64"))]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum MemLimitResult {
+    Success,
+    NotFound,
+    TooMany,
 }
 
 #[cfg(not(target_arch = "riscv64"))]
@@ -243,6 +640,33 @@ pub enum PingResult {
     Error,
 }
 
+#[cfg(not(target_arch = "riscv64"))]
+pub const DNS_TYPE_A: u16 = 1;
+#[cfg(not(target_arch = "riscv64"))]
+pub const DNS_TYPE_CNAME: u16 = 5;
+#[cfg(not(target_arch = "riscv64"))]
+pub const DNS_TYPE_TXT: u16 = 16;
+#[cfg(not(target_arch = "riscv64"))]
+pub const DNS_TYPE_AAAA: u16 = 28;
+#[cfg(not(target_arch = "riscv64"))]
+pub const DNS_TYPE_SRV: u16 = 33;
+
+#[cfg(not(target_arch = "riscv64"))]
+pub enum DnsQuery {
+    Records(usize),
+    NotFound,
+    Error,
+}
+
+#[cfg(not(target_arch = "riscv64"))]
+pub enum TraceResult {
+    Hop { from: [u8; 4], rtt_ms: u32 },
+    Reached { from: [u8; 4], rtt_ms: u32 },
+    Unreachable { from: [u8; 4], rtt_ms: u32, code: u8 },
+    Timeout,
+    NetworkError,
+}
+
 #[cfg(not(target_arch = "riscv64"))]
 pub struct FileStat {
     pub size: u32,
@@ -259,12 +683,32 @@ pub struct NetInfo {
     pub prefix_len: u8,
 }
 
+#[cfg(not(target_arch = "riscv64"))]
+pub struct InterfaceStats {
+    pub rx_packets: u64,
+    pub rx_bytes: u64,
+    pub rx_dropped: u64,
+    pub rx_errors: u64,
+    pub tx_packets: u64,
+    pub tx_bytes: u64,
+    pub tx_dropped: u64,
+    pub tx_errors: u64,
+}
+
 #[cfg(not(target_arch = "riscv64"))]
 pub struct HeapStats {
     pub used_bytes: u64,
     pub total_bytes: u64,
 }
 
+#[cfg(not(target_arch = "riscv64"))]
+#[derive(Clone, Copy)]
+pub struct DisplayParams {
+    pub brightness: u16,
+    pub contrast: u16,
+    pub gamma: u16,
+}
+
 #[cfg(not(target_arch = "riscv64"))]
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub enum ServiceStatus {
@@ -0,0 +1,139 @@
+//! LZ4 block format, used for `--compress`
+//!
+//! Compressed SFS files carry `COMPRESSED_FLAG` in their directory entry
+//! size; their data is the original length (u32 LE) followed by one LZ4
+//! block. The kernel's SFS reader (`fs::lz4`) decompresses them on read.
+//!
+//! Greedy single-pass compressor with a 64K-entry hash table: fast and good
+//! enough for text and binaries, which is all an image holds.
+
+/// Shortest match the format can encode
+const MIN_MATCH: usize = 4;
+/// The last 5 bytes are always literals
+const LAST_LITERALS: usize = 5;
+/// No match may start in the last 12 bytes
+const MF_LIMIT: usize = 12;
+const HASH_BITS: u32 = 16;
+
+/// Compress `input` into a single LZ4 block
+pub fn compress(input: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(input.len() / 2 + 16);
+    // Position + 1 of the last occurrence of each 4-byte hash (0 = none)
+    let mut table = vec![0usize; 1 << HASH_BITS];
+    let mut anchor = 0;
+    let mut pos = 0;
+
+    if input.len() > MF_LIMIT {
+        let limit = input.len() - MF_LIMIT;
+        let match_end = input.len() - LAST_LITERALS;
+
+        while pos < limit {
+            let seq = u32::from_le_bytes(input[pos..pos + 4].try_into().unwrap());
+            let hash = (seq.wrapping_mul(2_654_435_761) >> (32 - HASH_BITS)) as usize;
+            let candidate = table[hash];
+            table[hash] = pos + 1;
+
+            if candidate > 0 {
+                let cand = candidate - 1;
+                if pos - cand <= 0xFFFF && input[cand..cand + MIN_MATCH] == input[pos..pos + MIN_MATCH] {
+                    let mut len = MIN_MATCH;
+                    while pos + len < match_end && input[cand + len] == input[pos + len] {
+                        len += 1;
+                    }
+                    write_sequence(&mut out, &input[anchor..pos], Some(((pos - cand) as u16, len)));
+                    pos += len;
+                    anchor = pos;
+                    continue;
+                }
+            }
+            pos += 1;
+        }
+    }
+
+    write_sequence(&mut out, &input[anchor..], None);
+    out
+}
+
+/// Literals followed by an (offset, length) match; the last sequence has no match
+fn write_sequence(out: &mut Vec<u8>, literals: &[u8], matched: Option<(u16, usize)>) {
+    let match_len = matched.map(|(_, len)| len - MIN_MATCH).unwrap_or(0);
+    out.push(((literals.len().min(15) as u8) << 4) | match_len.min(15) as u8);
+    if literals.len() >= 15 {
+        write_length(out, literals.len() - 15);
+    }
+    out.extend_from_slice(literals);
+
+    if let Some((offset, _)) = matched {
+        out.extend_from_slice(&offset.to_le_bytes());
+        if match_len >= 15 {
+            write_length(out, match_len - 15);
+        }
+    }
+}
+
+fn write_length(out: &mut Vec<u8>, mut len: usize) {
+    while len >= 255 {
+        out.push(255);
+        len -= 255;
+    }
+    out.push(len as u8);
+}
+
+/// Decompress one LZ4 block that expands to exactly `len` bytes
+pub fn decompress(input: &[u8], len: usize) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(len);
+    let mut i = 0;
+
+    loop {
+        let token = *input.get(i)?;
+        i += 1;
+
+        let mut literals = (token >> 4) as usize;
+        if literals == 15 {
+            literals += read_length(input, &mut i)?;
+        }
+        if out.len() + literals > len {
+            return None;
+        }
+        out.extend_from_slice(input.get(i..i + literals)?);
+        i += literals;
+        if i == input.len() {
+            break;
+        }
+
+        let offset = u16::from_le_bytes([*input.get(i)?, *input.get(i + 1)?]) as usize;
+        i += 2;
+        let mut match_len = (token & 0x0F) as usize;
+        if match_len == 15 {
+            match_len += read_length(input, &mut i)?;
+        }
+        match_len += MIN_MATCH;
+        if offset == 0 || offset > out.len() || out.len() + match_len > len {
+            return None;
+        }
+
+        // Byte by byte: the match may overlap what it is copying
+        let start = out.len() - offset;
+        for k in 0..match_len {
+            out.push(out[start + k]);
+        }
+    }
+
+    if out.len() == len {
+        Some(out)
+    } else {
+        None
+    }
+}
+
+fn read_length(input: &[u8], i: &mut usize) -> Option<usize> {
+    let mut len = 0;
+    loop {
+        let byte = *input.get(*i)?;
+        *i += 1;
+        len += byte as usize;
+        if byte != 255 {
+            return Some(len);
+        }
+    }
+}
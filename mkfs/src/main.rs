@@ -1,7 +1,10 @@
-use clap::Parser;
+use clap::{Parser, Subcommand};
+use std::collections::HashSet;
 use std::fs::{self, File};
 use std::io::{Seek, SeekFrom, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+use manifest::{BinaryImport, Manifest};
 
 const SECTOR_SIZE: u64 = 512;
 const MAGIC: u32 = 0x53465331; // "SFS1"
@@ -14,28 +17,99 @@ const SEC_DIR_START: u64 = 65;
 const SEC_DIR_COUNT: u64 = 64; // 1024 files max
 const SEC_DATA_START: u64 = 129;
 
+// Superblock: magic, sector count (u32), then the format revision (major and
+// minor u16) and feature flags (u32). Images from before the revision was
+// recorded have zeros there and are 1.0.
 
+/// On-disk format revision written by this tool. Must match kernel.
+const FORMAT_MAJOR: u16 = 1;
+const FORMAT_MINOR: u16 = 1;
 
+/// Feature flags (must match kernel): symbolic links, LZ4-compressed files
+const FEATURE_SYMLINKS: u32 = 1 << 0;
+const FEATURE_LZ4: u32 = 1 << 1;
+const KNOWN_FEATURES: u32 = FEATURE_SYMLINKS | FEATURE_LZ4;
 
+mod fsck;
+mod inspect;
+mod lz4;
+mod manifest;
+mod upgrade;
 
-
-
-
-
-
+/// Build an SFS disk image, or check and inspect an existing one
 #[derive(Parser)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Output disk image path
+    #[arg(short, long, required = true)]
+    output: Option<PathBuf>,
+
+    /// Manifest listing the files to import (e.g. mkfs/image.toml)
     #[arg(short, long)]
-    output: PathBuf,
+    manifest: Option<PathBuf>,
 
-    /// Directory to import files from
+    /// Also import the manifest entries restricted to this profile
     #[arg(short, long)]
-    dir: Option<PathBuf>,
+    profile: Option<String>,
 
-    /// Disk size in MB
-    #[arg(short, long, default_value_t = 128)]
-    size: u64,
+    /// Disk size in MB [default: manifest, else 128]
+    #[arg(short, long)]
+    size: Option<u64>,
+
+    /// Reserve MB after the filesystem for `hibernate` images [default: manifest, else 0]
+    #[arg(long)]
+    hibernate: Option<u64>,
+
+    /// LZ4-compress files that take fewer sectors that way
+    #[arg(long)]
+    compress: bool,
+}
+
+// Tools that work on an existing image
+#[derive(Subcommand)]
+enum Command {
+    /// Check an SFS image for consistency
+    Fsck {
+        /// Disk image to check
+        image: PathBuf,
+
+        /// Repair the problems that can be fixed
+        #[arg(short = 'y', long)]
+        repair: bool,
+    },
+
+    /// List the files in an SFS image
+    Ls {
+        /// Disk image to list
+        image: PathBuf,
+    },
+
+    /// Copy every file out of an SFS image
+    Extract {
+        /// Disk image to read
+        image: PathBuf,
+
+        /// Directory to write the files to
+        dir: PathBuf,
+    },
+
+    /// Compare the files in two SFS images
+    Diff {
+        /// Old image
+        a: PathBuf,
+
+        /// New image
+        b: PathBuf,
+    },
+
+    /// Migrate an SFS image in place to the current format revision
+    Upgrade {
+        /// Disk image to upgrade
+        image: PathBuf,
+    },
 }
 
 #[repr(C, packed)]
@@ -45,6 +119,14 @@ struct DirEntry {
     head: u32,
 }
 
+/// High bit of `DirEntry::size` marks a symbolic link (data = target path).
+/// Must match kernel.
+const SYMLINK_FLAG: u32 = 0x8000_0000;
+
+/// Bit 30 of `DirEntry::size` marks LZ4-compressed data (original length as
+/// u32 LE, then one LZ4 block). Must match kernel.
+const COMPRESSED_FLAG: u32 = 0x4000_0000;
+
 /// Directory entry size: 64 (name) + 4 (size) + 4 (head) = 72 bytes
 const DIR_ENTRY_SIZE: usize = 72;
 /// Entries per sector: 512 / 72 = 7 (must match kernel)
@@ -53,14 +135,43 @@ const ENTRIES_PER_SECTOR: u64 = 7;
 fn main() -> std::io::Result<()> {
     let args = Args::parse();
 
-    let total_sectors = (args.size * 1024 * 1024) / SECTOR_SIZE;
+    if let Some(command) = args.command {
+        let code = match command {
+            Command::Fsck { image, repair } => fsck::run(&image, repair)?,
+            Command::Ls { image } => inspect::ls(&image)?,
+            Command::Extract { image, dir } => inspect::extract(&image, &dir)?,
+            Command::Diff { a, b } => inspect::diff(&a, &b)?,
+            Command::Upgrade { image } => upgrade::run(&image)?,
+        };
+        std::process::exit(code);
+    }
+    let output = args.output.expect("--output is required");
+
+    let manifest = match args.manifest {
+        Some(ref path) => {
+            println!("Using manifest {:?}", path);
+            Manifest::load(path)?
+        }
+        None => Manifest::default(),
+    };
+    let profile = args.profile.as_deref();
+    let size = args.size.or(manifest.image.size).unwrap_or(128);
+    let hibernate = args.hibernate.or(manifest.image.hibernate).unwrap_or(0);
+
+    let total_sectors = (size * 1024 * 1024) / SECTOR_SIZE;
     println!(
         "Creating SFS image: {:?} ({} MB, {} sectors)",
-        args.output, args.size, total_sectors
+        output, size, total_sectors
     );
 
-    let mut file = File::create(&args.output)?;
-    file.set_len(args.size * 1024 * 1024)?;
+    // set_len leaves the image as one hole: only sectors written below take
+    // up space. The hibernate region follows the filesystem, outside its
+    // sector count
+    let mut file = File::create(&output)?;
+    file.set_len((size + hibernate) * 1024 * 1024)?;
+    if hibernate > 0 {
+        println!("Reserving {} MB for hibernation", hibernate);
+    }
 
     // 1. Write Superblock
     file.seek(SeekFrom::Start(SEC_SUPER * SECTOR_SIZE))?;
@@ -78,243 +189,116 @@ fn main() -> std::io::Result<()> {
         }
     }
 
-    let mut dir_idx = 0u64;
-
-    // 3. Import Files from root directory (non-recursive, just files in root)
-    if let Some(ref src_dir) = args.dir {
-        if src_dir.exists() {
-            dir_idx = import_directory(&mut file, &mut bitmap, src_dir, dir_idx, "")?;
-        }
-    }
-
-    // 4. Import files from usr/bin/ subdirectory (scripts with /usr/bin/ prefix)
-    if let Some(ref src_dir) = args.dir {
-        let usr_bin_dir = src_dir.join("usr").join("bin");
-        if usr_bin_dir.exists() {
-            println!("\n📜 Importing scripts from usr/bin/...");
-            dir_idx = import_directory(&mut file, &mut bitmap, &usr_bin_dir, dir_idx, "/usr/bin/")?;
-        }
-    }
-
-    // 5. Import files from home/ subdirectory (with /home/ prefix)
-    if let Some(ref src_dir) = args.dir {
-        let home_dir = src_dir.join("home");
-        if home_dir.exists() {
-            println!("\n🏠 Importing files from home/...");
-            dir_idx = import_directory(&mut file, &mut bitmap, &home_dir, dir_idx, "/home/")?;
+    let mut image = Image {
+        file,
+        bitmap,
+        dir_idx: 0,
+        names: HashSet::new(),
+        compress: args.compress || manifest.image.compress,
+        features: 0,
+    };
+
+    // 3. Import directories, single files, then userspace binaries
+    for dir in manifest.dirs.iter().filter(|d| manifest::in_profile(&d.profiles, profile)) {
+        if !dir.src.is_dir() {
+            if dir.optional {
+                continue;
+            }
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("{:?} is not a directory", dir.src),
+            ));
         }
+        println!("\n📁 Importing {:?} -> {}", dir.src, dir.dest);
+        image.import_directory(&dir.src, manifest::dir_prefix(&dir.dest), dir.exec)?;
     }
 
-    // 6. Import files from var/log/ subdirectory (with /var/log/ prefix)
-    if let Some(ref src_dir) = args.dir {
-        let var_log_dir = src_dir.join("var").join("log");
-        if var_log_dir.exists() {
-            println!("\n📋 Importing files from var/log/...");
-            dir_idx = import_directory(&mut file, &mut bitmap, &var_log_dir, dir_idx, "/var/log/")?;
-        }
+    let files: Vec<_> = manifest.files.iter().filter(|f| manifest::in_profile(&f.profiles, profile)).collect();
+    if !files.is_empty() {
+        println!("\n📄 Importing single files...");
     }
-
-    // 7. Import files from etc/init.d/ subdirectory (with /etc/init.d/ prefix)
-    if let Some(ref src_dir) = args.dir {
-        let etc_init_dir = src_dir.join("etc").join("init.d");
-        if etc_init_dir.exists() {
-            println!("\n⚙️  Importing files from etc/init.d/...");
-            dir_idx = import_directory(
-                &mut file,
-                &mut bitmap,
-                &etc_init_dir,
-                dir_idx,
-                "/etc/init.d/",
-            )?;
-        }
+    for entry in files {
+        let data = fs::read(&entry.src)
+            .map_err(|e| std::io::Error::new(e.kind(), format!("{:?}: {}", entry.src, e)))?;
+        println!("  📄 Importing {:?} -> {}", entry.src, entry.dest);
+        image.add_file(manifest::file_name(&entry.dest), &data, entry.exec)?;
     }
 
-    // 8. Import httpd HTML files from etc/httpd/html/ subdirectory
-    if let Some(ref src_dir) = args.dir {
-        let httpd_dir = src_dir.join("etc").join("httpd").join("html");
-        if httpd_dir.exists() {
-            println!("\n🌐 Importing files from etc/httpd/html/...");
-            dir_idx = import_directory(
-                &mut file,
-                &mut bitmap,
-                &httpd_dir,
-                dir_idx,
-                "/etc/httpd/html/",
-            )?;
-        }
+    for bins in manifest.binaries.iter().filter(|b| manifest::in_profile(&b.profiles, profile)) {
+        image.import_binaries(bins)?;
     }
 
-    // 8. Import native RISC-V ELF binaries (preferred) or WASM binaries (fallback)
-    // Native binaries are in target/riscv64gc-unknown-none-elf/release/
-    // WASM binaries are in target/wasm32-unknown-unknown/release/
-    {
-        // Try native RISC-V first
-        let native_path = PathBuf::from("target/riscv64gc-unknown-none-elf/release");
-        let wasm_path = PathBuf::from("target/wasm32-unknown-unknown/release");
-        
-        if native_path.exists() && native_path.is_dir() {
-            println!("\n🔶 Importing native RISC-V binaries from {:?}...", native_path);
-            dir_idx = import_native_binaries(&mut file, &mut bitmap, &native_path, dir_idx)?;
-        } else if wasm_path.exists() && wasm_path.is_dir() {
-            println!("\n🔷 Importing WASM binaries from {:?}...", wasm_path);
-            dir_idx = import_wasm_binaries(&mut file, &mut bitmap, &wasm_path, dir_idx)?;
-        } else {
-            println!("\n⚠️  No userspace binaries found");
+    // 4. Write Bitmap back to disk, skipping all-zero sectors so they stay holes
+    for (i, sector) in image.bitmap.chunks(SECTOR_SIZE as usize).enumerate() {
+        if sector.iter().any(|&b| b != 0) {
+            image.file.seek(SeekFrom::Start((SEC_MAP_START + i as u64) * SECTOR_SIZE))?;
+            image.file.write_all(sector)?;
         }
     }
 
-    // 9. Write Bitmap back to disk
-    file.seek(SeekFrom::Start(SEC_MAP_START * SECTOR_SIZE))?;
-    file.write_all(&bitmap)?;
+    // 5. Record the format revision and the features the files use
+    image.file.seek(SeekFrom::Start(SEC_SUPER * SECTOR_SIZE + 8))?;
+    image.file.write_all(&FORMAT_MAJOR.to_le_bytes())?;
+    image.file.write_all(&FORMAT_MINOR.to_le_bytes())?;
+    image.file.write_all(&image.features.to_le_bytes())?;
 
-    println!("\n✅ Done. {} files imported.", dir_idx);
+    println!("\n✅ Done. {} files imported.", image.dir_idx);
+    report_allocated(&image.file)?;
     Ok(())
 }
 
-/// Import native RISC-V ELF binaries from target directory into /usr/bin/
-/// Only imports ELF files that correspond to binaries in mkfs/src/bin/
-fn import_native_binaries(
-    file: &mut File,
-    bitmap: &mut Vec<u8>,
-    native_dir: &PathBuf,
-    mut dir_idx: u64,
-) -> std::io::Result<u64> {
-    for entry in fs::read_dir(native_dir)? {
-        let entry = entry?;
-        let path = entry.path();
-
-        // Only process files (no extension for ELF binaries on Unix)
-        if !path.is_file() {
-            continue;
-        }
-
-        // Get the binary name
-        let bin_name = path
-            .file_name()
-            .and_then(|s| s.to_str())
-            .unwrap_or("");
-
-        // Skip library files, deps, special files, and non-script binaries
-        if bin_name.is_empty()
-            || bin_name.starts_with("lib")
-            || bin_name.contains('-')
-            || bin_name.contains('.')
-            || bin_name == "mkfs"
-            || bin_name == "deps"
-            || bin_name == "kernel"      // Skip kernel ELF
-            || bin_name == "wasmrun"     // Skip WASM-specific utility
-        {
-            continue;
-        }
-
-        // Check if it's actually an ELF file (magic: 0x7f 'E' 'L' 'F')
-        let data = fs::read(&path)?;
-        if data.len() < 4 || data[0..4] != [0x7f, b'E', b'L', b'F'] {
-            continue;
-        }
-
-        // Create the filesystem path: /usr/bin/<name>
-        let fs_path = format!("/usr/bin/{}", bin_name);
-
-        if fs_path.len() > 63 {
-            println!("  ⚠️  Skipping {}: Path too long (max 63 chars)", fs_path);
-            continue;
-        }
-
-        println!("  🔶 Importing {} -> {} ({} bytes)", bin_name, fs_path, data.len());
-
-        let head_sector = write_data(file, bitmap, &data)?;
-        write_dir_entry(file, dir_idx, &fs_path, data.len() as u32, head_sector)?;
-        dir_idx += 1;
-    }
-
-    Ok(dir_idx)
+#[cfg(unix)]
+fn report_allocated(file: &File) -> std::io::Result<()> {
+    use std::os::unix::fs::MetadataExt;
+    let meta = file.metadata()?;
+    println!(
+        "   Image is sparse: {} KB allocated of {} MB",
+        meta.blocks() * 512 / 1024,
+        meta.len() / (1024 * 1024)
+    );
+    Ok(())
 }
 
-/// Import WASM binaries from target directory into /usr/bin/
-/// Only imports .wasm files that correspond to binaries in mkfs/src/bin/
-fn import_wasm_binaries(
-    file: &mut File,
-    bitmap: &mut Vec<u8>,
-    wasm_dir: &PathBuf,
-    mut dir_idx: u64,
-) -> std::io::Result<u64> {
-    for entry in fs::read_dir(wasm_dir)? {
-        let entry = entry?;
-        let path = entry.path();
-
-        // Only process .wasm files
-        if !path.is_file() {
-            continue;
-        }
-
-        let extension = path.extension().and_then(|e| e.to_str());
-        if extension != Some("wasm") {
-            continue;
-        }
-
-        // Get the binary name (without .wasm extension)
-        let bin_name = path
-            .file_stem()
-            .and_then(|s| s.to_str())
-            .unwrap_or("");
-
-        // Skip library files, deps, and non-script binaries
-        if bin_name.is_empty()
-            || bin_name.starts_with("lib")
-            || bin_name.contains('-')
-            || bin_name == "mkfs"
-            || bin_name == "riscv_vm"  // Skip the VM itself
-        {
-            continue;
-        }
-
-        // Create the filesystem path: /usr/bin/<name>
-        let fs_path = format!("/usr/bin/{}", bin_name);
-
-        if fs_path.len() > 63 {
-            println!("  ⚠️  Skipping {}: Path too long (max 63 chars)", fs_path);
-            continue;
-        }
-
-        println!("  🔷 Importing {} -> {}", bin_name, fs_path);
-
-        let data = fs::read(&path)?;
-        let head_sector = write_data(file, bitmap, &data)?;
-        write_dir_entry(file, dir_idx, &fs_path, data.len() as u32, head_sector)?;
-        dir_idx += 1;
-    }
+#[cfg(not(unix))]
+fn report_allocated(_file: &File) -> std::io::Result<()> {
+    Ok(())
+}
 
-    Ok(dir_idx)
+/// An image being filled in
+struct Image {
+    file: File,
+    bitmap: Vec<u8>,
+    /// Next free directory entry
+    dir_idx: u64,
+    /// Names imported so far: a manifest may not import the same path twice
+    names: HashSet<String>,
+    compress: bool,
+    /// FEATURE_* flags of what has been imported
+    features: u32,
 }
 
-/// Import all files from a directory into the filesystem image
-fn import_directory(
-    file: &mut File,
-    bitmap: &mut Vec<u8>,
-    dir: &PathBuf,
-    mut dir_idx: u64,
-    prefix: &str,
-) -> std::io::Result<u64> {
-    for entry in fs::read_dir(dir)? {
-        let entry = entry?;
-        let path = entry.path();
-
-        // Skip subdirectories (except bin/ which is handled separately)
-        if path.is_dir() {
-            continue;
-        }
+impl Image {
+    /// Import every file and symlink directly inside `dir`, in name order
+    /// so the image does not depend on the host's directory order
+    fn import_directory(&mut self, dir: &Path, prefix: &str, exec: bool) -> std::io::Result<()> {
+        let mut paths: Vec<PathBuf> = fs::read_dir(dir)?.map(|e| e.map(|e| e.path())).collect::<Result<_, _>>()?;
+        paths.sort();
 
-        if path.is_file() {
+        for path in paths {
             let base_name = path.file_name().unwrap().to_str().unwrap();
-            let filename = if prefix.is_empty() {
-                base_name.to_string()
-            } else {
-                format!("{}{}", prefix, base_name)
-            };
+            let filename = format!("{}{}", prefix, base_name);
+
+            // Keep symlinks as links (checked first: is_dir/is_file follow them)
+            if path.is_symlink() {
+                let target = fs::read_link(&path)?;
+                let target = target.to_string_lossy();
+                println!("  🔗 Linking {} -> {}", filename, target);
+                self.add_symlink(&filename, &target)?;
+                continue;
+            }
 
-            if filename.len() > 63 {
-                println!("⚠️  Skipping {}: Name too long (max 63 chars)", filename);
+            // Subdirectories are imported by their own manifest entries
+            if !path.is_file() {
                 continue;
             }
 
@@ -329,12 +313,128 @@ fn import_directory(
             println!("  {} Importing {}", icon, filename);
 
             let data = fs::read(&path)?;
-            let head_sector = write_data(file, bitmap, &data)?;
-            write_dir_entry(file, dir_idx, &filename, data.len() as u32, head_sector)?;
-            dir_idx += 1;
+            self.add_file(&filename, &data, exec)?;
+        }
+        Ok(())
+    }
+
+    /// Import userspace programs: native RISC-V ELF binaries if their
+    /// directory exists, otherwise WASM binaries
+    fn import_binaries(&mut self, bins: &BinaryImport) -> std::io::Result<()> {
+        let (dir, native) = match (&bins.native, &bins.wasm) {
+            (Some(dir), _) if dir.is_dir() => (dir, true),
+            (_, Some(dir)) if dir.is_dir() => (dir, false),
+            _ => {
+                println!("\n⚠️  No userspace binaries found");
+                return Ok(());
+            }
+        };
+        if native {
+            println!("\n🔶 Importing native RISC-V binaries from {:?}...", dir);
+        } else {
+            println!("\n🔷 Importing WASM binaries from {:?}...", dir);
+        }
+
+        let mut paths: Vec<PathBuf> = fs::read_dir(dir)?.map(|e| e.map(|e| e.path())).collect::<Result<_, _>>()?;
+        paths.sort();
+
+        let mut found = HashSet::new();
+        for path in paths {
+            if !path.is_file() {
+                continue;
+            }
+            // ELF binaries have no extension; cargo's other outputs do
+            let bin_name = if native {
+                path.file_name().and_then(|s| s.to_str()).filter(|n| !n.contains('.'))
+            } else {
+                path.extension()
+                    .filter(|e| *e == "wasm")
+                    .and(path.file_stem())
+                    .and_then(|s| s.to_str())
+            };
+            let bin_name = match bin_name {
+                Some(name) if !name.is_empty() && !name.starts_with("lib") && !name.contains('-') => name,
+                _ => continue,
+            };
+            if bins.exclude.iter().any(|e| e == bin_name)
+                || bins.include.as_ref().is_some_and(|inc| !inc.iter().any(|i| i == bin_name))
+            {
+                continue;
+            }
+
+            let data = fs::read(&path)?;
+            if !is_executable(&data) {
+                continue;
+            }
+
+            let fs_path = format!("{}{}", bins.dest, bin_name);
+            if native {
+                println!("  🔶 Importing {} -> {} ({} bytes)", bin_name, fs_path, data.len());
+            } else {
+                println!("  🔷 Importing {} -> {}", bin_name, fs_path);
+            }
+            self.add_file(&fs_path, &data, true)?;
+            found.insert(bin_name.to_string());
+        }
+
+        for name in bins.include.iter().flatten() {
+            if !found.contains(name) {
+                println!("  ⚠️  {} is listed in the manifest but was not built", name);
+            }
+        }
+        Ok(())
+    }
+
+    fn add_file(&mut self, name: &str, data: &[u8], exec: bool) -> std::io::Result<()> {
+        if exec && !is_executable(data) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("{} is marked exec but is not an ELF or WASM binary or #! script", name),
+            ));
+        }
+        if !self.claim(name)? {
+            return Ok(());
+        }
+        let (size, head_sector) = write_file_data(&mut self.file, &mut self.bitmap, data, self.compress)?;
+        if size & COMPRESSED_FLAG != 0 {
+            self.features |= FEATURE_LZ4;
         }
+        write_dir_entry(&mut self.file, self.dir_idx, name, size, head_sector)?;
+        self.dir_idx += 1;
+        Ok(())
+    }
+
+    fn add_symlink(&mut self, name: &str, target: &str) -> std::io::Result<()> {
+        if !self.claim(name)? {
+            return Ok(());
+        }
+        let head_sector = write_data(&mut self.file, &mut self.bitmap, target.as_bytes())?;
+        write_dir_entry(&mut self.file, self.dir_idx, name, target.len() as u32 | SYMLINK_FLAG, head_sector)?;
+        self.features |= FEATURE_SYMLINKS;
+        self.dir_idx += 1;
+        Ok(())
+    }
+
+    /// Reserve `name`. Too-long names are skipped (false); a name imported
+    /// twice is an error in the manifest.
+    fn claim(&mut self, name: &str) -> std::io::Result<bool> {
+        if name.len() > 63 {
+            println!("  ⚠️  Skipping {}: Name too long (max 63 chars)", name);
+            return Ok(false);
+        }
+        if !self.names.insert(name.to_string()) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::AlreadyExists,
+                format!("{} is imported twice", name),
+            ));
+        }
+        Ok(true)
     }
-    Ok(dir_idx)
+}
+
+/// ELF (native) or WASM magic, or a `#!` line: what the kernel can run
+fn is_executable(data: &[u8]) -> bool {
+    data.starts_with(&[0x7f, b'E', b'L', b'F']) || data.starts_with(b"\0asm") || data.starts_with(b"#!")
 }
 
 fn find_free_sector(bitmap: &mut [u8]) -> Option<u32> {
@@ -351,6 +451,25 @@ fn find_free_sector(bitmap: &mut [u8]) -> Option<u32> {
     None
 }
 
+/// Write a file's data, LZ4-compressed if `compress` is set and that saves
+/// sectors. Returns the directory entry size field and the head sector.
+fn write_file_data(
+    file: &mut File,
+    bitmap: &mut [u8],
+    data: &[u8],
+    compress: bool,
+) -> std::io::Result<(u32, u32)> {
+    if compress {
+        let mut packed = (data.len() as u32).to_le_bytes().to_vec();
+        packed.extend_from_slice(&lz4::compress(data));
+        if packed.len().div_ceil(508) < data.len().div_ceil(508) {
+            let head = write_data(file, bitmap, &packed)?;
+            return Ok((packed.len() as u32 | COMPRESSED_FLAG, head));
+        }
+    }
+    Ok((data.len() as u32, write_data(file, bitmap, data)?))
+}
+
 fn write_data(file: &mut File, bitmap: &mut [u8], data: &[u8]) -> std::io::Result<u32> {
     if data.is_empty() {
         return Ok(0);
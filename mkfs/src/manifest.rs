@@ -0,0 +1,158 @@
+//! Image manifests: what `mkfs` puts into an image
+//!
+//! A manifest (`mkfs/image.toml` for the default image) lists the host
+//! directories, single files and userspace binaries to import, so the same
+//! manifest always builds the same image. Relative `src` paths are resolved
+//! against the manifest's own directory, not the working directory.
+//!
+//! Entries with `profiles = [...]` are only imported when one of those
+//! profiles is selected with `--profile`; entries without are always used.
+
+use serde::Deserialize;
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    #[serde(default)]
+    pub image: ImageConfig,
+
+    #[serde(default, rename = "dir")]
+    pub dirs: Vec<DirImport>,
+
+    #[serde(default, rename = "file")]
+    pub files: Vec<FileImport>,
+
+    #[serde(default)]
+    pub binaries: Vec<BinaryImport>,
+}
+
+/// `[image]`: defaults for options given on the command line
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct ImageConfig {
+    /// Disk size in MB
+    pub size: Option<u64>,
+    /// MB reserved after the filesystem for `hibernate`
+    pub hibernate: Option<u64>,
+    /// LZ4-compress files where that saves sectors
+    #[serde(default)]
+    pub compress: bool,
+}
+
+/// `[[dir]]`: every file and symlink directly inside `src` (not recursive)
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DirImport {
+    pub src: PathBuf,
+    /// Destination directory, "/" or ending in '/'
+    pub dest: String,
+    /// Every file must be a runnable (ELF or WASM) binary
+    #[serde(default)]
+    pub exec: bool,
+    /// Skip instead of failing when `src` does not exist
+    #[serde(default)]
+    pub optional: bool,
+    #[serde(default)]
+    pub profiles: Vec<String>,
+}
+
+/// `[[file]]`: a single file
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FileImport {
+    pub src: PathBuf,
+    /// Full destination path
+    pub dest: String,
+    /// Must be a runnable (ELF or WASM) binary
+    #[serde(default)]
+    pub exec: bool,
+    #[serde(default)]
+    pub profiles: Vec<String>,
+}
+
+/// `[[binaries]]`: userspace programs from a cargo target directory
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BinaryImport {
+    /// Directory of native RISC-V ELF binaries (preferred)
+    pub native: Option<PathBuf>,
+    /// Directory of `.wasm` binaries, used when `native` does not exist
+    pub wasm: Option<PathBuf>,
+    #[serde(default = "default_bin_dest")]
+    pub dest: String,
+    /// Only import these programs (default: every one found)
+    pub include: Option<Vec<String>>,
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    #[serde(default)]
+    pub profiles: Vec<String>,
+}
+
+fn default_bin_dest() -> String {
+    "/usr/bin/".into()
+}
+
+impl Manifest {
+    /// Parse and check a manifest, resolving `src` paths against its directory
+    pub fn load(path: &Path) -> std::io::Result<Manifest> {
+        let text = std::fs::read_to_string(path)?;
+        let mut manifest: Manifest = toml::from_str(&text)
+            .map_err(|e| Error::new(ErrorKind::InvalidData, format!("{}: {}", path.display(), e)))?;
+
+        let base = path.parent().unwrap_or(Path::new(""));
+        for dir in &mut manifest.dirs {
+            dir.src = base.join(&dir.src);
+            check_dir_dest(path, &dir.dest)?;
+        }
+        for file in &mut manifest.files {
+            file.src = base.join(&file.src);
+            if !file.dest.starts_with('/') || file.dest.ends_with('/') {
+                return Err(invalid(path, &file.dest, "a file destination must be an absolute path"));
+            }
+        }
+        for bins in &mut manifest.binaries {
+            bins.native = bins.native.as_ref().map(|p| base.join(p));
+            bins.wasm = bins.wasm.as_ref().map(|p| base.join(p));
+            check_dir_dest(path, &bins.dest)?;
+        }
+        Ok(manifest)
+    }
+}
+
+/// Whether an entry restricted to `profiles` is used when building `profile`
+pub fn in_profile(profiles: &[String], profile: Option<&str>) -> bool {
+    profiles.is_empty() || profile.is_some_and(|p| profiles.iter().any(|q| q == p))
+}
+
+/// SFS name prefix for a destination directory: root entries have no leading '/'
+pub fn dir_prefix(dest: &str) -> &str {
+    if dest == "/" {
+        ""
+    } else {
+        dest
+    }
+}
+
+/// SFS name for a destination file path
+pub fn file_name(dest: &str) -> &str {
+    let name = &dest[1..];
+    if name.contains('/') {
+        dest
+    } else {
+        name
+    }
+}
+
+fn check_dir_dest(path: &Path, dest: &str) -> std::io::Result<()> {
+    if dest.starts_with('/') && dest.ends_with('/') {
+        Ok(())
+    } else {
+        Err(invalid(path, dest, "a directory destination must start and end with '/'"))
+    }
+}
+
+fn invalid(path: &Path, dest: &str, why: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, format!("{}: {:?}: {}", path.display(), dest, why))
+}
@@ -31,6 +31,7 @@ const SYS_FS_REMOVE: u64 = 25;
 const SYS_FS_MKDIR: u64 = 26;
 const SYS_FS_IS_DIR: u64 = 27;
 const SYS_FS_LIST_DIR: u64 = 28;
+const SYS_FS_SYNC: u64 = 29;
 const SYS_NET_AVAILABLE: u64 = 30;
 const SYS_DNS_RESOLVE: u64 = 31;
 const SYS_SEND_PING: u64 = 32;
@@ -57,6 +58,80 @@ const SYS_SERVICE_RUNNING: u64 = 73;
 const SYS_NET_INFO: u64 = 80;
 const SYS_HEAP_STATS: u64 = 81;
 const SYS_SLEEP: u64 = 82;
+const SYS_OPEN: u64 = 90;
+const SYS_READ: u64 = 91;
+const SYS_WRITE: u64 = 92;
+const SYS_SEEK: u64 = 93;
+const SYS_CLOSE: u64 = 94;
+const SYS_SYMLINK: u64 = 100;
+const SYS_READLINK: u64 = 101;
+const SYS_RENAME: u64 = 102;
+const SYS_FS_APPEND: u64 = 103;
+const SYS_FS_PWRITE: u64 = 104;
+const SYS_KMOD_LOAD: u64 = 105;
+const SYS_KMOD_UNLOAD: u64 = 106;
+const SYS_KMOD_LIST: u64 = 107;
+const SYS_BOOTCHART: u64 = 108;
+const SYS_HIBERNATE: u64 = 109;
+const SYS_QRCODE: u64 = 110;
+const SYS_BEEP: u64 = 111;
+const SYS_EVENT_SOUNDS: u64 = 112;
+const SYS_MOUNT_9P: u64 = 113;
+const SYS_UMOUNT: u64 = 114;
+const SYS_MOUNTS: u64 = 115;
+const SYS_DISPLAY_ADJUST: u64 = 116;
+const SYS_SETTINGS_GET: u64 = 117;
+const SYS_SETTINGS_SET: u64 = 118;
+const SYS_NET_INFO6: u64 = 119;
+const SYS_PING6: u64 = 120;
+const SYS_DNS_RESOLVE6: u64 = 121;
+const SYS_DNS_FLUSH: u64 = 122;
+const SYS_TCP_LISTEN: u64 = 123;
+const SYS_TCP_ACCEPT: u64 = 124;
+const SYS_WATCH: u64 = 125;
+const SYS_WATCH_WAIT: u64 = 126;
+const SYS_HTTP_GET_EX: u64 = 127;
+const SYS_HEAPDIFF: u64 = 128;
+const SYS_WASM_PIPE: u64 = 129;
+const SYS_INPUT_REPLAY: u64 = 130;
+const SYS_HTTP_DOWNLOAD: u64 = 131;
+const SYS_KLOG_MAP: u64 = 132;
+const SYS_WS_CONNECT: u64 = 133;
+const SYS_WS_SEND: u64 = 134;
+const SYS_WS_RECV: u64 = 135;
+const SYS_WS_CLOSE: u64 = 136;
+const SYS_STRESS: u64 = 137;
+const SYS_NET_NEIGHBORS: u64 = 138;
+const SYS_FIREWALL: u64 = 139;
+const SYS_SFS_FORMAT: u64 = 140;
+const SYS_CLIPBOARD: u64 = 141;
+const SYS_TRACE_PROBE: u64 = 142;
+const SYS_NET_STATS: u64 = 143;
+const SYS_DNS_QUERY: u64 = 144;
+const SYS_GET_NICE: u64 = 145;
+const SYS_SET_NICE: u64 = 146;
+const SYS_GET_AFFINITY: u64 = 147;
+const SYS_SET_AFFINITY: u64 = 148;
+const SYS_WAIT: u64 = 149;
+const SYS_WAITPID: u64 = 150;
+const SYS_SPAWN: u64 = 151;
+const SYS_SIGNAL: u64 = 152;
+const SYS_SIGRETURN: u64 = 153;
+const SYS_MQ_OPEN: u64 = 154;
+const SYS_MQ_SEND: u64 = 155;
+const SYS_MQ_RECV: u64 = 156;
+const SYS_MQ_UNLINK: u64 = 157;
+const SYS_GET_MEM_LIMIT: u64 = 158;
+const SYS_SET_MEM_LIMIT: u64 = 159;
+const SYS_PROC_STATS: u64 = 160;
+const SYS_SETPGID: u64 = 161;
+const SYS_GETPGID: u64 = 162;
+const SYS_POLL: u64 = 163;
+const SYS_MMAP: u64 = 164;
+const SYS_MUNMAP: u64 = 165;
+const SYS_GETRANDOM: u64 = 166;
+const SYS_CLOCK_GETTIME: u64 = 167;
+const SYS_MEMSTAT: u64 = 168;
 
 
 
@@ -140,6 +215,43 @@ fn syscall4(num: u64, a0: u64, a1: u64, a2: u64, a3: u64) -> i64 {
     ret
 }
 
+#[inline(always)]
+fn syscall5(num: u64, a0: u64, a1: u64, a2: u64, a3: u64, a4: u64) -> i64 {
+    let ret: i64;
+    unsafe {
+        asm!(
+            "ecall",
+            in("a7") num,
+            inlateout("a0") a0 as i64 => ret,
+            in("a1") a1,
+            in("a2") a2,
+            in("a3") a3,
+            in("a4") a4,
+            options(nostack)
+        );
+    }
+    ret
+}
+
+#[inline(always)]
+fn syscall6(num: u64, a0: u64, a1: u64, a2: u64, a3: u64, a4: u64, a5: u64) -> i64 {
+    let ret: i64;
+    unsafe {
+        asm!(
+            "ecall",
+            in("a7") num,
+            inlateout("a0") a0 as i64 => ret,
+            in("a1") a1,
+            in("a2") a2,
+            in("a3") a3,
+            in("a4") a4,
+            in("a5") a5,
+            options(nostack)
+        );
+    }
+    ret
+}
+
 // ═══════════════════════════════════════════════════════════════════════════════
 // Raw Syscall Functions (matching WASM extern "C" declarations)
 // ═══════════════════════════════════════════════════════════════════════════════
@@ -207,6 +319,18 @@ pub fn fs_write(path_ptr: *const u8, path_len: i32, data_ptr: *const u8, data_le
     syscall4(SYS_FS_WRITE, path_ptr as u64, path_len as u64, data_ptr as u64, data_len as u64) as i32
 }
 
+/// Append to file
+#[inline]
+pub fn fs_append(path_ptr: *const u8, path_len: i32, data_ptr: *const u8, data_len: i32) -> i32 {
+    syscall4(SYS_FS_APPEND, path_ptr as u64, path_len as u64, data_ptr as u64, data_len as u64) as i32
+}
+
+/// Write file data at a byte offset
+#[inline]
+pub fn fs_pwrite(path_ptr: *const u8, path_len: i32, data_ptr: *const u8, data_len: i32, offset: u64) -> i32 {
+    syscall5(SYS_FS_PWRITE, path_ptr as u64, path_len as u64, data_ptr as u64, data_len as u64, offset) as i32
+}
+
 /// List files
 #[inline]
 pub fn fs_list(buf_ptr: *mut u8, buf_len: i32) -> i32 {
@@ -243,6 +367,12 @@ pub fn fs_is_dir(path_ptr: *const u8, path_len: i32) -> i32 {
     syscall2(SYS_FS_IS_DIR, path_ptr as u64, path_len as u64) as i32
 }
 
+/// Flush dirty cached blocks to disk
+#[inline]
+pub fn fs_sync() -> i32 {
+    syscall0(SYS_FS_SYNC) as i32
+}
+
 
 /// Network available
 #[inline]
@@ -262,7 +392,7 @@ pub fn send_ping(ip_ptr: *const u8, seq: i32, timeout_ms: i32, out_ptr: *mut u8)
     syscall4(SYS_SEND_PING, ip_ptr as u64, seq as u64, timeout_ms as u64, out_ptr as u64) as i32
 }
 
-/// TCP connect
+/// TCP connect (returns a socket handle, or -1)
 #[inline]
 pub fn tcp_connect(ip_ptr: *const u8, _ip_len: i32, port: i32) -> i32 {
     syscall2(SYS_TCP_CONNECT, ip_ptr as u64, port as u64) as i32
@@ -270,26 +400,26 @@ pub fn tcp_connect(ip_ptr: *const u8, _ip_len: i32, port: i32) -> i32 {
 
 /// TCP send
 #[inline]
-pub fn tcp_send(data_ptr: *const u8, data_len: i32) -> i32 {
-    syscall2(SYS_TCP_SEND, data_ptr as u64, data_len as u64) as i32
+pub fn tcp_send(sock: i32, data_ptr: *const u8, data_len: i32) -> i32 {
+    syscall3(SYS_TCP_SEND, sock as u64, data_ptr as u64, data_len as u64) as i32
 }
 
 /// TCP recv
 #[inline]
-pub fn tcp_recv(buf_ptr: *mut u8, buf_len: i32, _timeout_ms: i32) -> i32 {
-    syscall2(SYS_TCP_RECV, buf_ptr as u64, buf_len as u64) as i32
+pub fn tcp_recv(sock: i32, buf_ptr: *mut u8, buf_len: i32, _timeout_ms: i32) -> i32 {
+    syscall3(SYS_TCP_RECV, sock as u64, buf_ptr as u64, buf_len as u64) as i32
 }
 
 /// TCP close
 #[inline]
-pub fn tcp_close() -> i32 {
-    syscall0(SYS_TCP_CLOSE) as i32
+pub fn tcp_close(sock: i32) -> i32 {
+    syscall1(SYS_TCP_CLOSE, sock as u64) as i32
 }
 
 /// TCP status
 #[inline]
-pub fn tcp_status() -> i32 {
-    syscall0(SYS_TCP_STATUS) as i32
+pub fn tcp_status(sock: i32) -> i32 {
+    syscall1(SYS_TCP_STATUS, sock as u64) as i32
 }
 
 /// HTTP get
@@ -316,10 +446,10 @@ pub fn ps_list(buf_ptr: *mut u8, buf_len: i32) -> i32 {
     syscall2(SYS_PS_LIST, buf_ptr as u64, buf_len as u64) as i32
 }
 
-/// Kill process
+/// Send signal `sig` (0 = SIGKILL) to a process, or to group -pid
 #[inline]
-pub fn kill(pid: i32) -> i32 {
-    syscall1(SYS_KILL, pid as u64) as i32
+pub fn kill(pid: i32, sig: i32) -> i32 {
+    syscall2(SYS_KILL, pid as i64 as u64, sig as u64) as i32
 }
 
 /// Shutdown
@@ -385,7 +515,8 @@ pub fn service_running(buf_ptr: *mut u8, buf_len: i32) -> i32 {
     syscall2(SYS_SERVICE_RUNNING, buf_ptr as u64, buf_len as u64) as i32
 }
 
-/// Get network information: IP[4], MAC[6], Gateway[4], DNS[4], prefix_len[1] = 19 bytes
+/// Get network information: IP[4], MAC[6], Gateway[4], DNS[4], prefix_len[1] = 19 bytes,
+/// then 8 interface counters (u64 LE) = 83 bytes
 #[inline]
 pub fn net_info(out_ptr: *mut u8, out_len: i32) -> i32 {
     syscall2(SYS_NET_INFO, out_ptr as u64, out_len as u64) as i32
@@ -403,247 +534,1384 @@ pub fn sleep_ms(ms: u64) -> i32 {
     syscall1(SYS_SLEEP, ms) as i32
 }
 
+/// Open a file, returning a descriptor or -1
+#[inline]
+pub fn open(path_ptr: *const u8, path_len: i32, flags: i32) -> i32 {
+    syscall3(SYS_OPEN, path_ptr as u64, path_len as u64, flags as u64) as i32
+}
 
-// ═══════════════════════════════════════════════════════════════════════════════
-// Higher-level helpers (same as WASM module)
-// ═══════════════════════════════════════════════════════════════════════════════
+/// Read from a descriptor (0 = EOF)
+#[inline]
+pub fn read(fd: i32, buf_ptr: *mut u8, buf_len: i32) -> i32 {
+    syscall3(SYS_READ, fd as u64, buf_ptr as u64, buf_len as u64) as i32
+}
 
-/// Print a string to the console
-pub fn console_log(s: &str) {
-    print(s.as_ptr(), s.len());
+/// Write to a descriptor
+#[inline]
+pub fn write(fd: i32, data_ptr: *const u8, data_len: i32) -> i32 {
+    syscall3(SYS_WRITE, fd as u64, data_ptr as u64, data_len as u64) as i32
 }
 
-/// Get current time in milliseconds
-pub fn get_time() -> i64 {
-    time()
+/// Move a descriptor's offset, returning the new offset
+#[inline]
+pub fn seek(fd: i32, offset: i64, whence: i32) -> i64 {
+    syscall3(SYS_SEEK, fd as u64, offset as u64, whence as u64)
 }
 
-/// Get number of arguments
-pub fn argc() -> usize {
-    arg_count() as usize
+/// Close a descriptor (writes back modified data)
+#[inline]
+pub fn close(fd: i32) -> i32 {
+    syscall1(SYS_CLOSE, fd as u64) as i32
 }
 
-/// Get argument at index
-pub fn argv(index: usize, buf: &mut [u8]) -> Option<usize> {
-    let len = arg_get(index as i32, buf.as_mut_ptr(), buf.len() as i32);
-    if len >= 0 { Some(len as usize) } else { None }
+/// Create a symbolic link at path pointing to target
+#[inline]
+pub fn fs_symlink(target_ptr: *const u8, target_len: i32, path_ptr: *const u8, path_len: i32) -> i32 {
+    syscall4(SYS_SYMLINK, target_ptr as u64, target_len as u64, path_ptr as u64, path_len as u64) as i32
 }
 
-/// Get current working directory
-pub fn get_cwd(buf: &mut [u8]) -> Option<usize> {
-    let len = cwd_get(buf.as_mut_ptr(), buf.len() as i32);
-    if len >= 0 { Some(len as usize) } else { None }
+/// Read a symbolic link's target
+#[inline]
+pub fn fs_readlink(path_ptr: *const u8, path_len: i32, buf_ptr: *mut u8, buf_len: i32) -> i32 {
+    syscall4(SYS_READLINK, path_ptr as u64, path_len as u64, buf_ptr as u64, buf_len as u64) as i32
 }
 
-/// Set current working directory
-pub fn set_cwd(path: &str) -> bool {
-    cwd_set(path.as_ptr(), path.len() as i32) == 0
+/// Rename (move) a file or directory
+#[inline]
+pub fn fs_rename(from_ptr: *const u8, from_len: i32, to_ptr: *const u8, to_len: i32) -> i32 {
+    syscall4(SYS_RENAME, from_ptr as u64, from_len as u64, to_ptr as u64, to_len as u64) as i32
 }
 
-/// Check if file exists
-pub fn file_exists(path: &str) -> bool {
-    fs_exists(path.as_ptr(), path.len() as i32) == 1
+/// Load a signed WASM kernel module
+#[inline]
+pub fn kmod_load(path_ptr: *const u8, path_len: i32) -> i32 {
+    syscall2(SYS_KMOD_LOAD, path_ptr as u64, path_len as u64) as i32
 }
 
-/// Read file contents
-pub fn read_file(path: &str, buf: &mut [u8]) -> Option<usize> {
-    let len = fs_read(path.as_ptr(), path.len() as i32, buf.as_mut_ptr(), buf.len() as i32);
-    if len >= 0 { Some(len as usize) } else { None }
+/// Unload a kernel module
+#[inline]
+pub fn kmod_unload(name_ptr: *const u8, name_len: i32) -> i32 {
+    syscall2(SYS_KMOD_UNLOAD, name_ptr as u64, name_len as u64) as i32
 }
 
-/// Write file
-pub fn write_file(path: &str, data: &[u8]) -> bool {
-    let written = fs_write(path.as_ptr(), path.len() as i32, data.as_ptr(), data.len() as i32);
-    written >= 0
+/// List loaded kernel modules
+#[inline]
+pub fn kmod_list(buf_ptr: *mut u8, buf_len: i32) -> i32 {
+    syscall2(SYS_KMOD_LIST, buf_ptr as u64, buf_len as u64) as i32
 }
 
-/// List files
-pub fn list_files(buf: &mut [u8]) -> Option<usize> {
-    let len = fs_list(buf.as_mut_ptr(), buf.len() as i32);
-    if len >= 0 { Some(len as usize) } else { None }
+/// Get the boot time chart
+#[inline]
+pub fn bootchart(buf_ptr: *mut u8, buf_len: i32) -> i32 {
+    syscall2(SYS_BOOTCHART, buf_ptr as u64, buf_len as u64) as i32
 }
 
-/// List directory
-pub fn list_dir(path: &str, buf: &mut [u8]) -> Option<usize> {
-    let len = fs_list_dir(path.as_ptr(), path.len() as i32, buf.as_mut_ptr(), buf.len() as i32);
-    if len >= 0 { Some(len as usize) } else { None }
+/// Save the system to disk and power off
+#[inline]
+pub fn hibernate() -> i32 {
+    syscall0(SYS_HIBERNATE) as i32
 }
 
-/// File stat result
-pub struct FileStat {
-    pub size: u32,
-    pub exists: bool,
-    pub is_dir: bool,
+/// Encode text as a QR code (also shown on screen when there is one)
+#[inline]
+pub fn qrcode(text_ptr: *const u8, text_len: i32, out_ptr: *mut u8, out_len: i32) -> i32 {
+    syscall4(SYS_QRCODE, text_ptr as u64, text_len as u64, out_ptr as u64, out_len as u64) as i32
 }
 
-/// Get file stats
-pub fn file_stat(path: &str) -> Option<FileStat> {
-    let mut out = [0u8; 6];
-    let result = fs_stat(path.as_ptr(), path.len() as i32, out.as_mut_ptr());
-    if result == 0 {
-        Some(FileStat {
-            size: u32::from_le_bytes([out[0], out[1], out[2], out[3]]),
-            exists: out[4] != 0,
-            is_dir: out[5] != 0,
-        })
-    } else {
-        None
-    }
+/// Play a tone (waveform 0 = sine, 1 = square) and wait for it to finish
+#[inline]
+pub fn beep(freq: u32, duration_ms: u32, waveform: u32) -> i32 {
+    syscall3(SYS_BEEP, freq as u64, duration_ms as u64, waveform as u64) as i32
 }
 
-/// Create directory
-pub fn mkdir(path: &str) -> bool {
-    fs_mkdir(path.as_ptr(), path.len() as i32) == 0
+/// Set UI event sounds (0 = off, 1 = on, other = query); returns the current state
+#[inline]
+pub fn event_sounds(mode: u32) -> i32 {
+    syscall1(SYS_EVENT_SOUNDS, mode as u64) as i32
 }
 
-/// Remove file or directory
-pub fn remove_file(path: &str) -> bool {
-    fs_remove(path.as_ptr(), path.len() as i32) == 0
+/// Mount a 9P server at ip:port on a path
+#[inline]
+pub fn mount_9p(ip_ptr: *const u8, port: u32, path_ptr: *const u8, path_len: i32) -> i32 {
+    syscall4(SYS_MOUNT_9P, ip_ptr as u64, port as u64, path_ptr as u64, path_len as u64) as i32
 }
 
-/// Check if path is a directory
-pub fn is_dir(path: &str) -> bool {
-    fs_is_dir(path.as_ptr(), path.len() as i32) == 1
+/// Unmount the filesystem at a path
+#[inline]
+pub fn umount(path_ptr: *const u8, path_len: i32) -> i32 {
+    syscall2(SYS_UMOUNT, path_ptr as u64, path_len as u64) as i32
 }
 
+/// List mount points, one per line
+#[inline]
+pub fn mounts(buf_ptr: *mut u8, buf_len: i32) -> i32 {
+    syscall2(SYS_MOUNTS, buf_ptr as u64, buf_len as u64) as i32
+}
 
-/// Network available
-pub fn is_net_available() -> bool {
-    net_available() == 1
+/// Set (0 = keep) and return display brightness, contrast and gamma, packed
+#[inline]
+pub fn display_adjust(brightness: u32, contrast: u32, gamma: u32) -> i64 {
+    syscall3(SYS_DISPLAY_ADJUST, brightness as u64, contrast as u64, gamma as u64)
 }
 
-/// Get klog
-pub fn get_klog(count: usize, buf: &mut [u8]) -> Option<usize> {
-    let len = klog_get(count as i32, buf.as_mut_ptr(), buf.len() as i32);
-    if len >= 0 { Some(len as usize) } else { None }
+/// Read all system settings (settings.toml format)
+#[inline]
+pub fn settings_get(buf_ptr: *mut u8, buf_len: i32) -> i32 {
+    syscall2(SYS_SETTINGS_GET, buf_ptr as u64, buf_len as u64) as i32
 }
 
-/// HTTP fetch
-pub fn http_fetch(url: &str, buf: &mut [u8]) -> Option<usize> {
-    let len = http_get(url.as_ptr(), url.len() as i32, buf.as_mut_ptr(), buf.len() as i32);
-    if len >= 0 { Some(len as usize) } else { None }
+/// Change a system setting and save it
+#[inline]
+pub fn settings_set(key_ptr: *const u8, key_len: i32, value_ptr: *const u8, value_len: i32) -> i32 {
+    syscall4(SYS_SETTINGS_SET, key_ptr as u64, key_len as u64, value_ptr as u64, value_len as u64) as i32
 }
 
-/// DNS resolve
-pub fn resolve_dns(hostname: &str, ip_buf: &mut [u8; 4]) -> bool {
-    dns_resolve(hostname.as_ptr(), hostname.len() as i32, ip_buf.as_mut_ptr(), 4) == 4
+/// List IPv6 addresses and the default router, one per line
+#[inline]
+pub fn net_info6(buf_ptr: *mut u8, buf_len: i32) -> i32 {
+    syscall2(SYS_NET_INFO6, buf_ptr as u64, buf_len as u64) as i32
 }
 
-/// Get environment variable
-pub fn getenv(key: &str, buf: &mut [u8]) -> Option<usize> {
-    let len = env_get(key.as_ptr(), key.len() as i32, buf.as_mut_ptr(), buf.len() as i32);
-    if len >= 0 { Some(len as usize) } else { None }
+/// Send an ICMPv6 echo request to a 16-byte address
+#[inline]
+pub fn send_ping6(ip_ptr: *const u8, seq: i32, timeout_ms: i32, out_ptr: *mut u8) -> i32 {
+    syscall4(SYS_PING6, ip_ptr as u64, seq as u64, timeout_ms as u64, out_ptr as u64) as i32
 }
 
-/// Get random bytes
-pub fn get_random(buf: &mut [u8]) -> bool {
-    random(buf.as_mut_ptr(), buf.len() as i32) >= 0
+/// Resolve a hostname to an IPv6 address (AAAA)
+#[inline]
+pub fn dns_resolve6(host_ptr: *const u8, host_len: i32, ip_buf_ptr: *mut u8, ip_buf_len: i32) -> i32 {
+    syscall4(SYS_DNS_RESOLVE6, host_ptr as u64, host_len as u64, ip_buf_ptr as u64, ip_buf_len as u64) as i32
 }
 
-/// Network info structure
-pub struct NetInfo {
-    pub ip: [u8; 4],
-    pub mac: [u8; 6],
-    pub gateway: [u8; 4],
-    pub dns: [u8; 4],
-    pub prefix_len: u8,
+/// Empty the DNS cache and re-read /etc/hosts (dropped | hosts << 32)
+#[inline]
+pub fn dns_flush() -> i64 {
+    syscall0(SYS_DNS_FLUSH)
 }
 
-/// Get network information
-pub fn get_net_info() -> Option<NetInfo> {
-    let mut buf = [0u8; 19];
-    let result = net_info(buf.as_mut_ptr(), 19);
-    if result >= 19 {
-        Some(NetInfo {
-            ip: [buf[0], buf[1], buf[2], buf[3]],
-            mac: [buf[4], buf[5], buf[6], buf[7], buf[8], buf[9]],
-            gateway: [buf[10], buf[11], buf[12], buf[13]],
-            dns: [buf[14], buf[15], buf[16], buf[17]],
-            prefix_len: buf[18],
-        })
-    } else {
-        None
-    }
+/// Listen for TCP connections (returns a listener handle, or -1)
+#[inline]
+pub fn tcp_listen(port: i32) -> i32 {
+    syscall1(SYS_TCP_LISTEN, port as u64) as i32
 }
 
-/// Heap statistics structure
-pub struct HeapStats {
-    pub used_bytes: u64,
-    pub total_bytes: u64,
+/// Accept a waiting TCP connection (socket handle, -1 if none waiting, -2 on error)
+#[inline]
+pub fn tcp_accept(listener: i32, addr_out: *mut u8) -> i32 {
+    syscall2(SYS_TCP_ACCEPT, listener as u64, addr_out as u64) as i32
 }
 
-/// Get heap statistics
-pub fn get_heap_stats() -> HeapStats {
-    let mut buf = [0u8; 16];
-    heap_stats(buf.as_mut_ptr());
-    HeapStats {
-        used_bytes: u64::from_le_bytes([buf[0], buf[1], buf[2], buf[3], buf[4], buf[5], buf[6], buf[7]]),
-        total_bytes: u64::from_le_bytes([buf[8], buf[9], buf[10], buf[11], buf[12], buf[13], buf[14], buf[15]]),
-    }
+/// Watch a path for changes (watch fd, or -1 on error)
+#[inline]
+pub fn watch(path_ptr: *const u8, path_len: i32, mask: i32) -> i32 {
+    syscall3(SYS_WATCH, path_ptr as u64, path_len as u64, mask as u64) as i32
 }
 
-/// Sleep for milliseconds
-pub fn sleep(ms: u64) {
-    sleep_ms(ms);
+/// Block until a watch fd has events (1), the timeout passes or Ctrl+C (0), or error (-1)
+#[inline]
+pub fn watch_wait(fd: i32, timeout_ms: u64) -> i32 {
+    syscall2(SYS_WATCH_WAIT, fd as u64, timeout_ms) as i32
 }
 
+/// HTTP get with flags (HTTP_INSECURE)
+#[inline]
+pub fn http_get_ex(url_ptr: *const u8, url_len: i32, resp_ptr: *mut u8, resp_len: i32, flags: u64) -> i32 {
+    syscall5(SYS_HTTP_GET_EX, url_ptr as u64, url_len as u64, resp_ptr as u64, resp_len as u64, flags) as i32
+}
 
-/// Power off system
-pub fn poweroff() -> ! {
-    shutdown()
+/// Heap leak detector: op 0 = report, 1 = start, 2 = stop and report
+#[inline]
+pub fn heapdiff(op: u64, buf_ptr: *mut u8, buf_len: i32) -> i32 {
+    syscall3(SYS_HEAPDIFF, op, buf_ptr as u64, buf_len as u64) as i32
 }
 
-/// Get process list
-pub fn get_ps_list(buf: &mut [u8]) -> Option<usize> {
-    let len = ps_list(buf.as_mut_ptr(), buf.len() as i32);
-    if len >= 0 { Some(len as usize) } else { None }
+/// Start a WASM pipeline (one "<path> [args...]" line per stage); returns an fd
+#[inline]
+pub fn wasm_pipe(spec_ptr: *const u8, spec_len: i32) -> i32 {
+    syscall2(SYS_WASM_PIPE, spec_ptr as u64, spec_len as u64) as i32
 }
 
-/// Kill result
-#[derive(Clone, Copy, PartialEq, Eq)]
-pub enum KillResult {
-    Success,
-    NotFound,
-    CannotKill,
-    InvalidPid,
+/// Input record/replay: op 0 = status, 1 = record, 2 = check, 3 = stop, 4 = replay
+#[inline]
+pub fn input_replay(op: u64, ptr: *const u8, len: i32, arg: u64) -> i64 {
+    syscall4(SYS_INPUT_REPLAY, op, ptr as u64, len as u64, arg)
 }
 
-/// Kill process
-pub fn kill_process(pid: u32) -> KillResult {
-    let result = kill(pid as i32);
-    match result {
-        0 => KillResult::Success,
-        -2 => KillResult::CannotKill,
-        _ => if pid == 0 { KillResult::InvalidPid } else { KillResult::NotFound }
-    }
+/// Download a URL to a file (HTTP_INSECURE, HTTP_PROGRESS flags)
+#[inline]
+pub fn http_download(url_ptr: *const u8, url_len: i32, path_ptr: *const u8, path_len: i32, flags: u64) -> i64 {
+    syscall5(SYS_HTTP_DOWNLOAD, url_ptr as u64, url_len as u64, path_ptr as u64, path_len as u64, flags)
 }
 
-/// Format integer to string
-pub fn int_to_str(mut n: i64, buf: &mut [u8]) -> &str {
-    if n == 0 {
-        buf[0] = b'0';
-        return unsafe { core::str::from_utf8_unchecked(&buf[..1]) };
-    }
+/// Address of the shared kernel log page
+#[inline]
+pub fn klog_map() -> i64 {
+    syscall0(SYS_KLOG_MAP)
+}
 
-    let negative = n < 0;
-    if negative { n = -n; }
+/// Open a WebSocket (HTTP_INSECURE flag)
+#[inline]
+pub fn ws_connect(url_ptr: *const u8, url_len: i32, flags: u64) -> i64 {
+    syscall3(SYS_WS_CONNECT, url_ptr as u64, url_len as u64, flags)
+}
 
-    let mut i = buf.len();
-    while n > 0 && i > 0 {
-        i -= 1;
-        buf[i] = b'0' + (n % 10) as u8;
-        n /= 10;
-    }
+/// Send a WebSocket message (WS_BINARY flag)
+#[inline]
+pub fn ws_send(handle: i32, data_ptr: *const u8, data_len: i32, flags: u64) -> i64 {
+    syscall4(SYS_WS_SEND, handle as u64, data_ptr as u64, data_len as u64, flags)
+}
 
-    if negative && i > 0 {
-        i -= 1;
-        buf[i] = b'-';
-    }
+/// Wait for a WebSocket message
+#[inline]
+pub fn ws_recv(handle: i32, buf_ptr: *mut u8, buf_len: i32, timeout_ms: u64) -> i64 {
+    syscall4(SYS_WS_RECV, handle as u64, buf_ptr as u64, buf_len as u64, timeout_ms)
+}
 
-    unsafe { core::str::from_utf8_unchecked(&buf[i..]) }
+/// Close a WebSocket
+#[inline]
+pub fn ws_close(handle: i32) -> i64 {
+    syscall1(SYS_WS_CLOSE, handle as u64)
 }
 
-/// Print integer
+/// Stress test control (op: 0=status, 1=start, 2=stop)
+#[inline]
+pub fn stress(op: u64, a1: u64, a2: u64, a3: u64, a4: u64) -> i64 {
+    syscall5(SYS_STRESS, op, a1, a2, a3, a4)
+}
+
+/// List the IPv4 neighbor (ARP) table, one entry per line
+#[inline]
+pub fn net_neighbors(buf_ptr: *mut u8, buf_len: i32) -> i32 {
+    syscall2(SYS_NET_NEIGHBORS, buf_ptr as u64, buf_len as u64) as i32
+}
+
+/// Packet filter control (op: 0=list, 1=reload, 2=add, 3=delete)
+#[inline]
+pub fn firewall(op: u64, ptr: u64, len: u64) -> i64 {
+    syscall3(SYS_FIREWALL, op, ptr, len)
+}
+
+/// Root filesystem format (op: 0=status, 1=upgrade)
+#[inline]
+pub fn sfs_format(op: u64, ptr: u64, len: u64) -> i64 {
+    syscall3(SYS_SFS_FORMAT, op, ptr, len)
+}
+
+/// Kernel clipboard (op: 0=get, 1=set)
+#[inline]
+pub fn clipboard(op: u64, ptr: u64, len: u64) -> i64 {
+    syscall3(SYS_CLIPBOARD, op, ptr, len)
+}
+
+/// Send a traceroute probe and wait for the answer (out: address, rtt, code)
+#[inline]
+pub fn trace_probe(ip_ptr: *const u8, limit_proto: u64, seq: u16, timeout_ms: u32, out_ptr: *mut u8) -> i64 {
+    syscall5(SYS_TRACE_PROBE, ip_ptr as u64, limit_proto, seq as u64, timeout_ms as u64, out_ptr as u64)
+}
+
+/// List sockets with their traffic counters, one per line
+#[inline]
+pub fn net_stats(buf_ptr: *mut u8, buf_len: i32) -> i32 {
+    syscall2(SYS_NET_STATS, buf_ptr as u64, buf_len as u64) as i32
+}
+
+/// Query DNS records of type `qtype` as text lines
+#[inline]
+pub fn dns_query(host_ptr: *const u8, host_len: i32, qtype: u16, buf_ptr: *mut u8, buf_len: i32) -> i32 {
+    syscall5(SYS_DNS_QUERY, host_ptr as u64, host_len as u64, qtype as u64, buf_ptr as u64, buf_len as u64) as i32
+}
+
+#[inline]
+pub fn get_nice(pid: u32) -> i32 {
+    syscall1(SYS_GET_NICE, pid as u64) as i32
+}
+
+#[inline]
+pub fn set_nice(pid: u32, nice: i32) -> i32 {
+    syscall2(SYS_SET_NICE, pid as u64, nice as i64 as u64) as i32
+}
+
+#[inline]
+pub fn get_affinity(pid: u32, mask_ptr: *mut u8) -> i32 {
+    syscall2(SYS_GET_AFFINITY, pid as u64, mask_ptr as u64) as i32
+}
+
+#[inline]
+pub fn set_affinity(pid: u32, mask_ptr: *const u8) -> i32 {
+    syscall2(SYS_SET_AFFINITY, pid as u64, mask_ptr as u64) as i32
+}
+
+#[inline]
+pub fn wait(status_ptr: *mut i32) -> i32 {
+    syscall1(SYS_WAIT, status_ptr as u64) as i32
+}
+
+/// waitpid() flag: return at once if the child is still running
+pub const WNOHANG: u32 = 1;
+
+#[inline]
+pub fn waitpid(pid: u32, status_ptr: *mut i32, flags: u32) -> i32 {
+    syscall3(SYS_WAITPID, pid as u64, status_ptr as u64, flags as u64) as i32
+}
+
+#[inline]
+pub fn spawn(path: &[u8], args: &[u8]) -> i32 {
+    syscall4(SYS_SPAWN, path.as_ptr() as u64, path.len() as u64, args.as_ptr() as u64, args.len() as u64) as i32
+}
+
+/// Signal numbers
+pub const SIGINT: i32 = 2;
+pub const SIGKILL: i32 = 9;
+pub const SIGTERM: i32 = 15;
+pub const SIGCONT: i32 = 18;
+pub const SIGSTOP: i32 = 19;
+pub const SIGTSTP: i32 = 20;
+
+// Signal handlers return here. It must make SYS_SIGRETURN without touching
+// the stack, so the kernel finds the registers it saved where it left them.
+core::arch::global_asm!(
+    ".globl __mkfs_sigreturn",
+    "__mkfs_sigreturn:",
+    "li a7, 153", // SYS_SIGRETURN
+    "ecall",
+);
+
+extern "C" {
+    fn __mkfs_sigreturn();
+}
+
+#[inline]
+pub fn signal(sig: i32, handler: usize, restorer: usize) -> i64 {
+    syscall3(SYS_SIGNAL, sig as u64, handler as u64, restorer as u64)
+}
+
+#[inline]
+pub fn sigreturn() -> i64 {
+    syscall0(SYS_SIGRETURN)
+}
+
+#[inline]
+pub fn mq_open(name: &[u8], capacity: usize) -> i64 {
+    syscall3(SYS_MQ_OPEN, name.as_ptr() as u64, name.len() as u64, capacity as u64)
+}
+
+#[inline]
+pub fn mq_send(id: u32, data: &[u8], timeout_ms: u64) -> i64 {
+    syscall4(SYS_MQ_SEND, id as u64, data.as_ptr() as u64, data.len() as u64, timeout_ms)
+}
+
+#[inline]
+pub fn mq_recv(id: u32, buf: &mut [u8], timeout_ms: u64) -> i64 {
+    syscall4(SYS_MQ_RECV, id as u64, buf.as_mut_ptr() as u64, buf.len() as u64, timeout_ms)
+}
+
+#[inline]
+pub fn mq_unlink(name: &[u8]) -> i64 {
+    syscall2(SYS_MQ_UNLINK, name.as_ptr() as u64, name.len() as u64)
+}
+
+#[inline]
+pub fn get_mem_limit(pid: u32, out_ptr: *mut u8) -> i32 {
+    syscall2(SYS_GET_MEM_LIMIT, pid as u64, out_ptr as u64) as i32
+}
+
+#[inline]
+pub fn set_mem_limit(pid: u32, bytes: u64) -> i32 {
+    syscall2(SYS_SET_MEM_LIMIT, pid as u64, bytes) as i32
+}
+
+/// CPU usage per hart: "all:usage:harts:processes" then "cpuN:usage:pid:switches" lines
+#[inline]
+pub fn proc_stats(buf_ptr: *mut u8, buf_len: i32) -> i32 {
+    syscall2(SYS_PROC_STATS, buf_ptr as u64, buf_len as u64) as i32
+}
+
+#[inline]
+pub fn setpgid(pid: u32, pgid: u32) -> i32 {
+    syscall2(SYS_SETPGID, pid as u64, pgid as u64) as i32
+}
+
+#[inline]
+pub fn getpgid(pid: u32) -> i32 {
+    syscall1(SYS_GETPGID, pid as u64) as i32
+}
+
+/// Wait for any of `count` 8-byte poll entries to be ready (see `poll_fds`)
+#[inline]
+pub fn poll(entries_ptr: *mut u8, count: i32, timeout_ms: u64) -> i32 {
+    syscall3(SYS_POLL, entries_ptr as u64, count as u64, timeout_ms) as i32
+}
+
+/// Map `len` bytes of zeroed memory or of file `fd` (see `map_anonymous`)
+#[inline]
+pub fn mmap(addr: *mut u8, len: usize, prot: i32, flags: i32, fd: i32, offset: u64) -> i64 {
+    syscall6(SYS_MMAP, addr as u64, len as u64, prot as u64, flags as u64, fd as u64, offset)
+}
+
+#[inline]
+pub fn munmap(addr: *mut u8, len: usize) -> i32 {
+    syscall2(SYS_MUNMAP, addr as u64, len as u64) as i32
+}
+
+/// Fill up to 1 MiB from the kernel CSPRNG; returns the bytes filled or -1
+#[inline]
+pub fn getrandom(buf_ptr: *mut u8, buf_len: usize, flags: u32) -> i64 {
+    syscall3(SYS_GETRANDOM, buf_ptr as u64, buf_len as u64, flags as u64)
+}
+
+/// Read clock `clock_id` in nanoseconds; -1 unknown clock, -2 no RTC
+#[inline]
+pub fn clock_gettime(clock_id: u32) -> i64 {
+    syscall1(SYS_CLOCK_GETTIME, clock_id as u64)
+}
+
+/// Kernel heap use per subsystem; returns the report length or -1
+#[inline]
+pub fn memstat(buf_ptr: *mut u8, buf_len: i32) -> i32 {
+    syscall2(SYS_MEMSTAT, buf_ptr as u64, buf_len as u64) as i32
+}
+
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Higher-level helpers (same as WASM module)
+// ═══════════════════════════════════════════════════════════════════════════════
+
+/// Print a string to the console
+pub fn console_log(s: &str) {
+    print(s.as_ptr(), s.len());
+}
+
+/// Get current time in milliseconds
+pub fn get_time() -> i64 {
+    time()
+}
+
+/// Wall clock (nanoseconds since the Unix epoch)
+pub const CLOCK_REALTIME: u32 = 0;
+/// Time since boot (nanoseconds), for measuring intervals
+pub const CLOCK_MONOTONIC: u32 = 1;
+
+/// Nanoseconds since boot (100 ns resolution); use the difference of two
+/// readings to time code
+pub fn monotonic_ns() -> u64 {
+    clock_gettime(CLOCK_MONOTONIC).max(0) as u64
+}
+
+/// Nanoseconds since the Unix epoch, None without a real-time clock
+pub fn realtime_ns() -> Option<u64> {
+    let ns = clock_gettime(CLOCK_REALTIME);
+    if ns >= 0 { Some(ns as u64) } else { None }
+}
+
+/// Get number of arguments
+pub fn argc() -> usize {
+    arg_count() as usize
+}
+
+/// Get argument at index
+pub fn argv(index: usize, buf: &mut [u8]) -> Option<usize> {
+    let len = arg_get(index as i32, buf.as_mut_ptr(), buf.len() as i32);
+    if len >= 0 { Some(len as usize) } else { None }
+}
+
+/// Get current working directory
+pub fn get_cwd(buf: &mut [u8]) -> Option<usize> {
+    let len = cwd_get(buf.as_mut_ptr(), buf.len() as i32);
+    if len >= 0 { Some(len as usize) } else { None }
+}
+
+/// Set current working directory
+pub fn set_cwd(path: &str) -> bool {
+    cwd_set(path.as_ptr(), path.len() as i32) == 0
+}
+
+/// Check if file exists
+pub fn file_exists(path: &str) -> bool {
+    fs_exists(path.as_ptr(), path.len() as i32) == 1
+}
+
+/// Read file contents
+pub fn read_file(path: &str, buf: &mut [u8]) -> Option<usize> {
+    let len = fs_read(path.as_ptr(), path.len() as i32, buf.as_mut_ptr(), buf.len() as i32);
+    if len >= 0 { Some(len as usize) } else { None }
+}
+
+/// Write file
+pub fn write_file(path: &str, data: &[u8]) -> bool {
+    let written = fs_write(path.as_ptr(), path.len() as i32, data.as_ptr(), data.len() as i32);
+    written >= 0
+}
+
+/// Append data to a file (created if missing) without rewriting it
+pub fn append_file(path: &str, data: &[u8]) -> bool {
+    fs_append(path.as_ptr(), path.len() as i32, data.as_ptr(), data.len() as i32) >= 0
+}
+
+/// Write data at a byte offset of a file, growing it if needed
+pub fn write_file_at(path: &str, offset: u64, data: &[u8]) -> bool {
+    fs_pwrite(path.as_ptr(), path.len() as i32, data.as_ptr(), data.len() as i32, offset) >= 0
+}
+
+/// List files
+pub fn list_files(buf: &mut [u8]) -> Option<usize> {
+    let len = fs_list(buf.as_mut_ptr(), buf.len() as i32);
+    if len >= 0 { Some(len as usize) } else { None }
+}
+
+/// List directory
+pub fn list_dir(path: &str, buf: &mut [u8]) -> Option<usize> {
+    let len = fs_list_dir(path.as_ptr(), path.len() as i32, buf.as_mut_ptr(), buf.len() as i32);
+    if len >= 0 { Some(len as usize) } else { None }
+}
+
+/// File stat result
+pub struct FileStat {
+    pub size: u32,
+    pub exists: bool,
+    pub is_dir: bool,
+}
+
+/// Get file stats
+pub fn file_stat(path: &str) -> Option<FileStat> {
+    let mut out = [0u8; 6];
+    let result = fs_stat(path.as_ptr(), path.len() as i32, out.as_mut_ptr());
+    if result == 0 {
+        Some(FileStat {
+            size: u32::from_le_bytes([out[0], out[1], out[2], out[3]]),
+            exists: out[4] != 0,
+            is_dir: out[5] != 0,
+        })
+    } else {
+        None
+    }
+}
+
+/// Create directory
+pub fn mkdir(path: &str) -> bool {
+    fs_mkdir(path.as_ptr(), path.len() as i32) == 0
+}
+
+/// Remove file or directory
+pub fn remove_file(path: &str) -> bool {
+    fs_remove(path.as_ptr(), path.len() as i32) == 0
+}
+
+/// Check if path is a directory
+pub fn is_dir(path: &str) -> bool {
+    fs_is_dir(path.as_ptr(), path.len() as i32) == 1
+}
+
+/// Flush filesystem cache to disk
+pub fn sync() -> bool {
+    fs_sync() == 0
+}
+
+
+/// Network available
+pub fn is_net_available() -> bool {
+    net_available() == 1
+}
+
+/// Get klog
+pub fn get_klog(count: usize, buf: &mut [u8]) -> Option<usize> {
+    let len = klog_get(count as i32, buf.as_mut_ptr(), buf.len() as i32);
+    if len >= 0 { Some(len as usize) } else { None }
+}
+
+/// HTTP fetch
+pub fn http_fetch(url: &str, buf: &mut [u8]) -> Option<usize> {
+    let len = http_get(url.as_ptr(), url.len() as i32, buf.as_mut_ptr(), buf.len() as i32);
+    if len >= 0 { Some(len as usize) } else { None }
+}
+
+/// http_fetch_with flag: skip HTTPS certificate validation
+pub const HTTP_INSECURE: u64 = 1;
+
+/// HTTP fetch with flags
+pub fn http_fetch_with(url: &str, buf: &mut [u8], flags: u64) -> Option<usize> {
+    let len = http_get_ex(url.as_ptr(), url.len() as i32, buf.as_mut_ptr(), buf.len() as i32, flags);
+    if len >= 0 { Some(len as usize) } else { None }
+}
+
+/// download_to_file flag: print a progress line while downloading
+pub const HTTP_PROGRESS: u64 = 2;
+
+/// Stream a URL to a file without holding it in memory. Returns the bytes
+/// saved, or Err with the HTTP error status (0 if the download failed).
+pub fn download_to_file(url: &str, path: &str, flags: u64) -> Result<u64, u16> {
+    let r = http_download(url.as_ptr(), url.len() as i32, path.as_ptr(), path.len() as i32, flags);
+    if r >= 0 { Ok(r as u64) } else if r == -1 { Err(0) } else { Err((-r) as u16) }
+}
+
+/// websocket_send flag: send a binary message instead of text
+pub const WS_BINARY: u64 = 1;
+
+/// What `websocket_recv` got
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum WsEvent {
+    /// Nothing arrived before the timeout
+    Timeout,
+    /// A text message of this many bytes (truncated to the buffer)
+    Text(usize),
+    /// A binary message of this many bytes (truncated to the buffer)
+    Binary(usize),
+    /// The server closed the connection
+    Closed,
+    Error,
+}
+
+/// Connect to a `ws://` or `wss://` URL, returning the handle
+pub fn websocket_connect(url: &str, flags: u64) -> Option<i32> {
+    let handle = ws_connect(url.as_ptr(), url.len() as i32, flags);
+    if handle >= 0 { Some(handle as i32) } else { None }
+}
+
+/// Send a text message, or a binary one with WS_BINARY
+pub fn websocket_send(handle: i32, data: &[u8], flags: u64) -> bool {
+    ws_send(handle, data.as_ptr(), data.len() as i32, flags) >= 0
+}
+
+/// Wait up to `timeout_ms` for the next message
+pub fn websocket_recv(handle: i32, buf: &mut [u8], timeout_ms: u64) -> WsEvent {
+    let r = ws_recv(handle, buf.as_mut_ptr(), buf.len() as i32, timeout_ms);
+    let len = (r & 0xFFFF_FFFF) as usize;
+    match r >> 32 {
+        _ if r < 0 => WsEvent::Error,
+        0 => WsEvent::Timeout,
+        1 => WsEvent::Text(len),
+        2 => WsEvent::Binary(len),
+        _ => WsEvent::Closed,
+    }
+}
+
+/// Close a WebSocket
+pub fn websocket_close(handle: i32) {
+    ws_close(handle);
+}
+
+/// DNS resolve
+pub fn resolve_dns(hostname: &str, ip_buf: &mut [u8; 4]) -> bool {
+    dns_resolve(hostname.as_ptr(), hostname.len() as i32, ip_buf.as_mut_ptr(), 4) == 4
+}
+
+/// DNS resolve (AAAA record)
+pub fn resolve_dns6(hostname: &str, ip_buf: &mut [u8; 16]) -> bool {
+    dns_resolve6(hostname.as_ptr(), hostname.len() as i32, ip_buf.as_mut_ptr(), 16) == 16
+}
+
+/// Empty the DNS cache and re-read /etc/hosts.
+/// Returns (cached answers dropped, host names loaded).
+pub fn flush_dns() -> (usize, usize) {
+    let packed = dns_flush();
+    ((packed & 0xFFFF_FFFF) as usize, (packed >> 32) as usize)
+}
+
+/// DNS record types for `query_dns`
+pub const DNS_TYPE_A: u16 = 1;
+pub const DNS_TYPE_CNAME: u16 = 5;
+pub const DNS_TYPE_TXT: u16 = 16;
+pub const DNS_TYPE_AAAA: u16 = 28;
+pub const DNS_TYPE_SRV: u16 = 33;
+
+/// Result of `query_dns`
+pub enum DnsQuery {
+    /// Bytes of "<name> <ttl> <TYPE> <data>" lines written (0 if the name
+    /// has no records of that type)
+    Records(usize),
+    /// The name does not exist
+    NotFound,
+    Error,
+}
+
+/// Ask the DNS server for `hostname`'s records of type `qtype`, bypassing
+/// /etc/hosts and the cache
+pub fn query_dns(hostname: &str, qtype: u16, buf: &mut [u8]) -> DnsQuery {
+    match dns_query(hostname.as_ptr(), hostname.len() as i32, qtype, buf.as_mut_ptr(), buf.len() as i32) {
+        len if len >= 0 => DnsQuery::Records(len as usize),
+        -2 => DnsQuery::NotFound,
+        _ => DnsQuery::Error,
+    }
+}
+
+/// Get environment variable
+pub fn getenv(key: &str, buf: &mut [u8]) -> Option<usize> {
+    let len = env_get(key.as_ptr(), key.len() as i32, buf.as_mut_ptr(), buf.len() as i32);
+    if len >= 0 { Some(len as usize) } else { None }
+}
+
+/// Fill `buf` with cryptographically secure random bytes (keys, tokens)
+pub fn get_random(buf: &mut [u8]) -> bool {
+    let mut filled = 0;
+    while filled < buf.len() {
+        let n = getrandom(buf[filled..].as_mut_ptr(), buf.len() - filled, 0);
+        if n <= 0 {
+            return false;
+        }
+        filled += n as usize;
+    }
+    true
+}
+
+/// Network info structure
+pub struct NetInfo {
+    pub ip: [u8; 4],
+    pub mac: [u8; 6],
+    pub gateway: [u8; 4],
+    pub dns: [u8; 4],
+    pub prefix_len: u8,
+}
+
+/// Interface counters since boot
+pub struct InterfaceStats {
+    pub rx_packets: u64,
+    pub rx_bytes: u64,
+    pub rx_dropped: u64,
+    pub rx_errors: u64,
+    pub tx_packets: u64,
+    pub tx_bytes: u64,
+    pub tx_dropped: u64,
+    pub tx_errors: u64,
+}
+
+/// Get the interface counters
+pub fn get_interface_stats() -> Option<InterfaceStats> {
+    let mut buf = [0u8; 83];
+    if net_info(buf.as_mut_ptr(), 83) < 83 {
+        return None;
+    }
+    let counter = |i: usize| {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&buf[19 + i * 8..27 + i * 8]);
+        u64::from_le_bytes(bytes)
+    };
+    Some(InterfaceStats {
+        rx_packets: counter(0),
+        rx_bytes: counter(1),
+        rx_dropped: counter(2),
+        rx_errors: counter(3),
+        tx_packets: counter(4),
+        tx_bytes: counter(5),
+        tx_dropped: counter(6),
+        tx_errors: counter(7),
+    })
+}
+
+/// Get network information
+pub fn get_net_info() -> Option<NetInfo> {
+    let mut buf = [0u8; 19];
+    let result = net_info(buf.as_mut_ptr(), 19);
+    if result >= 19 {
+        Some(NetInfo {
+            ip: [buf[0], buf[1], buf[2], buf[3]],
+            mac: [buf[4], buf[5], buf[6], buf[7], buf[8], buf[9]],
+            gateway: [buf[10], buf[11], buf[12], buf[13]],
+            dns: [buf[14], buf[15], buf[16], buf[17]],
+            prefix_len: buf[18],
+        })
+    } else {
+        None
+    }
+}
+
+/// Get IPv6 addresses and router as text ("inet6 <addr>/<len> scope <scope>" lines)
+pub fn get_net_info6(buf: &mut [u8]) -> Option<usize> {
+    let len = net_info6(buf.as_mut_ptr(), buf.len() as i32);
+    if len >= 0 { Some(len as usize) } else { None }
+}
+
+/// Get the IPv4 neighbor table as text ("<ip> <mac> <age_ms> reachable|stale" lines)
+pub fn get_net_neighbors(buf: &mut [u8]) -> Option<usize> {
+    let len = net_neighbors(buf.as_mut_ptr(), buf.len() as i32);
+    if len >= 0 { Some(len as usize) } else { None }
+}
+
+/// Get the socket table as text ("<proto> <local> <remote> <state> <bytes_in>
+/// <bytes_out> <packets_in> <packets_out> <retransmits> <srtt_ms>" lines)
+pub fn get_net_stats(buf: &mut [u8]) -> Option<usize> {
+    let len = net_stats(buf.as_mut_ptr(), buf.len() as i32);
+    if len >= 0 { Some(len as usize) } else { None }
+}
+
+/// Heap statistics structure
+pub struct HeapStats {
+    pub used_bytes: u64,
+    pub total_bytes: u64,
+}
+
+/// Get heap statistics
+pub fn get_heap_stats() -> HeapStats {
+    let mut buf = [0u8; 16];
+    heap_stats(buf.as_mut_ptr());
+    HeapStats {
+        used_bytes: u64::from_le_bytes([buf[0], buf[1], buf[2], buf[3], buf[4], buf[5], buf[6], buf[7]]),
+        total_bytes: u64::from_le_bytes([buf[8], buf[9], buf[10], buf[11], buf[12], buf[13], buf[14], buf[15]]),
+    }
+}
+
+/// Sleep for milliseconds
+pub fn sleep(ms: u64) {
+    sleep_ms(ms);
+}
+
+
+/// Open for reading only
+pub const O_RDONLY: i32 = 0;
+/// Open for writing only
+pub const O_WRONLY: i32 = 1;
+/// Open for reading and writing
+pub const O_RDWR: i32 = 2;
+/// Create the file if it does not exist
+pub const O_CREAT: i32 = 0x40;
+/// Truncate the file on open
+pub const O_TRUNC: i32 = 0x200;
+/// Every write goes to the end of the file
+pub const O_APPEND: i32 = 0x400;
+
+/// Seek from start of file
+pub const SEEK_SET: i32 = 0;
+/// Seek from current offset
+pub const SEEK_CUR: i32 = 1;
+/// Seek from end of file
+pub const SEEK_END: i32 = 2;
+
+/// Open a file
+pub fn open_file(path: &str, flags: i32) -> Option<i32> {
+    let fd = open(path.as_ptr(), path.len() as i32, flags);
+    if fd >= 0 { Some(fd) } else { None }
+}
+
+/// Read from a file descriptor
+pub fn fd_read(fd: i32, buf: &mut [u8]) -> Option<usize> {
+    let len = read(fd, buf.as_mut_ptr(), buf.len() as i32);
+    if len >= 0 { Some(len as usize) } else { None }
+}
+
+/// Write to a file descriptor
+pub fn fd_write(fd: i32, data: &[u8]) -> Option<usize> {
+    let len = write(fd, data.as_ptr(), data.len() as i32);
+    if len >= 0 { Some(len as usize) } else { None }
+}
+
+/// Seek a file descriptor
+pub fn fd_seek(fd: i32, offset: i64, whence: i32) -> Option<u64> {
+    let pos = seek(fd, offset, whence);
+    if pos >= 0 { Some(pos as u64) } else { None }
+}
+
+/// Close a file descriptor
+pub fn close_file(fd: i32) -> bool {
+    close(fd) == 0
+}
+
+/// Create a symbolic link
+pub fn symlink(target: &str, path: &str) -> bool {
+    fs_symlink(target.as_ptr(), target.len() as i32, path.as_ptr(), path.len() as i32) == 0
+}
+
+/// Read a symbolic link's target into buf
+pub fn readlink(path: &str, buf: &mut [u8]) -> Option<usize> {
+    let len = fs_readlink(path.as_ptr(), path.len() as i32, buf.as_mut_ptr(), buf.len() as i32);
+    if len >= 0 { Some(len as usize) } else { None }
+}
+
+/// Rename (move) a file or directory
+pub fn rename(from: &str, to: &str) -> bool {
+    fs_rename(from.as_ptr(), from.len() as i32, to.as_ptr(), to.len() as i32) == 0
+}
+
+/// Load a signed WASM kernel module (`<path>.sig` must exist)
+pub fn load_module(path: &str) -> bool {
+    kmod_load(path.as_ptr(), path.len() as i32) == 0
+}
+
+/// Unload a kernel module by name
+pub fn unload_module(name: &str) -> bool {
+    kmod_unload(name.as_ptr(), name.len() as i32) == 0
+}
+
+/// List loaded kernel modules (one per line)
+pub fn list_modules(buf: &mut [u8]) -> Option<usize> {
+    let len = kmod_list(buf.as_mut_ptr(), buf.len() as i32);
+    if len >= 0 { Some(len as usize) } else { None }
+}
+
+/// Get the boot time chart (text)
+pub fn get_bootchart(buf: &mut [u8]) -> Option<usize> {
+    let len = bootchart(buf.as_mut_ptr(), buf.len() as i32);
+    if len >= 0 { Some(len as usize) } else { None }
+}
+
+/// Start (or restart) recording kernel heap allocations
+pub fn heapdiff_start() -> bool {
+    heapdiff(1, core::ptr::null_mut(), 0) == 0
+}
+
+/// Report allocations not freed since `heapdiff_start`; `stop` also ends
+/// the recording. None if nothing is being recorded.
+pub fn heapdiff_report(buf: &mut [u8], stop: bool) -> Option<usize> {
+    let len = heapdiff(if stop { 2 } else { 0 }, buf.as_mut_ptr(), buf.len() as i32);
+    if len >= 0 { Some(len as usize) } else { None }
+}
+
+/// Table of the kernel heap bytes and blocks each subsystem holds
+pub fn memstat_report(buf: &mut [u8]) -> Option<usize> {
+    let len = memstat(buf.as_mut_ptr(), buf.len() as i32);
+    if len >= 0 { Some(len as usize) } else { None }
+}
+
+/// Run WASM programs as a pipeline on the worker harts, each stage's output
+/// feeding the next. `spec` has one "<path> [args...]" line per stage.
+/// Read the last stage's output from the returned fd until it returns 0.
+pub fn start_wasm_pipeline(spec: &str) -> Option<i32> {
+    let fd = wasm_pipe(spec.as_ptr(), spec.len() as i32);
+    if fd >= 0 { Some(fd) } else { None }
+}
+
+/// Start recording GUI input events, saved to `path` by `inputrec_stop`
+pub fn inputrec_record(path: &str) -> bool {
+    input_replay(1, path.as_ptr(), path.len() as i32, 0) == 0
+}
+
+/// Add a screen checksum checkpoint to the recording; returns the checksum
+pub fn inputrec_check(x: u16, y: u16, w: u16, h: u16) -> Option<u32> {
+    let rect = x as u64 | (y as u64) << 16 | (w as u64) << 32 | (h as u64) << 48;
+    let sum = input_replay(2, core::ptr::null(), 0, rect);
+    if sum >= 0 { Some(sum as u32) } else { None }
+}
+
+/// Save the recording (returns the number of entries) or stop a replay (0)
+pub fn inputrec_stop() -> Option<usize> {
+    let n = input_replay(3, core::ptr::null(), 0, 0);
+    if n >= 0 { Some(n as usize) } else { None }
+}
+
+/// Replay a recording `speed` (1-16) times faster than recorded; returns
+/// the number of entries
+pub fn inputrec_replay(path: &str, speed: u64) -> Option<usize> {
+    let n = input_replay(4, path.as_ptr(), path.len() as i32, speed);
+    if n >= 0 { Some(n as usize) } else { None }
+}
+
+/// Whether a recording or replay is in progress
+pub fn inputrec_busy() -> bool {
+    input_replay(0, core::ptr::null(), 0, 0) == -2
+}
+
+/// Report of the last finished replay: (length written to `buf`, failed checks)
+pub fn inputrec_report(buf: &mut [u8]) -> Option<(usize, usize)> {
+    let r = input_replay(0, buf.as_mut_ptr(), buf.len() as i32, 0);
+    if r >= 0 { Some(((r & 0xFFFF_FFFF) as usize, (r >> 32) as usize)) } else { None }
+}
+
+/// Hibernate to disk. Returns true once the system has been resumed,
+/// false if nothing could be saved.
+pub fn hibernate_system() -> bool {
+    hibernate() == 0
+}
+
+/// State of a stress test run
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum StressState {
+    Running,
+    Passed,
+    Failed,
+}
+
+/// Start a stress test with the given worker counts; an empty `url` has
+/// the net workers fetch from the local httpd
+pub fn stress_start(cpu: u16, io: u16, net: u16, seconds: u64, url: &str) -> bool {
+    let counts = cpu as u64 | (io as u64) << 16 | (net as u64) << 32;
+    stress(1, counts, seconds, url.as_ptr() as u64, url.len() as u64) == 0
+}
+
+/// End the running stress test early
+pub fn stress_stop() -> bool {
+    stress(2, 0, 0, 0, 0) == 0
+}
+
+/// Firewall rules as text: "policy <allow|deny> <dropped>", then one
+/// "<hits> <rule>" line per rule in order
+pub fn firewall_list(buf: &mut [u8]) -> Option<usize> {
+    let len = firewall(0, buf.as_mut_ptr() as u64, buf.len() as u64);
+    if len >= 0 { Some(len as usize) } else { None }
+}
+
+/// Re-read /etc/firewall.rules; returns the number of rules
+pub fn firewall_reload() -> Option<usize> {
+    let count = firewall(1, 0, 0);
+    if count >= 0 { Some(count as usize) } else { None }
+}
+
+/// Append a rule or policy line to /etc/firewall.rules; returns the number of rules
+pub fn firewall_add(rule: &str) -> Option<usize> {
+    let count = firewall(2, rule.as_ptr() as u64, rule.len() as u64);
+    if count >= 0 { Some(count as usize) } else { None }
+}
+
+/// Remove rule `index` (1-based, as listed); returns the number of rules left
+pub fn firewall_delete(index: usize) -> Option<usize> {
+    let count = firewall(3, index as u64, 0);
+    if count >= 0 { Some(count as usize) } else { None }
+}
+
+/// Describe the root filesystem's format revision, features and mount mode
+pub fn sfs_format_info(buf: &mut [u8]) -> Option<usize> {
+    let len = sfs_format(0, buf.as_mut_ptr() as u64, buf.len() as u64);
+    if len >= 0 { Some(len as usize) } else { None }
+}
+
+/// Migrate the root filesystem to the latest format; Some(false) if it already was
+pub fn sfs_upgrade() -> Option<bool> {
+    match sfs_format(1, 0, 0) {
+        r if r < 0 => None,
+        r => Some(r == 1),
+    }
+}
+
+/// Copy the clipboard text into `buf`; returns the number of bytes
+pub fn clipboard_get(buf: &mut [u8]) -> Option<usize> {
+    let len = clipboard(0, buf.as_mut_ptr() as u64, buf.len() as u64);
+    if len >= 0 { Some(len as usize) } else { None }
+}
+
+/// Replace the clipboard text (also handed to the page hosting the emulator)
+pub fn clipboard_set(text: &str) -> bool {
+    clipboard(1, text.as_ptr() as u64, text.len() as u64) == 0
+}
+
+/// Report on the current or last stress test: bytes written to `buf` and the state
+pub fn stress_status(buf: &mut [u8]) -> Option<(usize, StressState)> {
+    let r = stress(0, buf.as_mut_ptr() as u64, buf.len() as u64, 0, 0);
+    let state = match r >> 32 {
+        _ if r < 0 => return None,
+        1 => StressState::Running,
+        2 => StressState::Passed,
+        _ => StressState::Failed,
+    };
+    Some(((r & 0xFFFF_FFFF) as usize, state))
+}
+
+/// Encode `text` as a QR code and show it on screen. `modules` receives
+/// one byte per module (1 = dark), row by row; returns modules per side.
+pub fn qr_encode(text: &str, modules: &mut [u8]) -> Option<usize> {
+    let size = qrcode(text.as_ptr(), text.len() as i32, modules.as_mut_ptr(), modules.len() as i32);
+    if size > 0 { Some(size as usize) } else { None }
+}
+
+/// Play a tone; `square` selects a square wave instead of a sine
+pub fn play_tone(freq: u32, duration_ms: u32, square: bool) -> bool {
+    beep(freq, duration_ms, square as u32) == 0
+}
+
+/// Turn UI event sounds on or off, or just query them with `None`
+pub fn set_event_sounds(enabled: Option<bool>) -> bool {
+    event_sounds(enabled.map_or(2, |on| on as u32)) == 1
+}
+
+/// Mount a 9P server reached over TCP at `path`
+pub fn mount_9p_tcp(ip: &[u8; 4], port: u16, path: &str) -> bool {
+    mount_9p(ip.as_ptr(), port as u32, path.as_ptr(), path.len() as i32) == 0
+}
+
+/// Unmount the filesystem at `path`
+pub fn unmount(path: &str) -> bool {
+    umount(path.as_ptr(), path.len() as i32) == 0
+}
+
+/// Get the mount point list (newline separated)
+pub fn get_mounts(buf: &mut [u8]) -> Option<usize> {
+    let len = mounts(buf.as_mut_ptr(), buf.len() as i32);
+    if len >= 0 { Some(len as usize) } else { None }
+}
+
+/// Display color adjustment: brightness and contrast in percent, gamma in hundredths
+#[derive(Clone, Copy)]
+pub struct DisplayParams {
+    pub brightness: u16,
+    pub contrast: u16,
+    pub gamma: u16,
+}
+
+/// Change the given display parameters (`None` keeps a value) and return
+/// the resulting ones; `None` if there is no display or a value is out of range
+pub fn set_display_params(brightness: Option<u16>, contrast: Option<u16>, gamma: Option<u16>) -> Option<DisplayParams> {
+    let arg = |v: Option<u16>| v.unwrap_or(0) as u32;
+    let packed = display_adjust(arg(brightness), arg(contrast), arg(gamma));
+    if packed < 0 {
+        return None;
+    }
+    Some(DisplayParams {
+        brightness: packed as u16,
+        contrast: (packed >> 16) as u16,
+        gamma: (packed >> 32) as u16,
+    })
+}
+
+/// Read all system settings into `buf`, returns the length
+pub fn get_settings(buf: &mut [u8]) -> Option<usize> {
+    let len = settings_get(buf.as_mut_ptr(), buf.len() as i32);
+    if len >= 0 { Some(len as usize) } else { None }
+}
+
+/// Change a system setting ("section.key"); false if the key or value is invalid
+pub fn set_setting(key: &str, value: &str) -> bool {
+    settings_set(key.as_ptr(), key.len() as i32, value.as_ptr(), value.len() as i32) == 0
+}
+
+/// Power off system
+pub fn poweroff() -> ! {
+    shutdown()
+}
+
+/// Get process list
+pub fn get_ps_list(buf: &mut [u8]) -> Option<usize> {
+    let len = ps_list(buf.as_mut_ptr(), buf.len() as i32);
+    if len >= 0 { Some(len as usize) } else { None }
+}
+
+/// Kill result
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum KillResult {
+    Success,
+    NotFound,
+    CannotKill,
+    InvalidPid,
+    InvalidSignal,
+}
+
+/// Kill process
+pub fn kill_process(pid: u32) -> KillResult {
+    signal_process(pid as i32, SIGKILL)
+}
+
+/// Send `sig` to a process, or to every process in group -pid when pid is
+/// negative
+pub fn signal_process(pid: i32, sig: i32) -> KillResult {
+    match kill(pid, sig) {
+        0 => KillResult::Success,
+        -2 => KillResult::CannotKill,
+        -3 => KillResult::InvalidSignal,
+        _ => if pid == 0 { KillResult::InvalidPid } else { KillResult::NotFound }
+    }
+}
+
+/// Process group of a process (0 = the one running this program), None if
+/// there is no such process
+pub fn process_group(pid: u32) -> Option<u32> {
+    let pgid = getpgid(pid);
+    if pgid >= 0 { Some(pgid as u32) } else { None }
+}
+
+/// Move this program's process (pid 0) or a child it spawned into group
+/// `pgid` (0 = a new group led by pid); false if not allowed or there is no
+/// such group
+pub fn set_process_group(pid: u32, pgid: u32) -> bool {
+    setpgid(pid, pgid) == 0
+}
+
+/// Nice value of a process (0 = the one running this program), None if
+/// there is no such process
+pub fn process_nice(pid: u32) -> Option<i32> {
+    match get_nice(pid) {
+        -100 => None,
+        nice => Some(nice),
+    }
+}
+
+/// Change a process's nice value (clamped to -20..19); false if there is no
+/// such process
+pub fn renice_process(pid: u32, nice: i32) -> bool {
+    set_nice(pid, nice) == 0
+}
+
+/// Harts a process may run on, bit N = hart N (pid 0 = the one running
+/// this program); None if there is no such process
+pub fn process_affinity(pid: u32) -> Option<u128> {
+    let mut mask = [0u8; 16];
+    (get_affinity(pid, mask.as_mut_ptr()) == 0).then(|| u128::from_le_bytes(mask))
+}
+
+/// Result of changing a process's affinity
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum AffinityResult {
+    Success,
+    NotFound,
+    /// None of the harts in the mask exists
+    NoSuchHart,
+}
+
+/// Restrict a process to the harts in `mask` (bit N = hart N)
+pub fn set_process_affinity(pid: u32, mask: u128) -> AffinityResult {
+    match set_affinity(pid, mask.to_le_bytes().as_ptr()) {
+        0 => AffinityResult::Success,
+        -2 => AffinityResult::NoSuchHart,
+        _ => AffinityResult::NotFound,
+    }
+}
+
+/// Result of waiting for a child process
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum WaitResult {
+    Exited { pid: u32, code: i32 },
+    /// Still running (only when not blocking)
+    Running,
+    /// No such child, or the wait was cancelled
+    NoChild,
+}
+
+/// Wait for child `pid` (0 = any child) to exit and collect its exit code;
+/// with `block` false, report Running instead of waiting
+pub fn wait_for_child(pid: u32, block: bool) -> WaitResult {
+    let mut code = 0i32;
+    match waitpid(pid, &mut code, if block { 0 } else { WNOHANG }) {
+        0 => WaitResult::Running,
+        pid if pid > 0 => WaitResult::Exited { pid: pid as u32, code },
+        _ => WaitResult::NoChild,
+    }
+}
+
+/// Result of spawning a program
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SpawnResult {
+    /// Started as a child with this PID
+    Started(u32),
+    NotFound,
+    NotExecutable,
+}
+
+/// Start the binary `path` with space-separated `args` as a child process;
+/// collect its exit code with `wait_for_child`
+pub fn spawn_program(path: &str, args: &str) -> SpawnResult {
+    match spawn(path.as_bytes(), args.as_bytes()) {
+        pid if pid > 0 => SpawnResult::Started(pid as u32),
+        -2 => SpawnResult::NotExecutable,
+        _ => SpawnResult::NotFound,
+    }
+}
+
+/// How a program handles a signal
+#[derive(Clone, Copy)]
+pub enum SigHandler {
+    /// End the program with exit code 128 + the signal number
+    Default,
+    Ignore,
+    /// Run the function, then go on where the program was interrupted
+    Handler(extern "C" fn(i32)),
+}
+
+/// Set how the program handles `sig`; false if it cannot be changed
+/// (SIGKILL, SIGSTOP). Ignoring SIGINT makes Ctrl+C readable from the
+/// console again.
+pub fn set_signal_handler(sig: i32, handler: SigHandler) -> bool {
+    let handler = match handler {
+        SigHandler::Default => 0,
+        SigHandler::Ignore => 1,
+        SigHandler::Handler(f) => f as usize,
+    };
+    signal(sig, handler, __mkfs_sigreturn as usize) >= 0
+}
+
+/// Message queue timeout: wait as long as it takes
+pub const MQ_FOREVER: u64 = u64::MAX;
+
+/// Result of a message queue send or receive
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum MqResult {
+    /// Sent, or received a message of this many bytes
+    Done(usize),
+    /// The queue stayed full (send) or empty (receive)
+    TimedOut,
+    /// No such queue, or it was removed
+    Closed,
+}
+
+/// Open the message queue `name`, creating it with room for `capacity`
+/// messages (0 = default) if needed; returns its ID
+pub fn open_queue(name: &str, capacity: usize) -> Option<u32> {
+    let id = mq_open(name.as_bytes(), capacity);
+    if id > 0 { Some(id as u32) } else { None }
+}
+
+/// Send a message (at most 4096 bytes), waiting up to `timeout_ms` for room
+pub fn queue_send(id: u32, data: &[u8], timeout_ms: u64) -> MqResult {
+    match mq_send(id, data, timeout_ms) {
+        0 => MqResult::Done(data.len()),
+        -2 => MqResult::TimedOut,
+        _ => MqResult::Closed,
+    }
+}
+
+/// Receive the oldest message into `buf`, waiting up to `timeout_ms`
+pub fn queue_recv(id: u32, buf: &mut [u8], timeout_ms: u64) -> MqResult {
+    match mq_recv(id, buf, timeout_ms) {
+        len if len >= 0 => MqResult::Done(len as usize),
+        -2 => MqResult::TimedOut,
+        _ => MqResult::Closed,
+    }
+}
+
+/// Remove the message queue `name`
+pub fn unlink_queue(name: &str) -> bool {
+    mq_unlink(name.as_bytes()) == 0
+}
+
+/// Heap bytes a process has in use and its cap (None = no cap), pid 0 =
+/// the one running this program; None if there is no such process
+pub fn process_memory(pid: u32) -> Option<(u64, Option<u64>)> {
+    let mut out = [0u8; 16];
+    if get_mem_limit(pid, out.as_mut_ptr()) != 0 {
+        return None;
+    }
+    let used = u64::from_le_bytes(out[0..8].try_into().unwrap_or([0; 8]));
+    let limit = u64::from_le_bytes(out[8..16].try_into().unwrap_or([0; 8]));
+    Some((used, (limit != 0).then_some(limit)))
+}
+
+/// Result of changing a process's memory cap
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum MemLimitResult {
+    Success,
+    NotFound,
+    /// The kernel tracks too many processes already
+    TooMany,
+}
+
+/// Cap a process's heap use at `limit` bytes (None = no cap)
+pub fn set_memory_limit(pid: u32, limit: Option<u64>) -> MemLimitResult {
+    match set_mem_limit(pid, limit.map_or(0, |bytes| bytes.max(1))) {
+        0 => MemLimitResult::Success,
+        -2 => MemLimitResult::TooMany,
+        _ => MemLimitResult::NotFound,
+    }
+}
+
+/// Format integer to string
+pub fn int_to_str(mut n: i64, buf: &mut [u8]) -> &str {
+    if n == 0 {
+        buf[0] = b'0';
+        return unsafe { core::str::from_utf8_unchecked(&buf[..1]) };
+    }
+
+    let negative = n < 0;
+    if negative { n = -n; }
+
+    let mut i = buf.len();
+    while n > 0 && i > 0 {
+        i -= 1;
+        buf[i] = b'0' + (n % 10) as u8;
+        n /= 10;
+    }
+
+    if negative && i > 0 {
+        i -= 1;
+        buf[i] = b'-';
+    }
+
+    unsafe { core::str::from_utf8_unchecked(&buf[i..]) }
+}
+
+/// Print integer
 pub fn print_int(n: i64) {
     let mut buf = [0u8; 20];
     let s = int_to_str(n, &mut buf);
@@ -681,6 +1949,34 @@ pub fn format_ipv4(ip: &[u8; 4], buf: &mut [u8]) -> usize {
     pos
 }
 
+/// Format IPv6 in the compressed form (e.g. fe80::1)
+pub fn format_ipv6(ip: &[u8; 16], buf: &mut [u8]) -> usize {
+    use core::fmt::Write;
+
+    struct Cursor<'a> {
+        buf: &'a mut [u8],
+        pos: usize,
+    }
+
+    impl Write for Cursor<'_> {
+        fn write_str(&mut self, s: &str) -> core::fmt::Result {
+            let n = s.len().min(self.buf.len() - self.pos);
+            self.buf[self.pos..self.pos + n].copy_from_slice(&s.as_bytes()[..n]);
+            self.pos += n;
+            Ok(())
+        }
+    }
+
+    let mut cursor = Cursor { buf, pos: 0 };
+    let _ = write!(cursor, "{}", core::net::Ipv6Addr::from(*ip));
+    cursor.pos
+}
+
+/// Parse an IPv6 address (e.g. "2001:db8::1")
+pub fn parse_ipv6(s: &str) -> Option<[u8; 16]> {
+    s.parse::<core::net::Ipv6Addr>().ok().map(|addr| addr.octets())
+}
+
 /// Format MAC address as XX:XX:XX:XX:XX:XX
 pub fn format_mac(mac: &[u8; 6], buf: &mut [u8]) -> usize {
     const HEX: [u8; 16] = *b"0123456789abcdef";
@@ -710,31 +2006,32 @@ pub enum TcpStatus {
     Failed = 3,
 }
 
-/// TCP connect by IP
-pub fn tcp_connect_ip(ip: &[u8; 4], port: u16) -> bool {
-    tcp_connect(ip.as_ptr(), 4, port as i32) == 0
+/// TCP connect by IP, returns the socket handle
+pub fn tcp_connect_ip(ip: &[u8; 4], port: u16) -> Option<i32> {
+    let sock = tcp_connect(ip.as_ptr(), 4, port as i32);
+    if sock >= 0 { Some(sock) } else { None }
 }
 
 /// TCP send data
-pub fn tcp_send_data(data: &[u8]) -> Option<usize> {
-    let result = tcp_send(data.as_ptr(), data.len() as i32);
+pub fn tcp_send_data(sock: i32, data: &[u8]) -> Option<usize> {
+    let result = tcp_send(sock, data.as_ptr(), data.len() as i32);
     if result >= 0 { Some(result as usize) } else { None }
 }
 
 /// TCP receive data
-pub fn tcp_recv_data(buf: &mut [u8], timeout_ms: u32) -> Option<usize> {
-    let result = tcp_recv(buf.as_mut_ptr(), buf.len() as i32, timeout_ms as i32);
+pub fn tcp_recv_data(sock: i32, buf: &mut [u8], timeout_ms: u32) -> Option<usize> {
+    let result = tcp_recv(sock, buf.as_mut_ptr(), buf.len() as i32, timeout_ms as i32);
     if result >= 0 { Some(result as usize) } else { None }
 }
 
 /// TCP disconnect
-pub fn tcp_disconnect() -> bool {
-    tcp_close() == 0
+pub fn tcp_disconnect(sock: i32) -> bool {
+    tcp_close(sock) == 0
 }
 
 /// TCP get status
-pub fn tcp_get_status() -> TcpStatus {
-    match tcp_status() {
+pub fn tcp_get_status(sock: i32) -> TcpStatus {
+    match tcp_status(sock) {
         1 => TcpStatus::Connecting,
         2 => TcpStatus::Connected,
         3 => TcpStatus::Failed,
@@ -742,6 +2039,246 @@ pub fn tcp_get_status() -> TcpStatus {
     }
 }
 
+/// Listen on a TCP port, returns the listener handle (close it with `tcp_disconnect`)
+pub fn tcp_listen_port(port: u16) -> Option<i32> {
+    let listener = tcp_listen(port as i32);
+    if listener >= 0 { Some(listener) } else { None }
+}
+
+/// Watch for files or directories being created
+pub const WATCH_CREATE: u8 = 1 << 0;
+/// Watch for files being written
+pub const WATCH_MODIFY: u8 = 1 << 1;
+/// Watch for files or directories being removed
+pub const WATCH_DELETE: u8 = 1 << 2;
+
+/// Watch `path` (a file, or a directory and everything below it) for the
+/// changes in `mask`. Reading the returned fd gives one "<C|M|D|O> <path>"
+/// line per change (O means events were dropped), or nothing if none
+/// happened yet. Close it with `close_file`.
+pub fn watch_path(path: &str, mask: u8) -> Option<i32> {
+    let fd = watch(path.as_ptr(), path.len() as i32, mask as i32);
+    if fd >= 0 { Some(fd) } else { None }
+}
+
+/// Wait up to `timeout_ms` for changes on a watch fd from `watch_path`.
+/// False on timeout or Ctrl+C.
+pub fn wait_for_changes(fd: i32, timeout_ms: u64) -> bool {
+    watch_wait(fd, timeout_ms) > 0
+}
+
+/// Remote end of an accepted TCP connection
+#[derive(Clone, Copy)]
+pub struct TcpPeer {
+    pub ip: [u8; 4],
+    pub port: u16,
+}
+
+/// Accept a connection waiting on `listener` without blocking.
+/// Returns the socket handle and the peer, or None if nobody is waiting.
+pub fn tcp_accept_conn(listener: i32) -> Result<Option<(i32, TcpPeer)>, &'static str> {
+    let mut addr = [0u8; 6];
+    match tcp_accept(listener, addr.as_mut_ptr()) {
+        -1 => Ok(None),
+        sock if sock >= 0 => {
+            let peer = TcpPeer {
+                ip: [addr[0], addr[1], addr[2], addr[3]],
+                port: u16::from_be_bytes([addr[4], addr[5]]),
+            };
+            Ok(Some((sock, peer)))
+        }
+        _ => Err("invalid listener"),
+    }
+}
+
+/// Data (or the end of the stream) to read, or a connection to accept
+pub const POLLIN: u16 = 0x01;
+/// Room to write
+pub const POLLOUT: u16 = 0x04;
+/// Error on the descriptor (always reported)
+pub const POLLERR: u16 = 0x08;
+/// The pipe's writer or the TCP peer has gone (always reported)
+pub const POLLHUP: u16 = 0x10;
+/// Not an open descriptor (always reported)
+pub const POLLNVAL: u16 = 0x20;
+/// The entry is a TCP socket or listener handle, not a file descriptor
+pub const POLLSOCK: u16 = 0x100;
+/// `poll_fds` timeout that waits until something is ready
+pub const POLL_FOREVER: u64 = u64::MAX;
+
+/// One descriptor for `poll_fds` to wait on
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct PollFd {
+    /// File descriptor or socket handle (negative = skipped)
+    pub fd: i32,
+    /// What to wait for (POLLIN, POLLOUT)
+    pub events: u16,
+    /// What it is ready for, filled in by `poll_fds`
+    pub revents: u16,
+}
+
+impl PollFd {
+    /// An entry `poll_fds` skips
+    pub const NONE: PollFd = PollFd { fd: -1, events: 0, revents: 0 };
+
+    /// Wait for `events` on file descriptor `fd` (0 is the console)
+    pub const fn fd(fd: i32, events: u16) -> Self {
+        PollFd { fd, events, revents: 0 }
+    }
+
+    /// Wait for `events` on a TCP socket or listener handle
+    pub const fn socket(handle: i32, events: u16) -> Self {
+        PollFd { fd: handle, events: events | POLLSOCK, revents: 0 }
+    }
+
+    /// Whether the last poll found any of `events` (or an error) on it
+    pub fn ready(&self, events: u16) -> bool {
+        self.revents & (events | POLLERR | POLLHUP | POLLNVAL) != 0
+    }
+}
+
+/// Sleep until one of `fds` is ready or `timeout_ms` passes (0 only
+/// checks, POLL_FOREVER waits as long as it takes), filling in their
+/// `revents`. Returns how many are ready (0 on timeout), or None on Ctrl+C,
+/// a signal or more than 64 entries.
+pub fn poll_fds(fds: &mut [PollFd], timeout_ms: u64) -> Option<usize> {
+    let ready = poll(fds.as_mut_ptr() as *mut u8, fds.len() as i32, timeout_ms);
+    if ready >= 0 { Some(ready as usize) } else { None }
+}
+
+/// Mapped memory can be read
+pub const PROT_READ: i32 = 0x1;
+/// Mapped memory can be written
+pub const PROT_WRITE: i32 = 0x2;
+/// Mapped memory can be executed
+pub const PROT_EXEC: i32 = 0x4;
+/// Writes to a file mapping go back to the file when it is unmapped
+pub const MAP_SHARED: i32 = 0x01;
+/// Writes to a file mapping stay in memory
+pub const MAP_PRIVATE: i32 = 0x02;
+/// Zeroed memory instead of a file
+pub const MAP_ANONYMOUS: i32 = 0x20;
+
+fn mapped(len: usize, prot: i32, flags: i32, fd: i32, offset: u64) -> Option<&'static mut [u8]> {
+    let addr = mmap(core::ptr::null_mut(), len, prot, flags, fd, offset);
+    if addr <= 0 {
+        return None;
+    }
+    Some(unsafe { core::slice::from_raw_parts_mut(addr as *mut u8, len) })
+}
+
+/// Allocate `len` zeroed bytes (up to 32 MiB), freed by `unmap` or when
+/// the program exits. None if out of memory.
+pub fn map_anonymous(len: usize) -> Option<&'static mut [u8]> {
+    mapped(len, PROT_READ | PROT_WRITE, MAP_PRIVATE | MAP_ANONYMOUS, -1, 0)
+}
+
+/// Map `len` bytes of open file `fd` from `offset` (a multiple of 4096).
+/// Bytes past the end of the file read as zero. With `shared`, changes are
+/// written back to the file (which must be open for writing) on `unmap`.
+pub fn map_file(fd: i32, len: usize, offset: u64, shared: bool) -> Option<&'static mut [u8]> {
+    let flags = if shared { MAP_SHARED } else { MAP_PRIVATE };
+    mapped(len, PROT_READ | PROT_WRITE, flags, fd, offset)
+}
+
+/// Unmap memory from `map_anonymous` or `map_file`
+pub fn unmap(mapping: &'static mut [u8]) -> bool {
+    munmap(mapping.as_mut_ptr(), mapping.len()) == 0
+}
+
+/// Resolve `host`, accepting dotted IPv4 literals without a DNS lookup
+pub fn resolve_host(host: &str, ip: &mut [u8; 4]) -> bool {
+    let mut parts = host.split('.');
+    let mut literal = [0u8; 4];
+    let is_literal = literal.iter_mut().all(|octet| match parts.next().and_then(|p| p.parse().ok()) {
+        Some(value) => {
+            *octet = value;
+            true
+        }
+        None => false,
+    }) && parts.next().is_none();
+    if is_literal {
+        *ip = literal;
+        return true;
+    }
+    resolve_dns(host, ip)
+}
+
+/// Resolve `host` and connect to `port`, waiting up to `timeout_ms` for
+/// the connection to be established. Returns the socket handle.
+pub fn tcp_open(host: &str, port: u16, timeout_ms: i64) -> Result<i32, &'static str> {
+    let mut ip = [0u8; 4];
+    if !resolve_host(host, &mut ip) {
+        return Err("could not resolve host");
+    }
+    let Some(sock) = tcp_connect_ip(&ip, port) else {
+        return Err("connection failed");
+    };
+
+    let start = get_time();
+    loop {
+        match tcp_get_status(sock) {
+            TcpStatus::Connected => return Ok(sock),
+            TcpStatus::Failed => {
+                tcp_disconnect(sock);
+                return Err("connection refused");
+            }
+            TcpStatus::Closed => {
+                tcp_disconnect(sock);
+                return Err("connection closed");
+            }
+            TcpStatus::Connecting => {
+                if get_time() - start > timeout_ms {
+                    tcp_disconnect(sock);
+                    return Err("connection timed out");
+                }
+                sleep(20);
+            }
+        }
+    }
+}
+
+/// Connect to `host:port`, send `request` and pass the reply to `on_data`
+/// as it arrives, until the server closes the connection, nothing arrives
+/// for `timeout_ms`, or the command is cancelled. Returns the bytes received.
+pub fn tcp_request(
+    host: &str,
+    port: u16,
+    request: &[u8],
+    timeout_ms: i64,
+    mut on_data: impl FnMut(&[u8]),
+) -> Result<usize, &'static str> {
+    let sock = tcp_open(host, port, timeout_ms)?;
+
+    if tcp_send_data(sock, request).is_none() {
+        tcp_disconnect(sock);
+        return Err("send failed");
+    }
+
+    let mut buf = [0u8; 1024];
+    let mut total = 0;
+    let mut last_data = get_time();
+    while should_cancel() == 0 {
+        if let Some(len) = tcp_recv_data(sock, &mut buf, 0) {
+            if len > 0 {
+                on_data(&buf[..len]);
+                total += len;
+                last_data = get_time();
+                continue;
+            }
+        }
+        // Only give up once everything buffered has been read
+        if tcp_get_status(sock) != TcpStatus::Connected || get_time() - last_data > timeout_ms {
+            break;
+        }
+        sleep(10);
+    }
+
+    tcp_disconnect(sock);
+    Ok(total)
+}
+
 /// Console available check
 pub fn is_console_available() -> bool {
     console_available() == 1
@@ -770,3 +2307,151 @@ pub fn ping(ip: &[u8; 4], seq: u16, timeout_ms: u32) -> PingResult {
         _ => PingResult::NetworkError,
     }
 }
+
+/// Answer to a traceroute probe
+pub enum TraceResult {
+    /// A router on the way (hop limit reached there)
+    Hop { from: [u8; 4], rtt_ms: u32 },
+    /// The destination itself
+    Reached { from: [u8; 4], rtt_ms: u32 },
+    /// Reported unreachable, with the ICMP code
+    Unreachable { from: [u8; 4], rtt_ms: u32, code: u8 },
+    Timeout,
+    NetworkError,
+}
+
+/// Send traceroute probe `seq` (0 starts a new trace) allowed `hop_limit`
+/// hops, as a UDP datagram or an ICMP echo request
+pub fn traceroute_probe(ip: &[u8; 4], udp: bool, hop_limit: u8, seq: u16, timeout_ms: u32) -> TraceResult {
+    let mut out = [0u8; 12];
+    let limit_proto = hop_limit as u64 | (udp as u64) << 8;
+    let result = trace_probe(ip.as_ptr(), limit_proto, seq, timeout_ms, out.as_mut_ptr());
+    let from = [out[0], out[1], out[2], out[3]];
+    let rtt_ms = u32::from_le_bytes([out[4], out[5], out[6], out[7]]);
+    match result {
+        0 => TraceResult::Hop { from, rtt_ms },
+        1 => TraceResult::Reached { from, rtt_ms },
+        2 => TraceResult::Unreachable { from, rtt_ms, code: out[8] },
+        -1 => TraceResult::Timeout,
+        _ => TraceResult::NetworkError,
+    }
+}
+
+/// Send ping to an IPv6 address
+pub fn ping6(ip: &[u8; 16], seq: u16, timeout_ms: u32) -> PingResult {
+    let mut out = [0u8; 4];
+    let result = send_ping6(ip.as_ptr(), seq as i32, timeout_ms as i32, out.as_mut_ptr());
+    match result {
+        0 => PingResult::Success { rtt_ms: u32::from_le_bytes(out) },
+        -1 => PingResult::Timeout,
+        _ => PingResult::NetworkError,
+    }
+}
+
+// ═══════════════════════════════════════════════════════════════════════════════
+// Shared kernel log page (layout in kernel/src/klog_page.rs)
+// ═══════════════════════════════════════════════════════════════════════════════
+
+const KLOG_MAGIC: u32 = u32::from_le_bytes(*b"KLOG");
+const KLOG_VERSION: u32 = 1;
+const KLOG_HEADER_SIZE: usize = 64;
+/// Offset of `head` in the header
+const KLOG_HEAD: usize = 16;
+/// Bytes of text in a record
+pub const KLOG_TEXT_LEN: usize = 232;
+
+/// One kernel log record; the text ("subsystem: message") is copied into
+/// the caller's buffer
+#[derive(Clone, Copy)]
+pub struct KlogRecord {
+    pub timestamp_ms: u64,
+    /// 0 (emergency) to 8 (trace), as in the kernel's LogLevel
+    pub level: u8,
+    pub hart: u8,
+    pub len: usize,
+}
+
+/// Follows the kernel log through the shared page: after `open`, reading
+/// new records is plain memory loads, no syscalls
+pub struct KlogReader {
+    page: *const u8,
+    slot_count: u64,
+    slot_size: usize,
+    next: u64,
+    /// Records that were overwritten before they could be read
+    pub lost: u64,
+}
+
+impl KlogReader {
+    /// Map the page and start at the next record to be logged, or (with
+    /// `from_oldest`) at the oldest record still in the page
+    pub fn open(from_oldest: bool) -> Option<Self> {
+        let page = klog_map();
+        if page <= 0 {
+            return None;
+        }
+        let page = page as *const u8;
+        unsafe {
+            let magic = core::ptr::read_volatile(page as *const u32);
+            let version = core::ptr::read_volatile(page.add(4) as *const u32);
+            if magic != KLOG_MAGIC || version != KLOG_VERSION {
+                return None;
+            }
+            let slot_count = core::ptr::read_volatile(page.add(8) as *const u32) as u64;
+            let slot_size = core::ptr::read_volatile(page.add(12) as *const u32) as usize;
+            let mut reader = KlogReader { page, slot_count, slot_size, next: 0, lost: 0 };
+            let head = reader.head();
+            reader.next = if from_oldest { head.saturating_sub(slot_count) } else { head };
+            Some(reader)
+        }
+    }
+
+    /// Number of records the kernel has published
+    pub fn head(&self) -> u64 {
+        unsafe { core::ptr::read_volatile(self.page.add(KLOG_HEAD) as *const u64) }
+    }
+
+    /// Copy the next record's text into `text`, or None if there is no new
+    /// record yet
+    pub fn next(&mut self, text: &mut [u8; KLOG_TEXT_LEN]) -> Option<KlogRecord> {
+        loop {
+            let head = self.head();
+            core::sync::atomic::fence(core::sync::atomic::Ordering::Acquire);
+            if self.next >= head {
+                return None;
+            }
+            // Fell more than a ring behind: skip to the oldest record left
+            if head - self.next > self.slot_count {
+                self.lost += head - self.slot_count - self.next;
+                self.next = head - self.slot_count;
+            }
+            let wanted = self.next + 1;
+            let slot = unsafe { self.page.add(KLOG_HEADER_SIZE + (self.next % self.slot_count) as usize * self.slot_size) };
+            unsafe {
+                let seq = core::ptr::read_volatile(slot as *const u64);
+                core::sync::atomic::fence(core::sync::atomic::Ordering::Acquire);
+                let record = KlogRecord {
+                    timestamp_ms: core::ptr::read_volatile(slot.add(8) as *const u64),
+                    level: core::ptr::read_volatile(slot.add(16)),
+                    hart: core::ptr::read_volatile(slot.add(17)),
+                    len: (core::ptr::read_volatile(slot.add(18) as *const u16) as usize).min(KLOG_TEXT_LEN),
+                };
+                for (i, byte) in text[..record.len].iter_mut().enumerate() {
+                    *byte = core::ptr::read_volatile(slot.add(24 + i));
+                }
+                core::sync::atomic::fence(core::sync::atomic::Ordering::Acquire);
+                let seq_after = core::ptr::read_volatile(slot as *const u64);
+                if seq == wanted && seq_after == wanted {
+                    self.next += 1;
+                    return Some(record);
+                }
+                if seq > wanted || seq_after > wanted {
+                    // Overwritten while we looked; the skip above catches up
+                    self.lost += 1;
+                    self.next += 1;
+                }
+                // Otherwise the kernel is mid-write: try again
+            }
+        }
+    }
+}
@@ -0,0 +1,101 @@
+//! `mkfs upgrade` - migrate SFS images between format revisions
+//!
+//! Steps run in order, one minor revision at a time, so an image of any
+//! older revision ends up current. Each step rewrites only what its revision
+//! changed. The kernel migrates a mounted root the same way (`sfs-upgrade`);
+//! keep the two in sync.
+//!
+//! - 1.0 -> 1.1: record the revision and feature flags in the superblock
+//!   (the flags describe the symlinks and compressed files already present)
+
+use std::fs::OpenOptions;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use crate::{
+    COMPRESSED_FLAG, DIR_ENTRY_SIZE, ENTRIES_PER_SECTOR, FEATURE_LZ4, FEATURE_SYMLINKS, FORMAT_MAJOR,
+    FORMAT_MINOR, KNOWN_FEATURES, MAGIC, SECTOR_SIZE, SEC_DIR_COUNT, SEC_DIR_START, SEC_SUPER, SYMLINK_FLAG,
+};
+
+/// Format revision and feature flags of a superblock: (major, minor, features)
+pub fn format_of(superblock: &[u8]) -> (u16, u16, u32) {
+    let major = u16::from_le_bytes([superblock[8], superblock[9]]);
+    let minor = u16::from_le_bytes([superblock[10], superblock[11]]);
+    if major == 0 && minor == 0 {
+        return (1, 0, 0);
+    }
+    (major, minor, u32::from_le_bytes(superblock[12..16].try_into().unwrap()))
+}
+
+/// Whether this tool can read an image of this format but must not change it
+pub fn is_newer(major: u16, minor: u16, features: u32) -> bool {
+    major != FORMAT_MAJOR || minor > FORMAT_MINOR || features & !KNOWN_FEATURES != 0
+}
+
+/// Upgrade `path` in place, returning the process exit code
+pub fn run(path: &Path) -> std::io::Result<i32> {
+    let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+    let mut superblock = [0u8; 512];
+    file.seek(SeekFrom::Start(SEC_SUPER * SECTOR_SIZE))?;
+    file.read_exact(&mut superblock)?;
+
+    if u32::from_le_bytes(superblock[0..4].try_into().unwrap()) != MAGIC {
+        println!("❌ {:?} is not an SFS image", path);
+        return Ok(4);
+    }
+    let (major, mut minor, mut features) = format_of(&superblock);
+    if is_newer(major, minor, features) {
+        println!(
+            "❌ {:?} is format {}.{}, newer than this tool ({}.{})",
+            path, major, minor, FORMAT_MAJOR, FORMAT_MINOR
+        );
+        return Ok(4);
+    }
+    if minor == FORMAT_MINOR {
+        println!("✅ {:?} is already format {}.{}", path, major, minor);
+        return Ok(0);
+    }
+
+    println!("Upgrading {:?} from format {}.{}", path, major, minor);
+    while minor < FORMAT_MINOR {
+        if minor == 0 {
+            features = scan_features(&mut file)?;
+        }
+        minor += 1;
+        println!("  ⬆️  {}.{}", major, minor);
+    }
+
+    superblock[8..10].copy_from_slice(&major.to_le_bytes());
+    superblock[10..12].copy_from_slice(&minor.to_le_bytes());
+    superblock[12..16].copy_from_slice(&features.to_le_bytes());
+    file.seek(SeekFrom::Start(SEC_SUPER * SECTOR_SIZE))?;
+    file.write_all(&superblock)?;
+    file.sync_all()?;
+
+    println!("✅ Now format {}.{}", major, minor);
+    Ok(0)
+}
+
+/// Feature flags describing the entries in the directory
+fn scan_features(file: &mut std::fs::File) -> std::io::Result<u32> {
+    let mut features = 0;
+    let mut buf = [0u8; 512];
+    for sector in SEC_DIR_START..SEC_DIR_START + SEC_DIR_COUNT {
+        file.seek(SeekFrom::Start(sector * SECTOR_SIZE))?;
+        file.read_exact(&mut buf)?;
+        for j in 0..ENTRIES_PER_SECTOR as usize {
+            let offset = j * DIR_ENTRY_SIZE;
+            if buf[offset] == 0 {
+                continue;
+            }
+            let size = u32::from_le_bytes(buf[offset + 64..offset + 68].try_into().unwrap());
+            if size & SYMLINK_FLAG != 0 {
+                features |= FEATURE_SYMLINKS;
+            }
+            if size & COMPRESSED_FLAG != 0 {
+                features |= FEATURE_LZ4;
+            }
+        }
+    }
+    Ok(features)
+}